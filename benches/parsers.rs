@@ -0,0 +1,130 @@
+//! Benchmarks for the HTML parsers
+//!
+//! Every `parse_*` function runs on its golden fixture page (see
+//! `parser::golden`) and on a scaled-up copy with 50x the articles or
+//! episodes, which is closer to a full listing page.
+//!
+//! Run with `cargo bench --bench parsers`.
+//!
+//! Reference numbers follow, in microseconds per parse. They are the best of
+//! 9 rounds in a release build. "Before" is the parser as it was before
+//! selectors were compiled once (see `parser::selectors`). "After" adds the
+//! shared text and attribute helpers.
+//!
+//! | Parser                  | Page    | Before |  After |
+//! |-------------------------|---------|-------:|-------:|
+//! | `parse_anime_updates`   | fixture |   54.6 |   47.1 |
+//! | `parse_anime_updates`   | x50     | 2190.3 | 2119.6 |
+//! | `parse_completed_anime` | fixture |   61.9 |   61.7 |
+//! | `parse_completed_anime` | x50     | 2588.0 | 2856.0 |
+//! | `parse_search_results`  | fixture |   41.4 |   41.7 |
+//! | `parse_search_results`  | x50     | 1594.3 | 1756.3 |
+//! | `parse_anime_list`      | fixture |   58.5 |   58.8 |
+//! | `parse_anime_list`      | x50     | 2439.1 | 2386.8 |
+//! | `parse_anime_detail`    | fixture |  106.1 |  102.9 |
+//! | `parse_anime_detail`    | x50     | 1642.3 | 1633.9 |
+//! | `parse_episode_list`    | fixture |   92.8 |   84.8 |
+//! | `parse_episode_list`    | x50     | 1616.6 | 1478.5 |
+//! | `parse_episode_detail`  | fixture |   36.7 |   32.8 |
+//! | `parse_episode_detail`  | x50     |  929.8 |  860.7 |
+//!
+//! Building the DOM (`Html::parse_document`) takes nearly all of the time.
+//! The differences after it are within run-to-run noise of about 10%, so
+//! further gains would need a streaming parser rather than a cheaper walk.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use anime_scraper::parser::{
+    parse_anime_detail, parse_anime_list, parse_anime_updates, parse_completed_anime,
+    parse_episode_detail, parse_episode_list, parse_search_results,
+};
+
+const UPDATES: &str = include_str!("../fixtures/parser/updates/home.html");
+const COMPLETED: &str = include_str!("../fixtures/parser/completed/completed.html");
+const SEARCH: &str = include_str!("../fixtures/parser/search/search.html");
+const ANIME_LIST: &str = include_str!("../fixtures/parser/anime_list/page-1.html");
+const ANIME_DETAIL: &str = include_str!("../fixtures/parser/anime_detail/frieren.html");
+const EPISODE: &str = include_str!("../fixtures/parser/episode/frieren-28.html");
+
+/// How many times the repeated block is copied in the scaled pages
+const SCALE: usize = 50;
+
+/// Repeat everything from the first `open` to the last `close` in `page`
+fn scale(page: &str, open: &str, close: &str, times: usize) -> String {
+    let start = page.find(open).expect("open tag in fixture");
+    let end = page.rfind(close).expect("close tag in fixture") + close.len();
+    format!(
+        "{}{}{}",
+        &page[..start],
+        page[start..end].repeat(times),
+        &page[end..]
+    )
+}
+
+fn bench_parser<T>(c: &mut Criterion, name: &str, parse: fn(&str) -> T, pages: &[(&str, String)]) {
+    let mut group = c.benchmark_group(name);
+    for (label, html) in pages {
+        group.throughput(Throughput::Bytes(html.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(label), html, |b, html| {
+            b.iter(|| parse(black_box(html)))
+        });
+    }
+    group.finish();
+}
+
+fn parsers(c: &mut Criterion) {
+    let pages = |page: &str, open: &str, close: &str| {
+        vec![
+            ("fixture", page.to_string()),
+            ("x50", scale(page, open, close, SCALE)),
+        ]
+    };
+
+    bench_parser(
+        c,
+        "parse_anime_updates",
+        parse_anime_updates,
+        &pages(UPDATES, "<article", "</article>"),
+    );
+    bench_parser(
+        c,
+        "parse_completed_anime",
+        parse_completed_anime,
+        &pages(COMPLETED, "<article", "</article>"),
+    );
+    bench_parser(
+        c,
+        "parse_search_results",
+        parse_search_results,
+        &pages(SEARCH, "<article", "</article>"),
+    );
+    bench_parser(
+        c,
+        "parse_anime_list",
+        parse_anime_list,
+        &pages(ANIME_LIST, "<article", "</article>"),
+    );
+    bench_parser(
+        c,
+        "parse_anime_detail",
+        parse_anime_detail,
+        &pages(ANIME_DETAIL, "<li>", "</li>"),
+    );
+    bench_parser(
+        c,
+        "parse_episode_list",
+        parse_episode_list,
+        &pages(ANIME_DETAIL, "<li>", "</li>"),
+    );
+    bench_parser(
+        c,
+        "parse_episode_detail",
+        parse_episode_detail,
+        &pages(EPISODE, "<option", "</option>"),
+    );
+}
+
+criterion_group!(benches, parsers);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+//! Build script: compiles proto/anime.proto when the `grpc` feature is on
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/anime.proto");
+        println!("cargo:rerun-if-env-changed=PROTOC");
+        // Use the vendored protoc unless one is given, so no system install is needed
+        if std::env::var_os("PROTOC").is_none() {
+            std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        }
+        tonic_build::compile_protos("proto/anime.proto")?;
+    }
+    Ok(())
+}
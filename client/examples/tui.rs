@@ -0,0 +1,346 @@
+//! Terminal UI for browsing the API
+//!
+//! Lists the latest updates, searches, shows anime detail with its episodes,
+//! and picks the best video URL of an episode. The URL picked last is
+//! printed when quitting, so it can be handed to a player:
+//!
+//! ```sh
+//! mpv "$(cargo run -p anime-scraper-client --example tui)"
+//! ```
+//!
+//! The API address is taken from the first argument or ANIME_API_URL
+//! (default: http://localhost:8080).
+//!
+//! Keys: arrows or j/k move, Enter opens, Esc goes back, / searches,
+//! q quits.
+
+use std::error::Error;
+use std::future::Future;
+
+use anime_scraper_client::models::{AnimeDetail, EpisodeDetail};
+use anime_scraper_client::{AnimeClient, ClientError};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+/// API address used when none is given
+const DEFAULT_API_URL: &str = "http://localhost:8080";
+
+/// A screen; the app keeps a stack of them and Esc pops one
+enum View {
+    /// Anime from the updates or a search, as (slug, label) pairs
+    Anime {
+        title: String,
+        entries: Vec<(String, String)>,
+        state: ListState,
+    },
+    /// An anime and its episodes
+    Detail {
+        detail: Box<AnimeDetail>,
+        state: ListState,
+    },
+    /// The video sources of an episode
+    Episode {
+        detail: EpisodeDetail,
+        state: ListState,
+    },
+}
+
+impl View {
+    fn state(&mut self) -> &mut ListState {
+        match self {
+            View::Anime { state, .. }
+            | View::Detail { state, .. }
+            | View::Episode { state, .. } => state,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            View::Anime { entries, .. } => entries.len(),
+            View::Detail { detail, .. } => detail.episodes.len(),
+            View::Episode { detail, .. } => detail.sources.len(),
+        }
+    }
+}
+
+/// A list state with the first entry selected, if there is one
+fn first_selected(len: usize) -> ListState {
+    ListState::default().with_selected((len > 0).then_some(0))
+}
+
+struct App {
+    client: AnimeClient,
+    runtime: tokio::runtime::Runtime,
+    views: Vec<View>,
+    /// Search being typed, while the search prompt is open
+    search: Option<String>,
+    status: String,
+    /// Video URL printed on exit
+    picked: Option<String>,
+}
+
+impl App {
+    /// Wait for a client call, showing its error in the status line
+    fn call<T>(&mut self, future: impl Future<Output = Result<T, ClientError>>) -> Option<T> {
+        match self.runtime.block_on(future) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.status = e.to_string();
+                None
+            }
+        }
+    }
+
+    fn load_updates(&mut self) {
+        let client = self.client.clone();
+        let Some(response) = self.call(client.updates()) else {
+            return;
+        };
+        let entries: Vec<(String, String)> = response
+            .data
+            .into_iter()
+            .map(|update| {
+                let label = format!("{} - {}", update.series_title, update.episode_number);
+                (update.slug, label)
+            })
+            .collect();
+        self.status = format!("{} updates", entries.len());
+        self.views.push(View::Anime {
+            title: "Latest updates".to_string(),
+            state: first_selected(entries.len()),
+            entries,
+        });
+    }
+
+    fn search(&mut self, query: String) {
+        let client = self.client.clone();
+        let Some(response) = self.call(client.search(&query)) else {
+            return;
+        };
+        let entries: Vec<(String, String)> = response
+            .data
+            .into_iter()
+            .map(|result| {
+                let label = format!(
+                    "{} [{}, {}]",
+                    result.title, result.anime_type, result.status
+                );
+                (result.slug, label)
+            })
+            .collect();
+        self.status = format!("{} results", entries.len());
+        self.views.push(View::Anime {
+            title: format!("Search: {}", query),
+            state: first_selected(entries.len()),
+            entries,
+        });
+    }
+
+    /// Open the selected entry of the current view
+    fn open(&mut self) {
+        let Some(view) = self.views.last_mut() else {
+            return;
+        };
+        let Some(index) = view.state().selected() else {
+            return;
+        };
+        let client = self.client.clone();
+        match view {
+            View::Anime { entries, .. } => {
+                let slug = entries[index].0.clone();
+                if let Some(response) = self.call(client.anime(&slug)) {
+                    self.status = format!("{} episodes", response.data.episodes.len());
+                    self.views.push(View::Detail {
+                        state: first_selected(response.data.episodes.len()),
+                        detail: Box::new(response.data),
+                    });
+                }
+            }
+            View::Detail { detail, .. } => {
+                let slug = detail.episodes[index].slug.clone();
+                if let Some(response) = self.call(client.episode(&slug)) {
+                    self.picked = response.data.best_video_url().map(str::to_string);
+                    self.status = match &self.picked {
+                        Some(url) => format!("Best video: {}", url),
+                        None => "No video sources".to_string(),
+                    };
+                    self.views.push(View::Episode {
+                        state: first_selected(response.data.sources.len()),
+                        detail: response.data,
+                    });
+                }
+            }
+            View::Episode { detail, .. } => {
+                let url = detail.sources[index].url.clone();
+                self.status = format!("Picked: {}", url);
+                self.picked = Some(url);
+            }
+        }
+    }
+
+    /// Handle a key press; returns false when the app should quit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(query) = &mut self.search {
+            match code {
+                KeyCode::Enter => {
+                    let query = std::mem::take(query);
+                    self.search = None;
+                    if !query.trim().is_empty() {
+                        self.search(query);
+                    }
+                }
+                KeyCode::Esc => self.search = None,
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) => query.push(c),
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('/') => self.search = Some(String::new()),
+            KeyCode::Esc | KeyCode::Backspace if self.views.len() > 1 => {
+                self.views.pop();
+            }
+            KeyCode::Enter => self.open(),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            _ => {}
+        }
+        true
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let Some(view) = self.views.last_mut() else {
+            return;
+        };
+        let len = view.len();
+        if len == 0 {
+            return;
+        }
+        let current = view.state().selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1);
+        view.state().select(Some(next as usize));
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        match self.views.last_mut() {
+            Some(View::Anime {
+                title,
+                entries,
+                state,
+            }) => {
+                let list = List::new(entries.iter().map(|(_, label)| label.as_str()))
+                    .block(Block::bordered().title(title.as_str()))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, main, state);
+            }
+            Some(View::Detail { detail, state }) => {
+                let [info, episodes] =
+                    Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                        .areas(main);
+                let text = vec![
+                    Line::from(format!("{} ({})", detail.anime_type, detail.status)),
+                    Line::from(format!("Rating: {}", detail.rating)),
+                    Line::from(format!("Genres: {}", detail.genres.join(", "))),
+                    Line::from(format!("Studio: {}", detail.studio)),
+                    Line::from(""),
+                    Line::from(detail.synopsis.as_str()),
+                ];
+                frame.render_widget(
+                    Paragraph::new(text)
+                        .wrap(Wrap { trim: true })
+                        .block(Block::bordered().title(detail.title.as_str())),
+                    info,
+                );
+                let list = List::new(
+                    detail
+                        .episodes
+                        .iter()
+                        .map(|episode| format!("{:>4}  {}", episode.number, episode.title)),
+                )
+                .block(Block::bordered().title("Episodes"))
+                .highlight_style(highlight);
+                frame.render_stateful_widget(list, episodes, state);
+            }
+            Some(View::Episode { detail, state }) => {
+                let list = List::new(detail.sources.iter().map(|source| {
+                    let player = source
+                        .embed
+                        .as_ref()
+                        .map(|embed| format!(" (player: {})", embed.host))
+                        .unwrap_or_default();
+                    format!("{} {}{}", source.server, source.quality, player)
+                }))
+                .block(Block::bordered().title(detail.title.as_str()))
+                .highlight_style(highlight);
+                frame.render_stateful_widget(list, main, state);
+            }
+            None => {}
+        }
+
+        let line = match &self.search {
+            Some(query) => format!("Search: {}_", query),
+            None => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(line), status);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let base_url = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("ANIME_API_URL").ok())
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+    let mut app = App {
+        client: AnimeClient::new(base_url)?,
+        runtime: tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?,
+        views: Vec::new(),
+        search: None,
+        status: "Loading updates...".to_string(),
+        picked: None,
+    };
+    app.load_updates();
+    if app.views.is_empty() {
+        // The API is unreachable; searching can still be tried
+        app.views.push(View::Anime {
+            title: "Latest updates".to_string(),
+            entries: Vec::new(),
+            state: ListState::default(),
+        });
+    }
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result?;
+
+    if let Some(url) = app.picked {
+        println!("{}", url);
+    }
+    Ok(())
+}
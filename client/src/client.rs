@@ -0,0 +1,366 @@
+//! HTTP client for the public endpoints
+
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
+
+use crate::error::ClientError;
+use crate::models::{
+    AddFavoriteRequest, AddHistoryRequest, AnimeDetail, AnimeListQuery, AnimeListResponse,
+    AnimeUpdate, ApiError, ApiResponse, AuthData, CompletedAnime, ContinueWatching,
+    DeviceRegistration, EpisodeDetail, LoginRequest, MarkWatchedRequest, PopularEntry,
+    PopularWindow, RegisterDeviceRequest, RegisterRequest, SearchResult, User, UserFavorite,
+    UserHistory, WatchProgress,
+};
+
+/// Header naming the tenant, unless the server set TENANT_HEADER otherwise
+pub const TENANT_HEADER: &str = "X-Tenant";
+
+/// Header carrying an anonymous device token
+pub const DEVICE_TOKEN_HEADER: &str = "X-Device-Token";
+
+/// Client for the Anime Scraper API
+///
+/// Cheap to clone; clones share the connection pool. Calls return the whole
+/// [`ApiResponse`] so callers can see `meta` (e.g. whether the data is a
+/// stale copy) next to `data`.
+#[derive(Debug, Clone)]
+pub struct AnimeClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    device_token: Option<String>,
+    tenant: Option<String>,
+}
+
+impl AnimeClient {
+    /// Create a client for the API at `base_url` (e.g. "http://localhost:8080")
+    pub fn new(base_url: impl Into<String>) -> Result<Self, ClientError> {
+        let base_url = base_url.into();
+        let url = Url::parse(&base_url).map_err(|e| ClientError::InvalidUrl(e.to_string()))?;
+        if url.cannot_be_a_base() {
+            return Err(ClientError::InvalidUrl(base_url));
+        }
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+            device_token: None,
+            tenant: None,
+        })
+    }
+
+    /// Use a configured reqwest client (timeouts, proxies, user agent)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Authenticate requests with a JWT from [`login`](Self::login) or
+    /// [`register`](Self::register)
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Send an anonymous device token from
+    /// [`register_device`](Self::register_device)
+    ///
+    /// Favorites, history, and watch progress work without signing in; a
+    /// later login or registration with the token set moves them into the
+    /// account.
+    pub fn with_device_token(mut self, token: impl Into<String>) -> Self {
+        self.device_token = Some(token.into());
+        self
+    }
+
+    /// Address a tenant by slug, for deployments serving several frontends
+    pub fn with_tenant(mut self, slug: impl Into<String>) -> Self {
+        self.tenant = Some(slug.into());
+        self
+    }
+
+    // ========================================================================
+    // Anime
+    // ========================================================================
+
+    /// GET /api/updates - Latest episode releases
+    pub async fn updates(&self) -> Result<ApiResponse<Vec<AnimeUpdate>>, ClientError> {
+        self.send(self.request(Method::GET, "/api/updates")).await
+    }
+
+    /// GET /api/completed - Completed anime
+    pub async fn completed(&self) -> Result<ApiResponse<Vec<CompletedAnime>>, ClientError> {
+        self.send(self.request(Method::GET, "/api/completed")).await
+    }
+
+    /// GET /api/popular - Popular anime of a window, by rank
+    pub async fn popular(
+        &self,
+        window: PopularWindow,
+    ) -> Result<ApiResponse<Vec<PopularEntry>>, ClientError> {
+        self.send(
+            self.request(Method::GET, "/api/popular")
+                .query(&[("window", window)]),
+        )
+        .await
+    }
+
+    /// GET /api/search - Search anime by title
+    pub async fn search(&self, query: &str) -> Result<ApiResponse<Vec<SearchResult>>, ClientError> {
+        self.send(
+            self.request(Method::GET, "/api/search")
+                .query(&[("q", query)]),
+        )
+        .await
+    }
+
+    /// GET /api/anime/list - A page of the anime list
+    pub async fn anime_list(
+        &self,
+        query: &AnimeListQuery,
+    ) -> Result<ApiResponse<AnimeListResponse>, ClientError> {
+        self.send(self.request(Method::GET, "/api/anime/list").query(query))
+            .await
+    }
+
+    /// GET /api/anime/{slug} - Anime detail with episodes
+    pub async fn anime(&self, slug: &str) -> Result<ApiResponse<AnimeDetail>, ClientError> {
+        let path = format!("/api/anime/{}", urlencoding::encode(slug));
+        self.send(self.request(Method::GET, &path)).await
+    }
+
+    /// GET /api/episode/{slug} - Episode detail with video sources
+    pub async fn episode(&self, slug: &str) -> Result<ApiResponse<EpisodeDetail>, ClientError> {
+        let path = format!("/api/episode/{}", urlencoding::encode(slug));
+        self.send(self.request(Method::GET, &path)).await
+    }
+
+    // ========================================================================
+    // Auth
+    // ========================================================================
+
+    /// POST /api/auth/register - Create an account and sign in
+    pub async fn register(
+        &self,
+        body: &RegisterRequest,
+    ) -> Result<ApiResponse<AuthData>, ClientError> {
+        self.send(self.request(Method::POST, "/api/auth/register").json(body))
+            .await
+    }
+
+    /// POST /api/auth/login - Sign in with email and password
+    pub async fn login(&self, body: &LoginRequest) -> Result<ApiResponse<AuthData>, ClientError> {
+        self.send(self.request(Method::POST, "/api/auth/login").json(body))
+            .await
+    }
+
+    /// POST /api/auth/logout - End the session of the token
+    pub async fn logout(&self) -> Result<ApiResponse<String>, ClientError> {
+        self.send(self.request(Method::POST, "/api/auth/logout"))
+            .await
+    }
+
+    /// GET /api/auth/me - The signed-in user
+    pub async fn me(&self) -> Result<ApiResponse<User>, ClientError> {
+        self.send(self.request(Method::GET, "/api/auth/me")).await
+    }
+
+    /// POST /api/device/register - Register an anonymous device
+    pub async fn register_device(
+        &self,
+        body: &RegisterDeviceRequest,
+    ) -> Result<ApiResponse<DeviceRegistration>, ClientError> {
+        self.send(
+            self.request(Method::POST, "/api/device/register")
+                .json(body),
+        )
+        .await
+    }
+
+    // ========================================================================
+    // Favorites and History
+    // ========================================================================
+
+    /// GET /api/favorites - Favorite anime
+    pub async fn favorites(&self) -> Result<ApiResponse<Vec<UserFavorite>>, ClientError> {
+        self.send(self.request(Method::GET, "/api/favorites")).await
+    }
+
+    /// POST /api/favorites - Add an anime to favorites
+    pub async fn add_favorite(
+        &self,
+        body: &AddFavoriteRequest,
+    ) -> Result<ApiResponse<UserFavorite>, ClientError> {
+        self.send(self.request(Method::POST, "/api/favorites").json(body))
+            .await
+    }
+
+    /// DELETE /api/favorites/{slug} - Remove an anime from favorites
+    pub async fn remove_favorite(&self, slug: &str) -> Result<ApiResponse<String>, ClientError> {
+        let path = format!("/api/favorites/{}", urlencoding::encode(slug));
+        self.send(self.request(Method::DELETE, &path)).await
+    }
+
+    /// GET /api/history - Watch history
+    pub async fn history(&self) -> Result<ApiResponse<Vec<UserHistory>>, ClientError> {
+        self.send(self.request(Method::GET, "/api/history")).await
+    }
+
+    /// POST /api/history - Record a watched episode
+    pub async fn add_history(
+        &self,
+        body: &AddHistoryRequest,
+    ) -> Result<ApiResponse<UserHistory>, ClientError> {
+        self.send(self.request(Method::POST, "/api/history").json(body))
+            .await
+    }
+
+    /// DELETE /api/history/{slug} - Remove an episode from the history
+    pub async fn remove_history(&self, slug: &str) -> Result<ApiResponse<String>, ClientError> {
+        let path = format!("/api/history/{}", urlencoding::encode(slug));
+        self.send(self.request(Method::DELETE, &path)).await
+    }
+
+    /// GET /api/user/continue-watching - Next episode of each anime being
+    /// watched; `limit` defaults to 20 on the server
+    pub async fn continue_watching(
+        &self,
+        limit: Option<i64>,
+    ) -> Result<ApiResponse<Vec<ContinueWatching>>, ClientError> {
+        let mut request = self.request(Method::GET, "/api/user/continue-watching");
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        self.send(request).await
+    }
+
+    /// GET /api/user/watched/{slug} - Watched episodes of an anime
+    pub async fn watched(&self, slug: &str) -> Result<ApiResponse<WatchProgress>, ClientError> {
+        let path = format!("/api/user/watched/{}", urlencoding::encode(slug));
+        self.send(self.request(Method::GET, &path)).await
+    }
+
+    /// POST /api/user/watched/{slug} - Mark episodes watched or unwatched
+    pub async fn mark_watched(
+        &self,
+        slug: &str,
+        body: &MarkWatchedRequest,
+    ) -> Result<ApiResponse<WatchProgress>, ClientError> {
+        let path = format!("/api/user/watched/{}", urlencoding::encode(slug));
+        self.send(self.request(Method::POST, &path).json(body))
+            .await
+    }
+
+    // ========================================================================
+    // Requests
+    // ========================================================================
+
+    /// Start a request to `path` with the client's credentials and tenant
+    ///
+    /// For endpoints without a typed method; pass the result to
+    /// [`send`](Self::send).
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(token) = &self.device_token {
+            request = request.header(DEVICE_TOKEN_HEADER, token);
+        }
+        if let Some(tenant) = &self.tenant {
+            request = request.header(TENANT_HEADER, tenant);
+        }
+        request
+    }
+
+    /// Send a request and decode the response
+    pub async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<ApiResponse<T>, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        decode_response(status, &body)
+    }
+}
+
+/// Decode a response body: the payload on success, the API error otherwise
+fn decode_response<T: DeserializeOwned>(
+    status: StatusCode,
+    body: &[u8],
+) -> Result<ApiResponse<T>, ClientError> {
+    if status.is_success() {
+        return Ok(serde_json::from_slice(body)?);
+    }
+    match serde_json::from_slice::<ApiError>(body) {
+        Ok(error) => Err(ClientError::Api {
+            status: status.as_u16(),
+            error,
+        }),
+        Err(_) => Err(ClientError::Status {
+            status: status.as_u16(),
+            body: String::from_utf8_lossy(body).into_owned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ErrorCode;
+
+    #[test]
+    fn test_request_headers() {
+        let client = AnimeClient::new("http://localhost:8080/").unwrap();
+        let request = client
+            .request(Method::GET, "/api/favorites")
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "http://localhost:8080/api/favorites"
+        );
+        assert!(request.headers().is_empty());
+
+        let client = client
+            .with_token("jwt")
+            .with_device_token("dev_abc")
+            .with_tenant("mobile");
+        let request = client.request(Method::GET, "/api/me").build().unwrap();
+        assert_eq!(request.headers()["authorization"], "Bearer jwt");
+        assert_eq!(request.headers()[DEVICE_TOKEN_HEADER], "dev_abc");
+        assert_eq!(request.headers()[TENANT_HEADER], "mobile");
+
+        assert!(matches!(
+            AnimeClient::new("not a url"),
+            Err(ClientError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let body = br#"{"success":true,"data":["a"],"timestamp":"2024-01-01T00:00:00Z"}"#;
+        let response: ApiResponse<Vec<String>> = decode_response(StatusCode::OK, body).unwrap();
+        assert_eq!(response.data, vec!["a"]);
+
+        let body = br#"{"success":false,"code":"ANIME_NOT_FOUND","error":"Anime not found","timestamp":"2024-01-01T00:00:00Z"}"#;
+        let error = decode_response::<AnimeDetail>(StatusCode::NOT_FOUND, body).unwrap_err();
+        assert_eq!(error.status(), Some(404));
+        assert_eq!(error.code(), Some(ErrorCode::AnimeNotFound));
+
+        let error =
+            decode_response::<AnimeDetail>(StatusCode::BAD_GATEWAY, b"<html>Bad Gateway</html>")
+                .unwrap_err();
+        assert!(matches!(
+            error,
+            ClientError::Status { status: 502, ref body } if body.contains("Bad Gateway")
+        ));
+        assert_eq!(error.code(), None);
+
+        let error = decode_response::<AnimeDetail>(StatusCode::OK, b"{}").unwrap_err();
+        assert!(matches!(error, ClientError::Decode(_)));
+    }
+}
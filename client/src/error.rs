@@ -0,0 +1,59 @@
+//! Client errors
+
+use thiserror::Error;
+
+use crate::models::{ApiError, ErrorCode};
+
+/// Errors returned by [`AnimeClient`](crate::AnimeClient) calls
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The base URL couldn't be parsed
+    #[error("Invalid base URL: {0}")]
+    InvalidUrl(String),
+
+    /// The request couldn't be sent or the response couldn't be read
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The API answered with an error body
+    #[error("API error ({status}): {}", .error.error)]
+    Api {
+        /// HTTP status of the response
+        status: u16,
+        /// The error body
+        error: ApiError,
+    },
+
+    /// The API answered with an error status and a body that isn't an
+    /// API error (e.g. from a proxy in front of it)
+    #[error("Unexpected response ({status}): {body}")]
+    Status {
+        /// HTTP status of the response
+        status: u16,
+        /// The response body
+        body: String,
+    },
+
+    /// A successful response body didn't match the expected model
+    #[error("Failed to decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+impl ClientError {
+    /// HTTP status of the response, if the API answered
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Api { status, .. } | Self::Status { status, .. } => Some(*status),
+            Self::Http(e) => e.status().map(|status| status.as_u16()),
+            Self::InvalidUrl(_) | Self::Decode(_) => None,
+        }
+    }
+
+    /// Machine-readable error code, if the API answered with an error body
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Api { error, .. } => Some(error.code),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Typed client for the Anime Scraper API
+//!
+//! Request and response models of the public endpoints, and a reqwest-based
+//! [`AnimeClient`] calling them, for Rust consumers (Discord bots, TUIs) that
+//! shouldn't depend on the server crate.
+//!
+//! The models are kept by hand in step with the server's: unknown fields
+//! are ignored and unknown error codes map to [`models::ErrorCode::Unknown`],
+//! so a newer server doesn't break an older client. The tests check the
+//! server's serialized models decode into these.
+//!
+//! `examples/tui.rs` is a terminal UI built on the client:
+//! `cargo run -p anime-scraper-client --example tui`.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), anime_scraper_client::ClientError> {
+//! use anime_scraper_client::AnimeClient;
+//!
+//! let client = AnimeClient::new("http://localhost:8080")?;
+//! let results = client.search("one piece").await?;
+//! for result in results.data {
+//!     println!("{} ({})", result.title, result.slug);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod error;
+pub mod models;
+
+pub use client::{AnimeClient, DEVICE_TOKEN_HEADER, TENANT_HEADER};
+pub use error::ClientError;
@@ -0,0 +1,907 @@
+//! Request and response models of the public endpoints
+//!
+//! Mirrors the server's models as they appear on the wire (camelCase JSON).
+//! Optional response fields default when absent, so responses from older
+//! and newer servers both decode.
+
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// Response Envelope
+// ============================================================================
+
+/// Where the data of a response came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum DataSource {
+    /// Stored data that is still within its cache TTL
+    Cache,
+    /// Scraped from the source site for this request
+    Live,
+    /// Stored data served because the source site could not be reached in
+    /// time; may be out of date
+    Stale,
+    /// A source this client doesn't know about
+    #[serde(other)]
+    Unknown,
+}
+
+/// How a response was produced
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseMeta {
+    /// Where the data came from
+    pub source: DataSource,
+    /// Time spent fetching from the source site
+    #[serde(default)]
+    pub fetch_duration_ms: Option<u64>,
+    /// HTTP status the source site answered with
+    #[serde(default)]
+    pub upstream_status: Option<u16>,
+    /// The source site did not answer in time, so the data may be out of date
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+/// Successful API response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiResponse<T> {
+    /// Always true
+    pub success: bool,
+    /// The response payload
+    pub data: T,
+    /// ISO timestamp of when the data was fetched
+    pub timestamp: String,
+    /// Where the data came from, on endpoints backed by the source site
+    #[serde(default)]
+    pub meta: Option<ResponseMeta>,
+}
+
+/// Machine-readable error code of an [`ApiError`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// A parameter or body field is missing or invalid
+    ValidationFailed,
+    /// Missing, invalid, or expired credentials
+    Unauthorized,
+    /// Authenticated but not allowed
+    Forbidden,
+    /// The requested resource does not exist
+    NotFound,
+    /// No anime with this slug
+    AnimeNotFound,
+    /// No episode with this slug
+    EpisodeNotFound,
+    /// The resource already exists
+    Conflict,
+    /// The request body exceeds the size limit
+    PayloadTooLarge,
+    /// The query string exceeds the length limit
+    UriTooLong,
+    /// The client sent too many requests
+    TooManyRequests,
+    /// The endpoint is switched off by an operator
+    FeatureDisabled,
+    /// The source site is rate limiting requests
+    RateLimited,
+    /// The source site could not be fetched
+    UpstreamUnavailable,
+    /// The source site did not answer in time
+    UpstreamTimeout,
+    /// A database query failed
+    DatabaseError,
+    /// Any other server-side failure
+    InternalError,
+    /// A code this client doesn't know about
+    #[serde(other)]
+    Unknown,
+}
+
+/// API error response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    /// Always false
+    pub success: bool,
+    /// Machine-readable error code
+    pub code: ErrorCode,
+    /// Error message describing what went wrong
+    pub error: String,
+    /// Structured information about the error (e.g., which fields failed)
+    #[serde(default)]
+    pub details: Option<serde_json::Value>,
+    /// ISO timestamp of when the error occurred
+    pub timestamp: String,
+}
+
+// ============================================================================
+// Anime Models
+// ============================================================================
+
+/// Size variants of a thumbnail
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Thumbnails {
+    /// Smallest variant
+    pub small: String,
+    /// Middle variant
+    pub medium: String,
+    /// Largest variant
+    pub large: String,
+}
+
+/// A recently released episode (GET /api/updates)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeUpdate {
+    /// Anime slug
+    pub slug: String,
+    /// Episode title
+    pub title: String,
+    /// Episode URL on the source site
+    pub episode_url: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Thumbnail size variants, if the source has them
+    #[serde(default)]
+    pub thumbnails: Option<Thumbnails>,
+    /// Episode number (e.g., "24/24")
+    pub episode_number: String,
+    /// Anime type (TV, OVA, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Anime title
+    pub series_title: String,
+    /// Anime URL on the source site
+    pub series_url: String,
+    /// Completed or Ongoing
+    pub status: String,
+    /// Release date as shown on the source site
+    pub release_info: String,
+    /// When the episode was posted (RFC3339), if known
+    #[serde(default)]
+    pub released_at: Option<String>,
+    /// Number of users who liked the episode
+    #[serde(default)]
+    pub like_count: Option<i64>,
+    /// Whether the signed-in user liked the episode
+    #[serde(default)]
+    pub liked_by_me: Option<bool>,
+}
+
+/// A completed anime (GET /api/completed)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedAnime {
+    /// Anime slug
+    pub slug: String,
+    /// Anime title
+    pub title: String,
+    /// Anime URL on the source site
+    pub url: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Thumbnail size variants, if the source has them
+    #[serde(default)]
+    pub thumbnails: Option<Thumbnails>,
+    /// Anime type (TV, Special, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Number of episodes
+    pub episode_count: String,
+    /// Anime status
+    pub status: String,
+    /// Who posted the anime on the source site
+    pub posted_by: String,
+    /// When the anime was posted on the source site
+    pub posted_at: String,
+    /// `posted_at` in ISO 8601, if it could be read
+    #[serde(default)]
+    pub released_at_iso: Option<String>,
+    /// Series title
+    pub series_title: String,
+    /// Series URL on the source site
+    pub series_url: String,
+    /// Genres
+    pub genres: Vec<String>,
+    /// Rating
+    pub rating: String,
+}
+
+/// Ranking window of GET /api/popular
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PopularWindow {
+    /// Popular today
+    Today,
+    /// Popular this week
+    Week,
+    /// Popular of all time
+    All,
+}
+
+/// A ranked popular anime (GET /api/popular)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PopularEntry {
+    /// Rank, starting at 1
+    pub rank: u32,
+    /// Anime slug
+    pub slug: String,
+    /// Anime title
+    pub title: String,
+    /// Anime URL on the source site
+    pub url: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Thumbnail size variants, if the source has them
+    #[serde(default)]
+    pub thumbnails: Option<Thumbnails>,
+    /// Genres, if the widget lists them
+    pub genres: Vec<String>,
+    /// Rating, if the widget shows it
+    pub rating: String,
+}
+
+/// A search result (GET /api/search)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    /// Anime slug
+    pub slug: String,
+    /// Anime title
+    pub title: String,
+    /// Anime URL on the source site
+    pub url: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Thumbnail size variants, if the source has them
+    #[serde(default)]
+    pub thumbnails: Option<Thumbnails>,
+    /// Anime status
+    pub status: String,
+    /// Anime type (TV, ONA, Movie)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Episode status (Completed, Ongoing)
+    pub episode_status: String,
+}
+
+/// An entry of the anime list (GET /api/anime/list)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeListItem {
+    /// Anime slug
+    pub slug: String,
+    /// Anime title
+    pub title: String,
+    /// Anime URL on the source site
+    pub url: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Thumbnail size variants, if the source has them
+    #[serde(default)]
+    pub thumbnails: Option<Thumbnails>,
+    /// Anime status
+    pub status: String,
+    /// Anime type (TV, ONA, Movie)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Episode status (Completed, Ongoing)
+    pub episode_status: String,
+}
+
+/// Filters applied to an anime list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeListFilters {
+    /// Type filter
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Status filter
+    pub status: String,
+    /// Sort order
+    pub order: String,
+    /// Tag filter (tag slug)
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Whether anime rated mature or adult are included
+    #[serde(default)]
+    pub include_adult: bool,
+}
+
+/// A page of the anime list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeListResponse {
+    /// Anime on the page
+    pub items: Vec<AnimeListItem>,
+    /// Page number
+    pub page: i32,
+    /// Filters applied
+    pub filters: AnimeListFilters,
+}
+
+/// Query parameters of GET /api/anime/list; unset fields mean "any"
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct AnimeListQuery {
+    /// Page number (default: 1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// Anime type (TV, OVA, Movie, etc.)
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub anime_type: Option<String>,
+    /// Status (Ongoing, Completed, etc.)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Sort order (title, titlereverse, update, latest, popular, rating)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    /// Only anime with this tag slug
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Whether to include anime rated mature or adult; the server decides when
+    /// unset
+    #[serde(rename = "includeAdult", skip_serializing_if = "Option::is_none")]
+    pub include_adult: Option<bool>,
+}
+
+/// An episode of an anime
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Episode {
+    /// Episode slug
+    pub slug: String,
+    /// Episode number
+    pub number: String,
+    /// Episode title
+    pub title: String,
+    /// Episode URL on the source site
+    pub url: String,
+    /// Release date as shown on the source site
+    pub release_date: String,
+    /// Release date in ISO 8601, if the shown one could be read
+    #[serde(default)]
+    pub released_at_iso: Option<String>,
+}
+
+/// Full anime information (GET /api/anime/{slug})
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeDetail {
+    /// Title
+    pub title: String,
+    /// Alternate titles
+    pub alternate_titles: String,
+    /// Poster image URL
+    pub poster: String,
+    /// Rating
+    pub rating: String,
+    /// Trailer URL
+    pub trailer_url: String,
+    /// Status
+    pub status: String,
+    /// Studio
+    pub studio: String,
+    /// Release date
+    pub release_date: String,
+    /// Episode duration
+    pub duration: String,
+    /// Season
+    pub season: String,
+    /// Anime type (TV, OVA, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Total number of episodes
+    pub total_episodes: String,
+    /// Director
+    pub director: String,
+    /// Cast
+    pub casts: Vec<String>,
+    /// Genres
+    pub genres: Vec<String>,
+    /// Synopsis
+    pub synopsis: String,
+    /// Episodes
+    pub episodes: Vec<Episode>,
+    /// Current slug, when the anime was requested by an old one
+    #[serde(default)]
+    pub canonical_slug: Option<String>,
+}
+
+/// How to show an iframe video source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedInfo {
+    /// Host name of the player
+    pub host: String,
+    /// Whether the host is a known embeddable player
+    pub known_player: bool,
+    /// Whether the player only plays with the source site's referer
+    pub requires_referer: bool,
+}
+
+/// A video source of an episode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoSource {
+    /// Server name (e.g., "SOKUJA")
+    pub server: String,
+    /// Quality (e.g., "720p")
+    pub quality: String,
+    /// Video or player URL
+    pub url: String,
+    /// Set when the URL is an iframe player page rather than a video file
+    #[serde(default)]
+    pub embed: Option<EmbedInfo>,
+}
+
+/// An external subtitle track
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrack {
+    /// Subtitle file URL; load it through /api/subtitles/proxy
+    pub url: String,
+    /// Language code (e.g., "id"); empty if not given
+    pub language: String,
+    /// Label for the player's track menu; empty if not given
+    pub label: String,
+    /// Whether the player enables the track by default
+    pub default: bool,
+}
+
+/// Episode detail with video sources (GET /api/episode/{slug})
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeDetail {
+    /// Episode title
+    pub title: String,
+    /// Default video URL
+    pub default_video: String,
+    /// Video sources, preferred server first
+    pub sources: Vec<VideoSource>,
+    /// Subtitle tracks
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleTrack>,
+    /// Number of visible comments
+    #[serde(default)]
+    pub comment_count: Option<i64>,
+}
+
+impl VideoSource {
+    /// Vertical resolution from the quality label ("720p" is 720); 0 when
+    /// the label has none
+    pub fn resolution(&self) -> u32 {
+        let digits: String = self
+            .quality
+            .chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(char::is_ascii_digit)
+            .collect();
+        digits.parse().unwrap_or(0)
+    }
+}
+
+impl EpisodeDetail {
+    /// URL most worth playing
+    ///
+    /// The highest-resolution video file, then the first player page, then
+    /// the default video. Sources of equal resolution keep the server's
+    /// order, which already puts preferred servers first.
+    pub fn best_video_url(&self) -> Option<&str> {
+        let direct = self
+            .sources
+            .iter()
+            .filter(|source| source.embed.is_none())
+            .rev()
+            .max_by_key(|source| source.resolution());
+        direct
+            .or_else(|| self.sources.first())
+            .map(|source| source.url.as_str())
+            .or_else(|| Some(self.default_video.as_str()).filter(|url| !url.is_empty()))
+    }
+}
+
+// ============================================================================
+// Auth Models
+// ============================================================================
+
+/// A user account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    /// User ID
+    pub id: i32,
+    /// Email address
+    pub email: String,
+    /// Display name
+    pub name: Option<String>,
+    /// Avatar URL
+    pub avatar: Option<String>,
+    /// ISO timestamp when the account was created
+    pub created_at: String,
+}
+
+/// A signed-in user and their token
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthData {
+    /// The user
+    pub user: User,
+    /// JWT to pass to [`AnimeClient::with_token`](crate::AnimeAnimeClient::with_token)
+    pub token: String,
+}
+
+/// Request body of POST /api/auth/register
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterRequest {
+    /// Email address
+    pub email: String,
+    /// Password
+    pub password: String,
+    /// Display name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Preferred email language ("en" or "id")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// CAPTCHA token, when the server requires one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captcha_token: Option<String>,
+}
+
+/// Request body of POST /api/auth/login
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginRequest {
+    /// Email address
+    pub email: String,
+    /// Password
+    pub password: String,
+}
+
+/// Request body of POST /api/device/register
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterDeviceRequest {
+    /// Platform of the client (e.g. "android")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+}
+
+/// A registered anonymous device
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceRegistration {
+    /// Device ID
+    pub device_id: i32,
+    /// Token to pass to
+    /// [`AnimeClient::with_device_token`](crate::AnimeAnimeClient::with_device_token)
+    pub token: String,
+}
+
+// ============================================================================
+// User Models
+// ============================================================================
+
+/// A favorite anime
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserFavorite {
+    /// Anime slug
+    pub anime_slug: String,
+    /// Anime title
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// ISO timestamp when added
+    pub created_at: String,
+    /// Number of episodes marked watched
+    #[serde(default)]
+    pub watched_count: i64,
+    /// Number of episodes released so far
+    #[serde(default)]
+    pub total_episodes: i64,
+}
+
+/// Request body of POST /api/favorites
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddFavoriteRequest {
+    /// Anime slug
+    pub anime_slug: String,
+    /// Anime title
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+}
+
+/// A watch history entry
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UserHistory {
+    /// Episode slug
+    pub episode_slug: String,
+    /// Anime slug
+    pub anime_slug: String,
+    /// Episode title
+    pub episode_title: String,
+    /// Anime title
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// ISO timestamp of the last watch
+    pub watched_at: String,
+}
+
+/// Request body of POST /api/history
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddHistoryRequest {
+    /// Episode slug
+    pub episode_slug: String,
+    /// Anime slug
+    pub anime_slug: String,
+    /// Episode title
+    pub episode_title: String,
+    /// Anime title
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+}
+
+/// An anime being watched with the next episode to watch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueWatching {
+    /// Anime slug
+    pub anime_slug: String,
+    /// Anime title
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Slug of the most recently watched episode
+    pub last_episode_slug: String,
+    /// ISO timestamp of the last watch
+    pub last_watched_at: String,
+    /// First unwatched episode after the last watched one
+    pub next_episode: Episode,
+    /// ISO timestamp when the next episode was first seen
+    pub next_released_at: String,
+    /// Whether the next episode was released after the last watch
+    pub new_episode: bool,
+}
+
+/// Watched episodes of one anime
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchProgress {
+    /// Anime slug
+    pub anime_slug: String,
+    /// Number of episodes marked watched
+    pub watched_count: i64,
+    /// Number of episodes released so far
+    pub total_episodes: i64,
+    /// Slugs of the watched episodes, in episode order
+    pub watched_episodes: Vec<String>,
+}
+
+/// Request body of POST /api/user/watched/{slug}
+///
+/// Exactly one of `all`, `up_to`, and `episodes` must be set.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkWatchedRequest {
+    /// Mark every released episode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all: Option<bool>,
+    /// Mark episodes numbered up to and including this one (e.g., "12")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub up_to: Option<String>,
+    /// Mark episodes with these slugs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episodes: Option<Vec<String>>,
+    /// Mark watched (default) or unwatched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watched: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anime_scraper::models as server;
+    use serde::de::DeserializeOwned;
+
+    /// Decode a server model's JSON as the client model
+    fn decode<S: Serialize, C: DeserializeOwned>(value: &S) -> C {
+        serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+    }
+
+    /// Encode a client request and decode it as the server's request model
+    fn encode<C: Serialize, S: DeserializeOwned>(value: &C) -> S {
+        serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+    }
+
+    fn server_episode() -> server::Episode {
+        server::Episode {
+            slug: "naruto-episode-1".to_string(),
+            number: "1".to_string(),
+            title: "Episode 1".to_string(),
+            url: "https://example.com/naruto-episode-1/".to_string(),
+            release_date: "2024-01-01".to_string(),
+            released_at_iso: Some("2024-01-01".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_decodes_server_responses() {
+        let detail = server::AnimeDetail {
+            title: "Naruto".to_string(),
+            anime_type: "TV".to_string(),
+            genres: vec!["Action".to_string()],
+            episodes: vec![server_episode()],
+            canonical_slug: Some("naruto".to_string()),
+            ..Default::default()
+        };
+        let response =
+            server::ApiResponse::live(detail, std::time::Duration::from_millis(120), 200);
+        let decoded: ApiResponse<AnimeDetail> = decode(&response);
+        assert_eq!(decoded.data.anime_type, "TV");
+        assert_eq!(decoded.data.episodes[0].slug, "naruto-episode-1");
+        assert_eq!(
+            decoded.data.episodes[0].released_at_iso.as_deref(),
+            Some("2024-01-01")
+        );
+        assert_eq!(decoded.data.canonical_slug.as_deref(), Some("naruto"));
+        let meta = decoded.meta.unwrap();
+        assert_eq!(meta.source, DataSource::Live);
+        assert_eq!(meta.fetch_duration_ms, Some(120));
+
+        let episode = server::EpisodeDetail {
+            title: "Episode 1".to_string(),
+            default_video: "https://example.com/1.mp4".to_string(),
+            sources: vec![server::VideoSource {
+                server: "Blogger".to_string(),
+                quality: "720p".to_string(),
+                url: "https://www.blogger.com/video.g?token=1".to_string(),
+                embed: Some(server::EmbedInfo {
+                    host: "www.blogger.com".to_string(),
+                    known_player: true,
+                    requires_referer: false,
+                }),
+            }],
+            subtitles: Vec::new(),
+            comment_count: Some(3),
+        };
+        let decoded: ApiResponse<EpisodeDetail> = decode(&server::ApiResponse::new(episode));
+        assert!(decoded.data.sources[0].embed.as_ref().unwrap().known_player);
+        assert_eq!(decoded.data.comment_count, Some(3));
+        assert_eq!(decoded.meta, None);
+
+        let continue_watching = server::ContinueWatching {
+            anime_slug: "naruto".to_string(),
+            anime_title: "Naruto".to_string(),
+            thumbnail: String::new(),
+            last_episode_slug: "naruto-episode-1".to_string(),
+            last_watched_at: "2024-01-01T00:00:00Z".to_string(),
+            next_episode: server_episode(),
+            next_released_at: "2024-01-02T00:00:00Z".to_string(),
+            new_episode: true,
+        };
+        let decoded: ContinueWatching = decode(&continue_watching);
+        assert!(decoded.new_episode);
+
+        let auth = server::AuthResponse {
+            success: true,
+            data: server::AuthData {
+                user: server::User {
+                    id: 1,
+                    email: "user@example.com".to_string(),
+                    name: None,
+                    avatar: None,
+                    created_at: "2024-01-01T00:00:00Z".to_string(),
+                },
+                token: "jwt".to_string(),
+            },
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let decoded: ApiResponse<AuthData> = decode(&auth);
+        assert_eq!(decoded.data.user.email, "user@example.com");
+        assert_eq!(decoded.data.token, "jwt");
+    }
+
+    #[test]
+    fn test_best_video_url() {
+        let source = |server: &str, quality: &str, embed: bool| VideoSource {
+            server: server.to_string(),
+            quality: quality.to_string(),
+            url: format!("https://example.com/{}-{}", server, quality),
+            embed: embed.then(|| EmbedInfo {
+                host: "example.com".to_string(),
+                known_player: true,
+                requires_referer: false,
+            }),
+        };
+        let mut detail = EpisodeDetail {
+            default_video: "https://example.com/default.mp4".to_string(),
+            sources: vec![
+                source("blogger", "1080p", true),
+                source("sokuja", "720p", false),
+                source("mirror", "720p", false),
+                source("mirror", "480p", false),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(detail.sources[0].resolution(), 1080);
+        assert_eq!(
+            detail.best_video_url(),
+            Some("https://example.com/sokuja-720p")
+        );
+
+        detail.sources.retain(|source| source.embed.is_some());
+        assert_eq!(
+            detail.best_video_url(),
+            Some("https://example.com/blogger-1080p")
+        );
+
+        detail.sources.clear();
+        assert_eq!(
+            detail.best_video_url(),
+            Some("https://example.com/default.mp4")
+        );
+        detail.default_video.clear();
+        assert_eq!(detail.best_video_url(), None);
+    }
+
+    #[test]
+    fn test_decodes_server_errors() {
+        let error = server::ApiError::new(server::ErrorCode::AnimeNotFound, "Anime not found")
+            .with_details(serde_json::json!({ "slug": "missing" }));
+        let decoded: ApiError = decode(&error);
+        assert_eq!(decoded.code, ErrorCode::AnimeNotFound);
+        assert_eq!(decoded.details.unwrap()["slug"], "missing");
+
+        let decoded: ApiError = serde_json::from_value(serde_json::json!({
+            "success": false,
+            "code": "SOMETHING_NEW",
+            "error": "New failure",
+            "timestamp": "2024-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        assert_eq!(decoded.code, ErrorCode::Unknown);
+    }
+
+    #[test]
+    fn test_requests_decode_on_server() {
+        let register: server::RegisterRequest = encode(&RegisterRequest {
+            email: "user@example.com".to_string(),
+            password: "correct horse battery staple".to_string(),
+            captcha_token: Some("captcha".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(register.captcha_token.as_deref(), Some("captcha"));
+        assert_eq!(register.name, None);
+
+        let favorite: anime_scraper::routes::user::AddFavoriteRequest =
+            encode(&AddFavoriteRequest {
+                anime_slug: "naruto".to_string(),
+                anime_title: "Naruto".to_string(),
+                thumbnail: String::new(),
+            });
+        assert_eq!(favorite.anime_slug, "naruto");
+
+        let marked: anime_scraper::routes::user::MarkWatchedRequest = encode(&MarkWatchedRequest {
+            up_to: Some("12".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(marked.up_to.as_deref(), Some("12"));
+        assert_eq!(marked.all, None);
+
+        let device: server::RegisterDeviceRequest = encode(&RegisterDeviceRequest::default());
+        assert_eq!(device.platform, None);
+    }
+}
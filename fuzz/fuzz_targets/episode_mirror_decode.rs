@@ -0,0 +1,32 @@
+//! Fuzz the base64 mirror decode path of `parse_episode_detail`
+//!
+//! Each input is placed in a `select.mirror` option twice: as the raw option
+//! value, which exercises base64 and UTF-8 rejection, and base64-encoded,
+//! which feeds arbitrary decoded bytes to the embed HTML parser. The input
+//! also serves as the option label for server/quality splitting.
+//!
+//! Run with `cargo +nightly fuzz run episode_mirror_decode` from the repo root.
+
+#![no_main]
+
+use anime_scraper::parser::parse_episode_detail;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let label = String::from_utf8_lossy(data);
+    let encoded = STANDARD.encode(data);
+
+    for value in [label.as_ref(), encoded.as_str()] {
+        let html = format!(
+            r#"<html><body><select class="mirror"><option value="{}">{}</option></select></body></html>"#,
+            value.replace('"', "&quot;"),
+            label
+        );
+
+        let detail = parse_episode_detail(&html);
+        for source in &detail.sources {
+            assert!(!source.url.is_empty(), "Source without URL: {:?}", source);
+        }
+    }
+});
@@ -0,0 +1,3 @@
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS language VARCHAR(10) DEFAULT 'en';
+ALTER TABLE email_deliveries ADD COLUMN IF NOT EXISTS language VARCHAR(10) NOT NULL DEFAULT 'en';
@@ -0,0 +1,18 @@
+
+CREATE TABLE IF NOT EXISTS user_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    email_notifications BOOLEAN NOT NULL DEFAULT TRUE,
+    digest_frequency VARCHAR(20) NOT NULL DEFAULT 'instant', -- 'instant', 'daily' or 'weekly'
+    webhooks_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    preferred_quality VARCHAR(20),
+    language VARCHAR(10) NOT NULL DEFAULT 'en',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Language used to live on users; move it so preferences have a single home
+INSERT INTO user_preferences (user_id, language)
+SELECT id, language FROM users WHERE language IS NOT NULL AND language <> 'en'
+ON CONFLICT (user_id) DO NOTHING;
+
+ALTER TABLE users DROP COLUMN IF EXISTS language;
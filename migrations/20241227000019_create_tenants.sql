@@ -0,0 +1,50 @@
+
+CREATE TABLE IF NOT EXISTS tenants (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR(100) UNIQUE NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    hostnames TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Existing users belong to the default tenant
+INSERT INTO tenants (id, slug, name) VALUES (1, 'default', 'Default')
+ON CONFLICT (id) DO NOTHING;
+SELECT setval(pg_get_serial_sequence('tenants', 'id'), (SELECT MAX(id) FROM tenants));
+
+-- Emails and Google accounts are unique per tenant rather than globally
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id);
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_google_id_key;
+ALTER TABLE users ADD CONSTRAINT users_tenant_email_key UNIQUE (tenant_id, email);
+ALTER TABLE users ADD CONSTRAINT users_tenant_google_id_key UNIQUE (tenant_id, google_id);
+CREATE INDEX IF NOT EXISTS idx_users_tenant_id ON users(tenant_id);
+
+-- User-owned rows inherit the tenant of their user
+CREATE OR REPLACE FUNCTION set_tenant_from_user() RETURNS TRIGGER AS $$
+BEGIN
+    SELECT tenant_id INTO NEW.tenant_id FROM users WHERE id = NEW.user_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE user_favorites ADD COLUMN IF NOT EXISTS tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id);
+ALTER TABLE user_subscriptions ADD COLUMN IF NOT EXISTS tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id);
+ALTER TABLE user_history ADD COLUMN IF NOT EXISTS tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id);
+ALTER TABLE user_preferences ADD COLUMN IF NOT EXISTS tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id);
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id);
+
+CREATE INDEX IF NOT EXISTS idx_user_favorites_tenant_id ON user_favorites(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_user_subscriptions_tenant_id ON user_subscriptions(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_user_history_tenant_id ON user_history(tenant_id);
+
+CREATE TRIGGER user_favorites_set_tenant BEFORE INSERT ON user_favorites
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
+CREATE TRIGGER user_subscriptions_set_tenant BEFORE INSERT ON user_subscriptions
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
+CREATE TRIGGER user_history_set_tenant BEFORE INSERT ON user_history
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
+CREATE TRIGGER user_preferences_set_tenant BEFORE INSERT ON user_preferences
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
+CREATE TRIGGER sessions_set_tenant BEFORE INSERT ON sessions
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
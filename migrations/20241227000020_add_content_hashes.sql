@@ -0,0 +1,5 @@
+
+-- SHA-256 of the scraped content, used to skip upserts of unchanged rows
+ALTER TABLE anime_details ADD COLUMN IF NOT EXISTS content_hash VARCHAR(64);
+ALTER TABLE episodes ADD COLUMN IF NOT EXISTS content_hash VARCHAR(64);
+ALTER TABLE crawled_anime ADD COLUMN IF NOT EXISTS content_hash VARCHAR(64);
@@ -0,0 +1,4 @@
+
+-- Support the change feed (GET /api/changes)
+CREATE INDEX IF NOT EXISTS idx_anime_details_updated_at ON anime_details(updated_at);
+CREATE INDEX IF NOT EXISTS idx_episodes_updated_at ON episodes(updated_at);
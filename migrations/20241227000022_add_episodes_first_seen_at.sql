@@ -0,0 +1,5 @@
+
+-- When each episode first appeared in our database; never updated by upserts
+ALTER TABLE episodes ADD COLUMN IF NOT EXISTS first_seen_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP;
+UPDATE episodes SET first_seen_at = created_at WHERE created_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_episodes_anime_first_seen ON episodes(anime_slug, first_seen_at);
@@ -0,0 +1,12 @@
+
+-- Search results keyed by normalized query; empty results are cached too
+CREATE TABLE IF NOT EXISTS search_cache (
+    query VARCHAR(255) PRIMARY KEY,
+    results TEXT NOT NULL,
+    result_count INTEGER NOT NULL,
+    hit_count INTEGER NOT NULL DEFAULT 0,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_hit_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_cache_fetched_at ON search_cache(fetched_at);
@@ -0,0 +1,13 @@
+
+-- Per-query search counts, keyed by normalized query
+CREATE TABLE IF NOT EXISTS search_analytics (
+    query VARCHAR(255) PRIMARY KEY,
+    search_count INTEGER NOT NULL DEFAULT 0,
+    zero_result_count INTEGER NOT NULL DEFAULT 0,
+    last_result_count INTEGER NOT NULL DEFAULT 0,
+    first_searched_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_searched_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_analytics_search_count ON search_analytics(search_count DESC);
+CREATE INDEX IF NOT EXISTS idx_search_analytics_zero_result_count ON search_analytics(zero_result_count DESC);
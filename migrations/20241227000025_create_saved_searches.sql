@@ -0,0 +1,21 @@
+
+-- Searches users want to be notified about; new crawled anime matching one
+-- are reported once, then last_checked_at moves past them
+CREATE TABLE IF NOT EXISTS saved_searches (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id),
+    name VARCHAR(200) NOT NULL,
+    query VARCHAR(255),
+    type VARCHAR(50),
+    status VARCHAR(50),
+    last_checked_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_saved_searches_user ON saved_searches(user_id);
+CREATE INDEX IF NOT EXISTS idx_saved_searches_tenant_id ON saved_searches(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_crawled_anime_created_at ON crawled_anime(created_at);
+
+CREATE TRIGGER saved_searches_set_tenant BEFORE INSERT ON saved_searches
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
@@ -0,0 +1,18 @@
+-- Episodes a user has marked watched (or explicitly unwatched) per anime
+CREATE TABLE IF NOT EXISTS user_watched_episodes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id),
+    anime_slug VARCHAR(500) NOT NULL,
+    episode_slug VARCHAR(500) NOT NULL,
+    watched BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_watched_episodes_user_episode_unique
+        UNIQUE(user_id, episode_slug)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_watched_episodes_user_anime ON user_watched_episodes(user_id, anime_slug);
+CREATE INDEX IF NOT EXISTS idx_user_watched_episodes_tenant_id ON user_watched_episodes(tenant_id);
+
+CREATE TRIGGER user_watched_episodes_set_tenant BEFORE INSERT ON user_watched_episodes
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
@@ -0,0 +1,8 @@
+-- Slugs merged into another anime, so old links keep resolving
+CREATE TABLE IF NOT EXISTS anime_aliases (
+    alias_slug VARCHAR(500) PRIMARY KEY,
+    anime_slug VARCHAR(500) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_aliases_anime_slug ON anime_aliases(anime_slug);
@@ -0,0 +1,25 @@
+-- Named sets of permission strings (e.g. "crawler:run"), granted to users
+-- through user_roles. Admins implicitly hold every permission.
+CREATE TABLE IF NOT EXISTS roles (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) UNIQUE NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role_id INTEGER NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, role_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_roles_role_id ON user_roles(role_id);
+
+INSERT INTO roles (name, description, permissions) VALUES
+    ('moderator', 'Moderates user comments', ARRAY['comments:moderate', 'users:read']),
+    ('operator', 'Runs crawls and keeps the database healthy',
+        ARRAY['crawler:run', 'cache:purge', 'jobs:manage', 'maintenance:run'])
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,42 @@
+-- Moderation queue. Reported content gets one moderation_items row, keyed by
+-- content type and ID, that collects every report against it. Removing the
+-- content gives its author a strike; enough recent strikes mute the author.
+CREATE TABLE IF NOT EXISTS moderation_items (
+    id SERIAL PRIMARY KEY,
+    content_type VARCHAR(50) NOT NULL,
+    content_id INTEGER NOT NULL,
+    author_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    excerpt TEXT NOT NULL DEFAULT '',
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'approved' or 'removed'
+    report_count INTEGER NOT NULL DEFAULT 0,
+    resolved_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    resolution_note TEXT,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (content_type, content_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_moderation_items_status ON moderation_items(status, created_at);
+CREATE INDEX IF NOT EXISTS idx_moderation_items_author_id ON moderation_items(author_id);
+
+CREATE TABLE IF NOT EXISTS content_reports (
+    item_id INTEGER NOT NULL REFERENCES moderation_items(id) ON DELETE CASCADE,
+    reporter_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (item_id, reporter_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_strikes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    item_id INTEGER REFERENCES moderation_items(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL DEFAULT '',
+    issued_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_strikes_user_id ON user_strikes(user_id, created_at);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS muted_until TIMESTAMPTZ;
@@ -0,0 +1,10 @@
+-- Client IPs of recent registrations, for per-IP registration throttling.
+-- Rows older than the throttle window are purged as new ones are recorded.
+CREATE TABLE IF NOT EXISTS registration_ips (
+    id SERIAL PRIMARY KEY,
+    ip_address VARCHAR(45) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_registration_ips_ip_address ON registration_ips(ip_address, created_at);
+CREATE INDEX IF NOT EXISTS idx_registration_ips_created_at ON registration_ips(created_at);
@@ -0,0 +1,8 @@
+-- Reports of finished crawl jobs: the full JSON report and its
+-- human-readable summary. Deleted with their job.
+CREATE TABLE IF NOT EXISTS crawl_reports (
+    job_id INTEGER PRIMARY KEY REFERENCES jobs(id) ON DELETE CASCADE,
+    report TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,16 @@
+-- Anime and episodes whose fetch or save failed during a crawl, retried by
+-- POST /api/crawler/retry-failed. One row per target; rows are deleted once
+-- a retry succeeds.
+CREATE TABLE IF NOT EXISTS crawl_failures (
+    id SERIAL PRIMARY KEY,
+    kind VARCHAR(20) NOT NULL,
+    slug VARCHAR(255) NOT NULL,
+    url TEXT NOT NULL,
+    last_error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (kind, slug)
+);
+
+CREATE INDEX IF NOT EXISTS idx_crawl_failures_updated_at ON crawl_failures(updated_at);
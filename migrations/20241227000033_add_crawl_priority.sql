@@ -0,0 +1,9 @@
+-- When each anime was last scraped, whether or not its content changed,
+-- so popular anime can be refreshed once they go stale
+ALTER TABLE anime_details ADD COLUMN IF NOT EXISTS scraped_at TIMESTAMPTZ;
+UPDATE anime_details SET scraped_at = updated_at WHERE scraped_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_anime_details_scraped_at ON anime_details(scraped_at);
+
+-- Runnable jobs are claimed highest priority first, then oldest first
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS priority INTEGER NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_jobs_status_priority_run_at ON jobs(status, priority DESC, run_at);
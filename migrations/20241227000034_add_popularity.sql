@@ -0,0 +1,15 @@
+-- Anime detail views per day, a popularity signal. Rows older than the
+-- popularity window are purged when scores are recomputed.
+CREATE TABLE IF NOT EXISTS anime_views (
+    anime_slug VARCHAR(500) NOT NULL,
+    day DATE NOT NULL,
+    views INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (anime_slug, day)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_views_day ON anime_views(day);
+
+-- Time-decayed popularity from views, favorites and subscriptions,
+-- recomputed periodically
+ALTER TABLE crawled_anime ADD COLUMN IF NOT EXISTS popularity_score DOUBLE PRECISION NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_crawled_anime_popularity_score ON crawled_anime(popularity_score DESC);
@@ -0,0 +1,12 @@
+-- Encrypted emails and Google IDs don't fit VARCHAR(255) and can't be
+-- searched, so lookups and uniqueness move to blind index columns
+ALTER TABLE users ALTER COLUMN email TYPE TEXT;
+ALTER TABLE users ALTER COLUMN google_id TYPE TEXT;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_hash VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS google_id_hash VARCHAR(64);
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_tenant_email_hash_key;
+ALTER TABLE users ADD CONSTRAINT users_tenant_email_hash_key UNIQUE (tenant_id, email_hash);
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_tenant_google_id_hash_key;
+ALTER TABLE users ADD CONSTRAINT users_tenant_google_id_hash_key UNIQUE (tenant_id, google_id_hash);
@@ -0,0 +1,25 @@
+-- Archives of everything stored about a user, requested through
+-- GET /api/user/data-export and built by a background job
+CREATE TABLE IF NOT EXISTS data_exports (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending', -- 'pending', 'ready' or 'failed'
+    object_key VARCHAR(500),
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_data_exports_user_id ON data_exports(user_id, created_at DESC);
+
+-- Log of accounts erased through DELETE /api/user/data. Holds no personal
+-- data: the user ID is kept without a foreign key, since the user is gone.
+CREATE TABLE IF NOT EXISTS data_erasures (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    tenant_id INTEGER NOT NULL,
+    affected_tables TEXT NOT NULL DEFAULT '[]',
+    erased_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_data_erasures_erased_at ON data_erasures(erased_at DESC);
@@ -0,0 +1,4 @@
+-- Soft deactivation: deactivated users can't sign in or use existing tokens
+-- until they reactivate through an emailed link. Their data is kept.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deactivated_at TIMESTAMPTZ;
@@ -0,0 +1,34 @@
+-- Named, ordered lists of anime curated by users ("Best of 2023"), kept apart
+-- from favorites. Public collections can be read by anyone via share_slug.
+CREATE TABLE IF NOT EXISTS collections (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id),
+    name VARCHAR(200) NOT NULL,
+    description TEXT,
+    is_public BOOLEAN NOT NULL DEFAULT FALSE,
+    share_slug VARCHAR(100) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_collections_user ON collections(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_collections_tenant_id ON collections(tenant_id);
+
+CREATE TRIGGER collections_set_tenant BEFORE INSERT ON collections
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
+
+-- Anime in a collection; position is the 0-based order within it
+CREATE TABLE IF NOT EXISTS collection_items (
+    id SERIAL PRIMARY KEY,
+    collection_id INTEGER NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    anime_slug VARCHAR(500) NOT NULL,
+    anime_title VARCHAR(500) NOT NULL,
+    thumbnail VARCHAR(1000),
+    position INTEGER NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT collection_items_collection_anime_unique
+        UNIQUE(collection_id, anime_slug)
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_items_position ON collection_items(collection_id, position);
@@ -0,0 +1,19 @@
+-- Comments on episodes, optionally pinned to a playback position ("at 12:34").
+-- Comments removed through moderation keep their row with removed_at set.
+CREATE TABLE IF NOT EXISTS episode_comments (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id),
+    episode_slug VARCHAR(500) NOT NULL,
+    body TEXT NOT NULL,
+    timestamp_secs INTEGER CHECK (timestamp_secs >= 0),
+    removed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_episode_comments_episode
+    ON episode_comments(tenant_id, episode_slug, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_episode_comments_user_id ON episode_comments(user_id);
+
+CREATE TRIGGER episode_comments_set_tenant BEFORE INSERT ON episode_comments
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
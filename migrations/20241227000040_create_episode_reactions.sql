@@ -0,0 +1,45 @@
+-- Per-user likes on episodes, keyed by episode slug
+CREATE TABLE IF NOT EXISTS episode_reactions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id),
+    episode_slug VARCHAR(500) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT episode_reactions_user_episode_unique
+        UNIQUE(user_id, episode_slug)
+);
+
+CREATE INDEX IF NOT EXISTS idx_episode_reactions_tenant_id ON episode_reactions(tenant_id);
+
+CREATE TRIGGER episode_reactions_set_tenant BEFORE INSERT ON episode_reactions
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
+
+-- Like counts per episode, kept in step with episode_reactions by trigger so
+-- the updates feed doesn't count rows on every request. Deleting a user
+-- cascades to their reactions and decrements the counts too.
+CREATE TABLE IF NOT EXISTS episode_reaction_counts (
+    tenant_id INTEGER NOT NULL REFERENCES tenants(id),
+    episode_slug VARCHAR(500) NOT NULL,
+    like_count INTEGER NOT NULL DEFAULT 0 CHECK (like_count >= 0),
+    PRIMARY KEY (tenant_id, episode_slug)
+);
+
+CREATE OR REPLACE FUNCTION update_episode_reaction_counts() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO episode_reaction_counts (tenant_id, episode_slug, like_count)
+        VALUES (NEW.tenant_id, NEW.episode_slug, 1)
+        ON CONFLICT (tenant_id, episode_slug)
+        DO UPDATE SET like_count = episode_reaction_counts.like_count + 1;
+        RETURN NEW;
+    END IF;
+
+    UPDATE episode_reaction_counts
+    SET like_count = like_count - 1
+    WHERE tenant_id = OLD.tenant_id AND episode_slug = OLD.episode_slug;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER episode_reactions_count AFTER INSERT OR DELETE ON episode_reactions
+    FOR EACH ROW EXECUTE FUNCTION update_episode_reaction_counts();
@@ -0,0 +1,11 @@
+-- Users can keep their watching and favorites out of community leaderboards
+ALTER TABLE user_preferences ADD COLUMN IF NOT EXISTS community_opt_out BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Computed leaderboards per tenant and time window, refreshed when stale
+CREATE TABLE IF NOT EXISTS community_top_cache (
+    tenant_id INTEGER NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+    time_window VARCHAR(20) NOT NULL,
+    items TEXT NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (tenant_id, time_window)
+);
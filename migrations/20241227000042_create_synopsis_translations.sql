@@ -0,0 +1,9 @@
+-- Machine translations of anime synopses, keyed by a hash of the source
+-- text so a changed synopsis is translated again
+CREATE TABLE IF NOT EXISTS synopsis_translations (
+    source_hash CHAR(64) NOT NULL,
+    language VARCHAR(10) NOT NULL,
+    translated_text TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (source_hash, language)
+);
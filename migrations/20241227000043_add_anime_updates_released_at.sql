@@ -0,0 +1,5 @@
+-- Absolute release time resolved from the relative release_info
+-- ("2 jam lalu") when the home page was fetched; the updates feed is
+-- sorted by it
+ALTER TABLE anime_updates ADD COLUMN IF NOT EXISTS released_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_anime_updates_released_at ON anime_updates(released_at DESC NULLS LAST);
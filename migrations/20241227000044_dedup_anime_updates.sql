@@ -0,0 +1,10 @@
+-- The home page repeats episodes across its sections, sometimes with and
+-- sometimes without a trailing slash. Drop rows saved twice that way,
+-- keeping the most recently updated, and key updates on the URL without
+-- trailing slashes so later saves upsert instead of inserting again
+DELETE FROM anime_updates a
+USING anime_updates b
+WHERE rtrim(a.episode_url, '/') = rtrim(b.episode_url, '/')
+  AND (COALESCE(a.updated_at, '-infinity'), a.id) < (COALESCE(b.updated_at, '-infinity'), b.id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_anime_updates_episode_key ON anime_updates(rtrim(episode_url, '/'));
@@ -0,0 +1,4 @@
+-- Thumbnail size variants from the list image's srcset, stored as JSON
+-- ({"small", "medium", "large"}); NULL when the image has none
+ALTER TABLE anime_updates ADD COLUMN IF NOT EXISTS thumbnails TEXT;
+ALTER TABLE completed_anime ADD COLUMN IF NOT EXISTS thumbnails TEXT;
@@ -0,0 +1,4 @@
+-- Whether the source is an iframe player page rather than a video file;
+-- embed metadata (host, known player, referer) is derived from the URL
+-- when the source is read, so host registry changes apply to stored rows
+ALTER TABLE video_sources ADD COLUMN IF NOT EXISTS embed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+-- Admin overrides of VIDEO_SERVER_BLACKLIST and VIDEO_SERVER_PRIORITY, per
+-- video server (lowercased name); sources from blocked servers are dropped
+-- and the rest ordered by priority, highest first
+CREATE TABLE IF NOT EXISTS video_server_rules (
+    server VARCHAR(100) PRIMARY KEY,
+    blocked BOOLEAN NOT NULL DEFAULT FALSE,
+    priority INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,14 @@
+-- External subtitle tracks of an episode's players, saved alongside its
+-- video sources; the subtitle proxy only fetches URLs stored here
+CREATE TABLE IF NOT EXISTS subtitle_tracks (
+    id SERIAL PRIMARY KEY,
+    episode_url VARCHAR(1000) NOT NULL,
+    url VARCHAR(2000) NOT NULL,
+    language VARCHAR(20) NOT NULL DEFAULT '',
+    label VARCHAR(200) NOT NULL DEFAULT '',
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_subtitle_tracks_episode_url ON subtitle_tracks(episode_url);
+CREATE INDEX IF NOT EXISTS idx_subtitle_tracks_url ON subtitle_tracks(url);
@@ -0,0 +1,3 @@
+-- Version carried in a user's tokens; bumping it (on password reset or
+-- deactivation) rejects every token issued before, session-bound or not
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,21 @@
+-- Anonymous devices: each gets an account of its own (is_anonymous, with a
+-- placeholder email) so favorites, history, and watch progress work
+-- unchanged. Signing in from the device merges that account into the real
+-- one and deletes it, along with the device.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_anonymous BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS devices (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    tenant_id INTEGER NOT NULL DEFAULT 1 REFERENCES tenants(id),
+    -- SHA-256 of the device token; the token itself is only returned once
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    platform VARCHAR(50),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_devices_user_id ON devices(user_id);
+
+CREATE TRIGGER devices_set_tenant BEFORE INSERT ON devices
+    FOR EACH ROW EXECUTE FUNCTION set_tenant_from_user();
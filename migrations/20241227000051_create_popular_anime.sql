@@ -0,0 +1,15 @@
+-- Ranked entries of the home page popular widgets, replaced wholesale per
+-- window ("today", "week", "all") on each scrape
+CREATE TABLE IF NOT EXISTS popular_anime (
+    time_window VARCHAR(10) NOT NULL,
+    rank INTEGER NOT NULL,
+    slug VARCHAR(500) NOT NULL,
+    title VARCHAR(500) NOT NULL,
+    url VARCHAR(1000) NOT NULL,
+    thumbnail VARCHAR(1000),
+    thumbnails TEXT,
+    genres TEXT[],
+    rating VARCHAR(20),
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (time_window, rank)
+);
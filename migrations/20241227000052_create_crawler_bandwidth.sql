@@ -0,0 +1,8 @@
+-- Bytes of pages the crawler downloaded per day (UTC), checked against the
+-- daily bandwidth quota
+CREATE TABLE IF NOT EXISTS crawler_bandwidth (
+    day DATE PRIMARY KEY,
+    bytes BIGINT NOT NULL DEFAULT 0,
+    requests INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
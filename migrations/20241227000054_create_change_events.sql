@@ -0,0 +1,40 @@
+-- Inserts and updates of scraped anime and episodes, numbered in the order
+-- they committed; the source of the change feed, its gRPC stream, and its
+-- webhook
+CREATE TABLE IF NOT EXISTS change_events (
+    id BIGSERIAL PRIMARY KEY,
+    kind VARCHAR(20) NOT NULL, -- 'anime' or 'episode'
+    entity_id INTEGER NOT NULL,
+    anime_slug VARCHAR(500) NOT NULL,
+    title VARCHAR(500),
+    number VARCHAR(20),
+    url VARCHAR(1000),
+    created BOOLEAN NOT NULL DEFAULT FALSE,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_events_occurred_at ON change_events(occurred_at);
+
+-- How far each consumer of the change feed has got
+CREATE TABLE IF NOT EXISTS change_feed_positions (
+    consumer VARCHAR(100) PRIMARY KEY,
+    last_event_id BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Start the log with the last change of every stored record
+INSERT INTO change_events (kind, entity_id, anime_slug, title, number, url, created, occurred_at)
+SELECT kind, id, anime_slug, title, number, url, created, occurred_at
+FROM (
+    SELECT 'anime' AS kind, id, slug AS anime_slug, title,
+           NULL::VARCHAR AS number, NULL::VARCHAR AS url,
+           created_at IS NOT DISTINCT FROM updated_at AS created,
+           COALESCE(updated_at, created_at, CURRENT_TIMESTAMP) AS occurred_at
+    FROM anime_details
+    UNION ALL
+    SELECT 'episode', id, anime_slug, title, number, url,
+           created_at IS NOT DISTINCT FROM updated_at,
+           COALESCE(updated_at, created_at, CURRENT_TIMESTAMP)
+    FROM episodes
+) existing
+ORDER BY occurred_at, kind, id;
@@ -0,0 +1,26 @@
+-- Tags describing anime beyond the scraped genres ("time travel",
+-- "tournament arc"). Users create uncurated tags by applying a new name;
+-- admins create curated ones and can curate, rename, or delete any tag.
+CREATE TABLE IF NOT EXISTS tags (
+    id SERIAL PRIMARY KEY,
+    slug VARCHAR(100) NOT NULL UNIQUE,
+    name VARCHAR(100) NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    curated BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- One row per user applying a tag to an anime; how many users applied a tag
+-- is its weight on that anime
+CREATE TABLE IF NOT EXISTS anime_tags (
+    anime_slug VARCHAR(500) NOT NULL,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (anime_slug, tag_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_anime_tags_tag ON anime_tags(tag_id, anime_slug);
+CREATE INDEX IF NOT EXISTS idx_anime_tags_user ON anime_tags(user_id);
@@ -0,0 +1,26 @@
+-- Age ratings flag anime unsuited to general audiences. The rating is derived
+-- from the scraped genres whenever a detail is saved (AgeRating::from_genres);
+-- existing details are backfilled with the same genre lists here.
+ALTER TABLE anime_details ADD COLUMN IF NOT EXISTS age_rating VARCHAR(20) NOT NULL DEFAULT 'general';
+
+UPDATE anime_details SET age_rating = CASE
+    WHEN EXISTS (
+        SELECT 1 FROM UNNEST(genres) AS g(name) WHERE lower(trim(g.name)) IN ('ecchi', 'hentai')
+    ) THEN 'adult'
+    WHEN EXISTS (
+        SELECT 1 FROM UNNEST(genres) AS g(name) WHERE lower(trim(g.name)) IN ('horror', 'gore')
+    ) THEN 'mature'
+    ELSE 'general'
+END;
+
+-- Admin overrides of the derived rating, per anime; kept apart from
+-- anime_details so re-scrapes don't undo them
+CREATE TABLE IF NOT EXISTS age_rating_overrides (
+    anime_slug VARCHAR(500) PRIMARY KEY,
+    age_rating VARCHAR(20) NOT NULL CHECK (age_rating IN ('general', 'mature', 'adult')),
+    updated_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Whether lists and search include adult anime for the user by default
+ALTER TABLE user_preferences ADD COLUMN IF NOT EXISTS show_adult_content BOOLEAN NOT NULL DEFAULT FALSE;
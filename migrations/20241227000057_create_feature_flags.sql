@@ -0,0 +1,9 @@
+-- Endpoint groups switched off by operators at runtime (crawler, upstream
+-- search, image proxy). Features without a row are enabled.
+CREATE TABLE IF NOT EXISTS feature_flags (
+    feature VARCHAR(50) PRIMARY KEY,
+    enabled BOOLEAN NOT NULL,
+    reason TEXT,
+    updated_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- Delivery recipients are encrypted like user emails when data encryption
+-- is on, so they no longer fit VARCHAR(255) and are found by a blind index
+ALTER TABLE email_deliveries ALTER COLUMN recipient TYPE TEXT;
+
+ALTER TABLE email_deliveries ADD COLUMN IF NOT EXISTS recipient_hash VARCHAR(64);
+
+CREATE INDEX IF NOT EXISTS idx_email_deliveries_recipient_hash ON email_deliveries(recipient_hash);
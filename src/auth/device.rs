@@ -0,0 +1,161 @@
+//! Anonymous device tokens
+//!
+//! Viewers who never sign up can still keep favorites, history, and watch
+//! progress. POST /api/device/register creates an anonymous account for the
+//! device and returns an opaque token, sent back in the X-Device-Token
+//! header. Only handlers taking a [`Viewer`] accept it; everything else
+//! still needs a signed-in user. Signing in or up with the header set merges
+//! the device's data into the account, after which the token stops working.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest};
+use sha2::{Digest, Sha256};
+use tracing::error;
+use uuid::Uuid;
+
+use super::{
+    auth_error_response, extract_token_from_cookie, verification_failed, Auth, AuthConfig,
+    AuthError,
+};
+use crate::db::find_device;
+use crate::tenants::CurrentTenant;
+
+/// Header carrying a device token
+pub const DEVICE_TOKEN_HEADER: &str = "X-Device-Token";
+
+/// Prefix of device tokens, so they can't be mistaken for JWTs
+const DEVICE_TOKEN_PREFIX: &str = "dev_";
+
+/// Generate a new device token
+pub fn generate_device_token() -> String {
+    format!(
+        "{}{}{}",
+        DEVICE_TOKEN_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// SHA-256 hex digest a device token is stored as
+pub fn hash_device_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Device token sent with a request, if any
+pub fn device_token_from_request(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(DEVICE_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|token| token.starts_with(DEVICE_TOKEN_PREFIX))
+}
+
+/// A signed-in user or an anonymous device
+///
+/// Extracted like [`Auth`], but also accepts a device token when the request
+/// carries no JWT. Anonymous devices act as their own account, so handlers
+/// use `user_id` either way.
+#[derive(Debug, Clone)]
+pub struct Viewer {
+    /// The user's account, or the device's anonymous account
+    pub user_id: i32,
+    /// The tenant the account belongs to
+    pub tenant_id: i32,
+    /// The device, when the request authenticated with a device token
+    pub device_id: Option<i32>,
+}
+
+impl Viewer {
+    /// Whether the viewer is an anonymous device
+    pub fn is_anonymous(&self) -> bool {
+        self.device_id.is_some()
+    }
+}
+
+impl From<Auth> for Viewer {
+    fn from(auth: Auth) -> Self {
+        Self {
+            user_id: auth.user_id,
+            tenant_id: auth.tenant_id,
+            device_id: None,
+        }
+    }
+}
+
+impl FromRequest for Viewer {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        // A JWT wins over a device token
+        let has_jwt = req.headers().contains_key(header::AUTHORIZATION)
+            || extract_token_from_cookie(req).is_some();
+        let token_hash = match device_token_from_request(req) {
+            Some(token) if !has_jwt => hash_device_token(token),
+            _ => {
+                let auth = Auth::from_request(req, payload);
+                return Box::pin(async move { auth.await.map(Viewer::from) });
+            }
+        };
+
+        let pool = req
+            .app_data::<web::Data<AuthConfig>>()
+            .and_then(|config| config.pool.clone());
+        let tenant = req.extensions().get::<CurrentTenant>().map(|t| t.id);
+
+        Box::pin(async move {
+            let Some(pool) = pool else {
+                return Err(verification_failed(
+                    "Failed to verify device",
+                    "Device check needs a database pool",
+                ));
+            };
+
+            match find_device(&pool, &token_hash).await {
+                Ok(Some(device)) if tenant.is_some_and(|id| id != device.tenant_id) => {
+                    Err(auth_error_response(AuthError::TenantMismatch))
+                }
+                Ok(Some(device)) => Ok(Viewer {
+                    user_id: device.user_id,
+                    tenant_id: device.tenant_id,
+                    device_id: Some(device.device_id),
+                }),
+                Ok(None) => Err(auth_error_response(AuthError::InvalidToken)),
+                Err(e) => {
+                    error!("Failed to check device token: {}", e);
+                    Err(verification_failed("Failed to verify device", e))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_device_token() {
+        let token = generate_device_token();
+        assert!(token.starts_with(DEVICE_TOKEN_PREFIX));
+        assert_ne!(token, generate_device_token());
+
+        let hash = hash_device_token(&token);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_device_token(&token));
+
+        let req = TestRequest::default()
+            .insert_header((DEVICE_TOKEN_HEADER, format!(" {} ", token)))
+            .to_http_request();
+        assert_eq!(device_token_from_request(&req), Some(token.as_str()));
+
+        let req = TestRequest::default()
+            .insert_header((DEVICE_TOKEN_HEADER, "eyJhbGciOiJIUzI1NiJ9"))
+            .to_http_request();
+        assert_eq!(device_token_from_request(&req), None);
+    }
+}
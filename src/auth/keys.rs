@@ -0,0 +1,574 @@
+//! JWT signing keys
+//!
+//! Tokens are signed with HS256 and JWT_SECRET by default. With
+//! JWT_PRIVATE_KEY_FILE set they are signed with RS256 or EdDSA instead, and
+//! the public key is published at GET /.well-known/jwks.json so other
+//! services can verify tokens without sharing a secret.
+//!
+//! Tokens carry the `kid` of the key that signed them. During a rotation the
+//! previous key (JWT_PREVIOUS_SECRET or JWT_PREVIOUS_PUBLIC_KEY_FILE) keeps
+//! verifying tokens, until JWT_PREVIOUS_KEY_UNTIL when set, so switching keys
+//! doesn't log everyone out.
+
+use std::sync::Arc;
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::config::JwtConfig;
+use crate::models::{Jwk, JwkSet};
+
+/// DER object identifier of rsaEncryption (1.2.840.113549.1.1.1)
+const RSA_OID: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+/// DER object identifier of Ed25519 (1.3.101.112)
+const ED25519_OID: &[u8] = &[0x2b, 0x65, 0x70];
+
+/// Length of an Ed25519 public key in bytes
+const ED25519_PUBLIC_KEY_LEN: usize = 32;
+
+/// Bytes of the secret's HMAC kept in the key ID of an HMAC key
+const HMAC_KEY_ID_BYTES: usize = 8;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// Errors loading JWT keys
+#[derive(Debug, Error)]
+pub enum JwtKeyError {
+    #[error("Failed to read key file {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Invalid key in {0}: {1}")]
+    InvalidKey(String, String),
+}
+
+/// A key tokens are verified with
+#[derive(Clone)]
+pub struct JwtKey {
+    kid: String,
+    algorithm: Algorithm,
+    decoding: DecodingKey,
+    /// Public key as published in the JWKS; `None` for HMAC secrets
+    jwk: Option<Jwk>,
+    /// When tokens signed with the key stop being accepted
+    until: Option<DateTime<Utc>>,
+}
+
+impl JwtKey {
+    /// HS256 key for a shared secret
+    ///
+    /// The key ID is derived from the secret without revealing it.
+    fn hmac(secret: &str) -> Self {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"jwt-key-id");
+        let digest = mac.finalize().into_bytes();
+
+        Self {
+            kid: URL_SAFE_NO_PAD.encode(&digest[..HMAC_KEY_ID_BYTES]),
+            algorithm: Algorithm::HS256,
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+            until: None,
+        }
+    }
+
+    /// RS256 or EdDSA key for a public key
+    ///
+    /// # Arguments
+    /// * `algorithm` - RS256 or EdDSA
+    /// * `public` - DER RSAPublicKey for RS256, the raw key for EdDSA
+    ///
+    /// The key ID is the key's RFC 7638 thumbprint.
+    fn public(algorithm: Algorithm, public: &[u8]) -> Option<Self> {
+        let (jwk, thumbprint_input) = match algorithm {
+            Algorithm::RS256 => {
+                let (key, _) = der_element(public, TAG_SEQUENCE)?;
+                let (n, rest) = der_element(key, TAG_INTEGER)?;
+                let (e, _) = der_element(rest, TAG_INTEGER)?;
+                let n = URL_SAFE_NO_PAD.encode(strip_leading_zeros(n));
+                let e = URL_SAFE_NO_PAD.encode(strip_leading_zeros(e));
+                let input = format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n);
+                let jwk = Jwk {
+                    kty: "RSA".to_string(),
+                    kid: String::new(),
+                    alg: "RS256".to_string(),
+                    key_use: "sig".to_string(),
+                    n: Some(n),
+                    e: Some(e),
+                    crv: None,
+                    x: None,
+                };
+                (jwk, input)
+            }
+            Algorithm::EdDSA if public.len() == ED25519_PUBLIC_KEY_LEN => {
+                let x = URL_SAFE_NO_PAD.encode(public);
+                let input = format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x);
+                let jwk = Jwk {
+                    kty: "OKP".to_string(),
+                    kid: String::new(),
+                    alg: "EdDSA".to_string(),
+                    key_use: "sig".to_string(),
+                    n: None,
+                    e: None,
+                    crv: Some("Ed25519".to_string()),
+                    x: Some(x),
+                };
+                (jwk, input)
+            }
+            _ => return None,
+        };
+        let decoding = match algorithm {
+            Algorithm::RS256 => DecodingKey::from_rsa_der(public),
+            _ => DecodingKey::from_ed_der(public),
+        };
+
+        let mut key = Self {
+            kid: String::new(),
+            algorithm,
+            decoding,
+            jwk: Some(jwk),
+            until: None,
+        };
+        key.set_kid(URL_SAFE_NO_PAD.encode(Sha256::digest(thumbprint_input)));
+        Some(key)
+    }
+
+    fn set_kid(&mut self, kid: String) {
+        if let Some(jwk) = &mut self.jwk {
+            jwk.kid = kid.clone();
+        }
+        self.kid = kid;
+    }
+
+    /// Key ID put in the `kid` header of tokens
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    /// Algorithm tokens are signed with
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Key to verify signatures with
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding
+    }
+
+    /// Whether tokens signed with the key are accepted at `now`
+    pub fn is_accepted_at(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+}
+
+struct JwtKeySet {
+    encoding: EncodingKey,
+    /// The signing key first, then keys kept from the previous rotation
+    keys: Vec<JwtKey>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+/// The key tokens are signed with, the keys they are accepted from, and the
+/// issuer and audience they carry
+///
+/// Cloning is cheap; clones share the keys.
+#[derive(Clone)]
+pub struct JwtKeys {
+    inner: Arc<JwtKeySet>,
+}
+
+impl std::fmt::Debug for JwtKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kids: Vec<&str> = self.inner.keys.iter().map(JwtKey::kid).collect();
+        f.debug_struct("JwtKeys").field("kids", &kids).finish()
+    }
+}
+
+impl JwtKeys {
+    /// Sign and verify with an HS256 secret only, without issuer or audience
+    pub fn hmac(secret: &str) -> Self {
+        Self {
+            inner: Arc::new(JwtKeySet {
+                encoding: EncodingKey::from_secret(secret.as_bytes()),
+                keys: vec![JwtKey::hmac(secret)],
+                issuer: None,
+                audience: None,
+            }),
+        }
+    }
+
+    /// Load the keys described by `config`
+    ///
+    /// # Arguments
+    /// * `config` - Key files, the previous key, issuer, and audience
+    /// * `secret` - JWT_SECRET, used when no private key file is set
+    ///
+    /// # Returns
+    /// * `Ok(JwtKeys)` - The loaded keys
+    /// * `Err(JwtKeyError)` - A key file couldn't be read or holds no supported key
+    pub fn load(config: &JwtConfig, secret: &str) -> Result<Self, JwtKeyError> {
+        let (encoding, mut signing) = match &config.private_key_file {
+            Some(path) => load_private_key(path, &read_key_file(path)?)?,
+            None => (
+                EncodingKey::from_secret(secret.as_bytes()),
+                JwtKey::hmac(secret),
+            ),
+        };
+        if let Some(kid) = &config.key_id {
+            signing.set_kid(kid.clone());
+        }
+
+        let mut keys = vec![signing];
+        if let Some(previous) = &config.previous_secret {
+            keys.push(JwtKey::hmac(previous));
+        }
+        if let Some(path) = &config.previous_public_key_file {
+            keys.push(load_public_key(path, &read_key_file(path)?)?);
+        }
+        for key in &mut keys[1..] {
+            key.until = config.previous_until;
+        }
+
+        Ok(Self {
+            inner: Arc::new(JwtKeySet {
+                encoding,
+                keys,
+                issuer: config.issuer.clone(),
+                audience: config.audience.clone(),
+            }),
+        })
+    }
+
+    /// The key new tokens are signed with
+    pub fn signing_key(&self) -> &JwtKey {
+        &self.inner.keys[0]
+    }
+
+    /// `iss` claim tokens carry
+    pub fn issuer(&self) -> Option<&str> {
+        self.inner.issuer.as_deref()
+    }
+
+    /// `aud` claim tokens carry
+    pub fn audience(&self) -> Option<&str> {
+        self.inner.audience.as_deref()
+    }
+
+    /// Private or secret key new tokens are signed with
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.inner.encoding
+    }
+
+    /// Keys a token could have been signed with
+    ///
+    /// The key named by the token's `kid` comes first, then other accepted
+    /// keys of the same algorithm, so tokens without a `kid` (or with one
+    /// set through JWT_KEY_ID before a rotation) still verify.
+    ///
+    /// # Arguments
+    /// * `kid` - The token's `kid` header, if any
+    /// * `algorithm` - The token's `alg` header
+    /// * `now` - Time the previous key's grace window is checked against
+    pub fn verifying_keys(
+        &self,
+        kid: Option<&str>,
+        algorithm: Algorithm,
+        now: DateTime<Utc>,
+    ) -> Vec<&JwtKey> {
+        let mut keys: Vec<&JwtKey> = self
+            .inner
+            .keys
+            .iter()
+            .filter(|key| key.algorithm == algorithm && key.is_accepted_at(now))
+            .collect();
+        // Stable sort keeps the signing key ahead of the previous one
+        keys.sort_by_key(|key| Some(key.kid()) != kid);
+        keys
+    }
+
+    /// Public keys of the accepted asymmetric keys, for other services to
+    /// verify tokens with
+    pub fn jwks(&self, now: DateTime<Utc>) -> JwkSet {
+        JwkSet {
+            keys: self
+                .inner
+                .keys
+                .iter()
+                .filter(|key| key.is_accepted_at(now))
+                .filter_map(|key| key.jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+fn read_key_file(path: &str) -> Result<String, JwtKeyError> {
+    std::fs::read_to_string(path).map_err(|e| JwtKeyError::Io(path.to_string(), e))
+}
+
+/// Load a PEM RSA (PKCS#1 or PKCS#8) or Ed25519 (PKCS#8) private key
+fn load_private_key(path: &str, pem: &str) -> Result<(EncodingKey, JwtKey), JwtKeyError> {
+    let invalid = |reason: String| JwtKeyError::InvalidKey(path.to_string(), reason);
+    let (label, der) = pem_block(pem).ok_or_else(|| invalid("not a PEM file".to_string()))?;
+
+    let (encoding, algorithm, public) = match label {
+        "RSA PRIVATE KEY" => {
+            let pair = RsaKeyPair::from_der(&der).map_err(|e| invalid(e.to_string()))?;
+            let encoding =
+                EncodingKey::from_rsa_pem(pem.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+            (
+                encoding,
+                Algorithm::RS256,
+                pair.public_key().as_ref().to_vec(),
+            )
+        }
+        "PRIVATE KEY" => match pkcs8_algorithm(&der) {
+            Some(Algorithm::RS256) => {
+                let pair = RsaKeyPair::from_pkcs8(&der).map_err(|e| invalid(e.to_string()))?;
+                let encoding = EncodingKey::from_rsa_pem(pem.as_bytes())
+                    .map_err(|e| invalid(e.to_string()))?;
+                (
+                    encoding,
+                    Algorithm::RS256,
+                    pair.public_key().as_ref().to_vec(),
+                )
+            }
+            Some(_) => {
+                let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+                    .map_err(|e| invalid(e.to_string()))?;
+                let encoding =
+                    EncodingKey::from_ed_pem(pem.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+                (
+                    encoding,
+                    Algorithm::EdDSA,
+                    pair.public_key().as_ref().to_vec(),
+                )
+            }
+            None => return Err(invalid("expected an RSA or Ed25519 key".to_string())),
+        },
+        other => return Err(invalid(format!("expected a private key, found {}", other))),
+    };
+
+    let key = JwtKey::public(algorithm, &public)
+        .ok_or_else(|| invalid("malformed public key".to_string()))?;
+    Ok((encoding, key))
+}
+
+/// Load a PEM RSA (PKCS#1 or SubjectPublicKeyInfo) or Ed25519
+/// (SubjectPublicKeyInfo) public key
+fn load_public_key(path: &str, pem: &str) -> Result<JwtKey, JwtKeyError> {
+    let invalid = |reason: String| JwtKeyError::InvalidKey(path.to_string(), reason);
+    let (label, der) = pem_block(pem).ok_or_else(|| invalid("not a PEM file".to_string()))?;
+
+    let key = match label {
+        "RSA PUBLIC KEY" => JwtKey::public(Algorithm::RS256, &der),
+        "PUBLIC KEY" => {
+            let (algorithm, public) = spki_key(&der)
+                .ok_or_else(|| invalid("expected an RSA or Ed25519 key".to_string()))?;
+            JwtKey::public(algorithm, public)
+        }
+        other => return Err(invalid(format!("expected a public key, found {}", other))),
+    };
+    key.ok_or_else(|| invalid("malformed public key".to_string()))
+}
+
+/// Label and decoded contents of the first PEM block
+fn pem_block(pem: &str) -> Option<(&str, Vec<u8>)> {
+    let start = pem.find("-----BEGIN ")? + "-----BEGIN ".len();
+    let (label, rest) = pem[start..].split_once("-----")?;
+    let end = rest.find("-----END ")?;
+    let base64: String = rest[..end].split_whitespace().collect();
+    Some((label, STANDARD.decode(base64).ok()?))
+}
+
+/// Split off a DER element with the given tag
+///
+/// # Returns
+/// The element's contents and the input following it
+fn der_element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&found, input) = input.split_first()?;
+    if found != tag {
+        return None;
+    }
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let octets = (first & 0x7f) as usize;
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let (len, rest) = input.split_at(octets);
+        input = rest;
+        len.iter().fold(0, |len, &byte| len << 8 | byte as usize)
+    };
+    (input.len() >= len).then(|| input.split_at(len))
+}
+
+/// Algorithm for a key's object identifier
+fn algorithm_for_oid(oid: &[u8]) -> Option<Algorithm> {
+    match oid {
+        RSA_OID => Some(Algorithm::RS256),
+        ED25519_OID => Some(Algorithm::EdDSA),
+        _ => None,
+    }
+}
+
+/// Algorithm of a PKCS#8 private key
+fn pkcs8_algorithm(der: &[u8]) -> Option<Algorithm> {
+    let (info, _) = der_element(der, TAG_SEQUENCE)?;
+    let (_, rest) = der_element(info, TAG_INTEGER)?;
+    let (algorithm, _) = der_element(rest, TAG_SEQUENCE)?;
+    let (oid, _) = der_element(algorithm, TAG_OID)?;
+    algorithm_for_oid(oid)
+}
+
+/// Algorithm and key bytes of a SubjectPublicKeyInfo
+fn spki_key(der: &[u8]) -> Option<(Algorithm, &[u8])> {
+    let (info, _) = der_element(der, TAG_SEQUENCE)?;
+    let (algorithm, rest) = der_element(info, TAG_SEQUENCE)?;
+    let (oid, _) = der_element(algorithm, TAG_OID)?;
+    let (bits, _) = der_element(rest, TAG_BIT_STRING)?;
+    // The key is a whole number of bytes, so no bits are unused
+    let key = bits.strip_prefix(&[0])?;
+    Some((algorithm_for_oid(oid)?, key))
+}
+
+/// Big-endian integer without the sign padding DER adds
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|&byte| byte != 0)
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn fixture(name: &str) -> Option<String> {
+        Some(format!(
+            "{}/fixtures/jwt/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+    }
+
+    #[test]
+    fn test_load_rsa_key() {
+        let config = JwtConfig {
+            private_key_file: fixture("rsa-private.pem"),
+            ..JwtConfig::default()
+        };
+        let keys = JwtKeys::load(&config, "secret").unwrap();
+        assert_eq!(keys.signing_key().algorithm(), Algorithm::RS256);
+
+        let jwks = keys.jwks(Utc::now());
+        assert_eq!(jwks.keys.len(), 1);
+        let jwk = &jwks.keys[0];
+        assert_eq!(jwk.kty, "RSA");
+        assert_eq!(jwk.alg, "RS256");
+        assert_eq!(jwk.e.as_deref(), Some("AQAB"));
+        // 2048-bit modulus
+        assert_eq!(jwk.n.as_ref().map(|n| n.len()), Some(342));
+        assert_eq!(jwk.kid, keys.signing_key().kid());
+
+        // The public key file describes the same key
+        let config = JwtConfig {
+            previous_public_key_file: fixture("rsa-public.pem"),
+            ..JwtConfig::default()
+        };
+        let previous = JwtKeys::load(&config, "secret").unwrap();
+        assert_eq!(previous.jwks(Utc::now()).keys, jwks.keys);
+    }
+
+    #[test]
+    fn test_load_ed25519_key() {
+        let config = JwtConfig {
+            private_key_file: fixture("ed25519-private.pem"),
+            key_id: Some("2026-10".to_string()),
+            previous_public_key_file: fixture("ed25519-public.pem"),
+            ..JwtConfig::default()
+        };
+        let keys = JwtKeys::load(&config, "secret").unwrap();
+        assert_eq!(keys.signing_key().algorithm(), Algorithm::EdDSA);
+        assert_eq!(keys.signing_key().kid(), "2026-10");
+
+        let jwks = keys.jwks(Utc::now());
+        assert_eq!(jwks.keys.len(), 2);
+        assert_eq!(jwks.keys[0].kid, "2026-10");
+        assert_eq!(jwks.keys[0].crv.as_deref(), Some("Ed25519"));
+        assert_eq!(jwks.keys[0].x, jwks.keys[1].x);
+    }
+
+    #[test]
+    fn test_load_invalid_keys() {
+        let config = JwtConfig {
+            private_key_file: fixture("rsa-public.pem"),
+            ..JwtConfig::default()
+        };
+        assert!(matches!(
+            JwtKeys::load(&config, "secret"),
+            Err(JwtKeyError::InvalidKey(..))
+        ));
+
+        let config = JwtConfig {
+            previous_public_key_file: fixture("missing.pem"),
+            ..JwtConfig::default()
+        };
+        assert!(matches!(
+            JwtKeys::load(&config, "secret"),
+            Err(JwtKeyError::Io(..))
+        ));
+    }
+
+    #[test]
+    fn test_verifying_keys() {
+        let now = Utc::now();
+        let config = JwtConfig {
+            previous_secret: Some("old-secret".to_string()),
+            previous_until: Some(now + Duration::days(1)),
+            ..JwtConfig::default()
+        };
+        let keys = JwtKeys::load(&config, "new-secret").unwrap();
+        let old_kid = JwtKey::hmac("old-secret").kid;
+        let kids = |kid: Option<&str>, algorithm: Algorithm, now: DateTime<Utc>| -> Vec<String> {
+            keys.verifying_keys(kid, algorithm, now)
+                .into_iter()
+                .map(|key| key.kid().to_string())
+                .collect()
+        };
+
+        let new_kid = keys.signing_key().kid().to_string();
+        assert_ne!(new_kid, old_kid);
+        assert_eq!(
+            kids(None, Algorithm::HS256, now),
+            vec![new_kid.clone(), old_kid.clone()]
+        );
+        assert_eq!(
+            kids(Some(&old_kid), Algorithm::HS256, now),
+            vec![old_kid.clone(), new_kid.clone()]
+        );
+        assert!(kids(None, Algorithm::RS256, now).is_empty());
+
+        // The previous key is dropped once its grace window ends
+        assert_eq!(
+            kids(Some(&old_kid), Algorithm::HS256, now + Duration::days(2)),
+            vec![new_kid]
+        );
+        // HMAC secrets are never published
+        assert!(keys.jwks(now).keys.is_empty());
+    }
+}
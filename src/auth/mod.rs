@@ -2,28 +2,53 @@
 //!
 //! This module provides authentication functionality including:
 //! - Password hashing with bcrypt
-//! - JWT token generation and verification
+//! - JWT token generation and verification, with HS256, RS256, or EdDSA
+//!   keys and rotation (see [`keys`])
 //! - Google OAuth token verification
 //! - Authentication middleware for protected routes
 //! - HTTP-only cookie support for secure token storage
+//! - Session-bound tokens that can be revoked per device
+//! - Token versions that revoke all of a user's tokens at once
+//! - Anonymous device tokens for viewers without an account (see [`device`])
+//! - Rejection of deactivated accounts on every authenticated request
+//! - Password strength and breach checking (see [`password`])
+//! - Signed, expiring URLs with key rotation (see [`signing`])
+//! - Role-based permissions for operator endpoints (see [`permissions`])
+//! - Spam and abuse protection for registration (see [`registration`])
+
+pub mod device;
+pub mod keys;
+pub mod password;
+pub mod permissions;
+pub mod registration;
+pub mod signing;
 
 use actix_web::cookie::time::Duration as CookieDuration;
-use actix_web::cookie::{Cookie, SameSite};
+use actix_web::cookie::{Cookie, CookieBuilder};
 use actix_web::dev::ServiceRequest;
-use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::future::{ready, Ready};
+use sqlx::PgPool;
+use std::future::Future;
+use std::pin::Pin;
 use thiserror::Error;
+use tracing::error;
 
-use crate::models::ApiError;
+use crate::config::CookieConfig;
+use crate::db::{get_token_state, touch_session, TokenState, DEFAULT_TENANT_ID};
+use crate::models::{ApiError, ErrorCode};
+use crate::tenants::CurrentTenant;
+
+pub use device::Viewer;
+pub use keys::{JwtKeyError, JwtKeys};
 
 /// Default bcrypt cost factor (12 is recommended for production)
 const BCRYPT_COST: u32 = 12;
 
 /// JWT token expiry duration in days
-const JWT_EXPIRY_DAYS: i64 = 7;
+pub const JWT_EXPIRY_DAYS: i64 = 7;
 
 /// Cookie name for JWT token
 pub const AUTH_COOKIE_NAME: &str = "auth_token";
@@ -60,6 +85,18 @@ pub enum AuthError {
 
     #[error("User not found")]
     UserNotFound,
+
+    #[error("Session revoked or expired")]
+    SessionRevoked,
+
+    #[error("Token has been revoked")]
+    TokenRevoked,
+
+    #[error("Token was issued for a different tenant")]
+    TenantMismatch,
+
+    #[error("Account is deactivated")]
+    AccountDeactivated,
 }
 
 /// JWT claims structure
@@ -71,6 +108,46 @@ pub struct Claims {
     pub exp: i64,
     /// Issued at time (Unix timestamp)
     pub iat: i64,
+    /// Session ID the token is bound to (absent on legacy tokens)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<i32>,
+    /// Tenant the token was issued for (absent on legacy tokens, which
+    /// belong to the default tenant)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tid: Option<i32>,
+    /// Issuer, when JWT_ISSUER is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    /// Audience, when JWT_AUDIENCE is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// The user's role ("admin" or "user") when the token was issued, for
+    /// services verifying tokens; permissions are still checked per request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// The user's token version when the token was issued (absent on legacy
+    /// tokens, which count as version 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_version: Option<i32>,
+}
+
+/// What a session token says about its user besides the ID
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserClaims {
+    /// The user's role
+    pub role: Option<String>,
+    /// The user's current token version
+    pub token_version: i32,
+}
+
+impl UserClaims {
+    /// Claims for a user's token state
+    pub fn from_state(state: &TokenState) -> Self {
+        Self {
+            role: Some(if state.is_admin { "admin" } else { "user" }.to_string()),
+            token_version: state.token_version,
+        }
+    }
 }
 
 /// Google OAuth token payload (subset of fields we need)
@@ -93,6 +170,23 @@ pub struct GoogleTokenPayload {
 pub struct AuthenticatedUser {
     /// User ID from the JWT
     pub user_id: i32,
+    /// Session ID from the JWT, if the token is session-bound
+    pub session_id: Option<i32>,
+    /// Tenant the token was issued for
+    pub tenant_id: i32,
+    /// Token version the token was issued with
+    pub token_version: i32,
+}
+
+impl From<Claims> for AuthenticatedUser {
+    fn from(claims: Claims) -> Self {
+        Self {
+            user_id: claims.sub,
+            session_id: claims.sid,
+            tenant_id: claims.tid.unwrap_or(DEFAULT_TENANT_ID),
+            token_version: claims.token_version.unwrap_or(0),
+        }
+    }
 }
 
 /// Hash a password using bcrypt
@@ -135,7 +229,7 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, AuthError> {
 ///
 /// # Arguments
 /// * `user_id` - The user's ID to encode in the token
-/// * `secret` - The JWT secret key for signing
+/// * `keys` - The JWT keys; the token is signed with the signing key
 ///
 /// # Returns
 /// * `Ok(String)` - The generated JWT token
@@ -143,9 +237,42 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, AuthError> {
 ///
 /// # Example
 /// ```ignore
-/// let token = generate_token(user_id, &jwt_secret)?;
+/// let token = generate_token(user_id, &jwt_keys)?;
 /// ```
-pub fn generate_token(user_id: i32, secret: &str) -> Result<String, AuthError> {
+pub fn generate_token(user_id: i32, keys: &JwtKeys) -> Result<String, AuthError> {
+    encode_token(user_id, None, None, None, keys)
+}
+
+/// Generate a JWT token bound to a session
+///
+/// The session and the user's token version are checked on every
+/// authenticated request, so revoking the session or bumping the version
+/// invalidates the token before it expires.
+///
+/// # Arguments
+/// * `user_id` - The user's ID to encode in the token
+/// * `session_id` - The session the token belongs to
+/// * `tenant_id` - The tenant the user belongs to
+/// * `user` - The user's role and token version
+/// * `keys` - The JWT keys; the token is signed with the signing key
+pub fn generate_session_token(
+    user_id: i32,
+    session_id: i32,
+    tenant_id: i32,
+    user: &UserClaims,
+    keys: &JwtKeys,
+) -> Result<String, AuthError> {
+    encode_token(user_id, Some(session_id), Some(tenant_id), Some(user), keys)
+}
+
+/// Encode and sign the claims for a token
+fn encode_token(
+    user_id: i32,
+    session_id: Option<i32>,
+    tenant_id: Option<i32>,
+    user: Option<&UserClaims>,
+    keys: &JwtKeys,
+) -> Result<String, AuthError> {
     let now = Utc::now();
     let expiry = now + Duration::days(JWT_EXPIRY_DAYS);
 
@@ -153,21 +280,31 @@ pub fn generate_token(user_id: i32, secret: &str) -> Result<String, AuthError> {
         sub: user_id,
         exp: expiry.timestamp(),
         iat: now.timestamp(),
+        sid: session_id,
+        tid: tenant_id,
+        iss: keys.issuer().map(str::to_string),
+        aud: keys.audience().map(str::to_string),
+        role: user.and_then(|user| user.role.clone()),
+        token_version: user.map(|user| user.token_version),
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
-    .map_err(|e| AuthError::TokenGenerationError(e.to_string()))
+    let signing = keys.signing_key();
+    let mut header = Header::new(signing.algorithm());
+    header.kid = Some(signing.kid().to_string());
+
+    encode(&header, &claims, keys.encoding_key())
+        .map_err(|e| AuthError::TokenGenerationError(e.to_string()))
 }
 
 /// Verify and decode a JWT token
 ///
+/// Tokens signed with the signing key or, during a rotation, the previous
+/// key are accepted; the `kid` header picks which is tried first. When the
+/// keys have an issuer or audience, tokens must carry it.
+///
 /// # Arguments
 /// * `token` - The JWT token to verify
-/// * `secret` - The JWT secret key for verification
+/// * `keys` - The JWT keys tokens are accepted from
 ///
 /// # Returns
 /// * `Ok(Claims)` - The decoded claims if valid
@@ -175,21 +312,46 @@ pub fn generate_token(user_id: i32, secret: &str) -> Result<String, AuthError> {
 ///
 /// # Example
 /// ```ignore
-/// let claims = verify_token(&token, &jwt_secret)?;
+/// let claims = verify_token(&token, &jwt_keys)?;
 /// let user_id = claims.sub;
 /// ```
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims, AuthError> {
-    let token_data: TokenData<Claims> = decode(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|e| match e.kind() {
-        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AuthError::TokenExpired,
-        _ => AuthError::TokenVerificationError(e.to_string()),
-    })?;
+pub fn verify_token(token: &str, keys: &JwtKeys) -> Result<Claims, AuthError> {
+    let header =
+        decode_header(token).map_err(|e| AuthError::TokenVerificationError(e.to_string()))?;
+
+    let mut error = AuthError::TokenVerificationError("No key for the token".to_string());
+    for key in keys.verifying_keys(header.kid.as_deref(), header.alg, Utc::now()) {
+        match decode::<Claims>(token, key.decoding_key(), &validation(key, keys)) {
+            Ok(token_data) => return Ok(token_data.claims),
+            Err(e) => match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                    return Err(AuthError::TokenExpired)
+                }
+                _ => error = AuthError::TokenVerificationError(e.to_string()),
+            },
+        }
+    }
+    Err(error)
+}
 
-    Ok(token_data.claims)
+/// Validation of a token signed with `key`: expiry, plus issuer and audience
+/// when configured
+fn validation(key: &keys::JwtKey, keys: &JwtKeys) -> Validation {
+    let mut validation = Validation::new(key.algorithm());
+    let mut required = vec!["exp"];
+    if let Some(issuer) = keys.issuer() {
+        validation.set_issuer(&[issuer]);
+        required.push("iss");
+    }
+    match keys.audience() {
+        Some(audience) => {
+            validation.set_audience(&[audience]);
+            required.push("aud");
+        }
+        None => validation.validate_aud = false,
+    }
+    validation.set_required_spec_claims(&required);
+    validation
 }
 
 // ============================================================================
@@ -200,34 +362,42 @@ pub fn verify_token(token: &str, secret: &str) -> Result<Claims, AuthError> {
 ///
 /// # Arguments
 /// * `token` - The JWT token to store in the cookie
+/// * `config` - Cookie attributes from COOKIE_* settings
 ///
 /// # Returns
 /// A Cookie configured with:
 /// - HttpOnly: true (prevents JavaScript access)
-/// - Secure: true (only sent over HTTPS in production)
-/// - SameSite: Lax (CSRF protection)
-/// - Path: "/" (available for all routes)
-/// - Max-Age: 7 days (matches JWT expiry)
-pub fn create_auth_cookie(token: &str) -> Cookie<'static> {
-    Cookie::build(AUTH_COOKIE_NAME, token.to_owned())
-        .path("/")
-        .http_only(true)
-        .secure(true) // Set to false for local development without HTTPS
-        .same_site(SameSite::Lax)
-        .max_age(CookieDuration::days(JWT_EXPIRY_DAYS))
+/// - Path, Secure, SameSite, Domain, and Max-Age from `config` (by default
+///   the API's base path or "/", Secure outside development, Lax, the API's
+///   host, and 7 days to match the JWT expiry)
+pub fn create_auth_cookie(token: &str, config: &CookieConfig) -> Cookie<'static> {
+    cookie_builder(token.to_owned(), config)
+        .max_age(CookieDuration::seconds(config.max_age_secs))
         .finish()
 }
 
+/// Auth cookie with every attribute but Max-Age set from `config`
+fn cookie_builder(value: String, config: &CookieConfig) -> CookieBuilder<'static> {
+    let builder = Cookie::build(AUTH_COOKIE_NAME, value)
+        .path(config.path.clone())
+        .http_only(true)
+        .secure(config.secure)
+        .same_site(config.same_site);
+    match &config.domain {
+        Some(domain) => builder.domain(domain.clone()),
+        None => builder,
+    }
+}
+
 /// Create a cookie that clears the auth token (for logout)
 ///
+/// Takes the same `config` as [`create_auth_cookie`]: browsers only replace
+/// a cookie with the same domain and path.
+///
 /// # Returns
 /// A Cookie configured to expire immediately, effectively removing the auth cookie
-pub fn create_logout_cookie() -> Cookie<'static> {
-    Cookie::build(AUTH_COOKIE_NAME, "")
-        .path("/")
-        .http_only(true)
-        .secure(true)
-        .same_site(SameSite::Lax)
+pub fn create_logout_cookie(config: &CookieConfig) -> Cookie<'static> {
+    cookie_builder(String::new(), config)
         .max_age(CookieDuration::ZERO)
         .finish()
 }
@@ -320,14 +490,14 @@ pub fn extract_token_from_header(auth_header: &str) -> Result<&str, AuthError> {
 ///
 /// # Arguments
 /// * `req` - The service request
-/// * `secret` - The JWT secret key
+/// * `keys` - The JWT keys tokens are accepted from
 ///
 /// # Returns
 /// * `Ok(AuthenticatedUser)` - The authenticated user info
 /// * `Err(AuthError)` - If authentication fails
 pub fn validate_request(
     req: &ServiceRequest,
-    secret: &str,
+    keys: &JwtKeys,
 ) -> Result<AuthenticatedUser, AuthError> {
     let auth_header = req
         .headers()
@@ -336,11 +506,9 @@ pub fn validate_request(
         .ok_or(AuthError::MissingAuthHeader)?;
 
     let token = extract_token_from_header(auth_header)?;
-    let claims = verify_token(token, secret)?;
+    let claims = verify_token(token, keys)?;
 
-    Ok(AuthenticatedUser {
-        user_id: claims.sub,
-    })
+    Ok(AuthenticatedUser::from(claims))
 }
 
 /// Validate an HTTP request and extract the authenticated user
@@ -351,14 +519,14 @@ pub fn validate_request(
 ///
 /// # Arguments
 /// * `req` - The HTTP request
-/// * `secret` - The JWT secret key
+/// * `keys` - The JWT keys tokens are accepted from
 ///
 /// # Returns
 /// * `Ok(AuthenticatedUser)` - The authenticated user info
 /// * `Err(AuthError)` - If authentication fails
 pub fn validate_http_request(
     req: &HttpRequest,
-    secret: &str,
+    keys: &JwtKeys,
 ) -> Result<AuthenticatedUser, AuthError> {
     // First, try to get token from Authorization header
     let token = if let Some(auth_header) = req
@@ -374,18 +542,19 @@ pub fn validate_http_request(
         return Err(AuthError::MissingAuthHeader);
     };
 
-    let claims = verify_token(&token, secret)?;
+    let claims = verify_token(&token, keys)?;
 
-    Ok(AuthenticatedUser {
-        user_id: claims.sub,
-    })
+    Ok(AuthenticatedUser::from(claims))
 }
 
 /// Configuration for the auth extractor
 #[derive(Clone)]
 pub struct AuthConfig {
-    /// JWT secret key
-    pub jwt_secret: String,
+    /// Keys tokens are accepted from
+    pub jwt_keys: JwtKeys,
+    /// Database pool used to check that session-bound tokens are still active
+    /// and their account is not deactivated
+    pub pool: Option<PgPool>,
 }
 
 /// Authenticated user extractor for Actix-web routes
@@ -404,57 +573,144 @@ pub struct AuthConfig {
 pub struct Auth {
     /// The authenticated user's ID
     pub user_id: i32,
+    /// The session the request's token belongs to
+    pub session_id: Option<i32>,
+    /// The tenant the user belongs to
+    pub tenant_id: i32,
+}
+
+/// Build the response for an authentication error
+///
+/// 401 for missing or invalid credentials, 403 for deactivated accounts.
+fn auth_error_response(e: AuthError) -> actix_web::Error {
+    let error_response = match &e {
+        AuthError::MissingAuthHeader => HttpResponse::Unauthorized().json(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Missing authorization header",
+        )),
+        AuthError::InvalidAuthHeaderFormat => HttpResponse::Unauthorized().json(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Invalid authorization header format",
+        )),
+        AuthError::TokenExpired => HttpResponse::Unauthorized()
+            .json(ApiError::new(ErrorCode::Unauthorized, "Token expired")),
+        AuthError::TokenVerificationError(_) | AuthError::InvalidToken => {
+            HttpResponse::Unauthorized()
+                .json(ApiError::new(ErrorCode::Unauthorized, "Invalid token"))
+        }
+        AuthError::SessionRevoked => HttpResponse::Unauthorized().json(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Session has been revoked",
+        )),
+        AuthError::TokenRevoked => HttpResponse::Unauthorized().json(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Token has been revoked",
+        )),
+        AuthError::TenantMismatch => HttpResponse::Unauthorized().json(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Token is not valid for this site",
+        )),
+        AuthError::AccountDeactivated => HttpResponse::Forbidden().json(ApiError::new(
+            ErrorCode::Forbidden,
+            "Account is deactivated",
+        )),
+        _ => HttpResponse::Unauthorized().json(ApiError::new(
+            ErrorCode::Unauthorized,
+            "Authentication failed",
+        )),
+    };
+    actix_web::error::InternalError::from_response(e, error_response).into()
+}
+
+/// Build the 500 response for a check that couldn't be run
+fn verification_failed(message: &str, e: impl std::fmt::Display) -> actix_web::Error {
+    let error_response =
+        HttpResponse::InternalServerError().json(ApiError::new(ErrorCode::InternalError, message));
+    actix_web::error::InternalError::from_response(
+        AuthError::TokenVerificationError(e.to_string()),
+        error_response,
+    )
+    .into()
 }
 
 impl FromRequest for Auth {
     type Error = actix_web::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
-        // Get the JWT secret from app data
-        let config = req.app_data::<web::Data<AuthConfig>>();
-
-        let result = match config {
-            Some(config) => match validate_http_request(req, &config.jwt_secret) {
-                Ok(user) => Ok(Auth {
-                    user_id: user.user_id,
-                }),
-                Err(e) => {
-                    let error_response = match &e {
-                        AuthError::MissingAuthHeader => HttpResponse::Unauthorized()
-                            .json(ApiError::new("Missing authorization header")),
-                        AuthError::InvalidAuthHeaderFormat => HttpResponse::Unauthorized()
-                            .json(ApiError::new("Invalid authorization header format")),
-                        AuthError::TokenExpired => {
-                            HttpResponse::Unauthorized().json(ApiError::new("Token expired"))
-                        }
-                        AuthError::TokenVerificationError(_) | AuthError::InvalidToken => {
-                            HttpResponse::Unauthorized().json(ApiError::new("Invalid token"))
-                        }
-                        _ => HttpResponse::Unauthorized()
-                            .json(ApiError::new("Authentication failed")),
-                    };
-                    Err(actix_web::error::InternalError::from_response(e, error_response).into())
-                }
-            },
+        // Get the JWT keys from app data
+        let config = match req.app_data::<web::Data<AuthConfig>>() {
+            Some(config) => config.clone(),
             None => {
-                let error_response = HttpResponse::InternalServerError()
-                    .json(ApiError::new("Auth configuration not found"));
-                Err(actix_web::error::InternalError::from_response(
+                let error_response = HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::InternalError,
+                    "Auth configuration not found",
+                ));
+                let error = actix_web::error::InternalError::from_response(
                     AuthError::TokenVerificationError("Config not found".to_string()),
                     error_response,
                 )
-                .into())
+                .into();
+                return Box::pin(async move { Err(error) });
             }
         };
 
-        ready(result)
+        let user = validate_http_request(req, &config.jwt_keys);
+        let tenant = req.extensions().get::<CurrentTenant>().map(|t| t.id);
+
+        Box::pin(async move {
+            let user = user.map_err(auth_error_response)?;
+
+            // Tokens only work on the tenant they were issued for
+            if tenant.is_some_and(|tenant_id| tenant_id != user.tenant_id) {
+                return Err(auth_error_response(AuthError::TenantMismatch));
+            }
+
+            // Session-bound tokens must refer to a session that is still active
+            if let (Some(session_id), Some(pool)) = (user.session_id, &config.pool) {
+                match touch_session(pool, session_id, user.user_id).await {
+                    Ok(true) => {}
+                    Ok(false) => return Err(auth_error_response(AuthError::SessionRevoked)),
+                    Err(e) => {
+                        error!("Failed to check session {}: {}", session_id, e);
+                        return Err(verification_failed("Failed to verify session", e));
+                    }
+                }
+            }
+
+            // Deactivated accounts lose access at once, whatever tokens they
+            // hold, and bumping the token version revokes every older token
+            if let Some(pool) = &config.pool {
+                match get_token_state(pool, user.user_id).await {
+                    Ok(Some(state)) if !state.is_active => {
+                        return Err(auth_error_response(AuthError::AccountDeactivated))
+                    }
+                    Ok(Some(state)) if state.token_version != user.token_version => {
+                        return Err(auth_error_response(AuthError::TokenRevoked))
+                    }
+                    Ok(Some(_)) => {}
+                    Ok(None) => return Err(auth_error_response(AuthError::AccountDeactivated)),
+                    Err(e) => {
+                        error!("Failed to check account of user {}: {}", user.user_id, e);
+                        return Err(verification_failed("Failed to verify account", e));
+                    }
+                }
+            }
+
+            Ok(Auth {
+                user_id: user.user_id,
+                session_id: user.session_id,
+                tenant_id: user.tenant_id,
+            })
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{AppEnv, JwtConfig};
+    use actix_web::cookie::SameSite;
 
     #[test]
     fn test_hash_password_creates_valid_hash() {
@@ -519,7 +775,7 @@ mod tests {
     #[test]
     fn test_generate_token_creates_valid_token() {
         let user_id = 42;
-        let secret = "test_secret_key";
+        let secret = &JwtKeys::hmac("test_secret_key");
 
         let token = generate_token(user_id, secret).unwrap();
 
@@ -532,7 +788,7 @@ mod tests {
     #[test]
     fn test_verify_token_valid_token() {
         let user_id = 123;
-        let secret = "test_secret_key";
+        let secret = &JwtKeys::hmac("test_secret_key");
 
         let token = generate_token(user_id, secret).unwrap();
         let claims = verify_token(&token, secret).unwrap();
@@ -540,11 +796,94 @@ mod tests {
         assert_eq!(claims.sub, user_id);
     }
 
+    #[test]
+    fn test_verify_token_without_key_id() {
+        // Tokens issued before key IDs were added
+        let claims = Claims {
+            sub: 7,
+            exp: (Utc::now() + Duration::days(1)).timestamp(),
+            iat: Utc::now().timestamp(),
+            sid: None,
+            tid: None,
+            iss: None,
+            aud: None,
+            role: None,
+            token_version: None,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"test_secret"),
+        )
+        .unwrap();
+
+        let keys = JwtKeys::hmac("test_secret");
+        assert_eq!(verify_token(&token, &keys).unwrap().sub, 7);
+    }
+
+    #[test]
+    fn test_verify_token_across_rotation() {
+        let fixture = |name: &str| {
+            Some(format!(
+                "{}/fixtures/jwt/{}",
+                env!("CARGO_MANIFEST_DIR"),
+                name
+            ))
+        };
+        let old_keys = JwtKeys::hmac("old_secret");
+        let old_token = generate_token(7, &old_keys).unwrap();
+
+        // Move from the shared secret to an RSA key, keeping the old secret
+        let rotated = JwtConfig {
+            private_key_file: fixture("rsa-private.pem"),
+            previous_secret: Some("old_secret".to_string()),
+            ..JwtConfig::default()
+        };
+        let keys = JwtKeys::load(&rotated, "new_secret").unwrap();
+        let token = generate_token(8, &keys).unwrap();
+        assert_eq!(verify_token(&token, &keys).unwrap().sub, 8);
+        assert_eq!(verify_token(&old_token, &keys).unwrap().sub, 7);
+        assert!(verify_token(&token, &old_keys).is_err());
+
+        // Other services verify with the public key alone
+        let verifier = JwtKeys::load(
+            &JwtConfig {
+                previous_public_key_file: fixture("rsa-public.pem"),
+                ..JwtConfig::default()
+            },
+            "unrelated",
+        )
+        .unwrap();
+        assert_eq!(verify_token(&token, &verifier).unwrap().sub, 8);
+
+        // After the grace window only new tokens work
+        let expired = JwtConfig {
+            previous_until: Some(Utc::now() - Duration::minutes(1)),
+            ..rotated
+        };
+        let keys = JwtKeys::load(&expired, "new_secret").unwrap();
+        assert!(verify_token(&token, &keys).is_ok());
+        assert!(verify_token(&old_token, &keys).is_err());
+
+        // EdDSA tokens
+        let ed_keys = JwtKeys::load(
+            &JwtConfig {
+                private_key_file: fixture("ed25519-private.pem"),
+                ..JwtConfig::default()
+            },
+            "new_secret",
+        )
+        .unwrap();
+        let token = generate_token(9, &ed_keys).unwrap();
+        assert_eq!(verify_token(&token, &ed_keys).unwrap().sub, 9);
+        assert!(verify_token(&token, &keys).is_err());
+    }
+
     #[test]
     fn test_verify_token_wrong_secret() {
         let user_id = 123;
-        let secret = "correct_secret";
-        let wrong_secret = "wrong_secret";
+        let secret = &JwtKeys::hmac("correct_secret");
+        let wrong_secret = &JwtKeys::hmac("wrong_secret");
 
         let token = generate_token(user_id, secret).unwrap();
         let result = verify_token(&token, wrong_secret);
@@ -554,7 +893,7 @@ mod tests {
 
     #[test]
     fn test_verify_token_invalid_token() {
-        let secret = "test_secret";
+        let secret = &JwtKeys::hmac("test_secret");
         let invalid_token = "invalid.token.here";
 
         let result = verify_token(invalid_token, secret);
@@ -562,10 +901,64 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_session_token_carries_session_id() {
+        let secret = &JwtKeys::hmac("test_secret");
+
+        let user = UserClaims {
+            role: Some("admin".to_string()),
+            token_version: 4,
+        };
+        let token = generate_session_token(7, 55, 3, &user, secret).unwrap();
+        let claims = verify_token(&token, secret).unwrap();
+        assert_eq!(claims.sub, 7);
+        assert_eq!(claims.sid, Some(55));
+        assert_eq!(claims.tid, Some(3));
+        assert_eq!(claims.role.as_deref(), Some("admin"));
+        assert_eq!(claims.token_version, Some(4));
+        assert_eq!(AuthenticatedUser::from(claims).token_version, 4);
+
+        let legacy = generate_token(7, secret).unwrap();
+        let claims = verify_token(&legacy, secret).unwrap();
+        assert_eq!(claims.sid, None);
+        assert_eq!(claims.tid, None);
+        assert_eq!(claims.role, None);
+        assert_eq!(AuthenticatedUser::from(claims).token_version, 0);
+    }
+
+    #[test]
+    fn test_verify_token_issuer_and_audience() {
+        let load = |issuer: Option<&str>, audience: Option<&str>| {
+            let config = JwtConfig {
+                issuer: issuer.map(str::to_string),
+                audience: audience.map(str::to_string),
+                ..JwtConfig::default()
+            };
+            JwtKeys::load(&config, "test_secret").unwrap()
+        };
+        let keys = load(Some("https://api.example.com"), Some("anime-web"));
+        let plain = load(None, None);
+
+        let token = generate_token(7, &keys).unwrap();
+        let claims = verify_token(&token, &keys).unwrap();
+        assert_eq!(claims.iss.as_deref(), Some("https://api.example.com"));
+        assert_eq!(claims.aud.as_deref(), Some("anime-web"));
+        // Deployments without an audience ignore it
+        assert!(verify_token(&token, &plain).is_ok());
+
+        // Tokens without the claims, or with other values, are rejected
+        let legacy = generate_token(7, &plain).unwrap();
+        assert!(verify_token(&legacy, &keys).is_err());
+        let other_audience = load(Some("https://api.example.com"), Some("mobile"));
+        assert!(verify_token(&token, &other_audience).is_err());
+        let other_issuer = load(Some("https://staging.example.com"), Some("anime-web"));
+        assert!(verify_token(&token, &other_issuer).is_err());
+    }
+
     #[test]
     fn test_token_contains_correct_claims() {
         let user_id = 999;
-        let secret = "test_secret";
+        let secret = &JwtKeys::hmac("test_secret");
 
         let token = generate_token(user_id, secret).unwrap();
         let claims = verify_token(&token, secret).unwrap();
@@ -626,7 +1019,8 @@ mod tests {
     #[test]
     fn test_create_auth_cookie_properties() {
         let token = "test_jwt_token_123";
-        let cookie = create_auth_cookie(token);
+        let config = CookieConfig::defaults_for(AppEnv::Production);
+        let cookie = create_auth_cookie(token, &config);
 
         assert_eq!(cookie.name(), AUTH_COOKIE_NAME);
         assert_eq!(cookie.value(), token);
@@ -634,11 +1028,46 @@ mod tests {
         assert!(cookie.http_only().unwrap_or(false));
         assert!(cookie.secure().unwrap_or(false));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.domain(), None);
+        assert_eq!(
+            cookie.max_age(),
+            Some(CookieDuration::days(JWT_EXPIRY_DAYS))
+        );
+
+        // Local development over plain HTTP
+        let cookie = create_auth_cookie(token, &CookieConfig::defaults_for(AppEnv::Development));
+        assert!(!cookie.secure().unwrap_or(false));
+        assert!(cookie.http_only().unwrap_or(false));
+
+        // Frontend on another site
+        let config = CookieConfig {
+            same_site: SameSite::None,
+            domain: Some("api.example.com".to_string()),
+            max_age_secs: 3600,
+            ..config
+        };
+        let cookie = create_auth_cookie(token, &config);
+        assert_eq!(cookie.same_site(), Some(SameSite::None));
+        assert_eq!(cookie.domain(), Some("api.example.com"));
+        assert_eq!(cookie.max_age(), Some(CookieDuration::hours(1)));
+
+        // API behind a path-based proxy
+        let config = CookieConfig {
+            path: "/anime-api".to_string(),
+            ..config
+        };
+        let cookie = create_auth_cookie(token, &config);
+        assert_eq!(cookie.path(), Some("/anime-api"));
+        assert_eq!(create_logout_cookie(&config).path(), Some("/anime-api"));
     }
 
     #[test]
     fn test_create_logout_cookie_clears_value() {
-        let cookie = create_logout_cookie();
+        let config = CookieConfig {
+            domain: Some("example.com".to_string()),
+            ..CookieConfig::defaults_for(AppEnv::Production)
+        };
+        let cookie = create_logout_cookie(&config);
 
         assert_eq!(cookie.name(), AUTH_COOKIE_NAME);
         assert_eq!(cookie.value(), "");
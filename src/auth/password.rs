@@ -0,0 +1,469 @@
+//! Password strength estimation and breach checking
+//!
+//! Strength is estimated zxcvbn-style: the password is split into guessable
+//! patterns (common passwords, the user's own name or email, repeats,
+//! sequences, keyboard rows) and brute-forced characters, and the summed
+//! guess count is mapped to a 0-4 score. Optionally, the password is checked
+//! against HaveIBeenPwned using the k-anonymity range API, which only ever
+//! sees the first five characters of the password's SHA-1 hash.
+
+use std::time::Duration;
+
+use sha1::{Digest, Sha1};
+use tracing::warn;
+
+use crate::models::PasswordFeedback;
+
+/// Highest strength score
+pub const MAX_SCORE: u8 = 4;
+
+/// Minimum score accepted when none is configured
+pub const DEFAULT_MIN_SCORE: u8 = 2;
+
+/// HaveIBeenPwned range API
+const HIBP_RANGE_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+/// Timeout for breach lookups; the check is skipped if it is exceeded
+const HIBP_TIMEOUT_SECS: u64 = 5;
+
+/// Most common passwords, most popular first
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456",
+    "password",
+    "12345678",
+    "qwerty",
+    "123456789",
+    "12345",
+    "1234",
+    "111111",
+    "1234567",
+    "dragon",
+    "123123",
+    "baseball",
+    "abc123",
+    "football",
+    "monkey",
+    "letmein",
+    "696969",
+    "shadow",
+    "master",
+    "666666",
+    "qwertyuiop",
+    "123321",
+    "mustang",
+    "1234567890",
+    "michael",
+    "654321",
+    "superman",
+    "1qaz2wsx",
+    "7777777",
+    "121212",
+    "000000",
+    "qazwsx",
+    "123qwe",
+    "killer",
+    "trustno1",
+    "jordan",
+    "jennifer",
+    "zxcvbnm",
+    "asdfgh",
+    "hunter",
+    "buster",
+    "soccer",
+    "harley",
+    "batman",
+    "andrew",
+    "tigger",
+    "sunshine",
+    "iloveyou",
+    "charlie",
+    "robert",
+    "thomas",
+    "hockey",
+    "ranger",
+    "daniel",
+    "starwars",
+    "klaster",
+    "112233",
+    "george",
+    "computer",
+    "michelle",
+    "jessica",
+    "pepper",
+    "zxcvbn",
+    "555555",
+    "freedom",
+    "princess",
+    "welcome",
+    "login",
+    "admin",
+    "passw0rd",
+    "secret",
+    "summer",
+    "flower",
+    "lovely",
+    "hello",
+    "whatever",
+    "qwerty123",
+    "ninja",
+    "azerty",
+    "solo",
+    "naruto",
+    "anime",
+    "onepiece",
+    "pokemon",
+    "goku",
+    "sasuke",
+    "otaku",
+    "senpai",
+    "waifu",
+];
+
+/// Keyboard rows checked for adjacent-key runs
+const KEYBOARD_ROWS: &[&str] = &["qwertyuiop", "asdfghjkl", "zxcvbnm", "1234567890"];
+
+/// Minimum length of a repeat, sequence, or keyboard run
+const MIN_RUN_LENGTH: usize = 3;
+
+/// Estimate the strength of a password
+///
+/// # Arguments
+/// * `password` - Password to check
+/// * `user_inputs` - Values the user is known by (email, name) that should not
+///   appear in the password
+///
+/// # Returns
+/// Score and feedback; `breached` is always false here
+pub fn estimate_strength(password: &str, user_inputs: &[&str]) -> PasswordFeedback {
+    let chars: Vec<char> = password.chars().collect();
+    let lower: Vec<char> = password.to_lowercase().chars().collect();
+    let normalized: Vec<char> = lower.iter().map(|c| unleet(*c)).collect();
+    let charset = charset_size(&chars).log10();
+
+    let mut warnings = Vec::new();
+    let mut suggestions = Vec::new();
+
+    // Whole-password match against common passwords, allowing trailing digits
+    // and symbols ("password123!")
+    let lower_str: String = lower.iter().collect();
+    let trimmed = match lower_str.trim_end_matches(|c: char| !c.is_alphabetic()) {
+        "" => lower_str.as_str(),
+        trimmed => trimmed,
+    };
+    let unleeted: String = trimmed.chars().map(unleet).collect();
+    let common = [trimmed, unleeted.as_str()].into_iter().find_map(|core| {
+        COMMON_PASSWORDS
+            .iter()
+            .position(|p| *p == core)
+            .map(|rank| (rank, core.chars().count()))
+    });
+    if let Some((rank, core_len)) = common {
+        // Appended digits and symbols are usually years or "!", so they add
+        // far less than brute-forced characters
+        let suffix = chars.len().saturating_sub(core_len);
+        let log_guesses = ((rank + 1) as f64).log10() + suffix as f64 * 0.5;
+        warnings.push("This is a commonly used password".to_string());
+        suggestions.push("Avoid common passwords and add uncommon words".to_string());
+        return feedback(log_guesses, warnings, suggestions);
+    }
+
+    let inputs: Vec<Vec<char>> = user_inputs
+        .iter()
+        .flat_map(|input| input.split(['@', ' ', '.', '_', '-']))
+        .filter(|part| part.chars().count() >= MIN_RUN_LENGTH)
+        .map(|part| part.to_lowercase().chars().collect())
+        .collect();
+
+    let mut log_guesses = 0.0;
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some(len) = find_word(&lower[i..], COMMON_PASSWORDS, 4).max(find_word(
+            &normalized[i..],
+            COMMON_PASSWORDS,
+            4,
+        )) {
+            push_once(&mut warnings, "Contains a commonly used password");
+            log_guesses += (len as f64).log10() + 2.0;
+            i += len;
+        } else if let Some(len) = find_input(&lower[i..], &inputs) {
+            push_once(
+                &mut warnings,
+                "Avoid using your name or email in the password",
+            );
+            log_guesses += 1.0;
+            i += len;
+        } else if let Some(len) = repeat_len(&lower[i..]) {
+            push_once(
+                &mut warnings,
+                "Repeated characters like \"aaa\" are easy to guess",
+            );
+            log_guesses += charset + (len as f64).log10();
+            i += len;
+        } else if let Some(len) = sequence_len(&lower[i..]).or_else(|| keyboard_len(&lower[i..])) {
+            push_once(
+                &mut warnings,
+                "Sequences like \"abc\" or \"qwerty\" are easy to guess",
+            );
+            log_guesses += charset.min(1.0) + (len as f64).log10();
+            i += len;
+        } else {
+            log_guesses += charset;
+            i += 1;
+        }
+    }
+
+    if chars.len() < 12 {
+        suggestions.push("Use a longer password; a few unrelated words work well".to_string());
+    }
+    if !warnings.is_empty() {
+        suggestions.push("Avoid repeats, sequences, and personal information".to_string());
+    }
+
+    feedback(log_guesses, warnings, suggestions)
+}
+
+/// Map the estimated guess count to a score and build the feedback
+fn feedback(log_guesses: f64, warnings: Vec<String>, suggestions: Vec<String>) -> PasswordFeedback {
+    let score = match log_guesses {
+        g if g < 3.0 => 0,
+        g if g < 6.0 => 1,
+        g if g < 8.0 => 2,
+        g if g < 10.0 => 3,
+        _ => MAX_SCORE,
+    };
+
+    PasswordFeedback {
+        score,
+        warnings,
+        suggestions,
+        breached: false,
+    }
+}
+
+fn push_once(warnings: &mut Vec<String>, warning: &str) {
+    if !warnings.iter().any(|w| w == warning) {
+        warnings.push(warning.to_string());
+    }
+}
+
+/// Size of the alphabet an attacker would brute force
+fn charset_size(chars: &[char]) -> f64 {
+    let mut size = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        size += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        size += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        size += 10;
+    }
+    if chars
+        .iter()
+        .any(|c| c.is_ascii() && !c.is_ascii_alphanumeric())
+    {
+        size += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        size += 100;
+    }
+    size.max(10) as f64
+}
+
+/// Undo common character substitutions ("p@ssw0rd" -> "password")
+fn unleet(c: char) -> char {
+    match c {
+        '4' | '@' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' => 't',
+        other => other,
+    }
+}
+
+/// Length of the longest word from `words` that `chars` starts with
+fn find_word(chars: &[char], words: &[&str], min_len: usize) -> Option<usize> {
+    words
+        .iter()
+        .map(|word| word.chars().collect::<Vec<char>>())
+        .filter(|word| word.len() >= min_len && chars.starts_with(word))
+        .map(|word| word.len())
+        .max()
+}
+
+/// Length of the longest user input that `chars` starts with
+fn find_input(chars: &[char], inputs: &[Vec<char>]) -> Option<usize> {
+    inputs
+        .iter()
+        .filter(|input| chars.starts_with(input))
+        .map(|input| input.len())
+        .max()
+}
+
+/// Length of a run of the same character ("aaaa")
+fn repeat_len(chars: &[char]) -> Option<usize> {
+    let first = *chars.first()?;
+    let len = chars.iter().take_while(|c| **c == first).count();
+    (len >= MIN_RUN_LENGTH).then_some(len)
+}
+
+/// Length of an ascending or descending run ("abcd", "4321")
+fn sequence_len(chars: &[char]) -> Option<usize> {
+    if chars.len() < MIN_RUN_LENGTH {
+        return None;
+    }
+    let delta = chars[1] as i64 - chars[0] as i64;
+    if delta.abs() != 1 {
+        return None;
+    }
+    let len = 1 + chars
+        .windows(2)
+        .take_while(|pair| pair[1] as i64 - pair[0] as i64 == delta)
+        .count();
+    (len >= MIN_RUN_LENGTH).then_some(len)
+}
+
+/// Length of a run of adjacent keys on a keyboard row ("qwer", "asdf")
+fn keyboard_len(chars: &[char]) -> Option<usize> {
+    KEYBOARD_ROWS
+        .iter()
+        .filter_map(|row| {
+            let row: Vec<char> = row.chars().collect();
+            let start = row.iter().position(|c| Some(c) == chars.first())?;
+            let len = row[start..]
+                .iter()
+                .zip(chars)
+                .take_while(|(a, b)| a == b)
+                .count();
+            (len > MIN_RUN_LENGTH).then_some(len)
+        })
+        .max()
+}
+
+/// Count how often a password appears in known breaches
+///
+/// Only the first five hex characters of the SHA-1 hash are sent to
+/// HaveIBeenPwned; the returned suffixes are compared locally.
+///
+/// # Returns
+/// * `Ok(count)` - Number of times the password was seen (0 if never)
+/// * `Err(reqwest::Error)` - The lookup failed
+pub async fn breach_count(password: &str) -> Result<u64, reqwest::Error> {
+    let hash = hex::encode_upper(Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = hash.split_at(5);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(HIBP_TIMEOUT_SECS))
+        .build()?;
+    let body = client
+        .get(format!("{}{}", HIBP_RANGE_URL, prefix))
+        .header("Add-Padding", "true")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(parse_range_response(&body, suffix))
+}
+
+/// Find a hash suffix in a range API response (`SUFFIX:COUNT` per line)
+fn parse_range_response(body: &str, suffix: &str) -> u64 {
+    body.lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Estimate strength and, if enabled, check the password against breaches
+///
+/// A breached password always scores 0. If the breach lookup fails the
+/// password is judged on strength alone so an outage doesn't block signups.
+pub async fn check_password(
+    password: &str,
+    user_inputs: &[&str],
+    check_breaches: bool,
+) -> PasswordFeedback {
+    let mut feedback = estimate_strength(password, user_inputs);
+
+    if check_breaches {
+        match breach_count(password).await {
+            Ok(0) => {}
+            Ok(count) => {
+                feedback.score = 0;
+                feedback.breached = true;
+                feedback.warnings.insert(
+                    0,
+                    format!("This password has appeared in {} data breaches", count),
+                );
+                feedback
+                    .suggestions
+                    .push("Choose a password you haven't used elsewhere".to_string());
+            }
+            Err(e) => warn!("Password breach check failed: {}", e),
+        }
+    }
+
+    feedback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_passwords_score_zero() {
+        for password in ["password", "P@ssw0rd", "qwerty123", "123456"] {
+            let feedback = estimate_strength(password, &[]);
+            assert_eq!(feedback.score, 0, "{}", password);
+            assert!(!feedback.warnings.is_empty());
+        }
+        assert!(estimate_strength("naruto2024!", &[]).score <= 1);
+    }
+
+    #[test]
+    fn test_patterns_lower_score() {
+        assert!(estimate_strength("abcdefghij", &[]).score <= 1);
+        assert!(estimate_strength("aaaaaaaaaa", &[]).score <= 1);
+        assert!(estimate_strength("asdfghjkl", &[]).score <= 1);
+        assert!(estimate_strength("hunter22", &[]).score <= 1);
+    }
+
+    #[test]
+    fn test_user_inputs_are_penalised() {
+        let feedback = estimate_strength("johnsmith", &["john.smith@example.com"]);
+        assert!(feedback.score <= 1);
+        assert!(feedback
+            .warnings
+            .iter()
+            .any(|w| w.contains("name or email")));
+    }
+
+    #[test]
+    fn test_strong_passwords_score_high() {
+        assert_eq!(
+            estimate_strength("correct horse battery staple", &[]).score,
+            MAX_SCORE
+        );
+        assert!(estimate_strength("g7#Kq!2vZp9w", &[]).score >= 3);
+    }
+
+    #[test]
+    fn test_parse_range_response() {
+        let body = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+                    1E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493\r\n\
+                    FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF:0";
+        assert_eq!(
+            parse_range_response(body, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"),
+            3861493
+        );
+        assert_eq!(
+            parse_range_response(body, "1e4c9b93f3f0682250b6cf8331b7ee68fd8"),
+            3861493
+        );
+        assert_eq!(parse_range_response(body, "0000"), 0);
+    }
+}
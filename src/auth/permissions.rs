@@ -0,0 +1,255 @@
+//! Role-based permissions
+//!
+//! Operator endpoints are guarded by permission strings such as
+//! `crawler:run`. Roles bundle permissions and are granted to users, so a
+//! moderator can manage comments without getting crawler access. Users with
+//! the admin flag hold every permission.
+//!
+//! Handlers require a permission by taking a [`Permission`] extractor:
+//!
+//! ```ignore
+//! async fn run(auth: Permission<CrawlerRun>) -> impl Responder {
+//!     HttpResponse::Ok().json(format!("user {} may crawl", auth.user_id))
+//! }
+//! ```
+//!
+//! Permissions span every tenant, so only users of the default tenant can
+//! hold them.
+
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::pin::Pin;
+
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use tracing::{error, warn};
+
+use super::{Auth, AuthConfig};
+use crate::db::{user_has_permission, RepositoryResult, DEFAULT_TENANT_ID};
+use crate::models::{ApiError, ErrorCode};
+
+/// A permission a route can require
+pub trait RequiredPermission {
+    /// Permission string stored in roles
+    const NAME: &'static str;
+}
+
+macro_rules! permissions {
+    ($($(#[$doc:meta])* $marker:ident => $name:literal,)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy)]
+            pub struct $marker;
+
+            impl RequiredPermission for $marker {
+                const NAME: &'static str = $name;
+            }
+        )*
+
+        /// Every permission string, for validating role definitions
+        pub const ALL: &[&str] = &[$($name),*];
+    };
+}
+
+permissions! {
+    /// Run crawls and queue crawl jobs
+    CrawlerRun => "crawler:run",
+    /// Purge cached data
+    CachePurge => "cache:purge",
+    /// Look up users and their roles
+    UsersRead => "users:read",
+    /// Create, change, grant, and revoke roles
+    RolesManage => "roles:manage",
+    /// Inspect and retry background jobs
+    JobsManage => "jobs:manage",
+    /// Inspect and resend emails
+    EmailsManage => "emails:manage",
+    /// List and create tenants
+    TenantsManage => "tenants:manage",
+    /// Diff, merge, and check stored anime and parsers, and manage video servers
+    AnimeManage => "anime:manage",
+    /// Read search analytics
+    AnalyticsRead => "analytics:read",
+    /// Run database maintenance and integrity checks, and switch features off
+    MaintenanceRun => "maintenance:run",
+    /// Moderate user comments
+    CommentsModerate => "comments:moderate",
+}
+
+/// Whether `name` is a known permission string
+pub fn is_known_permission(name: &str) -> bool {
+    ALL.contains(&name)
+}
+
+/// Whether `name` is a valid role name (lowercase letters, digits, and dashes)
+pub fn is_valid_role_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 100
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Normalize a role's permission list: trimmed, sorted, and deduplicated
+///
+/// # Returns
+/// * `Ok(Vec<String>)` - The normalized list
+/// * `Err(String)` - Message naming the first unknown permission
+pub fn normalize_permissions(permissions: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized = Vec::with_capacity(permissions.len());
+    for permission in permissions {
+        let permission = permission.trim();
+        if !is_known_permission(permission) {
+            return Err(format!("Unknown permission: {}", permission));
+        }
+        normalized.push(permission.to_string());
+    }
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+/// Whether an authenticated user holds `permission`
+pub async fn has_permission(
+    pool: &PgPool,
+    auth: &Auth,
+    permission: &str,
+) -> RepositoryResult<bool> {
+    if auth.tenant_id != DEFAULT_TENANT_ID {
+        return Ok(false);
+    }
+    user_has_permission(pool, auth.user_id, permission).await
+}
+
+/// Check that an authenticated user holds `permission`
+///
+/// For handlers whose permission depends on the request; fixed permissions
+/// are better required with the [`Permission`] extractor.
+///
+/// # Returns
+/// * `Ok(())` - User holds the permission
+/// * `Err(HttpResponse)` - 403 if not, 500 on database errors
+pub async fn ensure_permission(
+    pool: &PgPool,
+    auth: &Auth,
+    permission: &str,
+) -> Result<(), HttpResponse> {
+    match has_permission(pool, auth, permission).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            warn!(
+                "User {} of tenant {} lacks permission {}",
+                auth.user_id, auth.tenant_id, permission
+            );
+            Err(HttpResponse::Forbidden().json(ApiError::new(
+                ErrorCode::Forbidden,
+                format!("Permission required: {}", permission),
+            )))
+        }
+        Err(e) => {
+            error!("Failed to check permission {}: {}", permission, e);
+            Err(HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to verify permissions",
+            )))
+        }
+    }
+}
+
+/// Authenticated user holding permission `P`
+///
+/// Rejects the request with 401 if it isn't authenticated and with 403 if
+/// the user lacks the permission. Derefs to the underlying [`Auth`].
+#[derive(Debug, Clone)]
+pub struct Permission<P> {
+    /// The authenticated user
+    pub auth: Auth,
+    _permission: PhantomData<P>,
+}
+
+impl<P> Deref for Permission<P> {
+    type Target = Auth;
+
+    fn deref(&self) -> &Auth {
+        &self.auth
+    }
+}
+
+impl<P: RequiredPermission + 'static> FromRequest for Permission<P> {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let auth = Auth::from_request(req, payload);
+        let pool = req
+            .app_data::<web::Data<AuthConfig>>()
+            .and_then(|config| config.pool.clone());
+
+        Box::pin(async move {
+            let auth = auth.await?;
+            let Some(pool) = pool else {
+                let response = HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::InternalError,
+                    "Failed to verify permissions",
+                ));
+                return Err(actix_web::error::InternalError::from_response(
+                    "Permission check needs a database pool",
+                    response,
+                )
+                .into());
+            };
+
+            match ensure_permission(&pool, &auth, P::NAME).await {
+                Ok(()) => Ok(Permission {
+                    auth,
+                    _permission: PhantomData,
+                }),
+                Err(response) => Err(actix_web::error::InternalError::from_response(
+                    format!("Permission required: {}", P::NAME),
+                    response,
+                )
+                .into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_names_are_known() {
+        assert!(is_known_permission(CrawlerRun::NAME));
+        assert!(is_known_permission(CommentsModerate::NAME));
+        assert!(!is_known_permission("crawler:*"));
+        assert!(ALL.iter().all(|name| name.contains(':')));
+    }
+
+    #[test]
+    fn test_normalize_permissions() {
+        let permissions = vec![
+            " users:read".to_string(),
+            "crawler:run".to_string(),
+            "users:read".to_string(),
+        ];
+        assert_eq!(
+            normalize_permissions(&permissions),
+            Ok(vec!["crawler:run".to_string(), "users:read".to_string()])
+        );
+        assert_eq!(
+            normalize_permissions(&["everything".to_string()]),
+            Err("Unknown permission: everything".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_valid_role_name() {
+        assert!(is_valid_role_name("moderator"));
+        assert!(is_valid_role_name("support-2"));
+        assert!(!is_valid_role_name(""));
+        assert!(!is_valid_role_name("Moderator"));
+        assert!(!is_valid_role_name("mod role"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Spam and abuse protection for registration
+//!
+//! Public APIs attract bot signups, so registration checks, in order:
+//! - The email domain against the configured blocklist and a built-in list
+//!   of disposable email providers
+//! - How many accounts the client IP registered within the throttle window
+//! - A CAPTCHA token (hCaptcha or Cloudflare Turnstile), if one is configured
+//!
+//! See [`RegistrationConfig`](crate::config::RegistrationConfig) for the
+//! settings.
+
+use std::net::IpAddr;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::{CaptchaConfig, RegistrationConfig};
+
+/// Timeout for CAPTCHA verification requests
+const CAPTCHA_TIMEOUT_SECS: u64 = 5;
+
+/// Domains of well-known disposable email providers
+const DISPOSABLE_DOMAINS: &[&str] = &[
+    "10minutemail.com",
+    "20minutemail.com",
+    "burnermail.io",
+    "discard.email",
+    "dispostable.com",
+    "emailondeck.com",
+    "fakeinbox.com",
+    "getairmail.com",
+    "getnada.com",
+    "grr.la",
+    "guerrillamail.biz",
+    "guerrillamail.com",
+    "guerrillamail.de",
+    "guerrillamail.info",
+    "guerrillamail.net",
+    "guerrillamail.org",
+    "guerrillamailblock.com",
+    "inboxkitten.com",
+    "mail-temp.com",
+    "maildrop.cc",
+    "mailcatch.com",
+    "mailinator.com",
+    "mailinator.net",
+    "mailnesia.com",
+    "mintemail.com",
+    "moakt.com",
+    "mohmal.com",
+    "mytemp.email",
+    "nada.email",
+    "pokemail.net",
+    "sharklasers.com",
+    "spam4.me",
+    "spamgourmet.com",
+    "temp-mail.io",
+    "temp-mail.org",
+    "tempail.com",
+    "tempmail.com",
+    "tempmail.net",
+    "tempmailo.com",
+    "tempr.email",
+    "throwawaymail.com",
+    "trashmail.com",
+    "trashmail.de",
+    "yopmail.com",
+    "yopmail.fr",
+    "yopmail.net",
+];
+
+/// Why an email address can't register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailRejection {
+    /// The domain is on the configured blocklist
+    Blocked,
+    /// The domain belongs to a disposable email provider
+    Disposable,
+}
+
+impl EmailRejection {
+    /// Message shown to the client
+    pub fn message(&self) -> &'static str {
+        match self {
+            EmailRejection::Blocked => "Registrations from this email domain are not allowed",
+            EmailRejection::Disposable => "Disposable email addresses are not allowed",
+        }
+    }
+}
+
+/// Lowercased domain of an email address
+pub fn email_domain(email: &str) -> Option<String> {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.trim().trim_end_matches('.').to_lowercase())
+        .filter(|domain| !domain.is_empty())
+}
+
+/// Whether `domain` is `listed` or one of its subdomains
+fn domain_matches(domain: &str, listed: &str) -> bool {
+    domain == listed
+        || domain
+            .strip_suffix(listed)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Whether `domain` belongs to a known disposable email provider
+pub fn is_disposable_domain(domain: &str) -> bool {
+    DISPOSABLE_DOMAINS
+        .iter()
+        .any(|listed| domain_matches(domain, listed))
+}
+
+/// Check an email address's domain against the blocklist and disposable providers
+pub fn check_email_domain(config: &RegistrationConfig, email: &str) -> Result<(), EmailRejection> {
+    let Some(domain) = email_domain(email) else {
+        return Ok(());
+    };
+
+    if config
+        .blocked_domains
+        .iter()
+        .any(|listed| domain_matches(&domain, listed))
+    {
+        return Err(EmailRejection::Blocked);
+    }
+    if config.block_disposable && is_disposable_domain(&domain) {
+        return Err(EmailRejection::Disposable);
+    }
+    Ok(())
+}
+
+/// Response of a siteverify endpoint; hCaptcha and Turnstile share the shape
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+/// Validate a CAPTCHA token with the configured provider
+///
+/// # Arguments
+/// * `config` - Provider and secret key
+/// * `token` - Token the client got from the CAPTCHA widget
+/// * `remote_ip` - Client IP, passed along as an extra signal
+///
+/// # Returns
+/// * `Ok(true)` - The token is valid
+/// * `Ok(false)` - The provider rejected the token
+/// * `Err(reqwest::Error)` - The provider could not be reached
+pub async fn verify_captcha(
+    config: &CaptchaConfig,
+    token: &str,
+    remote_ip: Option<IpAddr>,
+) -> Result<bool, reqwest::Error> {
+    let remote_ip = remote_ip.map(|ip| ip.to_string());
+    let mut form = vec![("secret", config.secret.as_str()), ("response", token)];
+    if let Some(ip) = &remote_ip {
+        form.push(("remoteip", ip));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(CAPTCHA_TIMEOUT_SECS))
+        .build()?;
+    let response: SiteVerifyResponse = client
+        .post(config.provider.verify_url())
+        .form(&form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(response.success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_email_domain() {
+        let config = RegistrationConfig {
+            blocked_domains: vec!["spam.example".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(check_email_domain(&config, "fan@gmail.com"), Ok(()));
+        assert_eq!(
+            check_email_domain(&config, "bot@spam.example"),
+            Err(EmailRejection::Blocked)
+        );
+        assert_eq!(
+            check_email_domain(&config, "bot@mx.Spam.Example"),
+            Err(EmailRejection::Blocked)
+        );
+        assert_eq!(check_email_domain(&config, "fan@notspam.example"), Ok(()));
+        assert_eq!(
+            check_email_domain(&config, "bot@mailinator.com"),
+            Err(EmailRejection::Disposable)
+        );
+
+        let config = RegistrationConfig {
+            block_disposable: false,
+            ..Default::default()
+        };
+        assert_eq!(check_email_domain(&config, "bot@mailinator.com"), Ok(()));
+    }
+}
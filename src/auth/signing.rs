@@ -0,0 +1,361 @@
+//! Signed, expiring URLs
+//!
+//! Links that must work without a bearer token (the image proxy, downloads)
+//! carry an HMAC-SHA256 signature over the path, query parameters, and expiry
+//! time, so they can be handed to browsers and CDNs without exposing an open
+//! endpoint. Several keys can be configured for rotation: new URLs are signed
+//! with the first key, and URLs signed with any configured key are accepted
+//! until they expire.
+
+use std::collections::{BTreeMap, HashMap};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Query parameter holding the expiry (unix seconds)
+pub const PARAM_EXPIRES: &str = "expires";
+
+/// Query parameter holding the signing key ID
+pub const PARAM_KEY_ID: &str = "kid";
+
+/// Query parameter holding the signature
+pub const PARAM_SIGNATURE: &str = "sig";
+
+/// Key ID used for the key derived from the JWT secret
+pub const DEFAULT_KEY_ID: &str = "default";
+
+/// Signature verification errors
+#[derive(Debug, Error, PartialEq)]
+pub enum SignatureError {
+    #[error("URL is not signed")]
+    Missing,
+
+    #[error("Signed URL has expired")]
+    Expired,
+
+    #[error("Unknown signing key: {0}")]
+    UnknownKey(String),
+
+    #[error("Invalid signature")]
+    Invalid,
+}
+
+/// A URL signing key
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    /// Key ID embedded in signed URLs
+    pub id: String,
+    /// HMAC secret
+    pub secret: Vec<u8>,
+}
+
+impl SigningKey {
+    pub fn new(id: impl Into<String>, secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            id: id.into(),
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    /// Derive a signing key from the JWT secret
+    ///
+    /// Used when no URL signing keys are configured, so signed URLs work out
+    /// of the box without reusing the JWT secret directly.
+    pub fn derive_from(jwt_secret: &str) -> Self {
+        let mut mac = HmacSha256::new_from_slice(jwt_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"url-signing");
+        Self::new(DEFAULT_KEY_ID, mac.finalize().into_bytes())
+    }
+}
+
+/// Parse signing keys from `id:secret` pairs separated by commas
+///
+/// The first key is used for signing. Entries without a separator or with an
+/// empty ID or secret are ignored.
+pub fn parse_signing_keys(value: &str) -> Vec<SigningKey> {
+    value
+        .split(',')
+        .filter_map(|entry| entry.trim().split_once(':'))
+        .filter(|(id, secret)| !id.trim().is_empty() && !secret.trim().is_empty())
+        .map(|(id, secret)| SigningKey::new(id.trim(), secret.trim()))
+        .collect()
+}
+
+/// Signs and verifies expiring URLs
+#[derive(Debug, Clone)]
+pub struct UrlSigner {
+    keys: Vec<SigningKey>,
+    /// Prepended to signed URLs but not signed (the API's base path)
+    prefix: String,
+}
+
+impl UrlSigner {
+    /// Create a signer
+    ///
+    /// # Panics
+    /// Panics if `keys` is empty
+    pub fn new(keys: Vec<SigningKey>) -> Self {
+        assert!(!keys.is_empty(), "UrlSigner requires at least one key");
+        Self {
+            keys,
+            prefix: String::new(),
+        }
+    }
+
+    /// Prepend `prefix` to the URLs this signer hands out
+    ///
+    /// Signatures cover the path below the prefix, which is what the routes
+    /// verify, so moving the API to another base path doesn't change them.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Sign a URL that expires `ttl_secs` from now
+    ///
+    /// # Arguments
+    /// * `path` - Request path, e.g. "/api/images/proxy"
+    /// * `params` - Query parameters covered by the signature
+    /// * `ttl_secs` - Lifetime of the URL in seconds
+    ///
+    /// # Returns
+    /// The prefix and path with the parameters, expiry, key ID, and
+    /// signature in the query
+    pub fn sign(&self, path: &str, params: &[(&str, &str)], ttl_secs: i64) -> String {
+        self.sign_at(path, params, Utc::now().timestamp() + ttl_secs)
+    }
+
+    /// Sign a URL that expires at `expires` (unix seconds)
+    pub fn sign_at(&self, path: &str, params: &[(&str, &str)], expires: i64) -> String {
+        let key = &self.keys[0];
+        let expires = expires.to_string();
+
+        let mut signed: BTreeMap<&str, &str> = params.iter().copied().collect();
+        signed.insert(PARAM_EXPIRES, &expires);
+        signed.insert(PARAM_KEY_ID, &key.id);
+
+        let signature = URL_SAFE_NO_PAD.encode(mac_for(key, path, &signed).finalize().into_bytes());
+        format!(
+            "{}{}?{}&{}={}",
+            self.prefix,
+            path,
+            encode_query(&signed),
+            PARAM_SIGNATURE,
+            signature
+        )
+    }
+
+    /// Verify the signature of a request
+    ///
+    /// # Arguments
+    /// * `path` - Request path
+    /// * `query` - All query parameters of the request, including the signature
+    pub fn verify(
+        &self,
+        path: &str,
+        query: &HashMap<String, String>,
+    ) -> Result<(), SignatureError> {
+        self.verify_at(path, query, Utc::now().timestamp())
+    }
+
+    /// Verify the signature of a request as of `now` (unix seconds)
+    pub fn verify_at(
+        &self,
+        path: &str,
+        query: &HashMap<String, String>,
+        now: i64,
+    ) -> Result<(), SignatureError> {
+        let (Some(signature), Some(expires), Some(key_id)) = (
+            query.get(PARAM_SIGNATURE),
+            query.get(PARAM_EXPIRES),
+            query.get(PARAM_KEY_ID),
+        ) else {
+            return Err(SignatureError::Missing);
+        };
+
+        let key = self
+            .keys
+            .iter()
+            .find(|key| &key.id == key_id)
+            .ok_or_else(|| SignatureError::UnknownKey(key_id.clone()))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| SignatureError::Invalid)?;
+
+        let signed: BTreeMap<&str, &str> = query
+            .iter()
+            .filter(|(name, _)| name.as_str() != PARAM_SIGNATURE)
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        mac_for(key, path, &signed)
+            .verify_slice(&signature)
+            .map_err(|_| SignatureError::Invalid)?;
+
+        // Checked after the signature so a forged expiry can't be probed
+        let expires: i64 = expires.parse().map_err(|_| SignatureError::Invalid)?;
+        if expires < now {
+            return Err(SignatureError::Expired);
+        }
+
+        Ok(())
+    }
+}
+
+/// Seconds until a verified URL expires, for cache headers
+pub fn remaining_secs(query: &HashMap<String, String>, now: i64) -> i64 {
+    query
+        .get(PARAM_EXPIRES)
+        .and_then(|expires| expires.parse::<i64>().ok())
+        .map(|expires| (expires - now).max(0))
+        .unwrap_or(0)
+}
+
+/// MAC over the path and the sorted, encoded query
+fn mac_for(key: &SigningKey, path: &str, params: &BTreeMap<&str, &str>) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(&key.secret).expect("HMAC accepts keys of any length");
+    mac.update(path.as_bytes());
+    mac.update(b"\n");
+    mac.update(encode_query(params).as_bytes());
+    mac
+}
+
+fn encode_query(params: &BTreeMap<&str, &str>) -> String {
+    params
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                urlencoding::encode(name),
+                urlencoding::encode(value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the query of a signed URL the way actix's `web::Query` would
+    fn query_of(url: &str) -> HashMap<String, String> {
+        let (_, query) = url.split_once('?').unwrap();
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| {
+                (
+                    urlencoding::decode(k).unwrap().into_owned(),
+                    urlencoding::decode(v).unwrap().into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    fn signer() -> UrlSigner {
+        UrlSigner::new(vec![SigningKey::new("k1", "secret-one")])
+    }
+
+    #[test]
+    fn test_sign_and_verify_roundtrip() {
+        let url = signer().sign_at(
+            "/api/images/proxy",
+            &[("url", "https://example.com/a b.jpg")],
+            1_000,
+        );
+        assert!(url.starts_with("/api/images/proxy?"));
+
+        let query = query_of(&url);
+        assert_eq!(query["url"], "https://example.com/a b.jpg");
+        assert_eq!(signer().verify_at("/api/images/proxy", &query, 999), Ok(()));
+        assert_eq!(remaining_secs(&query, 900), 100);
+    }
+
+    #[test]
+    fn test_sign_with_prefix() {
+        let signed = signer().with_prefix("/anime-api").sign_at(
+            "/api/images/proxy",
+            &[("key", "avatars/1.png")],
+            1_000,
+        );
+        assert!(signed.starts_with("/anime-api/api/images/proxy?"));
+
+        // Routes verify the path below the prefix
+        let unprefixed = signer().sign_at("/api/images/proxy", &[("key", "avatars/1.png")], 1_000);
+        assert_eq!(signed.strip_prefix("/anime-api"), Some(unprefixed.as_str()));
+        assert_eq!(
+            signer().verify_at("/api/images/proxy", &query_of(&signed), 0),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_tampering_and_expiry() {
+        let url = signer().sign_at("/api/images/proxy", &[("url", "https://a/x.jpg")], 1_000);
+        let query = query_of(&url);
+
+        assert_eq!(
+            signer().verify_at("/api/images/proxy", &query, 1_001),
+            Err(SignatureError::Expired)
+        );
+        assert_eq!(
+            signer().verify_at("/api/other", &query, 0),
+            Err(SignatureError::Invalid)
+        );
+
+        let mut tampered = query.clone();
+        tampered.insert("url".to_string(), "https://evil/x.jpg".to_string());
+        assert_eq!(
+            signer().verify_at("/api/images/proxy", &tampered, 0),
+            Err(SignatureError::Invalid)
+        );
+
+        let mut extended = query.clone();
+        extended.insert(PARAM_EXPIRES.to_string(), "9999999999".to_string());
+        assert_eq!(
+            signer().verify_at("/api/images/proxy", &extended, 0),
+            Err(SignatureError::Invalid)
+        );
+
+        assert_eq!(
+            signer().verify_at("/api/images/proxy", &HashMap::new(), 0),
+            Err(SignatureError::Missing)
+        );
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = UrlSigner::new(vec![SigningKey::new("k1", "secret-one")]);
+        let rotated = UrlSigner::new(vec![
+            SigningKey::new("k2", "secret-two"),
+            SigningKey::new("k1", "secret-one"),
+        ]);
+        let retired = UrlSigner::new(vec![SigningKey::new("k2", "secret-two")]);
+
+        let query = query_of(&old.sign_at("/p", &[], 1_000));
+        assert_eq!(rotated.verify_at("/p", &query, 0), Ok(()));
+        assert_eq!(
+            retired.verify_at("/p", &query, 0),
+            Err(SignatureError::UnknownKey("k1".to_string()))
+        );
+
+        let query = query_of(&rotated.sign_at("/p", &[], 1_000));
+        assert_eq!(query[PARAM_KEY_ID], "k2");
+        assert_eq!(retired.verify_at("/p", &query, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_signing_keys() {
+        let keys = parse_signing_keys("new:abc, old:def,broken,:x,y:");
+        let ids: Vec<&str> = keys.iter().map(|k| k.id.as_str()).collect();
+        assert_eq!(ids, vec!["new", "old"]);
+        assert_eq!(keys[1].secret, b"def");
+    }
+}
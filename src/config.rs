@@ -2,19 +2,122 @@
 //!
 //! Handles loading environment variables and application configuration.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::env;
 
+use actix_web::cookie::SameSite;
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use thiserror::Error;
+
+use crate::auth::password::{DEFAULT_MIN_SCORE, MAX_SCORE};
+use crate::auth::signing::{parse_signing_keys, SigningKey, UrlSigner};
+use crate::auth::JWT_EXPIRY_DAYS;
+use crate::db::encryption::{parse_encryption_keys, EncryptionKey, FieldCipher};
+use crate::scraper::MAX_REDIRECTS;
+
+thread_local! {
+    /// Settings read from the .env file while [`Config::from_env_file`]
+    /// runs, taking precedence over the environment
+    static FILE_VALUES: RefCell<Option<HashMap<String, String>>> = const { RefCell::new(None) };
+}
+
+/// A setting that is missing or invalid
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{0}")]
+pub struct ConfigError(pub String);
+
+/// Read a setting that must be set
+fn required(name: &str) -> Result<String, ConfigError> {
+    env_var(name).map_err(|_| ConfigError(format!("{} must be set", name)))
+}
+
+/// Read a setting: from the .env file during [`Config::from_env_file`],
+/// otherwise from the environment
+fn env_var(name: &str) -> Result<String, env::VarError> {
+    let from_file = FILE_VALUES.with(|values| {
+        values
+            .borrow()
+            .as_ref()
+            .and_then(|values| values.get(name).cloned())
+    });
+    match from_file {
+        Some(value) => Ok(value),
+        None => env::var(name),
+    }
+}
+
+/// Deployment profile, picked with APP_ENV
+///
+/// The profile only changes defaults; every setting it affects can still be
+/// set explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEnv {
+    /// Local development over plain HTTP
+    Development,
+    /// Pre-production deployment
+    Staging,
+    /// Production deployment
+    Production,
+}
+
+impl AppEnv {
+    /// Parse APP_ENV ("dev", "development", "staging", "stage", "prod", or
+    /// "production"), ignoring case
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "dev" | "development" => Some(AppEnv::Development),
+            "staging" | "stage" => Some(AppEnv::Staging),
+            "prod" | "production" => Some(AppEnv::Production),
+            _ => None,
+        }
+    }
+
+    /// Name of the profile as logged
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AppEnv::Development => "development",
+            AppEnv::Staging => "staging",
+            AppEnv::Production => "production",
+        }
+    }
+}
+
 /// Application configuration loaded from environment variables
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// Deployment profile the defaults below were picked for
+    pub app_env: AppEnv,
+    /// Attributes of the auth cookie
+    pub cookies: CookieConfig,
+    /// Whether 5xx error bodies keep their internal error messages
+    pub verbose_errors: bool,
     /// Database connection URL
     pub database_url: String,
+    /// Read replica connection URL; heavy reads go to the primary when unset
+    pub database_replica_url: Option<String>,
+    /// Connection pool sizes and timeouts
+    pub database_pool: DatabasePoolConfig,
     /// Server host address
     pub host: String,
     /// Server port
     pub port: u16,
+    /// Path prefix every route is served under (e.g. "/anime-api"), for
+    /// path-based reverse proxies that forward the prefix; "" for the root.
+    /// Set with [`Config::with_base_path`], which keeps the cookie path in
+    /// step.
+    pub base_path: String,
+    /// HTTPS served by the server itself; plain HTTP when `None`
+    pub tls: Option<TlsConfig>,
+    /// Where the server listens; HOST and PORT unless BIND says otherwise
+    pub bind: BindTarget,
+    /// Permissions of the socket file when binding a Unix socket
+    pub unix_socket_mode: u32,
     /// JWT secret key for token signing
     pub jwt_secret: String,
+    /// Asymmetric JWT signing keys and keys kept during a rotation
+    pub jwt: JwtConfig,
     /// Google OAuth client ID
     pub google_client_id: Option<String>,
     /// Base URL for anime scraper source
@@ -23,6 +126,1183 @@ pub struct Config {
     pub smtp: Option<SmtpConfig>,
     /// Frontend URL for email links
     pub frontend_url: String,
+    /// Directory with email template overrides
+    pub email_templates_dir: Option<String>,
+    /// Number of background job workers
+    pub job_workers: usize,
+    /// Idle poll interval for background job workers (milliseconds)
+    pub job_poll_interval_ms: u64,
+    /// Minimum password strength score (0-4) for new passwords
+    pub password_min_score: u8,
+    /// Check new passwords against HaveIBeenPwned
+    pub password_breach_check: bool,
+    /// Keys for signed URLs; the first one signs, all of them verify
+    pub url_signing_keys: Vec<SigningKey>,
+    /// Lifetime of signed URLs (seconds)
+    pub signed_url_ttl_secs: i64,
+    /// Keys encrypting user emails and Google IDs; the first one encrypts,
+    /// all of them decrypt. Empty leaves the columns in plaintext
+    pub data_encryption_keys: Vec<EncryptionKey>,
+    /// Secret for the blind indexes used to look up encrypted columns
+    pub data_index_key: String,
+    /// Hosts the image proxy will sign URLs for
+    pub image_proxy_hosts: Vec<String>,
+    /// Whether list endpoints prefetch their thumbnails into the image
+    /// cache unless the request says `prefetchImages=false`
+    pub image_prefetch: bool,
+    /// Cache-Control lifetimes per endpoint class
+    pub cache_control: CacheControlConfig,
+    /// Request header naming the tenant slug
+    pub tenant_header: String,
+    /// Object storage for cached images, archived pages, and exports
+    pub storage: StorageConfig,
+    /// Directory with parser fixture pages and their golden output
+    pub parser_fixtures_dir: String,
+    /// JSON file overriding parser selectors (see
+    /// [`crate::parser::selectors`]); the built-in selectors when unset
+    pub selector_profile: Option<String>,
+    /// Address of the internal gRPC server (feature `grpc`); off when unset
+    pub grpc_addr: Option<String>,
+    /// How long each endpoint waits for the source site
+    pub upstream_timeouts: UpstreamTimeouts,
+    /// Most redirects the scraper follows for one page
+    pub scraper_max_redirects: usize,
+    /// Fail scrapes that redirect to a host other than the requested one
+    pub scraper_same_host_redirects: bool,
+    /// Bytes crawls may download per day (UTC); 0 means no limit
+    pub crawler_daily_bandwidth_bytes: u64,
+    /// When crawls back off to leave room for API traffic
+    pub crawler_backpressure: CrawlerBackpressureConfig,
+    /// Lifetime of cached search results (seconds); 0 disables the cache
+    pub search_cache_ttl_secs: u64,
+    /// Lifetime of cached empty search results (seconds)
+    pub search_cache_empty_ttl_secs: u64,
+    /// Lifetime of computed community leaderboards (seconds); 0 recomputes every request
+    pub community_top_ttl_secs: u64,
+    /// How often saved searches are checked for new matches (seconds); 0 disables it
+    pub saved_search_interval_secs: u64,
+    /// Re-scraping of subscribed and favorited anime ahead of the full crawl
+    pub priority_crawl: PriorityCrawlConfig,
+    /// How often anime popularity scores are recomputed (seconds); 0 disables it
+    pub popularity_interval_secs: u64,
+    /// How often stored episodes are checked for gaps and backfilled (seconds); 0 disables it
+    pub episode_gap_interval_secs: u64,
+    /// How often the completed anime archive is crawled (seconds); 0 disables it
+    pub completed_archive_interval_secs: u64,
+    /// How often the status of ongoing anime is re-checked (seconds); 0 disables it
+    pub status_reconcile_interval_secs: u64,
+    /// How often unused anonymous device accounts are deleted (seconds); 0 disables it
+    pub anonymous_cleanup_interval_secs: u64,
+    /// Days an unused anonymous device account is kept after its device was last seen
+    pub anonymous_max_idle_days: u32,
+    /// How often old change feed events are deleted (seconds); 0 disables it
+    pub change_events_cleanup_interval_secs: u64,
+    /// Days change feed events are kept; events a consumer hasn't read yet
+    /// are kept regardless
+    pub change_events_retention_days: u32,
+    /// How often feature flags are reloaded, picking up changes made through
+    /// other instances (seconds); 0 disables it
+    pub feature_flag_refresh_secs: u64,
+    /// Dispatching of notification and webhook events
+    pub outbox: OutboxConfig,
+    /// Spam and abuse protection for registration
+    pub registration: RegistrationConfig,
+    /// Client IP allow/deny lists and trusted reverse proxies
+    pub ip_filter: IpFilterConfig,
+    /// Request body and query string size limits
+    pub request_limits: RequestLimitsConfig,
+    /// Synopsis translation provider; synopses are served untranslated when unset
+    pub translation: Option<TranslationConfig>,
+    /// Video servers whose episode sources are dropped, lowercased; admins
+    /// can override this per server at runtime
+    pub video_server_blacklist: Vec<String>,
+    /// Video servers listed first in episode sources, lowercased, highest
+    /// priority first; admins can override this per server at runtime
+    pub video_server_priority: Vec<String>,
+    /// Frontend origin sitemap links point at, without a trailing slash
+    pub sitemap_base_url: String,
+    /// Log levels, format, and outputs
+    pub logging: LogConfig,
+}
+
+/// Object storage configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageConfig {
+    /// Where objects are stored
+    pub backend: StorageBackend,
+    /// Keep a copy of proxied images in storage
+    pub image_cache: bool,
+    /// Archive the raw HTML of every scraped page
+    pub archive_pages: bool,
+    /// Days cached images are kept
+    pub image_retention_days: u64,
+    /// Days archived pages are kept
+    pub page_retention_days: u64,
+    /// Days export files are kept
+    pub export_retention_days: u64,
+    /// Interval between cleanup runs (seconds)
+    pub cleanup_interval_secs: u64,
+}
+
+/// Object storage backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageBackend {
+    /// Directory on local disk
+    Local {
+        /// Root directory of the objects
+        dir: String,
+    },
+    /// S3-compatible bucket
+    S3(S3Config),
+}
+
+/// S3-compatible bucket configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Config {
+    /// Service endpoint, e.g. https://s3.us-east-1.amazonaws.com or http://minio:9000
+    pub endpoint: String,
+    /// Bucket name
+    pub bucket: String,
+    /// Region used for request signing
+    pub region: String,
+    /// Access key ID
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+    /// Address the bucket as endpoint/bucket instead of bucket.endpoint
+    pub path_style: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Local {
+                dir: "./storage".to_string(),
+            },
+            image_cache: true,
+            archive_pages: false,
+            image_retention_days: 30,
+            page_retention_days: 14,
+            export_retention_days: 7,
+            cleanup_interval_secs: 3600,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Load from STORAGE_* and S3_* environment variables
+    ///
+    /// # Errors
+    /// If STORAGE_BACKEND is "s3" and the bucket or credentials are not set
+    fn from_env() -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let flag = |name: &str, default: bool| {
+            env_var(name)
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(default)
+        };
+        let number = |name: &str, default: u64| {
+            env_var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let backend = match env_var("STORAGE_BACKEND")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "s3" => {
+                let region = env_var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+                // A custom endpoint is usually MinIO or similar, which wants path-style
+                let endpoint = env_var("S3_ENDPOINT").ok();
+                let path_style = flag("S3_PATH_STYLE", endpoint.is_some());
+                StorageBackend::S3(S3Config {
+                    endpoint: endpoint
+                        .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region)),
+                    bucket: required("S3_BUCKET")?,
+                    region,
+                    access_key_id: required("S3_ACCESS_KEY_ID")?,
+                    secret_access_key: required("S3_SECRET_ACCESS_KEY")?,
+                    path_style,
+                })
+            }
+            _ => StorageBackend::Local {
+                dir: env_var("STORAGE_LOCAL_DIR").unwrap_or_else(|_| "./storage".to_string()),
+            },
+        };
+
+        Ok(Self {
+            backend,
+            image_cache: flag("STORAGE_IMAGE_CACHE", defaults.image_cache),
+            archive_pages: flag("STORAGE_ARCHIVE_PAGES", defaults.archive_pages),
+            image_retention_days: number(
+                "STORAGE_IMAGE_RETENTION_DAYS",
+                defaults.image_retention_days,
+            ),
+            page_retention_days: number(
+                "STORAGE_PAGE_RETENTION_DAYS",
+                defaults.page_retention_days,
+            ),
+            export_retention_days: number(
+                "STORAGE_EXPORT_RETENTION_DAYS",
+                defaults.export_retention_days,
+            ),
+            cleanup_interval_secs: number(
+                "STORAGE_CLEANUP_INTERVAL_SECS",
+                defaults.cleanup_interval_secs,
+            )
+            .max(60),
+        })
+    }
+}
+
+/// Cache lifetimes sent in Cache-Control headers
+///
+/// `max_age` applies to browsers, `s_maxage` to shared caches (CDNs).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheControlConfig {
+    /// Whether the cache-control layer sets headers at all
+    pub enabled: bool,
+    /// Browser lifetime of list responses (updates, search, ...) in seconds
+    pub list_max_age: u32,
+    /// CDN lifetime of list responses in seconds
+    pub list_s_maxage: u32,
+    /// Browser lifetime of anime and episode details in seconds
+    pub detail_max_age: u32,
+    /// CDN lifetime of anime and episode details in seconds
+    pub detail_s_maxage: u32,
+}
+
+impl Default for CacheControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            list_max_age: 60,
+            list_s_maxage: 300,
+            detail_max_age: 600,
+            detail_s_maxage: 3600,
+        }
+    }
+}
+
+impl CacheControlConfig {
+    /// Load from CACHE_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |name: &str, default: u32| {
+            env_var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            enabled: env_var("CACHE_CONTROL_ENABLED")
+                .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no"))
+                .unwrap_or(defaults.enabled),
+            list_max_age: secs("CACHE_LIST_MAX_AGE", defaults.list_max_age),
+            list_s_maxage: secs("CACHE_LIST_S_MAXAGE", defaults.list_s_maxage),
+            detail_max_age: secs("CACHE_DETAIL_MAX_AGE", defaults.detail_max_age),
+            detail_s_maxage: secs("CACHE_DETAIL_S_MAXAGE", defaults.detail_s_maxage),
+        }
+    }
+}
+
+/// Upstream latency budget per endpoint, in milliseconds
+///
+/// A scrape that takes longer is abandoned. Endpoints with stored data
+/// (updates, completed, anime detail) then serve that data marked as timed
+/// out; the others fail with 504.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamTimeouts {
+    /// GET /api/updates
+    pub updates_ms: u64,
+    /// GET /api/completed
+    pub completed_ms: u64,
+    /// GET /api/popular
+    pub popular_ms: u64,
+    /// GET /api/search
+    pub search_ms: u64,
+    /// GET /api/anime/list
+    pub anime_list_ms: u64,
+    /// GET /api/anime/{slug}
+    pub anime_detail_ms: u64,
+    /// GET /api/episode/{slug}
+    pub episode_ms: u64,
+}
+
+impl Default for UpstreamTimeouts {
+    fn default() -> Self {
+        Self {
+            updates_ms: 3_000,
+            completed_ms: 3_000,
+            popular_ms: 3_000,
+            search_ms: 10_000,
+            anime_list_ms: 10_000,
+            anime_detail_ms: 5_000,
+            episode_ms: 10_000,
+        }
+    }
+}
+
+impl UpstreamTimeouts {
+    /// Load from UPSTREAM_TIMEOUT_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let millis = |name: &str, default: u64| {
+            env_var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&ms| ms > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            updates_ms: millis("UPSTREAM_TIMEOUT_UPDATES_MS", defaults.updates_ms),
+            completed_ms: millis("UPSTREAM_TIMEOUT_COMPLETED_MS", defaults.completed_ms),
+            popular_ms: millis("UPSTREAM_TIMEOUT_POPULAR_MS", defaults.popular_ms),
+            search_ms: millis("UPSTREAM_TIMEOUT_SEARCH_MS", defaults.search_ms),
+            anime_list_ms: millis("UPSTREAM_TIMEOUT_ANIME_LIST_MS", defaults.anime_list_ms),
+            anime_detail_ms: millis("UPSTREAM_TIMEOUT_ANIME_DETAIL_MS", defaults.anime_detail_ms),
+            episode_ms: millis("UPSTREAM_TIMEOUT_EPISODE_MS", defaults.episode_ms),
+        }
+    }
+}
+
+/// Spam and abuse protection for registration
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistrationConfig {
+    /// Email domains that can't register; subdomains are blocked too
+    pub blocked_domains: Vec<String>,
+    /// Whether addresses at known disposable email providers are rejected
+    pub block_disposable: bool,
+    /// Registrations allowed per client IP within the window (0 disables the limit)
+    pub max_per_ip: u32,
+    /// Length of the per-IP registration window in seconds
+    pub ip_window_secs: u64,
+    /// CAPTCHA every registration must pass, if configured
+    pub captcha: Option<CaptchaConfig>,
+}
+
+impl Default for RegistrationConfig {
+    fn default() -> Self {
+        Self {
+            blocked_domains: Vec::new(),
+            block_disposable: true,
+            max_per_ip: 5,
+            ip_window_secs: 3600,
+            captcha: None,
+        }
+    }
+}
+
+impl RegistrationConfig {
+    /// Defaults for a profile: development allows many registrations per IP
+    /// so testing sign-ups doesn't hit the limit
+    fn defaults_for(app_env: AppEnv) -> Self {
+        match app_env {
+            AppEnv::Development => Self {
+                max_per_ip: 1000,
+                ..Self::default()
+            },
+            AppEnv::Staging | AppEnv::Production => Self::default(),
+        }
+    }
+
+    /// Load from REGISTRATION_* and CAPTCHA_* environment variables
+    fn from_env(app_env: AppEnv) -> Self {
+        let defaults = Self::defaults_for(app_env);
+
+        // A CAPTCHA is required only when both the provider and secret are set
+        let captcha = match (
+            env_var("CAPTCHA_PROVIDER")
+                .ok()
+                .as_deref()
+                .and_then(CaptchaProvider::parse),
+            env_var("CAPTCHA_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+        ) {
+            (Some(provider), Some(secret)) => Some(CaptchaConfig { provider, secret }),
+            _ => None,
+        };
+
+        Self {
+            blocked_domains: env_var("REGISTRATION_BLOCKED_DOMAINS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|domain| domain.trim().trim_start_matches('@').to_lowercase())
+                        .filter(|domain| !domain.is_empty())
+                        .collect()
+                })
+                .unwrap_or(defaults.blocked_domains),
+            block_disposable: env_var("REGISTRATION_BLOCK_DISPOSABLE")
+                .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no"))
+                .unwrap_or(defaults.block_disposable),
+            max_per_ip: env_var("REGISTRATION_MAX_PER_IP")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_per_ip),
+            ip_window_secs: env_var("REGISTRATION_IP_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.ip_window_secs),
+            captcha,
+        }
+    }
+}
+
+/// JWT key settings
+///
+/// Tokens are signed with HS256 and JWT_SECRET unless a private key file is
+/// set; its algorithm (RS256 or EdDSA) follows from the key. After a
+/// rotation the previous secret or public key keeps verifying tokens, until
+/// `previous_until` when set. When an issuer or audience is set, tokens
+/// carry it and tokens without it are rejected. See [`crate::auth::keys`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JwtConfig {
+    /// PEM file of an RSA or Ed25519 private key to sign tokens with
+    pub private_key_file: Option<String>,
+    /// Key ID put in token headers; derived from the key when unset
+    pub key_id: Option<String>,
+    /// HMAC secret tokens were signed with before the rotation
+    pub previous_secret: Option<String>,
+    /// PEM file of the public key tokens were signed with before the rotation
+    pub previous_public_key_file: Option<String>,
+    /// When tokens signed with the previous key stop being accepted
+    pub previous_until: Option<DateTime<Utc>>,
+    /// `iss` claim of issued tokens, required on verified ones
+    pub issuer: Option<String>,
+    /// `aud` claim of issued tokens, required on verified ones
+    pub audience: Option<String>,
+}
+
+impl JwtConfig {
+    /// Load from JWT_* environment variables
+    fn from_env() -> Result<Self, ConfigError> {
+        let var = |name: &str| env_var(name).ok().filter(|v| !v.trim().is_empty());
+        Ok(Self {
+            private_key_file: var("JWT_PRIVATE_KEY_FILE"),
+            key_id: var("JWT_KEY_ID"),
+            previous_secret: var("JWT_PREVIOUS_SECRET"),
+            previous_public_key_file: var("JWT_PREVIOUS_PUBLIC_KEY_FILE"),
+            previous_until: var("JWT_PREVIOUS_KEY_UNTIL")
+                .map(|v| {
+                    DateTime::parse_from_rfc3339(v.trim())
+                        .map(|until| until.with_timezone(&Utc))
+                        .map_err(|_| {
+                            ConfigError("JWT_PREVIOUS_KEY_UNTIL must be an RFC3339 time".into())
+                        })
+                })
+                .transpose()?,
+            issuer: var("JWT_ISSUER"),
+            audience: var("JWT_AUDIENCE"),
+        })
+    }
+}
+
+/// HTTPS settings, for serving TLS without a reverse proxy
+///
+/// On when both TLS_CERT_FILE and TLS_KEY_FILE are set. The Secure cookie
+/// flag then defaults on in every profile. See [`crate::tls`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// PEM file with the certificate chain, leaf certificate first
+    pub cert_file: String,
+    /// PEM file with the private key (PKCS#8, PKCS#1, or SEC1)
+    pub key_file: String,
+    /// Plain HTTP port redirecting to HTTPS; no HTTP listener when unset
+    pub redirect_port: Option<u16>,
+}
+
+impl TlsConfig {
+    /// Load from TLS_* environment variables; `None` when TLS is off
+    fn from_env() -> Result<Option<Self>, ConfigError> {
+        let var = |name: &str| env_var(name).ok().filter(|v| !v.trim().is_empty());
+        match (var("TLS_CERT_FILE"), var("TLS_KEY_FILE")) {
+            (Some(cert_file), Some(key_file)) => Ok(Some(Self {
+                cert_file,
+                key_file,
+                redirect_port: var("TLS_REDIRECT_HTTP_PORT")
+                    .map(|v| {
+                        v.trim().parse().map_err(|_| {
+                            ConfigError("TLS_REDIRECT_HTTP_PORT must be a valid port".into())
+                        })
+                    })
+                    .transpose()?,
+            })),
+            (None, None) => Ok(None),
+            _ => Err(ConfigError(
+                "TLS_CERT_FILE and TLS_KEY_FILE must be set together".into(),
+            )),
+        }
+    }
+}
+
+/// Where the server listens (BIND)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindTarget {
+    /// HOST and PORT
+    Tcp,
+    /// A Unix socket at this path ("unix:/run/anime.sock")
+    Unix(String),
+    /// The sockets passed by systemd socket activation ("systemd")
+    Systemd,
+}
+
+impl BindTarget {
+    /// Parse a BIND value; "" and "tcp" are HOST and PORT
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if let Some(path) = value.strip_prefix("unix:") {
+            return (!path.is_empty()).then(|| Self::Unix(path.to_string()));
+        }
+        match value.to_ascii_lowercase().as_str() {
+            "" | "tcp" => Some(Self::Tcp),
+            "systemd" => Some(Self::Systemd),
+            _ => None,
+        }
+    }
+}
+
+/// Attributes of the auth cookie
+///
+/// The defaults suit a frontend served from the API's own site. A frontend
+/// on another site needs `SameSite=None`, which browsers only accept on
+/// Secure cookies, so it forces `secure` on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookieConfig {
+    /// Whether the cookie is only sent over HTTPS
+    pub secure: bool,
+    /// When the cookie is sent along with cross-site requests
+    pub same_site: SameSite,
+    /// Domain the cookie is scoped to (e.g. ".example.com" to share it with
+    /// subdomains); the API's own host when unset
+    pub domain: Option<String>,
+    /// Lifetime of the cookie (seconds)
+    pub max_age_secs: i64,
+    /// Path the cookie is scoped to: the API's base path, or "/"
+    pub path: String,
+}
+
+impl CookieConfig {
+    /// Defaults for a profile: Secure everywhere but development, where the
+    /// API is usually served over plain HTTP
+    pub fn defaults_for(app_env: AppEnv) -> Self {
+        Self {
+            secure: app_env != AppEnv::Development,
+            same_site: SameSite::Lax,
+            domain: None,
+            max_age_secs: JWT_EXPIRY_DAYS * 86400,
+            path: "/".to_string(),
+        }
+    }
+
+    /// Load from COOKIE_* environment variables, defaulting unset values
+    ///
+    /// The cookie defaults to Secure when the server terminates TLS itself
+    /// (`https`), whatever the profile.
+    fn from_env(app_env: AppEnv, https: bool) -> Self {
+        let defaults = Self::defaults_for(app_env);
+        let same_site = env_var("COOKIE_SAME_SITE")
+            .ok()
+            .and_then(|v| parse_same_site(&v))
+            .unwrap_or(defaults.same_site);
+
+        Self {
+            secure: env_var("COOKIE_SECURE")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(defaults.secure || https)
+                || same_site == SameSite::None,
+            same_site,
+            domain: env_var("COOKIE_DOMAIN")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|domain| !domain.is_empty()),
+            max_age_secs: env_var("COOKIE_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.max_age_secs),
+            path: defaults.path,
+        }
+    }
+}
+
+/// Normalize a base path to "" (the root) or "/segment[/...]" without a
+/// trailing slash, so it can be prepended to the API's paths
+pub fn normalize_base_path(value: &str) -> String {
+    let trimmed = value.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// Parse a SameSite attribute ("strict", "lax", or "none"), ignoring case
+pub fn parse_same_site(value: &str) -> Option<SameSite> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "strict" => Some(SameSite::Strict),
+        "lax" => Some(SameSite::Lax),
+        "none" => Some(SameSite::None),
+        _ => None,
+    }
+}
+
+/// Database connection pool settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabasePoolConfig {
+    /// Most connections the pool opens
+    pub max_connections: u32,
+    /// Connections kept open while idle
+    pub min_connections: u32,
+    /// How long a query waits for a free connection (seconds)
+    pub acquire_timeout_secs: u64,
+    /// Prepared statements cached per connection; 0 disables the cache
+    pub statement_cache_size: usize,
+}
+
+impl Default for DatabasePoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 2,
+            acquire_timeout_secs: 30,
+            statement_cache_size: 100,
+        }
+    }
+}
+
+impl DatabasePoolConfig {
+    /// Load from DATABASE_* pool environment variables, defaulting unset values
+    ///
+    /// The minimum is capped at the maximum.
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let max_connections = env_var("DATABASE_MAX_CONNECTIONS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&max| max > 0)
+            .unwrap_or(defaults.max_connections);
+
+        Self {
+            max_connections,
+            min_connections: env_var("DATABASE_MIN_CONNECTIONS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.min_connections)
+                .min(max_connections),
+            acquire_timeout_secs: env_var("DATABASE_ACQUIRE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs| secs > 0)
+                .unwrap_or(defaults.acquire_timeout_secs),
+            statement_cache_size: env_var("DATABASE_STATEMENT_CACHE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.statement_cache_size),
+        }
+    }
+}
+
+/// Size limits applied to every request
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLimitsConfig {
+    /// Largest accepted request body in bytes
+    pub max_body_bytes: usize,
+    /// Largest accepted multipart upload in bytes
+    pub max_upload_bytes: usize,
+    /// Longest accepted query string in bytes
+    pub max_query_len: usize,
+}
+
+impl Default for RequestLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 256 * 1024,
+            max_upload_bytes: 5 * 1024 * 1024,
+            max_query_len: 2048,
+        }
+    }
+}
+
+impl RequestLimitsConfig {
+    /// Load from REQUEST_MAX_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let size = |name: &str, default: usize| {
+            env_var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&size| size > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            max_body_bytes: size("REQUEST_MAX_BODY_BYTES", defaults.max_body_bytes),
+            max_upload_bytes: size("REQUEST_MAX_UPLOAD_BYTES", defaults.max_upload_bytes),
+            max_query_len: size("REQUEST_MAX_QUERY_LEN", defaults.max_query_len),
+        }
+    }
+}
+
+/// Frequent re-scraping of anime users subscribe to or favorite
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityCrawlConfig {
+    /// How often followed anime are checked for staleness (seconds); 0 disables it
+    pub interval_secs: u64,
+    /// Age after which a followed anime is re-scraped (seconds)
+    pub max_age_secs: u64,
+    /// Re-scrapes queued per check
+    pub batch_size: i64,
+}
+
+impl Default for PriorityCrawlConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 900,
+            max_age_secs: 6 * 3600,
+            batch_size: 50,
+        }
+    }
+}
+
+impl PriorityCrawlConfig {
+    /// Load from PRIORITY_CRAWL_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            interval_secs: env_var("PRIORITY_CRAWL_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.interval_secs),
+            max_age_secs: env_var("PRIORITY_CRAWL_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_age_secs),
+            batch_size: env_var("PRIORITY_CRAWL_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&size| size > 0)
+                .unwrap_or(defaults.batch_size),
+        }
+    }
+}
+
+/// Delivery of notification and webhook events from the outbox (see
+/// [`crate::jobs::outbox`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxConfig {
+    /// How often pending events are dispatched (seconds); 0 disables dispatching
+    pub interval_secs: u64,
+    /// Most events dispatched per run
+    pub batch_size: usize,
+    /// Failed deliveries after which an event is delivered without its webhook
+    pub max_attempts: i32,
+    /// URL every event is POSTed to as JSON; events only become emails when unset
+    pub webhook_url: Option<String>,
+    /// Secret webhook bodies are signed with (HMAC-SHA256)
+    pub webhook_secret: Option<String>,
+    /// How long a webhook request may take (seconds)
+    pub webhook_timeout_secs: u64,
+    /// Days delivered events are kept before they're deleted
+    pub retention_days: u32,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            batch_size: 100,
+            max_attempts: 10,
+            webhook_url: None,
+            webhook_secret: None,
+            webhook_timeout_secs: 10,
+            retention_days: 7,
+        }
+    }
+}
+
+impl OutboxConfig {
+    /// Load from OUTBOX_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            interval_secs: env_var("OUTBOX_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.interval_secs),
+            batch_size: env_var("OUTBOX_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&size| size > 0)
+                .unwrap_or(defaults.batch_size),
+            max_attempts: env_var("OUTBOX_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&attempts| attempts > 0)
+                .unwrap_or(defaults.max_attempts),
+            webhook_url: env_var("OUTBOX_WEBHOOK_URL")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            webhook_secret: env_var("OUTBOX_WEBHOOK_SECRET")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            webhook_timeout_secs: env_var("OUTBOX_WEBHOOK_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.webhook_timeout_secs),
+            retention_days: env_var("OUTBOX_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.retention_days),
+        }
+    }
+}
+
+/// Backing off crawls while the API is busy (see [`crate::crawler::backpressure`])
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrawlerBackpressureConfig {
+    /// Whether crawls back off at all
+    pub enabled: bool,
+    /// API requests in flight above which crawls pause; 0 ignores them
+    pub max_in_flight: usize,
+    /// Average API latency (milliseconds) above which crawls pause; 0 ignores it
+    pub max_latency_ms: u64,
+    /// Tokens added per second to the bucket API requests and crawl fetches share
+    pub rate_per_sec: f64,
+    /// Most tokens the shared bucket holds
+    pub burst: u32,
+}
+
+impl Default for CrawlerBackpressureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_in_flight: 4,
+            max_latency_ms: 1000,
+            rate_per_sec: 5.0,
+            burst: 20,
+        }
+    }
+}
+
+impl CrawlerBackpressureConfig {
+    /// Load from CRAWLER_BACKPRESSURE_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            enabled: env_var("CRAWLER_BACKPRESSURE")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(defaults.enabled),
+            max_in_flight: env_var("CRAWLER_BACKPRESSURE_MAX_IN_FLIGHT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_in_flight),
+            max_latency_ms: env_var("CRAWLER_BACKPRESSURE_MAX_LATENCY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_latency_ms),
+            rate_per_sec: env_var("CRAWLER_BACKPRESSURE_RATE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&rate: &f64| rate > 0.0)
+                .unwrap_or(defaults.rate_per_sec),
+            burst: env_var("CRAWLER_BACKPRESSURE_BURST")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&burst| burst > 0)
+                .unwrap_or(defaults.burst),
+        }
+    }
+}
+
+/// Log levels, format, and outputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    /// Base filter: RUST_LOG if set (any filter directives), otherwise LOG_LEVEL
+    pub level: String,
+    /// Per-module levels from LOG_MODULES ("scraper=debug,sqlx=warn")
+    pub modules: Vec<(String, String)>,
+    /// Format of log lines on every output
+    pub format: LogFormat,
+    /// Whether logs are written to stdout
+    pub stdout: bool,
+    /// Rolling log files, when LOG_FILE_DIR is set
+    pub file: Option<LogFileConfig>,
+    /// OpenTelemetry span export, when OTEL_EXPORTER_OTLP_ENDPOINT is set
+    pub otlp: Option<OtlpConfig>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: Vec::new(),
+            format: LogFormat::Text,
+            stdout: true,
+            file: None,
+            otlp: None,
+        }
+    }
+}
+
+impl LogConfig {
+    /// Load from RUST_LOG and LOG_* environment variables, defaulting unset values
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let flag = |name: &str, default: bool| {
+            env_var(name)
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(default)
+        };
+
+        Self {
+            level: env_var("RUST_LOG")
+                .or_else(|_| env_var("LOG_LEVEL"))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or(defaults.level),
+            modules: env_var("LOG_MODULES")
+                .map(|v| crate::logging::parse_module_levels(&v))
+                .unwrap_or_default(),
+            format: env_var("LOG_FORMAT")
+                .ok()
+                .and_then(|v| LogFormat::parse(&v))
+                .unwrap_or(defaults.format),
+            stdout: flag("LOG_STDOUT", defaults.stdout),
+            file: env_var("LOG_FILE_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+                .map(|dir| LogFileConfig {
+                    dir,
+                    prefix: env_var("LOG_FILE_PREFIX")
+                        .ok()
+                        .filter(|v| !v.trim().is_empty())
+                        .unwrap_or_else(|| "anime-scraper".to_string()),
+                    rotation: env_var("LOG_FILE_ROTATION")
+                        .ok()
+                        .and_then(|v| LogRotation::parse(&v))
+                        .unwrap_or(LogRotation::Daily),
+                    max_files: env_var("LOG_FILE_MAX_FILES")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(7),
+                }),
+            otlp: env_var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|endpoint| !endpoint.trim().is_empty())
+                .map(|endpoint| OtlpConfig {
+                    endpoint: endpoint.trim().to_string(),
+                    service_name: env_var("OTEL_SERVICE_NAME")
+                        .ok()
+                        .filter(|v| !v.trim().is_empty())
+                        .unwrap_or_else(|| "anime-scraper".to_string()),
+                }),
+        }
+    }
+}
+
+/// OpenTelemetry span export over OTLP/HTTP
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtlpConfig {
+    /// Collector base URL ("http://localhost:4318"); spans go to its /v1/traces
+    pub endpoint: String,
+    /// `service.name` the spans are reported under, from OTEL_SERVICE_NAME
+    pub service_name: String,
+}
+
+/// Format of log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl LogFormat {
+    /// Parse LOG_FORMAT ("text" or "json"), ignoring case
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" | "pretty" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Rolling log file output
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFileConfig {
+    /// Directory the files are written to
+    pub dir: String,
+    /// File name prefix; files are named like "anime-scraper.2024-12-27.log"
+    pub prefix: String,
+    /// How often a new file is started
+    pub rotation: LogRotation,
+    /// Files kept, oldest deleted first; 0 keeps every file
+    pub max_files: usize,
+}
+
+/// How often a new log file is started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    /// A single file, never rotated
+    Never,
+}
+
+impl LogRotation {
+    /// Parse LOG_FILE_ROTATION ("minutely", "hourly", "daily", or "never"), ignoring case
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "minutely" => Some(LogRotation::Minutely),
+            "hourly" => Some(LogRotation::Hourly),
+            "daily" => Some(LogRotation::Daily),
+            "never" => Some(LogRotation::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Client IP filtering and reverse proxy configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpFilterConfig {
+    /// Networks allowed to use the API; empty allows every address
+    pub allow: Vec<IpNet>,
+    /// Networks refused even when allowed
+    pub deny: Vec<IpNet>,
+    /// Reverse proxies whose X-Forwarded-For header is believed
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl IpFilterConfig {
+    /// Load from IP_ALLOWLIST, IP_DENYLIST, and TRUSTED_PROXIES
+    ///
+    /// # Errors
+    /// If an entry is neither an IP address nor a CIDR range, so a typo
+    /// can't silently open up an allow list
+    fn from_env() -> Result<Self, ConfigError> {
+        let networks = |name: &str| match env_var(name) {
+            Ok(v) => parse_networks(&v).map_err(|entry| {
+                ConfigError(format!(
+                    "{} has an invalid IP address or CIDR range: {}",
+                    name, entry
+                ))
+            }),
+            Err(_) => Ok(Vec::new()),
+        };
+
+        Ok(Self {
+            allow: networks("IP_ALLOWLIST")?,
+            deny: networks("IP_DENYLIST")?,
+            trusted_proxies: networks("TRUSTED_PROXIES")?,
+        })
+    }
+}
+
+/// Parse a comma-separated list of video server names, trimmed and lowercased
+pub fn parse_server_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|server| server.trim().to_lowercase())
+        .filter(|server| !server.is_empty())
+        .collect()
+}
+
+/// Parse a comma-separated list of IP addresses and CIDR ranges
+///
+/// Bare addresses become single-host networks.
+///
+/// # Returns
+/// * `Ok(Vec<IpNet>)` - The parsed networks
+/// * `Err(String)` - The first entry that couldn't be parsed
+pub fn parse_networks(value: &str) -> Result<Vec<IpNet>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<std::net::IpAddr>().map(IpNet::from))
+                .map_err(|_| entry.to_string())
+        })
+        .collect()
+}
+
+/// CAPTCHA service that verifies registration tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptchaConfig {
+    /// Verifying service
+    pub provider: CaptchaProvider,
+    /// Server-side secret key issued by the provider
+    pub secret: String,
+}
+
+/// Supported CAPTCHA services
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    /// hCaptcha
+    HCaptcha,
+    /// Cloudflare Turnstile
+    Turnstile,
+}
+
+impl CaptchaProvider {
+    /// Parse CAPTCHA_PROVIDER ("hcaptcha" or "turnstile")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "hcaptcha" => Some(CaptchaProvider::HCaptcha),
+            "turnstile" => Some(CaptchaProvider::Turnstile),
+            _ => None,
+        }
+    }
+
+    /// Endpoint that validates a client's token
+    pub fn verify_url(&self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            CaptchaProvider::Turnstile => {
+                "https://challenges.cloudflare.com/turnstile/v0/siteverify"
+            }
+        }
+    }
+}
+
+/// LibreTranslate-compatible translation service
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationConfig {
+    /// Base URL of the service; requests go to `{url}/translate`
+    pub url: String,
+    /// API key, for services that require one
+    pub api_key: Option<String>,
+}
+
+impl TranslationConfig {
+    /// Load from TRANSLATION_URL and TRANSLATION_API_KEY; `None` when no URL is set
+    fn from_env() -> Option<Self> {
+        let url = env_var("TRANSLATION_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())?;
+        Some(Self {
+            url,
+            api_key: env_var("TRANSLATION_API_KEY")
+                .ok()
+                .filter(|key| !key.is_empty()),
+        })
+    }
 }
 
 /// SMTP configuration for email sending
@@ -40,6 +1320,37 @@ pub struct SmtpConfig {
     pub from_email: String,
     /// Sender name
     pub from_name: String,
+    /// Transport security mode
+    pub tls: SmtpTlsMode,
+    /// Maximum number of pooled SMTP connections
+    pub pool_max_size: u32,
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTlsMode {
+    /// Plain connection upgraded with STARTTLS (usually port 587)
+    StartTls,
+    /// TLS from the first byte (usually port 465)
+    Implicit,
+    /// No encryption; only for local development relays
+    None,
+}
+
+impl SmtpTlsMode {
+    /// Parse SMTP_TLS ("starttls", "tls"/"implicit", "none")
+    ///
+    /// When unset or unrecognized, port 465 implies implicit TLS and any other
+    /// port uses STARTTLS.
+    pub fn parse(value: Option<&str>, port: u16) -> Self {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            Some("starttls") => SmtpTlsMode::StartTls,
+            Some("tls") | Some("implicit") | Some("ssl") => SmtpTlsMode::Implicit,
+            Some("none") => SmtpTlsMode::None,
+            _ if port == 465 => SmtpTlsMode::Implicit,
+            _ => SmtpTlsMode::StartTls,
+        }
+    }
 }
 
 impl Config {
@@ -49,42 +1360,350 @@ impl Config {
     /// Panics if required environment variables are not set
     pub fn from_env() -> Self {
         dotenvy::dotenv().ok();
+        Self::load().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Load configuration again for a reload
+    ///
+    /// The environment of a running process can't be changed from outside,
+    /// so values in the .env file take precedence over it; the file isn't
+    /// copied into the environment either.
+    ///
+    /// # Errors
+    /// If a setting is missing or invalid
+    pub fn from_env_file() -> Result<Self, ConfigError> {
+        /// Clears the file values however loading ends
+        struct Reset;
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                FILE_VALUES.with(|values| values.borrow_mut().take());
+            }
+        }
+
+        let values = dotenvy::dotenv_iter()
+            .map(|iter| iter.filter_map(Result::ok).collect())
+            .unwrap_or_default();
+        FILE_VALUES.with(|file_values| *file_values.borrow_mut() = Some(values));
+        let _reset = Reset;
+        Self::load()
+    }
+
+    /// Read every setting
+    fn load() -> Result<Self, ConfigError> {
+        // Unset or unknown profiles get the production defaults
+        let app_env = env_var("APP_ENV")
+            .ok()
+            .and_then(|v| AppEnv::parse(&v))
+            .unwrap_or(AppEnv::Production);
+        let flag = |name: &str, default: bool| {
+            env_var(name)
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(default)
+        };
 
         // Load SMTP config if all required vars are present
         let smtp = match (
-            env::var("SMTP_HOST").ok(),
-            env::var("SMTP_PORT").ok(),
-            env::var("SMTP_USERNAME").ok(),
-            env::var("SMTP_PASSWORD").ok(),
-            env::var("SMTP_FROM_EMAIL").ok(),
+            env_var("SMTP_HOST").ok(),
+            env_var("SMTP_PORT").ok(),
+            env_var("SMTP_USERNAME").ok(),
+            env_var("SMTP_PASSWORD").ok(),
+            env_var("SMTP_FROM_EMAIL").ok(),
         ) {
             (Some(host), Some(port), Some(username), Some(password), Some(from_email)) => {
+                let port = port.parse().unwrap_or(587);
                 Some(SmtpConfig {
                     host,
-                    port: port.parse().unwrap_or(587),
+                    port,
                     username,
                     password,
                     from_email: from_email.clone(),
-                    from_name: env::var("SMTP_FROM_NAME")
+                    from_name: env_var("SMTP_FROM_NAME")
                         .unwrap_or_else(|_| "Anime Scraper".to_string()),
+                    tls: SmtpTlsMode::parse(env_var("SMTP_TLS").ok().as_deref(), port),
+                    pool_max_size: env_var("SMTP_POOL_MAX_SIZE")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(4),
                 })
             }
             _ => None,
         };
 
-        Self {
-            database_url: env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
-            host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-            port: env::var("PORT")
+        let jwt_secret = required("JWT_SECRET")?;
+        let base_url = env_var("BASE_URL").unwrap_or_else(|_| "https://x3.sokuja.uk".to_string());
+
+        // Fall back to a key derived from the JWT secret
+        let url_signing_keys = env_var("URL_SIGNING_KEYS")
+            .map(|v| parse_signing_keys(&v))
+            .unwrap_or_default();
+        let url_signing_keys = if url_signing_keys.is_empty() {
+            vec![SigningKey::derive_from(&jwt_secret)]
+        } else {
+            url_signing_keys
+        };
+
+        let data_encryption_keys = match env_var("DATA_ENCRYPTION_KEYS") {
+            Ok(v) => parse_encryption_keys(&v)
+                .map_err(|e| ConfigError(format!("DATA_ENCRYPTION_KEYS is invalid: {}", e)))?,
+            Err(_) => Vec::new(),
+        };
+        let data_index_key = if data_encryption_keys.is_empty() {
+            String::new()
+        } else {
+            env_var("DATA_ENCRYPTION_INDEX_KEY").map_err(|_| {
+                ConfigError(
+                    "DATA_ENCRYPTION_INDEX_KEY must be set when DATA_ENCRYPTION_KEYS is".into(),
+                )
+            })?
+        };
+
+        // Default to the scraped site's host
+        let image_proxy_hosts = env_var("IMAGE_PROXY_HOSTS")
+            .map(|v| {
+                v.split(',')
+                    .map(|host| host.trim().to_lowercase())
+                    .filter(|host| !host.is_empty())
+                    .collect()
+            })
+            .unwrap_or_else(|_| {
+                reqwest::Url::parse(&base_url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_lowercase))
+                    .into_iter()
+                    .collect()
+            });
+
+        let tls = TlsConfig::from_env()?;
+
+        Ok(Self {
+            app_env,
+            cookies: CookieConfig::from_env(app_env, tls.is_some()),
+            base_path: String::new(),
+            tls,
+            bind: match env_var("BIND") {
+                Ok(v) => BindTarget::parse(&v).ok_or_else(|| {
+                    ConfigError("BIND must be tcp, unix:<path>, or systemd".into())
+                })?,
+                Err(_) => BindTarget::Tcp,
+            },
+            unix_socket_mode: match env_var("UNIX_SOCKET_MODE") {
+                Ok(v) => u32::from_str_radix(v.trim(), 8).map_err(|_| {
+                    ConfigError("UNIX_SOCKET_MODE must be an octal mode such as 660".into())
+                })?,
+                Err(_) => 0o660,
+            },
+            verbose_errors: flag("VERBOSE_ERRORS", app_env != AppEnv::Production),
+            database_url: required("DATABASE_URL")?,
+            database_replica_url: env_var("DATABASE_REPLICA_URL")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            database_pool: DatabasePoolConfig::from_env(),
+            host: env_var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            port: env_var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
-                .expect("PORT must be a valid number"),
-            jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
-            google_client_id: env::var("GOOGLE_CLIENT_ID").ok(),
-            base_url: env::var("BASE_URL").unwrap_or_else(|_| "https://x3.sokuja.uk".to_string()),
+                .map_err(|_| ConfigError("PORT must be a valid number".into()))?,
+            jwt_secret,
+            jwt: JwtConfig::from_env()?,
+            google_client_id: env_var("GOOGLE_CLIENT_ID").ok(),
+            base_url,
             smtp,
-            frontend_url: env::var("FRONTEND_URL")
+            frontend_url: env_var("FRONTEND_URL")
                 .unwrap_or_else(|_| "http://localhost:3000".to_string()),
+            email_templates_dir: env_var("EMAIL_TEMPLATES_DIR").ok(),
+            job_workers: env_var("JOB_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            job_poll_interval_ms: env_var("JOB_POLL_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            password_min_score: env_var("PASSWORD_MIN_SCORE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MIN_SCORE)
+                .min(MAX_SCORE),
+            password_breach_check: env_var("PASSWORD_BREACH_CHECK")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+            url_signing_keys,
+            data_encryption_keys,
+            data_index_key,
+            signed_url_ttl_secs: env_var("SIGNED_URL_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(86400),
+            image_proxy_hosts,
+            image_prefetch: flag("IMAGE_PREFETCH", false),
+            cache_control: CacheControlConfig::from_env(),
+            tenant_header: env_var("TENANT_HEADER").unwrap_or_else(|_| "X-Tenant".to_string()),
+            storage: StorageConfig::from_env()?,
+            parser_fixtures_dir: env_var("PARSER_FIXTURES_DIR")
+                .unwrap_or_else(|_| "fixtures/parser".to_string()),
+            selector_profile: env_var("SELECTOR_PROFILE")
+                .ok()
+                .filter(|path| !path.trim().is_empty()),
+            grpc_addr: env_var("GRPC_ADDR").ok(),
+            upstream_timeouts: UpstreamTimeouts::from_env(),
+            scraper_max_redirects: env_var("SCRAPER_MAX_REDIRECTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(MAX_REDIRECTS),
+            scraper_same_host_redirects: flag("SCRAPER_SAME_HOST_REDIRECTS", false),
+            crawler_daily_bandwidth_bytes: env_var("CRAWLER_DAILY_BANDWIDTH_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            crawler_backpressure: CrawlerBackpressureConfig::from_env(),
+            search_cache_ttl_secs: env_var("SEARCH_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300),
+            search_cache_empty_ttl_secs: env_var("SEARCH_CACHE_EMPTY_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            community_top_ttl_secs: env_var("COMMUNITY_TOP_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(900),
+            saved_search_interval_secs: env_var("SAVED_SEARCH_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(900),
+            priority_crawl: PriorityCrawlConfig::from_env(),
+            logging: LogConfig::from_env(),
+            popularity_interval_secs: env_var("POPULARITY_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3600),
+            episode_gap_interval_secs: env_var("EPISODE_GAP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(6 * 3600),
+            completed_archive_interval_secs: env_var("COMPLETED_ARCHIVE_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24 * 3600),
+            status_reconcile_interval_secs: env_var("STATUS_RECONCILE_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(7 * 24 * 3600),
+            anonymous_cleanup_interval_secs: env_var("ANONYMOUS_CLEANUP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24 * 3600),
+            anonymous_max_idle_days: env_var("ANONYMOUS_MAX_IDLE_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            change_events_cleanup_interval_secs: env_var("CHANGE_EVENTS_CLEANUP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24 * 3600),
+            change_events_retention_days: env_var("CHANGE_EVENTS_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            feature_flag_refresh_secs: env_var("FEATURE_FLAG_REFRESH_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            outbox: OutboxConfig::from_env(),
+            registration: RegistrationConfig::from_env(app_env),
+            ip_filter: IpFilterConfig::from_env()?,
+            request_limits: RequestLimitsConfig::from_env(),
+            translation: TranslationConfig::from_env(),
+            video_server_blacklist: env_var("VIDEO_SERVER_BLACKLIST")
+                .map(|v| parse_server_list(&v))
+                .unwrap_or_default(),
+            video_server_priority: env_var("VIDEO_SERVER_PRIORITY")
+                .map(|v| parse_server_list(&v))
+                .unwrap_or_default(),
+            sitemap_base_url: env_var("SITEMAP_BASE_URL")
+                .or_else(|_| env_var("FRONTEND_URL"))
+                .unwrap_or_else(|_| "http://localhost:3000".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+        }
+        .with_base_path(&env_var("BASE_PATH").unwrap_or_default()))
+    }
+
+    /// This configuration with the settings a reload applies taken from
+    /// `fresh`
+    ///
+    /// Settings the server, database, keys, email, storage, scraper, and
+    /// background jobs are set up with only take effect on restart, so they
+    /// are kept. Settings read per request (TTLs, limits, lists, timeouts)
+    /// are taken from `fresh`.
+    pub fn reloaded(&self, fresh: Config) -> Config {
+        Config {
+            app_env: self.app_env,
+            database_url: self.database_url.clone(),
+            database_replica_url: self.database_replica_url.clone(),
+            database_pool: self.database_pool.clone(),
+            host: self.host.clone(),
+            port: self.port,
+            tls: self.tls.clone(),
+            bind: self.bind.clone(),
+            unix_socket_mode: self.unix_socket_mode,
+            jwt_secret: self.jwt_secret.clone(),
+            jwt: self.jwt.clone(),
+            smtp: self.smtp.clone(),
+            frontend_url: self.frontend_url.clone(),
+            email_templates_dir: self.email_templates_dir.clone(),
+            job_workers: self.job_workers,
+            job_poll_interval_ms: self.job_poll_interval_ms,
+            data_encryption_keys: self.data_encryption_keys.clone(),
+            data_index_key: self.data_index_key.clone(),
+            storage: self.storage.clone(),
+            grpc_addr: self.grpc_addr.clone(),
+            scraper_max_redirects: self.scraper_max_redirects,
+            scraper_same_host_redirects: self.scraper_same_host_redirects,
+            saved_search_interval_secs: self.saved_search_interval_secs,
+            priority_crawl: self.priority_crawl.clone(),
+            popularity_interval_secs: self.popularity_interval_secs,
+            episode_gap_interval_secs: self.episode_gap_interval_secs,
+            completed_archive_interval_secs: self.completed_archive_interval_secs,
+            status_reconcile_interval_secs: self.status_reconcile_interval_secs,
+            anonymous_cleanup_interval_secs: self.anonymous_cleanup_interval_secs,
+            change_events_cleanup_interval_secs: self.change_events_cleanup_interval_secs,
+            feature_flag_refresh_secs: self.feature_flag_refresh_secs,
+            outbox: self.outbox.clone(),
+            crawler_backpressure: self.crawler_backpressure.clone(),
+            logging: self.logging.clone(),
+            request_limits: self.request_limits.clone(),
+            ..fresh
         }
+        .with_base_path(&self.base_path)
+    }
+
+    /// Serve the API under `base_path` (e.g. "/anime-api"; "" or "/" for the
+    /// root), scoping the auth cookie to it
+    pub fn with_base_path(mut self, base_path: &str) -> Self {
+        self.base_path = normalize_base_path(base_path);
+        self.cookies.path = if self.base_path.is_empty() {
+            "/".to_string()
+        } else {
+            self.base_path.clone()
+        };
+        self
+    }
+
+    /// Signer for expiring URLs using the configured keys
+    pub fn url_signer(&self) -> UrlSigner {
+        UrlSigner::new(self.url_signing_keys.clone()).with_prefix(&self.base_path)
+    }
+
+    /// Daily crawler bandwidth quota in bytes, or `None` for no limit
+    pub fn crawler_bandwidth_quota(&self) -> Option<u64> {
+        (self.crawler_daily_bandwidth_bytes > 0).then_some(self.crawler_daily_bandwidth_bytes)
+    }
+
+    /// Cipher for sensitive user columns, or `None` if no keys are configured
+    pub fn field_cipher(&self) -> Option<FieldCipher> {
+        (!self.data_encryption_keys.is_empty())
+            .then(|| FieldCipher::new(self.data_encryption_keys.clone(), &self.data_index_key))
     }
 }
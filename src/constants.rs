@@ -1,9 +1,13 @@
 //! Constants module for the Anime Scraper API
 //!
-//! Contains endpoint URL builders that use the base URL from configuration.
+//! Contains endpoint URL builders that use the base URL from configuration,
+//! the filter values accepted by the anime list, and what is known about the
+//! players that episode mirrors embed.
 
 /// URL builder functions for all endpoints
 pub mod endpoints {
+    use super::filters::{AnimeStatus, AnimeType, Order};
+
     /// Home page URL
     pub fn home(base_url: &str) -> String {
         base_url.to_string()
@@ -14,18 +18,96 @@ pub mod endpoints {
         format!("{}/?s={}", base_url, urlencoding::encode(query))
     }
 
-    /// Anime list URL with filters
-    pub fn anime_list(
-        base_url: &str,
+    /// Anime list URL builder
+    ///
+    /// Filters left unset are sent empty, which the site treats as "any".
+    ///
+    /// ```
+    /// use anime_scraper::constants::endpoints::ListUrl;
+    /// use anime_scraper::constants::filters::{AnimeType, Order};
+    ///
+    /// let url = ListUrl::new()
+    ///     .page(2)
+    ///     .type_(AnimeType::Tv)
+    ///     .order(Order::Popular)
+    ///     .build("https://example.com");
+    /// assert_eq!(
+    ///     url,
+    ///     "https://example.com/anime/?page=2&status=&type=TV&order=popular"
+    /// );
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ListUrl {
         page: u32,
-        type_filter: &str,
-        status: &str,
-        order: &str,
-    ) -> String {
-        format!(
-            "{}/anime/?page={}&status={}&type={}&order={}",
-            base_url, page, status, type_filter, order
-        )
+        anime_type: Option<AnimeType>,
+        status: Option<AnimeStatus>,
+        order: Option<Order>,
+    }
+
+    impl Default for ListUrl {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl ListUrl {
+        /// First page, no filters
+        pub fn new() -> Self {
+            Self {
+                page: 1,
+                anime_type: None,
+                status: None,
+                order: None,
+            }
+        }
+
+        /// Set the page number
+        pub fn page(mut self, page: u32) -> Self {
+            self.page = page;
+            self
+        }
+
+        /// Filter by anime type
+        pub fn type_(mut self, anime_type: AnimeType) -> Self {
+            self.anime_type = Some(anime_type);
+            self
+        }
+
+        /// Filter by status
+        pub fn status(mut self, status: AnimeStatus) -> Self {
+            self.status = Some(status);
+            self
+        }
+
+        /// Set the sort order
+        pub fn order(mut self, order: Order) -> Self {
+            self.order = Some(order);
+            self
+        }
+
+        /// Build the URL against a base URL, percent-encoding query values
+        pub fn build(&self, base_url: &str) -> String {
+            let status = self.status.map(AnimeStatus::as_str).unwrap_or("");
+            let anime_type = self.anime_type.map(AnimeType::as_str).unwrap_or("");
+            let order = self.order.map(Order::as_str).unwrap_or("");
+            format!(
+                "{}/anime/?page={}&status={}&type={}&order={}",
+                base_url,
+                self.page,
+                urlencoding::encode(status),
+                urlencoding::encode(anime_type),
+                urlencoding::encode(order)
+            )
+        }
+    }
+
+    /// Completed anime archive URL for a page, starting at 1
+    pub fn completed(base_url: &str, page: u32) -> String {
+        if page <= 1 {
+            format!("{}/completed/", base_url)
+        } else {
+            format!("{}/completed/page/{}/", base_url, page)
+        }
     }
 
     /// Anime detail page URL
@@ -68,4 +150,242 @@ pub mod filters {
         "popular",
         "rating",
     ];
+    /// Define a filter enum with its site value and a case-insensitive parser
+    macro_rules! filter_enum {
+        ($(#[$meta:meta])* $name:ident { $($(#[$vmeta:meta])* $variant:ident => $value:literal,)+ }) => {
+            $(#[$meta])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub enum $name {
+                $($(#[$vmeta])* $variant,)+
+            }
+
+            impl $name {
+                /// Every value, in the order the site lists them
+                pub const ALL: &'static [$name] = &[$($name::$variant,)+];
+
+                /// Value sent to the site
+                pub fn as_str(self) -> &'static str {
+                    match self {
+                        $($name::$variant => $value,)+
+                    }
+                }
+
+                /// Parse a site value, ignoring case
+                pub fn parse(value: &str) -> Option<Self> {
+                    Self::ALL
+                        .iter()
+                        .copied()
+                        .find(|v| v.as_str().eq_ignore_ascii_case(value.trim()))
+                }
+            }
+        };
+    }
+
+    filter_enum! {
+        /// Anime type filter
+        AnimeType {
+            /// TV series
+            Tv => "TV",
+            /// Original video animation
+            Ova => "OVA",
+            /// Theatrical movie
+            Movie => "Movie",
+            /// Live-action adaptation
+            LiveAction => "Live Action",
+            /// Special episode
+            Special => "Special",
+            /// Blu-ray release
+            Bd => "BD",
+            /// Original net animation
+            Ona => "ONA",
+            /// Music video
+            Music => "Music",
+        }
+    }
+
+    filter_enum! {
+        /// Airing status filter
+        AnimeStatus {
+            /// Currently airing
+            Ongoing => "Ongoing",
+            /// Finished airing
+            Completed => "Completed",
+            /// Not aired yet
+            Upcoming => "Upcoming",
+            /// Paused
+            Hiatus => "Hiatus",
+        }
+    }
+
+    filter_enum! {
+        /// Anime list sort order
+        Order {
+            /// A-Z
+            Title => "title",
+            /// Z-A
+            TitleReverse => "titlereverse",
+            /// Latest update
+            Update => "update",
+            /// Latest added
+            Latest => "latest",
+            /// Most popular
+            Popular => "popular",
+            /// Highest rating
+            Rating => "rating",
+        }
+    }
+}
+
+/// Capabilities of the hosts episode mirrors embed as iframes
+pub mod embed_hosts {
+    /// What is known about an embed host
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct HostCapabilities {
+        /// Registered domain; subdomains match too
+        pub domain: &'static str,
+        /// Serves a player page meant to be embedded, rather than a page
+        /// that only happens to hold a video
+        pub player: bool,
+        /// Refuses to play unless the embedding page's referer is sent
+        pub requires_referer: bool,
+    }
+
+    /// Known embed hosts
+    pub const KNOWN_HOSTS: &[HostCapabilities] = &[
+        HostCapabilities {
+            domain: "blogger.com",
+            player: true,
+            requires_referer: false,
+        },
+        HostCapabilities {
+            domain: "drive.google.com",
+            player: true,
+            requires_referer: false,
+        },
+        HostCapabilities {
+            domain: "ok.ru",
+            player: true,
+            requires_referer: false,
+        },
+        HostCapabilities {
+            domain: "mega.nz",
+            player: true,
+            requires_referer: false,
+        },
+        HostCapabilities {
+            domain: "streamtape.com",
+            player: true,
+            requires_referer: false,
+        },
+        HostCapabilities {
+            domain: "mp4upload.com",
+            player: true,
+            requires_referer: true,
+        },
+        HostCapabilities {
+            domain: "yourupload.com",
+            player: true,
+            requires_referer: true,
+        },
+        HostCapabilities {
+            domain: "krakenfiles.com",
+            player: true,
+            requires_referer: true,
+        },
+    ];
+
+    /// Look up a host name, ignoring case and matching subdomains
+    pub fn lookup(host: &str) -> Option<&'static HostCapabilities> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        KNOWN_HOSTS.iter().find(|known| {
+            host == known.domain
+                || host
+                    .strip_suffix(known.domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::embed_hosts;
+    use super::endpoints::{self, ListUrl};
+    use super::filters::*;
+
+    const BASE: &str = "https://x3.sokuja.uk";
+
+    #[test]
+    fn test_list_url_defaults() {
+        assert_eq!(
+            ListUrl::new().build(BASE),
+            "https://x3.sokuja.uk/anime/?page=1&status=&type=&order="
+        );
+    }
+
+    #[test]
+    fn test_list_url_with_filters() {
+        let url = ListUrl::new()
+            .page(2)
+            .type_(AnimeType::Tv)
+            .status(AnimeStatus::Ongoing)
+            .order(Order::Popular)
+            .build(BASE);
+        assert_eq!(
+            url,
+            "https://x3.sokuja.uk/anime/?page=2&status=Ongoing&type=TV&order=popular"
+        );
+    }
+
+    #[test]
+    fn test_list_url_encodes_values() {
+        let url = ListUrl::new().type_(AnimeType::LiveAction).build(BASE);
+        assert_eq!(
+            url,
+            "https://x3.sokuja.uk/anime/?page=1&status=&type=Live%20Action&order="
+        );
+    }
+
+    #[test]
+    fn test_completed_url() {
+        assert_eq!(
+            endpoints::completed(BASE, 1),
+            "https://x3.sokuja.uk/completed/"
+        );
+        assert_eq!(
+            endpoints::completed(BASE, 3),
+            "https://x3.sokuja.uk/completed/page/3/"
+        );
+    }
+
+    #[test]
+    fn test_filters_match_site_values() {
+        let types: Vec<&str> = AnimeType::ALL.iter().map(|t| t.as_str()).collect();
+        assert_eq!(types, &ANIME_TYPES[1..]);
+        let statuses: Vec<&str> = AnimeStatus::ALL.iter().map(|s| s.as_str()).collect();
+        assert_eq!(statuses, &ANIME_STATUS[1..]);
+        let orders: Vec<&str> = Order::ALL.iter().map(|o| o.as_str()).collect();
+        assert_eq!(orders, &ANIME_ORDER[1..]);
+    }
+
+    #[test]
+    fn test_filter_parse() {
+        assert_eq!(AnimeType::parse("tv"), Some(AnimeType::Tv));
+        assert_eq!(AnimeType::parse("live action"), Some(AnimeType::LiveAction));
+        assert_eq!(
+            AnimeStatus::parse("COMPLETED"),
+            Some(AnimeStatus::Completed)
+        );
+        assert_eq!(Order::parse("popular"), Some(Order::Popular));
+        assert_eq!(AnimeType::parse(""), None);
+        assert_eq!(Order::parse("TV&order=x"), None);
+    }
+
+    #[test]
+    fn test_embed_host_lookup() {
+        let blogger = embed_hosts::lookup("www.Blogger.com").expect("Blogger is known");
+        assert_eq!(blogger.domain, "blogger.com");
+        assert!(embed_hosts::lookup("mp4upload.com").is_some_and(|h| h.requires_referer));
+        assert_eq!(embed_hosts::lookup("notblogger.com"), None);
+        assert_eq!(embed_hosts::lookup("example.com"), None);
+    }
 }
@@ -0,0 +1,281 @@
+//! Backing off crawls while the API is busy
+//!
+//! On a small deployment a crawl competes with users for the same CPU,
+//! database pool, and upstream connection. Every API request goes through
+//! [`track_api_load`](crate::middleware::track_api_load), which counts it in
+//! the [`ApiLoad`] while it runs and records how long it took. API requests
+//! and crawl fetches also share a token bucket: a request takes a token
+//! (never waiting for one), and a fetch needs one. Before each fetch a crawl
+//! waits while:
+//! - more API requests are in flight than the configured maximum
+//! - API requests finished over the last [`LATENCY_WINDOW`] took longer
+//!   than the configured maximum on average
+//! - API traffic has emptied the bucket
+//!
+//! and carries on once the API is idle again.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::config::CrawlerBackpressureConfig;
+
+/// How long finished requests count towards the average latency
+pub const LATENCY_WINDOW: Duration = Duration::from_secs(10);
+
+/// Finished requests remembered for the average latency
+const MAX_LATENCY_SAMPLES: usize = 1000;
+
+/// How often a paused crawl checks whether the API is idle again
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Why a crawl is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pressure {
+    /// API requests in flight
+    InFlight(usize),
+    /// Average latency of recent API requests
+    Latency(Duration),
+    /// API traffic has used up the shared token bucket
+    NoTokens,
+}
+
+impl fmt::Display for Pressure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pressure::InFlight(count) => write!(f, "{} API requests in flight", count),
+            Pressure::Latency(latency) => {
+                write!(f, "API requests taking {} ms", latency.as_millis())
+            }
+            Pressure::NoTokens => write!(f, "API request rate over the shared budget"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LoadState {
+    in_flight: usize,
+    latencies: VecDeque<(Instant, Duration)>,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl LoadState {
+    /// Add the tokens earned since the last refill
+    fn refill(&mut self, config: &CrawlerBackpressureConfig, now: Instant) {
+        let earned = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64()
+            * config.rate_per_sec;
+        self.tokens = (self.tokens + earned).min(config.burst as f64);
+        self.refilled_at = now;
+    }
+
+    /// Average latency of requests finished within [`LATENCY_WINDOW`]
+    fn recent_latency(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .latencies
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > LATENCY_WINDOW)
+        {
+            self.latencies.pop_front();
+        }
+        let total: Duration = self.latencies.iter().map(|(_, latency)| *latency).sum();
+        (!self.latencies.is_empty()).then(|| total / self.latencies.len() as u32)
+    }
+}
+
+/// API traffic crawls back off from
+///
+/// Cloning shares the load, so the middleware counts requests into the same
+/// one crawls check.
+#[derive(Debug, Clone)]
+pub struct ApiLoad {
+    config: CrawlerBackpressureConfig,
+    state: Arc<Mutex<LoadState>>,
+}
+
+impl ApiLoad {
+    /// Track API load, starting idle with a full bucket
+    pub fn new(config: CrawlerBackpressureConfig) -> Self {
+        let state = LoadState {
+            in_flight: 0,
+            latencies: VecDeque::new(),
+            tokens: config.burst as f64,
+            refilled_at: Instant::now(),
+        };
+        Self {
+            config,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LoadState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count an API request until the returned guard is dropped
+    pub fn start_request(&self) -> ApiRequest {
+        self.start_request_at(Instant::now())
+    }
+
+    fn start_request_at(&self, now: Instant) -> ApiRequest {
+        let mut state = self.lock();
+        state.in_flight += 1;
+        state.refill(&self.config, now);
+        state.tokens = (state.tokens - 1.0).max(0.0);
+        ApiRequest {
+            load: self.clone(),
+            started: now,
+        }
+    }
+
+    /// Record a finished API request
+    fn finish_request(&self, started: Instant, now: Instant) {
+        let mut state = self.lock();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        if state.latencies.len() == MAX_LATENCY_SAMPLES {
+            state.latencies.pop_front();
+        }
+        state
+            .latencies
+            .push_back((now, now.saturating_duration_since(started)));
+    }
+
+    /// Take a token for a crawl fetch, unless the API is busy
+    fn try_acquire_at(&self, now: Instant) -> Result<(), Pressure> {
+        let mut state = self.lock();
+        if self.config.max_in_flight > 0 && state.in_flight > self.config.max_in_flight {
+            return Err(Pressure::InFlight(state.in_flight));
+        }
+        if self.config.max_latency_ms > 0 {
+            let max = Duration::from_millis(self.config.max_latency_ms);
+            if let Some(latency) = state.recent_latency(now).filter(|&l| l > max) {
+                return Err(Pressure::Latency(latency));
+            }
+        }
+        state.refill(&self.config, now);
+        if state.tokens < 1.0 {
+            return Err(Pressure::NoTokens);
+        }
+        state.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Wait until the API is idle enough for a crawl fetch
+    ///
+    /// # Returns
+    /// How long the crawl was paused
+    pub async fn wait_for_turn(&self) -> Duration {
+        if !self.config.enabled {
+            return Duration::ZERO;
+        }
+
+        let started = Instant::now();
+        let mut paused = false;
+        while let Err(pressure) = self.try_acquire_at(Instant::now()) {
+            if !paused {
+                info!("Pausing the crawl: {}", pressure);
+                paused = true;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        if !paused {
+            return Duration::ZERO;
+        }
+        let waited = started.elapsed();
+        info!(
+            "API idle again, resuming the crawl after {:.1}s",
+            waited.as_secs_f64()
+        );
+        waited
+    }
+}
+
+/// An API request counted in an [`ApiLoad`]; finishes when dropped
+#[derive(Debug)]
+pub struct ApiRequest {
+    load: ApiLoad,
+    started: Instant,
+}
+
+impl Drop for ApiRequest {
+    fn drop(&mut self) {
+        self.load.finish_request(self.started, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CrawlerBackpressureConfig {
+        CrawlerBackpressureConfig {
+            enabled: true,
+            max_in_flight: 1,
+            max_latency_ms: 500,
+            rate_per_sec: 1.0,
+            burst: 2,
+        }
+    }
+
+    #[test]
+    fn test_pauses_while_requests_in_flight() {
+        let load = ApiLoad::new(CrawlerBackpressureConfig {
+            burst: 10,
+            ..config()
+        });
+        let now = Instant::now();
+
+        let first = load.start_request_at(now);
+        assert_eq!(load.try_acquire_at(now), Ok(()));
+        let second = load.start_request_at(now);
+        assert_eq!(load.try_acquire_at(now), Err(Pressure::InFlight(2)));
+
+        drop(second);
+        drop(first);
+        assert_eq!(load.try_acquire_at(now), Ok(()));
+    }
+
+    #[test]
+    fn test_pauses_while_requests_are_slow() {
+        let load = ApiLoad::new(config());
+        let now = Instant::now();
+        load.finish_request(now, now + Duration::from_millis(900));
+        load.finish_request(now, now + Duration::from_millis(300));
+
+        let at = now + Duration::from_secs(1);
+        assert_eq!(
+            load.try_acquire_at(at),
+            Err(Pressure::Latency(Duration::from_millis(600)))
+        );
+
+        // Slow requests stop counting once they're out of the window
+        assert_eq!(load.try_acquire_at(at + LATENCY_WINDOW), Ok(()));
+    }
+
+    #[test]
+    fn test_shares_token_bucket_with_requests() {
+        let load = ApiLoad::new(config());
+        let now = Instant::now();
+
+        // Requests take tokens without waiting, even from an empty bucket
+        for _ in 0..3 {
+            load.start_request_at(now);
+        }
+        assert_eq!(load.try_acquire_at(now), Err(Pressure::NoTokens));
+
+        // The bucket refills at the configured rate, up to the burst
+        let later = now + Duration::from_secs(1);
+        assert_eq!(load.try_acquire_at(later), Ok(()));
+        assert_eq!(load.try_acquire_at(later), Err(Pressure::NoTokens));
+        let idle = later + Duration::from_secs(60);
+        assert_eq!(load.try_acquire_at(idle), Ok(()));
+        assert_eq!(load.try_acquire_at(idle), Ok(()));
+        assert_eq!(load.try_acquire_at(idle), Err(Pressure::NoTokens));
+    }
+}
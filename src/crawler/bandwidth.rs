@@ -0,0 +1,159 @@
+//! Daily bandwidth accounting for crawls
+//!
+//! Every page a crawl downloads is added to the day's total in
+//! crawler_bandwidth (days are UTC), counted as sent over the network, i.e.
+//! before decompression. With a daily quota set
+//! (CRAWLER_DAILY_BANDWIDTH_BYTES), a crawl stops starting new pages, anime,
+//! and episodes once the day's total reaches it. Crawls started later that
+//! day stop right away; the next day's crawls pick up where they left off.
+//!
+//! The background jobs fetching from the site count against the same quota:
+//! the completed archive crawl, status reconciliation, backfill scrapes, and
+//! image prefetches stop fetching once it is reached.
+
+use chrono::{NaiveDate, Utc};
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::db::{
+    add_crawler_bandwidth, get_crawler_bandwidth, list_crawler_bandwidth, RepositoryResult,
+};
+use crate::models::CrawlerBandwidthReport;
+use crate::scraper::{ScrapeClient, ScraperError, ScraperResult};
+
+/// Days listed in a bandwidth report
+const REPORT_DAYS: i64 = 30;
+
+/// Bytes downloaded on one day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DailyUsage {
+    /// Day (UTC) the bytes were downloaded on
+    pub day: NaiveDate,
+    /// Bytes downloaded
+    pub bytes: u64,
+}
+
+impl DailyUsage {
+    /// Bytes downloaded on `day`; none if the usage is of another day
+    pub fn bytes_on(&self, day: NaiveDate) -> u64 {
+        if self.day == day {
+            self.bytes
+        } else {
+            0
+        }
+    }
+
+    /// Whether the usage on `day` has reached `quota`
+    pub fn exhausts(&self, quota: Option<u64>, day: NaiveDate) -> bool {
+        quota.is_some_and(|quota| self.bytes_on(day) >= quota)
+    }
+}
+
+/// Today's date in UTC
+fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// Records a crawl's downloads and checks them against the daily quota
+#[derive(Debug)]
+pub struct BandwidthMeter {
+    pool: PgPool,
+    daily_quota: Option<u64>,
+    usage: DailyUsage,
+}
+
+impl BandwidthMeter {
+    /// Start metering with the bandwidth already used today
+    ///
+    /// # Arguments
+    /// * `daily_quota` - Bytes crawls may download per day, or `None` for no limit
+    pub async fn load(pool: &PgPool, daily_quota: Option<u64>) -> Self {
+        let day = today();
+        let bytes = get_crawler_bandwidth(pool, day).await.unwrap_or_else(|e| {
+            warn!("Failed to read today's crawler bandwidth: {}", e);
+            0
+        });
+        Self {
+            pool: pool.clone(),
+            daily_quota,
+            usage: DailyUsage { day, bytes },
+        }
+    }
+
+    /// Add a downloaded page to today's total
+    pub async fn record(&mut self, bytes: u64) {
+        let day = today();
+        let known = self.usage.bytes_on(day) + bytes;
+        let total = match add_crawler_bandwidth(&self.pool, day, bytes).await {
+            Ok(total) => total,
+            Err(e) => {
+                warn!("Failed to record crawler bandwidth: {}", e);
+                known
+            }
+        };
+        self.usage = DailyUsage { day, bytes: total };
+    }
+
+    /// Whether today's downloads have reached the daily quota
+    pub fn exhausted(&self) -> bool {
+        self.usage.exhausts(self.daily_quota, today())
+    }
+
+    /// Fetch a page through `scraper`, adding it to today's total
+    ///
+    /// Callers check [`BandwidthMeter::exhausted`] before fetching.
+    pub async fn fetch(
+        &mut self,
+        scraper: &dyn ScrapeClient,
+        url: &str,
+    ) -> Result<ScraperResult, ScraperError> {
+        let page = scraper.fetch_page(url).await?;
+        self.record(page.wire_bytes).await;
+        Ok(page)
+    }
+}
+
+/// Crawler bandwidth use over the last 30 days against the daily quota
+pub async fn bandwidth_report(
+    pool: &PgPool,
+    daily_quota: Option<u64>,
+) -> RepositoryResult<CrawlerBandwidthReport> {
+    let today = today();
+    let days = list_crawler_bandwidth(pool, REPORT_DAYS).await?;
+    let today_bytes = days
+        .iter()
+        .find(|day| day.day == today.to_string())
+        .map_or(0, |day| day.bytes);
+
+    Ok(CrawlerBandwidthReport {
+        today_bytes,
+        daily_quota_bytes: daily_quota,
+        quota_exhausted: DailyUsage {
+            day: today,
+            bytes: today_bytes,
+        }
+        .exhausts(daily_quota, today),
+        days,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_usage_against_quota() {
+        let day = NaiveDate::from_ymd_opt(2024, 12, 27).unwrap();
+        let next = day.succ_opt().unwrap();
+        let usage = DailyUsage { day, bytes: 1000 };
+
+        assert_eq!(usage.bytes_on(day), 1000);
+        assert!(usage.exhausts(Some(1000), day));
+        assert!(!usage.exhausts(Some(1001), day));
+        assert!(!usage.exhausts(None, day));
+
+        // The quota starts over the next day
+        assert_eq!(usage.bytes_on(next), 0);
+        assert!(!usage.exhausts(Some(1000), next));
+    }
+}
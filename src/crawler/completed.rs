@@ -0,0 +1,232 @@
+//! Crawling the completed anime archive
+//!
+//! The home page only shows the latest few completed anime. The site's
+//! completed archive lists all of them, newest first, across numbered pages;
+//! the crawl walks it until a page is missing, lists nothing, or lists only
+//! anime already seen (a site repeating its last page), and saves the whole
+//! archive into completed_anime in one go so it keeps the archive's order.
+//! The pages count against the daily crawler bandwidth quota; once it is
+//! reached the crawl stops and saves the pages read so far.
+
+use sqlx::PgPool;
+use thiserror::Error;
+use tracing::{info, instrument, warn};
+
+use super::bandwidth::BandwidthMeter;
+use crate::constants::endpoints;
+use crate::db::{save_completed_anime, RepositoryError};
+use crate::parser::{parse_completed_anime, CompletedAnime};
+use crate::scraper::{ScrapeClient, ScraperError};
+
+/// Maximum number of archive pages visited in a single crawl
+pub const MAX_COMPLETED_PAGES: u32 = 500;
+
+/// Errors crawling the completed archive
+#[derive(Debug, Error)]
+pub enum CompletedArchiveError {
+    /// The first archive page couldn't be fetched
+    #[error("Failed to fetch completed archive: {0}")]
+    Fetch(#[from] ScraperError),
+
+    /// The archive couldn't be saved
+    #[error("Failed to save completed archive: {0}")]
+    Save(#[from] RepositoryError),
+
+    /// The daily crawler bandwidth quota was already reached
+    #[error("Daily crawler bandwidth quota reached")]
+    QuotaExhausted,
+}
+
+/// Completed anime read from the archive
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompletedArchive {
+    /// Every anime listed, newest first
+    pub anime: Vec<CompletedAnime>,
+    /// Archive pages read
+    pub pages: u32,
+}
+
+/// Read every page of the completed archive
+///
+/// A failure past the first page ends the crawl with the pages read so far,
+/// as does reaching the bandwidth quota.
+///
+/// # Arguments
+/// * `base_url` - Base URL of the scraped site
+/// * `scraper` - Client to fetch pages with
+/// * `max_pages` - Pages read at most
+/// * `meter` - Meter to count the pages against, if any
+pub async fn fetch_completed_archive(
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    max_pages: u32,
+    mut meter: Option<&mut BandwidthMeter>,
+) -> Result<CompletedArchive, ScraperError> {
+    let mut archive = CompletedArchive::default();
+
+    for page in 1..=max_pages {
+        let url = endpoints::completed(base_url, page);
+        let fetched = match meter.as_deref_mut() {
+            Some(meter) if meter.exhausted() => {
+                warn!(
+                    "Daily crawler bandwidth quota reached, stopping the completed archive at page {}",
+                    page
+                );
+                break;
+            }
+            Some(meter) => meter.fetch(scraper, &url).await,
+            None => scraper.fetch_page(&url).await,
+        };
+        let result = match fetched {
+            Ok(result) => result,
+            Err(e) if page == 1 => return Err(e),
+            // Past the last page
+            Err(ScraperError::HttpError(404)) => break,
+            Err(e) => {
+                warn!(
+                    "Failed to fetch completed archive page {}: {}, stopping",
+                    page, e
+                );
+                break;
+            }
+        };
+
+        let listed = parse_completed_anime(&result.html);
+        let new: Vec<CompletedAnime> = listed
+            .into_iter()
+            .filter(|anime| !archive.anime.iter().any(|seen| seen.url == anime.url))
+            .collect();
+        if new.is_empty() {
+            break;
+        }
+        archive.anime.extend(new);
+        archive.pages = page;
+    }
+
+    Ok(archive)
+}
+
+/// Crawl the completed archive and save it
+///
+/// # Arguments
+/// * `daily_quota` - Bytes crawls may download per day, or `None` for no limit
+///
+/// # Returns
+/// * `Ok(CompletedArchive)` - The anime saved
+/// * `Err(CompletedArchiveError)` - The quota was already reached, the first
+///   page couldn't be fetched, or saving failed
+#[instrument(name = "crawl_completed", skip_all, fields(base_url = %base_url))]
+pub async fn crawl_completed_archive(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    daily_quota: Option<u64>,
+) -> Result<CompletedArchive, CompletedArchiveError> {
+    let mut meter = BandwidthMeter::load(pool, daily_quota).await;
+    if meter.exhausted() {
+        return Err(CompletedArchiveError::QuotaExhausted);
+    }
+    let archive =
+        fetch_completed_archive(base_url, scraper, MAX_COMPLETED_PAGES, Some(&mut meter)).await?;
+    save_completed_anime(pool, &archive.anime).await?;
+    info!(
+        "Saved {} completed anime from {} archive page(s)",
+        archive.anime.len(),
+        archive.pages
+    );
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraper::MockScraper;
+
+    const BASE: &str = "https://example.com";
+
+    fn page(slugs: &[&str]) -> String {
+        let articles: String = slugs
+            .iter()
+            .map(|slug| {
+                format!(
+                    r#"<article class="stylesix"><a itemprop="url" href="{}/{}/"></a>
+                    <h2 itemprop="headline"><a href="{}/{}/">{}</a></h2></article>"#,
+                    BASE, slug, BASE, slug, slug
+                )
+            })
+            .collect();
+        format!("<html><body>{}</body></html>", articles)
+    }
+
+    fn slugs(archive: &CompletedArchive) -> Vec<&str> {
+        archive.anime.iter().map(|a| a.slug.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_until_missing_page() {
+        let scraper = MockScraper::new()
+            .with_page(endpoints::completed(BASE, 1), page(&["a", "b"]))
+            .with_page(endpoints::completed(BASE, 2), page(&["c"]));
+
+        let archive = fetch_completed_archive(BASE, &scraper, 10, None)
+            .await
+            .unwrap();
+        assert_eq!(slugs(&archive), vec!["a", "b", "c"]);
+        assert_eq!(archive.pages, 2);
+        assert_eq!(
+            scraper.requests(),
+            vec![
+                endpoints::completed(BASE, 1),
+                endpoints::completed(BASE, 2),
+                endpoints::completed(BASE, 3),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stops_on_repeated_or_empty_page() {
+        // A site clamping the page number serves its last page again
+        let scraper = MockScraper::new()
+            .with_page(endpoints::completed(BASE, 1), page(&["a"]))
+            .with_page(endpoints::completed(BASE, 2), page(&["a"]));
+        let archive = fetch_completed_archive(BASE, &scraper, 10, None)
+            .await
+            .unwrap();
+        assert_eq!(slugs(&archive), vec!["a"]);
+        assert_eq!(archive.pages, 1);
+
+        let scraper = MockScraper::new()
+            .with_page(endpoints::completed(BASE, 1), page(&["a"]))
+            .with_page(endpoints::completed(BASE, 2), page(&[]));
+        let archive = fetch_completed_archive(BASE, &scraper, 10, None)
+            .await
+            .unwrap();
+        assert_eq!(archive.pages, 1);
+
+        let scraper =
+            MockScraper::new().with_page(endpoints::completed(BASE, 1), page(&["a", "b"]));
+        let archive = fetch_completed_archive(BASE, &scraper, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(scraper.requests().len(), 1);
+        assert_eq!(archive.anime.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_errors() {
+        let scraper = MockScraper::new().with_error(endpoints::completed(BASE, 1), 503);
+        assert!(matches!(
+            fetch_completed_archive(BASE, &scraper, 10, None).await,
+            Err(ScraperError::HttpError(503))
+        ));
+
+        // Later failures keep the pages read
+        let scraper = MockScraper::new()
+            .with_page(endpoints::completed(BASE, 1), page(&["a"]))
+            .with_error(endpoints::completed(BASE, 2), 500);
+        let archive = fetch_completed_archive(BASE, &scraper, 10, None)
+            .await
+            .unwrap();
+        assert_eq!(slugs(&archive), vec!["a"]);
+    }
+}
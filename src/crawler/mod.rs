@@ -0,0 +1,679 @@
+//! Crawler module for bulk scraping the anime catalog
+//!
+//! Walks every anime list page, then each anime detail page and its episodes,
+//! persisting metadata, episodes, and video sources as it goes. Used by both
+//! the synchronous `/api/crawler/run` endpoint and the background job queue.
+//!
+//! Crawl jobs also keep a report of the crawl (see [`report`]). Anime and
+//! episodes that fail are queued so [`retry_failed`] can pick them up
+//! without a full re-crawl. The completed anime archive is crawled on its
+//! own schedule (see [`completed`]). Crawls count the bytes they download
+//! and pause once the daily bandwidth quota is used up (see [`bandwidth`]).
+//! They also pause while the API is busy serving users (see
+//! [`backpressure`]).
+
+pub mod backpressure;
+pub mod bandwidth;
+pub mod completed;
+pub mod report;
+
+use sqlx::PgPool;
+use tracing::{error, info, instrument, warn};
+
+use crate::constants::endpoints;
+use crate::db::{
+    count_crawl_failures, delete_crawl_failure, list_crawl_failures, merge_anime,
+    record_anime_redirect, record_crawl_failure, save_anime_detail_with_episodes,
+    save_crawled_anime_batch, save_subtitle_tracks, save_video_sources, RepositoryResult,
+};
+use crate::models::{
+    CrawlError, CrawlFailureKind, CrawlReport, CrawlRequestKind, CrawlRetryResult, CrawledAnime,
+    CrawlerData,
+};
+use crate::parser::{parse_anime_detail, parse_anime_list, parse_episode_detail};
+use crate::scraper::{ScrapeClient, ScraperResult};
+use crate::video_servers::VideoServerRules;
+
+use backpressure::ApiLoad;
+use bandwidth::BandwidthMeter;
+use report::{fetch_error_kind, save_error_kind, CrawlRecorder};
+
+/// Maximum number of list pages visited in a single crawl
+pub const MAX_CRAWL_PAGES: u32 = 1000;
+
+/// Extract slug from a URL
+///
+/// Takes a URL like "https://x3.sokuja.uk/anime/one-piece-subtitle-indonesia/"
+/// and returns "one-piece-subtitle-indonesia"
+pub fn extract_slug_from_url(url: &str) -> String {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Slug and URL a page permanently moved to, if it's no longer at `slug`
+///
+/// Only moves to another anime page on the scraped site count: the target
+/// must be on the same host as `base_url` with a path of `/anime/<slug>/`.
+/// Anything else (another site, a search page, the home page) is ignored
+/// rather than saved as the anime's new home.
+pub fn moved_slug(result: &ScraperResult, base_url: &str, slug: &str) -> Option<(String, String)> {
+    let to = result.moved_to()?;
+    let target = reqwest::Url::parse(to).ok()?;
+    let base = reqwest::Url::parse(base_url).ok()?;
+    if target.host_str()? != base.host_str()? {
+        return None;
+    }
+
+    let path = target.path();
+    let moved = path.strip_prefix("/anime/")?.strip_suffix('/')?;
+    if moved.is_empty() || moved.contains('/') || moved == slug {
+        return None;
+    }
+    Some((moved.to_string(), to.to_string()))
+}
+
+/// Run a full crawl of the anime catalog
+///
+/// Iterates through all anime list pages, scrapes metadata, anime details,
+/// episodes, and video sources. Saves everything to the database. Individual
+/// failures are collected into `errors`, grouped by kind and slug or page,
+/// rather than aborting the crawl.
+///
+/// Runs in a `crawl` span with nested `crawl_page`, `crawl_anime`, and
+/// `crawl_episode` spans, so scraper fetches and database writes can be
+/// traced back to the page and slug they belong to.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `base_url` - Base URL of the scraped site
+/// * `scraper` - Client to fetch pages with
+/// * `servers` - Video server rules applied to saved sources
+/// * `daily_quota` - Bytes crawls may download per day, or `None` for no
+///   limit; the crawl stops early once the day's downloads reach it
+/// * `api_load` - API traffic the crawl pauses for
+///
+/// # Returns
+/// Totals and errors for the crawl
+pub async fn run_full_crawl(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    servers: &VideoServerRules,
+    daily_quota: Option<u64>,
+    api_load: &ApiLoad,
+) -> CrawlerData {
+    crawl_with_report(pool, base_url, scraper, servers, daily_quota, api_load)
+        .await
+        .0
+}
+
+/// Run a full crawl of the anime catalog and report on it
+///
+/// Same as [`run_full_crawl`], additionally returning a [`CrawlReport`] with
+/// per-page timings, new vs. updated counts, grouped errors, the slowest
+/// page fetches, and the bytes downloaded.
+///
+/// # Returns
+/// Totals and errors for the crawl, and its report
+#[instrument(name = "crawl", skip_all, fields(base_url = %base_url))]
+pub async fn crawl_with_report(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    servers: &VideoServerRules,
+    daily_quota: Option<u64>,
+    api_load: &ApiLoad,
+) -> (CrawlerData, CrawlReport) {
+    info!("Starting bulk crawler");
+
+    let mut recorder = CrawlRecorder::new()
+        .with_bandwidth(BandwidthMeter::load(pool, daily_quota).await)
+        .with_backpressure(api_load.clone());
+    let mut page: u32 = 1;
+
+    loop {
+        if recorder.quota_exhausted() {
+            break;
+        }
+        let timer = recorder.start_page(page);
+        match crawl_page(pool, base_url, scraper, servers, page, &mut recorder).await {
+            Some(anime_count) => recorder.finish_page(timer, anime_count),
+            None => break,
+        }
+
+        page += 1;
+
+        if page > MAX_CRAWL_PAGES {
+            info!("Reached page limit ({}), stopping crawler", MAX_CRAWL_PAGES);
+            break;
+        }
+    }
+
+    let (data, report) = recorder.finish();
+
+    info!(
+        "Crawler completed: {} anime, {} episodes, {} video sources, {} pages",
+        data.total_crawled, data.total_episodes, data.total_video_sources, data.pages_processed
+    );
+    info!(
+        "Changed since last crawl: {}/{} anime, {}/{} episodes, {}/{} video source sets",
+        data.anime_changes.changed,
+        data.anime_changes.changed + data.anime_changes.unchanged,
+        data.episode_changes.changed,
+        data.episode_changes.changed + data.episode_changes.unchanged,
+        data.video_source_changes.changed,
+        data.video_source_changes.changed + data.video_source_changes.unchanged
+    );
+
+    (data, report)
+}
+
+/// Crawl one list page and every anime on it
+///
+/// # Returns
+/// Number of anime listed on the page, or `None` once a page lists no anime
+/// and the crawl should stop
+#[instrument(skip(pool, base_url, scraper, servers, recorder))]
+async fn crawl_page(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    servers: &VideoServerRules,
+    page: u32,
+    recorder: &mut CrawlRecorder,
+) -> Option<i32> {
+    info!("Crawling page {}", page);
+    let url = endpoints::ListUrl::new().page(page).build(base_url);
+
+    let anime_list = match recorder.fetch(scraper, CrawlRequestKind::List, &url).await {
+        Ok(result) => {
+            let items = parse_anime_list(&result.html);
+            if items.is_empty() {
+                info!("No more anime found on page {}, stopping crawler", page);
+                return None;
+            }
+            items
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to fetch page {}: {}", page, e);
+            error!("{}", error_msg);
+            recorder.record_error(CrawlError::for_page(
+                fetch_error_kind("fetch list page", &e),
+                page,
+                error_msg,
+            ));
+            return Some(0);
+        }
+    };
+
+    recorder.data.pages_processed += 1;
+
+    let crawled_anime: Vec<CrawledAnime> = anime_list
+        .iter()
+        .map(|item| CrawledAnime {
+            slug: extract_slug_from_url(&item.url),
+            title: item.title.clone(),
+            url: item.url.clone(),
+            thumbnail: item.thumbnail.clone(),
+            status: item.status.clone(),
+            anime_type: item.anime_type.clone(),
+            episode_status: item.episode_status.clone(),
+        })
+        .collect();
+
+    match save_crawled_anime_batch(pool, &crawled_anime).await {
+        Ok(changes) => {
+            recorder.data.total_crawled += crawled_anime.len() as i32;
+            recorder.list_entries.add(changes);
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to save crawled anime batch on page {}: {}", page, e);
+            error!("{}", error_msg);
+            recorder.record_error(CrawlError::for_page(
+                save_error_kind("save list page"),
+                page,
+                error_msg,
+            ));
+        }
+    }
+
+    for anime in &crawled_anime {
+        if recorder.quota_exhausted() {
+            break;
+        }
+        crawl_anime(pool, base_url, scraper, servers, &anime.slug, recorder).await;
+    }
+
+    Some(crawled_anime.len() as i32)
+}
+
+/// Crawl an anime's detail page and each of its episodes
+///
+/// A failed fetch or save is queued for [`retry_failed`]; so are failed
+/// episodes, separately.
+///
+/// When the page permanently redirects to another slug, the anime moves
+/// there: the redirect is recorded as an alias, the detail is saved under
+/// the new slug, and anything stored under the old slug is merged into it.
+///
+/// # Returns
+/// Whether the anime itself was fetched and saved
+#[instrument(skip(pool, base_url, scraper, servers, recorder))]
+async fn crawl_anime(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    servers: &VideoServerRules,
+    slug: &str,
+    recorder: &mut CrawlRecorder,
+) -> bool {
+    let url = endpoints::anime(base_url, slug);
+    let (detail, moved_to) = match recorder.fetch(scraper, CrawlRequestKind::Anime, &url).await {
+        Ok(result) => {
+            let detail = parse_anime_detail(&result.html);
+            if detail.title.is_empty() {
+                warn!("Empty anime detail for slug: {}", slug);
+                return true;
+            }
+            (detail, moved_slug(&result, base_url, slug))
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to fetch anime detail for {}: {}", slug, e);
+            warn!("{}", error_msg);
+            queue_retry(pool, CrawlFailureKind::Anime, slug, &url, &error_msg).await;
+            recorder.record_error(CrawlError::for_slug(
+                fetch_error_kind("fetch anime", &e),
+                slug,
+                error_msg,
+            ));
+            return false;
+        }
+    };
+
+    let save_slug = match &moved_to {
+        Some((new_slug, new_url)) => {
+            info!("Anime {} moved to {}", slug, new_slug);
+            if let Err(e) = record_anime_redirect(pool, slug, new_slug, new_url).await {
+                warn!("Failed to record redirect of {}: {}", slug, e);
+            }
+            new_slug.as_str()
+        }
+        None => slug,
+    };
+
+    let saved = match save_anime_detail_with_episodes(pool, save_slug, &detail).await {
+        Ok(changes) => {
+            recorder.data.total_episodes += detail.episodes.len() as i32;
+            recorder.data.anime_changes.record_write(changes.detail);
+            recorder.data.episode_changes.add(changes.episodes);
+            true
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to save anime detail for {}: {}", save_slug, e);
+            warn!("{}", error_msg);
+            queue_retry(pool, CrawlFailureKind::Anime, slug, &url, &error_msg).await;
+            recorder.record_error(CrawlError::for_slug(
+                save_error_kind("save anime"),
+                slug,
+                error_msg,
+            ));
+            false
+        }
+    };
+
+    if saved && save_slug != slug {
+        match merge_anime(pool, slug, save_slug).await {
+            Ok(Some(merged)) if merged.episodes > 0 => {
+                info!(
+                    "Moved {} episodes of {} to {}",
+                    merged.episodes, slug, save_slug
+                );
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to merge {} into {}: {}", slug, save_slug, e),
+        }
+    }
+
+    for episode in &detail.episodes {
+        if recorder.quota_exhausted() {
+            break;
+        }
+        let episode_slug = extract_slug_from_url(&episode.url);
+        crawl_episode(
+            pool,
+            base_url,
+            scraper,
+            servers,
+            &episode_slug,
+            &episode.url,
+            recorder,
+        )
+        .await;
+    }
+
+    saved
+}
+
+/// Crawl an episode page and save its video sources
+///
+/// A failed fetch or save is queued for [`retry_failed`].
+///
+/// # Returns
+/// Whether the episode was fetched and its video sources saved
+#[instrument(skip(pool, base_url, scraper, servers, episode_url, recorder), fields(slug = %episode_slug))]
+async fn crawl_episode(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    servers: &VideoServerRules,
+    episode_slug: &str,
+    episode_url: &str,
+    recorder: &mut CrawlRecorder,
+) -> bool {
+    let url = endpoints::episode(base_url, episode_slug);
+    let result = match recorder
+        .fetch(scraper, CrawlRequestKind::Episode, &url)
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            let error_msg = format!("Failed to fetch episode {}: {}", episode_slug, e);
+            warn!("{}", error_msg);
+            queue_retry(
+                pool,
+                CrawlFailureKind::Episode,
+                episode_slug,
+                episode_url,
+                &error_msg,
+            )
+            .await;
+            recorder.record_error(CrawlError::for_slug(
+                fetch_error_kind("fetch episode", &e),
+                episode_slug,
+                error_msg,
+            ));
+            return false;
+        }
+    };
+
+    let episode_detail = servers.apply(parse_episode_detail(&result.html));
+    if let Err(e) = save_subtitle_tracks(pool, episode_url, &episode_detail.subtitles).await {
+        warn!("Failed to save subtitle tracks for {}: {}", episode_slug, e);
+    }
+    if episode_detail.sources.is_empty() {
+        return true;
+    }
+
+    match save_video_sources(pool, episode_url, &episode_detail.sources).await {
+        Ok(outcome) => {
+            recorder.data.total_video_sources += episode_detail.sources.len() as i32;
+            recorder.data.video_source_changes.record_write(outcome);
+            true
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to save video sources for {}: {}", episode_slug, e);
+            warn!("{}", error_msg);
+            queue_retry(
+                pool,
+                CrawlFailureKind::Episode,
+                episode_slug,
+                episode_url,
+                &error_msg,
+            )
+            .await;
+            recorder.record_error(CrawlError::for_slug(
+                save_error_kind("save video sources"),
+                episode_slug,
+                error_msg,
+            ));
+            false
+        }
+    }
+}
+
+/// Queue a failed anime or episode for [`retry_failed`]
+async fn queue_retry(pool: &PgPool, kind: CrawlFailureKind, slug: &str, url: &str, error: &str) {
+    if let Err(e) = record_crawl_failure(pool, kind, slug, url, error).await {
+        warn!(
+            "Failed to queue {} {} for retry: {}",
+            kind.as_str(),
+            slug,
+            e
+        );
+    }
+}
+
+/// Retry anime and episodes whose crawl failed
+///
+/// Processes only the queued failures, least recently attempted first,
+/// instead of crawling the whole catalog again. Anime are retried along
+/// with their episodes. Targets that succeed leave the queue; the others
+/// stay queued with their attempt count raised.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `base_url` - Base URL of the scraped site
+/// * `scraper` - Client to fetch pages with
+/// * `servers` - Video server rules applied to saved sources
+/// * `limit` - Maximum number of failures to retry
+/// * `daily_quota` - Bytes crawls may download per day, or `None` for no
+///   limit; failures not retried once the day's downloads reach it stay
+///   queued
+/// * `api_load` - API traffic the retries pause for
+///
+/// # Returns
+/// How many failures were retried and recovered, and the crawl totals
+#[instrument(name = "crawl_retry", skip(pool, base_url, scraper, servers, api_load))]
+pub async fn retry_failed(
+    pool: &PgPool,
+    base_url: &str,
+    scraper: &dyn ScrapeClient,
+    servers: &VideoServerRules,
+    limit: i64,
+    daily_quota: Option<u64>,
+    api_load: &ApiLoad,
+) -> RepositoryResult<CrawlRetryResult> {
+    let failures = list_crawl_failures(pool, limit).await?;
+    info!("Retrying {} failed crawl targets", failures.len());
+
+    let mut recorder = CrawlRecorder::new()
+        .with_bandwidth(BandwidthMeter::load(pool, daily_quota).await)
+        .with_backpressure(api_load.clone());
+    let mut recovered = 0;
+
+    for failure in &failures {
+        if recorder.quota_exhausted() {
+            break;
+        }
+        let succeeded = match failure.kind {
+            CrawlFailureKind::Anime => {
+                crawl_anime(
+                    pool,
+                    base_url,
+                    scraper,
+                    servers,
+                    &failure.slug,
+                    &mut recorder,
+                )
+                .await
+            }
+            CrawlFailureKind::Episode => {
+                crawl_episode(
+                    pool,
+                    base_url,
+                    scraper,
+                    servers,
+                    &failure.slug,
+                    &failure.url,
+                    &mut recorder,
+                )
+                .await
+            }
+        };
+
+        if succeeded {
+            delete_crawl_failure(pool, failure.kind, &failure.slug).await?;
+            recovered += 1;
+        }
+    }
+
+    let (crawl, _) = recorder.finish();
+    let remaining = count_crawl_failures(pool).await?;
+    info!(
+        "Retried {} failed crawl targets: {} recovered, {} still queued",
+        failures.len(),
+        recovered,
+        remaining
+    );
+
+    Ok(CrawlRetryResult {
+        attempted: failures.len() as i32,
+        recovered,
+        remaining,
+        crawl,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_slug_from_url() {
+        assert_eq!(
+            extract_slug_from_url("https://x3.sokuja.uk/anime/one-piece-subtitle-indonesia/"),
+            "one-piece-subtitle-indonesia"
+        );
+        assert_eq!(
+            extract_slug_from_url("https://x3.sokuja.uk/naruto-episode-1"),
+            "naruto-episode-1"
+        );
+        assert_eq!(extract_slug_from_url(""), "");
+    }
+
+    #[test]
+    fn test_moved_slug() {
+        use crate::scraper::Redirect;
+
+        let base_url = "https://example.com";
+        let result = |status, to: &str| ScraperResult {
+            html: String::new(),
+            status: 200,
+            redirects: vec![Redirect {
+                from: "https://example.com/anime/old/".to_string(),
+                to: to.to_string(),
+                status,
+            }],
+            final_url: to.to_string(),
+            wire_bytes: 0,
+        };
+        let new = "https://example.com/anime/new/";
+        assert_eq!(
+            moved_slug(&result(301, new), base_url, "old"),
+            Some(("new".to_string(), new.to_string()))
+        );
+        assert_eq!(moved_slug(&result(302, new), base_url, "old"), None);
+        assert_eq!(moved_slug(&result(301, new), base_url, "new"), None);
+
+        // Moves off the site or away from an anime page are ignored
+        for to in [
+            "https://evil.example.net/anime/new/",
+            "https://example.com.evil.net/anime/new/",
+            "https://example.com/",
+            "https://example.com/?s=old",
+            "https://example.com/new-episode-1/",
+            "https://example.com/anime/new/extra/",
+            "https://example.com/anime//",
+        ] {
+            assert_eq!(
+                moved_slug(&result(301, to), base_url, "old"),
+                None,
+                "{}",
+                to
+            );
+        }
+    }
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_full_crawl_with_mock_scraper() {
+        use crate::config::CrawlerBackpressureConfig;
+        use crate::db::delete_crawled_anime;
+        use crate::scraper::MockScraper;
+
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let base_url = "https://example.com";
+        let page_1 = endpoints::ListUrl::new().page(1).build(base_url);
+        let page_2 = endpoints::ListUrl::new().page(2).build(base_url);
+        let scraper = MockScraper::new()
+            .with_fixture(&page_1, "fixtures/parser/anime_list/page-1.html")
+            .unwrap()
+            .with_page(&page_2, "<html><body></body></html>");
+        let no_backpressure = ApiLoad::new(CrawlerBackpressureConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        let listed = parse_anime_list(
+            &std::fs::read_to_string("fixtures/parser/anime_list/page-1.html").unwrap(),
+        );
+
+        let data = run_full_crawl(
+            &pool,
+            base_url,
+            &scraper,
+            &VideoServerRules::default(),
+            None,
+            &no_backpressure,
+        )
+        .await;
+
+        // Detail pages aren't served, so each listed anime is an error
+        assert_eq!(data.pages_processed, 1);
+        assert_eq!(data.total_crawled, listed.len() as i32);
+        assert_eq!(data.errors.len(), listed.len());
+        let requests = scraper.requests();
+        assert_eq!(requests.first(), Some(&page_1));
+        assert_eq!(requests.last(), Some(&page_2));
+
+        // The failed anime are queued, and retrying them fetches only those
+        let failures = list_crawl_failures(&pool, 10_000).await.unwrap();
+        let slugs = listed
+            .iter()
+            .map(|item| extract_slug_from_url(&item.url))
+            .collect::<Vec<_>>();
+        let queued = failures
+            .iter()
+            .filter(|failure| failure.kind == CrawlFailureKind::Anime)
+            .filter(|failure| slugs.contains(&failure.slug))
+            .count();
+        assert_eq!(queued, listed.len());
+
+        let retry = retry_failed(
+            &pool,
+            base_url,
+            &scraper,
+            &VideoServerRules::default(),
+            10_000,
+            None,
+            &no_backpressure,
+        )
+        .await
+        .unwrap();
+        assert_eq!(retry.recovered, 0);
+        assert!(retry.remaining >= listed.len() as i64);
+        assert!(!scraper.requests()[requests.len()..].contains(&page_1));
+
+        // Clean up
+        for slug in &slugs {
+            let _ = delete_crawled_anime(&pool, slug).await;
+            let _ = delete_crawl_failure(&pool, CrawlFailureKind::Anime, slug).await;
+        }
+    }
+}
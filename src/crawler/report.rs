@@ -0,0 +1,530 @@
+//! Crawl reports
+//!
+//! A [`CrawlRecorder`] follows a crawl as it runs: it keeps the usual
+//! [`CrawlerData`] totals and, on top of them, per-page timings, the slowest
+//! page fetches, bytes downloaded, and errors grouped by what failed and how.
+//! With a [`BandwidthMeter`] it also adds the downloads to the day's crawler
+//! bandwidth and tells the crawl when the daily quota is used up. With an
+//! [`ApiLoad`] it waits before each fetch while the API is busy, adding up
+//! the time paused. Finishing it
+//! yields a [`CrawlReport`] with a human-readable summary, which crawl jobs
+//! persist next to their result.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+use crate::models::{
+    ChangeCount, CrawlError, CrawlErrorGroup, CrawlPageTiming, CrawlReport, CrawlRequestKind,
+    CrawlRequestTiming, CrawlerData,
+};
+use crate::scraper::{ScrapeClient, ScraperError, ScraperResult};
+
+use super::backpressure::ApiLoad;
+use super::bandwidth::BandwidthMeter;
+
+/// Number of slowest fetches kept in a report
+pub const SLOWEST_REQUESTS: usize = 20;
+
+/// Example messages kept per error group
+const ERROR_EXAMPLES: usize = 3;
+
+/// Error groups listed in the summary
+const SUMMARY_ERROR_GROUPS: usize = 5;
+
+/// Slowest fetches listed in the summary
+const SUMMARY_SLOWEST_REQUESTS: usize = 5;
+
+/// Cause of a failed write, used in error kinds
+const DATABASE_ERROR: &str = "database error";
+
+/// Kind of a failed fetch, e.g. "fetch episode: HTTP 404"
+///
+/// # Arguments
+/// * `stage` - What was being fetched
+/// * `err` - How it failed
+pub fn fetch_error_kind(stage: &str, err: &ScraperError) -> String {
+    let cause = match err {
+        ScraperError::NetworkError(_) => "network error".to_string(),
+        ScraperError::HttpError(status) => format!("HTTP {}", status),
+        ScraperError::ResponseError(_) => "response error".to_string(),
+        ScraperError::RateLimited => "rate limited".to_string(),
+        ScraperError::BodyTooLarge(_) => "body too large".to_string(),
+        ScraperError::Timeout(_) => "timeout".to_string(),
+        ScraperError::TooManyRedirects(_) => "too many redirects".to_string(),
+        ScraperError::CrossHostRedirect(_) => "cross-host redirect".to_string(),
+    };
+    format!("{}: {}", stage, cause)
+}
+
+/// Kind of a failed database write, e.g. "save anime: database error"
+pub fn save_error_kind(stage: &str) -> String {
+    format!("{}: {}", stage, DATABASE_ERROR)
+}
+
+/// Milliseconds in a duration, saturating
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+/// Start of a list page, see [`CrawlRecorder::start_page`]
+#[derive(Debug)]
+pub struct PageTimer {
+    page: u32,
+    started: Instant,
+    errors_before: i32,
+}
+
+/// Collects the totals and report of a running crawl
+#[derive(Debug)]
+pub struct CrawlRecorder {
+    /// Totals of the crawl so far
+    pub data: CrawlerData,
+    /// Anime list entries written so far
+    pub list_entries: ChangeCount,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    pages: Vec<CrawlPageTiming>,
+    requests: Vec<CrawlRequestTiming>,
+    error_groups: BTreeMap<String, CrawlErrorGroup>,
+    bytes_downloaded: u64,
+    bandwidth: Option<BandwidthMeter>,
+    quota_exhausted: bool,
+    api_load: Option<ApiLoad>,
+    backpressure_paused: Duration,
+}
+
+impl Default for CrawlRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrawlRecorder {
+    /// Start recording a crawl
+    pub fn new() -> Self {
+        Self {
+            data: CrawlerData::default(),
+            list_entries: ChangeCount::default(),
+            started_at: Utc::now(),
+            started: Instant::now(),
+            pages: Vec::new(),
+            requests: Vec::new(),
+            error_groups: BTreeMap::new(),
+            bytes_downloaded: 0,
+            bandwidth: None,
+            quota_exhausted: false,
+            api_load: None,
+            backpressure_paused: Duration::ZERO,
+        }
+    }
+
+    /// Add downloads to the day's crawler bandwidth, checking the quota
+    pub fn with_bandwidth(mut self, meter: BandwidthMeter) -> Self {
+        self.bandwidth = Some(meter);
+        self
+    }
+
+    /// Wait before each fetch while the API is busy
+    pub fn with_backpressure(mut self, api_load: ApiLoad) -> Self {
+        self.api_load = Some(api_load);
+        self
+    }
+
+    /// Whether the daily bandwidth quota is used up
+    ///
+    /// Once it is, the crawl should start nothing new; the report notes that
+    /// it stopped early.
+    pub fn quota_exhausted(&mut self) -> bool {
+        if !self.quota_exhausted && self.bandwidth.as_ref().is_some_and(|m| m.exhausted()) {
+            warn!("Daily crawler bandwidth quota reached, pausing the crawl");
+            self.quota_exhausted = true;
+        }
+        self.quota_exhausted
+    }
+
+    /// Fetch a page through `scraper`, recording how long it took
+    ///
+    /// Time spent waiting for the API to go idle isn't part of the fetch's
+    /// timing.
+    pub async fn fetch(
+        &mut self,
+        scraper: &dyn ScrapeClient,
+        kind: CrawlRequestKind,
+        url: &str,
+    ) -> Result<ScraperResult, ScraperError> {
+        if let Some(api_load) = &self.api_load {
+            self.backpressure_paused += api_load.wait_for_turn().await;
+        }
+        let started = Instant::now();
+        let result = scraper.fetch_page(url).await;
+        self.record_request(url, kind, started.elapsed(), result.is_ok());
+        if let Ok(page) = &result {
+            let bytes = page.wire_bytes;
+            self.bytes_downloaded += bytes;
+            if let Some(meter) = &mut self.bandwidth {
+                meter.record(bytes).await;
+            }
+        }
+        result
+    }
+
+    /// Record a page fetch
+    pub fn record_request(
+        &mut self,
+        url: &str,
+        kind: CrawlRequestKind,
+        duration: Duration,
+        success: bool,
+    ) {
+        self.requests.push(CrawlRequestTiming {
+            url: url.to_string(),
+            kind,
+            duration_ms: millis(duration),
+            success,
+        });
+        if self.requests.len() > 2 * SLOWEST_REQUESTS {
+            self.trim_requests();
+        }
+    }
+
+    /// Keep only the slowest fetches, slowest first
+    fn trim_requests(&mut self) {
+        self.requests
+            .sort_by_key(|request| Reverse(request.duration_ms));
+        self.requests.truncate(SLOWEST_REQUESTS);
+    }
+
+    /// Record an error in the crawl totals and the report's error groups
+    ///
+    /// Build its kind with [`fetch_error_kind`] or [`save_error_kind`].
+    pub fn record_error(&mut self, error: CrawlError) {
+        let group = self
+            .error_groups
+            .entry(error.kind.clone())
+            .or_insert_with(|| CrawlErrorGroup {
+                kind: error.kind.clone(),
+                count: 0,
+                examples: Vec::new(),
+            });
+        group.count += error.count;
+        if group.examples.len() < ERROR_EXAMPLES {
+            group.examples.push(error.message.clone());
+        }
+
+        self.data.record_error(error);
+    }
+
+    /// Start timing a list page
+    pub fn start_page(&self, page: u32) -> PageTimer {
+        PageTimer {
+            page,
+            started: Instant::now(),
+            errors_before: self.data.total_errors,
+        }
+    }
+
+    /// Finish timing a list page
+    ///
+    /// # Arguments
+    /// * `timer` - Returned by [`start_page`](Self::start_page)
+    /// * `anime_count` - Anime listed on the page
+    pub fn finish_page(&mut self, timer: PageTimer, anime_count: i32) {
+        self.pages.push(CrawlPageTiming {
+            page: timer.page,
+            duration_ms: millis(timer.started.elapsed()),
+            anime_count,
+            error_count: self.data.total_errors - timer.errors_before,
+        });
+    }
+
+    /// Finish the crawl
+    ///
+    /// # Returns
+    /// The crawl totals and its report
+    pub fn finish(mut self) -> (CrawlerData, CrawlReport) {
+        self.trim_requests();
+
+        let mut error_groups = self.error_groups.into_values().collect::<Vec<_>>();
+        error_groups.sort_by_key(|group| Reverse(group.count));
+
+        let mut report = CrawlReport {
+            started_at: self.started_at.to_rfc3339(),
+            finished_at: Utc::now().to_rfc3339(),
+            duration_ms: millis(self.started.elapsed()),
+            pages_processed: self.data.pages_processed,
+            list_entries: self.list_entries,
+            anime: self.data.anime_changes,
+            episodes: self.data.episode_changes,
+            video_sources: self.data.video_source_changes,
+            error_count: self.data.total_errors,
+            error_groups,
+            pages: self.pages,
+            slowest_requests: self.requests,
+            bytes_downloaded: self.bytes_downloaded,
+            quota_exhausted: self.quota_exhausted,
+            backpressure_paused_ms: millis(self.backpressure_paused),
+            summary: String::new(),
+        };
+        report.summary = render_summary(&report);
+
+        (self.data, report)
+    }
+}
+
+/// One line of new/updated/unchanged counts
+fn change_line(summary: &mut String, label: &str, count: &ChangeCount) {
+    let _ = writeln!(
+        summary,
+        "  {:<14} {} new, {} updated, {} unchanged",
+        format!("{}:", label),
+        count.inserted,
+        count.updated(),
+        count.unchanged
+    );
+}
+
+/// Render a report as plain text for humans
+pub fn render_summary(report: &CrawlReport) -> String {
+    let mut summary = String::new();
+    let _ = writeln!(
+        summary,
+        "Crawl started {}, finished {} ({:.1}s)",
+        report.started_at,
+        report.finished_at,
+        report.duration_ms as f64 / 1000.0
+    );
+    let _ = writeln!(summary, "Pages processed: {}", report.pages_processed);
+    let _ = writeln!(
+        summary,
+        "Downloaded: {:.1} MB",
+        report.bytes_downloaded as f64 / 1_000_000.0
+    );
+    if report.quota_exhausted {
+        summary.push_str("Stopped early: daily bandwidth quota reached\n");
+    }
+    if report.backpressure_paused_ms > 0 {
+        let _ = writeln!(
+            summary,
+            "Paused for API traffic: {:.1}s",
+            report.backpressure_paused_ms as f64 / 1000.0
+        );
+    }
+
+    summary.push_str("\nChanges:\n");
+    change_line(&mut summary, "List entries", &report.list_entries);
+    change_line(&mut summary, "Anime", &report.anime);
+    change_line(&mut summary, "Episodes", &report.episodes);
+    change_line(&mut summary, "Video sources", &report.video_sources);
+
+    if let Some(slowest) = report.pages.iter().max_by_key(|page| page.duration_ms) {
+        let total: u64 = report.pages.iter().map(|page| page.duration_ms).sum();
+        let _ = writeln!(
+            summary,
+            "\nPages: {} timed, {} ms on average, slowest was page {} ({} ms)",
+            report.pages.len(),
+            total / report.pages.len() as u64,
+            slowest.page,
+            slowest.duration_ms
+        );
+    }
+
+    let _ = writeln!(summary, "\nErrors: {}", report.error_count);
+    for group in report.error_groups.iter().take(SUMMARY_ERROR_GROUPS) {
+        let _ = writeln!(summary, "  {:>5}  {}", group.count, group.kind);
+    }
+    if report.error_groups.len() > SUMMARY_ERROR_GROUPS {
+        let _ = writeln!(
+            summary,
+            "  ... and {} more kinds",
+            report.error_groups.len() - SUMMARY_ERROR_GROUPS
+        );
+    }
+
+    if !report.slowest_requests.is_empty() {
+        summary.push_str("\nSlowest requests:\n");
+        for request in report
+            .slowest_requests
+            .iter()
+            .take(SUMMARY_SLOWEST_REQUESTS)
+        {
+            let _ = writeln!(
+                summary,
+                "  {:>6} ms  {}{}",
+                request.duration_ms,
+                request.url,
+                if request.success { "" } else { " (failed)" }
+            );
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_groups_errors() {
+        let mut recorder = CrawlRecorder::new();
+        let timer = recorder.start_page(1);
+        for i in 0..5 {
+            recorder.record_error(CrawlError::for_slug(
+                fetch_error_kind("fetch episode", &ScraperError::HttpError(404)),
+                &format!("ep-{}", i),
+                format!("Failed to fetch episode ep-{}", i),
+            ));
+        }
+        recorder.record_error(CrawlError::for_slug(
+            save_error_kind("save anime"),
+            "naruto",
+            "Failed to save anime detail for naruto",
+        ));
+        recorder.finish_page(timer, 12);
+        for _ in 0..100 {
+            recorder.record_error(CrawlError::for_slug(
+                fetch_error_kind("fetch anime", &ScraperError::RateLimited),
+                "one-piece",
+                "Failed to fetch anime detail for one-piece",
+            ));
+        }
+
+        let (data, report) = recorder.finish();
+        assert_eq!(data.total_errors, 106);
+        assert_eq!(data.errors.len(), 7);
+        assert_eq!(report.error_count, 106);
+
+        let kinds = report
+            .error_groups
+            .iter()
+            .map(|group| (group.kind.as_str(), group.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                ("fetch anime: rate limited", 100),
+                ("fetch episode: HTTP 404", 5),
+                ("save anime: database error", 1),
+            ]
+        );
+        assert_eq!(report.error_groups[1].examples.len(), ERROR_EXAMPLES);
+        assert_eq!(report.pages.len(), 1);
+        assert_eq!(report.pages[0].anime_count, 12);
+        assert_eq!(report.pages[0].error_count, 6);
+    }
+
+    #[test]
+    fn test_recorder_keeps_slowest_requests() {
+        let mut recorder = CrawlRecorder::new();
+        for ms in 0..100 {
+            recorder.record_request(
+                &format!("https://example.com/{}", ms),
+                CrawlRequestKind::Episode,
+                Duration::from_millis(ms),
+                ms % 10 != 0,
+            );
+        }
+
+        let (_, report) = recorder.finish();
+        assert_eq!(report.slowest_requests.len(), SLOWEST_REQUESTS);
+        assert_eq!(report.slowest_requests[0].duration_ms, 99);
+        assert_eq!(
+            report.slowest_requests[SLOWEST_REQUESTS - 1].duration_ms,
+            80
+        );
+    }
+
+    #[test]
+    fn test_render_summary() {
+        let mut report = CrawlReport {
+            duration_ms: 61_500,
+            pages_processed: 2,
+            anime: ChangeCount {
+                changed: 5,
+                unchanged: 10,
+                inserted: 3,
+            },
+            error_count: 7,
+            error_groups: vec![CrawlErrorGroup {
+                kind: "fetch episode: HTTP 404".to_string(),
+                count: 7,
+                examples: Vec::new(),
+            }],
+            pages: vec![
+                CrawlPageTiming {
+                    page: 1,
+                    duration_ms: 1000,
+                    anime_count: 20,
+                    error_count: 0,
+                },
+                CrawlPageTiming {
+                    page: 2,
+                    duration_ms: 3000,
+                    anime_count: 20,
+                    error_count: 7,
+                },
+            ],
+            slowest_requests: vec![CrawlRequestTiming {
+                url: "https://example.com/slow".to_string(),
+                kind: CrawlRequestKind::Anime,
+                duration_ms: 2500,
+                success: false,
+            }],
+            bytes_downloaded: 12_345_678,
+            ..Default::default()
+        };
+        report.summary = render_summary(&report);
+
+        assert!(report.summary.contains("(61.5s)"));
+        assert!(report
+            .summary
+            .contains("Anime:         3 new, 2 updated, 10 unchanged"));
+        assert!(report
+            .summary
+            .contains("2000 ms on average, slowest was page 2 (3000 ms)"));
+        assert!(report
+            .summary
+            .contains("Errors: 7\n      7  fetch episode: HTTP 404"));
+        assert!(report
+            .summary
+            .contains("2500 ms  https://example.com/slow (failed)"));
+        assert!(report.summary.contains("Downloaded: 12.3 MB"));
+        assert!(!report.summary.contains("Stopped early"));
+        assert!(!report.summary.contains("Paused for API traffic"));
+
+        report.quota_exhausted = true;
+        assert!(render_summary(&report).contains("Stopped early: daily bandwidth quota reached"));
+        report.backpressure_paused_ms = 4200;
+        assert!(render_summary(&report).contains("Paused for API traffic: 4.2s"));
+    }
+
+    #[tokio::test]
+    async fn test_recorder_counts_bytes_downloaded() {
+        use crate::scraper::MockScraper;
+
+        let scraper = MockScraper::new()
+            .with_page("https://example.com/a", "x".repeat(1000))
+            .with_page("https://example.com/b", "x".repeat(500));
+        let mut recorder = CrawlRecorder::new();
+        for url in ["https://example.com/a", "https://example.com/b"] {
+            recorder
+                .fetch(&scraper, CrawlRequestKind::Anime, url)
+                .await
+                .unwrap();
+        }
+        let _ = recorder
+            .fetch(&scraper, CrawlRequestKind::Anime, "https://example.com/c")
+            .await;
+
+        // Without a meter there is no quota to reach
+        assert!(!recorder.quota_exhausted());
+        let (_, report) = recorder.finish();
+        assert_eq!(report.bytes_downloaded, 1500);
+        assert!(!report.quota_exhausted);
+    }
+}
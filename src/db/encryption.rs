@@ -0,0 +1,374 @@
+//! Application-level encryption of sensitive user columns
+//!
+//! Email addresses and Google account IDs, and the recipients and contents of
+//! queued emails, are stored encrypted with AES-256-GCM when data encryption
+//! keys are configured. Stored values look
+//! like `enc:<key id>:<base64 nonce, ciphertext, and tag>`; the column name
+//! is bound as associated data, so a ciphertext copied into another column
+//! fails to decrypt. Several keys can be configured for rotation: new values
+//! are encrypted with the first key, and values encrypted with any configured
+//! key decrypt. The `encrypt-user-data` maintenance action re-encrypts rows
+//! still in plaintext or under an older key.
+//!
+//! Random nonces make ciphertexts unsearchable, so each encrypted column has
+//! a blind index next to it: an HMAC-SHA256 of the plaintext under a separate
+//! index key. Lookups and unique constraints use the blind index; changing
+//! the index key means running the backfill again before lookups work. Rows
+//! stored in plaintext before encryption was switched on are indexed at
+//! startup, so their addresses can't be signed up again (see
+//! [`backfill_blind_indexes`](super::backfill_blind_indexes)).
+//!
+//! The repository seals values on write and opens them on read through
+//! [`seal`], [`open`], and [`blind_index`], which use the cipher installed at
+//! startup (see [`install`]). Without one, values are stored as plaintext and
+//! plaintext values are read back unchanged, so encryption can be switched on
+//! for an existing database.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use sha2::Sha256;
+use thiserror::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Prefix of encrypted column values
+pub const CIPHERTEXT_PREFIX: &str = "enc:";
+
+/// Length of an AES-256 key in bytes
+pub const KEY_LEN: usize = 32;
+
+/// Column holding the user's email address
+pub const FIELD_EMAIL: &str = "email";
+
+/// Column holding the user's Google account ID
+pub const FIELD_GOOGLE_ID: &str = "google_id";
+
+/// Column holding the recipient of an email delivery
+pub const FIELD_RECIPIENT: &str = "recipient";
+
+/// Column holding the serialized message of an email delivery
+pub const FIELD_EMAIL_PAYLOAD: &str = "payload";
+
+/// Encryption errors
+#[derive(Debug, Error, PartialEq)]
+pub enum EncryptionError {
+    #[error("Invalid encryption key {0}: expected {KEY_LEN} base64-encoded bytes")]
+    InvalidKey(String),
+
+    #[error("No data encryption keys are configured")]
+    NotConfigured,
+
+    #[error("Unknown encryption key: {0}")]
+    UnknownKey(String),
+
+    #[error("Malformed encrypted value")]
+    Malformed,
+
+    #[error("Encrypted value failed authentication")]
+    Invalid,
+}
+
+/// A data encryption key
+#[derive(Clone)]
+pub struct EncryptionKey {
+    /// Key ID stored with each ciphertext
+    pub id: String,
+    key: [u8; KEY_LEN],
+}
+
+impl EncryptionKey {
+    /// Create a key from raw bytes
+    pub fn new(id: impl Into<String>, key: [u8; KEY_LEN]) -> Self {
+        Self { id: id.into(), key }
+    }
+
+    fn aead(&self) -> LessSafeKey {
+        LessSafeKey::new(
+            UnboundKey::new(&AES_256_GCM, &self.key).expect("AES-256 keys are 32 bytes"),
+        )
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parse encryption keys from `id:base64key` pairs separated by commas
+///
+/// The first key is used for encryption. Key IDs may not contain `:`.
+pub fn parse_encryption_keys(value: &str) -> Result<Vec<EncryptionKey>, EncryptionError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| EncryptionError::InvalidKey(entry.to_string()))?;
+            let id = id.trim();
+            let key = STANDARD
+                .decode(key.trim())
+                .ok()
+                .and_then(|key| <[u8; KEY_LEN]>::try_from(key).ok())
+                .filter(|_| !id.is_empty())
+                .ok_or_else(|| EncryptionError::InvalidKey(id.to_string()))?;
+            Ok(EncryptionKey::new(id, key))
+        })
+        .collect()
+}
+
+/// Encrypts, decrypts, and indexes sensitive column values
+#[derive(Clone)]
+pub struct FieldCipher {
+    keys: Vec<EncryptionKey>,
+    index_key: Vec<u8>,
+}
+
+impl fmt::Debug for FieldCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FieldCipher")
+            .field("keys", &self.keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FieldCipher {
+    /// Create a cipher
+    ///
+    /// # Arguments
+    /// * `keys` - Encryption keys; the first one encrypts
+    /// * `index_key` - Secret for blind indexes
+    ///
+    /// # Panics
+    /// Panics if `keys` is empty
+    pub fn new(keys: Vec<EncryptionKey>, index_key: impl AsRef<[u8]>) -> Self {
+        assert!(!keys.is_empty(), "FieldCipher requires at least one key");
+        Self {
+            keys,
+            index_key: index_key.as_ref().to_vec(),
+        }
+    }
+
+    /// ID of the key new values are encrypted with
+    pub fn current_key_id(&self) -> &str {
+        &self.keys[0].id
+    }
+
+    /// Encrypt a value of `field` with the current key
+    pub fn encrypt(&self, field: &str, plaintext: &str) -> String {
+        let key = &self.keys[0];
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let mut sealed = plaintext.as_bytes().to_vec();
+        key.aead()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(field.as_bytes()),
+                &mut sealed,
+            )
+            .expect("AES-GCM sealing only fails for oversized inputs");
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&sealed);
+        format!(
+            "{}{}:{}",
+            CIPHERTEXT_PREFIX,
+            key.id,
+            STANDARD.encode(payload)
+        )
+    }
+
+    /// Decrypt a stored value of `field`; plaintext values are returned as is
+    pub fn decrypt(&self, field: &str, stored: &str) -> Result<String, EncryptionError> {
+        let Some((key_id, payload)) = split_ciphertext(stored) else {
+            return Ok(stored.to_string());
+        };
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.id == key_id)
+            .ok_or_else(|| EncryptionError::UnknownKey(key_id.to_string()))?;
+
+        let payload = STANDARD
+            .decode(payload)
+            .map_err(|_| EncryptionError::Malformed)?;
+        if payload.len() < NONCE_LEN {
+            return Err(EncryptionError::Malformed);
+        }
+        let (nonce, sealed) = payload.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| EncryptionError::Malformed)?;
+        let mut sealed = sealed.to_vec();
+        let plaintext = key
+            .aead()
+            .open_in_place(nonce, Aad::from(field.as_bytes()), &mut sealed)
+            .map_err(|_| EncryptionError::Invalid)?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| EncryptionError::Malformed)
+    }
+
+    /// Blind index of a value of `field`, as lowercase hex
+    pub fn blind_index(&self, field: &str, plaintext: &str) -> String {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.index_key).expect("HMAC accepts keys of any length");
+        mac.update(field.as_bytes());
+        mac.update(&[0]);
+        mac.update(plaintext.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Whether a stored value is plaintext or encrypted with an older key
+    pub fn needs_reencryption(&self, stored: &str) -> bool {
+        split_ciphertext(stored).is_none_or(|(key_id, _)| key_id != self.current_key_id())
+    }
+}
+
+/// Key ID and payload of an encrypted value, or `None` for plaintext
+fn split_ciphertext(stored: &str) -> Option<(&str, &str)> {
+    stored.strip_prefix(CIPHERTEXT_PREFIX)?.split_once(':')
+}
+
+static CIPHER: OnceLock<FieldCipher> = OnceLock::new();
+
+/// Install the cipher used by the repository
+///
+/// Called once at startup; later calls are ignored.
+pub fn install(cipher: FieldCipher) {
+    if CIPHER.set(cipher).is_err() {
+        tracing::warn!("Data encryption cipher is already installed");
+    }
+}
+
+/// The installed cipher, if data encryption is on
+pub fn cipher() -> Option<&'static FieldCipher> {
+    CIPHER.get()
+}
+
+/// Value to store for `field`: ciphertext, or the plaintext if encryption is off
+pub fn seal(field: &str, plaintext: &str) -> String {
+    match cipher() {
+        Some(cipher) => cipher.encrypt(field, plaintext),
+        None => plaintext.to_string(),
+    }
+}
+
+/// Plaintext of a stored value of `field`
+pub fn open(field: &str, stored: String) -> Result<String, EncryptionError> {
+    match cipher() {
+        Some(cipher) => cipher.decrypt(field, &stored),
+        None if split_ciphertext(&stored).is_some() => Err(EncryptionError::NotConfigured),
+        None => Ok(stored),
+    }
+}
+
+/// Blind index to store and look up `field` by, or `None` if encryption is off
+pub fn blind_index(field: &str, plaintext: &str) -> Option<String> {
+    cipher().map(|cipher| cipher.blind_index(field, plaintext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cipher(ids: &[&str]) -> FieldCipher {
+        let keys = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| EncryptionKey::new(*id, [i as u8 + 1; KEY_LEN]))
+            .collect();
+        FieldCipher::new(keys, b"index-key")
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let cipher = test_cipher(&["2025-01"]);
+        let sealed = cipher.encrypt(FIELD_EMAIL, "user@example.com");
+        assert!(sealed.starts_with("enc:2025-01:"));
+        assert!(!sealed.contains("user@example.com"));
+        assert_ne!(sealed, cipher.encrypt(FIELD_EMAIL, "user@example.com"));
+        assert_eq!(
+            cipher.decrypt(FIELD_EMAIL, &sealed).unwrap(),
+            "user@example.com"
+        );
+
+        // Bound to its column
+        assert_eq!(
+            cipher.decrypt(FIELD_GOOGLE_ID, &sealed),
+            Err(EncryptionError::Invalid)
+        );
+        // Plaintext passes through
+        assert_eq!(
+            cipher.decrypt(FIELD_EMAIL, "user@example.com").unwrap(),
+            "user@example.com"
+        );
+        assert_eq!(
+            cipher.decrypt(FIELD_EMAIL, "enc:2025-01:not base64!"),
+            Err(EncryptionError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let old = test_cipher(&["old"]);
+        let sealed = old.encrypt(FIELD_EMAIL, "user@example.com");
+
+        let rotated = FieldCipher::new(
+            vec![
+                EncryptionKey::new("new", [9; KEY_LEN]),
+                EncryptionKey::new("old", [1; KEY_LEN]),
+            ],
+            b"index-key",
+        );
+        assert_eq!(
+            rotated.decrypt(FIELD_EMAIL, &sealed).unwrap(),
+            "user@example.com"
+        );
+        assert!(rotated.needs_reencryption(&sealed));
+        assert!(rotated.needs_reencryption("user@example.com"));
+        assert!(!rotated.needs_reencryption(&rotated.encrypt(FIELD_EMAIL, "user@example.com")));
+
+        assert_eq!(
+            test_cipher(&["other"]).decrypt(FIELD_EMAIL, &sealed),
+            Err(EncryptionError::UnknownKey("old".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_blind_index() {
+        let cipher = test_cipher(&["a"]);
+        let index = cipher.blind_index(FIELD_EMAIL, "user@example.com");
+        assert_eq!(index.len(), 64);
+        assert_eq!(
+            index,
+            test_cipher(&["b"]).blind_index(FIELD_EMAIL, "user@example.com")
+        );
+        assert_ne!(
+            index,
+            cipher.blind_index(FIELD_GOOGLE_ID, "user@example.com")
+        );
+        assert_ne!(index, cipher.blind_index(FIELD_EMAIL, "other@example.com"));
+    }
+
+    #[test]
+    fn test_parse_encryption_keys() {
+        let key = STANDARD.encode([7u8; KEY_LEN]);
+        let keys = parse_encryption_keys(&format!("2025-01:{}, 2024-06:{}", key, key)).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].id, "2025-01");
+        assert!(!format!("{:?}", keys[0]).contains(&key));
+
+        assert!(parse_encryption_keys("").unwrap().is_empty());
+        assert_eq!(
+            parse_encryption_keys("short:c2hvcnQ=").unwrap_err(),
+            EncryptionError::InvalidKey("short".to_string())
+        );
+        assert!(parse_encryption_keys(&key).is_err());
+    }
+}
@@ -1,16 +1,56 @@
 //! Database module for the Anime Scraper API
 //!
 //! Provides database connection pool management, health check functionality,
-//! and repository functions for anime data persistence.
+//! and repository functions for anime data persistence. Sensitive user
+//! columns are encrypted at rest when keys are configured (see [`encryption`]).
+//!
+//! With a read replica configured (DATABASE_REPLICA_URL), the heavy reads of
+//! scraped catalog data (lists, search, sitemaps, leaderboards, stats) go to
+//! [`Database::read`], while writes, user data, and anything read back
+//! right after a write stay on the primary. The replica is checked every
+//! [`REPLICA_CHECK_INTERVAL`]; while it's unreachable, reads fall back to the
+//! primary, and a read that fails on it in between is retried there.
+//!
+//! Flows that write in several steps run them in one transaction through a
+//! [`UnitOfWork`] (see [`unit_of_work`]).
+//!
+//! Pool sizes, the acquire timeout, and the statement cache come from
+//! [`DatabasePoolConfig`]; [`Database::pool_report`] shows how busy the
+//! pools are.
 
+pub mod encryption;
 pub mod repository;
+pub mod unit_of_work;
 
 pub use repository::*;
+pub use unit_of_work::UnitOfWork;
 
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
 use sqlx::Error as SqlxError;
-use std::time::Duration;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::config::DatabasePoolConfig;
+use crate::models::{DbPoolReport, DbPoolStats};
+
+/// How often the read replica is checked
+pub const REPLICA_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a replica check or connection waits before giving up
+const REPLICA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest a pool report waits for a connection
+///
+/// Reports are requested while diagnosing a saturated pool, so the probe
+/// mustn't queue behind the requests it is diagnosing for the whole acquire
+/// timeout.
+const POOL_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
 
 /// Database-related errors
 #[derive(Error, Debug)]
@@ -22,14 +62,68 @@ pub enum DbError {
     HealthCheckError(String),
 }
 
+/// Read replica pool and whether it answered the last check
+#[derive(Clone)]
+struct Replica {
+    pool: PgPool,
+    healthy: Arc<AtomicBool>,
+}
+
 /// Database connection pool wrapper
 #[derive(Clone)]
 pub struct Database {
     pool: PgPool,
+    replica: Option<Replica>,
+    config: DatabasePoolConfig,
+}
+
+/// Connection options for `url` with the configured statement cache
+fn connect_options(url: &str, config: &DatabasePoolConfig) -> Result<PgConnectOptions, DbError> {
+    Ok(PgConnectOptions::from_str(url)?.statement_cache_capacity(config.statement_cache_size))
+}
+
+/// Whether a query failed for want of a working connection rather than
+/// because of the query itself
+fn is_connection_error(e: &SqlxError) -> bool {
+    matches!(
+        e,
+        SqlxError::Io(_)
+            | SqlxError::Tls(_)
+            | SqlxError::Protocol(_)
+            | SqlxError::PoolTimedOut
+            | SqlxError::PoolClosed
+            | SqlxError::WorkerCrashed
+    )
+}
+
+/// Utilization of a pool, probed by acquiring a connection
+///
+/// An idle connection is taken without waiting; otherwise the probe waits
+/// at most [`POOL_PROBE_TIMEOUT`].
+async fn pool_stats(pool: &PgPool) -> DbPoolStats {
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+    let started = Instant::now();
+    let acquire_wait_ms = if pool.try_acquire().is_some() {
+        Some(0)
+    } else {
+        match tokio::time::timeout(POOL_PROBE_TIMEOUT, pool.acquire()).await {
+            Ok(Ok(_connection)) => Some(started.elapsed().as_millis() as u64),
+            _ => None,
+        }
+    };
+
+    DbPoolStats {
+        max_connections: pool.options().get_max_connections(),
+        size,
+        in_use: size.saturating_sub(idle),
+        idle,
+        acquire_wait_ms,
+    }
 }
 
 impl Database {
-    /// Create a new database connection pool
+    /// Create a new database connection pool with the default pool settings
     ///
     /// # Arguments
     /// * `database_url` - PostgreSQL connection string
@@ -37,15 +131,62 @@ impl Database {
     /// # Returns
     /// A new Database instance with an active connection pool
     pub async fn new(database_url: &str) -> Result<Self, DbError> {
+        Self::with_config(database_url, DatabasePoolConfig::default()).await
+    }
+
+    /// Create a new database connection pool
+    ///
+    /// # Arguments
+    /// * `database_url` - PostgreSQL connection string
+    /// * `config` - Pool sizes, acquire timeout, and statement cache size
+    ///
+    /// # Returns
+    /// A new Database instance with an active connection pool
+    pub async fn with_config(
+        database_url: &str,
+        config: DatabasePoolConfig,
+    ) -> Result<Self, DbError> {
         let pool = PgPoolOptions::new()
-            .max_connections(10)
-            .min_connections(2)
-            .acquire_timeout(Duration::from_secs(30))
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
             .idle_timeout(Duration::from_secs(600))
-            .connect(database_url)
+            .connect_with(connect_options(database_url, &config)?)
             .await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            replica: None,
+            config,
+        })
+    }
+
+    /// Send heavy reads to a read replica
+    ///
+    /// The replica doesn't have to be up: it's checked once here, and reads
+    /// go to the primary until a check succeeds. Its pool has the same size
+    /// and statement cache as the primary's, but opens connections only as
+    /// needed and gives up on them sooner.
+    ///
+    /// # Arguments
+    /// * `replica_url` - PostgreSQL connection string of the replica
+    ///
+    /// # Returns
+    /// The Database with the replica, or an error if the URL is invalid
+    pub async fn with_replica(mut self, replica_url: &str) -> Result<Self, DbError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(self.config.max_connections)
+            .min_connections(0)
+            .acquire_timeout(REPLICA_TIMEOUT)
+            .idle_timeout(Duration::from_secs(600))
+            .connect_lazy_with(connect_options(replica_url, &self.config)?);
+
+        self.replica = Some(Replica {
+            pool,
+            healthy: Arc::new(AtomicBool::new(false)),
+        });
+        self.check_replica().await;
+        Ok(self)
     }
 
     /// Get a reference to the underlying connection pool
@@ -53,6 +194,118 @@ impl Database {
         &self.pool
     }
 
+    /// Begin a transaction for a multi-step flow on the primary
+    pub async fn unit_of_work(&self) -> RepositoryResult<UnitOfWork> {
+        UnitOfWork::begin(&self.pool).await
+    }
+
+    /// Pool for heavy reads: the replica while it's up, otherwise the primary
+    ///
+    /// Reads from it may lag behind recent writes, so read back what a
+    /// request just wrote from [`pool`](Self::pool).
+    pub fn read_pool(&self) -> &PgPool {
+        match &self.replica {
+            Some(replica) if replica.healthy.load(Ordering::Relaxed) => &replica.pool,
+            _ => &self.pool,
+        }
+    }
+
+    /// Run a heavy read on the read pool, retrying it on the primary if the
+    /// replica fails
+    ///
+    /// A replica that became unreachable since its last check is marked down
+    /// right away, so the following reads go to the primary until a check
+    /// finds it up again.
+    ///
+    /// # Arguments
+    /// * `query` - The read, given the pool to run on; it may run twice
+    pub async fn read<'a, T, F, Fut>(&'a self, query: F) -> RepositoryResult<T>
+    where
+        F: Fn(&'a PgPool) -> Fut,
+        Fut: Future<Output = RepositoryResult<T>>,
+    {
+        let pool = self.read_pool();
+        if std::ptr::eq(pool, &self.pool) {
+            return query(pool).await;
+        }
+        match query(pool).await {
+            Err(RepositoryError::DatabaseError(e)) => {
+                warn!("Read replica query failed, retrying on the primary: {}", e);
+                if is_connection_error(&e) {
+                    if let Some(replica) = &self.replica {
+                        replica.healthy.store(false, Ordering::Relaxed);
+                    }
+                }
+                query(&self.pool).await
+            }
+            result => result,
+        }
+    }
+
+    /// Whether the read replica is up, or `None` without one
+    pub fn replica_healthy(&self) -> Option<bool> {
+        self.replica
+            .as_ref()
+            .map(|replica| replica.healthy.load(Ordering::Relaxed))
+    }
+
+    /// Check the read replica, switching reads to or away from it
+    ///
+    /// # Returns
+    /// Whether the replica is up, or `None` without one
+    pub async fn check_replica(&self) -> Option<bool> {
+        let replica = self.replica.as_ref()?;
+        let was_up = replica.healthy.load(Ordering::Relaxed);
+        let check = sqlx::query("SELECT 1").execute(&replica.pool);
+        let error = match tokio::time::timeout(REPLICA_TIMEOUT, check).await {
+            Ok(Ok(_)) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some("timed out".to_string()),
+        };
+
+        match &error {
+            None if !was_up => info!("Read replica is up, sending reads to it"),
+            Some(e) if was_up => warn!("Read replica is down, reading from the primary: {}", e),
+            _ => {}
+        }
+        replica.healthy.store(error.is_none(), Ordering::Relaxed);
+        Some(error.is_none())
+    }
+
+    /// Spawn a task checking the read replica every `interval`
+    ///
+    /// # Returns
+    /// The task, or `None` without a replica
+    pub fn spawn_replica_monitor(&self, interval: Duration) -> Option<JoinHandle<()>> {
+        self.replica.as_ref()?;
+        let db = self.clone();
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                db.check_replica().await;
+            }
+        }))
+    }
+
+    /// Utilization of the primary pool and, if configured, the replica's
+    ///
+    /// Each pool is probed by acquiring a connection, timing how long a
+    /// query would wait for one right now, up to half a second.
+    pub async fn pool_report(&self) -> DbPoolReport {
+        let replica = match &self.replica {
+            Some(replica) => Some(pool_stats(&replica.pool).await),
+            None => None,
+        };
+
+        DbPoolReport {
+            primary: pool_stats(&self.pool).await,
+            replica,
+            replica_healthy: self.replica_healthy(),
+            statement_cache_size: self.config.statement_cache_size,
+        }
+    }
+
     /// Run database migrations
     ///
     /// # Returns
@@ -77,8 +330,11 @@ impl Database {
         Ok(())
     }
 
-    /// Close the database connection pool gracefully
+    /// Close the database connection pools gracefully
     pub async fn close(&self) {
+        if let Some(replica) = &self.replica {
+            replica.pool.close().await;
+        }
         self.pool.close().await;
     }
 }
@@ -100,5 +356,96 @@ mod tests {
         let db = db.unwrap();
         let health = db.health_check().await;
         assert!(health.is_ok(), "Health check should pass");
+        assert_eq!(db.replica_healthy(), None);
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_pool_report() {
+        dotenvy::dotenv().ok();
+        let database_url =
+            std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for tests");
+        let config = DatabasePoolConfig {
+            max_connections: 3,
+            min_connections: 1,
+            statement_cache_size: 0,
+            ..Default::default()
+        };
+        let db = Database::with_config(&database_url, config).await.unwrap();
+
+        let held = db.pool().acquire().await.unwrap();
+        let report = db.pool_report().await;
+        assert_eq!(report.primary.max_connections, 3);
+        assert!(report.primary.in_use >= 1);
+        assert_eq!(
+            report.primary.in_use + report.primary.idle,
+            report.primary.size
+        );
+        assert!(report.primary.acquire_wait_ms.is_some());
+        assert_eq!(report.replica, None);
+        assert_eq!(report.statement_cache_size, 0);
+        drop(held);
+
+        // Queries still work without a statement cache
+        sqlx::query("SELECT $1::INT")
+            .bind(1)
+            .execute(db.pool())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_read_pool_falls_back_to_primary() {
+        dotenvy::dotenv().ok();
+        let database_url =
+            std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for tests");
+        let primary = Database::new(&database_url).await.unwrap();
+
+        // Nothing listens on port 1, so the replica stays down
+        let db = primary
+            .clone()
+            .with_replica("postgres://postgres@127.0.0.1:1/anime")
+            .await
+            .unwrap();
+        assert_eq!(db.replica_healthy(), Some(false));
+        assert_eq!(db.check_replica().await, Some(false));
+        assert!(std::ptr::eq(db.read_pool(), db.pool()));
+
+        // A replica going down between checks fails over right away
+        db.replica
+            .as_ref()
+            .unwrap()
+            .healthy
+            .store(true, Ordering::Relaxed);
+        let one: i32 = db
+            .read(|pool| async move { Ok(sqlx::query_scalar("SELECT 1").fetch_one(pool).await?) })
+            .await
+            .expect("Read should be retried on the primary");
+        assert_eq!(one, 1);
+        assert_eq!(db.replica_healthy(), Some(false));
+
+        // A reachable replica takes the reads; like a real one it's read-only,
+        // so anything writing is retried on the primary
+        let read_only = format!(
+            "{}{}options=-c%20default_transaction_read_only%3Don",
+            database_url,
+            if database_url.contains('?') { '&' } else { '?' }
+        );
+        let db = primary.with_replica(&read_only).await.unwrap();
+        assert_eq!(db.replica_healthy(), Some(true));
+        assert!(!std::ptr::eq(db.read_pool(), db.pool()));
+        sqlx::query("SELECT 1")
+            .execute(db.read_pool())
+            .await
+            .expect("Reads should go to the replica");
+        let query = "read replica check";
+        assert!(get_cached_search(db.read_pool(), query, 1000, 1000)
+            .await
+            .is_err());
+        db.read(|pool| get_cached_search(pool, query, 1000, 1000))
+            .await
+            .expect("Writes should be retried on the primary");
+        assert_eq!(db.replica_healthy(), Some(true));
     }
 }
@@ -2,16 +2,43 @@
 //!
 //! Provides CRUD operations with upsert logic for anime_updates, completed_anime,
 //! anime_details, episodes, video_sources, crawled_anime, users, user_favorites,
-//! user_subscriptions, and user_history tables.
-
-use chrono::{DateTime, Utc};
-use sqlx::{PgPool, Row};
+//! user_subscriptions, user_history, user_watched_episodes, user_preferences,
+//! saved_searches, collections, collection_items, episode_comments, episode_reactions,
+//! episode_reaction_counts, roles, moderation_items, user_strikes, registration_ips,
+//! sessions, data_exports, data_erasures, jobs, crawl_reports, crawl_failures,
+//! crawler_bandwidth,
+//! anime_views, email_deliveries, search_cache, search_analytics,
+//! community_top_cache, synopsis_translations, video_server_rules,
+//! subtitle_tracks, tags, anime_tags, age_rating_overrides, and feature_flags tables.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgExecutor, PgPool, Row};
 use thiserror::Error;
+use tracing::{instrument, warn};
 
+use super::encryption::{
+    self, EncryptionError, FIELD_EMAIL, FIELD_EMAIL_PAYLOAD, FIELD_GOOGLE_ID, FIELD_RECIPIENT,
+};
 use crate::models::{
-    CrawledAnime, CrawledAnimeRecord, User, UserFavorite, UserHistory, UserSubscription,
+    AgeRating, AnimeAgeRating, AnimeMergeResult, AnimeTag, CatalogOrder, ChangeCount, ChangeEntry,
+    ChangeKind, Collection, CollectionItem, CommunityTop, CommunityTopEntry, ContentReport,
+    ContinueWatching, CrawlFailure, CrawlFailureKind, CrawlReport, CrawledAnime,
+    CrawledAnimeRecord, CrawlerBandwidthDay, DataErasure, DataExport, DetailFields, EmailDelivery,
+    EpisodeComment, EpisodeLikes, Feature, FeatureFlag, JobQueueStats, JobRecord,
+    LeaderboardWindow, ModerationItem, ModerationStanding, ModerationStatus, OrphanGroup,
+    OutboxEventRecord, Role, SavedSearch, SearchQueryStats, Session, SitemapEntry, SitemapPage,
+    TableRowCount, Tag, Tenant, TimelineEpisode, UpdatePreferencesRequest, User, UserFavorite,
+    UserHistory, UserPreferences, UserRoles, UserStrike, UserSubscription, VideoServerRule,
+    VideoServerRuleOrigin, WatchProgress, WriteOutcome, DATA_EXPORT_FAILED, DATA_EXPORT_READY,
+};
+use crate::parser::{
+    embed_info, iso_date, AnimeDetail, AnimeUpdate, CompletedAnime, Episode, PopularEntry,
+    PopularWindow, SearchResult, SubtitleTrack, Thumbnails, VideoSource,
 };
-use crate::parser::{AnimeDetail, AnimeUpdate, CompletedAnime, Episode, VideoSource};
 
 /// Repository-related errors
 #[derive(Error, Debug)]
@@ -27,6 +54,9 @@ pub enum RepositoryError {
 
     #[error("Email already exists")]
     EmailAlreadyExists,
+
+    #[error("Encryption error: {0}")]
+    Encryption(#[from] EncryptionError),
 }
 
 /// Result type for repository operations
@@ -44,57 +74,146 @@ fn extract_slug_from_url(url: &str) -> String {
         .to_string()
 }
 
+/// SHA-256 hex digest of a value's JSON serialization
+///
+/// Stored with scraped records so unchanged data can be skipped on upsert,
+/// and used as the ETag of API responses.
+pub fn content_hash<T: Serialize + ?Sized>(value: &T) -> String {
+    let json = serde_json::to_vec(value).unwrap_or_default();
+    hex::encode(Sha256::digest(&json))
+}
+
+/// Content hash of an anime detail, excluding its episodes
+fn anime_detail_hash(detail: &AnimeDetail) -> String {
+    content_hash(&AnimeDetail {
+        episodes: Vec::new(),
+        ..detail.clone()
+    })
+}
+
+/// Content hash of an episode row
+fn episode_hash(anime_slug: &str, episode: &Episode) -> String {
+    // Derived from release_date, and for relative dates from the time of parsing
+    let episode = Episode {
+        released_at_iso: None,
+        ..episode.clone()
+    };
+    content_hash(&(anime_slug, &episode))
+}
+
+/// SQL expression for an episode row's slug, the last path segment of its URL
+const EPISODE_SLUG_SQL: &str = r"regexp_replace(rtrim(url, '/'), '^.*/', '')";
+
+/// SQL expression for an episode row's numeric number ("12 END" is 12), or NULL
+const EPISODE_NUMBER_SQL: &str = r"substring(number FROM '[0-9]+(?:\.[0-9]+)?')::numeric";
+
+/// SQL columns with a user's watched and released episode counts of the anime
+/// in `t.anime_slug`, for `t.user_id`
+const WATCH_PROGRESS_COLUMNS: &str = r#"
+    (SELECT COUNT(*) FROM user_watched_episodes w
+     WHERE w.user_id = t.user_id AND w.anime_slug = t.anime_slug AND w.watched) AS watched_count,
+    (SELECT COUNT(*) FROM episodes e WHERE e.anime_slug = t.anime_slug) AS total_episodes"#;
+
+/// Result of saving an anime detail with its episodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimeDetailChanges {
+    /// Whether the detail row was inserted, updated, or left alone
+    pub detail: WriteOutcome,
+    /// Changed and unchanged episode rows
+    pub episodes: ChangeCount,
+}
+
+/// Outcome of an upsert ending in `RETURNING (xmax = 0) AS inserted`
+///
+/// Upserts whose `WHERE` skips identical content return no row.
+fn upsert_outcome(row: Option<sqlx::postgres::PgRow>) -> WriteOutcome {
+    match row {
+        Some(row) if row.get::<bool, _>("inserted") => WriteOutcome::Inserted,
+        Some(_) => WriteOutcome::Updated,
+        None => WriteOutcome::Unchanged,
+    }
+}
+
 // ============================================================================
 // Anime Updates Repository
 // ============================================================================
 
+/// Thumbnail variants as stored in a `thumbnails` column
+fn thumbnails_json(thumbnails: Option<&Thumbnails>) -> Option<String> {
+    thumbnails.and_then(|thumbnails| serde_json::to_string(thumbnails).ok())
+}
+
+/// Thumbnail variants read back from a `thumbnails` column
+fn thumbnails_from_json(json: Option<String>) -> Option<Thumbnails> {
+    json.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// ISO 8601 form of a stored site date; relative dates ("kemarin") count
+/// back from the row's `updated_at`, when the text was scraped
+fn stored_iso_date(text: &str, row: &sqlx::postgres::PgRow) -> Option<String> {
+    let scraped_at = row
+        .get::<Option<DateTime<Utc>>, _>("updated_at")
+        .unwrap_or_else(Utc::now);
+    iso_date(text, scraped_at)
+}
+
 /// Save anime updates to the database with upsert logic
 ///
-/// Uses ON CONFLICT UPDATE to update existing records based on episode_url
+/// Uses ON CONFLICT UPDATE to update existing records based on episode_url,
+/// ignoring trailing slashes, so an episode repeated in a batch or across
+/// fetches is stored once. A stored `released_at` is kept: relative release info only gets coarser
+/// as the episode ages, so the first resolution is the most precise.
 pub async fn save_anime_updates(pool: &PgPool, updates: &[AnimeUpdate]) -> RepositoryResult<()> {
     for update in updates {
         sqlx::query(
             r#"
             INSERT INTO anime_updates (
-                title, episode_url, thumbnail, episode_number, type,
-                series_title, series_url, status, release_info, updated_at
+                title, episode_url, thumbnail, thumbnails, episode_number, type,
+                series_title, series_url, status, release_info, released_at, updated_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, CURRENT_TIMESTAMP)
-            ON CONFLICT (episode_url) DO UPDATE SET
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11::TIMESTAMPTZ, CURRENT_TIMESTAMP)
+            ON CONFLICT ((rtrim(episode_url, '/'))) DO UPDATE SET
                 title = EXCLUDED.title,
                 thumbnail = EXCLUDED.thumbnail,
+                thumbnails = EXCLUDED.thumbnails,
                 episode_number = EXCLUDED.episode_number,
                 type = EXCLUDED.type,
                 series_title = EXCLUDED.series_title,
                 series_url = EXCLUDED.series_url,
                 status = EXCLUDED.status,
                 release_info = EXCLUDED.release_info,
+                released_at = COALESCE(anime_updates.released_at, EXCLUDED.released_at),
                 updated_at = CURRENT_TIMESTAMP
             "#,
         )
         .bind(&update.title)
         .bind(&update.episode_url)
         .bind(&update.thumbnail)
+        .bind(thumbnails_json(update.thumbnails.as_ref()))
         .bind(&update.episode_number)
         .bind(&update.anime_type)
         .bind(&update.series_title)
         .bind(&update.series_url)
         .bind(&update.status)
         .bind(&update.release_info)
+        .bind(update.released_at.as_deref())
         .execute(pool)
         .await?;
     }
     Ok(())
 }
 
-/// Get all anime updates from the database
+/// Get all anime updates from the database, most recently released first
+///
+/// Updates whose release time couldn't be resolved come last, most recently
+/// saved first.
 pub async fn get_anime_updates(pool: &PgPool) -> RepositoryResult<Vec<AnimeUpdate>> {
     let rows = sqlx::query(
         r#"
-        SELECT title, episode_url, thumbnail, episode_number, type,
-               series_title, series_url, status, release_info
+        SELECT title, episode_url, thumbnail, thumbnails, episode_number, type,
+               series_title, series_url, status, release_info, released_at
         FROM anime_updates
-        ORDER BY updated_at DESC
+        ORDER BY released_at DESC NULLS LAST, updated_at DESC
         "#,
     )
     .fetch_all(pool)
@@ -113,6 +232,7 @@ pub async fn get_anime_updates(pool: &PgPool) -> RepositoryResult<Vec<AnimeUpdat
                 thumbnail: row
                     .get::<Option<String>, _>("thumbnail")
                     .unwrap_or_default(),
+                thumbnails: thumbnails_from_json(row.get("thumbnails")),
                 episode_number: row
                     .get::<Option<String>, _>("episode_number")
                     .unwrap_or_default(),
@@ -125,6 +245,11 @@ pub async fn get_anime_updates(pool: &PgPool) -> RepositoryResult<Vec<AnimeUpdat
                 release_info: row
                     .get::<Option<String>, _>("release_info")
                     .unwrap_or_default(),
+                released_at: row
+                    .get::<Option<DateTime<Utc>>, _>("released_at")
+                    .map(|time| time.to_rfc3339()),
+                like_count: None,
+                liked_by_me: None,
             }
         })
         .collect();
@@ -146,22 +271,25 @@ pub async fn delete_all_anime_updates(pool: &PgPool) -> RepositoryResult<u64> {
 
 /// Save completed anime to the database with upsert logic
 ///
-/// Uses ON CONFLICT UPDATE to update existing records based on url
+/// Uses ON CONFLICT UPDATE to update existing records based on url. The
+/// list is saved last to first, so its first (newest) entry gets the latest
+/// updated_at and is listed first.
 pub async fn save_completed_anime(
     pool: &PgPool,
     anime_list: &[CompletedAnime],
 ) -> RepositoryResult<()> {
-    for anime in anime_list {
+    for anime in anime_list.iter().rev() {
         sqlx::query(
             r#"
             INSERT INTO completed_anime (
-                title, url, thumbnail, type, episode_count, status,
+                title, url, thumbnail, thumbnails, type, episode_count, status,
                 posted_by, posted_at, series_title, series_url, genres, rating, updated_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, CURRENT_TIMESTAMP)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, CURRENT_TIMESTAMP)
             ON CONFLICT (url) DO UPDATE SET
                 title = EXCLUDED.title,
                 thumbnail = EXCLUDED.thumbnail,
+                thumbnails = EXCLUDED.thumbnails,
                 type = EXCLUDED.type,
                 episode_count = EXCLUDED.episode_count,
                 status = EXCLUDED.status,
@@ -177,6 +305,7 @@ pub async fn save_completed_anime(
         .bind(&anime.title)
         .bind(&anime.url)
         .bind(&anime.thumbnail)
+        .bind(thumbnails_json(anime.thumbnails.as_ref()))
         .bind(&anime.anime_type)
         .bind(&anime.episode_count)
         .bind(&anime.status)
@@ -192,16 +321,32 @@ pub async fn save_completed_anime(
     Ok(())
 }
 
-/// Get all completed anime from the database
+/// Get all completed anime from the database, most recently saved first
 pub async fn get_completed_anime(pool: &PgPool) -> RepositoryResult<Vec<CompletedAnime>> {
+    list_completed_anime(pool, None, 0).await
+}
+
+/// Get a page of completed anime, most recently saved first
+///
+/// # Arguments
+/// * `limit` - Maximum number of entries, or all of them if `None`
+/// * `offset` - Entries to skip
+pub async fn list_completed_anime(
+    pool: &PgPool,
+    limit: Option<i64>,
+    offset: i64,
+) -> RepositoryResult<Vec<CompletedAnime>> {
     let rows = sqlx::query(
         r#"
-        SELECT title, url, thumbnail, type, episode_count, status,
-               posted_by, posted_at, series_title, series_url, genres, rating
+        SELECT title, url, thumbnail, thumbnails, type, episode_count, status,
+               posted_by, posted_at, series_title, series_url, genres, rating, updated_at
         FROM completed_anime
-        ORDER BY updated_at DESC
+        ORDER BY updated_at DESC, id DESC
+        LIMIT $1 OFFSET $2
         "#,
     )
+    .bind(limit)
+    .bind(offset)
     .fetch_all(pool)
     .await?;
 
@@ -209,6 +354,9 @@ pub async fn get_completed_anime(pool: &PgPool) -> RepositoryResult<Vec<Complete
         .into_iter()
         .map(|row| {
             let url: String = row.get::<String, _>("url");
+            let posted_at = row
+                .get::<Option<String>, _>("posted_at")
+                .unwrap_or_default();
             CompletedAnime {
                 slug: extract_slug_from_url(&url),
                 title: row.get::<String, _>("title"),
@@ -216,6 +364,7 @@ pub async fn get_completed_anime(pool: &PgPool) -> RepositoryResult<Vec<Complete
                 thumbnail: row
                     .get::<Option<String>, _>("thumbnail")
                     .unwrap_or_default(),
+                thumbnails: thumbnails_from_json(row.get("thumbnails")),
                 anime_type: row.get::<Option<String>, _>("type").unwrap_or_default(),
                 episode_count: row
                     .get::<Option<String>, _>("episode_count")
@@ -224,9 +373,8 @@ pub async fn get_completed_anime(pool: &PgPool) -> RepositoryResult<Vec<Complete
                 posted_by: row
                     .get::<Option<String>, _>("posted_by")
                     .unwrap_or_default(),
-                posted_at: row
-                    .get::<Option<String>, _>("posted_at")
-                    .unwrap_or_default(),
+                released_at_iso: stored_iso_date(&posted_at, &row),
+                posted_at,
                 series_title: row
                     .get::<Option<String>, _>("series_title")
                     .unwrap_or_default(),
@@ -252,27 +400,125 @@ pub async fn delete_all_completed_anime(pool: &PgPool) -> RepositoryResult<u64>
     Ok(result.rows_affected())
 }
 
+// ============================================================================
+// Popular Anime Repository
+// ============================================================================
+
+/// Replace the stored ranking of a popular widget
+///
+/// # Arguments
+/// * `window` - Widget the entries were read from
+/// * `entries` - Its entries; if two share a rank, all are renumbered 1..n
+///   in rank order, keeping the given order between equal ranks
+pub async fn save_popular_anime(
+    pool: &PgPool,
+    window: PopularWindow,
+    entries: &[PopularEntry],
+) -> RepositoryResult<()> {
+    let mut entries: Vec<&PopularEntry> = entries.iter().collect();
+    entries.sort_by_key(|entry| entry.rank);
+    let renumber = entries.windows(2).any(|pair| pair[0].rank == pair[1].rank);
+    if renumber {
+        warn!(
+            "Popular {} ranking repeats a rank, renumbering its {} entries",
+            window.as_str(),
+            entries.len()
+        );
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM popular_anime WHERE time_window = $1")
+        .bind(window.as_str())
+        .execute(&mut *tx)
+        .await?;
+    for (i, entry) in entries.into_iter().enumerate() {
+        let rank = if renumber { i as u32 + 1 } else { entry.rank };
+        sqlx::query(
+            r#"
+            INSERT INTO popular_anime (
+                time_window, rank, slug, title, url, thumbnail, thumbnails, genres, rating
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(window.as_str())
+        .bind(rank as i32)
+        .bind(&entry.slug)
+        .bind(&entry.title)
+        .bind(&entry.url)
+        .bind(&entry.thumbnail)
+        .bind(thumbnails_json(entry.thumbnails.as_ref()))
+        .bind(&entry.genres)
+        .bind(&entry.rating)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Get the stored ranking of a popular widget, by rank
+pub async fn get_popular_anime(
+    pool: &PgPool,
+    window: PopularWindow,
+) -> RepositoryResult<Vec<PopularEntry>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT rank, slug, title, url, thumbnail, thumbnails, genres, rating
+        FROM popular_anime
+        WHERE time_window = $1
+        ORDER BY rank
+        "#,
+    )
+    .bind(window.as_str())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| PopularEntry {
+            rank: row.get::<i32, _>("rank").max(0) as u32,
+            slug: row.get("slug"),
+            title: row.get("title"),
+            url: row.get("url"),
+            thumbnail: row
+                .get::<Option<String>, _>("thumbnail")
+                .unwrap_or_default(),
+            thumbnails: thumbnails_from_json(row.get("thumbnails")),
+            genres: row
+                .get::<Option<Vec<String>>, _>("genres")
+                .unwrap_or_default(),
+            rating: row.get::<Option<String>, _>("rating").unwrap_or_default(),
+        })
+        .collect())
+}
+
 // ============================================================================
 // Anime Details Repository
 // ============================================================================
 
 /// Save anime detail to the database with upsert logic
 ///
-/// Uses ON CONFLICT UPDATE to update existing records based on slug.
+/// Uses ON CONFLICT UPDATE to update existing records based on slug; the row
+/// is left untouched if its content hash matches.
 /// Note: Episodes are saved separately using save_episodes.
+///
+/// # Returns
+/// Whether the row was inserted or updated
 pub async fn save_anime_detail(
     pool: &PgPool,
     slug: &str,
     detail: &AnimeDetail,
-) -> RepositoryResult<()> {
-    sqlx::query(
+) -> RepositoryResult<bool> {
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query(
         r#"
         INSERT INTO anime_details (
             slug, title, alternate_titles, poster, rating, trailer_url,
             status, studio, release_date, duration, season, type,
-            total_episodes, director, casts, genres, synopsis, updated_at
+            total_episodes, director, casts, genres, synopsis, content_hash, age_rating, updated_at
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, CURRENT_TIMESTAMP)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, CURRENT_TIMESTAMP)
         ON CONFLICT (slug) DO UPDATE SET
             title = EXCLUDED.title,
             alternate_titles = EXCLUDED.alternate_titles,
@@ -290,7 +536,11 @@ pub async fn save_anime_detail(
             casts = EXCLUDED.casts,
             genres = EXCLUDED.genres,
             synopsis = EXCLUDED.synopsis,
+            content_hash = EXCLUDED.content_hash,
+            age_rating = EXCLUDED.age_rating,
             updated_at = CURRENT_TIMESTAMP
+        WHERE anime_details.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+        RETURNING (xmax = 0) AS inserted
         "#,
     )
     .bind(slug)
@@ -310,10 +560,18 @@ pub async fn save_anime_detail(
     .bind(&detail.casts)
     .bind(&detail.genres)
     .bind(&detail.synopsis)
-    .execute(pool)
+    .bind(anime_detail_hash(detail))
+    .bind(AgeRating::from_genres(&detail.genres).as_str())
+    .fetch_optional(&mut *tx)
     .await?;
+    let outcome = upsert_outcome(row);
 
-    Ok(())
+    let mut changes = PendingChanges::default();
+    changes.anime(slug, outcome);
+    changes.record(&mut tx).await?;
+    tx.commit().await?;
+
+    Ok(outcome.changed())
 }
 
 /// Get anime detail by slug from the database
@@ -368,12 +626,87 @@ pub async fn get_anime_detail(pool: &PgPool, slug: &str) -> RepositoryResult<Opt
                     .unwrap_or_default(),
                 synopsis: row.get::<Option<String>, _>("synopsis").unwrap_or_default(),
                 episodes,
+                canonical_slug: None,
+                age_rating: None,
             }))
         }
         None => Ok(None),
     }
 }
 
+/// Get only the selected fields of an anime detail from the database
+///
+/// Only the columns of the selected fields are read, and episodes are only
+/// loaded when requested. Fields that were not selected are left empty.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `slug` - Anime slug
+/// * `fields` - Fields to load
+///
+/// # Returns
+/// * `Ok(Some(AnimeDetail))` - The anime with the selected fields filled in
+/// * `Ok(None)` - The anime is not found
+pub async fn get_anime_detail_fields(
+    pool: &PgPool,
+    slug: &str,
+    fields: &DetailFields,
+) -> RepositoryResult<Option<AnimeDetail>> {
+    // Column names come from the fixed list in DetailFields, never from input
+    let columns: String = fields
+        .columns()
+        .iter()
+        .map(|column| format!(", {}", column))
+        .collect();
+    let query = format!("SELECT slug{} FROM anime_details WHERE slug = $1", columns);
+
+    let Some(row) = sqlx::query(&query).bind(slug).fetch_optional(pool).await? else {
+        return Ok(None);
+    };
+
+    let mut detail = AnimeDetail::default();
+    for column in fields.columns() {
+        match column {
+            "casts" => {
+                detail.casts = row
+                    .get::<Option<Vec<String>>, _>("casts")
+                    .unwrap_or_default()
+            }
+            "genres" => {
+                detail.genres = row
+                    .get::<Option<Vec<String>>, _>("genres")
+                    .unwrap_or_default()
+            }
+            "title" => detail.title = row.get::<String, _>("title"),
+            _ => {
+                let target = match column {
+                    "alternate_titles" => &mut detail.alternate_titles,
+                    "poster" => &mut detail.poster,
+                    "rating" => &mut detail.rating,
+                    "trailer_url" => &mut detail.trailer_url,
+                    "status" => &mut detail.status,
+                    "studio" => &mut detail.studio,
+                    "release_date" => &mut detail.release_date,
+                    "duration" => &mut detail.duration,
+                    "season" => &mut detail.season,
+                    "type" => &mut detail.anime_type,
+                    "total_episodes" => &mut detail.total_episodes,
+                    "director" => &mut detail.director,
+                    "synopsis" => &mut detail.synopsis,
+                    _ => continue,
+                };
+                *target = row.get::<Option<String>, _>(column).unwrap_or_default();
+            }
+        }
+    }
+
+    if fields.includes_episodes() {
+        detail.episodes = get_episodes(pool, slug).await?;
+    }
+
+    Ok(Some(detail))
+}
+
 /// Delete anime detail by slug from the database
 ///
 /// This will also cascade delete associated episodes due to foreign key constraint
@@ -391,23 +724,30 @@ pub async fn delete_anime_detail(pool: &PgPool, slug: &str) -> RepositoryResult<
 
 /// Save episodes for an anime to the database with upsert logic
 ///
-/// Uses ON CONFLICT UPDATE to update existing records based on url
+/// Uses ON CONFLICT UPDATE to update existing records based on url; rows whose
+/// content hash matches are left untouched.
 pub async fn save_episodes(
     pool: &PgPool,
     anime_slug: &str,
     episodes: &[Episode],
-) -> RepositoryResult<()> {
+) -> RepositoryResult<ChangeCount> {
+    let mut tx = pool.begin().await?;
+    let mut changes = ChangeCount::default();
+    let mut pending = PendingChanges::default();
     for episode in episodes {
-        sqlx::query(
+        let row = sqlx::query(
             r#"
-            INSERT INTO episodes (anime_slug, number, title, url, release_date, updated_at)
-            VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+            INSERT INTO episodes (anime_slug, number, title, url, release_date, content_hash, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP)
             ON CONFLICT (url) DO UPDATE SET
                 anime_slug = EXCLUDED.anime_slug,
                 number = EXCLUDED.number,
                 title = EXCLUDED.title,
                 release_date = EXCLUDED.release_date,
+                content_hash = EXCLUDED.content_hash,
                 updated_at = CURRENT_TIMESTAMP
+            WHERE episodes.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+            RETURNING (xmax = 0) AS inserted
             "#,
         )
         .bind(anime_slug)
@@ -415,17 +755,24 @@ pub async fn save_episodes(
         .bind(&episode.title)
         .bind(&episode.url)
         .bind(&episode.release_date)
-        .execute(pool)
+        .bind(episode_hash(anime_slug, episode))
+        .fetch_optional(&mut *tx)
         .await?;
+        let outcome = upsert_outcome(row);
+        changes.record(outcome.changed());
+        pending.episode(&episode.url, outcome);
     }
-    Ok(())
+
+    pending.record(&mut tx).await?;
+    tx.commit().await?;
+    Ok(changes)
 }
 
 /// Get all episodes for an anime by slug
 pub async fn get_episodes(pool: &PgPool, anime_slug: &str) -> RepositoryResult<Vec<Episode>> {
     let rows = sqlx::query(
         r#"
-        SELECT number, title, url, release_date
+        SELECT number, title, url, release_date, updated_at
         FROM episodes
         WHERE anime_slug = $1
         ORDER BY id ASC
@@ -439,14 +786,16 @@ pub async fn get_episodes(pool: &PgPool, anime_slug: &str) -> RepositoryResult<V
         .into_iter()
         .map(|row| {
             let url: String = row.get::<String, _>("url");
+            let release_date = row
+                .get::<Option<String>, _>("release_date")
+                .unwrap_or_default();
             Episode {
                 slug: extract_slug_from_url(&url),
                 number: row.get::<Option<String>, _>("number").unwrap_or_default(),
                 title: row.get::<Option<String>, _>("title").unwrap_or_default(),
                 url,
-                release_date: row
-                    .get::<Option<String>, _>("release_date")
-                    .unwrap_or_default(),
+                released_at_iso: stored_iso_date(&release_date, &row),
+                release_date,
             }
         })
         .collect();
@@ -454,6 +803,102 @@ pub async fn get_episodes(pool: &PgPool, anime_slug: &str) -> RepositoryResult<V
     Ok(episodes)
 }
 
+/// Get a stored episode by URL, ignoring a trailing slash
+///
+/// # Returns
+/// * `Ok(Some((anime_slug, episode)))` - The episode and the anime it belongs to
+/// * `Ok(None)` - Episode not in the database
+pub async fn get_episode_by_url(
+    pool: &PgPool,
+    url: &str,
+) -> RepositoryResult<Option<(String, Episode)>> {
+    let row = sqlx::query(
+        r#"
+        SELECT anime_slug, number, title, url, release_date, updated_at
+        FROM episodes
+        WHERE rtrim(url, '/') = rtrim($1, '/')
+        ORDER BY id ASC
+        LIMIT 1
+        "#,
+    )
+    .bind(url)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        let url: String = row.get("url");
+        let release_date = row
+            .get::<Option<String>, _>("release_date")
+            .unwrap_or_default();
+        let episode = Episode {
+            slug: extract_slug_from_url(&url),
+            number: row.get::<Option<String>, _>("number").unwrap_or_default(),
+            title: row.get::<Option<String>, _>("title").unwrap_or_default(),
+            url,
+            released_at_iso: stored_iso_date(&release_date, &row),
+            release_date,
+        };
+        (row.get("anime_slug"), episode)
+    }))
+}
+
+/// Get the status and episode release history of an anime
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `anime_slug` - Anime slug
+///
+/// # Returns
+/// * `Ok(Some((status, episodes)))` - Episodes with their first-seen times
+/// * `Ok(None)` - Anime not in the database
+pub async fn get_episode_timeline(
+    pool: &PgPool,
+    anime_slug: &str,
+) -> RepositoryResult<Option<(String, Vec<(TimelineEpisode, DateTime<Utc>)>)>> {
+    let anime = sqlx::query("SELECT status FROM anime_details WHERE slug = $1")
+        .bind(anime_slug)
+        .fetch_optional(pool)
+        .await?;
+    let Some(anime) = anime else {
+        return Ok(None);
+    };
+
+    let rows = sqlx::query(
+        r#"
+        SELECT number, title, url, COALESCE(first_seen_at, created_at) AS first_seen_at
+        FROM episodes
+        WHERE anime_slug = $1
+        ORDER BY first_seen_at ASC, id ASC
+        "#,
+    )
+    .bind(anime_slug)
+    .fetch_all(pool)
+    .await?;
+
+    let episodes = rows
+        .into_iter()
+        .map(|row| {
+            let first_seen_at: DateTime<Utc> = row
+                .get::<Option<DateTime<Utc>>, _>("first_seen_at")
+                .unwrap_or_else(Utc::now);
+            (
+                TimelineEpisode {
+                    number: row.get::<Option<String>, _>("number").unwrap_or_default(),
+                    title: row.get::<Option<String>, _>("title").unwrap_or_default(),
+                    url: row.get("url"),
+                    first_seen_at: first_seen_at.to_rfc3339(),
+                },
+                first_seen_at,
+            )
+        })
+        .collect();
+
+    Ok(Some((
+        anime.get::<Option<String>, _>("status").unwrap_or_default(),
+        episodes,
+    )))
+}
+
 /// Delete all episodes for an anime by slug
 pub async fn delete_episodes_by_anime(pool: &PgPool, anime_slug: &str) -> RepositoryResult<u64> {
     let result = sqlx::query("DELETE FROM episodes WHERE anime_slug = $1")
@@ -469,34 +914,65 @@ pub async fn delete_episodes_by_anime(pool: &PgPool, anime_slug: &str) -> Reposi
 
 /// Save video sources for an episode to the database
 ///
-/// First deletes existing sources for the episode, then inserts new ones
+/// First deletes existing sources for the episode, then inserts new ones,
+/// and bumps the episode's updated_at, recording the change in the change
+/// feed, all in one transaction. Nothing is written if the stored sources
+/// are identical.
+///
+/// # Returns
+/// `Inserted` if the episode had no sources yet, `Updated` if they were
+/// replaced, or `Unchanged`
+#[instrument(skip_all, fields(episode_url = %episode_url, sources = sources.len()))]
 pub async fn save_video_sources(
     pool: &PgPool,
     episode_url: &str,
     sources: &[VideoSource],
-) -> RepositoryResult<()> {
+) -> RepositoryResult<WriteOutcome> {
+    let existing = get_video_sources(pool, episode_url).await?;
+    if content_hash(&existing) == content_hash(sources) {
+        return Ok(WriteOutcome::Unchanged);
+    }
+
+    let mut tx = pool.begin().await?;
+
     // Delete existing sources for this episode
     sqlx::query("DELETE FROM video_sources WHERE episode_url = $1")
         .bind(episode_url)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
 
     // Insert new sources
     for source in sources {
         sqlx::query(
             r#"
-            INSERT INTO video_sources (episode_url, server, quality, url, updated_at)
-            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            INSERT INTO video_sources (episode_url, server, quality, url, embed, updated_at)
+            VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
             "#,
         )
         .bind(episode_url)
         .bind(&source.server)
         .bind(&source.quality)
         .bind(&source.url)
-        .execute(pool)
+        .bind(source.embed.is_some())
+        .execute(&mut *tx)
         .await?;
     }
-    Ok(())
+
+    // New sources count as a change of the episode in the change feed
+    sqlx::query("UPDATE episodes SET updated_at = CURRENT_TIMESTAMP WHERE url = $1")
+        .bind(episode_url)
+        .execute(&mut *tx)
+        .await?;
+    let mut changes = PendingChanges::default();
+    changes.episode(episode_url, WriteOutcome::Updated);
+    changes.record(&mut tx).await?;
+    tx.commit().await?;
+
+    Ok(if existing.is_empty() {
+        WriteOutcome::Inserted
+    } else {
+        WriteOutcome::Updated
+    })
 }
 
 /// Get all video sources for an episode by URL
@@ -506,7 +982,7 @@ pub async fn get_video_sources(
 ) -> RepositoryResult<Vec<VideoSource>> {
     let rows = sqlx::query(
         r#"
-        SELECT server, quality, url
+        SELECT server, quality, url, embed
         FROM video_sources
         WHERE episode_url = $1
         ORDER BY id ASC
@@ -518,16 +994,35 @@ pub async fn get_video_sources(
 
     let sources = rows
         .into_iter()
-        .map(|row| VideoSource {
-            server: row.get::<Option<String>, _>("server").unwrap_or_default(),
-            quality: row.get::<Option<String>, _>("quality").unwrap_or_default(),
-            url: row.get::<Option<String>, _>("url").unwrap_or_default(),
+        .map(|row| {
+            let url = row.get::<Option<String>, _>("url").unwrap_or_default();
+            VideoSource {
+                server: row.get::<Option<String>, _>("server").unwrap_or_default(),
+                quality: row.get::<Option<String>, _>("quality").unwrap_or_default(),
+                embed: row
+                    .get::<bool, _>("embed")
+                    .then(|| embed_info(&url))
+                    .flatten(),
+                url,
+            }
         })
         .collect();
 
     Ok(sources)
 }
 
+/// Get which of the given URLs are stored video sources
+pub async fn get_known_video_source_urls(
+    pool: &PgPool,
+    urls: &[String],
+) -> RepositoryResult<HashSet<String>> {
+    let rows = sqlx::query("SELECT DISTINCT url FROM video_sources WHERE url = ANY($1)")
+        .bind(urls)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|row| row.get("url")).collect())
+}
+
 /// Delete all video sources for an episode by URL
 pub async fn delete_video_sources(pool: &PgPool, episode_url: &str) -> RepositoryResult<u64> {
     let result = sqlx::query("DELETE FROM video_sources WHERE episode_url = $1")
@@ -538,2698 +1033,12552 @@ pub async fn delete_video_sources(pool: &PgPool, episode_url: &str) -> Repositor
 }
 
 // ============================================================================
-// Batch Operations
+// Video Server Rules Repository
 // ============================================================================
 
-/// Save anime detail with its episodes in a single transaction
+/// Convert a database row to a VideoServerRule
+fn video_server_rule_from_row(row: &sqlx::postgres::PgRow) -> VideoServerRule {
+    VideoServerRule {
+        server: row.get("server"),
+        blocked: row.get("blocked"),
+        priority: row.get("priority"),
+        origin: VideoServerRuleOrigin::Admin,
+        updated_at: row
+            .get::<Option<DateTime<Utc>>, _>("updated_at")
+            .map(|time| time.to_rfc3339()),
+    }
+}
+
+/// Get the video server rules set by admins
+pub async fn get_video_server_rules(pool: &PgPool) -> RepositoryResult<Vec<VideoServerRule>> {
+    let rows = sqlx::query(
+        "SELECT server, blocked, priority, updated_at FROM video_server_rules ORDER BY server",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(video_server_rule_from_row).collect())
+}
+
+/// Create or replace the rule for a video server
 ///
-/// This ensures atomicity - either both anime detail and episodes are saved, or neither
-pub async fn save_anime_detail_with_episodes(
+/// # Arguments
+/// * `server` - Lowercased server name
+/// * `blocked` - Whether sources from the server are dropped
+/// * `priority` - Ordering priority, higher first
+pub async fn set_video_server_rule(
     pool: &PgPool,
-    slug: &str,
-    detail: &AnimeDetail,
-) -> RepositoryResult<()> {
-    let mut tx = pool.begin().await?;
-
-    // Save anime detail
-    sqlx::query(
+    server: &str,
+    blocked: bool,
+    priority: i32,
+) -> RepositoryResult<VideoServerRule> {
+    let row = sqlx::query(
         r#"
-        INSERT INTO anime_details (
-            slug, title, alternate_titles, poster, rating, trailer_url,
-            status, studio, release_date, duration, season, type,
-            total_episodes, director, casts, genres, synopsis, updated_at
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, CURRENT_TIMESTAMP)
-        ON CONFLICT (slug) DO UPDATE SET
-            title = EXCLUDED.title,
-            alternate_titles = EXCLUDED.alternate_titles,
-            poster = EXCLUDED.poster,
-            rating = EXCLUDED.rating,
-            trailer_url = EXCLUDED.trailer_url,
-            status = EXCLUDED.status,
-            studio = EXCLUDED.studio,
-            release_date = EXCLUDED.release_date,
-            duration = EXCLUDED.duration,
-            season = EXCLUDED.season,
-            type = EXCLUDED.type,
-            total_episodes = EXCLUDED.total_episodes,
-            director = EXCLUDED.director,
-            casts = EXCLUDED.casts,
-            genres = EXCLUDED.genres,
-            synopsis = EXCLUDED.synopsis,
+        INSERT INTO video_server_rules (server, blocked, priority, updated_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+        ON CONFLICT (server) DO UPDATE SET
+            blocked = EXCLUDED.blocked,
+            priority = EXCLUDED.priority,
             updated_at = CURRENT_TIMESTAMP
+        RETURNING server, blocked, priority, updated_at
         "#,
     )
-    .bind(slug)
-    .bind(&detail.title)
-    .bind(&detail.alternate_titles)
-    .bind(&detail.poster)
-    .bind(&detail.rating)
-    .bind(&detail.trailer_url)
-    .bind(&detail.status)
-    .bind(&detail.studio)
-    .bind(&detail.release_date)
-    .bind(&detail.duration)
-    .bind(&detail.season)
-    .bind(&detail.anime_type)
-    .bind(&detail.total_episodes)
-    .bind(&detail.director)
-    .bind(&detail.casts)
-    .bind(&detail.genres)
-    .bind(&detail.synopsis)
-    .execute(&mut *tx)
+    .bind(server)
+    .bind(blocked)
+    .bind(priority)
+    .fetch_one(pool)
     .await?;
+    Ok(video_server_rule_from_row(&row))
+}
 
-    // Save episodes
-    for episode in &detail.episodes {
-        sqlx::query(
-            r#"
-            INSERT INTO episodes (anime_slug, number, title, url, release_date, updated_at)
-            VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
-            ON CONFLICT (url) DO UPDATE SET
-                anime_slug = EXCLUDED.anime_slug,
-                number = EXCLUDED.number,
-                title = EXCLUDED.title,
-                release_date = EXCLUDED.release_date,
-                updated_at = CURRENT_TIMESTAMP
-            "#,
-        )
-        .bind(slug)
-        .bind(&episode.number)
-        .bind(&episode.title)
-        .bind(&episode.url)
-        .bind(&episode.release_date)
-        .execute(&mut *tx)
+/// Delete the rule for a video server
+///
+/// # Returns
+/// * `Ok(true)` - Rule deleted
+/// * `Ok(false)` - The server had no rule
+pub async fn delete_video_server_rule(pool: &PgPool, server: &str) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM video_server_rules WHERE server = $1")
+        .bind(server)
+        .execute(pool)
         .await?;
-    }
-
-    tx.commit().await?;
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
 // ============================================================================
-// Cache Layer
+// Feature Flags Repository
 // ============================================================================
 
-/// Default cache TTL in milliseconds (1 hour)
-pub const DEFAULT_CACHE_TTL_MS: i64 = 3600 * 1000;
-
-/// Check if cached data is still valid (not stale)
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `cache_key` - Unique identifier for the cached data (e.g., "updates", "completed", "anime:slug")
-/// * `max_age_ms` - Maximum age in milliseconds before cache is considered stale
-///
-/// # Returns
-/// * `Ok(true)` if cache exists and is fresh (less than max_age_ms old)
-/// * `Ok(false)` if cache doesn't exist or is stale
-pub async fn is_cache_valid(
-    pool: &PgPool,
-    cache_key: &str,
-    max_age_ms: i64,
-) -> RepositoryResult<bool> {
-    let row = sqlx::query(
-        r#"
-        SELECT last_fetched
-        FROM cache_metadata
-        WHERE cache_key = $1
-        "#,
-    )
-    .bind(cache_key)
-    .fetch_optional(pool)
-    .await?;
-
-    match row {
-        Some(row) => {
-            let last_fetched: DateTime<Utc> = row.get("last_fetched");
-            let now = Utc::now();
-            let age_ms = (now - last_fetched).num_milliseconds();
-            Ok(age_ms < max_age_ms)
-        }
-        None => Ok(false),
-    }
+/// Convert a database row to a FeatureFlag, or `None` for a feature this
+/// build doesn't know
+fn feature_flag_from_row(row: &sqlx::postgres::PgRow) -> Option<FeatureFlag> {
+    Some(FeatureFlag {
+        feature: Feature::parse(row.get("feature"))?,
+        enabled: row.get("enabled"),
+        reason: row.get("reason"),
+        updated_at: Some(row.get::<DateTime<Utc>, _>("updated_at").to_rfc3339()),
+    })
 }
 
-/// Update the cache timestamp for a given cache key
-///
-/// Creates a new cache entry if it doesn't exist, or updates the existing one.
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `cache_key` - Unique identifier for the cached data
+/// Get the feature flags set by operators
 ///
-/// # Returns
-/// * `Ok(())` on success
-pub async fn update_cache_timestamp(pool: &PgPool, cache_key: &str) -> RepositoryResult<()> {
-    sqlx::query(
-        r#"
-        INSERT INTO cache_metadata (cache_key, last_fetched)
-        VALUES ($1, CURRENT_TIMESTAMP)
-        ON CONFLICT (cache_key) DO UPDATE SET
-            last_fetched = CURRENT_TIMESTAMP
-        "#,
+/// Rows for features this build doesn't know (e.g. written by a newer
+/// version) are skipped.
+pub async fn get_feature_flags(pool: &PgPool) -> RepositoryResult<Vec<FeatureFlag>> {
+    let rows = sqlx::query(
+        "SELECT feature, enabled, reason, updated_at FROM feature_flags ORDER BY feature",
     )
-    .bind(cache_key)
-    .execute(pool)
+    .fetch_all(pool)
     .await?;
-
-    Ok(())
+    Ok(rows.iter().filter_map(feature_flag_from_row).collect())
 }
 
-/// Get the last fetched timestamp for a cache key
+/// Switch a feature on or off
 ///
 /// # Arguments
-/// * `pool` - Database connection pool
-/// * `cache_key` - Unique identifier for the cached data
-///
-/// # Returns
-/// * `Ok(Some(timestamp))` if cache entry exists
-/// * `Ok(None)` if cache entry doesn't exist
-pub async fn get_cache_timestamp(
+/// * `feature` - The feature
+/// * `enabled` - Whether its endpoints are served
+/// * `reason` - Why, if given
+/// * `updated_by` - User ID of the operator
+pub async fn set_feature_flag(
     pool: &PgPool,
-    cache_key: &str,
-) -> RepositoryResult<Option<DateTime<Utc>>> {
+    feature: Feature,
+    enabled: bool,
+    reason: Option<&str>,
+    updated_by: i32,
+) -> RepositoryResult<FeatureFlag> {
     let row = sqlx::query(
         r#"
-        SELECT last_fetched
-        FROM cache_metadata
-        WHERE cache_key = $1
+        INSERT INTO feature_flags (feature, enabled, reason, updated_by, updated_at)
+        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+        ON CONFLICT (feature) DO UPDATE SET
+            enabled = EXCLUDED.enabled,
+            reason = EXCLUDED.reason,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING feature, enabled, reason, updated_at
         "#,
     )
-    .bind(cache_key)
-    .fetch_optional(pool)
+    .bind(feature.as_str())
+    .bind(enabled)
+    .bind(reason)
+    .bind(updated_by)
+    .fetch_one(pool)
     .await?;
-
-    match row {
-        Some(row) => {
-            let last_fetched: DateTime<Utc> = row.get("last_fetched");
-            Ok(Some(last_fetched))
-        }
-        None => Ok(None),
-    }
+    Ok(feature_flag_from_row(&row).unwrap_or_else(|| FeatureFlag::default_for(feature)))
 }
 
-/// Delete a cache entry
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `cache_key` - Unique identifier for the cached data
+/// Delete the flag for a feature, enabling it again
 ///
 /// # Returns
-/// * `Ok(true)` if entry was deleted
-/// * `Ok(false)` if entry didn't exist
-pub async fn delete_cache_entry(pool: &PgPool, cache_key: &str) -> RepositoryResult<bool> {
-    let result = sqlx::query("DELETE FROM cache_metadata WHERE cache_key = $1")
-        .bind(cache_key)
+/// * `Ok(true)` - Flag deleted
+/// * `Ok(false)` - The feature had no flag
+pub async fn delete_feature_flag(pool: &PgPool, feature: Feature) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM feature_flags WHERE feature = $1")
+        .bind(feature.as_str())
         .execute(pool)
         .await?;
     Ok(result.rows_affected() > 0)
 }
 
-/// Delete all cache entries
-///
-/// # Returns
-/// * `Ok(count)` - Number of entries deleted
-pub async fn delete_all_cache_entries(pool: &PgPool) -> RepositoryResult<u64> {
-    let result = sqlx::query("DELETE FROM cache_metadata")
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected())
-}
-
 // ============================================================================
-// Crawled Anime Repository
+// Subtitle Tracks Repository
 // ============================================================================
 
-/// Save a single crawled anime to the database with upsert logic
+/// Save subtitle tracks for an episode to the database
 ///
-/// Uses ON CONFLICT UPDATE to update existing records based on slug
-pub async fn save_crawled_anime(pool: &PgPool, anime: &CrawledAnime) -> RepositoryResult<()> {
-    sqlx::query(
-        r#"
-        INSERT INTO crawled_anime (
-            slug, title, url, thumbnail, status, type, episode_status, updated_at
-        )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, CURRENT_TIMESTAMP)
-        ON CONFLICT (slug) DO UPDATE SET
-            title = EXCLUDED.title,
-            url = EXCLUDED.url,
-            thumbnail = EXCLUDED.thumbnail,
-            status = EXCLUDED.status,
-            type = EXCLUDED.type,
-            episode_status = EXCLUDED.episode_status,
-            updated_at = CURRENT_TIMESTAMP
-        "#,
-    )
-    .bind(&anime.slug)
-    .bind(&anime.title)
-    .bind(&anime.url)
-    .bind(&anime.thumbnail)
-    .bind(&anime.status)
-    .bind(&anime.anime_type)
-    .bind(&anime.episode_status)
-    .execute(pool)
-    .await?;
-
-    Ok(())
-}
-
-/// Save multiple crawled anime to the database with batch upsert for performance
+/// Replaces the episode's stored tracks. Nothing is written if they are
+/// identical.
 ///
-/// Uses a transaction to ensure atomicity and ON CONFLICT UPDATE for upsert logic
-pub async fn save_crawled_anime_batch(
+/// # Returns
+/// `Inserted` if the episode had no tracks yet, `Updated` if they were
+/// replaced, or `Unchanged`
+#[instrument(skip_all, fields(episode_url = %episode_url, tracks = tracks.len()))]
+pub async fn save_subtitle_tracks(
     pool: &PgPool,
-    anime_list: &[CrawledAnime],
-) -> RepositoryResult<()> {
-    if anime_list.is_empty() {
-        return Ok(());
+    episode_url: &str,
+    tracks: &[SubtitleTrack],
+) -> RepositoryResult<WriteOutcome> {
+    let existing = get_subtitle_tracks(pool, episode_url).await?;
+    if content_hash(&existing) == content_hash(tracks) {
+        return Ok(WriteOutcome::Unchanged);
     }
 
     let mut tx = pool.begin().await?;
-
-    for anime in anime_list {
+    sqlx::query("DELETE FROM subtitle_tracks WHERE episode_url = $1")
+        .bind(episode_url)
+        .execute(&mut *tx)
+        .await?;
+    for track in tracks {
         sqlx::query(
             r#"
-            INSERT INTO crawled_anime (
-                slug, title, url, thumbnail, status, type, episode_status, updated_at
-            )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, CURRENT_TIMESTAMP)
-            ON CONFLICT (slug) DO UPDATE SET
-                title = EXCLUDED.title,
-                url = EXCLUDED.url,
-                thumbnail = EXCLUDED.thumbnail,
-                status = EXCLUDED.status,
-                type = EXCLUDED.type,
-                episode_status = EXCLUDED.episode_status,
-                updated_at = CURRENT_TIMESTAMP
+            INSERT INTO subtitle_tracks (episode_url, url, language, label, is_default)
+            VALUES ($1, $2, $3, $4, $5)
             "#,
         )
-        .bind(&anime.slug)
-        .bind(&anime.title)
-        .bind(&anime.url)
-        .bind(&anime.thumbnail)
-        .bind(&anime.status)
-        .bind(&anime.anime_type)
-        .bind(&anime.episode_status)
+        .bind(episode_url)
+        .bind(&track.url)
+        .bind(&track.language)
+        .bind(&track.label)
+        .bind(track.default)
         .execute(&mut *tx)
         .await?;
     }
-
     tx.commit().await?;
-    Ok(())
-}
-
-/// Get the total count of crawled anime in the database
-pub async fn get_crawled_anime_count(pool: &PgPool) -> RepositoryResult<i64> {
-    let row = sqlx::query("SELECT COUNT(*) as count FROM crawled_anime")
-        .fetch_one(pool)
-        .await?;
 
-    let count: i64 = row.get("count");
-    Ok(count)
+    Ok(if existing.is_empty() {
+        WriteOutcome::Inserted
+    } else {
+        WriteOutcome::Updated
+    })
 }
 
-/// Get a crawled anime by slug
-pub async fn get_crawled_anime_by_slug(
+/// Get all subtitle tracks for an episode by URL
+pub async fn get_subtitle_tracks(
     pool: &PgPool,
-    slug: &str,
-) -> RepositoryResult<Option<CrawledAnimeRecord>> {
-    let row = sqlx::query(
+    episode_url: &str,
+) -> RepositoryResult<Vec<SubtitleTrack>> {
+    let rows = sqlx::query(
         r#"
-        SELECT id, slug, title, url, thumbnail, status, type, episode_status, created_at, updated_at
-        FROM crawled_anime
-        WHERE slug = $1
+        SELECT url, language, label, is_default
+        FROM subtitle_tracks
+        WHERE episode_url = $1
+        ORDER BY id ASC
         "#,
     )
-    .bind(slug)
-    .fetch_optional(pool)
+    .bind(episode_url)
+    .fetch_all(pool)
     .await?;
 
-    match row {
-        Some(row) => {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            let updated_at: DateTime<Utc> = row.get("updated_at");
+    Ok(rows
+        .iter()
+        .map(|row| SubtitleTrack {
+            url: row.get("url"),
+            language: row.get("language"),
+            label: row.get("label"),
+            default: row.get("is_default"),
+        })
+        .collect())
+}
 
-            Ok(Some(CrawledAnimeRecord {
-                id: row.get("id"),
-                slug: row.get("slug"),
-                title: row.get("title"),
-                url: row.get("url"),
-                thumbnail: row
-                    .get::<Option<String>, _>("thumbnail")
-                    .unwrap_or_default(),
-                status: row.get::<Option<String>, _>("status").unwrap_or_default(),
-                anime_type: row.get::<Option<String>, _>("type").unwrap_or_default(),
-                episode_status: row
-                    .get::<Option<String>, _>("episode_status")
-                    .unwrap_or_default(),
-                created_at: created_at.to_rfc3339(),
-                updated_at: updated_at.to_rfc3339(),
-            }))
-        }
-        None => Ok(None),
-    }
+/// Check whether a URL is a stored subtitle track of some episode
+pub async fn is_known_subtitle_url(pool: &PgPool, url: &str) -> RepositoryResult<bool> {
+    let known: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM subtitle_tracks WHERE url = $1)")
+            .bind(url)
+            .fetch_one(pool)
+            .await?;
+    Ok(known)
 }
 
-/// Get all crawled anime from the database
-pub async fn get_all_crawled_anime(pool: &PgPool) -> RepositoryResult<Vec<CrawledAnimeRecord>> {
+/// Delete all subtitle tracks for an episode by URL
+pub async fn delete_subtitle_tracks(pool: &PgPool, episode_url: &str) -> RepositoryResult<u64> {
+    let result = sqlx::query("DELETE FROM subtitle_tracks WHERE episode_url = $1")
+        .bind(episode_url)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// Sitemap Repository
+// ============================================================================
+
+/// Get the sitemap partials of stored anime
+///
+/// Anime are paged by slug, `page_size` per page.
+pub async fn get_sitemap_pages(
+    pool: &PgPool,
+    page_size: i64,
+) -> RepositoryResult<Vec<SitemapPage>> {
     let rows = sqlx::query(
         r#"
-        SELECT id, slug, title, url, thumbnail, status, type, episode_status, created_at, updated_at
-        FROM crawled_anime
-        ORDER BY updated_at DESC
+        SELECT page, MAX(updated_at) AS updated_at
+        FROM (
+            SELECT (row_number() OVER (ORDER BY slug) - 1) / $1 + 1 AS page, updated_at
+            FROM anime_details
+        ) paged
+        GROUP BY page
+        ORDER BY page
         "#,
     )
+    .bind(page_size)
     .fetch_all(pool)
     .await?;
 
-    let anime_list = rows
-        .into_iter()
-        .map(|row| {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            let updated_at: DateTime<Utc> = row.get("updated_at");
-
-            CrawledAnimeRecord {
-                id: row.get("id"),
-                slug: row.get("slug"),
-                title: row.get("title"),
-                url: row.get("url"),
-                thumbnail: row
-                    .get::<Option<String>, _>("thumbnail")
-                    .unwrap_or_default(),
-                status: row.get::<Option<String>, _>("status").unwrap_or_default(),
-                anime_type: row.get::<Option<String>, _>("type").unwrap_or_default(),
-                episode_status: row
-                    .get::<Option<String>, _>("episode_status")
-                    .unwrap_or_default(),
-                created_at: created_at.to_rfc3339(),
-                updated_at: updated_at.to_rfc3339(),
-            }
+    Ok(rows
+        .iter()
+        .map(|row| SitemapPage {
+            page: row.get("page"),
+            last_modified: row
+                .get::<Option<DateTime<Utc>>, _>("updated_at")
+                .map(|time| time.to_rfc3339()),
         })
-        .collect();
-
-    Ok(anime_list)
-}
-
-/// Delete a crawled anime by slug
-pub async fn delete_crawled_anime(pool: &PgPool, slug: &str) -> RepositoryResult<bool> {
-    let result = sqlx::query("DELETE FROM crawled_anime WHERE slug = $1")
-        .bind(slug)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected() > 0)
-}
-
-/// Delete all crawled anime from the database
-pub async fn delete_all_crawled_anime(pool: &PgPool) -> RepositoryResult<u64> {
-    let result = sqlx::query("DELETE FROM crawled_anime")
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected())
+        .collect())
 }
 
-/// Create a new user with email and password
+/// Get the anime on one sitemap partial
 ///
 /// # Arguments
-/// * `pool` - Database connection pool
-/// * `email` - User's email address
-/// * `password_hash` - Bcrypt hashed password
-/// * `name` - Optional display name
+/// * `page` - Page number, starting at 1
+/// * `page_size` - Anime per page
 ///
 /// # Returns
-/// * `Ok(User)` - The created user
-/// * `Err(RepositoryError::EmailAlreadyExists)` - If email is already registered
-pub async fn create_user(
+/// * `Ok(Vec<SitemapEntry>)` - The anime, none for a page past the end
+pub async fn get_sitemap_anime(
     pool: &PgPool,
-    email: &str,
-    password_hash: &str,
-    name: Option<&str>,
-) -> RepositoryResult<User> {
-    let row = sqlx::query(
+    page: i64,
+    page_size: i64,
+) -> RepositoryResult<Vec<SitemapEntry>> {
+    let Some(offset) = (page - 1).max(0).checked_mul(page_size) else {
+        return Ok(Vec::new());
+    };
+    let rows = sqlx::query(
         r#"
-        INSERT INTO users (email, password_hash, name, created_at, updated_at)
-        VALUES ($1, $2, $3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
-        RETURNING id, email, name, avatar, created_at
+        SELECT slug, updated_at
+        FROM anime_details
+        ORDER BY slug
+        LIMIT $1 OFFSET $2
         "#,
     )
-    .bind(email)
-    .bind(password_hash)
-    .bind(name)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref db_err) = e {
-            if db_err.constraint() == Some("users_email_key") {
-                return RepositoryError::EmailAlreadyExists;
-            }
-        }
-        RepositoryError::DatabaseError(e)
-    })?;
+    .bind(page_size)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
 
-    let created_at: DateTime<Utc> = row.get("created_at");
-    Ok(User {
-        id: row.get("id"),
-        email: row.get("email"),
-        name: row.get("name"),
-        avatar: row.get("avatar"),
-        created_at: created_at.to_rfc3339(),
-    })
+    Ok(rows
+        .iter()
+        .map(|row| SitemapEntry {
+            slug: row.get("slug"),
+            last_modified: row
+                .get::<Option<DateTime<Utc>>, _>("updated_at")
+                .map(|time| time.to_rfc3339()),
+        })
+        .collect())
 }
 
-/// Create a new user with Google OAuth
+// ============================================================================
+// Batch Operations
+// ============================================================================
+
+/// Save anime detail with its episodes in a single transaction
 ///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `email` - User's email from Google
-/// * `google_id` - Google user ID
-/// * `name` - User's name from Google
-/// * `avatar` - Optional profile picture URL
+/// This ensures atomicity - either both anime detail and episodes are saved, or neither,
+/// along with their events in the change feed. Rows whose content hash matches are
+/// left untouched.
 ///
 /// # Returns
-/// * `Ok(User)` - The created user
-/// * `Err(RepositoryError)` - If creation fails
-pub async fn create_google_user(
+/// Whether the detail changed, and how many episodes changed
+#[instrument(skip_all, fields(slug = %slug, episodes = detail.episodes.len()))]
+pub async fn save_anime_detail_with_episodes(
     pool: &PgPool,
-    email: &str,
-    google_id: &str,
-    name: &str,
-    avatar: Option<&str>,
-) -> RepositoryResult<User> {
+    slug: &str,
+    detail: &AnimeDetail,
+) -> RepositoryResult<AnimeDetailChanges> {
+    let mut tx = pool.begin().await?;
+
+    // Save anime detail
     let row = sqlx::query(
         r#"
-        INSERT INTO users (email, google_id, name, avatar, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
-        RETURNING id, email, name, avatar, created_at
+        INSERT INTO anime_details (
+            slug, title, alternate_titles, poster, rating, trailer_url,
+            status, studio, release_date, duration, season, type,
+            total_episodes, director, casts, genres, synopsis, content_hash, age_rating, updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, CURRENT_TIMESTAMP)
+        ON CONFLICT (slug) DO UPDATE SET
+            title = EXCLUDED.title,
+            alternate_titles = EXCLUDED.alternate_titles,
+            poster = EXCLUDED.poster,
+            rating = EXCLUDED.rating,
+            trailer_url = EXCLUDED.trailer_url,
+            status = EXCLUDED.status,
+            studio = EXCLUDED.studio,
+            release_date = EXCLUDED.release_date,
+            duration = EXCLUDED.duration,
+            season = EXCLUDED.season,
+            type = EXCLUDED.type,
+            total_episodes = EXCLUDED.total_episodes,
+            director = EXCLUDED.director,
+            casts = EXCLUDED.casts,
+            genres = EXCLUDED.genres,
+            synopsis = EXCLUDED.synopsis,
+            content_hash = EXCLUDED.content_hash,
+            age_rating = EXCLUDED.age_rating,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE anime_details.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+        RETURNING (xmax = 0) AS inserted
         "#,
     )
-    .bind(email)
-    .bind(google_id)
-    .bind(name)
-    .bind(avatar)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref db_err) = e {
-            if db_err.constraint() == Some("users_email_key") {
-                return RepositoryError::EmailAlreadyExists;
-            }
-        }
-        RepositoryError::DatabaseError(e)
-    })?;
+    .bind(slug)
+    .bind(&detail.title)
+    .bind(&detail.alternate_titles)
+    .bind(&detail.poster)
+    .bind(&detail.rating)
+    .bind(&detail.trailer_url)
+    .bind(&detail.status)
+    .bind(&detail.studio)
+    .bind(&detail.release_date)
+    .bind(&detail.duration)
+    .bind(&detail.season)
+    .bind(&detail.anime_type)
+    .bind(&detail.total_episodes)
+    .bind(&detail.director)
+    .bind(&detail.casts)
+    .bind(&detail.genres)
+    .bind(&detail.synopsis)
+    .bind(anime_detail_hash(detail))
+    .bind(AgeRating::from_genres(&detail.genres).as_str())
+    .fetch_optional(&mut *tx)
+    .await?;
+    let detail_outcome = upsert_outcome(row);
+    let mut changes = PendingChanges::default();
+    changes.anime(slug, detail_outcome);
 
-    let created_at: DateTime<Utc> = row.get("created_at");
-    Ok(User {
-        id: row.get("id"),
-        email: row.get("email"),
-        name: row.get("name"),
-        avatar: row.get("avatar"),
-        created_at: created_at.to_rfc3339(),
+    // Record the scrape even if nothing changed
+    sqlx::query("UPDATE anime_details SET scraped_at = CURRENT_TIMESTAMP WHERE slug = $1")
+        .bind(slug)
+        .execute(&mut *tx)
+        .await?;
+
+    // Save episodes
+    let mut episodes = ChangeCount::default();
+    for episode in &detail.episodes {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO episodes (anime_slug, number, title, url, release_date, content_hash, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP)
+            ON CONFLICT (url) DO UPDATE SET
+                anime_slug = EXCLUDED.anime_slug,
+                number = EXCLUDED.number,
+                title = EXCLUDED.title,
+                release_date = EXCLUDED.release_date,
+                content_hash = EXCLUDED.content_hash,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE episodes.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+            RETURNING (xmax = 0) AS inserted
+            "#,
+        )
+        .bind(slug)
+        .bind(&episode.number)
+        .bind(&episode.title)
+        .bind(&episode.url)
+        .bind(&episode.release_date)
+        .bind(episode_hash(slug, episode))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let outcome = upsert_outcome(row);
+        episodes.record_write(outcome);
+        changes.episode(&episode.url, outcome);
+    }
+
+    changes.record(&mut tx).await?;
+    tx.commit().await?;
+    Ok(AnimeDetailChanges {
+        detail: detail_outcome,
+        episodes,
     })
 }
 
-/// Find a user by email address
+// ============================================================================
+// Changes Repository
+// ============================================================================
+
+/// Lock held by transactions writing to the change feed, numbering events
+/// in commit order
+const CHANGE_FEED_LOCK: i64 = 0x6368_616e_6765;
+
+/// Anime and episodes a transaction inserted or updated, recorded in the
+/// change feed before it commits
+#[derive(Debug, Default)]
+struct PendingChanges {
+    /// Slugs of changed anime, and whether each was inserted
+    anime: Vec<(String, bool)>,
+    /// URLs of changed episodes, and whether each was inserted
+    episodes: Vec<(String, bool)>,
+}
+
+impl PendingChanges {
+    fn anime(&mut self, slug: &str, outcome: WriteOutcome) {
+        if outcome.changed() {
+            self.anime
+                .push((slug.to_string(), outcome == WriteOutcome::Inserted));
+        }
+    }
+
+    fn episode(&mut self, url: &str, outcome: WriteOutcome) {
+        if outcome.changed() {
+            self.episodes
+                .push((url.to_string(), outcome == WriteOutcome::Inserted));
+        }
+    }
+
+    /// Record the changes in the change feed
+    ///
+    /// Call it on the transaction that made the changes, right before it
+    /// commits: the lock it takes is held until then, so a reader paging by
+    /// event ID never passes an event that's still being written.
+    async fn record(&self, conn: &mut PgConnection) -> RepositoryResult<()> {
+        if self.anime.is_empty() && self.episodes.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(CHANGE_FEED_LOCK)
+            .execute(&mut *conn)
+            .await?;
+
+        if !self.anime.is_empty() {
+            let (slugs, created): (Vec<_>, Vec<_>) = self.anime.iter().cloned().unzip();
+            sqlx::query(
+                r#"
+                INSERT INTO change_events (kind, entity_id, anime_slug, title, created)
+                SELECT 'anime', a.id, a.slug, a.title, c.created
+                FROM UNNEST($1::TEXT[], $2::BOOLEAN[]) WITH ORDINALITY AS c(slug, created, n)
+                JOIN anime_details a ON a.slug = c.slug
+                ORDER BY c.n
+                "#,
+            )
+            .bind(&slugs)
+            .bind(&created)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        if !self.episodes.is_empty() {
+            let (urls, created): (Vec<_>, Vec<_>) = self.episodes.iter().cloned().unzip();
+            sqlx::query(
+                r#"
+                INSERT INTO change_events (kind, entity_id, anime_slug, title, number, url, created)
+                SELECT 'episode', e.id, e.anime_slug, e.title, e.number, e.url, c.created
+                FROM UNNEST($1::TEXT[], $2::BOOLEAN[]) WITH ORDINALITY AS c(url, created, n)
+                JOIN episodes e ON e.url = c.url
+                ORDER BY c.n
+                "#,
+            )
+            .bind(&urls)
+            .bind(&created)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Position in the change feed: the ID of the last returned event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChangeCursor {
+    /// ID of the last returned event
+    pub id: i64,
+}
+
+impl ChangeCursor {
+    /// Encode as an opaque string
+    pub fn encode(&self) -> String {
+        self.id.to_string()
+    }
+
+    /// Decode a cursor produced by `encode`
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let id: i64 = cursor.parse().ok()?;
+        (id > 0).then_some(Self { id })
+    }
+}
+
+/// Position in the change feed before it was read from change_events:
+/// changes were ordered by (updated_at, kind, id) of the anime or episode row
 ///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `email` - Email address to search for
+/// Clients may still hold these; [`resolve_change_cursor`] maps them to an
+/// event cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LegacyChangeCursor {
+    /// Update time of the last returned change
+    pub updated_at: DateTime<Utc>,
+    /// Kind of the last returned change
+    pub kind: ChangeKind,
+    /// Row ID of the last returned change
+    pub id: i32,
+}
+
+impl LegacyChangeCursor {
+    /// Decode a cursor of the form "<micros>.<kind>.<id>"
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.split('.');
+        let micros: i64 = parts.next()?.parse().ok()?;
+        let kind = match parts.next()? {
+            "0" => ChangeKind::Anime,
+            "1" => ChangeKind::Episode,
+            _ => return None,
+        };
+        let id: i32 = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            updated_at: DateTime::from_timestamp_micros(micros)?,
+            kind,
+            id,
+        })
+    }
+}
+
+/// Decode a cursor sent by a client, of either the current or legacy form
+///
+/// Legacy cursors resume before the first event ordered after the change
+/// they point at. change_events was started in that same order, with
+/// `entity_id` as the row ID and 'anime' sorting before 'episode' as kind
+/// 0 did before 1, so a client holding one sees no change twice or never.
 ///
 /// # Returns
-/// * `Ok(Some(user, password_hash))` - User found with optional password hash
-/// * `Ok(None)` - User not found
-pub async fn find_user_by_email(
-    pool: &PgPool,
-    email: &str,
-) -> RepositoryResult<Option<(User, Option<String>)>> {
-    let row = sqlx::query(
+/// * `Ok(Some(ChangeCursor))` - Where to resume
+/// * `Ok(None)` - Not a valid cursor of either form
+pub async fn resolve_change_cursor(
+    executor: impl PgExecutor<'_>,
+    cursor: &str,
+) -> RepositoryResult<Option<ChangeCursor>> {
+    if let Some(cursor) = ChangeCursor::decode(cursor) {
+        return Ok(Some(cursor));
+    }
+    let Some(legacy) = LegacyChangeCursor::decode(cursor) else {
+        return Ok(None);
+    };
+    let kind = match legacy.kind {
+        ChangeKind::Anime => "anime",
+        ChangeKind::Episode => "episode",
+    };
+
+    let id = sqlx::query_scalar(
         r#"
-        SELECT id, email, password_hash, name, avatar, created_at
-        FROM users
-        WHERE email = $1
+        SELECT COALESCE(
+            (SELECT MIN(id) - 1 FROM change_events
+             WHERE (occurred_at, kind, entity_id) > ($1, $2, $3)),
+            (SELECT COALESCE(MAX(id), 0) FROM change_events)
+        )
         "#,
     )
-    .bind(email)
-    .fetch_optional(pool)
+    .bind(legacy.updated_at)
+    .bind(kind)
+    .bind(legacy.id)
+    .fetch_one(executor)
     .await?;
-
-    match row {
-        Some(row) => {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            let user = User {
-                id: row.get("id"),
-                email: row.get("email"),
-                name: row.get("name"),
-                avatar: row.get("avatar"),
-                created_at: created_at.to_rfc3339(),
-            };
-            let password_hash: Option<String> = row.get("password_hash");
-            Ok(Some((user, password_hash)))
-        }
-        None => Ok(None),
-    }
+    Ok(Some(ChangeCursor { id }))
 }
 
-/// Find a user by Google ID
+/// Get the inserts and updates of anime and episodes recorded after `since`
+///
+/// Every write of changed content is an event, so a record changed twice
+/// is listed twice; a save that changes nothing records none. Events carry
+/// the record as it was written.
 ///
 /// # Arguments
-/// * `pool` - Database connection pool
-/// * `google_id` - Google user ID to search for
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `since` - Only events strictly after this time
+/// * `after` - Resume after this cursor (from a previous page)
+/// * `limit` - Maximum number of changes
 ///
 /// # Returns
-/// * `Ok(Some(User))` - User found
-/// * `Ok(None)` - User not found
-pub async fn find_user_by_google_id(
-    pool: &PgPool,
-    google_id: &str,
-) -> RepositoryResult<Option<User>> {
-    let row = sqlx::query(
+/// Changes in the order they were committed and the cursor of each
+pub async fn get_changes_since(
+    executor: impl PgExecutor<'_>,
+    since: DateTime<Utc>,
+    after: Option<ChangeCursor>,
+    limit: i64,
+) -> RepositoryResult<Vec<(ChangeEntry, ChangeCursor)>> {
+    let rows = sqlx::query(
         r#"
-        SELECT id, email, name, avatar, created_at
-        FROM users
-        WHERE google_id = $1
+        SELECT id, kind, anime_slug, title, number, url, created, occurred_at
+        FROM change_events
+        WHERE occurred_at > $1 AND id > $2
+        ORDER BY id
+        LIMIT $3
         "#,
     )
-    .bind(google_id)
-    .fetch_optional(pool)
+    .bind(since)
+    .bind(after.map_or(0, |cursor| cursor.id))
+    .bind(limit)
+    .fetch_all(executor)
     .await?;
 
-    match row {
-        Some(row) => {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            Ok(Some(User {
-                id: row.get("id"),
-                email: row.get("email"),
-                name: row.get("name"),
-                avatar: row.get("avatar"),
-                created_at: created_at.to_rfc3339(),
-            }))
-        }
-        None => Ok(None),
-    }
+    Ok(rows.iter().map(change_from_row).collect())
 }
 
-/// Find a user by ID
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID to search for
+/// A change feed entry and its cursor from a change_events row
+fn change_from_row(row: &sqlx::postgres::PgRow) -> (ChangeEntry, ChangeCursor) {
+    let kind = if row.get::<String, _>("kind") == "anime" {
+        ChangeKind::Anime
+    } else {
+        ChangeKind::Episode
+    };
+    let anime_slug: String = row.get("anime_slug");
+    let url: Option<String> = row.get("url");
+    let occurred_at: DateTime<Utc> = row.get("occurred_at");
+
+    let entry = ChangeEntry {
+        kind,
+        slug: match (kind, &url) {
+            (ChangeKind::Episode, Some(url)) => extract_slug_from_url(url),
+            _ => anime_slug.clone(),
+        },
+        anime_slug,
+        title: row.get::<Option<String>, _>("title").unwrap_or_default(),
+        number: row.get("number"),
+        url,
+        created: row.get("created"),
+        updated_at: occurred_at.to_rfc3339(),
+    };
+    (entry, ChangeCursor { id: row.get("id") })
+}
+
+/// Get where a consumer of the change feed has got to
 ///
-/// # Returns
-/// * `Ok(Some(User))` - User found
-/// * `Ok(None)` - User not found
-pub async fn find_user_by_id(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<User>> {
-    let row = sqlx::query(
+/// A consumer seen for the first time starts at the newest event, rather
+/// than replaying the whole log. The position stays locked until the
+/// transaction `conn` belongs to ends, so only one dispatcher reads from it
+/// at a time.
+pub async fn lock_change_feed_position(
+    conn: &mut PgConnection,
+    consumer: &str,
+) -> RepositoryResult<ChangeCursor> {
+    sqlx::query(
         r#"
-        SELECT id, email, name, avatar, created_at
-        FROM users
-        WHERE id = $1
+        INSERT INTO change_feed_positions (consumer, last_event_id)
+        SELECT $1, COALESCE(MAX(id), 0) FROM change_events
+        ON CONFLICT (consumer) DO NOTHING
         "#,
     )
-    .bind(user_id)
-    .fetch_optional(pool)
+    .bind(consumer)
+    .execute(&mut *conn)
     .await?;
 
-    match row {
-        Some(row) => {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            Ok(Some(User {
-                id: row.get("id"),
-                email: row.get("email"),
-                name: row.get("name"),
-                avatar: row.get("avatar"),
-                created_at: created_at.to_rfc3339(),
-            }))
-        }
-        None => Ok(None),
-    }
+    let id = sqlx::query_scalar(
+        "SELECT last_event_id FROM change_feed_positions WHERE consumer = $1 FOR UPDATE",
+    )
+    .bind(consumer)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(ChangeCursor { id })
 }
 
-/// Link a Google account to an existing user
+/// Delete change events from before `before` that every consumer has read
 ///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID to link
-/// * `google_id` - Google user ID to link
+/// Events past the position of any consumer in change_feed_positions are
+/// kept however old they are, so a consumer that fell behind (such as the
+/// webhook relay while its webhook is down) doesn't miss them.
 ///
 /// # Returns
-/// * `Ok(())` - Successfully linked
-/// * `Err(RepositoryError)` - If linking fails
-pub async fn link_google_account(
+/// * `Ok(count)` - Number of events deleted
+pub async fn delete_old_change_events(
     pool: &PgPool,
-    user_id: i32,
-    google_id: &str,
-) -> RepositoryResult<()> {
-    sqlx::query(
+    before: DateTime<Utc>,
+) -> RepositoryResult<u64> {
+    let result = sqlx::query(
         r#"
-        UPDATE users
-        SET google_id = $1, updated_at = CURRENT_TIMESTAMP
-        WHERE id = $2
+        DELETE FROM change_events
+        WHERE occurred_at < $1
+          AND id <= COALESCE((SELECT MIN(last_event_id) FROM change_feed_positions), id)
         "#,
     )
-    .bind(google_id)
-    .bind(user_id)
+    .bind(before)
     .execute(pool)
     .await?;
+    Ok(result.rows_affected())
+}
 
+/// Move a consumer of the change feed past `cursor`
+pub async fn set_change_feed_position(
+    executor: impl PgExecutor<'_>,
+    consumer: &str,
+    cursor: ChangeCursor,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE change_feed_positions
+        SET last_event_id = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE consumer = $1
+        "#,
+    )
+    .bind(consumer)
+    .bind(cursor.id)
+    .execute(executor)
+    .await?;
     Ok(())
 }
 
-/// Delete a user by ID
+// ============================================================================
+// Cache Layer
+// ============================================================================
+
+/// Default cache TTL in milliseconds (1 hour)
+pub const DEFAULT_CACHE_TTL_MS: i64 = 3600 * 1000;
+
+/// Check if cached data is still valid (not stale)
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID to delete
+/// * `cache_key` - Unique identifier for the cached data (e.g., "updates", "completed", "anime:slug")
+/// * `max_age_ms` - Maximum age in milliseconds before cache is considered stale
 ///
 /// # Returns
-/// * `Ok(true)` - User was deleted
-/// * `Ok(false)` - User not found
-pub async fn delete_user(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
-    let result = sqlx::query("DELETE FROM users WHERE id = $1")
-        .bind(user_id)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected() > 0)
+/// * `Ok(true)` if cache exists and is fresh (less than max_age_ms old)
+/// * `Ok(false)` if cache doesn't exist or is stale
+pub async fn is_cache_valid(
+    pool: &PgPool,
+    cache_key: &str,
+    max_age_ms: i64,
+) -> RepositoryResult<bool> {
+    let row = sqlx::query(
+        r#"
+        SELECT last_fetched
+        FROM cache_metadata
+        WHERE cache_key = $1
+        "#,
+    )
+    .bind(cache_key)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => {
+            let last_fetched: DateTime<Utc> = row.get("last_fetched");
+            let now = Utc::now();
+            let age_ms = (now - last_fetched).num_milliseconds();
+            Ok(age_ms < max_age_ms)
+        }
+        None => Ok(false),
+    }
 }
 
-/// Add an anime to user's favorites
+/// Update the cache timestamp for a given cache key
+///
+/// Creates a new cache entry if it doesn't exist, or updates the existing one.
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `anime_slug` - Anime slug identifier
-/// * `anime_title` - Anime title for display
-/// * `thumbnail` - Thumbnail image URL
+/// * `cache_key` - Unique identifier for the cached data
 ///
 /// # Returns
-/// * `Ok(UserFavorite)` - The created favorite
-/// * `Err(RepositoryError::Conflict)` - If already favorited
-pub async fn add_favorite(
-    pool: &PgPool,
-    user_id: i32,
-    anime_slug: &str,
-    anime_title: &str,
-    thumbnail: &str,
-) -> RepositoryResult<UserFavorite> {
-    let row = sqlx::query(
+/// * `Ok(())` on success
+pub async fn update_cache_timestamp(pool: &PgPool, cache_key: &str) -> RepositoryResult<()> {
+    sqlx::query(
         r#"
-        INSERT INTO user_favorites (user_id, anime_slug, anime_title, thumbnail, created_at)
-        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
-        RETURNING anime_slug, anime_title, thumbnail, created_at
+        INSERT INTO cache_metadata (cache_key, last_fetched)
+        VALUES ($1, CURRENT_TIMESTAMP)
+        ON CONFLICT (cache_key) DO UPDATE SET
+            last_fetched = CURRENT_TIMESTAMP
         "#,
     )
-    .bind(user_id)
-    .bind(anime_slug)
-    .bind(anime_title)
-    .bind(thumbnail)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref db_err) = e {
-            if db_err.constraint() == Some("user_favorites_user_anime_unique") {
-                return RepositoryError::Conflict("Anime already in favorites".to_string());
-            }
-        }
-        RepositoryError::DatabaseError(e)
-    })?;
+    .bind(cache_key)
+    .execute(pool)
+    .await?;
 
-    let created_at: DateTime<Utc> = row.get("created_at");
-    Ok(UserFavorite {
-        anime_slug: row.get("anime_slug"),
-        anime_title: row.get("anime_title"),
-        thumbnail: row
-            .get::<Option<String>, _>("thumbnail")
-            .unwrap_or_default(),
-        created_at: created_at.to_rfc3339(),
-    })
+    Ok(())
 }
 
-/// Get all favorites for a user
+/// Get the last fetched timestamp for a cache key
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
+/// * `cache_key` - Unique identifier for the cached data
 ///
 /// # Returns
-/// * `Ok(Vec<UserFavorite>)` - List of favorites
-pub async fn get_favorites(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<UserFavorite>> {
-    let rows = sqlx::query(
+/// * `Ok(Some(timestamp))` if cache entry exists
+/// * `Ok(None)` if cache entry doesn't exist
+pub async fn get_cache_timestamp(
+    pool: &PgPool,
+    cache_key: &str,
+) -> RepositoryResult<Option<DateTime<Utc>>> {
+    let row = sqlx::query(
         r#"
-        SELECT anime_slug, anime_title, thumbnail, created_at
-        FROM user_favorites
-        WHERE user_id = $1
-        ORDER BY created_at DESC
+        SELECT last_fetched
+        FROM cache_metadata
+        WHERE cache_key = $1
         "#,
     )
-    .bind(user_id)
-    .fetch_all(pool)
+    .bind(cache_key)
+    .fetch_optional(pool)
     .await?;
 
-    let favorites = rows
-        .into_iter()
-        .map(|row| {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            UserFavorite {
-                anime_slug: row.get("anime_slug"),
-                anime_title: row.get("anime_title"),
-                thumbnail: row
-                    .get::<Option<String>, _>("thumbnail")
-                    .unwrap_or_default(),
-                created_at: created_at.to_rfc3339(),
-            }
-        })
-        .collect();
-
-    Ok(favorites)
+    match row {
+        Some(row) => {
+            let last_fetched: DateTime<Utc> = row.get("last_fetched");
+            Ok(Some(last_fetched))
+        }
+        None => Ok(None),
+    }
 }
 
-/// Remove an anime from user's favorites
+/// Delete a cache entry
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `anime_slug` - Anime slug to remove
+/// * `cache_key` - Unique identifier for the cached data
 ///
 /// # Returns
-/// * `Ok(true)` - Favorite was removed
-/// * `Ok(false)` - Favorite not found
-pub async fn remove_favorite(
-    pool: &PgPool,
-    user_id: i32,
-    anime_slug: &str,
-) -> RepositoryResult<bool> {
-    let result = sqlx::query("DELETE FROM user_favorites WHERE user_id = $1 AND anime_slug = $2")
-        .bind(user_id)
-        .bind(anime_slug)
+/// * `Ok(true)` if entry was deleted
+/// * `Ok(false)` if entry didn't exist
+pub async fn delete_cache_entry(pool: &PgPool, cache_key: &str) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM cache_metadata WHERE cache_key = $1")
+        .bind(cache_key)
         .execute(pool)
         .await?;
     Ok(result.rows_affected() > 0)
 }
 
-/// Check if an anime is in user's favorites
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `anime_slug` - Anime slug to check
+/// Delete all cache entries
 ///
 /// # Returns
-/// * `Ok(true)` - Anime is favorited
-/// * `Ok(false)` - Anime is not favorited
-pub async fn is_favorite(pool: &PgPool, user_id: i32, anime_slug: &str) -> RepositoryResult<bool> {
-    let row = sqlx::query("SELECT 1 FROM user_favorites WHERE user_id = $1 AND anime_slug = $2")
-        .bind(user_id)
-        .bind(anime_slug)
-        .fetch_optional(pool)
+/// * `Ok(count)` - Number of entries deleted
+pub async fn delete_all_cache_entries(pool: &PgPool) -> RepositoryResult<u64> {
+    let result = sqlx::query("DELETE FROM cache_metadata")
+        .execute(pool)
         .await?;
-    Ok(row.is_some())
+    Ok(result.rows_affected())
 }
 
-/// Subscribe to an anime series
+// ============================================================================
+// Search Cache
+// ============================================================================
+
+/// Normalize a search query for use as a cache key
 ///
-/// # Arguments
+/// Lowercases, trims, and collapses runs of whitespace, so "One  Piece "
+/// and "one piece" share an entry.
+pub fn normalize_search_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Get cached search results and count the hit
+///
+/// Empty results have their own, usually shorter, lifetime so a query that
+/// starts matching soon shows up quickly.
+///
+/// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `anime_slug` - Anime slug identifier
-/// * `anime_title` - Anime title for display
-/// * `thumbnail` - Thumbnail image URL
+/// * `query` - Normalized query
+/// * `max_age_ms` - Lifetime of non-empty results
+/// * `empty_max_age_ms` - Lifetime of empty results
 ///
 /// # Returns
-/// * `Ok(UserSubscription)` - The created subscription
-/// * `Err(RepositoryError::Conflict)` - If already subscribed
-pub async fn add_subscription(
+/// * `Ok(Some(results))` - Fresh cached results (possibly empty)
+/// * `Ok(None)` - Not cached, expired, or unreadable
+pub async fn get_cached_search(
     pool: &PgPool,
-    user_id: i32,
-    anime_slug: &str,
-    anime_title: &str,
-    thumbnail: &str,
-) -> RepositoryResult<UserSubscription> {
+    query: &str,
+    max_age_ms: i64,
+    empty_max_age_ms: i64,
+) -> RepositoryResult<Option<Vec<SearchResult>>> {
     let row = sqlx::query(
         r#"
-        INSERT INTO user_subscriptions (user_id, anime_slug, anime_title, thumbnail, created_at)
-        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
-        RETURNING anime_slug, anime_title, thumbnail, created_at
+        UPDATE search_cache
+        SET hit_count = hit_count + 1, last_hit_at = CURRENT_TIMESTAMP
+        WHERE query = $1
+          AND fetched_at > CURRENT_TIMESTAMP - INTERVAL '1 millisecond' *
+              CASE WHEN result_count = 0 THEN $3 ELSE $2 END
+        RETURNING results
         "#,
     )
-    .bind(user_id)
-    .bind(anime_slug)
-    .bind(anime_title)
-    .bind(thumbnail)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| {
-        if let sqlx::Error::Database(ref db_err) = e {
-            if db_err.constraint() == Some("user_subscriptions_user_anime_unique") {
-                return RepositoryError::Conflict("Already subscribed to this anime".to_string());
-            }
-        }
-        RepositoryError::DatabaseError(e)
-    })?;
+    .bind(query)
+    .bind(max_age_ms)
+    .bind(empty_max_age_ms)
+    .fetch_optional(pool)
+    .await?;
 
-    let created_at: DateTime<Utc> = row.get("created_at");
-    Ok(UserSubscription {
-        anime_slug: row.get("anime_slug"),
-        anime_title: row.get("anime_title"),
-        thumbnail: row
-            .get::<Option<String>, _>("thumbnail")
-            .unwrap_or_default(),
-        created_at: created_at.to_rfc3339(),
-    })
+    Ok(row.and_then(|row| serde_json::from_str(&row.get::<String, _>("results")).ok()))
 }
 
-/// Get all subscriptions for a user
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
+/// Save search results for a normalized query, replacing older results
 ///
-/// # Returns
-/// * `Ok(Vec<UserSubscription>)` - List of subscriptions
-pub async fn get_subscriptions(
+/// The hit count is kept across refreshes.
+pub async fn save_search_results(
     pool: &PgPool,
-    user_id: i32,
-) -> RepositoryResult<Vec<UserSubscription>> {
-    let rows = sqlx::query(
+    query: &str,
+    results: &[SearchResult],
+) -> RepositoryResult<()> {
+    sqlx::query(
         r#"
-        SELECT anime_slug, anime_title, thumbnail, created_at
-        FROM user_subscriptions
-        WHERE user_id = $1
-        ORDER BY created_at DESC
+        INSERT INTO search_cache (query, results, result_count, fetched_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+        ON CONFLICT (query) DO UPDATE SET
+            results = EXCLUDED.results,
+            result_count = EXCLUDED.result_count,
+            fetched_at = EXCLUDED.fetched_at
         "#,
     )
-    .bind(user_id)
-    .fetch_all(pool)
+    .bind(query)
+    .bind(serde_json::to_string(results).unwrap_or_default())
+    .bind(results.len() as i32)
+    .execute(pool)
     .await?;
 
-    let subscriptions = rows
-        .into_iter()
-        .map(|row| {
-            let created_at: DateTime<Utc> = row.get("created_at");
-            UserSubscription {
-                anime_slug: row.get("anime_slug"),
-                anime_title: row.get("anime_title"),
-                thumbnail: row
-                    .get::<Option<String>, _>("thumbnail")
-                    .unwrap_or_default(),
-                created_at: created_at.to_rfc3339(),
-            }
-        })
-        .collect();
-
-    Ok(subscriptions)
+    Ok(())
 }
 
-/// Unsubscribe from an anime series
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `anime_slug` - Anime slug to unsubscribe from
+/// Delete cached searches fetched more than `max_age_ms` ago
 ///
 /// # Returns
-/// * `Ok(true)` - Subscription was removed
-/// * `Ok(false)` - Subscription not found
-pub async fn remove_subscription(
-    pool: &PgPool,
-    user_id: i32,
-    anime_slug: &str,
-) -> RepositoryResult<bool> {
-    let result =
-        sqlx::query("DELETE FROM user_subscriptions WHERE user_id = $1 AND anime_slug = $2")
-            .bind(user_id)
-            .bind(anime_slug)
-            .execute(pool)
-            .await?;
-    Ok(result.rows_affected() > 0)
+/// * `Ok(count)` - Number of entries deleted
+pub async fn delete_expired_searches(pool: &PgPool, max_age_ms: i64) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        "DELETE FROM search_cache WHERE fetched_at < CURRENT_TIMESTAMP - INTERVAL '1 millisecond' * $1",
+    )
+    .bind(max_age_ms)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
 }
 
-/// Check if user is subscribed to an anime
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `anime_slug` - Anime slug to check
-///
-/// # Returns
-/// * `Ok(true)` - User is subscribed
-/// * `Ok(false)` - User is not subscribed
-pub async fn is_subscribed(
-    pool: &PgPool,
-    user_id: i32,
-    anime_slug: &str,
-) -> RepositoryResult<bool> {
-    let row =
-        sqlx::query("SELECT 1 FROM user_subscriptions WHERE user_id = $1 AND anime_slug = $2")
-            .bind(user_id)
-            .bind(anime_slug)
-            .fetch_optional(pool)
-            .await?;
-    Ok(row.is_some())
+// ============================================================================
+// Search Analytics Repository
+// ============================================================================
+
+const SEARCH_QUERY_STATS_COLUMNS: &str = "query, search_count, zero_result_count, \
+    last_result_count, first_searched_at, last_searched_at";
+
+fn search_query_stats_from_row(row: &sqlx::postgres::PgRow) -> SearchQueryStats {
+    let first_searched_at: DateTime<Utc> = row.get("first_searched_at");
+    let last_searched_at: DateTime<Utc> = row.get("last_searched_at");
+
+    SearchQueryStats {
+        query: row.get("query"),
+        search_count: row.get("search_count"),
+        zero_result_count: row.get("zero_result_count"),
+        last_result_count: row.get("last_result_count"),
+        first_searched_at: first_searched_at.to_rfc3339(),
+        last_searched_at: last_searched_at.to_rfc3339(),
+    }
 }
 
-/// Add or update an episode in user's watch history
-///
-/// If the episode already exists in history, updates the watched_at timestamp.
+/// Count a search for a normalized query
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `episode_slug` - Episode slug identifier
-/// * `anime_slug` - Parent anime slug
-/// * `episode_title` - Episode title for display
-/// * `anime_title` - Anime title for display
-/// * `thumbnail` - Thumbnail image URL
-///
-/// # Returns
-/// * `Ok(UserHistory)` - The created/updated history entry
-pub async fn add_to_history(
-    pool: &PgPool,
-    user_id: i32,
-    episode_slug: &str,
-    anime_slug: &str,
-    episode_title: &str,
-    anime_title: &str,
-    thumbnail: &str,
-) -> RepositoryResult<UserHistory> {
-    let row = sqlx::query(
+/// * `query` - Normalized query
+/// * `result_count` - Number of results the search returned
+pub async fn record_search(pool: &PgPool, query: &str, result_count: i32) -> RepositoryResult<()> {
+    sqlx::query(
         r#"
-        INSERT INTO user_history (user_id, episode_slug, anime_slug, episode_title, anime_title, thumbnail, watched_at)
-        VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP)
-        ON CONFLICT (user_id, episode_slug) DO UPDATE SET
-            anime_slug = EXCLUDED.anime_slug,
-            episode_title = EXCLUDED.episode_title,
-            anime_title = EXCLUDED.anime_title,
-            thumbnail = EXCLUDED.thumbnail,
-            watched_at = CURRENT_TIMESTAMP
-        RETURNING episode_slug, anime_slug, episode_title, anime_title, thumbnail, watched_at
+        INSERT INTO search_analytics (query, search_count, zero_result_count, last_result_count)
+        VALUES ($1, 1, CASE WHEN $2 = 0 THEN 1 ELSE 0 END, $2)
+        ON CONFLICT (query) DO UPDATE SET
+            search_count = search_analytics.search_count + 1,
+            zero_result_count = search_analytics.zero_result_count + EXCLUDED.zero_result_count,
+            last_result_count = EXCLUDED.last_result_count,
+            last_searched_at = CURRENT_TIMESTAMP
         "#,
     )
-    .bind(user_id)
-    .bind(episode_slug)
-    .bind(anime_slug)
-    .bind(episode_title)
-    .bind(anime_title)
-    .bind(thumbnail)
-    .fetch_one(pool)
+    .bind(query)
+    .bind(result_count)
+    .execute(pool)
     .await?;
 
-    let watched_at: DateTime<Utc> = row.get("watched_at");
-    Ok(UserHistory {
-        episode_slug: row.get("episode_slug"),
-        anime_slug: row.get("anime_slug"),
-        episode_title: row
-            .get::<Option<String>, _>("episode_title")
-            .unwrap_or_default(),
-        anime_title: row
-            .get::<Option<String>, _>("anime_title")
-            .unwrap_or_default(),
-        thumbnail: row
-            .get::<Option<String>, _>("thumbnail")
-            .unwrap_or_default(),
-        watched_at: watched_at.to_rfc3339(),
-    })
+    Ok(())
 }
 
-/// Get user's watch history sorted by most recently watched
-///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
+/// Get the most searched queries
 ///
 /// # Returns
-/// * `Ok(Vec<UserHistory>)` - List of history entries sorted by most recent first
-pub async fn get_history(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<UserHistory>> {
-    let rows = sqlx::query(
+/// * `Ok(Vec<SearchQueryStats>)` - Up to `limit` queries, most searched first
+pub async fn get_popular_searches(
+    pool: &PgPool,
+    limit: i64,
+) -> RepositoryResult<Vec<SearchQueryStats>> {
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT episode_slug, anime_slug, episode_title, anime_title, thumbnail, watched_at
-        FROM user_history
-        WHERE user_id = $1
-        ORDER BY watched_at DESC
+        SELECT {}
+        FROM search_analytics
+        ORDER BY search_count DESC, last_searched_at DESC
+        LIMIT $1
         "#,
-    )
-    .bind(user_id)
+        SEARCH_QUERY_STATS_COLUMNS
+    ))
+    .bind(limit)
     .fetch_all(pool)
     .await?;
 
-    let history = rows
-        .into_iter()
-        .map(|row| {
-            let watched_at: DateTime<Utc> = row.get("watched_at");
-            UserHistory {
-                episode_slug: row.get("episode_slug"),
-                anime_slug: row.get("anime_slug"),
-                episode_title: row
-                    .get::<Option<String>, _>("episode_title")
-                    .unwrap_or_default(),
-                anime_title: row
-                    .get::<Option<String>, _>("anime_title")
-                    .unwrap_or_default(),
-                thumbnail: row
-                    .get::<Option<String>, _>("thumbnail")
-                    .unwrap_or_default(),
-                watched_at: watched_at.to_rfc3339(),
-            }
-        })
-        .collect();
-
-    Ok(history)
+    Ok(rows.iter().map(search_query_stats_from_row).collect())
 }
 
-/// Remove an episode from user's watch history
+/// Get queries whose most recent search found nothing
 ///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `episode_slug` - Episode slug to remove
+/// Queries that used to come up empty but match now are left out.
 ///
 /// # Returns
-/// * `Ok(true)` - History entry was removed
-/// * `Ok(false)` - History entry not found
-pub async fn remove_from_history(
+/// * `Ok(Vec<SearchQueryStats>)` - Up to `limit` queries, most empty searches first
+pub async fn get_zero_result_searches(
     pool: &PgPool,
-    user_id: i32,
-    episode_slug: &str,
-) -> RepositoryResult<bool> {
-    let result = sqlx::query("DELETE FROM user_history WHERE user_id = $1 AND episode_slug = $2")
-        .bind(user_id)
-        .bind(episode_slug)
-        .execute(pool)
-        .await?;
-    Ok(result.rows_affected() > 0)
+    limit: i64,
+) -> RepositoryResult<Vec<SearchQueryStats>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM search_analytics
+        WHERE last_result_count = 0
+        ORDER BY zero_result_count DESC, last_searched_at DESC
+        LIMIT $1
+        "#,
+        SEARCH_QUERY_STATS_COLUMNS
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(search_query_stats_from_row).collect())
 }
 
-/// Clear all watch history for a user
+// ============================================================================
+// Crawled Anime Repository
+// ============================================================================
+
+/// Save a single crawled anime to the database with upsert logic
 ///
-/// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
+/// Uses ON CONFLICT UPDATE to update existing records based on slug; the row
+/// is left untouched if its content hash matches.
 ///
 /// # Returns
-/// * `Ok(count)` - Number of entries deleted
-pub async fn clear_history(pool: &PgPool, user_id: i32) -> RepositoryResult<u64> {
-    let result = sqlx::query("DELETE FROM user_history WHERE user_id = $1")
-        .bind(user_id)
+/// Whether the row was inserted or updated
+pub async fn save_crawled_anime(
+    executor: impl PgExecutor<'_>,
+    anime: &CrawledAnime,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO crawled_anime (
+            slug, title, url, thumbnail, status, type, episode_status, content_hash, updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP)
+        ON CONFLICT (slug) DO UPDATE SET
+            title = EXCLUDED.title,
+            url = EXCLUDED.url,
+            thumbnail = EXCLUDED.thumbnail,
+            status = EXCLUDED.status,
+            type = EXCLUDED.type,
+            episode_status = EXCLUDED.episode_status,
+            content_hash = EXCLUDED.content_hash,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE crawled_anime.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+        "#,
+    )
+    .bind(&anime.slug)
+    .bind(&anime.title)
+    .bind(&anime.url)
+    .bind(&anime.thumbnail)
+    .bind(&anime.status)
+    .bind(&anime.anime_type)
+    .bind(&anime.episode_status)
+    .bind(content_hash(anime))
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Save multiple crawled anime to the database with batch upsert for performance
+///
+/// Uses a transaction to ensure atomicity and ON CONFLICT UPDATE for upsert logic;
+/// rows whose content hash matches are left untouched.
+#[instrument(skip_all, fields(anime = anime_list.len()))]
+pub async fn save_crawled_anime_batch(
+    pool: &PgPool,
+    anime_list: &[CrawledAnime],
+) -> RepositoryResult<ChangeCount> {
+    let mut changes = ChangeCount::default();
+    if anime_list.is_empty() {
+        return Ok(changes);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    for anime in anime_list {
+        let row = sqlx::query(
+            r#"
+            INSERT INTO crawled_anime (
+                slug, title, url, thumbnail, status, type, episode_status, content_hash, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP)
+            ON CONFLICT (slug) DO UPDATE SET
+                title = EXCLUDED.title,
+                url = EXCLUDED.url,
+                thumbnail = EXCLUDED.thumbnail,
+                status = EXCLUDED.status,
+                type = EXCLUDED.type,
+                episode_status = EXCLUDED.episode_status,
+                content_hash = EXCLUDED.content_hash,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE crawled_anime.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+            RETURNING (xmax = 0) AS inserted
+            "#,
+        )
+        .bind(&anime.slug)
+        .bind(&anime.title)
+        .bind(&anime.url)
+        .bind(&anime.thumbnail)
+        .bind(&anime.status)
+        .bind(&anime.anime_type)
+        .bind(&anime.episode_status)
+        .bind(content_hash(anime))
+        .fetch_optional(&mut *tx)
+        .await?;
+        changes.record_write(upsert_outcome(row));
+    }
+
+    tx.commit().await?;
+    Ok(changes)
+}
+
+/// Columns selected for every CrawledAnimeRecord query
+const CRAWLED_ANIME_COLUMNS: &str = "id, slug, title, url, thumbnail, status, type, \
+    episode_status, popularity_score, created_at, updated_at";
+
+/// Map a crawled_anime row into a CrawledAnimeRecord
+fn crawled_anime_from_row(row: &sqlx::postgres::PgRow) -> CrawledAnimeRecord {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let updated_at: DateTime<Utc> = row.get("updated_at");
+
+    CrawledAnimeRecord {
+        id: row.get("id"),
+        slug: row.get("slug"),
+        title: row.get("title"),
+        url: row.get("url"),
+        thumbnail: row
+            .get::<Option<String>, _>("thumbnail")
+            .unwrap_or_default(),
+        status: row.get::<Option<String>, _>("status").unwrap_or_default(),
+        anime_type: row.get::<Option<String>, _>("type").unwrap_or_default(),
+        episode_status: row
+            .get::<Option<String>, _>("episode_status")
+            .unwrap_or_default(),
+        popularity_score: row.get("popularity_score"),
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+    }
+}
+
+/// Get the total count of crawled anime in the database
+pub async fn get_crawled_anime_count(pool: &PgPool) -> RepositoryResult<i64> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM crawled_anime")
+        .fetch_one(pool)
+        .await?;
+
+    let count: i64 = row.get("count");
+    Ok(count)
+}
+
+/// Get a crawled anime by slug
+pub async fn get_crawled_anime_by_slug(
+    pool: &PgPool,
+    slug: &str,
+) -> RepositoryResult<Option<CrawledAnimeRecord>> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM crawled_anime WHERE slug = $1",
+        CRAWLED_ANIME_COLUMNS
+    ))
+    .bind(slug)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(crawled_anime_from_row))
+}
+
+/// Get all crawled anime from the database
+pub async fn get_all_crawled_anime(pool: &PgPool) -> RepositoryResult<Vec<CrawledAnimeRecord>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM crawled_anime ORDER BY updated_at DESC",
+        CRAWLED_ANIME_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(crawled_anime_from_row).collect())
+}
+
+/// Get crawled anime with a status (ignoring case), oldest update first
+pub async fn get_crawled_anime_by_status(
+    pool: &PgPool,
+    status: &str,
+) -> RepositoryResult<Vec<CrawledAnimeRecord>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM crawled_anime WHERE lower(status) = lower($1) ORDER BY updated_at, id",
+        CRAWLED_ANIME_COLUMNS
+    ))
+    .bind(status)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(crawled_anime_from_row).collect())
+}
+
+/// Delete a crawled anime by slug
+pub async fn delete_crawled_anime(pool: &PgPool, slug: &str) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM crawled_anime WHERE slug = $1")
+        .bind(slug)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete all crawled anime from the database
+pub async fn delete_all_crawled_anime(pool: &PgPool) -> RepositoryResult<u64> {
+    let result = sqlx::query("DELETE FROM crawled_anime")
         .execute(pool)
         .await?;
     Ok(result.rows_affected())
 }
 
-// ============================================================================
-// Verification Tokens Repository
-// ============================================================================
+/// Columns selected for a [`User`]
+const USER_COLUMNS: &str = "id, email, name, avatar, created_at";
 
-/// Token types for verification
-pub const TOKEN_TYPE_EMAIL_VERIFICATION: &str = "email_verification";
-pub const TOKEN_TYPE_PASSWORD_RESET: &str = "password_reset";
+/// Build a user from a row, decrypting the email address
+fn user_from_row(row: &sqlx::postgres::PgRow) -> RepositoryResult<User> {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    Ok(User {
+        id: row.get("id"),
+        email: encryption::open(FIELD_EMAIL, row.get("email"))?,
+        name: row.get("name"),
+        avatar: row.get("avatar"),
+        created_at: created_at.to_rfc3339(),
+    })
+}
 
-/// Verification token data
-#[derive(Debug, Clone)]
-pub struct VerificationToken {
-    pub id: i32,
-    pub user_id: i32,
-    pub token: String,
-    pub token_type: String,
-    pub expires_at: DateTime<Utc>,
-    pub used_at: Option<DateTime<Utc>>,
-    pub created_at: DateTime<Utc>,
+/// Map a duplicate email, plaintext or by blind index, to `EmailAlreadyExists`
+fn map_user_conflict(e: sqlx::Error) -> RepositoryError {
+    if let sqlx::Error::Database(ref db_err) = e {
+        if matches!(
+            db_err.constraint(),
+            Some(USERS_EMAIL_CONSTRAINT | USERS_EMAIL_HASH_CONSTRAINT)
+        ) {
+            return RepositoryError::EmailAlreadyExists;
+        }
+    }
+    RepositoryError::DatabaseError(e)
 }
 
-/// Create a verification token for a user
+/// Create a new user with email and password
 ///
 /// # Arguments
-/// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `token` - Unique token string
-/// * `token_type` - Type of token (email_verification or password_reset)
-/// * `expires_in_hours` - Hours until token expires
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `tenant_id` - Tenant the user signs up to
+/// * `email` - User's email address
+/// * `password_hash` - Bcrypt hashed password
+/// * `name` - Optional display name
 ///
 /// # Returns
-/// * `Ok(VerificationToken)` - The created token
-pub async fn create_verification_token(
-    pool: &PgPool,
-    user_id: i32,
-    token: &str,
-    token_type: &str,
-    expires_in_hours: i64,
-) -> RepositoryResult<VerificationToken> {
-    let expires_at = Utc::now() + chrono::Duration::hours(expires_in_hours);
-
-    let row = sqlx::query(
+/// * `Ok(User)` - The created user
+/// * `Err(RepositoryError::EmailAlreadyExists)` - If email is already registered in the tenant
+pub async fn create_user(
+    executor: impl PgExecutor<'_>,
+    tenant_id: i32,
+    email: &str,
+    password_hash: &str,
+    name: Option<&str>,
+) -> RepositoryResult<User> {
+    let row = sqlx::query(&format!(
         r#"
-        INSERT INTO verification_tokens (user_id, token, token_type, expires_at, created_at)
-        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
-        RETURNING id, user_id, token, token_type, expires_at, used_at, created_at
+        INSERT INTO users (email, email_hash, password_hash, name, tenant_id, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        RETURNING {}
         "#,
-    )
-    .bind(user_id)
-    .bind(token)
-    .bind(token_type)
-    .bind(expires_at)
-    .fetch_one(pool)
-    .await?;
+        USER_COLUMNS
+    ))
+    .bind(encryption::seal(FIELD_EMAIL, email))
+    .bind(encryption::blind_index(FIELD_EMAIL, email))
+    .bind(password_hash)
+    .bind(name)
+    .bind(tenant_id)
+    .fetch_one(executor)
+    .await
+    .map_err(map_user_conflict)?;
 
-    Ok(VerificationToken {
-        id: row.get("id"),
-        user_id: row.get("user_id"),
-        token: row.get("token"),
-        token_type: row.get("token_type"),
-        expires_at: row.get("expires_at"),
-        used_at: row.get("used_at"),
-        created_at: row.get("created_at"),
-    })
+    user_from_row(&row)
 }
 
-/// Find a verification token by token string
+/// Create a new user with Google OAuth
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `token` - Token string to search for
+/// * `tenant_id` - Tenant the user signs up to
+/// * `email` - User's email from Google
+/// * `google_id` - Google user ID
+/// * `name` - User's name from Google
+/// * `avatar` - Optional profile picture URL
 ///
 /// # Returns
-/// * `Ok(Some(VerificationToken))` - Token found
-/// * `Ok(None)` - Token not found
-pub async fn find_verification_token(
+/// * `Ok(User)` - The created user
+/// * `Err(RepositoryError)` - If creation fails
+pub async fn create_google_user(
     pool: &PgPool,
-    token: &str,
-) -> RepositoryResult<Option<VerificationToken>> {
-    let row = sqlx::query(
+    tenant_id: i32,
+    email: &str,
+    google_id: &str,
+    name: &str,
+    avatar: Option<&str>,
+) -> RepositoryResult<User> {
+    let row = sqlx::query(&format!(
         r#"
-        SELECT id, user_id, token, token_type, expires_at, used_at, created_at
-        FROM verification_tokens
-        WHERE token = $1
+        INSERT INTO users (
+            email, email_hash, google_id, google_id_hash, name, avatar, tenant_id,
+            created_at, updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        RETURNING {}
         "#,
-    )
-    .bind(token)
-    .fetch_optional(pool)
-    .await?;
+        USER_COLUMNS
+    ))
+    .bind(encryption::seal(FIELD_EMAIL, email))
+    .bind(encryption::blind_index(FIELD_EMAIL, email))
+    .bind(encryption::seal(FIELD_GOOGLE_ID, google_id))
+    .bind(encryption::blind_index(FIELD_GOOGLE_ID, google_id))
+    .bind(name)
+    .bind(avatar)
+    .bind(tenant_id)
+    .fetch_one(pool)
+    .await
+    .map_err(map_user_conflict)?;
 
-    match row {
-        Some(row) => Ok(Some(VerificationToken {
-            id: row.get("id"),
-            user_id: row.get("user_id"),
-            token: row.get("token"),
-            token_type: row.get("token_type"),
-            expires_at: row.get("expires_at"),
-            used_at: row.get("used_at"),
-            created_at: row.get("created_at"),
-        })),
-        None => Ok(None),
-    }
+    user_from_row(&row)
 }
 
-/// Mark a verification token as used
+/// Find a user by email address
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `token` - Token string to mark as used
+/// * `tenant_id` - Tenant to search in
+/// * `email` - Email address to search for
 ///
 /// # Returns
-/// * `Ok(true)` - Token was marked as used
-/// * `Ok(false)` - Token not found
-pub async fn mark_token_as_used(pool: &PgPool, token: &str) -> RepositoryResult<bool> {
-    let result = sqlx::query(
-        r#"
-        UPDATE verification_tokens
-        SET used_at = CURRENT_TIMESTAMP
-        WHERE token = $1 AND used_at IS NULL
+/// * `Ok(Some(user, password_hash))` - User found with optional password hash
+/// * `Ok(None)` - User not found
+pub async fn find_user_by_email(
+    pool: &PgPool,
+    tenant_id: i32,
+    email: &str,
+) -> RepositoryResult<Option<(User, Option<String>)>> {
+    // Rows not yet backfilled still hold the plaintext
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {}, password_hash
+        FROM users
+        WHERE (email = $1 OR email_hash = $3) AND tenant_id = $2
         "#,
-    )
-    .bind(token)
-    .execute(pool)
+        USER_COLUMNS
+    ))
+    .bind(email)
+    .bind(tenant_id)
+    .bind(encryption::blind_index(FIELD_EMAIL, email))
+    .fetch_optional(pool)
     .await?;
 
-    Ok(result.rows_affected() > 0)
+    match row {
+        Some(row) => {
+            let password_hash: Option<String> = row.get("password_hash");
+            Ok(Some((user_from_row(&row)?, password_hash)))
+        }
+        None => Ok(None),
+    }
 }
 
-/// Delete expired verification tokens
+/// Find a user by Google ID
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
+/// * `tenant_id` - Tenant to search in
+/// * `google_id` - Google user ID to search for
 ///
 /// # Returns
-/// * `Ok(count)` - Number of tokens deleted
-pub async fn delete_expired_tokens(pool: &PgPool) -> RepositoryResult<u64> {
-    let result = sqlx::query(
+/// * `Ok(Some(User))` - User found
+/// * `Ok(None)` - User not found
+pub async fn find_user_by_google_id(
+    pool: &PgPool,
+    tenant_id: i32,
+    google_id: &str,
+) -> RepositoryResult<Option<User>> {
+    let row = sqlx::query(&format!(
         r#"
-        DELETE FROM verification_tokens
-        WHERE expires_at < CURRENT_TIMESTAMP
+        SELECT {}
+        FROM users
+        WHERE (google_id = $1 OR google_id_hash = $3) AND tenant_id = $2
         "#,
-    )
-    .execute(pool)
+        USER_COLUMNS
+    ))
+    .bind(google_id)
+    .bind(tenant_id)
+    .bind(encryption::blind_index(FIELD_GOOGLE_ID, google_id))
+    .fetch_optional(pool)
     .await?;
 
-    Ok(result.rows_affected())
+    row.as_ref().map(user_from_row).transpose()
 }
 
-/// Delete all verification tokens for a user of a specific type
+/// Find a user by ID
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `token_type` - Type of tokens to delete
+/// * `user_id` - User ID to search for
 ///
 /// # Returns
-/// * `Ok(count)` - Number of tokens deleted
-pub async fn delete_user_tokens(
+/// * `Ok(Some(User))` - User found
+/// * `Ok(None)` - User not found
+pub async fn find_user_by_id(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<User>> {
+    let row = sqlx::query(&format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS))
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    row.as_ref().map(user_from_row).transpose()
+}
+
+/// Link a Google account to an existing user
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID to link
+/// * `google_id` - Google user ID to link
+///
+/// # Returns
+/// * `Ok(())` - Successfully linked
+/// * `Err(RepositoryError)` - If linking fails
+pub async fn link_google_account(
     pool: &PgPool,
     user_id: i32,
-    token_type: &str,
-) -> RepositoryResult<u64> {
-    let result = sqlx::query(
+    google_id: &str,
+) -> RepositoryResult<()> {
+    sqlx::query(
         r#"
-        DELETE FROM verification_tokens
-        WHERE user_id = $1 AND token_type = $2
+        UPDATE users
+        SET google_id = $1, google_id_hash = $3, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $2
         "#,
     )
+    .bind(encryption::seal(FIELD_GOOGLE_ID, google_id))
     .bind(user_id)
-    .bind(token_type)
+    .bind(encryption::blind_index(FIELD_GOOGLE_ID, google_id))
     .execute(pool)
     .await?;
 
-    Ok(result.rows_affected())
+    Ok(())
 }
 
-/// Update user's email_verified status
+/// Delete a user by ID
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
-/// * `user_id` - User ID
-/// * `verified` - Whether email is verified
+/// * `user_id` - User ID to delete
 ///
 /// # Returns
-/// * `Ok(true)` - Status was updated
+/// * `Ok(true)` - User was deleted
 /// * `Ok(false)` - User not found
-pub async fn set_email_verified(
-    pool: &PgPool,
-    user_id: i32,
-    verified: bool,
-) -> RepositoryResult<bool> {
+pub async fn delete_user(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check whether a user has the admin flag set
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID to check
+///
+/// # Returns
+/// * `Ok(true)` - User exists and is an admin
+/// * `Ok(false)` - User is not an admin or doesn't exist
+pub async fn is_user_admin(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
+    let row = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row
+        .and_then(|row| row.get::<Option<bool>, _>("is_admin"))
+        .unwrap_or(false))
+}
+
+/// Check whether a user's account is active
+///
+/// # Returns
+/// * `Ok(true)` - User exists and is not deactivated
+/// * `Ok(false)` - User is deactivated or doesn't exist
+pub async fn is_user_active(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
+    let row = sqlx::query("SELECT is_active FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.is_some_and(|row| row.get::<bool, _>("is_active")))
+}
+
+/// What a user's tokens are checked against on every authenticated request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenState {
+    /// Whether the account is active
+    pub is_active: bool,
+    /// Version tokens must carry to be accepted
+    pub token_version: i32,
+    /// Whether the user is an admin
+    pub is_admin: bool,
+}
+
+/// Get what a user's tokens are checked against
+///
+/// # Returns
+/// * `Ok(Some(TokenState))` - The user's account state and token version
+/// * `Ok(None)` - User not found
+pub async fn get_token_state(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<TokenState>> {
+    let row = sqlx::query("SELECT is_active, token_version, is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(|row| TokenState {
+        is_active: row.get("is_active"),
+        token_version: row.get("token_version"),
+        is_admin: row.get::<Option<bool>, _>("is_admin").unwrap_or(false),
+    }))
+}
+
+/// Bump a user's token version, rejecting every token issued before
+///
+/// # Returns
+/// * `Ok(Some(i32))` - The new token version
+/// * `Ok(None)` - User not found
+pub async fn bump_token_version(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<i32>> {
+    let row = sqlx::query(
+        r#"
+        UPDATE users
+        SET token_version = token_version + 1, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING token_version
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| row.get("token_version")))
+}
+
+/// Deactivate a user's account, keeping its data
+///
+/// # Returns
+/// * `Ok(true)` - Account was deactivated
+/// * `Ok(false)` - User not found or already deactivated
+pub async fn deactivate_user(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
     let result = sqlx::query(
         r#"
         UPDATE users
-        SET email_verified = $1, updated_at = CURRENT_TIMESTAMP
-        WHERE id = $2
+        SET is_active = FALSE, deactivated_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND is_active
         "#,
     )
-    .bind(verified)
     .bind(user_id)
     .execute(pool)
     .await?;
+    Ok(result.rows_affected() > 0)
+}
 
+/// Reactivate a deactivated account
+///
+/// # Returns
+/// * `Ok(true)` - Account was reactivated
+/// * `Ok(false)` - User not found or not deactivated
+pub async fn reactivate_user(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET is_active = TRUE, deactivated_at = NULL, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND NOT is_active
+        "#,
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
     Ok(result.rows_affected() > 0)
 }
 
-/// Check if user's email is verified
+/// Set a user's avatar
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
 /// * `user_id` - User ID
+/// * `avatar` - Avatar URL, or the storage key of an uploaded avatar
 ///
 /// # Returns
-/// * `Ok(Some(bool))` - Email verification status
+/// * `Ok(Some(User))` - The updated user
 /// * `Ok(None)` - User not found
-pub async fn is_email_verified(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<bool>> {
-    let row = sqlx::query(
+pub async fn update_user_avatar(
+    pool: &PgPool,
+    user_id: i32,
+    avatar: &str,
+) -> RepositoryResult<Option<User>> {
+    let row = sqlx::query(&format!(
         r#"
-        SELECT email_verified
-        FROM users
+        UPDATE users
+        SET avatar = $2, updated_at = CURRENT_TIMESTAMP
         WHERE id = $1
+        RETURNING {}
         "#,
-    )
+        USER_COLUMNS
+    ))
     .bind(user_id)
+    .bind(avatar)
     .fetch_optional(pool)
     .await?;
 
-    match row {
-        Some(row) => Ok(Some(
-            row.get::<Option<bool>, _>("email_verified")
-                .unwrap_or(false),
-        )),
-        None => Ok(None),
-    }
+    row.as_ref().map(user_from_row).transpose()
 }
 
-/// Update user's password
+/// Add an anime to user's favorites
 ///
 /// # Arguments
 /// * `pool` - Database connection pool
 /// * `user_id` - User ID
-/// * `password_hash` - New bcrypt hashed password
+/// * `anime_slug` - Anime slug identifier
+/// * `anime_title` - Anime title for display
+/// * `thumbnail` - Thumbnail image URL
 ///
 /// # Returns
-/// * `Ok(true)` - Password was updated
-/// * `Ok(false)` - User not found
-pub async fn update_user_password(
+/// * `Ok(UserFavorite)` - The created favorite
+/// * `Err(RepositoryError::Conflict)` - If already favorited
+pub async fn add_favorite(
     pool: &PgPool,
     user_id: i32,
-    password_hash: &str,
-) -> RepositoryResult<bool> {
-    let result = sqlx::query(
+    anime_slug: &str,
+    anime_title: &str,
+    thumbnail: &str,
+) -> RepositoryResult<UserFavorite> {
+    let row = sqlx::query(&format!(
         r#"
-        UPDATE users
-        SET password_hash = $1, updated_at = CURRENT_TIMESTAMP
-        WHERE id = $2
+        WITH t AS (
+            INSERT INTO user_favorites (user_id, anime_slug, anime_title, thumbnail, created_at)
+            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            RETURNING user_id, anime_slug, anime_title, thumbnail, created_at
+        )
+        SELECT anime_slug, anime_title, thumbnail, created_at, {}
+        FROM t
         "#,
-    )
-    .bind(password_hash)
+        WATCH_PROGRESS_COLUMNS
+    ))
     .bind(user_id)
-    .execute(pool)
-    .await?;
+    .bind(anime_slug)
+    .bind(anime_title)
+    .bind(thumbnail)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        if let sqlx::Error::Database(ref db_err) = e {
+            if db_err.constraint() == Some("user_favorites_user_anime_unique") {
+                return RepositoryError::Conflict("Anime already in favorites".to_string());
+            }
+        }
+        RepositoryError::DatabaseError(e)
+    })?;
 
-    Ok(result.rows_affected() > 0)
+    let created_at: DateTime<Utc> = row.get("created_at");
+    Ok(UserFavorite {
+        anime_slug: row.get("anime_slug"),
+        anime_title: row.get("anime_title"),
+        thumbnail: row
+            .get::<Option<String>, _>("thumbnail")
+            .unwrap_or_default(),
+        created_at: created_at.to_rfc3339(),
+        watched_count: row.get("watched_count"),
+        total_episodes: row.get("total_episodes"),
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Get all favorites for a user
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+///
+/// # Returns
+/// * `Ok(Vec<UserFavorite>)` - List of favorites
+pub async fn get_favorites(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<UserFavorite>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT anime_slug, anime_title, thumbnail, created_at, {}
+        FROM user_favorites t
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        WATCH_PROGRESS_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
 
-    // Helper function to create a test AnimeUpdate
-    fn create_test_anime_update(episode_url: &str) -> AnimeUpdate {
-        AnimeUpdate {
+    let favorites = rows
+        .into_iter()
+        .map(|row| {
+            let created_at: DateTime<Utc> = row.get("created_at");
+            UserFavorite {
+                anime_slug: row.get("anime_slug"),
+                anime_title: row.get("anime_title"),
+                thumbnail: row
+                    .get::<Option<String>, _>("thumbnail")
+                    .unwrap_or_default(),
+                created_at: created_at.to_rfc3339(),
+                watched_count: row.get("watched_count"),
+                total_episodes: row.get("total_episodes"),
+            }
+        })
+        .collect();
+
+    Ok(favorites)
+}
+
+/// Remove an anime from user's favorites
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug to remove
+///
+/// # Returns
+/// * `Ok(true)` - Favorite was removed
+/// * `Ok(false)` - Favorite not found
+pub async fn remove_favorite(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM user_favorites WHERE user_id = $1 AND anime_slug = $2")
+        .bind(user_id)
+        .bind(anime_slug)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check if an anime is in user's favorites
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug to check
+///
+/// # Returns
+/// * `Ok(true)` - Anime is favorited
+/// * `Ok(false)` - Anime is not favorited
+pub async fn is_favorite(pool: &PgPool, user_id: i32, anime_slug: &str) -> RepositoryResult<bool> {
+    let row = sqlx::query("SELECT 1 FROM user_favorites WHERE user_id = $1 AND anime_slug = $2")
+        .bind(user_id)
+        .bind(anime_slug)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Subscribe to an anime series
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug identifier
+/// * `anime_title` - Anime title for display
+/// * `thumbnail` - Thumbnail image URL
+///
+/// # Returns
+/// * `Ok(UserSubscription)` - The created subscription
+/// * `Err(RepositoryError::Conflict)` - If already subscribed
+pub async fn add_subscription(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+    anime_title: &str,
+    thumbnail: &str,
+) -> RepositoryResult<UserSubscription> {
+    let row = sqlx::query(&format!(
+        r#"
+        WITH t AS (
+            INSERT INTO user_subscriptions (user_id, anime_slug, anime_title, thumbnail, created_at)
+            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            RETURNING user_id, anime_slug, anime_title, thumbnail, created_at
+        )
+        SELECT anime_slug, anime_title, thumbnail, created_at, {}
+        FROM t
+        "#,
+        WATCH_PROGRESS_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(anime_slug)
+    .bind(anime_title)
+    .bind(thumbnail)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        if let sqlx::Error::Database(ref db_err) = e {
+            if db_err.constraint() == Some("user_subscriptions_user_anime_unique") {
+                return RepositoryError::Conflict("Already subscribed to this anime".to_string());
+            }
+        }
+        RepositoryError::DatabaseError(e)
+    })?;
+
+    let created_at: DateTime<Utc> = row.get("created_at");
+    Ok(UserSubscription {
+        anime_slug: row.get("anime_slug"),
+        anime_title: row.get("anime_title"),
+        thumbnail: row
+            .get::<Option<String>, _>("thumbnail")
+            .unwrap_or_default(),
+        created_at: created_at.to_rfc3339(),
+        watched_count: row.get("watched_count"),
+        total_episodes: row.get("total_episodes"),
+    })
+}
+
+/// Get all subscriptions for a user
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+///
+/// # Returns
+/// * `Ok(Vec<UserSubscription>)` - List of subscriptions
+pub async fn get_subscriptions(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<Vec<UserSubscription>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT anime_slug, anime_title, thumbnail, created_at, {}
+        FROM user_subscriptions t
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        WATCH_PROGRESS_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let subscriptions = rows
+        .into_iter()
+        .map(|row| {
+            let created_at: DateTime<Utc> = row.get("created_at");
+            UserSubscription {
+                anime_slug: row.get("anime_slug"),
+                anime_title: row.get("anime_title"),
+                thumbnail: row
+                    .get::<Option<String>, _>("thumbnail")
+                    .unwrap_or_default(),
+                created_at: created_at.to_rfc3339(),
+                watched_count: row.get("watched_count"),
+                total_episodes: row.get("total_episodes"),
+            }
+        })
+        .collect();
+
+    Ok(subscriptions)
+}
+
+/// Unsubscribe from an anime series
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug to unsubscribe from
+///
+/// # Returns
+/// * `Ok(true)` - Subscription was removed
+/// * `Ok(false)` - Subscription not found
+pub async fn remove_subscription(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+) -> RepositoryResult<bool> {
+    let result =
+        sqlx::query("DELETE FROM user_subscriptions WHERE user_id = $1 AND anime_slug = $2")
+            .bind(user_id)
+            .bind(anime_slug)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check if user is subscribed to an anime
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug to check
+///
+/// # Returns
+/// * `Ok(true)` - User is subscribed
+/// * `Ok(false)` - User is not subscribed
+pub async fn is_subscribed(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+) -> RepositoryResult<bool> {
+    let row =
+        sqlx::query("SELECT 1 FROM user_subscriptions WHERE user_id = $1 AND anime_slug = $2")
+            .bind(user_id)
+            .bind(anime_slug)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.is_some())
+}
+
+/// A subscriber of an anime and where to notify them
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriberRecipient {
+    pub user_id: i32,
+    pub email: String,
+    /// Email language code from the subscriber's preferences
+    pub language: String,
+    /// Whether the subscriber wants notification emails
+    pub email_notifications: bool,
+}
+
+/// Get the subscribers of an anime with their email and notification settings
+///
+/// Only active subscribers with a verified email address are included.
+pub async fn get_subscriber_recipients(
+    pool: &PgPool,
+    anime_slug: &str,
+) -> RepositoryResult<Vec<SubscriberRecipient>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT u.id AS user_id, u.email,
+               COALESCE(p.language, 'en') AS language,
+               COALESCE(p.email_notifications, TRUE) AS email_notifications
+        FROM user_subscriptions s
+        JOIN users u ON u.id = s.user_id
+        LEFT JOIN user_preferences p ON p.user_id = s.user_id
+        WHERE s.anime_slug = $1 AND u.email_verified = TRUE AND u.is_active
+        ORDER BY u.id
+        "#,
+    )
+    .bind(anime_slug)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(SubscriberRecipient {
+                user_id: row.get("user_id"),
+                email: encryption::open(FIELD_EMAIL, row.get("email"))?,
+                language: row.get("language"),
+                email_notifications: row.get("email_notifications"),
+            })
+        })
+        .collect()
+}
+
+/// Get a user's email and notification settings
+///
+/// # Returns
+/// * `Ok(Some(SubscriberRecipient))` - The user is active with a verified email
+/// * `Ok(None)` - No such user, or they can't be emailed
+pub async fn get_user_recipient(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<Option<SubscriberRecipient>> {
+    let row = sqlx::query(
+        r#"
+        SELECT u.id AS user_id, u.email,
+               COALESCE(p.language, 'en') AS language,
+               COALESCE(p.email_notifications, TRUE) AS email_notifications
+        FROM users u
+        LEFT JOIN user_preferences p ON p.user_id = u.id
+        WHERE u.id = $1 AND u.email_verified = TRUE AND u.is_active
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|row| {
+        Ok(SubscriberRecipient {
+            user_id: row.get("user_id"),
+            email: encryption::open(FIELD_EMAIL, row.get("email"))?,
+            language: row.get("language"),
+            email_notifications: row.get("email_notifications"),
+        })
+    })
+    .transpose()
+}
+
+/// Add or update an episode in user's watch history
+///
+/// If the episode already exists in history, updates the watched_at timestamp.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `episode_slug` - Episode slug identifier
+/// * `anime_slug` - Parent anime slug
+/// * `episode_title` - Episode title for display
+/// * `anime_title` - Anime title for display
+/// * `thumbnail` - Thumbnail image URL
+///
+/// # Returns
+/// * `Ok(UserHistory)` - The created/updated history entry
+pub async fn add_to_history(
+    pool: &PgPool,
+    user_id: i32,
+    episode_slug: &str,
+    anime_slug: &str,
+    episode_title: &str,
+    anime_title: &str,
+    thumbnail: &str,
+) -> RepositoryResult<UserHistory> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO user_history (user_id, episode_slug, anime_slug, episode_title, anime_title, thumbnail, watched_at)
+        VALUES ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP)
+        ON CONFLICT (user_id, episode_slug) DO UPDATE SET
+            anime_slug = EXCLUDED.anime_slug,
+            episode_title = EXCLUDED.episode_title,
+            anime_title = EXCLUDED.anime_title,
+            thumbnail = EXCLUDED.thumbnail,
+            watched_at = CURRENT_TIMESTAMP
+        RETURNING episode_slug, anime_slug, episode_title, anime_title, thumbnail, watched_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(episode_slug)
+    .bind(anime_slug)
+    .bind(episode_title)
+    .bind(anime_title)
+    .bind(thumbnail)
+    .fetch_one(pool)
+    .await?;
+
+    let watched_at: DateTime<Utc> = row.get("watched_at");
+    Ok(UserHistory {
+        episode_slug: row.get("episode_slug"),
+        anime_slug: row.get("anime_slug"),
+        episode_title: row
+            .get::<Option<String>, _>("episode_title")
+            .unwrap_or_default(),
+        anime_title: row
+            .get::<Option<String>, _>("anime_title")
+            .unwrap_or_default(),
+        thumbnail: row
+            .get::<Option<String>, _>("thumbnail")
+            .unwrap_or_default(),
+        watched_at: watched_at.to_rfc3339(),
+    })
+}
+
+/// Get user's watch history sorted by most recently watched
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+///
+/// # Returns
+/// * `Ok(Vec<UserHistory>)` - List of history entries sorted by most recent first
+pub async fn get_history(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<UserHistory>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT episode_slug, anime_slug, episode_title, anime_title, thumbnail, watched_at
+        FROM user_history
+        WHERE user_id = $1
+        ORDER BY watched_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let history = rows
+        .into_iter()
+        .map(|row| {
+            let watched_at: DateTime<Utc> = row.get("watched_at");
+            UserHistory {
+                episode_slug: row.get("episode_slug"),
+                anime_slug: row.get("anime_slug"),
+                episode_title: row
+                    .get::<Option<String>, _>("episode_title")
+                    .unwrap_or_default(),
+                anime_title: row
+                    .get::<Option<String>, _>("anime_title")
+                    .unwrap_or_default(),
+                thumbnail: row
+                    .get::<Option<String>, _>("thumbnail")
+                    .unwrap_or_default(),
+                watched_at: watched_at.to_rfc3339(),
+            }
+        })
+        .collect();
+
+    Ok(history)
+}
+
+/// Remove an episode from user's watch history
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `episode_slug` - Episode slug to remove
+///
+/// # Returns
+/// * `Ok(true)` - History entry was removed
+/// * `Ok(false)` - History entry not found
+pub async fn remove_from_history(
+    pool: &PgPool,
+    user_id: i32,
+    episode_slug: &str,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM user_history WHERE user_id = $1 AND episode_slug = $2")
+        .bind(user_id)
+        .bind(episode_slug)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Clear all watch history for a user
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+///
+/// # Returns
+/// * `Ok(count)` - Number of entries deleted
+pub async fn clear_history(pool: &PgPool, user_id: i32) -> RepositoryResult<u64> {
+    let result = sqlx::query("DELETE FROM user_history WHERE user_id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Get the next episode to watch for each anime in a user's history
+///
+/// Episodes of an anime are ordered by their numeric episode number, falling
+/// back to first-seen order. For each anime, the next episode is the first
+/// one after the most recently watched episode that isn't in the history or
+/// marked watched.
+/// Anime the user is caught up on, or whose episodes haven't been crawled,
+/// are left out.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `limit` - Maximum number of anime to return
+///
+/// # Returns
+/// * `Ok(Vec<ContinueWatching>)` - Entries sorted by the later of the last
+///   watch and the next episode's release, most recent first
+pub async fn get_continue_watching(
+    pool: &PgPool,
+    user_id: i32,
+    limit: i64,
+) -> RepositoryResult<Vec<ContinueWatching>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        WITH last_watched AS (
+            SELECT DISTINCT ON (anime_slug)
+                anime_slug, episode_slug, anime_title, thumbnail, watched_at
+            FROM user_history
+            WHERE user_id = $1
+            ORDER BY anime_slug, watched_at DESC NULLS LAST
+        ),
+        ordered AS (
+            SELECT anime_slug, number, title, url, release_date,
+                {slug} AS slug,
+                COALESCE(first_seen_at, created_at) AS first_seen_at,
+                ROW_NUMBER() OVER (
+                    PARTITION BY anime_slug
+                    ORDER BY {number} NULLS LAST, COALESCE(first_seen_at, created_at), id
+                ) AS position
+            FROM episodes
+            WHERE anime_slug IN (SELECT anime_slug FROM last_watched)
+        )
+        SELECT lw.anime_slug, lw.episode_slug AS last_episode_slug, lw.watched_at,
+            COALESCE(NULLIF(lw.anime_title, ''), ad.title) AS anime_title,
+            COALESCE(NULLIF(lw.thumbnail, ''), ad.poster) AS thumbnail,
+            nxt.slug, nxt.number, nxt.title, nxt.url, nxt.release_date, nxt.first_seen_at
+        FROM last_watched lw
+        JOIN anime_details ad ON ad.slug = lw.anime_slug
+        JOIN ordered cur ON cur.anime_slug = lw.anime_slug AND cur.slug = lw.episode_slug
+        JOIN LATERAL (
+            SELECT o.*
+            FROM ordered o
+            WHERE o.anime_slug = lw.anime_slug
+              AND o.position > cur.position
+              AND NOT EXISTS (
+                  SELECT 1 FROM user_history h
+                  WHERE h.user_id = $1 AND h.episode_slug = o.slug
+              )
+              AND NOT EXISTS (
+                  SELECT 1 FROM user_watched_episodes w
+                  WHERE w.user_id = $1 AND w.episode_slug = o.slug AND w.watched
+              )
+            ORDER BY o.position
+            LIMIT 1
+        ) nxt ON TRUE
+        ORDER BY GREATEST(lw.watched_at, nxt.first_seen_at) DESC, lw.anime_slug
+        LIMIT $2
+        "#,
+        slug = EPISODE_SLUG_SQL,
+        number = EPISODE_NUMBER_SQL,
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let entries = rows
+        .into_iter()
+        .map(|row| {
+            let watched_at: DateTime<Utc> = row
+                .get::<Option<DateTime<Utc>>, _>("watched_at")
+                .unwrap_or_else(Utc::now);
+            let first_seen_at: DateTime<Utc> = row
+                .get::<Option<DateTime<Utc>>, _>("first_seen_at")
+                .unwrap_or_else(Utc::now);
+            ContinueWatching {
+                anime_slug: row.get("anime_slug"),
+                anime_title: row
+                    .get::<Option<String>, _>("anime_title")
+                    .unwrap_or_default(),
+                thumbnail: row
+                    .get::<Option<String>, _>("thumbnail")
+                    .unwrap_or_default(),
+                last_episode_slug: row.get("last_episode_slug"),
+                last_watched_at: watched_at.to_rfc3339(),
+                next_episode: {
+                    let release_date = row
+                        .get::<Option<String>, _>("release_date")
+                        .unwrap_or_default();
+                    Episode {
+                        slug: row.get("slug"),
+                        number: row.get::<Option<String>, _>("number").unwrap_or_default(),
+                        title: row.get::<Option<String>, _>("title").unwrap_or_default(),
+                        url: row.get("url"),
+                        released_at_iso: iso_date(&release_date, first_seen_at),
+                        release_date,
+                    }
+                },
+                next_released_at: first_seen_at.to_rfc3339(),
+                new_episode: first_seen_at > watched_at,
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+// ============================================================================
+// Watched Episodes Repository
+// ============================================================================
+
+/// Episodes of an anime to mark watched or unwatched
+#[derive(Debug, Clone, PartialEq)]
+pub enum EpisodeSelection {
+    /// Every released episode (e.g., a whole season)
+    All,
+    /// Episodes numbered up to and including this number
+    UpTo(f64),
+    /// Episodes with these slugs
+    Episodes(Vec<String>),
+}
+
+/// Mark episodes of an anime watched or unwatched for a user
+///
+/// Only episodes stored for the anime are marked; unknown slugs are ignored.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug
+/// * `selection` - Which episodes to mark
+/// * `watched` - Mark watched (true) or unwatched (false)
+///
+/// # Returns
+/// * `Ok(count)` - Number of episodes marked
+pub async fn mark_episodes_watched(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+    selection: &EpisodeSelection,
+    watched: bool,
+) -> RepositoryResult<u64> {
+    let filter = match selection {
+        EpisodeSelection::All => String::new(),
+        EpisodeSelection::UpTo(_) => format!("AND {}::float8 <= $4", EPISODE_NUMBER_SQL),
+        EpisodeSelection::Episodes(_) => format!("AND {} = ANY($4)", EPISODE_SLUG_SQL),
+    };
+    let sql = format!(
+        r#"
+        INSERT INTO user_watched_episodes (user_id, anime_slug, episode_slug, watched, updated_at)
+        SELECT DISTINCT $1, anime_slug, {slug}, $3, CURRENT_TIMESTAMP
+        FROM episodes
+        WHERE anime_slug = $2 {filter}
+        ON CONFLICT (user_id, episode_slug) DO UPDATE SET
+            anime_slug = EXCLUDED.anime_slug,
+            watched = EXCLUDED.watched,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+        slug = EPISODE_SLUG_SQL,
+        filter = filter,
+    );
+
+    let query = sqlx::query(&sql)
+        .bind(user_id)
+        .bind(anime_slug)
+        .bind(watched);
+    let query = match selection {
+        EpisodeSelection::All => query,
+        EpisodeSelection::UpTo(number) => query.bind(*number),
+        EpisodeSelection::Episodes(slugs) => query.bind(slugs),
+    };
+
+    let result = query.execute(pool).await?;
+    Ok(result.rows_affected())
+}
+
+/// Get a user's watched episodes of an anime
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `anime_slug` - Anime slug
+///
+/// # Returns
+/// * `Ok(Some(WatchProgress))` - Watched episodes in episode order
+/// * `Ok(None)` - Anime not in the database
+pub async fn get_watch_progress(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+) -> RepositoryResult<Option<WatchProgress>> {
+    let anime = sqlx::query(
+        r#"
+        SELECT (SELECT COUNT(*) FROM episodes WHERE anime_slug = $1) AS total_episodes
+        FROM anime_details
+        WHERE slug = $1
+        "#,
+    )
+    .bind(anime_slug)
+    .fetch_optional(pool)
+    .await?;
+    let Some(anime) = anime else {
+        return Ok(None);
+    };
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT w.episode_slug
+        FROM user_watched_episodes w
+        LEFT JOIN episodes e
+            ON e.anime_slug = w.anime_slug AND {slug} = w.episode_slug
+        WHERE w.user_id = $1 AND w.anime_slug = $2 AND w.watched
+        ORDER BY {number} NULLS LAST, w.episode_slug
+        "#,
+        slug = EPISODE_SLUG_SQL,
+        number = EPISODE_NUMBER_SQL,
+    ))
+    .bind(user_id)
+    .bind(anime_slug)
+    .fetch_all(pool)
+    .await?;
+
+    let watched_episodes: Vec<String> = rows.iter().map(|row| row.get("episode_slug")).collect();
+    Ok(Some(WatchProgress {
+        anime_slug: anime_slug.to_string(),
+        watched_count: watched_episodes.len() as i64,
+        total_episodes: anime.get("total_episodes"),
+        watched_episodes,
+    }))
+}
+
+// ============================================================================
+// Tenants Repository
+// ============================================================================
+
+/// Tenant that existing and unresolved users belong to
+pub const DEFAULT_TENANT_ID: i32 = 1;
+
+/// Unique constraint on (tenant_id, email)
+const USERS_EMAIL_CONSTRAINT: &str = "users_tenant_email_key";
+
+/// Unique constraint on (tenant_id, email_hash)
+const USERS_EMAIL_HASH_CONSTRAINT: &str = "users_tenant_email_hash_key";
+
+fn tenant_from_row(row: &sqlx::postgres::PgRow) -> Tenant {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    Tenant {
+        id: row.get("id"),
+        slug: row.get("slug"),
+        name: row.get("name"),
+        hostnames: row.get("hostnames"),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+/// Get all tenants
+///
+/// # Returns
+/// * `Ok(Vec<Tenant>)` - Tenants ordered by ID
+pub async fn get_tenants(pool: &PgPool) -> RepositoryResult<Vec<Tenant>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, slug, name, hostnames, created_at
+        FROM tenants
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(tenant_from_row).collect())
+}
+
+/// Create a tenant
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `slug` - Unique identifier used in the tenant header
+/// * `name` - Display name
+/// * `hostnames` - Hostnames that resolve to the tenant
+///
+/// # Returns
+/// * `Ok(Tenant)` - The created tenant
+/// * `Err(RepositoryError::Conflict)` - If the slug is taken
+pub async fn create_tenant(
+    pool: &PgPool,
+    slug: &str,
+    name: &str,
+    hostnames: &[String],
+) -> RepositoryResult<Tenant> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO tenants (slug, name, hostnames)
+        VALUES ($1, $2, $3)
+        RETURNING id, slug, name, hostnames, created_at
+        "#,
+    )
+    .bind(slug)
+    .bind(name)
+    .bind(hostnames)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        if let sqlx::Error::Database(ref db_err) = e {
+            if db_err.is_unique_violation() {
+                return RepositoryError::Conflict(format!("Tenant {} already exists", slug));
+            }
+        }
+        RepositoryError::DatabaseError(e)
+    })?;
+
+    Ok(tenant_from_row(&row))
+}
+
+// ============================================================================
+// Roles Repository
+// ============================================================================
+
+const ROLE_COLUMNS: &str = "id, name, description, permissions, created_at, updated_at";
+
+fn role_from_row(row: &sqlx::postgres::PgRow) -> Role {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let updated_at: DateTime<Utc> = row.get("updated_at");
+    Role {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        permissions: row.get("permissions"),
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+    }
+}
+
+/// Get all roles
+///
+/// # Returns
+/// * `Ok(Vec<Role>)` - Roles ordered by name
+pub async fn get_roles(pool: &PgPool) -> RepositoryResult<Vec<Role>> {
+    let rows = sqlx::query(&format!("SELECT {} FROM roles ORDER BY name", ROLE_COLUMNS))
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(role_from_row).collect())
+}
+
+/// Create a role
+///
+/// # Returns
+/// * `Ok(Role)` - The created role
+/// * `Err(RepositoryError::Conflict)` - A role with this name exists
+pub async fn create_role(
+    pool: &PgPool,
+    name: &str,
+    description: &str,
+    permissions: &[String],
+) -> RepositoryResult<Role> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO roles (name, description, permissions)
+        VALUES ($1, $2, $3)
+        RETURNING {}
+        "#,
+        ROLE_COLUMNS
+    ))
+    .bind(name)
+    .bind(description)
+    .bind(permissions)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        if let sqlx::Error::Database(ref db_err) = e {
+            if db_err.is_unique_violation() {
+                return RepositoryError::Conflict(format!("Role {} already exists", name));
+            }
+        }
+        RepositoryError::DatabaseError(e)
+    })?;
+
+    Ok(role_from_row(&row))
+}
+
+/// Change a role's description and/or permissions
+///
+/// # Returns
+/// * `Ok(Some(Role))` - The updated role
+/// * `Ok(None)` - Role not found
+pub async fn update_role(
+    pool: &PgPool,
+    role_id: i32,
+    description: Option<&str>,
+    permissions: Option<&[String]>,
+) -> RepositoryResult<Option<Role>> {
+    let row = sqlx::query(&format!(
+        r#"
+        UPDATE roles SET
+            description = COALESCE($2, description),
+            permissions = COALESCE($3, permissions),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        ROLE_COLUMNS
+    ))
+    .bind(role_id)
+    .bind(description)
+    .bind(permissions)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(role_from_row))
+}
+
+/// Delete a role, revoking it from every user
+///
+/// # Returns
+/// * `Ok(true)` - Role deleted
+/// * `Ok(false)` - Role not found
+pub async fn delete_role(pool: &PgPool, role_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM roles WHERE id = $1")
+        .bind(role_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Grant a role to a user
+///
+/// Granting a role the user already has succeeds without changes.
+///
+/// # Returns
+/// * `Ok(true)` - Role granted (or already held)
+/// * `Ok(false)` - User or role not found
+pub async fn assign_role(pool: &PgPool, user_id: i32, role_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_roles (user_id, role_id)
+        SELECT u.id, r.id FROM users u, roles r
+        WHERE u.id = $1 AND r.id = $2
+        ON CONFLICT (user_id, role_id) DO UPDATE SET user_id = EXCLUDED.user_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(role_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke a role from a user
+///
+/// # Returns
+/// * `Ok(true)` - Role revoked
+/// * `Ok(false)` - The user didn't have the role
+pub async fn unassign_role(pool: &PgPool, user_id: i32, role_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2")
+        .bind(user_id)
+        .bind(role_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the roles of a user and the permissions they grant
+///
+/// # Returns
+/// * `Ok(Some(UserRoles))` - Roles ordered by name, permissions sorted and deduplicated
+/// * `Ok(None)` - User not found
+pub async fn get_user_roles(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<UserRoles>> {
+    let Some(user) = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let rows = sqlx::query(
+        r#"
+        SELECT r.id, r.name, r.description, r.permissions, r.created_at, r.updated_at
+        FROM roles r
+        JOIN user_roles ur ON ur.role_id = r.id
+        WHERE ur.user_id = $1
+        ORDER BY r.name
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    let roles: Vec<Role> = rows.iter().map(role_from_row).collect();
+
+    let mut permissions: Vec<String> = roles
+        .iter()
+        .flat_map(|role| role.permissions.iter().cloned())
+        .collect();
+    permissions.sort();
+    permissions.dedup();
+
+    Ok(Some(UserRoles {
+        user_id,
+        is_admin: user.get::<Option<bool>, _>("is_admin").unwrap_or(false),
+        roles,
+        permissions,
+    }))
+}
+
+/// Whether a user holds a permission, through a role or by being an admin
+pub async fn user_has_permission(
+    pool: &PgPool,
+    user_id: i32,
+    permission: &str,
+) -> RepositoryResult<bool> {
+    let row = sqlx::query(
+        r#"
+        SELECT COALESCE(u.is_admin, FALSE) OR EXISTS (
+            SELECT 1 FROM user_roles ur
+            JOIN roles r ON r.id = ur.role_id
+            WHERE ur.user_id = u.id AND $2 = ANY(r.permissions)
+        ) AS allowed
+        FROM users u
+        WHERE u.id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(permission)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.is_some_and(|row| row.get::<bool, _>("allowed")))
+}
+
+// ============================================================================
+// Moderation Repository
+// ============================================================================
+
+const MODERATION_ITEM_COLUMNS: &str = "id, content_type, content_id, author_id, excerpt, status, \
+     report_count, resolved_by, resolution_note, resolved_at, created_at";
+
+const USER_STRIKE_COLUMNS: &str = "id, user_id, item_id, reason, issued_by, created_at";
+
+fn moderation_item_from_row(row: &sqlx::postgres::PgRow) -> ModerationItem {
+    let status: String = row.get("status");
+    let resolved_at: Option<DateTime<Utc>> = row.get("resolved_at");
+    let created_at: DateTime<Utc> = row.get("created_at");
+    ModerationItem {
+        id: row.get("id"),
+        content_type: row.get("content_type"),
+        content_id: row.get("content_id"),
+        author_id: row.get("author_id"),
+        excerpt: row.get("excerpt"),
+        status: ModerationStatus::parse(&status).unwrap_or(ModerationStatus::Pending),
+        report_count: row.get("report_count"),
+        resolved_by: row.get("resolved_by"),
+        resolution_note: row.get("resolution_note"),
+        resolved_at: resolved_at.map(|t| t.to_rfc3339()),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+fn user_strike_from_row(row: &sqlx::postgres::PgRow) -> UserStrike {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    UserStrike {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        item_id: row.get("item_id"),
+        reason: row.get("reason"),
+        issued_by: row.get("issued_by"),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+/// Record a user's report against content, queueing the content for review
+///
+/// The first report creates the queue item with a snapshot of the content;
+/// later reports add to it. A user can report the same content only once.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `content_type` - Kind of content (e.g., "comment")
+/// * `content_id` - ID of the content within its kind
+/// * `author_id` - User who wrote the content
+/// * `excerpt` - Snapshot of the content
+/// * `reporter_id` - Reporting user
+/// * `reason` - Why the content was reported
+///
+/// # Returns
+/// * `Ok((item, true))` - Report recorded
+/// * `Ok((item, false))` - The user had already reported the content
+pub async fn report_content(
+    pool: &PgPool,
+    content_type: &str,
+    content_id: i32,
+    author_id: i32,
+    excerpt: &str,
+    reporter_id: i32,
+    reason: &str,
+) -> RepositoryResult<(ModerationItem, bool)> {
+    let mut tx = pool.begin().await?;
+
+    let item_id: i32 = sqlx::query(
+        r#"
+        INSERT INTO moderation_items (content_type, content_id, author_id, excerpt)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (content_type, content_id)
+        DO UPDATE SET updated_at = moderation_items.updated_at
+        RETURNING id
+        "#,
+    )
+    .bind(content_type)
+    .bind(content_id)
+    .bind(author_id)
+    .bind(excerpt)
+    .fetch_one(&mut *tx)
+    .await?
+    .get("id");
+
+    let reported = sqlx::query(
+        r#"
+        INSERT INTO content_reports (item_id, reporter_id, reason)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (item_id, reporter_id) DO NOTHING
+        "#,
+    )
+    .bind(item_id)
+    .bind(reporter_id)
+    .bind(reason)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+
+    let row = sqlx::query(&format!(
+        "UPDATE moderation_items SET report_count = report_count + $2, \
+         updated_at = CURRENT_TIMESTAMP WHERE id = $1 RETURNING {}",
+        MODERATION_ITEM_COLUMNS
+    ))
+    .bind(item_id)
+    .bind(i32::from(reported))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok((moderation_item_from_row(&row), reported))
+}
+
+/// Get moderation queue items in a status, most reported first
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `status` - Status to list
+/// * `limit` - Maximum number of items
+pub async fn get_moderation_queue(
+    pool: &PgPool,
+    status: ModerationStatus,
+    limit: i64,
+) -> RepositoryResult<Vec<ModerationItem>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM moderation_items WHERE status = $1 \
+         ORDER BY report_count DESC, created_at, id LIMIT $2",
+        MODERATION_ITEM_COLUMNS
+    ))
+    .bind(status.as_str())
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(moderation_item_from_row).collect())
+}
+
+/// Get a moderation queue item by ID
+pub async fn get_moderation_item(
+    pool: &PgPool,
+    item_id: i32,
+) -> RepositoryResult<Option<ModerationItem>> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM moderation_items WHERE id = $1",
+        MODERATION_ITEM_COLUMNS
+    ))
+    .bind(item_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.as_ref().map(moderation_item_from_row))
+}
+
+/// Get the reports against a moderation queue item, oldest first
+pub async fn get_content_reports(
+    pool: &PgPool,
+    item_id: i32,
+) -> RepositoryResult<Vec<ContentReport>> {
+    let rows = sqlx::query(
+        "SELECT reporter_id, reason, created_at FROM content_reports \
+         WHERE item_id = $1 ORDER BY created_at, reporter_id",
+    )
+    .bind(item_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let created_at: DateTime<Utc> = row.get("created_at");
+            ContentReport {
+                reporter_id: row.get("reporter_id"),
+                reason: row.get("reason"),
+                created_at: created_at.to_rfc3339(),
+            }
+        })
+        .collect())
+}
+
+/// Approve a moderation queue item
+///
+/// Only changes the item if it is still in status `from`, so two moderators
+/// deciding at once can't both succeed.
+///
+/// # Returns
+/// * `Ok(Some(item))` - The approved item
+/// * `Ok(None)` - Item not found or no longer in status `from`
+pub async fn approve_moderation_item(
+    pool: &PgPool,
+    item_id: i32,
+    from: ModerationStatus,
+    moderator_id: i32,
+    note: Option<&str>,
+) -> RepositoryResult<Option<ModerationItem>> {
+    let row = sqlx::query(&format!(
+        "UPDATE moderation_items SET status = 'approved', resolved_by = $3, \
+         resolution_note = $4, resolved_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP \
+         WHERE id = $1 AND status = $2 RETURNING {}",
+        MODERATION_ITEM_COLUMNS
+    ))
+    .bind(item_id)
+    .bind(from.as_str())
+    .bind(moderator_id)
+    .bind(note)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.as_ref().map(moderation_item_from_row))
+}
+
+/// Remove a moderation queue item and issue its author a strike
+///
+/// Like [`approve_moderation_item`], only changes an item still in status
+/// `from`. Pass a [`UnitOfWork`](super::UnitOfWork) connection so the
+/// status change, the strike, and whatever the caller records with them are
+/// written together.
+///
+/// # Returns
+/// * `Ok(Some((item, strike)))` - The removed item and the author's new strike
+/// * `Ok(None)` - Item not found or no longer in status `from`
+pub async fn remove_moderation_item(
+    conn: &mut PgConnection,
+    item_id: i32,
+    from: ModerationStatus,
+    moderator_id: i32,
+    note: Option<&str>,
+) -> RepositoryResult<Option<(ModerationItem, UserStrike)>> {
+    let Some(row) = sqlx::query(&format!(
+        "UPDATE moderation_items SET status = 'removed', resolved_by = $3, \
+         resolution_note = $4, resolved_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP \
+         WHERE id = $1 AND status = $2 RETURNING {}",
+        MODERATION_ITEM_COLUMNS
+    ))
+    .bind(item_id)
+    .bind(from.as_str())
+    .bind(moderator_id)
+    .bind(note)
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let item = moderation_item_from_row(&row);
+
+    let row = sqlx::query(&format!(
+        "INSERT INTO user_strikes (user_id, item_id, reason, issued_by) \
+         VALUES ($1, $2, $3, $4) RETURNING {}",
+        USER_STRIKE_COLUMNS
+    ))
+    .bind(item.author_id)
+    .bind(item.id)
+    .bind(note.unwrap_or_default())
+    .bind(moderator_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Some((item, user_strike_from_row(&row))))
+}
+
+/// Count a user's strikes issued since a point in time
+pub async fn count_user_strikes_since(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    since: DateTime<Utc>,
+) -> RepositoryResult<i64> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS count FROM user_strikes WHERE user_id = $1 AND created_at >= $2",
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_one(executor)
+    .await?;
+    Ok(row.get("count"))
+}
+
+/// Get a user's strike record and current mute
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `since` - Strikes issued since then count as recent
+///
+/// # Returns
+/// * `Ok(Some(standing))` - The user's record
+/// * `Ok(None)` - User not found
+pub async fn get_moderation_standing(
+    pool: &PgPool,
+    user_id: i32,
+    since: DateTime<Utc>,
+) -> RepositoryResult<Option<ModerationStanding>> {
+    let Some(user) = sqlx::query(
+        "SELECT CASE WHEN muted_until > CURRENT_TIMESTAMP THEN muted_until END AS muted_until \
+         FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let muted_until: Option<DateTime<Utc>> = user.get("muted_until");
+
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM user_strikes WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+        USER_STRIKE_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(ModerationStanding {
+        user_id,
+        recent_strikes: count_user_strikes_since(pool, user_id, since).await?,
+        muted_until: muted_until.map(|t| t.to_rfc3339()),
+        strikes: rows.iter().map(user_strike_from_row).collect(),
+    }))
+}
+
+/// Mute a user until a point in time
+///
+/// Never shortens a mute already in effect.
+///
+/// # Returns
+/// * `Ok(Some(until))` - End of the user's mute
+/// * `Ok(None)` - User not found
+pub async fn mute_user(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    until: DateTime<Utc>,
+) -> RepositoryResult<Option<DateTime<Utc>>> {
+    let row = sqlx::query(
+        "UPDATE users SET muted_until = GREATEST(COALESCE(muted_until, $2), $2) \
+         WHERE id = $1 RETURNING muted_until",
+    )
+    .bind(user_id)
+    .bind(until)
+    .fetch_optional(executor)
+    .await?;
+    Ok(row.map(|row| row.get("muted_until")))
+}
+
+/// Lift a user's mute
+///
+/// # Returns
+/// * `Ok(true)` - User was muted and no longer is
+/// * `Ok(false)` - User not found or not muted
+pub async fn unmute_user(pool: &PgPool, user_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        "UPDATE users SET muted_until = NULL \
+         WHERE id = $1 AND muted_until > CURRENT_TIMESTAMP",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// End of a user's current mute, or `None` if they aren't muted
+pub async fn get_user_muted_until(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<Option<DateTime<Utc>>> {
+    let row = sqlx::query(
+        "SELECT muted_until FROM users WHERE id = $1 AND muted_until > CURRENT_TIMESTAMP",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| row.get("muted_until")))
+}
+
+/// Email address and language for account notices to a user
+///
+/// # Returns
+/// * `Ok(Some((email, language)))` - The user's verified address and language
+/// * `Ok(None)` - User not found or their address isn't verified
+pub async fn get_notice_recipient(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<Option<(String, String)>> {
+    let row = sqlx::query(
+        r#"
+        SELECT u.email, COALESCE(p.language, 'en') AS language
+        FROM users u
+        LEFT JOIN user_preferences p ON p.user_id = u.id
+        WHERE u.id = $1 AND u.email_verified = TRUE
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    row.map(|row| {
+        Ok((
+            encryption::open(FIELD_EMAIL, row.get("email"))?,
+            row.get("language"),
+        ))
+    })
+    .transpose()
+}
+
+// ============================================================================
+// Registration Throttling Repository
+// ============================================================================
+
+/// Count registrations from a client IP since a point in time
+pub async fn count_registrations_from_ip(
+    pool: &PgPool,
+    ip_address: &str,
+    since: DateTime<Utc>,
+) -> RepositoryResult<i64> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS count FROM registration_ips WHERE ip_address = $1 AND created_at >= $2",
+    )
+    .bind(ip_address)
+    .bind(since)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.get("count"))
+}
+
+/// Record a registration from a client IP
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `ip_address` - Client IP address
+/// * `purge_before` - Registrations recorded before this, from any IP, are deleted
+pub async fn record_registration_ip(
+    pool: &PgPool,
+    ip_address: &str,
+    purge_before: DateTime<Utc>,
+) -> RepositoryResult<()> {
+    sqlx::query("DELETE FROM registration_ips WHERE created_at < $1")
+        .bind(purge_before)
+        .execute(pool)
+        .await?;
+    sqlx::query("INSERT INTO registration_ips (ip_address) VALUES ($1)")
+        .bind(ip_address)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Sessions Repository
+// ============================================================================
+
+/// Minimum seconds between last_seen_at updates for a session
+const SESSION_TOUCH_INTERVAL_SECS: f64 = 60.0;
+
+/// Map a sessions row into a Session
+fn session_from_row(row: &sqlx::postgres::PgRow) -> Session {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let last_seen_at: DateTime<Utc> = row.get("last_seen_at");
+    let expires_at: DateTime<Utc> = row.get("expires_at");
+
+    Session {
+        id: row.get("id"),
+        user_agent: row.get("user_agent"),
+        ip_address: row.get("ip_address"),
+        created_at: created_at.to_rfc3339(),
+        last_seen_at: last_seen_at.to_rfc3339(),
+        expires_at: expires_at.to_rfc3339(),
+        current: false,
+    }
+}
+
+/// Create a login session
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `user_agent` - Client user agent, if sent
+/// * `ip_address` - Client IP address, if known
+/// * `expires_in_days` - Days until the session's token expires
+///
+/// # Returns
+/// * `Ok(Session)` - The created session
+pub async fn create_session(
+    pool: &PgPool,
+    user_id: i32,
+    user_agent: Option<&str>,
+    ip_address: Option<&str>,
+    expires_in_days: i64,
+) -> RepositoryResult<Session> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO sessions (user_id, user_agent, ip_address, expires_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(days => $4))
+        RETURNING id, user_agent, ip_address, created_at, last_seen_at, expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(user_agent)
+    .bind(ip_address)
+    .bind(expires_in_days as i32)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(session_from_row(&row))
+}
+
+/// Check that a session is active and record that it was just used
+///
+/// last_seen_at is only written when it is more than a minute old, to avoid
+/// a write on every request.
+///
+/// # Returns
+/// * `Ok(true)` - Session exists, belongs to the user, and is not revoked or expired
+/// * `Ok(false)` - Session is revoked, expired, or unknown
+pub async fn touch_session(pool: &PgPool, session_id: i32, user_id: i32) -> RepositoryResult<bool> {
+    let row = sqlx::query(
+        r#"
+        WITH active AS (
+            SELECT id, last_seen_at FROM sessions
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+              AND expires_at > CURRENT_TIMESTAMP
+        ),
+        touched AS (
+            UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM active
+                WHERE last_seen_at < CURRENT_TIMESTAMP - make_interval(secs => $3)
+            )
+        )
+        SELECT EXISTS(SELECT 1 FROM active) AS active
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .bind(SESSION_TOUCH_INTERVAL_SECS)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.get("active"))
+}
+
+/// Get a user's active sessions, most recently used first
+pub async fn get_active_sessions(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<Session>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, user_agent, ip_address, created_at, last_seen_at, expires_at
+        FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+        ORDER BY last_seen_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(session_from_row).collect())
+}
+
+/// Revoke one of a user's sessions
+///
+/// # Returns
+/// * `Ok(true)` - Session was revoked
+/// * `Ok(false)` - Session not found, not owned by the user, or already revoked
+pub async fn revoke_session(
+    pool: &PgPool,
+    user_id: i32,
+    session_id: i32,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Revoke every active session of a user (e.g., after a password reset)
+///
+/// # Returns
+/// * `Ok(count)` - Number of sessions revoked
+pub async fn revoke_user_sessions(pool: &PgPool, user_id: i32) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// Devices Repository
+// ============================================================================
+
+/// Domain of the placeholder emails of anonymous accounts; `.invalid` is
+/// reserved, so nothing is ever delivered to them
+const ANONYMOUS_EMAIL_DOMAIN: &str = "anonymous.invalid";
+
+/// An anonymous device and the account its data is kept under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceAccount {
+    pub device_id: i32,
+    /// The device's anonymous account
+    pub user_id: i32,
+    pub tenant_id: i32,
+}
+
+/// Register an anonymous device, creating its account
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `tenant_id` - Tenant the device registers with
+/// * `token_hash` - SHA-256 hex digest of the device token
+/// * `platform` - Platform the client reported (e.g. "android")
+pub async fn create_device(
+    pool: &PgPool,
+    tenant_id: i32,
+    token_hash: &str,
+    platform: Option<&str>,
+) -> RepositoryResult<DeviceAccount> {
+    let email = format!(
+        "device-{}@{}",
+        uuid::Uuid::new_v4().simple(),
+        ANONYMOUS_EMAIL_DOMAIN
+    );
+    let mut tx = pool.begin().await?;
+
+    let user_id: i32 = sqlx::query(
+        r#"
+        INSERT INTO users (email, email_hash, tenant_id, is_anonymous, created_at, updated_at)
+        VALUES ($1, $2, $3, TRUE, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+        RETURNING id
+        "#,
+    )
+    .bind(encryption::seal(FIELD_EMAIL, &email))
+    .bind(encryption::blind_index(FIELD_EMAIL, &email))
+    .bind(tenant_id)
+    .fetch_one(&mut *tx)
+    .await?
+    .get("id");
+
+    let device_id: i32 = sqlx::query(
+        "INSERT INTO devices (user_id, token_hash, platform) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(user_id)
+    .bind(token_hash)
+    .bind(platform)
+    .fetch_one(&mut *tx)
+    .await?
+    .get("id");
+
+    tx.commit().await?;
+    Ok(DeviceAccount {
+        device_id,
+        user_id,
+        tenant_id,
+    })
+}
+
+/// Find the device a token belongs to, recording that it was used
+///
+/// `last_seen_at` is only written when older than
+/// [`SESSION_TOUCH_INTERVAL_SECS`], as for sessions.
+///
+/// # Returns
+/// * `Ok(Some(DeviceAccount))` - The device, if its account is active
+/// * `Ok(None)` - Unknown token, or the device was merged into an account
+pub async fn find_device(
+    pool: &PgPool,
+    token_hash: &str,
+) -> RepositoryResult<Option<DeviceAccount>> {
+    let row = sqlx::query(
+        r#"
+        WITH device AS (
+            SELECT d.id, d.user_id, d.tenant_id, d.last_seen_at
+            FROM devices d
+            JOIN users u ON u.id = d.user_id
+            WHERE d.token_hash = $1 AND u.is_active
+        ),
+        touched AS (
+            UPDATE devices SET last_seen_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM device
+                WHERE last_seen_at < CURRENT_TIMESTAMP - make_interval(secs => $2)
+            )
+        )
+        SELECT id, user_id, tenant_id FROM device
+        "#,
+    )
+    .bind(token_hash)
+    .bind(SESSION_TOUCH_INTERVAL_SECS)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| DeviceAccount {
+        device_id: row.get("id"),
+        user_id: row.get("user_id"),
+        tenant_id: row.get("tenant_id"),
+    }))
+}
+
+/// Merge an anonymous device's data into an account
+///
+/// Favorites, history, and watched episodes move to the account; where both
+/// have an entry, the account keeps its own but takes the later watch time.
+/// The anonymous account is then deleted with the device, so the token stops
+/// working.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `token_hash` - SHA-256 hex digest of the device token
+/// * `user_id` - Account the device signed in to
+/// * `tenant_id` - Tenant of the account; devices of other tenants are ignored
+///
+/// # Returns
+/// * `Ok(true)` - The device's data was merged
+/// * `Ok(false)` - No anonymous device with that token in the tenant
+pub async fn claim_device(
+    pool: &PgPool,
+    token_hash: &str,
+    user_id: i32,
+    tenant_id: i32,
+) -> RepositoryResult<bool> {
+    let mut tx = pool.begin().await?;
+
+    let Some(row) = sqlx::query(
+        r#"
+        SELECT d.user_id
+        FROM devices d
+        JOIN users u ON u.id = d.user_id
+        WHERE d.token_hash = $1 AND d.tenant_id = $2 AND u.is_anonymous AND d.user_id <> $3
+        FOR UPDATE OF d
+        "#,
+    )
+    .bind(token_hash)
+    .bind(tenant_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+    let anonymous_id: i32 = row.get("user_id");
+
+    let statements = [
+        r#"
+        UPDATE user_favorites SET user_id = $2
+        WHERE user_id = $1
+          AND anime_slug NOT IN (SELECT anime_slug FROM user_favorites WHERE user_id = $2)
+        "#,
+        r#"
+        UPDATE user_history h SET watched_at = a.watched_at
+        FROM user_history a
+        WHERE h.user_id = $2 AND a.user_id = $1
+          AND a.episode_slug = h.episode_slug AND a.watched_at > h.watched_at
+        "#,
+        r#"
+        UPDATE user_history SET user_id = $2
+        WHERE user_id = $1
+          AND episode_slug NOT IN (SELECT episode_slug FROM user_history WHERE user_id = $2)
+        "#,
+        r#"
+        UPDATE user_watched_episodes w SET watched = a.watched, updated_at = a.updated_at
+        FROM user_watched_episodes a
+        WHERE w.user_id = $2 AND a.user_id = $1
+          AND a.episode_slug = w.episode_slug AND a.updated_at > w.updated_at
+        "#,
+        r#"
+        UPDATE user_watched_episodes SET user_id = $2
+        WHERE user_id = $1
+          AND episode_slug NOT IN (SELECT episode_slug FROM user_watched_episodes WHERE user_id = $2)
+        "#,
+    ];
+    for statement in statements {
+        sqlx::query(statement)
+            .bind(anonymous_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query("DELETE FROM users WHERE id = $1 AND is_anonymous")
+        .bind(anonymous_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Delete anonymous accounts that were never used
+///
+/// An anonymous account is unused when none of the [`USER_DATA_TABLES`]
+/// hold anything of it besides its devices and sessions. It is deleted once
+/// it was created, and its devices last seen, before `idle_before`; its
+/// devices go with it.
+///
+/// # Returns
+/// * `Ok(count)` - Number of accounts deleted
+pub async fn delete_unused_anonymous_users(
+    pool: &PgPool,
+    idle_before: DateTime<Utc>,
+) -> RepositoryResult<u64> {
+    let unused = USER_DATA_TABLES
+        .iter()
+        .filter(|(table, _)| !matches!(*table, "devices" | "sessions"))
+        .map(|(table, column)| {
+            format!(
+                "AND NOT EXISTS (SELECT 1 FROM {} t WHERE t.{} = u.id)",
+                table, column
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n          ");
+    let result = sqlx::query(&format!(
+        r#"
+        DELETE FROM users u
+        WHERE u.is_anonymous
+          AND u.created_at < $1
+          AND NOT EXISTS (SELECT 1 FROM devices d WHERE d.user_id = u.id AND d.last_seen_at >= $1)
+          AND NOT EXISTS (SELECT 1 FROM collection_items t JOIN collections c ON c.id = t.collection_id WHERE c.user_id = u.id)
+          {}
+        "#,
+        unused
+    ))
+    .bind(idle_before)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// User Preferences Repository
+// ============================================================================
+
+/// Columns selected for every UserPreferences query
+const USER_PREFERENCES_COLUMNS: &str =
+    "email_notifications, digest_frequency, webhooks_enabled, preferred_quality, language, \
+    community_opt_out, show_adult_content";
+
+/// Map a user_preferences row into UserPreferences
+fn user_preferences_from_row(row: &sqlx::postgres::PgRow) -> UserPreferences {
+    UserPreferences {
+        email_notifications: row.get("email_notifications"),
+        digest_frequency: row.get("digest_frequency"),
+        webhooks_enabled: row.get("webhooks_enabled"),
+        preferred_quality: row.get("preferred_quality"),
+        language: row.get("language"),
+        community_opt_out: row.get("community_opt_out"),
+        show_adult_content: row.get("show_adult_content"),
+    }
+}
+
+/// Get a user's preferences
+///
+/// # Returns
+/// * `Ok(UserPreferences)` - Stored preferences, or the defaults if none were saved
+pub async fn get_user_preferences(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<UserPreferences> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM user_preferences
+        WHERE user_id = $1
+        "#,
+        USER_PREFERENCES_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row
+        .as_ref()
+        .map(user_preferences_from_row)
+        .unwrap_or_default())
+}
+
+/// Update a user's preferences, creating the row if needed
+///
+/// Fields that are `None` in the update keep their current (or default)
+/// value. An empty `preferred_quality` clears it.
+///
+/// # Returns
+/// * `Ok(UserPreferences)` - The preferences after the update
+pub async fn update_user_preferences(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    update: &UpdatePreferencesRequest,
+) -> RepositoryResult<UserPreferences> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO user_preferences (
+            user_id, email_notifications, digest_frequency, webhooks_enabled,
+            preferred_quality, language, community_opt_out, show_adult_content
+        )
+        VALUES (
+            $1, COALESCE($2, TRUE), COALESCE($3, 'instant'), COALESCE($4, FALSE),
+            NULLIF($6, ''), COALESCE($7, 'en'), COALESCE($8, FALSE), COALESCE($9, FALSE)
+        )
+        ON CONFLICT (user_id) DO UPDATE SET
+            email_notifications = COALESCE($2, user_preferences.email_notifications),
+            digest_frequency = COALESCE($3, user_preferences.digest_frequency),
+            webhooks_enabled = COALESCE($4, user_preferences.webhooks_enabled),
+            preferred_quality = CASE WHEN $5 THEN NULLIF($6, '')
+                                     ELSE user_preferences.preferred_quality END,
+            language = COALESCE($7, user_preferences.language),
+            community_opt_out = COALESCE($8, user_preferences.community_opt_out),
+            show_adult_content = COALESCE($9, user_preferences.show_adult_content),
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING {}
+        "#,
+        USER_PREFERENCES_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(update.email_notifications)
+    .bind(update.digest_frequency.as_deref())
+    .bind(update.webhooks_enabled)
+    .bind(update.preferred_quality.is_some())
+    .bind(update.preferred_quality.as_deref())
+    .bind(update.language.as_deref())
+    .bind(update.community_opt_out)
+    .bind(update.show_adult_content)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(user_preferences_from_row(&row))
+}
+
+/// Get a user's preferred language code
+///
+/// # Returns
+/// * `Ok(code)` - Language code, "en" if unset or the user doesn't exist
+pub async fn get_user_language(pool: &PgPool, user_id: i32) -> RepositoryResult<String> {
+    Ok(get_user_preferences(pool, user_id).await?.language)
+}
+
+/// Set a user's preferred language code
+pub async fn set_user_language(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    language: &str,
+) -> RepositoryResult<()> {
+    let update = UpdatePreferencesRequest {
+        language: Some(language.to_string()),
+        ..Default::default()
+    };
+    update_user_preferences(executor, user_id, &update).await?;
+    Ok(())
+}
+
+// ============================================================================
+// Saved Searches Repository
+// ============================================================================
+
+const SAVED_SEARCH_COLUMNS: &str = "id, name, query, type, status, last_checked_at, created_at";
+
+fn saved_search_from_row(row: &sqlx::postgres::PgRow) -> SavedSearch {
+    let last_checked_at: DateTime<Utc> = row.get("last_checked_at");
+    let created_at: DateTime<Utc> = row.get("created_at");
+
+    SavedSearch {
+        id: row.get("id"),
+        name: row.get("name"),
+        query: row.get("query"),
+        anime_type: row.get("type"),
+        status: row.get("status"),
+        last_checked_at: last_checked_at.to_rfc3339(),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+/// A saved search together with where to notify its owner
+#[derive(Debug, Clone)]
+pub struct SavedSearchRecipient {
+    pub search: SavedSearch,
+    pub user_id: i32,
+    pub email: String,
+    /// Email language code from the owner's preferences
+    pub language: String,
+    /// Whether the owner wants notification emails
+    pub email_notifications: bool,
+}
+
+/// Save a search for a user
+///
+/// New matches are counted from the moment the search is saved.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `name` - Display name
+/// * `query` - Normalized text query
+/// * `anime_type` - Anime type filter
+/// * `status` - Status filter
+///
+/// # Returns
+/// * `Ok(SavedSearch)` - The saved search
+pub async fn create_saved_search(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    query: Option<&str>,
+    anime_type: Option<&str>,
+    status: Option<&str>,
+) -> RepositoryResult<SavedSearch> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO saved_searches (user_id, name, query, type, status)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {}
+        "#,
+        SAVED_SEARCH_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(name)
+    .bind(query)
+    .bind(anime_type)
+    .bind(status)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(saved_search_from_row(&row))
+}
+
+/// Get a user's saved searches, newest first
+pub async fn get_saved_searches(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<SavedSearch>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM saved_searches
+        WHERE user_id = $1
+        ORDER BY created_at DESC, id DESC
+        "#,
+        SAVED_SEARCH_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(saved_search_from_row).collect())
+}
+
+/// Count a user's saved searches
+pub async fn count_saved_searches(pool: &PgPool, user_id: i32) -> RepositoryResult<i64> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM saved_searches WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("count"))
+}
+
+/// Delete one of a user's saved searches
+///
+/// # Returns
+/// * `Ok(true)` - Saved search was deleted
+/// * `Ok(false)` - Not found or owned by another user
+pub async fn delete_saved_search(
+    pool: &PgPool,
+    user_id: i32,
+    search_id: i32,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM saved_searches WHERE id = $1 AND user_id = $2")
+        .bind(search_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get every saved search with its owner's email and notification settings
+///
+/// Only active owners with a verified email address are included.
+pub async fn get_saved_search_recipients(
+    pool: &PgPool,
+) -> RepositoryResult<Vec<SavedSearchRecipient>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.id, s.name, s.query, s.type, s.status, s.last_checked_at, s.created_at,
+               u.id AS user_id, u.email,
+               COALESCE(p.language, 'en') AS language,
+               COALESCE(p.email_notifications, TRUE) AS email_notifications
+        FROM saved_searches s
+        JOIN users u ON u.id = s.user_id
+        LEFT JOIN user_preferences p ON p.user_id = s.user_id
+        WHERE u.email_verified = TRUE AND u.is_active
+        ORDER BY s.id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(SavedSearchRecipient {
+                search: saved_search_from_row(row),
+                user_id: row.get("user_id"),
+                email: encryption::open(FIELD_EMAIL, row.get("email"))?,
+                language: row.get("language"),
+                email_notifications: row.get("email_notifications"),
+            })
+        })
+        .collect()
+}
+
+/// Find anime crawled since a saved search was last checked that match it
+///
+/// The query matches case-insensitively anywhere in the title, or exactly
+/// one of the genres of the stored anime detail. Type and status must match
+/// exactly (ignoring case).
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `search_id` - Saved search ID
+/// * `until` - Only anime crawled up to this time are considered
+/// * `limit` - Maximum number of matches to return
+///
+/// # Returns
+/// * `Ok(Vec<CrawledAnimeRecord>)` - Matches, oldest first
+pub async fn find_saved_search_matches(
+    pool: &PgPool,
+    search_id: i32,
+    until: DateTime<Utc>,
+    limit: i64,
+) -> RepositoryResult<Vec<CrawledAnimeRecord>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT c.id, c.slug, c.title, c.url, c.thumbnail, c.status, c.type, c.episode_status,
+               c.popularity_score, c.created_at, c.updated_at
+        FROM crawled_anime c
+        JOIN saved_searches s ON s.id = $1
+        WHERE c.created_at > s.last_checked_at
+          AND c.created_at <= $2
+          AND (s.query IS NULL
+               OR strpos(lower(c.title), s.query) > 0
+               OR EXISTS (
+                   SELECT 1 FROM anime_details d, unnest(d.genres) AS genre
+                   WHERE d.slug = c.slug AND lower(genre) = s.query
+               ))
+          AND (s.type IS NULL OR lower(c.type) = lower(s.type))
+          AND (s.status IS NULL OR lower(c.status) = lower(s.status))
+        ORDER BY c.created_at, c.id
+        LIMIT $3
+        "#,
+    )
+    .bind(search_id)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(crawled_anime_from_row).collect())
+}
+
+/// Record that a saved search has been checked up to `checked_at`
+pub async fn mark_saved_search_checked(
+    executor: impl PgExecutor<'_>,
+    search_id: i32,
+    checked_at: DateTime<Utc>,
+) -> RepositoryResult<()> {
+    sqlx::query("UPDATE saved_searches SET last_checked_at = $2 WHERE id = $1")
+        .bind(search_id)
+        .bind(checked_at)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Collections Repository
+// ============================================================================
+
+/// Columns selected for every Collection query (aliased `c`), with the item count
+const COLLECTION_COLUMNS: &str = r#"
+    c.id, c.name, c.description, c.is_public, c.share_slug, c.created_at, c.updated_at,
+    (SELECT COUNT(*) FROM collection_items i WHERE i.collection_id = c.id) AS item_count
+"#;
+
+/// Map a collections row into a Collection
+fn collection_from_row(row: &sqlx::postgres::PgRow) -> Collection {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let updated_at: DateTime<Utc> = row.get("updated_at");
+
+    Collection {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        is_public: row.get("is_public"),
+        share_slug: row.get("share_slug"),
+        item_count: row.get("item_count"),
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+    }
+}
+
+/// Columns selected for every CollectionItem query
+const COLLECTION_ITEM_COLUMNS: &str = "anime_slug, anime_title, thumbnail, position, added_at";
+
+/// Map a collection_items row into a CollectionItem
+fn collection_item_from_row(row: &sqlx::postgres::PgRow) -> CollectionItem {
+    let added_at: DateTime<Utc> = row.get("added_at");
+
+    CollectionItem {
+        anime_slug: row.get("anime_slug"),
+        anime_title: row.get("anime_title"),
+        thumbnail: row
+            .get::<Option<String>, _>("thumbnail")
+            .unwrap_or_default(),
+        position: row.get("position"),
+        added_at: added_at.to_rfc3339(),
+    }
+}
+
+/// Create a collection for a user
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - Owner's user ID
+/// * `name` - Collection name
+/// * `description` - Optional description
+/// * `is_public` - Whether the collection can be viewed through its share slug
+/// * `share_slug` - Unique slug of the public link
+///
+/// # Returns
+/// * `Ok(Collection)` - The created, empty collection
+pub async fn create_collection(
+    pool: &PgPool,
+    user_id: i32,
+    name: &str,
+    description: Option<&str>,
+    is_public: bool,
+    share_slug: &str,
+) -> RepositoryResult<Collection> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO collections AS c (user_id, name, description, is_public, share_slug)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {}
+        "#,
+        COLLECTION_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(name)
+    .bind(description)
+    .bind(is_public)
+    .bind(share_slug)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(collection_from_row(&row))
+}
+
+/// Get a user's collections, newest first
+pub async fn get_collections(pool: &PgPool, user_id: i32) -> RepositoryResult<Vec<Collection>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM collections c
+        WHERE c.user_id = $1
+        ORDER BY c.created_at DESC, c.id DESC
+        "#,
+        COLLECTION_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(collection_from_row).collect())
+}
+
+/// Count a user's collections
+pub async fn count_collections(pool: &PgPool, user_id: i32) -> RepositoryResult<i64> {
+    let row = sqlx::query("SELECT COUNT(*) as count FROM collections WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("count"))
+}
+
+/// Get one of a user's collections
+///
+/// # Returns
+/// * `Ok(Some(Collection))` - The collection
+/// * `Ok(None)` - Not found or owned by another user
+pub async fn get_collection(
+    pool: &PgPool,
+    user_id: i32,
+    collection_id: i32,
+) -> RepositoryResult<Option<Collection>> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM collections c WHERE c.id = $1 AND c.user_id = $2",
+        COLLECTION_COLUMNS
+    ))
+    .bind(collection_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(collection_from_row))
+}
+
+/// Get a public collection by its share slug
+///
+/// Collections of deactivated owners are hidden along with the owner.
+///
+/// # Returns
+/// * `Ok(Some(Collection))` - The collection
+/// * `Ok(None)` - Not found, private, or owned by a deactivated user
+pub async fn get_public_collection(
+    pool: &PgPool,
+    tenant_id: i32,
+    share_slug: &str,
+) -> RepositoryResult<Option<Collection>> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM collections c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.share_slug = $1 AND c.tenant_id = $2 AND c.is_public AND u.is_active
+        "#,
+        COLLECTION_COLUMNS
+    ))
+    .bind(share_slug)
+    .bind(tenant_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(collection_from_row))
+}
+
+/// Update one of a user's collections
+///
+/// Fields left as `None` are kept; an empty description clears it.
+///
+/// # Returns
+/// * `Ok(Some(Collection))` - The updated collection
+/// * `Ok(None)` - Not found or owned by another user
+pub async fn update_collection(
+    pool: &PgPool,
+    user_id: i32,
+    collection_id: i32,
+    name: Option<&str>,
+    description: Option<&str>,
+    is_public: Option<bool>,
+) -> RepositoryResult<Option<Collection>> {
+    let row = sqlx::query(&format!(
+        r#"
+        UPDATE collections AS c
+        SET name = COALESCE($3, name),
+            description = CASE WHEN $4::TEXT IS NULL THEN description ELSE NULLIF($4, '') END,
+            is_public = COALESCE($5, is_public),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND user_id = $2
+        RETURNING {}
+        "#,
+        COLLECTION_COLUMNS
+    ))
+    .bind(collection_id)
+    .bind(user_id)
+    .bind(name)
+    .bind(description)
+    .bind(is_public)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(collection_from_row))
+}
+
+/// Delete one of a user's collections and its items
+///
+/// # Returns
+/// * `Ok(true)` - Collection was deleted
+/// * `Ok(false)` - Not found or owned by another user
+pub async fn delete_collection(
+    pool: &PgPool,
+    user_id: i32,
+    collection_id: i32,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM collections WHERE id = $1 AND user_id = $2")
+        .bind(collection_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the anime in a collection, in order
+pub async fn get_collection_items(
+    pool: &PgPool,
+    collection_id: i32,
+) -> RepositoryResult<Vec<CollectionItem>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM collection_items
+        WHERE collection_id = $1
+        ORDER BY position, id
+        "#,
+        COLLECTION_ITEM_COLUMNS
+    ))
+    .bind(collection_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(collection_item_from_row).collect())
+}
+
+/// Lock a collection for reordering its items and mark it updated
+///
+/// # Returns
+/// * `Ok(true)` - Collection is locked until the transaction ends
+/// * `Ok(false)` - Collection not found
+async fn lock_collection(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    collection_id: i32,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query("UPDATE collections SET updated_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(collection_id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Add an anime to a collection
+///
+/// Items at or after `position` move down one place. Without a position,
+/// or with one past the end, the anime is appended.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `collection_id` - Collection ID
+/// * `anime_slug` - Anime slug identifier
+/// * `anime_title` - Anime title for display
+/// * `thumbnail` - Thumbnail image URL
+/// * `position` - 0-based position to insert at
+///
+/// # Returns
+/// * `Ok(CollectionItem)` - The added item
+/// * `Err(RepositoryError::NotFound)` - Collection not found
+/// * `Err(RepositoryError::Conflict)` - Anime is already in the collection
+pub async fn add_collection_item(
+    pool: &PgPool,
+    collection_id: i32,
+    anime_slug: &str,
+    anime_title: &str,
+    thumbnail: &str,
+    position: Option<i32>,
+) -> RepositoryResult<CollectionItem> {
+    let mut tx = pool.begin().await?;
+
+    if !lock_collection(&mut tx, collection_id).await? {
+        return Err(RepositoryError::NotFound(format!(
+            "Collection {}",
+            collection_id
+        )));
+    }
+
+    let row = sqlx::query(
+        r#"
+        SELECT COUNT(*) AS count, BOOL_OR(anime_slug = $2) AS present
+        FROM collection_items
+        WHERE collection_id = $1
+        "#,
+    )
+    .bind(collection_id)
+    .bind(anime_slug)
+    .fetch_one(&mut *tx)
+    .await?;
+    if row.get::<Option<bool>, _>("present").unwrap_or(false) {
+        return Err(RepositoryError::Conflict(
+            "Anime already in collection".to_string(),
+        ));
+    }
+    let count = row.get::<i64, _>("count") as i32;
+    let position = position.map_or(count, |p| p.clamp(0, count));
+
+    sqlx::query(
+        r#"
+        UPDATE collection_items
+        SET position = position + 1
+        WHERE collection_id = $1 AND position >= $2
+        "#,
+    )
+    .bind(collection_id)
+    .bind(position)
+    .execute(&mut *tx)
+    .await?;
+
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO collection_items (collection_id, anime_slug, anime_title, thumbnail, position)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {}
+        "#,
+        COLLECTION_ITEM_COLUMNS
+    ))
+    .bind(collection_id)
+    .bind(anime_slug)
+    .bind(anime_title)
+    .bind(thumbnail)
+    .bind(position)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(collection_item_from_row(&row))
+}
+
+/// Remove an anime from a collection, closing the gap it leaves
+///
+/// # Returns
+/// * `Ok(true)` - Anime was removed
+/// * `Ok(false)` - Collection not found or anime not in it
+pub async fn remove_collection_item(
+    pool: &PgPool,
+    collection_id: i32,
+    anime_slug: &str,
+) -> RepositoryResult<bool> {
+    let mut tx = pool.begin().await?;
+
+    if !lock_collection(&mut tx, collection_id).await? {
+        return Ok(false);
+    }
+
+    let Some(position) = sqlx::query_scalar::<_, i32>(
+        "DELETE FROM collection_items WHERE collection_id = $1 AND anime_slug = $2 RETURNING position",
+    )
+    .bind(collection_id)
+    .bind(anime_slug)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE collection_items
+        SET position = position - 1
+        WHERE collection_id = $1 AND position > $2
+        "#,
+    )
+    .bind(collection_id)
+    .bind(position)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Reorder the anime in a collection
+///
+/// `anime_slugs` must list every anime in the collection exactly once, in
+/// the new order.
+///
+/// # Returns
+/// * `Ok(true)` - Items were reordered
+/// * `Ok(false)` - Collection not found, or the slugs don't match its items
+pub async fn reorder_collection_items(
+    pool: &PgPool,
+    collection_id: i32,
+    anime_slugs: &[String],
+) -> RepositoryResult<bool> {
+    let mut tx = pool.begin().await?;
+
+    if !lock_collection(&mut tx, collection_id).await? {
+        return Ok(false);
+    }
+
+    let mut current: Vec<String> =
+        sqlx::query_scalar("SELECT anime_slug FROM collection_items WHERE collection_id = $1")
+            .bind(collection_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    let mut requested = anime_slugs.to_vec();
+    current.sort();
+    requested.sort();
+    if current != requested {
+        return Ok(false);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE collection_items ci
+        SET position = o.ord - 1
+        FROM unnest($2::TEXT[]) WITH ORDINALITY AS o(anime_slug, ord)
+        WHERE ci.collection_id = $1 AND ci.anime_slug = o.anime_slug
+        "#,
+    )
+    .bind(collection_id)
+    .bind(anime_slugs)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+// ============================================================================
+// Episode Comments Repository
+// ============================================================================
+
+/// Columns selected for every EpisodeComment query (comment `c`, author `u`)
+const EPISODE_COMMENT_COLUMNS: &str = r#"
+    c.id, c.episode_slug, c.user_id, u.name AS author_name, u.avatar AS author_avatar,
+    c.body, c.timestamp_secs, c.created_at
+"#;
+
+/// Comments shown to users: not removed by moderators, author not deactivated
+const VISIBLE_COMMENT: &str = "c.removed_at IS NULL AND u.is_active";
+
+/// Map an episode_comments row into an EpisodeComment
+fn episode_comment_from_row(row: &sqlx::postgres::PgRow) -> EpisodeComment {
+    let created_at: DateTime<Utc> = row.get("created_at");
+
+    EpisodeComment {
+        id: row.get("id"),
+        episode_slug: row.get("episode_slug"),
+        author_id: row.get("user_id"),
+        author_name: row.get("author_name"),
+        author_avatar: row.get("author_avatar"),
+        body: row.get("body"),
+        timestamp_seconds: row.get("timestamp_secs"),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+/// Post a comment on an episode
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - Author's user ID
+/// * `episode_slug` - Episode slug identifier
+/// * `body` - Comment text
+/// * `timestamp_secs` - Playback position the comment refers to
+///
+/// # Returns
+/// * `Ok(EpisodeComment)` - The posted comment
+pub async fn create_episode_comment(
+    pool: &PgPool,
+    user_id: i32,
+    episode_slug: &str,
+    body: &str,
+    timestamp_secs: Option<i32>,
+) -> RepositoryResult<EpisodeComment> {
+    let row = sqlx::query(&format!(
+        r#"
+        WITH c AS (
+            INSERT INTO episode_comments (user_id, episode_slug, body, timestamp_secs)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+        )
+        SELECT {}
+        FROM c
+        JOIN users u ON u.id = c.user_id
+        "#,
+        EPISODE_COMMENT_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(episode_slug)
+    .bind(body)
+    .bind(timestamp_secs)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(episode_comment_from_row(&row))
+}
+
+/// Get a page of an episode's visible comments, newest first
+pub async fn get_episode_comments(
+    pool: &PgPool,
+    tenant_id: i32,
+    episode_slug: &str,
+    limit: i64,
+    offset: i64,
+) -> RepositoryResult<Vec<EpisodeComment>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM episode_comments c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.tenant_id = $1 AND c.episode_slug = $2 AND {}
+        ORDER BY c.created_at DESC, c.id DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        EPISODE_COMMENT_COLUMNS, VISIBLE_COMMENT
+    ))
+    .bind(tenant_id)
+    .bind(episode_slug)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(episode_comment_from_row).collect())
+}
+
+/// Count an episode's visible comments
+pub async fn count_episode_comments(
+    pool: &PgPool,
+    tenant_id: i32,
+    episode_slug: &str,
+) -> RepositoryResult<i64> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT COUNT(*) AS count
+        FROM episode_comments c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.tenant_id = $1 AND c.episode_slug = $2 AND {}
+        "#,
+        VISIBLE_COMMENT
+    ))
+    .bind(tenant_id)
+    .bind(episode_slug)
+    .fetch_one(pool)
+    .await?;
+    Ok(row.get("count"))
+}
+
+/// Get a visible comment on an episode
+///
+/// # Returns
+/// * `Ok(Some(EpisodeComment))` - The comment
+/// * `Ok(None)` - Not found, on another episode, or hidden
+pub async fn get_episode_comment(
+    pool: &PgPool,
+    tenant_id: i32,
+    episode_slug: &str,
+    comment_id: i32,
+) -> RepositoryResult<Option<EpisodeComment>> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM episode_comments c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.id = $1 AND c.tenant_id = $2 AND c.episode_slug = $3 AND {}
+        "#,
+        EPISODE_COMMENT_COLUMNS, VISIBLE_COMMENT
+    ))
+    .bind(comment_id)
+    .bind(tenant_id)
+    .bind(episode_slug)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(episode_comment_from_row))
+}
+
+/// Delete one of a user's comments on an episode
+///
+/// # Returns
+/// * `Ok(true)` - Comment was deleted
+/// * `Ok(false)` - Not found, on another episode, or written by another user
+pub async fn delete_episode_comment(
+    pool: &PgPool,
+    user_id: i32,
+    episode_slug: &str,
+    comment_id: i32,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        "DELETE FROM episode_comments WHERE id = $1 AND user_id = $2 AND episode_slug = $3",
+    )
+    .bind(comment_id)
+    .bind(user_id)
+    .bind(episode_slug)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Hide a comment removed by a moderator
+///
+/// # Returns
+/// * `Ok(true)` - Comment was hidden
+/// * `Ok(false)` - Comment not found or already hidden
+pub async fn remove_episode_comment(pool: &PgPool, comment_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE episode_comments
+        SET removed_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND removed_at IS NULL
+        "#,
+    )
+    .bind(comment_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// ============================================================================
+// Episode Reactions Repository
+// ============================================================================
+
+/// Like an episode
+///
+/// The episode's count in episode_reaction_counts is bumped by trigger.
+///
+/// # Returns
+/// * `Ok(true)` - Episode was liked
+/// * `Ok(false)` - User had already liked it
+pub async fn like_episode(
+    pool: &PgPool,
+    user_id: i32,
+    episode_slug: &str,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO episode_reactions (user_id, episode_slug)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, episode_slug) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(episode_slug)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Take back a like on an episode
+///
+/// # Returns
+/// * `Ok(true)` - Like was removed
+/// * `Ok(false)` - User hadn't liked the episode
+pub async fn unlike_episode(
+    pool: &PgPool,
+    user_id: i32,
+    episode_slug: &str,
+) -> RepositoryResult<bool> {
+    let result =
+        sqlx::query("DELETE FROM episode_reactions WHERE user_id = $1 AND episode_slug = $2")
+            .bind(user_id)
+            .bind(episode_slug)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the likes of several episodes in one query
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `tenant_id` - Tenant whose likes are counted
+/// * `user_id` - User for `liked_by_me`; `None` leaves it false
+/// * `episode_slugs` - Episode slug identifiers
+///
+/// # Returns
+/// * `Ok(Vec<EpisodeLikes>)` - One entry per slug, in the given order;
+///   episodes nobody liked have a count of 0
+pub async fn get_episode_likes(
+    pool: &PgPool,
+    tenant_id: i32,
+    user_id: Option<i32>,
+    episode_slugs: &[String],
+) -> RepositoryResult<Vec<EpisodeLikes>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.slug AS episode_slug,
+               COALESCE(c.like_count, 0)::BIGINT AS like_count,
+               EXISTS (
+                   SELECT 1 FROM episode_reactions r
+                   WHERE r.user_id = $3 AND r.episode_slug = s.slug
+               ) AS liked_by_me
+        FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS s(slug, position)
+        LEFT JOIN episode_reaction_counts c
+            ON c.tenant_id = $1 AND c.episode_slug = s.slug
+        ORDER BY s.position
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(episode_slugs)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| EpisodeLikes {
+            episode_slug: row.get("episode_slug"),
+            like_count: row.get("like_count"),
+            liked_by_me: row.get("liked_by_me"),
+        })
+        .collect())
+}
+
+// ============================================================================
+// Tags Repository
+// ============================================================================
+
+/// Tag query with popularity counts; callers append filters and ordering
+const TAG_SELECT: &str = r#"
+    SELECT t.id, t.slug, t.name, t.description, t.curated, t.created_at,
+           COUNT(DISTINCT at.anime_slug) AS anime_count,
+           COUNT(at.tag_id) AS usage_count
+    FROM tags t
+    LEFT JOIN anime_tags at ON at.tag_id = t.id
+"#;
+
+/// Map a TAG_SELECT row into a Tag
+fn tag_from_row(row: &sqlx::postgres::PgRow) -> Tag {
+    let created_at: DateTime<Utc> = row.get("created_at");
+
+    Tag {
+        id: row.get("id"),
+        slug: row.get("slug"),
+        name: row.get("name"),
+        description: row.get("description"),
+        curated: row.get("curated"),
+        anime_count: row.get("anime_count"),
+        usage_count: row.get("usage_count"),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+/// Map a unique violation on tags.slug into a conflict
+fn tag_conflict(e: sqlx::Error, slug: &str) -> RepositoryError {
+    if let sqlx::Error::Database(ref db_err) = e {
+        if db_err.is_unique_violation() {
+            return RepositoryError::Conflict(format!("Tag {} already exists", slug));
+        }
+    }
+    RepositoryError::DatabaseError(e)
+}
+
+/// Get tags ordered by popularity
+///
+/// # Arguments
+/// * `curated` - Only curated (`Some(true)`) or uncurated (`Some(false)`) tags
+/// * `limit` - Maximum number of tags
+///
+/// # Returns
+/// * `Ok(Vec<Tag>)` - Tags on the most anime first, then the most applied
+pub async fn get_tags(
+    pool: &PgPool,
+    curated: Option<bool>,
+    limit: i64,
+) -> RepositoryResult<Vec<Tag>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        {}
+        WHERE $1::BOOLEAN IS NULL OR t.curated = $1
+        GROUP BY t.id
+        ORDER BY anime_count DESC, usage_count DESC, t.name ASC
+        LIMIT $2
+        "#,
+        TAG_SELECT
+    ))
+    .bind(curated)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(tag_from_row).collect())
+}
+
+/// Get a tag by ID
+pub async fn get_tag(pool: &PgPool, tag_id: i32) -> RepositoryResult<Option<Tag>> {
+    let row = sqlx::query(&format!("{} WHERE t.id = $1 GROUP BY t.id", TAG_SELECT))
+        .bind(tag_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.as_ref().map(tag_from_row))
+}
+
+/// Create a curated tag
+///
+/// # Arguments
+/// * `slug` - Unique slug derived from the name
+/// * `created_by` - ID of the admin creating the tag
+///
+/// # Returns
+/// * `Ok(Tag)` - The created tag
+/// * `Err(RepositoryError::Conflict)` - A tag with this slug exists
+pub async fn create_tag(
+    pool: &PgPool,
+    slug: &str,
+    name: &str,
+    description: &str,
+    created_by: i32,
+) -> RepositoryResult<Tag> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO tags (slug, name, description, curated, created_by)
+        VALUES ($1, $2, $3, TRUE, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(slug)
+    .bind(name)
+    .bind(description)
+    .bind(created_by)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| tag_conflict(e, slug))?;
+
+    let tag = get_tag(pool, row.get("id")).await?;
+    tag.ok_or_else(|| RepositoryError::NotFound(format!("Tag {}", slug)))
+}
+
+/// Rename, describe, or curate a tag
+///
+/// # Arguments
+/// * `name` - New display name and the slug derived from it
+///
+/// # Returns
+/// * `Ok(Some(Tag))` - The updated tag
+/// * `Ok(None)` - Tag not found
+/// * `Err(RepositoryError::Conflict)` - Another tag has the new slug
+pub async fn update_tag(
+    pool: &PgPool,
+    tag_id: i32,
+    name: Option<(&str, &str)>,
+    description: Option<&str>,
+    curated: Option<bool>,
+) -> RepositoryResult<Option<Tag>> {
+    let (slug, name) = name.unzip();
+    let updated = sqlx::query(
+        r#"
+        UPDATE tags SET
+            slug = COALESCE($2, slug),
+            name = COALESCE($3, name),
+            description = COALESCE($4, description),
+            curated = COALESCE($5, curated),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(tag_id)
+    .bind(slug)
+    .bind(name)
+    .bind(description)
+    .bind(curated)
+    .execute(pool)
+    .await
+    .map_err(|e| tag_conflict(e, slug.unwrap_or_default()))?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+    get_tag(pool, tag_id).await
+}
+
+/// Delete a tag, untagging every anime
+///
+/// # Returns
+/// * `Ok(true)` - Tag was deleted
+/// * `Ok(false)` - Tag not found
+pub async fn delete_tag(pool: &PgPool, tag_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM tags WHERE id = $1")
+        .bind(tag_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Outcome of applying a tag to an anime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagOutcome {
+    /// The anime was tagged
+    Tagged,
+    /// The user had already applied the tag
+    AlreadyTagged,
+    /// The tag doesn't exist, and the user already created the most new tags
+    /// allowed for the day
+    NewTagLimitReached,
+}
+
+/// Apply a tag to an anime on behalf of a user
+///
+/// A tag slug matching no tag creates an uncurated tag named `name`, unless
+/// the user created `max_new_tags` uncurated tags in the last day.
+///
+/// # Returns
+/// * `Ok(TagOutcome)` - Whether the anime was tagged
+/// * `Err(RepositoryError::NotFound)` - No anime with this slug was scraped
+pub async fn tag_anime(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+    tag_slug: &str,
+    name: &str,
+    max_new_tags: i64,
+) -> RepositoryResult<TagOutcome> {
+    let known: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM anime_details WHERE slug = $1)
+            OR EXISTS (SELECT 1 FROM crawled_anime WHERE slug = $1)
+        "#,
+    )
+    .bind(anime_slug)
+    .fetch_one(pool)
+    .await?;
+    if !known {
+        return Err(RepositoryError::NotFound(format!("Anime {}", anime_slug)));
+    }
+
+    let existing: Option<i32> = sqlx::query_scalar("SELECT id FROM tags WHERE slug = $1")
+        .bind(tag_slug)
+        .fetch_optional(pool)
+        .await?;
+    let tag_id = match existing {
+        Some(id) => id,
+        None => {
+            let created: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(*) FROM tags
+                WHERE created_by = $1 AND NOT curated
+                  AND created_at > CURRENT_TIMESTAMP - INTERVAL '1 day'
+                "#,
+            )
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+            if created >= max_new_tags {
+                return Ok(TagOutcome::NewTagLimitReached);
+            }
+            // Another request may create the same tag meanwhile; the no-op
+            // update makes the insert return its ID either way
+            sqlx::query_scalar(
+                r#"
+                INSERT INTO tags (slug, name, created_by)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (slug) DO UPDATE SET slug = EXCLUDED.slug
+                RETURNING id
+                "#,
+            )
+            .bind(tag_slug)
+            .bind(name)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?
+        }
+    };
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO anime_tags (anime_slug, tag_id, user_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (anime_slug, tag_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(anime_slug)
+    .bind(tag_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(if result.rows_affected() > 0 {
+        TagOutcome::Tagged
+    } else {
+        TagOutcome::AlreadyTagged
+    })
+}
+
+/// Take back a user's tag on an anime
+///
+/// # Returns
+/// * `Ok(true)` - Tag was removed
+/// * `Ok(false)` - User hadn't applied the tag
+pub async fn untag_anime(
+    pool: &PgPool,
+    user_id: i32,
+    anime_slug: &str,
+    tag_slug: &str,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM anime_tags at
+        USING tags t
+        WHERE at.tag_id = t.id AND t.slug = $3
+          AND at.user_id = $1 AND at.anime_slug = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(anime_slug)
+    .bind(tag_slug)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the tags on an anime
+///
+/// # Arguments
+/// * `user_id` - Requesting user, for `taggedByMe`
+///
+/// # Returns
+/// * `Ok(Vec<AnimeTag>)` - Tags applied by the most users first
+pub async fn get_anime_tags(
+    pool: &PgPool,
+    anime_slug: &str,
+    user_id: Option<i32>,
+) -> RepositoryResult<Vec<AnimeTag>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT t.slug, t.name, t.curated,
+               COUNT(*) AS votes,
+               COALESCE(BOOL_OR(at.user_id = $2), FALSE) AS tagged_by_me
+        FROM anime_tags at
+        JOIN tags t ON t.id = at.tag_id
+        WHERE at.anime_slug = $1
+        GROUP BY t.id
+        ORDER BY votes DESC, t.name ASC
+        "#,
+    )
+    .bind(anime_slug)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| AnimeTag {
+            slug: row.get("slug"),
+            name: row.get("name"),
+            curated: row.get("curated"),
+            votes: row.get("votes"),
+            tagged_by_me: row.get("tagged_by_me"),
+        })
+        .collect())
+}
+
+/// Pick the anime carrying a tag out of a list of slugs
+///
+/// Used to filter results scraped from the source site by tag.
+pub async fn get_tagged_slugs(
+    pool: &PgPool,
+    tag_slug: &str,
+    anime_slugs: &[String],
+) -> RepositoryResult<HashSet<String>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT DISTINCT at.anime_slug
+        FROM anime_tags at
+        JOIN tags t ON t.id = at.tag_id
+        WHERE t.slug = $1 AND at.anime_slug = ANY($2)
+        "#,
+    )
+    .bind(tag_slug)
+    .bind(anime_slugs)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|row| row.get("anime_slug")).collect())
+}
+
+// ============================================================================
+// Age Ratings Repository
+// ============================================================================
+
+/// Slugs of anime rated mature or adult, by admin override or else by their
+/// genres
+const RESTRICTED_ANIME_SLUGS: &str = r#"
+    SELECT anime_slug FROM age_rating_overrides WHERE age_rating <> 'general'
+    UNION ALL
+    SELECT d.slug FROM anime_details d
+    WHERE d.age_rating <> 'general'
+      AND NOT EXISTS (SELECT 1 FROM age_rating_overrides o WHERE o.anime_slug = d.slug)
+"#;
+
+/// Get an anime's age rating: derived from its genres and overridden by admins
+///
+/// # Returns
+/// * `Ok(AnimeAgeRating)` - Ratings; all unset if the anime isn't stored
+///   and has no override
+pub async fn get_anime_age_rating(
+    pool: &PgPool,
+    anime_slug: &str,
+) -> RepositoryResult<AnimeAgeRating> {
+    let row = sqlx::query(
+        r#"
+        SELECT (SELECT age_rating FROM anime_details WHERE slug = $1) AS derived,
+               (SELECT age_rating FROM age_rating_overrides WHERE anime_slug = $1) AS overridden
+        "#,
+    )
+    .bind(anime_slug)
+    .fetch_one(pool)
+    .await?;
+
+    let rating = |column: &str| {
+        row.get::<Option<String>, _>(column)
+            .as_deref()
+            .and_then(AgeRating::parse)
+    };
+    let derived_rating = rating("derived");
+    let override_rating = rating("overridden");
+    Ok(AnimeAgeRating {
+        anime_slug: anime_slug.to_string(),
+        age_rating: override_rating.or(derived_rating),
+        derived_rating,
+        override_rating,
+    })
+}
+
+/// Pin an anime's age rating, whatever its genres say
+///
+/// # Arguments
+/// * `updated_by` - ID of the admin setting the override
+pub async fn set_age_rating_override(
+    pool: &PgPool,
+    anime_slug: &str,
+    rating: AgeRating,
+    updated_by: i32,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO age_rating_overrides (anime_slug, age_rating, updated_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (anime_slug) DO UPDATE SET
+            age_rating = EXCLUDED.age_rating,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(anime_slug)
+    .bind(rating.as_str())
+    .bind(updated_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remove an anime's age rating override, going back to the derived rating
+///
+/// # Returns
+/// * `Ok(true)` - Override was removed
+/// * `Ok(false)` - The anime had no override
+pub async fn delete_age_rating_override(pool: &PgPool, anime_slug: &str) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM age_rating_overrides WHERE anime_slug = $1")
+        .bind(anime_slug)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the age ratings in effect for a list of slugs
+///
+/// Used to filter results scraped from the source site. Admin overrides win
+/// over the ratings derived from stored genres; anime that were never
+/// stored and have no override are missing from the map.
+pub async fn get_age_ratings(
+    pool: &PgPool,
+    anime_slugs: &[String],
+) -> RepositoryResult<HashMap<String, AgeRating>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.slug, COALESCE(o.age_rating, d.age_rating) AS age_rating
+        FROM (SELECT DISTINCT slug FROM UNNEST($1::TEXT[]) AS u(slug)) s
+        LEFT JOIN age_rating_overrides o ON o.anime_slug = s.slug
+        LEFT JOIN anime_details d ON d.slug = s.slug
+        WHERE COALESCE(o.age_rating, d.age_rating) IS NOT NULL
+        "#,
+    )
+    .bind(anime_slugs)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let rating = AgeRating::parse(row.get::<&str, _>("age_rating"))?;
+            Some((row.get("slug"), rating))
+        })
+        .collect())
+}
+
+// ============================================================================
+// Community Repository
+// ============================================================================
+
+/// Rank anime by how many registered users watched them
+///
+/// Watchers are users with an episode in their history watched within the
+/// window; favorites are users who favorited the anime within it. Only
+/// active users of the tenant count, and users who opted out through
+/// preferences are left out.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `tenant_id` - Tenant whose users count
+/// * `since` - Start of the window; `None` for all time
+/// * `limit` - Maximum number of anime
+///
+/// # Returns
+/// * `Ok(Vec<CommunityTopEntry>)` - Most watched first, ties broken by favorites
+pub async fn compute_community_top(
+    pool: &PgPool,
+    tenant_id: i32,
+    since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> RepositoryResult<Vec<CommunityTopEntry>> {
+    let rows = sqlx::query(
+        r#"
+        WITH members AS (
+            SELECT u.id
+            FROM users u
+            LEFT JOIN user_preferences p ON p.user_id = u.id
+            WHERE u.tenant_id = $1 AND u.is_active AND NOT COALESCE(p.community_opt_out, FALSE)
+        ),
+        watches AS (
+            SELECT h.anime_slug, COUNT(DISTINCT h.user_id) AS watchers,
+                   MAX(h.anime_title) AS anime_title, MAX(h.thumbnail) AS thumbnail
+            FROM user_history h
+            JOIN members m ON m.id = h.user_id
+            WHERE $2::TIMESTAMPTZ IS NULL OR h.watched_at >= $2
+            GROUP BY h.anime_slug
+        ),
+        favorites AS (
+            SELECT f.anime_slug, COUNT(*) AS favorites,
+                   MAX(f.anime_title) AS anime_title, MAX(f.thumbnail) AS thumbnail
+            FROM user_favorites f
+            JOIN members m ON m.id = f.user_id
+            WHERE $2::TIMESTAMPTZ IS NULL OR f.created_at >= $2
+            GROUP BY f.anime_slug
+        )
+        SELECT COALESCE(w.anime_slug, f.anime_slug) AS anime_slug,
+               COALESCE(f.anime_title, w.anime_title, '') AS anime_title,
+               COALESCE(w.thumbnail, f.thumbnail) AS thumbnail,
+               COALESCE(w.watchers, 0) AS watchers,
+               COALESCE(f.favorites, 0) AS favorites
+        FROM watches w
+        FULL OUTER JOIN favorites f ON f.anime_slug = w.anime_slug
+        ORDER BY watchers DESC, favorites DESC, anime_slug
+        LIMIT $3
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .zip(1..)
+        .map(|(row, rank)| CommunityTopEntry {
+            rank,
+            anime_slug: row.get("anime_slug"),
+            anime_title: row.get("anime_title"),
+            thumbnail: row.get("thumbnail"),
+            watchers: row.get("watchers"),
+            favorites: row.get("favorites"),
+        })
+        .collect())
+}
+
+/// Get a computed leaderboard if it's fresh
+///
+/// # Returns
+/// * `Ok(Some(top))` - Leaderboard computed within `max_age_ms`
+/// * `Ok(None)` - Not computed, expired, or unreadable
+pub async fn get_cached_community_top(
+    pool: &PgPool,
+    tenant_id: i32,
+    window: LeaderboardWindow,
+    max_age_ms: i64,
+) -> RepositoryResult<Option<CommunityTop>> {
+    let row = sqlx::query(
+        r#"
+        SELECT items, computed_at
+        FROM community_top_cache
+        WHERE tenant_id = $1 AND time_window = $2
+          AND computed_at > CURRENT_TIMESTAMP - INTERVAL '1 millisecond' * $3
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(window.as_str())
+    .bind(max_age_ms)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.and_then(|row| {
+        let items = serde_json::from_str(&row.get::<String, _>("items")).ok()?;
+        let computed_at: DateTime<Utc> = row.get("computed_at");
+        Some(CommunityTop {
+            window,
+            items,
+            computed_at: computed_at.to_rfc3339(),
+        })
+    }))
+}
+
+/// Save a computed leaderboard, replacing the previous one
+pub async fn save_community_top(
+    pool: &PgPool,
+    tenant_id: i32,
+    top: &CommunityTop,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO community_top_cache (tenant_id, time_window, items, computed_at)
+        VALUES ($1, $2, $3, $4::TIMESTAMPTZ)
+        ON CONFLICT (tenant_id, time_window) DO UPDATE SET
+            items = EXCLUDED.items,
+            computed_at = EXCLUDED.computed_at
+        "#,
+    )
+    .bind(tenant_id)
+    .bind(top.window.as_str())
+    .bind(serde_json::to_string(&top.items).unwrap_or_default())
+    .bind(&top.computed_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Synopsis Translations Repository
+// ============================================================================
+
+/// Get a stored translation of a synopsis
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `synopsis` - Source text, as scraped
+/// * `language` - Target language code
+///
+/// # Returns
+/// * `Ok(Some(text))` - The translation
+/// * `Ok(None)` - This text hasn't been translated into the language
+pub async fn get_synopsis_translation(
+    pool: &PgPool,
+    synopsis: &str,
+    language: &str,
+) -> RepositoryResult<Option<String>> {
+    let row = sqlx::query(
+        "SELECT translated_text FROM synopsis_translations WHERE source_hash = $1 AND language = $2",
+    )
+    .bind(content_hash(synopsis))
+    .bind(language)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.get("translated_text")))
+}
+
+/// Store a translation of a synopsis, replacing any earlier one
+pub async fn save_synopsis_translation(
+    pool: &PgPool,
+    synopsis: &str,
+    language: &str,
+    translated: &str,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO synopsis_translations (source_hash, language, translated_text)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (source_hash, language) DO UPDATE SET
+            translated_text = EXCLUDED.translated_text,
+            created_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(content_hash(synopsis))
+    .bind(language)
+    .bind(translated)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// ============================================================================
+// Personal Data Repository
+// ============================================================================
+
+/// Tables holding a user's data, with the column naming the user
+///
+/// Exported by [`collect_user_data`] and emptied by [`erase_user_data`],
+/// along with the collection_items of the user's collections.
+/// Verification tokens are deleted on erasure but never exported.
+pub const USER_DATA_TABLES: [(&str, &str); 16] = [
+    ("user_preferences", "user_id"),
+    ("user_favorites", "user_id"),
+    ("user_subscriptions", "user_id"),
+    ("user_history", "user_id"),
+    ("user_watched_episodes", "user_id"),
+    ("saved_searches", "user_id"),
+    ("collections", "user_id"),
+    ("episode_comments", "user_id"),
+    ("episode_reactions", "user_id"),
+    ("anime_tags", "user_id"),
+    ("sessions", "user_id"),
+    ("devices", "user_id"),
+    ("user_roles", "user_id"),
+    ("user_strikes", "user_id"),
+    ("moderation_items", "author_id"),
+    ("content_reports", "reporter_id"),
+];
+
+/// Columns selected for every DataExport query
+const DATA_EXPORT_COLUMNS: &str = "id, user_id, status, object_key, created_at, completed_at";
+
+/// Map a data_exports row into a DataExport
+fn data_export_from_row(row: &sqlx::postgres::PgRow) -> DataExport {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let completed_at: Option<DateTime<Utc>> = row.get("completed_at");
+
+    DataExport {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        status: row.get("status"),
+        object_key: row.get("object_key"),
+        created_at: created_at.to_rfc3339(),
+        completed_at: completed_at.map(|dt| dt.to_rfc3339()),
+        download_url: None,
+        expires_at: None,
+    }
+}
+
+/// Collect everything stored about a user
+///
+/// Returns the profile (with the email and Google ID decrypted, without the
+/// password hash or blind indexes), the rows of every [`USER_DATA_TABLES`]
+/// table, and the emails sent to the user without their rendered content.
+///
+/// # Returns
+/// * `Ok(Some(data))` - A JSON object keyed by table
+/// * `Ok(None)` - User not found
+pub async fn collect_user_data(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<Option<serde_json::Value>> {
+    let Some(row) = sqlx::query(
+        r#"
+        SELECT id, tenant_id, email, google_id, name, avatar, email_verified,
+               is_active, deactivated_at, muted_until, created_at, updated_at
+        FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let email = encryption::open(FIELD_EMAIL, row.get("email"))?;
+    let google_id = row
+        .get::<Option<String>, _>("google_id")
+        .map(|id| encryption::open(FIELD_GOOGLE_ID, id))
+        .transpose()?;
+    let deactivated_at: Option<DateTime<Utc>> = row.get("deactivated_at");
+    let muted_until: Option<DateTime<Utc>> = row.get("muted_until");
+    let created_at: Option<DateTime<Utc>> = row.get("created_at");
+    let updated_at: Option<DateTime<Utc>> = row.get("updated_at");
+
+    let mut data = serde_json::Map::new();
+    data.insert(
+        "profile".to_string(),
+        serde_json::json!({
+            "id": user_id,
+            "tenantId": row.get::<i32, _>("tenant_id"),
+            "email": email,
+            "googleId": google_id,
+            "name": row.get::<Option<String>, _>("name"),
+            "avatar": row.get::<Option<String>, _>("avatar"),
+            "emailVerified": row.get::<Option<bool>, _>("email_verified"),
+            "isActive": row.get::<bool, _>("is_active"),
+            "deactivatedAt": deactivated_at.map(|dt| dt.to_rfc3339()),
+            "mutedUntil": muted_until.map(|dt| dt.to_rfc3339()),
+            "createdAt": created_at.map(|dt| dt.to_rfc3339()),
+            "updatedAt": updated_at.map(|dt| dt.to_rfc3339()),
+        }),
+    );
+
+    for (table, column) in USER_DATA_TABLES {
+        let rows: String = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(json_agg(t), '[]')::text FROM {} t WHERE {} = $1",
+            table, column
+        ))
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+        data.insert(
+            table.to_string(),
+            serde_json::from_str(&rows).unwrap_or_default(),
+        );
+    }
+
+    let items: String = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(json_agg(t), '[]')::text
+        FROM collection_items t
+        JOIN collections c ON c.id = t.collection_id
+        WHERE c.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    data.insert(
+        "collection_items".to_string(),
+        serde_json::from_str(&items).unwrap_or_default(),
+    );
+
+    let emails: String = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(json_agg(
+            jsonb_set(to_jsonb(t) - 'payload' - 'recipient_hash', '{recipient}', to_jsonb($1::text))
+        ), '[]')::text
+        FROM email_deliveries t
+        WHERE recipient = $1 OR recipient_hash = $2
+        "#,
+    )
+    .bind(&email)
+    .bind(encryption::blind_index(FIELD_RECIPIENT, &email))
+    .fetch_one(pool)
+    .await?;
+    data.insert(
+        "email_deliveries".to_string(),
+        serde_json::from_str(&emails).unwrap_or_default(),
+    );
+
+    Ok(Some(serde_json::Value::Object(data)))
+}
+
+/// Record a pending personal data export
+pub async fn create_data_export(pool: &PgPool, user_id: i32) -> RepositoryResult<DataExport> {
+    let row = sqlx::query(&format!(
+        "INSERT INTO data_exports (user_id) VALUES ($1) RETURNING {}",
+        DATA_EXPORT_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(data_export_from_row(&row))
+}
+
+/// Get a user's most recently requested data export
+pub async fn get_latest_data_export(
+    pool: &PgPool,
+    user_id: i32,
+) -> RepositoryResult<Option<DataExport>> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM data_exports WHERE user_id = $1 ORDER BY created_at DESC, id DESC LIMIT 1",
+        DATA_EXPORT_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(data_export_from_row))
+}
+
+/// Get a data export by ID
+pub async fn get_data_export(
+    pool: &PgPool,
+    export_id: i32,
+) -> RepositoryResult<Option<DataExport>> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM data_exports WHERE id = $1",
+        DATA_EXPORT_COLUMNS
+    ))
+    .bind(export_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(data_export_from_row))
+}
+
+/// Mark a data export ready, its archive stored under `object_key`
+pub async fn complete_data_export(
+    pool: &PgPool,
+    export_id: i32,
+    object_key: &str,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE data_exports
+        SET status = $2, object_key = $3, last_error = NULL, completed_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(export_id)
+    .bind(DATA_EXPORT_READY)
+    .bind(object_key)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Mark a data export failed
+pub async fn fail_data_export(pool: &PgPool, export_id: i32, error: &str) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE data_exports
+        SET status = $2, last_error = $3, completed_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(export_id)
+    .bind(DATA_EXPORT_FAILED)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Erase a user and everything stored about them
+///
+/// In one transaction: emails sent to the user are kept for delivery
+/// statistics but anonymized (recipient and content removed, queued ones
+/// cancelled), the user is unlinked from moderation decisions and strikes
+/// they made, their rows in [`USER_DATA_TABLES`], verification tokens, and
+/// data exports are deleted, then the user itself. The erasure is logged
+/// without personal data.
+///
+/// Stored export archives are not touched; delete them from object storage.
+///
+/// # Returns
+/// * `Ok(Some(erasure))` - The logged erasure with the rows affected per table
+/// * `Ok(None)` - User not found
+pub async fn erase_user_data(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<DataErasure>> {
+    let mut tx = pool.begin().await?;
+
+    let Some(row) = sqlx::query("SELECT email, tenant_id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let email = encryption::open(FIELD_EMAIL, row.get("email"))?;
+    let tenant_id: i32 = row.get("tenant_id");
+
+    let mut tables = Vec::new();
+    let mut record = |table: &str, rows: u64| {
+        if rows > 0 {
+            tables.push(TableRowCount {
+                table: table.to_string(),
+                rows,
+            });
+        }
+    };
+
+    let anonymized = sqlx::query(
+        r#"
+        UPDATE email_deliveries
+        SET recipient = 'erased',
+            recipient_hash = NULL,
+            payload = '{}',
+            job_id = NULL,
+            status = CASE WHEN status = $2 THEN $3 ELSE status END,
+            last_error = CASE WHEN status = $2 THEN 'Recipient erased' ELSE last_error END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE recipient = $1 OR recipient_hash = $4
+        "#,
+    )
+    .bind(&email)
+    .bind(EMAIL_STATUS_QUEUED)
+    .bind(EMAIL_STATUS_FAILED)
+    .bind(encryption::blind_index(FIELD_RECIPIENT, &email))
+    .execute(&mut *tx)
+    .await?;
+    record("email_deliveries", anonymized.rows_affected());
+
+    let unlinked =
+        sqlx::query("UPDATE moderation_items SET resolved_by = NULL WHERE resolved_by = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+            + sqlx::query("UPDATE user_strikes SET issued_by = NULL WHERE issued_by = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+    record("moderation_decisions", unlinked);
+
+    let items = sqlx::query(
+        r#"
+        DELETE FROM collection_items
+        WHERE collection_id IN (SELECT id FROM collections WHERE user_id = $1)
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    record("collection_items", items.rows_affected());
+
+    let owned = USER_DATA_TABLES.into_iter().chain([
+        ("verification_tokens", "user_id"),
+        ("data_exports", "user_id"),
+    ]);
+    for (table, column) in owned {
+        let deleted = sqlx::query(&format!("DELETE FROM {} WHERE {} = $1", table, column))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        record(table, deleted.rows_affected());
+    }
+
+    let deleted = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    record("users", deleted.rows_affected());
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO data_erasures (user_id, tenant_id, affected_tables)
+        VALUES ($1, $2, $3)
+        RETURNING id, erased_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(tenant_id)
+    .bind(serde_json::to_string(&tables).unwrap_or_else(|_| "[]".to_string()))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let erased_at: DateTime<Utc> = row.get("erased_at");
+    Ok(Some(DataErasure {
+        id: row.get("id"),
+        user_id,
+        tenant_id,
+        tables,
+        erased_at: erased_at.to_rfc3339(),
+    }))
+}
+
+/// List logged erasures, newest first
+pub async fn list_data_erasures(pool: &PgPool, limit: i64) -> RepositoryResult<Vec<DataErasure>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, user_id, tenant_id, affected_tables, erased_at
+        FROM data_erasures
+        ORDER BY erased_at DESC, id DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let erased_at: DateTime<Utc> = row.get("erased_at");
+            DataErasure {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                tenant_id: row.get("tenant_id"),
+                tables: serde_json::from_str(&row.get::<String, _>("affected_tables"))
+                    .unwrap_or_default(),
+                erased_at: erased_at.to_rfc3339(),
+            }
+        })
+        .collect())
+}
+
+// ============================================================================
+// Verification Tokens Repository
+// ============================================================================
+
+/// Token types for verification
+pub const TOKEN_TYPE_EMAIL_VERIFICATION: &str = "email_verification";
+pub const TOKEN_TYPE_PASSWORD_RESET: &str = "password_reset";
+pub const TOKEN_TYPE_REACTIVATION: &str = "reactivation";
+
+/// Verification token data
+#[derive(Debug, Clone)]
+pub struct VerificationToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token: String,
+    pub token_type: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Create a verification token for a user
+///
+/// # Arguments
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `user_id` - User ID
+/// * `token` - Unique token string
+/// * `token_type` - Type of token (email_verification or password_reset)
+/// * `expires_in_hours` - Hours until token expires
+///
+/// # Returns
+/// * `Ok(VerificationToken)` - The created token
+pub async fn create_verification_token(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    token: &str,
+    token_type: &str,
+    expires_in_hours: i64,
+) -> RepositoryResult<VerificationToken> {
+    let expires_at = Utc::now() + chrono::Duration::hours(expires_in_hours);
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO verification_tokens (user_id, token, token_type, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+        RETURNING id, user_id, token, token_type, expires_at, used_at, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(token)
+    .bind(token_type)
+    .bind(expires_at)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(VerificationToken {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        token: row.get("token"),
+        token_type: row.get("token_type"),
+        expires_at: row.get("expires_at"),
+        used_at: row.get("used_at"),
+        created_at: row.get("created_at"),
+    })
+}
+
+/// Find a verification token by token string
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `token` - Token string to search for
+///
+/// # Returns
+/// * `Ok(Some(VerificationToken))` - Token found
+/// * `Ok(None)` - Token not found
+pub async fn find_verification_token(
+    pool: &PgPool,
+    token: &str,
+) -> RepositoryResult<Option<VerificationToken>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, user_id, token, token_type, expires_at, used_at, created_at
+        FROM verification_tokens
+        WHERE token = $1
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => Ok(Some(VerificationToken {
+            id: row.get("id"),
+            user_id: row.get("user_id"),
+            token: row.get("token"),
+            token_type: row.get("token_type"),
+            expires_at: row.get("expires_at"),
+            used_at: row.get("used_at"),
+            created_at: row.get("created_at"),
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Mark a verification token as used
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `token` - Token string to mark as used
+///
+/// # Returns
+/// * `Ok(true)` - Token was marked as used
+/// * `Ok(false)` - Token not found
+pub async fn mark_token_as_used(pool: &PgPool, token: &str) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE verification_tokens
+        SET used_at = CURRENT_TIMESTAMP
+        WHERE token = $1 AND used_at IS NULL
+        "#,
+    )
+    .bind(token)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete expired verification tokens
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+///
+/// # Returns
+/// * `Ok(count)` - Number of tokens deleted
+pub async fn delete_expired_tokens(pool: &PgPool) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM verification_tokens
+        WHERE expires_at < CURRENT_TIMESTAMP
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete all verification tokens for a user of a specific type
+///
+/// # Arguments
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `user_id` - User ID
+/// * `token_type` - Type of tokens to delete
+///
+/// # Returns
+/// * `Ok(count)` - Number of tokens deleted
+pub async fn delete_user_tokens(
+    executor: impl PgExecutor<'_>,
+    user_id: i32,
+    token_type: &str,
+) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM verification_tokens
+        WHERE user_id = $1 AND token_type = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(token_type)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Update user's email_verified status
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `verified` - Whether email is verified
+///
+/// # Returns
+/// * `Ok(true)` - Status was updated
+/// * `Ok(false)` - User not found
+pub async fn set_email_verified(
+    pool: &PgPool,
+    user_id: i32,
+    verified: bool,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET email_verified = $1, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $2
+        "#,
+    )
+    .bind(verified)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Check if user's email is verified
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+///
+/// # Returns
+/// * `Ok(Some(bool))` - Email verification status
+/// * `Ok(None)` - User not found
+pub async fn is_email_verified(pool: &PgPool, user_id: i32) -> RepositoryResult<Option<bool>> {
+    let row = sqlx::query(
+        r#"
+        SELECT email_verified
+        FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => Ok(Some(
+            row.get::<Option<bool>, _>("email_verified")
+                .unwrap_or(false),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Update user's password
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+/// * `password_hash` - New bcrypt hashed password
+///
+/// # Returns
+/// * `Ok(true)` - Password was updated
+/// * `Ok(false)` - User not found
+pub async fn update_user_password(
+    pool: &PgPool,
+    user_id: i32,
+    password_hash: &str,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET password_hash = $1, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $2
+        "#,
+    )
+    .bind(password_hash)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// ============================================================================
+// Background Jobs Repository
+// ============================================================================
+
+/// Job status for jobs waiting to run (including scheduled retries)
+pub const JOB_STATUS_PENDING: &str = "pending";
+/// Job status for jobs claimed by a worker
+pub const JOB_STATUS_RUNNING: &str = "running";
+/// Job status for jobs that finished successfully
+pub const JOB_STATUS_COMPLETED: &str = "completed";
+/// Job status for jobs that exhausted their retries (dead-letter)
+pub const JOB_STATUS_DEAD: &str = "dead";
+
+/// Columns selected for every JobRecord query
+const JOB_COLUMNS: &str = "id, queue, job_type, payload, status, priority, attempts, \
+    max_attempts, last_error, result, run_at, created_at, updated_at";
+
+/// Map a jobs row into a JobRecord
+fn job_from_row(row: &sqlx::postgres::PgRow) -> JobRecord {
+    let run_at: DateTime<Utc> = row.get("run_at");
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let updated_at: DateTime<Utc> = row.get("updated_at");
+
+    JobRecord {
+        id: row.get("id"),
+        queue: row.get("queue"),
+        job_type: row.get("job_type"),
+        payload: serde_json::from_str(&row.get::<String, _>("payload"))
+            .unwrap_or(serde_json::Value::Null),
+        status: row.get("status"),
+        priority: row.get("priority"),
+        attempts: row.get("attempts"),
+        max_attempts: row.get("max_attempts"),
+        last_error: row.get("last_error"),
+        result: row
+            .get::<Option<String>, _>("result")
+            .and_then(|r| serde_json::from_str(&r).ok()),
+        run_at: run_at.to_rfc3339(),
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+    }
+}
+
+/// Insert a new pending job into the queue
+///
+/// # Arguments
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `queue` - Queue name (e.g., "crawler", "email")
+/// * `job_type` - Job type used to dispatch to a handler
+/// * `payload` - Serialized JSON payload for the handler
+/// * `max_attempts` - Attempts allowed before the job is dead-lettered
+///
+/// # Returns
+/// * `Ok(JobRecord)` - The queued job
+pub async fn enqueue_job(
+    executor: impl PgExecutor<'_>,
+    queue: &str,
+    job_type: &str,
+    payload: &str,
+    max_attempts: i32,
+) -> RepositoryResult<JobRecord> {
+    enqueue_job_with_priority(executor, queue, job_type, payload, max_attempts, 0).await
+}
+
+/// Insert a new pending job that is claimed ahead of lower-priority ones
+///
+/// # Arguments
+/// * `priority` - Runnable jobs are claimed highest priority first; the
+///   default for [`enqueue_job`] is 0
+pub async fn enqueue_job_with_priority(
+    executor: impl PgExecutor<'_>,
+    queue: &str,
+    job_type: &str,
+    payload: &str,
+    max_attempts: i32,
+    priority: i32,
+) -> RepositoryResult<JobRecord> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO jobs (queue, job_type, payload, max_attempts, priority)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {}
+        "#,
+        JOB_COLUMNS
+    ))
+    .bind(queue)
+    .bind(job_type)
+    .bind(payload)
+    .bind(max_attempts)
+    .bind(priority)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(job_from_row(&row))
+}
+
+/// Claim the next runnable job for a worker
+///
+/// Picks the highest-priority, then oldest, pending job whose run_at has
+/// passed, or a running job whose lock has gone stale (its worker crashed). Uses `FOR UPDATE SKIP LOCKED` so
+/// concurrent workers never claim the same job. The attempt counter is
+/// incremented as part of the claim.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `stale_after_secs` - Seconds after which a running job's lock is considered abandoned
+///
+/// # Returns
+/// * `Ok(Some(JobRecord))` - The claimed job, now in the running state
+/// * `Ok(None)` - No job is ready to run
+pub async fn claim_next_job(
+    pool: &PgPool,
+    stale_after_secs: i64,
+) -> RepositoryResult<Option<JobRecord>> {
+    let row = sqlx::query(&format!(
+        r#"
+        UPDATE jobs SET
+            status = $1,
+            attempts = attempts + 1,
+            locked_at = CURRENT_TIMESTAMP,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE (status = $2 AND run_at <= CURRENT_TIMESTAMP)
+               OR (status = $1 AND locked_at < CURRENT_TIMESTAMP - make_interval(secs => $3))
+            ORDER BY priority DESC, run_at ASC
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING {}
+        "#,
+        JOB_COLUMNS
+    ))
+    .bind(JOB_STATUS_RUNNING)
+    .bind(JOB_STATUS_PENDING)
+    .bind(stale_after_secs as f64)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(job_from_row))
+}
+
+/// Refresh the lock on a running job so it isn't reclaimed as stale
+///
+/// Workers call this periodically while a long-running job (e.g. a crawl) is in progress.
+pub async fn touch_job(pool: &PgPool, job_id: i32) -> RepositoryResult<()> {
+    sqlx::query("UPDATE jobs SET locked_at = CURRENT_TIMESTAMP WHERE id = $1 AND status = $2")
+        .bind(job_id)
+        .bind(JOB_STATUS_RUNNING)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Mark a job as completed
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `job_id` - Job ID
+/// * `result` - Optional serialized JSON result to store with the job
+pub async fn complete_job(
+    pool: &PgPool,
+    job_id: i32,
+    result: Option<&str>,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = $2,
+            result = $3,
+            locked_at = NULL,
+            completed_at = CURRENT_TIMESTAMP,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(job_id)
+    .bind(JOB_STATUS_COMPLETED)
+    .bind(result)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed job attempt
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `job_id` - Job ID
+/// * `error` - Error message from the failed attempt
+/// * `retry_in_secs` - `Some(secs)` to reschedule the job, `None` to move it to the dead-letter state
+pub async fn fail_job(
+    pool: &PgPool,
+    job_id: i32,
+    error: &str,
+    retry_in_secs: Option<i64>,
+) -> RepositoryResult<()> {
+    let status = if retry_in_secs.is_some() {
+        JOB_STATUS_PENDING
+    } else {
+        JOB_STATUS_DEAD
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = $2,
+            last_error = $3,
+            run_at = CURRENT_TIMESTAMP + make_interval(secs => $4),
+            locked_at = NULL,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(job_id)
+    .bind(status)
+    .bind(error)
+    .bind(retry_in_secs.unwrap_or(0) as f64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Move a dead-lettered job back to pending with a fresh attempt budget
+///
+/// # Returns
+/// * `Ok(true)` - Job was requeued
+/// * `Ok(false)` - Job not found or not dead
+pub async fn retry_dead_job(pool: &PgPool, job_id: i32) -> RepositoryResult<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = $2,
+            attempts = 0,
+            run_at = CURRENT_TIMESTAMP,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = $3
+        "#,
+    )
+    .bind(job_id)
+    .bind(JOB_STATUS_PENDING)
+    .bind(JOB_STATUS_DEAD)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get a job by ID
+pub async fn get_job(pool: &PgPool, job_id: i32) -> RepositoryResult<Option<JobRecord>> {
+    let row = sqlx::query(&format!("SELECT {} FROM jobs WHERE id = $1", JOB_COLUMNS))
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.as_ref().map(job_from_row))
+}
+
+/// Get the most recently completed job of a type
+///
+/// # Returns
+/// * `Ok(Some(JobRecord))` - Newest completed job, with its result
+/// * `Ok(None)` - No job of this type has completed
+pub async fn get_latest_completed_job(
+    pool: &PgPool,
+    job_type: &str,
+) -> RepositoryResult<Option<JobRecord>> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {} FROM jobs
+        WHERE job_type = $1 AND status = $2
+        ORDER BY completed_at DESC, id DESC
+        LIMIT 1
+        "#,
+        JOB_COLUMNS
+    ))
+    .bind(job_type)
+    .bind(JOB_STATUS_COMPLETED)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(job_from_row))
+}
+
+/// Get job counts grouped by queue and status
+pub async fn get_job_queue_stats(pool: &PgPool) -> RepositoryResult<Vec<JobQueueStats>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT queue,
+               COUNT(*) FILTER (WHERE status = 'pending') AS pending,
+               COUNT(*) FILTER (WHERE status = 'pending' AND attempts > 0) AS retrying,
+               COUNT(*) FILTER (WHERE status = 'running') AS running,
+               COUNT(*) FILTER (WHERE status = 'completed') AS completed,
+               COUNT(*) FILTER (WHERE status = 'dead') AS dead
+        FROM jobs
+        GROUP BY queue
+        ORDER BY queue
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let stats = rows
+        .into_iter()
+        .map(|row| JobQueueStats {
+            queue: row.get("queue"),
+            pending: row.get("pending"),
+            retrying: row.get("retrying"),
+            running: row.get("running"),
+            completed: row.get("completed"),
+            dead: row.get("dead"),
+        })
+        .collect();
+
+    Ok(stats)
+}
+
+/// Get the most recently failed jobs (retrying or dead), newest first
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `limit` - Maximum number of jobs to return
+pub async fn get_failed_jobs(pool: &PgPool, limit: i64) -> RepositoryResult<Vec<JobRecord>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM jobs
+        WHERE last_error IS NOT NULL AND status IN ($1, $2)
+        ORDER BY updated_at DESC
+        LIMIT $3
+        "#,
+        JOB_COLUMNS
+    ))
+    .bind(JOB_STATUS_PENDING)
+    .bind(JOB_STATUS_DEAD)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(job_from_row).collect())
+}
+
+/// Delete completed jobs older than the given number of days
+///
+/// # Returns
+/// * `Ok(count)` - Number of jobs deleted
+pub async fn delete_completed_jobs(pool: &PgPool, older_than_days: i32) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM jobs
+        WHERE status = $1 AND completed_at < CURRENT_TIMESTAMP - make_interval(days => $2)
+        "#,
+    )
+    .bind(JOB_STATUS_COMPLETED)
+    .bind(older_than_days)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// Crawl Reports Repository
+// ============================================================================
+
+/// Save the report of a crawl job, replacing any earlier one
+///
+/// # Arguments
+/// * `job_id` - The crawl job
+/// * `report` - The report; its summary is also stored as plain text
+pub async fn save_crawl_report(
+    pool: &PgPool,
+    job_id: i32,
+    report: &CrawlReport,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO crawl_reports (job_id, report, summary)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (job_id) DO UPDATE SET
+            report = EXCLUDED.report,
+            summary = EXCLUDED.summary,
+            created_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(job_id)
+    .bind(serde_json::to_string(report).unwrap_or_default())
+    .bind(&report.summary)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Get the report of a crawl job
+///
+/// # Returns
+/// * `Ok(Some(CrawlReport))` - The report
+/// * `Ok(None)` - The job has no report (yet)
+pub async fn get_crawl_report(pool: &PgPool, job_id: i32) -> RepositoryResult<Option<CrawlReport>> {
+    let row = sqlx::query("SELECT report FROM crawl_reports WHERE job_id = $1")
+        .bind(job_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.and_then(|row| serde_json::from_str(&row.get::<String, _>("report")).ok()))
+}
+
+// ============================================================================
+// Crawler Bandwidth Repository
+// ============================================================================
+
+/// Add a page download to the crawler's bandwidth use on a day
+///
+/// # Arguments
+/// * `day` - Day (UTC) of the download
+/// * `bytes` - Bytes downloaded
+///
+/// # Returns
+/// * `Ok(u64)` - Bytes downloaded on the day so far
+pub async fn add_crawler_bandwidth(
+    pool: &PgPool,
+    day: NaiveDate,
+    bytes: u64,
+) -> RepositoryResult<u64> {
+    let row = sqlx::query(
+        r#"
+        INSERT INTO crawler_bandwidth (day, bytes, requests)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (day) DO UPDATE SET
+            bytes = crawler_bandwidth.bytes + EXCLUDED.bytes,
+            requests = crawler_bandwidth.requests + 1,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING bytes
+        "#,
+    )
+    .bind(day)
+    .bind(i64::try_from(bytes).unwrap_or(i64::MAX))
+    .fetch_one(pool)
+    .await?;
+    Ok(row.get::<i64, _>("bytes").max(0) as u64)
+}
+
+/// Get the bytes the crawler downloaded on a day
+pub async fn get_crawler_bandwidth(pool: &PgPool, day: NaiveDate) -> RepositoryResult<u64> {
+    let row = sqlx::query("SELECT bytes FROM crawler_bandwidth WHERE day = $1")
+        .bind(day)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map_or(0, |row| row.get::<i64, _>("bytes").max(0) as u64))
+}
+
+/// Get the crawler's bandwidth use per day, newest first
+///
+/// # Arguments
+/// * `days` - Days to list at most
+pub async fn list_crawler_bandwidth(
+    pool: &PgPool,
+    days: i64,
+) -> RepositoryResult<Vec<CrawlerBandwidthDay>> {
+    let rows = sqlx::query(
+        "SELECT day, bytes, requests FROM crawler_bandwidth ORDER BY day DESC LIMIT $1",
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| CrawlerBandwidthDay {
+            day: row.get::<NaiveDate, _>("day").to_string(),
+            bytes: row.get::<i64, _>("bytes").max(0) as u64,
+            requests: row.get("requests"),
+        })
+        .collect())
+}
+
+// ============================================================================
+// Crawl Failures Repository
+// ============================================================================
+
+/// Columns selected for every CrawlFailure query
+const CRAWL_FAILURE_COLUMNS: &str =
+    "id, kind, slug, url, last_error, attempts, created_at, updated_at";
+
+/// Map a crawl_failures row into a CrawlFailure
+fn crawl_failure_from_row(row: &sqlx::postgres::PgRow) -> CrawlFailure {
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let updated_at: DateTime<Utc> = row.get("updated_at");
+
+    CrawlFailure {
+        id: row.get("id"),
+        kind: CrawlFailureKind::parse(&row.get::<String, _>("kind"))
+            .unwrap_or(CrawlFailureKind::Anime),
+        slug: row.get("slug"),
+        url: row.get("url"),
+        last_error: row.get("last_error"),
+        attempts: row.get("attempts"),
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+    }
+}
+
+/// Queue a failed crawl target for retry
+///
+/// A target that is already queued gets its error replaced and its
+/// attempt count raised.
+///
+/// # Arguments
+/// * `kind` - Anime or episode
+/// * `slug` - Anime or episode slug
+/// * `url` - Page URL; for episodes, the URL their video sources are saved under
+/// * `error` - Why the crawl failed
+pub async fn record_crawl_failure(
+    pool: &PgPool,
+    kind: CrawlFailureKind,
+    slug: &str,
+    url: &str,
+    error: &str,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO crawl_failures (kind, slug, url, last_error)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (kind, slug) DO UPDATE SET
+            url = EXCLUDED.url,
+            last_error = EXCLUDED.last_error,
+            attempts = crawl_failures.attempts + 1,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(kind.as_str())
+    .bind(slug)
+    .bind(url)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// List queued crawl failures, least recently attempted first
+///
+/// # Arguments
+/// * `limit` - Maximum number of failures to return
+pub async fn list_crawl_failures(pool: &PgPool, limit: i64) -> RepositoryResult<Vec<CrawlFailure>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM crawl_failures ORDER BY updated_at ASC, id ASC LIMIT $1",
+        CRAWL_FAILURE_COLUMNS
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(crawl_failure_from_row).collect())
+}
+
+/// Remove a crawl target from the retry queue
+///
+/// # Returns
+/// * `Ok(true)` - The target was queued and has been removed
+/// * `Ok(false)` - The target wasn't queued
+pub async fn delete_crawl_failure(
+    pool: &PgPool,
+    kind: CrawlFailureKind,
+    slug: &str,
+) -> RepositoryResult<bool> {
+    let result = sqlx::query("DELETE FROM crawl_failures WHERE kind = $1 AND slug = $2")
+        .bind(kind.as_str())
+        .bind(slug)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Count queued crawl failures
+pub async fn count_crawl_failures(pool: &PgPool) -> RepositoryResult<i64> {
+    let row = sqlx::query("SELECT COUNT(*) AS count FROM crawl_failures")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("count"))
+}
+
+// ============================================================================
+// Crawl Priority Repository
+// ============================================================================
+
+/// An anime users follow, with how many of them do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PopularAnime {
+    pub slug: String,
+    /// Users subscribed to new episodes
+    pub subscribers: i64,
+    /// Users who favorited the anime
+    pub favorites: i64,
+}
+
+/// Find subscribed or favorited anime that are due for a re-scrape
+///
+/// Only anime already in anime_details are considered; missing ones are
+/// restored by the integrity check instead. Anime with a pending or running
+/// job of `job_type` are skipped, so a slow queue doesn't pile up duplicates.
+///
+/// # Arguments
+/// * `scraped_before` - Anime last scraped at or after this are fresh enough
+/// * `job_type` - Job type that re-scrapes an anime, with a `slug` payload
+/// * `subscriber_weight` - How many favorites a subscriber counts as
+/// * `limit` - Maximum number of anime to return
+///
+/// # Returns
+/// The most followed anime first (subscribers weighted), least recently
+/// scraped first among equals
+pub async fn find_stale_popular_anime(
+    pool: &PgPool,
+    scraped_before: DateTime<Utc>,
+    job_type: &str,
+    subscriber_weight: i64,
+    limit: i64,
+) -> RepositoryResult<Vec<PopularAnime>> {
+    let rows = sqlx::query(
+        r#"
+        WITH followers AS (
+            SELECT anime_slug, COUNT(*) AS subscribers, 0::BIGINT AS favorites
+            FROM user_subscriptions GROUP BY anime_slug
+            UNION ALL
+            SELECT anime_slug, 0::BIGINT, COUNT(*)
+            FROM user_favorites GROUP BY anime_slug
+        ),
+        popularity AS (
+            SELECT anime_slug, SUM(subscribers)::BIGINT AS subscribers,
+                SUM(favorites)::BIGINT AS favorites
+            FROM followers GROUP BY anime_slug
+        )
+        SELECT p.anime_slug AS slug, p.subscribers, p.favorites
+        FROM popularity p
+        JOIN anime_details d ON d.slug = p.anime_slug
+        WHERE (d.scraped_at IS NULL OR d.scraped_at < $1)
+          AND NOT EXISTS (
+            SELECT 1 FROM jobs j
+            WHERE j.job_type = $2
+              AND j.status IN ($3, $4)
+              AND j.payload::jsonb ->> 'slug' = p.anime_slug
+          )
+        ORDER BY p.subscribers * $5 + p.favorites DESC, d.scraped_at ASC NULLS FIRST
+        LIMIT $6
+        "#,
+    )
+    .bind(scraped_before)
+    .bind(job_type)
+    .bind(JOB_STATUS_PENDING)
+    .bind(JOB_STATUS_RUNNING)
+    .bind(subscriber_weight)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| PopularAnime {
+            slug: row.get("slug"),
+            subscribers: row.get("subscribers"),
+            favorites: row.get("favorites"),
+        })
+        .collect())
+}
+
+// ============================================================================
+// Popularity Repository
+// ============================================================================
+
+/// How popularity signals are weighted and decayed
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopularityWeights {
+    /// Score of one anime detail view
+    pub view: f64,
+    /// Score of one favorite
+    pub favorite: f64,
+    /// Score of one subscription
+    pub subscription: f64,
+    /// Days after which a signal counts half as much
+    pub half_life_days: f64,
+    /// Days of daily view counts kept; older ones are purged
+    pub view_window_days: i32,
+}
+
+/// Count a view of an anime detail
+pub async fn record_anime_view(pool: &PgPool, slug: &str) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO anime_views (anime_slug, day, views)
+        VALUES ($1, CURRENT_DATE, 1)
+        ON CONFLICT (anime_slug, day) DO UPDATE SET views = anime_views.views + 1
+        "#,
+    )
+    .bind(slug)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Recompute the popularity score of every crawled anime
+///
+/// Each view, favorite, and subscription adds its weight, halved for every
+/// `half_life_days` since it happened. View counts older than the window
+/// are purged first.
+///
+/// # Returns
+/// * `Ok(count)` - Number of anime whose score changed
+pub async fn refresh_popularity_scores(
+    pool: &PgPool,
+    weights: &PopularityWeights,
+) -> RepositoryResult<u64> {
+    sqlx::query("DELETE FROM anime_views WHERE day < CURRENT_DATE - $1")
+        .bind(weights.view_window_days)
+        .execute(pool)
+        .await?;
+
+    let result = sqlx::query(
+        r#"
+        WITH signals AS (
+            SELECT anime_slug, views * $1 AS weight, day::TIMESTAMPTZ AS at
+            FROM anime_views
+            UNION ALL
+            SELECT anime_slug, $2, COALESCE(created_at, CURRENT_TIMESTAMP)
+            FROM user_favorites
+            UNION ALL
+            SELECT anime_slug, $3, COALESCE(created_at, CURRENT_TIMESTAMP)
+            FROM user_subscriptions
+        ),
+        scores AS (
+            SELECT anime_slug, SUM(weight * POWER(
+                0.5::FLOAT8,
+                GREATEST(EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - at))::FLOAT8, 0) / 86400.0 / $4
+            )) AS score
+            FROM signals
+            GROUP BY anime_slug
+        )
+        UPDATE crawled_anime c
+        SET popularity_score = COALESCE(s.score, 0)
+        FROM crawled_anime c2
+        LEFT JOIN scores s ON s.anime_slug = c2.slug
+        WHERE c.id = c2.id
+          AND c.popularity_score IS DISTINCT FROM COALESCE(s.score, 0)
+        "#,
+    )
+    .bind(weights.view)
+    .bind(weights.favorite)
+    .bind(weights.subscription)
+    .bind(weights.half_life_days)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// ORDER BY clause for a catalog order
+fn catalog_order_by(order: CatalogOrder) -> &'static str {
+    match order {
+        CatalogOrder::Title => "title ASC, id ASC",
+        CatalogOrder::TitleReverse => "title DESC, id DESC",
+        CatalogOrder::Update => "updated_at DESC, id DESC",
+        CatalogOrder::Latest => "created_at DESC, id DESC",
+        CatalogOrder::Popular => "popularity_score DESC, id ASC",
+    }
+}
+
+/// WHERE clause limiting the catalog to anime with the tag bound as $1, if
+/// any, and to anime not rated mature or adult unless $2 is true
+fn catalog_filter() -> String {
+    format!(
+        r#"($1::TEXT IS NULL OR slug IN (
+            SELECT at.anime_slug FROM anime_tags at
+            JOIN tags t ON t.id = at.tag_id
+            WHERE t.slug = $1
+        ))
+        AND ($2 OR slug NOT IN ({}))"#,
+        RESTRICTED_ANIME_SLUGS
+    )
+}
+
+/// Get a page of the crawled catalog
+///
+/// # Arguments
+/// * `order` - Sort order
+/// * `tag` - Only anime with this tag slug
+/// * `include_adult` - Whether to include anime rated mature or adult
+/// * `limit` - Anime per page
+/// * `offset` - Anime to skip
+pub async fn list_crawled_anime(
+    pool: &PgPool,
+    order: CatalogOrder,
+    tag: Option<&str>,
+    include_adult: bool,
+    limit: i64,
+    offset: i64,
+) -> RepositoryResult<Vec<CrawledAnimeRecord>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM crawled_anime WHERE {} ORDER BY {} LIMIT $3 OFFSET $4",
+        CRAWLED_ANIME_COLUMNS,
+        catalog_filter(),
+        catalog_order_by(order)
+    ))
+    .bind(tag)
+    .bind(include_adult)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(crawled_anime_from_row).collect())
+}
+
+/// Count the crawled catalog, filtered like [`list_crawled_anime`]
+pub async fn get_catalog_count(
+    pool: &PgPool,
+    tag: Option<&str>,
+    include_adult: bool,
+) -> RepositoryResult<i64> {
+    let row = sqlx::query(&format!(
+        "SELECT COUNT(*) AS count FROM crawled_anime WHERE {}",
+        catalog_filter()
+    ))
+    .bind(tag)
+    .bind(include_adult)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.get("count"))
+}
+
+// ============================================================================
+// Email Deliveries Repository
+// ============================================================================
+
+/// Delivery status for emails waiting to be sent (including retries)
+pub const EMAIL_STATUS_QUEUED: &str = "queued";
+/// Delivery status for emails accepted by the SMTP server
+pub const EMAIL_STATUS_SENT: &str = "sent";
+/// Delivery status for emails that exhausted their retries
+pub const EMAIL_STATUS_FAILED: &str = "failed";
+
+/// Columns selected for every EmailDelivery query
+const EMAIL_DELIVERY_COLUMNS: &str = "id, job_id, recipient, template, language, status, \
+    attempts, last_error, sent_at, created_at, updated_at";
+
+/// Map an email_deliveries row into an EmailDelivery
+fn email_delivery_from_row(row: &sqlx::postgres::PgRow) -> RepositoryResult<EmailDelivery> {
+    let sent_at: Option<DateTime<Utc>> = row.get("sent_at");
+    let created_at: DateTime<Utc> = row.get("created_at");
+    let updated_at: DateTime<Utc> = row.get("updated_at");
+
+    Ok(EmailDelivery {
+        id: row.get("id"),
+        job_id: row.get("job_id"),
+        recipient: encryption::open(FIELD_RECIPIENT, row.get("recipient"))?,
+        template: row.get("template"),
+        language: row.get("language"),
+        status: row.get("status"),
+        attempts: row.get("attempts"),
+        last_error: row.get("last_error"),
+        sent_at: sent_at.map(|dt| dt.to_rfc3339()),
+        created_at: created_at.to_rfc3339(),
+        updated_at: updated_at.to_rfc3339(),
+    })
+}
+
+/// Record a new queued email delivery
+///
+/// The recipient and payload are sealed with the data encryption cipher,
+/// if one is installed.
+///
+/// # Arguments
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `recipient` - Recipient email address
+/// * `template` - Template name
+/// * `language` - Language code used to render the email
+/// * `payload` - Serialized message used to render the email
+///
+/// # Returns
+/// * `Ok(EmailDelivery)` - The created delivery record
+pub async fn create_email_delivery(
+    executor: impl PgExecutor<'_>,
+    recipient: &str,
+    template: &str,
+    language: &str,
+    payload: &str,
+) -> RepositoryResult<EmailDelivery> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO email_deliveries (recipient, recipient_hash, template, language, payload)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING {}
+        "#,
+        EMAIL_DELIVERY_COLUMNS
+    ))
+    .bind(encryption::seal(FIELD_RECIPIENT, recipient))
+    .bind(encryption::blind_index(FIELD_RECIPIENT, recipient))
+    .bind(template)
+    .bind(language)
+    .bind(encryption::seal(FIELD_EMAIL_PAYLOAD, payload))
+    .fetch_one(executor)
+    .await?;
+
+    email_delivery_from_row(&row)
+}
+
+/// Attach the sending job to a delivery and reset it to queued
+pub async fn set_email_delivery_job(
+    executor: impl PgExecutor<'_>,
+    delivery_id: i32,
+    job_id: i32,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE email_deliveries SET
+            job_id = $2,
+            status = $3,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(job_id)
+    .bind(EMAIL_STATUS_QUEUED)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Get a delivery by ID
+pub async fn get_email_delivery(
+    pool: &PgPool,
+    delivery_id: i32,
+) -> RepositoryResult<Option<EmailDelivery>> {
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM email_deliveries WHERE id = $1",
+        EMAIL_DELIVERY_COLUMNS
+    ))
+    .bind(delivery_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.as_ref().map(email_delivery_from_row).transpose()
+}
+
+/// Get a delivery together with its serialized message payload
+///
+/// The payload contains tokens, so it is kept out of `EmailDelivery` and only
+/// loaded by the sending worker.
+pub async fn get_email_delivery_with_payload(
+    pool: &PgPool,
+    delivery_id: i32,
+) -> RepositoryResult<Option<(EmailDelivery, String)>> {
+    let row = sqlx::query(&format!(
+        "SELECT {}, payload FROM email_deliveries WHERE id = $1",
+        EMAIL_DELIVERY_COLUMNS
+    ))
+    .bind(delivery_id)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|row| {
+        Ok((
+            email_delivery_from_row(&row)?,
+            encryption::open(FIELD_EMAIL_PAYLOAD, row.get("payload"))?,
+        ))
+    })
+    .transpose()
+}
+
+/// List deliveries, newest first
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `status` - Optional status filter (queued, sent, failed)
+/// * `limit` - Maximum number of deliveries to return
+pub async fn get_email_deliveries(
+    pool: &PgPool,
+    status: Option<&str>,
+    limit: i64,
+) -> RepositoryResult<Vec<EmailDelivery>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM email_deliveries
+        WHERE $1::VARCHAR IS NULL OR status = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        EMAIL_DELIVERY_COLUMNS
+    ))
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(email_delivery_from_row).collect()
+}
+
+/// Mark a delivery as sent
+pub async fn mark_email_delivery_sent(pool: &PgPool, delivery_id: i32) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE email_deliveries SET
+            status = $2,
+            attempts = attempts + 1,
+            sent_at = CURRENT_TIMESTAMP,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(EMAIL_STATUS_SENT)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed send attempt
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `delivery_id` - Delivery ID
+/// * `error` - Error message from the failed attempt
+/// * `final_attempt` - `true` to mark the delivery as failed, `false` to keep it queued for retry
+pub async fn mark_email_delivery_failed(
+    pool: &PgPool,
+    delivery_id: i32,
+    error: &str,
+    final_attempt: bool,
+) -> RepositoryResult<()> {
+    let status = if final_attempt {
+        EMAIL_STATUS_FAILED
+    } else {
+        EMAIL_STATUS_QUEUED
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE email_deliveries SET
+            status = $2,
+            attempts = attempts + 1,
+            last_error = $3,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(status)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Maintenance Repository
+// ============================================================================
+
+/// Tables holding scraped data, covered by VACUUM and REINDEX maintenance
+pub const MAINTENANCE_TABLES: [&str; 8] = [
+    "anime_updates",
+    "completed_anime",
+    "anime_details",
+    "episodes",
+    "video_sources",
+    "cache_metadata",
+    "crawled_anime",
+    "search_cache",
+];
+
+/// Delete episodes whose anime is no longer stored
+///
+/// The foreign key normally cascades, so this only finds rows left behind
+/// by manual edits or imports.
+///
+/// # Returns
+/// * `Ok(count)` - Number of episodes deleted
+pub async fn delete_orphaned_episodes(pool: &PgPool) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM episodes e
+        WHERE NOT EXISTS (SELECT 1 FROM anime_details a WHERE a.slug = e.anime_slug)
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete video sources whose episode is not stored
+///
+/// # Returns
+/// * `Ok(count)` - Number of video sources deleted
+pub async fn delete_orphaned_video_sources(pool: &PgPool) -> RepositoryResult<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM video_sources v
+        WHERE NOT EXISTS (SELECT 1 FROM episodes e WHERE e.url = v.episode_url)
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// VACUUM ANALYZE the scraped data tables
+///
+/// Runs outside a transaction, as VACUUM requires.
+pub async fn vacuum_tables(pool: &PgPool) -> RepositoryResult<()> {
+    sqlx::raw_sql(&format!(
+        "VACUUM (ANALYZE) {}",
+        MAINTENANCE_TABLES.join(", ")
+    ))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Rebuild the indexes of the scraped data tables
+pub async fn reindex_tables(pool: &PgPool) -> RepositoryResult<()> {
+    for table in MAINTENANCE_TABLES {
+        sqlx::raw_sql(&format!("REINDEX TABLE {}", table))
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Users rewritten per batch by [`encrypt_user_data`]
+const ENCRYPTION_BATCH_SIZE: i64 = 500;
+
+/// Fill in the blind indexes of users stored before encryption was switched on
+///
+/// Emails and Google IDs are only unique by their blind index once
+/// encryption is on, so a plaintext row without one would let the same
+/// address sign up again. Only the indexes are written; the values stay as
+/// they are until [`encrypt_user_data`] runs. A row whose address was
+/// already signed up again, or whose values were encrypted with a key that
+/// is no longer configured, keeps its missing index and is logged.
+///
+/// # Returns
+/// * `Ok(count)` - Number of users indexed
+/// * `Err(RepositoryError::Encryption)` - If encryption is off
+pub async fn backfill_blind_indexes(pool: &PgPool) -> RepositoryResult<u64> {
+    let cipher = encryption::cipher().ok_or(EncryptionError::NotConfigured)?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, email, google_id
+        FROM users
+        WHERE email_hash IS NULL OR (google_id IS NOT NULL AND google_id_hash IS NULL)
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut indexed = 0;
+    for row in &rows {
+        let id: i32 = row.get("id");
+        let decrypted = cipher
+            .decrypt(FIELD_EMAIL, row.get("email"))
+            .and_then(|email| {
+                let google_id = row
+                    .get::<Option<&str>, _>("google_id")
+                    .map(|id| cipher.decrypt(FIELD_GOOGLE_ID, id))
+                    .transpose()?;
+                Ok((email, google_id))
+            });
+        let (email, google_id) = match decrypted {
+            Ok(values) => values,
+            Err(e) => {
+                warn!(
+                    "User {} can't be decrypted, leaving it unindexed: {}",
+                    id, e
+                );
+                continue;
+            }
+        };
+
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET email_hash = COALESCE(email_hash, $2),
+                google_id_hash = COALESCE(google_id_hash, $3)
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(cipher.blind_index(FIELD_EMAIL, &email))
+        .bind(
+            google_id
+                .as_deref()
+                .map(|id| cipher.blind_index(FIELD_GOOGLE_ID, id)),
+        )
+        .execute(pool)
+        .await;
+        match result {
+            Ok(_) => indexed += 1,
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                warn!("User {} is a duplicate of a newer account: {}", id, e);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(indexed)
+}
+
+/// Encrypt user emails and Google IDs with the current data encryption key
+///
+/// Rewrites users whose values are still plaintext, encrypted with an older
+/// key, or missing their blind index, and leaves the rest alone, so it can
+/// be rerun after rotating keys or after an interruption.
+///
+/// # Returns
+/// * `Ok(count)` - Number of users rewritten
+/// * `Err(RepositoryError::Encryption)` - If encryption is off, or a value
+///   was encrypted with a key that is no longer configured
+pub async fn encrypt_user_data(pool: &PgPool) -> RepositoryResult<u64> {
+    let cipher = encryption::cipher().ok_or(EncryptionError::NotConfigured)?;
+
+    let mut last_id = 0;
+    let mut rewritten = 0;
+    loop {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, email, email_hash, google_id, google_id_hash
+            FROM users
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(last_id)
+        .bind(ENCRYPTION_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        for row in &rows {
+            last_id = row.get("id");
+            let email: String = row.get("email");
+            let email_hash: Option<String> = row.get("email_hash");
+            let google_id: Option<String> = row.get("google_id");
+            let google_id_hash: Option<String> = row.get("google_id_hash");
+
+            let email_plain = cipher.decrypt(FIELD_EMAIL, &email)?;
+            let google_id_plain = google_id
+                .as_deref()
+                .map(|id| cipher.decrypt(FIELD_GOOGLE_ID, id))
+                .transpose()?;
+            let new_email_hash = cipher.blind_index(FIELD_EMAIL, &email_plain);
+            let new_google_id_hash = google_id_plain
+                .as_deref()
+                .map(|id| cipher.blind_index(FIELD_GOOGLE_ID, id));
+
+            let current = !cipher.needs_reencryption(&email)
+                && !google_id
+                    .as_deref()
+                    .is_some_and(|id| cipher.needs_reencryption(id))
+                && email_hash.as_deref() == Some(new_email_hash.as_str())
+                && google_id_hash == new_google_id_hash;
+            if current {
+                continue;
+            }
+
+            sqlx::query(
+                r#"
+                UPDATE users
+                SET email = $2, email_hash = $3, google_id = $4, google_id_hash = $5
+                WHERE id = $1
+                "#,
+            )
+            .bind(last_id)
+            .bind(cipher.encrypt(FIELD_EMAIL, &email_plain))
+            .bind(new_email_hash)
+            .bind(
+                google_id_plain
+                    .as_deref()
+                    .map(|id| cipher.encrypt(FIELD_GOOGLE_ID, id)),
+            )
+            .bind(new_google_id_hash)
+            .execute(pool)
+            .await?;
+            rewritten += 1;
+        }
+    }
+
+    Ok(rewritten)
+}
+
+// ============================================================================
+// Integrity Checks Repository
+// ============================================================================
+
+/// Group rows of a `SELECT parent, count` query into orphan groups
+async fn find_orphan_groups(
+    pool: &PgPool,
+    sql: &str,
+    limit: i64,
+) -> RepositoryResult<Vec<OrphanGroup>> {
+    let rows = sqlx::query(sql).bind(limit).fetch_all(pool).await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| OrphanGroup {
+            parent: row.get("parent"),
+            count: row.get("count"),
+        })
+        .collect())
+}
+
+/// Find episodes whose anime is not stored, grouped by anime slug
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `limit` - Maximum number of slugs, most episodes first
+pub async fn find_orphaned_episodes(
+    pool: &PgPool,
+    limit: i64,
+) -> RepositoryResult<Vec<OrphanGroup>> {
+    find_orphan_groups(
+        pool,
+        r#"
+        SELECT e.anime_slug AS parent, COUNT(*) AS count
+        FROM episodes e
+        WHERE NOT EXISTS (SELECT 1 FROM anime_details a WHERE a.slug = e.anime_slug)
+        GROUP BY e.anime_slug
+        ORDER BY count DESC, parent
+        LIMIT $1
+        "#,
+        limit,
+    )
+    .await
+}
+
+/// Find video sources whose episode is not stored, grouped by episode URL
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `limit` - Maximum number of URLs, most sources first
+pub async fn find_orphaned_video_sources(
+    pool: &PgPool,
+    limit: i64,
+) -> RepositoryResult<Vec<OrphanGroup>> {
+    find_orphan_groups(
+        pool,
+        r#"
+        SELECT v.episode_url AS parent, COUNT(*) AS count
+        FROM video_sources v
+        WHERE NOT EXISTS (SELECT 1 FROM episodes e WHERE e.url = v.episode_url)
+        GROUP BY v.episode_url
+        ORDER BY count DESC, parent
+        LIMIT $1
+        "#,
+        limit,
+    )
+    .await
+}
+
+/// Find favorites of anime missing from crawled_anime, grouped by slug
+///
+/// Counts favorites across all tenants, since the catalog is shared.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `limit` - Maximum number of slugs, most favorited first
+pub async fn find_dangling_favorites(
+    pool: &PgPool,
+    limit: i64,
+) -> RepositoryResult<Vec<OrphanGroup>> {
+    find_orphan_groups(
+        pool,
+        r#"
+        SELECT f.anime_slug AS parent, COUNT(*) AS count
+        FROM user_favorites f
+        WHERE NOT EXISTS (SELECT 1 FROM crawled_anime c WHERE c.slug = f.anime_slug)
+        GROUP BY f.anime_slug
+        ORDER BY count DESC, parent
+        LIMIT $1
+        "#,
+        limit,
+    )
+    .await
+}
+
+// ============================================================================
+// Episode Gaps Repository
+// ============================================================================
+
+/// The stored episode numbers of an anime, for gap detection
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEpisodeNumbers {
+    pub slug: String,
+    pub title: String,
+    /// Status from the detail page, e.g. "Completed"
+    pub status: String,
+    /// Episode count from the detail page, as shown ("12", "?")
+    pub total_episodes: String,
+    /// Episode numbers as stored, in no particular order
+    pub numbers: Vec<String>,
+    /// When the anime was last scraped
+    pub scraped_at: Option<DateTime<Utc>>,
+    /// A job of the given type is pending or running for the anime
+    pub job_pending: bool,
+}
+
+/// Get the stored episode numbers of every anime with episodes
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `job_type` - Job type with a `slug` payload checked for `job_pending`
+pub async fn get_stored_episode_numbers(
+    pool: &PgPool,
+    job_type: &str,
+) -> RepositoryResult<Vec<StoredEpisodeNumbers>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT d.slug, d.title, d.status, d.total_episodes, d.scraped_at,
+            ARRAY_AGG(e.number) FILTER (WHERE e.number IS NOT NULL) AS numbers,
+            EXISTS (
+                SELECT 1 FROM jobs j
+                WHERE j.job_type = $1
+                  AND j.status IN ($2, $3)
+                  AND j.payload::jsonb ->> 'slug' = d.slug
+            ) AS job_pending
+        FROM anime_details d
+        JOIN episodes e ON e.anime_slug = d.slug
+        GROUP BY d.slug, d.title, d.status, d.total_episodes, d.scraped_at
+        ORDER BY d.slug
+        "#,
+    )
+    .bind(job_type)
+    .bind(JOB_STATUS_PENDING)
+    .bind(JOB_STATUS_RUNNING)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| StoredEpisodeNumbers {
+            slug: row.get("slug"),
+            title: row.get("title"),
+            status: row.get::<Option<String>, _>("status").unwrap_or_default(),
+            total_episodes: row
+                .get::<Option<String>, _>("total_episodes")
+                .unwrap_or_default(),
+            numbers: row
+                .get::<Option<Vec<String>>, _>("numbers")
+                .unwrap_or_default(),
+            scraped_at: row.get("scraped_at"),
+            job_pending: row.get("job_pending"),
+        })
+        .collect())
+}
+
+// ============================================================================
+// Anime Merge Repository
+// ============================================================================
+
+/// Resolve a slug that was merged into another anime
+///
+/// # Returns
+/// * `Ok(Some(slug))` - The slug the alias now points to
+/// * `Ok(None)` - The slug is not an alias
+pub async fn resolve_anime_alias(pool: &PgPool, slug: &str) -> RepositoryResult<Option<String>> {
+    let row = sqlx::query("SELECT anime_slug FROM anime_aliases WHERE alias_slug = $1")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|row| row.get("anime_slug")))
+}
+
+/// Move per-user rows of `table` from one anime slug to another
+///
+/// Rows of users who already have a row for `into` are deleted instead.
+async fn move_user_anime_rows(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    table: &str,
+    from: &str,
+    into: &str,
+) -> RepositoryResult<u64> {
+    let moved = sqlx::query(&format!(
+        r#"
+        UPDATE {table} t SET anime_slug = $2
+        WHERE t.anime_slug = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM {table} k WHERE k.user_id = t.user_id AND k.anime_slug = $2
+          )
+        "#
+    ))
+    .bind(from)
+    .bind(into)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    sqlx::query(&format!("DELETE FROM {table} WHERE anime_slug = $1"))
+        .bind(from)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(moved)
+}
+
+/// Merge a duplicate anime into another in one transaction
+///
+/// Re-points episodes (their video sources follow, as they are keyed by
+/// episode URL), favorites, subscriptions, history, and watched marks from
+/// `from` to `into`. A user's favorite or subscription of `from` is dropped
+/// if they already have one of `into`. The duplicate's detail, catalog
+/// entry, and cache timestamp are then removed, and `from` is recorded as
+/// an alias of `into`, taking over any aliases that pointed at `from`.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `from` - Slug of the duplicate
+/// * `into` - Slug of the anime to keep
+///
+/// # Returns
+/// * `Ok(Some(AnimeMergeResult))` - Rows moved per table
+/// * `Ok(None)` - `into` is not a stored anime
+pub async fn merge_anime(
+    pool: &PgPool,
+    from: &str,
+    into: &str,
+) -> RepositoryResult<Option<AnimeMergeResult>> {
+    let mut tx = pool.begin().await?;
+
+    let kept = sqlx::query("SELECT 1 FROM anime_details WHERE slug = $1 FOR UPDATE")
+        .bind(into)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if kept.is_none() {
+        return Ok(None);
+    }
+
+    let video_sources: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM video_sources v
+        JOIN episodes e ON e.url = v.episode_url
+        WHERE e.anime_slug = $1
+        "#,
+    )
+    .bind(from)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let moved: Vec<String> = sqlx::query_scalar(
+        r#"
+        UPDATE episodes SET anime_slug = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE anime_slug = $1
+        RETURNING url
+        "#,
+    )
+    .bind(from)
+    .bind(into)
+    .fetch_all(&mut *tx)
+    .await?;
+    let episodes = moved.len() as u64;
+
+    let favorites = move_user_anime_rows(&mut tx, "user_favorites", from, into).await?;
+    let subscriptions = move_user_anime_rows(&mut tx, "user_subscriptions", from, into).await?;
+
+    let history = sqlx::query("UPDATE user_history SET anime_slug = $2 WHERE anime_slug = $1")
+        .bind(from)
+        .bind(into)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let watched_episodes =
+        sqlx::query("UPDATE user_watched_episodes SET anime_slug = $2 WHERE anime_slug = $1")
+            .bind(from)
+            .bind(into)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+    sqlx::query("DELETE FROM anime_details WHERE slug = $1")
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM crawled_anime WHERE slug = $1")
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM cache_metadata WHERE cache_key = $1")
+        .bind(format!("anime:{}", from))
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE anime_aliases SET anime_slug = $2 WHERE anime_slug = $1")
+        .bind(from)
+        .bind(into)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO anime_aliases (alias_slug, anime_slug)
+        VALUES ($1, $2)
+        ON CONFLICT (alias_slug) DO UPDATE SET
+            anime_slug = EXCLUDED.anime_slug,
+            created_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(from)
+    .bind(into)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut changes = PendingChanges::default();
+    for url in &moved {
+        changes.episode(url, WriteOutcome::Updated);
+    }
+    changes.record(&mut tx).await?;
+    tx.commit().await?;
+
+    Ok(Some(AnimeMergeResult {
+        from: from.to_string(),
+        into: into.to_string(),
+        episodes,
+        video_sources: video_sources as u64,
+        favorites,
+        subscriptions,
+        history,
+        watched_episodes,
+    }))
+}
+
+/// Record that an anime's page moved to a new slug
+///
+/// Moves the anime's catalog entry to `into` and its new URL, or drops it
+/// if `into` is already in the catalog, and records `from` as an alias of `into`,
+/// taking over any aliases that pointed at `from`. Stored details and user
+/// data are left alone; [`merge_anime`] moves those once `into` is saved.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `from` - Slug the anime was crawled under
+/// * `into` - Slug the page now redirects to
+/// * `url` - URL the page now redirects to
+pub async fn record_anime_redirect(
+    pool: &PgPool,
+    from: &str,
+    into: &str,
+    url: &str,
+) -> RepositoryResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE crawled_anime SET slug = $2, url = $3, updated_at = CURRENT_TIMESTAMP
+        WHERE slug = $1
+          AND NOT EXISTS (SELECT 1 FROM crawled_anime WHERE slug = $2 OR url = $3)
+        "#,
+    )
+    .bind(from)
+    .bind(into)
+    .bind(url)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM crawled_anime WHERE slug = $1")
+        .bind(from)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE anime_aliases SET anime_slug = $2 WHERE anime_slug = $1")
+        .bind(from)
+        .bind(into)
+        .execute(&mut *tx)
+        .await?;
+    // A page moving back to an old slug undoes that slug's alias
+    sqlx::query("DELETE FROM anime_aliases WHERE alias_slug = $1")
+        .bind(into)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO anime_aliases (alias_slug, anime_slug)
+        VALUES ($1, $2)
+        ON CONFLICT (alias_slug) DO UPDATE SET
+            anime_slug = EXCLUDED.anime_slug,
+            created_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(from)
+    .bind(into)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// ============================================================================
+// Outbox Repository
+// ============================================================================
+
+const OUTBOX_COLUMNS: &str = "id, event_type, payload, attempts, last_error, created_at";
+
+fn outbox_event_from_row(row: &sqlx::postgres::PgRow) -> OutboxEventRecord {
+    let created_at: DateTime<Utc> = row.get("created_at");
+
+    OutboxEventRecord {
+        id: row.get("id"),
+        event_type: row.get("event_type"),
+        payload: row.get("payload"),
+        attempts: row.get("attempts"),
+        last_error: row.get("last_error"),
+        created_at: created_at.to_rfc3339(),
+    }
+}
+
+/// Record an event in the outbox
+///
+/// Pass a [`UnitOfWork`](super::UnitOfWork) connection to record it only if
+/// the change it's about commits.
+///
+/// # Arguments
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `event_type` - Event type (e.g., "anime.completed")
+/// * `payload` - JSON payload of the event
+///
+/// # Returns
+/// The event ID
+pub async fn record_outbox_event(
+    executor: impl PgExecutor<'_>,
+    event_type: &str,
+    payload: &str,
+) -> RepositoryResult<i64> {
+    let id = sqlx::query_scalar(
+        "INSERT INTO outbox_events (event_type, payload) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(event_type)
+    .bind(payload)
+    .fetch_one(executor)
+    .await?;
+    Ok(id)
+}
+
+/// Lock the oldest event that is due for delivery
+///
+/// The row stays locked until the transaction `conn` belongs to ends, and
+/// `FOR UPDATE SKIP LOCKED` keeps concurrent dispatchers off it, so mark it
+/// dispatched or failed on the same transaction.
+///
+/// # Returns
+/// * `Ok(Some(OutboxEventRecord))` - The locked event
+/// * `Ok(None)` - No event is due
+pub async fn claim_next_outbox_event(
+    conn: &mut PgConnection,
+) -> RepositoryResult<Option<OutboxEventRecord>> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {}
+        FROM outbox_events
+        WHERE dispatched_at IS NULL AND available_at <= CURRENT_TIMESTAMP
+        ORDER BY available_at, id
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+        "#,
+        OUTBOX_COLUMNS
+    ))
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(row.as_ref().map(outbox_event_from_row))
+}
+
+/// Mark an event as delivered
+pub async fn mark_outbox_dispatched(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> RepositoryResult<()> {
+    sqlx::query("UPDATE outbox_events SET dispatched_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Delete events delivered before `before`
+///
+/// Pending events are kept however old they are.
+///
+/// # Returns
+/// * `Ok(count)` - Number of events deleted
+pub async fn delete_dispatched_outbox_events(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+) -> RepositoryResult<u64> {
+    let result = sqlx::query("DELETE FROM outbox_events WHERE dispatched_at < $1")
+        .bind(before)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// Record a failed delivery and schedule the next attempt
+///
+/// # Arguments
+/// * `executor` - Database connection pool, or a [`UnitOfWork`](super::UnitOfWork) connection
+/// * `id` - Event ID
+/// * `error` - Why the delivery failed
+/// * `retry_in_secs` - Seconds until the event is due again
+pub async fn mark_outbox_failed(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    error: &str,
+    retry_in_secs: i64,
+) -> RepositoryResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE outbox_events SET
+            attempts = attempts + 1,
+            last_error = $2,
+            available_at = CURRENT_TIMESTAMP + make_interval(secs => $3)
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(retry_in_secs as f64)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper function to create a test AnimeUpdate
+    fn create_test_anime_update(episode_url: &str) -> AnimeUpdate {
+        AnimeUpdate {
             slug: "test-series".to_string(),
             title: "Test Episode".to_string(),
             episode_url: episode_url.to_string(),
             thumbnail: "https://example.com/thumb.jpg".to_string(),
+            thumbnails: None,
             episode_number: "1".to_string(),
             anime_type: "TV".to_string(),
-            series_title: "Test Series".to_string(),
-            series_url: "https://example.com/series".to_string(),
+            series_title: "Test Series".to_string(),
+            series_url: "https://example.com/series".to_string(),
+            status: "Ongoing".to_string(),
+            release_info: "2024-01-01".to_string(),
+            released_at: None,
+            like_count: None,
+            liked_by_me: None,
+        }
+    }
+
+    // Helper function to create a test CompletedAnime
+    fn create_test_completed_anime(url: &str) -> CompletedAnime {
+        CompletedAnime {
+            slug: "test-completed".to_string(),
+            title: "Test Completed Anime".to_string(),
+            url: url.to_string(),
+            thumbnail: "https://example.com/thumb.jpg".to_string(),
+            thumbnails: None,
+            anime_type: "TV".to_string(),
+            episode_count: "24".to_string(),
+            status: "Completed".to_string(),
+            posted_by: "Admin".to_string(),
+            posted_at: "2024-01-01".to_string(),
+            released_at_iso: None,
+            series_title: "Test Series".to_string(),
+            series_url: "https://example.com/series".to_string(),
+            genres: vec!["Action".to_string(), "Adventure".to_string()],
+            rating: "8.5".to_string(),
+        }
+    }
+
+    // Helper function to create a test AnimeDetail
+    fn create_test_anime_detail() -> AnimeDetail {
+        AnimeDetail {
+            title: "Test Anime".to_string(),
+            alternate_titles: "Test Alt Title".to_string(),
+            poster: "https://example.com/poster.jpg".to_string(),
+            rating: "8.5".to_string(),
+            trailer_url: "https://youtube.com/watch?v=test".to_string(),
+            status: "Ongoing".to_string(),
+            studio: "Test Studio".to_string(),
+            release_date: "2024-01-01".to_string(),
+            duration: "24 min".to_string(),
+            season: "Winter 2024".to_string(),
+            anime_type: "TV".to_string(),
+            total_episodes: "24".to_string(),
+            director: "Test Director".to_string(),
+            casts: vec!["Actor 1".to_string(), "Actor 2".to_string()],
+            genres: vec!["Action".to_string(), "Adventure".to_string()],
+            synopsis: "Test synopsis".to_string(),
+            episodes: vec![
+                Episode {
+                    slug: "ep1".to_string(),
+                    number: "1".to_string(),
+                    title: "Episode 1".to_string(),
+                    url: "https://example.com/ep1".to_string(),
+                    release_date: "2024-01-01".to_string(),
+                    released_at_iso: None,
+                },
+                Episode {
+                    slug: "ep2".to_string(),
+                    number: "2".to_string(),
+                    title: "Episode 2".to_string(),
+                    url: "https://example.com/ep2".to_string(),
+                    release_date: "2024-01-08".to_string(),
+                    released_at_iso: None,
+                },
+            ],
+            canonical_slug: None,
+            age_rating: None,
+        }
+    }
+
+    // Helper function to create a test VideoSource
+    fn create_test_video_source(server: &str, quality: &str) -> VideoSource {
+        VideoSource {
+            server: server.to_string(),
+            quality: quality.to_string(),
+            url: format!("https://example.com/video-{}-{}.mp4", server, quality),
+            embed: None,
+        }
+    }
+
+    #[test]
+    fn test_create_anime_update() {
+        let update = create_test_anime_update("https://example.com/ep1");
+        assert_eq!(update.title, "Test Episode");
+        assert_eq!(update.episode_url, "https://example.com/ep1");
+    }
+
+    #[test]
+    fn test_create_completed_anime() {
+        let anime = create_test_completed_anime("https://example.com/anime1");
+        assert_eq!(anime.title, "Test Completed Anime");
+        assert_eq!(anime.url, "https://example.com/anime1");
+        assert_eq!(anime.genres.len(), 2);
+    }
+
+    #[test]
+    fn test_create_anime_detail() {
+        let detail = create_test_anime_detail();
+        assert_eq!(detail.title, "Test Anime");
+        assert_eq!(detail.episodes.len(), 2);
+        assert_eq!(detail.genres.len(), 2);
+        assert_eq!(detail.casts.len(), 2);
+    }
+
+    #[test]
+    fn test_create_video_source() {
+        let source = create_test_video_source("SOKUJA", "720p");
+        assert_eq!(source.server, "SOKUJA");
+        assert_eq!(source.quality, "720p");
+        assert!(source.url.contains("SOKUJA"));
+        assert!(source.url.contains("720p"));
+    }
+
+    // Integration tests that require a database connection
+    // These are marked with #[ignore] and can be run with `cargo test -- --ignored`
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_anime_updates_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        // Clean up first
+        let _ = delete_all_anime_updates(&pool).await;
+
+        // Create
+        let updates = vec![
+            create_test_anime_update("https://test.com/ep1"),
+            create_test_anime_update("https://test.com/ep2"),
+        ];
+        save_anime_updates(&pool, &updates)
+            .await
+            .expect("Failed to save");
+
+        // Read
+        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
+        assert!(fetched.len() >= 2);
+
+        // Update (upsert with same URL)
+        let mut updated = create_test_anime_update("https://test.com/ep1");
+        updated.title = "Updated Title".to_string();
+        save_anime_updates(&pool, &[updated])
+            .await
+            .expect("Failed to update");
+
+        // Verify update
+        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
+        let found = fetched
+            .iter()
+            .find(|u| u.episode_url == "https://test.com/ep1");
+        assert!(found.is_some());
+        assert_eq!(found.unwrap().title, "Updated Title");
+
+        // Clean up
+        delete_all_anime_updates(&pool)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_anime_updates_sorted_by_release() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let _ = delete_all_anime_updates(&pool).await;
+
+        let release = |url: &str, released_at: Option<&str>| AnimeUpdate {
+            released_at: released_at.map(str::to_string),
+            ..create_test_anime_update(url)
+        };
+        save_anime_updates(
+            &pool,
+            &[
+                release("https://test.com/older", Some("2024-12-26T10:00:00+00:00")),
+                release("https://test.com/unknown", None),
+                release("https://test.com/newer", Some("2024-12-27T10:00:00+00:00")),
+            ],
+        )
+        .await
+        .expect("Failed to save");
+
+        // A coarser release time from a later fetch doesn't replace the stored one
+        save_anime_updates(
+            &pool,
+            &[release(
+                "https://test.com/newer",
+                Some("2024-12-27T00:00:00+00:00"),
+            )],
+        )
+        .await
+        .expect("Failed to save");
+
+        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
+        let urls: Vec<&str> = fetched.iter().map(|u| u.episode_url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://test.com/newer",
+                "https://test.com/older",
+                "https://test.com/unknown"
+            ]
+        );
+        assert_eq!(
+            fetched[0].released_at.as_deref(),
+            Some("2024-12-27T10:00:00+00:00")
+        );
+
+        delete_all_anime_updates(&pool)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_anime_updates_duplicates() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let _ = delete_all_anime_updates(&pool).await;
+
+        let update = |url: &str, title: &str| AnimeUpdate {
+            title: title.to_string(),
+            ..create_test_anime_update(url)
+        };
+        save_anime_updates(
+            &pool,
+            &[
+                update("https://test.com/episode/", "First"),
+                update("https://test.com/episode", "Repeated"),
+            ],
+        )
+        .await
+        .expect("Failed to save");
+
+        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].episode_url, "https://test.com/episode/");
+        assert_eq!(fetched[0].title, "Repeated");
+
+        delete_all_anime_updates(&pool)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_anime_updates_thumbnails() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let _ = delete_all_anime_updates(&pool).await;
+
+        let thumbnails = Thumbnails {
+            small: "https://example.com/thumb-150.jpg".to_string(),
+            medium: "https://example.com/thumb-225.jpg".to_string(),
+            large: "https://example.com/thumb.jpg".to_string(),
+        };
+        let update = AnimeUpdate {
+            thumbnails: Some(thumbnails.clone()),
+            ..create_test_anime_update("https://test.com/thumbnails")
+        };
+        save_anime_updates(&pool, &[update])
+            .await
+            .expect("Failed to save");
+
+        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
+        assert_eq!(fetched[0].thumbnails, Some(thumbnails));
+
+        delete_all_anime_updates(&pool)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_video_server_rules_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let server = "test-mirror";
+        let _ = delete_video_server_rule(&pool, server).await;
+
+        let rule = set_video_server_rule(&pool, server, true, 0)
+            .await
+            .expect("Failed to set rule");
+        assert!(rule.blocked);
+        assert_eq!(rule.origin, VideoServerRuleOrigin::Admin);
+        assert!(rule.updated_at.is_some());
+
+        let rule = set_video_server_rule(&pool, server, false, 5)
+            .await
+            .expect("Failed to replace rule");
+        assert!(!rule.blocked);
+        assert_eq!(rule.priority, 5);
+
+        let rules = get_video_server_rules(&pool).await.expect("Failed to list");
+        assert_eq!(rules.iter().filter(|r| r.server == server).count(), 1);
+
+        assert!(delete_video_server_rule(&pool, server)
+            .await
+            .expect("Failed to delete"));
+        assert!(!delete_video_server_rule(&pool, server)
+            .await
+            .expect("Failed to delete"));
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_feature_flags() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_feature_flags@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
+        }
+        let operator = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+        let feature = Feature::ImageProxy;
+        let _ = delete_feature_flag(&pool, feature).await;
+
+        let flag = set_feature_flag(&pool, feature, false, Some("proxy abuse"), operator.id)
+            .await
+            .expect("Failed to set flag");
+        assert_eq!(flag.feature, feature);
+        assert!(!flag.enabled);
+        assert_eq!(flag.reason.as_deref(), Some("proxy abuse"));
+        assert!(flag.updated_at.is_some());
+
+        let flag = set_feature_flag(&pool, feature, true, None, operator.id)
+            .await
+            .expect("Failed to replace flag");
+        assert!(flag.enabled);
+        assert_eq!(flag.reason, None);
+
+        // Flags of features this build doesn't know are skipped
+        sqlx::query("INSERT INTO feature_flags (feature, enabled) VALUES ('test-unknown', FALSE) ON CONFLICT DO NOTHING")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let flags = get_feature_flags(&pool).await.expect("Failed to list");
+        assert_eq!(flags.iter().filter(|f| f.feature == feature).count(), 1);
+        sqlx::query("DELETE FROM feature_flags WHERE feature = 'test-unknown'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(delete_feature_flag(&pool, feature)
+            .await
+            .expect("Failed to delete"));
+        assert!(!delete_feature_flag(&pool, feature)
+            .await
+            .expect("Failed to delete"));
+        delete_user(&pool, operator.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_known_video_source_urls() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let episode_url = "https://test.com/episode/test-known-sources";
+        let source = create_test_video_source("KNOWN", "720p");
+        save_video_sources(&pool, episode_url, std::slice::from_ref(&source))
+            .await
+            .expect("Failed to save");
+
+        let unknown = "https://example.com/not-a-source.mp4".to_string();
+        let known = get_known_video_source_urls(&pool, &[source.url.clone(), unknown.clone()])
+            .await
+            .expect("Failed to look up");
+        assert!(known.contains(&source.url));
+        assert!(!known.contains(&unknown));
+
+        delete_video_sources(&pool, episode_url)
+            .await
+            .expect("Failed to clean up");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_subtitle_tracks_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let episode_url = "https://test.com/episode/test-subtitles";
+        let _ = delete_subtitle_tracks(&pool, episode_url).await;
+
+        let track = SubtitleTrack {
+            url: "https://test.com/subs/test-subtitles.id.vtt".to_string(),
+            language: "id".to_string(),
+            label: "Indonesia".to_string(),
+            default: true,
+        };
+        let outcome = save_subtitle_tracks(&pool, episode_url, std::slice::from_ref(&track))
+            .await
+            .expect("Failed to save");
+        assert_eq!(outcome, WriteOutcome::Inserted);
+        let outcome = save_subtitle_tracks(&pool, episode_url, std::slice::from_ref(&track))
+            .await
+            .expect("Failed to save");
+        assert_eq!(outcome, WriteOutcome::Unchanged);
+
+        let fetched = get_subtitle_tracks(&pool, episode_url)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(fetched, vec![track.clone()]);
+        assert!(is_known_subtitle_url(&pool, &track.url)
+            .await
+            .expect("Failed to look up"));
+
+        let outcome = save_subtitle_tracks(&pool, episode_url, &[])
+            .await
+            .expect("Failed to clear");
+        assert_eq!(outcome, WriteOutcome::Updated);
+        assert!(!is_known_subtitle_url(&pool, &track.url)
+            .await
+            .expect("Failed to look up"));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_get_episode_by_url() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slug = "test-anime-episode-by-url";
+        let episode_url = "https://test.com/test-anime-episode-by-url-episode-3/";
+        let detail = AnimeDetail {
+            title: "Test Anime".to_string(),
+            ..Default::default()
+        };
+        let episode = Episode {
+            slug: "test-anime-episode-by-url-episode-3".to_string(),
+            number: "3".to_string(),
+            title: "Episode 3".to_string(),
+            url: episode_url.to_string(),
+            release_date: String::new(),
+            released_at_iso: None,
+        };
+        save_anime_detail(&pool, slug, &detail)
+            .await
+            .expect("Failed to save detail");
+        save_episodes(&pool, slug, std::slice::from_ref(&episode))
+            .await
+            .expect("Failed to save episodes");
+
+        let found = get_episode_by_url(&pool, episode_url.trim_end_matches('/'))
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(found, Some((slug.to_string(), episode)));
+        assert_eq!(
+            get_episode_by_url(&pool, "https://test.com/not-an-episode/")
+                .await
+                .expect("Failed to fetch"),
+            None
+        );
+
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to clean up");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_sitemap_pages() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slug = "test-anime-sitemap";
+        let detail = AnimeDetail {
+            title: "Test Anime".to_string(),
+            ..Default::default()
+        };
+        save_anime_detail(&pool, slug, &detail)
+            .await
+            .expect("Failed to save detail");
+
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM anime_details")
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to count");
+        let pages = get_sitemap_pages(&pool, 1).await.expect("Failed to page");
+        assert_eq!(pages.len() as i64, total);
+        assert_eq!(pages[0].page, 1);
+
+        let mut entries = Vec::new();
+        for page in &pages {
+            entries.extend(
+                get_sitemap_anime(&pool, page.page, 1)
+                    .await
+                    .expect("Failed to list"),
+            );
+        }
+        let entry = entries
+            .iter()
+            .find(|entry| entry.slug == slug)
+            .expect("Anime missing from sitemap");
+        assert!(entry.last_modified.is_some());
+        assert!(get_sitemap_anime(&pool, total + 1, 1)
+            .await
+            .expect("Failed to list")
+            .is_empty());
+        assert!(get_sitemap_anime(&pool, i64::MAX, 1000)
+            .await
+            .expect("Failed to list")
+            .is_empty());
+
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to clean up");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_completed_anime_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        // Clean up first
+        let _ = delete_all_completed_anime(&pool).await;
+
+        // Create
+        let anime_list = vec![
+            create_test_completed_anime("https://test.com/anime1"),
+            create_test_completed_anime("https://test.com/anime2"),
+        ];
+        save_completed_anime(&pool, &anime_list)
+            .await
+            .expect("Failed to save");
+
+        // Read
+        let fetched = get_completed_anime(&pool).await.expect("Failed to fetch");
+        assert!(fetched.len() >= 2);
+
+        // The first entry of a saved list is listed first
+        let page = list_completed_anime(&pool, Some(1), 0)
+            .await
+            .expect("Failed to fetch page");
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].url, "https://test.com/anime1");
+        let page = list_completed_anime(&pool, Some(1), 1)
+            .await
+            .expect("Failed to fetch page");
+        assert_eq!(page[0].url, "https://test.com/anime2");
+
+        // Clean up
+        delete_all_completed_anime(&pool)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_popular_anime_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let entry = |rank: u32, slug: &str| PopularEntry {
+            rank,
+            slug: slug.to_string(),
+            title: slug.to_uppercase(),
+            url: format!("https://test.com/anime/{}/", slug),
+            thumbnail: String::new(),
+            thumbnails: None,
+            genres: vec!["Action".to_string()],
+            rating: "8.1".to_string(),
+        };
+
+        save_popular_anime(&pool, PopularWindow::Week, &[entry(2, "b"), entry(1, "a")])
+            .await
+            .expect("Failed to save");
+        save_popular_anime(&pool, PopularWindow::Today, &[entry(1, "c")])
+            .await
+            .expect("Failed to save");
+        let week = get_popular_anime(&pool, PopularWindow::Week)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(week, vec![entry(1, "a"), entry(2, "b")]);
+
+        // A new ranking replaces the window's old one only
+        save_popular_anime(&pool, PopularWindow::Week, &[entry(1, "d")])
+            .await
+            .expect("Failed to save");
+        let week = get_popular_anime(&pool, PopularWindow::Week)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(week, vec![entry(1, "d")]);
+        let today = get_popular_anime(&pool, PopularWindow::Today)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(today, vec![entry(1, "c")]);
+
+        // Entries sharing a rank are kept and renumbered
+        save_popular_anime(
+            &pool,
+            PopularWindow::Week,
+            &[entry(2, "f"), entry(1, "d"), entry(2, "e")],
+        )
+        .await
+        .expect("Failed to save");
+        let week = get_popular_anime(&pool, PopularWindow::Week)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(week, vec![entry(1, "d"), entry(2, "f"), entry(3, "e")]);
+
+        for window in PopularWindow::ALL {
+            save_popular_anime(&pool, window, &[])
+                .await
+                .expect("Failed to clean up");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_anime_detail_with_episodes_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slug = "test-anime-slug";
+
+        // Clean up first
+        let _ = delete_anime_detail(&pool, slug).await;
+
+        // Create with episodes
+        let detail = create_test_anime_detail();
+        save_anime_detail_with_episodes(&pool, slug, &detail)
+            .await
+            .expect("Failed to save");
+
+        // Read
+        let fetched = get_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to fetch");
+        assert!(fetched.is_some());
+        let fetched = fetched.unwrap();
+        assert_eq!(fetched.title, "Test Anime");
+        assert_eq!(fetched.episodes.len(), 2);
+
+        // Read selected fields only
+        let fields = DetailFields::parse("title,genres").unwrap();
+        let partial = get_anime_detail_fields(&pool, slug, &fields)
+            .await
+            .expect("Failed to fetch fields")
+            .unwrap();
+        assert_eq!(partial.title, "Test Anime");
+        assert_eq!(partial.genres, detail.genres);
+        assert!(partial.synopsis.is_empty());
+        assert!(partial.episodes.is_empty());
+
+        // Update (upsert)
+        let mut updated_detail = create_test_anime_detail();
+        updated_detail.title = "Updated Anime Title".to_string();
+        save_anime_detail_with_episodes(&pool, slug, &updated_detail)
+            .await
+            .expect("Failed to update");
+
+        // Verify update
+        let fetched = get_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to fetch");
+        assert!(fetched.is_some());
+        assert_eq!(fetched.unwrap().title, "Updated Anime Title");
+
+        // Clean up
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_change_events() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slug = "test-change-events";
+        let _ = delete_anime_detail(&pool, slug).await;
+        let start: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM change_events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let changes = |after: i64| {
+            let pool = pool.clone();
+            async move {
+                get_changes_since(
+                    &pool,
+                    DateTime::<Utc>::UNIX_EPOCH,
+                    ChangeCursor::decode(&after.to_string()),
+                    1000,
+                )
+                .await
+                .expect("Failed to get changes")
+                .into_iter()
+                .filter(|(entry, _)| entry.anime_slug == slug)
+                .collect::<Vec<_>>()
+            }
+        };
+
+        // Inserting the anime and its episodes records an insert each
+        let mut detail = create_test_anime_detail();
+        for episode in &mut detail.episodes {
+            episode.url = format!("https://test.com/{}/{}", slug, episode.number);
+        }
+        save_anime_detail_with_episodes(&pool, slug, &detail)
+            .await
+            .expect("Failed to save");
+        let inserted = changes(start).await;
+        assert_eq!(inserted.len(), 1 + detail.episodes.len());
+        assert_eq!(inserted[0].0.kind, ChangeKind::Anime);
+        assert_eq!(inserted[0].0.slug, slug);
+        assert!(inserted.iter().all(|(entry, _)| entry.created));
+        assert_eq!(inserted[1].0.kind, ChangeKind::Episode);
+        assert_eq!(
+            inserted[1].0.url.as_deref(),
+            Some(detail.episodes[0].url.as_str())
+        );
+
+        // A cursor from before change_events, pointing at the anime, still
+        // resumes right after it
+        let (occurred_at, entity_id): (DateTime<Utc>, i32) =
+            sqlx::query_as("SELECT occurred_at, entity_id FROM change_events WHERE id = $1")
+                .bind(inserted[0].1.id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let legacy = format!("{}.0.{}", occurred_at.timestamp_micros(), entity_id);
+        let resumed = resolve_change_cursor(&pool, &legacy)
+            .await
+            .expect("Failed to resolve cursor")
+            .expect("Legacy cursor rejected");
+        assert_eq!(
+            resumed,
+            ChangeCursor {
+                id: inserted[1].1.id - 1
+            }
+        );
+        assert_eq!(changes(resumed.id).await, inserted[1..].to_vec());
+        assert_eq!(
+            resolve_change_cursor(&pool, &inserted[0].1.encode())
+                .await
+                .unwrap(),
+            Some(inserted[0].1)
+        );
+        assert_eq!(resolve_change_cursor(&pool, "1.2.3").await.unwrap(), None);
+
+        // Saving the same content records nothing
+        let after = inserted.last().unwrap().1;
+        save_anime_detail_with_episodes(&pool, slug, &detail)
+            .await
+            .expect("Failed to save");
+        assert!(changes(after.id).await.is_empty());
+
+        // Changed content and new sources record updates, in commit order
+        detail.title = "Renamed".to_string();
+        save_anime_detail(&pool, slug, &detail)
+            .await
+            .expect("Failed to save");
+        save_video_sources(
+            &pool,
+            &detail.episodes[0].url,
+            &[create_test_video_source("SOKUJA", "720p")],
+        )
+        .await
+        .expect("Failed to save sources");
+        let updated = changes(after.id).await;
+        assert_eq!(updated.len(), 2);
+        assert_eq!(updated[0].0.title, "Renamed");
+        assert_eq!(updated[1].0.kind, ChangeKind::Episode);
+        assert!(updated.iter().all(|(entry, _)| !entry.created));
+        assert!(updated[0].1.id < updated[1].1.id);
+
+        let _ = delete_video_sources(&pool, &detail.episodes[0].url).await;
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to delete");
+        sqlx::query("DELETE FROM change_events WHERE anime_slug = $1")
+            .bind(slug)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_delete_old_change_events() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let consumer = "test-change-retention";
+        let old: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO change_events (kind, entity_id, anime_slug, occurred_at)
+            VALUES ('anime', 0, 'test-change-retention', CURRENT_TIMESTAMP - INTERVAL '400 days')
+            RETURNING id
+            "#,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let exists = || async {
+            sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS (SELECT 1 FROM change_events WHERE id = $1)",
+            )
+            .bind(old)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        };
+        let before = Utc::now() - chrono::Duration::days(365);
+
+        // A consumer that hasn't read the event keeps it
+        sqlx::query(
+            "INSERT INTO change_feed_positions (consumer, last_event_id) VALUES ($1, $2)
+             ON CONFLICT (consumer) DO UPDATE SET last_event_id = EXCLUDED.last_event_id",
+        )
+        .bind(consumer)
+        .bind(old - 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+        delete_old_change_events(&pool, before)
+            .await
+            .expect("Failed to delete change events");
+        assert!(exists().await);
+
+        // Once every consumer is past it, it goes
+        set_change_feed_position(&pool, consumer, ChangeCursor { id: old })
+            .await
+            .unwrap();
+        assert!(
+            delete_old_change_events(&pool, before)
+                .await
+                .expect("Failed to delete change events")
+                >= 1
+        );
+        assert!(!exists().await);
+
+        sqlx::query("DELETE FROM change_feed_positions WHERE consumer = $1")
+            .bind(consumer)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_video_sources_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let episode_url = "https://test.com/episode/test-ep";
+
+        // Clean up first
+        let _ = delete_video_sources(&pool, episode_url).await;
+
+        // Create
+        let sources = vec![
+            create_test_video_source("SOKUJA", "480p"),
+            create_test_video_source("SOKUJA", "720p"),
+            create_test_video_source("SOKUJA", "1080p"),
+        ];
+        save_video_sources(&pool, episode_url, &sources)
+            .await
+            .expect("Failed to save");
+
+        // Read
+        let fetched = get_video_sources(&pool, episode_url)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(fetched.len(), 3);
+
+        // Update (replace all sources)
+        let new_sources = vec![create_test_video_source("NEW_SERVER", "720p")];
+        save_video_sources(&pool, episode_url, &new_sources)
+            .await
+            .expect("Failed to update");
+
+        // Verify update
+        let fetched = get_video_sources(&pool, episode_url)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].server, "NEW_SERVER");
+        assert_eq!(fetched[0].embed, None);
+
+        // Iframe sources come back with their embed metadata
+        let player_url = "https://www.blogger.com/video.g?token=abc";
+        let embedded = VideoSource {
+            url: player_url.to_string(),
+            embed: embed_info(player_url),
+            ..create_test_video_source("Blogger", "")
+        };
+        save_video_sources(&pool, episode_url, std::slice::from_ref(&embedded))
+            .await
+            .expect("Failed to update");
+        let fetched = get_video_sources(&pool, episode_url)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(fetched, vec![embedded]);
+
+        // Clean up
+        delete_video_sources(&pool, episode_url)
+            .await
+            .expect("Failed to delete");
+    }
+
+    // Content hash tests
+
+    #[test]
+    fn test_anime_detail_hash_ignores_episodes() {
+        let mut detail = create_test_anime_detail();
+        let hash = anime_detail_hash(&detail);
+        assert_eq!(hash.len(), 64);
+
+        detail.episodes.pop();
+        assert_eq!(anime_detail_hash(&detail), hash);
+
+        detail.synopsis = "Changed".to_string();
+        assert_ne!(anime_detail_hash(&detail), hash);
+    }
+
+    #[test]
+    fn test_episode_hash_includes_anime_slug() {
+        let episode = create_test_anime_detail().episodes.remove(0);
+        assert_eq!(episode_hash("a", &episode), episode_hash("a", &episode));
+        assert_ne!(episode_hash("a", &episode), episode_hash("b", &episode));
+    }
+
+    #[test]
+    fn test_normalize_search_query() {
+        assert_eq!(normalize_search_query("  One   Piece "), "one piece");
+        assert_eq!(normalize_search_query("FRIEREN"), "frieren");
+        assert_eq!(normalize_search_query("\tmob\npsycho"), "mob psycho");
+        assert_eq!(normalize_search_query("   "), "");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search_cache() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let query = "test search cache query";
+        let results = vec![SearchResult {
+            slug: "test-anime".to_string(),
+            title: "Test Anime".to_string(),
+            url: "https://test.com/anime/test-anime/".to_string(),
+            thumbnail: String::new(),
+            thumbnails: None,
+            status: "Ongoing".to_string(),
+            anime_type: "TV".to_string(),
+            episode_status: String::new(),
+        }];
+
+        save_search_results(&pool, query, &results)
+            .await
+            .expect("Failed to save");
+        let cached = get_cached_search(&pool, query, 60_000, 60_000)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(cached, Some(results));
+
+        // Empty results expire on their own clock
+        save_search_results(&pool, query, &[])
+            .await
+            .expect("Failed to save");
+        let cached = get_cached_search(&pool, query, 60_000, 0)
+            .await
+            .expect("Failed to fetch");
+        assert_eq!(cached, None);
+
+        delete_expired_searches(&pool, 0)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search_analytics() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let query = "test search analytics query";
+        sqlx::query("DELETE FROM search_analytics WHERE query = $1")
+            .bind(query)
+            .execute(&pool)
+            .await
+            .expect("Failed to clean up");
+
+        record_search(&pool, query, 0)
+            .await
+            .expect("Failed to record");
+        record_search(&pool, query, 0)
+            .await
+            .expect("Failed to record");
+        let missing = get_zero_result_searches(&pool, 1000)
+            .await
+            .expect("Failed to fetch");
+        let stats = missing
+            .iter()
+            .find(|s| s.query == query)
+            .expect("Query missing");
+        assert_eq!(stats.search_count, 2);
+        assert_eq!(stats.zero_result_count, 2);
+
+        // Once it matches, the query is no longer reported as not found
+        record_search(&pool, query, 3)
+            .await
+            .expect("Failed to record");
+        let missing = get_zero_result_searches(&pool, 1000)
+            .await
+            .expect("Failed to fetch");
+        assert!(missing.iter().all(|s| s.query != query));
+        let popular = get_popular_searches(&pool, 1000)
+            .await
+            .expect("Failed to fetch");
+        let stats = popular
+            .iter()
+            .find(|s| s.query == query)
+            .expect("Query missing");
+        assert_eq!(stats.search_count, 3);
+        assert_eq!(stats.last_result_count, 3);
+    }
+
+    #[test]
+    fn test_change_cursor_roundtrip() {
+        let cursor = ChangeCursor { id: 42 };
+        let encoded = cursor.encode();
+        assert_eq!(ChangeCursor::decode(&encoded), Some(cursor));
+
+        assert_eq!(ChangeCursor::decode(""), None);
+        assert_eq!(ChangeCursor::decode("0"), None);
+        assert_eq!(ChangeCursor::decode("-3"), None);
+        assert_eq!(ChangeCursor::decode("123.0.1"), None);
+        assert_eq!(ChangeCursor::decode("abc"), None);
+    }
+
+    #[test]
+    fn test_legacy_change_cursor_decode() {
+        let cursor = LegacyChangeCursor::decode("1735294500123456.1.42").unwrap();
+        assert_eq!(
+            cursor.updated_at,
+            DateTime::parse_from_rfc3339("2024-12-27T10:15:00.123456Z").unwrap()
+        );
+        assert_eq!(cursor.kind, ChangeKind::Episode);
+        assert_eq!(cursor.id, 42);
+
+        assert_eq!(LegacyChangeCursor::decode(""), None);
+        assert_eq!(LegacyChangeCursor::decode("42"), None);
+        assert_eq!(LegacyChangeCursor::decode("123.2.1"), None);
+        assert_eq!(LegacyChangeCursor::decode("123.0.1.9"), None);
+        assert_eq!(LegacyChangeCursor::decode("abc.0.1"), None);
+    }
+
+    // Cache layer tests
+
+    #[test]
+    fn test_default_cache_ttl() {
+        // 1 hour in milliseconds
+        assert_eq!(DEFAULT_CACHE_TTL_MS, 3600 * 1000);
+        assert_eq!(DEFAULT_CACHE_TTL_MS, 3_600_000);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_cache_layer_basic_operations() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let cache_key = "test:cache:basic";
+
+        // Clean up first
+        let _ = delete_cache_entry(&pool, cache_key).await;
+
+        // Test 1: Cache should not be valid when entry doesn't exist
+        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+            .await
+            .expect("Failed to check cache");
+        assert!(
+            !is_valid,
+            "Cache should not be valid when entry doesn't exist"
+        );
+
+        // Test 2: Get timestamp should return None when entry doesn't exist
+        let timestamp = get_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to get timestamp");
+        assert!(
+            timestamp.is_none(),
+            "Timestamp should be None when entry doesn't exist"
+        );
+
+        // Test 3: Update cache timestamp (creates new entry)
+        update_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to update cache timestamp");
+
+        // Test 4: Cache should now be valid (just created)
+        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+            .await
+            .expect("Failed to check cache");
+        assert!(is_valid, "Cache should be valid immediately after creation");
+
+        // Test 5: Get timestamp should return Some value
+        let timestamp = get_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to get timestamp");
+        assert!(timestamp.is_some(), "Timestamp should exist after creation");
+
+        // Test 6: Delete cache entry
+        let deleted = delete_cache_entry(&pool, cache_key)
+            .await
+            .expect("Failed to delete cache entry");
+        assert!(deleted, "Should have deleted the cache entry");
+
+        // Test 7: Cache should not be valid after deletion
+        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+            .await
+            .expect("Failed to check cache");
+        assert!(!is_valid, "Cache should not be valid after deletion");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_cache_freshness_with_short_ttl() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let cache_key = "test:cache:freshness";
+
+        // Clean up first
+        let _ = delete_cache_entry(&pool, cache_key).await;
+
+        // Create cache entry
+        update_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to update cache timestamp");
+
+        // Test with very short TTL (1ms) - should be stale immediately
+        // Note: We use a small delay to ensure the cache is stale
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+        let is_valid = is_cache_valid(&pool, cache_key, 1)
+            .await
+            .expect("Failed to check cache");
+        assert!(!is_valid, "Cache should be stale with 1ms TTL after 10ms");
+
+        // Test with long TTL (1 hour) - should still be fresh
+        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+            .await
+            .expect("Failed to check cache");
+        assert!(is_valid, "Cache should be fresh with 1 hour TTL");
+
+        // Clean up
+        delete_cache_entry(&pool, cache_key)
+            .await
+            .expect("Failed to delete cache entry");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_cache_upsert_behavior() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let cache_key = "test:cache:upsert";
+
+        // Clean up first
+        let _ = delete_cache_entry(&pool, cache_key).await;
+
+        // Create initial cache entry
+        update_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to create cache entry");
+
+        let first_timestamp = get_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to get timestamp")
+            .expect("Timestamp should exist");
+
+        // Wait a bit and update again
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        update_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to update cache entry");
+
+        let second_timestamp = get_cache_timestamp(&pool, cache_key)
+            .await
+            .expect("Failed to get timestamp")
+            .expect("Timestamp should exist");
+
+        // Second timestamp should be newer
+        assert!(
+            second_timestamp > first_timestamp,
+            "Second timestamp should be newer than first"
+        );
+
+        // Clean up
+        delete_cache_entry(&pool, cache_key)
+            .await
+            .expect("Failed to delete cache entry");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_delete_all_cache_entries() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        // Create multiple cache entries
+        let keys = vec!["test:cache:all:1", "test:cache:all:2", "test:cache:all:3"];
+
+        for key in &keys {
+            update_cache_timestamp(&pool, key)
+                .await
+                .expect("Failed to create cache entry");
+        }
+
+        // Verify all entries exist
+        for key in &keys {
+            let is_valid = is_cache_valid(&pool, key, DEFAULT_CACHE_TTL_MS)
+                .await
+                .expect("Failed to check cache");
+            assert!(is_valid, "Cache entry should exist for {}", key);
+        }
+
+        // Delete all cache entries
+        let deleted_count = delete_all_cache_entries(&pool)
+            .await
+            .expect("Failed to delete all cache entries");
+        assert!(deleted_count >= 3, "Should have deleted at least 3 entries");
+
+        // Verify all entries are gone
+        for key in &keys {
+            let is_valid = is_cache_valid(&pool, key, DEFAULT_CACHE_TTL_MS)
+                .await
+                .expect("Failed to check cache");
+            assert!(
+                !is_valid,
+                "Cache entry should not exist for {} after deletion",
+                key
+            );
+        }
+    }
+
+    // Crawled Anime Repository Tests
+
+    // Helper function to create a test CrawledAnime
+    fn create_test_crawled_anime(slug: &str) -> CrawledAnime {
+        CrawledAnime {
+            slug: slug.to_string(),
+            title: format!("Test Anime {}", slug),
+            url: format!("https://example.com/anime/{}/", slug),
+            thumbnail: format!("https://example.com/thumb/{}.jpg", slug),
             status: "Ongoing".to_string(),
-            release_info: "2024-01-01".to_string(),
+            anime_type: "TV".to_string(),
+            episode_status: "12/24".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_crawled_anime() {
+        let anime = create_test_crawled_anime("test-anime");
+        assert_eq!(anime.slug, "test-anime");
+        assert_eq!(anime.title, "Test Anime test-anime");
+        assert!(anime.url.contains("test-anime"));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_crawled_anime_single_save() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slug = "test-single-crawled";
+
+        // Clean up first
+        let _ = delete_crawled_anime(&pool, slug).await;
+
+        // Create
+        let anime = create_test_crawled_anime(slug);
+        save_crawled_anime(&pool, &anime)
+            .await
+            .expect("Failed to save");
+
+        // Read
+        let fetched = get_crawled_anime_by_slug(&pool, slug)
+            .await
+            .expect("Failed to fetch");
+        assert!(fetched.is_some());
+        let fetched = fetched.unwrap();
+        assert_eq!(fetched.slug, slug);
+        assert_eq!(fetched.title, anime.title);
+
+        // Clean up
+        delete_crawled_anime(&pool, slug)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_crawled_anime_batch_save() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slugs = vec!["test-batch-1", "test-batch-2", "test-batch-3"];
+
+        // Clean up first
+        for slug in &slugs {
+            let _ = delete_crawled_anime(&pool, slug).await;
+        }
+
+        // Create batch
+        let anime_list: Vec<CrawledAnime> =
+            slugs.iter().map(|s| create_test_crawled_anime(s)).collect();
+
+        save_crawled_anime_batch(&pool, &anime_list)
+            .await
+            .expect("Failed to save batch");
+
+        // Verify all were saved
+        for slug in &slugs {
+            let fetched = get_crawled_anime_by_slug(&pool, slug)
+                .await
+                .expect("Failed to fetch");
+            assert!(fetched.is_some(), "Should find anime with slug {}", slug);
+        }
+
+        // Clean up
+        for slug in &slugs {
+            delete_crawled_anime(&pool, slug)
+                .await
+                .expect("Failed to delete");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_crawled_anime_upsert() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let slug = "test-upsert-crawled";
+
+        // Clean up first
+        let _ = delete_crawled_anime(&pool, slug).await;
+
+        // Create initial
+        let anime = create_test_crawled_anime(slug);
+        save_crawled_anime(&pool, &anime)
+            .await
+            .expect("Failed to save");
+
+        // Update with same slug
+        let mut updated_anime = create_test_crawled_anime(slug);
+        updated_anime.title = "Updated Title".to_string();
+        updated_anime.status = "Completed".to_string();
+        save_crawled_anime(&pool, &updated_anime)
+            .await
+            .expect("Failed to upsert");
+
+        // Verify update
+        let fetched = get_crawled_anime_by_slug(&pool, slug)
+            .await
+            .expect("Failed to fetch")
+            .expect("Should find anime");
+        assert_eq!(fetched.title, "Updated Title");
+        assert_eq!(fetched.status, "Completed");
+
+        // Verify count is still 1 (not duplicated)
+        let count_before = get_crawled_anime_count(&pool)
+            .await
+            .expect("Failed to count");
+        save_crawled_anime(&pool, &updated_anime)
+            .await
+            .expect("Failed to upsert again");
+        let count_after = get_crawled_anime_count(&pool)
+            .await
+            .expect("Failed to count");
+
+        // Count should not increase (upsert, not insert)
+        assert_eq!(
+            count_before, count_after,
+            "Count should not increase on upsert"
+        );
+
+        // Clean up
+        delete_crawled_anime(&pool, slug)
+            .await
+            .expect("Failed to delete");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_crawled_anime_count() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        // Get initial count
+        let initial_count = get_crawled_anime_count(&pool)
+            .await
+            .expect("Failed to count");
+
+        // Add some anime
+        let slugs = vec!["test-count-1", "test-count-2"];
+        for slug in &slugs {
+            let _ = delete_crawled_anime(&pool, slug).await;
+            let anime = create_test_crawled_anime(slug);
+            save_crawled_anime(&pool, &anime)
+                .await
+                .expect("Failed to save");
+        }
+
+        // Verify count increased
+        let new_count = get_crawled_anime_count(&pool)
+            .await
+            .expect("Failed to count");
+        assert_eq!(new_count, initial_count + 2);
+
+        // Clean up
+        for slug in &slugs {
+            delete_crawled_anime(&pool, slug)
+                .await
+                .expect("Failed to delete");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_crawled_anime_empty_batch() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        // Saving empty batch should succeed without error
+        let empty_list: Vec<CrawledAnime> = vec![];
+        save_crawled_anime_batch(&pool, &empty_list)
+            .await
+            .expect("Empty batch save should succeed");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_create_user_with_email_password() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_user_crud@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create user
+        let user = create_user(
+            &pool,
+            DEFAULT_TENANT_ID,
+            email,
+            "hashed_password",
+            Some("Test User"),
+        )
+        .await
+        .expect("Failed to create user");
+
+        assert_eq!(user.email, email);
+        assert_eq!(user.name, Some("Test User".to_string()));
+        assert!(user.id > 0);
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_create_user_duplicate_email() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_duplicate@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create first user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Try to create duplicate
+        let result = create_user(&pool, DEFAULT_TENANT_ID, email, "another_password", None).await;
+        assert!(matches!(result, Err(RepositoryError::EmailAlreadyExists)));
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_find_user_by_email() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_find_email@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create user
+        let created_user = create_user(
+            &pool,
+            DEFAULT_TENANT_ID,
+            email,
+            "hashed_password",
+            Some("Find Me"),
+        )
+        .await
+        .expect("Failed to create user");
+
+        // Find user
+        let result = find_user_by_email(&pool, DEFAULT_TENANT_ID, email)
+            .await
+            .expect("Failed to find user");
+
+        assert!(result.is_some());
+        let (found_user, password_hash) = result.unwrap();
+        assert_eq!(found_user.email, email);
+        assert_eq!(found_user.name, Some("Find Me".to_string()));
+        assert_eq!(password_hash, Some("hashed_password".to_string()));
+
+        // Find non-existent user
+        let result = find_user_by_email(&pool, DEFAULT_TENANT_ID, "nonexistent@example.com")
+            .await
+            .expect("Failed to query");
+        assert!(result.is_none());
+
+        // Clean up
+        delete_user(&pool, created_user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_create_google_user() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_google@example.com";
+        let google_id = "google_123456";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create Google user
+        let user = create_google_user(
+            &pool,
+            DEFAULT_TENANT_ID,
+            email,
+            google_id,
+            "Google User",
+            Some("https://example.com/avatar.jpg"),
+        )
+        .await
+        .expect("Failed to create Google user");
+
+        assert_eq!(user.email, email);
+        assert_eq!(user.name, Some("Google User".to_string()));
+        assert_eq!(
+            user.avatar,
+            Some("https://example.com/avatar.jpg".to_string())
+        );
+
+        // Find by Google ID
+        let found = find_user_by_google_id(&pool, DEFAULT_TENANT_ID, google_id)
+            .await
+            .expect("Failed to find user");
+        assert!(found.is_some());
+        assert_eq!(found.unwrap().email, email);
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_find_user_by_id() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_find_id@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create user
+        let created_user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Find by ID
+        let found = find_user_by_id(&pool, created_user.id)
+            .await
+            .expect("Failed to find user");
+        assert!(found.is_some());
+        assert_eq!(found.unwrap().email, email);
+
+        // Find non-existent ID
+        let found = find_user_by_id(&pool, 999999)
+            .await
+            .expect("Failed to query");
+        assert!(found.is_none());
+
+        // Clean up
+        delete_user(&pool, created_user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_link_google_account() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_link_google@example.com";
+        let google_id = "google_link_123";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create user with email/password
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Link Google account
+        link_google_account(&pool, user.id, google_id)
+            .await
+            .expect("Failed to link Google account");
+
+        // Verify link
+        let found = find_user_by_google_id(&pool, DEFAULT_TENANT_ID, google_id)
+            .await
+            .expect("Failed to find user");
+        assert!(found.is_some());
+        assert_eq!(found.unwrap().id, user.id);
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_favorites_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_favorites@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Add favorite
+        let favorite = add_favorite(
+            &pool,
+            user.id,
+            "naruto",
+            "Naruto",
+            "https://example.com/naruto.jpg",
+        )
+        .await
+        .expect("Failed to add favorite");
+        assert_eq!(favorite.anime_slug, "naruto");
+        assert_eq!(favorite.anime_title, "Naruto");
+
+        // Check is_favorite
+        let is_fav = is_favorite(&pool, user.id, "naruto")
+            .await
+            .expect("Failed to check favorite");
+        assert!(is_fav);
+
+        let is_not_fav = is_favorite(&pool, user.id, "one-piece")
+            .await
+            .expect("Failed to check favorite");
+        assert!(!is_not_fav);
+
+        // Get favorites
+        let favorites = get_favorites(&pool, user.id)
+            .await
+            .expect("Failed to get favorites");
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].anime_slug, "naruto");
+
+        // Try to add duplicate
+        let result = add_favorite(
+            &pool,
+            user.id,
+            "naruto",
+            "Naruto",
+            "https://example.com/naruto.jpg",
+        )
+        .await;
+        assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+
+        // Remove favorite
+        let removed = remove_favorite(&pool, user.id, "naruto")
+            .await
+            .expect("Failed to remove favorite");
+        assert!(removed);
+
+        // Verify removed
+        let is_fav = is_favorite(&pool, user.id, "naruto")
+            .await
+            .expect("Failed to check favorite");
+        assert!(!is_fav);
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_subscriptions_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_subscriptions@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
         }
+
+        // Create user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Add subscription
+        let subscription = add_subscription(
+            &pool,
+            user.id,
+            "one-piece",
+            "One Piece",
+            "https://example.com/onepiece.jpg",
+        )
+        .await
+        .expect("Failed to add subscription");
+        assert_eq!(subscription.anime_slug, "one-piece");
+        assert_eq!(subscription.anime_title, "One Piece");
+
+        // Check is_subscribed
+        let is_sub = is_subscribed(&pool, user.id, "one-piece")
+            .await
+            .expect("Failed to check subscription");
+        assert!(is_sub);
+
+        let is_not_sub = is_subscribed(&pool, user.id, "naruto")
+            .await
+            .expect("Failed to check subscription");
+        assert!(!is_not_sub);
+
+        // Get subscriptions
+        let subscriptions = get_subscriptions(&pool, user.id)
+            .await
+            .expect("Failed to get subscriptions");
+        assert_eq!(subscriptions.len(), 1);
+        assert_eq!(subscriptions[0].anime_slug, "one-piece");
+
+        // Only verified subscribers are notified
+        let recipients = get_subscriber_recipients(&pool, "one-piece")
+            .await
+            .expect("Failed to get recipients");
+        assert!(recipients.iter().all(|r| r.user_id != user.id));
+        set_email_verified(&pool, user.id, true)
+            .await
+            .expect("Failed to verify email");
+        let recipients = get_subscriber_recipients(&pool, "one-piece")
+            .await
+            .expect("Failed to get recipients");
+        let recipient = recipients
+            .iter()
+            .find(|r| r.user_id == user.id)
+            .expect("Subscriber missing");
+        assert_eq!(recipient.email, email);
+        assert!(recipient.email_notifications);
+
+        // Try to add duplicate
+        let result = add_subscription(
+            &pool,
+            user.id,
+            "one-piece",
+            "One Piece",
+            "https://example.com/onepiece.jpg",
+        )
+        .await;
+        assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+
+        // Remove subscription
+        let removed = remove_subscription(&pool, user.id, "one-piece")
+            .await
+            .expect("Failed to remove subscription");
+        assert!(removed);
+
+        // Verify removed
+        let is_sub = is_subscribed(&pool, user.id, "one-piece")
+            .await
+            .expect("Failed to check subscription");
+        assert!(!is_sub);
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
     }
 
-    // Helper function to create a test CompletedAnime
-    fn create_test_completed_anime(url: &str) -> CompletedAnime {
-        CompletedAnime {
-            slug: "test-completed".to_string(),
-            title: "Test Completed Anime".to_string(),
-            url: url.to_string(),
-            thumbnail: "https://example.com/thumb.jpg".to_string(),
-            anime_type: "TV".to_string(),
-            episode_count: "24".to_string(),
-            status: "Completed".to_string(),
-            posted_by: "Admin".to_string(),
-            posted_at: "2024-01-01".to_string(),
-            series_title: "Test Series".to_string(),
-            series_url: "https://example.com/series".to_string(),
-            genres: vec!["Action".to_string(), "Adventure".to_string()],
-            rating: "8.5".to_string(),
+    #[tokio::test]
+    #[ignore]
+    async fn test_history_crud() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_history@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
         }
+
+        // Create user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Add to history
+        let history = add_to_history(
+            &pool,
+            user.id,
+            "naruto-ep-1",
+            "naruto",
+            "Episode 1",
+            "Naruto",
+            "https://example.com/naruto-ep1.jpg",
+        )
+        .await
+        .expect("Failed to add to history");
+        assert_eq!(history.episode_slug, "naruto-ep-1");
+        assert_eq!(history.anime_slug, "naruto");
+
+        // Get history
+        let history_list = get_history(&pool, user.id)
+            .await
+            .expect("Failed to get history");
+        assert_eq!(history_list.len(), 1);
+        assert_eq!(history_list[0].episode_slug, "naruto-ep-1");
+
+        // Remove from history
+        let removed = remove_from_history(&pool, user.id, "naruto-ep-1")
+            .await
+            .expect("Failed to remove from history");
+        assert!(removed);
+
+        // Verify removed
+        let history_list = get_history(&pool, user.id)
+            .await
+            .expect("Failed to get history");
+        assert!(history_list.is_empty());
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
     }
 
-    // Helper function to create a test AnimeDetail
-    fn create_test_anime_detail() -> AnimeDetail {
-        AnimeDetail {
-            title: "Test Anime".to_string(),
-            alternate_titles: "Test Alt Title".to_string(),
-            poster: "https://example.com/poster.jpg".to_string(),
-            rating: "8.5".to_string(),
-            trailer_url: "https://youtube.com/watch?v=test".to_string(),
-            status: "Ongoing".to_string(),
-            studio: "Test Studio".to_string(),
-            release_date: "2024-01-01".to_string(),
-            duration: "24 min".to_string(),
-            season: "Winter 2024".to_string(),
-            anime_type: "TV".to_string(),
-            total_episodes: "24".to_string(),
-            director: "Test Director".to_string(),
-            casts: vec!["Actor 1".to_string(), "Actor 2".to_string()],
-            genres: vec!["Action".to_string(), "Adventure".to_string()],
-            synopsis: "Test synopsis".to_string(),
-            episodes: vec![
-                Episode {
-                    slug: "ep1".to_string(),
-                    number: "1".to_string(),
-                    title: "Episode 1".to_string(),
-                    url: "https://example.com/ep1".to_string(),
-                    release_date: "2024-01-01".to_string(),
-                },
-                Episode {
-                    slug: "ep2".to_string(),
-                    number: "2".to_string(),
-                    title: "Episode 2".to_string(),
-                    url: "https://example.com/ep2".to_string(),
-                    release_date: "2024-01-08".to_string(),
-                },
-            ],
+    #[tokio::test]
+    #[ignore]
+    async fn test_continue_watching() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_continue_watching@example.com";
+        let slug = "test-continue-watching";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
         }
+        let _ = delete_anime_detail(&pool, slug).await;
+
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Episodes are listed newest first, as on the site
+        let mut detail = create_test_anime_detail();
+        detail.episodes = ["3", "2", "1"]
+            .iter()
+            .map(|n| Episode {
+                slug: format!("{}-ep{}", slug, n),
+                number: n.to_string(),
+                title: format!("Episode {}", n),
+                url: format!("https://example.com/{}-ep{}/", slug, n),
+                release_date: String::new(),
+                released_at_iso: None,
+            })
+            .collect();
+        save_anime_detail_with_episodes(&pool, slug, &detail)
+            .await
+            .expect("Failed to save anime");
+
+        // Watched episode 1: next is episode 2
+        add_to_history(&pool, user.id, &format!("{}-ep1", slug), slug, "", "", "")
+            .await
+            .expect("Failed to add to history");
+        let entries = get_continue_watching(&pool, user.id, 20)
+            .await
+            .expect("Failed to get continue watching");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].next_episode.number, "2");
+        assert_eq!(entries[0].anime_title, "Test Anime");
+        assert!(!entries[0].new_episode);
+
+        // Episode 2 already watched earlier: skipped
+        add_to_history(&pool, user.id, &format!("{}-ep2", slug), slug, "", "", "")
+            .await
+            .expect("Failed to add to history");
+        add_to_history(&pool, user.id, &format!("{}-ep1", slug), slug, "", "", "")
+            .await
+            .expect("Failed to add to history");
+        let entries = get_continue_watching(&pool, user.id, 20)
+            .await
+            .expect("Failed to get continue watching");
+        assert_eq!(entries[0].next_episode.number, "3");
+
+        // Caught up: nothing to continue
+        add_to_history(&pool, user.id, &format!("{}-ep3", slug), slug, "", "", "")
+            .await
+            .expect("Failed to add to history");
+        let entries = get_continue_watching(&pool, user.id, 20)
+            .await
+            .expect("Failed to get continue watching");
+        assert!(entries.is_empty());
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to delete");
     }
 
-    // Helper function to create a test VideoSource
-    fn create_test_video_source(server: &str, quality: &str) -> VideoSource {
-        VideoSource {
-            server: server.to_string(),
-            quality: quality.to_string(),
-            url: format!("https://example.com/video-{}-{}.mp4", server, quality),
+    #[tokio::test]
+    #[ignore]
+    async fn test_watched_episodes() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_watched_episodes@example.com";
+        let slug = "test-watched-episodes";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
         }
-    }
+        let _ = delete_anime_detail(&pool, slug).await;
 
-    #[test]
-    fn test_create_anime_update() {
-        let update = create_test_anime_update("https://example.com/ep1");
-        assert_eq!(update.title, "Test Episode");
-        assert_eq!(update.episode_url, "https://example.com/ep1");
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        let mut detail = create_test_anime_detail();
+        detail.episodes = ["1", "2", "3", "4"]
+            .iter()
+            .map(|n| Episode {
+                slug: format!("{}-ep{}", slug, n),
+                number: n.to_string(),
+                title: format!("Episode {}", n),
+                url: format!("https://example.com/{}-ep{}/", slug, n),
+                release_date: String::new(),
+                released_at_iso: None,
+            })
+            .collect();
+        save_anime_detail_with_episodes(&pool, slug, &detail)
+            .await
+            .expect("Failed to save anime");
+
+        // Unknown anime
+        let missing = get_watch_progress(&pool, user.id, "no-such-anime")
+            .await
+            .expect("Failed to get progress");
+        assert!(missing.is_none());
+
+        // Mark up to episode 2
+        let marked =
+            mark_episodes_watched(&pool, user.id, slug, &EpisodeSelection::UpTo(2.0), true)
+                .await
+                .expect("Failed to mark watched");
+        assert_eq!(marked, 2);
+
+        // Progress shows in favorites
+        add_favorite(&pool, user.id, slug, "Test Anime", "")
+            .await
+            .expect("Failed to add favorite");
+        let favorites = get_favorites(&pool, user.id)
+            .await
+            .expect("Failed to get favorites");
+        assert_eq!(favorites[0].watched_count, 2);
+        assert_eq!(favorites[0].total_episodes, 4);
+
+        // Mark the whole season, then unmark one episode
+        mark_episodes_watched(&pool, user.id, slug, &EpisodeSelection::All, true)
+            .await
+            .expect("Failed to mark watched");
+        let episodes =
+            EpisodeSelection::Episodes(vec![format!("{}-ep3", slug), "not-an-episode".to_string()]);
+        let marked = mark_episodes_watched(&pool, user.id, slug, &episodes, false)
+            .await
+            .expect("Failed to mark unwatched");
+        assert_eq!(marked, 1);
+
+        let progress = get_watch_progress(&pool, user.id, slug)
+            .await
+            .expect("Failed to get progress")
+            .unwrap();
+        assert_eq!(progress.watched_count, 3);
+        assert_eq!(progress.total_episodes, 4);
+        assert_eq!(
+            progress.watched_episodes,
+            vec![
+                format!("{}-ep1", slug),
+                format!("{}-ep2", slug),
+                format!("{}-ep4", slug)
+            ]
+        );
+
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to delete");
     }
 
-    #[test]
-    fn test_create_completed_anime() {
-        let anime = create_test_completed_anime("https://example.com/anime1");
-        assert_eq!(anime.title, "Test Completed Anime");
-        assert_eq!(anime.url, "https://example.com/anime1");
-        assert_eq!(anime.genres.len(), 2);
-    }
+    #[tokio::test]
+    #[ignore]
+    async fn test_history_update_timestamp_on_rewatch() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = "test_history_rewatch@example.com";
+
+        // Clean up first
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        // Create user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+
+        // Add to history first time
+        let first_watch = add_to_history(
+            &pool,
+            user.id,
+            "naruto-ep-1",
+            "naruto",
+            "Episode 1",
+            "Naruto",
+            "https://example.com/naruto-ep1.jpg",
+        )
+        .await
+        .expect("Failed to add to history");
+
+        // Wait a bit
+        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+        // Re-watch (add again)
+        let second_watch = add_to_history(
+            &pool,
+            user.id,
+            "naruto-ep-1",
+            "naruto",
+            "Episode 1 - Updated",
+            "Naruto",
+            "https://example.com/naruto-ep1.jpg",
+        )
+        .await
+        .expect("Failed to update history");
 
-    #[test]
-    fn test_create_anime_detail() {
-        let detail = create_test_anime_detail();
-        assert_eq!(detail.title, "Test Anime");
-        assert_eq!(detail.episodes.len(), 2);
-        assert_eq!(detail.genres.len(), 2);
-        assert_eq!(detail.casts.len(), 2);
-    }
+        // Timestamp should be updated
+        assert!(second_watch.watched_at > first_watch.watched_at);
+        // Title should be updated
+        assert_eq!(second_watch.episode_title, "Episode 1 - Updated");
 
-    #[test]
-    fn test_create_video_source() {
-        let source = create_test_video_source("SOKUJA", "720p");
-        assert_eq!(source.server, "SOKUJA");
-        assert_eq!(source.quality, "720p");
-        assert!(source.url.contains("SOKUJA"));
-        assert!(source.url.contains("720p"));
-    }
+        // Should still be only one entry
+        let history_list = get_history(&pool, user.id)
+            .await
+            .expect("Failed to get history");
+        assert_eq!(history_list.len(), 1);
 
-    // Integration tests that require a database connection
-    // These are marked with #[ignore] and can be run with `cargo test -- --ignored`
+        // Clean up
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+    }
 
     #[tokio::test]
     #[ignore]
-    async fn test_anime_updates_crud() {
+    async fn test_history_sorted_by_most_recent() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
+        let email = "test_history_sort@example.com";
+
         // Clean up first
-        let _ = delete_all_anime_updates(&pool).await;
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
 
-        // Create
-        let updates = vec![
-            create_test_anime_update("https://test.com/ep1"),
-            create_test_anime_update("https://test.com/ep2"),
-        ];
-        save_anime_updates(&pool, &updates)
+        // Create user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to save");
+            .expect("Failed to create user");
 
-        // Read
-        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
-        assert!(fetched.len() >= 2);
+        // Add multiple episodes
+        add_to_history(&pool, user.id, "ep-1", "anime", "Episode 1", "Anime", "")
+            .await
+            .expect("Failed to add");
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
 
-        // Update (upsert with same URL)
-        let mut updated = create_test_anime_update("https://test.com/ep1");
-        updated.title = "Updated Title".to_string();
-        save_anime_updates(&pool, &[updated])
+        add_to_history(&pool, user.id, "ep-2", "anime", "Episode 2", "Anime", "")
             .await
-            .expect("Failed to update");
+            .expect("Failed to add");
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
 
-        // Verify update
-        let fetched = get_anime_updates(&pool).await.expect("Failed to fetch");
-        let found = fetched
-            .iter()
-            .find(|u| u.episode_url == "https://test.com/ep1");
-        assert!(found.is_some());
-        assert_eq!(found.unwrap().title, "Updated Title");
+        add_to_history(&pool, user.id, "ep-3", "anime", "Episode 3", "Anime", "")
+            .await
+            .expect("Failed to add");
+
+        // Get history - should be sorted by most recent first
+        let history_list = get_history(&pool, user.id)
+            .await
+            .expect("Failed to get history");
+
+        assert_eq!(history_list.len(), 3);
+        assert_eq!(history_list[0].episode_slug, "ep-3"); // Most recent
+        assert_eq!(history_list[1].episode_slug, "ep-2");
+        assert_eq!(history_list[2].episode_slug, "ep-1"); // Oldest
 
         // Clean up
-        delete_all_anime_updates(&pool)
+        delete_user(&pool, user.id)
             .await
-            .expect("Failed to delete");
+            .expect("Failed to delete user");
     }
 
     #[tokio::test]
     #[ignore]
-    async fn test_completed_anime_crud() {
+    async fn test_clear_history() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
+        let email = "test_clear_history@example.com";
+
         // Clean up first
-        let _ = delete_all_completed_anime(&pool).await;
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
 
-        // Create
-        let anime_list = vec![
-            create_test_completed_anime("https://test.com/anime1"),
-            create_test_completed_anime("https://test.com/anime2"),
-        ];
-        save_completed_anime(&pool, &anime_list)
+        // Create user
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to save");
+            .expect("Failed to create user");
 
-        // Read
-        let fetched = get_completed_anime(&pool).await.expect("Failed to fetch");
-        assert!(fetched.len() >= 2);
+        // Add multiple episodes
+        add_to_history(&pool, user.id, "ep-1", "anime", "Episode 1", "Anime", "")
+            .await
+            .expect("Failed to add");
+        add_to_history(&pool, user.id, "ep-2", "anime", "Episode 2", "Anime", "")
+            .await
+            .expect("Failed to add");
+
+        // Clear history
+        let deleted = clear_history(&pool, user.id)
+            .await
+            .expect("Failed to clear history");
+        assert_eq!(deleted, 2);
+
+        // Verify cleared
+        let history_list = get_history(&pool, user.id)
+            .await
+            .expect("Failed to get history");
+        assert!(history_list.is_empty());
 
         // Clean up
-        delete_all_completed_anime(&pool)
+        delete_user(&pool, user.id)
             .await
-            .expect("Failed to delete");
+            .expect("Failed to delete user");
     }
 
     #[tokio::test]
     #[ignore]
-    async fn test_anime_detail_with_episodes_crud() {
+    async fn test_maintenance() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let slug = "test-anime-slug";
-
-        // Clean up first
-        let _ = delete_anime_detail(&pool, slug).await;
-
-        // Create with episodes
-        let detail = create_test_anime_detail();
+        let slug = "test-maintenance";
+        let episode_url = "https://example.com/test-maintenance-ep1/";
+        let orphan_url = "https://example.com/test-maintenance-gone/";
+
+        let mut detail = create_test_anime_detail();
+        detail.episodes = vec![Episode {
+            slug: "test-maintenance-ep1".to_string(),
+            number: "1".to_string(),
+            title: "Episode 1".to_string(),
+            url: episode_url.to_string(),
+            release_date: String::new(),
+            released_at_iso: None,
+        }];
         save_anime_detail_with_episodes(&pool, slug, &detail)
             .await
-            .expect("Failed to save");
-
-        // Read
-        let fetched = get_anime_detail(&pool, slug)
+            .expect("Failed to save anime");
+        let sources = vec![create_test_video_source("SOKUJA", "720p")];
+        save_video_sources(&pool, episode_url, &sources)
             .await
-            .expect("Failed to fetch");
-        assert!(fetched.is_some());
-        let fetched = fetched.unwrap();
-        assert_eq!(fetched.title, "Test Anime");
-        assert_eq!(fetched.episodes.len(), 2);
-
-        // Update (upsert)
-        let mut updated_detail = create_test_anime_detail();
-        updated_detail.title = "Updated Anime Title".to_string();
-        save_anime_detail_with_episodes(&pool, slug, &updated_detail)
+            .expect("Failed to save sources");
+        save_video_sources(&pool, orphan_url, &sources)
             .await
-            .expect("Failed to update");
+            .expect("Failed to save orphan sources");
 
-        // Verify update
-        let fetched = get_anime_detail(&pool, slug)
+        // Only sources without a stored episode are removed
+        let deleted = delete_orphaned_video_sources(&pool)
             .await
-            .expect("Failed to fetch");
-        assert!(fetched.is_some());
-        assert_eq!(fetched.unwrap().title, "Updated Anime Title");
+            .expect("Failed to delete orphaned sources");
+        assert!(deleted >= 1);
+        assert!(get_video_sources(&pool, orphan_url)
+            .await
+            .expect("Failed to fetch")
+            .is_empty());
+        assert_eq!(
+            get_video_sources(&pool, episode_url)
+                .await
+                .expect("Failed to fetch")
+                .len(),
+            1
+        );
+        delete_orphaned_episodes(&pool)
+            .await
+            .expect("Failed to delete orphaned episodes");
+
+        vacuum_tables(&pool).await.expect("Failed to vacuum");
+        reindex_tables(&pool).await.expect("Failed to reindex");
 
         // Clean up
+        let _ = delete_video_sources(&pool, episode_url).await;
         delete_anime_detail(&pool, slug)
             .await
-            .expect("Failed to delete");
+            .expect("Failed to delete anime");
     }
 
     #[tokio::test]
     #[ignore]
-    async fn test_video_sources_crud() {
+    async fn test_integrity_checks() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let episode_url = "https://test.com/episode/test-ep";
+        let email = "test_integrity_checks@example.com";
+        let slug = "test-integrity-missing";
+        let orphan_url = "https://example.com/test-integrity-gone/";
 
         // Clean up first
-        let _ = delete_video_sources(&pool, episode_url).await;
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+        let _ = delete_crawled_anime(&pool, slug).await;
+        let _ = delete_video_sources(&pool, orphan_url).await;
 
-        // Create
-        let sources = vec![
-            create_test_video_source("SOKUJA", "480p"),
-            create_test_video_source("SOKUJA", "720p"),
-            create_test_video_source("SOKUJA", "1080p"),
-        ];
-        save_video_sources(&pool, episode_url, &sources)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to save");
-
-        // Read
-        let fetched = get_video_sources(&pool, episode_url)
+            .expect("Failed to create user");
+        add_favorite(&pool, user.id, slug, "Missing Anime", "")
             .await
-            .expect("Failed to fetch");
-        assert_eq!(fetched.len(), 3);
+            .expect("Failed to add favorite");
+        save_video_sources(
+            &pool,
+            orphan_url,
+            &[create_test_video_source("SOKUJA", "720p")],
+        )
+        .await
+        .expect("Failed to save sources");
 
-        // Update (replace all sources)
-        let new_sources = vec![create_test_video_source("NEW_SERVER", "720p")];
-        save_video_sources(&pool, episode_url, &new_sources)
+        let dangling = find_dangling_favorites(&pool, 10_000)
             .await
-            .expect("Failed to update");
+            .expect("Failed to find dangling favorites");
+        assert!(dangling.iter().any(|g| g.parent == slug && g.count == 1));
+        let orphaned = find_orphaned_video_sources(&pool, 10_000)
+            .await
+            .expect("Failed to find orphaned sources");
+        assert!(orphaned
+            .iter()
+            .any(|g| g.parent == orphan_url && g.count == 1));
+        find_orphaned_episodes(&pool, 10)
+            .await
+            .expect("Failed to find orphaned episodes");
 
-        // Verify update
-        let fetched = get_video_sources(&pool, episode_url)
+        // A crawled catalog entry resolves the favorite
+        save_crawled_anime(
+            &pool,
+            &CrawledAnime {
+                slug: slug.to_string(),
+                title: "Missing Anime".to_string(),
+                url: String::new(),
+                thumbnail: String::new(),
+                status: String::new(),
+                anime_type: String::new(),
+                episode_status: String::new(),
+            },
+        )
+        .await
+        .expect("Failed to save crawled anime");
+        let dangling = find_dangling_favorites(&pool, 10_000)
             .await
-            .expect("Failed to fetch");
-        assert_eq!(fetched.len(), 1);
-        assert_eq!(fetched[0].server, "NEW_SERVER");
+            .expect("Failed to find dangling favorites");
+        assert!(!dangling.iter().any(|g| g.parent == slug));
 
         // Clean up
-        delete_video_sources(&pool, episode_url)
+        let _ = delete_video_sources(&pool, orphan_url).await;
+        let _ = delete_crawled_anime(&pool, slug).await;
+        delete_user(&pool, user.id)
             .await
-            .expect("Failed to delete");
+            .expect("Failed to delete user");
     }
 
-    // Cache layer tests
+    #[tokio::test]
+    #[ignore]
+    async fn test_get_stored_episode_numbers() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
 
-    #[test]
-    fn test_default_cache_ttl() {
-        // 1 hour in milliseconds
-        assert_eq!(DEFAULT_CACHE_TTL_MS, 3600 * 1000);
-        assert_eq!(DEFAULT_CACHE_TTL_MS, 3_600_000);
+        let slug = "test-episode-gaps";
+        let _ = delete_anime_detail(&pool, slug).await;
+
+        let mut detail = create_test_anime_detail();
+        detail.episodes = ["1", "2", "3", "5"]
+            .iter()
+            .map(|number| Episode {
+                slug: format!("test-episode-gaps-{}", number),
+                number: number.to_string(),
+                title: format!("Episode {}", number),
+                url: format!("https://example.com/test-episode-gaps-{}/", number),
+                release_date: String::new(),
+                released_at_iso: None,
+            })
+            .collect();
+        save_anime_detail_with_episodes(&pool, slug, &detail)
+            .await
+            .expect("Failed to save anime");
+
+        let stored = get_stored_episode_numbers(&pool, "test_episode_gaps")
+            .await
+            .expect("Failed to get episode numbers");
+        let anime = stored
+            .iter()
+            .find(|anime| anime.slug == slug)
+            .expect("Anime not listed");
+        let mut numbers = anime.numbers.clone();
+        numbers.sort();
+        assert_eq!(numbers, vec!["1", "2", "3", "5"]);
+        assert_eq!(anime.total_episodes, "24");
+        assert_eq!(anime.status, "Ongoing");
+        assert!(!anime.job_pending);
+
+        // Only jobs of the given type for this slug count
+        enqueue_job(
+            &pool,
+            "test",
+            "test_episode_gaps",
+            &serde_json::json!({ "slug": slug }).to_string(),
+            1,
+        )
+        .await
+        .expect("Failed to enqueue job");
+        let stored = get_stored_episode_numbers(&pool, "test_episode_gaps")
+            .await
+            .expect("Failed to get episode numbers");
+        assert!(stored
+            .iter()
+            .any(|anime| anime.slug == slug && anime.job_pending));
+        assert!(stored
+            .iter()
+            .all(|anime| anime.slug == slug || !anime.job_pending));
+
+        // Clean up
+        sqlx::query("DELETE FROM jobs WHERE job_type = 'test_episode_gaps'")
+            .execute(&pool)
+            .await
+            .expect("Failed to delete jobs");
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to delete anime");
     }
 
     #[tokio::test]
     #[ignore]
-    async fn test_cache_layer_basic_operations() {
+    async fn test_merge_anime() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let cache_key = "test:cache:basic";
+        let email = "test_merge_anime@example.com";
+        let from = "test-merge-duplicate";
+        let into = "test-merge-kept";
 
         // Clean up first
-        let _ = delete_cache_entry(&pool, cache_key).await;
-
-        // Test 1: Cache should not be valid when entry doesn't exist
-        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+        let _ = delete_anime_detail(&pool, from).await;
+        let _ = delete_anime_detail(&pool, into).await;
+
+        let episode = |slug: &str, n: &str| Episode {
+            slug: format!("{}-ep{}", slug, n),
+            number: n.to_string(),
+            title: format!("Episode {}", n),
+            url: format!("https://example.com/{}-ep{}/", slug, n),
+            release_date: String::new(),
+            released_at_iso: None,
+        };
+        let mut detail = create_test_anime_detail();
+        detail.episodes = vec![episode(from, "1")];
+        save_anime_detail_with_episodes(&pool, from, &detail)
             .await
-            .expect("Failed to check cache");
-        assert!(
-            !is_valid,
-            "Cache should not be valid when entry doesn't exist"
-        );
-
-        // Test 2: Get timestamp should return None when entry doesn't exist
-        let timestamp = get_cache_timestamp(&pool, cache_key)
+            .expect("Failed to save duplicate");
+        detail.episodes = vec![episode(into, "2")];
+        save_anime_detail_with_episodes(&pool, into, &detail)
             .await
-            .expect("Failed to get timestamp");
-        assert!(
-            timestamp.is_none(),
-            "Timestamp should be None when entry doesn't exist"
-        );
+            .expect("Failed to save kept anime");
 
-        // Test 3: Update cache timestamp (creates new entry)
-        update_cache_timestamp(&pool, cache_key)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to update cache timestamp");
+            .expect("Failed to create user");
+        add_favorite(&pool, user.id, from, "Test Anime", "")
+            .await
+            .expect("Failed to add favorite");
+        add_favorite(&pool, user.id, into, "Test Anime", "")
+            .await
+            .expect("Failed to add favorite");
+        add_subscription(&pool, user.id, from, "Test Anime", "")
+            .await
+            .expect("Failed to add subscription");
 
-        // Test 4: Cache should now be valid (just created)
-        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+        // Merging into an anime that isn't stored does nothing
+        assert!(merge_anime(&pool, from, "test-merge-missing")
             .await
-            .expect("Failed to check cache");
-        assert!(is_valid, "Cache should be valid immediately after creation");
+            .expect("Failed to merge")
+            .is_none());
 
-        // Test 5: Get timestamp should return Some value
-        let timestamp = get_cache_timestamp(&pool, cache_key)
+        let result = merge_anime(&pool, from, into)
             .await
-            .expect("Failed to get timestamp");
-        assert!(timestamp.is_some(), "Timestamp should exist after creation");
+            .expect("Failed to merge")
+            .expect("Kept anime not found");
+        assert_eq!(result.episodes, 1);
+        assert_eq!(result.favorites, 0);
+        assert_eq!(result.subscriptions, 1);
 
-        // Test 6: Delete cache entry
-        let deleted = delete_cache_entry(&pool, cache_key)
+        assert!(get_anime_detail(&pool, from)
             .await
-            .expect("Failed to delete cache entry");
-        assert!(deleted, "Should have deleted the cache entry");
+            .expect("Failed to get anime")
+            .is_none());
+        assert_eq!(get_episodes(&pool, into).await.expect("Failed").len(), 2);
+        assert_eq!(
+            get_favorites(&pool, user.id).await.expect("Failed").len(),
+            1
+        );
+        let subscriptions = get_subscriptions(&pool, user.id).await.expect("Failed");
+        assert_eq!(subscriptions[0].anime_slug, into);
+        assert_eq!(
+            resolve_anime_alias(&pool, from).await.expect("Failed"),
+            Some(into.to_string())
+        );
+        assert_eq!(
+            resolve_anime_alias(&pool, into).await.expect("Failed"),
+            None
+        );
 
-        // Test 7: Cache should not be valid after deletion
-        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+        // Clean up
+        sqlx::query("DELETE FROM anime_aliases WHERE alias_slug = $1")
+            .bind(from)
+            .execute(&pool)
             .await
-            .expect("Failed to check cache");
-        assert!(!is_valid, "Cache should not be valid after deletion");
+            .expect("Failed to delete alias");
+        delete_anime_detail(&pool, into)
+            .await
+            .expect("Failed to delete anime");
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_cache_freshness_with_short_ttl() {
+    #[ignore] // Requires database connection
+    async fn test_record_anime_redirect() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let cache_key = "test:cache:freshness";
+        let older = "test-redirect-older";
+        let from = "test-redirect-old";
+        let into = "test-redirect-new";
+        let into_url = "https://example.com/anime/test-redirect-new/";
 
         // Clean up first
-        let _ = delete_cache_entry(&pool, cache_key).await;
-
-        // Create cache entry
-        update_cache_timestamp(&pool, cache_key)
+        for slug in [from, into] {
+            let _ = delete_crawled_anime(&pool, slug).await;
+        }
+        sqlx::query("DELETE FROM anime_aliases WHERE alias_slug = ANY($1)")
+            .bind(vec![older, from, into])
+            .execute(&pool)
             .await
-            .expect("Failed to update cache timestamp");
+            .expect("Failed to delete aliases");
 
-        // Test with very short TTL (1ms) - should be stale immediately
-        // Note: We use a small delay to ensure the cache is stale
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        save_crawled_anime(&pool, &create_test_crawled_anime(from))
+            .await
+            .expect("Failed to save crawled anime");
+        record_anime_redirect(
+            &pool,
+            older,
+            from,
+            &format!("https://example.com/anime/{}/", from),
+        )
+        .await
+        .expect("Failed to record redirect");
 
-        let is_valid = is_cache_valid(&pool, cache_key, 1)
+        record_anime_redirect(&pool, from, into, into_url)
             .await
-            .expect("Failed to check cache");
-        assert!(!is_valid, "Cache should be stale with 1ms TTL after 10ms");
+            .expect("Failed to record redirect");
 
-        // Test with long TTL (1 hour) - should still be fresh
-        let is_valid = is_cache_valid(&pool, cache_key, DEFAULT_CACHE_TTL_MS)
+        assert!(get_crawled_anime_by_slug(&pool, from)
             .await
-            .expect("Failed to check cache");
-        assert!(is_valid, "Cache should be fresh with 1 hour TTL");
+            .expect("Failed to get crawled anime")
+            .is_none());
+        let moved = get_crawled_anime_by_slug(&pool, into)
+            .await
+            .expect("Failed to get crawled anime")
+            .expect("Crawled anime not moved");
+        assert_eq!(moved.url, into_url);
+        assert_eq!(moved.title, format!("Test Anime {}", from));
+        for alias in [older, from] {
+            assert_eq!(
+                resolve_anime_alias(&pool, alias).await.expect("Failed"),
+                Some(into.to_string())
+            );
+        }
+
+        // Moving back undoes the alias of the slug moved back to
+        record_anime_redirect(
+            &pool,
+            into,
+            from,
+            &format!("https://example.com/anime/{}/", from),
+        )
+        .await
+        .expect("Failed to record redirect");
+        assert_eq!(
+            resolve_anime_alias(&pool, from).await.expect("Failed"),
+            None
+        );
+        assert_eq!(
+            resolve_anime_alias(&pool, into).await.expect("Failed"),
+            Some(from.to_string())
+        );
 
         // Clean up
-        delete_cache_entry(&pool, cache_key)
+        delete_crawled_anime(&pool, from)
             .await
-            .expect("Failed to delete cache entry");
+            .expect("Failed to delete crawled anime");
+        sqlx::query("DELETE FROM anime_aliases WHERE alias_slug = ANY($1)")
+            .bind(vec![older, from, into])
+            .execute(&pool)
+            .await
+            .expect("Failed to delete aliases");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_cache_upsert_behavior() {
+    #[ignore] // Requires database connection
+    async fn test_roles() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let cache_key = "test:cache:upsert";
+        let email = "test_roles@example.com";
+        let role_name = "test-comment-moderator";
 
         // Clean up first
-        let _ = delete_cache_entry(&pool, cache_key).await;
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+        for role in get_roles(&pool).await.expect("Failed to get roles") {
+            if role.name == role_name {
+                let _ = delete_role(&pool, role.id).await;
+            }
+        }
 
-        // Create initial cache entry
-        update_cache_timestamp(&pool, cache_key)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to create cache entry");
-
-        let first_timestamp = get_cache_timestamp(&pool, cache_key)
+            .expect("Failed to create user");
+        let role = create_role(
+            &pool,
+            role_name,
+            "Moderates comments",
+            &["comments:moderate".to_string()],
+        )
+        .await
+        .expect("Failed to create role");
+        assert_eq!(role.permissions, vec!["comments:moderate".to_string()]);
+        assert!(matches!(
+            create_role(&pool, role_name, "", &[]).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        assert!(!user_has_permission(&pool, user.id, "comments:moderate")
             .await
-            .expect("Failed to get timestamp")
-            .expect("Timestamp should exist");
-
-        // Wait a bit and update again
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+            .unwrap());
+        assert!(assign_role(&pool, user.id, role.id).await.unwrap());
+        assert!(assign_role(&pool, user.id, role.id).await.unwrap());
+        assert!(!assign_role(&pool, user.id, -1).await.unwrap());
+        assert!(user_has_permission(&pool, user.id, "comments:moderate")
+            .await
+            .unwrap());
+        assert!(!user_has_permission(&pool, user.id, "crawler:run")
+            .await
+            .unwrap());
 
-        update_cache_timestamp(&pool, cache_key)
+        let user_roles = get_user_roles(&pool, user.id)
             .await
-            .expect("Failed to update cache entry");
+            .unwrap()
+            .expect("User should exist");
+        assert!(!user_roles.is_admin);
+        assert_eq!(user_roles.roles.len(), 1);
+        assert_eq!(user_roles.roles[0].name, role_name);
+        assert_eq!(
+            user_roles.permissions,
+            vec!["comments:moderate".to_string()]
+        );
 
-        let second_timestamp = get_cache_timestamp(&pool, cache_key)
+        let updated = update_role(&pool, role.id, None, Some(&["crawler:run".to_string()]))
             .await
-            .expect("Failed to get timestamp")
-            .expect("Timestamp should exist");
+            .unwrap()
+            .expect("Role should exist");
+        assert_eq!(updated.description, "Moderates comments");
+        assert!(user_has_permission(&pool, user.id, "crawler:run")
+            .await
+            .unwrap());
 
-        // Second timestamp should be newer
-        assert!(
-            second_timestamp > first_timestamp,
-            "Second timestamp should be newer than first"
-        );
+        assert!(unassign_role(&pool, user.id, role.id).await.unwrap());
+        assert!(!unassign_role(&pool, user.id, role.id).await.unwrap());
+        assert!(!user_has_permission(&pool, user.id, "crawler:run")
+            .await
+            .unwrap());
 
-        // Clean up
-        delete_cache_entry(&pool, cache_key)
+        assert!(delete_role(&pool, role.id).await.unwrap());
+        assert!(update_role(&pool, role.id, Some("gone"), None)
             .await
-            .expect("Failed to delete cache entry");
+            .unwrap()
+            .is_none());
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_delete_all_cache_entries() {
+    #[ignore] // Requires database connection
+    async fn test_moderation() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        // Create multiple cache entries
-        let keys = vec!["test:cache:all:1", "test:cache:all:2", "test:cache:all:3"];
+        let author_email = "test_moderation_author@example.com";
+        let reporter_email = "test_moderation_reporter@example.com";
 
-        for key in &keys {
-            update_cache_timestamp(&pool, key)
-                .await
-                .expect("Failed to create cache entry");
+        // Clean up first
+        for email in [author_email, reporter_email] {
+            if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+                let _ = delete_user(&pool, user.id).await;
+            }
         }
 
-        // Verify all entries exist
-        for key in &keys {
-            let is_valid = is_cache_valid(&pool, key, DEFAULT_CACHE_TTL_MS)
+        let author = create_user(
+            &pool,
+            DEFAULT_TENANT_ID,
+            author_email,
+            "hashed_password",
+            None,
+        )
+        .await
+        .expect("Failed to create author");
+        let reporter = create_user(
+            &pool,
+            DEFAULT_TENANT_ID,
+            reporter_email,
+            "hashed_password",
+            None,
+        )
+        .await
+        .expect("Failed to create reporter");
+        let since = Utc::now() - chrono::Duration::days(1);
+
+        let (item, reported) =
+            report_content(&pool, "test", 1, author.id, "rude", reporter.id, "Rude")
                 .await
-                .expect("Failed to check cache");
-            assert!(is_valid, "Cache entry should exist for {}", key);
-        }
+                .expect("Failed to report");
+        assert!(reported);
+        assert_eq!(item.status, ModerationStatus::Pending);
+        assert_eq!(item.report_count, 1);
+        let (again, reported) = report_content(
+            &pool,
+            "test",
+            1,
+            author.id,
+            "rude",
+            reporter.id,
+            "Still rude",
+        )
+        .await
+        .unwrap();
+        assert!(!reported);
+        assert_eq!(again.id, item.id);
+        assert_eq!(again.report_count, 1);
 
-        // Delete all cache entries
-        let deleted_count = delete_all_cache_entries(&pool)
+        let queue = get_moderation_queue(&pool, ModerationStatus::Pending, 500)
             .await
-            .expect("Failed to delete all cache entries");
-        assert!(deleted_count >= 3, "Should have deleted at least 3 entries");
-
-        // Verify all entries are gone
-        for key in &keys {
-            let is_valid = is_cache_valid(&pool, key, DEFAULT_CACHE_TTL_MS)
+            .unwrap();
+        assert!(queue.iter().any(|queued| queued.id == item.id));
+        let reports = get_content_reports(&pool, item.id).await.unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].reason, "Rude");
+
+        let approved =
+            approve_moderation_item(&pool, item.id, ModerationStatus::Pending, reporter.id, None)
                 .await
-                .expect("Failed to check cache");
-            assert!(
-                !is_valid,
-                "Cache entry should not exist for {} after deletion",
-                key
-            );
-        }
-    }
+                .unwrap()
+                .expect("Item should be pending");
+        assert_eq!(approved.status, ModerationStatus::Approved);
+        assert!(approve_moderation_item(
+            &pool,
+            item.id,
+            ModerationStatus::Pending,
+            reporter.id,
+            None
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+        let mut uow = super::super::UnitOfWork::begin(&pool).await.unwrap();
+        let (removed, strike) = remove_moderation_item(
+            uow.conn(),
+            item.id,
+            ModerationStatus::Approved,
+            reporter.id,
+            Some("Rude"),
+        )
+        .await
+        .unwrap()
+        .expect("Item should be approved");
+        uow.commit().await.unwrap();
+        assert_eq!(removed.status, ModerationStatus::Removed);
+        assert_eq!(removed.resolution_note.as_deref(), Some("Rude"));
+        assert_eq!(strike.user_id, author.id);
+        assert_eq!(
+            count_user_strikes_since(&pool, author.id, since)
+                .await
+                .unwrap(),
+            1
+        );
 
-    // Crawled Anime Repository Tests
+        assert_eq!(get_user_muted_until(&pool, author.id).await.unwrap(), None);
+        let until = Utc::now() + chrono::Duration::days(1);
+        assert!(mute_user(&pool, author.id, until).await.unwrap().is_some());
+        let shorter = Utc::now() + chrono::Duration::hours(1);
+        let muted_until = mute_user(&pool, author.id, shorter).await.unwrap().unwrap();
+        assert!(muted_until > shorter);
+        assert!(get_user_muted_until(&pool, author.id)
+            .await
+            .unwrap()
+            .is_some());
 
-    // Helper function to create a test CrawledAnime
-    fn create_test_crawled_anime(slug: &str) -> CrawledAnime {
-        CrawledAnime {
-            slug: slug.to_string(),
-            title: format!("Test Anime {}", slug),
-            url: format!("https://example.com/anime/{}/", slug),
-            thumbnail: format!("https://example.com/thumb/{}.jpg", slug),
-            status: "Ongoing".to_string(),
-            anime_type: "TV".to_string(),
-            episode_status: "12/24".to_string(),
+        let standing = get_moderation_standing(&pool, author.id, since)
+            .await
+            .unwrap()
+            .expect("Author should exist");
+        assert_eq!(standing.recent_strikes, 1);
+        assert_eq!(standing.strikes.len(), 1);
+        assert!(standing.muted_until.is_some());
+
+        assert!(unmute_user(&pool, author.id).await.unwrap());
+        assert!(!unmute_user(&pool, author.id).await.unwrap());
+        assert_eq!(get_user_muted_until(&pool, author.id).await.unwrap(), None);
+
+        for user in [author, reporter] {
+            delete_user(&pool, user.id)
+                .await
+                .expect("Failed to delete user");
         }
     }
 
-    #[test]
-    fn test_create_crawled_anime() {
-        let anime = create_test_crawled_anime("test-anime");
-        assert_eq!(anime.slug, "test-anime");
-        assert_eq!(anime.title, "Test Anime test-anime");
-        assert!(anime.url.contains("test-anime"));
-    }
-
     #[tokio::test]
-    #[ignore]
-    async fn test_crawled_anime_single_save() {
+    #[ignore] // Requires database connection
+    async fn test_registration_ip_throttle() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let slug = "test-single-crawled";
+        let ip = "198.51.100.77";
+        sqlx::query("DELETE FROM registration_ips WHERE ip_address = $1")
+            .bind(ip)
+            .execute(&pool)
+            .await
+            .expect("Failed to clean up registration IPs");
 
-        // Clean up first
-        let _ = delete_crawled_anime(&pool, slug).await;
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(
+            count_registrations_from_ip(&pool, ip, hour_ago)
+                .await
+                .expect("Failed to count registrations"),
+            0
+        );
 
-        // Create
-        let anime = create_test_crawled_anime(slug);
-        save_crawled_anime(&pool, &anime)
+        for _ in 0..2 {
+            record_registration_ip(&pool, ip, hour_ago)
+                .await
+                .expect("Failed to record registration");
+        }
+        assert_eq!(
+            count_registrations_from_ip(&pool, ip, hour_ago)
+                .await
+                .expect("Failed to count registrations"),
+            2
+        );
+        assert_eq!(
+            count_registrations_from_ip(&pool, ip, Utc::now() + chrono::Duration::minutes(1))
+                .await
+                .expect("Failed to count registrations"),
+            0
+        );
+
+        // Recording purges rows older than the window
+        record_registration_ip(&pool, ip, Utc::now() + chrono::Duration::minutes(1))
             .await
-            .expect("Failed to save");
+            .expect("Failed to record registration");
+        assert_eq!(
+            count_registrations_from_ip(&pool, ip, hour_ago)
+                .await
+                .expect("Failed to count registrations"),
+            1
+        );
 
-        // Read
-        let fetched = get_crawled_anime_by_slug(&pool, slug)
+        sqlx::query("DELETE FROM registration_ips WHERE ip_address = $1")
+            .bind(ip)
+            .execute(&pool)
             .await
-            .expect("Failed to fetch");
-        assert!(fetched.is_some());
-        let fetched = fetched.unwrap();
-        assert_eq!(fetched.slug, slug);
-        assert_eq!(fetched.title, anime.title);
+            .expect("Failed to clean up registration IPs");
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_crawler_bandwidth_accumulates() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
 
-        // Clean up
-        delete_crawled_anime(&pool, slug)
+        let day = NaiveDate::from_ymd_opt(1999, 12, 31).unwrap();
+        sqlx::query("DELETE FROM crawler_bandwidth WHERE day = $1")
+            .bind(day)
+            .execute(&pool)
             .await
-            .expect("Failed to delete");
+            .expect("Failed to clean up");
+        assert_eq!(get_crawler_bandwidth(&pool, day).await.unwrap(), 0);
+
+        assert_eq!(add_crawler_bandwidth(&pool, day, 1000).await.unwrap(), 1000);
+        assert_eq!(add_crawler_bandwidth(&pool, day, 500).await.unwrap(), 1500);
+        assert_eq!(get_crawler_bandwidth(&pool, day).await.unwrap(), 1500);
+
+        let days = list_crawler_bandwidth(&pool, 10_000).await.unwrap();
+        let stored = days
+            .iter()
+            .find(|d| d.day == "1999-12-31")
+            .expect("Day missing");
+        assert_eq!(stored.bytes, 1500);
+        assert_eq!(stored.requests, 2);
+
+        sqlx::query("DELETE FROM crawler_bandwidth WHERE day = $1")
+            .bind(day)
+            .execute(&pool)
+            .await
+            .expect("Failed to clean up");
     }
 
     #[tokio::test]
     #[ignore]
-    async fn test_crawled_anime_batch_save() {
+    async fn test_crawl_report_roundtrip() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let slugs = vec!["test-batch-1", "test-batch-2", "test-batch-3"];
-
-        // Clean up first
-        for slug in &slugs {
-            let _ = delete_crawled_anime(&pool, slug).await;
-        }
-
-        // Create batch
-        let anime_list: Vec<CrawledAnime> =
-            slugs.iter().map(|s| create_test_crawled_anime(s)).collect();
-
-        save_crawled_anime_batch(&pool, &anime_list)
+        let job = enqueue_job(&pool, "test-crawl-report", "crawl", "{}", 1)
             .await
-            .expect("Failed to save batch");
+            .expect("Failed to enqueue job");
+        assert_eq!(
+            get_crawl_report(&pool, job.id)
+                .await
+                .expect("Failed to get report"),
+            None
+        );
 
-        // Verify all were saved
-        for slug in &slugs {
-            let fetched = get_crawled_anime_by_slug(&pool, slug)
+        let mut report = CrawlReport {
+            pages_processed: 3,
+            summary: "first".to_string(),
+            ..Default::default()
+        };
+        save_crawl_report(&pool, job.id, &report)
+            .await
+            .expect("Failed to save report");
+        report.summary = "second".to_string();
+        save_crawl_report(&pool, job.id, &report)
+            .await
+            .expect("Failed to save report");
+        assert_eq!(
+            get_crawl_report(&pool, job.id)
                 .await
-                .expect("Failed to fetch");
-            assert!(fetched.is_some(), "Should find anime with slug {}", slug);
-        }
+                .expect("Failed to get report"),
+            Some(report)
+        );
 
-        // Clean up
-        for slug in &slugs {
-            delete_crawled_anime(&pool, slug)
+        // Reports are deleted with their job
+        sqlx::query("DELETE FROM jobs WHERE id = $1")
+            .bind(job.id)
+            .execute(&pool)
+            .await
+            .expect("Failed to clean up job");
+        assert_eq!(
+            get_crawl_report(&pool, job.id)
                 .await
-                .expect("Failed to delete");
-        }
+                .expect("Failed to get report"),
+            None
+        );
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_crawled_anime_upsert() {
+    #[ignore] // Requires database connection
+    async fn test_crawl_failure_queue() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("Failed to connect");
 
-        let slug = "test-upsert-crawled";
-
-        // Clean up first
-        let _ = delete_crawled_anime(&pool, slug).await;
-
-        // Create initial
-        let anime = create_test_crawled_anime(slug);
-        save_crawled_anime(&pool, &anime)
+        let slug = "test-crawl-failure-episode-1";
+        let url = "https://example.com/test-crawl-failure-episode-1/";
+        record_crawl_failure(&pool, CrawlFailureKind::Episode, slug, url, "timeout")
             .await
-            .expect("Failed to save");
+            .expect("Failed to record failure");
+        record_crawl_failure(&pool, CrawlFailureKind::Episode, slug, url, "HTTP 500")
+            .await
+            .expect("Failed to record failure");
 
-        // Update with same slug
-        let mut updated_anime = create_test_crawled_anime(slug);
-        updated_anime.title = "Updated Title".to_string();
-        updated_anime.status = "Completed".to_string();
-        save_crawled_anime(&pool, &updated_anime)
+        let failures = list_crawl_failures(&pool, 10_000)
             .await
-            .expect("Failed to upsert");
+            .expect("Failed to list failures");
+        let failure = failures
+            .iter()
+            .find(|failure| failure.slug == slug)
+            .expect("Failure not queued");
+        assert_eq!(failure.kind, CrawlFailureKind::Episode);
+        assert_eq!(failure.url, url);
+        assert_eq!(failure.last_error, "HTTP 500");
+        assert_eq!(failure.attempts, 2);
+
+        assert!(!delete_crawl_failure(&pool, CrawlFailureKind::Anime, slug)
+            .await
+            .expect("Failed to delete failure"));
+        assert!(delete_crawl_failure(&pool, CrawlFailureKind::Episode, slug)
+            .await
+            .expect("Failed to delete failure"));
+        assert!(!list_crawl_failures(&pool, 10_000)
+            .await
+            .expect("Failed to list failures")
+            .iter()
+            .any(|failure| failure.slug == slug));
+    }
 
-        // Verify update
-        let fetched = get_crawled_anime_by_slug(&pool, slug)
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_find_stale_popular_anime() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to fetch")
-            .expect("Should find anime");
-        assert_eq!(fetched.title, "Updated Title");
-        assert_eq!(fetched.status, "Completed");
+            .expect("Failed to connect");
 
-        // Verify count is still 1 (not duplicated)
-        let count_before = get_crawled_anime_count(&pool)
+        let slug = "test-popular-anime";
+        let email = "test-popular-anime@example.com";
+        let _ = delete_anime_detail(&pool, slug).await;
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            let _ = delete_user(&pool, user.id).await;
+        }
+
+        save_anime_detail_with_episodes(&pool, slug, &create_test_anime_detail())
             .await
-            .expect("Failed to count");
-        save_crawled_anime(&pool, &updated_anime)
+            .expect("Failed to save");
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to upsert again");
-        let count_after = get_crawled_anime_count(&pool)
+            .expect("Failed to create user");
+        add_subscription(&pool, user.id, slug, "Test Anime", "")
             .await
-            .expect("Failed to count");
+            .expect("Failed to subscribe");
+        add_favorite(&pool, user.id, slug, "Test Anime", "")
+            .await
+            .expect("Failed to favorite");
+
+        let find = |scraped_before| {
+            let pool = pool.clone();
+            async move {
+                find_stale_popular_anime(&pool, scraped_before, "test_scrape", 3, 10_000)
+                    .await
+                    .expect("Failed to find stale anime")
+                    .into_iter()
+                    .find(|anime| anime.slug == slug)
+            }
+        };
 
-        // Count should not increase (upsert, not insert)
-        assert_eq!(
-            count_before, count_after,
-            "Count should not increase on upsert"
-        );
+        // Just scraped, so fresh
+        assert_eq!(find(Utc::now() - chrono::Duration::hours(1)).await, None);
+
+        let stale = find(Utc::now() + chrono::Duration::minutes(1))
+            .await
+            .expect("Anime not found");
+        assert_eq!(stale.subscribers, 1);
+        assert_eq!(stale.favorites, 1);
+
+        // A queued re-scrape hides it; claimed jobs come highest priority first
+        let low = enqueue_job(
+            &pool,
+            "test-priority",
+            "test_scrape",
+            r#"{"slug":"other"}"#,
+            1,
+        )
+        .await
+        .expect("Failed to enqueue job");
+        let high = enqueue_job_with_priority(
+            &pool,
+            "test-priority",
+            "test_scrape",
+            &format!(r#"{{"slug":"{}"}}"#, slug),
+            1,
+            50,
+        )
+        .await
+        .expect("Failed to enqueue job");
+        assert_eq!(high.priority, 50);
+        assert_eq!(find(Utc::now() + chrono::Duration::minutes(1)).await, None);
+
+        let claimed = claim_next_job(&pool, 300)
+            .await
+            .expect("Failed to claim job")
+            .expect("No job to claim");
+        assert_eq!(claimed.id, high.id);
 
         // Clean up
-        delete_crawled_anime(&pool, slug)
+        sqlx::query("DELETE FROM jobs WHERE id = ANY($1)")
+            .bind(vec![low.id, high.id])
+            .execute(&pool)
             .await
-            .expect("Failed to delete");
+            .expect("Failed to clean up jobs");
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+        delete_anime_detail(&pool, slug)
+            .await
+            .expect("Failed to delete anime");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_crawled_anime_count() {
+    #[ignore] // Requires database connection
+    async fn test_popularity_scores() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
+            .expect("Failed to connect to database");
+
+        let viewed = "test-popularity-viewed";
+        let favorited = "test-popularity-favorited";
+        let ignored = "test-popularity-ignored";
+        let email = "popularity@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
+        }
+        for slug in [viewed, favorited, ignored] {
+            delete_crawled_anime(&pool, slug).await.ok();
+            sqlx::query("DELETE FROM anime_views WHERE anime_slug = $1")
+                .bind(slug)
+                .execute(&pool)
+                .await
+                .expect("Failed to clean up views");
+            save_crawled_anime(&pool, &create_test_crawled_anime(slug))
+                .await
+                .expect("Failed to save anime");
+        }
 
-        // Get initial count
-        let initial_count = get_crawled_anime_count(&pool)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to count");
-
-        // Add some anime
-        let slugs = vec!["test-count-1", "test-count-2"];
-        for slug in &slugs {
-            let _ = delete_crawled_anime(&pool, slug).await;
-            let anime = create_test_crawled_anime(slug);
-            save_crawled_anime(&pool, &anime)
+            .expect("Failed to create user");
+        add_favorite(&pool, user.id, favorited, "Favorited", "")
+            .await
+            .expect("Failed to add favorite");
+        for _ in 0..3 {
+            record_anime_view(&pool, viewed)
                 .await
-                .expect("Failed to save");
+                .expect("Failed to record view");
         }
 
-        // Verify count increased
-        let new_count = get_crawled_anime_count(&pool)
+        let weights = PopularityWeights {
+            view: 1.0,
+            favorite: 5.0,
+            subscription: 10.0,
+            half_life_days: 7.0,
+            view_window_days: 56,
+        };
+        let updated = refresh_popularity_scores(&pool, &weights)
             .await
-            .expect("Failed to count");
-        assert_eq!(new_count, initial_count + 2);
+            .expect("Failed to refresh scores");
+        assert!(updated >= 2);
+
+        let score = |slug: &'static str| {
+            let pool = pool.clone();
+            async move {
+                get_crawled_anime_by_slug(&pool, slug)
+                    .await
+                    .expect("Failed to get anime")
+                    .expect("Anime not found")
+                    .popularity_score
+            }
+        };
+        // Today's views have decayed by at most a day
+        let viewed_score = score(viewed).await;
+        assert!(
+            viewed_score > 2.5 && viewed_score <= 3.0,
+            "{}",
+            viewed_score
+        );
+        let favorited_score = score(favorited).await;
+        assert!((favorited_score - 5.0).abs() < 0.01, "{}", favorited_score);
+        assert_eq!(score(ignored).await, 0.0);
+
+        let total = get_crawled_anime_count(&pool)
+            .await
+            .expect("Failed to count anime");
+        let popular = list_crawled_anime(&pool, CatalogOrder::Popular, None, true, total, 0)
+            .await
+            .expect("Failed to list anime");
+        let position = |slug: &str| popular.iter().position(|a| a.slug == slug).unwrap();
+        assert!(position(favorited) < position(viewed));
+        assert!(position(viewed) < position(ignored));
 
         // Clean up
-        for slug in &slugs {
-            delete_crawled_anime(&pool, slug)
-                .await
-                .expect("Failed to delete");
+        delete_user(&pool, user.id)
+            .await
+            .expect("Failed to delete user");
+        for slug in [viewed, favorited, ignored] {
+            delete_crawled_anime(&pool, slug).await.ok();
         }
+        sqlx::query("DELETE FROM anime_views WHERE anime_slug = $1")
+            .bind(viewed)
+            .execute(&pool)
+            .await
+            .expect("Failed to clean up views");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_crawled_anime_empty_batch() {
+    #[ignore] // Requires database connection
+    async fn test_encrypted_user_columns() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
+            .expect("Failed to connect to database");
+
+        // Installed for the rest of the process; plaintext rows stay readable
+        encryption::install(encryption::FieldCipher::new(
+            vec![encryption::EncryptionKey::new(
+                "test",
+                [42; encryption::KEY_LEN],
+            )],
+            "test-index-key",
+        ));
+
+        let email = "test_encrypted@example.com";
+        let legacy_email = "test_encrypted_legacy@example.com";
+        for email in [email, legacy_email] {
+            if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+                delete_user(&pool, user.id).await.ok();
+            }
+        }
 
-        // Saving empty batch should succeed without error
-        let empty_list: Vec<CrawledAnime> = vec![];
-        save_crawled_anime_batch(&pool, &empty_list)
-            .await
-            .expect("Empty batch save should succeed");
-    }
+        let user = create_google_user(
+            &pool,
+            DEFAULT_TENANT_ID,
+            email,
+            "google_encrypted",
+            "Encrypted",
+            None,
+        )
+        .await
+        .expect("Failed to create user");
+        assert_eq!(user.email, email);
 
-    #[tokio::test]
-    #[ignore]
-    async fn test_create_user_with_email_password() {
-        dotenvy::dotenv().ok();
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let pool = PgPool::connect(&database_url)
+        let row = sqlx::query("SELECT email, email_hash, google_id FROM users WHERE id = $1")
+            .bind(user.id)
+            .fetch_one(&pool)
             .await
-            .expect("Failed to connect");
+            .expect("Failed to read user");
+        let stored: String = row.get("email");
+        assert!(stored.starts_with("enc:test:"));
+        assert!(row.get::<Option<String>, _>("email_hash").is_some());
+        assert!(!row
+            .get::<String, _>("google_id")
+            .contains("google_encrypted"));
+
+        let (found, _) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email)
+            .await
+            .expect("Failed to find user")
+            .expect("User not found");
+        assert_eq!(found.email, email);
+        let found = find_user_by_google_id(&pool, DEFAULT_TENANT_ID, "google_encrypted")
+            .await
+            .expect("Failed to find user")
+            .expect("User not found");
+        assert_eq!(found.id, user.id);
+        assert!(matches!(
+            create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None).await,
+            Err(RepositoryError::EmailAlreadyExists)
+        ));
+
+        // A row written before encryption was switched on
+        let legacy_id: i32 =
+            sqlx::query("INSERT INTO users (email, tenant_id) VALUES ($1, $2) RETURNING id")
+                .bind(legacy_email)
+                .bind(DEFAULT_TENANT_ID)
+                .fetch_one(&pool)
+                .await
+                .expect("Failed to insert user")
+                .get("id");
+        assert!(find_user_by_email(&pool, DEFAULT_TENANT_ID, legacy_email)
+            .await
+            .expect("Failed to find user")
+            .is_some());
 
-        let email = "test_user_crud@example.com";
+        // Indexed, it blocks signing up again with the same address
+        assert!(
+            backfill_blind_indexes(&pool)
+                .await
+                .expect("Backfill failed")
+                >= 1
+        );
+        assert!(matches!(
+            create_user(
+                &pool,
+                DEFAULT_TENANT_ID,
+                legacy_email,
+                "hashed_password",
+                None
+            )
+            .await,
+            Err(RepositoryError::EmailAlreadyExists)
+        ));
+        assert_eq!(
+            backfill_blind_indexes(&pool)
+                .await
+                .expect("Backfill failed"),
+            0
+        );
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
-        }
+        // A row encrypted with a retired key is skipped, not fatal
+        let retired_id: i32 =
+            sqlx::query("INSERT INTO users (email, tenant_id) VALUES ($1, $2) RETURNING id")
+                .bind("enc:retired:AAAA")
+                .bind(DEFAULT_TENANT_ID)
+                .fetch_one(&pool)
+                .await
+                .expect("Failed to insert user")
+                .get("id");
+        assert_eq!(
+            backfill_blind_indexes(&pool)
+                .await
+                .expect("Backfill failed"),
+            0
+        );
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(retired_id)
+            .execute(&pool)
+            .await
+            .expect("Failed to delete user");
 
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", Some("Test User"))
+        // Queued emails are sealed too
+        let delivery = create_email_delivery(&pool, email, "verification", "en", "{\"token\":1}")
             .await
-            .expect("Failed to create user");
+            .expect("Failed to create delivery");
+        assert_eq!(delivery.recipient, email);
+        let row = sqlx::query("SELECT recipient, payload FROM email_deliveries WHERE id = $1")
+            .bind(delivery.id)
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to read delivery");
+        assert!(row.get::<String, _>("recipient").starts_with("enc:test:"));
+        assert!(row.get::<String, _>("payload").starts_with("enc:test:"));
+        let (found, payload) = get_email_delivery_with_payload(&pool, delivery.id)
+            .await
+            .expect("Failed to get delivery")
+            .expect("Delivery not found");
+        assert_eq!(found.recipient, email);
+        assert_eq!(payload, "{\"token\":1}");
+        let data = collect_user_data(&pool, user.id)
+            .await
+            .expect("Failed to collect data")
+            .expect("User not found");
+        let emails = data["email_deliveries"].as_array().unwrap();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0]["recipient"], email);
+        sqlx::query("DELETE FROM email_deliveries WHERE id = $1")
+            .bind(delivery.id)
+            .execute(&pool)
+            .await
+            .expect("Failed to delete delivery");
 
-        assert_eq!(user.email, email);
-        assert_eq!(user.name, Some("Test User".to_string()));
-        assert!(user.id > 0);
+        assert!(encrypt_user_data(&pool).await.expect("Backfill failed") >= 1);
+        let stored: String = sqlx::query("SELECT email FROM users WHERE id = $1")
+            .bind(legacy_id)
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to read user")
+            .get("email");
+        assert!(stored.starts_with("enc:test:"));
+        let legacy = find_user_by_id(&pool, legacy_id)
+            .await
+            .expect("Failed to find user")
+            .expect("User not found");
+        assert_eq!(legacy.email, legacy_email);
+        assert_eq!(encrypt_user_data(&pool).await.expect("Backfill failed"), 0);
 
         // Clean up
         delete_user(&pool, user.id)
             .await
             .expect("Failed to delete user");
+        delete_user(&pool, legacy_id)
+            .await
+            .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_create_user_duplicate_email() {
+    #[ignore] // Requires database connection
+    async fn test_user_data_export_and_erasure() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_duplicate@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_erasure@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
 
-        // Create first user
-        let user = create_user(&pool, email, "hashed_password", None)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
             .expect("Failed to create user");
+        add_favorite(&pool, user.id, "test-erasure-anime", "Erasure", "thumb.jpg")
+            .await
+            .expect("Failed to add favorite");
+        create_session(&pool, user.id, Some("test-agent"), None, 1)
+            .await
+            .expect("Failed to create session");
+        let delivery = create_email_delivery(&pool, email, "verification", "en", "{\"body\":1}")
+            .await
+            .expect("Failed to create delivery");
 
-        // Try to create duplicate
-        let result = create_user(&pool, email, "another_password", None).await;
-        assert!(matches!(result, Err(RepositoryError::EmailAlreadyExists)));
+        let data = collect_user_data(&pool, user.id)
+            .await
+            .expect("Failed to collect data")
+            .expect("User not found");
+        assert_eq!(data["profile"]["email"], email);
+        assert!(data["profile"].get("passwordHash").is_none());
+        assert_eq!(data["user_favorites"].as_array().unwrap().len(), 1);
+        assert_eq!(data["sessions"].as_array().unwrap().len(), 1);
+        let emails = data["email_deliveries"].as_array().unwrap();
+        assert_eq!(emails.len(), 1);
+        assert!(emails[0].get("payload").is_none());
+
+        let export = create_data_export(&pool, user.id)
+            .await
+            .expect("Failed to create export");
+        assert_eq!(export.status, crate::models::DATA_EXPORT_PENDING);
+        complete_data_export(&pool, export.id, "exports/user-data/test.json")
+            .await
+            .expect("Failed to complete export");
+        let latest = get_latest_data_export(&pool, user.id)
+            .await
+            .expect("Failed to get export")
+            .expect("Export not found");
+        assert_eq!(latest.status, DATA_EXPORT_READY);
+        assert_eq!(
+            latest.object_key.as_deref(),
+            Some("exports/user-data/test.json")
+        );
 
-        // Clean up
-        delete_user(&pool, user.id)
+        let erasure = erase_user_data(&pool, user.id)
             .await
-            .expect("Failed to delete user");
+            .expect("Failed to erase data")
+            .expect("User not found");
+        let rows = |table: &str| {
+            erasure
+                .tables
+                .iter()
+                .find(|t| t.table == table)
+                .map_or(0, |t| t.rows)
+        };
+        assert_eq!(rows("users"), 1);
+        assert_eq!(rows("user_favorites"), 1);
+        assert_eq!(rows("data_exports"), 1);
+        assert_eq!(rows("email_deliveries"), 1);
+
+        assert!(find_user_by_id(&pool, user.id).await.unwrap().is_none());
+        assert!(collect_user_data(&pool, user.id).await.unwrap().is_none());
+        assert!(erase_user_data(&pool, user.id).await.unwrap().is_none());
+        let anonymized = get_email_delivery(&pool, delivery.id)
+            .await
+            .unwrap()
+            .expect("Delivery not found");
+        assert_eq!(anonymized.recipient, "erased");
+        assert_eq!(anonymized.status, EMAIL_STATUS_FAILED);
+
+        let logged = list_data_erasures(&pool, 10).await.unwrap();
+        assert!(logged
+            .iter()
+            .any(|e| e.id == erasure.id && e.tables == erasure.tables));
+
+        sqlx::query("DELETE FROM email_deliveries WHERE id = $1")
+            .bind(delivery.id)
+            .execute(&pool)
+            .await
+            .ok();
+        sqlx::query("DELETE FROM data_erasures WHERE id = $1")
+            .bind(erasure.id)
+            .execute(&pool)
+            .await
+            .ok();
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_find_user_by_email() {
+    #[ignore] // Requires database connection
+    async fn test_deactivate_and_reactivate_user() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_find_email@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_deactivate@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
 
-        // Create user
-        let created_user = create_user(&pool, email, "hashed_password", Some("Find Me"))
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
             .expect("Failed to create user");
+        assert!(is_user_active(&pool, user.id).await.unwrap());
 
-        // Find user
-        let result = find_user_by_email(&pool, email)
-            .await
-            .expect("Failed to find user");
+        assert!(deactivate_user(&pool, user.id).await.unwrap());
+        assert!(!deactivate_user(&pool, user.id).await.unwrap());
+        assert!(!is_user_active(&pool, user.id).await.unwrap());
 
-        assert!(result.is_some());
-        let (found_user, password_hash) = result.unwrap();
-        assert_eq!(found_user.email, email);
-        assert_eq!(found_user.name, Some("Find Me".to_string()));
-        assert_eq!(password_hash, Some("hashed_password".to_string()));
+        let data = collect_user_data(&pool, user.id).await.unwrap().unwrap();
+        assert_eq!(data["profile"]["isActive"], false);
+        assert!(data["profile"]["deactivatedAt"].is_string());
 
-        // Find non-existent user
-        let result = find_user_by_email(&pool, "nonexistent@example.com")
-            .await
-            .expect("Failed to query");
-        assert!(result.is_none());
+        assert!(reactivate_user(&pool, user.id).await.unwrap());
+        assert!(!reactivate_user(&pool, user.id).await.unwrap());
+        assert!(is_user_active(&pool, user.id).await.unwrap());
 
-        // Clean up
-        delete_user(&pool, created_user.id)
+        delete_user(&pool, user.id)
             .await
             .expect("Failed to delete user");
+        assert!(!is_user_active(&pool, user.id).await.unwrap());
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_create_google_user() {
+    #[ignore] // Requires database connection
+    async fn test_token_state() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_google@example.com";
-        let google_id = "google_123456";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_token_state@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
 
-        // Create Google user
-        let user = create_google_user(
-            &pool,
-            email,
-            google_id,
-            "Google User",
-            Some("https://example.com/avatar.jpg"),
-        )
-        .await
-        .expect("Failed to create Google user");
-
-        assert_eq!(user.email, email);
-        assert_eq!(user.name, Some("Google User".to_string()));
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
+            .await
+            .expect("Failed to create user");
         assert_eq!(
-            user.avatar,
-            Some("https://example.com/avatar.jpg".to_string())
+            get_token_state(&pool, user.id).await.unwrap(),
+            Some(TokenState {
+                is_active: true,
+                token_version: 0,
+                is_admin: false,
+            })
         );
 
-        // Find by Google ID
-        let found = find_user_by_google_id(&pool, google_id)
-            .await
-            .expect("Failed to find user");
-        assert!(found.is_some());
-        assert_eq!(found.unwrap().email, email);
+        assert_eq!(bump_token_version(&pool, user.id).await.unwrap(), Some(1));
+        assert_eq!(bump_token_version(&pool, user.id).await.unwrap(), Some(2));
+        deactivate_user(&pool, user.id).await.unwrap();
+        let state = get_token_state(&pool, user.id).await.unwrap().unwrap();
+        assert_eq!(state.token_version, 2);
+        assert!(!state.is_active);
 
-        // Clean up
         delete_user(&pool, user.id)
             .await
             .expect("Failed to delete user");
+        assert_eq!(get_token_state(&pool, user.id).await.unwrap(), None);
+        assert_eq!(bump_token_version(&pool, user.id).await.unwrap(), None);
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_find_user_by_id() {
+    #[ignore] // Requires database connection
+    async fn test_device_claim() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_find_id@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_device_claim@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
+        let token_hash = format!("test-device-{}", uuid::Uuid::new_v4().simple());
 
-        // Create user
-        let created_user = create_user(&pool, email, "hashed_password", None)
+        let device = create_device(&pool, DEFAULT_TENANT_ID, &token_hash, Some("android"))
             .await
-            .expect("Failed to create user");
+            .expect("Failed to create device");
+        assert_eq!(device.tenant_id, DEFAULT_TENANT_ID);
+        assert_eq!(find_device(&pool, &token_hash).await.unwrap(), Some(device));
+        add_favorite(
+            &pool,
+            device.user_id,
+            "test-device-anime",
+            "Test Device Anime",
+            "",
+        )
+        .await
+        .expect("Failed to add favorite");
 
-        // Find by ID
-        let found = find_user_by_id(&pool, created_user.id)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to find user");
-        assert!(found.is_some());
-        assert_eq!(found.unwrap().email, email);
+            .expect("Failed to create user");
+        assert!(
+            !claim_device(&pool, &token_hash, user.id, DEFAULT_TENANT_ID + 1)
+                .await
+                .unwrap()
+        );
+        assert!(claim_device(&pool, &token_hash, user.id, DEFAULT_TENANT_ID)
+            .await
+            .unwrap());
 
-        // Find non-existent ID
-        let found = find_user_by_id(&pool, 999999)
+        let favorites = get_favorites(&pool, user.id).await.unwrap();
+        assert!(favorites
+            .iter()
+            .any(|favorite| favorite.anime_slug == "test-device-anime"));
+        assert_eq!(find_device(&pool, &token_hash).await.unwrap(), None);
+        assert!(get_token_state(&pool, device.user_id)
             .await
-            .expect("Failed to query");
-        assert!(found.is_none());
+            .unwrap()
+            .is_none());
+        assert!(
+            !claim_device(&pool, &token_hash, user.id, DEFAULT_TENANT_ID)
+                .await
+                .unwrap()
+        );
 
-        // Clean up
-        delete_user(&pool, created_user.id)
+        delete_user(&pool, user.id)
             .await
             .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_link_google_account() {
+    #[ignore] // Requires database connection
+    async fn test_delete_unused_anonymous_users() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
+            .expect("Failed to connect to database");
+
+        let device = |name: &str| {
+            let pool = pool.clone();
+            let token_hash = format!("test-{}-{}", name, uuid::Uuid::new_v4().simple());
+            async move {
+                let device = create_device(&pool, DEFAULT_TENANT_ID, &token_hash, None)
+                    .await
+                    .expect("Failed to create device");
+                sqlx::query(
+                    "UPDATE users SET created_at = created_at - INTERVAL '60 days' WHERE id = $1",
+                )
+                .bind(device.user_id)
+                .execute(&pool)
+                .await
+                .unwrap();
+                sqlx::query("UPDATE devices SET last_seen_at = last_seen_at - INTERVAL '60 days' WHERE id = $1")
+                    .bind(device.device_id)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+                (device, token_hash)
+            }
+        };
+        let (unused, unused_token) = device("unused").await;
+        let (used, _) = device("used").await;
+        add_favorite(&pool, used.user_id, "test-anonymous-anime", "Test", "")
+            .await
+            .expect("Failed to add favorite");
+        let (recent, recent_token) = device("recent").await;
+        sqlx::query("UPDATE devices SET last_seen_at = CURRENT_TIMESTAMP WHERE id = $1")
+            .bind(recent.device_id)
+            .execute(&pool)
+            .await
+            .unwrap();
 
-        let email = "test_link_google@example.com";
-        let google_id = "google_link_123";
+        let idle_before = Utc::now() - chrono::Duration::days(30);
+        assert!(
+            delete_unused_anonymous_users(&pool, idle_before)
+                .await
+                .expect("Failed to prune")
+                >= 1
+        );
+        assert_eq!(find_device(&pool, &unused_token).await.unwrap(), None);
+        assert!(get_token_state(&pool, unused.user_id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(get_token_state(&pool, used.user_id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(find_device(&pool, &recent_token).await.unwrap().is_some());
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        for user_id in [used.user_id, recent.user_id] {
+            delete_user(&pool, user_id)
+                .await
+                .expect("Failed to delete user");
         }
+    }
 
-        // Create user with email/password
-        let user = create_user(&pool, email, "hashed_password", None)
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_update_user_avatar() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to create user");
+            .expect("Failed to connect to database");
 
-        // Link Google account
-        link_google_account(&pool, user.id, google_id)
+        let email = "test_avatar@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
+        }
+
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
-            .expect("Failed to link Google account");
+            .expect("Failed to create user");
+        assert_eq!(user.avatar, None);
 
-        // Verify link
-        let found = find_user_by_google_id(&pool, google_id)
+        let key = format!("avatars/{}/0123456789abcdef.png", user.id);
+        let updated = update_user_avatar(&pool, user.id, &key)
             .await
-            .expect("Failed to find user");
-        assert!(found.is_some());
-        assert_eq!(found.unwrap().id, user.id);
+            .unwrap()
+            .expect("User should exist");
+        assert_eq!(updated.avatar.as_deref(), Some(key.as_str()));
+        assert_eq!(updated.email, email);
 
-        // Clean up
         delete_user(&pool, user.id)
             .await
             .expect("Failed to delete user");
+        assert!(update_user_avatar(&pool, user.id, &key)
+            .await
+            .unwrap()
+            .is_none());
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_favorites_crud() {
+    #[ignore] // Requires database connection
+    async fn test_collections() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_favorites@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_collections@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
 
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", None)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
             .expect("Failed to create user");
+        let share_slug = format!("test-collection-{}", user.id);
+        let collection =
+            create_collection(&pool, user.id, "Best of 2023", None, false, &share_slug)
+                .await
+                .expect("Failed to create collection");
+        assert_eq!(collection.item_count, 0);
+        assert_eq!(count_collections(&pool, user.id).await.unwrap(), 1);
 
-        // Add favorite
-        let favorite = add_favorite(
-            &pool,
-            user.id,
-            "naruto",
-            "Naruto",
-            "https://example.com/naruto.jpg",
-        )
-        .await
-        .expect("Failed to add favorite");
-        assert_eq!(favorite.anime_slug, "naruto");
-        assert_eq!(favorite.anime_title, "Naruto");
-
-        // Check is_favorite
-        let is_fav = is_favorite(&pool, user.id, "naruto")
+        for slug in ["test-col-a", "test-col-b"] {
+            add_collection_item(&pool, collection.id, slug, slug, "thumb.jpg", None)
+                .await
+                .expect("Failed to add item");
+        }
+        let first = add_collection_item(&pool, collection.id, "test-col-c", "C", "", Some(0))
             .await
-            .expect("Failed to check favorite");
-        assert!(is_fav);
+            .expect("Failed to insert item");
+        assert_eq!(first.position, 0);
+        assert!(matches!(
+            add_collection_item(&pool, collection.id, "test-col-a", "A", "", None).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        let slugs = |items: Vec<CollectionItem>| {
+            items
+                .into_iter()
+                .map(|item| (item.position, item.anime_slug))
+                .collect::<Vec<_>>()
+        };
+        let items = get_collection_items(&pool, collection.id).await.unwrap();
+        assert_eq!(
+            slugs(items),
+            vec![
+                (0, "test-col-c".to_string()),
+                (1, "test-col-a".to_string()),
+                (2, "test-col-b".to_string()),
+            ]
+        );
 
-        let is_not_fav = is_favorite(&pool, user.id, "one-piece")
+        let order = vec!["test-col-b".to_string(), "test-col-c".to_string()];
+        assert!(!reorder_collection_items(&pool, collection.id, &order)
             .await
-            .expect("Failed to check favorite");
-        assert!(!is_not_fav);
-
-        // Get favorites
-        let favorites = get_favorites(&pool, user.id)
+            .unwrap());
+        let order = vec![
+            "test-col-b".to_string(),
+            "test-col-a".to_string(),
+            "test-col-c".to_string(),
+        ];
+        assert!(reorder_collection_items(&pool, collection.id, &order)
             .await
-            .expect("Failed to get favorites");
-        assert_eq!(favorites.len(), 1);
-        assert_eq!(favorites[0].anime_slug, "naruto");
+            .unwrap());
+        assert!(remove_collection_item(&pool, collection.id, "test-col-a")
+            .await
+            .unwrap());
+        assert!(!remove_collection_item(&pool, collection.id, "test-col-a")
+            .await
+            .unwrap());
+        let items = get_collection_items(&pool, collection.id).await.unwrap();
+        assert_eq!(
+            slugs(items),
+            vec![(0, "test-col-b".to_string()), (1, "test-col-c".to_string())]
+        );
 
-        // Try to add duplicate
-        let result = add_favorite(
+        assert!(get_public_collection(&pool, DEFAULT_TENANT_ID, &share_slug)
+            .await
+            .unwrap()
+            .is_none());
+        let updated = update_collection(
             &pool,
             user.id,
-            "naruto",
-            "Naruto",
-            "https://example.com/naruto.jpg",
+            collection.id,
+            None,
+            Some("Top picks"),
+            Some(true),
         )
-        .await;
-        assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+        .await
+        .unwrap()
+        .expect("Collection should exist");
+        assert_eq!(updated.name, "Best of 2023");
+        assert_eq!(updated.description.as_deref(), Some("Top picks"));
+        assert_eq!(updated.item_count, 2);
+        let shared = get_public_collection(&pool, DEFAULT_TENANT_ID, &share_slug)
+            .await
+            .unwrap()
+            .expect("Collection should be public");
+        assert_eq!(shared.id, collection.id);
 
-        // Remove favorite
-        let removed = remove_favorite(&pool, user.id, "naruto")
+        deactivate_user(&pool, user.id).await.unwrap();
+        assert!(get_public_collection(&pool, DEFAULT_TENANT_ID, &share_slug)
             .await
-            .expect("Failed to remove favorite");
-        assert!(removed);
+            .unwrap()
+            .is_none());
 
-        // Verify removed
-        let is_fav = is_favorite(&pool, user.id, "naruto")
+        let data = collect_user_data(&pool, user.id).await.unwrap().unwrap();
+        assert_eq!(data["collections"].as_array().unwrap().len(), 1);
+        assert_eq!(data["collection_items"].as_array().unwrap().len(), 2);
+
+        assert!(!delete_collection(&pool, user.id + 1, collection.id)
             .await
-            .expect("Failed to check favorite");
-        assert!(!is_fav);
+            .unwrap());
+        assert!(delete_collection(&pool, user.id, collection.id)
+            .await
+            .unwrap());
+        assert!(get_collection(&pool, user.id, collection.id)
+            .await
+            .unwrap()
+            .is_none());
 
-        // Clean up
         delete_user(&pool, user.id)
             .await
             .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_subscriptions_crud() {
+    #[ignore] // Requires database connection
+    async fn test_episode_comments() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_subscriptions@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_episode_comments@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
 
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", None)
+        let user = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
             .expect("Failed to create user");
+        let slug = format!("test-comments-episode-{}", user.id);
 
-        // Add subscription
-        let subscription = add_subscription(
-            &pool,
-            user.id,
-            "one-piece",
-            "One Piece",
-            "https://example.com/onepiece.jpg",
-        )
-        .await
-        .expect("Failed to add subscription");
-        assert_eq!(subscription.anime_slug, "one-piece");
-        assert_eq!(subscription.anime_title, "One Piece");
+        let first =
+            create_episode_comment(&pool, user.id, &slug, "at 12:34 the OP drops", Some(754))
+                .await
+                .expect("Failed to create comment");
+        assert_eq!(first.timestamp_seconds, Some(754));
+        assert_eq!(first.author_id, user.id);
+        let second = create_episode_comment(&pool, user.id, &slug, "Great episode", None)
+            .await
+            .expect("Failed to create comment");
 
-        // Check is_subscribed
-        let is_sub = is_subscribed(&pool, user.id, "one-piece")
+        let comments = get_episode_comments(&pool, DEFAULT_TENANT_ID, &slug, 10, 0)
             .await
-            .expect("Failed to check subscription");
-        assert!(is_sub);
+            .unwrap();
+        assert_eq!(
+            comments.iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![second.id, first.id]
+        );
+        assert_eq!(
+            count_episode_comments(&pool, DEFAULT_TENANT_ID, &slug)
+                .await
+                .unwrap(),
+            2
+        );
 
-        let is_not_sub = is_subscribed(&pool, user.id, "naruto")
+        assert!(remove_episode_comment(&pool, first.id).await.unwrap());
+        assert!(
+            get_episode_comment(&pool, DEFAULT_TENANT_ID, &slug, first.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            count_episode_comments(&pool, DEFAULT_TENANT_ID, &slug)
+                .await
+                .unwrap(),
+            1
+        );
+
+        deactivate_user(&pool, user.id).await.unwrap();
+        assert_eq!(
+            count_episode_comments(&pool, DEFAULT_TENANT_ID, &slug)
+                .await
+                .unwrap(),
+            0
+        );
+
+        assert!(
+            !delete_episode_comment(&pool, user.id + 1, &slug, second.id)
+                .await
+                .unwrap()
+        );
+        assert!(delete_episode_comment(&pool, user.id, &slug, second.id)
             .await
-            .expect("Failed to check subscription");
-        assert!(!is_not_sub);
+            .unwrap());
 
-        // Get subscriptions
-        let subscriptions = get_subscriptions(&pool, user.id)
+        delete_user(&pool, user.id)
             .await
-            .expect("Failed to get subscriptions");
-        assert_eq!(subscriptions.len(), 1);
-        assert_eq!(subscriptions[0].anime_slug, "one-piece");
+            .expect("Failed to delete user");
+    }
 
-        // Try to add duplicate
-        let result = add_subscription(
-            &pool,
-            user.id,
-            "one-piece",
-            "One Piece",
-            "https://example.com/onepiece.jpg",
-        )
-        .await;
-        assert!(matches!(result, Err(RepositoryError::Conflict(_))));
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_episode_likes() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to database");
+
+        let emails = ["test_likes_a@example.com", "test_likes_b@example.com"];
+        for email in emails {
+            if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+                delete_user(&pool, user.id).await.ok();
+            }
+        }
+
+        let alice = create_user(&pool, DEFAULT_TENANT_ID, emails[0], "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+        let bob = create_user(&pool, DEFAULT_TENANT_ID, emails[1], "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+        let liked = format!("test-likes-episode-{}", alice.id);
+        let unliked = format!("test-likes-other-{}", alice.id);
+        let slugs = [liked.clone(), unliked.clone()];
+
+        assert!(like_episode(&pool, alice.id, &liked).await.unwrap());
+        assert!(!like_episode(&pool, alice.id, &liked).await.unwrap());
+        assert!(like_episode(&pool, bob.id, &liked).await.unwrap());
+
+        let likes = get_episode_likes(&pool, DEFAULT_TENANT_ID, Some(alice.id), &slugs)
+            .await
+            .unwrap();
+        assert_eq!(
+            likes,
+            vec![
+                EpisodeLikes {
+                    episode_slug: liked.clone(),
+                    like_count: 2,
+                    liked_by_me: true,
+                },
+                EpisodeLikes {
+                    episode_slug: unliked.clone(),
+                    like_count: 0,
+                    liked_by_me: false,
+                },
+            ]
+        );
 
-        // Remove subscription
-        let removed = remove_subscription(&pool, user.id, "one-piece")
+        assert!(unlike_episode(&pool, alice.id, &liked).await.unwrap());
+        assert!(!unlike_episode(&pool, alice.id, &liked).await.unwrap());
+        let likes = get_episode_likes(&pool, DEFAULT_TENANT_ID, None, &slugs)
             .await
-            .expect("Failed to remove subscription");
-        assert!(removed);
+            .unwrap();
+        assert_eq!(likes[0].like_count, 1);
+        assert!(!likes[0].liked_by_me);
 
-        // Verify removed
-        let is_sub = is_subscribed(&pool, user.id, "one-piece")
+        // Deleting a user takes their likes out of the counts
+        delete_user(&pool, bob.id)
             .await
-            .expect("Failed to check subscription");
-        assert!(!is_sub);
+            .expect("Failed to delete user");
+        let likes = get_episode_likes(&pool, DEFAULT_TENANT_ID, Some(alice.id), &slugs[..1])
+            .await
+            .unwrap();
+        assert_eq!(likes[0].like_count, 0);
 
-        // Clean up
-        delete_user(&pool, user.id)
+        delete_user(&pool, alice.id)
             .await
             .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_history_crud() {
+    #[ignore] // Requires database connection
+    async fn test_age_ratings() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_history@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let email = "test_age_ratings@example.com";
+        if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+            delete_user(&pool, user.id).await.ok();
         }
-
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", None)
+        let admin = create_user(&pool, DEFAULT_TENANT_ID, email, "hashed_password", None)
             .await
             .expect("Failed to create user");
 
-        // Add to history
-        let history = add_to_history(
-            &pool,
-            user.id,
-            "naruto-ep-1",
-            "naruto",
-            "Episode 1",
-            "Naruto",
-            "https://example.com/naruto-ep1.jpg",
-        )
-        .await
-        .expect("Failed to add to history");
-        assert_eq!(history.episode_slug, "naruto-ep-1");
-        assert_eq!(history.anime_slug, "naruto");
+        let ecchi = "test-age-ratings-ecchi";
+        let general = "test-age-ratings-general";
+        let unknown = "test-age-ratings-unknown";
+        for (slug, genre) in [(ecchi, "Ecchi"), (general, "Comedy")] {
+            delete_anime_detail(&pool, slug).await.unwrap();
+            delete_age_rating_override(&pool, slug).await.unwrap();
+            let detail = AnimeDetail {
+                title: slug.to_string(),
+                genres: vec![genre.to_string()],
+                ..Default::default()
+            };
+            save_anime_detail(&pool, slug, &detail).await.unwrap();
+            save_crawled_anime(&pool, &create_test_crawled_anime(slug))
+                .await
+                .unwrap();
+        }
 
-        // Get history
-        let history_list = get_history(&pool, user.id)
-            .await
-            .expect("Failed to get history");
-        assert_eq!(history_list.len(), 1);
-        assert_eq!(history_list[0].episode_slug, "naruto-ep-1");
+        let rating = get_anime_age_rating(&pool, ecchi).await.unwrap();
+        assert_eq!(rating.age_rating, Some(AgeRating::Adult));
+        assert_eq!(rating.override_rating, None);
+        let rating = get_anime_age_rating(&pool, unknown).await.unwrap();
+        assert_eq!(rating.age_rating, None);
 
-        // Remove from history
-        let removed = remove_from_history(&pool, user.id, "naruto-ep-1")
+        let slugs = [ecchi.to_string(), general.to_string(), unknown.to_string()];
+        let ratings = get_age_ratings(&pool, &slugs).await.unwrap();
+        assert_eq!(
+            ratings,
+            HashMap::from([
+                (ecchi.to_string(), AgeRating::Adult),
+                (general.to_string(), AgeRating::General)
+            ])
+        );
+
+        let catalog = |include_adult| {
+            let pool = &pool;
+            async move {
+                list_crawled_anime(pool, CatalogOrder::Title, None, include_adult, 100000, 0)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|anime| anime.slug)
+                    .filter(|slug| slug.starts_with("test-age-ratings-"))
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(catalog(false).await, vec![general.to_string()]);
+        assert_eq!(catalog(true).await.len(), 2);
+
+        // Overrides win over the genres and survive re-scrapes
+        set_age_rating_override(&pool, ecchi, AgeRating::General, admin.id)
             .await
-            .expect("Failed to remove from history");
-        assert!(removed);
+            .unwrap();
+        set_age_rating_override(&pool, general, AgeRating::Adult, admin.id)
+            .await
+            .unwrap();
+        let rescraped = AnimeDetail {
+            title: general.to_string(),
+            genres: vec!["Comedy".to_string(), "Romance".to_string()],
+            ..Default::default()
+        };
+        save_anime_detail(&pool, general, &rescraped).await.unwrap();
+        let rating = get_anime_age_rating(&pool, general).await.unwrap();
+        assert_eq!(rating.age_rating, Some(AgeRating::Adult));
+        assert_eq!(rating.derived_rating, Some(AgeRating::General));
+        assert_eq!(catalog(false).await, vec![ecchi.to_string()]);
+
+        // Mature anime are hidden like adult ones
+        set_age_rating_override(&pool, ecchi, AgeRating::Mature, admin.id)
+            .await
+            .unwrap();
+        let ratings = get_age_ratings(&pool, &slugs).await.unwrap();
+        assert_eq!(
+            ratings,
+            HashMap::from([
+                (ecchi.to_string(), AgeRating::Mature),
+                (general.to_string(), AgeRating::Adult)
+            ])
+        );
+        assert!(catalog(false).await.is_empty());
 
-        // Verify removed
-        let history_list = get_history(&pool, user.id)
+        assert!(delete_age_rating_override(&pool, ecchi).await.unwrap());
+        assert!(!delete_age_rating_override(&pool, ecchi).await.unwrap());
+        let rating = get_anime_age_rating(&pool, ecchi).await.unwrap();
+        assert_eq!(rating.age_rating, Some(AgeRating::Adult));
+
+        // Adult content stays hidden until the user opts in
+        assert!(
+            !get_user_preferences(&pool, admin.id)
+                .await
+                .unwrap()
+                .show_adult_content
+        );
+        let opt_in = UpdatePreferencesRequest {
+            show_adult_content: Some(true),
+            ..Default::default()
+        };
+        let preferences = update_user_preferences(&pool, admin.id, &opt_in)
             .await
-            .expect("Failed to get history");
-        assert!(history_list.is_empty());
+            .unwrap();
+        assert!(preferences.show_adult_content);
 
-        // Clean up
-        delete_user(&pool, user.id)
+        for slug in [ecchi, general] {
+            delete_age_rating_override(&pool, slug).await.unwrap();
+            delete_anime_detail(&pool, slug).await.unwrap();
+            delete_crawled_anime(&pool, slug).await.unwrap();
+        }
+        delete_user(&pool, admin.id)
             .await
             .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_history_update_timestamp_on_rewatch() {
+    #[ignore] // Requires database connection
+    async fn test_tags() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
-
-        let email = "test_history_rewatch@example.com";
+            .expect("Failed to connect to database");
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let emails = ["test_tags_a@example.com", "test_tags_b@example.com"];
+        for email in emails {
+            if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+                delete_user(&pool, user.id).await.ok();
+            }
         }
+        sqlx::query("DELETE FROM tags WHERE slug LIKE 'test-tags-%'")
+            .execute(&pool)
+            .await
+            .unwrap();
 
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", None)
+        let alice = create_user(&pool, DEFAULT_TENANT_ID, emails[0], "hashed_password", None)
+            .await
+            .expect("Failed to create user");
+        let bob = create_user(&pool, DEFAULT_TENANT_ID, emails[1], "hashed_password", None)
             .await
             .expect("Failed to create user");
+        let anime = format!("test-tags-anime-{}", alice.id);
+        let other = format!("test-tags-other-{}", alice.id);
+        for slug in [&anime, &other] {
+            let detail = AnimeDetail {
+                title: slug.clone(),
+                ..Default::default()
+            };
+            save_anime_detail(&pool, slug, &detail).await.unwrap();
+        }
 
-        // Add to history first time
-        let first_watch = add_to_history(
-            &pool,
-            user.id,
-            "naruto-ep-1",
-            "naruto",
-            "Episode 1",
-            "Naruto",
-            "https://example.com/naruto-ep1.jpg",
-        )
-        .await
-        .expect("Failed to add to history");
+        let curated = create_tag(&pool, "test-tags-curated", "Curated", "", alice.id)
+            .await
+            .unwrap();
+        assert!(curated.curated);
+        assert!(matches!(
+            create_tag(&pool, "test-tags-curated", "Again", "", alice.id).await,
+            Err(RepositoryError::Conflict(_))
+        ));
+
+        // Applying an unknown tag creates it uncurated
+        let tag = |user_id, anime: &str, tag: &'static str, name: &'static str| {
+            let (pool, anime) = (pool.clone(), anime.to_string());
+            async move { tag_anime(&pool, user_id, &anime, tag, name, 2).await }
+        };
+        assert_eq!(
+            tag(alice.id, &anime, "test-tags-new", "New").await.unwrap(),
+            TagOutcome::Tagged
+        );
+        assert_eq!(
+            tag(alice.id, &anime, "test-tags-new", "New").await.unwrap(),
+            TagOutcome::AlreadyTagged
+        );
+        assert_eq!(
+            tag(bob.id, &anime, "test-tags-new", "Ignored")
+                .await
+                .unwrap(),
+            TagOutcome::Tagged
+        );
+        assert_eq!(
+            tag(bob.id, &other, "test-tags-curated", "").await.unwrap(),
+            TagOutcome::Tagged
+        );
 
-        // Wait a bit
-        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        // Only scraped anime can be tagged
+        assert!(matches!(
+            tag(bob.id, "test-tags-missing", "test-tags-new", "New").await,
+            Err(RepositoryError::NotFound(_))
+        ));
 
-        // Re-watch (add again)
-        let second_watch = add_to_history(
+        // New tags are limited per user per day; existing ones aren't
+        assert_eq!(
+            tag(alice.id, &other, "test-tags-second", "Second")
+                .await
+                .unwrap(),
+            TagOutcome::Tagged
+        );
+        assert_eq!(
+            tag(alice.id, &other, "test-tags-third", "Third")
+                .await
+                .unwrap(),
+            TagOutcome::NewTagLimitReached
+        );
+        assert_eq!(
+            tag(alice.id, &other, "test-tags-curated", "")
+                .await
+                .unwrap(),
+            TagOutcome::Tagged
+        );
+        for slug in ["test-tags-second", "test-tags-curated"] {
+            untag_anime(&pool, alice.id, &other, slug).await.unwrap();
+        }
+        sqlx::query("DELETE FROM tags WHERE slug = 'test-tags-second'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let tags = get_anime_tags(&pool, &anime, Some(bob.id)).await.unwrap();
+        assert_eq!(
+            tags,
+            vec![AnimeTag {
+                slug: "test-tags-new".to_string(),
+                name: "New".to_string(),
+                curated: false,
+                votes: 2,
+                tagged_by_me: true,
+            }]
+        );
+
+        let tags = get_tags(&pool, None, 1000).await.unwrap();
+        let new = tags.iter().find(|t| t.slug == "test-tags-new").unwrap();
+        assert_eq!((new.anime_count, new.usage_count), (1, 2));
+        let tags = get_tags(&pool, Some(true), 1000).await.unwrap();
+        assert!(tags.iter().all(|t| t.curated));
+
+        let slugs = [anime.clone(), other.clone()];
+        let tagged = get_tagged_slugs(&pool, "test-tags-curated", &slugs)
+            .await
+            .unwrap();
+        assert_eq!(tagged, HashSet::from([other.clone()]));
+
+        // Renaming moves the slug; taking the last tag back empties the anime
+        let renamed = update_tag(
             &pool,
-            user.id,
-            "naruto-ep-1",
-            "naruto",
-            "Episode 1 - Updated",
-            "Naruto",
-            "https://example.com/naruto-ep1.jpg",
+            curated.id,
+            Some(("test-tags-renamed", "Renamed")),
+            None,
+            Some(false),
         )
         .await
-        .expect("Failed to update history");
+        .unwrap()
+        .expect("Tag should exist");
+        assert_eq!(renamed.slug, "test-tags-renamed");
+        assert!(!renamed.curated);
+        assert_eq!(renamed.usage_count, 1);
+        assert!(untag_anime(&pool, bob.id, &other, "test-tags-renamed")
+            .await
+            .unwrap());
+        assert!(get_anime_tags(&pool, &other, None)
+            .await
+            .unwrap()
+            .is_empty());
 
-        // Timestamp should be updated
-        assert!(second_watch.watched_at > first_watch.watched_at);
-        // Title should be updated
-        assert_eq!(second_watch.episode_title, "Episode 1 - Updated");
+        assert!(delete_tag(&pool, curated.id).await.unwrap());
+        assert!(!delete_tag(&pool, curated.id).await.unwrap());
+        assert!(get_tag(&pool, curated.id).await.unwrap().is_none());
 
-        // Should still be only one entry
-        let history_list = get_history(&pool, user.id)
+        // Tags applied are personal data
+        let data = collect_user_data(&pool, bob.id).await.unwrap().unwrap();
+        assert_eq!(data["anime_tags"].as_array().unwrap().len(), 1);
+
+        // Deleting a user takes their tags off
+        delete_user(&pool, bob.id)
             .await
-            .expect("Failed to get history");
-        assert_eq!(history_list.len(), 1);
+            .expect("Failed to delete user");
+        let tags = get_anime_tags(&pool, &anime, None).await.unwrap();
+        assert_eq!(tags[0].votes, 1);
 
-        // Clean up
-        delete_user(&pool, user.id)
+        delete_user(&pool, alice.id)
             .await
             .expect("Failed to delete user");
+        sqlx::query("DELETE FROM tags WHERE slug LIKE 'test-tags-%'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM anime_details WHERE slug LIKE 'test-tags-%'")
+            .execute(&pool)
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_history_sorted_by_most_recent() {
+    #[ignore] // Requires database connection
+    async fn test_community_top() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
+            .expect("Failed to connect to database");
 
-        let email = "test_history_sort@example.com";
-
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
+        let emails = [
+            "test_community_a@example.com",
+            "test_community_b@example.com",
+        ];
+        for email in emails {
+            if let Ok(Some((user, _))) = find_user_by_email(&pool, DEFAULT_TENANT_ID, email).await {
+                delete_user(&pool, user.id).await.ok();
+            }
         }
 
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", None)
+        let alice = create_user(&pool, DEFAULT_TENANT_ID, emails[0], "hashed_password", None)
             .await
             .expect("Failed to create user");
-
-        // Add multiple episodes
-        add_to_history(&pool, user.id, "ep-1", "anime", "Episode 1", "Anime", "")
+        let bob = create_user(&pool, DEFAULT_TENANT_ID, emails[1], "hashed_password", None)
             .await
-            .expect("Failed to add");
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-
-        add_to_history(&pool, user.id, "ep-2", "anime", "Episode 2", "Anime", "")
+            .expect("Failed to create user");
+        let popular = format!("test-community-popular-{}", alice.id);
+        let niche = format!("test-community-niche-{}", alice.id);
+
+        for user in [&alice, &bob] {
+            add_to_history(
+                &pool,
+                user.id,
+                &format!("{}-episode-1", popular),
+                &popular,
+                "Ep 1",
+                "Popular",
+                "",
+            )
             .await
-            .expect("Failed to add");
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-
-        add_to_history(&pool, user.id, "ep-3", "anime", "Episode 3", "Anime", "")
+            .unwrap();
+        }
+        add_to_history(
+            &pool,
+            alice.id,
+            &format!("{}-episode-1", niche),
+            &niche,
+            "Ep 1",
+            "Niche",
+            "",
+        )
+        .await
+        .unwrap();
+        add_favorite(&pool, bob.id, &niche, "Niche", "")
             .await
-            .expect("Failed to add");
+            .unwrap();
 
-        // Get history - should be sorted by most recent first
-        let history_list = get_history(&pool, user.id)
+        let find = |top: &[CommunityTopEntry], slug: &str| {
+            top.iter().find(|entry| entry.anime_slug == slug).cloned()
+        };
+        let top = compute_community_top(&pool, DEFAULT_TENANT_ID, None, 1000)
             .await
-            .expect("Failed to get history");
-
-        assert_eq!(history_list.len(), 3);
-        assert_eq!(history_list[0].episode_slug, "ep-3"); // Most recent
-        assert_eq!(history_list[1].episode_slug, "ep-2");
-        assert_eq!(history_list[2].episode_slug, "ep-1"); // Oldest
+            .unwrap();
+        let first = find(&top, &popular).expect("Popular anime should be ranked");
+        let second = find(&top, &niche).expect("Niche anime should be ranked");
+        assert_eq!((first.watchers, first.favorites), (2, 0));
+        assert_eq!((second.watchers, second.favorites), (1, 1));
+        assert!(first.rank < second.rank);
+
+        // Activity before the window doesn't count
+        let future = Some(Utc::now() + chrono::Duration::days(1));
+        let top = compute_community_top(&pool, DEFAULT_TENANT_ID, future, 1000)
+            .await
+            .unwrap();
+        assert!(find(&top, &popular).is_none());
+
+        // Opted-out users are left out
+        let opt_out = UpdatePreferencesRequest {
+            community_opt_out: Some(true),
+            ..Default::default()
+        };
+        let preferences = update_user_preferences(&pool, bob.id, &opt_out)
+            .await
+            .unwrap();
+        assert!(preferences.community_opt_out);
+        let top = compute_community_top(&pool, DEFAULT_TENANT_ID, None, 1000)
+            .await
+            .unwrap();
+        assert_eq!(find(&top, &popular).unwrap().watchers, 1);
+        assert_eq!(find(&top, &niche).unwrap().favorites, 0);
+
+        let cached = CommunityTop {
+            window: LeaderboardWindow::AllTime,
+            items: top,
+            computed_at: Utc::now().to_rfc3339(),
+        };
+        save_community_top(&pool, DEFAULT_TENANT_ID, &cached)
+            .await
+            .unwrap();
+        let fresh =
+            get_cached_community_top(&pool, DEFAULT_TENANT_ID, LeaderboardWindow::AllTime, 60_000)
+                .await
+                .unwrap()
+                .expect("Leaderboard should be cached");
+        assert_eq!(fresh.items, cached.items);
+        assert!(
+            get_cached_community_top(&pool, DEFAULT_TENANT_ID, LeaderboardWindow::AllTime, 0)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        sqlx::query("DELETE FROM community_top_cache WHERE tenant_id = $1")
+            .bind(DEFAULT_TENANT_ID)
+            .execute(&pool)
+            .await
+            .unwrap();
 
-        // Clean up
-        delete_user(&pool, user.id)
+        delete_user(&pool, alice.id)
+            .await
+            .expect("Failed to delete user");
+        delete_user(&pool, bob.id)
             .await
             .expect("Failed to delete user");
     }
 
     #[tokio::test]
-    #[ignore]
-    async fn test_clear_history() {
+    #[ignore] // Requires database connection
+    async fn test_synopsis_translations() {
         dotenvy::dotenv().ok();
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
         let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to connect");
+            .expect("Failed to connect to database");
 
-        let email = "test_clear_history@example.com";
+        let synopsis = "Test sinopsis: Naruto Uzumaki adalah seorang ninja muda.";
+        assert_eq!(
+            get_synopsis_translation(&pool, synopsis, "en")
+                .await
+                .unwrap(),
+            None
+        );
 
-        // Clean up first
-        if let Ok(Some((user, _))) = find_user_by_email(&pool, email).await {
-            let _ = delete_user(&pool, user.id).await;
-        }
+        save_synopsis_translation(&pool, synopsis, "en", "Naruto is a young ninja.")
+            .await
+            .unwrap();
+        save_synopsis_translation(&pool, synopsis, "en", "Naruto Uzumaki is a young ninja.")
+            .await
+            .unwrap();
+        assert_eq!(
+            get_synopsis_translation(&pool, synopsis, "en")
+                .await
+                .unwrap(),
+            Some("Naruto Uzumaki is a young ninja.".to_string())
+        );
 
-        // Create user
-        let user = create_user(&pool, email, "hashed_password", None)
+        // A changed synopsis needs a new translation
+        assert_eq!(
+            get_synopsis_translation(&pool, &format!("{} Lagi.", synopsis), "en")
+                .await
+                .unwrap(),
+            None
+        );
+
+        sqlx::query("DELETE FROM synopsis_translations WHERE source_hash = $1")
+            .bind(content_hash(synopsis))
+            .execute(&pool)
             .await
-            .expect("Failed to create user");
+            .unwrap();
+    }
 
-        // Add multiple episodes
-        add_to_history(&pool, user.id, "ep-1", "anime", "Episode 1", "Anime", "")
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_outbox_events() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
             .await
-            .expect("Failed to add");
-        add_to_history(&pool, user.id, "ep-2", "anime", "Episode 2", "Anime", "")
+            .expect("Failed to connect to database");
+
+        // An event recorded in a rolled back unit of work is gone with it
+        let mut uow = super::super::UnitOfWork::begin(&pool).await.unwrap();
+        let rolled_back = record_outbox_event(uow.conn(), "test.outbox", "{}")
             .await
-            .expect("Failed to add");
+            .unwrap();
+        uow.rollback().await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox_events WHERE id = $1")
+            .bind(rolled_back)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
 
-        // Clear history
-        let deleted = clear_history(&pool, user.id)
+        let id = record_outbox_event(&pool, "test.outbox", r#"{"n":1}"#)
             .await
-            .expect("Failed to clear history");
-        assert_eq!(deleted, 2);
+            .unwrap();
 
-        // Verify cleared
-        let history_list = get_history(&pool, user.id)
+        // A claimed event is skipped by other dispatchers until released
+        let mut first = pool.begin().await.unwrap();
+        let claimed = claim_next_outbox_event(&mut first)
             .await
-            .expect("Failed to get history");
-        assert!(history_list.is_empty());
+            .unwrap()
+            .expect("An event should be due");
+        let mut second = pool.begin().await.unwrap();
+        let other = claim_next_outbox_event(&mut second).await.unwrap();
+        assert_ne!(other.map(|event| event.id), Some(claimed.id));
+        second.rollback().await.unwrap();
+        first.rollback().await.unwrap();
+
+        // A failed event isn't due again until its retry
+        mark_outbox_failed(&pool, id, "boom", 3600).await.unwrap();
+        let row = sqlx::query(
+            "SELECT attempts, last_error, available_at > CURRENT_TIMESTAMP AS later \
+             FROM outbox_events WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(row.get::<i32, _>("attempts"), 1);
+        assert_eq!(
+            row.get::<Option<String>, _>("last_error").as_deref(),
+            Some("boom")
+        );
+        assert!(row.get::<bool, _>("later"));
 
-        // Clean up
-        delete_user(&pool, user.id)
+        mark_outbox_dispatched(&pool, id).await.unwrap();
+        let dispatched: bool =
+            sqlx::query_scalar("SELECT dispatched_at IS NOT NULL FROM outbox_events WHERE id = $1")
+                .bind(id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(dispatched);
+
+        // Delivered events are deleted once past retention; pending ones stay
+        let pending = record_outbox_event(&pool, "test.outbox", "{}")
             .await
-            .expect("Failed to delete user");
+            .unwrap();
+        let deleted =
+            delete_dispatched_outbox_events(&pool, Utc::now() + chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        assert!(deleted >= 1);
+        let remaining: Vec<i64> =
+            sqlx::query_scalar("SELECT id FROM outbox_events WHERE id = ANY($1) ORDER BY id")
+                .bind(vec![id, pending])
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(remaining, vec![pending]);
+
+        sqlx::query("DELETE FROM outbox_events WHERE event_type = 'test.outbox'")
+            .execute(&pool)
+            .await
+            .unwrap();
     }
 }
@@ -0,0 +1,110 @@
+//! Several repository calls in one transaction
+//!
+//! Repository functions taking an executor (`impl PgExecutor`) or a
+//! `&mut PgConnection` run against either the pool or a [`UnitOfWork`]. A flow
+//! that mustn't leave half its writes behind, such as registering a user and
+//! queueing their verification email, begins a unit of work, passes
+//! [`UnitOfWork::conn`] to each call, and commits at the end. Returning
+//! early, or dropping the unit of work on an error, rolls everything back.
+//!
+//! Within a unit of work a failed statement fails the whole transaction, so
+//! a step whose failure should be tolerated belongs before or after it.
+
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+
+use super::repository::RepositoryResult;
+
+/// An open transaction for a multi-step flow
+///
+/// Rolled back when dropped without [`commit`](Self::commit).
+#[derive(Debug)]
+pub struct UnitOfWork {
+    tx: Transaction<'static, Postgres>,
+}
+
+impl UnitOfWork {
+    /// Begin a unit of work on a connection from `pool`
+    pub async fn begin(pool: &PgPool) -> RepositoryResult<Self> {
+        Ok(Self {
+            tx: pool.begin().await?,
+        })
+    }
+
+    /// Connection to pass to repository calls
+    pub fn conn(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+
+    /// Commit every write made through the unit of work
+    pub async fn commit(self) -> RepositoryResult<()> {
+        self.tx.commit().await?;
+        Ok(())
+    }
+
+    /// Undo every write made through the unit of work
+    pub async fn rollback(self) -> RepositoryResult<()> {
+        self.tx.rollback().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        create_user, create_verification_token, delete_user, find_user_by_email,
+        find_verification_token,
+    };
+
+    #[tokio::test]
+    #[ignore] // Requires a running database
+    async fn test_unit_of_work_commits_or_rolls_back_together() {
+        dotenvy::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect");
+
+        let email = format!("uow-{}@example.com", uuid::Uuid::new_v4());
+        let token = uuid::Uuid::new_v4().to_string();
+
+        // Dropped without committing: neither the user nor the token stays
+        {
+            let mut uow = UnitOfWork::begin(&pool).await.unwrap();
+            let user = create_user(uow.conn(), 1, &email, "hash", None)
+                .await
+                .unwrap();
+            create_verification_token(uow.conn(), user.id, &token, "email_verification", 1)
+                .await
+                .unwrap();
+        }
+        assert!(find_user_by_email(&pool, 1, &email)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(find_verification_token(&pool, &token)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Committed: both are there
+        let mut uow = UnitOfWork::begin(&pool).await.unwrap();
+        let user = create_user(uow.conn(), 1, &email, "hash", None)
+            .await
+            .unwrap();
+        create_verification_token(uow.conn(), user.id, &token, "email_verification", 1)
+            .await
+            .unwrap();
+        uow.commit().await.unwrap();
+        assert!(find_user_by_email(&pool, 1, &email)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(find_verification_token(&pool, &token)
+            .await
+            .unwrap()
+            .is_some());
+
+        let _ = delete_user(&pool, user.id).await;
+    }
+}
@@ -3,13 +3,34 @@
 //! This module provides functionality for:
 //! - Sending email verification emails
 //! - Sending password reset emails
+//! - Sending new episode notifications
+//! - Sending saved search match notifications
+//! - Sending moderation notices (content removed, account muted)
+//! - Sending account reactivation links
+//!
+//! Emails are rendered from localized templates (see [`templates`]) and sent
+//! as multipart messages with HTML and plaintext parts.
+//!
+//! Handlers don't send mail directly; they queue an [`EmailMessage`] through
+//! `jobs::enqueue_email` and a background worker delivers it with retries.
+
+pub mod templates;
+
+use std::time::Duration;
 
-use lettre::message::header::ContentType;
+use lettre::message::MultiPart;
 use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::PoolConfig;
 use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::config::SmtpConfig;
+use crate::config::{SmtpConfig, SmtpTlsMode};
+
+/// How long an idle pooled SMTP connection is kept open
+const POOL_IDLE_TIMEOUT_SECS: u64 = 60;
+
+pub use templates::{EmailTemplates, Language, RenderedEmail};
 
 /// Email service errors
 #[derive(Debug, Error)]
@@ -20,40 +41,119 @@ pub enum EmailError {
     #[error("Failed to build email: {0}")]
     BuildError(String),
 
+    #[error("Email template error: {0}")]
+    TemplateError(String),
+
     #[error("Email service not configured")]
     NotConfigured,
 }
 
+/// A transactional email and the data needed to render it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "template", rename_all = "camelCase")]
+pub enum EmailMessage {
+    /// Email address verification link
+    Verification { token: String },
+    /// Password reset link
+    PasswordReset { token: String },
+    /// New episode of a subscribed anime
+    #[serde(rename_all = "camelCase")]
+    NewEpisode {
+        anime_title: String,
+        episode_title: String,
+        episode_url: String,
+    },
+    /// A subscribed anime finished airing
+    #[serde(rename_all = "camelCase")]
+    SeriesCompleted {
+        anime_slug: String,
+        anime_title: String,
+        total_episodes: String,
+    },
+    /// New crawled anime matching a saved search
+    #[serde(rename_all = "camelCase")]
+    SavedSearchMatches {
+        search_name: String,
+        match_count: usize,
+        titles: Vec<String>,
+    },
+    /// A moderator removed the user's content
+    #[serde(rename_all = "camelCase")]
+    ContentRemoved {
+        content_type: String,
+        reason: String,
+        strike_count: i64,
+    },
+    /// The user collected enough strikes to be muted
+    #[serde(rename_all = "camelCase")]
+    AccountMuted {
+        muted_until: String,
+        strike_count: i64,
+    },
+    /// Link restoring access to a deactivated account
+    Reactivation { token: String },
+}
+
+impl EmailMessage {
+    /// Template name stored with the delivery record
+    pub fn template_name(&self) -> &'static str {
+        match self {
+            EmailMessage::Verification { .. } => "verification",
+            EmailMessage::PasswordReset { .. } => "passwordReset",
+            EmailMessage::NewEpisode { .. } => "newEpisode",
+            EmailMessage::SeriesCompleted { .. } => "seriesCompleted",
+            EmailMessage::SavedSearchMatches { .. } => "savedSearchMatches",
+            EmailMessage::ContentRemoved { .. } => "contentRemoved",
+            EmailMessage::AccountMuted { .. } => "accountMuted",
+            EmailMessage::Reactivation { .. } => "reactivation",
+        }
+    }
+}
+
 /// Email service for sending transactional emails
+///
+/// Holds a single pooled SMTP transport that is shared by all clones.
 #[derive(Clone)]
 pub struct EmailService {
     config: SmtpConfig,
     frontend_url: String,
+    templates: EmailTemplates,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
 }
 
 impl EmailService {
-    /// Create a new email service
-    pub fn new(config: SmtpConfig, frontend_url: String) -> Self {
-        Self {
+    /// Create a new email service using the bundled templates
+    ///
+    /// Connections are opened lazily, so this doesn't contact the SMTP server.
+    pub fn new(config: SmtpConfig, frontend_url: String) -> Result<Self, EmailError> {
+        let transport = build_transport(&config)?;
+        Ok(Self {
             config,
             frontend_url,
-        }
+            templates: EmailTemplates::bundled(),
+            transport,
+        })
     }
 
-    /// Build SMTP transport
-    fn build_transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>, EmailError> {
-        let creds = Credentials::new(self.config.username.clone(), self.config.password.clone());
+    /// Use a custom template registry (e.g. loaded from EMAIL_TEMPLATES_DIR)
+    pub fn with_templates(mut self, templates: EmailTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
 
-        AsyncSmtpTransport::<Tokio1Executor>::relay(&self.config.host)
-            .map_err(|e| EmailError::SmtpError(e.to_string()))?
-            .credentials(creds)
-            .port(self.config.port)
-            .build()
-            .pipe(Ok)
+    /// Check that the SMTP server accepts connections
+    pub async fn health_check(&self) -> Result<(), EmailError> {
+        match self.transport.test_connection().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(EmailError::SmtpError(
+                "SMTP server rejected the connection".to_string(),
+            )),
+            Err(e) => Err(EmailError::SmtpError(e.to_string())),
+        }
     }
 
-    /// Send an email
-    async fn send_email(&self, to: &str, subject: &str, body: String) -> Result<(), EmailError> {
+    /// Send a rendered email as a multipart (plaintext + HTML) message
+    async fn send_email(&self, to: &str, email: RenderedEmail) -> Result<(), EmailError> {
         let from = format!("{} <{}>", self.config.from_name, self.config.from_email);
 
         let email = Message::builder()
@@ -64,13 +164,11 @@ impl EmailService {
             .to(to
                 .parse()
                 .map_err(|e| EmailError::BuildError(format!("{}", e)))?)
-            .subject(subject)
-            .header(ContentType::TEXT_HTML)
-            .body(body)
+            .subject(email.subject)
+            .multipart(MultiPart::alternative_plain_html(email.text, email.html))
             .map_err(|e| EmailError::BuildError(e.to_string()))?;
 
-        let transport = self.build_transport()?;
-        transport
+        self.transport
             .send(email)
             .await
             .map_err(|e| EmailError::SmtpError(e.to_string()))?;
@@ -78,73 +176,148 @@ impl EmailService {
         Ok(())
     }
 
-    /// Send email verification email
-    pub async fn send_verification_email(&self, to: &str, token: &str) -> Result<(), EmailError> {
-        let verification_url = format!("{}/verify-email?token={}", self.frontend_url, token);
-
-        let body = format!(
-            r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>Verify Your Email</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h1 style="color: #2563eb;">Verify Your Email Address</h1>
-        <p>Thank you for registering! Please click the button below to verify your email address:</p>
-        <p style="text-align: center; margin: 30px 0;">
-            <a href="{}" style="background-color: #2563eb; color: white; padding: 12px 24px; text-decoration: none; border-radius: 6px; display: inline-block;">
-                Verify Email
-            </a>
-        </p>
-        <p>Or copy and paste this link into your browser:</p>
-        <p style="word-break: break-all; color: #666;">{}</p>
-        <p style="color: #666; font-size: 14px; margin-top: 30px;">
-            This link will expire in 24 hours. If you didn't create an account, you can safely ignore this email.
-        </p>
-    </div>
-</body>
-</html>"#,
-            verification_url, verification_url
-        );
+    /// Render a message in the given language without sending it
+    pub fn render_message(
+        &self,
+        language: Language,
+        message: &EmailMessage,
+    ) -> Result<RenderedEmail, EmailError> {
+        let template = self.templates.get(language, message.template_name())?;
 
-        self.send_email(to, "Verify Your Email Address", body).await
+        match message {
+            EmailMessage::Verification { token } => {
+                let url = format!("{}/verify-email?token={}", self.frontend_url, token);
+                template.render(&[("url", &url)])
+            }
+            EmailMessage::PasswordReset { token } => {
+                let url = format!("{}/reset-password?token={}", self.frontend_url, token);
+                template.render(&[("url", &url)])
+            }
+            EmailMessage::NewEpisode {
+                anime_title,
+                episode_title,
+                episode_url,
+            } => template.render(&[
+                ("animeTitle", anime_title),
+                ("episodeTitle", episode_title),
+                ("url", episode_url),
+            ]),
+            EmailMessage::SeriesCompleted {
+                anime_slug,
+                anime_title,
+                total_episodes,
+            } => {
+                let url = format!("{}/anime/{}", self.frontend_url, anime_slug);
+                template.render(&[
+                    ("animeTitle", anime_title),
+                    ("totalEpisodes", total_episodes),
+                    ("url", &url),
+                ])
+            }
+            EmailMessage::SavedSearchMatches {
+                search_name,
+                match_count,
+                titles,
+            } => {
+                let url = format!("{}/saved-searches", self.frontend_url);
+                template.render(&[
+                    ("searchName", search_name),
+                    ("matchCount", &match_count.to_string()),
+                    ("titles", &titles.join(", ")),
+                    ("url", &url),
+                ])
+            }
+            EmailMessage::ContentRemoved {
+                content_type,
+                reason,
+                strike_count,
+            } => {
+                let url = format!("{}/guidelines", self.frontend_url);
+                template.render(&[
+                    ("contentType", content_type),
+                    ("reason", reason),
+                    ("strikeCount", &strike_count.to_string()),
+                    ("url", &url),
+                ])
+            }
+            EmailMessage::AccountMuted {
+                muted_until,
+                strike_count,
+            } => {
+                let url = format!("{}/guidelines", self.frontend_url);
+                template.render(&[
+                    ("mutedUntil", muted_until),
+                    ("strikeCount", &strike_count.to_string()),
+                    ("url", &url),
+                ])
+            }
+            EmailMessage::Reactivation { token } => {
+                let url = format!("{}/reactivate?token={}", self.frontend_url, token);
+                template.render(&[("url", &url)])
+            }
+        }
     }
 
-    /// Send password reset email
-    pub async fn send_password_reset_email(&self, to: &str, token: &str) -> Result<(), EmailError> {
-        let reset_url = format!("{}/reset-password?token={}", self.frontend_url, token);
-
-        let body = format!(
-            r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>Reset Your Password</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h1 style="color: #2563eb;">Reset Your Password</h1>
-        <p>We received a request to reset your password. Click the button below to create a new password:</p>
-        <p style="text-align: center; margin: 30px 0;">
-            <a href="{}" style="background-color: #2563eb; color: white; padding: 12px 24px; text-decoration: none; border-radius: 6px; display: inline-block;">
-                Reset Password
-            </a>
-        </p>
-        <p>Or copy and paste this link into your browser:</p>
-        <p style="word-break: break-all; color: #666;">{}</p>
-        <p style="color: #666; font-size: 14px; margin-top: 30px;">
-            This link will expire in 1 hour. If you didn't request a password reset, you can safely ignore this email.
-        </p>
-    </div>
-</body>
-</html>"#,
-            reset_url, reset_url
-        );
+    /// Render and send a queued email message
+    pub async fn send_message(
+        &self,
+        to: &str,
+        language: Language,
+        message: &EmailMessage,
+    ) -> Result<(), EmailError> {
+        let email = self.render_message(language, message)?;
+        self.send_email(to, email).await
+    }
 
-        self.send_email(to, "Reset Your Password", body).await
+    /// Send email verification email
+    pub async fn send_verification_email(
+        &self,
+        to: &str,
+        language: Language,
+        token: &str,
+    ) -> Result<(), EmailError> {
+        let message = EmailMessage::Verification {
+            token: token.to_string(),
+        };
+        self.send_message(to, language, &message).await
     }
+
+    /// Send password reset email
+    pub async fn send_password_reset_email(
+        &self,
+        to: &str,
+        language: Language,
+        token: &str,
+    ) -> Result<(), EmailError> {
+        let message = EmailMessage::PasswordReset {
+            token: token.to_string(),
+        };
+        self.send_message(to, language, &message).await
+    }
+}
+
+/// Build the pooled SMTP transport for the configured TLS mode
+fn build_transport(config: &SmtpConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>, EmailError> {
+    let creds = Credentials::new(config.username.clone(), config.password.clone());
+
+    let builder = match config.tls {
+        SmtpTlsMode::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+            .map_err(|e| EmailError::SmtpError(e.to_string()))?,
+        SmtpTlsMode::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            .map_err(|e| EmailError::SmtpError(e.to_string()))?,
+        SmtpTlsMode::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+    };
+
+    let pool = PoolConfig::new()
+        .max_size(config.pool_max_size.max(1))
+        .idle_timeout(Duration::from_secs(POOL_IDLE_TIMEOUT_SECS));
+
+    builder
+        .credentials(creds)
+        .port(config.port)
+        .pool_config(pool)
+        .build()
+        .pipe(Ok)
 }
 
 /// Helper trait for pipe syntax
@@ -171,4 +344,75 @@ mod tests {
         let err = EmailError::SmtpError("connection failed".to_string());
         assert_eq!(err.to_string(), "SMTP transport error: connection failed");
     }
+
+    #[test]
+    fn test_email_message_serialization() {
+        let message = EmailMessage::PasswordReset {
+            token: "abc".to_string(),
+        };
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, r#"{"template":"passwordReset","token":"abc"}"#);
+        assert_eq!(message.template_name(), "passwordReset");
+
+        let parsed: EmailMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, message);
+    }
+
+    fn test_config() -> SmtpConfig {
+        SmtpConfig {
+            host: "localhost".to_string(),
+            port: 587,
+            username: String::new(),
+            password: String::new(),
+            from_email: "noreply@example.com".to_string(),
+            from_name: "Anime Scraper".to_string(),
+            tls: SmtpTlsMode::StartTls,
+            pool_max_size: 4,
+        }
+    }
+
+    #[test]
+    fn test_smtp_tls_mode_parse() {
+        assert_eq!(SmtpTlsMode::parse(None, 587), SmtpTlsMode::StartTls);
+        assert_eq!(SmtpTlsMode::parse(None, 465), SmtpTlsMode::Implicit);
+        assert_eq!(SmtpTlsMode::parse(Some("TLS"), 587), SmtpTlsMode::Implicit);
+        assert_eq!(
+            SmtpTlsMode::parse(Some("starttls"), 465),
+            SmtpTlsMode::StartTls
+        );
+        assert_eq!(SmtpTlsMode::parse(Some("none"), 25), SmtpTlsMode::None);
+    }
+
+    #[tokio::test]
+    async fn test_service_builds_transport_for_each_tls_mode() {
+        for tls in [
+            SmtpTlsMode::StartTls,
+            SmtpTlsMode::Implicit,
+            SmtpTlsMode::None,
+        ] {
+            let config = SmtpConfig {
+                tls,
+                ..test_config()
+            };
+            assert!(EmailService::new(config, String::new()).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render_message_localized() {
+        let service =
+            EmailService::new(test_config(), "https://app.example.com".to_string()).unwrap();
+        let message = EmailMessage::Verification {
+            token: "abc".to_string(),
+        };
+
+        let en = service.render_message(Language::En, &message).unwrap();
+        assert_eq!(en.subject, "Verify Your Email Address");
+        assert!(en
+            .text
+            .contains("https://app.example.com/verify-email?token=abc"));
+
+        let id = service.render_message(Language::Id, &message).unwrap();
+        assert_eq!(id.subject, "Verifikasi Alamat Email Anda");
+    }
 }
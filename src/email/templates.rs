@@ -0,0 +1,367 @@
+//! Email templates and localization
+//!
+//! Each template is a pair of files under `templates/email/<lang>/`:
+//! - `<name>.html` - HTML body
+//! - `<name>.txt` - Plaintext alternative; its first line is `Subject: ...`
+//!
+//! The bundled templates are compiled into the binary. Setting
+//! `EMAIL_TEMPLATES_DIR` overrides any of them with files from that directory.
+//! Templates are rendered with Tera: variables are written as `{{name}}` and
+//! are HTML-escaped in the HTML part.
+
+use std::collections::HashMap;
+use std::error::Error as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tera::{Context, Tera};
+use tracing::info;
+
+use super::EmailError;
+
+/// Supported email languages
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// English
+    #[default]
+    En,
+    /// Indonesian
+    Id,
+}
+
+impl Language {
+    /// All supported languages
+    pub const ALL: [Language; 2] = [Language::En, Language::Id];
+
+    /// Parse a language code (e.g. "id", "id-ID", "en-US"), falling back to English
+    pub fn from_code(code: &str) -> Self {
+        let primary = code.split(['-', '_']).next().unwrap_or("").trim();
+        if primary.eq_ignore_ascii_case("id") {
+            Language::Id
+        } else {
+            Language::En
+        }
+    }
+
+    /// ISO 639-1 language code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Id => "id",
+        }
+    }
+}
+
+/// Template names shipped with the service
+pub const TEMPLATE_NAMES: [&str; 8] = [
+    "verification",
+    "passwordReset",
+    "newEpisode",
+    "seriesCompleted",
+    "savedSearchMatches",
+    "contentRemoved",
+    "accountMuted",
+    "reactivation",
+];
+
+/// Bundled (html, txt) sources for a language/template pair
+fn bundled(language: Language, name: &str) -> Option<(&'static str, &'static str)> {
+    macro_rules! pair {
+        ($lang:literal, $name:literal) => {
+            (
+                include_str!(concat!(
+                    "../../templates/email/",
+                    $lang,
+                    "/",
+                    $name,
+                    ".html"
+                )),
+                include_str!(concat!("../../templates/email/", $lang, "/", $name, ".txt")),
+            )
+        };
+    }
+
+    let sources = match (language, name) {
+        (Language::En, "verification") => pair!("en", "verification"),
+        (Language::En, "passwordReset") => pair!("en", "passwordReset"),
+        (Language::En, "newEpisode") => pair!("en", "newEpisode"),
+        (Language::En, "seriesCompleted") => pair!("en", "seriesCompleted"),
+        (Language::En, "savedSearchMatches") => pair!("en", "savedSearchMatches"),
+        (Language::En, "contentRemoved") => pair!("en", "contentRemoved"),
+        (Language::En, "accountMuted") => pair!("en", "accountMuted"),
+        (Language::En, "reactivation") => pair!("en", "reactivation"),
+        (Language::Id, "verification") => pair!("id", "verification"),
+        (Language::Id, "passwordReset") => pair!("id", "passwordReset"),
+        (Language::Id, "newEpisode") => pair!("id", "newEpisode"),
+        (Language::Id, "seriesCompleted") => pair!("id", "seriesCompleted"),
+        (Language::Id, "savedSearchMatches") => pair!("id", "savedSearchMatches"),
+        (Language::Id, "contentRemoved") => pair!("id", "contentRemoved"),
+        (Language::Id, "accountMuted") => pair!("id", "accountMuted"),
+        (Language::Id, "reactivation") => pair!("id", "reactivation"),
+        _ => return None,
+    };
+    Some(sources)
+}
+
+/// Names the parts of a template are compiled under; Tera escapes the ones
+/// ending in .html
+const SUBJECT_PART: &str = "subject.txt";
+const HTML_PART: &str = "body.html";
+const TEXT_PART: &str = "body.txt";
+
+/// A parsed email template
+#[derive(Debug, Clone)]
+pub struct EmailTemplate {
+    /// Subject line template
+    pub subject: String,
+    /// HTML body template
+    pub html: String,
+    /// Plaintext body template
+    pub text: String,
+    /// The three parts, compiled
+    compiled: Tera,
+}
+
+impl EmailTemplate {
+    /// Build a template from its HTML and plaintext sources
+    ///
+    /// The plaintext source must start with a `Subject: ` line. Syntax errors
+    /// in any part are reported here rather than when sending.
+    pub fn parse(html: &str, text: &str) -> Result<Self, EmailError> {
+        let (first_line, rest) = text.split_once('\n').unwrap_or((text, ""));
+        let subject = first_line
+            .strip_prefix("Subject:")
+            .ok_or_else(|| {
+                EmailError::TemplateError("Plaintext template must start with 'Subject:'".into())
+            })?
+            .trim()
+            .to_string();
+
+        let text = rest.trim_start_matches(['\r', '\n']).to_string();
+
+        let mut compiled = Tera::default();
+        compiled.set_escape_fn(escape_html);
+        compiled
+            .add_raw_templates([
+                (SUBJECT_PART, subject.as_str()),
+                (HTML_PART, html),
+                (TEXT_PART, text.as_str()),
+            ])
+            .map_err(template_error)?;
+
+        Ok(Self {
+            subject,
+            html: html.to_string(),
+            text,
+            compiled,
+        })
+    }
+
+    /// Render the subject, HTML body, and plaintext body
+    ///
+    /// Unknown variables are errors so typos in templates surface instead of
+    /// sending broken mail.
+    pub fn render(&self, vars: &[(&str, &str)]) -> Result<RenderedEmail, EmailError> {
+        let mut context = Context::new();
+        for (name, value) in vars {
+            context.insert(*name, value);
+        }
+        let render = |part| self.compiled.render(part, &context).map_err(template_error);
+        Ok(RenderedEmail {
+            subject: render(SUBJECT_PART)?,
+            html: render(HTML_PART)?,
+            text: render(TEXT_PART)?,
+        })
+    }
+}
+
+/// Template error with the causes Tera reports, e.g. the unknown variable
+fn template_error(e: tera::Error) -> EmailError {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    EmailError::TemplateError(message)
+}
+
+/// A rendered email ready to send
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Registry of templates keyed by language and name
+#[derive(Debug, Clone)]
+pub struct EmailTemplates {
+    templates: HashMap<(Language, String), EmailTemplate>,
+}
+
+impl EmailTemplates {
+    /// Load the bundled templates
+    pub fn bundled() -> Self {
+        let mut templates = HashMap::new();
+        for language in Language::ALL {
+            for name in TEMPLATE_NAMES {
+                if let Some((html, text)) = bundled(language, name) {
+                    let template =
+                        EmailTemplate::parse(html, text).expect("bundled email template is valid");
+                    templates.insert((language, name.to_string()), template);
+                }
+            }
+        }
+        Self { templates }
+    }
+
+    /// Load the bundled templates, overriding them with files from `dir`
+    ///
+    /// Both the `.html` and `.txt` file must exist for an override to apply.
+    pub fn load(dir: Option<&Path>) -> Result<Self, EmailError> {
+        let mut registry = Self::bundled();
+        let Some(dir) = dir else {
+            return Ok(registry);
+        };
+
+        for language in Language::ALL {
+            for name in TEMPLATE_NAMES {
+                let base = dir.join(language.code()).join(name);
+                let html = std::fs::read_to_string(base.with_extension("html"));
+                let text = std::fs::read_to_string(base.with_extension("txt"));
+                if let (Ok(html), Ok(text)) = (html, text) {
+                    info!("Loaded email template override {}", base.display());
+                    registry.templates.insert(
+                        (language, name.to_string()),
+                        EmailTemplate::parse(&html, &text)?,
+                    );
+                }
+            }
+        }
+
+        Ok(registry)
+    }
+
+    /// Get a template, falling back to English when a translation is missing
+    pub fn get(&self, language: Language, name: &str) -> Result<&EmailTemplate, EmailError> {
+        self.templates
+            .get(&(language, name.to_string()))
+            .or_else(|| self.templates.get(&(Language::En, name.to_string())))
+            .ok_or_else(|| EmailError::TemplateError(format!("Unknown template: {}", name)))
+    }
+}
+
+impl Default for EmailTemplates {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+/// Escape a value for inclusion in HTML text or attributes
+///
+/// Unlike Tera's default, slashes are kept so links stay readable in the
+/// source of the mail.
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_from_code() {
+        assert_eq!(Language::from_code("id"), Language::Id);
+        assert_eq!(Language::from_code("id-ID"), Language::Id);
+        assert_eq!(Language::from_code("en-US"), Language::En);
+        assert_eq!(Language::from_code("fr"), Language::En);
+        assert_eq!(Language::from_code(""), Language::En);
+    }
+
+    #[test]
+    fn test_render_substitutes_and_escapes() {
+        let template = EmailTemplate::parse(
+            r#"<b>{{ title }}</b> <a href="{{url}}">Watch</a>"#,
+            "Subject: New: {{ title }}\n\nTitle: {{title}}",
+        )
+        .unwrap();
+        let vars = [
+            ("title", "Tom & Jerry <3"),
+            ("url", "https://example.com/x\" onclick=\"alert('hi')"),
+        ];
+
+        let rendered = template.render(&vars).unwrap();
+        assert_eq!(
+            rendered.html,
+            "<b>Tom &amp; Jerry &lt;3</b> \
+             <a href=\"https://example.com/x&quot; onclick=&quot;alert(&#39;hi&#39;)\">Watch</a>"
+        );
+        assert_eq!(rendered.subject, "New: Tom & Jerry <3");
+        assert_eq!(rendered.text, "Title: Tom & Jerry <3");
+    }
+
+    #[test]
+    fn test_render_rejects_unknown_variable() {
+        let template = EmailTemplate::parse("<p>{{missing}}</p>", "Subject: Hi\n").unwrap();
+        let error = template.render(&[]).unwrap_err().to_string();
+        assert!(error.contains("missing"), "{}", error);
+        assert!(EmailTemplate::parse("{{unterminated", "Subject: Hi\n").is_err());
+    }
+
+    #[test]
+    fn test_bundled_templates_render() {
+        let templates = EmailTemplates::bundled();
+        let vars = [
+            ("url", "https://example.com/x"),
+            ("animeTitle", "Naruto"),
+            ("episodeTitle", "Episode 1"),
+            ("totalEpisodes", "28"),
+            ("searchName", "Isekai TV"),
+            ("matchCount", "2"),
+            ("titles", "Re:Zero, Mushoku Tensei"),
+            ("contentType", "comment"),
+            ("reason", "Spoilers"),
+            ("strikeCount", "3"),
+            ("mutedUntil", "2024-12-27 10:00 UTC"),
+        ];
+
+        for language in Language::ALL {
+            for name in TEMPLATE_NAMES {
+                let rendered = templates
+                    .get(language, name)
+                    .unwrap()
+                    .render(&vars)
+                    .unwrap();
+                assert!(!rendered.subject.is_empty());
+                assert!(rendered.text.contains("https://example.com/x"));
+                assert!(rendered.html.contains("https://example.com/x"));
+            }
+        }
+
+        let subject = &templates.get(Language::Id, "verification").unwrap().subject;
+        assert_eq!(subject, "Verifikasi Alamat Email Anda");
+    }
+
+    #[test]
+    fn test_template_parse_requires_subject() {
+        assert!(EmailTemplate::parse("<p></p>", "no subject").is_err());
+
+        let template = EmailTemplate::parse("<p></p>", "Subject: Hi\n\nBody").unwrap();
+        assert_eq!(template.subject, "Hi");
+        assert_eq!(template.text, "Body");
+    }
+}
@@ -8,7 +8,7 @@ use thiserror::Error;
 
 use crate::auth::AuthError;
 use crate::db::DbError;
-use crate::models::ApiError;
+use crate::models::{ApiError, ErrorCode};
 use crate::scraper::ScraperError;
 
 /// Application-wide error type that unifies all error sources
@@ -81,7 +81,12 @@ impl AppError {
                 | AuthError::InvalidToken
                 | AuthError::MissingAuthHeader
                 | AuthError::InvalidAuthHeaderFormat
-                | AuthError::TokenVerificationError(_) => StatusCode::UNAUTHORIZED,
+                | AuthError::TokenVerificationError(_)
+                | AuthError::SessionRevoked
+                | AuthError::TokenRevoked
+                | AuthError::TenantMismatch => StatusCode::UNAUTHORIZED,
+                // 403 Forbidden - Valid credentials for a deactivated account
+                AuthError::AccountDeactivated => StatusCode::FORBIDDEN,
                 // Other auth errors are internal
                 _ => StatusCode::INTERNAL_SERVER_ERROR,
             },
@@ -92,6 +97,9 @@ impl AppError {
             // 409 Conflict
             AppError::Conflict(_) => StatusCode::CONFLICT,
 
+            // 504 Gateway Timeout - The source site took too long
+            AppError::Scraping(ScraperError::Timeout(_)) => StatusCode::GATEWAY_TIMEOUT,
+
             // 500 Internal Server Error - Scraping, Database, Internal errors
             AppError::Scraping(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -100,6 +108,23 @@ impl AppError {
         }
     }
 
+    /// Get the machine-readable error code for this error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::Internal(_) => ErrorCode::InternalError,
+            AppError::Auth(_) if self.status_code() == StatusCode::UNAUTHORIZED => {
+                ErrorCode::Unauthorized
+            }
+            AppError::Auth(AuthError::AccountDeactivated) => ErrorCode::Forbidden,
+            AppError::Auth(_) => ErrorCode::InternalError,
+            AppError::Scraping(scraper_err) => scraper_err.into(),
+            AppError::Database(_) | AppError::SqlxError(_) => ErrorCode::DatabaseError,
+        }
+    }
+
     /// Get a user-friendly error message
     pub fn user_message(&self) -> String {
         match self {
@@ -118,6 +143,14 @@ impl AppError {
                 }
                 AuthError::TokenVerificationError(_) => "Invalid authentication token".to_string(),
                 AuthError::UserNotFound => "User not found".to_string(),
+                AuthError::SessionRevoked => {
+                    "Session has been revoked, please login again".to_string()
+                }
+                AuthError::TokenRevoked => "Token has been revoked, please login again".to_string(),
+                AuthError::TenantMismatch => "Token is not valid for this site".to_string(),
+                AuthError::AccountDeactivated => {
+                    "Account is deactivated, reactivate it to sign in".to_string()
+                }
                 AuthError::HashingError(_) => "Authentication processing error".to_string(),
                 AuthError::TokenGenerationError(_) => {
                     "Failed to generate authentication token".to_string()
@@ -136,6 +169,18 @@ impl AppError {
                 ScraperError::RateLimited => {
                     "Server is rate limiting requests, please try again later".to_string()
                 }
+                ScraperError::BodyTooLarge(limit) => {
+                    format!("Page is larger than the {} byte limit", limit)
+                }
+                ScraperError::Timeout(ms) => {
+                    format!("Server did not respond within {} ms", ms)
+                }
+                ScraperError::TooManyRedirects(max) => {
+                    format!("Server redirected more than {} times", max)
+                }
+                ScraperError::CrossHostRedirect(_) => {
+                    "Server redirected to another host".to_string()
+                }
             },
 
             AppError::Database(db_err) => match db_err {
@@ -155,12 +200,22 @@ impl ResponseError for AppError {
 
     fn error_response(&self) -> HttpResponse {
         let status = self.status_code();
-        let error_response = ApiError::new(self.user_message());
+        let error_response = ApiError::new(self.code(), self.user_message());
 
         HttpResponse::build(status).json(error_response)
     }
 }
 
+impl From<&ScraperError> for ErrorCode {
+    fn from(err: &ScraperError) -> Self {
+        match err {
+            ScraperError::RateLimited => ErrorCode::RateLimited,
+            ScraperError::Timeout(_) => ErrorCode::UpstreamTimeout,
+            _ => ErrorCode::UpstreamUnavailable,
+        }
+    }
+}
+
 /// Result type alias for operations that can fail with AppError
 pub type AppResult<T> = Result<T, AppError>;
 
@@ -268,6 +323,56 @@ mod tests {
         assert_eq!(format!("{}", error), "Not found: anime");
     }
 
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            AppError::validation("bad").code(),
+            ErrorCode::ValidationFailed
+        );
+        assert_eq!(AppError::not_found("gone").code(), ErrorCode::NotFound);
+        assert_eq!(
+            AppError::Auth(AuthError::TokenExpired).code(),
+            ErrorCode::Unauthorized
+        );
+        assert_eq!(
+            AppError::Auth(AuthError::HashingError("bcrypt".to_string())).code(),
+            ErrorCode::InternalError
+        );
+        assert_eq!(
+            AppError::Auth(AuthError::AccountDeactivated).code(),
+            ErrorCode::Forbidden
+        );
+        assert_eq!(
+            AppError::Scraping(ScraperError::RateLimited).code(),
+            ErrorCode::RateLimited
+        );
+        assert_eq!(
+            AppError::Scraping(ScraperError::HttpError(503)).code(),
+            ErrorCode::UpstreamUnavailable
+        );
+        assert_eq!(
+            AppError::Scraping(ScraperError::Timeout(3000)).code(),
+            ErrorCode::UpstreamTimeout
+        );
+        assert_eq!(
+            AppError::Database(DbError::HealthCheckError("down".to_string())).code(),
+            ErrorCode::DatabaseError
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_error_response_carries_code() {
+        let response = AppError::not_found("Anime not found").error_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert_eq!(error.error, "Anime not found");
+    }
+
     #[test]
     fn test_from_scraper_error() {
         let scraper_err = ScraperError::NetworkError("timeout".to_string());
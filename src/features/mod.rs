@@ -0,0 +1,169 @@
+//! Feature flags
+//!
+//! Some endpoints are expensive or abuse-prone: running crawls, searching
+//! the source site, and proxying images. Operators switch them off at
+//! runtime through /api/admin/flags, e.g. while the source site is down or
+//! the proxy is being scraped, and requests to them get 503 with code
+//! `FEATURE_DISABLED` (see [`crate::middleware::features`]). Search is the
+//! exception: it keeps answering from the search cache, and only searches
+//! that would reach the source site are refused.
+//!
+//! Flags are stored in feature_flags; a feature without one is enabled.
+//! They are consulted on every request, so they are kept in memory,
+//! reloaded when an operator changes one here, and refreshed every
+//! FEATURE_FLAG_REFRESH_SECS so changes made through other instances reach
+//! this one.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use actix_web::http::Method;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::db::{get_feature_flags, RepositoryError};
+use crate::models::{Feature, FeatureFlag};
+
+/// Feature serving a request, if it is one that can be switched off
+///
+/// # Arguments
+/// * `method` - Request method
+/// * `path` - Path below the scope the API is mounted under, without a
+///   version prefix
+pub fn gated_feature(method: &Method, path: &str) -> Option<Feature> {
+    let path = path.trim_end_matches('/');
+    if path.starts_with("/api/crawler/") && method != Method::GET && method != Method::HEAD {
+        return Some(Feature::Crawler);
+    }
+    match path {
+        "/api/images/proxy" => Some(Feature::ImageProxy),
+        _ => None,
+    }
+}
+
+/// In-memory feature flags set by operators
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    flags: Arc<RwLock<HashMap<Feature, FeatureFlag>>>,
+}
+
+impl FeatureFlags {
+    /// Create the flags from the ones set by operators
+    pub fn new(flags: Vec<FeatureFlag>) -> Self {
+        let features = Self::default();
+        features.replace(flags);
+        features
+    }
+
+    /// Load the flags from the database
+    pub async fn load(pool: &PgPool) -> Result<Self, RepositoryError> {
+        Ok(Self::new(get_feature_flags(pool).await?))
+    }
+
+    /// Reload the flags from the database
+    pub async fn reload(&self, pool: &PgPool) -> Result<(), RepositoryError> {
+        self.replace(get_feature_flags(pool).await?);
+        Ok(())
+    }
+
+    /// Replace the flags set by operators
+    pub fn replace(&self, flags: Vec<FeatureFlag>) {
+        let flags = flags.into_iter().map(|flag| (flag.feature, flag)).collect();
+        *self.flags.write().unwrap_or_else(|e| e.into_inner()) = flags;
+    }
+
+    /// The flag in effect for a feature
+    pub fn get(&self, feature: Feature) -> FeatureFlag {
+        self.flags
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&feature)
+            .cloned()
+            .unwrap_or_else(|| FeatureFlag::default_for(feature))
+    }
+
+    /// Every feature's flag, in the order features are documented
+    pub fn all(&self) -> Vec<FeatureFlag> {
+        Feature::ALL
+            .into_iter()
+            .map(|feature| self.get(feature))
+            .collect()
+    }
+
+    /// Whether a feature's endpoints are served
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.get(feature).enabled
+    }
+
+    /// Spawn a task reloading the flags every `interval`
+    ///
+    /// Failed reloads are logged and the flags kept as they were.
+    pub fn spawn_refresh(&self, pool: PgPool, interval: Duration) -> JoinHandle<()> {
+        let features = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = features.reload(&pool).await {
+                    warn!("Failed to refresh feature flags: {}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disabled(feature: Feature) -> FeatureFlag {
+        FeatureFlag {
+            enabled: false,
+            reason: Some("upstream down".to_string()),
+            ..FeatureFlag::default_for(feature)
+        }
+    }
+
+    #[test]
+    fn test_gated_feature() {
+        assert_eq!(
+            gated_feature(&Method::POST, "/api/crawler/run"),
+            Some(Feature::Crawler)
+        );
+        assert_eq!(
+            gated_feature(&Method::POST, "/api/crawler/jobs/"),
+            Some(Feature::Crawler)
+        );
+        assert_eq!(gated_feature(&Method::GET, "/api/crawler/jobs/7"), None);
+        // Refused by the search itself, on a cache miss
+        assert_eq!(gated_feature(&Method::GET, "/api/search"), None);
+        assert_eq!(
+            gated_feature(&Method::GET, "/api/images/proxy"),
+            Some(Feature::ImageProxy)
+        );
+        assert_eq!(gated_feature(&Method::GET, "/api/images/sign"), None);
+        assert_eq!(gated_feature(&Method::GET, "/api/searches"), None);
+        assert_eq!(gated_feature(&Method::GET, "/api/anime/search"), None);
+    }
+
+    #[test]
+    fn test_flags_default_to_enabled() {
+        let features = FeatureFlags::new(vec![disabled(Feature::ImageProxy)]);
+
+        assert!(features.is_enabled(Feature::Crawler));
+        assert!(!features.is_enabled(Feature::ImageProxy));
+        assert_eq!(
+            features.get(Feature::ImageProxy).reason.as_deref(),
+            Some("upstream down")
+        );
+        let all = features.all();
+        assert_eq!(all.len(), Feature::ALL.len());
+        assert_eq!(all[0], FeatureFlag::default_for(Feature::Crawler));
+
+        features.replace(Vec::new());
+        assert!(features.is_enabled(Feature::ImageProxy));
+    }
+}
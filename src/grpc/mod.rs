@@ -0,0 +1,473 @@
+//! gRPC interface for internal services (feature `grpc`)
+//!
+//! Serves `anime.v1.AnimeService` from proto/anime.proto on GRPC_ADDR, next
+//! to the REST API. The RPCs share the repository, scraper, and search cache
+//! with their REST counterparts; `StreamChanges` turns the change feed into a
+//! server stream that keeps polling for new changes until the client
+//! disconnects. Like the REST lists, `Search` and `ListUpdates` leave out
+//! anime rated mature or adult unless the request includes them, and
+//! `Search` is refused with `UNAVAILABLE` while the search-upstream feature is off.
+//!
+//! There is no authentication: bind GRPC_ADDR to an internal interface only.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+
+use crate::db::{
+    get_anime_detail, get_anime_updates, get_changes_since, resolve_change_cursor, ChangeCursor,
+};
+use crate::middleware::features::disabled_error;
+use crate::models::{ChangeEntry, ChangeKind};
+use crate::parser;
+use crate::routes::{filter_scraped, search_with_cache, AppState, SearchError};
+use crate::scraper::ScraperError;
+
+/// Generated protobuf messages and service traits
+pub mod proto {
+    tonic::include_proto!("anime.v1");
+}
+
+use proto::anime_service_server::{AnimeService, AnimeServiceServer};
+
+/// Changes fetched per database query while streaming
+const CHANGES_PAGE_SIZE: i64 = 500;
+
+/// Wait between polls once a change stream has caught up
+const CHANGES_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `AnimeService` backed by the application state
+pub struct AnimeGrpc {
+    state: web::Data<AppState>,
+}
+
+impl AnimeGrpc {
+    /// Create the service
+    pub fn new(state: web::Data<AppState>) -> Self {
+        Self { state }
+    }
+}
+
+/// Serve the gRPC API on `addr` until the process exits
+pub async fn serve(
+    addr: SocketAddr,
+    state: web::Data<AppState>,
+) -> Result<(), tonic::transport::Error> {
+    info!("Starting gRPC server on {}", addr);
+    Server::builder()
+        .add_service(AnimeServiceServer::new(AnimeGrpc::new(state)))
+        .serve(addr)
+        .await
+}
+
+#[tonic::async_trait]
+impl AnimeService for AnimeGrpc {
+    async fn get_anime(
+        &self,
+        request: Request<proto::GetAnimeRequest>,
+    ) -> Result<Response<proto::AnimeDetail>, Status> {
+        let slug = request.into_inner().slug;
+        match self
+            .state
+            .db
+            .read(|pool| get_anime_detail(pool, &slug))
+            .await
+        {
+            Ok(Some(detail)) => Ok(Response::new(detail.into())),
+            Ok(None) => Err(Status::not_found("Anime not found")),
+            Err(e) => {
+                error!("Failed to get anime detail {}: {}", slug, e);
+                Err(Status::internal(format!("Database error: {}", e)))
+            }
+        }
+    }
+
+    async fn search(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> Result<Response<proto::SearchResponse>, Status> {
+        let request = request.into_inner();
+        let keyword = request.query.trim();
+        if keyword.is_empty() {
+            return Err(Status::invalid_argument("Search query is required"));
+        }
+
+        match search_with_cache(&self.state, keyword).await {
+            Ok((mut results, _)) => {
+                filter_scraped(
+                    &self.state,
+                    None,
+                    request.include_adult,
+                    &mut results,
+                    |r| &r.slug,
+                    |_| &[],
+                )
+                .await
+                .map_err(|e| {
+                    error!("Failed to filter search results: {}", e);
+                    Status::internal(format!("Database error: {}", e))
+                })?;
+                Ok(Response::new(proto::SearchResponse {
+                    results: results.into_iter().map(Into::into).collect(),
+                }))
+            }
+            Err(SearchError::Disabled(flag)) => {
+                Err(Status::unavailable(disabled_error(&flag).error))
+            }
+            Err(SearchError::Scraper(e @ ScraperError::Timeout(_))) => {
+                Err(Status::deadline_exceeded(e.to_string()))
+            }
+            Err(SearchError::Scraper(e)) => {
+                error!("Failed to search anime: {}", e);
+                Err(Status::unavailable(format!("Failed to fetch data: {}", e)))
+            }
+        }
+    }
+
+    async fn list_updates(
+        &self,
+        request: Request<proto::ListUpdatesRequest>,
+    ) -> Result<Response<proto::ListUpdatesResponse>, Status> {
+        let include_adult = request.into_inner().include_adult;
+        match self.state.db.read(get_anime_updates).await {
+            Ok(mut updates) => {
+                filter_scraped(
+                    &self.state,
+                    None,
+                    include_adult,
+                    &mut updates,
+                    |u| &u.slug,
+                    |_| &[],
+                )
+                .await
+                .map_err(|e| {
+                    error!("Failed to filter anime updates: {}", e);
+                    Status::internal(format!("Database error: {}", e))
+                })?;
+                Ok(Response::new(proto::ListUpdatesResponse {
+                    updates: updates.into_iter().map(Into::into).collect(),
+                }))
+            }
+            Err(e) => {
+                error!("Failed to get anime updates: {}", e);
+                Err(Status::internal(format!("Database error: {}", e)))
+            }
+        }
+    }
+
+    type StreamChangesStream = ReceiverStream<Result<proto::Change, Status>>;
+
+    async fn stream_changes(
+        &self,
+        request: Request<proto::StreamChangesRequest>,
+    ) -> Result<Response<Self::StreamChangesStream>, Status> {
+        let request = request.into_inner();
+        let since = DateTime::parse_from_rfc3339(&request.since)
+            .map_err(|_| Status::invalid_argument("since must be an RFC 3339 timestamp"))?
+            .with_timezone(&Utc);
+        let mut after = match request.cursor.as_deref() {
+            Some(cursor) => Some(
+                resolve_change_cursor(self.state.db.pool(), cursor)
+                    .await
+                    .map_err(|e| Status::internal(format!("Database error: {}", e)))?
+                    .ok_or_else(|| Status::invalid_argument("Invalid cursor"))?,
+            ),
+            None => None,
+        };
+
+        let pool = self.state.db.pool().clone();
+        let (tx, rx) = mpsc::channel(CHANGES_PAGE_SIZE as usize);
+
+        tokio::spawn(async move {
+            loop {
+                let page = match get_changes_since(&pool, since, after, CHANGES_PAGE_SIZE).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        error!("Failed to stream changes since {}: {}", since, e);
+                        let _ = tx
+                            .send(Err(Status::internal(format!("Database error: {}", e))))
+                            .await;
+                        return;
+                    }
+                };
+
+                let caught_up = (page.len() as i64) < CHANGES_PAGE_SIZE;
+                for (entry, cursor) in page {
+                    if tx.send(Ok(change_message(entry, &cursor))).await.is_err() {
+                        // The client went away
+                        return;
+                    }
+                    after = Some(cursor);
+                }
+
+                if caught_up {
+                    tokio::select! {
+                        _ = tokio::time::sleep(CHANGES_POLL_INTERVAL) => {}
+                        _ = tx.closed() => return,
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Protobuf message of a change together with its resume cursor
+fn change_message(entry: ChangeEntry, cursor: &ChangeCursor) -> proto::Change {
+    let kind = match entry.kind {
+        ChangeKind::Anime => proto::ChangeKind::Anime,
+        ChangeKind::Episode => proto::ChangeKind::Episode,
+    };
+    proto::Change {
+        kind: kind as i32,
+        slug: entry.slug,
+        anime_slug: entry.anime_slug,
+        title: entry.title,
+        number: entry.number,
+        url: entry.url,
+        created: entry.created,
+        updated_at: entry.updated_at,
+        cursor: cursor.encode(),
+    }
+}
+
+impl From<parser::Episode> for proto::Episode {
+    fn from(episode: parser::Episode) -> Self {
+        Self {
+            slug: episode.slug,
+            number: episode.number,
+            title: episode.title,
+            url: episode.url,
+            release_date: episode.release_date,
+        }
+    }
+}
+
+impl From<parser::AnimeDetail> for proto::AnimeDetail {
+    fn from(detail: parser::AnimeDetail) -> Self {
+        Self {
+            title: detail.title,
+            alternate_titles: detail.alternate_titles,
+            poster: detail.poster,
+            rating: detail.rating,
+            trailer_url: detail.trailer_url,
+            status: detail.status,
+            studio: detail.studio,
+            release_date: detail.release_date,
+            duration: detail.duration,
+            season: detail.season,
+            r#type: detail.anime_type,
+            total_episodes: detail.total_episodes,
+            director: detail.director,
+            casts: detail.casts,
+            genres: detail.genres,
+            synopsis: detail.synopsis,
+            episodes: detail.episodes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<parser::SearchResult> for proto::SearchResult {
+    fn from(result: parser::SearchResult) -> Self {
+        Self {
+            slug: result.slug,
+            title: result.title,
+            url: result.url,
+            thumbnail: result.thumbnail,
+            status: result.status,
+            r#type: result.anime_type,
+            episode_status: result.episode_status,
+        }
+    }
+}
+
+impl From<parser::AnimeUpdate> for proto::AnimeUpdate {
+    fn from(update: parser::AnimeUpdate) -> Self {
+        Self {
+            slug: update.slug,
+            title: update.title,
+            episode_url: update.episode_url,
+            thumbnail: update.thumbnail,
+            episode_number: update.episode_number,
+            r#type: update.anime_type,
+            series_title: update.series_title,
+            series_url: update.series_url,
+            status: update.status,
+            release_info: update.release_info,
+            released_at: update.released_at.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_message_carries_resume_cursor() {
+        let cursor = ChangeCursor { id: 42 };
+        let entry = ChangeEntry {
+            kind: ChangeKind::Episode,
+            slug: "frieren-episode-28".to_string(),
+            anime_slug: "frieren".to_string(),
+            title: "Episode 28".to_string(),
+            number: Some("28".to_string()),
+            url: Some("https://x3.sokuja.uk/frieren-episode-28/".to_string()),
+            created: true,
+            updated_at: "2024-12-27T10:15:00+00:00".to_string(),
+        };
+
+        let change = change_message(entry, &cursor);
+        assert_eq!(change.kind(), proto::ChangeKind::Episode);
+        assert_eq!(change.number.as_deref(), Some("28"));
+        assert_eq!(ChangeCursor::decode(&change.cursor), Some(cursor));
+    }
+
+    #[test]
+    fn test_anime_detail_conversion() {
+        let detail = parser::AnimeDetail {
+            title: "Frieren".to_string(),
+            anime_type: "TV".to_string(),
+            genres: vec!["Fantasy".to_string()],
+            ..Default::default()
+        };
+
+        let message = proto::AnimeDetail::from(detail);
+        assert_eq!(message.title, "Frieren");
+        assert_eq!(message.r#type, "TV");
+        assert_eq!(message.genres, vec!["Fantasy"]);
+        assert!(message.episodes.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_search_leaves_out_adult_anime() {
+        use crate::config::Config;
+        use crate::db::{normalize_search_query, save_anime_detail, save_search_results};
+        use crate::routes::init;
+
+        dotenvy::dotenv().ok();
+        if std::env::var("JWT_SECRET").is_err() {
+            std::env::set_var("JWT_SECRET", "test-secret-for-embedding");
+        }
+        let state = init(Config::from_env())
+            .await
+            .expect("Failed to initialize");
+        let pool = state.db.pool();
+
+        let adult = "test-grpc-adult-search-ecchi";
+        let unrated = "test-grpc-adult-search-unrated";
+        let detail = parser::AnimeDetail {
+            title: adult.to_string(),
+            genres: vec!["Ecchi".to_string()],
+            ..Default::default()
+        };
+        save_anime_detail(pool, adult, &detail).await.unwrap();
+        let results: Vec<parser::SearchResult> = [adult, unrated]
+            .into_iter()
+            .map(|slug| parser::SearchResult {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                url: format!("https://example.com/anime/{}/", slug),
+                thumbnail: String::new(),
+                thumbnails: None,
+                status: "Ongoing".to_string(),
+                anime_type: "TV".to_string(),
+                episode_status: "Ongoing".to_string(),
+            })
+            .collect();
+        let query = "grpc adult search test";
+        save_search_results(pool, &normalize_search_query(query), &results)
+            .await
+            .unwrap();
+
+        let service = AnimeGrpc::new(state.clone());
+        let search = |include_adult| {
+            let service = &service;
+            async move {
+                let request = Request::new(proto::SearchRequest {
+                    query: query.to_string(),
+                    include_adult,
+                });
+                service
+                    .search(request)
+                    .await
+                    .expect("Search failed")
+                    .into_inner()
+                    .results
+                    .into_iter()
+                    .map(|result| result.slug)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(search(false).await, vec![unrated.to_string()]);
+        assert_eq!(search(true).await.len(), 2);
+    }
+
+    #[tokio::test]
+    #[ignore] // Requires database connection
+    async fn test_search_refused_while_disabled() {
+        use crate::config::Config;
+        use crate::db::save_search_results;
+        use crate::models::{Feature, FeatureFlag};
+        use crate::parser::SearchResult;
+        use crate::routes::init;
+
+        dotenvy::dotenv().ok();
+        if std::env::var("JWT_SECRET").is_err() {
+            std::env::set_var("JWT_SECRET", "test-secret-for-embedding");
+        }
+        let state = init(Config::from_env())
+            .await
+            .expect("Failed to initialize");
+        state.features.replace(vec![FeatureFlag {
+            enabled: false,
+            reason: Some("source site is down".to_string()),
+            ..FeatureFlag::default_for(Feature::SearchUpstream)
+        }]);
+
+        let service = AnimeGrpc::new(state.clone());
+        let search = |query: &str| {
+            service.search(Request::new(proto::SearchRequest {
+                query: query.to_string(),
+                include_adult: false,
+            }))
+        };
+        let status = search("test-search-refused-uncached").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert_eq!(
+            status.message(),
+            "The search-upstream feature is disabled: source site is down"
+        );
+
+        // Cached searches are still answered
+        let cached = SearchResult {
+            slug: "test-search-refused".to_string(),
+            title: "Cached".to_string(),
+            url: String::new(),
+            thumbnail: String::new(),
+            thumbnails: None,
+            status: String::new(),
+            anime_type: String::new(),
+            episode_status: String::new(),
+        };
+        save_search_results(state.db.pool(), "test-search-refused-cached", &[cached])
+            .await
+            .unwrap();
+        let results = search("Test-Search-Refused-Cached")
+            .await
+            .expect("Cached search should be answered")
+            .into_inner()
+            .results;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].slug, "test-search-refused");
+    }
+}
@@ -0,0 +1,51 @@
+//! Anonymous account cleanup
+//!
+//! Every device registration creates an anonymous account (see
+//! `POST /api/device/register`). Most are used, but apps that register and
+//! never come back, or clients registering in a loop, leave accounts behind
+//! that hold nothing. A scheduler task deletes the ones that never stored
+//! any data once their device has been idle for ANONYMOUS_MAX_IDLE_DAYS.
+
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use crate::db::{delete_unused_anonymous_users, RepositoryError};
+use crate::routes::AppState;
+
+/// Spawn a task deleting unused anonymous accounts every `interval`
+///
+/// The idle period is read from the configuration on each run.
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!(
+        "Deleting unused anonymous accounts every {}s",
+        interval.as_secs()
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let max_idle_days = state.config.load().anonymous_max_idle_days;
+            match prune_unused(state.db.pool(), max_idle_days).await {
+                Ok(0) => debug!("No unused anonymous accounts to delete"),
+                Ok(deleted) => info!("Deleted {} unused anonymous accounts", deleted),
+                Err(e) => error!("Anonymous account cleanup failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Delete anonymous accounts that never stored anything and whose devices
+/// were last seen more than `max_idle_days` ago
+///
+/// # Returns
+/// * `Ok(count)` - Number of accounts deleted
+pub async fn prune_unused(pool: &PgPool, max_idle_days: u32) -> Result<u64, RepositoryError> {
+    let idle_before = Utc::now() - chrono::Duration::days(i64::from(max_idle_days));
+    delete_unused_anonymous_users(pool, idle_before).await
+}
@@ -0,0 +1,51 @@
+//! Change feed retention
+//!
+//! Every insert and update of scraped anime and episodes appends a row to
+//! change_events, the log behind the change feed, its gRPC stream, and its
+//! webhook. A scheduler task deletes events older than
+//! CHANGE_EVENTS_RETENTION_DAYS, except ones a consumer in
+//! change_feed_positions hasn't read yet.
+
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use crate::db::{delete_old_change_events, RepositoryError};
+use crate::routes::AppState;
+
+/// Spawn a task deleting old change feed events every `interval`
+///
+/// The retention period is read from the configuration on each run.
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!(
+        "Deleting old change feed events every {}s",
+        interval.as_secs()
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let retention_days = state.config.load().change_events_retention_days;
+            match prune_old(state.db.pool(), retention_days).await {
+                Ok(0) => debug!("No old change feed events to delete"),
+                Ok(deleted) => info!("Deleted {} old change feed events", deleted),
+                Err(e) => error!("Change feed cleanup failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Delete change feed events older than `retention_days` that every
+/// consumer has read
+///
+/// # Returns
+/// * `Ok(count)` - Number of events deleted
+pub async fn prune_old(pool: &PgPool, retention_days: u32) -> Result<u64, RepositoryError> {
+    let before = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+    delete_old_change_events(pool, before).await
+}
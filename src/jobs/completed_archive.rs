@@ -0,0 +1,40 @@
+//! Daily refresh of the completed anime archive
+//!
+//! A scheduler task re-crawls the site's completed archive (see
+//! [`crate::crawler::completed`]) so /api/completed lists every completed
+//! anime, not only the few the home page shows.
+
+use std::time::Duration;
+
+use actix_web::web;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::crawler::completed::crawl_completed_archive;
+use crate::routes::AppState;
+
+/// Spawn a task crawling the completed archive every `interval`
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!(
+        "Crawling the completed anime archive every {}s",
+        interval.as_secs()
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let config = state.config.load_full();
+            if let Err(e) = crawl_completed_archive(
+                state.db.pool(),
+                &config.base_url,
+                state.scraper.as_ref(),
+                config.crawler_bandwidth_quota(),
+            )
+            .await
+            {
+                error!("Completed archive crawl failed: {}", e);
+            }
+        }
+    })
+}
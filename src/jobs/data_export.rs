@@ -0,0 +1,121 @@
+//! Personal data exports
+//!
+//! GET /api/user/data-export queues an export_user_data job, which collects
+//! everything stored about the user into a JSON archive and puts it in
+//! object storage under [`keys::user_data_export`]. The user downloads it
+//! through a signed URL; archives are purged with the other exports after
+//! STORAGE_EXPORT_RETENTION_DAYS.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::db::{
+    collect_user_data, complete_data_export, enqueue_job_with_priority, fail_data_export,
+    get_data_export, RepositoryError,
+};
+use crate::models::{DataExport, JobRecord, DATA_EXPORT_PENDING};
+use crate::routes::AppState;
+use crate::storage::keys;
+
+use super::{
+    JobError, DEFAULT_MAX_ATTEMPTS, JOB_TYPE_EXPORT_USER_DATA, PRIORITY_INTERACTIVE,
+    QUEUE_MAINTENANCE,
+};
+
+/// Version of the archive layout, bumped when it changes incompatibly
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Payload of an export_user_data job
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportUserDataPayload {
+    /// ID of the data export to build
+    pub export_id: i32,
+}
+
+/// Queue building a data export, ahead of background work
+pub async fn enqueue_export_user_data(
+    pool: &PgPool,
+    export_id: i32,
+) -> Result<JobRecord, RepositoryError> {
+    let payload = serde_json::to_string(&ExportUserDataPayload { export_id })
+        .unwrap_or_else(|_| "{}".to_string());
+    enqueue_job_with_priority(
+        pool,
+        QUEUE_MAINTENANCE,
+        JOB_TYPE_EXPORT_USER_DATA,
+        &payload,
+        DEFAULT_MAX_ATTEMPTS,
+        PRIORITY_INTERACTIVE,
+    )
+    .await
+}
+
+/// Run an export_user_data job
+///
+/// Marks the export failed once the final attempt fails.
+pub(super) async fn export_user_data_job(
+    state: &AppState,
+    job: &JobRecord,
+) -> Result<(), JobError> {
+    let payload: ExportUserDataPayload = serde_json::from_value(job.payload.clone())
+        .map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+
+    // The user may have erased their account since requesting the export
+    let Some(export) = get_data_export(state.db.pool(), payload.export_id)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?
+    else {
+        info!("Skipping export {}, no longer stored", payload.export_id);
+        return Ok(());
+    };
+    if export.status != DATA_EXPORT_PENDING {
+        return Ok(());
+    }
+
+    let result = build_export(state, &export).await;
+    if let Err(e) = &result {
+        if job.attempts >= job.max_attempts {
+            if let Err(e) = fail_data_export(state.db.pool(), export.id, &e.to_string()).await {
+                warn!("Failed to mark export {} failed: {}", export.id, e);
+            }
+        }
+    }
+    result
+}
+
+/// Collect a user's data and store the archive
+async fn build_export(state: &AppState, export: &DataExport) -> Result<(), JobError> {
+    let pool = state.db.pool();
+    let Some(data) = collect_user_data(pool, export.user_id)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?
+    else {
+        return Ok(());
+    };
+
+    let archive = serde_json::json!({
+        "formatVersion": ARCHIVE_FORMAT_VERSION,
+        "generatedAt": Utc::now().to_rfc3339(),
+        "data": data,
+    });
+    let bytes = serde_json::to_vec_pretty(&archive).map_err(|e| JobError::Failed(e.to_string()))?;
+
+    let key = keys::user_data_export(export.user_id, export.id);
+    state
+        .storage
+        .put(&key, bytes, "application/json")
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+    complete_data_export(pool, export.id, &key)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+
+    info!(
+        "Stored data export {} of user {}",
+        export.id, export.user_id
+    );
+    Ok(())
+}
@@ -0,0 +1,195 @@
+//! Episode gap detection and backfill
+//!
+//! A crawl that fails halfway through an anime's episode list, or a page
+//! that briefly lists fewer episodes, leaves holes in the stored sequence:
+//! episodes 1-3 and 5 stored, 4 missing. The check counts an episode number
+//! as missing when it is below the highest stored one, or, once the anime is
+//! completed, below the episode count on its detail page. Episodes numbered
+//! other than a whole number ("12.5") are specials and never fill a gap.
+//!
+//! A scheduler task runs the check periodically and queues a scrape_anime
+//! job for each affected anime, which re-reads its detail page and episode
+//! list. Anime scraped since the previous check or with a scrape already
+//! queued are skipped, so a gap the site itself has is retried once per
+//! interval rather than piling up jobs.
+
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::db::{get_stored_episode_numbers, RepositoryError, StoredEpisodeNumbers};
+use crate::models::{EpisodeGap, EpisodeGapReport};
+use crate::routes::AppState;
+
+use super::integrity::enqueue_scrape_anime;
+use super::JOB_TYPE_SCRAPE_ANIME;
+
+/// Anime with gaps listed in a report
+const REPORT_LIMIT: usize = 500;
+
+/// Backfill scrapes queued by a single check
+const MAX_BACKFILLS: usize = 50;
+
+/// Highest episode number a gap is looked for below
+///
+/// Guards against a mistyped count or episode number ("20231") making the
+/// report list thousands of missing episodes.
+const MAX_EPISODE: u32 = 5000;
+
+/// The gap in an anime's stored episodes, if it has one
+pub fn find_gap(stored: &StoredEpisodeNumbers) -> Option<EpisodeGap> {
+    let mut numbers: Vec<u32> = stored
+        .numbers
+        .iter()
+        .filter_map(|number| number.trim().parse().ok())
+        .collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    let highest = *numbers.last()?;
+    let total_episodes = stored.total_episodes.trim().parse::<u32>().ok();
+    let completed = stored.status.trim().eq_ignore_ascii_case("completed");
+    let last = match total_episodes {
+        Some(total) if completed => total.max(highest),
+        _ => highest,
+    };
+    if last > MAX_EPISODE {
+        return None;
+    }
+
+    let missing: Vec<u32> = (1..=last)
+        .filter(|number| numbers.binary_search(number).is_err())
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+    Some(EpisodeGap {
+        slug: stored.slug.clone(),
+        title: stored.title.clone(),
+        total_episodes,
+        highest_episode: highest,
+        missing,
+        scraped_at: stored.scraped_at.map(|at| at.to_rfc3339()),
+    })
+}
+
+/// Find anime with gaps in their stored episodes
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `backfill_scraped_before` - Queue a scrape of each anime with a gap
+///   last scraped before this (at most 50 per check), or none if `None`
+pub async fn check_gaps(
+    pool: &PgPool,
+    backfill_scraped_before: Option<DateTime<Utc>>,
+) -> Result<EpisodeGapReport, RepositoryError> {
+    let stored = get_stored_episode_numbers(pool, JOB_TYPE_SCRAPE_ANIME).await?;
+    let mut gaps: Vec<(&StoredEpisodeNumbers, EpisodeGap)> = stored
+        .iter()
+        .filter_map(|anime| find_gap(anime).map(|gap| (anime, gap)))
+        .collect();
+    gaps.sort_by(|(_, a), (_, b)| {
+        b.missing
+            .len()
+            .cmp(&a.missing.len())
+            .then_with(|| a.slug.cmp(&b.slug))
+    });
+
+    let mut backfills_queued = Vec::new();
+    if let Some(scraped_before) = backfill_scraped_before {
+        let due = gaps.iter().filter(|(anime, _)| {
+            !anime.job_pending && anime.scraped_at.is_none_or(|at| at < scraped_before)
+        });
+        for (anime, _) in due.take(MAX_BACKFILLS) {
+            match enqueue_scrape_anime(pool, &anime.slug).await {
+                Ok(_) => backfills_queued.push(anime.slug.clone()),
+                Err(e) => warn!("Failed to queue backfill scrape of {}: {}", anime.slug, e),
+            }
+        }
+    }
+
+    Ok(EpisodeGapReport {
+        checked_at: Utc::now().to_rfc3339(),
+        gaps: gaps
+            .into_iter()
+            .take(REPORT_LIMIT)
+            .map(|(_, gap)| gap)
+            .collect(),
+        backfills_queued,
+    })
+}
+
+/// Spawn a task checking for gaps and queueing backfills every `interval`
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!("Checking for episode gaps every {}s", interval.as_secs());
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let scraped_before = Utc::now()
+                - chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::zero());
+            match check_gaps(state.db.pool(), Some(scraped_before)).await {
+                Ok(report) if report.gaps.is_empty() => {}
+                Ok(report) => info!(
+                    "Found {} anime with episode gaps, queued {} backfill(s)",
+                    report.gaps.len(),
+                    report.backfills_queued.len()
+                ),
+                Err(e) => error!("Episode gap check failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(numbers: &[&str], total_episodes: &str, status: &str) -> StoredEpisodeNumbers {
+        StoredEpisodeNumbers {
+            slug: "frieren".to_string(),
+            title: "Frieren".to_string(),
+            status: status.to_string(),
+            total_episodes: total_episodes.to_string(),
+            numbers: numbers.iter().map(|number| number.to_string()).collect(),
+            scraped_at: None,
+            job_pending: false,
+        }
+    }
+
+    #[test]
+    fn test_find_gap_below_highest_episode() {
+        let gap = find_gap(&stored(&["1", "2", "3", "5"], "12", "Ongoing")).unwrap();
+        assert_eq!(gap.missing, vec![4]);
+        assert_eq!(gap.highest_episode, 5);
+        assert_eq!(gap.total_episodes, Some(12));
+
+        // Episodes yet to air aren't gaps
+        assert_eq!(find_gap(&stored(&["1", "2", "3"], "12", "Ongoing")), None);
+    }
+
+    #[test]
+    fn test_find_gap_up_to_count_when_completed() {
+        let gap = find_gap(&stored(&["1", "2", "4"], "6", "Completed")).unwrap();
+        assert_eq!(gap.missing, vec![3, 5, 6]);
+        assert_eq!(gap.highest_episode, 4);
+
+        // An unknown count only looks below the highest episode
+        assert_eq!(find_gap(&stored(&["1", "2"], "?", "Completed")), None);
+    }
+
+    #[test]
+    fn test_find_gap_ignores_specials_and_junk() {
+        assert_eq!(
+            find_gap(&stored(&["1", "2", "2", "2.5", " 3 "], "", "Ongoing")),
+            None
+        );
+        assert_eq!(find_gap(&stored(&["2.5", "OVA"], "", "Ongoing")), None);
+        assert_eq!(find_gap(&stored(&["1", "20231"], "", "Ongoing")), None);
+    }
+}
@@ -0,0 +1,254 @@
+//! Warming the image proxy cache for list responses
+//!
+//! With `?prefetchImages=true` (or IMAGE_PREFETCH on), list endpoints queue
+//! a prefetch_images job for the thumbnails they return, so by the time the
+//! client loads them through the proxy they are already in object storage.
+//! A burst of list requests mustn't turn into a burst of upstream fetches:
+//! - URLs this process queued in the last [`RECENT_PREFETCH_SECS`] are left
+//!   out of new jobs (see [`RecentPrefetches`])
+//! - a job carries at most [`MAX_PREFETCH_URLS`] URLs, and is not retried
+//! - the job fetches one image at a time and skips those already stored
+//! - the images count against the daily crawler bandwidth quota, and the job
+//!   stops fetching once it is reached
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::crawler::bandwidth::BandwidthMeter;
+use crate::db::{enqueue_job_with_priority, RepositoryError};
+use crate::models::JobRecord;
+use crate::routes::images::{fetch_upstream_image, is_allowed_image_url};
+use crate::routes::AppState;
+use crate::storage::keys;
+
+use super::{JobError, JOB_TYPE_PREFETCH_IMAGES, QUEUE_IMAGES};
+
+/// Images a single prefetch job fetches at most
+pub const MAX_PREFETCH_URLS: usize = 100;
+
+/// How long a queued URL is left out of new prefetch jobs
+pub const RECENT_PREFETCH_SECS: u64 = 600;
+
+/// URLs remembered by [`RecentPrefetches`] before expired ones are dropped
+const MAX_RECENT_PREFETCHES: usize = 10_000;
+
+/// Priority of prefetch jobs: ahead of background crawls, behind jobs a
+/// user is waiting on
+const PRIORITY_PREFETCH: i32 = 500;
+
+/// Image URLs recently queued for prefetching by this process
+#[derive(Debug, Clone, Default)]
+pub struct RecentPrefetches {
+    queued: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl RecentPrefetches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The URLs not queued within `ttl`, now marked as queued
+    pub fn claim(&self, urls: Vec<String>, ttl: Duration) -> Vec<String> {
+        let now = Instant::now();
+        let mut queued = self.queued.lock().unwrap_or_else(|e| e.into_inner());
+        if queued.len() >= MAX_RECENT_PREFETCHES {
+            queued.retain(|_, at| now.duration_since(*at) < ttl);
+        }
+
+        let mut claimed = Vec::new();
+        for url in urls {
+            let recent = queued
+                .get(&url)
+                .is_some_and(|at| now.duration_since(*at) < ttl);
+            if recent || queued.len() >= MAX_RECENT_PREFETCHES {
+                continue;
+            }
+            queued.insert(url.clone(), now);
+            claimed.push(url);
+        }
+        claimed
+    }
+}
+
+/// Payload of a prefetch_images job
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchImagesPayload {
+    /// Image URLs to fetch into the proxy cache
+    pub urls: Vec<String>,
+}
+
+/// Outcome of a prefetch_images job
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchReport {
+    /// Images fetched and stored
+    pub fetched: usize,
+    /// Images already stored
+    pub cached: usize,
+    /// Images that couldn't be fetched or stored, or aren't on an allowed host
+    pub failed: usize,
+    /// Images left unfetched because the daily crawler bandwidth quota was
+    /// reached
+    pub skipped: usize,
+}
+
+/// Queue a job fetching `urls` into the image proxy cache
+pub async fn enqueue_prefetch_images(
+    pool: &PgPool,
+    urls: Vec<String>,
+) -> Result<JobRecord, RepositoryError> {
+    let payload =
+        serde_json::to_string(&PrefetchImagesPayload { urls }).unwrap_or_else(|_| "{}".to_string());
+    enqueue_job_with_priority(
+        pool,
+        QUEUE_IMAGES,
+        JOB_TYPE_PREFETCH_IMAGES,
+        &payload,
+        1,
+        PRIORITY_PREFETCH,
+    )
+    .await
+}
+
+/// Queue a prefetch of the thumbnails of a list response in the background
+///
+/// Does nothing when the image cache is off. Empty URLs, URLs the proxy
+/// wouldn't sign, and URLs queued recently are left out.
+pub fn prefetch_thumbnails<'a>(
+    state: &actix_web::web::Data<AppState>,
+    thumbnails: impl IntoIterator<Item = &'a str>,
+) {
+    let config = state.config.load();
+    if !config.storage.image_cache {
+        return;
+    }
+
+    let mut urls: Vec<String> = Vec::new();
+    for url in thumbnails {
+        if is_allowed_image_url(url, &config.image_proxy_hosts) && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls.truncate(MAX_PREFETCH_URLS);
+    let urls = state
+        .image_prefetches
+        .claim(urls, Duration::from_secs(RECENT_PREFETCH_SECS));
+    if urls.is_empty() {
+        return;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        let count = urls.len();
+        if let Err(e) = enqueue_prefetch_images(state.db.pool(), urls).await {
+            warn!("Failed to queue prefetch of {} image(s): {}", count, e);
+        }
+    });
+}
+
+/// Fetch a prefetch job's images into the image proxy cache
+pub(super) async fn prefetch_images_job(
+    state: &AppState,
+    job: &JobRecord,
+) -> Result<Option<String>, JobError> {
+    let payload: PrefetchImagesPayload = serde_json::from_value(job.payload.clone())
+        .map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+    let config = state.config.load_full();
+    let mut report = PrefetchReport::default();
+    let mut meter = BandwidthMeter::load(state.db.pool(), config.crawler_bandwidth_quota()).await;
+
+    for url in payload.urls.iter().take(MAX_PREFETCH_URLS) {
+        // The allowed hosts may have changed since the job was queued
+        if !is_allowed_image_url(url, &config.image_proxy_hosts) {
+            report.failed += 1;
+            continue;
+        }
+        let key = keys::image(url);
+        match state.storage.exists(&key).await {
+            Ok(true) => {
+                report.cached += 1;
+                continue;
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to check cached image {}: {}", url, e),
+        }
+
+        if meter.exhausted() {
+            report.skipped += 1;
+            continue;
+        }
+        let stored = match fetch_upstream_image(url, &config.image_proxy_hosts).await {
+            Ok((content_type, body)) => {
+                meter.record(body.len() as u64).await;
+                state
+                    .storage
+                    .put(&key, body, &content_type)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match stored {
+            Ok(()) => report.fetched += 1,
+            Err(e) => {
+                warn!("Failed to prefetch image {}: {}", url, e);
+                report.failed += 1;
+            }
+        }
+    }
+
+    info!(
+        "Prefetched {} image(s), {} already cached, {} failed, {} skipped",
+        report.fetched, report.cached, report.failed, report.skipped
+    );
+    serde_json::to_string(&report)
+        .map(Some)
+        .map_err(|e| JobError::Failed(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(urls: &[&str]) -> Vec<String> {
+        urls.iter().map(|url| url.to_string()).collect()
+    }
+
+    #[test]
+    fn test_claim_skips_recent_urls() {
+        let recent = RecentPrefetches::new();
+        let ttl = Duration::from_secs(60);
+
+        assert_eq!(recent.claim(urls(&["a", "b"]), ttl), urls(&["a", "b"]));
+        assert_eq!(recent.claim(urls(&["b", "c"]), ttl), urls(&["c"]));
+        assert!(recent.claim(urls(&["a", "b", "c"]), ttl).is_empty());
+
+        // Claims are shared between clones, as between requests
+        assert!(recent.clone().claim(urls(&["a"]), ttl).is_empty());
+
+        // Once expired, a URL can be queued again
+        assert_eq!(recent.claim(urls(&["a"]), Duration::ZERO), urls(&["a"]));
+    }
+
+    #[test]
+    fn test_prefetch_payload_serialization() {
+        let payload = PrefetchImagesPayload {
+            urls: urls(&["https://example.com/a.jpg"]),
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "urls": ["https://example.com/a.jpg"] })
+        );
+        assert_eq!(
+            serde_json::from_value::<PrefetchImagesPayload>(value).unwrap(),
+            payload
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Consistency checks between stored records
+//!
+//! The integrity check job looks for episodes whose anime is gone, video
+//! sources whose episode is gone, and favorites of anime missing from the
+//! crawled catalog, and stores an [`IntegrityReport`] as its result. With
+//! auto-repair on, it also queues a scrape of every missing anime, which
+//! restores its detail, episodes, and catalog entry.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::constants::endpoints;
+use crate::crawler::bandwidth::BandwidthMeter;
+use crate::db::{
+    enqueue_job, enqueue_job_with_priority, find_dangling_favorites, find_orphaned_episodes,
+    find_orphaned_video_sources, save_anime_detail_with_episodes, save_crawled_anime,
+    RepositoryError,
+};
+use crate::models::{AnimeDetail, CrawledAnime, IntegrityReport, JobRecord};
+use crate::parser::parse_anime_detail;
+use crate::routes::AppState;
+
+use super::{
+    JobError, DEFAULT_MAX_ATTEMPTS, JOB_TYPE_INTEGRITY_CHECK, JOB_TYPE_SCRAPE_ANIME, QUEUE_CRAWLER,
+    QUEUE_MAINTENANCE,
+};
+
+/// Missing parents listed per kind of inconsistency
+const REPORT_LIMIT: i64 = 500;
+
+/// Scrapes queued by a single repair run
+const MAX_REPAIRS: usize = 100;
+
+/// Payload of an integrity_check job
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityCheckPayload {
+    /// Queue a scrape for every missing anime
+    #[serde(default)]
+    pub repair: bool,
+}
+
+/// Payload of a scrape_anime job
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrapeAnimePayload {
+    /// Slug of the anime to scrape
+    pub slug: String,
+}
+
+/// Queue an integrity check
+pub async fn enqueue_integrity_check(
+    pool: &PgPool,
+    repair: bool,
+) -> Result<JobRecord, RepositoryError> {
+    let payload = serde_json::to_string(&IntegrityCheckPayload { repair })
+        .unwrap_or_else(|_| "{}".to_string());
+    enqueue_job(
+        pool,
+        QUEUE_MAINTENANCE,
+        JOB_TYPE_INTEGRITY_CHECK,
+        &payload,
+        1,
+    )
+    .await
+}
+
+/// Queue a scrape that restores an anime's detail, episodes, and catalog entry
+pub async fn enqueue_scrape_anime(pool: &PgPool, slug: &str) -> Result<JobRecord, RepositoryError> {
+    enqueue_scrape_anime_with_priority(pool, slug, 0).await
+}
+
+/// Queue a scrape of an anime, claimed ahead of lower-priority jobs
+pub async fn enqueue_scrape_anime_with_priority(
+    pool: &PgPool,
+    slug: &str,
+    priority: i32,
+) -> Result<JobRecord, RepositoryError> {
+    let payload = serde_json::to_string(&ScrapeAnimePayload {
+        slug: slug.to_string(),
+    })
+    .unwrap_or_else(|_| "{}".to_string());
+    enqueue_job_with_priority(
+        pool,
+        QUEUE_CRAWLER,
+        JOB_TYPE_SCRAPE_ANIME,
+        &payload,
+        DEFAULT_MAX_ATTEMPTS,
+        priority,
+    )
+    .await
+}
+
+/// Scan for inconsistencies, optionally queueing scrapes to repair them
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `repair` - Queue a scrape for each missing anime (at most 100 per run)
+pub async fn check_integrity(
+    pool: &PgPool,
+    repair: bool,
+) -> Result<IntegrityReport, RepositoryError> {
+    let mut report = IntegrityReport {
+        checked_at: Utc::now().to_rfc3339(),
+        orphaned_episodes: find_orphaned_episodes(pool, REPORT_LIMIT).await?,
+        orphaned_video_sources: find_orphaned_video_sources(pool, REPORT_LIMIT).await?,
+        dangling_favorites: find_dangling_favorites(pool, REPORT_LIMIT).await?,
+        repairs_queued: Vec::new(),
+    };
+
+    if repair {
+        for slug in report.missing_anime().into_iter().take(MAX_REPAIRS) {
+            match enqueue_scrape_anime(pool, &slug).await {
+                Ok(_) => report.repairs_queued.push(slug),
+                Err(e) => warn!("Failed to queue repair scrape of {}: {}", slug, e),
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Run an integrity_check job, returning the serialized report
+pub(super) async fn integrity_check_job(
+    state: &AppState,
+    job: &JobRecord,
+) -> Result<Option<String>, JobError> {
+    let payload: IntegrityCheckPayload = serde_json::from_value(job.payload.clone())
+        .map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+
+    let report = check_integrity(state.db.pool(), payload.repair)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+
+    if report.is_clean() {
+        info!("Integrity check found no inconsistencies");
+    } else {
+        warn!(
+            "Integrity check: {} orphaned episode group(s), {} orphaned video source group(s), \
+             {} dangling favorite group(s), {} repair(s) queued",
+            report.orphaned_episodes.len(),
+            report.orphaned_video_sources.len(),
+            report.dangling_favorites.len(),
+            report.repairs_queued.len()
+        );
+    }
+
+    serde_json::to_string(&report)
+        .map(Some)
+        .map_err(|e| JobError::Failed(e.to_string()))
+}
+
+/// Run a scrape_anime job
+///
+/// An anime page that no longer parses won't recover on retry, so it fails
+/// the job permanently. With the daily crawler bandwidth quota reached, the
+/// attempt fails without fetching and is retried later.
+pub(super) async fn scrape_anime_job(state: &AppState, job: &JobRecord) -> Result<(), JobError> {
+    let payload: ScrapeAnimePayload = serde_json::from_value(job.payload.clone())
+        .map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+
+    let mut meter = crawler_meter(state).await;
+    if meter.exhausted() {
+        return Err(JobError::Failed(
+            "Daily crawler bandwidth quota reached".to_string(),
+        ));
+    }
+    let detail = scrape_and_save_anime(state, &payload.slug, &mut meter).await?;
+    info!(
+        "Restored anime {} ({} episodes)",
+        payload.slug,
+        detail.episodes.len()
+    );
+    Ok(())
+}
+
+/// Scrape an anime's detail page and save its detail, episodes, and catalog
+/// entry
+///
+/// # Returns
+/// * `Ok(AnimeDetail)` - The detail saved
+/// * `Err(JobError::InvalidPayload)` - The page has no anime on it
+/// * `Err(JobError::Failed)` - Fetching or saving failed
+pub(super) async fn scrape_and_save_anime(
+    state: &AppState,
+    slug: &str,
+    meter: &mut BandwidthMeter,
+) -> Result<AnimeDetail, JobError> {
+    let detail = scrape_and_save_detail(state, slug, meter).await?;
+    save_crawled_anime(state.db.pool(), &catalog_entry(state, slug, &detail))
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+
+    Ok(detail)
+}
+
+/// Bandwidth meter for job fetches, under the crawler's daily quota
+pub(super) async fn crawler_meter(state: &AppState) -> BandwidthMeter {
+    BandwidthMeter::load(
+        state.db.pool(),
+        state.config.load().crawler_bandwidth_quota(),
+    )
+    .await
+}
+
+/// Scrape an anime's detail page and save its detail and episodes, leaving
+/// its catalog entry to the caller
+///
+/// The page is counted against `meter`; checking its quota is left to the
+/// caller.
+///
+/// # Returns
+/// * `Ok(AnimeDetail)` - The detail saved
+/// * `Err(JobError::InvalidPayload)` - The page has no anime on it
+/// * `Err(JobError::Failed)` - Fetching or saving failed
+pub(super) async fn scrape_and_save_detail(
+    state: &AppState,
+    slug: &str,
+    meter: &mut BandwidthMeter,
+) -> Result<AnimeDetail, JobError> {
+    let url = endpoints::anime(&state.config.load().base_url, slug);
+    let result = meter
+        .fetch(state.scraper.as_ref(), &url)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+
+    let detail = parse_anime_detail(&result.html);
+    if detail.title.is_empty() {
+        return Err(JobError::InvalidPayload(format!(
+            "Anime {} not found",
+            slug
+        )));
+    }
+
+    save_anime_detail_with_episodes(state.db.pool(), slug, &detail)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+
+    Ok(detail)
+}
+
+/// Catalog entry for a scraped anime
+pub(super) fn catalog_entry(state: &AppState, slug: &str, detail: &AnimeDetail) -> CrawledAnime {
+    CrawledAnime {
+        slug: slug.to_string(),
+        title: detail.title.clone(),
+        url: endpoints::anime(&state.config.load().base_url, slug),
+        thumbnail: detail.poster.clone(),
+        status: detail.status.clone(),
+        anime_type: detail.anime_type.clone(),
+        episode_status: detail.total_episodes.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_serialization() {
+        let payload: IntegrityCheckPayload = serde_json::from_str("{}").unwrap();
+        assert!(!payload.repair);
+        assert_eq!(
+            serde_json::to_value(IntegrityCheckPayload { repair: true }).unwrap(),
+            serde_json::json!({ "repair": true })
+        );
+
+        let payload = ScrapeAnimePayload {
+            slug: "naruto".to_string(),
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value, serde_json::json!({ "slug": "naruto" }));
+        assert_eq!(
+            serde_json::from_value::<ScrapeAnimePayload>(value).unwrap(),
+            payload
+        );
+    }
+}
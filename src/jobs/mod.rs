@@ -0,0 +1,499 @@
+//! Background job queue
+//!
+//! Jobs are persisted in the `jobs` table and executed by a pool of worker
+//! tasks spawned at startup. Failed jobs are retried with exponential backoff
+//! until `max_attempts` is reached, after which they are moved to the `dead`
+//! state and can be requeued from the admin API.
+//!
+//! Runnable jobs are claimed highest priority first, then oldest first.
+//!
+//! [`saved_searches`] holds the scheduler that notifies users about new
+//! matches for their saved searches. [`integrity`] checks stored records
+//! for missing parents and queues scrapes to restore them. [`priority`]
+//! queues prioritized re-scrapes of the anime users follow. [`popularity`]
+//! recomputes the popularity scores behind the catalog's popular order.
+//! [`data_export`] builds the personal data archives users request.
+//! [`gaps`] finds anime missing episodes and queues scrapes to backfill them.
+//! [`completed_archive`] re-crawls the completed anime archive daily.
+//! [`image_prefetch`] warms the image proxy cache for list responses.
+//! [`status_reconcile`] re-checks ongoing anime weekly and tells subscribers
+//! when one completes. [`outbox`] delivers the notification and webhook
+//! events those record. [`anonymous_cleanup`] deletes anonymous device
+//! accounts that were never used. [`change_events_cleanup`] deletes change
+//! feed events every consumer has read once they're old enough.
+
+pub mod anonymous_cleanup;
+pub mod change_events_cleanup;
+pub mod completed_archive;
+pub mod data_export;
+pub mod gaps;
+pub mod image_prefetch;
+pub mod integrity;
+pub mod outbox;
+pub mod popularity;
+pub mod priority;
+pub mod saved_searches;
+pub mod status_reconcile;
+
+use std::time::Duration;
+
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{error, field, info, info_span, warn, Instrument, Span};
+
+use crate::crawler::crawl_with_report;
+use crate::db::{
+    claim_next_job, complete_job, create_email_delivery, enqueue_job, enqueue_job_with_priority,
+    fail_job, get_email_delivery_with_payload, mark_email_delivery_failed,
+    mark_email_delivery_sent, save_crawl_report, set_email_delivery_job, touch_job,
+    RepositoryError,
+};
+use crate::email::{EmailMessage, Language};
+use crate::middleware::trace::{continue_trace, current_traceparent};
+use crate::models::{EmailDelivery, JobRecord};
+use crate::routes::AppState;
+
+/// Queue for bulk crawler jobs
+pub const QUEUE_CRAWLER: &str = "crawler";
+
+/// Queue for transactional emails
+pub const QUEUE_EMAIL: &str = "email";
+
+/// Queue for database consistency checks
+pub const QUEUE_MAINTENANCE: &str = "maintenance";
+
+/// Queue for fetching images into the proxy cache
+pub const QUEUE_IMAGES: &str = "images";
+
+/// Job type for a full catalog crawl
+pub const JOB_TYPE_CRAWL: &str = "crawl";
+
+/// Job type for sending a queued email delivery
+pub const JOB_TYPE_SEND_EMAIL: &str = "send_email";
+
+/// Job type for a consistency check of stored records
+pub const JOB_TYPE_INTEGRITY_CHECK: &str = "integrity_check";
+
+/// Job type for scraping and saving a single anime
+pub const JOB_TYPE_SCRAPE_ANIME: &str = "scrape_anime";
+
+/// Job type for building a user's personal data export
+pub const JOB_TYPE_EXPORT_USER_DATA: &str = "export_user_data";
+
+/// Job type for fetching list thumbnails into the image proxy cache
+pub const JOB_TYPE_PREFETCH_IMAGES: &str = "prefetch_images";
+
+/// Job type for re-checking the status of ongoing anime
+pub const JOB_TYPE_RECONCILE_STATUS: &str = "reconcile_status";
+
+/// Default attempts before a job is dead-lettered
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+/// Priority of jobs a user is waiting on, claimed before background work
+pub const PRIORITY_INTERACTIVE: i32 = 1000;
+
+/// Upper bound for the retry backoff (1 hour)
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+
+/// Job execution errors
+#[derive(Debug, Error)]
+pub enum JobError {
+    #[error("Unknown job type: {0}")]
+    UnknownJobType(String),
+
+    #[error("Invalid job payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("Job failed: {0}")]
+    Failed(String),
+}
+
+impl JobError {
+    /// Whether a failed attempt should be retried
+    ///
+    /// Unknown job types and malformed payloads will never succeed, so they
+    /// are dead-lettered immediately.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, JobError::Failed(_))
+    }
+}
+
+/// Worker pool configuration
+#[derive(Debug, Clone)]
+pub struct JobWorkerConfig {
+    /// Number of concurrent worker tasks
+    pub worker_count: usize,
+    /// How long an idle worker sleeps before polling again (milliseconds)
+    pub poll_interval_ms: u64,
+    /// Base delay for exponential retry backoff (seconds)
+    pub backoff_base_secs: i64,
+    /// Seconds without a heartbeat after which a running job is reclaimed
+    pub stale_after_secs: i64,
+}
+
+impl Default for JobWorkerConfig {
+    fn default() -> Self {
+        Self {
+            worker_count: 2,
+            poll_interval_ms: 1000,
+            backoff_base_secs: 10,
+            stale_after_secs: 300,
+        }
+    }
+}
+
+/// Payload of a crawl job
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlPayload {
+    /// Trace context of the request that queued the crawl
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<String>,
+}
+
+/// Queue a full catalog crawl
+///
+/// Queued from within a traced span (such as a request's), the crawl's
+/// spans join that span's trace.
+pub async fn enqueue_crawl(pool: &PgPool) -> Result<JobRecord, RepositoryError> {
+    let payload = serde_json::to_string(&CrawlPayload {
+        traceparent: current_traceparent(),
+    })
+    .unwrap_or_else(|_| "{}".to_string());
+    enqueue_job(pool, QUEUE_CRAWLER, JOB_TYPE_CRAWL, &payload, 1).await
+}
+
+/// Payload of a send_email job
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SendEmailPayload {
+    /// Delivery record to send
+    pub delivery_id: i32,
+}
+
+/// Queue an email for background delivery
+///
+/// Records a delivery in the email_deliveries table and queues a job to send it.
+///
+/// # Returns
+/// * `Ok(EmailDelivery)` - The queued delivery
+pub async fn enqueue_email(
+    pool: &PgPool,
+    to: &str,
+    language: Language,
+    message: &EmailMessage,
+) -> Result<EmailDelivery, RepositoryError> {
+    let mut conn = pool.acquire().await?;
+    enqueue_email_in(&mut conn, to, language, message).await
+}
+
+/// Queue an email for background delivery on `conn`
+///
+/// Pass a [`UnitOfWork`](crate::db::UnitOfWork) connection to queue it only
+/// if the rest of the flow commits.
+///
+/// # Returns
+/// * `Ok(EmailDelivery)` - The queued delivery
+pub async fn enqueue_email_in(
+    conn: &mut PgConnection,
+    to: &str,
+    language: Language,
+    message: &EmailMessage,
+) -> Result<EmailDelivery, RepositoryError> {
+    let payload = serde_json::to_string(message).unwrap_or_else(|_| "{}".to_string());
+    let delivery = create_email_delivery(
+        &mut *conn,
+        to,
+        message.template_name(),
+        language.code(),
+        &payload,
+    )
+    .await?;
+    let job = enqueue_send_email_job(conn, delivery.id).await?;
+
+    Ok(EmailDelivery {
+        job_id: Some(job.id),
+        ..delivery
+    })
+}
+
+/// Queue another send attempt for an existing delivery
+///
+/// # Returns
+/// * `Ok(Some(JobRecord))` - The new send job
+/// * `Ok(None)` - Delivery not found
+pub async fn resend_email(
+    pool: &PgPool,
+    delivery_id: i32,
+) -> Result<Option<JobRecord>, RepositoryError> {
+    if get_email_delivery_with_payload(pool, delivery_id)
+        .await?
+        .is_none()
+    {
+        return Ok(None);
+    }
+
+    let mut conn = pool.acquire().await?;
+    enqueue_send_email_job(&mut conn, delivery_id)
+        .await
+        .map(Some)
+}
+
+/// Queue a send_email job and link it to the delivery
+async fn enqueue_send_email_job(
+    conn: &mut PgConnection,
+    delivery_id: i32,
+) -> Result<JobRecord, RepositoryError> {
+    let payload = serde_json::to_string(&SendEmailPayload { delivery_id })
+        .unwrap_or_else(|_| "{}".to_string());
+    let job = enqueue_job_with_priority(
+        &mut *conn,
+        QUEUE_EMAIL,
+        JOB_TYPE_SEND_EMAIL,
+        &payload,
+        DEFAULT_MAX_ATTEMPTS,
+        PRIORITY_INTERACTIVE,
+    )
+    .await?;
+    set_email_delivery_job(&mut *conn, delivery_id, job.id).await?;
+    Ok(job)
+}
+
+/// Compute the retry delay for a job that has failed `attempts` times
+///
+/// Doubles the base delay for each attempt, capped at one hour.
+pub fn retry_delay_secs(base_secs: i64, attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    base_secs
+        .saturating_mul(2i64.saturating_pow(exponent))
+        .min(MAX_RETRY_DELAY_SECS)
+}
+
+/// Spawn the job worker pool
+///
+/// # Returns
+/// Handles for the spawned worker tasks
+pub fn spawn_workers(state: web::Data<AppState>, config: JobWorkerConfig) -> Vec<JoinHandle<()>> {
+    info!("Starting {} job workers", config.worker_count);
+
+    (0..config.worker_count)
+        .map(|worker_id| {
+            let state = state.clone();
+            let config = config.clone();
+            tokio::spawn(async move { worker_loop(worker_id, state, config).await })
+        })
+        .collect()
+}
+
+/// Poll for jobs and execute them until the runtime shuts down
+async fn worker_loop(worker_id: usize, state: web::Data<AppState>, config: JobWorkerConfig) {
+    let pool = state.db.pool();
+    let idle = Duration::from_millis(config.poll_interval_ms);
+    let heartbeat = Duration::from_secs((config.stale_after_secs / 3).max(1) as u64);
+
+    loop {
+        let job = match claim_next_job(pool, config.stale_after_secs).await {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                tokio::time::sleep(idle).await;
+                continue;
+            }
+            Err(e) => {
+                error!("Worker {} failed to claim job: {}", worker_id, e);
+                tokio::time::sleep(idle).await;
+                continue;
+            }
+        };
+
+        info!(
+            "Worker {} running job {} ({}/{}, attempt {})",
+            worker_id, job.id, job.queue, job.job_type, job.attempts
+        );
+
+        let execution = execute_job(&state, &job).instrument(job_span(&job));
+        tokio::pin!(execution);
+        let mut ticker = tokio::time::interval(heartbeat);
+        ticker.tick().await;
+
+        let outcome = loop {
+            tokio::select! {
+                outcome = &mut execution => break outcome,
+                _ = ticker.tick() => {
+                    if let Err(e) = touch_job(pool, job.id).await {
+                        warn!("Failed to refresh lock on job {}: {}", job.id, e);
+                    }
+                }
+            }
+        };
+
+        let recorded = match outcome {
+            Ok(result) => {
+                info!("Job {} completed", job.id);
+                complete_job(pool, job.id, result.as_deref()).await
+            }
+            Err(e) => {
+                let retry_in = (e.is_retryable() && job.attempts < job.max_attempts)
+                    .then(|| retry_delay_secs(config.backoff_base_secs, job.attempts));
+                match retry_in {
+                    Some(secs) => warn!("Job {} failed, retrying in {}s: {}", job.id, secs, e),
+                    None => error!("Job {} failed permanently: {}", job.id, e),
+                }
+                fail_job(pool, job.id, &e.to_string(), retry_in).await
+            }
+        };
+
+        if let Err(e) = recorded {
+            error!("Failed to record outcome of job {}: {}", job.id, e);
+        }
+    }
+}
+
+/// Span a job runs in
+///
+/// Continues the trace named by a `traceparent` in the payload, so work
+/// queued by a request shows up in that request's trace.
+fn job_span(job: &JobRecord) -> Span {
+    let span = info_span!(
+        "job",
+        id = job.id,
+        queue = %job.queue,
+        job_type = %job.job_type,
+        attempt = job.attempts,
+        trace_id = field::Empty,
+    );
+    continue_trace(
+        &span,
+        job.payload
+            .get("traceparent")
+            .and_then(|value| value.as_str()),
+    );
+    span
+}
+
+/// Dispatch a job to its handler
+///
+/// # Returns
+/// * `Ok(Some(json))` - Serialized result to store with the job
+/// * `Ok(None)` - Job succeeded without a result
+async fn execute_job(state: &AppState, job: &JobRecord) -> Result<Option<String>, JobError> {
+    match job.job_type.as_str() {
+        JOB_TYPE_CRAWL => {
+            let (data, report) = crawl_with_report(
+                state.db.pool(),
+                &state.config.load().base_url,
+                state.scraper.as_ref(),
+                &state.video_servers,
+                state.config.load().crawler_bandwidth_quota(),
+                &state.api_load,
+            )
+            .await;
+            if let Err(e) = save_crawl_report(state.db.pool(), job.id, &report).await {
+                error!("Failed to save report of crawl job {}: {}", job.id, e);
+            }
+            serde_json::to_string(&data)
+                .map(Some)
+                .map_err(|e| JobError::Failed(e.to_string()))
+        }
+        JOB_TYPE_SEND_EMAIL => send_email_job(state, job).await.map(|_| None),
+        JOB_TYPE_INTEGRITY_CHECK => integrity::integrity_check_job(state, job).await,
+        JOB_TYPE_SCRAPE_ANIME => integrity::scrape_anime_job(state, job).await.map(|_| None),
+        JOB_TYPE_EXPORT_USER_DATA => data_export::export_user_data_job(state, job)
+            .await
+            .map(|_| None),
+        JOB_TYPE_PREFETCH_IMAGES => image_prefetch::prefetch_images_job(state, job).await,
+        JOB_TYPE_RECONCILE_STATUS => status_reconcile::reconcile_status_job(state).await,
+        other => Err(JobError::UnknownJobType(other.to_string())),
+    }
+}
+
+/// Send a queued email and record the outcome on its delivery
+async fn send_email_job(state: &AppState, job: &JobRecord) -> Result<(), JobError> {
+    let pool = state.db.pool();
+    let payload: SendEmailPayload = serde_json::from_value(job.payload.clone())
+        .map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+
+    let (delivery, message) = get_email_delivery_with_payload(pool, payload.delivery_id)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?
+        .ok_or_else(|| {
+            JobError::InvalidPayload(format!("Delivery {} not found", payload.delivery_id))
+        })?;
+
+    // An admin resend supersedes any earlier job still retrying this delivery
+    if delivery.job_id != Some(job.id) {
+        info!("Skipping superseded email job {}", job.id);
+        return Ok(());
+    }
+
+    let message: EmailMessage =
+        serde_json::from_str(&message).map_err(|e| JobError::InvalidPayload(e.to_string()))?;
+
+    let result = match &state.email_service {
+        Some(service) => service
+            .send_message(
+                &delivery.recipient,
+                Language::from_code(&delivery.language),
+                &message,
+            )
+            .await
+            .map_err(|e| JobError::Failed(e.to_string())),
+        None => Err(JobError::Failed("Email service not configured".to_string())),
+    };
+
+    let recorded = match &result {
+        Ok(()) => {
+            info!("Sent {} email to {}", delivery.template, delivery.recipient);
+            mark_email_delivery_sent(pool, delivery.id).await
+        }
+        Err(e) => {
+            let final_attempt = job.attempts >= job.max_attempts;
+            mark_email_delivery_failed(pool, delivery.id, &e.to_string(), final_attempt).await
+        }
+    };
+
+    if let Err(e) = recorded {
+        warn!("Failed to update email delivery {}: {}", delivery.id, e);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_secs_doubles_per_attempt() {
+        assert_eq!(retry_delay_secs(10, 1), 10);
+        assert_eq!(retry_delay_secs(10, 2), 20);
+        assert_eq!(retry_delay_secs(10, 3), 40);
+        assert_eq!(retry_delay_secs(10, 0), 10);
+    }
+
+    #[test]
+    fn test_retry_delay_secs_is_capped() {
+        assert_eq!(retry_delay_secs(10, 20), MAX_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay_secs(10, i32::MAX), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[test]
+    fn test_send_email_payload_serialization() {
+        let payload = SendEmailPayload { delivery_id: 42 };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value, serde_json::json!({ "deliveryId": 42 }));
+        assert_eq!(
+            serde_json::from_value::<SendEmailPayload>(value).unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_job_error_retryable() {
+        assert!(JobError::Failed("timeout".to_string()).is_retryable());
+        assert!(!JobError::UnknownJobType("x".to_string()).is_retryable());
+        assert!(!JobError::InvalidPayload("x".to_string()).is_retryable());
+    }
+}
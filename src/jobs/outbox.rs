@@ -0,0 +1,523 @@
+//! Outbox of notification and webhook events
+//!
+//! A change that should notify someone records an [`OutboxEvent`] in the
+//! same [`UnitOfWork`](crate::db::UnitOfWork) as the change itself, so the
+//! event exists exactly when the change does. A dispatcher task delivers due
+//! events one at a time, each in a transaction holding the event's row lock:
+//! 1. the event is POSTed to the configured webhook, if any
+//! 2. the event's notification emails are queued as send_email jobs
+//! 3. the event is marked dispatched and the transaction commits
+//!
+//! The queued emails and the dispatched mark commit together, so each email
+//! is queued exactly once however often delivery is retried. A failed
+//! delivery is retried with backoff; once the webhook has failed
+//! `max_attempts` times the emails go out without it. Events that only go
+//! to the webhook (`changes`) are never given up on: they're retried at the
+//! longest backoff delay until the webhook takes them. A process that dies
+//! after the webhook answered but before committing sends the webhook again,
+//! with the same event ID in `X-Webhook-Id`, so receivers can drop the
+//! duplicate.
+//!
+//! With a webhook configured, every dispatch first relays new change feed
+//! events (see [`crate::db::get_changes_since`]) into the outbox, in
+//! batches of up to `batch_size` changes per `changes` event. The relay's
+//! feed position moves in the same transaction as each batch is recorded,
+//! so every change is relayed exactly once.
+//!
+//! Delivered events are deleted once they're `retention_days` old, checked
+//! hourly by the dispatcher task.
+
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{PgExecutor, PgPool};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::config::OutboxConfig;
+use crate::db::{
+    claim_next_outbox_event, delete_dispatched_outbox_events, get_changes_since,
+    get_notice_recipient, get_subscriber_recipients, get_user_recipient, lock_change_feed_position,
+    mark_outbox_dispatched, mark_outbox_failed, record_outbox_event, set_change_feed_position,
+    RepositoryError, SubscriberRecipient, UnitOfWork,
+};
+use crate::email::{EmailMessage, Language};
+use crate::middleware::trace::with_traceparent;
+use crate::models::{ChangeEntry, OutboxEventRecord};
+use crate::routes::AppState;
+
+use super::{enqueue_email_in, retry_delay_secs};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Base delay before a failed event is retried (seconds), doubled per failure
+const RETRY_BASE_SECS: i64 = 30;
+
+/// Consumer the webhook relay's change feed position is stored under
+const WEBHOOK_CONSUMER: &str = "webhook";
+
+/// How often delivered events past their retention are deleted (seconds)
+const PRUNE_INTERVAL_SECS: u64 = 3600;
+
+/// Something subscribers and the webhook hear about
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum OutboxEvent {
+    /// An anime finished airing; its subscribers are emailed
+    #[serde(rename = "anime.completed", rename_all = "camelCase")]
+    AnimeCompleted {
+        anime_slug: String,
+        anime_title: String,
+        total_episodes: String,
+    },
+    /// A saved search matched newly crawled anime; its owner is emailed
+    #[serde(rename = "saved_search.matched", rename_all = "camelCase")]
+    SavedSearchMatched {
+        user_id: i32,
+        search_id: i32,
+        search_name: String,
+        match_count: usize,
+        titles: Vec<String>,
+    },
+    /// Anime and episodes were inserted or updated; sent to the webhook only
+    #[serde(rename = "changes")]
+    Changes { changes: Vec<ChangeEntry> },
+    /// A moderator removed a user's content; its author is emailed
+    #[serde(rename = "moderation.content_removed", rename_all = "camelCase")]
+    ContentRemoved {
+        user_id: i32,
+        content_type: String,
+        reason: String,
+        strike_count: i64,
+    },
+    /// A user collected enough strikes to be muted; they're emailed
+    #[serde(rename = "moderation.account_muted", rename_all = "camelCase")]
+    AccountMuted {
+        user_id: i32,
+        muted_until: String,
+        strike_count: i64,
+    },
+}
+
+impl OutboxEvent {
+    /// Event type stored with the event and sent to the webhook
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            OutboxEvent::AnimeCompleted { .. } => "anime.completed",
+            OutboxEvent::SavedSearchMatched { .. } => "saved_search.matched",
+            OutboxEvent::Changes { .. } => "changes",
+            OutboxEvent::ContentRemoved { .. } => "moderation.content_removed",
+            OutboxEvent::AccountMuted { .. } => "moderation.account_muted",
+        }
+    }
+
+    /// Notification email sent for the event, if it has one
+    pub fn email_message(&self) -> Option<EmailMessage> {
+        let message = match self {
+            OutboxEvent::AnimeCompleted {
+                anime_slug,
+                anime_title,
+                total_episodes,
+            } => EmailMessage::SeriesCompleted {
+                anime_slug: anime_slug.clone(),
+                anime_title: anime_title.clone(),
+                total_episodes: total_episodes.clone(),
+            },
+            OutboxEvent::SavedSearchMatched {
+                search_name,
+                match_count,
+                titles,
+                ..
+            } => EmailMessage::SavedSearchMatches {
+                search_name: search_name.clone(),
+                match_count: *match_count,
+                titles: titles.clone(),
+            },
+            OutboxEvent::ContentRemoved {
+                content_type,
+                reason,
+                strike_count,
+                ..
+            } => EmailMessage::ContentRemoved {
+                content_type: content_type.clone(),
+                reason: reason.clone(),
+                strike_count: *strike_count,
+            },
+            OutboxEvent::AccountMuted {
+                muted_until,
+                strike_count,
+                ..
+            } => EmailMessage::AccountMuted {
+                muted_until: muted_until.clone(),
+                strike_count: *strike_count,
+            },
+            OutboxEvent::Changes { .. } => return None,
+        };
+        Some(message)
+    }
+
+    /// Users to email about the event, as of delivery
+    ///
+    /// Moderation notices go to the user's verified address whatever their
+    /// notification preferences.
+    async fn recipients(&self, pool: &PgPool) -> Result<Vec<SubscriberRecipient>, RepositoryError> {
+        match self {
+            OutboxEvent::AnimeCompleted { anime_slug, .. } => {
+                get_subscriber_recipients(pool, anime_slug).await
+            }
+            OutboxEvent::SavedSearchMatched { user_id, .. } => {
+                Ok(get_user_recipient(pool, *user_id)
+                    .await?
+                    .into_iter()
+                    .collect())
+            }
+            OutboxEvent::ContentRemoved { user_id, .. }
+            | OutboxEvent::AccountMuted { user_id, .. } => Ok(get_notice_recipient(pool, *user_id)
+                .await?
+                .map(|(email, language)| SubscriberRecipient {
+                    user_id: *user_id,
+                    email,
+                    language,
+                    email_notifications: true,
+                })
+                .into_iter()
+                .collect()),
+            OutboxEvent::Changes { .. } => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Record an event in the outbox
+///
+/// Pass the [`UnitOfWork`](crate::db::UnitOfWork) connection the change it's
+/// about is written on.
+///
+/// # Returns
+/// The event ID
+pub async fn record_event(
+    executor: impl PgExecutor<'_>,
+    event: &OutboxEvent,
+) -> Result<i64, RepositoryError> {
+    let payload = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string());
+    record_outbox_event(executor, event.event_type(), &payload).await
+}
+
+/// JSON body POSTed to the webhook
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookBody<'a> {
+    id: i64,
+    created_at: &'a str,
+    #[serde(flatten)]
+    event: &'a OutboxEvent,
+}
+
+/// X-Webhook-Signature header value for a body
+fn webhook_signature(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POST an event to the webhook, failing unless it answers with a success
+async fn post_webhook(
+    client: &reqwest::Client,
+    config: &OutboxConfig,
+    url: &str,
+    record: &OutboxEventRecord,
+    event: &OutboxEvent,
+) -> Result<(), String> {
+    let body = serde_json::to_vec(&WebhookBody {
+        id: record.id,
+        created_at: &record.created_at,
+        event,
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut request = with_traceparent(client.post(url))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Id", record.id.to_string())
+        .header("X-Webhook-Event", event.event_type());
+    if let Some(secret) = &config.webhook_secret {
+        request = request.header("X-Webhook-Signature", webhook_signature(secret, &body));
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Webhook answered {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Deliver an event within `uow`: webhook first, then the emails
+///
+/// # Returns
+/// Number of emails queued
+async fn deliver(
+    state: &AppState,
+    client: &reqwest::Client,
+    config: &OutboxConfig,
+    uow: &mut UnitOfWork,
+    record: &OutboxEventRecord,
+) -> Result<usize, String> {
+    let event: OutboxEvent = serde_json::from_str(&record.payload)
+        .map_err(|e| format!("Invalid event payload: {}", e))?;
+
+    if let Some(url) = &config.webhook_url {
+        // An event only the webhook hears about has nothing to fall back
+        // on, so it keeps retrying at the capped delay
+        if record.attempts < config.max_attempts || event.email_message().is_none() {
+            post_webhook(client, config, url, record, &event).await?;
+        } else {
+            warn!(
+                "Delivering outbox event {} without its webhook after {} failed attempts",
+                record.id, record.attempts
+            );
+        }
+    }
+
+    let Some(message) = event.email_message() else {
+        return Ok(0);
+    };
+    let recipients = event
+        .recipients(state.db.pool())
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut queued = 0;
+    for recipient in recipients.iter().filter(|r| r.email_notifications) {
+        let language = Language::from_code(&recipient.language);
+        enqueue_email_in(uow.conn(), &recipient.email, language, &message)
+            .await
+            .map_err(|e| e.to_string())?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+/// Deliver due events, up to the configured batch size
+///
+/// # Returns
+/// * `Ok(count)` - Number of events delivered
+pub async fn dispatch_pending(
+    state: &AppState,
+    client: &reqwest::Client,
+    config: &OutboxConfig,
+) -> Result<usize, RepositoryError> {
+    let mut dispatched = 0;
+
+    for _ in 0..config.batch_size {
+        let mut uow = state.db.unit_of_work().await?;
+        let Some(record) = claim_next_outbox_event(uow.conn()).await? else {
+            break;
+        };
+
+        match deliver(state, client, config, &mut uow, &record).await {
+            Ok(queued) => {
+                mark_outbox_dispatched(uow.conn(), record.id).await?;
+                uow.commit().await?;
+                info!(
+                    "Delivered outbox event {} ({}), {} email(s) queued",
+                    record.id, record.event_type, queued
+                );
+                dispatched += 1;
+            }
+            Err(e) => {
+                // Drop any emails queued before the failure; they're queued
+                // again with the retry
+                uow.rollback().await?;
+                warn!(
+                    "Failed to deliver outbox event {} ({}): {}",
+                    record.id, record.event_type, e
+                );
+                let retry_in = retry_delay_secs(RETRY_BASE_SECS, record.attempts + 1);
+                mark_outbox_failed(state.db.pool(), record.id, &e, retry_in).await?;
+            }
+        }
+    }
+
+    Ok(dispatched)
+}
+
+/// Relay new change feed events into the outbox for the webhook
+///
+/// Does nothing without a webhook. A relay seen for the first time starts
+/// at the newest change rather than replaying the whole feed.
+///
+/// # Returns
+/// * `Ok(count)` - Number of changes relayed
+pub async fn relay_changes(
+    state: &AppState,
+    config: &OutboxConfig,
+) -> Result<usize, RepositoryError> {
+    if config.webhook_url.is_none() {
+        return Ok(0);
+    }
+
+    let mut relayed = 0;
+    loop {
+        let mut uow = state.db.unit_of_work().await?;
+        let position = lock_change_feed_position(uow.conn(), WEBHOOK_CONSUMER).await?;
+        let page = get_changes_since(
+            uow.conn(),
+            DateTime::<Utc>::UNIX_EPOCH,
+            Some(position),
+            config.batch_size as i64,
+        )
+        .await?;
+        let Some(last) = page.last().map(|(_, cursor)| *cursor) else {
+            return Ok(relayed);
+        };
+
+        let count = page.len();
+        let event = OutboxEvent::Changes {
+            changes: page.into_iter().map(|(entry, _)| entry).collect(),
+        };
+        record_event(uow.conn(), &event).await?;
+        set_change_feed_position(uow.conn(), WEBHOOK_CONSUMER, last).await?;
+        uow.commit().await?;
+
+        relayed += count;
+        if count < config.batch_size {
+            return Ok(relayed);
+        }
+    }
+}
+
+/// Delete events delivered more than `retention_days` ago
+///
+/// # Returns
+/// * `Ok(count)` - Number of events deleted
+pub async fn prune_dispatched(pool: &PgPool, retention_days: u32) -> Result<u64, RepositoryError> {
+    let before = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+    delete_dispatched_outbox_events(pool, before).await
+}
+
+/// Spawn a task delivering due events every `config.interval_secs`
+///
+/// The same task deletes old delivered events every hour.
+pub fn spawn_dispatcher(state: web::Data<AppState>, config: OutboxConfig) -> JoinHandle<()> {
+    info!(
+        "Dispatching outbox events every {}s{}",
+        config.interval_secs,
+        if config.webhook_url.is_some() {
+            " to email and the webhook"
+        } else {
+            ""
+        }
+    );
+
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.webhook_timeout_secs))
+            .build()
+            .expect("Failed to build webhook client");
+        let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        let mut pruner = tokio::time::interval(Duration::from_secs(PRUNE_INTERVAL_SECS));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if let Err(e) = relay_changes(&state, &config).await {
+                        error!("Failed to relay changes to the outbox: {}", e);
+                    }
+                    if let Err(e) = dispatch_pending(&state, &client, &config).await {
+                        error!("Outbox dispatch failed: {}", e);
+                    }
+                }
+                _ = pruner.tick() => {
+                    match prune_dispatched(state.db.pool(), config.retention_days).await {
+                        Ok(0) => {}
+                        Ok(deleted) => info!("Deleted {} delivered outbox events", deleted),
+                        Err(e) => error!("Outbox cleanup failed: {}", e),
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed() -> OutboxEvent {
+        OutboxEvent::AnimeCompleted {
+            anime_slug: "frieren".to_string(),
+            anime_title: "Frieren".to_string(),
+            total_episodes: "28".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_round_trips_with_type() {
+        let event = completed();
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(payload["type"], "anime.completed");
+        assert_eq!(payload["animeSlug"], "frieren");
+        assert_eq!(payload["type"], event.event_type());
+        assert_eq!(
+            serde_json::from_value::<OutboxEvent>(payload).unwrap(),
+            event
+        );
+
+        let body = serde_json::to_value(WebhookBody {
+            id: 7,
+            created_at: "2024-01-01T00:00:00+00:00",
+            event: &event,
+        })
+        .unwrap();
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["type"], "anime.completed");
+        assert_eq!(body["totalEpisodes"], "28");
+    }
+
+    #[test]
+    fn test_email_message() {
+        assert_eq!(
+            completed().email_message(),
+            Some(EmailMessage::SeriesCompleted {
+                anime_slug: "frieren".to_string(),
+                anime_title: "Frieren".to_string(),
+                total_episodes: "28".to_string(),
+            })
+        );
+        let changes = OutboxEvent::Changes {
+            changes: Vec::new(),
+        };
+        assert_eq!(changes.email_message(), None);
+
+        let muted = OutboxEvent::AccountMuted {
+            user_id: 42,
+            muted_until: "2024-12-28 10:30 UTC".to_string(),
+            strike_count: 3,
+        };
+        assert_eq!(
+            muted.email_message(),
+            Some(EmailMessage::AccountMuted {
+                muted_until: "2024-12-28 10:30 UTC".to_string(),
+                strike_count: 3,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&muted).unwrap()["type"],
+            muted.event_type()
+        );
+    }
+
+    #[test]
+    fn test_webhook_signature() {
+        // HMAC-SHA256 test vector from RFC 4231, test case 2
+        assert_eq!(
+            webhook_signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
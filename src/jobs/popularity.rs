@@ -0,0 +1,72 @@
+//! Popularity scoring
+//!
+//! Every crawled anime carries a popularity score used by the catalog's
+//! `order=popular`. The score sums detail-page views, favorites, and
+//! subscriptions, each weighted by how strong a signal it is and halved for
+//! every [`HALF_LIFE_DAYS`] since it happened, so a series that was hot last
+//! year sinks below one people are watching this week. A scheduler task
+//! recomputes the scores periodically.
+
+use std::time::Duration;
+
+use actix_web::web;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+use crate::db::{refresh_popularity_scores, PopularityWeights, RepositoryError};
+use crate::routes::AppState;
+
+/// Score of one anime detail view
+pub const VIEW_WEIGHT: f64 = 1.0;
+
+/// Score of one favorite
+pub const FAVORITE_WEIGHT: f64 = 5.0;
+
+/// Score of one subscription
+///
+/// Subscribers come back for every episode, so they outweigh favorites.
+pub const SUBSCRIPTION_WEIGHT: f64 = 10.0;
+
+/// Days after which a signal counts half as much
+pub const HALF_LIFE_DAYS: f64 = 7.0;
+
+/// Days of daily view counts kept
+///
+/// Eight half-lives in, a view is worth under 0.4% of a fresh one.
+pub const VIEW_WINDOW_DAYS: i32 = 56;
+
+/// Weights used for the popularity score
+pub fn weights() -> PopularityWeights {
+    PopularityWeights {
+        view: VIEW_WEIGHT,
+        favorite: FAVORITE_WEIGHT,
+        subscription: SUBSCRIPTION_WEIGHT,
+        half_life_days: HALF_LIFE_DAYS,
+        view_window_days: VIEW_WINDOW_DAYS,
+    }
+}
+
+/// Spawn a task recomputing popularity scores every `interval`
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!("Refreshing popularity scores every {}s", interval.as_secs());
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match refresh_scores(state.db.pool()).await {
+                Ok(updated) => debug!("Updated popularity of {} anime", updated),
+                Err(e) => error!("Popularity refresh failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Recompute every crawled anime's popularity score
+///
+/// # Returns
+/// * `Ok(count)` - Number of anime whose score changed
+pub async fn refresh_scores(pool: &PgPool) -> Result<u64, RepositoryError> {
+    refresh_popularity_scores(pool, &weights()).await
+}
@@ -0,0 +1,120 @@
+//! Priority re-scraping of followed anime
+//!
+//! The full crawl revisits the whole catalog, which takes hours and mostly
+//! finds nothing new. Anime that users subscribe to or favorite are where
+//! fresh data matters, so a scheduler task periodically queues scrape_anime
+//! jobs for the followed anime whose last scrape is older than the configured
+//! age. The jobs carry a priority computed from how many users follow the
+//! anime, so the queue handles the most popular series first.
+
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::Utc;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::config::PriorityCrawlConfig;
+use crate::db::{find_stale_popular_anime, PopularAnime, RepositoryError};
+use crate::routes::AppState;
+
+use super::integrity::enqueue_scrape_anime_with_priority;
+use super::JOB_TYPE_SCRAPE_ANIME;
+
+/// How many favorites a subscriber counts as
+///
+/// Subscribers are waiting for new episodes, favorites merely bookmarked.
+pub const SUBSCRIBER_WEIGHT: i64 = 3;
+
+/// Highest priority given to a re-scrape
+///
+/// Keeps background refreshes behind jobs a user is waiting on (see
+/// [`PRIORITY_INTERACTIVE`](super::PRIORITY_INTERACTIVE)).
+pub const MAX_REFRESH_PRIORITY: i32 = 100;
+
+/// Job priority for re-scraping an anime
+///
+/// Grows with the number of followers, weighting subscribers by
+/// [`SUBSCRIBER_WEIGHT`], and is capped at [`MAX_REFRESH_PRIORITY`]. Every
+/// followed anime gets at least 1, ahead of unprioritized jobs.
+pub fn refresh_priority(anime: &PopularAnime) -> i32 {
+    let followers = anime
+        .subscribers
+        .saturating_mul(SUBSCRIBER_WEIGHT)
+        .saturating_add(anime.favorites);
+    followers.clamp(1, MAX_REFRESH_PRIORITY as i64) as i32
+}
+
+/// Spawn a task queueing re-scrapes of stale followed anime every `interval_secs`
+pub fn spawn_scheduler(state: web::Data<AppState>, config: PriorityCrawlConfig) -> JoinHandle<()> {
+    info!(
+        "Re-scraping followed anime older than {}s every {}s",
+        config.max_age_secs, config.interval_secs
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        loop {
+            ticker.tick().await;
+            match queue_priority_scrapes(state.db.pool(), &config).await {
+                Ok(0) => {}
+                Ok(queued) => info!("Queued {} priority re-scrape(s)", queued),
+                Err(e) => error!("Priority crawl check failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Queue re-scrapes of the most followed anime that have gone stale
+///
+/// # Returns
+/// * `Ok(count)` - Number of scrape jobs queued
+pub async fn queue_priority_scrapes(
+    pool: &PgPool,
+    config: &PriorityCrawlConfig,
+) -> Result<usize, RepositoryError> {
+    let scraped_before = Utc::now() - chrono::Duration::seconds(config.max_age_secs as i64);
+    let stale = find_stale_popular_anime(
+        pool,
+        scraped_before,
+        JOB_TYPE_SCRAPE_ANIME,
+        SUBSCRIBER_WEIGHT,
+        config.batch_size,
+    )
+    .await?;
+
+    let mut queued = 0;
+    for anime in &stale {
+        match enqueue_scrape_anime_with_priority(pool, &anime.slug, refresh_priority(anime)).await {
+            Ok(_) => queued += 1,
+            Err(e) => warn!("Failed to queue re-scrape of {}: {}", anime.slug, e),
+        }
+    }
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn popular(subscribers: i64, favorites: i64) -> PopularAnime {
+        PopularAnime {
+            slug: "one-piece".to_string(),
+            subscribers,
+            favorites,
+        }
+    }
+
+    #[test]
+    fn test_refresh_priority() {
+        assert_eq!(refresh_priority(&popular(0, 1)), 1);
+        assert_eq!(refresh_priority(&popular(0, 0)), 1);
+        assert_eq!(refresh_priority(&popular(2, 4)), 10);
+        assert!(refresh_priority(&popular(1, 0)) > refresh_priority(&popular(0, 2)));
+        assert_eq!(
+            refresh_priority(&popular(i64::MAX, i64::MAX)),
+            MAX_REFRESH_PRIORITY
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Saved search notifications
+//!
+//! A scheduler task periodically re-runs every saved search against anime
+//! crawled since its last check and records one outbox event per search with
+//! new matches, which emails the owner. Each search is marked checked up to
+//! the start of the run in the same transaction as its event, so an anime is
+//! reported exactly once per search.
+
+use std::time::Duration;
+
+use actix_web::web;
+use chrono::Utc;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::db::{
+    find_saved_search_matches, get_saved_search_recipients, mark_saved_search_checked,
+    RepositoryError, SavedSearchRecipient,
+};
+use crate::models::CrawledAnimeRecord;
+use crate::routes::AppState;
+
+use super::outbox::{record_event, OutboxEvent};
+
+/// Matches fetched per saved search and check
+const MAX_MATCHES: i64 = 100;
+
+/// Titles listed in a notification email
+const MAX_LISTED_TITLES: usize = 10;
+
+/// Spawn a task checking saved searches every `interval`
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!("Checking saved searches every {}s", interval.as_secs());
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match check_saved_searches(&state).await {
+                Ok(0) => {}
+                Ok(notified) => info!("Recorded {} saved search notification(s)", notified),
+                Err(e) => error!("Saved search check failed: {}", e),
+            }
+        }
+    })
+}
+
+/// Check every saved search once
+///
+/// Owners who turned off email notifications still have their searches
+/// checked and events recorded, but aren't emailed, so turning notifications
+/// back on doesn't send a backlog.
+///
+/// # Returns
+/// * `Ok(count)` - Number of notification events recorded
+pub async fn check_saved_searches(state: &AppState) -> Result<usize, RepositoryError> {
+    let pool = state.db.pool();
+    let checked_at = Utc::now();
+    let mut notified = 0;
+
+    for recipient in get_saved_search_recipients(pool).await? {
+        let search_id = recipient.search.id;
+        let matches = find_saved_search_matches(pool, search_id, checked_at, MAX_MATCHES).await?;
+
+        let mut uow = state.db.unit_of_work().await?;
+        if let Some(event) = notification(&recipient, &matches) {
+            record_event(uow.conn(), &event).await?;
+            notified += 1;
+        }
+        mark_saved_search_checked(uow.conn(), search_id, checked_at).await?;
+        uow.commit().await?;
+    }
+
+    Ok(notified)
+}
+
+/// Notification event for new matches, or `None` if there are none
+fn notification(
+    recipient: &SavedSearchRecipient,
+    matches: &[CrawledAnimeRecord],
+) -> Option<OutboxEvent> {
+    if matches.is_empty() {
+        return None;
+    }
+
+    Some(OutboxEvent::SavedSearchMatched {
+        user_id: recipient.user_id,
+        search_id: recipient.search.id,
+        search_name: recipient.search.name.clone(),
+        match_count: matches.len(),
+        titles: matches
+            .iter()
+            .take(MAX_LISTED_TITLES)
+            .map(|anime| anime.title.clone())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::EmailMessage;
+    use crate::models::SavedSearch;
+
+    fn record(title: &str) -> CrawledAnimeRecord {
+        CrawledAnimeRecord {
+            id: 1,
+            slug: title.to_lowercase(),
+            title: title.to_string(),
+            url: String::new(),
+            thumbnail: String::new(),
+            status: "Ongoing".to_string(),
+            anime_type: "TV".to_string(),
+            episode_status: String::new(),
+            popularity_score: 0.0,
+            created_at: "2024-12-27T10:00:00+00:00".to_string(),
+            updated_at: "2024-12-27T10:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_notification_lists_capped_titles() {
+        let recipient = SavedSearchRecipient {
+            search: SavedSearch {
+                id: 7,
+                name: "isekai TV".to_string(),
+                query: Some("isekai".to_string()),
+                anime_type: Some("TV".to_string()),
+                status: None,
+                last_checked_at: "2024-12-27T09:00:00+00:00".to_string(),
+                created_at: "2024-12-27T09:00:00+00:00".to_string(),
+            },
+            user_id: 1,
+            email: "user@example.com".to_string(),
+            language: "en".to_string(),
+            email_notifications: true,
+        };
+
+        assert_eq!(notification(&recipient, &[]), None);
+
+        let matches: Vec<_> = (0..12).map(|i| record(&format!("Isekai {}", i))).collect();
+        match notification(&recipient, &matches).and_then(|event| event.email_message()) {
+            Some(EmailMessage::SavedSearchMatches {
+                search_name,
+                match_count,
+                titles,
+            }) => {
+                assert_eq!(search_name, "isekai TV");
+                assert_eq!(match_count, 12);
+                assert_eq!(titles.len(), MAX_LISTED_TITLES);
+                assert_eq!(titles[0], "Isekai 0");
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Weekly reconciliation of airing status
+//!
+//! Anime are marked Ongoing when first crawled, and the list pages that
+//! would show them finishing aren't re-crawled often. A scheduler task
+//! queues a reconcile_status job every week, which re-scrapes the detail
+//! page of every anime still marked Ongoing in crawled_anime, saving its
+//! current status and episode count. An anime found to have completed gets
+//! an outbox event, recorded with its catalog status so it's sent exactly
+//! once, which emails its subscribers unless they turned those off. The
+//! re-scrapes count against the daily crawler bandwidth quota; once it is
+//! reached the rest wait for the next run.
+
+use std::time::Duration;
+
+use actix_web::web;
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::db::{enqueue_job, get_crawled_anime_by_status, save_crawled_anime, RepositoryError};
+use crate::models::{AnimeDetail, JobRecord};
+use crate::routes::AppState;
+
+use super::integrity::{catalog_entry, crawler_meter, scrape_and_save_detail};
+use super::outbox::{record_event, OutboxEvent};
+use super::{JobError, JOB_TYPE_RECONCILE_STATUS, QUEUE_MAINTENANCE};
+
+/// Status of anime the reconciliation re-checks
+const ONGOING: &str = "Ongoing";
+
+/// Outcome of a reconcile_status job
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusReconcileReport {
+    /// Ongoing anime re-scraped
+    pub checked: usize,
+    /// Anime whose status or episode count changed
+    pub updated: usize,
+    /// Slugs of anime that have completed
+    pub completed: Vec<String>,
+    /// Anime that couldn't be scraped or saved
+    pub failed: usize,
+    /// Ongoing anime left unchecked because the daily crawler bandwidth
+    /// quota was reached
+    pub skipped: usize,
+}
+
+/// Whether a status means the anime finished airing
+fn is_completed(status: &str) -> bool {
+    status.trim().eq_ignore_ascii_case("completed")
+}
+
+/// Event telling subscribers an anime completed
+fn completion_event(slug: &str, detail: &AnimeDetail) -> OutboxEvent {
+    let total_episodes = match detail.total_episodes.trim() {
+        "" | "?" => detail.episodes.len().to_string(),
+        total => total.to_string(),
+    };
+    OutboxEvent::AnimeCompleted {
+        anime_slug: slug.to_string(),
+        anime_title: detail.title.clone(),
+        total_episodes,
+    }
+}
+
+/// Queue a reconciliation of every ongoing anime
+pub async fn enqueue_reconcile_status(pool: &PgPool) -> Result<JobRecord, RepositoryError> {
+    enqueue_job(pool, QUEUE_MAINTENANCE, JOB_TYPE_RECONCILE_STATUS, "{}", 1).await
+}
+
+/// Save an anime's catalog entry, recording a completion event with it if
+/// it completed
+async fn save_status(
+    state: &AppState,
+    slug: &str,
+    detail: &AnimeDetail,
+) -> Result<(), RepositoryError> {
+    let mut uow = state.db.unit_of_work().await?;
+    save_crawled_anime(uow.conn(), &catalog_entry(state, slug, detail)).await?;
+    if is_completed(&detail.status) {
+        record_event(uow.conn(), &completion_event(slug, detail)).await?;
+    }
+    uow.commit().await
+}
+
+/// Re-scrape every ongoing anime and record events for completed ones
+///
+/// An anime that fails to scrape is counted and skipped; the next run
+/// checks it again, as it does the anime left once the bandwidth quota is
+/// reached.
+pub async fn reconcile_status(state: &AppState) -> Result<StatusReconcileReport, RepositoryError> {
+    let pool = state.db.pool();
+    let mut report = StatusReconcileReport::default();
+    let mut meter = crawler_meter(state).await;
+
+    let ongoing = get_crawled_anime_by_status(pool, ONGOING).await?;
+    let total = ongoing.len();
+    for (index, anime) in ongoing.into_iter().enumerate() {
+        if meter.exhausted() {
+            report.skipped = total - index;
+            warn!(
+                "Daily crawler bandwidth quota reached, leaving {} ongoing anime unchecked",
+                report.skipped
+            );
+            break;
+        }
+        let saved = match scrape_and_save_detail(state, &anime.slug, &mut meter).await {
+            Ok(detail) => save_status(state, &anime.slug, &detail)
+                .await
+                .map(|_| detail)
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        let detail = match saved {
+            Ok(detail) => detail,
+            Err(e) => {
+                warn!("Failed to reconcile status of {}: {}", anime.slug, e);
+                report.failed += 1;
+                continue;
+            }
+        };
+        report.checked += 1;
+
+        if detail.status != anime.status || detail.total_episodes != anime.episode_status {
+            report.updated += 1;
+        }
+        if is_completed(&detail.status) {
+            report.completed.push(anime.slug);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Run a reconcile_status job, returning the serialized report
+pub(super) async fn reconcile_status_job(state: &AppState) -> Result<Option<String>, JobError> {
+    let report = reconcile_status(state)
+        .await
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+
+    info!(
+        "Reconciled {} ongoing anime: {} updated, {} completed, {} failed, {} skipped",
+        report.checked,
+        report.updated,
+        report.completed.len(),
+        report.failed,
+        report.skipped
+    );
+    serde_json::to_string(&report)
+        .map(Some)
+        .map_err(|e| JobError::Failed(e.to_string()))
+}
+
+/// Spawn a task queueing a reconciliation every `interval`
+pub fn spawn_scheduler(state: web::Data<AppState>, interval: Duration) -> JoinHandle<()> {
+    info!(
+        "Reconciling the status of ongoing anime every {}s",
+        interval.as_secs()
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = enqueue_reconcile_status(state.db.pool()).await {
+                error!("Failed to queue status reconciliation: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_notice() {
+        let mut detail = AnimeDetail {
+            title: "Frieren".to_string(),
+            status: "Completed".to_string(),
+            total_episodes: "28".to_string(),
+            ..Default::default()
+        };
+        assert!(is_completed(&detail.status));
+        assert!(!is_completed(ONGOING));
+        assert_eq!(
+            completion_event("frieren", &detail),
+            OutboxEvent::AnimeCompleted {
+                anime_slug: "frieren".to_string(),
+                anime_title: "Frieren".to_string(),
+                total_episodes: "28".to_string(),
+            }
+        );
+
+        // An unknown count falls back to the episodes listed
+        detail.total_episodes = "?".to_string();
+        match completion_event("frieren", &detail) {
+            OutboxEvent::AnimeCompleted { total_episodes, .. } => {
+                assert_eq!(total_episodes, "0")
+            }
+            other => panic!("unexpected notice: {:?}", other),
+        }
+    }
+}
@@ -6,10 +6,29 @@
 pub mod auth;
 pub mod config;
 pub mod constants;
+pub mod crawler;
 pub mod db;
 pub mod email;
 pub mod error;
+pub mod features;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod jobs;
+#[cfg(unix)]
+pub mod listeners;
+pub mod logging;
+pub mod middleware;
 pub mod models;
+pub mod moderation;
+pub mod nfo;
 pub mod parser;
+pub mod reload;
 pub mod routes;
 pub mod scraper;
+pub mod sitemap;
+pub mod storage;
+pub mod tenants;
+pub mod tls;
+pub mod translation;
+pub mod video_servers;
+pub mod xml;
@@ -0,0 +1,178 @@
+//! Sockets the bundled server listens on besides HOST:PORT
+//!
+//! A Unix socket (BIND=unix:<path>) is created with UNIX_SOCKET_MODE
+//! permissions, replacing the socket file a previous run left behind. With
+//! BIND=systemd the server takes the sockets systemd passes under socket
+//! activation (LISTEN_PID and LISTEN_FDS, descriptors from 3 on), TCP or
+//! Unix, e.g. from a socket unit:
+//!
+//! ```ini
+//! [Socket]
+//! ListenStream=/run/anime-scraper/api.sock
+//! SocketMode=0660
+//! SocketGroup=www-data
+//! ```
+
+use std::fs::Permissions;
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+
+use thiserror::Error;
+
+/// First descriptor systemd passes
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Errors setting up the listening sockets
+#[derive(Debug, Error)]
+pub enum ListenError {
+    #[error("Failed to bind Unix socket {0}: {1}")]
+    Unix(String, std::io::Error),
+
+    #[error("{0} exists and isn't a socket")]
+    NotASocket(String),
+
+    #[error("No sockets passed by systemd (LISTEN_FDS unset or for another process)")]
+    NotActivated,
+}
+
+/// A socket passed by systemd
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Bind a Unix socket at `path` with `mode` permissions
+///
+/// A socket file already at `path` is removed first; any other file is left
+/// alone and refused.
+pub fn bind_unix(path: &str, mode: u32) -> Result<UnixListener, ListenError> {
+    let io_error = |e| ListenError::Unix(path.to_string(), e);
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(io_error)?
+        }
+        Ok(_) => return Err(ListenError::NotASocket(path.to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(io_error(e)),
+    }
+
+    let listener = UnixListener::bind(path).map_err(io_error)?;
+    std::fs::set_permissions(path, Permissions::from_mode(mode)).map_err(io_error)?;
+    Ok(listener)
+}
+
+/// Take the sockets systemd passed to this process
+///
+/// Call once: the descriptors are owned by the returned listeners.
+pub fn systemd_listeners() -> Result<Vec<Listener>, ListenError> {
+    let count = listen_fds(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    )
+    .ok_or(ListenError::NotActivated)?;
+
+    Ok((LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(listener_from_fd)
+        .collect())
+}
+
+/// Number of sockets passed to process `pid`, from LISTEN_PID and LISTEN_FDS
+fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Option<RawFd> {
+    if listen_pid?.trim().parse::<u32>().ok()? != pid {
+        return None;
+    }
+    let count = listen_fds?.trim().parse().ok()?;
+    (count > 0).then_some(count)
+}
+
+/// Wrap a passed descriptor in a listener of its socket type
+fn listener_from_fd(fd: RawFd) -> Listener {
+    // SAFETY: systemd passes the descriptor to this process alone, and
+    // systemd_listeners takes each one once
+    let tcp = unsafe { TcpListener::from_raw_fd(fd) };
+    // Only an IP socket has an IP address
+    if tcp.local_addr().is_ok() {
+        return Listener::Tcp(tcp);
+    }
+    // SAFETY: ownership moves from the TCP listener, which is consumed
+    Listener::Unix(unsafe { UnixListener::from_raw_fd(tcp.into_raw_fd()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn socket_path() -> PathBuf {
+        std::env::temp_dir().join(format!("listener-test-{}.sock", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_bind_unix_sets_mode_and_replaces_stale_socket() {
+        let path = socket_path();
+        let path_str = path.to_str().unwrap();
+
+        let first = bind_unix(path_str, 0o660).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        // A crashed run leaves its socket file behind
+        drop(first);
+        let listener = bind_unix(path_str, 0o600).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bind_unix_refuses_other_files() {
+        let path = socket_path();
+        std::fs::write(&path, "not a socket").unwrap();
+
+        let result = bind_unix(path.to_str().unwrap(), 0o660);
+        assert!(matches!(result, Err(ListenError::NotASocket(_))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_listen_fds() {
+        assert_eq!(listen_fds(Some("42"), Some("2"), 42), Some(2));
+        // Passed to another process, e.g. inherited from a parent
+        assert_eq!(listen_fds(Some("41"), Some("2"), 42), None);
+        assert_eq!(listen_fds(None, Some("2"), 42), None);
+        assert_eq!(listen_fds(Some("42"), None, 42), None);
+        assert_eq!(listen_fds(Some("42"), Some("0"), 42), None);
+        assert_eq!(listen_fds(Some("42"), Some("two"), 42), None);
+    }
+
+    #[test]
+    fn test_listener_from_fd_detects_socket_type() {
+        let tcp = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = tcp.local_addr().unwrap();
+        match listener_from_fd(tcp.into_raw_fd()) {
+            Listener::Tcp(listener) => assert_eq!(listener.local_addr().unwrap(), addr),
+            Listener::Unix(_) => panic!("expected a TCP listener"),
+        }
+
+        let path = socket_path();
+        let unix = UnixListener::bind(&path).unwrap();
+        match listener_from_fd(unix.into_raw_fd()) {
+            Listener::Unix(listener) => {
+                let addr = listener.local_addr().unwrap();
+                assert_eq!(addr.as_pathname(), Some(path.as_path()));
+            }
+            Listener::Tcp(_) => panic!("expected a Unix listener"),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! Log output setup
+//!
+//! Logs go to stdout, to rolling files, or both, as text or one JSON object
+//! per line. The level comes from RUST_LOG (any filter directives) or
+//! LOG_LEVEL, and LOG_MODULES sets levels per module on top of it:
+//!
+//! ```text
+//! LOG_MODULES=scraper=debug,sqlx=warn
+//! ```
+//!
+//! A bare name ("scraper", "jobs", "sqlx") sets the level of the module of
+//! that name in this crate and of any crate of that name, so this crate's
+//! modules and dependencies can both be named directly; paths with `::`
+//! ("actix_web::middleware") are used as written. See [`LogConfig`] for the
+//! settings.
+//!
+//! Spans also feed OpenTelemetry, which gives every request and job a W3C
+//! trace context (see [`crate::middleware::trace`]). When
+//! OTEL_EXPORTER_OTLP_ENDPOINT is set, they're exported to that collector
+//! over OTLP/HTTP in batches; otherwise they only carry trace IDs.
+
+use opentelemetry::trace::{TraceError, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use thiserror::Error;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{InitError, RollingFileAppender, Rotation};
+use tracing_subscriber::filter::Directive;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+use crate::config::{LogConfig, LogFileConfig, LogFormat, LogRotation, OtlpConfig};
+
+/// Crate name log targets of this crate's modules start with
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// Parse LOG_MODULES ("scraper=debug,sqlx=warn") into (module, level) pairs
+///
+/// Entries without a module or a level are skipped.
+pub fn parse_module_levels(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let (module, level) = entry.split_once('=')?;
+            let (module, level) = (module.trim(), level.trim());
+            (!module.is_empty() && !level.is_empty())
+                .then(|| (module.to_string(), level.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+/// Log targets of a module named in LOG_MODULES
+///
+/// A bare name targets both this crate's module and a crate of that name;
+/// whichever doesn't exist matches no logs.
+pub fn module_targets(module: &str) -> Vec<String> {
+    if module.contains("::") || module == CRATE_TARGET {
+        vec![module.to_string()]
+    } else {
+        vec![format!("{}::{}", CRATE_TARGET, module), module.to_string()]
+    }
+}
+
+/// Filter with the base level and every per-module level
+///
+/// Invalid directives are reported on stderr, as logging isn't up yet, and
+/// left out.
+pub fn build_filter(config: &LogConfig) -> EnvFilter {
+    let mut filter = EnvFilter::try_new(&config.level).unwrap_or_else(|e| {
+        eprintln!("Invalid log level {:?} ({}), using info", config.level, e);
+        EnvFilter::new("info")
+    });
+    for (module, level) in &config.modules {
+        for target in module_targets(module) {
+            let directive = format!("{}={}", target, level);
+            match directive.parse::<Directive>() {
+                Ok(directive) => filter = filter.add_directive(directive),
+                Err(e) => eprintln!("Ignoring log level {:?} ({})", directive, e),
+            }
+        }
+    }
+    filter
+}
+
+/// One output in the configured format
+fn output_layer<W>(
+    format: LogFormat,
+    writer: W,
+    ansi: bool,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Rolling file appender for the configured directory and rotation
+fn file_appender(config: &LogFileConfig) -> Result<RollingFileAppender, InitError> {
+    let rotation = match config.rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(&config.prefix)
+        .filename_suffix("log");
+    if config.max_files > 0 {
+        builder = builder.max_log_files(config.max_files);
+    }
+    builder.build(&config.dir)
+}
+
+/// URL spans are POSTed to on an OTLP collector
+fn otlp_traces_endpoint(endpoint: &str) -> String {
+    format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+}
+
+/// Tracer provider for the OpenTelemetry layer, exporting when configured
+///
+/// The batch exporter runs on its own thread, so flushing it at shutdown
+/// doesn't block the runtime it was started from.
+fn tracer_provider(otlp: Option<&OtlpConfig>) -> Result<TracerProvider, TraceError> {
+    let mut builder = TracerProvider::builder();
+    if let Some(otlp) = otlp {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(otlp_traces_endpoint(&otlp.endpoint))
+            .build()?;
+        builder = builder
+            .with_batch_exporter(exporter, runtime::TokioCurrentThread)
+            .with_resource(Resource::new([KeyValue::new(
+                "service.name",
+                otlp.service_name.clone(),
+            )]));
+    }
+    Ok(builder.build())
+}
+
+/// Why logging couldn't be set up
+#[derive(Debug, Error)]
+pub enum LogInitError {
+    #[error("Failed to open log files: {0}")]
+    Files(#[from] InitError),
+
+    #[error("Failed to set up the OTLP exporter: {0}")]
+    Exporter(#[from] TraceError),
+}
+
+/// Keeps log outputs running; dropping it flushes them
+///
+/// Pending log lines are written to the files and pending spans are
+/// exported before the drop returns.
+pub struct LogGuard {
+    _files: Option<WorkerGuard>,
+    tracer_provider: TracerProvider,
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to flush spans: {}", e);
+        }
+    }
+}
+
+/// Install the global log subscriber
+///
+/// Must be called from within a Tokio runtime when spans are exported.
+///
+/// # Returns
+/// * `Ok(LogGuard)` - Keep it alive for as long as the process logs
+/// * `Err(LogInitError)` - The log directory couldn't be created or opened,
+///   or the OTLP exporter couldn't be built
+pub fn init(config: &LogConfig) -> Result<LogGuard, LogInitError> {
+    let mut outputs = Vec::new();
+    if config.stdout {
+        outputs.push(output_layer(config.format, std::io::stdout, true));
+    }
+
+    let guard = match &config.file {
+        Some(file) => {
+            let (writer, guard) = tracing_appender::non_blocking(file_appender(file)?);
+            outputs.push(output_layer(config.format, writer, false));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    let tracer_provider = tracer_provider(config.otlp.as_ref())?;
+    outputs.push(
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer_provider.tracer(CRATE_TARGET))
+            .boxed(),
+    );
+
+    tracing_subscriber::registry()
+        .with(outputs)
+        .with(build_filter(config))
+        .init();
+    Ok(LogGuard {
+        _files: guard,
+        tracer_provider,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module_levels() {
+        assert_eq!(
+            parse_module_levels("scraper=debug, sqlx=WARN,,broken, =info,jobs="),
+            vec![
+                ("scraper".to_string(), "debug".to_string()),
+                ("sqlx".to_string(), "warn".to_string()),
+            ]
+        );
+        assert!(parse_module_levels("").is_empty());
+    }
+
+    #[test]
+    fn test_build_filter_targets_crate_modules() {
+        assert_eq!(
+            module_targets("scraper"),
+            vec!["anime_scraper::scraper", "scraper"]
+        );
+        assert_eq!(module_targets("sqlx"), vec!["anime_scraper::sqlx", "sqlx"]);
+        assert_eq!(
+            module_targets("actix_web::middleware"),
+            vec!["actix_web::middleware"]
+        );
+        assert_eq!(module_targets("anime_scraper"), vec!["anime_scraper"]);
+
+        let config = LogConfig {
+            level: "info".to_string(),
+            modules: parse_module_levels("scraper=debug,sqlx=warn,jobs=loud"),
+            ..LogConfig::default()
+        };
+        let filter = build_filter(&config).to_string();
+        assert!(filter.contains("anime_scraper::scraper=debug"));
+        assert!(filter.contains("sqlx=warn"));
+        assert!(!filter.contains("jobs"));
+    }
+
+    #[test]
+    fn test_file_appender_creates_directory() {
+        let dir = std::env::temp_dir().join(format!("anime-scraper-logs-{}", uuid::Uuid::new_v4()));
+        let config = LogFileConfig {
+            dir: dir.to_string_lossy().into_owned(),
+            prefix: "test".to_string(),
+            rotation: LogRotation::Daily,
+            max_files: 3,
+        };
+        assert!(file_appender(&config).is_ok());
+        assert!(dir.is_dir());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_otlp_traces_endpoint() {
+        assert_eq!(
+            otlp_traces_endpoint("http://collector:4318"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            otlp_traces_endpoint("http://collector:4318/"),
+            "http://collector:4318/v1/traces"
+        );
+    }
+}
@@ -2,110 +2,153 @@
 //!
 //! Main entry point for the anime scraper REST API service.
 
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{web, App, HttpServer};
 use tracing::{error, info};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
 
-use anime_scraper::auth::AuthConfig;
-use anime_scraper::config::Config;
-use anime_scraper::db::Database;
-use anime_scraper::email::EmailService;
-use anime_scraper::routes::{
-    configure_auth_routes, configure_routes, configure_user_routes, ApiDoc, AppState,
-};
-
-/// Health check endpoint
-async fn health_check() -> impl Responder {
-    HttpResponse::Ok().json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
-}
-
-/// Database health check endpoint
-async fn db_health_check(data: web::Data<AppState>) -> impl Responder {
-    match data.db.health_check().await {
-        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
-            "status": "healthy",
-            "database": "connected",
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        })),
-        Err(e) => {
-            error!("Database health check failed: {}", e);
-            HttpResponse::ServiceUnavailable().json(serde_json::json!({
-                "status": "unhealthy",
-                "database": "disconnected",
-                "error": e.to_string(),
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            }))
-        }
-    }
-}
+use anime_scraper::config::{BindTarget, Config};
+#[cfg(unix)]
+use anime_scraper::listeners::{self, Listener};
+use anime_scraper::logging;
+use anime_scraper::routes::{build_app, init, spawn_background_tasks};
+use anime_scraper::tls::{self, HttpsPort};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
     let config = Config::from_env();
-    let bind_address = format!("{}:{}", config.host, config.port);
-
-    info!("Connecting to database...");
-    let db = Database::new(&config.database_url)
-        .await
-        .expect("Failed to connect to database");
+    // Dropping the guard would stop writing log files and exporting spans
+    let _log_guard = logging::init(&config.logging)
+        .unwrap_or_else(|e| panic!("Failed to set up logging: {}", e));
 
-    info!("Running database migrations...");
-    db.run_migrations()
+    let bind_address = format!("{}:{}", config.host, config.port);
+    info!(
+        "Environment profile: {} (secure cookies {}, SameSite {}, verbose errors {})",
+        config.app_env.as_str(),
+        config.cookies.secure,
+        config.cookies.same_site,
+        config.verbose_errors
+    );
+
+    let app_state = init(config.clone())
         .await
-        .expect("Failed to run database migrations");
-
-    info!("Database connected and migrations complete");
-
-    // Initialize email service if SMTP is configured
-    let email_service = config.smtp.as_ref().map(|smtp_config| {
-        info!("Email service configured");
-        EmailService::new(smtp_config.clone(), config.frontend_url.clone())
-    });
-
-    if email_service.is_none() {
-        info!("Email service not configured - email features will be disabled");
+        .unwrap_or_else(|e| panic!("Failed to start: {}", e));
+    spawn_background_tasks(&app_state);
+    #[cfg(unix)]
+    anime_scraper::reload::spawn_reload_on_sighup(app_state.clone());
+
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_addr) = &config.grpc_addr {
+        let addr = grpc_addr
+            .parse()
+            .expect("GRPC_ADDR must be a socket address");
+        let state = app_state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = anime_scraper::grpc::serve(addr, state).await {
+                error!("gRPC server failed: {}", e);
+            }
+        });
+    }
+    #[cfg(not(feature = "grpc"))]
+    if config.grpc_addr.is_some() {
+        error!("GRPC_ADDR is set but this build has no gRPC support (feature `grpc`)");
     }
 
-    let app_state = web::Data::new(AppState {
-        db,
-        config: config.clone(),
-        email_service,
-    });
-
-    let auth_config = web::Data::new(AuthConfig {
-        jwt_secret: config.jwt_secret.clone(),
+    let server = HttpServer::new(move || App::new().service(build_app(app_state.clone())));
+    let server_config = config.tls.as_ref().map(|tls_config| {
+        tls::server_config(tls_config)
+            .unwrap_or_else(|e| panic!("Failed to load TLS certificate: {}", e))
     });
-
-    info!("Starting Anime Scraper API server on {}", bind_address);
-
-    let openapi = ApiDoc::openapi();
-
-    HttpServer::new(move || {
-        App::new()
-            .app_data(app_state.clone())
-            .app_data(auth_config.clone())
-            .route("/health", web::get().to(health_check))
-            .route("/health/db", web::get().to(db_health_check))
-            .service(
-                SwaggerUi::new("/swagger-ui/{_:.*}").url("/api-docs/openapi.json", openapi.clone()),
-            )
-            .configure(configure_routes)
-            .configure(configure_auth_routes)
-            .configure(configure_user_routes)
-    })
-    .bind(&bind_address)?
-    .run()
-    .await
+    let scheme = if server_config.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
+    let server = match &config.bind {
+        BindTarget::Tcp => {
+            info!(
+                "Starting Anime Scraper API server on {}://{}{}",
+                scheme, bind_address, config.base_path
+            );
+            match server_config.clone() {
+                Some(server_config) => server.bind_rustls_0_23(&bind_address, server_config)?,
+                None => server.bind(&bind_address)?,
+            }
+        }
+        #[cfg(unix)]
+        BindTarget::Unix(path) => {
+            if server_config.is_some() {
+                panic!("TLS is only supported on TCP sockets; unset TLS_* with BIND=unix:...");
+            }
+            info!(
+                "Starting Anime Scraper API server on unix:{}{}",
+                path, config.base_path
+            );
+            let listener = listeners::bind_unix(path, config.unix_socket_mode)
+                .unwrap_or_else(|e| panic!("Failed to listen: {}", e));
+            server.listen_uds(listener)?
+        }
+        #[cfg(unix)]
+        BindTarget::Systemd => {
+            let mut server = server;
+            let sockets = listeners::systemd_listeners()
+                .unwrap_or_else(|e| panic!("Failed to listen: {}", e));
+            for socket in sockets {
+                server = match socket {
+                    Listener::Tcp(listener) => {
+                        info!(
+                            "Starting Anime Scraper API server on {}://{}{} (systemd)",
+                            scheme,
+                            listener.local_addr()?,
+                            config.base_path
+                        );
+                        match server_config.clone() {
+                            Some(server_config) => {
+                                server.listen_rustls_0_23(listener, server_config)?
+                            }
+                            None => server.listen(listener)?,
+                        }
+                    }
+                    Listener::Unix(listener) => {
+                        info!(
+                            "Starting Anime Scraper API server on unix:{}{} (systemd)",
+                            listener
+                                .local_addr()?
+                                .as_pathname()
+                                .map(|path| path.display().to_string())
+                                .unwrap_or_default(),
+                            config.base_path
+                        );
+                        server.listen_uds(listener)?
+                    }
+                };
+            }
+            server
+        }
+        #[cfg(not(unix))]
+        BindTarget::Unix(_) | BindTarget::Systemd => {
+            panic!("BIND=unix:... and BIND=systemd need a Unix platform")
+        }
+    };
+    let server = server.run();
+
+    match config
+        .tls
+        .as_ref()
+        .and_then(|tls_config| tls_config.redirect_port)
+    {
+        Some(redirect_port) => {
+            let https_port = HttpsPort(config.port);
+            let redirect_address = format!("{}:{}", config.host, redirect_port);
+            info!("Redirecting HTTP on {} to HTTPS", redirect_address);
+            let redirect = HttpServer::new(move || {
+                App::new()
+                    .app_data(web::Data::new(https_port))
+                    .default_service(web::to(tls::redirect_to_https))
+            })
+            .bind(&redirect_address)?
+            .run();
+            tokio::try_join!(server, redirect).map(|_| ())
+        }
+        None => server.await,
+    }
 }
@@ -0,0 +1,196 @@
+//! Cache-Control headers per endpoint class
+//!
+//! Scraped data changes slowly, so public reads can be cached by browsers and
+//! CDNs (Cloudflare, Fastly) in front of the service. Lists change whenever
+//! new episodes land and are cached for minutes; anime and episode details
+//! are cached for hours. Anything user-specific or mutating is marked
+//! `private, no-store`.
+//!
+//! Requests carrying credentials (Authorization header or auth cookie) are
+//! always treated as private, since some public endpoints personalize their
+//! response (e.g. preferred episode quality).
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+
+use crate::auth::AUTH_COOKIE_NAME;
+use crate::config::CacheControlConfig;
+use crate::routes::AppState;
+
+/// Cache-Control for private and mutating responses
+pub const NO_STORE: &str = "private, no-store";
+
+/// Caching class of an endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointClass {
+    /// Public lists: updates, completed, search, anime list, community top
+    List,
+    /// Public details: anime and episode pages
+    Detail,
+    /// User, auth, admin, crawler, health, and all non-GET requests
+    Private,
+    /// Endpoints that set their own Cache-Control (image proxy, docs)
+    Unmanaged,
+}
+
+/// Classify a request by method, path, and whether it carries credentials
+pub fn classify(method: &Method, path: &str, has_credentials: bool) -> EndpointClass {
+    if path.starts_with("/api/images/proxy") || path.starts_with("/swagger-ui") {
+        return EndpointClass::Unmanaged;
+    }
+    if (method != Method::GET && method != Method::HEAD) || has_credentials {
+        return EndpointClass::Private;
+    }
+
+    match path.trim_end_matches('/') {
+        "/api/updates" | "/api/completed" | "/api/search" | "/api/anime/list"
+        | "/api/community/top" => EndpointClass::List,
+        p if p.starts_with("/api/anime/") || p.starts_with("/api/episode/") => {
+            EndpointClass::Detail
+        }
+        _ => EndpointClass::Private,
+    }
+}
+
+/// Cache-Control value for a class, or `None` to leave the response alone
+pub fn header_value(class: EndpointClass, config: &CacheControlConfig) -> Option<String> {
+    match class {
+        EndpointClass::List => Some(format!(
+            "public, max-age={}, s-maxage={}",
+            config.list_max_age, config.list_s_maxage
+        )),
+        EndpointClass::Detail => Some(format!(
+            "public, max-age={}, s-maxage={}",
+            config.detail_max_age, config.detail_s_maxage
+        )),
+        EndpointClass::Private => Some(NO_STORE.to_string()),
+        EndpointClass::Unmanaged => None,
+    }
+}
+
+/// Middleware setting Cache-Control according to the endpoint class
+///
+/// Only successful and 304 responses get public caching; errors are
+/// `no-store` so a transient upstream failure isn't cached by a CDN. A
+/// Cache-Control header set by the handler is never overwritten.
+pub async fn cache_control(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let config = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.config.load().cache_control.clone())
+        .unwrap_or_default();
+    let has_credentials =
+        req.headers().contains_key(header::AUTHORIZATION) || req.cookie(AUTH_COOKIE_NAME).is_some();
+    // Classified by the path below the scope the API is mounted under, if any
+    let class = classify(
+        req.method(),
+        req.match_info().unprocessed(),
+        has_credentials,
+    );
+
+    let mut res = next.call(req).await?;
+
+    if !config.enabled || res.headers().contains_key(header::CACHE_CONTROL) {
+        return Ok(res);
+    }
+
+    let value = if res.status().is_success() || res.status() == StatusCode::NOT_MODIFIED {
+        header_value(class, &config)
+    } else if class == EndpointClass::Unmanaged {
+        None
+    } else {
+        Some(NO_STORE.to_string())
+    };
+
+    if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        res.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_public_endpoints() {
+        assert_eq!(
+            classify(&Method::GET, "/api/updates", false),
+            EndpointClass::List
+        );
+        assert_eq!(
+            classify(&Method::GET, "/api/anime/list", false),
+            EndpointClass::List
+        );
+        assert_eq!(
+            classify(&Method::GET, "/api/community/top", false),
+            EndpointClass::List
+        );
+        assert_eq!(
+            classify(&Method::GET, "/api/anime/one-piece", false),
+            EndpointClass::Detail
+        );
+        assert_eq!(
+            classify(&Method::HEAD, "/api/episode/one-piece-episode-1", false),
+            EndpointClass::Detail
+        );
+    }
+
+    #[test]
+    fn test_classify_private_endpoints() {
+        assert_eq!(
+            classify(&Method::GET, "/api/updates", true),
+            EndpointClass::Private
+        );
+        assert_eq!(
+            classify(&Method::POST, "/api/crawler/run", false),
+            EndpointClass::Private
+        );
+        for path in [
+            "/api/favorites",
+            "/api/user/sessions",
+            "/api/admin/jobs",
+            "/health",
+        ] {
+            assert_eq!(
+                classify(&Method::GET, path, false),
+                EndpointClass::Private,
+                "{}",
+                path
+            );
+        }
+        assert_eq!(
+            classify(&Method::GET, "/api/images/proxy", true),
+            EndpointClass::Unmanaged
+        );
+    }
+
+    #[test]
+    fn test_header_value_uses_config() {
+        let config = CacheControlConfig {
+            list_max_age: 30,
+            list_s_maxage: 120,
+            ..Default::default()
+        };
+        assert_eq!(
+            header_value(EndpointClass::List, &config).as_deref(),
+            Some("public, max-age=30, s-maxage=120")
+        );
+        assert_eq!(
+            header_value(EndpointClass::Detail, &config).as_deref(),
+            Some("public, max-age=600, s-maxage=3600")
+        );
+        assert_eq!(
+            header_value(EndpointClass::Private, &config).as_deref(),
+            Some(NO_STORE)
+        );
+        assert_eq!(header_value(EndpointClass::Unmanaged, &config), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! Response encoding negotiated from the Accept header
+//!
+//! Handlers always produce JSON. Clients that prefer a binary format can ask
+//! for it with `Accept: application/msgpack` or `Accept: application/cbor`;
+//! the JSON body is then re-encoded on the way out. Large payloads (anime
+//! with thousands of episodes) shrink substantially, mostly because numbers
+//! and short strings no longer need quoting and escaping.
+//!
+//! MessagePack is written by rmp-serde and CBOR by ciborium, both from the
+//! JSON value, so only the JSON data model (null, booleans, numbers, strings,
+//! arrays, and maps with string keys) ever reaches them.
+
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::Error;
+use serde_json::Value;
+use tracing::warn;
+
+/// Media type of MessagePack responses
+pub const MSGPACK: &str = "application/msgpack";
+/// Media type of CBOR responses
+pub const CBOR: &str = "application/cbor";
+
+/// Response body encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// JSON, as produced by the handlers
+    Json,
+    /// MessagePack
+    MessagePack,
+    /// CBOR (RFC 8949)
+    Cbor,
+}
+
+impl Encoding {
+    /// Pick the encoding an Accept header prefers
+    ///
+    /// A binary format is only used when the client ranks it above JSON (or
+    /// a wildcard covering JSON); between the two binary formats the higher
+    /// quality wins, the first listed on a tie. Anything unsupported falls
+    /// back to JSON rather than 406 Not Acceptable.
+    pub fn from_accept(accept: &str) -> Self {
+        let mut best = (Encoding::Json, 0.0);
+        let mut json_quality: f32 = 0.0;
+        for (media_type, quality) in media_ranges(accept) {
+            let encoding = match media_type.as_str() {
+                MSGPACK | "application/x-msgpack" | "application/vnd.msgpack" => {
+                    Encoding::MessagePack
+                }
+                CBOR => Encoding::Cbor,
+                "application/json" | "application/*" | "*/*" => {
+                    json_quality = json_quality.max(quality);
+                    continue;
+                }
+                _ => continue,
+            };
+            if quality > best.1 {
+                best = (encoding, quality);
+            }
+        }
+
+        if best.1 > json_quality {
+            best.0
+        } else {
+            Encoding::Json
+        }
+    }
+
+    /// Content-Type of responses in this encoding
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::MessagePack => MSGPACK,
+            Encoding::Cbor => CBOR,
+        }
+    }
+
+    /// Encode a JSON value
+    ///
+    /// # Returns
+    /// * `Ok(bytes)` - The encoded value
+    /// * `Err(message)` - The encoder failed
+    pub fn encode(self, value: &Value) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Encoding::MessagePack => rmp_serde::to_vec(value).map_err(|e| e.to_string()),
+            Encoding::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(value, &mut out).map_err(|e| e.to_string())?;
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Media types of an Accept header, lowercased, with their quality
+fn media_ranges(accept: &str) -> impl Iterator<Item = (String, f32)> + '_ {
+    accept.split(',').map(|range| {
+        let mut params = range.split(';');
+        let media_type = params
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.parse().ok())
+            .unwrap_or(1.0);
+        (media_type, quality)
+    })
+}
+
+/// Middleware re-encoding JSON responses in the encoding the client accepts
+///
+/// Every JSON (and 304) response gets `Vary: Accept` so shared caches keep
+/// the encodings apart. Responses that are not JSON, such as proxied images,
+/// pass through untouched.
+pub async fn negotiate_encoding(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let encoding = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(Encoding::from_accept)
+        .unwrap_or(Encoding::Json);
+
+    let mut res = next.call(req).await?;
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if is_json || res.status() == StatusCode::NOT_MODIFIED {
+        res.headers_mut()
+            .append(header::VARY, HeaderValue::from_static("Accept"));
+    }
+    if !is_json || encoding == Encoding::Json {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let e: Box<dyn std::error::Error> = e.into();
+            return Err(actix_web::error::ErrorInternalServerError(e.to_string()));
+        }
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        // Not valid JSON after all; send it as it is
+        return Ok(ServiceResponse::new(
+            req,
+            res.set_body(bytes).map_into_boxed_body(),
+        ));
+    };
+
+    let encoded = match encoding.encode(&value) {
+        Ok(encoded) => encoded,
+        Err(e) => {
+            warn!(
+                "Failed to encode response as {}: {}",
+                encoding.content_type(),
+                e
+            );
+            return Ok(ServiceResponse::new(
+                req,
+                res.set_body(bytes).map_into_boxed_body(),
+            ));
+        }
+    };
+    let mut res = res.set_body(encoded);
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(encoding.content_type()),
+    );
+    Ok(ServiceResponse::new(req, res.map_into_boxed_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn msgpack(value: &Value) -> Vec<u8> {
+        Encoding::MessagePack.encode(value).unwrap()
+    }
+
+    fn cbor(value: &Value) -> Vec<u8> {
+        Encoding::Cbor.encode(value).unwrap()
+    }
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(Encoding::from_accept("application/json"), Encoding::Json);
+        assert_eq!(Encoding::from_accept("*/*"), Encoding::Json);
+        assert_eq!(
+            Encoding::from_accept("text/html,application/xhtml+xml,*/*;q=0.8"),
+            Encoding::Json
+        );
+        assert_eq!(
+            Encoding::from_accept("application/msgpack"),
+            Encoding::MessagePack
+        );
+        assert_eq!(
+            Encoding::from_accept("application/cbor, application/json;q=0.5"),
+            Encoding::Cbor
+        );
+        assert_eq!(
+            Encoding::from_accept("application/x-msgpack;q=0.9, application/cbor;q=0.4"),
+            Encoding::MessagePack
+        );
+        // JSON wins when ranked at least as high
+        assert_eq!(
+            Encoding::from_accept("application/json, application/cbor"),
+            Encoding::Json
+        );
+        assert_eq!(
+            Encoding::from_accept("application/cbor;q=0"),
+            Encoding::Json
+        );
+        assert_eq!(Encoding::from_accept("application/xml"), Encoding::Json);
+    }
+
+    #[test]
+    fn test_msgpack_encoding() {
+        assert_eq!(msgpack(&json!(null)), [0xc0]);
+        assert_eq!(msgpack(&json!(true)), [0xc3]);
+        assert_eq!(msgpack(&json!(5)), [0x05]);
+        assert_eq!(msgpack(&json!(200)), [0xcc, 0xc8]);
+        assert_eq!(msgpack(&json!(1000)), [0xcd, 0x03, 0xe8]);
+        assert_eq!(msgpack(&json!(-1)), [0xff]);
+        assert_eq!(msgpack(&json!(-100)), [0xd0, 0x9c]);
+        assert_eq!(msgpack(&json!(1.5)), [0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(msgpack(&json!("ab")), [0xa2, b'a', b'b']);
+        assert_eq!(
+            msgpack(&json!({"a": [1, "x"]})),
+            [0x81, 0xa1, b'a', 0x92, 0x01, 0xa1, b'x']
+        );
+
+        let long = "x".repeat(40);
+        assert_eq!(msgpack(&json!(long))[..2], [0xd9, 40]);
+        let items: Vec<u8> = vec![0; 20];
+        assert_eq!(msgpack(&json!(items))[..3], [0xdc, 0, 20]);
+    }
+
+    #[test]
+    fn test_cbor_encoding() {
+        // Examples from RFC 8949 Appendix A
+        assert_eq!(cbor(&json!(null)), [0xf6]);
+        assert_eq!(cbor(&json!(false)), [0xf4]);
+        assert_eq!(cbor(&json!(10)), [0x0a]);
+        assert_eq!(cbor(&json!(100)), [0x18, 0x64]);
+        assert_eq!(cbor(&json!(1000)), [0x19, 0x03, 0xe8]);
+        assert_eq!(cbor(&json!(-1)), [0x20]);
+        assert_eq!(cbor(&json!(-1000)), [0x39, 0x03, 0xe7]);
+        assert_eq!(
+            cbor(&json!(1.1)),
+            [0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]
+        );
+        assert_eq!(cbor(&json!("IETF")), [0x64, 0x49, 0x45, 0x54, 0x46]);
+        assert_eq!(
+            cbor(&json!({"a": 1, "b": [2, 3]})),
+            [0xa2, 0x61, 0x61, 0x01, 0x61, 0x62, 0x82, 0x02, 0x03]
+        );
+    }
+
+    #[test]
+    fn test_binary_encodings_are_smaller() {
+        let episodes: Vec<Value> = (1..=2000)
+            .map(|n| json!({"number": n.to_string(), "title": format!("Episode {}", n)}))
+            .collect();
+        let value = json!({"success": true, "data": {"episodes": episodes}});
+
+        let json_len = Encoding::Json.encode(&value).unwrap().len();
+        assert!(msgpack(&value).len() < json_len);
+        assert!(cbor(&value).len() < json_len);
+    }
+}
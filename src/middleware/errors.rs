@@ -0,0 +1,142 @@
+//! Error body sanitizing
+//!
+//! Handlers put the underlying error into 5xx messages ("Database error:
+//! relation ... does not exist"), which helps in development but leaks
+//! internals in production. Unless `Config::verbose_errors` is on, the
+//! message of a 5xx [`ApiError`](crate::models::ApiError) body is replaced
+//! with the status's generic reason and its details are dropped; the error
+//! code is kept so clients can still tell failures apart. `FEATURE_DISABLED`
+//! bodies are left alone, as they carry the operator's reason for clients.
+
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use serde_json::Value;
+
+use crate::models::ErrorCode;
+use crate::routes::AppState;
+
+/// Replace the message and drop the details of an API error body
+///
+/// # Returns
+/// Whether `value` was an API error body and was changed
+pub fn sanitize_error_body(value: &mut Value, status: StatusCode) -> bool {
+    let Some(object) = value.as_object_mut() else {
+        return false;
+    };
+    if object.get("success") != Some(&Value::Bool(false)) || !object.contains_key("error") {
+        return false;
+    }
+    if object.get("code") == Some(&serde_json::json!(ErrorCode::FeatureDisabled)) {
+        return false;
+    }
+    let reason = status.canonical_reason().unwrap_or("Server Error");
+    object.insert("error".to_string(), Value::String(reason.to_string()));
+    object.remove("details");
+    true
+}
+
+/// Middleware hiding internal error messages of 5xx responses
+///
+/// Must be wrapped inside [`negotiate_encoding`](super::negotiate_encoding)
+/// so it sees the JSON body before it's re-encoded.
+pub async fn sanitize_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let verbose = req
+        .app_data::<web::Data<AppState>>()
+        .is_none_or(|state| state.config.load().verbose_errors);
+    let res = next.call(req).await?;
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if verbose || !is_json || !res.status().is_server_error() {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let status = res.status();
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let e: Box<dyn std::error::Error> = e.into();
+            return Err(actix_web::error::ErrorInternalServerError(e.to_string()));
+        }
+    };
+    let mut value = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => value,
+        Err(_) => {
+            return Ok(ServiceResponse::new(
+                req,
+                res.set_body(bytes).map_into_boxed_body(),
+            ))
+        }
+    };
+    if !sanitize_error_body(&mut value, status) {
+        return Ok(ServiceResponse::new(
+            req,
+            res.set_body(bytes).map_into_boxed_body(),
+        ));
+    }
+
+    let mut res = res.set_body(serde_json::to_vec(&value).unwrap_or_default());
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Ok(ServiceResponse::new(req, res.map_into_boxed_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ApiError;
+
+    #[test]
+    fn test_sanitize_error_body() {
+        let error = ApiError::new(
+            ErrorCode::DatabaseError,
+            "Database error: relation \"anime_details\" does not exist",
+        )
+        .with_details(serde_json::json!({ "query": "SELECT 1" }));
+        let mut value = serde_json::to_value(&error).unwrap();
+
+        assert!(sanitize_error_body(
+            &mut value,
+            StatusCode::INTERNAL_SERVER_ERROR
+        ));
+        assert_eq!(value["error"], "Internal Server Error");
+        assert_eq!(value["code"], "DATABASE_ERROR");
+        assert_eq!(value["timestamp"], error.timestamp);
+        assert!(value.get("details").is_none());
+
+        let mut value = serde_json::to_value(
+            ApiError::new(
+                ErrorCode::FeatureDisabled,
+                "The search-upstream feature is disabled",
+            )
+            .with_details(serde_json::json!({ "feature": "search-upstream" })),
+        )
+        .unwrap();
+        assert!(!sanitize_error_body(
+            &mut value,
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert_eq!(value["details"]["feature"], "search-upstream");
+
+        let mut value = serde_json::json!({ "status": "unhealthy", "error": "down" });
+        assert!(!sanitize_error_body(
+            &mut value,
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert_eq!(value["error"], "down");
+    }
+}
@@ -0,0 +1,83 @@
+//! Feature flag enforcement
+//!
+//! Requests to an endpoint whose feature an operator switched off (see
+//! [`crate::features`]) get 503 with code `FEATURE_DISABLED` before reaching
+//! any handler.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use serde_json::json;
+
+use crate::features::gated_feature;
+use crate::models::{ApiError, ErrorCode, FeatureFlag};
+use crate::routes::AppState;
+
+/// 503 body for a disabled feature, with the operator's reason if any
+pub fn disabled_error(flag: &FeatureFlag) -> ApiError {
+    let message = match &flag.reason {
+        Some(reason) => format!(
+            "The {} feature is disabled: {}",
+            flag.feature.as_str(),
+            reason
+        ),
+        None => format!(
+            "The {} feature is temporarily disabled",
+            flag.feature.as_str()
+        ),
+    };
+    ApiError::new(ErrorCode::FeatureDisabled, message)
+        .with_details(json!({ "feature": flag.feature }))
+}
+
+/// Middleware refusing requests to disabled features
+///
+/// Must be wrapped outside [`sanitize_errors`](super::sanitize_errors), which
+/// would replace the reason with a generic message.
+pub async fn gate_features(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    // Matched by the path below the scope the API is mounted under, if any
+    let flag = gated_feature(req.method(), req.match_info().unprocessed()).and_then(|feature| {
+        let state = req.app_data::<web::Data<AppState>>()?;
+        Some(state.features.get(feature))
+    });
+
+    if let Some(flag) = flag.filter(|flag| !flag.enabled) {
+        let response = HttpResponse::ServiceUnavailable().json(disabled_error(&flag));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Feature;
+
+    #[test]
+    fn test_disabled_error() {
+        let mut flag = FeatureFlag {
+            enabled: false,
+            ..FeatureFlag::default_for(Feature::SearchUpstream)
+        };
+        let error = disabled_error(&flag);
+        assert_eq!(error.code, ErrorCode::FeatureDisabled);
+        assert_eq!(
+            error.error,
+            "The search-upstream feature is temporarily disabled"
+        );
+        assert_eq!(error.details, Some(json!({ "feature": "search-upstream" })));
+
+        flag.reason = Some("source site is down".to_string());
+        assert_eq!(
+            disabled_error(&flag).error,
+            "The search-upstream feature is disabled: source site is down"
+        );
+    }
+}
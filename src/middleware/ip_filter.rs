@@ -0,0 +1,274 @@
+//! Client IP resolution and filtering
+//!
+//! Behind a reverse proxy the peer address is the proxy's, and the client is
+//! in X-Forwarded-For. That header is only believed when the peer is a
+//! trusted proxy, and it is read from the right: each trusted hop is skipped
+//! and the first untrusted address is the client. Anything further left was
+//! written by the client and could be spoofed.
+//!
+//! The resolved IP is stored in the request extensions for rate limiting and
+//! session records (see [`client_ip`]), then checked against the configured
+//! deny and allow lists.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse};
+use ipnet::IpNet;
+use tracing::warn;
+
+use crate::config::{BindTarget, IpFilterConfig};
+use crate::models::{ApiError, ErrorCode};
+use crate::routes::AppState;
+
+/// Header carrying the client and proxy chain
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Client IP resolved by [`filter_ips`], stored in the request extensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Parse an address as found in a peer address or X-Forwarded-For hop
+///
+/// Accepts `ip`, `ip:port`, and `[ipv6]:port`; IPv4-mapped IPv6 addresses
+/// are returned as IPv4.
+pub fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<SocketAddr>()
+        .map(|addr| addr.ip())
+        .or_else(|_| value.trim_matches(['[', ']']).parse::<IpAddr>())
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+/// Resolve the client IP from the peer address and X-Forwarded-For
+///
+/// # Arguments
+/// * `peer` - Address of the connecting peer
+/// * `forwarded_for` - Comma-separated X-Forwarded-For hops, oldest first
+/// * `trusted_proxies` - Networks whose forwarding headers are believed
+///
+/// # Returns
+/// The first untrusted address walking back from the peer. When every hop
+/// is trusted, the oldest one; when a hop is malformed, the last good one.
+pub fn resolve_client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpNet],
+) -> Option<IpAddr> {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+
+    let mut client = peer?.to_canonical();
+    let Some(forwarded_for) = forwarded_for else {
+        return Some(client);
+    };
+
+    for hop in forwarded_for.rsplit(',') {
+        if !is_trusted(&client) {
+            break;
+        }
+        match parse_ip(hop) {
+            Some(ip) => client = ip,
+            None => break,
+        }
+    }
+    Some(client)
+}
+
+/// IP of the connecting peer
+///
+/// Peers on a Unix socket have no address; when the server listens on one
+/// (`bind` other than TCP) they count as 127.0.0.1, the local proxy in
+/// front of it.
+fn peer_ip(peer: Option<SocketAddr>, bind: Option<&BindTarget>) -> Option<IpAddr> {
+    match peer {
+        Some(addr) => Some(addr.ip()),
+        None if bind.is_some_and(|bind| *bind != BindTarget::Tcp) => {
+            Some(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        }
+        None => None,
+    }
+}
+
+/// Whether an IP may use the API under the configured lists
+///
+/// The deny list wins over the allow list. An empty allow list allows every
+/// address; a non-empty one refuses requests whose IP is unknown.
+pub fn is_allowed(ip: Option<IpAddr>, config: &IpFilterConfig) -> bool {
+    let Some(ip) = ip else {
+        return config.allow.is_empty();
+    };
+    if config.deny.iter().any(|net| net.contains(&ip)) {
+        return false;
+    }
+    config.allow.is_empty() || config.allow.iter().any(|net| net.contains(&ip))
+}
+
+/// Every X-Forwarded-For header, joined in the order received
+fn forwarded_for(headers: &HeaderMap) -> Option<String> {
+    let hops = headers
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>();
+    (!hops.is_empty()).then(|| hops.join(","))
+}
+
+/// Client IP of a request
+///
+/// Uses the IP resolved by [`filter_ips`], falling back to resolving it
+/// from the request when the middleware didn't run.
+pub fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    if let Some(ClientIp(ip)) = req.extensions().get::<ClientIp>() {
+        return Some(*ip);
+    }
+
+    let config = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.config.load_full());
+    let config = config.as_deref();
+    resolve_client_ip(
+        peer_ip(req.peer_addr(), config.map(|config| &config.bind)),
+        forwarded_for(req.headers()).as_deref(),
+        config
+            .map(|config| config.ip_filter.trusted_proxies.as_slice())
+            .unwrap_or_default(),
+    )
+}
+
+/// Middleware resolving the client IP and enforcing the allow/deny lists
+///
+/// Refused requests get 403 before reaching any handler.
+pub async fn filter_ips(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let app_config = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.config.load_full());
+    let config = app_config
+        .as_ref()
+        .map(|config| config.ip_filter.clone())
+        .unwrap_or_default();
+
+    let ip = resolve_client_ip(
+        peer_ip(
+            req.peer_addr(),
+            app_config.as_ref().map(|config| &config.bind),
+        ),
+        forwarded_for(req.headers()).as_deref(),
+        &config.trusted_proxies,
+    );
+
+    if !is_allowed(ip, &config) {
+        warn!(
+            "Refused {} {} from {}",
+            req.method(),
+            req.path(),
+            ip.map(|ip| ip.to_string())
+                .unwrap_or_else(|| "unknown address".to_string())
+        );
+        let response = HttpResponse::Forbidden().json(ApiError::new(
+            ErrorCode::Forbidden,
+            "Access from this address is not allowed",
+        ));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    if let Some(ip) = ip {
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_networks;
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        value.parse().ok()
+    }
+
+    #[test]
+    fn test_parse_ip() {
+        assert_eq!(parse_ip("203.0.113.7"), ip("203.0.113.7"));
+        assert_eq!(parse_ip(" 203.0.113.7:51234"), ip("203.0.113.7"));
+        assert_eq!(parse_ip("[2001:db8::1]:443"), ip("2001:db8::1"));
+        assert_eq!(parse_ip("::ffff:203.0.113.7"), ip("203.0.113.7"));
+        assert_eq!(parse_ip("unknown"), None);
+    }
+
+    #[test]
+    fn test_resolve_client_ip() {
+        let trusted = parse_networks("10.0.0.0/8,127.0.0.1").unwrap();
+        let peer = ip("10.0.0.2");
+
+        // Forwarding headers from untrusted peers are ignored
+        assert_eq!(
+            resolve_client_ip(ip("198.51.100.9"), Some("203.0.113.7"), &trusted),
+            ip("198.51.100.9")
+        );
+        assert_eq!(
+            resolve_client_ip(peer, Some("203.0.113.7"), &[]),
+            ip("10.0.0.2")
+        );
+
+        // Trusted hops are skipped; a spoofed leftmost entry is not believed
+        assert_eq!(
+            resolve_client_ip(peer, Some("1.2.3.4, 203.0.113.7, 10.0.0.1"), &trusted),
+            ip("203.0.113.7")
+        );
+
+        // All hops trusted: the oldest one
+        assert_eq!(
+            resolve_client_ip(peer, Some("10.1.1.1, 127.0.0.1"), &trusted),
+            ip("10.1.1.1")
+        );
+
+        // A malformed hop stops the walk
+        assert_eq!(
+            resolve_client_ip(peer, Some("203.0.113.7, garbage"), &trusted),
+            ip("10.0.0.2")
+        );
+        assert_eq!(resolve_client_ip(None, Some("203.0.113.7"), &trusted), None);
+    }
+
+    #[test]
+    fn test_peer_ip_on_unix_socket() {
+        let peer = "10.0.0.2:5000".parse().ok();
+        let unix = BindTarget::Unix("/run/anime.sock".to_string());
+        assert_eq!(peer_ip(peer, Some(&unix)), ip("10.0.0.2"));
+
+        // No peer address: localhost on a Unix socket, unknown otherwise
+        assert_eq!(peer_ip(None, Some(&unix)), ip("127.0.0.1"));
+        assert_eq!(peer_ip(None, Some(&BindTarget::Systemd)), ip("127.0.0.1"));
+        assert_eq!(peer_ip(None, Some(&BindTarget::Tcp)), None);
+        assert_eq!(peer_ip(None, None), None);
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let open = IpFilterConfig::default();
+        assert!(is_allowed(ip("203.0.113.7"), &open));
+        assert!(is_allowed(None, &open));
+
+        let config = IpFilterConfig {
+            allow: parse_networks("203.0.113.0/24, 2001:db8::/32").unwrap(),
+            deny: parse_networks("203.0.113.66").unwrap(),
+            ..Default::default()
+        };
+        assert!(is_allowed(ip("203.0.113.7"), &config));
+        assert!(is_allowed(ip("2001:db8::1"), &config));
+        assert!(!is_allowed(ip("203.0.113.66"), &config));
+        assert!(!is_allowed(ip("198.51.100.1"), &config));
+        assert!(!is_allowed(None, &config));
+
+        assert_eq!(parse_networks("10.0.0.0/8, nope"), Err("nope".to_string()));
+    }
+}
@@ -0,0 +1,265 @@
+//! Request size and slug limits
+//!
+//! Absurd inputs are refused before they reach the scraper or the database:
+//! - Query strings longer than the limit get 414
+//! - Bodies larger than the limit get 413, whether declared in
+//!   Content-Length or discovered while reading JSON (see [`json_config`]);
+//!   multipart uploads get the larger upload limit instead (see
+//!   [`multipart_config`])
+//! - Slugs in paths must be short and made of ASCII letters, digits, `-`,
+//!   and `_` (see [`Slug`])
+
+use std::future::{ready, Ready};
+
+use actix_multipart::form::MultipartFormConfig;
+use actix_multipart::MultipartError;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::{InternalError, JsonPayloadError, PayloadError};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, FromRequest, HttpRequest, HttpResponse};
+use tracing::warn;
+
+use crate::config::RequestLimitsConfig;
+use crate::models::{ApiError, ErrorCode};
+use crate::routes::AppState;
+
+/// Longest accepted anime or episode slug
+pub const MAX_SLUG_LEN: usize = 200;
+
+/// Whether `slug` is a plausible anime or episode slug
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= MAX_SLUG_LEN
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// 413 response for a body over `max_body_bytes`
+fn payload_too_large(max_body_bytes: usize) -> HttpResponse {
+    HttpResponse::PayloadTooLarge().json(
+        ApiError::new(ErrorCode::PayloadTooLarge, "Request body is too large")
+            .with_details(serde_json::json!({ "maxBytes": max_body_bytes })),
+    )
+}
+
+/// 414 response for a query string over `max_query_len`
+fn uri_too_long(max_query_len: usize) -> HttpResponse {
+    HttpResponse::UriTooLong().json(
+        ApiError::new(ErrorCode::UriTooLong, "Query string is too long")
+            .with_details(serde_json::json!({ "maxLength": max_query_len })),
+    )
+}
+
+/// Declared Content-Length of a request, if any
+fn content_length(req: &ServiceRequest) -> Option<usize> {
+    req.headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Whether a request carries a multipart/form-data body
+fn is_multipart(req: &ServiceRequest) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .to_ascii_lowercase()
+                .starts_with("multipart/form-data")
+        })
+}
+
+/// Middleware refusing over-long query strings and over-large declared bodies
+pub async fn enforce_request_limits(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let limits = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.config.load().request_limits.clone())
+        .unwrap_or_default();
+
+    let max_body_bytes = if is_multipart(&req) {
+        limits.max_upload_bytes
+    } else {
+        limits.max_body_bytes
+    };
+
+    let rejection = if req.query_string().len() > limits.max_query_len {
+        Some(uri_too_long(limits.max_query_len))
+    } else if content_length(&req).is_some_and(|len| len > max_body_bytes) {
+        Some(payload_too_large(max_body_bytes))
+    } else {
+        None
+    };
+
+    if let Some(response) = rejection {
+        warn!(
+            "Refused {} {}: {}",
+            req.method(),
+            req.path(),
+            response.status()
+        );
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/// JSON extractor config enforcing the body limit with structured errors
+///
+/// Bodies that overflow while streaming get 413, non-JSON bodies 415, and
+/// malformed JSON 400.
+pub fn json_config(limits: &RequestLimitsConfig) -> web::JsonConfig {
+    let max_body_bytes = limits.max_body_bytes;
+    web::JsonConfig::default()
+        .limit(max_body_bytes)
+        .error_handler(move |err, _req| {
+            let response = match &err {
+                JsonPayloadError::Overflow { .. }
+                | JsonPayloadError::OverflowKnownLength { .. } => payload_too_large(max_body_bytes),
+                JsonPayloadError::ContentType => HttpResponse::UnsupportedMediaType().json(
+                    ApiError::new(ErrorCode::ValidationFailed, "Expected a JSON body"),
+                ),
+                _ => HttpResponse::BadRequest().json(ApiError::new(
+                    ErrorCode::ValidationFailed,
+                    format!("Invalid JSON body: {}", err),
+                )),
+            };
+            InternalError::from_response(err, response).into()
+        })
+}
+
+/// Multipart form extractor config enforcing the upload limit with structured errors
+///
+/// Uploads that overflow while streaming get 413, non-multipart bodies 415,
+/// and malformed or incomplete forms 400.
+pub fn multipart_config(limits: &RequestLimitsConfig) -> MultipartFormConfig {
+    let max_upload_bytes = limits.max_upload_bytes;
+    MultipartFormConfig::default()
+        .total_limit(max_upload_bytes)
+        .memory_limit(max_upload_bytes)
+        .error_handler(move |err, _req| {
+            let response = match &err {
+                MultipartError::Payload(PayloadError::Overflow) => {
+                    payload_too_large(max_upload_bytes)
+                }
+                MultipartError::ContentTypeMissing
+                | MultipartError::ContentTypeParse
+                | MultipartError::ContentTypeIncompatible => HttpResponse::UnsupportedMediaType()
+                    .json(ApiError::new(
+                        ErrorCode::ValidationFailed,
+                        "Expected a multipart/form-data body",
+                    )),
+                _ => HttpResponse::BadRequest().json(ApiError::new(
+                    ErrorCode::ValidationFailed,
+                    format!("Invalid form: {}", err),
+                )),
+            };
+            InternalError::from_response(err, response).into()
+        })
+}
+
+/// Validated `{slug}` path parameter
+///
+/// Rejects the request with 400 if the slug is missing, longer than
+/// [`MAX_SLUG_LEN`], or contains characters slugs never have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slug(String);
+
+impl Slug {
+    /// The slug
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl std::ops::Deref for Slug {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromRequest for Slug {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let slug = req.match_info().get("slug").unwrap_or_default();
+        if is_valid_slug(slug) {
+            return ready(Ok(Slug(slug.to_string())));
+        }
+
+        let response = HttpResponse::BadRequest().json(
+            ApiError::new(ErrorCode::ValidationFailed, "Invalid slug").with_details(
+                serde_json::json!({
+                    "maxLength": MAX_SLUG_LEN,
+                    "allowedCharacters": "ASCII letters, digits, '-' and '_'",
+                }),
+            ),
+        );
+        ready(Err(
+            InternalError::from_response("Invalid slug", response).into()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_is_valid_slug() {
+        assert!(is_valid_slug(
+            "sousou-no-frieren-episode-28-subtitle-indonesia"
+        ));
+        assert!(is_valid_slug("86-eighty-six_s2"));
+        assert!(!is_valid_slug(""));
+        assert!(!is_valid_slug("../etc/passwd"));
+        assert!(!is_valid_slug("one piece"));
+        assert!(!is_valid_slug("frieren?page=2"));
+        assert!(!is_valid_slug(&"a".repeat(MAX_SLUG_LEN + 1)));
+        assert!(is_valid_slug(&"a".repeat(MAX_SLUG_LEN)));
+    }
+
+    #[actix_rt::test]
+    async fn test_slug_extractor() {
+        let req = TestRequest::default()
+            .param("slug", "one-piece-subtitle-indonesia")
+            .to_http_request();
+        let slug = Slug::extract(&req).await.unwrap();
+        assert_eq!(slug.into_inner(), "one-piece-subtitle-indonesia");
+
+        let req = TestRequest::default()
+            .param("slug", "one%20piece")
+            .to_http_request();
+        let err = Slug::extract(&req).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_is_multipart() {
+        let req = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=x"))
+            .to_srv_request();
+        assert!(is_multipart(&req));
+
+        let req = TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .to_srv_request();
+        assert!(!is_multipart(&req));
+        assert!(!is_multipart(&TestRequest::default().to_srv_request()));
+    }
+}
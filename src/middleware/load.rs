@@ -0,0 +1,24 @@
+//! API load tracking
+//!
+//! Counts every request in the shared
+//! [`ApiLoad`](crate::crawler::backpressure::ApiLoad) while it runs, so crawls
+//! can back off while users are waiting on the API (see
+//! [`crate::crawler::backpressure`]).
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+
+use crate::routes::AppState;
+
+/// Middleware counting requests in flight and their latency
+pub async fn track_api_load(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let _request = req
+        .app_data::<web::Data<AppState>>()
+        .map(|state| state.api_load.start_request());
+    next.call(req).await
+}
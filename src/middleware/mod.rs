@@ -0,0 +1,34 @@
+//! HTTP middleware for the Anime Scraper API
+//!
+//! - [`cache_control`] - Cache-Control headers per endpoint class
+//! - [`encoding`] - MessagePack / CBOR responses negotiated from Accept
+//! - [`errors`] - Internal error messages hidden from 5xx responses outside development
+//! - [`features`] - 503 for endpoints whose feature an operator switched off
+//! - [`ip_filter`] - Client IP resolution behind proxies and allow/deny lists
+//! - [`limits`] - Request body, query string, and slug limits
+//! - [`load`] - API requests in flight and their latency, for crawl backpressure
+//! - [`tenant`] - Tenant resolution from the tenant header or hostname
+//! - [`trace`] - Request spans continuing the caller's W3C trace context
+//! - [`versioning`] - `/api/v1` and `/api/v2` routing and version negotiation
+
+pub mod cache_control;
+pub mod encoding;
+pub mod errors;
+pub mod features;
+pub mod ip_filter;
+pub mod limits;
+pub mod load;
+pub mod tenant;
+pub mod trace;
+pub mod versioning;
+
+pub use cache_control::cache_control;
+pub use encoding::negotiate_encoding;
+pub use errors::sanitize_errors;
+pub use features::gate_features;
+pub use ip_filter::{client_ip, filter_ips};
+pub use limits::{enforce_request_limits, Slug};
+pub use load::track_api_load;
+pub use tenant::resolve_tenant;
+pub use trace::trace_requests;
+pub use versioning::{negotiate_api_version, ApiVersion};
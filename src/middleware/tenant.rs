@@ -0,0 +1,33 @@
+//! Tenant resolution
+//!
+//! Resolves the tenant of every request and stores it in the request
+//! extensions, where the `CurrentTenant` and `Auth` extractors pick it up.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage};
+
+use crate::routes::AppState;
+use crate::tenants::CurrentTenant;
+
+/// Middleware resolving the request's tenant from the tenant header or host
+pub async fn resolve_tenant(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let tenant = match req.app_data::<web::Data<AppState>>() {
+        Some(state) => {
+            let header = req
+                .headers()
+                .get(state.config.load().tenant_header.as_str())
+                .and_then(|value| value.to_str().ok());
+            let host = req.connection_info().host().to_string();
+            state.tenants.resolve(header, Some(&host))
+        }
+        None => CurrentTenant::default(),
+    };
+
+    req.extensions_mut().insert(tenant);
+    next.call(req).await
+}
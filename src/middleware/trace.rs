@@ -0,0 +1,210 @@
+//! Request tracing
+//!
+//! Every request runs inside an `http_request` span, exported through
+//! OpenTelemetry when an OTLP endpoint is configured (see
+//! [`crate::logging`]). The caller's W3C `traceparent` header is continued
+//! when present, otherwise a new trace is started. Work done on behalf of
+//! the request, such as a crawl's scraper fetches and database writes, nests
+//! under that span; background jobs carry a `traceparent` in their payload
+//! (see [`current_traceparent`]) and outgoing webhook and translation
+//! requests send one (see [`with_traceparent`]), so their spans join the
+//! same trace.
+//!
+//! The trace ID is returned in the `X-Trace-Id` response header.
+
+use std::collections::HashMap;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use tracing::{field, info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// W3C trace context header
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Response header carrying the trace ID
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// Request headers as a source of trace context
+struct RequestHeaders<'a>(&'a HeaderMap);
+
+impl Extractor for RequestHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+/// Make `span` continue the trace found in `carrier`
+///
+/// A missing or malformed trace context leaves `span` starting a new trace.
+/// Either way the trace ID is recorded in the span's `trace_id` field, if
+/// it has one.
+fn join_trace(span: &Span, carrier: &dyn Extractor) {
+    let parent = TraceContextPropagator::new().extract(carrier);
+    if parent.span().span_context().is_valid() {
+        span.set_parent(parent);
+    }
+    if let Some(trace_id) = trace_id(span) {
+        span.record("trace_id", trace_id.as_str());
+    }
+}
+
+/// Make `span` continue the trace named by a `traceparent` value
+///
+/// A missing or malformed value leaves `span` starting a new trace. Job
+/// spans are started this way from the `traceparent` in their payload.
+pub fn continue_trace(span: &Span, traceparent: Option<&str>) {
+    let carrier: HashMap<String, String> = traceparent
+        .map(|value| HashMap::from([(TRACEPARENT.to_string(), value.to_string())]))
+        .unwrap_or_default();
+    join_trace(span, &carrier);
+}
+
+/// Trace ID of `span`, as 32 lowercase hex digits
+///
+/// # Returns
+/// `None` when the span isn't traced, e.g. because the log filter disabled it
+pub fn trace_id(span: &Span) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// `traceparent` value naming the current span as the parent
+///
+/// # Returns
+/// `None` outside of a traced span
+pub fn current_traceparent() -> Option<String> {
+    let context = Span::current().context();
+    if !context.span().span_context().is_valid() {
+        return None;
+    }
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&context, &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// Send the current span's `traceparent` with an outgoing request
+pub fn with_traceparent(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    match current_traceparent() {
+        Some(traceparent) => request.header(TRACEPARENT, traceparent),
+        None => request,
+    }
+}
+
+/// Middleware running each request inside a span of its trace
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let span = info_span!(
+        "http_request",
+        method = %req.method(),
+        path = %req.path(),
+        trace_id = field::Empty,
+        status = field::Empty,
+    );
+    join_trace(&span, &RequestHeaders(req.headers()));
+    let trace_id = trace_id(&span).and_then(|id| HeaderValue::from_str(&id).ok());
+
+    let mut res = next.call(req).instrument(span.clone()).await?;
+    span.record("status", res.status().as_u16());
+    if let Some(trace_id) = trace_id {
+        res.headers_mut()
+            .insert(HeaderName::from_static(TRACE_ID_HEADER), trace_id);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing::subscriber::DefaultGuard;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    const TRACE: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    /// Trace spans on this thread until the guard is dropped
+    fn traced() -> DefaultGuard {
+        let tracer = TracerProvider::builder().build().tracer("test");
+        tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .set_default()
+    }
+
+    #[test]
+    fn test_continue_trace() {
+        let _guard = traced();
+
+        let span = info_span!("job", trace_id = field::Empty);
+        continue_trace(&span, Some(PARENT));
+        assert_eq!(trace_id(&span).as_deref(), Some(TRACE));
+        let traceparent = span.in_scope(current_traceparent).unwrap();
+        assert!(traceparent.starts_with(&format!("00-{}-", TRACE)));
+        assert!(!traceparent.contains("00f067aa0ba902b7"));
+
+        for invalid in [
+            None,
+            Some(""),
+            Some("00-00000000000000000000000000000000-00f067aa0ba902b7-01"),
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01"),
+        ] {
+            let span = info_span!("job");
+            continue_trace(&span, invalid);
+            let id = trace_id(&span).unwrap();
+            assert_eq!(id.len(), 32);
+            assert_ne!(id, TRACE, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_untraced_span() {
+        assert_eq!(trace_id(&Span::none()), None);
+        assert_eq!(current_traceparent(), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_trace_requests_continues_traceparent() {
+        let _guard = traced();
+        let app = init_service(App::new().wrap(from_fn(trace_requests)).route(
+            "/",
+            web::get().to(|| async {
+                HttpResponse::Ok().body(current_traceparent().unwrap_or_default())
+            }),
+        ))
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header((TRACEPARENT, PARENT))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.headers().get(TRACE_ID_HEADER).unwrap(), TRACE);
+        let body = read_body(res).await;
+        assert!(body.starts_with(format!("00-{}-", TRACE).as_bytes()));
+
+        let res = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        let trace_id = res.headers().get(TRACE_ID_HEADER).unwrap();
+        assert_ne!(trace_id, TRACE);
+        assert_eq!(trace_id.len(), 32);
+    }
+}
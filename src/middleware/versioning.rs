@@ -0,0 +1,276 @@
+//! API versioning
+//!
+//! Every `/api` endpoint is served under `/api/v1` and `/api/v2` as well as
+//! the unversioned `/api` path. The version prefix is stripped before
+//! routing, so both versions share handlers; endpoints whose v2 behavior
+//! differs (typed enums, pagination envelopes, error codes) take the
+//! [`ApiVersion`] extractor and branch on it.
+//!
+//! The version is negotiated as follows:
+//! - A `/api/v{n}` path prefix wins
+//! - Otherwise an `Api-Version` request header picks it
+//! - Otherwise the request is v1, the behavior unversioned paths have always had
+//!
+//! Responses carry the version they were served with in `Api-Version`.
+
+use std::future::{ready, Ready};
+use std::str::FromStr;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::http::uri::{PathAndQuery, Uri};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+
+use crate::models::{ApiError, ErrorCode};
+
+/// Request and response header carrying the API version
+pub const API_VERSION_HEADER: &str = "api-version";
+
+/// Version of the API a request is served with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// Original API
+    V1,
+    /// Current API
+    V2,
+}
+
+impl ApiVersion {
+    /// Every supported version
+    pub const ALL: [ApiVersion; 2] = [Self::V1, Self::V2];
+
+    /// Newest version
+    pub const LATEST: ApiVersion = Self::V2;
+
+    /// Version number as used in paths and headers
+    pub fn number(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// Look up a version by number, accepting a leading `v`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let number = value
+            .strip_prefix(['v', 'V'])
+            .unwrap_or(value)
+            .parse::<u8>()
+            .ok()?;
+        Self::ALL.into_iter().find(|v| v.number() == number)
+    }
+}
+
+/// Split a `/api/v{n}` prefix off a path
+///
+/// # Returns
+/// The version and the unversioned path, or `None` if the path has no
+/// supported version prefix
+pub fn split_version(path: &str) -> Option<(ApiVersion, String)> {
+    let rest = path.strip_prefix("/api/")?;
+    let (segment, tail) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    if !segment.starts_with('v') {
+        return None;
+    }
+    let version = ApiVersion::parse(segment)?;
+    Some((version, format!("/api{}", tail)))
+}
+
+impl FromRequest for ApiVersion {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<ApiVersion>()
+            .copied()
+            .unwrap_or(ApiVersion::V1)))
+    }
+}
+
+/// Replace the path of a request, keeping its query string
+fn rewrite_path(req: &mut ServiceRequest, path: &str) {
+    let uri = req.uri().clone();
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let mut parts = uri.into_parts();
+    parts.path_and_query = PathAndQuery::from_str(&path_and_query).ok();
+    if let Ok(uri) = Uri::from_parts(parts) {
+        req.match_info_mut().get_mut().update(&uri);
+        req.head_mut().uri = uri;
+    }
+}
+
+/// Middleware resolving the API version and routing versioned paths
+pub async fn negotiate_api_version(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    // Paths are matched below the scope the API is mounted under, if any
+    let path = req.match_info().unprocessed().to_string();
+    let mount = req.path()[..req.path().len() - path.len()].to_string();
+    if !path.starts_with("/api/") {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    let versioned = split_version(&path);
+    let in_path = versioned.is_some();
+    let version = match versioned {
+        Some((version, path)) => {
+            rewrite_path(&mut req, &format!("{}{}", mount, path));
+            version
+        }
+        None => match req.headers().get(API_VERSION_HEADER) {
+            None => ApiVersion::V1,
+            Some(value) => match value.to_str().ok().and_then(ApiVersion::parse) {
+                Some(version) => version,
+                None => {
+                    let supported: Vec<u8> = ApiVersion::ALL.iter().map(|v| v.number()).collect();
+                    let response = HttpResponse::BadRequest().json(
+                        ApiError::new(ErrorCode::ValidationFailed, "Unsupported API version")
+                            .with_details(serde_json::json!({ "supportedVersions": supported })),
+                    );
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+            },
+        },
+    };
+    req.extensions_mut().insert(version);
+
+    let mut res = next.call(req).await?;
+    let headers = res.headers_mut();
+    headers.insert(
+        HeaderName::from_static(API_VERSION_HEADER),
+        HeaderValue::from(u16::from(version.number())),
+    );
+    if !in_path {
+        headers.append(header::VARY, HeaderValue::from_static("Api-Version"));
+    }
+    Ok(res.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{web, App};
+
+    #[test]
+    fn test_split_version() {
+        assert_eq!(
+            split_version("/api/v1/anime/one-piece"),
+            Some((ApiVersion::V1, "/api/anime/one-piece".to_string()))
+        );
+        assert_eq!(
+            split_version("/api/v2/catalog"),
+            Some((ApiVersion::V2, "/api/catalog".to_string()))
+        );
+        assert_eq!(
+            split_version("/api/v2"),
+            Some((ApiVersion::V2, "/api".to_string()))
+        );
+        assert_eq!(split_version("/api/anime/v1"), None);
+        assert_eq!(split_version("/api/v3/catalog"), None);
+        assert_eq!(split_version("/api/videos"), None);
+        assert_eq!(split_version("/health"), None);
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(ApiVersion::parse("1"), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::parse(" v2 "), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::parse("3"), None);
+        assert_eq!(ApiVersion::parse("latest"), None);
+    }
+
+    #[actix_rt::test]
+    async fn test_negotiate_api_version() {
+        let app = init_service(App::new().wrap(from_fn(negotiate_api_version)).route(
+            "/api/version",
+            web::get().to(|version: ApiVersion, req: HttpRequest| async move {
+                format!("{} {}", version.number(), req.query_string())
+            }),
+        ))
+        .await;
+
+        let res = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/api/v2/version?page=2")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(res.headers().get(API_VERSION_HEADER).unwrap(), "2");
+        assert_eq!(read_body(res).await, "2 page=2");
+
+        // v1 isn't marked deprecated while v2 serves the same responses
+        let res = call_service(&app, TestRequest::get().uri("/api/v1/version").to_request()).await;
+        assert_eq!(res.headers().get(API_VERSION_HEADER).unwrap(), "1");
+        assert!(res.headers().get("deprecation").is_none());
+        assert!(res.headers().get(header::LINK).is_none());
+        assert_eq!(read_body(res).await, "1 ");
+
+        let res = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/api/version")
+                .insert_header((API_VERSION_HEADER, "2"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(read_body(res).await, "2 ");
+
+        let res = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/api/version")
+                .insert_header((API_VERSION_HEADER, "9"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_negotiate_api_version_under_scope() {
+        let app = init_service(
+            App::new().service(
+                web::scope("/anime")
+                    .wrap(from_fn(negotiate_api_version))
+                    .route(
+                        "/api/version",
+                        web::get()
+                            .to(|version: ApiVersion| async move { version.number().to_string() }),
+                    ),
+            ),
+        )
+        .await;
+
+        let res = call_service(
+            &app,
+            TestRequest::get().uri("/anime/api/v2/version").to_request(),
+        )
+        .await;
+        assert_eq!(read_body(res).await, "2");
+
+        let res = call_service(
+            &app,
+            TestRequest::get().uri("/anime/api/v1/version").to_request(),
+        )
+        .await;
+        assert_eq!(read_body(res).await, "1");
+    }
+}
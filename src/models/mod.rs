@@ -3,14 +3,16 @@
 //! This module contains all data structures used throughout the application,
 //! including user-related models, API responses, and crawler data.
 
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 // Re-export parser models for convenience
 pub use crate::parser::{
-    AnimeDetail, AnimeListItem, AnimeUpdate, CompletedAnime, Episode, EpisodeDetail, SearchResult,
-    VideoSource,
+    AgeRating, AnimeDetail, AnimeListItem, AnimeUpdate, CompletedAnime, EmbedInfo, Episode,
+    EpisodeDetail, SearchResult, SubtitleTrack, Thumbnails, VideoSource,
 };
 
 /// Represents a user's favorite anime
@@ -25,6 +27,12 @@ pub struct UserFavorite {
     pub thumbnail: String,
     /// ISO timestamp when added to favorites
     pub created_at: String,
+    /// Number of episodes marked watched
+    #[serde(default)]
+    pub watched_count: i64,
+    /// Number of episodes released so far
+    #[serde(default)]
+    pub total_episodes: i64,
 }
 
 /// Represents a user's subscription to an anime series
@@ -39,6 +47,12 @@ pub struct UserSubscription {
     pub thumbnail: String,
     /// ISO timestamp when subscribed
     pub created_at: String,
+    /// Number of episodes marked watched
+    #[serde(default)]
+    pub watched_count: i64,
+    /// Number of episodes released so far
+    #[serde(default)]
+    pub total_episodes: i64,
 }
 
 /// Represents a user's watch history entry
@@ -59,6 +73,42 @@ pub struct UserHistory {
     pub watched_at: String,
 }
 
+/// A user's watched episodes of one anime
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchProgress {
+    /// Anime slug identifier
+    pub anime_slug: String,
+    /// Number of episodes marked watched
+    pub watched_count: i64,
+    /// Number of episodes released so far
+    pub total_episodes: i64,
+    /// Slugs of the watched episodes, in episode order
+    pub watched_episodes: Vec<String>,
+}
+
+/// An anime the user has been watching with the next episode to watch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueWatching {
+    /// Anime slug identifier
+    pub anime_slug: String,
+    /// Anime title for display
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Slug of the most recently watched episode
+    pub last_episode_slug: String,
+    /// ISO timestamp of the last watch
+    pub last_watched_at: String,
+    /// First episode after the last watched one that hasn't been watched
+    pub next_episode: Episode,
+    /// ISO timestamp when the next episode was first seen
+    pub next_released_at: String,
+    /// Whether the next episode was released after the last watch
+    pub new_episode: bool,
+}
+
 /// Represents a user account
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -85,6 +135,13 @@ pub struct RegisterRequest {
     pub password: String,
     /// Optional display name
     pub name: Option<String>,
+    /// Preferred email language ("en" or "id", default: "en")
+    #[serde(default)]
+    pub language: Option<String>,
+    /// CAPTCHA token from the hCaptcha or Turnstile widget, required when
+    /// the server has CAPTCHA verification enabled
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
 /// Request body for user login
@@ -127,6 +184,70 @@ pub struct AuthData {
     pub token: String,
 }
 
+/// Where the data of a response came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSource {
+    /// Stored data that is still within its cache TTL
+    Cache,
+    /// Scraped from the source site for this request
+    Live,
+    /// Stored data served because the source site could not be reached in
+    /// time; may be out of date
+    Stale,
+}
+
+/// How a response was produced
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseMeta {
+    /// Where the data came from
+    pub source: DataSource,
+    /// Time spent fetching from the source site, including a fetch that
+    /// timed out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetch_duration_ms: Option<u64>,
+    /// HTTP status the source site answered with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_status: Option<u16>,
+    /// The source site did not answer within the endpoint's budget, so the
+    /// data is the stored copy and may be out of date
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+impl ResponseMeta {
+    /// Served from the cache
+    pub fn cached() -> Self {
+        Self {
+            source: DataSource::Cache,
+            fetch_duration_ms: None,
+            upstream_status: None,
+            timed_out: false,
+        }
+    }
+
+    /// Scraped live, taking `fetch_duration` and answered with `upstream_status`
+    pub fn live(fetch_duration: Duration, upstream_status: u16) -> Self {
+        Self {
+            source: DataSource::Live,
+            fetch_duration_ms: Some(fetch_duration.as_millis() as u64),
+            upstream_status: Some(upstream_status),
+            timed_out: false,
+        }
+    }
+
+    /// Stored data served after the scrape timed out after `fetch_duration`
+    pub fn timed_out(fetch_duration: Duration) -> Self {
+        Self {
+            source: DataSource::Stale,
+            fetch_duration_ms: Some(fetch_duration.as_millis() as u64),
+            upstream_status: None,
+            timed_out: true,
+        }
+    }
+}
+
 /// Generic API response wrapper for successful responses
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -137,16 +258,15 @@ pub struct ApiResponse<T> {
     pub data: T,
     /// ISO timestamp of when data was fetched
     pub timestamp: String,
+    /// Where the data came from, on endpoints backed by the source site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ResponseMeta>,
 }
 
 impl<T> ApiResponse<T> {
     /// Create a new successful API response with the current timestamp
     pub fn new(data: T) -> Self {
-        Self {
-            success: true,
-            data,
-            timestamp: Utc::now().to_rfc3339(),
-        }
+        Self::with_timestamp(data, Utc::now())
     }
 
     /// Create a new successful API response with a custom timestamp
@@ -155,8 +275,92 @@ impl<T> ApiResponse<T> {
             success: true,
             data,
             timestamp: timestamp.to_rfc3339(),
+            meta: None,
+        }
+    }
+
+    /// Attach metadata on how the data was produced
+    pub fn with_meta(self, meta: ResponseMeta) -> Self {
+        Self {
+            meta: Some(meta),
+            ..self
         }
     }
+
+    /// Data served from the cache
+    pub fn cached(data: T) -> Self {
+        Self::new(data).with_meta(ResponseMeta::cached())
+    }
+
+    /// Data scraped live for this request
+    pub fn live(data: T, fetch_duration: Duration, upstream_status: u16) -> Self {
+        Self::new(data).with_meta(ResponseMeta::live(fetch_duration, upstream_status))
+    }
+
+    /// Stored data served because the source site timed out
+    pub fn timed_out(data: T, fetch_duration: Duration) -> Self {
+        Self::new(data).with_meta(ResponseMeta::timed_out(fetch_duration))
+    }
+}
+
+/// Machine-readable error code of an [`ApiError`]
+///
+/// Clients should branch on the code rather than the message, which is
+/// meant for people and may change.
+///
+/// | Code | Status | Meaning |
+/// |------|--------|---------|
+/// | `VALIDATION_FAILED` | 400, 415 | A parameter or body field is missing or invalid |
+/// | `UNAUTHORIZED` | 401 | Missing, invalid, or expired credentials |
+/// | `FORBIDDEN` | 403 | Authenticated but not allowed |
+/// | `NOT_FOUND` | 404 | The requested resource does not exist |
+/// | `ANIME_NOT_FOUND` | 404 | No anime with this slug, here or upstream |
+/// | `EPISODE_NOT_FOUND` | 404 | No episode with this slug, here or upstream |
+/// | `CONFLICT` | 409 | The resource already exists |
+/// | `PAYLOAD_TOO_LARGE` | 413 | The request body exceeds the size limit |
+/// | `URI_TOO_LONG` | 414 | The query string exceeds the length limit |
+/// | `TOO_MANY_REQUESTS` | 429 | The client sent too many requests; retry after `Retry-After` |
+/// | `FEATURE_DISABLED` | 503 | An operator switched the endpoint off for now |
+/// | `RATE_LIMITED` | 500 | The source site is rate limiting us; retry later |
+/// | `UPSTREAM_UNAVAILABLE` | 500, 502 | The source site could not be fetched |
+/// | `UPSTREAM_TIMEOUT` | 504 | The source site did not answer within the endpoint's budget |
+/// | `DATABASE_ERROR` | 500 | A database query failed |
+/// | `INTERNAL_ERROR` | 500 | Any other server-side failure |
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// A parameter or body field is missing or invalid
+    ValidationFailed,
+    /// Missing, invalid, or expired credentials
+    Unauthorized,
+    /// Authenticated but not allowed
+    Forbidden,
+    /// The requested resource does not exist
+    NotFound,
+    /// No anime with this slug
+    AnimeNotFound,
+    /// No episode with this slug
+    EpisodeNotFound,
+    /// The resource already exists
+    Conflict,
+    /// The request body exceeds the size limit
+    PayloadTooLarge,
+    /// The query string exceeds the length limit
+    UriTooLong,
+    /// The client sent too many requests
+    TooManyRequests,
+    /// The endpoint is switched off by an operator
+    FeatureDisabled,
+    /// The source site is rate limiting requests
+    RateLimited,
+    /// The source site could not be fetched
+    UpstreamUnavailable,
+    /// The source site did not answer in time
+    UpstreamTimeout,
+    /// A database query failed
+    DatabaseError,
+    /// Any other server-side failure
+    InternalError,
 }
 
 /// API error response
@@ -165,30 +369,43 @@ impl<T> ApiResponse<T> {
 pub struct ApiError {
     /// Whether the operation was successful (always false for errors)
     pub success: bool,
+    /// Machine-readable error code
+    pub code: ErrorCode,
     /// Error message describing what went wrong
     pub error: String,
+    /// Structured information about the error (e.g., which fields failed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
     /// ISO timestamp of when the error occurred
     pub timestamp: String,
 }
 
 impl ApiError {
     /// Create a new API error response with the current timestamp
-    pub fn new(error: impl Into<String>) -> Self {
-        Self {
-            success: false,
-            error: error.into(),
-            timestamp: Utc::now().to_rfc3339(),
-        }
+    pub fn new(code: ErrorCode, error: impl Into<String>) -> Self {
+        Self::with_timestamp(code, error, Utc::now())
     }
 
     /// Create a new API error response with a custom timestamp
-    pub fn with_timestamp(error: impl Into<String>, timestamp: DateTime<Utc>) -> Self {
+    pub fn with_timestamp(
+        code: ErrorCode,
+        error: impl Into<String>,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
         Self {
             success: false,
+            code,
             error: error.into(),
+            details: None,
             timestamp: timestamp.to_rfc3339(),
         }
     }
+
+    /// Attach structured details
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
 }
 
 /// Response wrapper for anime list endpoint
@@ -203,139 +420,2435 @@ pub struct AnimeListResponse {
     pub filters: AnimeListFilters,
 }
 
-/// Filters applied to anime list query
+/// Filters applied to anime list query
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeListFilters {
+    /// Type filter (TV, OVA, Movie, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Status filter (Ongoing, Completed, etc.)
+    pub status: String,
+    /// Sort order
+    pub order: String,
+    /// Tag filter (tag slug)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Whether anime rated mature or adult are included
+    #[serde(default)]
+    pub include_adult: bool,
+}
+
+/// Represents a crawled anime entry from bulk crawler
+/// Same fields as AnimeListItem for consistency
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawledAnime {
+    /// Extracted from URL (e.g., "one-piece-subtitle-indonesia")
+    pub slug: String,
+    /// Anime title from h2[itemprop="headline"]
+    pub title: String,
+    /// Full URL to anime page from a[itemprop="url"]
+    pub url: String,
+    /// Thumbnail image URL from img.ts-post-image
+    pub thumbnail: String,
+    /// From div.status (Completed, Ongoing, etc.)
+    pub status: String,
+    /// From div.typez (TV, ONA, Movie, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// From span.epx (episode count or status text)
+    pub episode_status: String,
+}
+
+/// Database representation of CrawledAnime with timestamps
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawledAnimeRecord {
+    /// Database ID
+    pub id: i32,
+    /// Extracted from URL (e.g., "one-piece-subtitle-indonesia")
+    pub slug: String,
+    /// Anime title
+    pub title: String,
+    /// Full URL to anime page
+    pub url: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// Completed, Ongoing, etc.
+    pub status: String,
+    /// TV, ONA, Movie, etc.
+    #[serde(rename = "type")]
+    pub anime_type: String,
+    /// Episode count or status text
+    pub episode_status: String,
+    /// Time-decayed popularity from views, favorites, and subscriptions
+    #[serde(default)]
+    pub popularity_score: f64,
+    /// ISO timestamp when created
+    pub created_at: String,
+    /// ISO timestamp when last updated
+    pub updated_at: String,
+}
+
+/// Sort order of the stored catalog
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogOrder {
+    /// A-Z
+    Title,
+    /// Z-A
+    TitleReverse,
+    /// Recently updated first
+    Update,
+    /// Recently added first
+    Latest,
+    /// Highest popularity score first
+    Popular,
+}
+
+impl CatalogOrder {
+    /// Every order
+    pub const ALL: [CatalogOrder; 5] = [
+        Self::Title,
+        Self::TitleReverse,
+        Self::Update,
+        Self::Latest,
+        Self::Popular,
+    ];
+
+    /// Name of the order as used in queries
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::TitleReverse => "titlereverse",
+            Self::Update => "update",
+            Self::Latest => "latest",
+            Self::Popular => "popular",
+        }
+    }
+
+    /// Look up an order by name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|order| order.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Page of the stored catalog
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogPage {
+    /// Anime on this page
+    pub items: Vec<CrawledAnimeRecord>,
+    /// Current page number
+    pub page: i64,
+    /// Anime per page
+    pub per_page: i64,
+    /// Anime in the catalog
+    pub total: i64,
+    /// Applied sort order
+    pub order: CatalogOrder,
+    /// Applied tag filter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Whether anime rated mature or adult are included
+    pub include_adult: bool,
+}
+
+/// Response for the bulk crawler endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlerResponse {
+    /// Whether the operation was successful
+    pub success: bool,
+    /// Crawler result data
+    pub data: CrawlerData,
+    /// ISO timestamp of the response
+    pub timestamp: String,
+}
+
+/// Data returned by the crawler endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlerData {
+    /// Total anime saved/updated
+    pub total_crawled: i32,
+    /// Total episodes saved/updated
+    pub total_episodes: i32,
+    /// Total video sources saved/updated
+    pub total_video_sources: i32,
+    /// Number of pages crawled
+    pub pages_processed: i32,
+    /// Errors encountered during crawling, one entry per kind and target
+    /// (see [`CrawlerData::record_error`])
+    pub errors: Vec<CrawlError>,
+    /// Anime details that changed since the last crawl
+    #[serde(default)]
+    pub anime_changes: ChangeCount,
+    /// Episodes that changed since the last crawl
+    #[serde(default)]
+    pub episode_changes: ChangeCount,
+    /// Episodes whose video sources changed since the last crawl
+    #[serde(default)]
+    pub video_source_changes: ChangeCount,
+    /// Total number of errors, including repeats and unlisted ones
+    #[serde(default)]
+    pub total_errors: i32,
+    /// Whether errors were left out of `errors` because it was full
+    #[serde(default)]
+    pub errors_truncated: bool,
+}
+
+/// Most distinct errors listed in `CrawlerData::errors`
+pub const MAX_CRAWL_ERRORS: usize = 100;
+
+/// Errors of one kind on one target during a crawl
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlError {
+    /// What failed and how, e.g. "fetch episode: timeout"
+    pub kind: String,
+    /// Anime or episode slug the error is about
+    pub slug: Option<String>,
+    /// List page the error is about
+    pub page: Option<u32>,
+    /// Message of the first occurrence
+    pub message: String,
+    /// Number of occurrences
+    pub count: i32,
+}
+
+impl CrawlError {
+    /// An error about an anime or episode
+    pub fn for_slug(kind: impl Into<String>, slug: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            slug: Some(slug.to_string()),
+            page: None,
+            message: message.into(),
+            count: 1,
+        }
+    }
+
+    /// An error about a list page
+    pub fn for_page(kind: impl Into<String>, page: u32, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            slug: None,
+            page: Some(page),
+            message: message.into(),
+            count: 1,
+        }
+    }
+
+    /// Whether `other` is the same kind of error on the same target
+    fn same_as(&self, other: &CrawlError) -> bool {
+        self.kind == other.kind && self.slug == other.slug && self.page == other.page
+    }
+}
+
+impl CrawlerData {
+    /// Record an error
+    ///
+    /// Repeats of a listed kind and target only raise its `count`. Once
+    /// [`MAX_CRAWL_ERRORS`] entries are listed, new ones are only counted in
+    /// `total_errors` and `errors_truncated` is set.
+    pub fn record_error(&mut self, error: CrawlError) {
+        self.total_errors += error.count;
+        if let Some(listed) = self.errors.iter_mut().find(|listed| listed.same_as(&error)) {
+            listed.count += error.count;
+        } else if self.errors.len() < MAX_CRAWL_ERRORS {
+            self.errors.push(error);
+        } else {
+            self.errors_truncated = true;
+        }
+    }
+}
+
+/// Number of records that were written vs. skipped as unchanged
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeCount {
+    /// Records inserted or updated
+    pub changed: i32,
+    /// Records skipped because their content hash matched
+    pub unchanged: i32,
+    /// Records among `changed` that didn't exist before
+    #[serde(default)]
+    pub inserted: i32,
+}
+
+impl ChangeCount {
+    /// Count one record
+    pub fn record(&mut self, changed: bool) {
+        if changed {
+            self.changed += 1;
+        } else {
+            self.unchanged += 1;
+        }
+    }
+
+    /// Count one record by how its write went
+    pub fn record_write(&mut self, outcome: WriteOutcome) {
+        self.record(outcome.changed());
+        if outcome == WriteOutcome::Inserted {
+            self.inserted += 1;
+        }
+    }
+
+    /// Add another count to this one
+    pub fn add(&mut self, other: ChangeCount) {
+        self.changed += other.changed;
+        self.unchanged += other.unchanged;
+        self.inserted += other.inserted;
+    }
+
+    /// Records that existed before and were changed
+    pub fn updated(&self) -> i32 {
+        self.changed - self.inserted
+    }
+}
+
+/// How an upsert affected a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// The record didn't exist and was inserted
+    Inserted,
+    /// The record existed and its content changed
+    Updated,
+    /// The record existed with the same content and was left alone
+    Unchanged,
+}
+
+impl WriteOutcome {
+    /// Whether anything was written
+    pub fn changed(&self) -> bool {
+        *self != WriteOutcome::Unchanged
+    }
+}
+
+impl CrawlerResponse {
+    /// Create a new crawler response with the current timestamp
+    pub fn new(
+        total_crawled: i32,
+        total_episodes: i32,
+        total_video_sources: i32,
+        pages_processed: i32,
+        errors: Vec<CrawlError>,
+    ) -> Self {
+        let mut data = CrawlerData {
+            total_crawled,
+            total_episodes,
+            total_video_sources,
+            pages_processed,
+            ..Default::default()
+        };
+        for error in errors {
+            data.record_error(error);
+        }
+        Self::from(data)
+    }
+}
+
+impl From<CrawlerData> for CrawlerResponse {
+    fn from(data: CrawlerData) -> Self {
+        Self {
+            success: true,
+            data,
+            timestamp: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Detailed report of a crawl, persisted for crawl jobs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlReport {
+    /// ISO timestamp when the crawl started
+    pub started_at: String,
+    /// ISO timestamp when the crawl finished
+    pub finished_at: String,
+    /// Wall-clock duration in milliseconds
+    pub duration_ms: u64,
+    /// Number of list pages crawled
+    pub pages_processed: i32,
+    /// Anime list entries: new, updated, and unchanged
+    pub list_entries: ChangeCount,
+    /// Anime details: new, updated, and unchanged
+    pub anime: ChangeCount,
+    /// Episodes: new, updated, and unchanged
+    pub episodes: ChangeCount,
+    /// Episode video source sets: new, updated, and unchanged
+    pub video_sources: ChangeCount,
+    /// Total number of errors
+    pub error_count: i32,
+    /// Errors grouped by type, most frequent first
+    pub error_groups: Vec<CrawlErrorGroup>,
+    /// Timing of each list page, including its anime and episodes
+    pub pages: Vec<CrawlPageTiming>,
+    /// Slowest page fetches, slowest first
+    pub slowest_requests: Vec<CrawlRequestTiming>,
+    /// Bytes of pages downloaded
+    #[serde(default)]
+    pub bytes_downloaded: u64,
+    /// Whether the crawl stopped early on reaching the daily bandwidth quota
+    #[serde(default)]
+    pub quota_exhausted: bool,
+    /// Time the crawl spent paused while the API was busy (milliseconds)
+    #[serde(default)]
+    pub backpressure_paused_ms: u64,
+    /// Human-readable summary of the report
+    pub summary: String,
+}
+
+/// Errors of one type in a crawl report
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlErrorGroup {
+    /// What failed and how, e.g. "fetch episode: HTTP 404"
+    pub kind: String,
+    /// Number of errors of this type
+    pub count: i32,
+    /// First few error messages of this type
+    pub examples: Vec<String>,
+}
+
+/// Time spent on one list page of a crawl
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlPageTiming {
+    /// List page number
+    pub page: u32,
+    /// Time spent on the page, its anime, and their episodes, in milliseconds
+    pub duration_ms: u64,
+    /// Anime listed on the page
+    pub anime_count: i32,
+    /// Errors while crawling the page
+    pub error_count: i32,
+}
+
+/// Kind of page fetched during a crawl
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CrawlRequestKind {
+    /// An anime list page
+    List,
+    /// An anime detail page
+    Anime,
+    /// An episode page
+    Episode,
+}
+
+/// One page fetch during a crawl
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlRequestTiming {
+    /// Fetched URL
+    pub url: String,
+    /// Kind of page
+    pub kind: CrawlRequestKind,
+    /// Time until the page was fetched or failed, in milliseconds
+    pub duration_ms: u64,
+    /// Whether the fetch succeeded
+    pub success: bool,
+}
+
+/// Kind of crawl target that can be retried
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CrawlFailureKind {
+    /// An anime detail page, retried along with its episodes
+    Anime,
+    /// An episode page and its video sources
+    Episode,
+}
+
+impl CrawlFailureKind {
+    /// Every kind
+    pub const ALL: [CrawlFailureKind; 2] = [Self::Anime, Self::Episode];
+
+    /// Name of the kind as stored
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Anime => "anime",
+            Self::Episode => "episode",
+        }
+    }
+
+    /// Look up a kind by name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+/// Anime or episode whose crawl failed, waiting to be retried
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlFailure {
+    /// Failure ID
+    pub id: i32,
+    /// Kind of target
+    pub kind: CrawlFailureKind,
+    /// Anime or episode slug
+    pub slug: String,
+    /// Page URL; for episodes, the URL their video sources are saved under
+    pub url: String,
+    /// Error of the latest attempt
+    pub last_error: String,
+    /// Number of failed attempts
+    pub attempts: i32,
+    /// ISO timestamp of the first failure
+    pub created_at: String,
+    /// ISO timestamp of the latest failure
+    pub updated_at: String,
+}
+
+/// Outcome of retrying failed crawl targets
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlRetryResult {
+    /// Failures retried
+    pub attempted: i32,
+    /// Failures that succeeded and left the queue
+    pub recovered: i32,
+    /// Failures still queued afterwards, including ones not retried yet
+    pub remaining: i64,
+    /// Totals of the retried crawl
+    pub crawl: CrawlerData,
+}
+
+// ============================================================================
+// Change Feed Models
+// ============================================================================
+
+/// Kind of record in the change feed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// An anime detail
+    Anime,
+    /// An episode (including its video sources)
+    Episode,
+}
+
+/// An insert or update of a record after the requested time
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEntry {
+    /// Kind of record
+    pub kind: ChangeKind,
+    /// Anime slug, or episode slug for episodes
+    pub slug: String,
+    /// Slug of the anime the record belongs to
+    pub anime_slug: String,
+    /// Anime or episode title
+    pub title: String,
+    /// Episode number (episodes only)
+    pub number: Option<String>,
+    /// Episode URL (episodes only)
+    pub url: Option<String>,
+    /// Whether the change inserted the record (rather than updating it)
+    pub created: bool,
+    /// ISO timestamp of the change
+    pub updated_at: String,
+}
+
+/// Page of the change feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesData {
+    /// The requested point in time
+    pub since: String,
+    /// Server time of the request; use as the next `since` once `nextCursor` is null
+    pub until: String,
+    /// Changes, oldest first
+    pub changes: Vec<ChangeEntry>,
+    /// Cursor for the next page, if there are more changes
+    pub next_cursor: Option<String>,
+}
+
+// ============================================================================
+// Session Models
+// ============================================================================
+
+/// A login session on one device
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Session ID
+    pub id: i32,
+    /// User agent of the client that logged in
+    pub user_agent: Option<String>,
+    /// IP address of the client that logged in
+    pub ip_address: Option<String>,
+    /// ISO timestamp when the session was created
+    pub created_at: String,
+    /// ISO timestamp of the last authenticated request
+    pub last_seen_at: String,
+    /// ISO timestamp when the session's token expires
+    pub expires_at: String,
+    /// Whether this is the session making the request
+    #[serde(default)]
+    pub current: bool,
+}
+
+// ============================================================================
+// Device Models
+// ============================================================================
+
+/// Longest platform name accepted when registering a device
+pub const MAX_DEVICE_PLATFORM_LEN: usize = 50;
+
+/// Request body for registering an anonymous device
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterDeviceRequest {
+    /// Platform of the client (e.g. "android", "ios")
+    #[serde(default)]
+    pub platform: Option<String>,
+}
+
+/// A registered anonymous device
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceRegistration {
+    /// Device ID
+    pub device_id: i32,
+    /// Token to send in the X-Device-Token header; only returned here
+    pub token: String,
+}
+
+// ============================================================================
+// User Preferences Models
+// ============================================================================
+
+/// Digest frequencies accepted for notification emails
+pub const DIGEST_FREQUENCIES: [&str; 3] = ["instant", "daily", "weekly"];
+
+/// Notification and playback preferences for a user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPreferences {
+    /// Whether to send new-episode notification emails
+    pub email_notifications: bool,
+    /// How often notification emails are sent (instant, daily, weekly)
+    pub digest_frequency: String,
+    /// Whether to deliver notifications to registered webhooks
+    pub webhooks_enabled: bool,
+    /// Preferred video quality (e.g., "720p"); the best available is used when unset
+    pub preferred_quality: Option<String>,
+    /// Language for emails ("en" or "id")
+    pub language: String,
+    /// Whether to leave the user's watching and favorites out of community leaderboards
+    pub community_opt_out: bool,
+    /// Whether lists and search include anime rated mature or adult when the
+    /// request doesn't say
+    pub show_adult_content: bool,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            email_notifications: true,
+            digest_frequency: "instant".to_string(),
+            webhooks_enabled: false,
+            preferred_quality: None,
+            language: "en".to_string(),
+            community_opt_out: false,
+            show_adult_content: false,
+        }
+    }
+}
+
+/// Request body for updating preferences; omitted fields are left unchanged
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePreferencesRequest {
+    /// Whether to send new-episode notification emails
+    pub email_notifications: Option<bool>,
+    /// How often notification emails are sent (instant, daily, weekly)
+    pub digest_frequency: Option<String>,
+    /// Whether to deliver notifications to registered webhooks
+    pub webhooks_enabled: Option<bool>,
+    /// Preferred video quality (e.g., "720p"); an empty string clears it
+    pub preferred_quality: Option<String>,
+    /// Language for emails ("en" or "id")
+    pub language: Option<String>,
+    /// Whether to leave the user's watching and favorites out of community leaderboards
+    pub community_opt_out: Option<bool>,
+    /// Whether lists and search include anime rated mature or adult when the
+    /// request doesn't say
+    pub show_adult_content: Option<bool>,
+}
+
+/// Move sources matching the preferred quality to the front
+///
+/// When a matching source exists it also becomes the default video. The
+/// comparison ignores case, so "720P" matches "720p".
+pub fn apply_preferred_quality(mut detail: EpisodeDetail, quality: &str) -> EpisodeDetail {
+    let matches = |source: &VideoSource| source.quality.eq_ignore_ascii_case(quality);
+
+    if let Some(source) = detail.sources.iter().find(|s| matches(s)) {
+        detail.default_video = source.url.clone();
+    }
+    // Stable sort keeps the scraped server order within each group
+    detail.sources.sort_by_key(|source| !matches(source));
+    detail
+}
+
+// ============================================================================
+// Video Server Models
+// ============================================================================
+
+/// Where a video server rule comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoServerRuleOrigin {
+    /// VIDEO_SERVER_BLACKLIST or VIDEO_SERVER_PRIORITY
+    Config,
+    /// Set by an admin at runtime
+    Admin,
+}
+
+/// How episode sources from one video server are treated
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoServerRule {
+    /// Server name as shown on episode pages, lowercased (e.g., "sokuja")
+    pub server: String,
+    /// Whether sources from the server are dropped
+    pub blocked: bool,
+    /// Servers with a higher priority are listed first; servers without a
+    /// rule count as 0
+    pub priority: i32,
+    /// Where the rule comes from
+    pub origin: VideoServerRuleOrigin,
+    /// When an admin last changed the rule; absent for config rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+/// Request body for setting a video server rule; omitted fields keep the
+/// server's current value
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoServerRuleRequest {
+    /// Whether to drop sources from the server
+    pub blocked: Option<bool>,
+    /// Ordering priority, higher first
+    pub priority: Option<i32>,
+}
+
+// ============================================================================
+// Feature Flag Models
+// ============================================================================
+
+/// Group of endpoints operators can switch off at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Feature {
+    /// Running crawls and queueing crawl jobs (POST /api/crawler/*)
+    Crawler,
+    /// Searching the source site (GET /api/search)
+    SearchUpstream,
+    /// Fetching images through the proxy (GET /api/images/proxy)
+    ImageProxy,
+}
+
+impl Feature {
+    /// Every feature, in the order they are documented
+    pub const ALL: [Feature; 3] = [Self::Crawler, Self::SearchUpstream, Self::ImageProxy];
+
+    /// Name of the feature as used in URLs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Crawler => "crawler",
+            Self::SearchUpstream => "search-upstream",
+            Self::ImageProxy => "image-proxy",
+        }
+    }
+
+    /// Look up a feature by name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|feature| feature.as_str() == name)
+    }
+}
+
+/// Whether a feature is on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlag {
+    /// The feature
+    pub feature: Feature,
+    /// Whether its endpoints are served; disabled ones answer 503
+    pub enabled: bool,
+    /// Why an operator changed it, shown in the 503 message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// When an operator last changed it; absent for features never changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+impl FeatureFlag {
+    /// An enabled feature no operator has changed
+    pub fn default_for(feature: Feature) -> Self {
+        Self {
+            feature,
+            enabled: true,
+            reason: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// Request body for switching a feature on or off
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlagRequest {
+    /// Whether the feature's endpoints are served
+    pub enabled: bool,
+    /// Why, for other operators and the 503 message
+    pub reason: Option<String>,
+}
+
+// ============================================================================
+// Source Check Models
+// ============================================================================
+
+/// Request body for checking video sources
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceCheckRequest {
+    /// Video source URLs from episode responses (1 to 20)
+    pub urls: Vec<String>,
+}
+
+/// Result of checking one video source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceStatus {
+    /// The URL as given
+    pub url: String,
+    /// Whether the source answered with a success status
+    pub alive: bool,
+    /// HTTP status of the response; absent if no response arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Milliseconds until the response headers arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Why the source couldn't be checked or reached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// Cast Models
+// ============================================================================
+
+/// Chromecast metadata type of TV show episodes
+pub const CAST_METADATA_TYPE_TV_SHOW: u8 = 2;
+
+/// Media information for casting an episode, shaped like a Chromecast
+/// receiver's `MediaInformation`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CastMetadata {
+    /// Video URL, also used as the content ID
+    pub content_id: String,
+    /// Video URL
+    pub content_url: String,
+    /// MIME type of the video (e.g., "video/mp4", "application/x-mpegURL")
+    pub content_type: String,
+    /// Always "BUFFERED"; episodes aren't live streams
+    pub stream_type: String,
+    /// Title, series, and artwork
+    pub metadata: CastMediaMetadata,
+    /// Subtitle tracks, served as WebVTT through the subtitle proxy
+    pub tracks: Vec<CastTrack>,
+    /// IDs of the tracks to enable when loading
+    pub active_track_ids: Vec<u32>,
+}
+
+/// Chromecast `TvShowMediaMetadata` of an episode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CastMediaMetadata {
+    /// Always 2 (TV_SHOW)
+    pub metadata_type: u8,
+    /// Episode title
+    pub title: String,
+    /// Anime title
+    pub series_title: String,
+    /// Episode number, when it is a plain number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode: Option<u32>,
+    /// Artwork, the anime poster first
+    pub images: Vec<CastImage>,
+}
+
+/// Image shown by the receiver
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CastImage {
+    /// Image URL
+    pub url: String,
+}
+
+/// Chromecast text track of a subtitle file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CastTrack {
+    /// Track ID, starting at 1
+    pub track_id: u32,
+    /// Always "TEXT"
+    #[serde(rename = "type")]
+    pub track_type: String,
+    /// Always "SUBTITLES"
+    pub subtype: String,
+    /// Absolute URL of the track through the subtitle proxy
+    pub track_content_id: String,
+    /// Always "text/vtt"
+    pub track_content_type: String,
+    /// Track name shown by the receiver
+    pub name: String,
+    /// Language code; empty if unknown
+    pub language: String,
+}
+
+/// MIME type of a video URL, from its file extension
+///
+/// Unknown extensions are assumed to be MP4, the format the source site
+/// serves.
+pub fn cast_content_type(url: &str) -> &'static str {
+    let path = reqwest::Url::parse(url)
+        .map(|url| url.path().to_ascii_lowercase())
+        .unwrap_or_default();
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("m3u8") => "application/x-mpegURL",
+        Some("mpd") => "application/dash+xml",
+        Some("webm") => "video/webm",
+        _ => "video/mp4",
+    }
+}
+
+impl CastMetadata {
+    /// Build cast metadata for an episode's video source
+    ///
+    /// # Arguments
+    /// * `source` - Native video source to cast
+    /// * `episode` - The episode
+    /// * `series_title` - Title of the anime
+    /// * `poster` - Poster URL of the anime; skipped if empty
+    /// * `subtitles` - Subtitle tracks of the episode
+    /// * `subtitle_proxy` - Absolute URL of the subtitle proxy endpoint
+    pub fn build(
+        source: &VideoSource,
+        episode: &Episode,
+        series_title: &str,
+        poster: &str,
+        subtitles: &[SubtitleTrack],
+        subtitle_proxy: &str,
+    ) -> Self {
+        let mut tracks = Vec::new();
+        let mut active_track_ids = Vec::new();
+        for (track_id, track) in (1..).zip(subtitles) {
+            let mut proxy_url = match reqwest::Url::parse(subtitle_proxy) {
+                Ok(url) => url,
+                Err(_) => break,
+            };
+            proxy_url.query_pairs_mut().append_pair("url", &track.url);
+
+            let name = [&track.label, &track.language]
+                .into_iter()
+                .find(|name| !name.is_empty())
+                .map_or_else(|| format!("Subtitle {}", track_id), |name| name.to_string());
+            tracks.push(CastTrack {
+                track_id,
+                track_type: "TEXT".to_string(),
+                subtype: "SUBTITLES".to_string(),
+                track_content_id: proxy_url.to_string(),
+                track_content_type: "text/vtt".to_string(),
+                name,
+                language: track.language.clone(),
+            });
+            if track.default {
+                active_track_ids.push(track_id);
+            }
+        }
+
+        Self {
+            content_id: source.url.clone(),
+            content_url: source.url.clone(),
+            content_type: cast_content_type(&source.url).to_string(),
+            stream_type: "BUFFERED".to_string(),
+            metadata: CastMediaMetadata {
+                metadata_type: CAST_METADATA_TYPE_TV_SHOW,
+                title: episode.title.clone(),
+                series_title: series_title.to_string(),
+                episode: episode.number.trim().parse().ok(),
+                images: (!poster.is_empty())
+                    .then(|| CastImage {
+                        url: poster.to_string(),
+                    })
+                    .into_iter()
+                    .collect(),
+            },
+            tracks,
+            active_track_ids,
+        }
+    }
+}
+
+// ============================================================================
+// Sitemap Models
+// ============================================================================
+
+/// An anime detail page listed in a sitemap
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+    /// Anime slug
+    pub slug: String,
+    /// When the stored detail last changed (RFC3339)
+    pub last_modified: Option<String>,
+}
+
+/// A sitemap partial listed in the sitemap index
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapPage {
+    /// Page number, starting at 1
+    pub page: i64,
+    /// Latest change of the anime on the page (RFC3339)
+    pub last_modified: Option<String>,
+}
+
+// ============================================================================
+// Upstream Anomaly Models
+// ============================================================================
+
+/// Way a page from the source site looked wrong
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpstreamAnomalyKind {
+    /// Content-Type missing or not HTML
+    UnexpectedContentType,
+    /// Body too short to be a real page
+    TinyBody,
+    /// Page redirects with a `<meta http-equiv="refresh">` tag
+    MetaRefresh,
+    /// Page is scripts with next to no text, e.g. a bot challenge
+    JsOnly,
+}
+
+impl UpstreamAnomalyKind {
+    /// Name of the kind as logged and serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UnexpectedContentType => "unexpected-content-type",
+            Self::TinyBody => "tiny-body",
+            Self::MetaRefresh => "meta-refresh",
+            Self::JsOnly => "js-only",
+        }
+    }
+}
+
+/// An anomalous response from the source site
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamAnomaly {
+    /// What looked wrong
+    pub kind: UpstreamAnomalyKind,
+    /// URL the response came from
+    pub url: String,
+    /// HTTP status of the response
+    pub status: u16,
+    /// Content-Type header, if any
+    pub content_type: Option<String>,
+    /// Body length in bytes
+    pub body_bytes: usize,
+    /// What was found, e.g. the meta refresh target
+    pub detail: String,
+    /// When the response was received (RFC3339)
+    pub detected_at: String,
+}
+
+/// Anomalies seen since startup, per kind
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamAnomalyCounts {
+    /// Responses with a missing or non-HTML Content-Type
+    pub unexpected_content_type: u64,
+    /// Responses with a near-empty body
+    pub tiny_body: u64,
+    /// Pages redirecting with a meta refresh
+    pub meta_refresh: u64,
+    /// Pages with scripts and next to no text
+    pub js_only: u64,
+}
+
+impl UpstreamAnomalyCounts {
+    /// Count one more anomaly of `kind`
+    pub fn add(&mut self, kind: UpstreamAnomalyKind) {
+        let count = match kind {
+            UpstreamAnomalyKind::UnexpectedContentType => &mut self.unexpected_content_type,
+            UpstreamAnomalyKind::TinyBody => &mut self.tiny_body,
+            UpstreamAnomalyKind::MetaRefresh => &mut self.meta_refresh,
+            UpstreamAnomalyKind::JsOnly => &mut self.js_only,
+        };
+        *count += 1;
+    }
+}
+
+/// Recent upstream anomalies and counts since startup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamAnomalyReport {
+    /// Anomalies seen since startup, per kind
+    pub counts: UpstreamAnomalyCounts,
+    /// The latest anomalies, newest first
+    pub recent: Vec<UpstreamAnomaly>,
+}
+
+/// Bytes the crawler downloaded on one day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlerBandwidthDay {
+    /// Day (UTC), e.g. "2024-12-27"
+    pub day: String,
+    /// Bytes of pages downloaded
+    pub bytes: u64,
+    /// Pages fetched
+    pub requests: i32,
+}
+
+/// Crawler bandwidth use against the daily quota
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlerBandwidthReport {
+    /// Bytes downloaded today (UTC)
+    pub today_bytes: u64,
+    /// Daily quota in bytes, if one is set
+    pub daily_quota_bytes: Option<u64>,
+    /// Whether crawls are paused until the next day
+    pub quota_exhausted: bool,
+    /// Use over the last 30 days, newest first
+    pub days: Vec<CrawlerBandwidthDay>,
+}
+
+/// Utilization of one database connection pool
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DbPoolStats {
+    /// Most connections the pool opens
+    pub max_connections: u32,
+    /// Connections open, in use or idle
+    pub size: u32,
+    /// Connections running a query
+    pub in_use: u32,
+    /// Connections open and free
+    pub idle: u32,
+    /// How long acquiring a connection took just now (milliseconds); 0 if
+    /// one was idle, absent if none could be acquired within 500 ms
+    pub acquire_wait_ms: Option<u64>,
+}
+
+/// Utilization of the database connection pools
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DbPoolReport {
+    /// Primary pool
+    pub primary: DbPoolStats,
+    /// Read replica pool, if a replica is configured
+    pub replica: Option<DbPoolStats>,
+    /// Whether reads go to the replica, if one is configured
+    pub replica_healthy: Option<bool>,
+    /// Prepared statements cached per connection
+    pub statement_cache_size: usize,
+}
+
+/// Outcome of a configuration reload
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigReload {
+    /// Selector profile now in use, if any (the built-in selectors otherwise)
+    pub selector_profile: Option<String>,
+    /// Video server rules now in effect
+    pub video_server_rules: usize,
+    /// When the reload took effect (RFC3339)
+    pub reloaded_at: String,
+}
+
+// ============================================================================
+// JSON Web Key Models
+// ============================================================================
+
+/// Public key a token signature can be verified with (RFC 7517)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Jwk {
+    /// Key type: "RSA" or "OKP"
+    pub kty: String,
+    /// Key ID, matching the `kid` header of tokens signed with the key
+    pub kid: String,
+    /// Signature algorithm: "RS256" or "EdDSA"
+    pub alg: String,
+    /// Intended use, always "sig"
+    #[serde(rename = "use")]
+    pub key_use: String,
+    /// RSA modulus (base64url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// RSA public exponent (base64url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// Curve of an OKP key: "Ed25519"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// Public key of an OKP key (base64url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+/// Keys tokens are currently accepted from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct JwkSet {
+    /// Public keys, the signing key first
+    pub keys: Vec<Jwk>,
+}
+
+// ============================================================================
+// Saved Search Models
+// ============================================================================
+
+/// A search whose new matches in the crawled catalog are emailed to the user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    /// Saved search ID
+    pub id: i32,
+    /// Display name used in notifications
+    pub name: String,
+    /// Normalized text matched against titles and genres
+    pub query: Option<String>,
+    /// Anime type filter (TV, OVA, Movie, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: Option<String>,
+    /// Status filter (Ongoing, Completed, etc.)
+    pub status: Option<String>,
+    /// ISO timestamp up to which crawled anime have been checked
+    pub last_checked_at: String,
+    /// ISO timestamp when the search was saved
+    pub created_at: String,
+}
+
+// ============================================================================
+// Collection Models
+// ============================================================================
+
+/// A named, ordered list of anime curated by a user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    /// Collection ID
+    pub id: i32,
+    /// Collection name (e.g. "Best of 2023")
+    pub name: String,
+    /// Optional description
+    pub description: Option<String>,
+    /// Whether anyone can view the collection through its share slug
+    pub is_public: bool,
+    /// Slug of the public link (GET /api/collections/{shareSlug})
+    pub share_slug: String,
+    /// Number of anime in the collection
+    pub item_count: i64,
+    /// ISO timestamp when the collection was created
+    pub created_at: String,
+    /// ISO timestamp of the last change to the collection or its items
+    pub updated_at: String,
+}
+
+/// An anime in a collection
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItem {
+    /// Anime slug identifier
+    pub anime_slug: String,
+    /// Anime title for display
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: String,
+    /// 0-based position within the collection
+    pub position: i32,
+    /// ISO timestamp when the anime was added
+    pub added_at: String,
+}
+
+/// A collection together with its anime, in order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionDetail {
+    /// The collection
+    pub collection: Collection,
+    /// Anime in the collection, ordered by position
+    pub items: Vec<CollectionItem>,
+}
+
+// ============================================================================
+// Comment Models
+// ============================================================================
+
+/// Moderation content type of episode comments
+pub const COMMENT_CONTENT_TYPE: &str = "comment";
+
+/// A comment on an episode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeComment {
+    /// Comment ID
+    pub id: i32,
+    /// Slug of the episode commented on
+    pub episode_slug: String,
+    /// Author's user ID
+    pub author_id: i32,
+    /// Author's display name
+    pub author_name: Option<String>,
+    /// Author's avatar URL
+    pub author_avatar: Option<String>,
+    /// Comment text
+    pub body: String,
+    /// Playback position the comment refers to, in seconds
+    pub timestamp_seconds: Option<i32>,
+    /// ISO timestamp when the comment was posted
+    pub created_at: String,
+}
+
+/// Page of an episode's comments, newest first
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentPage {
+    /// Comments on this page
+    pub items: Vec<EpisodeComment>,
+    /// Current page number
+    pub page: i64,
+    /// Comments per page
+    pub per_page: i64,
+    /// Visible comments on the episode
+    pub total: i64,
+}
+
+// ============================================================================
+// Community Models
+// ============================================================================
+
+/// Time window of a community leaderboard
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum LeaderboardWindow {
+    /// The last 7 days
+    Weekly,
+    /// The last 30 days
+    Monthly,
+    /// All recorded activity
+    AllTime,
+}
+
+impl LeaderboardWindow {
+    /// Every window
+    pub const ALL: [LeaderboardWindow; 3] = [Self::Weekly, Self::Monthly, Self::AllTime];
+
+    /// Name of the window as used in queries
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Weekly => "weekly",
+            Self::Monthly => "monthly",
+            Self::AllTime => "all-time",
+        }
+    }
+
+    /// Look up a window by name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|window| window.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Start of the window ending at `now`, or `None` for all time
+    pub fn since(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Weekly => Some(now - chrono::Duration::days(7)),
+            Self::Monthly => Some(now - chrono::Duration::days(30)),
+            Self::AllTime => None,
+        }
+    }
+}
+
+/// An anime's standing on a community leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityTopEntry {
+    /// Position on the leaderboard, starting at 1
+    pub rank: i64,
+    /// Anime slug identifier
+    pub anime_slug: String,
+    /// Anime title
+    pub anime_title: String,
+    /// Thumbnail image URL
+    pub thumbnail: Option<String>,
+    /// Users who watched an episode within the window
+    pub watchers: i64,
+    /// Users who favorited the anime within the window
+    pub favorites: i64,
+}
+
+/// Most-watched anime among registered users
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityTop {
+    /// Time window the leaderboard covers
+    pub window: LeaderboardWindow,
+    /// Anime, most watched first
+    pub items: Vec<CommunityTopEntry>,
+    /// When the leaderboard was computed (RFC3339)
+    pub computed_at: String,
+}
+
+// ============================================================================
+// Reaction Models
+// ============================================================================
+
+/// Likes on an episode as seen by the requesting user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeLikes {
+    /// Episode slug
+    pub episode_slug: String,
+    /// Number of users who liked the episode
+    pub like_count: i64,
+    /// Whether the requesting user liked the episode
+    pub liked_by_me: bool,
+}
+
+// ============================================================================
+// Age Rating Models
+// ============================================================================
+
+/// Age ratings of an anime
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeAgeRating {
+    /// Anime slug
+    pub anime_slug: String,
+    /// Rating in effect: the override, else the derived rating
+    pub age_rating: Option<AgeRating>,
+    /// Rating derived from the stored genres; unset if the anime isn't stored
+    pub derived_rating: Option<AgeRating>,
+    /// Rating pinned by an admin
+    pub override_rating: Option<AgeRating>,
+}
+
+/// Request body for overriding an anime's age rating
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AgeRatingOverrideRequest {
+    /// Rating to pin
+    pub age_rating: AgeRating,
+}
+
+// ============================================================================
+// Tag Models
+// ============================================================================
+
+/// A tag describing anime beyond the scraped genres ("time travel")
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// Tag ID
+    pub id: i32,
+    /// Unique slug derived from the name (e.g., "time-travel")
+    pub slug: String,
+    /// Display name
+    pub name: String,
+    /// What the tag means
+    pub description: String,
+    /// Whether an admin curated the tag; user-created tags start uncurated
+    pub curated: bool,
+    /// Number of anime tagged with it
+    pub anime_count: i64,
+    /// Number of times users applied it, across all anime
+    pub usage_count: i64,
+    /// When the tag was created (RFC3339)
+    pub created_at: String,
+}
+
+/// A tag on one anime as seen by the requesting user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeTag {
+    /// Tag slug
+    pub slug: String,
+    /// Display name
+    pub name: String,
+    /// Whether an admin curated the tag
+    pub curated: bool,
+    /// Number of users who applied the tag to this anime
+    pub votes: i64,
+    /// Whether the requesting user applied the tag
+    pub tagged_by_me: bool,
+}
+
+/// Request body for tagging an anime
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TagAnimeRequest {
+    /// Tag name; a name matching no tag creates an uncurated one
+    pub name: String,
+}
+
+/// Request body for creating a curated tag
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTagRequest {
+    /// Display name; the slug is derived from it
+    pub name: String,
+    /// What the tag means
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Request body for changing a tag; omitted fields are left unchanged
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTagRequest {
+    /// New display name; the slug follows it
+    pub name: Option<String>,
+    /// New description
+    pub description: Option<String>,
+    /// Curate or uncurate the tag
+    pub curated: Option<bool>,
+}
+
+// ============================================================================
+// Personal Data Models
+// ============================================================================
+
+/// Export status while the archive is being built
+pub const DATA_EXPORT_PENDING: &str = "pending";
+/// Export status once the archive is stored and downloadable
+pub const DATA_EXPORT_READY: &str = "ready";
+/// Export status after the job gave up building the archive
+pub const DATA_EXPORT_FAILED: &str = "failed";
+
+/// A requested archive of everything stored about a user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DataExport {
+    /// Export ID
+    pub id: i32,
+    /// ID of the user the export is for
+    pub user_id: i32,
+    /// pending, ready, or failed
+    pub status: String,
+    /// Storage key of the archive, once ready
+    #[serde(skip)]
+    pub object_key: Option<String>,
+    /// ISO timestamp when the export was requested
+    pub created_at: String,
+    /// ISO timestamp when the archive was stored or the export failed
+    pub completed_at: Option<String>,
+    /// Signed download URL, set for ready exports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    /// When the download URL stops working (RFC3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+/// Log entry for an account whose data was erased
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DataErasure {
+    /// Erasure ID
+    pub id: i32,
+    /// ID the erased user had
+    pub user_id: i32,
+    /// Tenant the erased user belonged to
+    pub tenant_id: i32,
+    /// Tables rows were deleted or anonymized in, with the rows affected
+    pub tables: Vec<TableRowCount>,
+    /// ISO timestamp of the erasure
+    pub erased_at: String,
+}
+
+// ============================================================================
+// Anime Diff Models
+// ============================================================================
+
+/// A field whose stored and scraped values differ
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDiff {
+    /// Field name as it appears in the API (camelCase)
+    pub field: String,
+    /// Value in the database
+    #[schema(value_type = Object)]
+    pub stored: serde_json::Value,
+    /// Value in the freshly scraped page
+    #[schema(value_type = Object)]
+    pub scraped: serde_json::Value,
+}
+
+/// Field differences of one episode, matched by URL
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeDiff {
+    /// Episode URL
+    pub url: String,
+    /// Fields that differ
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Difference between a stored anime detail and a fresh scrape
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeDiff {
+    /// Anime slug
+    pub slug: String,
+    /// Whether the anime was in the database; if not, every field differs
+    pub stored_found: bool,
+    /// Whether anything differs
+    pub changed: bool,
+    /// Anime fields that differ (episodes are compared separately)
+    pub fields: Vec<FieldDiff>,
+    /// Episodes only in the scraped page
+    pub episodes_added: Vec<Episode>,
+    /// Episodes only in the database
+    pub episodes_removed: Vec<Episode>,
+    /// Episodes in both whose fields differ
+    pub episodes_changed: Vec<EpisodeDiff>,
+    /// The freshly scraped record
+    pub scraped: AnimeDetail,
+}
+
+/// Compare the top-level fields of two serialized records
+fn diff_fields<T: Serialize>(stored: &T, scraped: &T, skip: &[&str]) -> Vec<FieldDiff> {
+    let (Ok(serde_json::Value::Object(stored)), Ok(serde_json::Value::Object(scraped))) =
+        (serde_json::to_value(stored), serde_json::to_value(scraped))
+    else {
+        return Vec::new();
+    };
+
+    scraped
+        .iter()
+        .filter(|(field, _)| !skip.contains(&field.as_str()))
+        .filter_map(|(field, scraped_value)| {
+            let stored_value = stored.get(field).cloned().unwrap_or_default();
+            (stored_value != *scraped_value).then(|| FieldDiff {
+                field: field.clone(),
+                stored: stored_value,
+                scraped: scraped_value.clone(),
+            })
+        })
+        .collect()
+}
+
+impl AnimeDiff {
+    /// Diff a stored record (if any) against a freshly scraped one
+    pub fn between(slug: &str, stored: Option<&AnimeDetail>, scraped: AnimeDetail) -> Self {
+        let empty = AnimeDetail::default();
+        let stored_found = stored.is_some();
+        let stored = stored.unwrap_or(&empty);
+
+        let fields = diff_fields(stored, &scraped, &["episodes"]);
+
+        let episodes_added: Vec<Episode> = scraped
+            .episodes
+            .iter()
+            .filter(|e| !stored.episodes.iter().any(|s| s.url == e.url))
+            .cloned()
+            .collect();
+        let episodes_removed: Vec<Episode> = stored
+            .episodes
+            .iter()
+            .filter(|s| !scraped.episodes.iter().any(|e| e.url == s.url))
+            .cloned()
+            .collect();
+        let episodes_changed: Vec<EpisodeDiff> = scraped
+            .episodes
+            .iter()
+            .filter_map(|episode| {
+                let stored_episode = stored.episodes.iter().find(|s| s.url == episode.url)?;
+                let fields = diff_fields(stored_episode, episode, &[]);
+                (!fields.is_empty()).then(|| EpisodeDiff {
+                    url: episode.url.clone(),
+                    fields,
+                })
+            })
+            .collect();
+
+        Self {
+            slug: slug.to_string(),
+            stored_found,
+            changed: !fields.is_empty()
+                || !episodes_added.is_empty()
+                || !episodes_removed.is_empty()
+                || !episodes_changed.is_empty(),
+            fields,
+            episodes_added,
+            episodes_removed,
+            episodes_changed,
+            scraped,
+        }
+    }
+}
+
+// ============================================================================
+// Episode Timeline Models
+// ============================================================================
+
+/// Episodes first seen less than this far apart belong to the same release
+/// batch (e.g. a multi-episode drop, or the initial import of a catalog)
+pub const RELEASE_BATCH_WINDOW_SECS: i64 = 3600;
+
+/// An episode and when it first appeared in our database
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEpisode {
+    /// Episode number
+    pub number: String,
+    /// Episode title
+    pub title: String,
+    /// Episode URL
+    pub url: String,
+    /// ISO timestamp when the episode was first seen
+    pub first_seen_at: String,
+}
+
+/// Release history of an anime with airing-gap statistics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeTimeline {
+    /// Anime slug
+    pub slug: String,
+    /// Airing status (Ongoing, Completed, etc.)
+    pub status: String,
+    /// Episodes, oldest first
+    pub episodes: Vec<TimelineEpisode>,
+    /// Median time between release batches, in hours
+    pub median_gap_hours: Option<f64>,
+    /// ISO timestamp of the latest release batch
+    pub last_release_at: Option<String>,
+    /// ISO timestamp the next episode is expected (last release + median gap)
+    pub next_expected_at: Option<String>,
+    /// No new episode for more than twice the median gap on an airing anime
+    pub likely_hiatus: bool,
+}
+
+impl AnimeTimeline {
+    /// Build a timeline from episodes and their first-seen times
+    ///
+    /// Gaps are measured between release batches (see
+    /// [`RELEASE_BATCH_WINDOW_SECS`]); at least two gaps are needed for a
+    /// median. Completed anime are never on hiatus.
+    pub fn build(
+        slug: &str,
+        status: &str,
+        mut episodes: Vec<(TimelineEpisode, DateTime<Utc>)>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        episodes.sort_by_key(|(_, seen)| *seen);
+
+        let mut releases: Vec<DateTime<Utc>> = Vec::new();
+        for (_, seen) in &episodes {
+            match releases.last() {
+                Some(last) if (*seen - *last).num_seconds() < RELEASE_BATCH_WINDOW_SECS => {}
+                _ => releases.push(*seen),
+            }
+        }
+
+        let mut gaps: Vec<i64> = releases
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).num_seconds())
+            .collect();
+        gaps.sort_unstable();
+        let median_gap = match gaps.len() {
+            0 | 1 => None,
+            n if n % 2 == 0 => Some((gaps[n / 2 - 1] + gaps[n / 2]) / 2),
+            n => Some(gaps[n / 2]),
+        };
+
+        let last_release = releases.last().copied();
+        let airing = !status.eq_ignore_ascii_case("completed");
+        let likely_hiatus = match (median_gap, last_release) {
+            (Some(gap), Some(last)) => airing && (now - last).num_seconds() > 2 * gap,
+            _ => false,
+        };
+        let next_expected_at = match (median_gap, last_release) {
+            (Some(gap), Some(last)) if airing => {
+                Some((last + chrono::Duration::seconds(gap)).to_rfc3339())
+            }
+            _ => None,
+        };
+
+        Self {
+            slug: slug.to_string(),
+            status: status.to_string(),
+            episodes: episodes.into_iter().map(|(episode, _)| episode).collect(),
+            median_gap_hours: median_gap.map(|secs| secs as f64 / 3600.0),
+            last_release_at: last_release.map(|at| at.to_rfc3339()),
+            next_expected_at,
+            likely_hiatus,
+        }
+    }
+}
+
+// ============================================================================
+// Background Job Models
+// ============================================================================
+
+/// A background job stored in the persistent job queue
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+    /// Job ID
+    pub id: i32,
+    /// Queue the job belongs to (e.g., "crawler", "email")
+    pub queue: String,
+    /// Job type used to pick the handler (e.g., "crawl", "send_email")
+    pub job_type: String,
+    /// Handler-specific JSON payload
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    /// pending, running, completed, or dead (retries exhausted)
+    pub status: String,
+    /// Runnable jobs are claimed highest priority first
+    #[serde(default)]
+    pub priority: i32,
+    /// Number of attempts made so far
+    pub attempts: i32,
+    /// Attempts allowed before the job is moved to the dead-letter state
+    pub max_attempts: i32,
+    /// Error message from the most recent failed attempt
+    pub last_error: Option<String>,
+    /// Handler-specific JSON result of a completed job
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<serde_json::Value>,
+    /// ISO timestamp when the job becomes eligible to run
+    pub run_at: String,
+    /// ISO timestamp when the job was created
+    pub created_at: String,
+    /// ISO timestamp when the job was last updated
+    pub updated_at: String,
+}
+
+/// A notification or webhook event in the outbox
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEventRecord {
+    /// Event ID, sent with its webhook so receivers can drop duplicates
+    pub id: i64,
+    /// Event type (e.g., "anime.completed")
+    pub event_type: String,
+    /// JSON payload of the event
+    pub payload: String,
+    /// Failed deliveries so far
+    pub attempts: i32,
+    /// Error from the most recent failed delivery
+    pub last_error: Option<String>,
+    /// ISO timestamp when the event was recorded
+    pub created_at: String,
+}
+
+/// Job counts for a single queue
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobQueueStats {
+    /// Queue name
+    pub queue: String,
+    /// Jobs waiting to run (including scheduled retries)
+    pub pending: i64,
+    /// Pending jobs that have failed at least once and are awaiting retry
+    pub retrying: i64,
+    /// Jobs currently being processed by a worker
+    pub running: i64,
+    /// Jobs that finished successfully
+    pub completed: i64,
+    /// Jobs that exhausted their retries (dead-letter)
+    pub dead: i64,
+}
+
+/// Overview of the job queue returned by the admin jobs endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobsOverview {
+    /// Per-queue job counts
+    pub queues: Vec<JobQueueStats>,
+    /// Most recently failed jobs (retrying or dead), newest first
+    pub recent_failures: Vec<JobRecord>,
+}
+
+/// Request body for merging a duplicate anime into another
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeAnimeRequest {
+    /// Slug of the duplicate, which becomes an alias
+    pub from: String,
+    /// Slug of the anime to keep
+    pub into: String,
+}
+
+/// Rows moved from the duplicate to the kept anime by a merge
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeMergeResult {
+    /// Slug of the duplicate, now an alias
+    pub from: String,
+    /// Slug of the kept anime
+    pub into: String,
+    /// Episodes moved
+    pub episodes: u64,
+    /// Video sources of the moved episodes
+    pub video_sources: u64,
+    /// Favorites moved; favorites of users who already had both are dropped
+    pub favorites: u64,
+    /// Subscriptions moved, dropping duplicates the same way
+    pub subscriptions: u64,
+    /// Watch history entries moved
+    pub history: u64,
+    /// Watched-episode marks moved
+    pub watched_episodes: u64,
+}
+
+/// Rows referencing one missing parent record
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanGroup {
+    /// Key of the missing parent (anime slug or episode URL)
+    pub parent: String,
+    /// Number of rows referencing it
+    pub count: i64,
+}
+
+/// Result of a consistency check, stored as the integrity check job result
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// ISO timestamp when the check ran
+    pub checked_at: String,
+    /// Episodes whose anime_slug has no anime_details row, by slug
+    pub orphaned_episodes: Vec<OrphanGroup>,
+    /// Video sources whose episode_url has no episodes row, by URL
+    pub orphaned_video_sources: Vec<OrphanGroup>,
+    /// Favorites of slugs missing from crawled_anime, by slug
+    pub dangling_favorites: Vec<OrphanGroup>,
+    /// Anime slugs a scrape was queued for (auto-repair only)
+    pub repairs_queued: Vec<String>,
+}
+
+impl IntegrityReport {
+    /// Whether no inconsistencies were found
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_episodes.is_empty()
+            && self.orphaned_video_sources.is_empty()
+            && self.dangling_favorites.is_empty()
+    }
+
+    /// Distinct anime slugs that a scrape would restore, in report order
+    ///
+    /// Orphaned video sources are not included: their anime can't be told
+    /// from the episode URL, so they are only reported.
+    pub fn missing_anime(&self) -> Vec<String> {
+        let mut slugs: Vec<String> = Vec::new();
+        for group in self
+            .orphaned_episodes
+            .iter()
+            .chain(&self.dangling_favorites)
+        {
+            if !slugs.contains(&group.parent) {
+                slugs.push(group.parent.clone());
+            }
+        }
+        slugs
+    }
+}
+
+/// An anime missing episodes below its highest stored one, or below its
+/// episode count once completed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeGap {
+    pub slug: String,
+    pub title: String,
+    /// Episode count from the detail page, if it is a number
+    pub total_episodes: Option<u32>,
+    /// Highest stored episode number
+    pub highest_episode: u32,
+    /// Episode numbers missing, ascending
+    pub missing: Vec<u32>,
+    /// ISO timestamp of the anime's last scrape
+    pub scraped_at: Option<String>,
+}
+
+/// Anime with gaps in their stored episodes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeGapReport {
+    /// ISO timestamp when the check ran
+    pub checked_at: String,
+    /// Anime with gaps, most episodes missing first
+    pub gaps: Vec<EpisodeGap>,
+    /// Anime slugs a backfill scrape was queued for (scheduled checks only)
+    pub backfills_queued: Vec<String>,
+}
+
+// ============================================================================
+// Search Analytics Models
+// ============================================================================
+
+/// Search counts for one normalized query
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQueryStats {
+    /// Normalized query (lowercased, whitespace collapsed)
+    pub query: String,
+    /// Number of times the query was searched
+    pub search_count: i32,
+    /// Number of those searches that returned no results
+    pub zero_result_count: i32,
+    /// Number of results the most recent search returned
+    pub last_result_count: i32,
+    /// ISO timestamp of the first search
+    pub first_searched_at: String,
+    /// ISO timestamp of the most recent search
+    pub last_searched_at: String,
+}
+
+/// Search analytics returned by the admin search analytics endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchAnalytics {
+    /// Most searched queries
+    pub popular: Vec<SearchQueryStats>,
+    /// Queries whose most recent search found nothing, most often empty first
+    pub zero_results: Vec<SearchQueryStats>,
+}
+
+/// Database maintenance operation run from the admin maintenance endpoints
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum MaintenanceAction {
+    /// Delete all stored anime updates
+    DeleteAnimeUpdates,
+    /// Delete all stored completed anime
+    DeleteCompletedAnime,
+    /// Delete all crawled anime records
+    DeleteCrawledAnime,
+    /// Delete all cache timestamps, so the next requests scrape fresh data
+    DeleteCacheEntries,
+    /// Delete episodes without a parent anime and video sources without an episode
+    DeleteOrphans,
+    /// VACUUM ANALYZE the scraped data tables
+    Vacuum,
+    /// Rebuild the indexes of the scraped data tables
+    Reindex,
+    /// Encrypt user emails and Google IDs with the current data encryption key
+    EncryptUserData,
+}
+
+impl MaintenanceAction {
+    /// Every action, in the order they are documented
+    pub const ALL: [MaintenanceAction; 8] = [
+        Self::DeleteAnimeUpdates,
+        Self::DeleteCompletedAnime,
+        Self::DeleteCrawledAnime,
+        Self::DeleteCacheEntries,
+        Self::DeleteOrphans,
+        Self::Vacuum,
+        Self::Reindex,
+        Self::EncryptUserData,
+    ];
+
+    /// Name of the action as used in URLs
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DeleteAnimeUpdates => "delete-anime-updates",
+            Self::DeleteCompletedAnime => "delete-completed-anime",
+            Self::DeleteCrawledAnime => "delete-crawled-anime",
+            Self::DeleteCacheEntries => "delete-cache-entries",
+            Self::DeleteOrphans => "delete-orphans",
+            Self::Vacuum => "vacuum",
+            Self::Reindex => "reindex",
+            Self::EncryptUserData => "encrypt-user-data",
+        }
+    }
+
+    /// Look up an action by name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.as_str() == name)
+    }
+}
+
+/// Rows affected in one table by a maintenance action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TableRowCount {
+    /// Table name
+    pub table: String,
+    /// Rows deleted or rewritten (always 0 for VACUUM and REINDEX)
+    pub rows: u64,
+}
+
+/// Outcome of a maintenance action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceResult {
+    /// The action that ran
+    pub action: MaintenanceAction,
+    /// Total rows affected across all tables
+    pub affected_rows: u64,
+    /// Tables the action touched, with the rows affected in each
+    pub tables: Vec<TableRowCount>,
+}
+
+impl MaintenanceResult {
+    /// Build a result from per-table counts
+    pub fn new(action: MaintenanceAction, tables: Vec<TableRowCount>) -> Self {
+        Self {
+            action,
+            affected_rows: tables.iter().map(|t| t.rows).sum(),
+            tables,
+        }
+    }
+}
+
+// ============================================================================
+// Email Delivery Models
+// ============================================================================
+
+/// Delivery record for a queued transactional email
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailDelivery {
+    /// Delivery ID
+    pub id: i32,
+    /// ID of the job that is (or was last) sending this email
+    pub job_id: Option<i32>,
+    /// Recipient email address
+    pub recipient: String,
+    /// Email template (e.g., "verification", "passwordReset")
+    pub template: String,
+    /// Language the email is rendered in (e.g., "en", "id")
+    pub language: String,
+    /// queued, sent, or failed (retries exhausted)
+    pub status: String,
+    /// Number of send attempts made so far
+    pub attempts: i32,
+    /// Error message from the most recent failed attempt
+    pub last_error: Option<String>,
+    /// ISO timestamp when the email was sent
+    pub sent_at: Option<String>,
+    /// ISO timestamp when the email was queued
+    pub created_at: String,
+    /// ISO timestamp when the delivery was last updated
+    pub updated_at: String,
+}
+
+// ============================================================================
+// Email Verification and Password Reset Models
+// ============================================================================
+
+/// Request body for forgot password
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgotPasswordRequest {
+    /// User email address
+    pub email: String,
+}
+
+/// Request body for password reset
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPasswordRequest {
+    /// Password reset token
+    pub token: String,
+    /// New password
+    pub new_password: String,
+}
+
+/// Password strength feedback
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordFeedback {
+    /// Strength score from 0 (trivially guessable) to 4 (very strong)
+    pub score: u8,
+    /// Problems found in the password
+    pub warnings: Vec<String>,
+    /// Suggestions for a stronger password
+    pub suggestions: Vec<String>,
+    /// Whether the password appears in a known data breach
+    pub breached: bool,
+}
+
+/// Error response for a password that doesn't meet the strength policy
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeakPasswordResponse {
+    /// Whether the operation was successful (always false)
+    pub success: bool,
+    /// Error message describing what went wrong
+    pub error: String,
+    /// Strength feedback for the rejected password
+    pub feedback: PasswordFeedback,
+    /// Minimum score required
+    pub min_score: u8,
+    /// ISO timestamp of when the error occurred
+    pub timestamp: String,
+}
+
+/// A signed, expiring URL
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct AnimeListFilters {
-    /// Type filter (TV, OVA, Movie, etc.)
-    #[serde(rename = "type")]
-    pub anime_type: String,
-    /// Status filter (Ongoing, Completed, etc.)
-    pub status: String,
-    /// Sort order
-    pub order: String,
+pub struct SignedUrl {
+    /// Path and query of the signed URL, relative to the API host
+    pub url: String,
+    /// When the URL stops working (RFC3339)
+    pub expires_at: String,
 }
 
-/// Represents a crawled anime entry from bulk crawler
-/// Same fields as AnimeListItem for consistency
+/// A tenant: one frontend with its own isolated user base
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct CrawledAnime {
-    /// Extracted from URL (e.g., "one-piece-subtitle-indonesia")
+pub struct Tenant {
+    /// Tenant ID
+    pub id: i32,
+    /// Unique identifier, accepted in the tenant header
     pub slug: String,
-    /// Anime title from h2[itemprop="headline"]
-    pub title: String,
-    /// Full URL to anime page from a[itemprop="url"]
-    pub url: String,
-    /// Thumbnail image URL from img.ts-post-image
-    pub thumbnail: String,
-    /// From div.status (Completed, Ongoing, etc.)
-    pub status: String,
-    /// From div.typez (TV, ONA, Movie, etc.)
-    #[serde(rename = "type")]
-    pub anime_type: String,
-    /// From span.epx (episode count or status text)
-    pub episode_status: String,
+    /// Display name
+    pub name: String,
+    /// Hostnames that resolve to this tenant
+    pub hostnames: Vec<String>,
+    /// When the tenant was created (RFC3339)
+    pub created_at: String,
 }
 
-/// Database representation of CrawledAnime with timestamps
+/// A named set of permissions that can be granted to users
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct CrawledAnimeRecord {
-    /// Database ID
+pub struct Role {
+    /// Role ID
     pub id: i32,
-    /// Extracted from URL (e.g., "one-piece-subtitle-indonesia")
-    pub slug: String,
-    /// Anime title
-    pub title: String,
-    /// Full URL to anime page
-    pub url: String,
-    /// Thumbnail image URL
-    pub thumbnail: String,
-    /// Completed, Ongoing, etc.
-    pub status: String,
-    /// TV, ONA, Movie, etc.
-    #[serde(rename = "type")]
-    pub anime_type: String,
-    /// Episode count or status text
-    pub episode_status: String,
-    /// ISO timestamp when created
+    /// Unique name (e.g., "moderator")
+    pub name: String,
+    /// What the role is for
+    pub description: String,
+    /// Permission strings granted by the role (e.g., "crawler:run")
+    pub permissions: Vec<String>,
+    /// When the role was created (RFC3339)
     pub created_at: String,
-    /// ISO timestamp when last updated
+    /// When the role was last changed (RFC3339)
     pub updated_at: String,
 }
 
-/// Response for the bulk crawler endpoint
+/// Request body for creating a role
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct CrawlerResponse {
-    /// Whether the operation was successful
-    pub success: bool,
-    /// Crawler result data
-    pub data: CrawlerData,
-    /// ISO timestamp of the response
-    pub timestamp: String,
+pub struct CreateRoleRequest {
+    /// Unique name (lowercase letters, digits, and dashes)
+    pub name: String,
+    /// What the role is for
+    #[serde(default)]
+    pub description: String,
+    /// Permission strings to grant
+    #[serde(default)]
+    pub permissions: Vec<String>,
 }
 
-/// Data returned by the crawler endpoint
+/// Request body for changing a role; omitted fields are left unchanged
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct CrawlerData {
-    /// Total anime saved/updated
-    pub total_crawled: i32,
-    /// Total episodes saved/updated
-    pub total_episodes: i32,
-    /// Total video sources saved/updated
-    pub total_video_sources: i32,
-    /// Number of pages crawled
-    pub pages_processed: i32,
-    /// Any errors encountered during crawling
-    pub errors: Vec<String>,
+pub struct UpdateRoleRequest {
+    /// New description
+    pub description: Option<String>,
+    /// Replacement permission list
+    pub permissions: Option<Vec<String>>,
 }
 
-impl CrawlerResponse {
-    /// Create a new crawler response with the current timestamp
-    pub fn new(
-        total_crawled: i32,
-        total_episodes: i32,
-        total_video_sources: i32,
-        pages_processed: i32,
-        errors: Vec<String>,
-    ) -> Self {
-        Self {
-            success: true,
-            data: CrawlerData {
-                total_crawled,
-                total_episodes,
-                total_video_sources,
-                pages_processed,
-                errors,
-            },
-            timestamp: Utc::now().to_rfc3339(),
+/// Roles of a user and the permissions they add up to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRoles {
+    /// User ID
+    pub user_id: i32,
+    /// Admins hold every permission regardless of roles
+    pub is_admin: bool,
+    /// Roles granted to the user
+    pub roles: Vec<Role>,
+    /// Distinct permissions from all roles, sorted
+    pub permissions: Vec<String>,
+}
+
+/// Moderation state of reported content
+///
+/// Content starts pending when first reported. A moderator either approves
+/// it, keeping it up, or removes it; approved content can still be removed
+/// later, but removal is final.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationStatus {
+    /// Reported and awaiting review
+    Pending,
+    /// Reviewed and kept
+    Approved,
+    /// Reviewed and taken down
+    Removed,
+}
+
+impl ModerationStatus {
+    /// Every status
+    pub const ALL: [ModerationStatus; 3] = [Self::Pending, Self::Approved, Self::Removed];
+
+    /// Name of the status as stored and used in queries
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Removed => "removed",
         }
     }
+
+    /// Look up a status by name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|status| status.as_str() == name)
+    }
+
+    /// Whether a moderator may move content from this status to `next`
+    pub fn can_transition_to(self, next: ModerationStatus) -> bool {
+        matches!(
+            (self, next),
+            (Self::Pending, Self::Approved)
+                | (Self::Pending, Self::Removed)
+                | (Self::Approved, Self::Removed)
+        )
+    }
 }
 
-// ============================================================================
-// Email Verification and Password Reset Models
-// ============================================================================
+/// Reported content in the moderation queue
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationItem {
+    /// Queue item ID
+    pub id: i32,
+    /// Kind of content (e.g., "comment")
+    pub content_type: String,
+    /// ID of the content within its kind
+    pub content_id: i32,
+    /// User who wrote the content
+    pub author_id: i32,
+    /// Snapshot of the content when it was first reported
+    pub excerpt: String,
+    /// Moderation state
+    pub status: ModerationStatus,
+    /// Number of users who reported the content
+    pub report_count: i32,
+    /// Moderator who last resolved the item
+    pub resolved_by: Option<i32>,
+    /// Moderator's note on the resolution
+    pub resolution_note: Option<String>,
+    /// When the item was last resolved (RFC3339)
+    pub resolved_at: Option<String>,
+    /// When the content was first reported (RFC3339)
+    pub created_at: String,
+}
 
-/// Request body for forgot password
+/// A user's report against content
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct ForgotPasswordRequest {
-    /// User email address
-    pub email: String,
+pub struct ContentReport {
+    /// Reporting user
+    pub reporter_id: i32,
+    /// Why the user reported the content
+    pub reason: String,
+    /// When the report was made (RFC3339)
+    pub created_at: String,
 }
 
-/// Request body for password reset
+/// Moderation queue item with the reports against it
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct ResetPasswordRequest {
-    /// Password reset token
-    pub token: String,
-    /// New password
-    pub new_password: String,
+pub struct ModerationItemDetail {
+    /// The queue item
+    pub item: ModerationItem,
+    /// Reports, oldest first
+    pub reports: Vec<ContentReport>,
+}
+
+/// Request body for approving or removing reported content
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationDecision {
+    /// Note on the decision; for removals, also the strike reason the author sees
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// A strike against a user for content removed by a moderator
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStrike {
+    /// Strike ID
+    pub id: i32,
+    /// User the strike was issued to
+    pub user_id: i32,
+    /// Moderation item whose removal caused the strike
+    pub item_id: Option<i32>,
+    /// Why the content was removed
+    pub reason: String,
+    /// Moderator who issued the strike
+    pub issued_by: Option<i32>,
+    /// When the strike was issued (RFC3339)
+    pub created_at: String,
+}
+
+/// A user's moderation record
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationStanding {
+    /// User ID
+    pub user_id: i32,
+    /// Strikes still counting toward a mute
+    pub recent_strikes: i64,
+    /// End of the user's current mute (RFC3339), if muted
+    pub muted_until: Option<String>,
+    /// All strikes, newest first
+    pub strikes: Vec<UserStrike>,
+}
+
+/// Outcome of a moderation decision
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationResolution {
+    /// The item after the decision
+    pub item: ModerationItem,
+    /// Strike issued to the author (removals only)
+    pub strike: Option<UserStrike>,
+    /// End of the mute the strike triggered (RFC3339), if any
+    pub muted_until: Option<String>,
+}
+
+/// Request body for creating a tenant
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTenantRequest {
+    /// Unique identifier (lowercase letters, digits, and dashes)
+    pub slug: String,
+    /// Display name
+    pub name: String,
+    /// Hostnames that resolve to this tenant
+    #[serde(default)]
+    pub hostnames: Vec<String>,
 }
 
 /// Request body for email verification
@@ -354,6 +2867,124 @@ pub struct ResendVerificationRequest {
     pub email: String,
 }
 
+/// Request body for requesting an account reactivation email
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactivateAccountRequest {
+    /// Email address of the deactivated account
+    pub email: String,
+}
+
+/// Request body for confirming an account reactivation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmReactivationRequest {
+    /// Reactivation token from the email
+    pub token: String,
+}
+
+/// Anime detail fields that can be requested, as (JSON name, database column)
+///
+/// `episodes` has no column; episodes are stored in their own table.
+const DETAIL_FIELDS: &[(&str, Option<&str>)] = &[
+    ("title", Some("title")),
+    ("alternateTitles", Some("alternate_titles")),
+    ("poster", Some("poster")),
+    ("rating", Some("rating")),
+    ("trailerUrl", Some("trailer_url")),
+    ("status", Some("status")),
+    ("studio", Some("studio")),
+    ("releaseDate", Some("release_date")),
+    ("duration", Some("duration")),
+    ("season", Some("season")),
+    ("type", Some("type")),
+    ("totalEpisodes", Some("total_episodes")),
+    ("director", Some("director")),
+    ("casts", Some("casts")),
+    ("genres", Some("genres")),
+    ("synopsis", Some("synopsis")),
+    ("episodes", None),
+];
+
+/// A selection of anime detail fields, from the `fields` query parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetailFields {
+    /// Requested JSON names, in the order of [`AnimeDetail`]
+    names: Vec<&'static str>,
+}
+
+impl DetailFields {
+    /// Parse a comma-separated list of camelCase field names
+    ///
+    /// # Returns
+    /// * `Ok(DetailFields)` - The requested fields, without duplicates
+    /// * `Err(String)` - A field is unknown or no field was given
+    pub fn parse(param: &str) -> Result<Self, String> {
+        let requested: Vec<&str> = param
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        if requested.is_empty() {
+            return Err("fields must name at least one field".to_string());
+        }
+        if let Some(unknown) = requested
+            .iter()
+            .find(|name| !DETAIL_FIELDS.iter().any(|(field, _)| field == *name))
+        {
+            return Err(format!("Unknown field: {}", unknown));
+        }
+
+        let names = DETAIL_FIELDS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| requested.contains(name))
+            .collect();
+        Ok(Self { names })
+    }
+
+    /// JSON names of all selectable fields
+    pub fn available() -> Vec<&'static str> {
+        DETAIL_FIELDS.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Whether a field is selected, by JSON name
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&name)
+    }
+
+    /// Whether episodes are selected
+    pub fn includes_episodes(&self) -> bool {
+        self.contains("episodes")
+    }
+
+    /// Database columns of the selected fields
+    pub fn columns(&self) -> Vec<&'static str> {
+        DETAIL_FIELDS
+            .iter()
+            .filter(|(name, _)| self.contains(name))
+            .filter_map(|(_, column)| *column)
+            .collect()
+    }
+
+    /// Serialize a detail keeping only the selected fields
+    ///
+    /// `canonicalSlug` is kept whenever it is set.
+    pub fn project(&self, mut detail: AnimeDetail) -> serde_json::Value {
+        // Episodes dominate the size of a detail; don't serialize them just
+        // to drop them again
+        if !self.includes_episodes() {
+            detail.episodes = Vec::new();
+        }
+        let mut value = serde_json::to_value(&detail).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.retain(|key, _| key == "canonicalSlug" || self.contains(key));
+        }
+        value
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -365,6 +2996,8 @@ mod tests {
             anime_title: "Naruto Shippuden".to_string(),
             thumbnail: "https://example.com/naruto.jpg".to_string(),
             created_at: "2024-01-01T00:00:00Z".to_string(),
+            watched_count: 3,
+            total_episodes: 500,
         };
 
         let json = serde_json::to_string(&favorite).unwrap();
@@ -372,6 +3005,8 @@ mod tests {
         assert!(json.contains("\"animeTitle\""));
         assert!(json.contains("\"thumbnail\""));
         assert!(json.contains("\"createdAt\""));
+        assert!(json.contains("\"watchedCount\":3"));
+        assert!(json.contains("\"totalEpisodes\":500"));
     }
 
     #[test]
@@ -381,6 +3016,8 @@ mod tests {
             anime_title: "One Piece".to_string(),
             thumbnail: "https://example.com/onepiece.jpg".to_string(),
             created_at: "2024-01-01T00:00:00Z".to_string(),
+            watched_count: 0,
+            total_episodes: 1100,
         };
 
         let json = serde_json::to_string(&subscription).unwrap();
@@ -449,6 +3086,8 @@ mod tests {
             email: "test@example.com".to_string(),
             password: "password123".to_string(),
             name: Some("Test User".to_string()),
+            language: None,
+            captcha_token: None,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -487,14 +3126,127 @@ mod tests {
         assert!(json.contains("\"success\":true"));
         assert!(json.contains("\"data\""));
         assert!(json.contains("\"timestamp\""));
+        assert!(!json.contains("\"meta\""));
+
+        let json = serde_json::to_value(ApiResponse::timed_out(
+            vec!["item1"],
+            Duration::from_millis(8000),
+        ))
+        .unwrap();
+        assert_eq!(json["meta"]["timedOut"], true);
+        assert_eq!(json["meta"]["source"], "stale");
+        assert_eq!(json["meta"]["fetchDurationMs"], 8000);
+        assert!(json["meta"].get("upstreamStatus").is_none());
+
+        let json = serde_json::to_value(ApiResponse::live(
+            vec!["item1"],
+            Duration::from_millis(250),
+            200,
+        ))
+        .unwrap();
+        assert_eq!(json["meta"]["source"], "live");
+        assert_eq!(json["meta"]["fetchDurationMs"], 250);
+        assert_eq!(json["meta"]["upstreamStatus"], 200);
+        assert_eq!(json["meta"]["timedOut"], false);
+
+        let json = serde_json::to_value(ApiResponse::cached(vec!["item1"])).unwrap();
+        assert_eq!(json["meta"]["source"], "cache");
+        assert!(json["meta"].get("fetchDurationMs").is_none());
+    }
+
+    #[test]
+    fn test_integrity_report_missing_anime() {
+        let group = |parent: &str| OrphanGroup {
+            parent: parent.to_string(),
+            count: 1,
+        };
+        let mut report = IntegrityReport {
+            checked_at: "2024-12-27T10:00:00+00:00".to_string(),
+            orphaned_episodes: vec![],
+            orphaned_video_sources: vec![],
+            dangling_favorites: vec![],
+            repairs_queued: vec![],
+        };
+        assert!(report.is_clean());
+        assert!(report.missing_anime().is_empty());
+
+        report.orphaned_episodes = vec![group("naruto"), group("bleach")];
+        report.orphaned_video_sources = vec![group("https://example.com/ep-1/")];
+        report.dangling_favorites = vec![group("bleach"), group("one-piece")];
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.missing_anime(),
+            vec!["naruto", "bleach", "one-piece"]
+        );
+    }
+
+    #[test]
+    fn test_maintenance_action_names() {
+        for action in MaintenanceAction::ALL {
+            assert_eq!(MaintenanceAction::parse(action.as_str()), Some(action));
+            assert_eq!(
+                serde_json::to_value(action).unwrap(),
+                serde_json::json!(action.as_str())
+            );
+        }
+        assert_eq!(MaintenanceAction::parse("drop-everything"), None);
+
+        let result = MaintenanceResult::new(
+            MaintenanceAction::DeleteOrphans,
+            vec![
+                TableRowCount {
+                    table: "episodes".to_string(),
+                    rows: 2,
+                },
+                TableRowCount {
+                    table: "video_sources".to_string(),
+                    rows: 5,
+                },
+            ],
+        );
+        assert_eq!(result.affected_rows, 7);
+    }
+
+    #[test]
+    fn test_feature_names() {
+        for feature in Feature::ALL {
+            assert_eq!(Feature::parse(feature.as_str()), Some(feature));
+            assert_eq!(
+                serde_json::to_value(feature).unwrap(),
+                serde_json::json!(feature.as_str())
+            );
+        }
+        assert_eq!(Feature::parse("search_upstream"), None);
+        assert_eq!(
+            serde_json::to_value(ErrorCode::FeatureDisabled).unwrap(),
+            serde_json::json!("FEATURE_DISABLED")
+        );
+    }
+
+    #[test]
+    fn test_moderation_status_transitions() {
+        use ModerationStatus::*;
+
+        for status in ModerationStatus::ALL {
+            assert_eq!(ModerationStatus::parse(status.as_str()), Some(status));
+            assert!(!status.can_transition_to(status));
+            assert!(!status.can_transition_to(Pending));
+        }
+        assert!(Pending.can_transition_to(Approved));
+        assert!(Pending.can_transition_to(Removed));
+        assert!(Approved.can_transition_to(Removed));
+        assert!(!Removed.can_transition_to(Approved));
+        assert_eq!(ModerationStatus::parse("hidden"), None);
     }
 
     #[test]
     fn test_api_error_serialization() {
-        let error = ApiError::new("Something went wrong");
+        let error = ApiError::new(ErrorCode::InternalError, "Something went wrong");
 
         let json = serde_json::to_string(&error).unwrap();
         assert!(json.contains("\"success\":false"));
+        assert!(json.contains("\"code\":\"INTERNAL_ERROR\""));
+        assert!(!json.contains("\"details\""));
         assert!(json.contains("\"error\":\"Something went wrong\""));
         assert!(json.contains("\"timestamp\""));
     }
@@ -508,6 +3260,8 @@ mod tests {
                 anime_type: "TV".to_string(),
                 status: "Ongoing".to_string(),
                 order: "latest".to_string(),
+                tag: None,
+                include_adult: false,
             },
         };
 
@@ -553,6 +3307,7 @@ mod tests {
             status: "Ongoing".to_string(),
             anime_type: "TV".to_string(),
             episode_status: "1000+ Episodes".to_string(),
+            popularity_score: 12.5,
             created_at: "2024-01-01T00:00:00Z".to_string(),
             updated_at: "2024-01-02T00:00:00Z".to_string(),
         };
@@ -566,13 +3321,24 @@ mod tests {
         assert!(json.contains("\"status\""));
         assert!(json.contains("\"type\":\"TV\""));
         assert!(json.contains("\"episodeStatus\""));
+        assert!(json.contains("\"popularityScore\":12.5"));
         assert!(json.contains("\"createdAt\""));
         assert!(json.contains("\"updatedAt\""));
     }
 
     #[test]
     fn test_crawler_response_serialization() {
-        let response = CrawlerResponse::new(100, 500, 2000, 5, vec!["Error on page 3".to_string()]);
+        let response = CrawlerResponse::new(
+            100,
+            500,
+            2000,
+            5,
+            vec![CrawlError::for_page(
+                "fetch list page: timeout",
+                3,
+                "Error on page 3",
+            )],
+        );
 
         let json = serde_json::to_string(&response).unwrap();
         assert!(json.contains("\"success\":true"));
@@ -580,10 +3346,53 @@ mod tests {
         assert!(json.contains("\"totalEpisodes\":500"));
         assert!(json.contains("\"totalVideoSources\":2000"));
         assert!(json.contains("\"pagesProcessed\":5"));
-        assert!(json.contains("\"errors\""));
+        assert!(json.contains("\"errors\":[{\"kind\":\"fetch list page: timeout\""));
+        assert!(json.contains("\"totalErrors\":1"));
+        assert!(json.contains("\"errorsTruncated\":false"));
         assert!(json.contains("\"timestamp\""));
     }
 
+    #[test]
+    fn test_crawler_data_record_error() {
+        let mut data = CrawlerData::default();
+        for _ in 0..5000 {
+            data.record_error(CrawlError::for_slug(
+                "fetch episode: timeout",
+                "naruto-episode-1",
+                "No response within 30000 ms",
+            ));
+        }
+        data.record_error(CrawlError::for_page(
+            "fetch list page: HTTP 500",
+            2,
+            "Server returned status 500",
+        ));
+        assert_eq!(data.errors.len(), 2);
+        assert_eq!(data.errors[0].count, 5000);
+        assert_eq!(data.errors[1].page, Some(2));
+        assert_eq!(data.total_errors, 5001);
+        assert!(!data.errors_truncated);
+
+        for i in 0..MAX_CRAWL_ERRORS {
+            data.record_error(CrawlError::for_slug(
+                "fetch anime: HTTP 404",
+                &format!("anime-{}", i),
+                "Server returned status 404",
+            ));
+        }
+        assert_eq!(data.errors.len(), MAX_CRAWL_ERRORS);
+        assert_eq!(data.total_errors, 5001 + MAX_CRAWL_ERRORS as i32);
+        assert!(data.errors_truncated);
+
+        // Repeats of listed errors are still counted on their entry
+        data.record_error(CrawlError::for_page(
+            "fetch list page: HTTP 500",
+            2,
+            "Server returned status 500",
+        ));
+        assert_eq!(data.errors[1].count, 2);
+    }
+
     #[test]
     fn test_auth_response_serialization() {
         let response = AuthResponse {
@@ -619,10 +3428,15 @@ mod tests {
 
     #[test]
     fn test_api_error_new() {
-        let error = ApiError::new("test error");
+        let error = ApiError::new(ErrorCode::ValidationFailed, "test error");
         assert!(!error.success);
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
         assert_eq!(error.error, "test error");
         assert!(!error.timestamp.is_empty());
+
+        let error = error.with_details(serde_json::json!({"field": "email"}));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["details"]["field"], "email");
     }
 
     #[test]
@@ -637,6 +3451,298 @@ mod tests {
         assert!(!response.timestamp.is_empty());
     }
 
+    fn diff_test_detail() -> AnimeDetail {
+        AnimeDetail {
+            title: "Naruto".to_string(),
+            alternate_titles: String::new(),
+            poster: String::new(),
+            rating: "8.0".to_string(),
+            trailer_url: String::new(),
+            status: "Ongoing".to_string(),
+            studio: String::new(),
+            release_date: String::new(),
+            duration: String::new(),
+            season: String::new(),
+            anime_type: "TV".to_string(),
+            total_episodes: String::new(),
+            director: String::new(),
+            casts: vec![],
+            genres: vec!["Action".to_string()],
+            synopsis: String::new(),
+            episodes: vec![
+                Episode {
+                    slug: "ep-1".to_string(),
+                    number: "1".to_string(),
+                    title: "Episode 1".to_string(),
+                    url: "https://example.com/ep-1".to_string(),
+                    release_date: String::new(),
+                    released_at_iso: None,
+                },
+                Episode {
+                    slug: "ep-2".to_string(),
+                    number: "2".to_string(),
+                    title: "Episode 2".to_string(),
+                    url: "https://example.com/ep-2".to_string(),
+                    release_date: String::new(),
+                    released_at_iso: None,
+                },
+            ],
+            canonical_slug: None,
+            age_rating: None,
+        }
+    }
+
+    #[test]
+    fn test_anime_diff_fields_and_episodes() {
+        let stored = diff_test_detail();
+        let mut scraped = diff_test_detail();
+        scraped.rating = "8.2".to_string();
+        scraped.genres.push("Comedy".to_string());
+        scraped.episodes[0].title = "Enter Naruto".to_string();
+        scraped.episodes.remove(1);
+        scraped.episodes.push(Episode {
+            slug: "ep-3".to_string(),
+            number: "3".to_string(),
+            title: "Episode 3".to_string(),
+            url: "https://example.com/ep-3".to_string(),
+            release_date: String::new(),
+            released_at_iso: None,
+        });
+
+        let diff = AnimeDiff::between("naruto", Some(&stored), scraped);
+        assert!(diff.changed);
+        let fields: Vec<&str> = diff.fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, vec!["genres", "rating"]);
+        assert_eq!(diff.fields[1].stored, serde_json::json!("8.0"));
+        assert_eq!(diff.fields[1].scraped, serde_json::json!("8.2"));
+        assert_eq!(diff.episodes_added[0].number, "3");
+        assert_eq!(diff.episodes_removed[0].number, "2");
+        assert_eq!(diff.episodes_changed[0].fields[0].field, "title");
+    }
+
+    #[test]
+    fn test_anime_diff_identical_and_missing() {
+        let diff = AnimeDiff::between("naruto", Some(&diff_test_detail()), diff_test_detail());
+        assert!(!diff.changed);
+        assert!(diff.fields.is_empty());
+
+        let diff = AnimeDiff::between("naruto", None, diff_test_detail());
+        assert!(!diff.stored_found);
+        assert!(diff.changed);
+        assert_eq!(diff.episodes_added.len(), 2);
+    }
+
+    fn timeline_episode(number: &str, seen: &str) -> (TimelineEpisode, DateTime<Utc>) {
+        (
+            TimelineEpisode {
+                number: number.to_string(),
+                title: format!("Episode {}", number),
+                url: format!("https://example.com/ep-{}", number),
+                first_seen_at: seen.to_string(),
+            },
+            DateTime::parse_from_rfc3339(seen)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_timeline_weekly_release_and_hiatus() {
+        // Initial import of episodes 1-2, then weekly releases
+        let episodes = vec![
+            timeline_episode("4", "2024-01-15T00:00:00Z"),
+            timeline_episode("1", "2024-01-01T00:00:00Z"),
+            timeline_episode("2", "2024-01-01T00:00:05Z"),
+            timeline_episode("3", "2024-01-08T00:00:00Z"),
+        ];
+
+        let timeline =
+            AnimeTimeline::build("a", "Ongoing", episodes.clone(), at("2024-01-20T00:00:00Z"));
+        assert_eq!(timeline.episodes[0].number, "1");
+        assert_eq!(timeline.episodes[3].number, "4");
+        assert_eq!(timeline.median_gap_hours, Some(168.0));
+        assert_eq!(
+            timeline.next_expected_at.as_deref(),
+            Some("2024-01-22T00:00:00+00:00")
+        );
+        assert!(!timeline.likely_hiatus);
+
+        let late =
+            AnimeTimeline::build("a", "Ongoing", episodes.clone(), at("2024-02-01T00:00:00Z"));
+        assert!(late.likely_hiatus);
+
+        let completed =
+            AnimeTimeline::build("a", "Completed", episodes, at("2024-02-01T00:00:00Z"));
+        assert!(!completed.likely_hiatus);
+        assert_eq!(completed.next_expected_at, None);
+    }
+
+    #[test]
+    fn test_timeline_needs_two_gaps() {
+        let episodes = vec![
+            timeline_episode("1", "2024-01-01T00:00:00Z"),
+            timeline_episode("2", "2024-01-08T00:00:00Z"),
+        ];
+        let timeline = AnimeTimeline::build("a", "Ongoing", episodes, at("2025-01-01T00:00:00Z"));
+        assert_eq!(timeline.median_gap_hours, None);
+        assert!(!timeline.likely_hiatus);
+        assert_eq!(
+            timeline.last_release_at.as_deref(),
+            Some("2024-01-08T00:00:00+00:00")
+        );
+
+        let empty = AnimeTimeline::build("a", "Ongoing", vec![], at("2025-01-01T00:00:00Z"));
+        assert!(empty.episodes.is_empty());
+        assert_eq!(empty.last_release_at, None);
+    }
+
+    #[test]
+    fn test_apply_preferred_quality() {
+        let source = |quality: &str| VideoSource {
+            server: "SOKUJA".to_string(),
+            quality: quality.to_string(),
+            url: format!("https://example.com/{}.mp4", quality),
+            embed: None,
+        };
+        let detail = EpisodeDetail {
+            title: "Episode 1".to_string(),
+            default_video: "https://example.com/480p.mp4".to_string(),
+            sources: vec![source("480p"), source("720p"), source("1080p")],
+            subtitles: Vec::new(),
+            comment_count: None,
+        };
+
+        let preferred = apply_preferred_quality(detail.clone(), "720P");
+        assert_eq!(preferred.default_video, "https://example.com/720p.mp4");
+        assert_eq!(preferred.sources[0].quality, "720p");
+        assert_eq!(preferred.sources[1].quality, "480p");
+
+        let unchanged = apply_preferred_quality(detail.clone(), "4k");
+        assert_eq!(unchanged, detail);
+    }
+
+    #[test]
+    fn test_cast_content_type() {
+        assert_eq!(cast_content_type("https://x.com/ep.mp4"), "video/mp4");
+        assert_eq!(
+            cast_content_type("https://x.com/hls/MASTER.M3U8?token=a.b"),
+            "application/x-mpegURL"
+        );
+        assert_eq!(
+            cast_content_type("https://x.com/ep.mpd"),
+            "application/dash+xml"
+        );
+        assert_eq!(cast_content_type("https://x.com/ep.webm"), "video/webm");
+        assert_eq!(cast_content_type("https://x.com/stream/123"), "video/mp4");
+    }
+
+    #[test]
+    fn test_cast_metadata_build() {
+        let source = VideoSource {
+            server: "SOKUJA".to_string(),
+            quality: "720p".to_string(),
+            url: "https://example.com/720p.mp4".to_string(),
+            embed: None,
+        };
+        let episode = Episode {
+            slug: "frieren-episode-28".to_string(),
+            number: "28".to_string(),
+            title: "Frieren Episode 28".to_string(),
+            url: "https://example.com/frieren-episode-28/".to_string(),
+            release_date: String::new(),
+            released_at_iso: None,
+        };
+        let subtitles = vec![
+            SubtitleTrack {
+                url: "https://example.com/subs/28.id.srt?a=1&b=2".to_string(),
+                language: "id".to_string(),
+                label: String::new(),
+                default: true,
+            },
+            SubtitleTrack {
+                url: "https://example.com/subs/28.vtt".to_string(),
+                language: String::new(),
+                label: String::new(),
+                default: false,
+            },
+        ];
+
+        let cast = CastMetadata::build(
+            &source,
+            &episode,
+            "Sousou no Frieren",
+            "https://example.com/poster.jpg",
+            &subtitles,
+            "https://api.example.com/api/subtitles/proxy",
+        );
+        assert_eq!(cast.content_url, source.url);
+        assert_eq!(cast.content_type, "video/mp4");
+        assert_eq!(cast.metadata.metadata_type, CAST_METADATA_TYPE_TV_SHOW);
+        assert_eq!(cast.metadata.series_title, "Sousou no Frieren");
+        assert_eq!(cast.metadata.episode, Some(28));
+        assert_eq!(cast.metadata.images.len(), 1);
+        assert_eq!(
+            cast.tracks[0].track_content_id,
+            "https://api.example.com/api/subtitles/proxy?url=https%3A%2F%2Fexample.com%2Fsubs%2F28.id.srt%3Fa%3D1%26b%3D2"
+        );
+        assert_eq!(cast.tracks[0].name, "id");
+        assert_eq!(cast.tracks[1].track_id, 2);
+        assert_eq!(cast.tracks[1].name, "Subtitle 2");
+        assert_eq!(cast.active_track_ids, vec![1]);
+
+        let json = serde_json::to_value(&cast).unwrap();
+        assert_eq!(json["streamType"], "BUFFERED");
+        assert_eq!(json["tracks"][0]["type"], "TEXT");
+        assert_eq!(json["tracks"][0]["trackContentType"], "text/vtt");
+
+        let bare = CastMetadata::build(
+            &source,
+            &Episode {
+                number: "28.5".to_string(),
+                ..episode
+            },
+            "",
+            "",
+            &[],
+            "",
+        );
+        assert_eq!(bare.metadata.episode, None);
+        assert!(bare.metadata.images.is_empty());
+        assert!(bare.tracks.is_empty());
+    }
+
+    #[test]
+    fn test_job_record_serialization() {
+        let job = JobRecord {
+            id: 7,
+            queue: "crawler".to_string(),
+            job_type: "crawl".to_string(),
+            payload: serde_json::json!({}),
+            status: "dead".to_string(),
+            priority: 0,
+            attempts: 5,
+            max_attempts: 5,
+            last_error: Some("Failed to connect to server".to_string()),
+            result: None,
+            run_at: "2024-01-01T00:00:00Z".to_string(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:05:00Z".to_string(),
+        };
+
+        let json = serde_json::to_string(&job).unwrap();
+        assert!(json.contains("\"jobType\":\"crawl\""));
+        assert!(json.contains("\"maxAttempts\":5"));
+        assert!(json.contains("\"lastError\""));
+        assert!(json.contains("\"result\":null"));
+        assert!(json.contains("\"runAt\""));
+    }
+
     // Test deserialization
 
     #[test]
@@ -693,4 +3799,82 @@ mod tests {
         assert_eq!(request.password, "secret123");
         assert_eq!(request.name, None);
     }
+
+    #[test]
+    fn test_detail_fields_parse() {
+        let fields = DetailFields::parse("genres, title,episodes,title").unwrap();
+        assert_eq!(fields.names, vec!["title", "genres", "episodes"]);
+        assert!(fields.includes_episodes());
+        assert_eq!(fields.columns(), vec!["title", "genres"]);
+
+        let fields = DetailFields::parse("type,releaseDate").unwrap();
+        assert!(!fields.includes_episodes());
+        assert_eq!(fields.columns(), vec!["release_date", "type"]);
+
+        assert!(DetailFields::parse("").is_err());
+        assert!(DetailFields::parse(" , ").is_err());
+        assert_eq!(
+            DetailFields::parse("title,release_date"),
+            Err("Unknown field: release_date".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detail_fields_project() {
+        let detail = AnimeDetail {
+            title: "Frieren".to_string(),
+            anime_type: "TV".to_string(),
+            genres: vec!["Fantasy".to_string()],
+            episodes: vec![Episode {
+                slug: "frieren-1".to_string(),
+                number: "1".to_string(),
+                title: "Episode 1".to_string(),
+                url: "https://example.com/frieren-1/".to_string(),
+                release_date: String::new(),
+                released_at_iso: None,
+            }],
+            ..Default::default()
+        };
+
+        let value = DetailFields::parse("title,type")
+            .unwrap()
+            .project(detail.clone());
+        assert_eq!(value, serde_json::json!({"title": "Frieren", "type": "TV"}));
+
+        let value = DetailFields::parse("episodes")
+            .unwrap()
+            .project(detail.clone());
+        assert_eq!(value["episodes"].as_array().unwrap().len(), 1);
+        assert_eq!(value.as_object().unwrap().len(), 1);
+
+        let moved = AnimeDetail {
+            canonical_slug: Some("frieren-new".to_string()),
+            ..detail
+        };
+        let value = DetailFields::parse("title").unwrap().project(moved);
+        assert_eq!(
+            value,
+            serde_json::json!({"title": "Frieren", "canonicalSlug": "frieren-new"})
+        );
+    }
+
+    #[test]
+    fn test_leaderboard_window() {
+        assert_eq!(
+            LeaderboardWindow::parse("All-Time"),
+            Some(LeaderboardWindow::AllTime)
+        );
+        assert_eq!(LeaderboardWindow::parse("daily"), None);
+        assert_eq!(
+            serde_json::to_string(&LeaderboardWindow::AllTime).unwrap(),
+            "\"all-time\""
+        );
+
+        let now = Utc::now();
+        assert_eq!(
+            LeaderboardWindow::Weekly.since(now),
+            Some(now - chrono::Duration::days(7))
+        );
+        assert_eq!(LeaderboardWindow::AllTime.since(now), None);
+    }
 }
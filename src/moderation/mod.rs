@@ -0,0 +1,427 @@
+//! Moderation of user content
+//!
+//! Content types (e.g. comments) pass user reports to [`report`]. Each
+//! reported piece of content gets one item in the moderation queue, which
+//! moderators work through from the admin moderation endpoints by approving
+//! or removing items (see [`ModerationStatus`] for the allowed transitions).
+//!
+//! Removing content gives its author a strike. Once an author collects
+//! [`MUTE_THRESHOLD`] strikes within [`STRIKE_WINDOW_DAYS`], they are muted,
+//! and every further strike doubles the mute (see [`mute_duration`]).
+//! Content types check [`get_user_muted_until`](crate::db::get_user_muted_until)
+//! before accepting posts.
+//!
+//! Each step is announced to the registered [`ModerationHook`]s as a
+//! [`ModerationEvent`] once it's committed. Content types register a hook to
+//! hide what moderators remove. Authors are emailed about removals and mutes
+//! through the outbox (see [`notice`]), recorded in the same transaction as
+//! the removal, so a notice goes out exactly when the decision stands.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::db::{
+    approve_moderation_item, count_user_strikes_since, get_moderation_item, mute_user,
+    remove_moderation_item, report_content, RepositoryError, UnitOfWork,
+};
+use crate::jobs::outbox::{record_event, OutboxEvent};
+use crate::models::{ModerationItem, ModerationResolution, ModerationStatus, UserStrike};
+
+/// Strikes older than this no longer count toward a mute
+pub const STRIKE_WINDOW_DAYS: i64 = 90;
+
+/// Recent strikes that mute an author
+pub const MUTE_THRESHOLD: i64 = 3;
+
+/// Length of the first mute
+const BASE_MUTE_DAYS: i64 = 1;
+
+/// Longest mute a strike can trigger
+const MAX_MUTE_DAYS: i64 = 30;
+
+/// Characters of reported content kept in the queue
+const MAX_EXCERPT_CHARS: usize = 500;
+
+/// Moderation errors
+#[derive(Debug, Error)]
+pub enum ModerationError {
+    #[error("Moderation item not found")]
+    NotFound,
+
+    #[error("Cannot move {} content to {}", from.as_str(), to.as_str())]
+    InvalidTransition {
+        from: ModerationStatus,
+        to: ModerationStatus,
+    },
+
+    #[error("Moderation item was changed by another moderator")]
+    Changed,
+
+    #[error("Users can't report their own content")]
+    SelfReport,
+
+    #[error(transparent)]
+    Repository(#[from] RepositoryError),
+}
+
+/// Something that happened in moderation
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModerationEvent {
+    /// A user reported content
+    Reported {
+        item: ModerationItem,
+        reporter_id: i32,
+    },
+    /// A moderator kept reported content
+    Approved { item: ModerationItem },
+    /// A moderator removed content and its author got a strike
+    Removed {
+        item: ModerationItem,
+        strike: UserStrike,
+        recent_strikes: i64,
+    },
+    /// An author collected enough strikes to be muted
+    Muted {
+        user_id: i32,
+        until: DateTime<Utc>,
+        recent_strikes: i64,
+    },
+}
+
+/// Boxed future returned by [`ModerationHook::on_event`]
+pub type HookFuture<'a> = Pin<Box<dyn Future<Output = Result<(), RepositoryError>> + Send + 'a>>;
+
+/// Reacts to moderation events
+///
+/// A failing hook is logged and doesn't undo the moderation decision or stop
+/// later hooks.
+pub trait ModerationHook: Send + Sync {
+    /// Handle an event
+    fn on_event<'a>(&'a self, pool: &'a PgPool, event: &'a ModerationEvent) -> HookFuture<'a>;
+}
+
+/// Hooks notified of moderation events, in registration order
+#[derive(Clone, Default)]
+pub struct ModerationHooks {
+    hooks: Vec<Arc<dyn ModerationHook>>,
+}
+
+impl ModerationHooks {
+    /// Create a registry without hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a hook
+    pub fn with_hook(mut self, hook: Arc<dyn ModerationHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Notify every hook of an event
+    pub async fn dispatch(&self, pool: &PgPool, event: &ModerationEvent) {
+        for hook in &self.hooks {
+            if let Err(e) = hook.on_event(pool, event).await {
+                warn!("Moderation hook failed on {:?}: {}", event, e);
+            }
+        }
+    }
+}
+
+/// A user's report against content
+#[derive(Debug, Clone)]
+pub struct NewReport<'a> {
+    /// Kind of content (e.g., "comment")
+    pub content_type: &'a str,
+    /// ID of the content within its kind
+    pub content_id: i32,
+    /// User who wrote the content
+    pub author_id: i32,
+    /// The content as it currently reads
+    pub content: &'a str,
+    /// Reporting user
+    pub reporter_id: i32,
+    /// Why the user reported the content
+    pub reason: &'a str,
+}
+
+/// Start of the window in which strikes count toward a mute
+pub fn strike_window_start() -> DateTime<Utc> {
+    Utc::now() - Duration::days(STRIKE_WINDOW_DAYS)
+}
+
+/// How long an author with `recent_strikes` strikes is muted, if at all
+pub fn mute_duration(recent_strikes: i64) -> Option<Duration> {
+    if recent_strikes < MUTE_THRESHOLD {
+        return None;
+    }
+    let doublings = (recent_strikes - MUTE_THRESHOLD).min(8) as u32;
+    Some(Duration::days(
+        (BASE_MUTE_DAYS << doublings).min(MAX_MUTE_DAYS),
+    ))
+}
+
+/// Queue reported content for review
+///
+/// Reporting the same content twice is accepted but only counted once.
+///
+/// # Returns
+/// * `Ok(item)` - The content's queue item
+/// * `Err(ModerationError::SelfReport)` - The reporter wrote the content
+pub async fn report(
+    pool: &PgPool,
+    hooks: &ModerationHooks,
+    report: &NewReport<'_>,
+) -> Result<ModerationItem, ModerationError> {
+    if report.reporter_id == report.author_id {
+        return Err(ModerationError::SelfReport);
+    }
+
+    let excerpt: String = report.content.chars().take(MAX_EXCERPT_CHARS).collect();
+    let (item, reported) = report_content(
+        pool,
+        report.content_type,
+        report.content_id,
+        report.author_id,
+        &excerpt,
+        report.reporter_id,
+        report.reason.trim(),
+    )
+    .await?;
+
+    if reported {
+        hooks
+            .dispatch(
+                pool,
+                &ModerationEvent::Reported {
+                    item: item.clone(),
+                    reporter_id: report.reporter_id,
+                },
+            )
+            .await;
+    }
+    Ok(item)
+}
+
+/// Load a queue item, checking it can move to `to`
+async fn item_for_transition(
+    pool: &PgPool,
+    item_id: i32,
+    to: ModerationStatus,
+) -> Result<ModerationItem, ModerationError> {
+    let item = get_moderation_item(pool, item_id)
+        .await?
+        .ok_or(ModerationError::NotFound)?;
+    if !item.status.can_transition_to(to) {
+        return Err(ModerationError::InvalidTransition {
+            from: item.status,
+            to,
+        });
+    }
+    Ok(item)
+}
+
+/// Keep reported content
+pub async fn approve(
+    pool: &PgPool,
+    hooks: &ModerationHooks,
+    item_id: i32,
+    moderator_id: i32,
+    note: Option<&str>,
+) -> Result<ModerationResolution, ModerationError> {
+    let current = item_for_transition(pool, item_id, ModerationStatus::Approved).await?;
+    let item = approve_moderation_item(pool, item_id, current.status, moderator_id, note)
+        .await?
+        .ok_or(ModerationError::Changed)?;
+
+    info!(
+        "Moderator {} approved {} {}",
+        moderator_id, item.content_type, item.content_id
+    );
+    hooks
+        .dispatch(pool, &ModerationEvent::Approved { item: item.clone() })
+        .await;
+
+    Ok(ModerationResolution {
+        item,
+        strike: None,
+        muted_until: None,
+    })
+}
+
+/// Remove reported content, giving its author a strike and muting them if
+/// they've collected too many
+pub async fn remove(
+    pool: &PgPool,
+    hooks: &ModerationHooks,
+    item_id: i32,
+    moderator_id: i32,
+    reason: &str,
+) -> Result<ModerationResolution, ModerationError> {
+    let current = item_for_transition(pool, item_id, ModerationStatus::Removed).await?;
+    let mut uow = UnitOfWork::begin(pool).await?;
+    let (item, strike) = remove_moderation_item(
+        uow.conn(),
+        item_id,
+        current.status,
+        moderator_id,
+        Some(reason),
+    )
+    .await?
+    .ok_or(ModerationError::Changed)?;
+
+    let author_id = item.author_id;
+    let recent_strikes =
+        count_user_strikes_since(uow.conn(), author_id, strike_window_start()).await?;
+    let muted_until = match mute_duration(recent_strikes) {
+        Some(duration) => mute_user(uow.conn(), author_id, Utc::now() + duration).await?,
+        None => None,
+    };
+
+    let removed = ModerationEvent::Removed {
+        item: item.clone(),
+        strike: strike.clone(),
+        recent_strikes,
+    };
+    let muted = muted_until.map(|until| ModerationEvent::Muted {
+        user_id: author_id,
+        until,
+        recent_strikes,
+    });
+    for event in std::iter::once(&removed).chain(&muted) {
+        if let Some(notice) = notice(event) {
+            record_event(uow.conn(), &notice).await?;
+        }
+    }
+    uow.commit().await?;
+
+    info!(
+        "Moderator {} removed {} {}; author {} has {} recent strike(s)",
+        moderator_id, item.content_type, item.content_id, author_id, recent_strikes
+    );
+    hooks.dispatch(pool, &removed).await;
+    if let (Some(until), Some(muted)) = (muted_until, &muted) {
+        info!("Muted user {} until {}", author_id, until.to_rfc3339());
+        hooks.dispatch(pool, muted).await;
+    }
+
+    Ok(ModerationResolution {
+        item,
+        strike: Some(strike),
+        muted_until: muted_until.map(|until| until.to_rfc3339()),
+    })
+}
+
+/// Notice telling a user about an event, or `None` if it isn't announced
+///
+/// Authors are told when their content is removed and when they are muted.
+/// The notice is recorded in the outbox together with the decision, and
+/// emailed to the user's verified address in their language regardless of
+/// their notification preferences.
+pub fn notice(event: &ModerationEvent) -> Option<OutboxEvent> {
+    match event {
+        ModerationEvent::Removed {
+            item,
+            strike,
+            recent_strikes,
+        } => Some(OutboxEvent::ContentRemoved {
+            user_id: item.author_id,
+            content_type: item.content_type.clone(),
+            reason: strike.reason.clone(),
+            strike_count: *recent_strikes,
+        }),
+        ModerationEvent::Muted {
+            user_id,
+            until,
+            recent_strikes,
+        } => Some(OutboxEvent::AccountMuted {
+            user_id: *user_id,
+            muted_until: until.format("%Y-%m-%d %H:%M UTC").to_string(),
+            strike_count: *recent_strikes,
+        }),
+        ModerationEvent::Reported { .. } | ModerationEvent::Approved { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> ModerationItem {
+        ModerationItem {
+            id: 1,
+            content_type: "comment".to_string(),
+            content_id: 7,
+            author_id: 42,
+            excerpt: "spoilers!".to_string(),
+            status: ModerationStatus::Removed,
+            report_count: 2,
+            resolved_by: Some(1),
+            resolution_note: Some("Spoilers".to_string()),
+            resolved_at: None,
+            created_at: "2024-12-27T10:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mute_duration_doubles_and_caps() {
+        assert_eq!(mute_duration(0), None);
+        assert_eq!(mute_duration(MUTE_THRESHOLD - 1), None);
+        assert_eq!(mute_duration(MUTE_THRESHOLD), Some(Duration::days(1)));
+        assert_eq!(mute_duration(MUTE_THRESHOLD + 1), Some(Duration::days(2)));
+        assert_eq!(mute_duration(MUTE_THRESHOLD + 3), Some(Duration::days(8)));
+        assert_eq!(mute_duration(100), Some(Duration::days(MAX_MUTE_DAYS)));
+    }
+
+    #[test]
+    fn test_notices() {
+        let strike = UserStrike {
+            id: 3,
+            user_id: 42,
+            item_id: Some(1),
+            reason: "Spoilers".to_string(),
+            issued_by: Some(1),
+            created_at: "2024-12-27T10:00:00+00:00".to_string(),
+        };
+        let removed = ModerationEvent::Removed {
+            item: item(),
+            strike,
+            recent_strikes: 2,
+        };
+        assert_eq!(
+            notice(&removed),
+            Some(OutboxEvent::ContentRemoved {
+                user_id: 42,
+                content_type: "comment".to_string(),
+                reason: "Spoilers".to_string(),
+                strike_count: 2,
+            })
+        );
+
+        let until = DateTime::parse_from_rfc3339("2024-12-28T10:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let muted = ModerationEvent::Muted {
+            user_id: 42,
+            until,
+            recent_strikes: 3,
+        };
+        assert_eq!(
+            notice(&muted),
+            Some(OutboxEvent::AccountMuted {
+                user_id: 42,
+                muted_until: "2024-12-28 10:30 UTC".to_string(),
+                strike_count: 3,
+            })
+        );
+
+        let approved = ModerationEvent::Approved { item: item() };
+        assert_eq!(notice(&approved), None);
+    }
+}
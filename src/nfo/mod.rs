@@ -0,0 +1,237 @@
+//! Kodi/Jellyfin NFO export
+//!
+//! Media centers read show and episode metadata from NFO files next to the
+//! video files: `tvshow.nfo` for a show, and one file per episode. These
+//! helpers build both from stored anime details. The site has no seasons,
+//! so every episode is in season 1.
+
+use chrono::NaiveDate;
+
+use crate::models::{AnimeDetail, Episode};
+use crate::parser::dates::parse_written_date;
+use crate::xml::XmlWriter;
+
+/// `uniqueid` type of IDs from this API; the ID is the slug
+pub const NFO_ID_TYPE: &str = "sokuja";
+
+/// Parse a release date as shown on the site
+///
+/// Understands "Sep 29, 2023", "Maret 22, 2024", "29 September 2023", and
+/// ISO dates; see [`parse_written_date`].
+pub fn parse_release_date(text: &str) -> Option<NaiveDate> {
+    parse_written_date(text).map(|date| date.date())
+}
+
+/// First four-digit year in a text ("Fall 2023")
+fn year(text: &str) -> Option<&str> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .find(|word| word.len() == 4)
+}
+
+/// Minutes from a duration like "24 min per ep"
+fn runtime_minutes(duration: &str) -> Option<u32> {
+    duration
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|word| !word.is_empty())?
+        .parse()
+        .ok()
+}
+
+/// Write a text element unless the text is empty
+fn optional(xml: &mut XmlWriter, name: &str, text: &str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        xml.element(name, &[], text);
+    }
+}
+
+/// Build `tvshow.nfo` for an anime
+///
+/// # Arguments
+/// * `slug` - Anime slug, written as the show's unique ID
+/// * `detail` - Stored anime detail
+pub fn tvshow_nfo(slug: &str, detail: &AnimeDetail) -> String {
+    let mut xml = XmlWriter::new();
+    xml.start("tvshow", &[]);
+
+    optional(&mut xml, "title", &detail.title);
+    optional(
+        &mut xml,
+        "originaltitle",
+        detail.alternate_titles.split(',').next().unwrap_or(""),
+    );
+    optional(&mut xml, "plot", &detail.synopsis);
+    if let Ok(rating) = detail.rating.trim().parse::<f32>() {
+        xml.start("ratings", &[]);
+        xml.start(
+            "rating",
+            &[("name", NFO_ID_TYPE), ("max", "10"), ("default", "true")],
+        );
+        xml.element("value", &[], &rating.to_string());
+        xml.end();
+        xml.end();
+    }
+
+    let premiered = parse_release_date(&detail.release_date);
+    let year = premiered
+        .map(|date| date.format("%Y").to_string())
+        .or_else(|| year(&detail.season).map(str::to_string));
+    optional(&mut xml, "year", year.as_deref().unwrap_or(""));
+    if let Some(date) = premiered {
+        xml.element("premiered", &[], &date.format("%Y-%m-%d").to_string());
+    }
+    if let Some(minutes) = runtime_minutes(&detail.duration) {
+        xml.element("runtime", &[], &minutes.to_string());
+    }
+    let status = match detail.status.trim().to_lowercase().as_str() {
+        "ongoing" => "Continuing",
+        "completed" => "Ended",
+        _ => "",
+    };
+    optional(&mut xml, "status", status);
+    optional(&mut xml, "studio", &detail.studio);
+    for genre in &detail.genres {
+        optional(&mut xml, "genre", genre);
+    }
+    optional(&mut xml, "director", &detail.director);
+    for (order, actor) in detail.casts.iter().enumerate() {
+        xml.start("actor", &[]);
+        xml.element("name", &[], actor);
+        xml.element("order", &[], &order.to_string());
+        xml.end();
+    }
+    if !detail.poster.is_empty() {
+        xml.element("thumb", &[("aspect", "poster")], &detail.poster);
+    }
+    optional(&mut xml, "trailer", &detail.trailer_url);
+    xml.element(
+        "uniqueid",
+        &[("type", NFO_ID_TYPE), ("default", "true")],
+        slug,
+    );
+
+    xml.finish()
+}
+
+/// Build the NFO of one episode of an anime
+///
+/// Episodes whose number isn't a plain number (e.g., "12.5") get no
+/// `episode` element, so media centers fall back to the file name.
+pub fn episode_nfo(detail: &AnimeDetail, episode: &Episode) -> String {
+    let mut xml = XmlWriter::new();
+    xml.start("episodedetails", &[]);
+
+    optional(&mut xml, "title", &episode.title);
+    optional(&mut xml, "showtitle", &detail.title);
+    xml.element("season", &[], "1");
+    if let Ok(number) = episode.number.trim().parse::<u32>() {
+        xml.element("episode", &[], &number.to_string());
+    }
+    if let Some(date) = parse_release_date(&episode.release_date) {
+        xml.element("aired", &[], &date.format("%Y-%m-%d").to_string());
+    }
+    if let Some(minutes) = runtime_minutes(&detail.duration) {
+        xml.element("runtime", &[], &minutes.to_string());
+    }
+    xml.element(
+        "uniqueid",
+        &[("type", NFO_ID_TYPE), ("default", "true")],
+        &episode.slug,
+    );
+
+    xml.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        assert_eq!(parse_release_date("Sep 29, 2023"), date(2023, 9, 29));
+        assert_eq!(parse_release_date("Maret 22, 2024"), date(2024, 3, 22));
+        assert_eq!(parse_release_date("Agustus 1, 2024"), date(2024, 8, 1));
+        assert_eq!(parse_release_date("29 September 2023"), date(2023, 9, 29));
+        assert_eq!(parse_release_date("2023-09-29"), date(2023, 9, 29));
+        assert_eq!(parse_release_date("Fall 2023"), None);
+        assert_eq!(parse_release_date("Februari 30, 2024"), None);
+        assert_eq!(parse_release_date(""), None);
+    }
+
+    fn frieren() -> AnimeDetail {
+        AnimeDetail {
+            title: "Sousou no Frieren".to_string(),
+            alternate_titles: "Frieren: Beyond Journey's End, 葬送のフリーレン".to_string(),
+            poster: "https://x3.sokuja.uk/poster.jpg?a=1&b=2".to_string(),
+            rating: "9.31".to_string(),
+            status: "Completed".to_string(),
+            studio: "Madhouse".to_string(),
+            release_date: "Sep 29, 2023".to_string(),
+            duration: "24 min per ep".to_string(),
+            season: "Fall 2023".to_string(),
+            genres: vec!["Adventure".to_string(), "Drama".to_string()],
+            casts: vec!["Atsumi Tanezaki".to_string()],
+            synopsis: "Setelah mengalahkan Raja Iblis <...>".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tvshow_nfo() {
+        let nfo = tvshow_nfo("sousou-no-frieren", &frieren());
+
+        assert!(nfo.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<tvshow>\n"
+        ));
+        assert!(nfo.contains("  <title>Sousou no Frieren</title>\n"));
+        assert!(nfo.contains("<originaltitle>Frieren: Beyond Journey&apos;s End</originaltitle>"));
+        assert!(nfo.contains("<plot>Setelah mengalahkan Raja Iblis &lt;...&gt;</plot>"));
+        assert!(nfo.contains("<value>9.31</value>"));
+        assert!(nfo.contains("<year>2023</year>"));
+        assert!(nfo.contains("<premiered>2023-09-29</premiered>"));
+        assert!(nfo.contains("<runtime>24</runtime>"));
+        assert!(nfo.contains("<status>Ended</status>"));
+        assert!(nfo.contains("<genre>Adventure</genre>\n  <genre>Drama</genre>"));
+        assert!(nfo.contains(
+            "<actor>\n    <name>Atsumi Tanezaki</name>\n    <order>0</order>\n  </actor>"
+        ));
+        assert!(nfo.contains(
+            "<thumb aspect=\"poster\">https://x3.sokuja.uk/poster.jpg?a=1&amp;b=2</thumb>"
+        ));
+        assert!(
+            nfo.contains("<uniqueid type=\"sokuja\" default=\"true\">sousou-no-frieren</uniqueid>")
+        );
+        assert!(!nfo.contains("<director>"));
+        assert!(!nfo.contains("<trailer>"));
+        assert!(nfo.ends_with("</tvshow>\n"));
+    }
+
+    #[test]
+    fn test_episode_nfo() {
+        let mut episode = Episode {
+            slug: "sousou-no-frieren-episode-28-subtitle-indonesia".to_string(),
+            number: "28".to_string(),
+            title: "Sousou no Frieren Episode 28 END".to_string(),
+            url: "https://x3.sokuja.uk/sousou-no-frieren-episode-28-subtitle-indonesia/"
+                .to_string(),
+            release_date: "Maret 22, 2024".to_string(),
+            released_at_iso: None,
+        };
+        let nfo = episode_nfo(&frieren(), &episode);
+
+        assert!(nfo.contains("<episodedetails>\n  <title>Sousou no Frieren Episode 28 END</title>"));
+        assert!(nfo.contains("<showtitle>Sousou no Frieren</showtitle>"));
+        assert!(nfo.contains("<season>1</season>\n  <episode>28</episode>"));
+        assert!(nfo.contains("<aired>2024-03-22</aired>"));
+        assert!(nfo.contains(
+            "<uniqueid type=\"sokuja\" default=\"true\">sousou-no-frieren-episode-28-subtitle-indonesia</uniqueid>"
+        ));
+
+        episode.number = "12.5".to_string();
+        assert!(!episode_nfo(&frieren(), &episode).contains("<episode>"));
+    }
+}
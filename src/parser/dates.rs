@@ -0,0 +1,251 @@
+//! Dates as the site writes them
+//!
+//! Episode lists and posts carry dates in Indonesian or English month
+//! formats ("Mar 23, 2017", "Maret 15, 2024", "12:00 Desember 25, 2022",
+//! "23 Maret 2017") and sometimes as relative phrases ("kemarin"). These
+//! helpers turn them into ISO 8601, leaving the original text alone. Times
+//! on the site are in Western Indonesian Time (UTC+7).
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+
+use super::relative_time::parse_relative_time;
+
+/// Offset of the times the site shows (WIB, UTC+7), in seconds
+pub const SITE_UTC_OFFSET_SECS: i32 = 7 * 3600;
+
+/// Month names and abbreviations, Indonesian and English
+const MONTHS: [(&str, u32); 41] = [
+    ("januari", 1),
+    ("january", 1),
+    ("jan", 1),
+    ("februari", 2),
+    ("pebruari", 2),
+    ("february", 2),
+    ("feb", 2),
+    ("maret", 3),
+    ("march", 3),
+    ("mar", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("mei", 5),
+    ("may", 5),
+    ("juni", 6),
+    ("june", 6),
+    ("jun", 6),
+    ("juli", 7),
+    ("july", 7),
+    ("jul", 7),
+    ("agustus", 8),
+    ("august", 8),
+    ("agu", 8),
+    ("agt", 8),
+    ("ags", 8),
+    ("aug", 8),
+    ("september", 9),
+    ("sept", 9),
+    ("sep", 9),
+    ("oktober", 10),
+    ("october", 10),
+    ("okt", 10),
+    ("oct", 10),
+    ("november", 11),
+    ("nopember", 11),
+    ("nov", 11),
+    ("nop", 11),
+    ("desember", 12),
+    ("december", 12),
+    ("des", 12),
+    ("dec", 12),
+];
+
+/// A date read from the site, with its time of day when it has one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiteDate {
+    Date(NaiveDate),
+    DateTime(DateTime<FixedOffset>),
+}
+
+impl SiteDate {
+    /// The day, in site time
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            SiteDate::Date(date) => *date,
+            SiteDate::DateTime(at) => at.date_naive(),
+        }
+    }
+
+    /// ISO 8601: "2017-03-23", or "2022-12-25T12:00:00+07:00" with a time
+    pub fn to_iso(&self) -> String {
+        match self {
+            SiteDate::Date(date) => date.format("%Y-%m-%d").to_string(),
+            SiteDate::DateTime(at) => at.to_rfc3339(),
+        }
+    }
+}
+
+/// Month number of a month name or abbreviation, in either language
+pub fn month_number(name: &str) -> Option<u32> {
+    let name = name.trim().trim_end_matches('.').to_lowercase();
+    MONTHS
+        .iter()
+        .find(|(month, _)| *month == name)
+        .map(|(_, number)| *number)
+}
+
+/// Parse a date as the site writes it
+///
+/// Like [`parse_written_date`], but text without a written date falls back
+/// to a relative phrase ("kemarin", "3 hari lalu", "hari ini"), giving the
+/// day it falls on.
+///
+/// # Arguments
+/// * `text` - Text holding the date
+/// * `anchor` - Time relative phrases count back from, usually the fetch time
+pub fn parse_site_date(text: &str, anchor: DateTime<Utc>) -> Option<SiteDate> {
+    parse_written_date(text).or_else(|| relative_day(text, anchor).map(SiteDate::Date))
+}
+
+/// Parse a written-out date
+///
+/// Reads month-name dates in either order ("Mar 23, 2017", "23 Maret
+/// 2017"), optionally with a time ("12:00 Desember 25, 2022"), as well as
+/// "2024-01-01" and "23/03/2017".
+pub fn parse_written_date(text: &str) -> Option<SiteDate> {
+    let offset = FixedOffset::east_opt(SITE_UTC_OFFSET_SECS)?;
+    let lower = text.to_lowercase();
+    let tokens: Vec<&str> = lower
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    let mut date = None;
+    let mut time = None;
+    let (mut day, mut month, mut year) = (None, None, None);
+    for token in &tokens {
+        if let Ok(parsed) = NaiveDate::parse_from_str(token, "%Y-%m-%d") {
+            date = Some(parsed);
+        } else if let Ok(parsed) = NaiveDate::parse_from_str(token, "%d/%m/%Y") {
+            date = Some(parsed);
+        } else if let Ok(parsed) = NaiveTime::parse_from_str(token, "%H:%M") {
+            time = Some(parsed);
+        } else if let Some(number) = month_number(token) {
+            month = Some(number);
+        } else if let Ok(number) = token.parse::<u32>() {
+            match token.len() {
+                4 => year = Some(number as i32),
+                1 | 2 => day = Some(number),
+                _ => {}
+            }
+        }
+    }
+
+    let date = date.or_else(|| NaiveDate::from_ymd_opt(year?, month?, day?))?;
+    Some(match time {
+        Some(time) => match date.and_time(time).and_local_timezone(offset).single() {
+            Some(at) => SiteDate::DateTime(at),
+            None => SiteDate::Date(date),
+        },
+        None => SiteDate::Date(date),
+    })
+}
+
+/// The date a site date falls on, in ISO 8601
+///
+/// See [`parse_site_date`]; `None` when the text holds no date.
+pub fn iso_date(text: &str, anchor: DateTime<Utc>) -> Option<String> {
+    parse_site_date(text, anchor).map(|date| date.to_iso())
+}
+
+/// Day, in site time, a relative phrase points at
+fn relative_day(text: &str, anchor: DateTime<Utc>) -> Option<NaiveDate> {
+    let offset = FixedOffset::east_opt(SITE_UTC_OFFSET_SECS)?;
+    let lower = text.to_lowercase();
+    let at = if lower.contains("hari ini") || lower.contains("today") {
+        anchor
+    } else {
+        parse_relative_time(&lower, anchor)?
+    };
+    Some(at.with_timezone(&offset).date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn anchor() -> DateTime<Utc> {
+        // 2024-04-05 20:00 WIB
+        Utc.with_ymd_and_hms(2024, 4, 5, 13, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_iso_date_month_names() {
+        assert_eq!(
+            iso_date("Mar 23, 2017", anchor()).as_deref(),
+            Some("2017-03-23")
+        );
+        assert_eq!(
+            iso_date("Maret 15, 2024", anchor()).as_deref(),
+            Some("2024-03-15")
+        );
+        assert_eq!(
+            iso_date("September 29, 2023", anchor()).as_deref(),
+            Some("2023-09-29")
+        );
+        assert_eq!(
+            iso_date("5 Agustus 2024", anchor()).as_deref(),
+            Some("2024-08-05")
+        );
+        assert_eq!(
+            iso_date("Okt. 1, 2022", anchor()).as_deref(),
+            Some("2022-10-01")
+        );
+        assert_eq!(
+            iso_date("Mei 31 2021", anchor()).as_deref(),
+            Some("2021-05-31")
+        );
+    }
+
+    #[test]
+    fn test_iso_date_with_time_and_numeric_formats() {
+        assert_eq!(
+            iso_date("12:00 Desember 25, 2022", anchor()).as_deref(),
+            Some("2022-12-25T12:00:00+07:00")
+        );
+        assert_eq!(
+            iso_date("2024-01-01", anchor()).as_deref(),
+            Some("2024-01-01")
+        );
+        assert_eq!(
+            iso_date("23/03/2017", anchor()).as_deref(),
+            Some("2017-03-23")
+        );
+    }
+
+    #[test]
+    fn test_iso_date_relative_phrases() {
+        assert_eq!(iso_date("kemarin", anchor()).as_deref(), Some("2024-04-04"));
+        assert_eq!(
+            iso_date("3 hari yang lalu", anchor()).as_deref(),
+            Some("2024-04-02")
+        );
+        assert_eq!(
+            iso_date("Hari ini", anchor()).as_deref(),
+            Some("2024-04-05")
+        );
+        // 13:00 UTC is already the evening in WIB; 20:00 UTC the next day
+        let late = Utc.with_ymd_and_hms(2024, 4, 5, 20, 0, 0).unwrap();
+        assert_eq!(iso_date("baru saja", late).as_deref(), Some("2024-04-06"));
+    }
+
+    #[test]
+    fn test_iso_date_rejects_non_dates() {
+        assert_eq!(iso_date("", anchor()), None);
+        assert_eq!(iso_date("TBA", anchor()), None);
+        assert_eq!(iso_date("Feb 30, 2024", anchor()), None);
+        assert_eq!(iso_date("Maret 2024", anchor()), None);
+        assert_eq!(parse_written_date("kemarin"), None);
+        assert_eq!(month_number("Agu."), Some(8));
+        assert_eq!(month_number("Mars"), None);
+    }
+}
@@ -0,0 +1,412 @@
+//! Golden-output regression checks for the parsers
+//!
+//! Fixture pages live under `fixtures/parser/<kind>/<name>.html`, each next to
+//! the parsed output it must produce, `<name>.json`. Re-parsing the fixtures
+//! and diffing against the goldens catches selector changes that silently
+//! drop or alter fields.
+//!
+//! After an intended parser change, regenerate the goldens and review the
+//! resulting JSON diff before committing:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test golden
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::{
+    parse_anime_detail, parse_anime_list, parse_anime_updates, parse_completed_anime,
+    parse_episode_detail, parse_popular, parse_search_results, PopularWindow,
+};
+
+/// Environment variable that makes the golden test rewrite the goldens
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// Longest rendered JSON value in a mismatch line before it is shortened
+const MAX_RENDERED_VALUE_LEN: usize = 120;
+
+/// Kind of page a fixture is, named after its fixture directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PageKind {
+    /// Home page latest updates (`parse_anime_updates`)
+    Updates,
+    /// Completed anime list (`parse_completed_anime`)
+    Completed,
+    /// Search results (`parse_search_results`)
+    Search,
+    /// Paginated anime list (`parse_anime_list`)
+    AnimeList,
+    /// Anime detail page (`parse_anime_detail`)
+    AnimeDetail,
+    /// Episode page (`parse_episode_detail`)
+    Episode,
+    /// Home page popular widgets (`parse_popular`), by window
+    Popular,
+}
+
+impl PageKind {
+    /// All page kinds, in fixture check order
+    pub const ALL: [PageKind; 7] = [
+        PageKind::Updates,
+        PageKind::Completed,
+        PageKind::Search,
+        PageKind::AnimeList,
+        PageKind::AnimeDetail,
+        PageKind::Episode,
+        PageKind::Popular,
+    ];
+
+    /// Fixture directory name
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            PageKind::Updates => "updates",
+            PageKind::Completed => "completed",
+            PageKind::Search => "search",
+            PageKind::AnimeList => "anime_list",
+            PageKind::AnimeDetail => "anime_detail",
+            PageKind::Episode => "episode",
+            PageKind::Popular => "popular",
+        }
+    }
+
+    /// Parse a page with the matching parser, as JSON
+    pub fn parse(self, html: &str) -> Value {
+        let parsed = match self {
+            PageKind::Updates => serde_json::to_value(parse_anime_updates(html)),
+            PageKind::Completed => serde_json::to_value(parse_completed_anime(html)),
+            PageKind::Search => serde_json::to_value(parse_search_results(html)),
+            PageKind::AnimeList => serde_json::to_value(parse_anime_list(html)),
+            PageKind::AnimeDetail => serde_json::to_value(parse_anime_detail(html)),
+            PageKind::Episode => serde_json::to_value(parse_episode_detail(html)),
+            PageKind::Popular => PopularWindow::ALL
+                .into_iter()
+                .map(|window| {
+                    serde_json::to_value(parse_popular(html, window))
+                        .map(|entries| (window.as_str().to_string(), entries))
+                })
+                .collect::<Result<serde_json::Map<_, _>, _>>()
+                .map(Value::Object),
+        };
+        // Parser structs only hold strings and lists, so this cannot fail
+        parsed.unwrap_or(Value::Null)
+    }
+}
+
+/// One field where parsed output differs from the golden
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMismatch {
+    /// Path of the field, e.g. `$.episodes[2].title`
+    pub path: String,
+    /// Golden value, absent if the parser produced an unexpected field
+    pub expected: Option<Value>,
+    /// Parsed value, absent if the parser no longer produces the field
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for FieldMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let render = |value: &Option<Value>| match value {
+            Some(value) => {
+                let rendered = value.to_string();
+                if rendered.chars().count() > MAX_RENDERED_VALUE_LEN {
+                    let short: String = rendered.chars().take(MAX_RENDERED_VALUE_LEN).collect();
+                    format!("{}...", short)
+                } else {
+                    rendered
+                }
+            }
+            None => "(missing)".to_string(),
+        };
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.path,
+            render(&self.expected),
+            render(&self.actual)
+        )
+    }
+}
+
+/// Compare two JSON values field by field
+///
+/// Objects are compared by key and arrays by index, so a dropped list item
+/// shows up as a missing element rather than as every later element shifting.
+///
+/// # Returns
+/// All mismatching leaf paths, empty if the values are equal
+pub fn diff_json(expected: &Value, actual: &Value) -> Vec<FieldMismatch> {
+    let mut mismatches = Vec::new();
+    diff_at("$", Some(expected), Some(actual), &mut mismatches);
+    mismatches
+}
+
+fn diff_at(
+    path: &str,
+    expected: Option<&Value>,
+    actual: Option<&Value>,
+    mismatches: &mut Vec<FieldMismatch>,
+) {
+    match (expected, actual) {
+        (Some(Value::Object(expected)), Some(Value::Object(actual))) => {
+            for (key, value) in expected {
+                diff_at(
+                    &format!("{}.{}", path, key),
+                    Some(value),
+                    actual.get(key),
+                    mismatches,
+                );
+            }
+            for (key, value) in actual {
+                if !expected.contains_key(key) {
+                    diff_at(&format!("{}.{}", path, key), None, Some(value), mismatches);
+                }
+            }
+        }
+        (Some(Value::Array(expected)), Some(Value::Array(actual))) => {
+            for i in 0..expected.len().max(actual.len()) {
+                diff_at(
+                    &format!("{}[{}]", path, i),
+                    expected.get(i),
+                    actual.get(i),
+                    mismatches,
+                );
+            }
+        }
+        (expected, actual) if expected != actual => mismatches.push(FieldMismatch {
+            path: path.to_string(),
+            expected: expected.cloned(),
+            actual: actual.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+/// Outcome of checking one fixture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GoldenStatus {
+    /// Parsed output matches the golden
+    Passed,
+    /// Parsed output differs from the golden
+    Failed,
+    /// The fixture has no golden yet
+    Missing,
+    /// The golden was (re)written from the parsed output
+    Updated,
+}
+
+/// Result of checking one fixture page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GoldenResult {
+    /// Page kind, i.e. which parser was run
+    pub kind: PageKind,
+    /// Fixture name, the file name without extension
+    pub name: String,
+    /// Check outcome
+    pub status: GoldenStatus,
+    /// Differences from the golden (only for failed checks)
+    pub mismatches: Vec<FieldMismatch>,
+}
+
+/// Results of checking all fixture pages
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GoldenReport {
+    /// Fixtures matching their golden
+    pub passed: usize,
+    /// Fixtures differing from their golden
+    pub failed: usize,
+    /// Fixtures without a golden
+    pub missing: usize,
+    /// Goldens rewritten in update mode
+    pub updated: usize,
+    /// Per-fixture results
+    pub results: Vec<GoldenResult>,
+}
+
+impl GoldenReport {
+    /// Whether every fixture matched (or was updated)
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0 && self.missing == 0
+    }
+
+    /// Human-readable summary listing every failing field
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{} passed, {} failed, {} missing, {} updated\n",
+            self.passed, self.failed, self.missing, self.updated
+        );
+        for result in &self.results {
+            let fixture = format!("{}/{}", result.kind.dir_name(), result.name);
+            match result.status {
+                GoldenStatus::Failed => {
+                    out.push_str(&format!("\nFAILED {}\n", fixture));
+                    for mismatch in &result.mismatches {
+                        out.push_str(&format!("  {}\n", mismatch));
+                    }
+                }
+                GoldenStatus::Missing => {
+                    out.push_str(&format!(
+                        "\nMISSING {} (run with {}=1 to create it)\n",
+                        fixture, UPDATE_GOLDEN_ENV
+                    ));
+                }
+                GoldenStatus::Passed | GoldenStatus::Updated => {}
+            }
+        }
+        out
+    }
+
+    fn push(&mut self, result: GoldenResult) {
+        match result.status {
+            GoldenStatus::Passed => self.passed += 1,
+            GoldenStatus::Failed => self.failed += 1,
+            GoldenStatus::Missing => self.missing += 1,
+            GoldenStatus::Updated => self.updated += 1,
+        }
+        self.results.push(result);
+    }
+}
+
+/// Re-parse every fixture under `dir` and compare it with its golden
+///
+/// Kind directories that don't exist are skipped.
+///
+/// # Arguments
+/// * `dir` - Fixture root containing one directory per page kind
+/// * `update` - Write the parsed output as the new golden instead of failing
+///
+/// # Returns
+/// * `Ok(GoldenReport)` - Per-fixture results, sorted by kind and name
+/// * `Err(io::Error)` - A fixture could not be read, or a golden is not JSON
+pub fn check_fixtures(dir: &Path, update: bool) -> io::Result<GoldenReport> {
+    let mut report = GoldenReport::default();
+
+    for kind in PageKind::ALL {
+        let kind_dir = dir.join(kind.dir_name());
+        let entries = match std::fs::read_dir(&kind_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        let mut pages = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "html") {
+                pages.push(path);
+            }
+        }
+        pages.sort();
+
+        for page in pages {
+            let name = page
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+            let actual = kind.parse(&std::fs::read_to_string(&page)?);
+            let golden_path = page.with_extension("json");
+
+            let golden = match std::fs::read_to_string(&golden_path) {
+                Ok(contents) => Some(serde_json::from_str::<Value>(&contents).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: {}", golden_path.display(), e),
+                    )
+                })?),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+
+            let mismatches = golden
+                .as_ref()
+                .map(|golden| diff_json(golden, &actual))
+                .unwrap_or_default();
+
+            let status = if update && (golden.is_none() || !mismatches.is_empty()) {
+                let mut contents = serde_json::to_string_pretty(&actual)?;
+                contents.push('\n');
+                std::fs::write(&golden_path, contents)?;
+                GoldenStatus::Updated
+            } else if golden.is_none() {
+                GoldenStatus::Missing
+            } else if mismatches.is_empty() {
+                GoldenStatus::Passed
+            } else {
+                GoldenStatus::Failed
+            };
+
+            report.push(GoldenResult {
+                kind,
+                name,
+                mismatches: if status == GoldenStatus::Failed {
+                    mismatches
+                } else {
+                    Vec::new()
+                },
+                status,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_json_reports_field_paths() {
+        let expected = json!({
+            "title": "Frieren",
+            "genres": ["Adventure", "Fantasy"],
+            "episodes": [{"number": "28", "title": "END"}]
+        });
+        let actual = json!({
+            "title": "Frieren",
+            "genres": ["Adventure"],
+            "episodes": [{"number": "28", "title": ""}],
+            "extra": 1
+        });
+
+        let diffs = diff_json(&expected, &actual);
+        let rendered: Vec<String> = diffs.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "$.episodes[0].title: expected \"END\", got \"\"",
+                "$.genres[1]: expected \"Fantasy\", got (missing)",
+                "$.extra: expected (missing), got 1",
+            ]
+        );
+        assert!(diff_json(&expected, &expected).is_empty());
+    }
+
+    /// Re-parses `fixtures/parser` and fails with a field-level diff if any
+    /// parser output changed. Set UPDATE_GOLDEN=1 to accept the new output.
+    #[test]
+    fn test_parser_goldens() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/parser");
+        let update = std::env::var(UPDATE_GOLDEN_ENV).is_ok_and(|v| v == "1");
+
+        let report = check_fixtures(&dir, update).expect("Failed to check parser fixtures");
+        assert!(!report.results.is_empty(), "No parser fixtures found");
+        assert!(
+            report.is_ok(),
+            "Parser output differs from goldens:\n{}",
+            report.render()
+        );
+    }
+}
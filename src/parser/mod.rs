@@ -3,11 +3,27 @@
 //! This module provides parsing functionality to extract anime data
 //! from the HTML content fetched from sokuja.uk.
 
+pub mod dates;
+pub mod golden;
+pub mod relative_time;
+pub mod selectors;
+
+pub use dates::{iso_date, parse_site_date, parse_written_date, SiteDate};
+pub use relative_time::parse_relative_time;
+pub use selectors::{init, ProfileError, SelectorError};
+
+use selectors::EpisodeListSelectors;
+
+use std::collections::HashSet;
+
 use base64::{engine::general_purpose::STANDARD, Engine};
-use scraper::{Html, Selector};
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::constants::embed_hosts;
+
 /// Extract slug from a URL
 ///
 /// Takes a URL like "https://x3.sokuja.uk/anime/one-piece-subtitle-indonesia/"
@@ -20,6 +36,125 @@ fn extract_slug_from_url(url: &str) -> String {
         .to_string()
 }
 
+/// Text content of an element with surrounding whitespace removed
+///
+/// Trims in place, so only the collected text is allocated.
+fn element_text(el: ElementRef<'_>) -> String {
+    let mut text: String = el.text().collect();
+    text.truncate(text.trim_end().len());
+    let leading = text.len() - text.trim_start().len();
+    text.drain(..leading);
+    text
+}
+
+/// Attribute of an element, or an empty string if it is missing
+fn attr(el: ElementRef<'_>, name: &str) -> String {
+    el.value().attr(name).unwrap_or_default().to_string()
+}
+
+/// Trimmed text of the first element below `el` matching `selector`
+fn select_text(el: ElementRef<'_>, selector: &Selector) -> String {
+    el.select(selector)
+        .next()
+        .map(element_text)
+        .unwrap_or_default()
+}
+
+/// Attribute of the first element below `el` matching `selector`
+fn select_attr(el: ElementRef<'_>, selector: &Selector, name: &str) -> String {
+    el.select(selector)
+        .next()
+        .map(|el| attr(el, name))
+        .unwrap_or_default()
+}
+
+/// Image URL of the first matching element: `src`, falling back to `data-src`
+/// for lazy-loaded images
+fn select_image(el: ElementRef<'_>, selector: &Selector) -> String {
+    el.select(selector)
+        .next()
+        .and_then(|el| {
+            el.value()
+                .attr("src")
+                .or_else(|| el.value().attr("data-src"))
+        })
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Candidate URLs of a srcset, smallest first
+///
+/// Candidates are ordered by their width (`300w`) or density (`2x`)
+/// descriptor; a candidate without one counts as `1x`. Malformed candidates
+/// are skipped.
+fn parse_srcset(srcset: &str) -> Vec<String> {
+    let mut candidates: Vec<(f32, &str)> = srcset
+        .split(',')
+        .filter_map(|candidate| {
+            let mut parts = candidate.split_whitespace();
+            let url = parts.next()?;
+            let size = match parts.next() {
+                Some(descriptor) => descriptor
+                    .strip_suffix(['w', 'x'])
+                    .and_then(|size| size.parse().ok())?,
+                None => 1.0,
+            };
+            Some((size, url))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    candidates
+        .into_iter()
+        .map(|(_, url)| url.to_string())
+        .collect()
+}
+
+/// Size variants of the first matching image, from its `srcset`, falling
+/// back to `data-srcset` for lazy-loaded images
+///
+/// # Returns
+/// The smallest, middle and largest candidates, or `None` if the image has
+/// no srcset
+fn select_thumbnails(el: ElementRef<'_>, selector: &Selector) -> Option<Thumbnails> {
+    let image = el.select(selector).next()?;
+    let srcset = image
+        .value()
+        .attr("srcset")
+        .or_else(|| image.value().attr("data-srcset"))?;
+    let mut candidates = parse_srcset(srcset);
+    let large = candidates.pop()?;
+    let small = candidates.first().cloned().unwrap_or_else(|| large.clone());
+    let medium = candidates
+        .get(candidates.len() / 2)
+        .cloned()
+        .unwrap_or_else(|| large.clone());
+    Some(Thumbnails {
+        small,
+        medium,
+        large,
+    })
+}
+
+/// Everything after the first colon of a "Label: value" text, trimmed
+fn value_after_colon(text: &str) -> String {
+    text.split_once(':')
+        .map(|(_, value)| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Thumbnail size variants from an image's srcset
+///
+/// With fewer than three candidates, sizes share URLs.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct Thumbnails {
+    /// Smallest candidate
+    pub small: String,
+    /// Middle candidate
+    pub medium: String,
+    /// Largest candidate
+    pub large: String,
+}
+
 /// Represents an anime update from the latest updates section
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -32,6 +167,10 @@ pub struct AnimeUpdate {
     pub episode_url: String,
     /// Image from img.ts-post-image
     pub thumbnail: String,
+    /// Size variants from img.ts-post-image srcset or data-srcset; absent
+    /// when the image has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnails: Option<Thumbnails>,
     /// From div.epin (e.g., "24/24")
     pub episode_number: String,
     /// From span.type (TV, OVA, etc.)
@@ -45,6 +184,18 @@ pub struct AnimeUpdate {
     pub status: String,
     /// From div.sosev span (date/time info)
     pub release_info: String,
+    /// When the episode was posted (RFC3339), resolved from `release_info`
+    /// at fetch time; absent from parsed pages and when it can't be resolved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_at: Option<String>,
+    /// Number of users who liked the episode; filled in by the API, absent
+    /// from parsed pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub like_count: Option<i64>,
+    /// Whether the requesting user liked the episode; only set on
+    /// authenticated requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liked_by_me: Option<bool>,
 }
 
 /// Represents a search result entry from search results (article.bs)
@@ -59,6 +210,10 @@ pub struct SearchResult {
     pub url: String,
     /// From img.ts-post-image
     pub thumbnail: String,
+    /// Size variants from img.ts-post-image srcset or data-srcset; absent
+    /// when the image has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnails: Option<Thumbnails>,
     /// From div.status
     pub status: String,
     /// From div.typez (TV, ONA, Movie)
@@ -81,6 +236,10 @@ pub struct AnimeListItem {
     pub url: String,
     /// From img.ts-post-image
     pub thumbnail: String,
+    /// Size variants from img.ts-post-image srcset or data-srcset; absent
+    /// when the image has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnails: Option<Thumbnails>,
     /// From div.status
     pub status: String,
     /// From div.typez (TV, ONA, Movie)
@@ -90,6 +249,67 @@ pub struct AnimeListItem {
     pub episode_status: String,
 }
 
+/// Time window of a home page popular widget
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PopularWindow {
+    /// "Popular Today" row
+    Today,
+    /// Weekly tab of the popular sidebar
+    Week,
+    /// All-time tab of the popular sidebar
+    All,
+}
+
+impl PopularWindow {
+    /// Every window
+    pub const ALL: [PopularWindow; 3] = [
+        PopularWindow::Today,
+        PopularWindow::Week,
+        PopularWindow::All,
+    ];
+
+    /// Name used in queries and storage
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PopularWindow::Today => "today",
+            PopularWindow::Week => "week",
+            PopularWindow::All => "all",
+        }
+    }
+
+    /// Parse a window name, ignoring case
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|window| window.as_str().eq_ignore_ascii_case(value.trim()))
+    }
+}
+
+/// Represents a ranked entry of a home page popular widget
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PopularEntry {
+    /// From div.ctr, or the position in the widget when it shows no rank
+    pub rank: u32,
+    /// Extracted slug from URL (e.g., "one-piece-subtitle-indonesia")
+    pub slug: String,
+    /// From h2[itemprop="headline"] or the a.series heading
+    pub title: String,
+    /// From a[itemprop="url"] or a.series
+    pub url: String,
+    /// From img
+    pub thumbnail: String,
+    /// Size variants from the img srcset or data-srcset; absent when the
+    /// image has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnails: Option<Thumbnails>,
+    /// From genre links (sidebar lists only)
+    pub genres: Vec<String>,
+    /// From div.numscore or span.scr
+    pub rating: String,
+}
+
 /// Represents an episode entry from the episode list
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -104,6 +324,10 @@ pub struct Episode {
     pub url: String,
     /// From div.epl-date
     pub release_date: String,
+    /// `releaseDate` in ISO 8601 ("2017-03-23"); absent when it isn't a
+    /// date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_at_iso: Option<String>,
 }
 
 /// Represents a video source with server, quality, and URL
@@ -116,11 +340,45 @@ pub struct VideoSource {
     pub quality: String,
     /// Direct video URL from decoded base64
     pub url: String,
+    /// Embed metadata when the URL is an iframe player page rather than a
+    /// video file; absent for native video sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embed: Option<EmbedInfo>,
 }
 
-/// Represents episode detail with video sources
+/// An external subtitle track from a `<track>` element
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleTrack {
+    /// Subtitle file URL (.vtt or .srt); load it through /api/subtitles/proxy
+    pub url: String,
+    /// Language code from `srclang` (e.g., "id"); empty if not given
+    pub language: String,
+    /// Label shown in the player's track menu; empty if not given
+    pub label: String,
+    /// Whether the player enables the track by default
+    pub default: bool,
+}
+
+/// What frontends need to decide how to show an iframe video source
+///
+/// Known players with `requiresReferer: false` can be embedded in a
+/// sandboxed iframe; others may need to be opened from the source site.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
+pub struct EmbedInfo {
+    /// Host name of the player (e.g., "www.blogger.com")
+    pub host: String,
+    /// Whether the host is a known embeddable player
+    pub known_player: bool,
+    /// Whether the player only plays with the source site's referer; false
+    /// for unknown hosts
+    pub requires_referer: bool,
+}
+
+/// Represents episode detail with video sources
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct EpisodeDetail {
     /// Episode title
     pub title: String,
@@ -128,10 +386,83 @@ pub struct EpisodeDetail {
     pub default_video: String,
     /// All available video sources
     pub sources: Vec<VideoSource>,
+    /// Subtitle tracks from the default player and the mirrors
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleTrack>,
+    /// Number of visible comments; filled in by the API, absent from parsed pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_count: Option<i64>,
+}
+
+/// Genres that rate an anime adult, compared ignoring case
+const ADULT_GENRES: [&str; 2] = ["ecchi", "hentai"];
+
+/// Genres that rate an anime mature, compared ignoring case
+const MATURE_GENRES: [&str; 2] = ["horror", "gore"];
+
+/// Audience an anime is suitable for
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeRating {
+    /// Suitable for everyone
+    #[default]
+    General,
+    /// Disturbing content (e.g., horror), hidden like adult anime
+    Mature,
+    /// Hidden from lists and search unless adult content is included
+    Adult,
+}
+
+impl AgeRating {
+    /// Every rating
+    pub const ALL: [AgeRating; 3] = [AgeRating::General, AgeRating::Mature, AgeRating::Adult];
+
+    /// Name used in queries and storage
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AgeRating::General => "general",
+            AgeRating::Mature => "mature",
+            AgeRating::Adult => "adult",
+        }
+    }
+
+    /// Whether lists and search hide the anime unless adult content is
+    /// included
+    pub fn is_restricted(self) -> bool {
+        self != AgeRating::General
+    }
+
+    /// Parse a rating name, ignoring case
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|rating| rating.as_str().eq_ignore_ascii_case(value.trim()))
+    }
+
+    /// Derive the rating from scraped genres
+    ///
+    /// The migration creating `anime_details.age_rating` backfills it with
+    /// the same genre lists.
+    pub fn from_genres(genres: &[String]) -> Self {
+        let has_any = |names: &[&str]| {
+            genres.iter().any(|genre| {
+                names
+                    .iter()
+                    .any(|name| genre.trim().eq_ignore_ascii_case(name))
+            })
+        };
+        if has_any(&ADULT_GENRES) {
+            AgeRating::Adult
+        } else if has_any(&MATURE_GENRES) {
+            AgeRating::Mature
+        } else {
+            AgeRating::General
+        }
+    }
 }
 
 /// Represents full anime information from detail page
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AnimeDetail {
     /// From h1.entry-title
@@ -169,6 +500,14 @@ pub struct AnimeDetail {
     pub synopsis: String,
     /// From div.eplister
     pub episodes: Vec<Episode>,
+    /// Slug the anime is stored under when it was requested by an old one;
+    /// filled in by the API, absent from parsed pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_slug: Option<String>,
+    /// Age rating from the genres or an admin override; filled in by the
+    /// API, absent from parsed pages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_rating: Option<AgeRating>,
 }
 
 /// Represents a completed anime entry
@@ -183,6 +522,10 @@ pub struct CompletedAnime {
     pub url: String,
     /// From img.ts-post-image
     pub thumbnail: String,
+    /// Size variants from img.ts-post-image srcset or data-srcset; absent
+    /// when the image has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnails: Option<Thumbnails>,
     /// From div.typez (TV, Special, etc.)
     #[serde(rename = "type")]
     pub anime_type: String,
@@ -194,6 +537,10 @@ pub struct CompletedAnime {
     pub posted_by: String,
     /// From li containing "Dipos pada:"
     pub posted_at: String,
+    /// `postedAt` in ISO 8601 ("2022-12-25T12:00:00+07:00", or just the
+    /// date when no time is given); absent when it isn't a date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_at_iso: Option<String>,
     /// From series link text
     pub series_title: String,
     /// From series link href
@@ -206,7 +553,9 @@ pub struct CompletedAnime {
 
 /// Parse anime updates from the home page HTML
 ///
-/// Extracts data from elements matching `article.seventh`
+/// Extracts data from elements matching `article.seventh`. The home page
+/// repeats episodes across its sections, so only the first entry for each
+/// episode URL is kept.
 ///
 /// # Arguments
 /// * `html` - The HTML content to parse
@@ -214,108 +563,76 @@ pub struct CompletedAnime {
 /// # Returns
 /// A vector of `AnimeUpdate` structs
 pub fn parse_anime_updates(html: &str) -> Vec<AnimeUpdate> {
+    let Ok(selectors) = selectors::get() else {
+        return Vec::new();
+    };
+    let selectors = &selectors.updates;
     let document = Html::parse_document(html);
 
-    // Selector for article.seventh elements
-    let article_selector = Selector::parse("article.seventh").unwrap();
-
-    // Selectors for individual fields
-    let title_selector = Selector::parse("h2[itemprop=\"headline\"] a").unwrap();
-    let url_selector = Selector::parse("a[itemprop=\"url\"]").unwrap();
-    let thumbnail_selector = Selector::parse("img.ts-post-image").unwrap();
-    let episode_number_selector = Selector::parse("div.epin").unwrap();
-    let type_selector = Selector::parse("span.type").unwrap();
-    let series_selector = Selector::parse("div.sosev span a").unwrap();
-    let release_info_selector = Selector::parse("div.sosev span").unwrap();
-    let status_selector = Selector::parse("span.status").unwrap();
-
     let mut updates = Vec::new();
 
-    for article in document.select(&article_selector) {
-        let title = article
-            .select(&title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let episode_url = article
-            .select(&url_selector)
-            .next()
-            .and_then(|el| el.value().attr("href"))
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let thumbnail = article
-            .select(&thumbnail_selector)
-            .next()
-            .and_then(|el| {
-                el.value()
-                    .attr("src")
-                    .or_else(|| el.value().attr("data-src"))
-            })
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let episode_number = article
-            .select(&episode_number_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let anime_type = article
-            .select(&type_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
+    for article in document.select(&selectors.article) {
         let (series_title, series_url) = article
-            .select(&series_selector)
+            .select(&selectors.series)
             .next()
-            .map(|el| {
-                let text = el.text().collect::<String>().trim().to_string();
-                let href = el.value().attr("href").unwrap_or_default().to_string();
-                (text, href)
-            })
+            .map(|el| (element_text(el), attr(el, "href")))
             .unwrap_or_default();
 
-        // Extract release info from div.sosev span (the one containing date/time)
+        // Extract release info from div.sosev span (the one containing date/time,
+        // not the series link)
         let release_info = article
-            .select(&release_info_selector)
-            .filter_map(|el| {
-                let text = el.text().collect::<String>().trim().to_string();
-                // Look for spans that contain date/time info (not the series link)
-                if !text.is_empty() && el.select(&Selector::parse("a").unwrap()).next().is_none() {
-                    Some(text)
-                } else {
-                    None
-                }
-            })
-            .next()
-            .unwrap_or_default();
-
-        let status = article
-            .select(&status_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
+            .select(&selectors.release_info)
+            .filter(|el| el.select(&selectors.link).next().is_none())
+            .map(element_text)
+            .find(|text| !text.is_empty())
             .unwrap_or_default();
 
         updates.push(AnimeUpdate {
             slug: extract_slug_from_url(&series_url),
-            title,
-            episode_url,
-            thumbnail,
-            episode_number,
-            anime_type,
+            title: select_text(article, &selectors.title),
+            episode_url: select_attr(article, &selectors.url, "href"),
+            thumbnail: select_image(article, &selectors.thumbnail),
+            thumbnails: select_thumbnails(article, &selectors.thumbnail),
+            episode_number: select_text(article, &selectors.episode_number),
+            anime_type: select_text(article, &selectors.anime_type),
             series_title,
             series_url,
-            status,
+            status: select_text(article, &selectors.status),
             release_info,
+            released_at: None,
+            like_count: None,
+            liked_by_me: None,
         });
     }
 
+    dedup_updates(&mut updates);
     updates
 }
 
+/// Drop repeated episodes from a list of updates, keeping the first entry
+///
+/// Episode URLs are compared without trailing slashes. Updates without an
+/// episode URL are always kept.
+pub fn dedup_updates(updates: &mut Vec<AnimeUpdate>) {
+    let mut seen = HashSet::new();
+    updates.retain(|update| {
+        let key = update.episode_url.trim_end_matches('/');
+        key.is_empty() || seen.insert(key.to_string())
+    });
+}
+
+/// Fill in `released_at` from each update's relative `release_info`
+///
+/// # Arguments
+/// * `updates` - Updates parsed from the home page
+/// * `fetched_at` - When the page was fetched, the anchor of its relative times
+pub fn resolve_release_times(updates: &mut [AnimeUpdate], fetched_at: DateTime<Utc>) {
+    for update in updates {
+        update.released_at =
+            parse_relative_time(&update.release_info, fetched_at).map(|time| time.to_rfc3339());
+    }
+}
+
 /// Parse completed anime from the HTML
 ///
 /// Extracts data from elements matching `article.stylesix`
@@ -326,71 +643,21 @@ pub fn parse_anime_updates(html: &str) -> Vec<AnimeUpdate> {
 /// # Returns
 /// A vector of `CompletedAnime` structs
 pub fn parse_completed_anime(html: &str) -> Vec<CompletedAnime> {
+    let Ok(selectors) = selectors::get() else {
+        return Vec::new();
+    };
+    let selectors = &selectors.completed;
     let document = Html::parse_document(html);
 
-    // Selector for article.stylesix elements
-    let article_selector = Selector::parse("article.stylesix").unwrap();
-
-    // Selectors for individual fields
-    let title_selector = Selector::parse("h2[itemprop=\"headline\"] a").unwrap();
-    let url_selector = Selector::parse("a[itemprop=\"url\"]").unwrap();
-    let thumbnail_selector = Selector::parse("img.ts-post-image").unwrap();
-    let type_selector = Selector::parse("div.typez").unwrap();
-    let episode_count_selector = Selector::parse("span.epx").unwrap();
-    let rating_selector = Selector::parse("span.scr").unwrap();
-    let genre_selector = Selector::parse("a[rel=\"tag\"]").unwrap();
-    let li_selector = Selector::parse("li").unwrap();
-    let series_link_selector = Selector::parse("a").unwrap();
-
     let mut completed = Vec::new();
 
-    for article in document.select(&article_selector) {
-        let title = article
-            .select(&title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let url = article
-            .select(&url_selector)
-            .next()
-            .and_then(|el| el.value().attr("href"))
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let thumbnail = article
-            .select(&thumbnail_selector)
-            .next()
-            .and_then(|el| {
-                el.value()
-                    .attr("src")
-                    .or_else(|| el.value().attr("data-src"))
-            })
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let anime_type = article
-            .select(&type_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let episode_count = article
-            .select(&episode_count_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let rating = article
-            .select(&rating_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
+    for article in document.select(&selectors.article) {
+        let url = select_attr(article, &selectors.url, "href");
 
         // Extract genres from genre links
         let genres: Vec<String> = article
-            .select(&genre_selector)
-            .map(|el| el.text().collect::<String>().trim().to_string())
+            .select(&selectors.genre)
+            .map(element_text)
             .filter(|s| !s.is_empty())
             .collect();
 
@@ -401,7 +668,7 @@ pub fn parse_completed_anime(html: &str) -> Vec<CompletedAnime> {
         let mut series_title = String::new();
         let mut series_url = String::new();
 
-        for li in article.select(&li_selector) {
+        for li in article.select(&selectors.li) {
             let text = li.text().collect::<String>();
             let text_lower = text.to_lowercase();
 
@@ -421,39 +688,37 @@ pub fn parse_completed_anime(html: &str) -> Vec<CompletedAnime> {
                 || text_lower.contains("posted at:")
                 || text_lower.contains("posted on:")
             {
-                posted_at = text
-                    .split(':')
-                    .skip(1)
-                    .collect::<Vec<_>>()
-                    .join(":")
-                    .trim()
-                    .to_string();
+                posted_at = value_after_colon(&text);
             }
 
             // Check for series link in list items
-            if let Some(link) = li.select(&series_link_selector).next() {
-                let href = link.value().attr("href").unwrap_or_default();
-                if href.contains("/anime/") && series_url.is_empty() {
-                    series_title = link.text().collect::<String>().trim().to_string();
-                    series_url = href.to_string();
+            if series_url.is_empty() {
+                if let Some(link) = li.select(&selectors.series_link).next() {
+                    let href = link.value().attr("href").unwrap_or_default();
+                    if href.contains("/anime/") {
+                        series_title = element_text(link);
+                        series_url = href.to_string();
+                    }
                 }
             }
         }
 
         completed.push(CompletedAnime {
             slug: extract_slug_from_url(&url),
-            title,
+            title: select_text(article, &selectors.title),
             url,
-            thumbnail,
-            anime_type,
-            episode_count,
+            thumbnail: select_image(article, &selectors.thumbnail),
+            thumbnails: select_thumbnails(article, &selectors.thumbnail),
+            anime_type: select_text(article, &selectors.anime_type),
+            episode_count: select_text(article, &selectors.episode_count),
             status,
             posted_by,
+            released_at_iso: iso_date(&posted_at, Utc::now()),
             posted_at,
             series_title,
             series_url,
             genres,
-            rating,
+            rating: select_text(article, &selectors.rating),
         });
     }
 
@@ -470,85 +735,36 @@ pub fn parse_completed_anime(html: &str) -> Vec<CompletedAnime> {
 /// # Returns
 /// A vector of `SearchResult` structs. Returns empty array if no results found.
 pub fn parse_search_results(html: &str) -> Vec<SearchResult> {
+    let Ok(selectors) = selectors::get() else {
+        return Vec::new();
+    };
+    let selectors = &selectors.listing;
     let document = Html::parse_document(html);
 
-    // First try to find div.listupd container, then look for article.bs inside
-    let listupd_selector = Selector::parse("div.listupd").unwrap();
-    let article_selector = Selector::parse("article.bs").unwrap();
-
-    // Selectors for individual fields
-    let title_selector = Selector::parse("h2[itemprop=\"headline\"]").unwrap();
-    let url_selector = Selector::parse("a[itemprop=\"url\"]").unwrap();
-    let thumbnail_selector = Selector::parse("img.ts-post-image").unwrap();
-    let status_selector = Selector::parse("div.status").unwrap();
-    let type_selector = Selector::parse("div.typez").unwrap();
-    let episode_status_selector = Selector::parse("span.epx").unwrap();
-
-    let mut results = Vec::new();
-
     // Try to find articles inside div.listupd first
-    let articles: Vec<_> = if let Some(listupd) = document.select(&listupd_selector).next() {
-        listupd.select(&article_selector).collect()
+    let articles: Vec<_> = if let Some(listupd) = document.select(&selectors.listupd).next() {
+        listupd.select(&selectors.article).collect()
     } else {
         // Fallback: look for article.bs anywhere in the document
-        document.select(&article_selector).collect()
+        document.select(&selectors.article).collect()
     };
 
-    for article in articles {
-        let title = article
-            .select(&title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let url = article
-            .select(&url_selector)
-            .next()
-            .and_then(|el| el.value().attr("href"))
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let thumbnail = article
-            .select(&thumbnail_selector)
-            .next()
-            .and_then(|el| {
-                el.value()
-                    .attr("src")
-                    .or_else(|| el.value().attr("data-src"))
-            })
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let status = article
-            .select(&status_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let anime_type = article
-            .select(&type_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let episode_status = article
-            .select(&episode_status_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        results.push(SearchResult {
-            slug: extract_slug_from_url(&url),
-            title,
-            url,
-            thumbnail,
-            status,
-            anime_type,
-            episode_status,
-        });
-    }
-
-    results
+    articles
+        .into_iter()
+        .map(|article| {
+            let url = select_attr(article, &selectors.url, "href");
+            SearchResult {
+                slug: extract_slug_from_url(&url),
+                title: select_text(article, &selectors.title),
+                url,
+                thumbnail: select_image(article, &selectors.thumbnail),
+                thumbnails: select_thumbnails(article, &selectors.thumbnail),
+                status: select_text(article, &selectors.status),
+                anime_type: select_text(article, &selectors.anime_type),
+                episode_status: select_text(article, &selectors.episode_status),
+            }
+        })
+        .collect()
 }
 
 /// Parse anime list from the anime list page HTML
@@ -562,85 +778,92 @@ pub fn parse_search_results(html: &str) -> Vec<SearchResult> {
 /// # Returns
 /// A vector of `AnimeListItem` structs. Returns empty array if no results found.
 pub fn parse_anime_list(html: &str) -> Vec<AnimeListItem> {
+    let Ok(selectors) = selectors::get() else {
+        return Vec::new();
+    };
+    let selectors = &selectors.listing;
     let document = Html::parse_document(html);
 
-    // First try to find div.listupd container, then look for article.bs inside
-    let listupd_selector = Selector::parse("div.listupd").unwrap();
-    let article_selector = Selector::parse("article.bs").unwrap();
-
-    // Selectors for individual fields
-    let title_selector = Selector::parse("h2[itemprop=\"headline\"]").unwrap();
-    let url_selector = Selector::parse("a[itemprop=\"url\"]").unwrap();
-    let thumbnail_selector = Selector::parse("img.ts-post-image").unwrap();
-    let status_selector = Selector::parse("div.status").unwrap();
-    let type_selector = Selector::parse("div.typez").unwrap();
-    let episode_status_selector = Selector::parse("span.epx").unwrap();
-
-    let mut results = Vec::new();
-
     // Try to find articles inside div.listupd first
-    let articles: Vec<_> = if let Some(listupd) = document.select(&listupd_selector).next() {
-        listupd.select(&article_selector).collect()
+    let articles: Vec<_> = if let Some(listupd) = document.select(&selectors.listupd).next() {
+        listupd.select(&selectors.article).collect()
     } else {
         // Fallback: look for article.bs anywhere in the document
-        document.select(&article_selector).collect()
+        document.select(&selectors.article).collect()
     };
 
-    for article in articles {
-        let title = article
-            .select(&title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
+    articles
+        .into_iter()
+        .map(|article| {
+            let url = select_attr(article, &selectors.url, "href");
+            AnimeListItem {
+                slug: extract_slug_from_url(&url),
+                title: select_text(article, &selectors.title),
+                url,
+                thumbnail: select_image(article, &selectors.thumbnail),
+                thumbnails: select_thumbnails(article, &selectors.thumbnail),
+                status: select_text(article, &selectors.status),
+                anime_type: select_text(article, &selectors.anime_type),
+                episode_status: select_text(article, &selectors.episode_status),
+            }
+        })
+        .collect()
+}
 
-        let url = article
-            .select(&url_selector)
-            .next()
-            .and_then(|el| el.value().attr("href"))
-            .map(|s| s.to_string())
-            .unwrap_or_default();
+/// Parse a popular widget from the home page HTML
+///
+/// Reads the "Popular Today" row (`div.hothome article.bs`) or the weekly
+/// or all-time tab of the popular sidebar (`div.wpop-weekly li`,
+/// `div.wpop-alltime li`). Entries are returned in rank order.
+///
+/// # Arguments
+/// * `html` - The HTML content to parse
+/// * `window` - Which widget to read
+///
+/// # Returns
+/// A vector of `PopularEntry` structs. Returns empty array if the page has
+/// no such widget.
+pub fn parse_popular(html: &str, window: PopularWindow) -> Vec<PopularEntry> {
+    let Ok(selectors) = selectors::get() else {
+        return Vec::new();
+    };
+    let selectors = &selectors.popular;
+    let document = Html::parse_document(html);
+    let items = match window {
+        PopularWindow::Today => &selectors.today,
+        PopularWindow::Week => &selectors.week,
+        PopularWindow::All => &selectors.all,
+    };
 
-        let thumbnail = article
-            .select(&thumbnail_selector)
-            .next()
-            .and_then(|el| {
-                el.value()
-                    .attr("src")
-                    .or_else(|| el.value().attr("data-src"))
+    let mut entries: Vec<PopularEntry> = document
+        .select(items)
+        .enumerate()
+        .filter_map(|(position, item)| {
+            let url = select_attr(item, &selectors.url, "href");
+            if url.is_empty() {
+                return None;
+            }
+            let rank = select_text(item, &selectors.rank)
+                .parse()
+                .unwrap_or(position as u32 + 1);
+            Some(PopularEntry {
+                rank,
+                slug: extract_slug_from_url(&url),
+                title: select_text(item, &selectors.title),
+                url,
+                thumbnail: select_image(item, &selectors.thumbnail),
+                thumbnails: select_thumbnails(item, &selectors.thumbnail),
+                genres: item
+                    .select(&selectors.genre)
+                    .map(element_text)
+                    .filter(|genre| !genre.is_empty())
+                    .collect(),
+                rating: select_text(item, &selectors.rating),
             })
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let status = article
-            .select(&status_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let anime_type = article
-            .select(&type_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let episode_status = article
-            .select(&episode_status_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        results.push(AnimeListItem {
-            slug: extract_slug_from_url(&url),
-            title,
-            url,
-            thumbnail,
-            status,
-            anime_type,
-            episode_status,
-        });
-    }
-
-    results
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.rank);
+    entries
 }
 
 /// Parse anime detail from an anime detail page HTML
@@ -653,67 +876,13 @@ pub fn parse_anime_list(html: &str) -> Vec<AnimeListItem> {
 /// # Returns
 /// An `AnimeDetail` struct with all extracted information
 pub fn parse_anime_detail(html: &str) -> AnimeDetail {
+    let Ok(selectors) = selectors::get() else {
+        return AnimeDetail::default();
+    };
+    let episode_selectors = &selectors.episode_list;
+    let selectors = &selectors.detail;
     let document = Html::parse_document(html);
-
-    // Selectors for metadata
-    let title_selector = Selector::parse("h1.entry-title").unwrap();
-    let alternate_titles_selector = Selector::parse("span.alter").unwrap();
-    let poster_selector = Selector::parse("div.thumb img").unwrap();
-    let rating_selector = Selector::parse("meta[itemprop=\"ratingValue\"]").unwrap();
-    let trailer_selector = Selector::parse("a.trailerbutton").unwrap();
-    let spe_span_selector = Selector::parse("div.spe span").unwrap();
-    let casts_selector = Selector::parse("a.casts").unwrap();
-    let genres_selector = Selector::parse("div.genxed a").unwrap();
-    let synopsis_selector = Selector::parse("div.desc").unwrap();
-
-    // Episode list selectors
-    let episode_list_selector = Selector::parse("div.eplister ul li").unwrap();
-    let episode_num_selector = Selector::parse("div.epl-num").unwrap();
-    let episode_title_selector = Selector::parse("div.epl-title").unwrap();
-    let episode_url_selector = Selector::parse("a").unwrap();
-    let episode_date_selector = Selector::parse("div.epl-date").unwrap();
-
-    // Extract title
-    let title = document
-        .select(&title_selector)
-        .next()
-        .map(|el| el.text().collect::<String>().trim().to_string())
-        .unwrap_or_default();
-
-    // Extract alternate titles
-    let alternate_titles = document
-        .select(&alternate_titles_selector)
-        .next()
-        .map(|el| el.text().collect::<String>().trim().to_string())
-        .unwrap_or_default();
-
-    // Extract poster image
-    let poster = document
-        .select(&poster_selector)
-        .next()
-        .and_then(|el| {
-            el.value()
-                .attr("src")
-                .or_else(|| el.value().attr("data-src"))
-        })
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-
-    // Extract rating from meta tag
-    let rating = document
-        .select(&rating_selector)
-        .next()
-        .and_then(|el| el.value().attr("content"))
-        .map(|s| s.to_string())
-        .unwrap_or_default();
-
-    // Extract trailer URL
-    let trailer_url = document
-        .select(&trailer_selector)
-        .next()
-        .and_then(|el| el.value().attr("href"))
-        .map(|s| s.to_string())
-        .unwrap_or_default();
+    let root = document.root_element();
 
     // Extract metadata from div.spe span elements
     let mut status = String::new();
@@ -725,110 +894,52 @@ pub fn parse_anime_detail(html: &str) -> AnimeDetail {
     let mut total_episodes = String::new();
     let mut director = String::new();
 
-    for span in document.select(&spe_span_selector) {
+    for span in root.select(&selectors.spe_span) {
         let text = span.text().collect::<String>();
         let text_lower = text.to_lowercase();
 
-        // Extract value after colon
-        let extract_value = |text: &str| -> String {
-            text.split(':')
-                .skip(1)
-                .collect::<Vec<_>>()
-                .join(":")
-                .trim()
-                .to_string()
-        };
-
         if text_lower.contains("status") {
-            status = extract_value(&text);
+            status = value_after_colon(&text);
         } else if text_lower.contains("studio") {
-            studio = extract_value(&text);
+            studio = value_after_colon(&text);
         } else if text_lower.contains("tanggal rilis")
             || text_lower.contains("release")
             || text_lower.contains("released")
         {
-            release_date = extract_value(&text);
+            release_date = value_after_colon(&text);
         } else if text_lower.contains("durasi") || text_lower.contains("duration") {
-            duration = extract_value(&text);
+            duration = value_after_colon(&text);
         } else if text_lower.contains("season") {
-            season = extract_value(&text);
+            season = value_after_colon(&text);
         } else if text_lower.contains("tipe") || text_lower.contains("type") {
-            anime_type = extract_value(&text);
+            anime_type = value_after_colon(&text);
         } else if text_lower.contains("total episode") || text_lower.contains("episodes") {
-            total_episodes = extract_value(&text);
+            total_episodes = value_after_colon(&text);
         } else if text_lower.contains("director") || text_lower.contains("sutradara") {
-            director = extract_value(&text);
+            director = value_after_colon(&text);
         }
     }
 
     // Extract casts
-    let casts: Vec<String> = document
-        .select(&casts_selector)
-        .map(|el| el.text().collect::<String>().trim().to_string())
+    let casts: Vec<String> = root
+        .select(&selectors.casts)
+        .map(element_text)
         .filter(|s| !s.is_empty())
         .collect();
 
     // Extract genres
-    let genres: Vec<String> = document
-        .select(&genres_selector)
-        .map(|el| el.text().collect::<String>().trim().to_string())
+    let genres: Vec<String> = root
+        .select(&selectors.genres)
+        .map(element_text)
         .filter(|s| !s.is_empty())
         .collect();
 
-    // Extract synopsis
-    let synopsis = document
-        .select(&synopsis_selector)
-        .next()
-        .map(|el| {
-            // Get all text content, preserving some structure
-            el.text().collect::<String>().trim().to_string()
-        })
-        .unwrap_or_default();
-
-    // Extract episodes from div.eplister
-    let mut episodes: Vec<Episode> = Vec::new();
-
-    for li in document.select(&episode_list_selector) {
-        let number = li
-            .select(&episode_num_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let ep_title = li
-            .select(&episode_title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let url = li
-            .select(&episode_url_selector)
-            .next()
-            .and_then(|el| el.value().attr("href"))
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let ep_release_date = li
-            .select(&episode_date_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        episodes.push(Episode {
-            slug: extract_slug_from_url(&url),
-            number,
-            title: ep_title,
-            url,
-            release_date: ep_release_date,
-        });
-    }
-
     AnimeDetail {
-        title,
-        alternate_titles,
-        poster,
-        rating,
-        trailer_url,
+        title: select_text(root, &selectors.title),
+        alternate_titles: select_text(root, &selectors.alternate_titles),
+        poster: select_image(root, &selectors.poster),
+        rating: select_attr(root, &selectors.rating, "content"),
+        trailer_url: select_attr(root, &selectors.trailer, "href"),
         status,
         studio,
         release_date,
@@ -838,70 +949,49 @@ pub fn parse_anime_detail(html: &str) -> AnimeDetail {
         total_episodes,
         director,
         casts,
-        genres,
-        synopsis,
-        episodes,
-    }
-}
-
-/// Parse episode list from HTML
-///
-/// Extracts episodes from `div.eplister ul li` elements.
-/// Episodes are returned in the same order as they appear in the HTML (newest first).
-///
-/// # Arguments
-/// * `html` - The HTML content to parse
-///
-/// # Returns
-/// A vector of `Episode` structs in the order they appear in the HTML
-pub fn parse_episode_list(html: &str) -> Vec<Episode> {
-    let document = Html::parse_document(html);
-
-    // Selectors for episode list
-    let episode_list_selector = Selector::parse("div.eplister ul li").unwrap();
-    let episode_num_selector = Selector::parse("div.epl-num").unwrap();
-    let episode_title_selector = Selector::parse("div.epl-title").unwrap();
-    let episode_url_selector = Selector::parse("a").unwrap();
-    let episode_date_selector = Selector::parse("div.epl-date").unwrap();
-
-    let mut episodes: Vec<Episode> = Vec::new();
-
-    for li in document.select(&episode_list_selector) {
-        let number = li
-            .select(&episode_num_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let title = li
-            .select(&episode_title_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        let url = li
-            .select(&episode_url_selector)
-            .next()
-            .and_then(|el| el.value().attr("href"))
-            .map(|s| s.to_string())
-            .unwrap_or_default();
-
-        let release_date = li
-            .select(&episode_date_selector)
-            .next()
-            .map(|el| el.text().collect::<String>().trim().to_string())
-            .unwrap_or_default();
-
-        episodes.push(Episode {
-            slug: extract_slug_from_url(&url),
-            number,
-            title,
-            url,
-            release_date,
-        });
+        genres,
+        synopsis: select_text(root, &selectors.synopsis),
+        episodes: extract_episodes(root, episode_selectors),
+        canonical_slug: None,
+        age_rating: None,
     }
+}
+
+/// Parse episode list from HTML
+///
+/// Extracts episodes from `div.eplister ul li` elements.
+/// Episodes are returned in the same order as they appear in the HTML (newest first).
+///
+/// # Arguments
+/// * `html` - The HTML content to parse
+///
+/// # Returns
+/// A vector of `Episode` structs in the order they appear in the HTML
+pub fn parse_episode_list(html: &str) -> Vec<Episode> {
+    let Ok(selectors) = selectors::get() else {
+        return Vec::new();
+    };
+    let document = Html::parse_document(html);
 
-    episodes
+    extract_episodes(document.root_element(), &selectors.episode_list)
+}
+
+/// Extract episodes from the `div.eplister ul li` elements below `root`
+fn extract_episodes(root: ElementRef<'_>, selectors: &EpisodeListSelectors) -> Vec<Episode> {
+    root.select(&selectors.item)
+        .map(|li| {
+            let url = select_attr(li, &selectors.url, "href");
+            let release_date = select_text(li, &selectors.date);
+            Episode {
+                slug: extract_slug_from_url(&url),
+                number: select_text(li, &selectors.number),
+                title: select_text(li, &selectors.title),
+                url,
+                released_at_iso: iso_date(&release_date, Utc::now()),
+                release_date,
+            }
+        })
+        .collect()
 }
 
 /// Parse video sources from an episode page HTML
@@ -916,45 +1006,25 @@ pub fn parse_episode_list(html: &str) -> Vec<Episode> {
 /// # Returns
 /// An `EpisodeDetail` struct with title, default video, and all video sources
 pub fn parse_episode_detail(html: &str) -> EpisodeDetail {
+    let Ok(selectors) = selectors::get() else {
+        return EpisodeDetail::default();
+    };
+    let selectors = &selectors.episode;
     let document = Html::parse_document(html);
-
-    // Selectors
-    let title_selector = Selector::parse("h1.entry-title").unwrap();
-    let default_video_selector = Selector::parse("div#embed_holder video source").unwrap();
-    let mirror_option_selector = Selector::parse("select.mirror option").unwrap();
-
-    // Extract episode title
-    let title = document
-        .select(&title_selector)
-        .next()
-        .map(|el| el.text().collect::<String>().trim().to_string())
-        .unwrap_or_default();
-
-    // Extract default video URL from div#embed_holder video source
-    let default_video = document
-        .select(&default_video_selector)
-        .next()
-        .and_then(|el| el.value().attr("src"))
-        .map(|s| s.to_string())
-        .unwrap_or_default();
+    let root = document.root_element();
 
     // Extract video sources from select.mirror option elements
     let mut sources: Vec<VideoSource> = Vec::new();
+    let mut subtitles = Vec::new();
+    collect_subtitle_tracks(root.select(&selectors.subtitle_track), &mut subtitles);
 
-    for option in document.select(&mirror_option_selector) {
+    for option in root.select(&selectors.mirror_option) {
         // Get the base64-encoded value
         let value = match option.value().attr("value") {
             Some(v) if !v.is_empty() => v,
             _ => continue,
         };
 
-        // Get the option text for server and quality info
-        let option_text = option.text().collect::<String>().trim().to_string();
-
-        // Parse server and quality from option text
-        // Format is typically "SERVER - QUALITY" or "SERVER QUALITY" or just "SERVER"
-        let (server, quality) = parse_server_quality(&option_text);
-
         // Decode base64 value
         let decoded_html = match decode_base64_value(value) {
             Some(html) => html,
@@ -962,21 +1032,31 @@ pub fn parse_episode_detail(html: &str) -> EpisodeDetail {
         };
 
         // Extract video URL from decoded HTML
-        let video_url = extract_video_url_from_html(&decoded_html);
-
-        if !video_url.is_empty() {
-            sources.push(VideoSource {
-                server,
-                quality,
-                url: video_url,
-            });
+        let (video_url, framed) = extract_video_from_html(&decoded_html);
+        if video_url.is_empty() {
+            continue;
         }
+        extract_tracks_from_html(&decoded_html, &mut subtitles);
+
+        // Parse server and quality from option text
+        // Format is typically "SERVER - QUALITY" or "SERVER QUALITY" or just "SERVER"
+        let (server, quality) = parse_server_quality(&element_text(option));
+
+        sources.push(VideoSource {
+            server,
+            quality,
+            embed: framed.then(|| embed_info(&video_url)).flatten(),
+            url: video_url,
+        });
     }
 
     EpisodeDetail {
-        title,
-        default_video,
+        title: select_text(root, &selectors.title),
+        // Default video URL from div#embed_holder video source
+        default_video: select_attr(root, &selectors.default_video, "src"),
         sources,
+        subtitles,
+        comment_count: None,
     }
 }
 
@@ -998,9 +1078,11 @@ fn parse_server_quality(text: &str) -> (String, String) {
     }
 
     // Try to find quality pattern (e.g., "480p", "720p", "1080p")
+    // ASCII lowercasing keeps byte offsets, so `idx` is valid in `text` too
     let quality_patterns = ["1080p", "720p", "480p", "360p", "240p"];
+    let text_lower = text.to_ascii_lowercase();
     for pattern in quality_patterns {
-        if let Some(idx) = text.to_lowercase().find(&pattern.to_lowercase()) {
+        if let Some(idx) = text_lower.find(pattern) {
             let server = text[..idx].trim().to_string();
             let quality = text[idx..].trim().to_string();
             if !server.is_empty() {
@@ -1028,46 +1110,88 @@ fn decode_base64_value(value: &str) -> Option<String> {
 /// Extract video URL from decoded HTML content
 ///
 /// Looks for video source elements or iframe src attributes
-fn extract_video_url_from_html(html: &str) -> String {
+///
+/// # Returns
+/// The URL, empty if none was found, and whether it came from an iframe or
+/// embed rather than a native video element
+fn extract_video_from_html(html: &str) -> (String, bool) {
+    let Ok(selectors) = selectors::get() else {
+        return (String::new(), false);
+    };
+    let selectors = &selectors.embed;
     let document = Html::parse_fragment(html);
 
-    // Try to find video source element
-    if let Ok(source_selector) = Selector::parse("source") {
-        if let Some(source) = document.select(&source_selector).next() {
-            if let Some(src) = source.value().attr("src") {
-                return src.to_string();
-            }
-        }
-    }
+    // Try to find video source element, then a video element with src,
+    // then an iframe, then an embed
+    let video = [
+        (&selectors.source, false),
+        (&selectors.video, false),
+        (&selectors.iframe, true),
+        (&selectors.embed, true),
+    ]
+    .into_iter()
+    .find_map(|(selector, framed)| {
+        document
+            .select(selector)
+            .next()
+            .and_then(|el| el.value().attr("src"))
+            .map(|src| (src.to_string(), framed))
+    })
+    .unwrap_or_default();
+    video
+}
 
-    // Try to find video element with src
-    if let Ok(video_selector) = Selector::parse("video") {
-        if let Some(video) = document.select(&video_selector).next() {
-            if let Some(src) = video.value().attr("src") {
-                return src.to_string();
-            }
-        }
-    }
+/// Collect subtitle tracks from decoded mirror HTML
+fn extract_tracks_from_html(html: &str, tracks: &mut Vec<SubtitleTrack>) {
+    let Ok(selectors) = selectors::get() else {
+        return;
+    };
+    let document = Html::parse_fragment(html);
+    collect_subtitle_tracks(document.select(&selectors.embed.track), tracks);
+}
 
-    // Try to find iframe src
-    if let Ok(iframe_selector) = Selector::parse("iframe") {
-        if let Some(iframe) = document.select(&iframe_selector).next() {
-            if let Some(src) = iframe.value().attr("src") {
-                return src.to_string();
-            }
+/// Add subtitle and caption tracks to `tracks`, skipping URLs already there
+///
+/// Tracks without a `kind` are subtitles, as in HTML; chapters, descriptions,
+/// and metadata tracks are skipped.
+fn collect_subtitle_tracks<'a>(
+    elements: impl Iterator<Item = ElementRef<'a>>,
+    tracks: &mut Vec<SubtitleTrack>,
+) {
+    for el in elements {
+        let kind = el.value().attr("kind").unwrap_or("subtitles");
+        if !kind.eq_ignore_ascii_case("subtitles") && !kind.eq_ignore_ascii_case("captions") {
+            continue;
         }
-    }
-
-    // Try to find embed src
-    if let Ok(embed_selector) = Selector::parse("embed") {
-        if let Some(embed) = document.select(&embed_selector).next() {
-            if let Some(src) = embed.value().attr("src") {
-                return src.to_string();
-            }
+        let url = el.value().attr("src").unwrap_or("").trim();
+        if url.is_empty() || tracks.iter().any(|track| track.url == url) {
+            continue;
         }
+        tracks.push(SubtitleTrack {
+            url: url.to_string(),
+            language: el.value().attr("srclang").unwrap_or("").trim().to_string(),
+            label: el.value().attr("label").unwrap_or("").trim().to_string(),
+            default: el.value().attr("default").is_some(),
+        });
     }
+}
 
-    String::new()
+/// Embed metadata for a video URL that is played in an iframe
+///
+/// Capabilities come from the known host registry,
+/// `constants::embed_hosts`.
+///
+/// # Returns
+/// The metadata, or `None` if the URL has no host
+pub fn embed_info(url: &str) -> Option<EmbedInfo> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    let known = embed_hosts::lookup(&host);
+    Some(EmbedInfo {
+        host,
+        known_player: known.is_some_and(|known| known.player),
+        requires_referer: known.is_some_and(|known| known.requires_referer),
+    })
 }
 
 #[cfg(test)]
@@ -1116,6 +1240,33 @@ mod tests {
         assert_eq!(update.status, "Ongoing");
     }
 
+    #[test]
+    fn test_resolve_release_times() {
+        let mut updates = parse_anime_updates(
+            r#"
+            <article class="seventh">
+                <h2 itemprop="headline"><a href="/episode-1/">Episode 1</a></h2>
+                <div class="sosev"><span>Dipos pada: 2 jam lalu</span></div>
+            </article>
+            <article class="seventh">
+                <h2 itemprop="headline"><a href="/episode-2/">Episode 2</a></h2>
+                <div class="sosev"><span>27 Desember 2024</span></div>
+            </article>
+            "#,
+        );
+        assert!(updates.iter().all(|update| update.released_at.is_none()));
+
+        let fetched_at = DateTime::parse_from_rfc3339("2024-12-27T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        resolve_release_times(&mut updates, fetched_at);
+        assert_eq!(
+            updates[0].released_at.as_deref(),
+            Some("2024-12-27T10:00:00+00:00")
+        );
+        assert_eq!(updates[1].released_at, None);
+    }
+
     #[test]
     fn test_parse_anime_updates_missing_elements() {
         let html = r#"
@@ -1139,6 +1290,99 @@ mod tests {
         assert_eq!(update.anime_type, "");
     }
 
+    #[test]
+    fn test_parse_anime_updates_duplicates() {
+        let html = r#"
+        <html>
+        <body>
+            <div class="listupd">
+                <article class="seventh">
+                    <h2 itemprop="headline"><a href="/episode-1/">Latest</a></h2>
+                    <a itemprop="url" href="/episode-1/"></a>
+                </article>
+                <article class="seventh">
+                    <h2 itemprop="headline"><a href="/episode-2/">Other</a></h2>
+                    <a itemprop="url" href="/episode-2/"></a>
+                </article>
+            </div>
+            <div class="listupd popular">
+                <article class="seventh">
+                    <h2 itemprop="headline"><a href="/episode-1">Popular</a></h2>
+                    <a itemprop="url" href="/episode-1"></a>
+                </article>
+            </div>
+        </body>
+        </html>
+        "#;
+
+        let updates = parse_anime_updates(html);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].title, "Latest");
+        assert_eq!(updates[1].title, "Other");
+    }
+
+    #[test]
+    fn test_parse_anime_updates_keeps_missing_urls() {
+        let html = r#"
+        <html>
+        <body>
+            <article class="seventh"><h2 itemprop="headline">First</h2></article>
+            <article class="seventh"><h2 itemprop="headline">Second</h2></article>
+        </body>
+        </html>
+        "#;
+
+        let updates = parse_anime_updates(html);
+        assert_eq!(updates.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_srcset() {
+        assert_eq!(
+            parse_srcset("/b.jpg 300w, /a.jpg 150w,/c.jpg 600w"),
+            vec!["/a.jpg", "/b.jpg", "/c.jpg"]
+        );
+        assert_eq!(
+            parse_srcset("/hi.jpg 2x, /lo.jpg"),
+            vec!["/lo.jpg", "/hi.jpg"]
+        );
+        assert_eq!(
+            parse_srcset("/bad.jpg large, , /ok.jpg 1x"),
+            vec!["/ok.jpg"]
+        );
+        assert!(parse_srcset("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_anime_updates_thumbnails() {
+        let html = r#"
+        <html>
+        <body>
+            <article class="seventh">
+                <img class="ts-post-image" data-src="/full.jpg"
+                     data-srcset="/small.jpg 150w, /full.jpg 300w">
+            </article>
+            <article class="seventh">
+                <img class="ts-post-image" src="/plain.jpg">
+            </article>
+        </body>
+        </html>
+        "#;
+
+        let updates = parse_anime_updates(html);
+        assert_eq!(updates[0].thumbnail, "/full.jpg");
+        assert_eq!(
+            updates[0].thumbnails,
+            Some(Thumbnails {
+                small: "/small.jpg".to_string(),
+                medium: "/small.jpg".to_string(),
+                large: "/full.jpg".to_string(),
+            })
+        );
+        assert_eq!(updates[1].thumbnail, "/plain.jpg");
+        assert_eq!(updates[1].thumbnails, None);
+    }
+
     #[test]
     fn test_parse_completed_anime_empty_html() {
         let html = "<html><body></body></html>";
@@ -1183,6 +1427,7 @@ mod tests {
         assert_eq!(anime.status, "Completed");
         assert_eq!(anime.posted_by, "Admin");
         assert_eq!(anime.posted_at, "2024-01-01");
+        assert_eq!(anime.released_at_iso.as_deref(), Some("2024-01-01"));
         assert_eq!(anime.rating, "8.5");
         assert_eq!(anime.genres, vec!["Action", "Adventure"]);
     }
@@ -1211,6 +1456,83 @@ mod tests {
         assert!(anime.genres.is_empty());
     }
 
+    #[test]
+    fn test_parse_popular_windows() {
+        let html = r#"
+        <html>
+        <body>
+            <div class="hothome">
+                <article class="bs">
+                    <a itemprop="url" href="/anime/first/"><h2 itemprop="headline">First</h2></a>
+                </article>
+                <article class="bs">
+                    <a itemprop="url" href="/anime/second/"><h2 itemprop="headline">Second</h2></a>
+                </article>
+            </div>
+            <div class="wpop-weekly"><ul>
+                <li>
+                    <div class="ctr">2</div>
+                    <h4><a class="series" href="/anime/runner-up/">Runner Up</a></h4>
+                    <a rel="tag" href="/genre/drama/">Drama</a>
+                    <div class="numscore">7.9</div>
+                </li>
+                <li>
+                    <div class="ctr">1</div>
+                    <h4><a class="series" href="/anime/winner/">Winner</a></h4>
+                </li>
+                <li><h4>No link</h4></li>
+            </ul></div>
+        </body>
+        </html>
+        "#;
+
+        let today = parse_popular(html, PopularWindow::Today);
+        let ranks: Vec<(u32, &str)> = today.iter().map(|e| (e.rank, e.slug.as_str())).collect();
+        assert_eq!(ranks, vec![(1, "first"), (2, "second")]);
+        assert_eq!(today[0].title, "First");
+
+        // Ranked by the widget's own counter, entries without a link skipped
+        let week = parse_popular(html, PopularWindow::Week);
+        assert_eq!(week.len(), 2);
+        assert_eq!(week[0].slug, "winner");
+        assert_eq!(week[1].rank, 2);
+        assert_eq!(week[1].title, "Runner Up");
+        assert_eq!(week[1].genres, vec!["Drama"]);
+        assert_eq!(week[1].rating, "7.9");
+
+        assert!(parse_popular(html, PopularWindow::All).is_empty());
+    }
+
+    #[test]
+    fn test_popular_window_parse() {
+        assert_eq!(PopularWindow::parse("today"), Some(PopularWindow::Today));
+        assert_eq!(PopularWindow::parse(" Week "), Some(PopularWindow::Week));
+        assert_eq!(PopularWindow::parse("all"), Some(PopularWindow::All));
+        assert_eq!(PopularWindow::parse("month"), None);
+        assert_eq!(PopularWindow::Week.as_str(), "week");
+    }
+
+    #[test]
+    fn test_age_rating_from_genres() {
+        let genres = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            AgeRating::from_genres(&genres(&["Action", "Comedy"])),
+            AgeRating::General
+        );
+        assert_eq!(
+            AgeRating::from_genres(&genres(&["Action", " horror "])),
+            AgeRating::Mature
+        );
+        assert_eq!(
+            AgeRating::from_genres(&genres(&["Horror", "Ecchi"])),
+            AgeRating::Adult
+        );
+        assert_eq!(AgeRating::from_genres(&[]), AgeRating::General);
+        assert_eq!(AgeRating::parse(" Adult"), Some(AgeRating::Adult));
+        assert_eq!(AgeRating::parse("r18"), None);
+    }
+
     #[test]
     fn test_anime_update_serialization() {
         let update = AnimeUpdate {
@@ -1218,12 +1540,16 @@ mod tests {
             title: "Test".to_string(),
             episode_url: "/ep/1".to_string(),
             thumbnail: "https://example.com/img.jpg".to_string(),
+            thumbnails: None,
             episode_number: "1".to_string(),
             anime_type: "TV".to_string(),
             series_title: "Test Series".to_string(),
             series_url: "/anime/test/".to_string(),
             status: "Ongoing".to_string(),
             release_info: "2 hours ago".to_string(),
+            released_at: None,
+            like_count: None,
+            liked_by_me: None,
         };
 
         let json = serde_json::to_string(&update).unwrap();
@@ -1232,6 +1558,17 @@ mod tests {
         assert!(json.contains("\"seriesTitle\""));
         assert!(json.contains("\"releaseInfo\""));
         assert!(json.contains("\"type\"")); // anime_type should serialize as "type"
+        assert!(!json.contains("likeCount"));
+        assert!(!json.contains("likedByMe"));
+
+        let json = serde_json::to_string(&AnimeUpdate {
+            like_count: Some(5),
+            liked_by_me: Some(true),
+            ..update
+        })
+        .unwrap();
+        assert!(json.contains("\"likeCount\":5"));
+        assert!(json.contains("\"likedByMe\":true"));
     }
 
     #[test]
@@ -1241,11 +1578,13 @@ mod tests {
             title: "Test".to_string(),
             url: "/anime/test/".to_string(),
             thumbnail: "https://example.com/img.jpg".to_string(),
+            thumbnails: None,
             anime_type: "TV".to_string(),
             episode_count: "24".to_string(),
             status: "Completed".to_string(),
             posted_by: "Admin".to_string(),
             posted_at: "2024-01-01".to_string(),
+            released_at_iso: None,
             series_title: "Test".to_string(),
             series_url: "/anime/test/".to_string(),
             genres: vec!["Action".to_string()],
@@ -1415,6 +1754,7 @@ mod tests {
             title: "Test Anime".to_string(),
             url: "/anime/test/".to_string(),
             thumbnail: "https://example.com/img.jpg".to_string(),
+            thumbnails: None,
             status: "Ongoing".to_string(),
             anime_type: "TV".to_string(),
             episode_status: "12 Episodes".to_string(),
@@ -1603,6 +1943,7 @@ mod tests {
             title: "Test Anime".to_string(),
             url: "/anime/test/".to_string(),
             thumbnail: "https://example.com/img.jpg".to_string(),
+            thumbnails: None,
             status: "Ongoing".to_string(),
             anime_type: "TV".to_string(),
             episode_status: "12 Episodes".to_string(),
@@ -1731,6 +2072,10 @@ mod tests {
         assert_eq!(detail.episodes[0].title, "The Message");
         assert_eq!(detail.episodes[0].url, "/naruto-shippuden-episode-500/");
         assert_eq!(detail.episodes[0].release_date, "Mar 23, 2017");
+        assert_eq!(
+            detail.episodes[0].released_at_iso.as_deref(),
+            Some("2017-03-23")
+        );
 
         assert_eq!(detail.episodes[1].number, "499");
         assert_eq!(
@@ -1866,7 +2211,10 @@ mod tests {
                 title: "Episode 1".to_string(),
                 url: "/ep-1/".to_string(),
                 release_date: "Jan 1, 2024".to_string(),
+                released_at_iso: None,
             }],
+            canonical_slug: None,
+            age_rating: None,
         };
 
         let json = serde_json::to_string(&detail).unwrap();
@@ -1887,6 +2235,7 @@ mod tests {
             title: "First Episode".to_string(),
             url: "/anime/test/episode-1/".to_string(),
             release_date: "Jan 1, 2024".to_string(),
+            released_at_iso: None,
         };
 
         let json = serde_json::to_string(&episode).unwrap();
@@ -2262,6 +2611,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_episode_detail_subtitles() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(
+            r#"<video><source src="https://example.com/720p.mp4" /><track src="https://example.com/ep1.en.srt" srclang="en" label="English" /><track kind="chapters" src="https://example.com/ep1.chapters.vtt" /></video>"#,
+        );
+
+        let html = format!(
+            r#"
+        <html>
+        <body>
+            <h1 class="entry-title">Test Episode</h1>
+            <div id="embed_holder">
+                <video>
+                    <source src="https://example.com/480p.mp4" type="video/mp4" />
+                    <track kind="subtitles" src="https://example.com/ep1.id.vtt" srclang="id" label="Indonesia" default />
+                </video>
+            </div>
+            <select class="mirror">
+                <option value="{encoded}">SOKUJA - 720p</option>
+            </select>
+        </body>
+        </html>
+        "#
+        );
+
+        let detail = parse_episode_detail(&html);
+        assert_eq!(
+            detail.subtitles,
+            vec![
+                SubtitleTrack {
+                    url: "https://example.com/ep1.id.vtt".to_string(),
+                    language: "id".to_string(),
+                    label: "Indonesia".to_string(),
+                    default: true,
+                },
+                SubtitleTrack {
+                    url: "https://example.com/ep1.en.srt".to_string(),
+                    language: "en".to_string(),
+                    label: "English".to_string(),
+                    default: false,
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_episode_detail_invalid_base64() {
         let html = r#"
@@ -2360,6 +2754,15 @@ mod tests {
         assert_eq!(quality, "1080p HD");
     }
 
+    #[test]
+    fn test_parse_server_quality_non_ascii() {
+        // "İ" grows when Unicode-lowercased, which used to shift the split
+        // point into the middle of "日"
+        let (server, quality) = parse_server_quality("İİİİİ720p日");
+        assert_eq!(server, "İİİİİ");
+        assert_eq!(quality, "720p日");
+    }
+
     #[test]
     fn test_parse_server_quality_lowercase() {
         let (server, quality) = parse_server_quality("server 480p");
@@ -2390,44 +2793,74 @@ mod tests {
     #[test]
     fn test_extract_video_url_from_source() {
         let html = r#"<source src="https://example.com/video.mp4" type="video/mp4" />"#;
-        let url = extract_video_url_from_html(html);
+        let (url, framed) = extract_video_from_html(html);
         assert_eq!(url, "https://example.com/video.mp4");
+        assert!(!framed);
     }
 
     #[test]
     fn test_extract_video_url_from_video() {
         let html = r#"<video src="https://example.com/video.mp4"></video>"#;
-        let url = extract_video_url_from_html(html);
+        let (url, framed) = extract_video_from_html(html);
         assert_eq!(url, "https://example.com/video.mp4");
+        assert!(!framed);
     }
 
     #[test]
     fn test_extract_video_url_from_iframe() {
         let html = r#"<iframe src="https://player.example.com/embed/123"></iframe>"#;
-        let url = extract_video_url_from_html(html);
+        let (url, framed) = extract_video_from_html(html);
         assert_eq!(url, "https://player.example.com/embed/123");
+        assert!(framed);
     }
 
     #[test]
     fn test_extract_video_url_from_embed() {
         let html = r#"<embed src="https://example.com/player.swf" />"#;
-        let url = extract_video_url_from_html(html);
+        let (url, framed) = extract_video_from_html(html);
         assert_eq!(url, "https://example.com/player.swf");
+        assert!(framed);
     }
 
     #[test]
     fn test_extract_video_url_no_url() {
         let html = r#"<div>No video here</div>"#;
-        let url = extract_video_url_from_html(html);
+        let (url, _) = extract_video_from_html(html);
         assert_eq!(url, "");
     }
 
+    #[test]
+    fn test_embed_info() {
+        assert_eq!(
+            embed_info("https://www.blogger.com/video.g?token=abc"),
+            Some(EmbedInfo {
+                host: "www.blogger.com".to_string(),
+                known_player: true,
+                requires_referer: false,
+            })
+        );
+        assert_eq!(
+            embed_info("https://www.mp4upload.com/embed-abc.html").map(|e| e.requires_referer),
+            Some(true)
+        );
+        assert_eq!(
+            embed_info("https://player.example.com/embed/123"),
+            Some(EmbedInfo {
+                host: "player.example.com".to_string(),
+                known_player: false,
+                requires_referer: false,
+            })
+        );
+        assert_eq!(embed_info("/relative/player"), None);
+    }
+
     #[test]
     fn test_video_source_serialization() {
         let source = VideoSource {
             server: "SOKUJA".to_string(),
             quality: "720p".to_string(),
             url: "https://example.com/video.mp4".to_string(),
+            embed: None,
         };
 
         let json = serde_json::to_string(&source).unwrap();
@@ -2446,7 +2879,10 @@ mod tests {
                 server: "SOKUJA".to_string(),
                 quality: "720p".to_string(),
                 url: "https://example.com/720p.mp4".to_string(),
+                embed: None,
             }],
+            subtitles: Vec::new(),
+            comment_count: None,
         };
 
         let json = serde_json::to_string(&detail).unwrap();
@@ -2454,13 +2890,23 @@ mod tests {
         assert!(json.contains("\"title\""));
         assert!(json.contains("\"defaultVideo\""));
         assert!(json.contains("\"sources\""));
+        assert!(!json.contains("commentCount"));
+
+        let json = serde_json::to_string(&EpisodeDetail {
+            comment_count: Some(3),
+            ..detail
+        })
+        .unwrap();
+        assert!(json.contains("\"commentCount\":3"));
     }
 }
 
 #[cfg(test)]
 mod property_tests {
     use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
     use proptest::prelude::*;
+    use serde::de::DeserializeOwned;
 
     /// Generate a random string for HTML content
     fn arbitrary_text() -> impl Strategy<Value = String> {
@@ -2523,6 +2969,7 @@ mod property_tests {
     }
 
     /// Generate HTML for a completed anime article
+    #[allow(clippy::too_many_arguments)]
     fn generate_completed_anime_html(
         title: &str,
         url: &str,
@@ -2710,4 +3157,257 @@ mod property_tests {
             prop_assert!(anime.genres.is_empty(), "Missing genres should be empty array");
         }
     }
+
+    /// Well-formed pages whose truncated and duplicated copies seed the
+    /// malformed-HTML properties
+    const SEED_PAGES: &[&str] = &[
+        include_str!("../../fixtures/parser/updates/home.html"),
+        include_str!("../../fixtures/parser/completed/completed.html"),
+        include_str!("../../fixtures/parser/search/search.html"),
+        include_str!("../../fixtures/parser/anime_list/page-1.html"),
+        include_str!("../../fixtures/parser/anime_detail/frieren.html"),
+        include_str!("../../fixtures/parser/episode/frieren-28.html"),
+    ];
+
+    /// Generate a tag name the parsers select on, or a few they don't
+    fn arbitrary_tag() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec![
+            "article", "div", "span", "a", "h1", "h2", "ul", "li", "img", "select", "option",
+            "video", "source", "iframe", "meta", "p", "b",
+        ])
+    }
+
+    /// Generate a class name the parsers select on
+    fn arbitrary_class() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec![
+            "",
+            "seventh",
+            "stylesix",
+            "bs",
+            "listupd",
+            "epin",
+            "type",
+            "sosev",
+            "status",
+            "typez",
+            "epx",
+            "scr",
+            "spe",
+            "genxed",
+            "desc",
+            "eplister",
+            "epl-num",
+            "epl-title",
+            "epl-date",
+            "mirror",
+            "entry-title",
+            "alter",
+            "thumb",
+            "trailerbutton",
+            "casts",
+            "ts-post-image",
+        ])
+    }
+
+    /// Generate an itemprop the parsers select on
+    fn arbitrary_itemprop() -> impl Strategy<Value = &'static str> {
+        prop::sample::select(vec!["", "headline", "url", "ratingValue"])
+    }
+
+    /// Generate an attribute value: URL-like, arbitrary Unicode, base64
+    /// garbage, or a base64-encoded video embed as mirror options carry
+    fn arbitrary_attr_value() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[a-z0-9/:.?=_-]{0,30}",
+            "\\PC{0,15}",
+            "[A-Za-z0-9+/=]{0,24}",
+            "\\PC{0,30}".prop_map(|src| STANDARD.encode(format!(r#"<source src="{}">"#, src))),
+        ]
+    }
+
+    /// Generate markup from the parsers' own tags and classes, nested a few
+    /// levels deep, with unclosed elements and stray fragments
+    fn arbitrary_markup() -> impl Strategy<Value = String> {
+        let leaf = prop_oneof![
+            "\\PC{0,20}",
+            Just(String::new()),
+            Just("</div>".to_string()),
+            Just("<a href=".to_string()),
+        ];
+
+        leaf.prop_recursive(4, 64, 6, |inner| {
+            (
+                arbitrary_tag(),
+                arbitrary_class(),
+                arbitrary_itemprop(),
+                arbitrary_attr_value(),
+                prop::collection::vec(inner, 0..6),
+                any::<bool>(),
+            )
+                .prop_map(|(tag, class, itemprop, value, children, close)| {
+                    format!(
+                        r#"<{tag} class="{class}" itemprop="{itemprop}" href="{value}" src="{value}" data-src="{value}" value="{value}" content="{value}">{children}{end}"#,
+                        children = children.concat(),
+                        end = if close {
+                            format!("</{}>", tag)
+                        } else {
+                            String::new()
+                        },
+                    )
+                })
+        })
+    }
+
+    /// Cut `html` off at `fraction` of its length, on a char boundary
+    fn truncate_at(html: &str, fraction: f64) -> String {
+        let mut cut = (html.len() as f64 * fraction) as usize;
+        while !html.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        html[..cut].to_string()
+    }
+
+    /// Nest `body` inside `depth` containers, each holding two copies of
+    /// everything inside it
+    fn nest_duplicates(body: &str, class: &str, depth: usize) -> String {
+        let mut html = body.to_string();
+        for _ in 0..depth {
+            html = format!(
+                r#"<article class="{class}"><div class="listupd"><div class="eplister"><ul><li>{html}{html}</li></ul></div></div></article>"#,
+            );
+        }
+        format!("<html><body>{}</body></html>", html)
+    }
+
+    /// Generate a malformed page: random markup, optionally truncated or with
+    /// nested duplicates, or a truncated or duplicated seed page
+    fn arbitrary_malformed_page() -> impl Strategy<Value = String> {
+        let body = prop::collection::vec(arbitrary_markup(), 0..8)
+            .prop_map(|parts| parts.concat())
+            .boxed();
+        let seed = prop::sample::select(SEED_PAGES.to_vec());
+
+        prop_oneof![
+            body.clone()
+                .prop_map(|body| format!("<html><body>{}</body></html>", body)),
+            (body.clone(), 0.0f64..1.0).prop_map(|(body, cut)| truncate_at(
+                &format!("<html><body>{}</body></html>", body),
+                cut
+            )),
+            (body, arbitrary_class(), 1usize..4)
+                .prop_map(|(body, class, depth)| nest_duplicates(&body, class, depth)),
+            (seed.clone(), 0.0f64..1.0).prop_map(|(page, cut)| truncate_at(page, cut)),
+            (seed, arbitrary_class(), 1usize..3)
+                .prop_map(|(page, class, depth)| nest_duplicates(page, class, depth)),
+        ]
+    }
+
+    /// Whether a slug was derived from every URL that has one to give
+    ///
+    /// Items are kept even without a URL (see the missing-elements tests),
+    /// but any URL with a path segment must yield a non-empty slug.
+    fn slug_is_usable(slug: &str, url: &str) -> bool {
+        !slug.contains('/') && (!slug.is_empty() || url.trim_matches('/').is_empty())
+    }
+
+    /// Check that parsed output survives a JSON round trip as valid UTF-8
+    fn check_utf8_roundtrip<T>(value: &T) -> Result<(), TestCaseError>
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let bytes = serde_json::to_vec(value).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        prop_assert!(
+            std::str::from_utf8(&bytes).is_ok(),
+            "Output is not valid UTF-8"
+        );
+        let parsed: T =
+            serde_json::from_slice(&bytes).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        prop_assert_eq!(&parsed, value);
+        Ok(())
+    }
+
+    /// Run every parser on `html` and check the invariants that must hold for
+    /// any input: no panics, usable slugs, and valid UTF-8 output
+    fn check_parser_invariants(html: &str) -> Result<(), TestCaseError> {
+        for update in parse_anime_updates(html) {
+            prop_assert!(
+                slug_is_usable(&update.slug, &update.series_url),
+                "Unusable slug: {:?}",
+                update
+            );
+            check_utf8_roundtrip(&update)?;
+        }
+
+        for anime in parse_completed_anime(html) {
+            prop_assert!(
+                slug_is_usable(&anime.slug, &anime.url),
+                "Unusable slug: {:?}",
+                anime
+            );
+            check_utf8_roundtrip(&anime)?;
+        }
+
+        for result in parse_search_results(html) {
+            prop_assert!(
+                slug_is_usable(&result.slug, &result.url),
+                "Unusable slug: {:?}",
+                result
+            );
+            check_utf8_roundtrip(&result)?;
+        }
+
+        for item in parse_anime_list(html) {
+            prop_assert!(
+                slug_is_usable(&item.slug, &item.url),
+                "Unusable slug: {:?}",
+                item
+            );
+            check_utf8_roundtrip(&item)?;
+        }
+
+        let detail = parse_anime_detail(html);
+        for episode in detail.episodes.iter().chain(&parse_episode_list(html)) {
+            prop_assert!(
+                slug_is_usable(&episode.slug, &episode.url),
+                "Unusable slug: {:?}",
+                episode
+            );
+        }
+        check_utf8_roundtrip(&detail)?;
+
+        let episode = parse_episode_detail(html);
+        for source in &episode.sources {
+            prop_assert!(!source.url.is_empty(), "Source without URL: {:?}", source);
+        }
+        check_utf8_roundtrip(&episode)?;
+
+        Ok(())
+    }
+
+    proptest! {
+        /// Property: Parsers tolerate malformed HTML
+        ///
+        /// For any truncated, unclosed, or nested-duplicate markup, no parser
+        /// panics, every item with a URL has a slug, and all output is valid UTF-8.
+        #[test]
+        fn property_parsers_tolerate_malformed_html(html in arbitrary_malformed_page()) {
+            check_parser_invariants(&html)?;
+        }
+
+        /// Property: Mirror options tolerate arbitrary values and labels
+        ///
+        /// For any option value (valid base64 or not) and any option text, the
+        /// episode parser neither panics nor emits sources without a URL.
+        #[test]
+        fn property_mirror_options_tolerate_arbitrary_values(
+            value in arbitrary_attr_value(),
+            label in "\\PC{0,30}",
+        ) {
+            let html = format!(
+                r#"<html><body><select class="mirror"><option value="{}">{}</option></select></body></html>"#,
+                value, label
+            );
+            check_parser_invariants(&html)?;
+        }
+    }
 }
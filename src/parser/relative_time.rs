@@ -0,0 +1,123 @@
+//! Relative time phrases
+//!
+//! The latest updates list says when an episode was posted relative to the
+//! page ("Dipos pada: 2 jam lalu", "2 hours ago"). These helpers turn such
+//! phrases into absolute times, counting back from when the page was fetched.
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Resolve the first relative time phrase in `text`
+///
+/// Understands Indonesian ("2 jam yang lalu", "sehari lalu", "kemarin",
+/// "baru saja") and English ("2 hours ago", "an hour ago", "yesterday",
+/// "just now") phrases, possibly surrounded by other text. Months count as
+/// 30 days and years as 365.
+///
+/// # Arguments
+/// * `text` - Text holding the phrase
+/// * `anchor` - Time the phrase is relative to, usually the fetch time
+///
+/// # Returns
+/// The absolute time, or `None` if the text holds no relative time
+pub fn parse_relative_time(text: &str, anchor: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    for (i, word) in words.iter().enumerate() {
+        match *word {
+            "kemarin" | "yesterday" => return Some(anchor - Duration::days(1)),
+            "just" if words.get(i + 1) == Some(&"now") => return Some(anchor),
+            "baru" if words.get(i + 1) == Some(&"saja") => return Some(anchor),
+            _ => {}
+        }
+
+        // "2 jam", "an hour", or a glued Indonesian "sejam"
+        let (amount, unit) = match unit_duration(word) {
+            Some(unit) => match i.checked_sub(1).and_then(|j| amount(words[j])) {
+                Some(amount) => (amount, unit),
+                None => continue,
+            },
+            None => match word.strip_prefix("se").and_then(unit_duration) {
+                Some(unit) => (1, unit),
+                None => continue,
+            },
+        };
+
+        let rest = &words[i + 1..];
+        let is_past = matches!(rest, ["ago", ..] | ["lalu", ..] | ["yang", "lalu", ..]);
+        if is_past {
+            let offset = unit.checked_mul(amount)?;
+            return anchor.checked_sub_signed(offset);
+        }
+    }
+    None
+}
+
+/// Number of units a word stands for ("2", "a", "an", "satu")
+fn amount(word: &str) -> Option<i32> {
+    match word {
+        "a" | "an" | "one" | "satu" => Some(1),
+        _ => word.parse().ok(),
+    }
+}
+
+/// Length of one unit named by a word, singular or plural
+fn unit_duration(word: &str) -> Option<Duration> {
+    let unit = match word {
+        "detik" | "second" | "seconds" | "sec" | "secs" => Duration::seconds(1),
+        "menit" | "minute" | "minutes" | "min" | "mins" => Duration::minutes(1),
+        "jam" | "hour" | "hours" => Duration::hours(1),
+        "hari" | "day" | "days" => Duration::days(1),
+        "minggu" | "pekan" | "week" | "weeks" => Duration::weeks(1),
+        "bulan" | "month" | "months" => Duration::days(30),
+        "tahun" | "year" | "years" => Duration::days(365),
+        _ => return None,
+    };
+    Some(unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relative_time() {
+        let anchor = DateTime::parse_from_rfc3339("2024-12-27T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |text: &str| parse_relative_time(text, anchor);
+
+        assert_eq!(
+            at("Dipos pada: 2 jam lalu"),
+            Some(anchor - Duration::hours(2))
+        );
+        assert_eq!(at("2 jam yang lalu"), Some(anchor - Duration::hours(2)));
+        assert_eq!(
+            at("15 menit yang lalu"),
+            Some(anchor - Duration::minutes(15))
+        );
+        assert_eq!(at("sehari lalu"), Some(anchor - Duration::days(1)));
+        assert_eq!(
+            at("Dipos pada: 3 minggu lalu"),
+            Some(anchor - Duration::weeks(3))
+        );
+        assert_eq!(at("1 bulan yang lalu"), Some(anchor - Duration::days(30)));
+        assert_eq!(at("kemarin"), Some(anchor - Duration::days(1)));
+        assert_eq!(at("Baru saja"), Some(anchor));
+
+        assert_eq!(at("2 hours ago"), Some(anchor - Duration::hours(2)));
+        assert_eq!(at("an hour ago"), Some(anchor - Duration::hours(1)));
+        assert_eq!(at("Posted 1 year ago"), Some(anchor - Duration::days(365)));
+        assert_eq!(at("Yesterday"), Some(anchor - Duration::days(1)));
+        assert_eq!(at("just now"), Some(anchor));
+
+        assert_eq!(at(""), None);
+        assert_eq!(at("December 27, 2024"), None);
+        assert_eq!(at("2 jam"), None);
+        assert_eq!(at("in 2 hours"), None);
+        assert_eq!(at("99999999999 years ago"), None);
+    }
+}
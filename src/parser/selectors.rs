@@ -0,0 +1,352 @@
+//! CSS selectors used by the parsers
+//!
+//! All selectors are defined here, grouped per page type, and compiled once
+//! on first use instead of on every parse. [`init`] compiles them eagerly
+//! so an invalid selector fails startup rather than the first request; the
+//! parsers return empty results if compilation failed.
+//!
+//! A selector profile (SELECTOR_PROFILE) overrides individual selectors
+//! without a rebuild, as a hot-fix when the source site changes its markup.
+//! It is a JSON object of sets and fields, named as in the `selector_set!`
+//! definitions below:
+//!
+//! ```json
+//! { "updates": { "article": "article.eighth" } }
+//! ```
+//!
+//! [`load_profile`] swaps the compiled selectors atomically; parses already
+//! running finish with the selectors they started with.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+use scraper::Selector;
+use thiserror::Error;
+
+/// A selector definition that failed to compile
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid selector {css:?}: {message}")]
+pub struct SelectorError {
+    /// CSS of the selector
+    pub css: String,
+    /// Parse error reported by the selector engine
+    pub message: String,
+}
+
+/// Errors loading a selector profile
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("Failed to read selector profile {0}: {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Invalid selector profile {0}: {1}")]
+    Json(String, serde_json::Error),
+
+    /// A set or field name that doesn't exist, as "set" or "set.field"
+    #[error("Unknown selector {0}")]
+    UnknownSelector(String),
+
+    #[error(transparent)]
+    Selector(#[from] SelectorError),
+}
+
+/// Selector overrides: CSS by field name, by set name
+pub type SelectorProfile = HashMap<String, HashMap<String, String>>;
+
+fn compile(css: &str) -> Result<Selector, SelectorError> {
+    Selector::parse(css).map_err(|e| SelectorError {
+        css: css.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Define a struct of named selectors with a fallible constructor
+macro_rules! selector_set {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $($(#[$field_meta:meta])* $field:ident: $css:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        pub(crate) struct $name {
+            $($(#[$field_meta])* pub $field: Selector,)*
+        }
+
+        impl $name {
+            /// Names of the selectors in the set
+            const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            /// Compile the set, with the CSS in `overrides` replacing the
+            /// defaults
+            fn compile(overrides: Option<&HashMap<String, String>>) -> Result<Self, SelectorError> {
+                let css = |field: &str, default: &'static str| {
+                    overrides
+                        .and_then(|overrides| overrides.get(field))
+                        .map_or(default, String::as_str)
+                };
+                Ok(Self {
+                    $($field: compile(css(stringify!($field), $css))?,)*
+                })
+            }
+        }
+    };
+}
+
+selector_set! {
+    /// Latest updates on the home page (`article.seventh`)
+    UpdateSelectors {
+        article: "article.seventh",
+        title: "h2[itemprop=\"headline\"] a",
+        url: "a[itemprop=\"url\"]",
+        thumbnail: "img.ts-post-image",
+        episode_number: "div.epin",
+        anime_type: "span.type",
+        series: "div.sosev span a",
+        release_info: "div.sosev span",
+        link: "a",
+        status: "span.status",
+    }
+}
+
+selector_set! {
+    /// Completed anime list (`article.stylesix`)
+    CompletedSelectors {
+        article: "article.stylesix",
+        title: "h2[itemprop=\"headline\"] a",
+        url: "a[itemprop=\"url\"]",
+        thumbnail: "img.ts-post-image",
+        anime_type: "div.typez",
+        episode_count: "span.epx",
+        rating: "span.scr",
+        genre: "a[rel=\"tag\"]",
+        li: "li",
+        series_link: "a",
+    }
+}
+
+selector_set! {
+    /// Search results and the paginated anime list (`article.bs`)
+    ListingSelectors {
+        listupd: "div.listupd",
+        article: "article.bs",
+        title: "h2[itemprop=\"headline\"]",
+        url: "a[itemprop=\"url\"]",
+        thumbnail: "img.ts-post-image",
+        status: "div.status",
+        anime_type: "div.typez",
+        episode_status: "span.epx",
+    }
+}
+
+selector_set! {
+    /// Home page popular widgets: today's row (`div.hothome article.bs`) and
+    /// the ranked weekly and all-time sidebar lists
+    PopularSelectors {
+        today: "div.hothome article.bs",
+        week: "div.wpop-weekly li",
+        all: "div.wpop-alltime li",
+        rank: "div.ctr",
+        title: "h2[itemprop=\"headline\"], h2 a.series, h4 a.series",
+        url: "a[itemprop=\"url\"], a.series",
+        thumbnail: "img",
+        genre: "a[rel=\"tag\"]",
+        rating: "div.numscore, span.scr",
+    }
+}
+
+selector_set! {
+    /// Anime detail page metadata
+    DetailSelectors {
+        title: "h1.entry-title",
+        alternate_titles: "span.alter",
+        poster: "div.thumb img",
+        rating: "meta[itemprop=\"ratingValue\"]",
+        trailer: "a.trailerbutton",
+        spe_span: "div.spe span",
+        casts: "a.casts",
+        genres: "div.genxed a",
+        synopsis: "div.desc",
+    }
+}
+
+selector_set! {
+    /// Episode list on the anime detail page (`div.eplister`)
+    EpisodeListSelectors {
+        item: "div.eplister ul li",
+        number: "div.epl-num",
+        title: "div.epl-title",
+        url: "a",
+        date: "div.epl-date",
+    }
+}
+
+selector_set! {
+    /// Episode page video player and mirrors
+    EpisodeSelectors {
+        title: "h1.entry-title",
+        default_video: "div#embed_holder video source",
+        mirror_option: "select.mirror option",
+        subtitle_track: "div#embed_holder video track",
+    }
+}
+
+selector_set! {
+    /// Video elements inside a decoded mirror option
+    EmbedSelectors {
+        source: "source",
+        video: "video",
+        iframe: "iframe",
+        embed: "embed",
+        track: "track",
+    }
+}
+
+/// Every selector set, compiled
+pub(crate) struct Selectors {
+    pub updates: UpdateSelectors,
+    pub completed: CompletedSelectors,
+    pub listing: ListingSelectors,
+    pub popular: PopularSelectors,
+    pub detail: DetailSelectors,
+    pub episode_list: EpisodeListSelectors,
+    pub episode: EpisodeSelectors,
+    pub embed: EmbedSelectors,
+}
+
+impl Selectors {
+    /// Compile every set, with `profile` overriding the defaults
+    fn compile(profile: &SelectorProfile) -> Result<Self, ProfileError> {
+        for (set, fields) in profile {
+            let known = match set.as_str() {
+                "updates" => UpdateSelectors::FIELDS,
+                "completed" => CompletedSelectors::FIELDS,
+                "listing" => ListingSelectors::FIELDS,
+                "popular" => PopularSelectors::FIELDS,
+                "detail" => DetailSelectors::FIELDS,
+                "episode_list" => EpisodeListSelectors::FIELDS,
+                "episode" => EpisodeSelectors::FIELDS,
+                "embed" => EmbedSelectors::FIELDS,
+                _ => return Err(ProfileError::UnknownSelector(set.clone())),
+            };
+            if let Some(field) = fields.keys().find(|field| !known.contains(&field.as_str())) {
+                return Err(ProfileError::UnknownSelector(format!("{}.{}", set, field)));
+            }
+        }
+        Ok(Self::compile_sets(profile)?)
+    }
+
+    /// Compile every set; names in `profile` must have been checked
+    fn compile_sets(profile: &SelectorProfile) -> Result<Self, SelectorError> {
+        Ok(Self {
+            updates: UpdateSelectors::compile(profile.get("updates"))?,
+            completed: CompletedSelectors::compile(profile.get("completed"))?,
+            listing: ListingSelectors::compile(profile.get("listing"))?,
+            popular: PopularSelectors::compile(profile.get("popular"))?,
+            detail: DetailSelectors::compile(profile.get("detail"))?,
+            episode_list: EpisodeListSelectors::compile(profile.get("episode_list"))?,
+            episode: EpisodeSelectors::compile(profile.get("episode"))?,
+            embed: EmbedSelectors::compile(profile.get("embed"))?,
+        })
+    }
+}
+
+/// Selectors in use; the defaults until a profile is loaded
+static SELECTORS: ArcSwapOption<Selectors> = ArcSwapOption::const_empty();
+
+/// The compiled selectors, compiling the defaults on first call
+pub(crate) fn get() -> Result<Arc<Selectors>, SelectorError> {
+    if let Some(selectors) = SELECTORS.load_full() {
+        return Ok(selectors);
+    }
+    let defaults = Arc::new(Selectors::compile_sets(&SelectorProfile::new())?);
+    // A profile loaded meanwhile wins over the defaults
+    let previous = SELECTORS.compare_and_swap(&None::<Arc<Selectors>>, Some(defaults.clone()));
+    Ok((*previous).clone().unwrap_or(defaults))
+}
+
+/// Compile all parser selectors
+///
+/// Call once at startup. Later calls return the selectors in use.
+///
+/// # Returns
+/// * `Ok(())` - All selectors compiled
+/// * `Err(SelectorError)` - The first selector that failed to compile
+pub fn init() -> Result<(), SelectorError> {
+    get().map(|_| ())
+}
+
+/// Read a selector profile from a JSON file
+pub fn read_profile(path: &str) -> Result<SelectorProfile, ProfileError> {
+    let json = std::fs::read_to_string(path).map_err(|e| ProfileError::Io(path.to_string(), e))?;
+    serde_json::from_str(&json).map_err(|e| ProfileError::Json(path.to_string(), e))
+}
+
+/// Compile the selectors with the profile at `path` (the defaults when
+/// `None`) and put them in use
+///
+/// Nothing changes if the profile can't be read or a selector in it is
+/// invalid.
+pub fn load_profile(path: Option<&str>) -> Result<(), ProfileError> {
+    let profile = match path {
+        Some(path) => read_profile(path)?,
+        None => SelectorProfile::new(),
+    };
+    SELECTORS.store(Some(Arc::new(Selectors::compile(&profile)?)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_selectors_compile() {
+        assert_eq!(init(), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_selector_reports_css() {
+        let err = compile("div[").unwrap_err();
+        assert_eq!(err.css, "div[");
+        assert!(err.to_string().contains("div["));
+    }
+
+    fn profile(json: &str) -> SelectorProfile {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_profile_overrides_selectors() {
+        let selectors =
+            Selectors::compile(&profile(r#"{"updates": {"article": "article.eighth"}}"#)).unwrap();
+        let html = scraper::Html::parse_fragment(
+            r#"<article class="seventh"></article><article class="eighth"></article>"#,
+        );
+        assert_eq!(html.select(&selectors.updates.article).count(), 1);
+        assert!(html.select(&selectors.updates.article).all(|el| el
+            .value()
+            .has_class("eighth", scraper::CaseSensitivity::CaseSensitive)));
+    }
+
+    #[test]
+    fn test_profile_errors() {
+        assert!(matches!(
+            Selectors::compile(&profile(r#"{"updatez": {"article": "a"}}"#)),
+            Err(ProfileError::UnknownSelector(name)) if name == "updatez"
+        ));
+        assert!(matches!(
+            Selectors::compile(&profile(r#"{"updates": {"artcle": "a"}}"#)),
+            Err(ProfileError::UnknownSelector(name)) if name == "updates.artcle"
+        ));
+        assert!(matches!(
+            Selectors::compile(&profile(r#"{"episode": {"title": "h1["}}"#)),
+            Err(ProfileError::Selector(e)) if e.css == "h1["
+        ));
+        assert!(matches!(
+            read_profile("/nonexistent/selectors.json"),
+            Err(ProfileError::Io(..))
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+//! Reloading configuration without a restart
+//!
+//! On SIGHUP (see [`spawn_reload_on_sighup`]) or POST /api/admin/reload, the
+//! .env file and environment are read again and take effect for requests
+//! from then on, while connections in flight are kept:
+//! - the selector profile (SELECTOR_PROFILE), re-read even if the path is
+//!   unchanged, so a parser hot-fix is an edit of the file and a reload
+//! - the video server lists (VIDEO_SERVER_BLACKLIST, VIDEO_SERVER_PRIORITY),
+//!   merged again with the admin rules
+//! - settings read per request, like cache TTLs and rate limits (see
+//!   [`Config::reloaded`] for those that need a restart)
+//!
+//! Everything is read and checked before anything is swapped in, so a
+//! reload that fails leaves the running configuration as it was.
+
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::config::{Config, ConfigError};
+use crate::db::{get_video_server_rules, RepositoryError};
+use crate::models::ConfigReload;
+use crate::parser::selectors::{self, ProfileError};
+use crate::routes::AppState;
+use crate::video_servers::config_rules;
+
+/// Errors reloading the configuration
+#[derive(Debug, Error)]
+pub enum ReloadError {
+    /// A setting is missing or invalid
+    #[error("Invalid configuration: {0}")]
+    Config(#[from] ConfigError),
+
+    /// The selector profile is unreadable or a selector in it is invalid
+    #[error("Failed to load parser selectors: {0}")]
+    Selectors(#[from] ProfileError),
+
+    /// Admin video server rules couldn't be loaded
+    #[error("Failed to load video server rules: {0}")]
+    VideoServers(#[from] RepositoryError),
+}
+
+/// Read the configuration again and put it in use
+///
+/// # Returns
+/// * `Ok(ConfigReload)` - What is in effect now
+/// * `Err(ReloadError)` - Nothing was changed
+pub async fn reload(state: &AppState) -> Result<ConfigReload, ReloadError> {
+    let admin_rules = get_video_server_rules(state.db.pool()).await?;
+
+    let fresh = Config::from_env_file()?;
+    let config = state.config.load().reloaded(fresh);
+
+    selectors::load_profile(config.selector_profile.as_deref())?;
+    state.video_servers.replace(
+        config_rules(
+            &config.video_server_blacklist,
+            &config.video_server_priority,
+        ),
+        admin_rules,
+    );
+    let selector_profile = config.selector_profile.clone();
+    state.config.store(Arc::new(config));
+
+    Ok(ConfigReload {
+        selector_profile,
+        video_server_rules: state.video_servers.all().len(),
+        reloaded_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Reload the configuration whenever the process receives SIGHUP
+///
+/// Call once, from inside the Tokio runtime. Failed reloads are logged and
+/// the running configuration kept.
+#[cfg(unix)]
+pub fn spawn_reload_on_sighup(state: actix_web::web::Data<AppState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    use tracing::{error, info};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            error!("Failed to listen for SIGHUP, reload disabled: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match reload(&state).await {
+                Ok(reloaded) => info!(
+                    "Configuration reloaded on SIGHUP ({} video server rules, selector profile {})",
+                    reloaded.video_server_rules,
+                    reloaded.selector_profile.as_deref().unwrap_or("none")
+                ),
+                Err(e) => error!(
+                    "Configuration reload failed, keeping the current one: {}",
+                    e
+                ),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    #[ignore] // Requires database connection
+    async fn test_reload_keeps_config_on_error() {
+        dotenvy::dotenv().ok();
+        if std::env::var("JWT_SECRET").is_err() {
+            std::env::set_var("JWT_SECRET", "test-secret-for-reload");
+        }
+        let state = crate::routes::init(Config::from_env())
+            .await
+            .expect("Failed to initialize");
+        let before = state.config.load_full();
+
+        std::env::set_var("SELECTOR_PROFILE", "/nonexistent/selectors.json");
+        let result = reload(&state).await;
+        std::env::remove_var("SELECTOR_PROFILE");
+        assert!(matches!(result, Err(ReloadError::Selectors(_))));
+        assert!(Arc::ptr_eq(&before, &state.config.load_full()));
+
+        std::env::set_var("TLS_CERT_FILE", "/nonexistent/cert.pem");
+        let result = reload(&state).await;
+        std::env::remove_var("TLS_CERT_FILE");
+        match result {
+            Err(ReloadError::Config(e)) => assert_eq!(
+                e.to_string(),
+                "TLS_CERT_FILE and TLS_KEY_FILE must be set together"
+            ),
+            other => panic!("Expected a config error, got {:?}", other.map(|_| ())),
+        }
+        assert!(Arc::ptr_eq(&before, &state.config.load_full()));
+
+        let reloaded = reload(&state).await.expect("Failed to reload");
+        assert_eq!(reloaded.selector_profile, None);
+        assert!(!Arc::ptr_eq(&before, &state.config.load_full()));
+        assert_eq!(state.config.load().database_url, before.database_url);
+    }
+}
@@ -0,0 +1,2969 @@
+//! Admin routes for the Anime Scraper API
+//!
+//! This module contains HTTP route handlers for operator endpoints. Each route
+//! requires an authenticated user of the default tenant holding the route's
+//! permission (see [`crate::auth::permissions`]), either through a role or
+//! the admin flag, since jobs, emails, and tenants span every tenant:
+//! - GET /api/admin/jobs - Inspect background job queue depth and failures
+//! - POST /api/admin/jobs/:id/retry - Requeue a dead-lettered job
+//! - GET /api/admin/emails - List email deliveries and their status
+//! - POST /api/admin/emails/:id/resend - Queue another send of an email
+//! - GET /api/admin/tenants - List tenants
+//! - POST /api/admin/tenants - Create a tenant
+//! - GET /api/admin/anime/:slug/diff - Compare a stored anime with a fresh scrape
+//! - POST /api/admin/anime/merge - Merge a duplicate anime into another
+//! - GET /api/admin/parser/golden - Re-parse fixture pages and diff against goldens
+//! - GET /api/admin/search-analytics - Popular and zero-result search queries
+//! - POST /api/admin/maintenance/:action/confirm - Get a confirmation token for a maintenance action
+//! - POST /api/admin/maintenance/:action - Run a confirmed maintenance action
+//! - GET /api/admin/gaps - Anime missing episodes below their highest or final one
+//! - GET /api/admin/integrity - Latest consistency check report
+//! - POST /api/admin/integrity - Queue a consistency check, optionally with auto-repair
+//! - GET /api/admin/roles - List roles
+//! - POST /api/admin/roles - Create a role
+//! - PUT /api/admin/roles/:id - Change a role's description or permissions
+//! - DELETE /api/admin/roles/:id - Delete a role
+//! - GET /api/admin/users/:id/roles - A user's roles and effective permissions
+//! - PUT /api/admin/users/:id/roles/:role_id - Grant a role to a user
+//! - DELETE /api/admin/users/:id/roles/:role_id - Revoke a role from a user
+//! - GET /api/admin/moderation - List reported content awaiting review
+//! - GET /api/admin/moderation/:id - A reported item and its reports
+//! - POST /api/admin/moderation/:id/approve - Keep reported content
+//! - POST /api/admin/moderation/:id/remove - Remove reported content and strike its author
+//! - GET /api/admin/moderation/users/:id - A user's strikes and mute
+//! - DELETE /api/admin/moderation/users/:id/mute - Lift a user's mute
+//! - GET /api/admin/erasures - Log of accounts erased by their users
+//! - GET /api/admin/video-servers - Video server rules in effect
+//! - PUT /api/admin/video-servers/:server - Block or prioritize a video server
+//! - DELETE /api/admin/video-servers/:server - Remove an admin rule
+//! - GET /api/admin/flags - Feature flags in effect
+//! - PUT /api/admin/flags/:feature - Switch a feature on or off
+//! - DELETE /api/admin/flags/:feature - Reset a feature to enabled
+//! - POST /api/admin/tags - Create a curated tag
+//! - PUT /api/admin/tags/:id - Rename, describe, or curate a tag
+//! - DELETE /api/admin/tags/:id - Delete a tag
+//! - GET /api/admin/anime/:slug/age-rating - An anime's derived and overridden age rating
+//! - PUT /api/admin/anime/:slug/age-rating - Override an anime's age rating
+//! - DELETE /api/admin/anime/:slug/age-rating - Remove an age rating override
+//! - GET /api/admin/anomalies - Recent anomalous responses from the source site
+//! - GET /api/admin/bandwidth - Crawler bandwidth use against the daily quota
+//! - GET /api/admin/db-pool - Database connection pool utilization
+//! - POST /api/admin/reload - Reload the selector profile, video server lists, and settings
+
+use std::collections::HashMap;
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::permissions::{
+    self, ensure_permission, is_valid_role_name, normalize_permissions, AnalyticsRead, AnimeManage,
+    CachePurge, CommentsModerate, EmailsManage, JobsManage, MaintenanceRun, Permission,
+    RequiredPermission, RolesManage, TenantsManage, UsersRead,
+};
+use crate::auth::signing::SignatureError;
+use crate::auth::Auth;
+use crate::constants::endpoints;
+use crate::crawler::bandwidth::bandwidth_report;
+use crate::db::{
+    assign_role, create_role, create_tag, create_tenant, delete_age_rating_override,
+    delete_all_anime_updates, delete_all_cache_entries, delete_all_completed_anime,
+    delete_all_crawled_anime, delete_feature_flag, delete_orphaned_episodes,
+    delete_orphaned_video_sources, delete_role, delete_tag, delete_video_server_rule,
+    encrypt_user_data, get_anime_age_rating, get_anime_detail, get_content_reports,
+    get_email_deliveries, get_email_delivery, get_failed_jobs, get_job_queue_stats,
+    get_latest_completed_job, get_moderation_item, get_moderation_queue, get_moderation_standing,
+    get_popular_searches, get_roles, get_user_roles, get_zero_result_searches, list_data_erasures,
+    merge_anime, reindex_tables, retry_dead_job, set_age_rating_override, set_feature_flag,
+    set_video_server_rule, unassign_role, unmute_user, update_role, update_tag, vacuum_tables,
+    RepositoryError, RepositoryResult, MAINTENANCE_TABLES,
+};
+use crate::jobs;
+use crate::middleware::Slug;
+use crate::models::{
+    AgeRatingOverrideRequest, AnimeAgeRating, AnimeDiff, AnimeMergeResult, ApiError, ApiResponse,
+    ConfigReload, CrawlerBandwidthReport, CreateRoleRequest, CreateTagRequest, CreateTenantRequest,
+    DataErasure, DbPoolReport, EmailDelivery, EpisodeGapReport, ErrorCode, Feature, FeatureFlag,
+    FeatureFlagRequest, IntegrityReport, JobRecord, JobsOverview, MaintenanceAction,
+    MaintenanceResult, MergeAnimeRequest, ModerationDecision, ModerationItem, ModerationItemDetail,
+    ModerationResolution, ModerationStanding, ModerationStatus, Role, SearchAnalytics, SignedUrl,
+    TableRowCount, Tag, Tenant, UpdateRoleRequest, UpdateTagRequest, UpstreamAnomalyReport,
+    UserRoles, VideoServerRule, VideoServerRuleRequest,
+};
+use crate::moderation::{self, ModerationError};
+use crate::parser::golden::{check_fixtures, GoldenReport};
+use crate::parser::parse_anime_detail;
+use crate::reload::{self, ReloadError};
+use crate::routes::tags::validate_tag_name;
+use crate::routes::AppState;
+use crate::tenants::is_valid_tenant_slug;
+use crate::video_servers::normalize_server;
+
+/// Number of recent failures included in the jobs overview
+const RECENT_FAILURES_LIMIT: i64 = 50;
+
+/// Lifetime of a maintenance confirmation token
+const MAINTENANCE_CONFIRM_TTL_SECS: i64 = 300;
+
+/// Query parameter binding a maintenance confirmation token to its user
+const PARAM_CONFIRM_USER: &str = "user";
+
+/// GET /api/admin/jobs - Get background job queue overview
+///
+/// Requires the `jobs:manage` permission.
+///
+/// # Responses
+/// - 200: Per-queue counts and the most recent failures
+/// - 401: Not authenticated
+/// - 403: Missing the `jobs:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Job queue overview", body = ApiResponse<JobsOverview>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_jobs_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<JobsManage>,
+) -> impl Responder {
+    let pool = data.db.pool();
+
+    let queues = match get_job_queue_stats(pool).await {
+        Ok(queues) => queues,
+        Err(e) => {
+            error!("Failed to get job queue stats: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get job queue stats",
+            ));
+        }
+    };
+
+    match get_failed_jobs(pool, RECENT_FAILURES_LIMIT).await {
+        Ok(recent_failures) => HttpResponse::Ok().json(ApiResponse::new(JobsOverview {
+            queues,
+            recent_failures,
+        })),
+        Err(e) => {
+            error!("Failed to get failed jobs: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get failed jobs",
+            ))
+        }
+    }
+}
+
+/// POST /api/admin/jobs/:id/retry - Requeue a dead-lettered job
+///
+/// Requires the `jobs:manage` permission. Resets the attempt counter so the job
+/// gets a full retry budget again.
+///
+/// # Responses
+/// - 200: Job requeued
+/// - 401: Not authenticated
+/// - 403: Missing the `jobs:manage` permission
+/// - 404: Job not found or not dead
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/{id}/retry",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Job ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Job requeued", body = ApiResponse<String>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Dead job not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn retry_job_handler(
+    data: web::Data<AppState>,
+    auth: Permission<JobsManage>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let job_id = path.into_inner();
+
+    match retry_dead_job(data.db.pool(), job_id).await {
+        Ok(true) => {
+            info!("Admin {} requeued job {}", auth.user_id, job_id);
+            HttpResponse::Ok().json(ApiResponse::new("Job requeued".to_string()))
+        }
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Dead job not found"))
+        }
+        Err(e) => {
+            error!("Failed to requeue job {}: {}", job_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to requeue job",
+            ))
+        }
+    }
+}
+
+/// Query parameters for the email deliveries endpoint
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct EmailDeliveriesQuery {
+    /// Status filter (queued, sent, failed)
+    pub status: Option<String>,
+    /// Maximum number of deliveries to return (default: 50, max: 500)
+    pub limit: Option<i64>,
+}
+
+/// GET /api/admin/emails - List email deliveries, newest first
+///
+/// Requires the `emails:manage` permission.
+///
+/// Query parameters:
+/// - status: Status filter (queued, sent, failed)
+/// - limit: Maximum number of deliveries (default: 50, max: 500)
+#[utoipa::path(
+    get,
+    path = "/api/admin/emails",
+    tag = "admin",
+    params(EmailDeliveriesQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Email deliveries retrieved", body = ApiResponse<Vec<EmailDelivery>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_email_deliveries_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<EmailsManage>,
+    query: web::Query<EmailDeliveriesQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    match get_email_deliveries(data.db.pool(), query.status.as_deref(), limit).await {
+        Ok(deliveries) => HttpResponse::Ok().json(ApiResponse::new(deliveries)),
+        Err(e) => {
+            error!("Failed to get email deliveries: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get email deliveries",
+            ))
+        }
+    }
+}
+
+/// POST /api/admin/emails/:id/resend - Queue another send of an email
+///
+/// Requires the `emails:manage` permission. Works for both failed and
+/// already-sent deliveries; the delivery is reset to queued.
+///
+/// # Responses
+/// - 200: Resend queued, returns the updated delivery
+/// - 401: Not authenticated
+/// - 403: Missing the `emails:manage` permission
+/// - 404: Delivery not found
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/emails/{id}/resend",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Email delivery ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Resend queued", body = ApiResponse<EmailDelivery>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Email delivery not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn resend_email_handler(
+    data: web::Data<AppState>,
+    auth: Permission<EmailsManage>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let delivery_id = path.into_inner();
+
+    match jobs::resend_email(pool, delivery_id).await {
+        Ok(Some(job)) => {
+            info!(
+                "Admin {} queued resend of email {} (job {})",
+                auth.user_id, delivery_id, job.id
+            );
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ApiError::new(
+                ErrorCode::NotFound,
+                "Email delivery not found",
+            ));
+        }
+        Err(e) => {
+            error!("Failed to queue resend of email {}: {}", delivery_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to queue resend",
+            ));
+        }
+    }
+
+    match get_email_delivery(pool, delivery_id).await {
+        Ok(Some(delivery)) => HttpResponse::Ok().json(ApiResponse::new(delivery)),
+        Ok(None) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "Email delivery not found",
+        )),
+        Err(e) => {
+            error!("Failed to get email delivery {}: {}", delivery_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get email delivery",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/tenants - List tenants
+///
+/// Requires the `tenants:manage` permission.
+///
+/// # Responses
+/// - 200: All tenants
+/// - 401: Not authenticated
+/// - 403: Missing the `tenants:manage` permission
+#[utoipa::path(
+    get,
+    path = "/api/admin/tenants",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Tenants retrieved", body = ApiResponse<Vec<Tenant>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError)
+    )
+)]
+pub async fn get_tenants_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<TenantsManage>,
+) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::new(data.tenants.all()))
+}
+
+/// POST /api/admin/tenants - Create a tenant
+///
+/// Requires the `tenants:manage` permission. The tenant is usable immediately;
+/// requests are routed to it by the tenant header or one of its hostnames.
+///
+/// # Request Body
+/// - slug: Unique identifier (lowercase letters, digits, and dashes)
+/// - name: Display name
+/// - hostnames: Hostnames that resolve to the tenant (optional)
+///
+/// # Responses
+/// - 200: Tenant created
+/// - 400: Invalid slug or name
+/// - 401: Not authenticated
+/// - 403: Missing the `tenants:manage` permission
+/// - 409: Slug already taken
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/tenants",
+    tag = "admin",
+    request_body = CreateTenantRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Tenant created", body = ApiResponse<Tenant>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 409, description = "Tenant already exists", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn create_tenant_handler(
+    data: web::Data<AppState>,
+    auth: Permission<TenantsManage>,
+    body: web::Json<CreateTenantRequest>,
+) -> impl Responder {
+    let slug = body.slug.trim();
+    if !is_valid_tenant_slug(slug) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Slug must be lowercase letters, digits, and dashes",
+        ));
+    }
+    if body.name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Name is required",
+        ));
+    }
+
+    let hostnames: Vec<String> = body
+        .hostnames
+        .iter()
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect();
+
+    let pool = data.db.pool();
+    let tenant = match create_tenant(pool, slug, body.name.trim(), &hostnames).await {
+        Ok(tenant) => tenant,
+        Err(RepositoryError::Conflict(msg)) => {
+            return HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg));
+        }
+        Err(e) => {
+            error!("Failed to create tenant: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create tenant",
+            ));
+        }
+    };
+
+    if let Err(e) = data.tenants.reload(pool).await {
+        warn!("Failed to reload tenants: {}", e);
+    }
+
+    info!("Admin {} created tenant {}", auth.user_id, tenant.slug);
+    HttpResponse::Ok().json(ApiResponse::new(tenant))
+}
+
+/// GET /api/admin/anime/{slug}/diff - Compare a stored anime with a fresh scrape
+///
+/// Requires the `anime:manage` permission. Scrapes the anime page and reports,
+/// field by field, how it differs from the stored record, without saving
+/// anything. Useful for spotting parser regressions before a refresh overwrites
+/// good data.
+///
+/// # Responses
+/// - 200: Field-by-field diff and the scraped record
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: Anime page not found or could not be parsed
+/// - 500: Database error
+/// - 502: Scraping failed
+#[utoipa::path(
+    get,
+    path = "/api/admin/anime/{slug}/diff",
+    tag = "admin",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Diff computed", body = ApiResponse<AnimeDiff>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Anime not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 502, description = "Scraping failed", body = ApiError)
+    )
+)]
+pub async fn anime_diff_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+    path: Slug,
+) -> impl Responder {
+    let slug = path.into_inner();
+    let stored = match get_anime_detail(data.db.pool(), &slug).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            error!("Failed to get stored anime detail for {}: {}", slug, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Database error: {}", e),
+            ));
+        }
+    };
+
+    let scraper = &data.scraper;
+    let scraped = match scraper
+        .fetch_page_no_delay(&endpoints::anime(&data.config.load().base_url, &slug))
+        .await
+    {
+        Ok(result) => parse_anime_detail(&result.html),
+        Err(e) => {
+            warn!("Failed to scrape {} for diff: {}", slug, e);
+            return HttpResponse::BadGateway().json(ApiError::new(
+                ErrorCode::UpstreamUnavailable,
+                format!("Failed to fetch data: {}", e),
+            ));
+        }
+    };
+
+    if scraped.title.is_empty() {
+        return HttpResponse::NotFound()
+            .json(ApiError::new(ErrorCode::AnimeNotFound, "Anime not found"));
+    }
+
+    HttpResponse::Ok().json(ApiResponse::new(AnimeDiff::between(
+        &slug,
+        stored.as_ref(),
+        scraped,
+    )))
+}
+
+/// POST /api/admin/anime/merge - Merge a duplicate anime into another
+///
+/// Requires the `anime:manage` permission. In one transaction, moves the
+/// episodes (with their video sources), favorites, subscriptions, history, and
+/// watched marks of `from` to `into`, removes the duplicate, and records `from`
+/// as an alias so the anime detail endpoint serves `into` for it.
+///
+/// # Responses
+/// - 200: Rows moved per table
+/// - 400: Missing slugs, or both slugs are the same
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: `into` is not a stored anime
+/// - 500: Database error
+#[utoipa::path(
+    post,
+    path = "/api/admin/anime/merge",
+    tag = "admin",
+    request_body = MergeAnimeRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Anime merged", body = ApiResponse<AnimeMergeResult>),
+        (status = 400, description = "Invalid slugs", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Target anime not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn merge_anime_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    body: web::Json<MergeAnimeRequest>,
+) -> impl Responder {
+    let from = body.from.trim();
+    let into = body.into.trim();
+    if from.is_empty() || into.is_empty() {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Both from and into are required",
+        ));
+    }
+    if from == into {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Cannot merge an anime into itself",
+        ));
+    }
+
+    match merge_anime(data.db.pool(), from, into).await {
+        Ok(Some(result)) => {
+            info!(
+                "Admin {} merged anime {} into {} ({} episodes, {} favorites)",
+                auth.user_id, from, into, result.episodes, result.favorites
+            );
+            HttpResponse::Ok().json(ApiResponse::new(result))
+        }
+        Ok(None) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::AnimeNotFound,
+            "Target anime not found",
+        )),
+        Err(e) => {
+            error!("Failed to merge anime {} into {}: {}", from, into, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Database error: {}", e),
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/parser/golden - Check the parsers against golden output
+///
+/// Requires the `anime:manage` permission. Re-parses every fixture page in
+/// PARSER_FIXTURES_DIR and reports field-level differences from the stored
+/// goldens. Goldens are never rewritten here; see `parser::golden`.
+///
+/// # Responses
+/// - 200: Per-fixture results (check `failed` and `missing`)
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: Fixtures directory not found
+/// - 500: A fixture or golden could not be read
+#[utoipa::path(
+    get,
+    path = "/api/admin/parser/golden",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Fixtures checked", body = ApiResponse<GoldenReport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Fixtures directory not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn parser_golden_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+) -> impl Responder {
+    let dir = std::path::PathBuf::from(&data.config.load().parser_fixtures_dir);
+    if !dir.is_dir() {
+        return HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            format!("Fixtures directory not found: {}", dir.display()),
+        ));
+    }
+
+    match tokio::task::spawn_blocking(move || check_fixtures(&dir, false)).await {
+        Ok(Ok(report)) => {
+            if !report.is_ok() {
+                warn!("Parser golden check failed:\n{}", report.render());
+            }
+            HttpResponse::Ok().json(ApiResponse::new(report))
+        }
+        Ok(Err(e)) => {
+            error!("Failed to check parser fixtures: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                format!("Failed to check fixtures: {}", e),
+            ))
+        }
+        Err(e) => {
+            error!("Parser golden check panicked: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to check fixtures",
+            ))
+        }
+    }
+}
+
+/// Query parameters for the search analytics endpoint
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct SearchAnalyticsQuery {
+    /// Maximum number of queries per list (default: 50, max: 500)
+    pub limit: Option<i64>,
+}
+
+/// GET /api/admin/search-analytics - Popular and zero-result search queries
+///
+/// Requires the `analytics:read` permission. Queries are counted under their
+/// normalized form; use the zero-result list to find titles worth crawling or
+/// aliasing.
+///
+/// Query parameters:
+/// - limit: Maximum number of queries per list (default: 50, max: 500)
+#[utoipa::path(
+    get,
+    path = "/api/admin/search-analytics",
+    tag = "admin",
+    params(SearchAnalyticsQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Search analytics retrieved", body = ApiResponse<SearchAnalytics>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn search_analytics_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnalyticsRead>,
+    query: web::Query<SearchAnalyticsQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let popular = match data.db.read(|pool| get_popular_searches(pool, limit)).await {
+        Ok(popular) => popular,
+        Err(e) => {
+            error!("Failed to get popular searches: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get search analytics",
+            ));
+        }
+    };
+
+    match data
+        .db
+        .read(|pool| get_zero_result_searches(pool, limit))
+        .await
+    {
+        Ok(zero_results) => HttpResponse::Ok().json(ApiResponse::new(SearchAnalytics {
+            popular,
+            zero_results,
+        })),
+        Err(e) => {
+            error!("Failed to get zero-result searches: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get search analytics",
+            ))
+        }
+    }
+}
+
+/// Path of a maintenance action, covered by its confirmation token
+fn maintenance_path(action: MaintenanceAction) -> String {
+    format!("/api/admin/maintenance/{}", action.as_str())
+}
+
+/// Permission a maintenance action requires
+///
+/// Clearing the cache is routine enough to hand out separately from
+/// destructive deletes and table maintenance.
+fn maintenance_permission(action: MaintenanceAction) -> &'static str {
+    match action {
+        MaintenanceAction::DeleteCacheEntries => CachePurge::NAME,
+        _ => MaintenanceRun::NAME,
+    }
+}
+
+/// 400 for an unknown action, listing the known ones
+fn unknown_maintenance_action(name: &str) -> HttpResponse {
+    let available: Vec<&str> = MaintenanceAction::ALL.iter().map(|a| a.as_str()).collect();
+    HttpResponse::BadRequest().json(
+        ApiError::new(
+            ErrorCode::ValidationFailed,
+            format!("Unknown maintenance action: {}", name),
+        )
+        .with_details(serde_json::json!({ "availableActions": available })),
+    )
+}
+
+/// Run a maintenance action, counting the rows it affected per table
+async fn run_maintenance(
+    data: &AppState,
+    action: MaintenanceAction,
+) -> RepositoryResult<MaintenanceResult> {
+    let pool = data.db.pool();
+    let count = |table: &str, rows: u64| TableRowCount {
+        table: table.to_string(),
+        rows,
+    };
+
+    let tables = match action {
+        MaintenanceAction::DeleteAnimeUpdates => {
+            vec![count(
+                "anime_updates",
+                delete_all_anime_updates(pool).await?,
+            )]
+        }
+        MaintenanceAction::DeleteCompletedAnime => {
+            vec![count(
+                "completed_anime",
+                delete_all_completed_anime(pool).await?,
+            )]
+        }
+        MaintenanceAction::DeleteCrawledAnime => {
+            vec![count(
+                "crawled_anime",
+                delete_all_crawled_anime(pool).await?,
+            )]
+        }
+        MaintenanceAction::DeleteCacheEntries => {
+            vec![count(
+                "cache_metadata",
+                delete_all_cache_entries(pool).await?,
+            )]
+        }
+        MaintenanceAction::DeleteOrphans => {
+            // Episodes first, so sources of the episodes it removes go too
+            let episodes = delete_orphaned_episodes(pool).await?;
+            let sources = delete_orphaned_video_sources(pool).await?;
+            vec![count("episodes", episodes), count("video_sources", sources)]
+        }
+        MaintenanceAction::Vacuum => {
+            vacuum_tables(pool).await?;
+            MAINTENANCE_TABLES.iter().map(|t| count(t, 0)).collect()
+        }
+        MaintenanceAction::Reindex => {
+            reindex_tables(pool).await?;
+            MAINTENANCE_TABLES.iter().map(|t| count(t, 0)).collect()
+        }
+        MaintenanceAction::EncryptUserData => {
+            vec![count("users", encrypt_user_data(pool).await?)]
+        }
+    };
+
+    Ok(MaintenanceResult::new(action, tables))
+}
+
+/// POST /api/admin/maintenance/{action}/confirm - Get a confirmation token
+///
+/// Requires the action's permission: `cache:purge` for delete-cache-entries,
+/// `maintenance:run` for the rest. Returns the signed URL to POST to run the
+/// action; its query is the confirmation token. The token only works for
+/// this action and this user, and expires after five minutes.
+///
+/// Actions: delete-anime-updates, delete-completed-anime,
+/// delete-crawled-anime, delete-cache-entries, delete-orphans, vacuum, reindex,
+/// encrypt-user-data
+///
+/// # Responses
+/// - 200: Signed URL that runs the action
+/// - 400: Unknown action
+/// - 401: Not authenticated
+/// - 403: Missing the action's permission
+#[utoipa::path(
+    post,
+    path = "/api/admin/maintenance/{action}/confirm",
+    tag = "admin",
+    params(
+        ("action" = MaintenanceAction, Path, description = "Maintenance action")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Confirmation token issued", body = ApiResponse<SignedUrl>),
+        (status = 400, description = "Unknown action", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn confirm_maintenance_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Some(action) = MaintenanceAction::parse(&path) else {
+        return unknown_maintenance_action(&path);
+    };
+    if let Err(response) =
+        ensure_permission(data.db.pool(), &auth, maintenance_permission(action)).await
+    {
+        return response;
+    }
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(MAINTENANCE_CONFIRM_TTL_SECS);
+    let user_id = auth.user_id.to_string();
+    let url = data.config.load().url_signer().sign_at(
+        &maintenance_path(action),
+        &[(PARAM_CONFIRM_USER, user_id.as_str())],
+        expires_at.timestamp(),
+    );
+
+    HttpResponse::Ok().json(ApiResponse::new(SignedUrl {
+        url,
+        expires_at: expires_at.to_rfc3339(),
+    }))
+}
+
+/// POST /api/admin/maintenance/{action} - Run a maintenance action
+///
+/// Requires the action's permission and the confirmation token from
+/// `/api/admin/maintenance/{action}/confirm` in the query, issued to the
+/// same admin. Deletions report the rows removed per table; VACUUM and
+/// REINDEX report the tables they processed; encrypt-user-data reports the
+/// users it re-encrypted.
+///
+/// # Responses
+/// - 200: Action ran; affected rows per table
+/// - 400: Unknown action or missing confirmation token
+/// - 401: Not authenticated
+/// - 403: Missing the action's permission, or the token is invalid, expired,
+///   or someone else's
+/// - 500: Database error
+#[utoipa::path(
+    post,
+    path = "/api/admin/maintenance/{action}",
+    tag = "admin",
+    params(
+        ("action" = MaintenanceAction, Path, description = "Maintenance action"),
+        ("user" = i32, Query, description = "User the token was issued to"),
+        ("expires" = i64, Query, description = "Expiry (unix seconds)"),
+        ("kid" = String, Query, description = "Signing key ID"),
+        ("sig" = String, Query, description = "Signature")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Maintenance action ran", body = ApiResponse<MaintenanceResult>),
+        (status = 400, description = "Unknown action or missing confirmation", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required or invalid confirmation", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn run_maintenance_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let Some(action) = MaintenanceAction::parse(&path) else {
+        return unknown_maintenance_action(&path);
+    };
+    if let Err(response) =
+        ensure_permission(data.db.pool(), &auth, maintenance_permission(action)).await
+    {
+        return response;
+    }
+
+    let message = match data
+        .config
+        .load()
+        .url_signer()
+        .verify(&maintenance_path(action), &query)
+    {
+        Ok(()) if query.get(PARAM_CONFIRM_USER) == Some(&auth.user_id.to_string()) => None,
+        Ok(()) => Some("Confirmation token was issued to another user"),
+        Err(SignatureError::Missing) => {
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Confirmation token required",
+            ));
+        }
+        Err(SignatureError::Expired) => Some("Confirmation token has expired"),
+        Err(_) => Some("Invalid confirmation token"),
+    };
+    if let Some(message) = message {
+        warn!(
+            "Admin {} sent a rejected maintenance confirmation for {}",
+            auth.user_id,
+            action.as_str()
+        );
+        return HttpResponse::Forbidden().json(ApiError::new(ErrorCode::Forbidden, message));
+    }
+
+    match run_maintenance(&data, action).await {
+        Ok(result) => {
+            info!(
+                "Admin {} ran maintenance {}: {} rows affected",
+                auth.user_id,
+                action.as_str(),
+                result.affected_rows
+            );
+            HttpResponse::Ok().json(ApiResponse::new(result))
+        }
+        Err(e) => {
+            error!("Maintenance {} failed: {}", action.as_str(), e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Maintenance failed: {}", e),
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/integrity - Latest consistency check report
+///
+/// Requires the `maintenance:run` permission. Returns the report of the most
+/// recently completed integrity check job: episodes without their anime, video
+/// sources without their episode, and favorites of anime missing from the
+/// crawled catalog, grouped by the missing parent.
+///
+/// # Responses
+/// - 200: The latest report
+/// - 401: Not authenticated
+/// - 403: Missing the `maintenance:run` permission
+/// - 404: No integrity check has completed yet
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/integrity",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Latest integrity report", body = ApiResponse<IntegrityReport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "No integrity check has completed", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_integrity_report_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<MaintenanceRun>,
+) -> impl Responder {
+    let job = match get_latest_completed_job(data.db.pool(), jobs::JOB_TYPE_INTEGRITY_CHECK).await {
+        Ok(job) => job,
+        Err(e) => {
+            error!("Failed to get latest integrity check: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get integrity report",
+            ));
+        }
+    };
+
+    match job
+        .and_then(|job| job.result)
+        .and_then(|result| serde_json::from_value::<IntegrityReport>(result).ok())
+    {
+        Some(report) => HttpResponse::Ok().json(ApiResponse::new(report)),
+        None => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "No integrity check has completed yet",
+        )),
+    }
+}
+
+/// Query parameters for queueing an integrity check
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct IntegrityCheckQuery {
+    /// Queue a scrape for every missing anime (default: false)
+    pub repair: Option<bool>,
+}
+
+/// POST /api/admin/integrity - Queue a consistency check
+///
+/// Requires the `maintenance:run` permission. The report is stored as the job
+/// result and served by GET /api/admin/integrity once the job completes. With
+/// `repair=true`, a scrape is queued for each missing anime (up to 100 per
+/// run), restoring its detail, episodes, and catalog entry. Orphaned video
+/// sources are only reported; remove them with the delete-orphans maintenance
+/// action.
+///
+/// Query parameters:
+/// - repair: Queue scrapes for missing anime (default: false)
+#[utoipa::path(
+    post,
+    path = "/api/admin/integrity",
+    tag = "admin",
+    params(IntegrityCheckQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Integrity check queued", body = ApiResponse<JobRecord>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn enqueue_integrity_check_handler(
+    data: web::Data<AppState>,
+    auth: Permission<MaintenanceRun>,
+    query: web::Query<IntegrityCheckQuery>,
+) -> impl Responder {
+    let repair = query.repair.unwrap_or(false);
+    match jobs::integrity::enqueue_integrity_check(data.db.pool(), repair).await {
+        Ok(job) => {
+            info!(
+                "Admin {} queued integrity check {} (repair: {})",
+                auth.user_id, job.id, repair
+            );
+            HttpResponse::Ok().json(ApiResponse::new(job))
+        }
+        Err(e) => {
+            error!("Failed to enqueue integrity check: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to queue integrity check",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/gaps - Anime missing episodes below their highest or final one
+///
+/// Requires the `anime:manage` permission. Checks the stored episodes now,
+/// without queueing anything; the scheduled check (EPISODE_GAP_INTERVAL_SECS)
+/// queues the backfill scrapes. Lists at most 500 anime, most episodes
+/// missing first.
+///
+/// # Responses
+/// - 200: Anime with gaps
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/gaps",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Episode gaps retrieved", body = ApiResponse<EpisodeGapReport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_episode_gaps_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+) -> impl Responder {
+    match jobs::gaps::check_gaps(data.db.pool(), None).await {
+        Ok(report) => HttpResponse::Ok().json(ApiResponse::new(report)),
+        Err(e) => {
+            error!("Failed to check episode gaps: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to check episode gaps",
+            ))
+        }
+    }
+}
+
+/// 400 for a role definition naming an unknown permission, listing the known ones
+fn invalid_permissions(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(
+        ApiError::new(ErrorCode::ValidationFailed, message)
+            .with_details(serde_json::json!({ "availablePermissions": permissions::ALL })),
+    )
+}
+
+/// GET /api/admin/roles - List roles and their permissions
+///
+/// Requires the `roles:manage` permission.
+///
+/// # Responses
+/// - 200: All roles, by name
+/// - 401: Not authenticated
+/// - 403: Missing the `roles:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/roles",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Roles retrieved", body = ApiResponse<Vec<Role>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_roles_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<RolesManage>,
+) -> impl Responder {
+    match get_roles(data.db.pool()).await {
+        Ok(roles) => HttpResponse::Ok().json(ApiResponse::new(roles)),
+        Err(e) => {
+            error!("Failed to get roles: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get roles",
+            ))
+        }
+    }
+}
+
+/// POST /api/admin/roles - Create a role
+///
+/// Requires the `roles:manage` permission.
+///
+/// # Request Body
+/// - name: Unique name (lowercase letters, digits, and dashes)
+/// - description: What the role is for (optional)
+/// - permissions: Permission strings the role grants (optional)
+///
+/// # Responses
+/// - 200: Role created
+/// - 400: Invalid name or unknown permission
+/// - 401: Not authenticated
+/// - 403: Missing the `roles:manage` permission
+/// - 409: Name already taken
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/roles",
+    tag = "admin",
+    request_body = CreateRoleRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Role created", body = ApiResponse<Role>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 409, description = "Role already exists", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn create_role_handler(
+    data: web::Data<AppState>,
+    auth: Permission<RolesManage>,
+    body: web::Json<CreateRoleRequest>,
+) -> impl Responder {
+    let name = body.name.trim();
+    if !is_valid_role_name(name) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Name must be lowercase letters, digits, and dashes",
+        ));
+    }
+    let role_permissions = match normalize_permissions(&body.permissions) {
+        Ok(role_permissions) => role_permissions,
+        Err(message) => return invalid_permissions(message),
+    };
+
+    match create_role(
+        data.db.pool(),
+        name,
+        body.description.trim(),
+        &role_permissions,
+    )
+    .await
+    {
+        Ok(role) => {
+            info!("User {} created role {}", auth.user_id, role.name);
+            HttpResponse::Ok().json(ApiResponse::new(role))
+        }
+        Err(RepositoryError::Conflict(msg)) => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg))
+        }
+        Err(e) => {
+            error!("Failed to create role: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create role",
+            ))
+        }
+    }
+}
+
+/// PUT /api/admin/roles/{id} - Change a role's description or permissions
+///
+/// Requires the `roles:manage` permission. The permission list, when given,
+/// replaces the role's current one; users holding the role are affected on
+/// their next request.
+///
+/// # Responses
+/// - 200: Updated role
+/// - 400: Unknown permission
+/// - 401: Not authenticated
+/// - 403: Missing the `roles:manage` permission
+/// - 404: Role not found
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/admin/roles/{id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Role ID")
+    ),
+    request_body = UpdateRoleRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Role updated", body = ApiResponse<Role>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Role not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn update_role_handler(
+    data: web::Data<AppState>,
+    auth: Permission<RolesManage>,
+    path: web::Path<i32>,
+    body: web::Json<UpdateRoleRequest>,
+) -> impl Responder {
+    let role_id = path.into_inner();
+    let role_permissions = match body.permissions.as_deref().map(normalize_permissions) {
+        Some(Ok(role_permissions)) => Some(role_permissions),
+        Some(Err(message)) => return invalid_permissions(message),
+        None => None,
+    };
+
+    match update_role(
+        data.db.pool(),
+        role_id,
+        body.description.as_deref().map(str::trim),
+        role_permissions.as_deref(),
+    )
+    .await
+    {
+        Ok(Some(role)) => {
+            info!("User {} updated role {}", auth.user_id, role.name);
+            HttpResponse::Ok().json(ApiResponse::new(role))
+        }
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Role not found"))
+        }
+        Err(e) => {
+            error!("Failed to update role {}: {}", role_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to update role",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/admin/roles/{id} - Delete a role
+///
+/// Requires the `roles:manage` permission. Users holding the role lose its
+/// permissions.
+///
+/// # Responses
+/// - 204: Role deleted
+/// - 401: Not authenticated
+/// - 403: Missing the `roles:manage` permission
+/// - 404: Role not found
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/roles/{id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Role ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 204, description = "Role deleted"),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Role not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_role_handler(
+    data: web::Data<AppState>,
+    auth: Permission<RolesManage>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let role_id = path.into_inner();
+    match delete_role(data.db.pool(), role_id).await {
+        Ok(true) => {
+            info!("User {} deleted role {}", auth.user_id, role_id);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Role not found"))
+        }
+        Err(e) => {
+            error!("Failed to delete role {}: {}", role_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete role",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/users/{id}/roles - A user's roles and effective permissions
+///
+/// Requires the `users:read` permission.
+///
+/// # Responses
+/// - 200: The user's roles and the permissions they grant
+/// - 401: Not authenticated
+/// - 403: Missing the `users:read` permission
+/// - 404: User not found
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/users/{id}/roles",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "User roles retrieved", body = ApiResponse<UserRoles>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "User not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_user_roles_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<UsersRead>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    user_roles_response(data.db.pool(), path.into_inner()).await
+}
+
+/// PUT /api/admin/users/{id}/roles/{role_id} - Grant a role to a user
+///
+/// Requires the `roles:manage` permission. Granting a role the user already
+/// holds succeeds without changes.
+///
+/// # Responses
+/// - 200: The user's roles after the grant
+/// - 401: Not authenticated
+/// - 403: Missing the `roles:manage` permission
+/// - 404: User or role not found
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/admin/users/{id}/roles/{role_id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        ("role_id" = i32, Path, description = "Role ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Role granted", body = ApiResponse<UserRoles>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "User or role not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn assign_role_handler(
+    data: web::Data<AppState>,
+    auth: Permission<RolesManage>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (user_id, role_id) = path.into_inner();
+    let pool = data.db.pool();
+
+    match assign_role(pool, user_id, role_id).await {
+        Ok(true) => {
+            info!(
+                "User {} granted role {} to user {}",
+                auth.user_id, role_id, user_id
+            );
+        }
+        Ok(false) => {
+            return HttpResponse::NotFound()
+                .json(ApiError::new(ErrorCode::NotFound, "User or role not found"));
+        }
+        Err(e) => {
+            error!(
+                "Failed to grant role {} to user {}: {}",
+                role_id, user_id, e
+            );
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to grant role",
+            ));
+        }
+    }
+
+    user_roles_response(pool, user_id).await
+}
+
+/// DELETE /api/admin/users/{id}/roles/{role_id} - Revoke a role from a user
+///
+/// Requires the `roles:manage` permission.
+///
+/// # Responses
+/// - 200: The user's roles after the revocation
+/// - 401: Not authenticated
+/// - 403: Missing the `roles:manage` permission
+/// - 404: User doesn't hold the role
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/users/{id}/roles/{role_id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        ("role_id" = i32, Path, description = "Role ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Role revoked", body = ApiResponse<UserRoles>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "User doesn't hold the role", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn unassign_role_handler(
+    data: web::Data<AppState>,
+    auth: Permission<RolesManage>,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (user_id, role_id) = path.into_inner();
+    let pool = data.db.pool();
+
+    match unassign_role(pool, user_id, role_id).await {
+        Ok(true) => {
+            info!(
+                "User {} revoked role {} from user {}",
+                auth.user_id, role_id, user_id
+            );
+        }
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ApiError::new(
+                ErrorCode::NotFound,
+                "User doesn't hold the role",
+            ));
+        }
+        Err(e) => {
+            error!(
+                "Failed to revoke role {} from user {}: {}",
+                role_id, user_id, e
+            );
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to revoke role",
+            ));
+        }
+    }
+
+    user_roles_response(pool, user_id).await
+}
+
+/// 200 with a user's current roles, or 404 if the user doesn't exist
+async fn user_roles_response(pool: &sqlx::PgPool, user_id: i32) -> HttpResponse {
+    match get_user_roles(pool, user_id).await {
+        Ok(Some(user_roles)) => HttpResponse::Ok().json(ApiResponse::new(user_roles)),
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "User not found"))
+        }
+        Err(e) => {
+            error!("Failed to get roles of user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get user roles",
+            ))
+        }
+    }
+}
+
+/// Query parameters for the moderation queue endpoint
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct ModerationQueueQuery {
+    /// Status to list (pending, approved, removed; default: pending)
+    pub status: Option<String>,
+    /// Maximum number of items to return (default: 50, max: 500)
+    pub limit: Option<i64>,
+}
+
+/// Response for a failed moderation decision
+fn moderation_error_response(e: ModerationError) -> HttpResponse {
+    match e {
+        ModerationError::NotFound => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "Moderation item not found",
+        )),
+        ModerationError::InvalidTransition { .. } | ModerationError::Changed => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, e.to_string()))
+        }
+        ModerationError::SelfReport => HttpResponse::BadRequest()
+            .json(ApiError::new(ErrorCode::ValidationFailed, e.to_string())),
+        ModerationError::Repository(e) => {
+            error!("Moderation decision failed: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to apply moderation decision",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/moderation - List the moderation queue
+///
+/// Requires the `comments:moderate` permission. Items are listed most
+/// reported first, then oldest first.
+///
+/// Query parameters:
+/// - status: pending, approved, or removed (default: pending)
+/// - limit: Maximum number of items (default: 50, max: 500)
+#[utoipa::path(
+    get,
+    path = "/api/admin/moderation",
+    tag = "admin",
+    params(ModerationQueueQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Moderation queue retrieved", body = ApiResponse<Vec<ModerationItem>>),
+        (status = 400, description = "Unknown status", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_moderation_queue_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<CommentsModerate>,
+    query: web::Query<ModerationQueueQuery>,
+) -> impl Responder {
+    let status = match query.status.as_deref() {
+        None => ModerationStatus::Pending,
+        Some(name) => match ModerationStatus::parse(name) {
+            Some(status) => status,
+            None => {
+                return HttpResponse::BadRequest().json(ApiError::new(
+                    ErrorCode::ValidationFailed,
+                    format!("Unknown moderation status: {}", name),
+                ));
+            }
+        },
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    match get_moderation_queue(data.db.pool(), status, limit).await {
+        Ok(items) => HttpResponse::Ok().json(ApiResponse::new(items)),
+        Err(e) => {
+            error!("Failed to get moderation queue: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get moderation queue",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/moderation/{id} - Get a moderation item with its reports
+///
+/// Requires the `comments:moderate` permission.
+///
+/// # Responses
+/// - 200: The item and every report against it
+/// - 401: Not authenticated
+/// - 403: Missing the `comments:moderate` permission
+/// - 404: Moderation item not found
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/moderation/{id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Moderation item ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Moderation item retrieved", body = ApiResponse<ModerationItemDetail>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Moderation item not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_moderation_item_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<CommentsModerate>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let item_id = path.into_inner();
+    let pool = data.db.pool();
+
+    let item = match get_moderation_item(pool, item_id).await {
+        Ok(Some(item)) => item,
+        Ok(None) => return moderation_error_response(ModerationError::NotFound),
+        Err(e) => return moderation_error_response(e.into()),
+    };
+
+    match get_content_reports(pool, item_id).await {
+        Ok(reports) => {
+            HttpResponse::Ok().json(ApiResponse::new(ModerationItemDetail { item, reports }))
+        }
+        Err(e) => moderation_error_response(e.into()),
+    }
+}
+
+/// POST /api/admin/moderation/{id}/approve - Keep reported content
+///
+/// Requires the `comments:moderate` permission. Only pending items can be
+/// approved.
+///
+/// # Request Body
+/// - note: Note on the decision (optional)
+///
+/// # Responses
+/// - 200: The approved item
+/// - 401: Not authenticated
+/// - 403: Missing the `comments:moderate` permission
+/// - 404: Moderation item not found
+/// - 409: Item was already resolved
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/moderation/{id}/approve",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Moderation item ID")
+    ),
+    request_body = ModerationDecision,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Content approved", body = ApiResponse<ModerationResolution>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Moderation item not found", body = ApiError),
+        (status = 409, description = "Item already resolved", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn approve_moderation_item_handler(
+    data: web::Data<AppState>,
+    auth: Permission<CommentsModerate>,
+    path: web::Path<i32>,
+    body: web::Json<ModerationDecision>,
+) -> impl Responder {
+    let note = body
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+
+    match moderation::approve(
+        data.db.pool(),
+        &data.moderation,
+        path.into_inner(),
+        auth.user_id,
+        note,
+    )
+    .await
+    {
+        Ok(resolution) => HttpResponse::Ok().json(ApiResponse::new(resolution)),
+        Err(e) => moderation_error_response(e),
+    }
+}
+
+/// POST /api/admin/moderation/{id}/remove - Remove reported content
+///
+/// Requires the `comments:moderate` permission. Pending and approved items
+/// can be removed. The author gets a strike with the note as its reason and
+/// is muted once they have three strikes from the last 90 days; each further
+/// strike doubles the mute, up to 30 days. The author is emailed about the
+/// removal and any mute.
+///
+/// # Request Body
+/// - note: Why the content was removed, shown to the author
+///
+/// # Responses
+/// - 200: The removed item, the strike, and the mute it triggered
+/// - 400: Missing note
+/// - 401: Not authenticated
+/// - 403: Missing the `comments:moderate` permission
+/// - 404: Moderation item not found
+/// - 409: Item was already removed
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/moderation/{id}/remove",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Moderation item ID")
+    ),
+    request_body = ModerationDecision,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Content removed", body = ApiResponse<ModerationResolution>),
+        (status = 400, description = "Missing note", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Moderation item not found", body = ApiError),
+        (status = 409, description = "Item already removed", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn remove_moderation_item_handler(
+    data: web::Data<AppState>,
+    auth: Permission<CommentsModerate>,
+    path: web::Path<i32>,
+    body: web::Json<ModerationDecision>,
+) -> impl Responder {
+    let Some(reason) = body
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty())
+    else {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "A note explaining the removal is required",
+        ));
+    };
+
+    match moderation::remove(
+        data.db.pool(),
+        &data.moderation,
+        path.into_inner(),
+        auth.user_id,
+        reason,
+    )
+    .await
+    {
+        Ok(resolution) => HttpResponse::Ok().json(ApiResponse::new(resolution)),
+        Err(e) => moderation_error_response(e),
+    }
+}
+
+/// GET /api/admin/moderation/users/{id} - A user's strikes and mute
+///
+/// Requires the `comments:moderate` permission.
+///
+/// # Responses
+/// - 200: The user's strikes, how many still count, and their current mute
+/// - 401: Not authenticated
+/// - 403: Missing the `comments:moderate` permission
+/// - 404: User not found
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/moderation/users/{id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Moderation standing retrieved", body = ApiResponse<ModerationStanding>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "User not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_moderation_standing_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<CommentsModerate>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    match get_moderation_standing(data.db.pool(), user_id, moderation::strike_window_start()).await
+    {
+        Ok(Some(standing)) => HttpResponse::Ok().json(ApiResponse::new(standing)),
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "User not found"))
+        }
+        Err(e) => {
+            error!(
+                "Failed to get moderation standing of user {}: {}",
+                user_id, e
+            );
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get moderation standing",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/admin/moderation/users/{id}/mute - Lift a user's mute
+///
+/// Requires the `comments:moderate` permission. The user's strikes are kept,
+/// so their next strike mutes them again.
+///
+/// # Responses
+/// - 204: Mute lifted
+/// - 401: Not authenticated
+/// - 403: Missing the `comments:moderate` permission
+/// - 404: User not found or not muted
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/moderation/users/{id}/mute",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 204, description = "Mute lifted"),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "User not muted", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn unmute_user_handler(
+    data: web::Data<AppState>,
+    auth: Permission<CommentsModerate>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    match unmute_user(data.db.pool(), user_id).await {
+        Ok(true) => {
+            info!("User {} lifted the mute of user {}", auth.user_id, user_id);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "User is not muted"))
+        }
+        Err(e) => {
+            error!("Failed to unmute user {}: {}", user_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to unmute user",
+            ))
+        }
+    }
+}
+
+/// Query parameters for the erasure log endpoint
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct DataErasuresQuery {
+    /// Maximum number of erasures to return (default: 50, max: 500)
+    pub limit: Option<i64>,
+}
+
+/// GET /api/admin/erasures - Log of accounts erased by their users
+///
+/// Requires the `users:read` permission. Entries name the erased user ID and
+/// the rows affected per table; no personal data is kept.
+///
+/// Query parameters:
+/// - limit: Maximum number of erasures (default: 50, max: 500)
+#[utoipa::path(
+    get,
+    path = "/api/admin/erasures",
+    tag = "admin",
+    params(DataErasuresQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Erasures retrieved", body = ApiResponse<Vec<DataErasure>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_data_erasures_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<UsersRead>,
+    query: web::Query<DataErasuresQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    match list_data_erasures(data.db.pool(), limit).await {
+        Ok(erasures) => HttpResponse::Ok().json(ApiResponse::new(erasures)),
+        Err(e) => {
+            error!("Failed to get data erasures: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get data erasures",
+            ))
+        }
+    }
+}
+
+/// Longest video server name accepted
+const MAX_VIDEO_SERVER_LEN: usize = 100;
+
+/// Normalized video server name from a path, or `None` if it is invalid
+fn video_server_name(server: &str) -> Option<String> {
+    let server = normalize_server(server);
+    (!server.is_empty() && server.len() <= MAX_VIDEO_SERVER_LEN).then_some(server)
+}
+
+/// 400 response for an invalid video server name
+fn invalid_video_server() -> HttpResponse {
+    HttpResponse::BadRequest().json(ApiError::new(
+        ErrorCode::ValidationFailed,
+        format!(
+            "Server name must be 1 to {} characters",
+            MAX_VIDEO_SERVER_LEN
+        ),
+    ))
+}
+
+/// Reload video server rules after a change, logging failures
+async fn reload_video_servers(data: &AppState) {
+    if let Err(e) = data.video_servers.reload(data.db.pool()).await {
+        warn!("Failed to reload video server rules: {}", e);
+    }
+}
+
+/// GET /api/admin/video-servers - Video server rules in effect
+///
+/// Requires the `anime:manage` permission. Lists the rules from
+/// VIDEO_SERVER_BLACKLIST and VIDEO_SERVER_PRIORITY merged with the ones set
+/// by admins, which take precedence, highest priority first.
+#[utoipa::path(
+    get,
+    path = "/api/admin/video-servers",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Video server rules retrieved", body = ApiResponse<Vec<VideoServerRule>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError)
+    )
+)]
+pub async fn get_video_servers_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::new(data.video_servers.all()))
+}
+
+/// PUT /api/admin/video-servers/{server} - Block or prioritize a video server
+///
+/// Requires the `anime:manage` permission. Omitted fields keep the server's
+/// current value. The rule applies to episodes served and crawled from now
+/// on, without a redeploy.
+///
+/// # Responses
+/// - 200: The rule now in effect
+/// - 400: Invalid server name
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/admin/video-servers/{server}",
+    tag = "admin",
+    params(
+        ("server" = String, Path, description = "Video server name, matched ignoring case")
+    ),
+    request_body = VideoServerRuleRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Video server rule set", body = ApiResponse<VideoServerRule>),
+        (status = 400, description = "Invalid server name", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn set_video_server_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    path: web::Path<String>,
+    body: web::Json<VideoServerRuleRequest>,
+) -> impl Responder {
+    let Some(server) = video_server_name(&path) else {
+        return invalid_video_server();
+    };
+    let current = data.video_servers.get(&server);
+    let blocked = body
+        .blocked
+        .or(current.as_ref().map(|rule| rule.blocked))
+        .unwrap_or(false);
+    let priority = body
+        .priority
+        .or(current.as_ref().map(|rule| rule.priority))
+        .unwrap_or(0);
+
+    match set_video_server_rule(data.db.pool(), &server, blocked, priority).await {
+        Ok(rule) => {
+            reload_video_servers(&data).await;
+            info!(
+                "User {} set video server {} (blocked: {}, priority: {})",
+                auth.user_id, server, blocked, priority
+            );
+            HttpResponse::Ok().json(ApiResponse::new(rule))
+        }
+        Err(e) => {
+            error!("Failed to set video server rule for {}: {}", server, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to set video server rule",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/admin/video-servers/{server} - Remove an admin rule
+///
+/// Requires the `anime:manage` permission. The server falls back to its
+/// config rule, if any.
+///
+/// # Responses
+/// - 204: Rule removed
+/// - 400: Invalid server name
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: The server has no admin rule
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/video-servers/{server}",
+    tag = "admin",
+    params(
+        ("server" = String, Path, description = "Video server name, matched ignoring case")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 204, description = "Video server rule removed"),
+        (status = 400, description = "Invalid server name", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "No admin rule for the server", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_video_server_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Some(server) = video_server_name(&path) else {
+        return invalid_video_server();
+    };
+
+    match delete_video_server_rule(data.db.pool(), &server).await {
+        Ok(true) => {
+            reload_video_servers(&data).await;
+            info!("User {} removed video server rule {}", auth.user_id, server);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "No admin rule for this video server",
+        )),
+        Err(e) => {
+            error!("Failed to delete video server rule for {}: {}", server, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete video server rule",
+            ))
+        }
+    }
+}
+
+/// Longest reason accepted for a feature flag
+const MAX_FLAG_REASON_LEN: usize = 500;
+
+/// 400 for an unknown feature, listing the known ones
+fn unknown_feature(name: &str) -> HttpResponse {
+    let available: Vec<&str> = Feature::ALL.iter().map(|f| f.as_str()).collect();
+    HttpResponse::BadRequest().json(
+        ApiError::new(
+            ErrorCode::ValidationFailed,
+            format!("Unknown feature: {}", name),
+        )
+        .with_details(serde_json::json!({ "availableFeatures": available })),
+    )
+}
+
+/// Reload feature flags after a change, logging failures
+async fn reload_feature_flags(data: &AppState) {
+    if let Err(e) = data.features.reload(data.db.pool()).await {
+        warn!("Failed to reload feature flags: {}", e);
+    }
+}
+
+/// GET /api/admin/flags - Feature flags in effect
+///
+/// Requires the `maintenance:run` permission. Lists every feature, enabled
+/// unless an operator switched it off.
+#[utoipa::path(
+    get,
+    path = "/api/admin/flags",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Feature flags retrieved", body = ApiResponse<Vec<FeatureFlag>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError)
+    )
+)]
+pub async fn get_feature_flags_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<MaintenanceRun>,
+) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::new(data.features.all()))
+}
+
+/// PUT /api/admin/flags/{feature} - Switch a feature on or off
+///
+/// Requires the `maintenance:run` permission. While a feature is off its
+/// endpoints answer 503 with code `FEATURE_DISABLED` and the reason, on
+/// this instance at once and on others within FEATURE_FLAG_REFRESH_SECS.
+///
+/// # Request Body
+/// - enabled: Whether the feature's endpoints are served
+/// - reason: Why (optional, at most 500 characters)
+///
+/// # Responses
+/// - 200: The flag now in effect
+/// - 400: Unknown feature or reason too long
+/// - 401: Not authenticated
+/// - 403: Missing the `maintenance:run` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/admin/flags/{feature}",
+    tag = "admin",
+    params(
+        ("feature" = Feature, Path, description = "Feature name")
+    ),
+    request_body = FeatureFlagRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Feature flag set", body = ApiResponse<FeatureFlag>),
+        (status = 400, description = "Unknown feature or invalid reason", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn set_feature_flag_handler(
+    data: web::Data<AppState>,
+    auth: Permission<MaintenanceRun>,
+    path: web::Path<String>,
+    body: web::Json<FeatureFlagRequest>,
+) -> impl Responder {
+    let Some(feature) = Feature::parse(&path) else {
+        return unknown_feature(&path);
+    };
+    let reason = body
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+    if reason.is_some_and(|reason| reason.chars().count() > MAX_FLAG_REASON_LEN) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            format!("Reason must be at most {} characters", MAX_FLAG_REASON_LEN),
+        ));
+    }
+
+    match set_feature_flag(data.db.pool(), feature, body.enabled, reason, auth.user_id).await {
+        Ok(flag) => {
+            reload_feature_flags(&data).await;
+            warn!(
+                "User {} {} feature {}{}",
+                auth.user_id,
+                if flag.enabled { "enabled" } else { "disabled" },
+                feature.as_str(),
+                reason.map(|r| format!(": {}", r)).unwrap_or_default()
+            );
+            HttpResponse::Ok().json(ApiResponse::new(flag))
+        }
+        Err(e) => {
+            error!("Failed to set feature flag {}: {}", feature.as_str(), e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to set feature flag",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/admin/flags/{feature} - Reset a feature to enabled
+///
+/// Requires the `maintenance:run` permission. Removes the operator's flag,
+/// so the feature is enabled like one never changed.
+///
+/// # Responses
+/// - 204: Flag removed
+/// - 400: Unknown feature
+/// - 401: Not authenticated
+/// - 403: Missing the `maintenance:run` permission
+/// - 404: The feature has no flag
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/flags/{feature}",
+    tag = "admin",
+    params(
+        ("feature" = Feature, Path, description = "Feature name")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 204, description = "Feature flag removed"),
+        (status = 400, description = "Unknown feature", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "No flag for the feature", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_feature_flag_handler(
+    data: web::Data<AppState>,
+    auth: Permission<MaintenanceRun>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Some(feature) = Feature::parse(&path) else {
+        return unknown_feature(&path);
+    };
+
+    match delete_feature_flag(data.db.pool(), feature).await {
+        Ok(true) => {
+            reload_feature_flags(&data).await;
+            info!("User {} reset feature {}", auth.user_id, feature.as_str());
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "No flag for this feature",
+        )),
+        Err(e) => {
+            error!("Failed to delete feature flag {}: {}", feature.as_str(), e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete feature flag",
+            ))
+        }
+    }
+}
+
+/// POST /api/admin/tags - Create a curated tag
+///
+/// Requires the `anime:manage` permission.
+///
+/// # Request Body
+/// - name: Display name (at most 50 characters); the slug is derived from it
+/// - description: What the tag means (optional)
+///
+/// # Responses
+/// - 200: Tag created
+/// - 400: Invalid name
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 409: A tag with the same slug exists
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/tags",
+    tag = "admin",
+    request_body = CreateTagRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Tag created", body = ApiResponse<Tag>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 409, description = "Tag already exists", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn create_tag_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    body: web::Json<CreateTagRequest>,
+) -> impl Responder {
+    let (name, slug) = match validate_tag_name(&body.name) {
+        Ok(validated) => validated,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match create_tag(
+        data.db.pool(),
+        &slug,
+        &name,
+        body.description.trim(),
+        auth.user_id,
+    )
+    .await
+    {
+        Ok(tag) => {
+            info!("User {} created tag {}", auth.user_id, tag.slug);
+            HttpResponse::Ok().json(ApiResponse::new(tag))
+        }
+        Err(RepositoryError::Conflict(msg)) => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg))
+        }
+        Err(e) => {
+            error!("Failed to create tag: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create tag",
+            ))
+        }
+    }
+}
+
+/// PUT /api/admin/tags/{id} - Rename, describe, or curate a tag
+///
+/// Requires the `anime:manage` permission. Renaming changes the tag's slug,
+/// so links filtering by the old slug stop matching.
+///
+/// # Responses
+/// - 200: Updated tag
+/// - 400: Invalid name
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: Tag not found
+/// - 409: Another tag has the new name's slug
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/admin/tags/{id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Tag ID")
+    ),
+    request_body = UpdateTagRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Tag updated", body = ApiResponse<Tag>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Tag not found", body = ApiError),
+        (status = 409, description = "Tag already exists", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn update_tag_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    path: web::Path<i32>,
+    body: web::Json<UpdateTagRequest>,
+) -> impl Responder {
+    let tag_id = path.into_inner();
+    let name = match body.name.as_deref().map(validate_tag_name) {
+        Some(Ok(validated)) => Some(validated),
+        Some(Err(msg)) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+        None => None,
+    };
+
+    match update_tag(
+        data.db.pool(),
+        tag_id,
+        name.as_ref()
+            .map(|(name, slug)| (slug.as_str(), name.as_str())),
+        body.description.as_deref().map(str::trim),
+        body.curated,
+    )
+    .await
+    {
+        Ok(Some(tag)) => {
+            info!("User {} updated tag {}", auth.user_id, tag.slug);
+            HttpResponse::Ok().json(ApiResponse::new(tag))
+        }
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Tag not found"))
+        }
+        Err(RepositoryError::Conflict(msg)) => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg))
+        }
+        Err(e) => {
+            error!("Failed to update tag {}: {}", tag_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to update tag",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/admin/tags/{id} - Delete a tag
+///
+/// Requires the `anime:manage` permission. The tag is taken off every anime.
+///
+/// # Responses
+/// - 204: Tag deleted
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: Tag not found
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/tags/{id}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "Tag ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 204, description = "Tag deleted"),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "Tag not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_tag_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let tag_id = path.into_inner();
+    match delete_tag(data.db.pool(), tag_id).await {
+        Ok(true) => {
+            info!("User {} deleted tag {}", auth.user_id, tag_id);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Tag not found"))
+        }
+        Err(e) => {
+            error!("Failed to delete tag {}: {}", tag_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete tag",
+            ))
+        }
+    }
+}
+
+/// Respond with an anime's age ratings after a change
+async fn age_rating_response(data: &AppState, slug: &str) -> HttpResponse {
+    match get_anime_age_rating(data.db.pool(), slug).await {
+        Ok(rating) => HttpResponse::Ok().json(ApiResponse::new(rating)),
+        Err(e) => {
+            error!("Failed to get age rating of {}: {}", slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get age rating",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/anime/{slug}/age-rating - An anime's derived and overridden age rating
+///
+/// Requires the `anime:manage` permission.
+///
+/// # Responses
+/// - 200: The anime's age ratings
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/anime/{slug}/age-rating",
+    tag = "admin",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Age rating retrieved", body = ApiResponse<AnimeAgeRating>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_age_rating_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+    slug: Slug,
+) -> impl Responder {
+    age_rating_response(&data, &slug).await
+}
+
+/// PUT /api/admin/anime/{slug}/age-rating - Override an anime's age rating
+///
+/// Requires the `anime:manage` permission. The override wins over the rating
+/// derived from the genres and survives re-scrapes.
+///
+/// # Responses
+/// - 200: The anime's age ratings
+/// - 400: Invalid slug or rating
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/admin/anime/{slug}/age-rating",
+    tag = "admin",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    request_body = AgeRatingOverrideRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Age rating overridden", body = ApiResponse<AnimeAgeRating>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn set_age_rating_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    slug: Slug,
+    body: web::Json<AgeRatingOverrideRequest>,
+) -> impl Responder {
+    let rating = body.age_rating;
+    match set_age_rating_override(data.db.pool(), &slug, rating, auth.user_id).await {
+        Ok(()) => {
+            info!("User {} rated {} {}", auth.user_id, &*slug, rating.as_str());
+            age_rating_response(&data, &slug).await
+        }
+        Err(e) => {
+            error!("Failed to override age rating of {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to override age rating",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/admin/anime/{slug}/age-rating - Remove an age rating override
+///
+/// Requires the `anime:manage` permission. The anime goes back to the rating
+/// derived from its genres.
+///
+/// # Responses
+/// - 200: The anime's age ratings
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 404: The anime has no override
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/admin/anime/{slug}/age-rating",
+    tag = "admin",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Override removed", body = ApiResponse<AnimeAgeRating>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 404, description = "No override for this anime", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_age_rating_handler(
+    data: web::Data<AppState>,
+    auth: Permission<AnimeManage>,
+    slug: Slug,
+) -> impl Responder {
+    match delete_age_rating_override(data.db.pool(), &slug).await {
+        Ok(true) => {
+            info!(
+                "User {} removed the age rating override of {}",
+                auth.user_id, &*slug
+            );
+            age_rating_response(&data, &slug).await
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "No age rating override for this anime",
+        )),
+        Err(e) => {
+            error!("Failed to remove age rating override of {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to remove age rating override",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/anomalies - Recent anomalous responses from the source site
+///
+/// Requires the `anime:manage` permission. Fetched pages with a non-HTML
+/// content type, a near-empty body, a meta refresh, or scripts and next to
+/// no text are counted since startup; the latest 100 are listed, newest
+/// first. A burst of them usually means the site changed.
+#[utoipa::path(
+    get,
+    path = "/api/admin/anomalies",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Upstream anomalies retrieved", body = ApiResponse<UpstreamAnomalyReport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError)
+    )
+)]
+pub async fn get_anomalies_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::new(data.anomalies.report()))
+}
+
+/// GET /api/admin/bandwidth - Crawler bandwidth use against the daily quota
+///
+/// Requires the `anime:manage` permission. Lists the bytes crawls downloaded
+/// per day (UTC) over the last 30 days, newest first. With
+/// CRAWLER_DAILY_BANDWIDTH_BYTES set, crawls pause once today's total
+/// reaches it.
+///
+/// # Responses
+/// - 200: Bandwidth use
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/admin/bandwidth",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Crawler bandwidth retrieved", body = ApiResponse<CrawlerBandwidthReport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_bandwidth_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+) -> impl Responder {
+    let quota = data.config.load().crawler_bandwidth_quota();
+    match bandwidth_report(data.db.pool(), quota).await {
+        Ok(report) => HttpResponse::Ok().json(ApiResponse::new(report)),
+        Err(e) => {
+            error!("Failed to get crawler bandwidth: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get crawler bandwidth",
+            ))
+        }
+    }
+}
+
+/// GET /api/admin/db-pool - Database connection pool utilization
+///
+/// Requires the `anime:manage` permission. Reports the open, in-use, and
+/// idle connections of the primary pool and, if configured, the read
+/// replica's, with how long acquiring a connection takes right now. Pool
+/// sizes are set with DATABASE_MAX_CONNECTIONS and DATABASE_MIN_CONNECTIONS.
+///
+/// # Responses
+/// - 200: Pool utilization
+/// - 401: Not authenticated
+/// - 403: Missing the `anime:manage` permission
+#[utoipa::path(
+    get,
+    path = "/api/admin/db-pool",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Pool utilization retrieved", body = ApiResponse<DbPoolReport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError)
+    )
+)]
+pub async fn get_db_pool_handler(
+    data: web::Data<AppState>,
+    _auth: Permission<AnimeManage>,
+) -> impl Responder {
+    HttpResponse::Ok().json(ApiResponse::new(data.db.pool_report().await))
+}
+
+/// POST /api/admin/reload - Reload the selector profile, video server lists, and settings
+///
+/// Requires the `maintenance:run` permission. Same as sending the server
+/// SIGHUP: the .env file and environment are read again and the selector
+/// profile, video server lists, and per-request settings put in use without
+/// dropping connections (see [`crate::reload`]).
+///
+/// # Responses
+/// - 200: What is in effect now
+/// - 401: Not authenticated
+/// - 403: Missing the `maintenance:run` permission
+/// - 422: Invalid setting or selector profile; the running one is kept
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/admin/reload",
+    tag = "admin",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Configuration reloaded", body = ApiResponse<ConfigReload>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 422, description = "Invalid configuration", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn reload_config_handler(
+    data: web::Data<AppState>,
+    auth: Permission<MaintenanceRun>,
+) -> impl Responder {
+    match reload::reload(&data).await {
+        Ok(reloaded) => {
+            info!("Admin {} reloaded the configuration", auth.user_id);
+            HttpResponse::Ok().json(ApiResponse::new(reloaded))
+        }
+        Err(ReloadError::VideoServers(e)) => {
+            error!("Failed to reload configuration: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to load video server rules",
+            ))
+        }
+        Err(e) => {
+            warn!("Rejected configuration reload: {}", e);
+            HttpResponse::UnprocessableEntity()
+                .json(ApiError::new(ErrorCode::ValidationFailed, e.to_string()))
+        }
+    }
+}
+
+/// Configure admin routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/admin")
+            .route("/jobs", web::get().to(get_jobs_handler))
+            .route("/jobs/{id}/retry", web::post().to(retry_job_handler))
+            .route("/emails", web::get().to(get_email_deliveries_handler))
+            .route("/emails/{id}/resend", web::post().to(resend_email_handler))
+            .route("/tenants", web::get().to(get_tenants_handler))
+            .route("/tenants", web::post().to(create_tenant_handler))
+            .route("/anime/merge", web::post().to(merge_anime_handler))
+            .route("/anime/{slug}/diff", web::get().to(anime_diff_handler))
+            .route("/parser/golden", web::get().to(parser_golden_handler))
+            .route("/search-analytics", web::get().to(search_analytics_handler))
+            .route(
+                "/maintenance/{action}/confirm",
+                web::post().to(confirm_maintenance_handler),
+            )
+            .route(
+                "/maintenance/{action}",
+                web::post().to(run_maintenance_handler),
+            )
+            .route("/gaps", web::get().to(get_episode_gaps_handler))
+            .route("/integrity", web::get().to(get_integrity_report_handler))
+            .route(
+                "/integrity",
+                web::post().to(enqueue_integrity_check_handler),
+            )
+            .route("/roles", web::get().to(get_roles_handler))
+            .route("/roles", web::post().to(create_role_handler))
+            .route("/roles/{id}", web::put().to(update_role_handler))
+            .route("/roles/{id}", web::delete().to(delete_role_handler))
+            .route("/users/{id}/roles", web::get().to(get_user_roles_handler))
+            .route(
+                "/users/{id}/roles/{role_id}",
+                web::put().to(assign_role_handler),
+            )
+            .route(
+                "/users/{id}/roles/{role_id}",
+                web::delete().to(unassign_role_handler),
+            )
+            .route("/moderation", web::get().to(get_moderation_queue_handler))
+            .route(
+                "/moderation/users/{id}",
+                web::get().to(get_moderation_standing_handler),
+            )
+            .route(
+                "/moderation/users/{id}/mute",
+                web::delete().to(unmute_user_handler),
+            )
+            .route(
+                "/moderation/{id}",
+                web::get().to(get_moderation_item_handler),
+            )
+            .route(
+                "/moderation/{id}/approve",
+                web::post().to(approve_moderation_item_handler),
+            )
+            .route(
+                "/moderation/{id}/remove",
+                web::post().to(remove_moderation_item_handler),
+            )
+            .route("/erasures", web::get().to(get_data_erasures_handler))
+            .route("/video-servers", web::get().to(get_video_servers_handler))
+            .route(
+                "/video-servers/{server}",
+                web::put().to(set_video_server_handler),
+            )
+            .route(
+                "/video-servers/{server}",
+                web::delete().to(delete_video_server_handler),
+            )
+            .route("/flags", web::get().to(get_feature_flags_handler))
+            .route("/flags/{feature}", web::put().to(set_feature_flag_handler))
+            .route(
+                "/flags/{feature}",
+                web::delete().to(delete_feature_flag_handler),
+            )
+            .route("/tags", web::post().to(create_tag_handler))
+            .route("/tags/{id}", web::put().to(update_tag_handler))
+            .route("/tags/{id}", web::delete().to(delete_tag_handler))
+            .route(
+                "/anime/{slug}/age-rating",
+                web::get().to(get_age_rating_handler),
+            )
+            .route(
+                "/anime/{slug}/age-rating",
+                web::put().to(set_age_rating_handler),
+            )
+            .route(
+                "/anime/{slug}/age-rating",
+                web::delete().to(delete_age_rating_handler),
+            )
+            .route("/anomalies", web::get().to(get_anomalies_handler))
+            .route("/bandwidth", web::get().to(get_bandwidth_handler))
+            .route("/db-pool", web::get().to(get_db_pool_handler))
+            .route("/reload", web::post().to(reload_config_handler)),
+    );
+}
@@ -0,0 +1,582 @@
+//! Embedding the API in another actix application
+//!
+//! The bundled server is [`init`], [`spawn_background_tasks`], and
+//! [`build_app`] in an otherwise empty app. Applications of their own can
+//! mount the API under a path instead:
+//!
+//! ```no_run
+//! use actix_web::{App, HttpServer};
+//! use anime_scraper::config::Config;
+//! use anime_scraper::routes::{build_app, init, spawn_background_tasks};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let state = init(Config::from_env().with_base_path("/anime")).await?;
+//! spawn_background_tasks(&state);
+//! HttpServer::new(move || App::new().service(build_app(state.clone())))
+//!     .bind("0.0.0.0:8080")?
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every route and middleware moves under the base path (BASE_PATH for the
+//! bundled server), Swagger UI included, and so do the links the API hands
+//! out, the auth cookie's path, and the server URL in the OpenAPI document.
+
+use std::sync::Arc;
+
+use actix_web::dev::HttpServiceFactory;
+use actix_web::middleware::from_fn;
+use actix_web::web;
+use arc_swap::ArcSwap;
+use thiserror::Error;
+use tracing::{info, warn};
+use utoipa::openapi::Server;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::auth::{AuthConfig, JwtKeyError, JwtKeys};
+use crate::config::Config;
+use crate::crawler::backpressure::ApiLoad;
+use crate::db::{
+    backfill_blind_indexes, Database, DbError, RepositoryError, REPLICA_CHECK_INTERVAL,
+};
+use crate::email::{EmailError, EmailService, EmailTemplates};
+use crate::features::FeatureFlags;
+use crate::jobs::image_prefetch::RecentPrefetches;
+use crate::jobs::{self, JobWorkerConfig};
+use crate::middleware;
+use crate::moderation::ModerationHooks;
+use crate::parser::selectors::{self, ProfileError};
+use crate::routes::comments::CommentHider;
+use crate::routes::{
+    configure_admin_routes, configure_auth_routes, configure_cast_routes,
+    configure_collection_routes, configure_comment_routes, configure_community_routes,
+    configure_health_routes, configure_image_routes, configure_reaction_routes, configure_routes,
+    configure_sitemap_routes, configure_source_routes, configure_subtitle_routes,
+    configure_tag_routes, configure_user_routes, ApiDoc, AppState,
+};
+use crate::scraper::{AnomalyLog, RedirectPolicy, Scraper, ScraperConfig, USER_AGENTS};
+use crate::storage::{self, Storage};
+use crate::tenants::TenantRegistry;
+use crate::video_servers::VideoServerRules;
+
+/// Path the OpenAPI document is served at, below the mount path
+pub const OPENAPI_PATH: &str = "/api-docs/openapi.json";
+
+/// Errors setting up the application state
+#[derive(Debug, Error)]
+pub enum InitError {
+    /// Signing or verification keys couldn't be read
+    #[error("Failed to load JWT keys: {0}")]
+    Keys(#[from] JwtKeyError),
+
+    /// The selector profile is unreadable or a parser selector is invalid
+    #[error("Failed to load parser selectors: {0}")]
+    Selectors(#[from] ProfileError),
+
+    /// The database is unreachable
+    #[error("Failed to connect to database: {0}")]
+    Database(DbError),
+
+    /// A migration failed
+    #[error("Failed to run database migrations: {0}")]
+    Migrations(DbError),
+
+    /// Email templates or the SMTP transport couldn't be set up
+    #[error("Failed to set up email: {0}")]
+    Email(#[from] EmailError),
+
+    /// Tenants couldn't be loaded
+    #[error("Failed to load tenants: {0}")]
+    Tenants(RepositoryError),
+
+    /// Admin video server rules couldn't be loaded
+    #[error("Failed to load video server rules: {0}")]
+    VideoServers(RepositoryError),
+
+    /// Feature flags couldn't be loaded
+    #[error("Failed to load feature flags: {0}")]
+    FeatureFlags(RepositoryError),
+
+    /// Users stored before encryption was switched on couldn't be indexed
+    #[error("Failed to index user data: {0}")]
+    BlindIndexes(RepositoryError),
+
+    /// The client for subtitle and video hosts couldn't be built
+    #[error("Failed to build media client: {0}")]
+    MediaClient(reqwest::Error),
+}
+
+/// Build the application state from the configuration
+///
+/// Connects to the database and runs migrations, installs the user data
+/// cipher if one is configured (indexing users stored in plaintext before
+/// it was), and loads the JWT keys, email templates,
+/// tenants, video server rules, and feature flags. Background tasks aren't started; see
+/// [`spawn_background_tasks`].
+pub async fn init(config: Config) -> Result<web::Data<AppState>, InitError> {
+    let jwt_keys = JwtKeys::load(&config.jwt, &config.jwt_secret)?;
+    info!(
+        "Signing tokens with {:?} key {}",
+        jwt_keys.signing_key().algorithm(),
+        jwt_keys.signing_key().kid()
+    );
+
+    selectors::load_profile(config.selector_profile.as_deref())?;
+
+    info!("Connecting to database...");
+    let mut db = Database::with_config(&config.database_url, config.database_pool.clone())
+        .await
+        .map_err(InitError::Database)?;
+
+    info!("Running database migrations...");
+    db.run_migrations().await.map_err(InitError::Migrations)?;
+
+    info!("Database connected and migrations complete");
+
+    if let Some(replica_url) = &config.database_replica_url {
+        db = db
+            .with_replica(replica_url)
+            .await
+            .map_err(InitError::Database)?;
+        info!(
+            "Read replica configured ({})",
+            if db.replica_healthy() == Some(true) {
+                "up"
+            } else {
+                "down, reading from the primary until it's up"
+            }
+        );
+    }
+
+    if let Some(cipher) = config.field_cipher() {
+        info!(
+            "User data encryption on (current key {})",
+            cipher.current_key_id()
+        );
+        crate::db::encryption::install(cipher);
+        let indexed = backfill_blind_indexes(db.pool())
+            .await
+            .map_err(InitError::BlindIndexes)?;
+        if indexed > 0 {
+            info!(
+                "Indexed {} user(s) stored before encryption was on",
+                indexed
+            );
+        }
+    }
+
+    // Initialize email service if SMTP is configured
+    let email_templates = EmailTemplates::load(
+        config
+            .email_templates_dir
+            .as_deref()
+            .map(std::path::Path::new),
+    )?;
+    let email_service = match &config.smtp {
+        Some(smtp_config) => {
+            info!("Email service configured ({:?})", smtp_config.tls);
+            Some(
+                EmailService::new(smtp_config.clone(), config.frontend_url.clone())?
+                    .with_templates(email_templates),
+            )
+        }
+        None => {
+            info!("Email service not configured - email features will be disabled");
+            None
+        }
+    };
+
+    let tenants = TenantRegistry::load(db.pool())
+        .await
+        .map_err(InitError::Tenants)?;
+    info!("Loaded {} tenant(s)", tenants.all().len());
+
+    let video_servers = VideoServerRules::load(db.pool(), &config)
+        .await
+        .map_err(InitError::VideoServers)?;
+
+    let features = FeatureFlags::load(db.pool())
+        .await
+        .map_err(InitError::FeatureFlags)?;
+    for flag in features.all().iter().filter(|flag| !flag.enabled) {
+        warn!("Feature {} is disabled", flag.feature.as_str());
+    }
+
+    let storage = Storage::from_config(&config.storage);
+    info!("Object storage: {}", storage.backend_name());
+
+    // Keep the raw HTML of scraped pages if STORAGE_ARCHIVE_PAGES is on
+    let page_archive = config.storage.archive_pages.then(|| storage.clone());
+    let scraper_config = ScraperConfig {
+        redirects: RedirectPolicy {
+            max_redirects: config.scraper_max_redirects,
+            same_host_only: config.scraper_same_host_redirects,
+        },
+        ..ScraperConfig::default()
+    };
+    let anomalies = AnomalyLog::new();
+    let api_load = ApiLoad::new(config.crawler_backpressure.clone());
+    let scraper = Arc::new(
+        Scraper::with_config(scraper_config)
+            .with_archive(page_archive)
+            .with_anomaly_log(anomalies.clone()),
+    );
+    let media_client = reqwest::Client::builder()
+        .user_agent(USER_AGENTS[0])
+        .build()
+        .map_err(InitError::MediaClient)?;
+
+    Ok(web::Data::new(AppState {
+        db,
+        config: ArcSwap::from_pointee(config),
+        email_service,
+        tenants,
+        storage,
+        scraper,
+        moderation: ModerationHooks::new().with_hook(Arc::new(CommentHider)),
+        video_servers,
+        anomalies,
+        jwt_keys,
+        image_prefetches: RecentPrefetches::new(),
+        api_load,
+        features,
+        media_client,
+    }))
+}
+
+/// Start the job workers, schedulers, storage cleanup, read replica checks,
+/// and feature flag refresh
+///
+/// Call once per process, from inside the Tokio runtime; the tasks run
+/// until it shuts down.
+pub fn spawn_background_tasks(state: &web::Data<AppState>) {
+    let config = state.config.load_full();
+
+    state.db.spawn_replica_monitor(REPLICA_CHECK_INTERVAL);
+    if config.feature_flag_refresh_secs > 0 {
+        state.features.spawn_refresh(
+            state.db.pool().clone(),
+            std::time::Duration::from_secs(config.feature_flag_refresh_secs),
+        );
+    }
+    storage::spawn_cleanup(
+        state.storage.clone(),
+        storage::retention_rules(&config.storage),
+        std::time::Duration::from_secs(config.storage.cleanup_interval_secs),
+    );
+
+    jobs::spawn_workers(
+        state.clone(),
+        JobWorkerConfig {
+            worker_count: config.job_workers,
+            poll_interval_ms: config.job_poll_interval_ms,
+            ..Default::default()
+        },
+    );
+    if config.saved_search_interval_secs > 0 {
+        jobs::saved_searches::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.saved_search_interval_secs),
+        );
+    }
+    if config.priority_crawl.interval_secs > 0 {
+        jobs::priority::spawn_scheduler(state.clone(), config.priority_crawl.clone());
+    }
+    if config.popularity_interval_secs > 0 {
+        jobs::popularity::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.popularity_interval_secs),
+        );
+    }
+    if config.episode_gap_interval_secs > 0 {
+        jobs::gaps::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.episode_gap_interval_secs),
+        );
+    }
+    if config.completed_archive_interval_secs > 0 {
+        jobs::completed_archive::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.completed_archive_interval_secs),
+        );
+    }
+    if config.status_reconcile_interval_secs > 0 {
+        jobs::status_reconcile::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.status_reconcile_interval_secs),
+        );
+    }
+    if config.anonymous_cleanup_interval_secs > 0 {
+        jobs::anonymous_cleanup::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.anonymous_cleanup_interval_secs),
+        );
+    }
+    if config.change_events_cleanup_interval_secs > 0 {
+        jobs::change_events_cleanup::spawn_scheduler(
+            state.clone(),
+            std::time::Duration::from_secs(config.change_events_cleanup_interval_secs),
+        );
+    }
+    if config.outbox.interval_secs > 0 {
+        jobs::outbox::spawn_dispatcher(state.clone(), config.outbox.clone());
+    }
+}
+
+/// The whole API as one service, mounted at the configured base path
+///
+/// # Arguments
+/// * `state` - State from [`init`]; its `config.base_path` (see
+///   [`Config::with_base_path`]) is the path to mount the API under
+pub fn build_app(state: web::Data<AppState>) -> impl HttpServiceFactory {
+    let base_path = state.config.load().base_path.clone();
+    let limits = &state.config.load().request_limits;
+    let auth_config = web::Data::new(AuthConfig {
+        jwt_keys: state.jwt_keys.clone(),
+        pool: Some(state.db.pool().clone()),
+    });
+    let mut openapi = ApiDoc::openapi();
+    if !base_path.is_empty() {
+        openapi.servers = Some(vec![Server::new(base_path.clone())]);
+    }
+    let swagger = SwaggerUi::new("/swagger-ui/{_:.*}")
+        .url(OPENAPI_PATH, openapi)
+        .config(utoipa_swagger_ui::Config::from(format!(
+            "{}{}",
+            base_path, OPENAPI_PATH
+        )));
+
+    web::scope(&base_path)
+        .app_data(middleware::limits::json_config(limits))
+        .app_data(middleware::limits::multipart_config(limits))
+        .app_data(auth_config)
+        .app_data(state)
+        .wrap(from_fn(middleware::sanitize_errors))
+        .wrap(from_fn(middleware::gate_features))
+        .wrap(from_fn(middleware::negotiate_encoding))
+        .wrap(from_fn(middleware::cache_control))
+        .wrap(from_fn(middleware::resolve_tenant))
+        .wrap(from_fn(middleware::enforce_request_limits))
+        .wrap(from_fn(middleware::filter_ips))
+        .wrap(from_fn(middleware::negotiate_api_version))
+        .wrap(from_fn(middleware::track_api_load))
+        .wrap(from_fn(middleware::trace_requests))
+        .configure(configure_health_routes)
+        .configure(configure_sitemap_routes)
+        .service(swagger)
+        // More specific /api/* scopes must come before the catch-all /api scope
+        .configure(configure_auth_routes)
+        .configure(configure_collection_routes)
+        .configure(configure_comment_routes)
+        .configure(configure_reaction_routes)
+        .configure(configure_tag_routes)
+        .configure(configure_cast_routes)
+        .configure(configure_community_routes)
+        .configure(configure_user_routes)
+        .configure(configure_admin_routes)
+        .configure(configure_image_routes)
+        .configure(configure_source_routes)
+        .configure(configure_subtitle_routes)
+        .configure(configure_routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::App;
+
+    #[actix_rt::test]
+    #[ignore] // Requires database connection
+    async fn test_build_app_under_path() {
+        dotenvy::dotenv().ok();
+        if std::env::var("JWT_SECRET").is_err() {
+            std::env::set_var("JWT_SECRET", "test-secret-for-embedding");
+        }
+        let config = Config::from_env().with_base_path("/anime/");
+        let state = init(config).await.expect("Failed to initialize");
+        let app = init_service(App::new().service(build_app(state.clone()))).await;
+
+        let res = call_service(&app, TestRequest::get().uri("/anime/health").to_request()).await;
+        assert!(res.status().is_success());
+        let body: serde_json::Value = read_body_json(res).await;
+        assert_eq!(body["status"], "healthy");
+
+        let res = call_service(
+            &app,
+            TestRequest::get().uri("/anime/api/v2/auth/me").to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers().get("api-version").unwrap(), "2");
+
+        state.features.replace(vec![crate::models::FeatureFlag {
+            enabled: false,
+            ..crate::models::FeatureFlag::default_for(crate::models::Feature::SearchUpstream)
+        }]);
+        let res = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/anime/api/v2/search?q=naruto")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            res.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+        );
+        let body: serde_json::Value = read_body_json(res).await;
+        assert_eq!(body["code"], "FEATURE_DISABLED");
+        assert_eq!(body["details"]["feature"], "search-upstream");
+        state.features.replace(Vec::new());
+
+        let res = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/anime/api-docs/openapi.json")
+                .to_request(),
+        )
+        .await;
+        assert!(res.status().is_success());
+
+        let res = call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+        assert_eq!(res.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    #[ignore] // Requires database connection
+    async fn test_lists_leave_out_adult_anime() {
+        use crate::db::{
+            save_anime_detail, save_anime_updates, save_completed_anime, save_popular_anime,
+            update_cache_timestamp,
+        };
+        use crate::parser::{
+            AnimeDetail, AnimeUpdate, CompletedAnime, PopularEntry, PopularWindow,
+        };
+
+        dotenvy::dotenv().ok();
+        if std::env::var("JWT_SECRET").is_err() {
+            std::env::set_var("JWT_SECRET", "test-secret-for-embedding");
+        }
+        let state = init(Config::from_env())
+            .await
+            .expect("Failed to initialize");
+        let pool = state.db.pool();
+        let app = init_service(App::new().service(build_app(state.clone()))).await;
+
+        // Stored as ecchi, never crawled but listed as hentai, and unrated
+        let stored = "test-adult-lists-stored";
+        let listed = "test-adult-lists-listed";
+        let unrated = "test-adult-lists-unrated";
+        let detail = AnimeDetail {
+            title: stored.to_string(),
+            genres: vec!["Ecchi".to_string()],
+            ..Default::default()
+        };
+        save_anime_detail(pool, stored, &detail).await.unwrap();
+        let genres = |slug: &str| match slug {
+            s if s == listed => vec!["Hentai".to_string()],
+            _ => vec!["Comedy".to_string()],
+        };
+
+        let completed: Vec<CompletedAnime> = [stored, listed, unrated]
+            .into_iter()
+            .map(|slug| CompletedAnime {
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                url: format!("https://example.com/anime/{}/", slug),
+                thumbnail: String::new(),
+                thumbnails: None,
+                anime_type: "TV".to_string(),
+                episode_count: "12".to_string(),
+                status: "Completed".to_string(),
+                posted_by: "Admin".to_string(),
+                posted_at: "2024-01-01".to_string(),
+                released_at_iso: None,
+                series_title: slug.to_string(),
+                series_url: format!("https://example.com/anime/{}/", slug),
+                genres: genres(slug),
+                rating: "8.0".to_string(),
+            })
+            .collect();
+        save_completed_anime(pool, &completed).await.unwrap();
+        let popular: Vec<PopularEntry> = [stored, listed, unrated]
+            .into_iter()
+            .zip(1..)
+            .map(|(slug, rank)| PopularEntry {
+                rank,
+                slug: slug.to_string(),
+                title: slug.to_string(),
+                url: format!("https://example.com/anime/{}/", slug),
+                thumbnail: String::new(),
+                thumbnails: None,
+                genres: genres(slug),
+                rating: "8.0".to_string(),
+            })
+            .collect();
+        save_popular_anime(pool, PopularWindow::Today, &popular)
+            .await
+            .unwrap();
+        let updates: Vec<AnimeUpdate> = [stored, unrated]
+            .into_iter()
+            .map(|slug| AnimeUpdate {
+                slug: slug.to_string(),
+                title: format!("{} Episode 1", slug),
+                episode_url: format!("https://example.com/{}-episode-1/", slug),
+                thumbnail: String::new(),
+                thumbnails: None,
+                episode_number: "1".to_string(),
+                anime_type: "TV".to_string(),
+                series_title: slug.to_string(),
+                series_url: format!("https://example.com/anime/{}/", slug),
+                status: "Ongoing".to_string(),
+                release_info: "1 hour ago".to_string(),
+                released_at: None,
+                like_count: None,
+                liked_by_me: None,
+            })
+            .collect();
+        save_anime_updates(pool, &updates).await.unwrap();
+        for key in ["completed", "popular:today", "updates"] {
+            update_cache_timestamp(pool, key).await.unwrap();
+        }
+
+        let listed_slugs = |uri: &'static str| {
+            let app = &app;
+            async move {
+                let res = call_service(app, TestRequest::get().uri(uri).to_request()).await;
+                assert!(res.status().is_success(), "{}: {}", uri, res.status());
+                let body: serde_json::Value = read_body_json(res).await;
+                let mut slugs: Vec<String> = body["data"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|item| item["slug"].as_str())
+                    .filter(|slug| slug.starts_with("test-adult-lists-"))
+                    .map(str::to_string)
+                    .collect();
+                slugs.sort();
+                slugs
+            }
+        };
+        let unrated_only = vec![unrated.to_string()];
+        assert_eq!(listed_slugs("/api/completed").await, unrated_only);
+        assert_eq!(listed_slugs("/api/popular").await, unrated_only);
+        assert_eq!(listed_slugs("/api/updates").await, unrated_only);
+        assert_eq!(
+            listed_slugs("/api/completed?includeAdult=true").await.len(),
+            3
+        );
+        assert_eq!(
+            listed_slugs("/api/popular?includeAdult=true").await.len(),
+            3
+        );
+        assert_eq!(
+            listed_slugs("/api/updates?includeAdult=true").await.len(),
+            2
+        );
+    }
+}
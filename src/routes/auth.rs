@@ -10,27 +10,358 @@
 //! - POST /api/auth/reset-password - Reset password with token
 //! - POST /api/auth/verify-email - Verify email with token
 //! - POST /api/auth/resend-verification - Resend verification email
+//! - POST /api/auth/reactivate - Request a reactivation email for a deactivated account
+//! - POST /api/auth/reactivate/confirm - Reactivate an account with token
+//! - POST /api/device/register - Register an anonymous device
+//! - GET /.well-known/jwks.json - Public keys tokens can be verified with
 
-use actix_web::{web, HttpResponse, Responder};
+use std::net::IpAddr;
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Responder};
+use sqlx::PgConnection;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use crate::auth::device::{device_token_from_request, generate_device_token, hash_device_token};
 use crate::auth::{
-    create_auth_cookie, create_logout_cookie, generate_token, hash_password, verify_google_token,
-    verify_password, Auth,
+    create_auth_cookie, create_logout_cookie, generate_session_token, hash_password, password,
+    registration, verify_google_token, verify_password, Auth, UserClaims, JWT_EXPIRY_DAYS,
 };
 use crate::db::{
-    create_google_user, create_user, create_verification_token, delete_user_tokens,
+    bump_token_version, claim_device, count_registrations_from_ip, create_device,
+    create_google_user, create_session, create_user, create_verification_token, delete_user_tokens,
     find_user_by_email, find_user_by_google_id, find_user_by_id, find_verification_token,
-    link_google_account, mark_token_as_used, set_email_verified, update_user_password,
-    RepositoryError, TOKEN_TYPE_EMAIL_VERIFICATION, TOKEN_TYPE_PASSWORD_RESET,
+    get_token_state, get_user_language, is_user_active, link_google_account, mark_token_as_used,
+    reactivate_user, record_registration_ip, revoke_session, revoke_user_sessions,
+    set_email_verified, set_user_language, update_user_password, RepositoryError,
+    TOKEN_TYPE_EMAIL_VERIFICATION, TOKEN_TYPE_PASSWORD_RESET, TOKEN_TYPE_REACTIVATION,
 };
+use crate::email::{EmailMessage, Language};
+use crate::jobs;
+use crate::middleware::client_ip;
 use crate::models::{
-    ApiError, ApiResponse, AuthData, AuthResponse, ForgotPasswordRequest, GoogleAuthRequest,
-    LoginRequest, RegisterRequest, ResendVerificationRequest, ResetPasswordRequest, User,
-    VerifyEmailRequest,
+    ApiError, ApiResponse, AuthData, AuthResponse, ConfirmReactivationRequest, DeviceRegistration,
+    ErrorCode, ForgotPasswordRequest, GoogleAuthRequest, JwkSet, LoginRequest,
+    ReactivateAccountRequest, RegisterDeviceRequest, RegisterRequest, ResendVerificationRequest,
+    ResetPasswordRequest, User, VerifyEmailRequest, WeakPasswordResponse, MAX_DEVICE_PLATFORM_LEN,
 };
+use crate::routes::images::with_avatar_url;
 use crate::routes::AppState;
+use crate::tenants::CurrentTenant;
+
+/// Issue a fresh email verification token and queue the verification email
+///
+/// Any previous verification tokens for the user are invalidated. Pass a
+/// [`UnitOfWork`](crate::db::UnitOfWork) connection to issue the token and queue the email only if
+/// the rest of the flow commits.
+async fn queue_verification_email(
+    conn: &mut PgConnection,
+    user_id: i32,
+    email: &str,
+    language: Language,
+) -> Result<(), RepositoryError> {
+    // Delete any existing verification tokens for this user
+    delete_user_tokens(&mut *conn, user_id, TOKEN_TYPE_EMAIL_VERIFICATION).await?;
+
+    // Generate a new token (expires in 24 hours)
+    let token = Uuid::new_v4().to_string();
+    create_verification_token(
+        &mut *conn,
+        user_id,
+        &token,
+        TOKEN_TYPE_EMAIL_VERIFICATION,
+        24,
+    )
+    .await?;
+
+    jobs::enqueue_email_in(conn, email, language, &EmailMessage::Verification { token }).await?;
+    Ok(())
+}
+
+/// Create a user with their language and queue their verification email,
+/// all in one [`UnitOfWork`](crate::db::UnitOfWork)
+///
+/// The email is queued only if email is configured; delivery failures are
+/// retried in the background.
+///
+/// # Returns
+/// * `Ok(User)` - The created user
+/// * `Err(RepositoryError::EmailAlreadyExists)` - Email already registered in the tenant
+async fn create_user_with_verification(
+    data: &AppState,
+    tenant_id: i32,
+    email: &str,
+    password_hash: &str,
+    name: Option<&str>,
+    language: Language,
+) -> Result<User, RepositoryError> {
+    let mut uow = data.db.unit_of_work().await?;
+
+    let user = create_user(uow.conn(), tenant_id, email, password_hash, name).await?;
+    if language != Language::default() {
+        set_user_language(uow.conn(), user.id, language.code()).await?;
+    }
+    if data.email_service.is_some() {
+        queue_verification_email(uow.conn(), user.id, &user.email, language).await?;
+    }
+
+    uow.commit().await?;
+    Ok(user)
+}
+
+/// Look up the language to send a user's emails in
+async fn user_email_language(pool: &sqlx::PgPool, user_id: i32) -> Language {
+    match get_user_language(pool, user_id).await {
+        Ok(code) => Language::from_code(&code),
+        Err(e) => {
+            warn!("Failed to get language for user {}: {}", user_id, e);
+            Language::default()
+        }
+    }
+}
+
+/// Refuse to sign in to a deactivated account
+///
+/// # Returns
+/// * `Ok(())` - Account is active
+/// * `Err(HttpResponse)` - 403 if the account is deactivated, 500 if the check failed
+async fn ensure_active(pool: &sqlx::PgPool, user_id: i32) -> Result<(), HttpResponse> {
+    match is_user_active(pool, user_id).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(HttpResponse::Forbidden().json(ApiError::new(
+            ErrorCode::Forbidden,
+            "Account is deactivated, request a reactivation email to restore access",
+        ))),
+        Err(e) => {
+            error!("Failed to check account of user {}: {}", user_id, e);
+            Err(HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process login",
+            )))
+        }
+    }
+}
+
+/// Record a new device session and issue a JWT bound to it
+///
+/// The user agent and client IP are taken from the request so the session can
+/// be recognised in the sessions list. An anonymous device token sent along
+/// has its data merged into the account.
+///
+/// # Returns
+/// * `Ok(String)` - Signed JWT carrying the session ID
+/// * `Err(HttpResponse)` - 500 if the session or token could not be created
+async fn issue_session_token(
+    data: &AppState,
+    req: &HttpRequest,
+    tenant: &CurrentTenant,
+    user_id: i32,
+) -> Result<String, HttpResponse> {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(512).collect::<String>());
+    let ip_address = client_ip(req).map(|ip| ip.to_string());
+
+    // Role and token version carried in the token
+    let user_claims = match get_token_state(data.db.pool(), user_id).await {
+        Ok(state) => state
+            .map(|state| UserClaims::from_state(&state))
+            .unwrap_or_default(),
+        Err(e) => {
+            error!("Failed to get token state of user {}: {}", user_id, e);
+            return Err(HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to generate authentication token",
+            )));
+        }
+    };
+
+    let session = match create_session(
+        data.db.pool(),
+        user_id,
+        user_agent.as_deref(),
+        ip_address.as_deref(),
+        JWT_EXPIRY_DAYS,
+    )
+    .await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            error!("Failed to create session: {}", e);
+            return Err(HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to generate authentication token",
+            )));
+        }
+    };
+
+    let token =
+        generate_session_token(user_id, session.id, tenant.id, &user_claims, &data.jwt_keys)
+            .map_err(|e| {
+                error!("Failed to generate token: {}", e);
+                HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::InternalError,
+                    "Failed to generate authentication token",
+                ))
+            })?;
+
+    // A failed merge doesn't fail the sign-in; the device keeps its data
+    if let Some(device_token) = device_token_from_request(req) {
+        let token_hash = hash_device_token(device_token);
+        match claim_device(data.db.pool(), &token_hash, user_id, tenant.id).await {
+            Ok(true) => info!("Merged anonymous device into user {}", user_id),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to merge device into user {}: {}", user_id, e),
+        }
+    }
+
+    Ok(token)
+}
+
+/// Check a new password against the configured strength policy
+///
+/// # Arguments
+/// * `user_inputs` - The user's email and name, which shouldn't appear in the password
+///
+/// # Returns
+/// * `Ok(())` - Password is acceptable
+/// * `Err(HttpResponse)` - 422 with strength feedback
+async fn enforce_password_policy(
+    data: &AppState,
+    password: &str,
+    user_inputs: &[&str],
+) -> Result<(), HttpResponse> {
+    let config = data.config.load_full();
+    let feedback =
+        password::check_password(password, user_inputs, config.password_breach_check).await;
+
+    if feedback.score >= config.password_min_score {
+        return Ok(());
+    }
+
+    let error = if feedback.breached {
+        "Password has appeared in a data breach"
+    } else {
+        "Password is too weak"
+    };
+    Err(
+        HttpResponse::UnprocessableEntity().json(WeakPasswordResponse {
+            success: false,
+            error: error.to_string(),
+            feedback,
+            min_score: config.password_min_score,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }),
+    )
+}
+
+/// Apply the registration abuse checks: email domain, per-IP throttle, and CAPTCHA
+///
+/// # Returns
+/// * `Ok(Option<IpAddr>)` - Registration may proceed; the client IP, if known
+/// * `Err(HttpResponse)` - 400 for a rejected domain or missing/invalid
+///   CAPTCHA, 429 when the IP is throttled, 500 when a check can't be run
+async fn enforce_registration_policy(
+    data: &AppState,
+    req: &HttpRequest,
+    body: &RegisterRequest,
+) -> Result<Option<IpAddr>, HttpResponse> {
+    let config = data.config.load_full();
+    let config = &config.registration;
+
+    if let Err(rejection) = registration::check_email_domain(config, &body.email) {
+        warn!("Rejected registration for {}: {:?}", body.email, rejection);
+        return Err(HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            rejection.message(),
+        )));
+    }
+
+    let ip = client_ip(req);
+    throttle_registration_ip(data, ip).await?;
+
+    if let Some(captcha) = &config.captcha {
+        let Some(token) = body.captcha_token.as_deref().filter(|t| !t.is_empty()) else {
+            return Err(HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "CAPTCHA token is required",
+            )));
+        };
+        match registration::verify_captcha(captcha, token, ip).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err(HttpResponse::BadRequest().json(ApiError::new(
+                    ErrorCode::ValidationFailed,
+                    "CAPTCHA verification failed",
+                )));
+            }
+            Err(e) => {
+                error!("Failed to verify CAPTCHA: {}", e);
+                return Err(HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::InternalError,
+                    "Failed to verify CAPTCHA",
+                )));
+            }
+        }
+    }
+
+    Ok(ip)
+}
+
+/// Refuse a registration when its IP already registered REGISTRATION_MAX_PER_IP
+/// accounts within the window
+///
+/// Covers account sign-ups and anonymous device registrations alike, which
+/// share the per-IP budget.
+///
+/// # Returns
+/// * `Ok(())` - Registration may proceed, or the IP is unknown
+/// * `Err(HttpResponse)` - 429 when the IP is throttled, 500 when the check
+///   can't be run
+async fn throttle_registration_ip(data: &AppState, ip: Option<IpAddr>) -> Result<(), HttpResponse> {
+    let config = data.config.load_full();
+    let config = &config.registration;
+    let (Some(ip), true) = (ip, config.max_per_ip > 0) else {
+        return Ok(());
+    };
+
+    let window = chrono::Duration::seconds(config.ip_window_secs as i64);
+    let since = chrono::Utc::now() - window;
+    match count_registrations_from_ip(data.db.pool(), &ip.to_string(), since).await {
+        Ok(count) if count >= i64::from(config.max_per_ip) => {
+            warn!("Throttled registration from {} ({} recent)", ip, count);
+            Err(HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, config.ip_window_secs.to_string()))
+                .json(ApiError::new(
+                    ErrorCode::TooManyRequests,
+                    "Too many registrations from this address, try again later",
+                )))
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to count registrations from {}: {}", ip, e);
+            Err(HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process registration",
+            )))
+        }
+    }
+}
+
+/// Count a registration against its IP's budget, logging failures
+async fn record_registration(data: &AppState, ip: Option<IpAddr>) {
+    let config = data.config.load_full();
+    let (Some(ip), true) = (ip, config.registration.max_per_ip > 0) else {
+        return;
+    };
+    let window = chrono::Duration::seconds(config.registration.ip_window_secs as i64);
+    let purge_before = chrono::Utc::now() - window;
+    if let Err(e) = record_registration_ip(data.db.pool(), &ip.to_string(), purge_before).await {
+        warn!("Failed to record registration from {}: {}", ip, e);
+    }
+}
 
 /// Simple email validation using basic regex pattern
 fn is_valid_email(email: &str) -> bool {
@@ -67,11 +398,16 @@ fn is_valid_email(email: &str) -> bool {
 /// - email: User's email address (required, must be valid format)
 /// - password: User's password (required)
 /// - name: Optional display name
+/// - language: Optional preferred email language ("en" or "id")
+/// - captchaToken: CAPTCHA token (required when CAPTCHA verification is enabled)
 ///
 /// # Responses
 /// - 200: Registration successful, returns user info and JWT token
-/// - 400: Invalid email format or missing required fields
+/// - 400: Invalid email format, missing required fields, blocked or
+///   disposable email domain, or failed CAPTCHA
 /// - 409: Email already exists
+/// - 422: Password doesn't meet the strength policy, returns feedback
+/// - 429: Too many registrations from this IP, with `Retry-After`
 /// - 500: Internal server error
 #[utoipa::path(
     post,
@@ -82,23 +418,41 @@ fn is_valid_email(email: &str) -> bool {
         (status = 200, description = "Registration successful", body = AuthResponse),
         (status = 400, description = "Invalid request", body = ApiError),
         (status = 409, description = "Email already exists", body = ApiError),
+        (status = 422, description = "Password too weak", body = WeakPasswordResponse),
+        (status = 429, description = "Too many registrations from this IP", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     )
 )]
 pub async fn register(
     data: web::Data<AppState>,
+    req: HttpRequest,
+    tenant: CurrentTenant,
     body: web::Json<RegisterRequest>,
 ) -> impl Responder {
-    let pool = data.db.pool();
-
     // Validate email format
     if !is_valid_email(&body.email) {
-        return HttpResponse::BadRequest().json(ApiError::new("Invalid email format"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid email format",
+        ));
     }
 
     // Validate password is not empty
     if body.password.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Password is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Password is required",
+        ));
+    }
+
+    let client_ip = match enforce_registration_policy(&data, &req, &body).await {
+        Ok(ip) => ip,
+        Err(response) => return response,
+    };
+
+    let user_inputs = [body.email.as_str(), body.name.as_deref().unwrap_or("")];
+    if let Err(response) = enforce_password_policy(&data, &body.password, &user_inputs).await {
+        return response;
     }
 
     // Hash the password
@@ -106,42 +460,64 @@ pub async fn register(
         Ok(hash) => hash,
         Err(e) => {
             error!("Failed to hash password: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process registration"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process registration",
+            ));
         }
     };
 
-    // Create the user
-    let user = match create_user(pool, &body.email, &password_hash, body.name.as_deref()).await {
+    let language = body
+        .language
+        .as_deref()
+        .map(Language::from_code)
+        .unwrap_or_default();
+
+    // Create the user, their language, and the verification email together,
+    // so a failure leaves no account behind that can't be verified
+    let user = match create_user_with_verification(
+        &data,
+        tenant.id,
+        &body.email,
+        &password_hash,
+        body.name.as_deref(),
+        language,
+    )
+    .await
+    {
         Ok(user) => user,
         Err(RepositoryError::EmailAlreadyExists) => {
-            return HttpResponse::Conflict().json(ApiError::new("Email already exists"));
+            return HttpResponse::Conflict()
+                .json(ApiError::new(ErrorCode::Conflict, "Email already exists"));
         }
         Err(e) => {
             error!("Failed to create user: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to create user"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create user",
+            ));
         }
     };
 
     info!("User registered: {}", user.email);
 
+    record_registration(&data, client_ip).await;
+
     // Generate JWT token
-    let token = match generate_token(user.id, &data.config.jwt_secret) {
+    let token = match issue_session_token(&data, &req, &tenant, user.id).await {
         Ok(token) => token,
-        Err(e) => {
-            error!("Failed to generate token: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to generate authentication token"));
-        }
+        Err(response) => return response,
     };
 
     // Create HTTP-only cookie with the token
-    let cookie = create_auth_cookie(&token);
+    let cookie = create_auth_cookie(&token, &data.config.load().cookies);
 
     HttpResponse::Ok().cookie(cookie).json(AuthResponse {
         success: true,
-        data: AuthData { user, token },
+        data: AuthData {
+            user: with_avatar_url(&data.config.load(), user),
+            token,
+        },
         timestamp: chrono::Utc::now().to_rfc3339(),
     })
 }
@@ -156,6 +532,7 @@ pub async fn register(
 /// - 200: Login successful, returns user info and JWT token
 /// - 400: Missing required fields
 /// - 401: Invalid credentials
+/// - 403: Account is deactivated
 /// - 500: Internal server error
 #[utoipa::path(
     post,
@@ -166,31 +543,48 @@ pub async fn register(
         (status = 200, description = "Login successful", body = AuthResponse),
         (status = 400, description = "Invalid request", body = ApiError),
         (status = 401, description = "Invalid credentials", body = ApiError),
+        (status = 403, description = "Account is deactivated", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     )
 )]
-pub async fn login(data: web::Data<AppState>, body: web::Json<LoginRequest>) -> impl Responder {
+pub async fn login(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    tenant: CurrentTenant,
+    body: web::Json<LoginRequest>,
+) -> impl Responder {
     let pool = data.db.pool();
 
     // Validate required fields
     if body.email.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Email is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Email is required",
+        ));
     }
 
     if body.password.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Password is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Password is required",
+        ));
     }
 
     // Find user by email
-    let (user, password_hash) = match find_user_by_email(pool, &body.email).await {
+    let (user, password_hash) = match find_user_by_email(pool, tenant.id, &body.email).await {
         Ok(Some((user, hash))) => (user, hash),
         Ok(None) => {
-            return HttpResponse::Unauthorized().json(ApiError::new("Invalid credentials"));
+            return HttpResponse::Unauthorized().json(ApiError::new(
+                ErrorCode::Unauthorized,
+                "Invalid credentials",
+            ));
         }
         Err(e) => {
             error!("Failed to find user: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process login"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process login",
+            ));
         }
     };
 
@@ -198,7 +592,10 @@ pub async fn login(data: web::Data<AppState>, body: web::Json<LoginRequest>) ->
     let password_hash = match password_hash {
         Some(hash) => hash,
         None => {
-            return HttpResponse::Unauthorized().json(ApiError::new("Invalid credentials"));
+            return HttpResponse::Unauthorized().json(ApiError::new(
+                ErrorCode::Unauthorized,
+                "Invalid credentials",
+            ));
         }
     };
 
@@ -206,33 +603,41 @@ pub async fn login(data: web::Data<AppState>, body: web::Json<LoginRequest>) ->
     match verify_password(&body.password, &password_hash) {
         Ok(true) => {}
         Ok(false) => {
-            return HttpResponse::Unauthorized().json(ApiError::new("Invalid credentials"));
+            return HttpResponse::Unauthorized().json(ApiError::new(
+                ErrorCode::Unauthorized,
+                "Invalid credentials",
+            ));
         }
         Err(e) => {
             error!("Failed to verify password: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process login"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process login",
+            ));
         }
     }
 
+    if let Err(response) = ensure_active(pool, user.id).await {
+        return response;
+    }
+
     info!("User logged in: {}", user.email);
 
     // Generate JWT token
-    let token = match generate_token(user.id, &data.config.jwt_secret) {
+    let token = match issue_session_token(&data, &req, &tenant, user.id).await {
         Ok(token) => token,
-        Err(e) => {
-            error!("Failed to generate token: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to generate authentication token"));
-        }
+        Err(response) => return response,
     };
 
     // Create HTTP-only cookie with the token
-    let cookie = create_auth_cookie(&token);
+    let cookie = create_auth_cookie(&token, &data.config.load().cookies);
 
     HttpResponse::Ok().cookie(cookie).json(AuthResponse {
         success: true,
-        data: AuthData { user, token },
+        data: AuthData {
+            user: with_avatar_url(&data.config.load(), user),
+            token,
+        },
         timestamp: chrono::Utc::now().to_rfc3339(),
     })
 }
@@ -245,6 +650,7 @@ pub async fn login(data: web::Data<AppState>, body: web::Json<LoginRequest>) ->
 /// # Responses
 /// - 200: Authentication successful, returns user info and JWT token
 /// - 400: Missing or invalid ID token
+/// - 403: Account is deactivated
 /// - 500: Internal server error
 #[utoipa::path(
     post,
@@ -254,55 +660,68 @@ pub async fn login(data: web::Data<AppState>, body: web::Json<LoginRequest>) ->
     responses(
         (status = 200, description = "Authentication successful", body = AuthResponse),
         (status = 400, description = "Invalid request", body = ApiError),
+        (status = 403, description = "Account is deactivated", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     )
 )]
 pub async fn google_auth(
     data: web::Data<AppState>,
+    req: HttpRequest,
+    tenant: CurrentTenant,
     body: web::Json<GoogleAuthRequest>,
 ) -> impl Responder {
     let pool = data.db.pool();
 
     // Check if Google OAuth is configured
-    let google_client_id = match &data.config.google_client_id {
+    let google_client_id = match data.config.load().google_client_id.clone() {
         Some(id) => id,
         None => {
-            return HttpResponse::BadRequest()
-                .json(ApiError::new("Google OAuth is not configured"));
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Google OAuth is not configured",
+            ));
         }
     };
 
     // Validate ID token is not empty
     if body.id_token.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("ID token is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "ID token is required",
+        ));
     }
 
     // Verify Google ID token
-    let google_payload = match verify_google_token(&body.id_token, google_client_id).await {
+    let google_payload = match verify_google_token(&body.id_token, &google_client_id).await {
         Ok(payload) => payload,
         Err(e) => {
             warn!("Google token verification failed: {}", e);
-            return HttpResponse::BadRequest().json(ApiError::new("Invalid Google ID token"));
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Invalid Google ID token",
+            ));
         }
     };
 
     // Try to find existing user by Google ID
-    let user = match find_user_by_google_id(pool, &google_payload.sub).await {
+    let user = match find_user_by_google_id(pool, tenant.id, &google_payload.sub).await {
         Ok(Some(user)) => {
             info!("Existing Google user logged in: {}", user.email);
             user
         }
         Ok(None) => {
             // Check if user exists with this email (link accounts)
-            match find_user_by_email(pool, &google_payload.email).await {
+            match find_user_by_email(pool, tenant.id, &google_payload.email).await {
                 Ok(Some((existing_user, _))) => {
                     // Link Google account to existing user
                     if let Err(e) =
                         link_google_account(pool, existing_user.id, &google_payload.sub).await
                     {
                         error!("Failed to link Google account: {}", e);
-                        return HttpResponse::InternalServerError()
-                            .json(ApiError::new("Failed to link Google account"));
+                        return HttpResponse::InternalServerError().json(ApiError::new(
+                            ErrorCode::InternalError,
+                            "Failed to link Google account",
+                        ));
                     }
                     info!(
                         "Linked Google account to existing user: {}",
@@ -314,6 +733,7 @@ pub async fn google_auth(
                     // Create new user with Google OAuth
                     match create_google_user(
                         pool,
+                        tenant.id,
                         &google_payload.email,
                         &google_payload.sub,
                         google_payload
@@ -330,50 +750,58 @@ pub async fn google_auth(
                         }
                         Err(e) => {
                             error!("Failed to create Google user: {}", e);
-                            return HttpResponse::InternalServerError()
-                                .json(ApiError::new("Failed to create user"));
+                            return HttpResponse::InternalServerError().json(ApiError::new(
+                                ErrorCode::InternalError,
+                                "Failed to create user",
+                            ));
                         }
                     }
                 }
                 Err(e) => {
                     error!("Failed to find user by email: {}", e);
-                    return HttpResponse::InternalServerError()
-                        .json(ApiError::new("Failed to process authentication"));
+                    return HttpResponse::InternalServerError().json(ApiError::new(
+                        ErrorCode::InternalError,
+                        "Failed to process authentication",
+                    ));
                 }
             }
         }
         Err(e) => {
             error!("Failed to find user by Google ID: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process authentication"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process authentication",
+            ));
         }
     };
 
+    if let Err(response) = ensure_active(pool, user.id).await {
+        return response;
+    }
+
     // Generate JWT token
-    let token = match generate_token(user.id, &data.config.jwt_secret) {
+    let token = match issue_session_token(&data, &req, &tenant, user.id).await {
         Ok(token) => token,
-        Err(e) => {
-            error!("Failed to generate token: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to generate authentication token"));
-        }
+        Err(response) => return response,
     };
 
     // Create HTTP-only cookie with the token
-    let cookie = create_auth_cookie(&token);
+    let cookie = create_auth_cookie(&token, &data.config.load().cookies);
 
     HttpResponse::Ok().cookie(cookie).json(AuthResponse {
         success: true,
-        data: AuthData { user, token },
+        data: AuthData {
+            user: with_avatar_url(&data.config.load(), user),
+            token,
+        },
         timestamp: chrono::Utc::now().to_rfc3339(),
     })
 }
 
 /// POST /api/auth/logout - Logout (clears HTTP-only cookie)
 ///
-/// This endpoint clears the HTTP-only authentication cookie.
-/// The server doesn't maintain a token blacklist, so the client
-/// should also discard any stored token upon receiving a successful response.
+/// This endpoint clears the HTTP-only authentication cookie and revokes the
+/// session the token belongs to, so the token is rejected from then on.
 ///
 /// # Responses
 /// - 200: Logout successful
@@ -385,9 +813,21 @@ pub async fn google_auth(
         (status = 200, description = "Logout successful", body = ApiResponse<String>)
     )
 )]
-pub async fn logout() -> impl Responder {
+pub async fn logout(data: web::Data<AppState>, auth: Option<Auth>) -> impl Responder {
+    // Revoke the session so the token stops working even if it was copied
+    if let Some(Auth {
+        user_id,
+        session_id: Some(session_id),
+        ..
+    }) = auth
+    {
+        if let Err(e) = revoke_session(data.db.pool(), user_id, session_id).await {
+            warn!("Failed to revoke session {}: {}", session_id, e);
+        }
+    }
+
     // Clear the HTTP-only cookie by setting it to expire immediately
-    let cookie = create_logout_cookie();
+    let cookie = create_logout_cookie(&data.config.load().cookies);
 
     HttpResponse::Ok()
         .cookie(cookie)
@@ -420,11 +860,17 @@ pub async fn get_me(data: web::Data<AppState>, auth: Auth) -> impl Responder {
 
     // Find user by ID from JWT
     match find_user_by_id(pool, auth.user_id).await {
-        Ok(Some(user)) => HttpResponse::Ok().json(ApiResponse::new(user)),
-        Ok(None) => HttpResponse::Unauthorized().json(ApiError::new("User not found")),
+        Ok(Some(user)) => {
+            HttpResponse::Ok().json(ApiResponse::new(with_avatar_url(&data.config.load(), user)))
+        }
+        Ok(None) => HttpResponse::Unauthorized()
+            .json(ApiError::new(ErrorCode::Unauthorized, "User not found")),
         Err(e) => {
             error!("Failed to find user: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to get user info"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get user info",
+            ))
         }
     }
 }
@@ -451,27 +897,30 @@ pub async fn get_me(data: web::Data<AppState>, auth: Auth) -> impl Responder {
 )]
 pub async fn forgot_password(
     data: web::Data<AppState>,
+    tenant: CurrentTenant,
     body: web::Json<ForgotPasswordRequest>,
 ) -> impl Responder {
     let pool = data.db.pool();
 
     // Validate email format
     if !is_valid_email(&body.email) {
-        return HttpResponse::BadRequest().json(ApiError::new("Invalid email format"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid email format",
+        ));
     }
 
     // Check if email service is configured
-    let email_service = match &data.email_service {
-        Some(service) => service,
-        None => {
-            error!("Email service not configured");
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Email service not available"));
-        }
-    };
+    if data.email_service.is_none() {
+        error!("Email service not configured");
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Email service not available",
+        ));
+    }
 
     // Find user by email (don't reveal if user exists for security)
-    let user = match find_user_by_email(pool, &body.email).await {
+    let user = match find_user_by_email(pool, tenant.id, &body.email).await {
         Ok(Some((user, _))) => user,
         Ok(None) => {
             // Return success even if user doesn't exist (security best practice)
@@ -485,8 +934,10 @@ pub async fn forgot_password(
         }
         Err(e) => {
             error!("Failed to find user: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process request"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
         }
     };
 
@@ -503,20 +954,30 @@ pub async fn forgot_password(
         create_verification_token(pool, user.id, &token, TOKEN_TYPE_PASSWORD_RESET, 1).await
     {
         error!("Failed to create password reset token: {}", e);
-        return HttpResponse::InternalServerError()
-            .json(ApiError::new("Failed to process request"));
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to process request",
+        ));
     }
 
-    // Send password reset email
-    if let Err(e) = email_service
-        .send_password_reset_email(&body.email, &token)
-        .await
+    // Queue password reset email
+    let language = user_email_language(pool, user.id).await;
+    if let Err(e) = jobs::enqueue_email(
+        pool,
+        &body.email,
+        language,
+        &EmailMessage::PasswordReset { token },
+    )
+    .await
     {
-        error!("Failed to send password reset email: {}", e);
-        return HttpResponse::InternalServerError().json(ApiError::new("Failed to send email"));
+        error!("Failed to queue password reset email: {}", e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to send email",
+        ));
     }
 
-    info!("Password reset email sent to: {}", body.email);
+    info!("Password reset email queued for: {}", body.email);
     HttpResponse::Ok().json(ApiResponse::new(
         "If the email exists, a password reset link has been sent".to_string(),
     ))
@@ -531,6 +992,7 @@ pub async fn forgot_password(
 /// # Responses
 /// - 200: Password reset successful
 /// - 400: Invalid or expired token, or invalid password
+/// - 422: Password doesn't meet the strength policy, returns feedback
 /// - 500: Internal server error
 #[utoipa::path(
     post,
@@ -540,6 +1002,7 @@ pub async fn forgot_password(
     responses(
         (status = 200, description = "Password reset successful", body = ApiResponse<String>),
         (status = 400, description = "Invalid or expired token", body = ApiError),
+        (status = 422, description = "Password too weak", body = WeakPasswordResponse),
         (status = 500, description = "Internal server error", body = ApiError)
     )
 )]
@@ -551,40 +1014,78 @@ pub async fn reset_password(
 
     // Validate new password
     if body.new_password.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Password is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Password is required",
+        ));
     }
 
     if body.new_password.len() < 6 {
-        return HttpResponse::BadRequest()
-            .json(ApiError::new("Password must be at least 6 characters"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Password must be at least 6 characters",
+        ));
     }
 
     // Find the token
     let verification_token = match find_verification_token(pool, &body.token).await {
         Ok(Some(token)) => token,
         Ok(None) => {
-            return HttpResponse::BadRequest().json(ApiError::new("Invalid or expired token"));
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Invalid or expired token",
+            ));
         }
         Err(e) => {
             error!("Failed to find token: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process request"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
         }
     };
 
     // Check token type
     if verification_token.token_type != TOKEN_TYPE_PASSWORD_RESET {
-        return HttpResponse::BadRequest().json(ApiError::new("Invalid token type"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid token type",
+        ));
     }
 
     // Check if token is expired
     if verification_token.expires_at < chrono::Utc::now() {
-        return HttpResponse::BadRequest().json(ApiError::new("Token has expired"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Token has expired",
+        ));
     }
 
     // Check if token was already used
     if verification_token.used_at.is_some() {
-        return HttpResponse::BadRequest().json(ApiError::new("Token has already been used"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Token has already been used",
+        ));
+    }
+
+    // Check password strength against the account's own details
+    let user = match find_user_by_id(pool, verification_token.user_id).await {
+        Ok(user) => user,
+        Err(e) => {
+            error!("Failed to find user: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
+        }
+    };
+    let user_inputs = user
+        .as_ref()
+        .map(|user| [user.email.as_str(), user.name.as_deref().unwrap_or("")])
+        .unwrap_or_default();
+    if let Err(response) = enforce_password_policy(&data, &body.new_password, &user_inputs).await {
+        return response;
     }
 
     // Hash the new password
@@ -592,16 +1093,20 @@ pub async fn reset_password(
         Ok(hash) => hash,
         Err(e) => {
             error!("Failed to hash password: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process request"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
         }
     };
 
     // Update user's password
     if let Err(e) = update_user_password(pool, verification_token.user_id, &password_hash).await {
         error!("Failed to update password: {}", e);
-        return HttpResponse::InternalServerError()
-            .json(ApiError::new("Failed to update password"));
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to update password",
+        ));
     }
 
     // Mark token as used
@@ -609,6 +1114,14 @@ pub async fn reset_password(
         warn!("Failed to mark token as used: {}", e);
     }
 
+    // Sign out every device that was using the old password
+    if let Err(e) = revoke_user_sessions(pool, verification_token.user_id).await {
+        warn!("Failed to revoke sessions: {}", e);
+    }
+    if let Err(e) = bump_token_version(pool, verification_token.user_id).await {
+        warn!("Failed to revoke tokens: {}", e);
+    }
+
     info!(
         "Password reset successful for user_id: {}",
         verification_token.user_id
@@ -646,34 +1159,51 @@ pub async fn verify_email(
     let verification_token = match find_verification_token(pool, &body.token).await {
         Ok(Some(token)) => token,
         Ok(None) => {
-            return HttpResponse::BadRequest().json(ApiError::new("Invalid or expired token"));
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Invalid or expired token",
+            ));
         }
         Err(e) => {
             error!("Failed to find token: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process request"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
         }
     };
 
     // Check token type
     if verification_token.token_type != TOKEN_TYPE_EMAIL_VERIFICATION {
-        return HttpResponse::BadRequest().json(ApiError::new("Invalid token type"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid token type",
+        ));
     }
 
     // Check if token is expired
     if verification_token.expires_at < chrono::Utc::now() {
-        return HttpResponse::BadRequest().json(ApiError::new("Token has expired"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Token has expired",
+        ));
     }
 
     // Check if token was already used
     if verification_token.used_at.is_some() {
-        return HttpResponse::BadRequest().json(ApiError::new("Token has already been used"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Token has already been used",
+        ));
     }
 
     // Set email as verified
     if let Err(e) = set_email_verified(pool, verification_token.user_id, true).await {
         error!("Failed to verify email: {}", e);
-        return HttpResponse::InternalServerError().json(ApiError::new("Failed to verify email"));
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to verify email",
+        ));
     }
 
     // Mark token as used
@@ -707,27 +1237,30 @@ pub async fn verify_email(
 )]
 pub async fn resend_verification(
     data: web::Data<AppState>,
+    tenant: CurrentTenant,
     body: web::Json<ResendVerificationRequest>,
 ) -> impl Responder {
     let pool = data.db.pool();
 
     // Validate email format
     if !is_valid_email(&body.email) {
-        return HttpResponse::BadRequest().json(ApiError::new("Invalid email format"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid email format",
+        ));
     }
 
     // Check if email service is configured
-    let email_service = match &data.email_service {
-        Some(service) => service,
-        None => {
-            error!("Email service not configured");
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Email service not available"));
-        }
-    };
+    if data.email_service.is_none() {
+        error!("Email service not configured");
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Email service not available",
+        ));
+    }
 
     // Find user by email
-    let user = match find_user_by_email(pool, &body.email).await {
+    let user = match find_user_by_email(pool, tenant.id, &body.email).await {
         Ok(Some((user, _))) => user,
         Ok(None) => {
             // Return success even if user doesn't exist (security best practice)
@@ -742,45 +1275,357 @@ pub async fn resend_verification(
         }
         Err(e) => {
             error!("Failed to find user: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to process request"));
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
         }
     };
 
-    // Delete any existing verification tokens for this user
-    if let Err(e) = delete_user_tokens(pool, user.id, TOKEN_TYPE_EMAIL_VERIFICATION).await {
+    // Issue a new token and queue the verification email
+    let language = user_email_language(pool, user.id).await;
+    let queued = async {
+        let mut uow = data.db.unit_of_work().await?;
+        queue_verification_email(uow.conn(), user.id, &body.email, language).await?;
+        uow.commit().await
+    };
+    if let Err(e) = queued.await {
+        error!("Failed to queue verification email: {}", e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to send email",
+        ));
+    }
+
+    info!("Verification email queued for: {}", body.email);
+    HttpResponse::Ok().json(ApiResponse::new(
+        "If the email exists and is not verified, a verification link has been sent".to_string(),
+    ))
+}
+
+/// POST /api/auth/reactivate - Request an account reactivation email
+///
+/// # Request Body
+/// - email: Email address of the deactivated account (required)
+///
+/// # Responses
+/// - 200: Reactivation email sent (always returns success for security)
+/// - 400: Invalid email format
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/auth/reactivate",
+    tag = "auth",
+    request_body = ReactivateAccountRequest,
+    responses(
+        (status = 200, description = "Reactivation email sent", body = ApiResponse<String>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn request_reactivation(
+    data: web::Data<AppState>,
+    tenant: CurrentTenant,
+    body: web::Json<ReactivateAccountRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let sent = || {
+        HttpResponse::Ok().json(ApiResponse::new(
+            "If the account exists and is deactivated, a reactivation link has been sent"
+                .to_string(),
+        ))
+    };
+
+    // Validate email format
+    if !is_valid_email(&body.email) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid email format",
+        ));
+    }
+
+    // Check if email service is configured
+    if data.email_service.is_none() {
+        error!("Email service not configured");
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Email service not available",
+        ));
+    }
+
+    // Find user by email (don't reveal if the account exists or is active)
+    let user = match find_user_by_email(pool, tenant.id, &body.email).await {
+        Ok(Some((user, _))) => user,
+        Ok(None) => return sent(),
+        Err(e) => {
+            error!("Failed to find user: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
+        }
+    };
+    match is_user_active(pool, user.id).await {
+        Ok(false) => {}
+        Ok(true) => return sent(),
+        Err(e) => {
+            error!("Failed to check account of user {}: {}", user.id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
+        }
+    }
+
+    // Delete any existing reactivation tokens for this user
+    if let Err(e) = delete_user_tokens(pool, user.id, TOKEN_TYPE_REACTIVATION).await {
         warn!("Failed to delete existing tokens: {}", e);
     }
 
-    // Generate a new token
+    // Create reactivation token (expires in 24 hours)
     let token = Uuid::new_v4().to_string();
-
-    // Create verification token (expires in 24 hours)
     if let Err(e) =
-        create_verification_token(pool, user.id, &token, TOKEN_TYPE_EMAIL_VERIFICATION, 24).await
+        create_verification_token(pool, user.id, &token, TOKEN_TYPE_REACTIVATION, 24).await
     {
-        error!("Failed to create verification token: {}", e);
-        return HttpResponse::InternalServerError()
-            .json(ApiError::new("Failed to process request"));
+        error!("Failed to create reactivation token: {}", e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to process request",
+        ));
     }
 
-    // Send verification email
-    if let Err(e) = email_service
-        .send_verification_email(&body.email, &token)
-        .await
+    // Queue reactivation email
+    let language = user_email_language(pool, user.id).await;
+    if let Err(e) = jobs::enqueue_email(
+        pool,
+        &body.email,
+        language,
+        &EmailMessage::Reactivation { token },
+    )
+    .await
     {
-        error!("Failed to send verification email: {}", e);
-        return HttpResponse::InternalServerError().json(ApiError::new("Failed to send email"));
+        error!("Failed to queue reactivation email: {}", e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to send email",
+        ));
     }
 
-    info!("Verification email sent to: {}", body.email);
-    HttpResponse::Ok().json(ApiResponse::new(
-        "If the email exists and is not verified, a verification link has been sent".to_string(),
-    ))
+    info!("Reactivation email queued for user_id: {}", user.id);
+    sent()
+}
+
+/// POST /api/auth/reactivate/confirm - Reactivate an account with token
+///
+/// The user signs in normally afterwards.
+///
+/// # Request Body
+/// - token: Reactivation token (required)
+///
+/// # Responses
+/// - 200: Account reactivated
+/// - 400: Invalid or expired token
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/auth/reactivate/confirm",
+    tag = "auth",
+    request_body = ConfirmReactivationRequest,
+    responses(
+        (status = 200, description = "Account reactivated", body = ApiResponse<String>),
+        (status = 400, description = "Invalid or expired token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn confirm_reactivation(
+    data: web::Data<AppState>,
+    body: web::Json<ConfirmReactivationRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+
+    // Find the token
+    let verification_token = match find_verification_token(pool, &body.token).await {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Invalid or expired token",
+            ));
+        }
+        Err(e) => {
+            error!("Failed to find token: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process request",
+            ));
+        }
+    };
+
+    // Check token type
+    if verification_token.token_type != TOKEN_TYPE_REACTIVATION {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid token type",
+        ));
+    }
+
+    // Check if token is expired
+    if verification_token.expires_at < chrono::Utc::now() {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Token has expired",
+        ));
+    }
+
+    // Check if token was already used
+    if verification_token.used_at.is_some() {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Token has already been used",
+        ));
+    }
+
+    if let Err(e) = reactivate_user(pool, verification_token.user_id).await {
+        error!("Failed to reactivate account: {}", e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to reactivate account",
+        ));
+    }
+
+    // Mark token as used
+    if let Err(e) = mark_token_as_used(pool, &body.token).await {
+        warn!("Failed to mark token as used: {}", e);
+    }
+
+    info!(
+        "Account reactivated for user_id: {}",
+        verification_token.user_id
+    );
+    HttpResponse::Ok().json(ApiResponse::new("Account reactivated".to_string()))
+}
+
+/// POST /api/device/register - Register an anonymous device
+///
+/// Creates an anonymous account for the device and returns its token. Send
+/// the token in the X-Device-Token header to keep favorites, history, and
+/// watch progress without signing up; send it along when signing in or up
+/// to move that data into the account.
+///
+/// Device registrations count against the same per-IP budget as sign-ups
+/// (REGISTRATION_MAX_PER_IP). Accounts of devices that never stored
+/// anything are deleted once idle for ANONYMOUS_MAX_IDLE_DAYS.
+///
+/// # Request Body
+/// - platform: Optional client platform (e.g. "android")
+///
+/// # Responses
+/// - 200: Device registered, returns the device token
+/// - 400: Platform too long
+/// - 429: Too many registrations from this address
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/device/register",
+    tag = "auth",
+    request_body = RegisterDeviceRequest,
+    responses(
+        (status = 200, description = "Device registered", body = ApiResponse<DeviceRegistration>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 429, description = "Too many registrations from this IP", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn register_device(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    tenant: CurrentTenant,
+    body: Option<web::Json<RegisterDeviceRequest>>,
+) -> impl Responder {
+    let body = body.map(web::Json::into_inner).unwrap_or_default();
+    let platform = body
+        .platform
+        .as_deref()
+        .map(str::trim)
+        .filter(|platform| !platform.is_empty());
+    if platform.is_some_and(|platform| platform.chars().count() > MAX_DEVICE_PLATFORM_LEN) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            format!(
+                "Platform must be at most {} characters",
+                MAX_DEVICE_PLATFORM_LEN
+            ),
+        ));
+    }
+
+    let ip = client_ip(&req);
+    if let Err(response) = throttle_registration_ip(&data, ip).await {
+        return response;
+    }
+
+    let token = generate_device_token();
+    match create_device(
+        data.db.pool(),
+        tenant.id,
+        &hash_device_token(&token),
+        platform,
+    )
+    .await
+    {
+        Ok(device) => {
+            info!(
+                "Registered anonymous device {} (user {})",
+                device.device_id, device.user_id
+            );
+            record_registration(&data, ip).await;
+            HttpResponse::Ok().json(ApiResponse::new(DeviceRegistration {
+                device_id: device.device_id,
+                token,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to register device: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to register device",
+            ))
+        }
+    }
+}
+
+/// How long clients may cache the JWKS; short enough that a rotated-in key
+/// is picked up well within a grace window
+const JWKS_MAX_AGE_SECS: u64 = 300;
+
+/// GET /.well-known/jwks.json - Public keys tokens can be verified with
+///
+/// Lists the signing key and, during a rotation, the previous key, when they
+/// are RS256 or EdDSA keys. Deployments signing with the shared HS256 secret
+/// get an empty set.
+///
+/// # Responses
+/// - 200: JSON Web Key Set
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    tag = "auth",
+    responses(
+        (status = 200, description = "JSON Web Key Set", body = JwkSet)
+    )
+)]
+pub async fn jwks(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", JWKS_MAX_AGE_SECS),
+        ))
+        .json(data.jwt_keys.jwks(chrono::Utc::now()))
 }
 
 /// Configure authentication routes
 pub fn configure_auth_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/.well-known/jwks.json", web::get().to(jwks))
+        .route("/api/device/register", web::post().to(register_device));
     cfg.service(
         web::scope("/api/auth")
             .route("/register", web::post().to(register))
@@ -791,7 +1636,9 @@ pub fn configure_auth_routes(cfg: &mut web::ServiceConfig) {
             .route("/forgot-password", web::post().to(forgot_password))
             .route("/reset-password", web::post().to(reset_password))
             .route("/verify-email", web::post().to(verify_email))
-            .route("/resend-verification", web::post().to(resend_verification)),
+            .route("/resend-verification", web::post().to(resend_verification))
+            .route("/reactivate", web::post().to(request_reactivation))
+            .route("/reactivate/confirm", web::post().to(confirm_reactivation)),
     );
 }
 
@@ -0,0 +1,163 @@
+//! Cast routes for the Anime Scraper API
+//!
+//! Companion apps cast episodes to Chromecast receivers. The metadata is
+//! built from stored video sources, subtitle tracks, and anime details, so
+//! it is only available for crawled or previously viewed episodes:
+//! - GET /api/episode/:slug/cast-metadata - Media information for casting
+
+use actix_web::dev::ConnectionInfo;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use tracing::error;
+
+use super::subtitles::PROXY_PATH;
+use crate::constants::endpoints;
+use crate::db::{get_anime_detail, get_episode_by_url, get_subtitle_tracks, get_video_sources};
+use crate::middleware::limits::Slug;
+use crate::models::{ApiError, ApiResponse, CastMetadata, ErrorCode};
+use crate::routes::AppState;
+
+/// Absolute URL of the subtitle proxy, under the path the API is served at
+fn subtitle_proxy_url(connection: &ConnectionInfo, base_path: &str) -> String {
+    format!(
+        "{}://{}{}{}",
+        connection.scheme(),
+        connection.host(),
+        base_path,
+        PROXY_PATH
+    )
+}
+
+/// 500 response for a failed lookup
+fn lookup_error(what: &str, slug: &str, e: impl std::fmt::Display) -> HttpResponse {
+    error!("Failed to get {} of {}: {}", what, slug, e);
+    HttpResponse::InternalServerError().json(ApiError::new(
+        ErrorCode::InternalError,
+        "Failed to get cast metadata",
+    ))
+}
+
+/// GET /api/episode/:slug/cast-metadata - Media information for casting
+///
+/// Doesn't require authentication. Uses the first stored video source that
+/// is a video file, after video server rules are applied; iframe players
+/// can't be cast. Subtitle tracks point at the subtitle proxy on this
+/// server, so receivers get WebVTT with CORS headers.
+///
+/// # Responses
+/// - 200: Returns the cast metadata
+/// - 400: Invalid slug
+/// - 404: Episode not stored, or it has no castable source
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/episode/{slug}/cast-metadata",
+    tag = "anime",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier")
+    ),
+    responses(
+        (status = 200, description = "Cast metadata retrieved successfully", body = ApiResponse<CastMetadata>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 404, description = "Episode not found or not castable", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_cast_metadata_handler(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    slug: Slug,
+) -> impl Responder {
+    let db = &data.db;
+    let url = endpoints::episode(&data.config.load().base_url, &slug);
+
+    let (anime_slug, episode) = match db.read(|pool| get_episode_by_url(pool, &url)).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ApiError::new(
+                ErrorCode::EpisodeNotFound,
+                "Episode not found",
+            ))
+        }
+        Err(e) => return lookup_error("episode", &slug, e),
+    };
+
+    // Sources are saved under the page URL by the API and under the listed
+    // episode URL by the crawler
+    let mut sources = match db.read(|pool| get_video_sources(pool, &episode.url)).await {
+        Ok(sources) if sources.is_empty() && episode.url != url => {
+            match db.read(|pool| get_video_sources(pool, &url)).await {
+                Ok(sources) => sources,
+                Err(e) => return lookup_error("video sources", &slug, e),
+            }
+        }
+        Ok(sources) => sources,
+        Err(e) => return lookup_error("video sources", &slug, e),
+    };
+    data.video_servers.apply_sources(&mut sources);
+    let Some(source) = sources.iter().find(|source| source.embed.is_none()) else {
+        return HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "Episode has no castable video source",
+        ));
+    };
+
+    let anime = match db.read(|pool| get_anime_detail(pool, &anime_slug)).await {
+        Ok(anime) => anime.unwrap_or_default(),
+        Err(e) => return lookup_error("anime detail", &slug, e),
+    };
+    let subtitles = match db
+        .read(|pool| get_subtitle_tracks(pool, &episode.url))
+        .await
+    {
+        Ok(tracks) if tracks.is_empty() && episode.url != url => {
+            match db.read(|pool| get_subtitle_tracks(pool, &url)).await {
+                Ok(tracks) => tracks,
+                Err(e) => return lookup_error("subtitle tracks", &slug, e),
+            }
+        }
+        Ok(tracks) => tracks,
+        Err(e) => return lookup_error("subtitle tracks", &slug, e),
+    };
+
+    let subtitle_proxy = subtitle_proxy_url(&req.connection_info(), &data.config.load().base_path);
+    HttpResponse::Ok().json(ApiResponse::new(CastMetadata::build(
+        source,
+        &episode,
+        &anime.title,
+        &anime.poster,
+        &subtitles,
+        &subtitle_proxy,
+    )))
+}
+
+/// Configure cast routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_cast_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/episode/{slug}/cast-metadata")
+            .route("", web::get().to(get_cast_metadata_handler)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_subtitle_proxy_url() {
+        let req = TestRequest::default()
+            .insert_header(("Host", "api.example.com"))
+            .to_http_request();
+        assert_eq!(
+            subtitle_proxy_url(&req.connection_info(), ""),
+            format!("http://api.example.com{}", PROXY_PATH)
+        );
+        assert_eq!(
+            subtitle_proxy_url(&req.connection_info(), "/anime"),
+            format!("http://api.example.com/anime{}", PROXY_PATH)
+        );
+    }
+}
@@ -0,0 +1,892 @@
+//! Collection routes for the Anime Scraper API
+//!
+//! Collections are named, ordered lists of anime ("Best of 2023") kept apart
+//! from favorites. Each has a share slug; public collections can be read by
+//! anyone through it:
+//! - POST /api/user/collections - Create a collection
+//! - GET /api/user/collections - List user's collections
+//! - GET /api/user/collections/:id - Get a collection with its anime
+//! - PATCH /api/user/collections/:id - Rename, describe, or share a collection
+//! - DELETE /api/user/collections/:id - Delete a collection
+//! - POST /api/user/collections/:id/items - Add an anime
+//! - PUT /api/user/collections/:id/items - Reorder the anime
+//! - DELETE /api/user/collections/:id/items/:slug - Remove an anime
+//! - GET /api/collections/:shareSlug - Get a public collection
+
+use actix_web::{web, HttpResponse, Responder};
+use rand::Rng;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::auth::Auth;
+use crate::db::{
+    add_collection_item, count_collections, create_collection, delete_collection, get_collection,
+    get_collection_items, get_collections, get_public_collection, remove_collection_item,
+    reorder_collection_items, update_collection, RepositoryError, RepositoryResult,
+};
+use crate::middleware::limits::{is_valid_slug, Slug};
+use crate::models::{
+    ApiError, ApiResponse, Collection, CollectionDetail, CollectionItem, ErrorCode,
+};
+use crate::routes::AppState;
+use crate::tenants::CurrentTenant;
+
+/// Maximum number of collections per user
+pub const MAX_COLLECTIONS: i64 = 50;
+
+/// Maximum number of anime in one collection
+pub const MAX_COLLECTION_ITEMS: i64 = 500;
+
+/// Longest accepted collection name, in characters
+pub const MAX_COLLECTION_NAME_LEN: usize = 200;
+
+/// Longest accepted collection description, in characters
+pub const MAX_COLLECTION_DESCRIPTION_LEN: usize = 2000;
+
+/// Longest name-derived prefix of a share slug
+const SHARE_SLUG_PREFIX_LEN: usize = 60;
+
+/// Length of the random suffix that makes share slugs unguessable
+const SHARE_SLUG_SUFFIX_LEN: usize = 10;
+
+// ============================================================================
+// Request Bodies
+// ============================================================================
+
+/// Request body for creating a collection
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCollectionRequest {
+    /// Collection name
+    pub name: String,
+    /// Optional description
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether anyone can view the collection through its share slug (default: false)
+    #[serde(default)]
+    pub is_public: bool,
+}
+
+/// Request body for updating a collection; omitted fields are kept
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCollectionRequest {
+    /// New name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// New description; an empty string clears it
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether anyone can view the collection through its share slug
+    #[serde(default)]
+    pub is_public: Option<bool>,
+}
+
+/// Request body for adding an anime to a collection
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddCollectionItemRequest {
+    /// Anime slug identifier
+    pub anime_slug: String,
+    /// Anime title for display
+    pub anime_title: String,
+    /// Thumbnail image URL
+    #[serde(default)]
+    pub thumbnail: String,
+    /// 0-based position to insert at (default: append)
+    #[serde(default)]
+    pub position: Option<i32>,
+}
+
+/// Request body for reordering a collection
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderCollectionRequest {
+    /// Every anime slug in the collection, in the new order
+    pub anime_slugs: Vec<String>,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Trim and check a collection name
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Collection name is required".to_string());
+    }
+    if name.chars().count() > MAX_COLLECTION_NAME_LEN {
+        return Err(format!(
+            "Collection name must be at most {} characters",
+            MAX_COLLECTION_NAME_LEN
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Trim and check a collection description
+fn validate_description(description: &str) -> Result<String, String> {
+    let description = description.trim();
+    if description.chars().count() > MAX_COLLECTION_DESCRIPTION_LEN {
+        return Err(format!(
+            "Description must be at most {} characters",
+            MAX_COLLECTION_DESCRIPTION_LEN
+        ));
+    }
+    Ok(description.to_string())
+}
+
+/// Generate the share slug of a new collection
+///
+/// The slug starts with the name, lowercased and with runs of other
+/// characters turned into `-` (e.g. "best-of-2023"), and ends with a random
+/// suffix so private collections can't be found by guessing. It is fixed at
+/// creation, so renaming a collection doesn't break shared links.
+pub fn generate_share_slug(name: &str) -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+    let mut prefix = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            prefix.push(c);
+        } else if !prefix.is_empty() && !prefix.ends_with('-') {
+            prefix.push('-');
+        }
+        if prefix.len() >= SHARE_SLUG_PREFIX_LEN {
+            break;
+        }
+    }
+    let prefix = prefix.trim_end_matches('-');
+
+    let mut rng = rand::thread_rng();
+    let suffix: String = (0..SHARE_SLUG_SUFFIX_LEN)
+        .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+        .collect();
+
+    if prefix.is_empty() {
+        suffix
+    } else {
+        format!("{}-{}", prefix, suffix)
+    }
+}
+
+/// Load a collection's anime
+async fn collection_detail(
+    pool: &PgPool,
+    collection: Collection,
+) -> RepositoryResult<CollectionDetail> {
+    let items = get_collection_items(pool, collection.id).await?;
+    Ok(CollectionDetail { collection, items })
+}
+
+/// 404 response for a collection the user doesn't own
+fn collection_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Collection not found"))
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// POST /api/user/collections - Create a collection
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Request Body
+/// - name: Collection name
+/// - description: Optional description
+/// - isPublic: Whether the collection can be viewed through its share slug (optional)
+///
+/// # Responses
+/// - 200: Collection created
+/// - 400: Invalid name or description, or too many collections
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/user/collections",
+    tag = "collections",
+    request_body = CreateCollectionRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Collection created", body = ApiResponse<Collection>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn create_collection_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    body: web::Json<CreateCollectionRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+
+    let validated = validate_name(&body.name).and_then(|name| {
+        let description = body
+            .description
+            .as_deref()
+            .map(validate_description)
+            .transpose()?
+            .filter(|d| !d.is_empty());
+        Ok((name, description))
+    });
+    let (name, description) = match validated {
+        Ok(validated) => validated,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match count_collections(pool, auth.user_id).await {
+        Ok(count) if count >= MAX_COLLECTIONS => {
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                format!("At most {} collections are allowed", MAX_COLLECTIONS),
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to count collections: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create collection",
+            ));
+        }
+    }
+
+    match create_collection(
+        pool,
+        auth.user_id,
+        &name,
+        description.as_deref(),
+        body.is_public,
+        &generate_share_slug(&name),
+    )
+    .await
+    {
+        Ok(collection) => {
+            info!("User {} created collection {}", auth.user_id, collection.id);
+            HttpResponse::Ok().json(ApiResponse::new(collection))
+        }
+        Err(e) => {
+            error!("Failed to create collection: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create collection",
+            ))
+        }
+    }
+}
+
+/// GET /api/user/collections - List user's collections
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Responses
+/// - 200: Returns collections with their item counts, newest first
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/collections",
+    tag = "collections",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Collections retrieved successfully", body = ApiResponse<Vec<Collection>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_collections_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    match get_collections(data.db.pool(), auth.user_id).await {
+        Ok(collections) => HttpResponse::Ok().json(ApiResponse::new(collections)),
+        Err(e) => {
+            error!("Failed to get collections: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get collections",
+            ))
+        }
+    }
+}
+
+/// GET /api/user/collections/:id - Get a collection with its anime
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Responses
+/// - 200: Returns the collection and its anime, in order
+/// - 401: Not authenticated
+/// - 404: Collection not found
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/collections/{id}",
+    tag = "collections",
+    params(
+        ("id" = i32, Path, description = "Collection ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Collection retrieved successfully", body = ApiResponse<CollectionDetail>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Collection not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_collection_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let collection_id = path.into_inner();
+
+    let result = match get_collection(pool, auth.user_id, collection_id).await {
+        Ok(Some(collection)) => collection_detail(pool, collection).await.map(Some),
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(Some(detail)) => HttpResponse::Ok().json(ApiResponse::new(detail)),
+        Ok(None) => collection_not_found(),
+        Err(e) => {
+            error!("Failed to get collection {}: {}", collection_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get collection",
+            ))
+        }
+    }
+}
+
+/// PATCH /api/user/collections/:id - Update a collection
+///
+/// Requires authentication via JWT token in Authorization header. Omitted
+/// fields are kept; the share slug never changes, so making a collection
+/// public again restores its old link.
+///
+/// # Request Body
+/// - name: New name (optional)
+/// - description: New description, empty to clear (optional)
+/// - isPublic: Whether the collection can be viewed through its share slug (optional)
+///
+/// # Responses
+/// - 200: Collection updated
+/// - 400: Invalid name or description
+/// - 401: Not authenticated
+/// - 404: Collection not found
+/// - 500: Internal server error
+#[utoipa::path(
+    patch,
+    path = "/api/user/collections/{id}",
+    tag = "collections",
+    params(
+        ("id" = i32, Path, description = "Collection ID")
+    ),
+    request_body = UpdateCollectionRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Collection updated", body = ApiResponse<Collection>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Collection not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn update_collection_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+    body: web::Json<UpdateCollectionRequest>,
+) -> impl Responder {
+    let collection_id = path.into_inner();
+
+    let validated = body
+        .name
+        .as_deref()
+        .map(validate_name)
+        .transpose()
+        .and_then(|name| {
+            let description = body
+                .description
+                .as_deref()
+                .map(validate_description)
+                .transpose()?;
+            Ok((name, description))
+        });
+    let (name, description) = match validated {
+        Ok(validated) => validated,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match update_collection(
+        data.db.pool(),
+        auth.user_id,
+        collection_id,
+        name.as_deref(),
+        description.as_deref(),
+        body.is_public,
+    )
+    .await
+    {
+        Ok(Some(collection)) => {
+            info!("User {} updated collection {}", auth.user_id, collection_id);
+            HttpResponse::Ok().json(ApiResponse::new(collection))
+        }
+        Ok(None) => collection_not_found(),
+        Err(e) => {
+            error!("Failed to update collection {}: {}", collection_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to update collection",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/user/collections/:id - Delete a collection
+///
+/// Requires authentication via JWT token in Authorization header. The anime
+/// in it are not affected elsewhere (favorites, history, ...).
+///
+/// # Responses
+/// - 200: Collection deleted
+/// - 401: Not authenticated
+/// - 404: Collection not found
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/user/collections/{id}",
+    tag = "collections",
+    params(
+        ("id" = i32, Path, description = "Collection ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Collection deleted", body = ApiResponse<String>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Collection not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_collection_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let collection_id = path.into_inner();
+
+    match delete_collection(data.db.pool(), auth.user_id, collection_id).await {
+        Ok(true) => {
+            info!("User {} deleted collection {}", auth.user_id, collection_id);
+            HttpResponse::Ok().json(ApiResponse::new("Collection deleted".to_string()))
+        }
+        Ok(false) => collection_not_found(),
+        Err(e) => {
+            error!("Failed to delete collection {}: {}", collection_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete collection",
+            ))
+        }
+    }
+}
+
+/// POST /api/user/collections/:id/items - Add an anime to a collection
+///
+/// Requires authentication via JWT token in Authorization header. The anime
+/// is inserted at `position`, moving later anime down, or appended.
+///
+/// # Request Body
+/// - animeSlug: Anime slug identifier
+/// - animeTitle: Anime title for display
+/// - thumbnail: Thumbnail image URL (optional)
+/// - position: 0-based position to insert at (optional)
+///
+/// # Responses
+/// - 200: Anime added
+/// - 400: Invalid anime, or the collection is full
+/// - 401: Not authenticated
+/// - 404: Collection not found
+/// - 409: Anime already in the collection
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/user/collections/{id}/items",
+    tag = "collections",
+    params(
+        ("id" = i32, Path, description = "Collection ID")
+    ),
+    request_body = AddCollectionItemRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Anime added", body = ApiResponse<CollectionItem>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Collection not found", body = ApiError),
+        (status = 409, description = "Anime already in collection", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn add_collection_item_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+    body: web::Json<AddCollectionItemRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let collection_id = path.into_inner();
+
+    if !is_valid_slug(&body.anime_slug) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid anime slug",
+        ));
+    }
+    if body.anime_title.trim().is_empty() {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Anime title is required",
+        ));
+    }
+
+    match get_collection(pool, auth.user_id, collection_id).await {
+        Ok(Some(collection)) if collection.item_count >= MAX_COLLECTION_ITEMS => {
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                format!(
+                    "A collection can hold at most {} anime",
+                    MAX_COLLECTION_ITEMS
+                ),
+            ));
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => return collection_not_found(),
+        Err(e) => {
+            error!("Failed to get collection {}: {}", collection_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to add anime to collection",
+            ));
+        }
+    }
+
+    match add_collection_item(
+        pool,
+        collection_id,
+        &body.anime_slug,
+        body.anime_title.trim(),
+        &body.thumbnail,
+        body.position,
+    )
+    .await
+    {
+        Ok(item) => {
+            info!(
+                "User {} added {} to collection {}",
+                auth.user_id, body.anime_slug, collection_id
+            );
+            HttpResponse::Ok().json(ApiResponse::new(item))
+        }
+        Err(RepositoryError::NotFound(_)) => collection_not_found(),
+        Err(RepositoryError::Conflict(msg)) => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg))
+        }
+        Err(e) => {
+            error!("Failed to add anime to collection {}: {}", collection_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to add anime to collection",
+            ))
+        }
+    }
+}
+
+/// PUT /api/user/collections/:id/items - Reorder the anime in a collection
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Request Body
+/// - animeSlugs: Every anime slug in the collection exactly once, in the new order
+///
+/// # Responses
+/// - 200: Returns the reordered collection
+/// - 400: Slugs don't match the anime in the collection
+/// - 401: Not authenticated
+/// - 404: Collection not found
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/user/collections/{id}/items",
+    tag = "collections",
+    params(
+        ("id" = i32, Path, description = "Collection ID")
+    ),
+    request_body = ReorderCollectionRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Collection reordered", body = ApiResponse<CollectionDetail>),
+        (status = 400, description = "Invalid order", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Collection not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn reorder_collection_items_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+    body: web::Json<ReorderCollectionRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let collection_id = path.into_inner();
+
+    let collection = match get_collection(pool, auth.user_id, collection_id).await {
+        Ok(Some(collection)) => collection,
+        Ok(None) => return collection_not_found(),
+        Err(e) => {
+            error!("Failed to get collection {}: {}", collection_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to reorder collection",
+            ));
+        }
+    };
+
+    let result = match reorder_collection_items(pool, collection.id, &body.anime_slugs).await {
+        Ok(true) => collection_detail(pool, collection).await.map(Some),
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(Some(detail)) => {
+            info!(
+                "User {} reordered collection {}",
+                auth.user_id, collection_id
+            );
+            HttpResponse::Ok().json(ApiResponse::new(detail))
+        }
+        Ok(None) => HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "animeSlugs must list every anime in the collection exactly once",
+        )),
+        Err(e) => {
+            error!("Failed to reorder collection {}: {}", collection_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to reorder collection",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/user/collections/:id/items/:slug - Remove an anime from a collection
+///
+/// Requires authentication via JWT token in Authorization header. Later
+/// anime move up to close the gap.
+///
+/// # Responses
+/// - 200: Anime removed
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 404: Collection not found or anime not in it
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/user/collections/{id}/items/{slug}",
+    tag = "collections",
+    params(
+        ("id" = i32, Path, description = "Collection ID"),
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Anime removed", body = ApiResponse<String>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn remove_collection_item_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<(i32, String)>,
+    slug: Slug,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let (collection_id, _) = path.into_inner();
+    let slug = slug.into_inner();
+
+    let removed = match get_collection(pool, auth.user_id, collection_id).await {
+        Ok(Some(_)) => remove_collection_item(pool, collection_id, &slug).await,
+        Ok(None) => return collection_not_found(),
+        Err(e) => Err(e),
+    };
+
+    match removed {
+        Ok(true) => {
+            info!(
+                "User {} removed {} from collection {}",
+                auth.user_id, slug, collection_id
+            );
+            HttpResponse::Ok().json(ApiResponse::new(
+                "Anime removed from collection".to_string(),
+            ))
+        }
+        Ok(false) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "Anime not in collection",
+        )),
+        Err(e) => {
+            error!(
+                "Failed to remove anime from collection {}: {}",
+                collection_id, e
+            );
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to remove anime from collection",
+            ))
+        }
+    }
+}
+
+/// GET /api/collections/:shareSlug - Get a public collection
+///
+/// Doesn't require authentication. Private collections, and those of
+/// deactivated users, are reported as not found.
+///
+/// # Responses
+/// - 200: Returns the collection and its anime, in order
+/// - 400: Invalid slug
+/// - 404: Collection not found or not public
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/collections/{slug}",
+    tag = "collections",
+    params(
+        ("slug" = String, Path, description = "Share slug of the collection")
+    ),
+    responses(
+        (status = 200, description = "Collection retrieved successfully", body = ApiResponse<CollectionDetail>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 404, description = "Collection not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_shared_collection_handler(
+    data: web::Data<AppState>,
+    tenant: CurrentTenant,
+    slug: Slug,
+) -> impl Responder {
+    let pool = data.db.pool();
+
+    let result = match get_public_collection(pool, tenant.id, &slug).await {
+        Ok(Some(collection)) => collection_detail(pool, collection).await.map(Some),
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(Some(detail)) => HttpResponse::Ok().json(ApiResponse::new(detail)),
+        Ok(None) => collection_not_found(),
+        Err(e) => {
+            error!("Failed to get shared collection {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get collection",
+            ))
+        }
+    }
+}
+
+/// Configure collection routes
+///
+/// `/api/user/collections` must be configured before the `/api/user` scope
+/// in `configure_user_routes`, and `/api/collections` before the catch-all
+/// `/api` scope in `configure_routes`.
+pub fn configure_collection_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/user/collections")
+            .route("", web::post().to(create_collection_handler))
+            .route("", web::get().to(get_collections_handler))
+            .route("/{id}", web::get().to(get_collection_handler))
+            .route("/{id}", web::patch().to(update_collection_handler))
+            .route("/{id}", web::delete().to(delete_collection_handler))
+            .route("/{id}/items", web::post().to(add_collection_item_handler))
+            .route(
+                "/{id}/items",
+                web::put().to(reorder_collection_items_handler),
+            )
+            .route(
+                "/{id}/items/{slug}",
+                web::delete().to(remove_collection_item_handler),
+            ),
+    )
+    .service(
+        web::scope("/api/collections")
+            .route("/{slug}", web::get().to(get_shared_collection_handler)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_share_slug() {
+        let slug = generate_share_slug("Best of 2023!");
+        assert!(slug.starts_with("best-of-2023-"));
+        assert_eq!(slug.len(), "best-of-2023-".len() + SHARE_SLUG_SUFFIX_LEN);
+        assert!(is_valid_slug(&slug));
+        assert_ne!(slug, generate_share_slug("Best of 2023!"));
+
+        let slug = generate_share_slug("  ¡¡ ");
+        assert_eq!(slug.len(), SHARE_SLUG_SUFFIX_LEN);
+        assert!(is_valid_slug(&slug));
+
+        let slug = generate_share_slug(&"Ä long name ".repeat(40));
+        assert!(slug.len() <= SHARE_SLUG_PREFIX_LEN + 1 + SHARE_SLUG_SUFFIX_LEN);
+        assert!(is_valid_slug(&slug));
+    }
+
+    #[test]
+    fn test_validate_collection_fields() {
+        assert_eq!(
+            validate_name("  Best of 2023 "),
+            Ok("Best of 2023".to_string())
+        );
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"a".repeat(MAX_COLLECTION_NAME_LEN + 1)).is_err());
+        assert!(validate_name(&"あ".repeat(MAX_COLLECTION_NAME_LEN)).is_ok());
+
+        assert_eq!(validate_description("  "), Ok(String::new()));
+        assert!(validate_description(&"a".repeat(MAX_COLLECTION_DESCRIPTION_LEN + 1)).is_err());
+    }
+}
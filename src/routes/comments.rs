@@ -0,0 +1,528 @@
+//! Episode comment routes for the Anime Scraper API
+//!
+//! Comments can point at a playback position ("at 12:34 ...") and go through
+//! moderation: users report them with [`moderation::report`], muted users
+//! can't post, and [`CommentHider`] hides comments moderators remove.
+//! Comments of deactivated users are hidden while the account is inactive:
+//! - GET /api/episode/:slug/comments - List an episode's comments
+//! - POST /api/episode/:slug/comments - Post a comment
+//! - DELETE /api/episode/:slug/comments/:id - Delete own comment
+//! - POST /api/episode/:slug/comments/:id/report - Report a comment
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::Auth;
+use crate::db::{
+    count_episode_comments, create_episode_comment, delete_episode_comment, get_episode_comment,
+    get_episode_comments, get_user_muted_until, remove_episode_comment,
+};
+use crate::middleware::limits::Slug;
+use crate::models::{
+    ApiError, ApiResponse, CommentPage, EpisodeComment, ErrorCode, COMMENT_CONTENT_TYPE,
+};
+use crate::moderation::{
+    self, HookFuture, ModerationError, ModerationEvent, ModerationHook, NewReport,
+};
+use crate::routes::images::avatar_url;
+use crate::routes::AppState;
+use crate::tenants::CurrentTenant;
+
+/// Longest accepted comment, in characters
+pub const MAX_COMMENT_LEN: usize = 2000;
+
+/// Latest playback position a comment can refer to (24 hours)
+pub const MAX_COMMENT_TIMESTAMP_SECS: i32 = 24 * 60 * 60;
+
+/// Longest accepted report reason, in characters
+const MAX_REPORT_REASON_LEN: usize = 500;
+
+/// Default number of comments per page
+const DEFAULT_COMMENTS_PER_PAGE: i64 = 20;
+
+/// Maximum number of comments per page
+const MAX_COMMENTS_PER_PAGE: i64 = 100;
+
+// ============================================================================
+// Request Bodies
+// ============================================================================
+
+/// Query parameters for listing comments
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct CommentsQuery {
+    /// Page number (default: 1)
+    pub page: Option<i64>,
+    /// Comments per page (default: 20, max: 100)
+    pub per_page: Option<i64>,
+}
+
+/// Request body for posting a comment
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCommentRequest {
+    /// Comment text
+    pub body: String,
+    /// Playback position the comment refers to, in seconds (optional)
+    #[serde(default)]
+    pub timestamp_seconds: Option<i32>,
+}
+
+/// Request body for reporting a comment
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportCommentRequest {
+    /// Why the comment breaks the rules
+    #[serde(default)]
+    pub reason: String,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Trim and check a comment, returning the text to store
+fn validate_comment(body: &CreateCommentRequest) -> Result<String, String> {
+    let text = body.body.trim();
+    if text.is_empty() {
+        return Err("Comment is required".to_string());
+    }
+    if text.chars().count() > MAX_COMMENT_LEN {
+        return Err(format!(
+            "Comment must be at most {} characters",
+            MAX_COMMENT_LEN
+        ));
+    }
+    if body
+        .timestamp_seconds
+        .is_some_and(|secs| !(0..=MAX_COMMENT_TIMESTAMP_SECS).contains(&secs))
+    {
+        return Err(format!(
+            "timestampSeconds must be between 0 and {}",
+            MAX_COMMENT_TIMESTAMP_SECS
+        ));
+    }
+    Ok(text.to_string())
+}
+
+/// Resolve the author's avatar into a URL clients can load
+fn present_comment(data: &AppState, mut comment: EpisodeComment) -> EpisodeComment {
+    comment.author_avatar = comment
+        .author_avatar
+        .map(|avatar| avatar_url(&data.config.load(), avatar));
+    comment
+}
+
+/// 404 response for a comment that doesn't exist or is hidden
+fn comment_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Comment not found"))
+}
+
+/// Hides comments that moderators remove
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommentHider;
+
+impl ModerationHook for CommentHider {
+    fn on_event<'a>(&'a self, pool: &'a PgPool, event: &'a ModerationEvent) -> HookFuture<'a> {
+        Box::pin(async move {
+            if let ModerationEvent::Removed { item, .. } = event {
+                if item.content_type == COMMENT_CONTENT_TYPE {
+                    remove_episode_comment(pool, item.content_id).await?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// GET /api/episode/:slug/comments - List an episode's comments
+///
+/// Doesn't require authentication. Comments are listed newest first;
+/// removed comments and those of deactivated users are left out.
+///
+/// Query parameters:
+/// - page: Page number (default: 1)
+/// - per_page: Comments per page (default: 20, max: 100)
+///
+/// # Responses
+/// - 200: Returns a page of comments
+/// - 400: Invalid slug
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/episode/{slug}/comments",
+    tag = "comments",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier"),
+        CommentsQuery
+    ),
+    responses(
+        (status = 200, description = "Comments retrieved successfully", body = ApiResponse<CommentPage>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_comments_handler(
+    data: web::Data<AppState>,
+    tenant: CurrentTenant,
+    slug: Slug,
+    query: web::Query<CommentsQuery>,
+) -> impl Responder {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_COMMENTS_PER_PAGE)
+        .clamp(1, MAX_COMMENTS_PER_PAGE);
+
+    let pool = data.db.pool();
+    let items = get_episode_comments(
+        pool,
+        tenant.id,
+        &slug,
+        per_page,
+        (page - 1).saturating_mul(per_page),
+    );
+    match tokio::try_join!(items, count_episode_comments(pool, tenant.id, &slug)) {
+        Ok((items, total)) => HttpResponse::Ok().json(ApiResponse::new(CommentPage {
+            items: items
+                .into_iter()
+                .map(|comment| present_comment(&data, comment))
+                .collect(),
+            page,
+            per_page,
+            total,
+        })),
+        Err(e) => {
+            error!("Failed to get comments of {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get comments",
+            ))
+        }
+    }
+}
+
+/// POST /api/episode/:slug/comments - Post a comment on an episode
+///
+/// Requires authentication via JWT token in Authorization header. Users
+/// muted by moderators can't post until the mute ends.
+///
+/// # Request Body
+/// - body: Comment text
+/// - timestampSeconds: Playback position the comment refers to (optional)
+///
+/// # Responses
+/// - 200: Comment posted
+/// - 400: Invalid slug, empty or too long comment, or invalid timestamp
+/// - 401: Not authenticated
+/// - 403: User is muted
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/episode/{slug}/comments",
+    tag = "comments",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier")
+    ),
+    request_body = CreateCommentRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Comment posted", body = ApiResponse<EpisodeComment>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "User is muted", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn create_comment_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    slug: Slug,
+    body: web::Json<CreateCommentRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+
+    let text = match validate_comment(&body) {
+        Ok(text) => text,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match get_user_muted_until(pool, auth.user_id).await {
+        Ok(Some(until)) => {
+            return HttpResponse::Forbidden().json(
+                ApiError::new(
+                    ErrorCode::Forbidden,
+                    "You are muted and can't post comments",
+                )
+                .with_details(serde_json::json!({ "mutedUntil": until.to_rfc3339() })),
+            );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to check mute of user {}: {}", auth.user_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to post comment",
+            ));
+        }
+    }
+
+    match create_episode_comment(pool, auth.user_id, &slug, &text, body.timestamp_seconds).await {
+        Ok(comment) => {
+            info!(
+                "User {} commented on {} (comment {})",
+                auth.user_id, &*slug, comment.id
+            );
+            HttpResponse::Ok().json(ApiResponse::new(present_comment(&data, comment)))
+        }
+        Err(e) => {
+            error!("Failed to post comment: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to post comment",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/episode/:slug/comments/:id - Delete own comment
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Responses
+/// - 200: Comment deleted
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 404: Comment not found or written by another user
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/episode/{slug}/comments/{id}",
+    tag = "comments",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier"),
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Comment deleted", body = ApiResponse<String>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Comment not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_comment_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    slug: Slug,
+    path: web::Path<(String, i32)>,
+) -> impl Responder {
+    let (_, comment_id) = path.into_inner();
+
+    match delete_episode_comment(data.db.pool(), auth.user_id, &slug, comment_id).await {
+        Ok(true) => {
+            info!("User {} deleted comment {}", auth.user_id, comment_id);
+            HttpResponse::Ok().json(ApiResponse::new("Comment deleted".to_string()))
+        }
+        Ok(false) => comment_not_found(),
+        Err(e) => {
+            error!("Failed to delete comment {}: {}", comment_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete comment",
+            ))
+        }
+    }
+}
+
+/// POST /api/episode/:slug/comments/:id/report - Report a comment
+///
+/// Requires authentication via JWT token in Authorization header. The
+/// comment is queued for moderators; reporting it again is accepted but
+/// counted once.
+///
+/// # Request Body
+/// - reason: Why the comment breaks the rules
+///
+/// # Responses
+/// - 200: Comment reported
+/// - 400: Invalid slug, reason too long, or reporting own comment
+/// - 401: Not authenticated
+/// - 404: Comment not found
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/episode/{slug}/comments/{id}/report",
+    tag = "comments",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier"),
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    request_body = ReportCommentRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Comment reported", body = ApiResponse<String>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Comment not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn report_comment_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    tenant: CurrentTenant,
+    slug: Slug,
+    path: web::Path<(String, i32)>,
+    body: web::Json<ReportCommentRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let (_, comment_id) = path.into_inner();
+
+    if body.reason.trim().chars().count() > MAX_REPORT_REASON_LEN {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            format!(
+                "Reason must be at most {} characters",
+                MAX_REPORT_REASON_LEN
+            ),
+        ));
+    }
+
+    let comment = match get_episode_comment(pool, tenant.id, &slug, comment_id).await {
+        Ok(Some(comment)) => comment,
+        Ok(None) => return comment_not_found(),
+        Err(e) => {
+            error!("Failed to get comment {}: {}", comment_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to report comment",
+            ));
+        }
+    };
+
+    let report = NewReport {
+        content_type: COMMENT_CONTENT_TYPE,
+        content_id: comment.id,
+        author_id: comment.author_id,
+        content: &comment.body,
+        reporter_id: auth.user_id,
+        reason: &body.reason,
+    };
+    match moderation::report(pool, &data.moderation, &report).await {
+        Ok(item) => {
+            info!(
+                "User {} reported comment {} (moderation item {})",
+                auth.user_id, comment_id, item.id
+            );
+            HttpResponse::Ok().json(ApiResponse::new("Comment reported".to_string()))
+        }
+        Err(ModerationError::SelfReport) => HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "You can't report your own comment",
+        )),
+        Err(e) => {
+            error!("Failed to report comment {}: {}", comment_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to report comment",
+            ))
+        }
+    }
+}
+
+/// Configure episode comment routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_comment_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/episode/{slug}/comments")
+            .route("", web::get().to(get_comments_handler))
+            .route("", web::post().to(create_comment_handler))
+            .route("/{id}", web::delete().to(delete_comment_handler))
+            .route("/{id}/report", web::post().to(report_comment_handler)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ModerationItem, ModerationStatus, UserStrike};
+
+    fn request(body: &str, timestamp_seconds: Option<i32>) -> CreateCommentRequest {
+        CreateCommentRequest {
+            body: body.to_string(),
+            timestamp_seconds,
+        }
+    }
+
+    #[test]
+    fn test_validate_comment() {
+        assert_eq!(
+            validate_comment(&request("  at 12:34 the OP drops  ", Some(754))),
+            Ok("at 12:34 the OP drops".to_string())
+        );
+        assert!(validate_comment(&request("  ", None)).is_err());
+        assert!(validate_comment(&request(&"a".repeat(MAX_COMMENT_LEN + 1), None)).is_err());
+        assert!(validate_comment(&request(&"あ".repeat(MAX_COMMENT_LEN), None)).is_ok());
+        assert!(validate_comment(&request("hi", Some(-1))).is_err());
+        assert!(validate_comment(&request("hi", Some(MAX_COMMENT_TIMESTAMP_SECS + 1))).is_err());
+        assert!(validate_comment(&request("hi", Some(0))).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_comment_hider_ignores_other_events() {
+        // A lazy pool never connects, so any query would fail the hook
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let item = ModerationItem {
+            id: 1,
+            content_type: "review".to_string(),
+            content_id: 7,
+            author_id: 42,
+            excerpt: String::new(),
+            status: ModerationStatus::Removed,
+            report_count: 1,
+            resolved_by: Some(1),
+            resolution_note: None,
+            resolved_at: None,
+            created_at: "2024-12-27T10:00:00+00:00".to_string(),
+        };
+        let removed = ModerationEvent::Removed {
+            item: item.clone(),
+            strike: UserStrike {
+                id: 3,
+                user_id: 42,
+                item_id: Some(1),
+                reason: String::new(),
+                issued_by: Some(1),
+                created_at: "2024-12-27T10:00:00+00:00".to_string(),
+            },
+            recent_strikes: 1,
+        };
+        assert!(CommentHider.on_event(&pool, &removed).await.is_ok());
+
+        let approved = ModerationEvent::Approved {
+            item: ModerationItem {
+                content_type: COMMENT_CONTENT_TYPE.to_string(),
+                ..item
+            },
+        };
+        assert!(CommentHider.on_event(&pool, &approved).await.is_ok());
+    }
+}
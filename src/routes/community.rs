@@ -0,0 +1,147 @@
+//! Community routes for the Anime Scraper API
+//!
+//! Leaderboards are computed from registered users' watch history and
+//! favorites, per tenant. Users who set `communityOptOut` in their
+//! preferences are left out. Computed leaderboards are cached for
+//! COMMUNITY_TOP_TTL_SECS:
+//! - GET /api/community/top - Most-watched anime among registered users
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use super::parse_list_filter;
+use crate::db::{
+    compute_community_top, get_cached_community_top, save_community_top, RepositoryResult,
+};
+use crate::models::{ApiError, ApiResponse, CommunityTop, ErrorCode, LeaderboardWindow};
+use crate::routes::AppState;
+use crate::tenants::CurrentTenant;
+
+/// Default number of anime on a leaderboard
+const DEFAULT_COMMUNITY_TOP_LIMIT: usize = 20;
+
+/// Anime kept per computed leaderboard, the most `limit` can ask for
+const MAX_COMMUNITY_TOP_LIMIT: usize = 100;
+
+/// Query parameters for the community leaderboard
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct CommunityTopQuery {
+    /// Time window (weekly, monthly, all-time; default: weekly)
+    pub window: Option<String>,
+    /// Number of anime (default: 20, max: 100)
+    pub limit: Option<usize>,
+}
+
+/// Get the tenant's leaderboard for a window, computing it if the cache is stale
+async fn community_top(
+    data: &AppState,
+    tenant_id: i32,
+    window: LeaderboardWindow,
+) -> RepositoryResult<CommunityTop> {
+    let pool = data.db.pool();
+    let ttl_ms = (data.config.load().community_top_ttl_secs * 1000) as i64;
+
+    match get_cached_community_top(pool, tenant_id, window, ttl_ms).await {
+        Ok(Some(top)) => return Ok(top),
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read cached community top: {}", e),
+    }
+
+    let now = chrono::Utc::now();
+    let since = window.since(now);
+    let items = data
+        .db
+        .read(|pool| compute_community_top(pool, tenant_id, since, MAX_COMMUNITY_TOP_LIMIT as i64))
+        .await?;
+    let top = CommunityTop {
+        window,
+        items,
+        computed_at: now.to_rfc3339(),
+    };
+    info!(
+        "Computed {} community top for tenant {} ({} anime)",
+        window.as_str(),
+        tenant_id,
+        top.items.len()
+    );
+
+    if let Err(e) = save_community_top(pool, tenant_id, &top).await {
+        warn!("Failed to cache community top: {}", e);
+    }
+    Ok(top)
+}
+
+/// GET /api/community/top - Most-watched anime among registered users
+///
+/// Doesn't require authentication. Anime are ranked by how many users
+/// watched an episode within the window, then by how many favorited them.
+/// Only active users of the tenant who haven't opted out are counted.
+///
+/// Query parameters:
+/// - window: weekly (last 7 days), monthly (last 30 days), or all-time
+///   (default: weekly)
+/// - limit: Number of anime (default: 20, max: 100)
+///
+/// # Responses
+/// - 200: Returns the leaderboard
+/// - 400: Unknown window
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/community/top",
+    tag = "community",
+    params(CommunityTopQuery),
+    responses(
+        (status = 200, description = "Leaderboard retrieved successfully", body = ApiResponse<CommunityTop>),
+        (status = 400, description = "Unknown window", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_community_top_handler(
+    data: web::Data<AppState>,
+    tenant: CurrentTenant,
+    query: web::Query<CommunityTopQuery>,
+) -> impl Responder {
+    let windows: Vec<&str> = LeaderboardWindow::ALL.iter().map(|w| w.as_str()).collect();
+    let window = match parse_list_filter(
+        "window",
+        query.window.as_deref(),
+        LeaderboardWindow::parse,
+        &windows,
+    ) {
+        Ok(window) => window.unwrap_or(LeaderboardWindow::Weekly),
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_COMMUNITY_TOP_LIMIT)
+        .clamp(1, MAX_COMMUNITY_TOP_LIMIT);
+
+    match community_top(&data, tenant.id, window).await {
+        Ok(mut top) => {
+            top.items.truncate(limit);
+            HttpResponse::Ok().json(ApiResponse::new(top))
+        }
+        Err(e) => {
+            error!("Failed to compute community top: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get community top",
+            ))
+        }
+    }
+}
+
+/// Configure community routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_community_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/community").route("/top", web::get().to(get_community_top_handler)),
+    );
+}
@@ -0,0 +1,90 @@
+//! Health check routes
+//!
+//! Outside `/api`, for load balancers and orchestrators:
+//! - GET /health - Liveness; always healthy while the process serves requests
+//! - GET /health/db - Database connectivity, and the read replica's if one
+//!   is configured (reads fall back to the primary, so a replica being down
+//!   doesn't make the service unhealthy)
+//! - GET /health/ready - Readiness: database and, if configured, SMTP
+
+use actix_web::{web, HttpResponse, Responder};
+use tracing::error;
+
+use crate::routes::AppState;
+
+/// Health check endpoint
+async fn health_check() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "healthy",
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+/// Read replica status reported by the database health check
+fn replica_status(healthy: Option<bool>) -> &'static str {
+    match healthy {
+        Some(true) => "connected",
+        Some(false) => "disconnected",
+        None => "disabled",
+    }
+}
+
+/// Database health check endpoint
+async fn db_health_check(data: web::Data<AppState>) -> impl Responder {
+    match data.db.health_check().await {
+        Ok(()) => HttpResponse::Ok().json(serde_json::json!({
+            "status": "healthy",
+            "database": "connected",
+            "replica": replica_status(data.db.replica_healthy()),
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })),
+        Err(e) => {
+            error!("Database health check failed: {}", e);
+            HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "status": "unhealthy",
+                "database": "disconnected",
+                "error": e.to_string(),
+                "timestamp": chrono::Utc::now().to_rfc3339()
+            }))
+        }
+    }
+}
+
+/// Readiness check endpoint
+///
+/// Reports ready only when the database and, if configured, the SMTP server
+/// are reachable.
+async fn readiness_check(data: web::Data<AppState>) -> impl Responder {
+    let database = data.db.health_check().await.map_err(|e| e.to_string());
+    let email = match &data.email_service {
+        Some(service) => Some(service.health_check().await.map_err(|e| e.to_string())),
+        None => None,
+    };
+
+    let check = |result: &Result<(), String>| match result {
+        Ok(()) => serde_json::json!({ "status": "healthy" }),
+        Err(e) => serde_json::json!({ "status": "unhealthy", "error": e }),
+    };
+
+    let ready = database.is_ok() && email.as_ref().is_none_or(|r| r.is_ok());
+    let body = serde_json::json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "database": check(&database),
+        "email": email.as_ref().map(check).unwrap_or(serde_json::json!({ "status": "disabled" })),
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        error!("Readiness check failed: {}", body);
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+/// Configure health check routes
+pub fn configure_health_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/health", web::get().to(health_check))
+        .route("/health/db", web::get().to(db_health_check))
+        .route("/health/ready", web::get().to(readiness_check));
+}
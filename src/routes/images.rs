@@ -0,0 +1,631 @@
+//! Image proxy routes for the Anime Scraper API
+//!
+//! Thumbnails are served from the scraped site, which may block hotlinking.
+//! The proxy refetches them, but only for URLs signed by this server, so it
+//! can't be used as an open proxy. Fetched images are kept in object storage
+//! (unless STORAGE_IMAGE_CACHE is off) so repeat requests skip upstream.
+//! Uploaded avatars live in object storage and are served through the same
+//! proxy, signed with their storage key instead of a URL:
+//! - GET /api/images/sign - Get a signed proxy URL for an image
+//! - GET /api/images/proxy - Fetch an image through a signed URL
+
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::io::Cursor;
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, Responder};
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageReader, Limits};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{error, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::signing::{remaining_secs, SignatureError};
+use crate::auth::Auth;
+use crate::config::Config;
+use crate::jobs::image_prefetch::prefetch_thumbnails;
+use crate::models::{ApiError, ApiResponse, ErrorCode, SignedUrl, User};
+use crate::routes::AppState;
+use crate::storage::{keys, StoredObject};
+
+/// Path of the proxy endpoint, covered by the signature
+pub const PROXY_PATH: &str = "/api/images/proxy";
+
+/// Largest image the proxy will relay (10 MB)
+const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Timeout for fetching an image from upstream
+const UPSTREAM_TIMEOUT_SECS: u64 = 15;
+
+/// Most redirects followed when fetching an image from upstream
+const MAX_IMAGE_REDIRECTS: usize = 5;
+
+/// Width and height of processed avatars, in pixels
+pub const AVATAR_SIZE: u32 = 256;
+
+/// Largest width or height of an uploaded avatar before resizing
+const MAX_AVATAR_DIMENSION: u32 = 8192;
+
+/// Content types accepted for avatar uploads
+pub const AVATAR_CONTENT_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// Query parameters for the sign endpoint
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct SignImageQuery {
+    /// Absolute http(s) URL of the image
+    pub url: String,
+}
+
+/// Whether `url` is an http(s) URL on one of the allowed hosts or their subdomains
+pub fn is_allowed_image_url(url: &str, allowed_hosts: &[String]) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = parsed.host_str().map(str::to_lowercase) else {
+        return false;
+    };
+    allowed_hosts
+        .iter()
+        .any(|allowed| host == *allowed || host.ends_with(&format!(".{}", allowed)))
+}
+
+/// Query parameter of list endpoints that prefetch thumbnails
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchImagesQuery {
+    /// Queue the returned thumbnails for the image cache (default:
+    /// IMAGE_PREFETCH)
+    pub prefetch_images: Option<bool>,
+}
+
+/// Whether a list response should prefetch its thumbnails
+///
+/// Read from `prefetchImages` in the query string, or IMAGE_PREFETCH when
+/// it's absent or not a boolean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefetchImages(pub bool);
+
+impl PrefetchImages {
+    /// Queue a prefetch of `thumbnails` if the request asked for one
+    pub fn thumbnails<'a>(
+        self,
+        state: &web::Data<AppState>,
+        thumbnails: impl IntoIterator<Item = &'a str>,
+    ) {
+        if self.0 {
+            prefetch_thumbnails(state, thumbnails);
+        }
+    }
+}
+
+impl FromRequest for PrefetchImages {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        let requested = web::Query::<PrefetchImagesQuery>::from_query(req.query_string())
+            .ok()
+            .and_then(|query| query.prefetch_images);
+        let configured = req
+            .app_data::<web::Data<AppState>>()
+            .is_some_and(|state| state.config.load().image_prefetch);
+        ready(Ok(PrefetchImages(requested.unwrap_or(configured))))
+    }
+}
+
+/// Content type of an image, detected from its leading bytes
+///
+/// The local storage backend doesn't record content types, so cached images
+/// are identified by their signature instead.
+pub fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => Some("image/avif"),
+        _ => None,
+    }
+}
+
+/// Content type of a stored image, from storage metadata or its bytes
+fn stored_content_type(object: &StoredObject) -> Option<String> {
+    object
+        .content_type
+        .clone()
+        .filter(|t| t.starts_with("image/"))
+        .or_else(|| sniff_image_type(&object.bytes).map(str::to_string))
+}
+
+/// Decode an uploaded avatar, crop it to a centered square, and re-encode it
+/// as an [`AVATAR_SIZE`] pixel PNG
+///
+/// Dimensions are capped at [`MAX_AVATAR_DIMENSION`] before decoding, so a
+/// small file can't expand into a huge bitmap. Only the first frame of an
+/// animated image is kept. CPU-bound; run it on the blocking pool.
+pub fn process_avatar(bytes: &[u8]) -> Result<Vec<u8>, image::ImageError> {
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_AVATAR_DIMENSION);
+    limits.max_image_height = Some(MAX_AVATAR_DIMENSION);
+    reader.limits(limits);
+
+    let avatar = reader
+        .decode()?
+        .resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3);
+    let mut png = Cursor::new(Vec::new());
+    avatar.write_to(&mut png, ImageFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+/// Whether an avatar is the storage key of an uploaded image rather than
+/// an external URL
+pub fn is_stored_avatar(avatar: &str) -> bool {
+    avatar.starts_with(keys::AVATARS)
+}
+
+/// Signed proxy URL of an image in object storage
+pub fn sign_stored_image(config: &Config, key: &str) -> SignedUrl {
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(config.signed_url_ttl_secs);
+    let url = config
+        .url_signer()
+        .sign_at(PROXY_PATH, &[("key", key)], expires_at.timestamp());
+    SignedUrl {
+        url,
+        expires_at: expires_at.to_rfc3339(),
+    }
+}
+
+/// URL of an avatar: a signed proxy URL for uploaded avatars, stored by
+/// key, and the avatar itself for external ones (from Google sign-in)
+pub fn avatar_url(config: &Config, avatar: String) -> String {
+    if is_stored_avatar(&avatar) {
+        sign_stored_image(config, &avatar).url
+    } else {
+        avatar
+    }
+}
+
+/// Replace an uploaded avatar's storage key with a signed proxy URL
+pub fn with_avatar_url(config: &Config, mut user: User) -> User {
+    user.avatar = user.avatar.map(|avatar| avatar_url(config, avatar));
+    user
+}
+
+/// Image response, publicly cacheable until the signed URL expires
+fn image_response(
+    query: &HashMap<String, String>,
+    content_type: &str,
+    body: impl Into<actix_web::web::Bytes>,
+) -> HttpResponse {
+    let max_age = remaining_secs(query, chrono::Utc::now().timestamp());
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, content_type.to_string()))
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", max_age),
+        ))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .body(body.into())
+}
+
+/// GET /api/images/sign - Get a signed proxy URL for an image
+///
+/// Requires authentication. Only images on the configured proxy hosts
+/// (IMAGE_PROXY_HOSTS, defaulting to the scraped site) can be signed.
+///
+/// # Responses
+/// - 200: Returns the signed URL and its expiry
+/// - 400: URL is not an allowed image URL
+/// - 401: Not authenticated
+#[utoipa::path(
+    get,
+    path = "/api/images/sign",
+    tag = "images",
+    params(SignImageQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Signed URL created", body = ApiResponse<SignedUrl>),
+        (status = 400, description = "URL not allowed", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError)
+    )
+)]
+pub async fn sign_image_handler(
+    data: web::Data<AppState>,
+    _auth: Auth,
+    query: web::Query<SignImageQuery>,
+) -> impl Responder {
+    let config = data.config.load_full();
+
+    if !is_allowed_image_url(&query.url, &config.image_proxy_hosts) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Image URL is not allowed",
+        ));
+    }
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::seconds(config.signed_url_ttl_secs);
+    let url = config.url_signer().sign_at(
+        PROXY_PATH,
+        &[("url", query.url.as_str())],
+        expires_at.timestamp(),
+    );
+
+    HttpResponse::Ok().json(ApiResponse::new(SignedUrl {
+        url,
+        expires_at: expires_at.to_rfc3339(),
+    }))
+}
+
+/// GET /api/images/proxy - Fetch an image through a signed URL
+///
+/// Doesn't require authentication; the signature authorizes the request.
+/// Responses are publicly cacheable until the URL expires. URLs signed with
+/// a `key` instead of a `url` serve an uploaded avatar from object storage.
+///
+/// # Responses
+/// - 200: Image bytes
+/// - 403: Missing, invalid, or expired signature
+/// - 404: Stored image no longer exists
+/// - 502: Upstream fetch failed or did not return an image
+#[utoipa::path(
+    get,
+    path = "/api/images/proxy",
+    tag = "images",
+    params(
+        ("url" = Option<String>, Query, description = "Image URL"),
+        ("key" = Option<String>, Query, description = "Storage key of an uploaded avatar"),
+        ("expires" = i64, Query, description = "Expiry (unix seconds)"),
+        ("kid" = String, Query, description = "Signing key ID"),
+        ("sig" = String, Query, description = "Signature")
+    ),
+    responses(
+        (status = 200, description = "Image", content_type = "image/*"),
+        (status = 403, description = "Invalid or expired signature", body = ApiError),
+        (status = 404, description = "Stored image not found", body = ApiError),
+        (status = 502, description = "Upstream error", body = ApiError),
+        (status = 503, description = "Image proxy disabled by an operator", body = ApiError)
+    )
+)]
+pub async fn proxy_image_handler(
+    data: web::Data<AppState>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    if let Err(e) = data.config.load().url_signer().verify(PROXY_PATH, &query) {
+        let message = match e {
+            SignatureError::Expired => "Signed URL has expired",
+            _ => "Invalid signature",
+        };
+        return HttpResponse::Forbidden().json(ApiError::new(ErrorCode::Forbidden, message));
+    }
+
+    if let Some(key) = query.get("key") {
+        return stored_image_response(&data, &query, key).await;
+    }
+
+    let Some(url) = query.get("url") else {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Missing image URL",
+        ));
+    };
+
+    let cache_key = keys::image(url);
+    let use_cache = data.config.load().storage.image_cache;
+    if use_cache {
+        match data.storage.get(&cache_key).await {
+            Ok(Some(object)) => {
+                if let Some(content_type) = stored_content_type(&object) {
+                    return image_response(&query, &content_type, object.bytes);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cached image {}: {}", url, e),
+        }
+    }
+
+    let allowed_hosts = data.config.load().image_proxy_hosts.clone();
+    let (content_type, body) = match fetch_upstream_image(url, &allowed_hosts).await {
+        Ok(image) => image,
+        Err(e @ ImageFetchError::Client(_)) => {
+            error!("Image proxy failed to fetch {}: {}", url, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to fetch image",
+            ));
+        }
+        Err(e) => {
+            warn!("Image proxy failed to fetch {}: {}", url, e);
+            let message = match e {
+                ImageFetchError::NotAnImage(_) => "Upstream did not return an image",
+                ImageFetchError::TooLarge => "Image is too large",
+                _ => "Failed to fetch image",
+            };
+            return HttpResponse::BadGateway()
+                .json(ApiError::new(ErrorCode::UpstreamUnavailable, message));
+        }
+    };
+
+    if use_cache {
+        if let Err(e) = data
+            .storage
+            .put(&cache_key, body.clone(), &content_type)
+            .await
+        {
+            warn!("Failed to cache image {}: {}", url, e);
+        }
+    }
+
+    image_response(&query, &content_type, body)
+}
+
+/// Why an image couldn't be fetched from upstream
+#[derive(Debug, Error)]
+pub enum ImageFetchError {
+    #[error("Failed to build image client: {0}")]
+    Client(reqwest::Error),
+
+    #[error("Upstream returned {0}")]
+    Status(reqwest::StatusCode),
+
+    #[error("Request failed: {0}")]
+    Request(reqwest::Error),
+
+    #[error("Upstream did not return an image (content type {0:?})")]
+    NotAnImage(String),
+
+    #[error("Image is larger than {} bytes", MAX_IMAGE_BYTES)]
+    TooLarge,
+}
+
+/// Fetch an image from upstream, refusing non-images and oversized ones
+///
+/// Redirects are only followed to URLs that pass `is_allowed_image_url`, so
+/// an allowed host can't bounce the proxy to an internal address. A refused
+/// redirect surfaces as `ImageFetchError::Status`.
+///
+/// # Arguments
+/// * `url` - The image URL
+/// * `allowed_hosts` - Hosts a redirect may lead to
+///
+/// # Returns
+/// * `Ok((content_type, bytes))` - The image
+/// * `Err(ImageFetchError)` - Nothing usable was fetched
+pub async fn fetch_upstream_image(
+    url: &str,
+    allowed_hosts: &[String],
+) -> Result<(String, Vec<u8>), ImageFetchError> {
+    let hosts = allowed_hosts.to_vec();
+    let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_IMAGE_REDIRECTS
+            || !is_allowed_image_url(attempt.url().as_str(), &hosts)
+        {
+            attempt.stop()
+        } else {
+            attempt.follow()
+        }
+    });
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(UPSTREAM_TIMEOUT_SECS))
+        .redirect(redirects)
+        .build()
+        .map_err(ImageFetchError::Client)?;
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(ImageFetchError::Request)?;
+    if !response.status().is_success() {
+        return Err(ImageFetchError::Status(response.status()));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    if !content_type.starts_with("image/") {
+        return Err(ImageFetchError::NotAnImage(content_type));
+    }
+
+    match read_body_capped(response, MAX_IMAGE_BYTES).await {
+        Ok(Some(body)) => Ok((content_type, body)),
+        Ok(None) => Err(ImageFetchError::TooLarge),
+        Err(e) => Err(ImageFetchError::Request(e)),
+    }
+}
+
+/// Read a response body, giving up once it exceeds `limit` bytes
+///
+/// The declared Content-Length is checked first, then the body is read in
+/// chunks so an undeclared oversized body is never buffered whole.
+///
+/// # Returns
+/// * `Ok(Some(bytes))` - The whole body
+/// * `Ok(None)` - The body is larger than `limit`
+/// * `Err(reqwest::Error)` - Reading the body failed
+pub(crate) async fn read_body_capped(
+    mut response: reqwest::Response,
+    limit: usize,
+) -> Result<Option<Vec<u8>>, reqwest::Error> {
+    let declared = response.content_length().unwrap_or(0);
+    if declared > limit as u64 {
+        return Ok(None);
+    }
+
+    let mut body = Vec::with_capacity(declared as usize);
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Some(body))
+}
+
+/// Serve an uploaded avatar from object storage
+async fn stored_image_response(
+    data: &AppState,
+    query: &HashMap<String, String>,
+    key: &str,
+) -> HttpResponse {
+    if !is_stored_avatar(key) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Image key is not allowed",
+        ));
+    }
+
+    match data.storage.get(key).await {
+        Ok(Some(object)) => match stored_content_type(&object) {
+            Some(content_type) => image_response(query, &content_type, object.bytes),
+            None => {
+                error!("Stored image {} is not an image", key);
+                HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::InternalError,
+                    "Failed to read image",
+                ))
+            }
+        },
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Image not found"))
+        }
+        Err(e) => {
+            error!("Failed to read stored image {}: {}", key, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to read image",
+            ))
+        }
+    }
+}
+
+/// Configure image proxy routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_image_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/images")
+            .route("/sign", web::get().to(sign_image_handler))
+            .route("/proxy", web::get().to(proxy_image_handler)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_image_url() {
+        let hosts = vec!["x3.sokuja.uk".to_string(), "wp.com".to_string()];
+        assert!(is_allowed_image_url(
+            "https://x3.sokuja.uk/wp-content/a.jpg",
+            &hosts
+        ));
+        assert!(is_allowed_image_url("https://i0.wp.com/a.jpg", &hosts));
+        assert!(!is_allowed_image_url("https://evilwp.com/a.jpg", &hosts));
+        assert!(!is_allowed_image_url("file:///etc/passwd", &hosts));
+        assert!(!is_allowed_image_url("http://127.0.0.1/a.jpg", &hosts));
+        assert!(!is_allowed_image_url("not a url", &hosts));
+    }
+
+    #[test]
+    fn test_sniff_image_type() {
+        assert_eq!(
+            sniff_image_type(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_image_type(b"\x89PNG\r\n\x1a\n"), Some("image/png"));
+        assert_eq!(
+            sniff_image_type(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(sniff_image_type(b"<html>"), None);
+        assert_eq!(sniff_image_type(&[]), None);
+    }
+
+    #[test]
+    fn test_process_avatar() {
+        let source = image::RgbImage::from_pixel(600, 300, image::Rgb([200, 30, 30]));
+        let mut jpeg = Cursor::new(Vec::new());
+        source.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+
+        let png = process_avatar(jpeg.get_ref()).unwrap();
+        assert_eq!(sniff_image_type(&png), Some("image/png"));
+        let avatar = image::load_from_memory(&png).unwrap();
+        assert_eq!(
+            (avatar.width(), avatar.height()),
+            (AVATAR_SIZE, AVATAR_SIZE)
+        );
+
+        assert!(process_avatar(b"\x89PNG\r\n\x1a\n truncated").is_err());
+        assert!(process_avatar(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_is_stored_avatar() {
+        assert!(is_stored_avatar(&keys::avatar(7, b"png")));
+        assert!(!is_stored_avatar(
+            "https://lh3.googleusercontent.com/a/photo.jpg"
+        ));
+        assert!(!is_stored_avatar("images/abc"));
+    }
+
+    /// Serve a single raw HTTP response on a local port, returning its URL
+    async fn serve_once(response: String) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = socket.read(&mut request).await;
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+
+        format!("http://{}/a.jpg", addr)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_upstream_image_refuses_redirect_off_allowlist() {
+        let hosts = vec!["127.0.0.1".to_string()];
+        let url = serve_once(
+            "HTTP/1.1 302 Found\r\nLocation: http://localhost:1/internal\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        )
+        .await;
+
+        let result = fetch_upstream_image(&url, &hosts).await;
+        assert!(matches!(
+            result,
+            Err(ImageFetchError::Status(status)) if status == reqwest::StatusCode::FOUND
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_upstream_image_caps_undeclared_body() {
+        let hosts = vec!["127.0.0.1".to_string()];
+        let len = MAX_IMAGE_BYTES + 1;
+        let url = serve_once(format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            len,
+            "a".repeat(len)
+        ))
+        .await;
+
+        let result = fetch_upstream_image(&url, &hosts).await;
+        assert!(matches!(result, Err(ImageFetchError::TooLarge)));
+    }
+}
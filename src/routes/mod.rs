@@ -2,44 +2,197 @@
 //!
 //! This module contains all HTTP route handlers for the public API endpoints.
 
+pub mod admin;
+pub mod app;
 pub mod auth;
+pub mod cast;
+pub mod collections;
+pub mod comments;
+pub mod community;
+pub mod health;
+pub mod images;
+pub mod reactions;
+pub mod sitemap;
+pub mod sources;
+pub mod subtitles;
+pub mod tags;
 pub mod user;
 
-use actix_web::{web, HttpResponse, Responder};
-use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
 use tracing::{error, info, warn};
 use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use crate::config::Config;
-use crate::constants::endpoints;
+use crate::auth::permissions::{CrawlerRun, Permission};
+use crate::auth::{Auth, JwtKeys};
+use crate::config::{Config, TranslationConfig};
+use crate::constants::endpoints::{self, ListUrl};
+use crate::constants::filters::{self, AnimeStatus, AnimeType, Order};
+use crate::crawler::backpressure::ApiLoad;
+use crate::crawler::{extract_slug_from_url, moved_slug, retry_failed, run_full_crawl};
 use crate::db::{
-    get_anime_detail, get_anime_updates, get_completed_anime, is_cache_valid,
-    save_anime_detail_with_episodes, save_anime_updates, save_completed_anime,
-    save_crawled_anime_batch, save_video_sources, update_cache_timestamp, Database,
-    DEFAULT_CACHE_TTL_MS,
+    content_hash, count_episode_comments, delete_expired_searches, get_age_ratings,
+    get_anime_age_rating, get_anime_detail, get_anime_detail_fields, get_anime_updates,
+    get_cached_search, get_catalog_count, get_changes_since, get_crawl_report, get_episode_likes,
+    get_episode_timeline, get_job, get_popular_anime, get_synopsis_translation,
+    get_user_preferences, is_cache_valid, list_completed_anime, list_crawled_anime, merge_anime,
+    normalize_search_query, record_anime_redirect, record_anime_view, record_search,
+    resolve_anime_alias, resolve_change_cursor, save_anime_detail_with_episodes,
+    save_anime_updates, save_completed_anime, save_popular_anime, save_search_results,
+    save_subtitle_tracks, save_synopsis_translation, save_video_sources, update_cache_timestamp,
+    Database, RepositoryError, RepositoryResult, DEFAULT_CACHE_TTL_MS,
 };
 use crate::email::EmailService;
+use crate::features::FeatureFlags;
+use crate::jobs::{self, image_prefetch::RecentPrefetches};
+use crate::middleware::features::disabled_error;
+use crate::middleware::Slug;
 use crate::models::{
-    AnimeListFilters, AnimeListResponse, ApiError, ApiResponse, AuthData, AuthResponse,
-    CrawledAnime, CrawledAnimeRecord, CrawlerData, CrawlerResponse, ForgotPasswordRequest,
-    GoogleAuthRequest, LoginRequest, RegisterRequest, ResendVerificationRequest,
-    ResetPasswordRequest, User, UserFavorite, UserHistory, UserSubscription, VerifyEmailRequest,
+    apply_preferred_quality, AgeRatingOverrideRequest, AnimeAgeRating, AnimeDiff, AnimeListFilters,
+    AnimeListResponse, AnimeMergeResult, AnimeTag, AnimeTimeline, ApiError, ApiResponse, AuthData,
+    AuthResponse, CastImage, CastMediaMetadata, CastMetadata, CastTrack, CatalogOrder, CatalogPage,
+    ChangeCount, ChangeEntry, ChangeKind, ChangesData, Collection, CollectionDetail,
+    CollectionItem, CommentPage, CommunityTop, CommunityTopEntry, ConfigReload,
+    ConfirmReactivationRequest, ContentReport, ContinueWatching, CrawlError, CrawlErrorGroup,
+    CrawlFailure, CrawlFailureKind, CrawlPageTiming, CrawlReport, CrawlRequestKind,
+    CrawlRequestTiming, CrawlRetryResult, CrawledAnime, CrawledAnimeRecord, CrawlerBandwidthDay,
+    CrawlerBandwidthReport, CrawlerData, CrawlerResponse, CreateRoleRequest, CreateTagRequest,
+    CreateTenantRequest, DataErasure, DataExport, DataSource, DbPoolReport, DbPoolStats,
+    DetailFields, DeviceRegistration, EmailDelivery, EpisodeComment, EpisodeDiff, EpisodeGap,
+    EpisodeGapReport, EpisodeLikes, ErrorCode, Feature, FeatureFlag, FeatureFlagRequest, FieldDiff,
+    ForgotPasswordRequest, GoogleAuthRequest, IntegrityReport, JobQueueStats, JobRecord,
+    JobsOverview, Jwk, JwkSet, LeaderboardWindow, LoginRequest, MaintenanceAction,
+    MaintenanceResult, MergeAnimeRequest, ModerationDecision, ModerationItem, ModerationItemDetail,
+    ModerationResolution, ModerationStanding, ModerationStatus, OrphanGroup, PasswordFeedback,
+    ReactivateAccountRequest, RegisterDeviceRequest, RegisterRequest, ResendVerificationRequest,
+    ResetPasswordRequest, ResponseMeta, Role, SavedSearch, SearchAnalytics, SearchQueryStats,
+    Session, SignedUrl, SourceCheckRequest, SourceStatus, TableRowCount, Tag, TagAnimeRequest,
+    Tenant, TimelineEpisode, UpdatePreferencesRequest, UpdateRoleRequest, UpdateTagRequest,
+    UpstreamAnomaly, UpstreamAnomalyCounts, UpstreamAnomalyKind, UpstreamAnomalyReport, User,
+    UserFavorite, UserHistory, UserPreferences, UserRoles, UserStrike, UserSubscription,
+    VerifyEmailRequest, VideoServerRule, VideoServerRuleOrigin, VideoServerRuleRequest,
+    WatchProgress, WeakPasswordResponse,
 };
+use crate::moderation::ModerationHooks;
+use crate::nfo;
+use crate::parser::golden::{FieldMismatch, GoldenReport, GoldenResult, GoldenStatus, PageKind};
 use crate::parser::{
     parse_anime_detail, parse_anime_list, parse_anime_updates, parse_completed_anime,
-    parse_episode_detail, parse_search_results, AnimeDetail, AnimeListItem, AnimeUpdate,
-    CompletedAnime, Episode, EpisodeDetail, SearchResult, VideoSource,
+    parse_episode_detail, parse_popular, parse_search_results, resolve_release_times, AgeRating,
+    AnimeDetail, AnimeListItem, AnimeUpdate, CompletedAnime, EmbedInfo, Episode, EpisodeDetail,
+    PopularEntry, PopularWindow, SearchResult, SubtitleTrack, Thumbnails, VideoSource,
 };
-use crate::scraper::Scraper;
-
+use crate::scraper::{AnomalyLog, ScrapeClient, ScraperError};
+use crate::storage::Storage;
+use crate::tenants::{CurrentTenant, TenantRegistry};
+use crate::translation;
+use crate::video_servers::VideoServerRules;
+use images::PrefetchImages;
+
+pub use admin::configure_admin_routes;
+pub use app::{build_app, init, spawn_background_tasks, InitError};
 pub use auth::configure_auth_routes;
+pub use cast::configure_cast_routes;
+pub use collections::configure_collection_routes;
+pub use comments::configure_comment_routes;
+pub use community::configure_community_routes;
+pub use health::configure_health_routes;
+pub use images::configure_image_routes;
+pub use reactions::configure_reaction_routes;
+pub use sitemap::configure_sitemap_routes;
+pub use sources::configure_source_routes;
+pub use subtitles::configure_subtitle_routes;
+pub use tags::configure_tag_routes;
 pub use user::configure_user_routes;
 
 /// Application state shared across handlers
 pub struct AppState {
     pub db: Database,
-    pub config: Config,
+    /// Configuration, replaced as a whole by a reload (see
+    /// [`crate::reload`]); load it once to read several settings
+    pub config: ArcSwap<Config>,
     pub email_service: Option<EmailService>,
+    pub tenants: TenantRegistry,
+    pub storage: Storage,
+    /// Client for fetching pages from the source site
+    pub scraper: Arc<dyn ScrapeClient>,
+    /// Hooks notified of moderation decisions
+    pub moderation: ModerationHooks,
+    /// Video server blacklist and priorities applied to episode sources
+    pub video_servers: VideoServerRules,
+    /// Anomalous responses seen by the scraper
+    pub anomalies: AnomalyLog,
+    /// Keys auth tokens are signed and verified with
+    pub jwt_keys: JwtKeys,
+    /// Thumbnails recently queued for prefetching into the image cache
+    pub image_prefetches: RecentPrefetches,
+    /// API traffic crawls back off from
+    pub api_load: ApiLoad,
+    /// Features operators switched off at runtime
+    pub features: FeatureFlags,
+    /// Client for fetching subtitle files and checking video sources; each
+    /// request sets its own timeout
+    pub media_client: reqwest::Client,
+}
+
+/// ETag of a response body, quoted as the header requires
+pub fn etag_for<T: Serialize>(data: &T) -> String {
+    format!("\"{}\"", content_hash(data))
+}
+
+/// Whether an If-None-Match header value matches `etag`
+///
+/// Weak comparison, as required for GET: a `W/` prefix is ignored.
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+/// JSON response with an ETag, or 304 Not Modified if the client has it
+fn json_with_etag<T: Serialize>(req: &HttpRequest, data: T, meta: ResponseMeta) -> HttpResponse {
+    response_with_etag(req, ApiResponse::new(data).with_meta(meta))
+}
+
+/// Like [`json_with_etag`] for an already wrapped response; the ETag only
+/// covers the data
+fn response_with_etag<T: Serialize>(req: &HttpRequest, response: ApiResponse<T>) -> HttpResponse {
+    let etag = etag_for(&response.data);
+    let not_modified = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag));
+
+    if not_modified {
+        HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .finish()
+    } else {
+        HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(response)
+    }
+}
+
+/// Error response for a failed scrape: 504 when the upstream budget ran
+/// out, 500 otherwise
+fn scrape_error_response(e: &ScraperError) -> HttpResponse {
+    let status = match e {
+        ScraperError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    HttpResponse::build(status).json(ApiError::new(
+        ErrorCode::from(e),
+        format!("Failed to fetch data: {}", e),
+    ))
 }
 
 /// Cache keys for different data types
@@ -50,64 +203,152 @@ mod cache_keys {
     pub fn anime_detail(slug: &str) -> String {
         format!("anime:{}", slug)
     }
+
+    pub fn popular(window: super::PopularWindow) -> String {
+        format!("popular:{}", window.as_str())
+    }
 }
 
 /// GET /api/updates - Get latest anime updates
 ///
 /// Returns cached data if fresh (< 1 hour old), otherwise scrapes fresh data.
+/// If the scrape exceeds UPSTREAM_TIMEOUT_UPDATES_MS, the stored updates are
+/// returned with `meta.source` "stale". Updates are sorted by `releasedAt`,
+/// resolved from the relative release info when the page was fetched. Each
+/// update carries the episode's `likeCount`, plus `likedByMe` when the
+/// request is authenticated. Anime rated mature or adult are left out unless
+/// included; anime that were never crawled are unrated and kept.
 #[utoipa::path(
     get,
     path = "/api/updates",
     tag = "anime",
+    params(AdultQuery, images::PrefetchImagesQuery),
     responses(
         (status = 200, description = "Latest anime updates retrieved successfully", body = Vec<AnimeUpdate>),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 504, description = "Source site timed out and nothing is stored", body = ApiError)
     )
 )]
-pub async fn get_updates(data: web::Data<AppState>) -> impl Responder {
+pub async fn get_updates(
+    data: web::Data<AppState>,
+    auth: Option<Auth>,
+    tenant: CurrentTenant,
+    query: web::Query<AdultQuery>,
+    prefetch: PrefetchImages,
+) -> impl Responder {
     let pool = data.db.pool();
+    let viewer = FeedViewer {
+        tenant_id: tenant.id,
+        user_id: auth.as_ref().map(|auth| auth.user_id),
+        include_adult: include_adult(&data, auth.as_ref(), query.include_adult).await,
+    };
 
     match is_cache_valid(pool, cache_keys::UPDATES, DEFAULT_CACHE_TTL_MS).await {
         Ok(true) => {
             info!("Returning cached anime updates");
-            match get_anime_updates(pool).await {
+            match data.db.read(get_anime_updates).await {
                 Ok(updates) if !updates.is_empty() => {
-                    HttpResponse::Ok().json(ApiResponse::new(updates))
+                    let updates = match viewable_updates(&data, &viewer, updates).await {
+                        Ok(updates) => updates,
+                        Err(response) => return response,
+                    };
+                    prefetch.thumbnails(&data, updates.iter().map(|u| u.thumbnail.as_str()));
+                    HttpResponse::Ok().json(ApiResponse::cached(updates))
                 }
                 Ok(_) => {
                     info!("Cache valid but database empty, scraping fresh data");
-                    scrape_and_return_updates(&data).await
+                    scrape_and_return_updates(&data, &viewer, prefetch).await
                 }
                 Err(e) => {
                     error!("Failed to get cached anime updates: {}", e);
-                    HttpResponse::InternalServerError()
-                        .json(ApiError::new(format!("Database error: {}", e)))
+                    HttpResponse::InternalServerError().json(ApiError::new(
+                        ErrorCode::DatabaseError,
+                        format!("Database error: {}", e),
+                    ))
                 }
             }
         }
         Ok(false) => {
             info!("Cache stale, scraping fresh anime updates");
-            scrape_and_return_updates(&data).await
+            scrape_and_return_updates(&data, &viewer, prefetch).await
         }
         Err(e) => {
             error!("Failed to check cache validity: {}", e);
-            scrape_and_return_updates(&data).await
+            scrape_and_return_updates(&data, &viewer, prefetch).await
+        }
+    }
+}
+
+/// Who is reading the updates feed, for filling in likes
+struct FeedViewer {
+    tenant_id: i32,
+    user_id: Option<i32>,
+    include_adult: bool,
+}
+
+/// Updates the viewer may see, with their likes
+async fn viewable_updates(
+    data: &AppState,
+    viewer: &FeedViewer,
+    updates: Vec<AnimeUpdate>,
+) -> Result<Vec<AnimeUpdate>, HttpResponse> {
+    let updates = without_adult(data, viewer.include_adult, updates, |u| &u.slug, |_| &[]).await?;
+    Ok(with_likes(data.db.pool(), viewer, updates).await)
+}
+
+/// Fill in `likeCount`, and `likedByMe` for authenticated viewers
+///
+/// Likes are looked up by the episode slug of each update's URL. If the
+/// lookup fails, the updates are returned without them.
+async fn with_likes(
+    pool: &sqlx::PgPool,
+    viewer: &FeedViewer,
+    mut updates: Vec<AnimeUpdate>,
+) -> Vec<AnimeUpdate> {
+    let slugs: Vec<String> = updates
+        .iter()
+        .map(|update| extract_slug_from_url(&update.episode_url))
+        .collect();
+
+    match get_episode_likes(pool, viewer.tenant_id, viewer.user_id, &slugs).await {
+        Ok(likes) => {
+            for (update, likes) in updates.iter_mut().zip(likes) {
+                update.like_count = Some(likes.like_count);
+                update.liked_by_me = viewer.user_id.map(|_| likes.liked_by_me);
+            }
         }
+        Err(e) => warn!("Failed to get likes for anime updates: {}", e),
     }
+    updates
 }
 
 /// Helper function to scrape and return anime updates
-async fn scrape_and_return_updates(data: &web::Data<AppState>) -> HttpResponse {
+async fn scrape_and_return_updates(
+    data: &web::Data<AppState>,
+    viewer: &FeedViewer,
+    prefetch: PrefetchImages,
+) -> HttpResponse {
     let pool = data.db.pool();
-    let scraper = Scraper::new();
-    let url = endpoints::home(&data.config.base_url);
+    let scraper = &data.scraper;
+    let url = endpoints::home(&data.config.load().base_url);
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.updates_ms);
     info!("Fetching URL: {}", url);
 
-    match scraper.fetch_page(&url).await {
+    let started = Instant::now();
+    match scraper.fetch_page_within(&url, budget).await {
         Ok(result) => {
-            info!("Fetched {} bytes of HTML", result.html.len());
-
-            let updates = parse_anime_updates(&result.html);
+            let elapsed = started.elapsed();
+            info!(
+                "Fetched {} bytes of HTML in {}ms",
+                result.html.len(),
+                elapsed.as_millis()
+            );
+
+            let mut updates = parse_anime_updates(&result.html);
+            resolve_release_times(&mut updates, chrono::Utc::now());
+            // Same order as stored updates: newest release first, unresolved
+            // ones last (None sorts before Some)
+            updates.sort_by(|a, b| b.released_at.cmp(&a.released_at));
             info!("Parsed {} anime updates", updates.len());
 
             if let Err(e) = save_anime_updates(pool, &updates).await {
@@ -118,70 +359,166 @@ async fn scrape_and_return_updates(data: &web::Data<AppState>) -> HttpResponse {
                 error!("Failed to update cache timestamp: {}", e);
             }
 
-            HttpResponse::Ok().json(ApiResponse::new(updates))
+            let updates = match viewable_updates(data, viewer, updates).await {
+                Ok(updates) => updates,
+                Err(response) => return response,
+            };
+            prefetch.thumbnails(data, updates.iter().map(|u| u.thumbnail.as_str()));
+            HttpResponse::Ok().json(ApiResponse::live(updates, elapsed, result.status))
+        }
+        Err(e @ ScraperError::Timeout(_)) => {
+            warn!("Anime updates: {}, serving stale stored data", e);
+            match get_anime_updates(pool).await {
+                Ok(updates) if !updates.is_empty() => {
+                    let updates = match viewable_updates(data, viewer, updates).await {
+                        Ok(updates) => updates,
+                        Err(response) => return response,
+                    };
+                    prefetch.thumbnails(data, updates.iter().map(|u| u.thumbnail.as_str()));
+                    HttpResponse::Ok().json(ApiResponse::timed_out(updates, started.elapsed()))
+                }
+                _ => scrape_error_response(&e),
+            }
         }
         Err(e) => {
             error!("Failed to scrape anime updates: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
         }
     }
 }
 
+/// Maximum number of completed anime per page
+const MAX_COMPLETED_PER_PAGE: i64 = 100;
+
+/// Query parameters for the completed anime list
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct CompletedQuery {
+    /// Page number, starting at 1 (default: 1)
+    pub page: Option<i64>,
+    /// Anime per page (default: 24, max: 100)
+    pub per_page: Option<i64>,
+    /// Whether to include anime rated mature or adult (default: the user's
+    /// `showAdultContent` preference, false when signed out)
+    #[serde(rename = "includeAdult")]
+    pub include_adult: Option<bool>,
+}
+
+impl CompletedQuery {
+    /// Limit and offset of the requested page
+    fn limit_offset(&self) -> (i64, i64) {
+        let page = self.page.unwrap_or(1).max(1);
+        let per_page = self
+            .per_page
+            .unwrap_or(DEFAULT_CATALOG_PER_PAGE)
+            .clamp(1, MAX_COMPLETED_PER_PAGE);
+        (per_page, (page - 1).saturating_mul(per_page))
+    }
+}
+
 /// GET /api/completed - Get completed anime list
 ///
-/// Returns cached data if fresh (< 1 hour old), otherwise scrapes fresh data.
+/// Lists the stored completed anime, newest first: the whole archive once
+/// the archive crawl (COMPLETED_ARCHIVE_INTERVAL_SECS) has run, paged with
+/// `page` and `per_page` (24 per page by default). If the list is stale (> 1 hour old), the latest
+/// ones are first scraped from the home page. If that scrape exceeds
+/// UPSTREAM_TIMEOUT_COMPLETED_MS, the stored list is returned with
+/// `meta.source` "stale". Anime rated mature or adult are left out of each
+/// page unless included, so pages may come out short. Entries never crawled
+/// are rated by the genres listed with them.
 #[utoipa::path(
     get,
     path = "/api/completed",
     tag = "anime",
+    params(CompletedQuery, images::PrefetchImagesQuery),
     responses(
         (status = 200, description = "Completed anime list retrieved successfully", body = Vec<CompletedAnime>),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 504, description = "Source site timed out and nothing is stored", body = ApiError)
     )
 )]
-pub async fn get_completed(data: web::Data<AppState>) -> impl Responder {
+pub async fn get_completed(
+    data: web::Data<AppState>,
+    auth: Option<Auth>,
+    query: web::Query<CompletedQuery>,
+    prefetch: PrefetchImages,
+) -> impl Responder {
     let pool = data.db.pool();
+    let page = query.limit_offset();
+    let include_adult = include_adult(&data, auth.as_ref(), query.include_adult).await;
 
     match is_cache_valid(pool, cache_keys::COMPLETED, DEFAULT_CACHE_TTL_MS).await {
         Ok(true) => {
             info!("Returning cached completed anime");
-            match get_completed_anime(pool).await {
-                Ok(completed) if !completed.is_empty() => {
-                    HttpResponse::Ok().json(ApiResponse::new(completed))
+            match data.db.read(|pool| stored_completed(pool, page)).await {
+                Ok(completed) if !completed.is_empty() || page.1 > 0 => {
+                    let completed = match without_adult(
+                        &data,
+                        include_adult,
+                        completed,
+                        |c| &c.slug,
+                        |c| &c.genres,
+                    )
+                    .await
+                    {
+                        Ok(completed) => completed,
+                        Err(response) => return response,
+                    };
+                    prefetch.thumbnails(&data, completed.iter().map(|c| c.thumbnail.as_str()));
+                    HttpResponse::Ok().json(ApiResponse::cached(completed))
                 }
                 Ok(_) => {
                     info!("Cache valid but database empty, scraping fresh data");
-                    scrape_and_return_completed(&data).await
+                    scrape_and_return_completed(&data, page, include_adult, prefetch).await
                 }
                 Err(e) => {
                     error!("Failed to get cached completed anime: {}", e);
-                    HttpResponse::InternalServerError()
-                        .json(ApiError::new(format!("Database error: {}", e)))
+                    HttpResponse::InternalServerError().json(ApiError::new(
+                        ErrorCode::DatabaseError,
+                        format!("Database error: {}", e),
+                    ))
                 }
             }
         }
         Ok(false) => {
             info!("Cache stale, scraping fresh completed anime");
-            scrape_and_return_completed(&data).await
+            scrape_and_return_completed(&data, page, include_adult, prefetch).await
         }
         Err(e) => {
             error!("Failed to check cache validity: {}", e);
-            scrape_and_return_completed(&data).await
+            scrape_and_return_completed(&data, page, include_adult, prefetch).await
         }
     }
 }
 
+/// Stored completed anime, a `(limit, offset)` page
+async fn stored_completed(
+    pool: &sqlx::PgPool,
+    (limit, offset): (i64, i64),
+) -> RepositoryResult<Vec<CompletedAnime>> {
+    list_completed_anime(pool, Some(limit), offset).await
+}
+
 /// Helper function to scrape and return completed anime
-async fn scrape_and_return_completed(data: &web::Data<AppState>) -> HttpResponse {
+///
+/// The scraped home page entries are saved, then the requested stored
+/// entries returned, so the archive stays listed behind them.
+async fn scrape_and_return_completed(
+    data: &web::Data<AppState>,
+    page: (i64, i64),
+    include_adult: bool,
+    prefetch: PrefetchImages,
+) -> HttpResponse {
     let pool = data.db.pool();
-    let scraper = Scraper::new();
+    let scraper = &data.scraper;
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.completed_ms);
 
+    let started = Instant::now();
     match scraper
-        .fetch_page(&endpoints::home(&data.config.base_url))
+        .fetch_page_within(&endpoints::home(&data.config.load().base_url), budget)
         .await
     {
         Ok(result) => {
+            let elapsed = started.elapsed();
             let completed = parse_completed_anime(&result.html);
             info!("Parsed {} completed anime", completed.len());
 
@@ -193,12 +530,220 @@ async fn scrape_and_return_completed(data: &web::Data<AppState>) -> HttpResponse
                 error!("Failed to update cache timestamp: {}", e);
             }
 
-            HttpResponse::Ok().json(ApiResponse::new(completed))
+            let completed = match stored_completed(pool, page).await {
+                Ok(stored) if !stored.is_empty() || completed.is_empty() => stored,
+                Ok(_) => completed,
+                Err(e) => {
+                    error!("Failed to get stored completed anime: {}", e);
+                    completed
+                }
+            };
+            let completed =
+                match without_adult(data, include_adult, completed, |c| &c.slug, |c| &c.genres)
+                    .await
+                {
+                    Ok(completed) => completed,
+                    Err(response) => return response,
+                };
+            prefetch.thumbnails(data, completed.iter().map(|c| c.thumbnail.as_str()));
+            HttpResponse::Ok().json(ApiResponse::live(completed, elapsed, result.status))
+        }
+        Err(e @ ScraperError::Timeout(_)) => {
+            warn!("Completed anime: {}, serving stale stored data", e);
+            match stored_completed(pool, page).await {
+                Ok(completed) if !completed.is_empty() => {
+                    let completed = match without_adult(
+                        data,
+                        include_adult,
+                        completed,
+                        |c| &c.slug,
+                        |c| &c.genres,
+                    )
+                    .await
+                    {
+                        Ok(completed) => completed,
+                        Err(response) => return response,
+                    };
+                    prefetch.thumbnails(data, completed.iter().map(|c| c.thumbnail.as_str()));
+                    HttpResponse::Ok().json(ApiResponse::timed_out(completed, started.elapsed()))
+                }
+                _ => scrape_error_response(&e),
+            }
         }
         Err(e) => {
             error!("Failed to scrape completed anime: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
+        }
+    }
+}
+
+/// Query parameters for the popular anime ranking
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct PopularQuery {
+    /// Ranking window: today (default), week, or all
+    pub window: Option<String>,
+    /// Whether to include anime rated mature or adult (default: the user's
+    /// `showAdultContent` preference, false when signed out)
+    #[serde(rename = "includeAdult")]
+    pub include_adult: Option<bool>,
+}
+
+/// GET /api/popular - Get the popular anime ranking
+///
+/// Lists the entries of the home page's popular widget for `window`, by
+/// rank. If the stored ranking is stale (> 1 hour old), the home page is
+/// scraped and all three widgets saved. If that scrape exceeds
+/// UPSTREAM_TIMEOUT_POPULAR_MS, the stored ranking is returned with
+/// `meta.source` "stale". Anime rated mature or adult are left out unless
+/// included; entries never crawled are rated by the genres listed with them.
+#[utoipa::path(
+    get,
+    path = "/api/popular",
+    tag = "anime",
+    params(PopularQuery, images::PrefetchImagesQuery),
+    responses(
+        (status = 200, description = "Popular anime retrieved successfully", body = Vec<PopularEntry>),
+        (status = 400, description = "Invalid window", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 504, description = "Source site timed out and nothing is stored", body = ApiError)
+    )
+)]
+pub async fn get_popular(
+    data: web::Data<AppState>,
+    auth: Option<Auth>,
+    query: web::Query<PopularQuery>,
+    prefetch: PrefetchImages,
+) -> impl Responder {
+    let window = match parse_list_filter(
+        "window",
+        query.window.as_deref(),
+        PopularWindow::parse,
+        &PopularWindow::ALL.map(PopularWindow::as_str),
+    ) {
+        Ok(window) => window.unwrap_or(PopularWindow::Today),
+        Err(message) => {
+            return HttpResponse::BadRequest()
+                .json(ApiError::new(ErrorCode::ValidationFailed, message));
+        }
+    };
+    let pool = data.db.pool();
+    let include_adult = include_adult(&data, auth.as_ref(), query.include_adult).await;
+
+    match is_cache_valid(pool, &cache_keys::popular(window), DEFAULT_CACHE_TTL_MS).await {
+        Ok(true) => match data.db.read(|pool| get_popular_anime(pool, window)).await {
+            Ok(popular) if !popular.is_empty() => {
+                info!("Returning cached popular anime ({})", window.as_str());
+                let popular =
+                    match without_adult(&data, include_adult, popular, |p| &p.slug, |p| &p.genres)
+                        .await
+                    {
+                        Ok(popular) => popular,
+                        Err(response) => return response,
+                    };
+                prefetch.thumbnails(&data, popular.iter().map(|p| p.thumbnail.as_str()));
+                HttpResponse::Ok().json(ApiResponse::cached(popular))
+            }
+            Ok(_) => {
+                info!("Cache valid but database empty, scraping fresh data");
+                scrape_and_return_popular(&data, window, include_adult, prefetch).await
+            }
+            Err(e) => {
+                error!("Failed to get cached popular anime: {}", e);
+                HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::DatabaseError,
+                    format!("Database error: {}", e),
+                ))
+            }
+        },
+        Ok(false) => {
+            info!("Cache stale, scraping fresh popular anime");
+            scrape_and_return_popular(&data, window, include_adult, prefetch).await
+        }
+        Err(e) => {
+            error!("Failed to check cache validity: {}", e);
+            scrape_and_return_popular(&data, window, include_adult, prefetch).await
+        }
+    }
+}
+
+/// Helper function to scrape and return a popular anime ranking
+///
+/// Every widget found on the home page is saved, not just the requested
+/// one; a widget missing from the page keeps its stored ranking.
+async fn scrape_and_return_popular(
+    data: &web::Data<AppState>,
+    window: PopularWindow,
+    include_adult: bool,
+    prefetch: PrefetchImages,
+) -> HttpResponse {
+    let pool = data.db.pool();
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.popular_ms);
+
+    let started = Instant::now();
+    match data
+        .scraper
+        .fetch_page_within(&endpoints::home(&data.config.load().base_url), budget)
+        .await
+    {
+        Ok(result) => {
+            let elapsed = started.elapsed();
+            let mut requested = Vec::new();
+            for scraped in PopularWindow::ALL {
+                let popular = parse_popular(&result.html, scraped);
+                info!(
+                    "Parsed {} popular anime ({})",
+                    popular.len(),
+                    scraped.as_str()
+                );
+                if !popular.is_empty() {
+                    if let Err(e) = save_popular_anime(pool, scraped, &popular).await {
+                        error!("Failed to save popular anime: {}", e);
+                    }
+                    if let Err(e) =
+                        update_cache_timestamp(pool, &cache_keys::popular(scraped)).await
+                    {
+                        error!("Failed to update cache timestamp: {}", e);
+                    }
+                }
+                if scraped == window {
+                    requested = popular;
+                }
+            }
+            let requested =
+                match without_adult(data, include_adult, requested, |p| &p.slug, |p| &p.genres)
+                    .await
+                {
+                    Ok(requested) => requested,
+                    Err(response) => return response,
+                };
+            prefetch.thumbnails(data, requested.iter().map(|p| p.thumbnail.as_str()));
+            HttpResponse::Ok().json(ApiResponse::live(requested, elapsed, result.status))
+        }
+        Err(e @ ScraperError::Timeout(_)) => {
+            warn!("Popular anime: {}, serving stale stored data", e);
+            match get_popular_anime(pool, window).await {
+                Ok(popular) if !popular.is_empty() => {
+                    let popular = match without_adult(
+                        data,
+                        include_adult,
+                        popular,
+                        |p| &p.slug,
+                        |p| &p.genres,
+                    )
+                    .await
+                    {
+                        Ok(popular) => popular,
+                        Err(response) => return response,
+                    };
+                    prefetch.thumbnails(data, popular.iter().map(|p| p.thumbnail.as_str()));
+                    HttpResponse::Ok().json(ApiResponse::timed_out(popular, started.elapsed()))
+                }
+                _ => scrape_error_response(&e),
+            }
+        }
+        Err(e) => {
+            error!("Failed to scrape popular anime: {}", e);
+            scrape_error_response(&e)
         }
     }
 }
@@ -208,52 +753,172 @@ async fn scrape_and_return_completed(data: &web::Data<AppState>) -> HttpResponse
 pub struct SearchQuery {
     /// Search keyword
     pub q: Option<String>,
+    /// Only anime with this tag slug
+    pub tag: Option<String>,
+    /// Whether to include anime rated mature or adult (default: the user's
+    /// `showAdultContent` preference, false when signed out)
+    #[serde(rename = "includeAdult")]
+    pub include_adult: Option<bool>,
 }
 
 /// GET /api/search - Search for anime
 ///
-/// Query parameter: q (required) - search keyword
+/// Query parameters:
+/// - q (required): search keyword
+/// - tag: only anime with this tag slug
+/// - includeAdult: whether to include anime rated mature or adult
+///
+/// Results are cached briefly per normalized query (case and whitespace
+/// don't matter), so repeated autocomplete queries don't hit the source site.
+/// The tag and age rating filters apply to the cached results. While an
+/// operator has switched off the search-upstream feature, cached results are
+/// still served and only a cache miss gets 503.
 #[utoipa::path(
     get,
     path = "/api/search",
     tag = "anime",
-    params(SearchQuery),
+    params(SearchQuery, images::PrefetchImagesQuery),
     responses(
         (status = 200, description = "Search results retrieved successfully", body = Vec<SearchResult>),
         (status = 400, description = "Bad request - search query is required", body = ApiError),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Not cached, and upstream search disabled by an operator", body = ApiError),
+        (status = 504, description = "Source site timed out", body = ApiError)
     )
 )]
 pub async fn search_anime(
     data: web::Data<AppState>,
+    auth: Option<Auth>,
     query: web::Query<SearchQuery>,
+    prefetch: PrefetchImages,
 ) -> impl Responder {
     let keyword = match &query.q {
         Some(q) if !q.trim().is_empty() => q.trim(),
         _ => {
-            return HttpResponse::BadRequest().json(ApiError::new("Search query is required"));
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Search query is required",
+            ));
         }
     };
 
-    info!("Searching for anime: {}", keyword);
-    let scraper = Scraper::new();
-
-    match scraper
-        .fetch_page(&endpoints::search(&data.config.base_url, keyword))
-        .await
-    {
-        Ok(result) => {
-            let results = parse_search_results(&result.html);
-            HttpResponse::Ok().json(ApiResponse::new(results))
+    let tag = tags::tag_filter(query.tag.as_deref());
+    let include_adult = include_adult(&data, auth.as_ref(), query.include_adult).await;
+
+    match search_with_cache(&data, keyword).await {
+        Ok((mut results, meta)) => {
+            let filtered = filter_scraped(
+                &data,
+                tag.as_deref(),
+                include_adult,
+                &mut results,
+                |r| &r.slug,
+                |_| &[],
+            );
+            if let Err(e) = filtered.await {
+                error!("Failed to filter search results: {}", e);
+                return list_filter_error(&e);
+            }
+            prefetch.thumbnails(&data, results.iter().map(|r| r.thumbnail.as_str()));
+            HttpResponse::Ok().json(ApiResponse::new(results).with_meta(meta))
         }
-        Err(e) => {
+        Err(SearchError::Disabled(flag)) => {
+            HttpResponse::ServiceUnavailable().json(disabled_error(&flag))
+        }
+        Err(SearchError::Scraper(e)) => {
             error!("Failed to search anime: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
         }
     }
 }
 
+/// Why a search couldn't be answered
+#[derive(Debug)]
+pub(crate) enum SearchError {
+    /// Not cached, and an operator switched off searching the source site
+    Disabled(FeatureFlag),
+    /// Searching the source site failed
+    Scraper(ScraperError),
+}
+
+impl From<ScraperError> for SearchError {
+    fn from(e: ScraperError) -> Self {
+        SearchError::Scraper(e)
+    }
+}
+
+/// Search the source site, answering repeated queries from the search cache
+///
+/// Queries are cached under their normalized form, and empty results are
+/// cached for SEARCH_CACHE_EMPTY_TTL_SECS. Cache failures are logged and
+/// fall through to a live search, unless the search-upstream feature is
+/// switched off. Every answered search is counted in the search analytics.
+/// The results come with how they were produced.
+pub(crate) async fn search_with_cache(
+    state: &AppState,
+    keyword: &str,
+) -> Result<(Vec<SearchResult>, ResponseMeta), SearchError> {
+    let query = normalize_search_query(keyword);
+    let (results, meta) = cached_or_live_search(state, &query).await?;
+
+    if let Err(e) = record_search(state.db.pool(), &query, results.len() as i32).await {
+        error!("Failed to record search analytics: {}", e);
+    }
+
+    Ok((results, meta))
+}
+
+async fn cached_or_live_search(
+    state: &AppState,
+    query: &str,
+) -> Result<(Vec<SearchResult>, ResponseMeta), SearchError> {
+    let pool = state.db.pool();
+    let ttl_ms = (state.config.load().search_cache_ttl_secs * 1000) as i64;
+    let empty_ttl_ms = (state.config.load().search_cache_empty_ttl_secs * 1000) as i64;
+    let cache_enabled = ttl_ms > 0;
+
+    if cache_enabled {
+        // Counts the hit, so it can't run on a read-only replica
+        match get_cached_search(pool, query, ttl_ms, empty_ttl_ms).await {
+            Ok(Some(results)) => {
+                info!("Returning cached search results for: {}", query);
+                return Ok((results, ResponseMeta::cached()));
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to read search cache: {}", e),
+        }
+    }
+
+    let flag = state.features.get(Feature::SearchUpstream);
+    if !flag.enabled {
+        return Err(SearchError::Disabled(flag));
+    }
+
+    info!("Searching for anime: {}", query);
+    let scraper = &state.scraper;
+    let budget = Duration::from_millis(state.config.load().upstream_timeouts.search_ms);
+    let started = Instant::now();
+    let result = scraper
+        .fetch_page_within(
+            &endpoints::search(&state.config.load().base_url, query),
+            budget,
+        )
+        .await?;
+    let meta = ResponseMeta::live(started.elapsed(), result.status);
+    let results = parse_search_results(&result.html);
+
+    if cache_enabled {
+        if let Err(e) = save_search_results(pool, query, &results).await {
+            error!("Failed to save search results: {}", e);
+        }
+        if let Err(e) = delete_expired_searches(pool, ttl_ms.max(empty_ttl_ms)).await {
+            error!("Failed to prune search cache: {}", e);
+        }
+    }
+
+    Ok((results, meta))
+}
+
 /// Query parameters for anime list endpoint
 #[derive(Debug, Deserialize, IntoParams, ToSchema)]
 pub struct AnimeListQuery {
@@ -266,6 +931,150 @@ pub struct AnimeListQuery {
     pub status: Option<String>,
     /// Sort order (title, titlereverse, update, latest, popular, rating)
     pub order: Option<String>,
+    /// Only anime with this tag slug
+    pub tag: Option<String>,
+    /// Whether to include anime rated mature or adult (default: the user's
+    /// `showAdultContent` preference, false when signed out)
+    #[serde(rename = "includeAdult")]
+    pub include_adult: Option<bool>,
+}
+
+/// Whether a list includes anime rated mature or adult
+///
+/// `includeAdult` decides when given; otherwise signed-in users get their
+/// `showAdultContent` preference and everyone else doesn't see them.
+/// Preference lookup failures are logged and leave them out.
+async fn include_adult(data: &AppState, auth: Option<&Auth>, requested: Option<bool>) -> bool {
+    if let Some(requested) = requested {
+        return requested;
+    }
+    let Some(auth) = auth else {
+        return false;
+    };
+    match get_user_preferences(data.db.pool(), auth.user_id).await {
+        Ok(preferences) => preferences.show_adult_content,
+        Err(e) => {
+            warn!("Failed to get preferences of user {}: {}", auth.user_id, e);
+            false
+        }
+    }
+}
+
+/// Query parameter for lists that hide anime rated mature or adult
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct AdultQuery {
+    /// Whether to include anime rated mature or adult (default: the user's
+    /// `showAdultContent` preference, false when signed out)
+    #[serde(rename = "includeAdult")]
+    pub include_adult: Option<bool>,
+}
+
+/// Apply the tag and age rating filters to anime scraped from the source site
+///
+/// An anime is rated by its override or stored genres, else by the genres
+/// scraped with it (`genres`, empty for lists that have none). Anime that
+/// were never crawled and come without genres are unrated, and kept: that
+/// is the default on purpose, as hiding them would empty search results and
+/// fresh updates until the crawler reaches them.
+pub(crate) async fn filter_scraped<T>(
+    data: &AppState,
+    tag: Option<&str>,
+    include_adult: bool,
+    items: &mut Vec<T>,
+    slug: fn(&T) -> &str,
+    genres: fn(&T) -> &[String],
+) -> RepositoryResult<()> {
+    tags::retain_tagged(&data.db, tag, items, slug).await?;
+    if !include_adult && !items.is_empty() {
+        let slugs: Vec<String> = items.iter().map(|item| slug(item).to_string()).collect();
+        let ratings = data.db.read(|pool| get_age_ratings(pool, &slugs)).await?;
+        items.retain(|item| {
+            let rating = ratings
+                .get(slug(item))
+                .copied()
+                .unwrap_or_else(|| AgeRating::from_genres(genres(item)));
+            !rating.is_restricted()
+        });
+    }
+    Ok(())
+}
+
+/// Drop the anime rated mature or adult from a list, unless it includes them
+///
+/// A failed rating lookup is answered with a 500 rather than risk showing
+/// them.
+async fn without_adult<T>(
+    data: &AppState,
+    include_adult: bool,
+    mut items: Vec<T>,
+    slug: fn(&T) -> &str,
+    genres: fn(&T) -> &[String],
+) -> Result<Vec<T>, HttpResponse> {
+    match filter_scraped(data, None, include_adult, &mut items, slug, genres).await {
+        Ok(()) => Ok(items),
+        Err(e) => {
+            error!("Failed to filter adult anime: {}", e);
+            Err(list_filter_error(&e))
+        }
+    }
+}
+
+/// 500 response for a failed list filter
+fn list_filter_error(e: &RepositoryError) -> HttpResponse {
+    HttpResponse::InternalServerError().json(ApiError::new(
+        ErrorCode::DatabaseError,
+        format!("Database error: {}", e),
+    ))
+}
+
+/// Parse one anime list filter; empty values mean "any"
+fn parse_list_filter<T>(
+    name: &str,
+    value: Option<&str>,
+    parse: fn(&str) -> Option<T>,
+    values: &[&str],
+) -> Result<Option<T>, String> {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        None => Ok(None),
+        Some(value) => parse(value)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid {}, expected one of: {}", name, values.join(", "))),
+    }
+}
+
+/// Build the source list URL for an anime list query
+///
+/// # Returns
+/// The URL builder, or a message describing the invalid filter
+fn list_url_from_query(query: &AnimeListQuery) -> Result<ListUrl, String> {
+    let mut list_url = ListUrl::new().page(query.page.unwrap_or(1));
+
+    let anime_type = query.anime_type.as_deref();
+    if let Some(anime_type) = parse_list_filter(
+        "type",
+        anime_type,
+        AnimeType::parse,
+        &filters::ANIME_TYPES[1..],
+    )? {
+        list_url = list_url.type_(anime_type);
+    }
+    let status = query.status.as_deref();
+    if let Some(status) = parse_list_filter(
+        "status",
+        status,
+        AnimeStatus::parse,
+        &filters::ANIME_STATUS[1..],
+    )? {
+        list_url = list_url.status(status);
+    }
+    let order = query.order.as_deref();
+    if let Some(order) =
+        parse_list_filter("order", order, Order::parse, &filters::ANIME_ORDER[1..])?
+    {
+        list_url = list_url.order(order);
+    }
+
+    Ok(list_url)
 }
 
 /// GET /api/anime/list - Get anime list with filters
@@ -275,36 +1084,74 @@ pub struct AnimeListQuery {
 /// - type: Anime type filter (TV, OVA, Movie, etc.)
 /// - status: Status filter (Ongoing, Completed, etc.)
 /// - order: Sort order (title, titlereverse, update, latest, popular, rating)
+/// - tag: Only anime with this tag slug
+/// - includeAdult: Whether to include anime rated mature or adult
+///
+/// Filter values are matched ignoring case; unknown values are rejected.
+/// The tag and age rating filters apply to the fetched page, so filtered
+/// pages can come back with fewer items.
 #[utoipa::path(
     get,
     path = "/api/anime/list",
     tag = "anime",
-    params(AnimeListQuery),
+    params(AnimeListQuery, images::PrefetchImagesQuery),
     responses(
         (status = 200, description = "Anime list retrieved successfully", body = AnimeListResponse),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 400, description = "Unknown filter value", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 504, description = "Source site timed out", body = ApiError)
     )
 )]
 pub async fn get_anime_list(
     data: web::Data<AppState>,
+    auth: Option<Auth>,
     query: web::Query<AnimeListQuery>,
+    prefetch: PrefetchImages,
 ) -> impl Responder {
     let page = query.page.unwrap_or(1);
+    let list_url = match list_url_from_query(&query) {
+        Ok(list_url) => list_url,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
     let anime_type = query.anime_type.as_deref().unwrap_or("");
     let status = query.status.as_deref().unwrap_or("");
     let order = query.order.as_deref().unwrap_or("");
+    let tag = tags::tag_filter(query.tag.as_deref());
+    let include_adult = include_adult(&data, auth.as_ref(), query.include_adult).await;
 
     info!(
-        "Fetching anime list: page={}, type={}, status={}, order={}",
-        page, anime_type, status, order
+        "Fetching anime list: page={}, type={}, status={}, order={}, tag={}",
+        page,
+        anime_type,
+        status,
+        order,
+        tag.as_deref().unwrap_or("")
     );
 
-    let scraper = Scraper::new();
-    let url = endpoints::anime_list(&data.config.base_url, page, anime_type, status, order);
+    let scraper = &data.scraper;
+    let url = list_url.build(&data.config.load().base_url);
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.anime_list_ms);
 
-    match scraper.fetch_page(&url).await {
+    let started = Instant::now();
+    match scraper.fetch_page_within(&url, budget).await {
         Ok(result) => {
-            let items = parse_anime_list(&result.html);
+            let elapsed = started.elapsed();
+            let mut items = parse_anime_list(&result.html);
+            let filtered = filter_scraped(
+                &data,
+                tag.as_deref(),
+                include_adult,
+                &mut items,
+                |i| &i.slug,
+                |_| &[],
+            );
+            if let Err(e) = filtered.await {
+                error!("Failed to filter anime list: {}", e);
+                return list_filter_error(&e);
+            }
+            prefetch.thumbnails(&data, items.iter().map(|i| i.thumbnail.as_str()));
 
             let response = AnimeListResponse {
                 items,
@@ -313,128 +1160,657 @@ pub async fn get_anime_list(
                     anime_type: anime_type.to_string(),
                     status: status.to_string(),
                     order: order.to_string(),
+                    tag,
+                    include_adult,
                 },
             };
 
-            HttpResponse::Ok().json(ApiResponse::new(response))
+            HttpResponse::Ok().json(ApiResponse::live(response, elapsed, result.status))
         }
         Err(e) => {
             error!("Failed to fetch anime list: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
+        }
+    }
+}
+
+/// Default number of anime per catalog page
+const DEFAULT_CATALOG_PER_PAGE: i64 = 24;
+
+/// Maximum number of anime per catalog page
+const MAX_CATALOG_PER_PAGE: i64 = 100;
+
+/// Query parameters for the stored catalog
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct CatalogQuery {
+    /// Page number (default: 1)
+    pub page: Option<i64>,
+    /// Anime per page (default: 24, max: 100)
+    pub per_page: Option<i64>,
+    /// Sort order (title, titlereverse, update, latest, popular; default: title)
+    pub order: Option<String>,
+    /// Only anime with this tag slug
+    pub tag: Option<String>,
+    /// Whether to include anime rated mature or adult (default: the user's
+    /// `showAdultContent` preference, false when signed out)
+    #[serde(rename = "includeAdult")]
+    pub include_adult: Option<bool>,
+}
+
+/// GET /api/catalog - Page through the crawled catalog
+///
+/// Unlike `/api/anime/list`, which proxies the source site, this lists the
+/// anime stored by crawls. `order=popular` sorts by popularity score: views,
+/// favorites, and subscriptions, weighted toward recent activity. `tag`
+/// limits the catalog to anime with a tag. Anime rated mature or adult are
+/// left out unless `includeAdult` (or, when it's unset, the user's
+/// `showAdultContent` preference) is true.
+#[utoipa::path(
+    get,
+    path = "/api/catalog",
+    tag = "anime",
+    params(CatalogQuery, images::PrefetchImagesQuery),
+    responses(
+        (status = 200, description = "Catalog page retrieved successfully", body = ApiResponse<CatalogPage>),
+        (status = 400, description = "Unknown sort order", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_catalog(
+    data: web::Data<AppState>,
+    auth: Option<Auth>,
+    query: web::Query<CatalogQuery>,
+    prefetch: PrefetchImages,
+) -> impl Responder {
+    let orders: Vec<&str> = CatalogOrder::ALL.iter().map(|o| o.as_str()).collect();
+    let order = match parse_list_filter(
+        "order",
+        query.order.as_deref(),
+        CatalogOrder::parse,
+        &orders,
+    ) {
+        Ok(order) => order.unwrap_or(CatalogOrder::Title),
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_CATALOG_PER_PAGE)
+        .clamp(1, MAX_CATALOG_PER_PAGE);
+
+    let tag = tags::tag_filter(query.tag.as_deref());
+    let include_adult = include_adult(&data, auth.as_ref(), query.include_adult).await;
+
+    let offset = (page - 1).saturating_mul(per_page);
+    let items = data.db.read(|pool| {
+        list_crawled_anime(pool, order, tag.as_deref(), include_adult, per_page, offset)
+    });
+    let total = data
+        .db
+        .read(|pool| get_catalog_count(pool, tag.as_deref(), include_adult));
+    match tokio::try_join!(items, total) {
+        Ok((items, total)) => {
+            prefetch.thumbnails(&data, items.iter().map(|i| i.thumbnail.as_str()));
+            HttpResponse::Ok().json(ApiResponse::new(CatalogPage {
+                items,
+                page,
+                per_page,
+                total,
+                order,
+                tag,
+                include_adult,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to list catalog: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Database error: {}", e),
+            ))
         }
     }
 }
 
+/// Query parameters for the anime detail endpoint
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct AnimeDetailQuery {
+    /// Comma-separated fields to return (e.g., "title,episodes,genres");
+    /// all fields when omitted
+    pub fields: Option<String>,
+    /// Language of the synopsis (id, en); overrides Accept-Language
+    pub lang: Option<String>,
+}
+
 /// GET /api/anime/{slug} - Get anime detail with episodes
 ///
 /// Returns cached data if fresh (< 1 hour old), otherwise scrapes fresh data.
+/// If the scrape exceeds UPSTREAM_TIMEOUT_ANIME_DETAIL_MS, the stored detail
+/// is returned with `meta.source` "stale". The response carries an ETag of
+/// its content; a matching If-None-Match gets 304 Not Modified.
+/// Slugs merged into another anime, and slugs whose page moved to a new
+/// one, serve the anime under its current slug with `canonicalSlug` set.
+///
+/// With `fields`, only the named fields are returned and only their columns
+/// are read from the cache. Freshly scraped pages are still parsed and saved
+/// in full so the cache stays complete.
+///
+/// When a translation service is configured, the synopsis is served in the
+/// language picked by `lang` or Accept-Language, and Content-Language names
+/// the language it ended up in. Synopses that can't be translated are
+/// served in Indonesian.
 #[utoipa::path(
     get,
     path = "/api/anime/{slug}",
     tag = "anime",
     params(
-        ("slug" = String, Path, description = "Anime slug identifier")
+        ("slug" = String, Path, description = "Anime slug identifier"),
+        AnimeDetailQuery
     ),
     responses(
         (status = 200, description = "Anime detail retrieved successfully", body = AnimeDetail),
+        (status = 304, description = "Anime detail not modified"),
+        (status = 400, description = "Unknown field or language requested", body = ApiError),
         (status = 404, description = "Anime not found", body = ApiError),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 504, description = "Source site timed out and nothing is stored", body = ApiError)
     )
 )]
 pub async fn get_anime_by_slug(
+    req: HttpRequest,
     data: web::Data<AppState>,
-    path: web::Path<String>,
+    path: Slug,
+    query: web::Query<AnimeDetailQuery>,
 ) -> impl Responder {
-    let slug = path.into_inner();
     let pool = data.db.pool();
+    let slug = resolve_slug(pool, path.into_inner()).await;
     let cache_key = cache_keys::anime_detail(&slug);
 
-    match is_cache_valid(pool, &cache_key, DEFAULT_CACHE_TTL_MS).await {
+    let fields = match query.fields.as_deref().map(DetailFields::parse).transpose() {
+        Ok(fields) => fields,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(
+                ApiError::new(ErrorCode::ValidationFailed, message).with_details(
+                    serde_json::json!({ "availableFields": DetailFields::available() }),
+                ),
+            )
+        }
+    };
+    let fields = fields.as_ref();
+
+    let language = match parse_list_filter(
+        "lang",
+        query.lang.as_deref(),
+        translation::parse_language,
+        &translation::LANGUAGES,
+    ) {
+        Ok(Some(language)) => Some(language),
+        Ok(None) => req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(translation::negotiate_language),
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    let response = match is_cache_valid(pool, &cache_key, DEFAULT_CACHE_TTL_MS).await {
         Ok(true) => {
             info!("Returning cached anime detail for: {}", slug);
-            match get_anime_detail(pool, &slug).await {
-                Ok(Some(detail)) => HttpResponse::Ok().json(ApiResponse::new(detail)),
-                Ok(None) => scrape_and_save_anime_detail(&data, &slug).await,
+            let cached = match fields {
+                Some(fields) => get_anime_detail_fields(pool, &slug, fields).await,
+                None => get_anime_detail(pool, &slug).await,
+            };
+            match cached {
+                Ok(Some(detail)) => {
+                    anime_detail_response(
+                        &req,
+                        &data,
+                        &slug,
+                        detail,
+                        fields,
+                        language,
+                        ResponseMeta::cached(),
+                    )
+                    .await
+                }
+                Ok(None) => {
+                    scrape_and_save_anime_detail(&req, &data, &slug, fields, language).await
+                }
                 Err(e) => {
                     error!("Failed to get cached anime detail: {}", e);
-                    HttpResponse::InternalServerError()
-                        .json(ApiError::new(format!("Database error: {}", e)))
+                    HttpResponse::InternalServerError().json(ApiError::new(
+                        ErrorCode::DatabaseError,
+                        format!("Database error: {}", e),
+                    ))
                 }
             }
         }
-        Ok(false) => scrape_and_save_anime_detail(&data, &slug).await,
+        Ok(false) => scrape_and_save_anime_detail(&req, &data, &slug, fields, language).await,
         Err(e) => {
             error!("Failed to check cache validity: {}", e);
-            scrape_anime_detail_only(&data, &slug).await
+            scrape_anime_detail_only(&req, &data, &slug, fields, language).await
+        }
+    };
+
+    let status = response.status();
+    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+        if let Err(e) = record_anime_view(pool, &slug).await {
+            warn!("Failed to record view of {}: {}", slug, e);
+        }
+    }
+    response
+}
+
+/// The slug an alias was merged into, or `slug` itself
+///
+/// Lookup failures are logged and fall back to `slug`.
+async fn resolve_slug(pool: &sqlx::PgPool, slug: String) -> String {
+    match resolve_anime_alias(pool, &slug).await {
+        Ok(Some(target)) => {
+            info!("Anime {} is an alias of {}", slug, target);
+            target
+        }
+        Ok(None) => slug,
+        Err(e) => {
+            warn!("Failed to resolve anime alias {}: {}", slug, e);
+            slug
+        }
+    }
+}
+
+/// Respond with the detail of the anime stored under `slug`, limited to the
+/// selected fields if any
+///
+/// When the request named another slug (an alias, or a page that moved),
+/// `canonicalSlug` says where the anime lives now. With a translation
+/// service configured, the synopsis is translated into `language` when
+/// possible and Content-Language names the language it's in.
+async fn anime_detail_response(
+    req: &HttpRequest,
+    data: &AppState,
+    slug: &str,
+    mut detail: AnimeDetail,
+    fields: Option<&DetailFields>,
+    language: Option<&str>,
+    meta: ResponseMeta,
+) -> HttpResponse {
+    if req
+        .match_info()
+        .get("slug")
+        .is_some_and(|requested| requested != slug)
+    {
+        detail.canonical_slug = Some(slug.to_string());
+    }
+    if fields.is_none() {
+        detail.age_rating = match get_anime_age_rating(data.db.pool(), slug).await {
+            Ok(rating) => rating.age_rating,
+            Err(e) => {
+                warn!("Failed to get age rating of {}: {}", slug, e);
+                None
+            }
+        }
+        .or_else(|| Some(AgeRating::from_genres(&detail.genres)));
+    }
+
+    let Some(config) = &data.config.load().translation else {
+        return match fields {
+            Some(fields) => json_with_etag(req, fields.project(detail), meta),
+            None => json_with_etag(req, detail, meta),
+        };
+    };
+
+    let mut served = translation::SOURCE_LANGUAGE;
+    if let Some(language) = language.filter(|&l| l != translation::SOURCE_LANGUAGE) {
+        if !detail.synopsis.is_empty() {
+            if let Some(text) =
+                translated_synopsis(data.db.pool(), config, &detail.synopsis, language).await
+            {
+                detail.synopsis = text;
+                served = language;
+            }
+        }
+    }
+
+    let mut response = match fields {
+        Some(fields) => json_with_etag(req, fields.project(detail), meta),
+        None => json_with_etag(req, detail, meta),
+    };
+    let headers = response.headers_mut();
+    if let Ok(value) = header::HeaderValue::from_str(served) {
+        headers.insert(header::CONTENT_LANGUAGE, value);
+    }
+    headers.append(
+        header::VARY,
+        header::HeaderValue::from_static("Accept-Language"),
+    );
+    response
+}
+
+/// A synopsis translated into `language`, from storage or the translation
+/// service; `None` if it couldn't be translated
+async fn translated_synopsis(
+    pool: &sqlx::PgPool,
+    config: &TranslationConfig,
+    synopsis: &str,
+    language: &str,
+) -> Option<String> {
+    match get_synopsis_translation(pool, synopsis, language).await {
+        Ok(Some(text)) => return Some(text),
+        Ok(None) => {}
+        Err(e) => warn!("Failed to get stored synopsis translation: {}", e),
+    }
+
+    match translation::translate(config, synopsis, translation::SOURCE_LANGUAGE, language).await {
+        Ok(text) => {
+            if let Err(e) = save_synopsis_translation(pool, synopsis, language, &text).await {
+                warn!("Failed to store synopsis translation: {}", e);
+            }
+            Some(text)
+        }
+        Err(e) => {
+            warn!("Failed to translate synopsis into {}: {}", language, e);
+            None
         }
     }
 }
 
+/// Serve the stored anime detail, marked as timed out, after the scrape ran
+/// out of time; the timeout error if nothing is stored
+async fn stored_anime_detail_after_timeout(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    slug: &str,
+    fields: Option<&DetailFields>,
+    language: Option<&str>,
+    e: &ScraperError,
+    fetch_duration: Duration,
+) -> HttpResponse {
+    warn!("Anime detail {}: {}, serving stale stored data", slug, e);
+    let pool = data.db.pool();
+    let stored = match fields {
+        Some(fields) => get_anime_detail_fields(pool, slug, fields).await,
+        None => get_anime_detail(pool, slug).await,
+    };
+
+    match stored {
+        Ok(Some(detail)) => {
+            anime_detail_response(
+                req,
+                data,
+                slug,
+                detail,
+                fields,
+                language,
+                ResponseMeta::timed_out(fetch_duration),
+            )
+            .await
+        }
+        _ => scrape_error_response(e),
+    }
+}
+
 /// Helper function to scrape and save anime detail
-async fn scrape_and_save_anime_detail(data: &web::Data<AppState>, slug: &str) -> HttpResponse {
+async fn scrape_and_save_anime_detail(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    slug: &str,
+    fields: Option<&DetailFields>,
+    language: Option<&str>,
+) -> HttpResponse {
     info!("Scraping fresh anime detail for: {}", slug);
-    let scraper = Scraper::new();
+    let scraper = &data.scraper;
     let pool = data.db.pool();
-    let cache_key = cache_keys::anime_detail(slug);
 
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.anime_detail_ms);
+
+    let started = Instant::now();
+    let base_url = data.config.load().base_url.clone();
     match scraper
-        .fetch_page(&endpoints::anime(&data.config.base_url, slug))
+        .fetch_page_within(&endpoints::anime(&base_url, slug), budget)
         .await
     {
         Ok(result) => {
+            let meta = ResponseMeta::live(started.elapsed(), result.status);
             let detail = parse_anime_detail(&result.html);
 
             if detail.title.is_empty() {
-                return HttpResponse::NotFound().json(ApiError::new("Anime not found"));
+                return HttpResponse::NotFound()
+                    .json(ApiError::new(ErrorCode::AnimeNotFound, "Anime not found"));
             }
 
-            if let Err(e) = save_anime_detail_with_episodes(pool, slug, &detail).await {
-                error!("Failed to save anime detail: {}", e);
+            // A page that moved is saved under its new slug, taking over
+            // what was stored under the old one
+            let requested = slug;
+            let moved = moved_slug(&result, &base_url, slug);
+            if let Some((new_slug, new_url)) = &moved {
+                info!("Anime {} moved to {}", slug, new_slug);
+                if let Err(e) = record_anime_redirect(pool, slug, new_slug, new_url).await {
+                    warn!("Failed to record redirect of {}: {}", slug, e);
+                }
             }
+            let slug = moved
+                .as_ref()
+                .map_or(slug, |(new_slug, _)| new_slug.as_str());
 
-            if let Err(e) = update_cache_timestamp(pool, &cache_key).await {
+            match save_anime_detail_with_episodes(pool, slug, &detail).await {
+                Ok(_) => {
+                    if moved.is_some() {
+                        if let Err(e) = merge_anime(pool, requested, slug).await {
+                            warn!("Failed to merge {} into {}: {}", requested, slug, e);
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to save anime detail: {}", e),
+            }
+
+            if let Err(e) = update_cache_timestamp(pool, &cache_keys::anime_detail(slug)).await {
                 error!("Failed to update cache timestamp: {}", e);
             }
 
-            HttpResponse::Ok().json(ApiResponse::new(detail))
+            anime_detail_response(req, data, slug, detail, fields, language, meta).await
+        }
+        Err(e @ ScraperError::Timeout(_)) => {
+            stored_anime_detail_after_timeout(
+                req,
+                data,
+                slug,
+                fields,
+                language,
+                &e,
+                started.elapsed(),
+            )
+            .await
         }
         Err(e) => {
             error!("Failed to scrape anime detail: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
         }
     }
 }
 
 /// Helper function to scrape anime detail without saving (fallback)
-async fn scrape_anime_detail_only(data: &web::Data<AppState>, slug: &str) -> HttpResponse {
-    let scraper = Scraper::new();
-
+async fn scrape_anime_detail_only(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    slug: &str,
+    fields: Option<&DetailFields>,
+    language: Option<&str>,
+) -> HttpResponse {
+    let scraper = &data.scraper;
+
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.anime_detail_ms);
+
+    let started = Instant::now();
     match scraper
-        .fetch_page(&endpoints::anime(&data.config.base_url, slug))
+        .fetch_page_within(
+            &endpoints::anime(&data.config.load().base_url, slug),
+            budget,
+        )
         .await
     {
         Ok(result) => {
+            let meta = ResponseMeta::live(started.elapsed(), result.status);
             let detail = parse_anime_detail(&result.html);
 
             if detail.title.is_empty() {
-                return HttpResponse::NotFound().json(ApiError::new("Anime not found"));
+                return HttpResponse::NotFound()
+                    .json(ApiError::new(ErrorCode::AnimeNotFound, "Anime not found"));
             }
 
-            HttpResponse::Ok().json(ApiResponse::new(detail))
+            anime_detail_response(req, data, slug, detail, fields, language, meta).await
+        }
+        Err(e @ ScraperError::Timeout(_)) => {
+            stored_anime_detail_after_timeout(
+                req,
+                data,
+                slug,
+                fields,
+                language,
+                &e,
+                started.elapsed(),
+            )
+            .await
         }
         Err(e) => {
             error!("Failed to scrape anime detail: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
+        }
+    }
+}
+
+/// GET /api/anime/{slug}/timeline - Get the episode release history of an anime
+///
+/// Lists when each episode first appeared in our database, with the median
+/// gap between releases and a `likelyHiatus` flag when an airing anime has
+/// gone more than twice its median gap without a new episode. Only uses
+/// stored data; the anime must have been scraped before.
+#[utoipa::path(
+    get,
+    path = "/api/anime/{slug}/timeline",
+    tag = "anime",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    responses(
+        (status = 200, description = "Timeline retrieved successfully", body = ApiResponse<AnimeTimeline>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 404, description = "Anime not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_anime_timeline(data: web::Data<AppState>, path: Slug) -> impl Responder {
+    let slug = path.into_inner();
+
+    match data.db.read(|pool| get_episode_timeline(pool, &slug)).await {
+        Ok(Some((status, episodes))) => HttpResponse::Ok().json(ApiResponse::new(
+            AnimeTimeline::build(&slug, &status, episodes, chrono::Utc::now()),
+        )),
+        Ok(None) => HttpResponse::NotFound()
+            .json(ApiError::new(ErrorCode::AnimeNotFound, "Anime not found")),
+        Err(e) => {
+            error!("Failed to get timeline for {}: {}", slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Database error: {}", e),
+            ))
         }
     }
 }
 
+/// Query parameters for the NFO export
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct AnimeNfoQuery {
+    /// Episode slug; returns that episode's NFO instead of tvshow.nfo
+    pub episode: Option<String>,
+}
+
+/// GET /api/anime/{slug}/nfo - Export anime metadata as a Kodi/Jellyfin NFO
+///
+/// Returns `tvshow.nfo` XML for the anime, or with `episode` the NFO of one
+/// of its episodes, so media centers can scrape local libraries against
+/// this API. Only uses stored data; the anime must have been scraped
+/// before. Slugs merged into another anime export the anime they were
+/// merged into.
+///
+/// # Responses
+/// - 200: NFO XML
+/// - 400: Invalid slug
+/// - 404: Anime or episode not found
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/anime/{slug}/nfo",
+    tag = "anime",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier"),
+        AnimeNfoQuery
+    ),
+    responses(
+        (status = 200, description = "NFO exported successfully", content_type = "application/xml"),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 404, description = "Anime or episode not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_anime_nfo(
+    data: web::Data<AppState>,
+    path: Slug,
+    query: web::Query<AnimeNfoQuery>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let slug = resolve_slug(pool, path.into_inner()).await;
+
+    let detail = match get_anime_detail(pool, &slug).await {
+        Ok(Some(detail)) => detail,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(ApiError::new(ErrorCode::AnimeNotFound, "Anime not found"))
+        }
+        Err(e) => {
+            error!("Failed to get anime detail for NFO of {}: {}", slug, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Database error: {}", e),
+            ));
+        }
+    };
+
+    let (filename, body) = match query.episode.as_deref() {
+        Some(episode_slug) => match detail.episodes.iter().find(|ep| ep.slug == episode_slug) {
+            Some(episode) => (
+                format!("{}.nfo", episode.slug),
+                nfo::episode_nfo(&detail, episode),
+            ),
+            None => {
+                return HttpResponse::NotFound().json(ApiError::new(
+                    ErrorCode::EpisodeNotFound,
+                    "Episode not found",
+                ))
+            }
+        },
+        None => ("tvshow.nfo".to_string(), nfo::tvshow_nfo(&slug, &detail)),
+    };
+
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("inline; filename=\"{}\"", filename),
+        ))
+        .body(body)
+}
+
 /// GET /api/episode/{slug} - Get episode video sources
 ///
-/// Scrapes the episode page and returns video sources.
+/// Scrapes the episode page and returns video sources. Sources from blocked
+/// video servers are dropped and the rest ordered by server priority (see
+/// `/api/admin/video-servers`). When the request is authenticated, sources
+/// in the user's preferred quality are listed first and used as the default
+/// video. The response carries an ETag of its
+/// content; a matching If-None-Match gets 304 Not Modified.
 #[utoipa::path(
     get,
     path = "/api/episode/{slug}",
@@ -444,213 +1820,405 @@ async fn scrape_anime_detail_only(data: &web::Data<AppState>, slug: &str) -> Htt
     ),
     responses(
         (status = 200, description = "Episode detail with video sources retrieved successfully", body = EpisodeDetail),
+        (status = 304, description = "Episode detail not modified"),
+        (status = 400, description = "Invalid slug", body = ApiError),
         (status = 404, description = "Episode not found", body = ApiError),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 504, description = "Source site timed out", body = ApiError)
     )
 )]
 pub async fn get_episode_by_slug(
+    req: HttpRequest,
     data: web::Data<AppState>,
-    path: web::Path<String>,
+    auth: Option<Auth>,
+    tenant: CurrentTenant,
+    path: Slug,
 ) -> impl Responder {
     let slug = path.into_inner();
     let pool = data.db.pool();
 
     info!("Fetching episode: {}", slug);
-    let scraper = Scraper::new();
-    let url = endpoints::episode(&data.config.base_url, &slug);
+    let scraper = &data.scraper;
+    let url = endpoints::episode(&data.config.load().base_url, &slug);
+    let budget = Duration::from_millis(data.config.load().upstream_timeouts.episode_ms);
 
-    match scraper.fetch_page(&url).await {
+    let started = Instant::now();
+    match scraper.fetch_page_within(&url, budget).await {
         Ok(result) => {
+            let meta = ResponseMeta::live(started.elapsed(), result.status);
             let episode_detail = parse_episode_detail(&result.html);
 
             if episode_detail.title.is_empty() && episode_detail.sources.is_empty() {
-                return HttpResponse::NotFound().json(ApiError::new("Episode not found"));
+                return HttpResponse::NotFound().json(ApiError::new(
+                    ErrorCode::EpisodeNotFound,
+                    "Episode not found",
+                ));
             }
+            let episode_detail = data.video_servers.apply(episode_detail);
 
             if !episode_detail.sources.is_empty() {
                 if let Err(e) = save_video_sources(pool, &url, &episode_detail.sources).await {
                     error!("Failed to save video sources: {}", e);
                 }
             }
+            if let Err(e) = save_subtitle_tracks(pool, &url, &episode_detail.subtitles).await {
+                error!("Failed to save subtitle tracks: {}", e);
+            }
+
+            let episode_detail = match auth {
+                Some(auth) => match get_user_preferences(pool, auth.user_id).await {
+                    Ok(UserPreferences {
+                        preferred_quality: Some(quality),
+                        ..
+                    }) => apply_preferred_quality(episode_detail, &quality),
+                    Ok(_) => episode_detail,
+                    Err(e) => {
+                        warn!(
+                            "Failed to load preferences for user {}: {}",
+                            auth.user_id, e
+                        );
+                        episode_detail
+                    }
+                },
+                None => episode_detail,
+            };
+
+            let mut episode_detail = episode_detail;
+            match count_episode_comments(pool, tenant.id, &slug).await {
+                Ok(count) => episode_detail.comment_count = Some(count),
+                Err(e) => warn!("Failed to count comments of {}: {}", slug, e),
+            }
 
-            HttpResponse::Ok().json(ApiResponse::new(episode_detail))
+            json_with_etag(&req, episode_detail, meta)
         }
         Err(e) => {
             error!("Failed to fetch episode: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new(format!("Failed to fetch data: {}", e)))
+            scrape_error_response(&e)
         }
     }
 }
 
-/// Helper function to extract slug from URL
-fn extract_slug_from_url(url: &str) -> String {
-    url.trim_end_matches('/')
-        .rsplit('/')
-        .next()
-        .unwrap_or("")
-        .to_string()
+/// Query parameters for the change feed
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct ChangesQuery {
+    /// ISO 8601 timestamp; only changes after it are returned
+    pub since: String,
+    /// Cursor from the previous page's nextCursor
+    pub cursor: Option<String>,
+    /// Maximum number of changes to return (default: 500, max: 1000)
+    pub limit: Option<i64>,
+}
+
+/// GET /api/changes - Anime and episodes changed since a point in time
+///
+/// Lets mirrors and bots sync incrementally. Only records whose scraped
+/// content changed are listed; a crawl that finds nothing new produces no
+/// changes. Page through with `cursor` until `nextCursor` is null, then use
+/// `until` as the next `since`.
+///
+/// Query parameters:
+/// - since: ISO 8601 timestamp (required)
+/// - cursor: Cursor from the previous page; cursors of the form
+///   `<micros>.<kind>.<id>` handed out before the change log are still
+///   accepted
+/// - limit: Maximum number of changes (default: 500, max: 1000)
+#[utoipa::path(
+    get,
+    path = "/api/changes",
+    tag = "anime",
+    params(ChangesQuery),
+    responses(
+        (status = 200, description = "Changes retrieved successfully", body = ApiResponse<ChangesData>),
+        (status = 400, description = "Invalid since or cursor", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_changes(
+    data: web::Data<AppState>,
+    query: web::Query<ChangesQuery>,
+) -> impl Responder {
+    let since = match chrono::DateTime::parse_from_rfc3339(&query.since) {
+        Ok(since) => since.with_timezone(&chrono::Utc),
+        Err(_) => {
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "since must be an ISO 8601 timestamp",
+            ));
+        }
+    };
+    let after = match query.cursor.as_deref() {
+        Some(cursor) => match resolve_change_cursor(data.db.pool(), cursor).await {
+            Ok(Some(cursor)) => Some(cursor),
+            Ok(None) => {
+                return HttpResponse::BadRequest()
+                    .json(ApiError::new(ErrorCode::ValidationFailed, "Invalid cursor"))
+            }
+            Err(e) => {
+                error!("Failed to resolve change cursor {}: {}", cursor, e);
+                return HttpResponse::InternalServerError().json(ApiError::new(
+                    ErrorCode::DatabaseError,
+                    format!("Database error: {}", e),
+                ));
+            }
+        },
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(500).clamp(1, 1000);
+    let until = chrono::Utc::now();
+
+    // Fetch one extra row to know whether there is another page
+    let mut rows = match get_changes_since(data.db.pool(), since, after, limit + 1).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to get changes since {}: {}", since, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::DatabaseError,
+                format!("Database error: {}", e),
+            ));
+        }
+    };
+
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let next_cursor = has_more
+        .then(|| rows.last().map(|(_, cursor)| cursor.encode()))
+        .flatten();
+
+    HttpResponse::Ok().json(ApiResponse::new(ChangesData {
+        since: since.to_rfc3339(),
+        until: until.to_rfc3339(),
+        changes: rows.into_iter().map(|(entry, _)| entry).collect(),
+        next_cursor,
+    }))
 }
 
 /// POST /api/crawler/run - Start bulk crawling all anime pages
 ///
 /// Iterates through all anime list pages, scrapes metadata, anime details,
-/// episodes, and video sources. Saves everything to the database.
+/// episodes, and video sources. Saves everything to the database. Requires
+/// the `crawler:run` permission.
 #[utoipa::path(
     post,
     path = "/api/crawler/run",
     tag = "crawler",
+    security(
+        ("bearer_auth" = [])
+    ),
     responses(
         (status = 200, description = "Crawler completed successfully", body = CrawlerResponse),
-        (status = 500, description = "Internal server error", body = ApiError)
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Crawler disabled by an operator", body = ApiError)
     )
 )]
-pub async fn run_crawler(data: web::Data<AppState>) -> impl Responder {
-    info!("Starting bulk crawler");
-    let pool = data.db.pool();
-    let scraper = Scraper::new();
-
-    let mut total_crawled: i32 = 0;
-    let mut total_episodes: i32 = 0;
-    let mut total_video_sources: i32 = 0;
-    let mut pages_processed: i32 = 0;
-    let mut errors: Vec<String> = Vec::new();
-
-    let mut page: u32 = 1;
-
-    loop {
-        info!("Crawling page {}", page);
-        let url = endpoints::anime_list(&data.config.base_url, page, "", "", "");
-
-        let anime_list = match scraper.fetch_page(&url).await {
-            Ok(result) => {
-                let items = parse_anime_list(&result.html);
-                if items.is_empty() {
-                    info!("No more anime found on page {}, stopping crawler", page);
-                    break;
-                }
-                items
-            }
-            Err(e) => {
-                let error_msg = format!("Failed to fetch page {}: {}", page, e);
-                error!("{}", error_msg);
-                errors.push(error_msg);
-                page += 1;
-                if page > 1000 {
-                    break;
-                }
-                continue;
-            }
-        };
+pub async fn run_crawler(
+    data: web::Data<AppState>,
+    _auth: Permission<CrawlerRun>,
+) -> impl Responder {
+    let result = run_full_crawl(
+        data.db.pool(),
+        &data.config.load().base_url,
+        data.scraper.as_ref(),
+        &data.video_servers,
+        data.config.load().crawler_bandwidth_quota(),
+        &data.api_load,
+    )
+    .await;
 
-        pages_processed += 1;
-
-        let crawled_anime: Vec<CrawledAnime> = anime_list
-            .iter()
-            .map(|item| CrawledAnime {
-                slug: extract_slug_from_url(&item.url),
-                title: item.title.clone(),
-                url: item.url.clone(),
-                thumbnail: item.thumbnail.clone(),
-                status: item.status.clone(),
-                anime_type: item.anime_type.clone(),
-                episode_status: item.episode_status.clone(),
-            })
-            .collect();
-
-        if let Err(e) = save_crawled_anime_batch(pool, &crawled_anime).await {
-            let error_msg = format!("Failed to save crawled anime batch on page {}: {}", page, e);
-            error!("{}", error_msg);
-            errors.push(error_msg);
-        } else {
-            total_crawled += crawled_anime.len() as i32;
-        }
-
-        for anime in &crawled_anime {
-            let slug = &anime.slug;
-
-            let detail = match scraper
-                .fetch_page(&endpoints::anime(&data.config.base_url, slug))
-                .await
-            {
-                Ok(result) => {
-                    let detail = parse_anime_detail(&result.html);
-                    if detail.title.is_empty() {
-                        warn!("Empty anime detail for slug: {}", slug);
-                        continue;
-                    }
-                    detail
-                }
-                Err(e) => {
-                    let error_msg = format!("Failed to fetch anime detail for {}: {}", slug, e);
-                    warn!("{}", error_msg);
-                    errors.push(error_msg);
-                    continue;
-                }
-            };
+    HttpResponse::Ok().json(CrawlerResponse::from(result))
+}
 
-            if let Err(e) = save_anime_detail_with_episodes(pool, slug, &detail).await {
-                let error_msg = format!("Failed to save anime detail for {}: {}", slug, e);
-                warn!("{}", error_msg);
-                errors.push(error_msg);
-            } else {
-                total_episodes += detail.episodes.len() as i32;
-            }
-
-            for episode in &detail.episodes {
-                let episode_slug = extract_slug_from_url(&episode.url);
-                let episode_url = endpoints::episode(&data.config.base_url, &episode_slug);
-
-                match scraper.fetch_page(&episode_url).await {
-                    Ok(result) => {
-                        let episode_detail = parse_episode_detail(&result.html);
-
-                        if !episode_detail.sources.is_empty() {
-                            if let Err(e) =
-                                save_video_sources(pool, &episode.url, &episode_detail.sources)
-                                    .await
-                            {
-                                let error_msg = format!(
-                                    "Failed to save video sources for {}: {}",
-                                    episode_slug, e
-                                );
-                                warn!("{}", error_msg);
-                                errors.push(error_msg);
-                            } else {
-                                total_video_sources += episode_detail.sources.len() as i32;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let error_msg = format!("Failed to fetch episode {}: {}", episode_slug, e);
-                        warn!("{}", error_msg);
-                        errors.push(error_msg);
-                        continue;
-                    }
-                }
-            }
+/// POST /api/crawler/jobs - Enqueue a bulk crawl as a background job
+///
+/// Returns immediately with the queued job. Progress and the final crawl
+/// totals can be polled via GET /api/crawler/jobs/{id}. The crawl's spans
+/// join this request's trace. Requires the `crawler:run` permission.
+#[utoipa::path(
+    post,
+    path = "/api/crawler/jobs",
+    tag = "crawler",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Crawl job queued", body = ApiResponse<JobRecord>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Crawler disabled by an operator", body = ApiError)
+    )
+)]
+pub async fn enqueue_crawler_job(
+    data: web::Data<AppState>,
+    _auth: Permission<CrawlerRun>,
+) -> impl Responder {
+    match jobs::enqueue_crawl(data.db.pool()).await {
+        Ok(job) => {
+            info!("Queued crawl job {}", job.id);
+            HttpResponse::Ok().json(ApiResponse::new(job))
+        }
+        Err(e) => {
+            error!("Failed to enqueue crawl job: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to queue crawl job",
+            ))
+        }
+    }
+}
+
+/// GET /api/crawler/jobs/{id} - Get the status of a crawl job
+///
+/// Once the job has completed, `result` holds the serialized crawl totals.
+#[utoipa::path(
+    get,
+    path = "/api/crawler/jobs/{id}",
+    tag = "crawler",
+    params(
+        ("id" = i32, Path, description = "Job ID returned when the crawl was queued")
+    ),
+    responses(
+        (status = 200, description = "Crawl job retrieved successfully", body = ApiResponse<JobRecord>),
+        (status = 404, description = "Crawl job not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_crawler_job(data: web::Data<AppState>, path: web::Path<i32>) -> impl Responder {
+    let job_id = path.into_inner();
+
+    match get_job(data.db.pool(), job_id).await {
+        Ok(Some(job)) if job.job_type == jobs::JOB_TYPE_CRAWL => {
+            HttpResponse::Ok().json(ApiResponse::new(job))
+        }
+        Ok(_) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Crawl job not found"))
         }
+        Err(e) => {
+            error!("Failed to get crawl job {}: {}", job_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get crawl job",
+            ))
+        }
+    }
+}
 
-        page += 1;
+/// Query parameters for a crawl report
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct CrawlReportQuery {
+    /// `json` (default) for the full report, `text` for the plain-text summary
+    pub format: Option<String>,
+}
 
-        if page > 1000 {
-            info!("Reached page limit (1000), stopping crawler");
-            break;
+/// GET /api/crawler/jobs/{id}/report - Get the report of a finished crawl job
+///
+/// The report has per-page timings, new vs. updated counts, errors grouped
+/// by type, and the slowest page fetches. With `format=text`, only its
+/// human-readable summary is returned, as `text/plain`.
+#[utoipa::path(
+    get,
+    path = "/api/crawler/jobs/{id}/report",
+    tag = "crawler",
+    params(
+        ("id" = i32, Path, description = "Job ID returned when the crawl was queued"),
+        CrawlReportQuery
+    ),
+    responses(
+        (status = 200, description = "Crawl report retrieved successfully", body = ApiResponse<CrawlReport>),
+        (status = 404, description = "No report for this job (unknown, not a crawl, or not finished)", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_crawler_job_report(
+    data: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<CrawlReportQuery>,
+) -> impl Responder {
+    let job_id = path.into_inner();
+
+    match get_crawl_report(data.db.pool(), job_id).await {
+        Ok(Some(report)) if query.format.as_deref() == Some("text") => HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .body(report.summary),
+        Ok(Some(report)) => HttpResponse::Ok().json(ApiResponse::new(report)),
+        Ok(None) => HttpResponse::NotFound()
+            .json(ApiError::new(ErrorCode::NotFound, "Crawl report not found")),
+        Err(e) => {
+            error!("Failed to get report of crawl job {}: {}", job_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get crawl report",
+            ))
         }
     }
+}
 
-    info!(
-        "Crawler completed: {} anime, {} episodes, {} video sources, {} pages",
-        total_crawled, total_episodes, total_video_sources, pages_processed
-    );
+/// Default number of failures retried per request
+const DEFAULT_RETRY_LIMIT: i64 = 100;
 
-    HttpResponse::Ok().json(CrawlerResponse::new(
-        total_crawled,
-        total_episodes,
-        total_video_sources,
-        pages_processed,
-        errors,
-    ))
+/// Maximum number of failures retried per request
+const MAX_RETRY_LIMIT: i64 = 1000;
+
+/// Query parameters for retrying failed crawl targets
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct CrawlRetryQuery {
+    /// Maximum number of failures to retry (default: 100, max: 1000)
+    pub limit: Option<i64>,
+}
+
+/// POST /api/crawler/retry-failed - Retry anime and episodes that failed to crawl
+///
+/// Crawls pick up most of the catalog; the few anime and episodes whose
+/// fetch or save failed are queued, and this endpoint retries only those,
+/// least recently attempted first. Recovered targets leave the queue.
+/// Requires the `crawler:run` permission.
+#[utoipa::path(
+    post,
+    path = "/api/crawler/retry-failed",
+    tag = "crawler",
+    params(CrawlRetryQuery),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Failed targets retried", body = ApiResponse<CrawlRetryResult>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "Permission required", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Crawler disabled by an operator", body = ApiError)
+    )
+)]
+pub async fn retry_failed_crawls(
+    data: web::Data<AppState>,
+    _auth: Permission<CrawlerRun>,
+    query: web::Query<CrawlRetryQuery>,
+) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_RETRY_LIMIT)
+        .clamp(1, MAX_RETRY_LIMIT);
+
+    match retry_failed(
+        data.db.pool(),
+        &data.config.load().base_url,
+        data.scraper.as_ref(),
+        &data.video_servers,
+        limit,
+        data.config.load().crawler_bandwidth_quota(),
+        &data.api_load,
+    )
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().json(ApiResponse::new(result)),
+        Err(e) => {
+            error!("Failed to retry failed crawl targets: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to retry failed crawl targets",
+            ))
+        }
+    }
 }
 
 /// OpenAPI documentation
@@ -659,7 +2227,7 @@ pub async fn run_crawler(data: web::Data<AppState>) -> impl Responder {
     info(
         title = "Anime Scraper API",
         version = "0.1.0",
-        description = "API for scraping and accessing anime data from sokuja.uk",
+        description = "API for scraping and accessing anime data from sokuja.uk\n\nError responses carry a machine-readable `code`; see the ErrorCode schema for the catalog.\n\n## Versioning\n\nEvery `/api` path is also served as `/api/v1/...` and `/api/v2/...`. The version is picked by the path prefix, else by an `Api-Version: 1|2` request header, else v1 (the behavior of unversioned paths). Breaking improvements (typed enums, pagination envelopes, error codes) land in v2 only. Responses report the version served in `Api-Version`. Unsupported `Api-Version` values get 400.",
         contact(
             name = "API Support",
             url = "https://github.com/yourusername/anime-scraper"
@@ -671,11 +2239,20 @@ pub async fn run_crawler(data: web::Data<AppState>) -> impl Responder {
     paths(
         get_updates,
         get_completed,
+        get_popular,
         search_anime,
         get_anime_list,
+        get_catalog,
         get_anime_by_slug,
+        get_anime_timeline,
+        get_anime_nfo,
         get_episode_by_slug,
+        get_changes,
         run_crawler,
+        enqueue_crawler_job,
+        get_crawler_job,
+        get_crawler_job_report,
+        retry_failed_crawls,
         auth::register,
         auth::login,
         auth::google_auth,
@@ -685,6 +2262,10 @@ pub async fn run_crawler(data: web::Data<AppState>) -> impl Responder {
         auth::reset_password,
         auth::verify_email,
         auth::resend_verification,
+        auth::request_reactivation,
+        auth::confirm_reactivation,
+        auth::register_device,
+        auth::jwks,
         user::add_favorite_handler,
         user::get_favorites_handler,
         user::remove_favorite_handler,
@@ -693,64 +2274,324 @@ pub async fn run_crawler(data: web::Data<AppState>) -> impl Responder {
         user::remove_subscription_handler,
         user::add_history_handler,
         user::get_history_handler,
-        user::remove_history_handler
+        user::remove_history_handler,
+        user::continue_watching_handler,
+        user::get_watched_handler,
+        user::mark_watched_handler,
+        user::get_preferences_handler,
+        user::update_preferences_handler,
+        user::list_sessions_handler,
+        user::revoke_session_handler,
+        user::create_saved_search_handler,
+        user::get_saved_searches_handler,
+        user::delete_saved_search_handler,
+        user::upload_avatar_handler,
+        collections::create_collection_handler,
+        collections::get_collections_handler,
+        collections::get_collection_handler,
+        collections::update_collection_handler,
+        collections::delete_collection_handler,
+        collections::add_collection_item_handler,
+        collections::reorder_collection_items_handler,
+        collections::remove_collection_item_handler,
+        collections::get_shared_collection_handler,
+        comments::get_comments_handler,
+        comments::create_comment_handler,
+        comments::delete_comment_handler,
+        comments::report_comment_handler,
+        reactions::like_episode_handler,
+        reactions::unlike_episode_handler,
+        tags::get_tags_handler,
+        tags::get_anime_tags_handler,
+        tags::tag_anime_handler,
+        tags::untag_anime_handler,
+        community::get_community_top_handler,
+        user::deactivate_account_handler,
+        user::data_export_handler,
+        user::download_data_export_handler,
+        user::erase_data_handler,
+        admin::get_tenants_handler,
+        admin::create_tenant_handler,
+        admin::anime_diff_handler,
+        admin::merge_anime_handler,
+        admin::parser_golden_handler,
+        admin::search_analytics_handler,
+        admin::confirm_maintenance_handler,
+        admin::run_maintenance_handler,
+        admin::get_episode_gaps_handler,
+        admin::get_integrity_report_handler,
+        admin::enqueue_integrity_check_handler,
+        admin::get_roles_handler,
+        admin::create_role_handler,
+        admin::update_role_handler,
+        admin::delete_role_handler,
+        admin::get_user_roles_handler,
+        admin::assign_role_handler,
+        admin::unassign_role_handler,
+        admin::get_moderation_queue_handler,
+        admin::get_moderation_item_handler,
+        admin::approve_moderation_item_handler,
+        admin::remove_moderation_item_handler,
+        admin::get_moderation_standing_handler,
+        admin::unmute_user_handler,
+        admin::get_data_erasures_handler,
+        admin::get_video_servers_handler,
+        admin::set_video_server_handler,
+        admin::delete_video_server_handler,
+        admin::get_feature_flags_handler,
+        admin::set_feature_flag_handler,
+        admin::delete_feature_flag_handler,
+        admin::create_tag_handler,
+        admin::update_tag_handler,
+        admin::delete_tag_handler,
+        admin::get_age_rating_handler,
+        admin::set_age_rating_handler,
+        admin::delete_age_rating_handler,
+        admin::get_anomalies_handler,
+        admin::get_bandwidth_handler,
+        admin::get_db_pool_handler,
+        admin::reload_config_handler,
+        images::sign_image_handler,
+        images::proxy_image_handler,
+        sources::check_sources_handler,
+        subtitles::proxy_subtitle_handler,
+        cast::get_cast_metadata_handler,
+        sitemap::sitemap_index_handler,
+        sitemap::sitemap_partial_handler,
+        admin::get_jobs_handler,
+        admin::retry_job_handler,
+        admin::get_email_deliveries_handler,
+        admin::resend_email_handler
     ),
     components(
         schemas(
             AnimeUpdate,
+            Thumbnails,
             SearchResult,
             AnimeListItem,
             Episode,
             VideoSource,
+            EmbedInfo,
+            SubtitleTrack,
             EpisodeDetail,
             AnimeDetail,
             CompletedAnime,
+            PopularEntry,
+            PopularWindow,
             UserFavorite,
             UserSubscription,
             UserHistory,
+            ContinueWatching,
+            WatchProgress,
             User,
             RegisterRequest,
             LoginRequest,
             GoogleAuthRequest,
             AuthResponse,
             AuthData,
+            Jwk,
+            JwkSet,
+            RegisterDeviceRequest,
+            DeviceRegistration,
             ApiError,
+            ErrorCode,
+            DataSource,
+            ResponseMeta,
             AnimeListResponse,
             AnimeListFilters,
             CrawledAnime,
             CrawledAnimeRecord,
             CrawlerResponse,
             CrawlerData,
+            CrawlError,
+            ChangeCount,
+            CrawlReportQuery,
+            CrawlReport,
+            CrawlErrorGroup,
+            CrawlPageTiming,
+            CrawlRequestKind,
+            CrawlRequestTiming,
+            CrawlRetryQuery,
+            CrawlFailureKind,
+            CrawlFailure,
+            CrawlRetryResult,
+            ChangesQuery,
+            ChangeKind,
+            ChangeEntry,
+            ChangesData,
+            TimelineEpisode,
+            AnimeTimeline,
+            FieldDiff,
+            EpisodeDiff,
+            AnimeDiff,
+            PageKind,
+            FieldMismatch,
+            GoldenStatus,
+            GoldenResult,
+            GoldenReport,
+            JobRecord,
+            JobQueueStats,
+            JobsOverview,
+            EmailDelivery,
+            SearchQueryStats,
+            SearchAnalytics,
+            MaintenanceAction,
+            MaintenanceResult,
+            TableRowCount,
+            OrphanGroup,
+            EpisodeGap,
+            EpisodeGapReport,
+            IntegrityReport,
+            MergeAnimeRequest,
+            AnimeMergeResult,
+            Role,
+            CreateRoleRequest,
+            UpdateRoleRequest,
+            UserRoles,
+            VideoServerRuleOrigin,
+            VideoServerRule,
+            VideoServerRuleRequest,
+            Feature,
+            FeatureFlag,
+            FeatureFlagRequest,
+            UpstreamAnomalyKind,
+            UpstreamAnomaly,
+            UpstreamAnomalyCounts,
+            UpstreamAnomalyReport,
+            CrawlerBandwidthDay,
+            CrawlerBandwidthReport,
+            DbPoolStats,
+            DbPoolReport,
+            ConfigReload,
+            ModerationStatus,
+            ModerationItem,
+            ContentReport,
+            ModerationItemDetail,
+            ModerationDecision,
+            UserStrike,
+            ModerationStanding,
+            ModerationResolution,
+            admin::EmailDeliveriesQuery,
+            admin::SearchAnalyticsQuery,
+            admin::IntegrityCheckQuery,
+            admin::ModerationQueueQuery,
+            admin::DataErasuresQuery,
             SearchQuery,
+            AnimeDetailQuery,
+            AnimeNfoQuery,
             AnimeListQuery,
+            CatalogQuery,
+            CompletedQuery,
+            PopularQuery,
+            AdultQuery,
+            images::PrefetchImagesQuery,
+            CatalogOrder,
+            CatalogPage,
             user::AddFavoriteRequest,
             user::AddSubscriptionRequest,
             user::AddHistoryRequest,
+            user::ContinueWatchingQuery,
+            user::MarkWatchedRequest,
+            user::CreateSavedSearchRequest,
+            user::AvatarUploadForm,
+            SavedSearch,
+            collections::CreateCollectionRequest,
+            collections::UpdateCollectionRequest,
+            collections::AddCollectionItemRequest,
+            collections::ReorderCollectionRequest,
+            Collection,
+            CollectionItem,
+            CollectionDetail,
+            comments::CommentsQuery,
+            comments::CreateCommentRequest,
+            comments::ReportCommentRequest,
+            EpisodeComment,
+            CommentPage,
+            EpisodeLikes,
+            tags::TagsQuery,
+            Tag,
+            AnimeTag,
+            TagAnimeRequest,
+            CreateTagRequest,
+            UpdateTagRequest,
+            AgeRating,
+            AnimeAgeRating,
+            AgeRatingOverrideRequest,
+            community::CommunityTopQuery,
+            LeaderboardWindow,
+            CommunityTopEntry,
+            CommunityTop,
+            DataExport,
+            DataErasure,
+            UserPreferences,
+            UpdatePreferencesRequest,
+            Session,
+            PasswordFeedback,
+            WeakPasswordResponse,
+            SignedUrl,
+            Tenant,
+            CreateTenantRequest,
+            images::SignImageQuery,
+            SourceCheckRequest,
+            SourceStatus,
+            subtitles::SubtitleProxyQuery,
+            CastMetadata,
+            CastMediaMetadata,
+            CastImage,
+            CastTrack,
             ForgotPasswordRequest,
             ResetPasswordRequest,
             VerifyEmailRequest,
-            ResendVerificationRequest
+            ResendVerificationRequest,
+            ReactivateAccountRequest,
+            ConfirmReactivationRequest
         )
     ),
     tags(
         (name = "anime", description = "Anime data endpoints"),
         (name = "auth", description = "Authentication endpoints"),
         (name = "user", description = "User-specific endpoints (favorites, subscriptions, history)"),
-        (name = "crawler", description = "Bulk crawling operations")
+        (name = "collections", description = "User-curated anime collections and their public links"),
+        (name = "comments", description = "Episode comments with playback timestamps"),
+        (name = "reactions", description = "Episode likes shown on the updates feed"),
+        (name = "tags", description = "User and curated tags describing anime beyond genres"),
+        (name = "community", description = "Leaderboards from registered users' activity"),
+        (name = "crawler", description = "Bulk crawling operations"),
+        (name = "images", description = "Signed image proxy"),
+        (name = "sources", description = "Video source health checks"),
+        (name = "subtitles", description = "Subtitle track proxy"),
+        (name = "sitemap", description = "Search engine sitemaps for frontends"),
+        (name = "admin", description = "Administrative endpoints (admin accounts only)")
     )
 )]
 pub struct ApiDoc;
 
 /// Configure API routes
+///
+/// Registers the catch-all `/api` scope. Actix matches scopes by prefix in
+/// registration order, so more specific scopes (`/api/auth`, `/api/admin`, ...)
+/// must be configured before this one.
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
             .route("/updates", web::get().to(get_updates))
             .route("/completed", web::get().to(get_completed))
+            .route("/popular", web::get().to(get_popular))
             .route("/search", web::get().to(search_anime))
             .route("/anime/list", web::get().to(get_anime_list))
+            .route("/catalog", web::get().to(get_catalog))
             .route("/anime/{slug}", web::get().to(get_anime_by_slug))
+            .route("/anime/{slug}/timeline", web::get().to(get_anime_timeline))
+            .route("/anime/{slug}/nfo", web::get().to(get_anime_nfo))
             .route("/episode/{slug}", web::get().to(get_episode_by_slug))
-            .route("/crawler/run", web::post().to(run_crawler)),
+            .route("/changes", web::get().to(get_changes))
+            .route("/crawler/run", web::post().to(run_crawler))
+            .route("/crawler/jobs", web::post().to(enqueue_crawler_job))
+            .route("/crawler/retry-failed", web::post().to(retry_failed_crawls))
+            .route("/crawler/jobs/{id}", web::get().to(get_crawler_job))
+            .route(
+                "/crawler/jobs/{id}/report",
+                web::get().to(get_crawler_job_report),
+            ),
     );
 }
@@ -0,0 +1,155 @@
+//! Episode reaction routes for the Anime Scraper API
+//!
+//! Users like episodes; the counts show up on the updates feed as
+//! `likeCount`, with `likedByMe` on authenticated requests:
+//! - PUT /api/episode/:slug/like - Like an episode
+//! - DELETE /api/episode/:slug/like - Take back a like
+
+use actix_web::{web, HttpResponse, Responder};
+use tracing::{error, info};
+
+use crate::auth::Auth;
+use crate::db::{get_episode_likes, like_episode, unlike_episode};
+use crate::middleware::limits::Slug;
+use crate::models::{ApiError, ApiResponse, EpisodeLikes, ErrorCode};
+use crate::routes::AppState;
+use crate::tenants::CurrentTenant;
+
+/// Respond with the episode's likes after a change
+async fn likes_response(data: &AppState, tenant_id: i32, user_id: i32, slug: &str) -> HttpResponse {
+    match get_episode_likes(
+        data.db.pool(),
+        tenant_id,
+        Some(user_id),
+        &[slug.to_string()],
+    )
+    .await
+    {
+        Ok(mut likes) if !likes.is_empty() => {
+            HttpResponse::Ok().json(ApiResponse::new(likes.remove(0)))
+        }
+        Ok(_) => HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to get likes",
+        )),
+        Err(e) => {
+            error!("Failed to get likes of {}: {}", slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get likes",
+            ))
+        }
+    }
+}
+
+/// PUT /api/episode/:slug/like - Like an episode
+///
+/// Requires authentication via JWT token in Authorization header. Liking an
+/// episode twice is a no-op.
+///
+/// # Responses
+/// - 200: Returns the episode's likes
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    put,
+    path = "/api/episode/{slug}/like",
+    tag = "reactions",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Episode liked", body = ApiResponse<EpisodeLikes>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn like_episode_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    tenant: CurrentTenant,
+    slug: Slug,
+) -> impl Responder {
+    match like_episode(data.db.pool(), auth.user_id, &slug).await {
+        Ok(liked) => {
+            if liked {
+                info!("User {} liked {}", auth.user_id, &*slug);
+            }
+            likes_response(&data, tenant.id, auth.user_id, &slug).await
+        }
+        Err(e) => {
+            error!("Failed to like {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to like episode",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/episode/:slug/like - Take back a like
+///
+/// Requires authentication via JWT token in Authorization header. Unliking
+/// an episode that isn't liked is a no-op.
+///
+/// # Responses
+/// - 200: Returns the episode's likes
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/episode/{slug}/like",
+    tag = "reactions",
+    params(
+        ("slug" = String, Path, description = "Episode slug identifier")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Like removed", body = ApiResponse<EpisodeLikes>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn unlike_episode_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    tenant: CurrentTenant,
+    slug: Slug,
+) -> impl Responder {
+    match unlike_episode(data.db.pool(), auth.user_id, &slug).await {
+        Ok(unliked) => {
+            if unliked {
+                info!("User {} unliked {}", auth.user_id, &*slug);
+            }
+            likes_response(&data, tenant.id, auth.user_id, &slug).await
+        }
+        Err(e) => {
+            error!("Failed to unlike {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to unlike episode",
+            ))
+        }
+    }
+}
+
+/// Configure episode reaction routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_reaction_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/episode/{slug}/like")
+            .route("", web::put().to(like_episode_handler))
+            .route("", web::delete().to(unlike_episode_handler)),
+    );
+}
@@ -0,0 +1,124 @@
+//! Sitemap routes
+//!
+//! Served outside `/api` so frontends can proxy them at the paths search
+//! engines expect. Built from stored anime, so only crawled or previously
+//! viewed anime are listed:
+//! - GET /sitemap.xml - Sitemap index
+//! - GET /sitemaps/anime-{page}.xml - Anime detail pages, one partial per page
+
+use actix_web::http::header;
+use actix_web::{web, HttpResponse, Responder};
+use tracing::error;
+
+use crate::db::{get_sitemap_anime, get_sitemap_pages};
+use crate::models::{ApiError, ErrorCode};
+use crate::routes::AppState;
+use crate::sitemap::{self, SITEMAP_PAGE_SIZE};
+
+/// How long clients and crawlers may cache a sitemap
+const SITEMAP_MAX_AGE_SECS: u64 = 3600;
+
+/// Respond with a sitemap document
+fn xml_response(body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", SITEMAP_MAX_AGE_SECS),
+        ))
+        .body(body)
+}
+
+/// GET /sitemap.xml - Sitemap index
+///
+/// Lists the anime sitemap partials, each with the latest change of its
+/// anime. An empty database gets an empty index.
+///
+/// # Responses
+/// - 200: Sitemap index XML
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/sitemap.xml",
+    tag = "sitemap",
+    responses(
+        (status = 200, description = "Sitemap index", content_type = "application/xml"),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn sitemap_index_handler(data: web::Data<AppState>) -> impl Responder {
+    match data
+        .db
+        .read(|pool| get_sitemap_pages(pool, SITEMAP_PAGE_SIZE))
+        .await
+    {
+        Ok(pages) => xml_response(sitemap::sitemap_index(
+            &data.config.load().sitemap_base_url,
+            &pages,
+        )),
+        Err(e) => {
+            error!("Failed to get sitemap pages: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to build sitemap",
+            ))
+        }
+    }
+}
+
+/// GET /sitemaps/anime-{page}.xml - Anime detail pages
+///
+/// Lists up to 50,000 anime by slug, with when each was last updated.
+///
+/// # Responses
+/// - 200: Sitemap XML
+/// - 404: No such partial
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/sitemaps/anime-{page}.xml",
+    tag = "sitemap",
+    params(
+        ("page" = i64, Path, description = "Partial number, starting at 1")
+    ),
+    responses(
+        (status = 200, description = "Sitemap partial", content_type = "application/xml"),
+        (status = 404, description = "Sitemap partial not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn sitemap_partial_handler(
+    data: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let not_found =
+        || HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Sitemap not found"));
+    let Some(page) = sitemap::parse_partial_name(&path) else {
+        return not_found();
+    };
+
+    match data
+        .db
+        .read(|pool| get_sitemap_anime(pool, page, SITEMAP_PAGE_SIZE))
+        .await
+    {
+        Ok(entries) if entries.is_empty() => not_found(),
+        Ok(entries) => xml_response(sitemap::urlset(
+            &data.config.load().sitemap_base_url,
+            &entries,
+        )),
+        Err(e) => {
+            error!("Failed to get sitemap page {}: {}", page, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to build sitemap",
+            ))
+        }
+    }
+}
+
+/// Configure sitemap routes
+pub fn configure_sitemap_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/sitemap.xml", web::get().to(sitemap_index_handler))
+        .route("/sitemaps/{name}", web::get().to(sitemap_partial_handler));
+}
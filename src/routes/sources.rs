@@ -0,0 +1,207 @@
+//! Video source check routes for the Anime Scraper API
+//!
+//! Mirrors on episode pages go down without notice. Players send the sources
+//! of an episode here to pick a working one before playback starts. Only
+//! URLs stored as video sources are fetched, so the endpoint can't be used
+//! to probe arbitrary hosts:
+//! - POST /api/sources/check - Check whether video sources respond
+
+use std::time::{Duration, Instant};
+
+use actix_web::{web, HttpResponse, Responder};
+use reqwest::header;
+use tracing::error;
+
+use crate::constants::embed_hosts;
+use crate::db::get_known_video_source_urls;
+use crate::models::{ApiError, ApiResponse, ErrorCode, SourceCheckRequest, SourceStatus};
+use crate::routes::AppState;
+
+/// Most URLs one request can check
+pub const MAX_SOURCE_CHECK_URLS: usize = 20;
+
+/// Timeout for each source check
+const SOURCE_CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// Validate the URLs to check
+///
+/// # Returns
+/// An error message, or `None` if the URLs are valid
+fn source_check_error(request: &SourceCheckRequest) -> Option<String> {
+    if request.urls.is_empty() || request.urls.len() > MAX_SOURCE_CHECK_URLS {
+        return Some(format!(
+            "urls must hold 1 to {} URLs",
+            MAX_SOURCE_CHECK_URLS
+        ));
+    }
+    None
+}
+
+/// Referer to send when fetching `url`
+///
+/// Some embed hosts refuse requests that don't come from an embedding page;
+/// the source site's own files are fetched as if from its pages too.
+pub fn source_referer(url: &reqwest::Url, base_url: &str) -> Option<String> {
+    let host = url.host_str()?;
+    let base_host = reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|base| base.host_str().map(str::to_lowercase));
+    let needs_referer = embed_hosts::lookup(host).is_some_and(|known| known.requires_referer)
+        || base_host.is_some_and(|base| host.eq_ignore_ascii_case(&base));
+    needs_referer.then(|| format!("{}/", base_url.trim_end_matches('/')))
+}
+
+/// Status of a source that wasn't fetched
+fn unchecked(url: &str, reason: &str) -> SourceStatus {
+    SourceStatus {
+        url: url.to_string(),
+        alive: false,
+        status: None,
+        latency_ms: None,
+        error: Some(reason.to_string()),
+    }
+}
+
+/// Fetch the first byte of a source and report how it responded
+async fn check_source(client: reqwest::Client, url: String, base_url: String) -> SourceStatus {
+    let parsed = match reqwest::Url::parse(&url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => parsed,
+        _ => return unchecked(&url, "Not an http(s) URL"),
+    };
+
+    let mut request = client
+        .get(parsed.clone())
+        .timeout(Duration::from_secs(SOURCE_CHECK_TIMEOUT_SECS))
+        .header(header::RANGE, "bytes=0-0");
+    if let Some(referer) = source_referer(&parsed, &base_url) {
+        request = request.header(header::REFERER, referer);
+    }
+
+    let started = Instant::now();
+    match request.send().await {
+        Ok(response) => SourceStatus {
+            url,
+            alive: response.status().is_success(),
+            status: Some(response.status().as_u16()),
+            latency_ms: Some(started.elapsed().as_millis() as u64),
+            error: None,
+        },
+        Err(e) if e.is_timeout() => unchecked(&url, "Timed out"),
+        Err(_) => unchecked(&url, "Unreachable"),
+    }
+}
+
+/// POST /api/sources/check - Check whether video sources respond
+///
+/// Doesn't require authentication. Each URL is fetched concurrently with
+/// the headers its host expects, asking for a single byte; sources
+/// answering with a 2xx status are alive. URLs that aren't stored video
+/// sources are reported without being fetched. Results are in request
+/// order.
+///
+/// # Responses
+/// - 200: Returns the status of each URL
+/// - 400: No URLs or more than 20
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/sources/check",
+    tag = "sources",
+    request_body = SourceCheckRequest,
+    responses(
+        (status = 200, description = "Sources checked", body = ApiResponse<Vec<SourceStatus>>),
+        (status = 400, description = "No URLs or too many", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn check_sources_handler(
+    data: web::Data<AppState>,
+    body: web::Json<SourceCheckRequest>,
+) -> impl Responder {
+    if let Some(msg) = source_check_error(&body) {
+        return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg));
+    }
+
+    let known = match get_known_video_source_urls(data.db.pool(), &body.urls).await {
+        Ok(known) => known,
+        Err(e) => {
+            error!("Failed to look up video sources: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to check sources",
+            ));
+        }
+    };
+    let checks: Vec<_> = body
+        .urls
+        .iter()
+        .map(|url| {
+            known.contains(url).then(|| {
+                tokio::spawn(check_source(
+                    data.media_client.clone(),
+                    url.clone(),
+                    data.config.load().base_url.clone(),
+                ))
+            })
+        })
+        .collect();
+
+    let mut statuses = Vec::with_capacity(checks.len());
+    for (url, check) in body.urls.iter().zip(checks) {
+        let status = match check {
+            Some(handle) => match handle.await {
+                Ok(status) => status,
+                Err(e) => {
+                    error!("Source check for {} failed: {}", url, e);
+                    unchecked(url, "Check failed")
+                }
+            },
+            None => unchecked(url, "Unknown source"),
+        };
+        statuses.push(status);
+    }
+
+    HttpResponse::Ok().json(ApiResponse::new(statuses))
+}
+
+/// Configure video source check routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_source_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/api/sources").route("/check", web::post().to(check_sources_handler)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://x3.sokuja.uk";
+
+    fn referer(url: &str) -> Option<String> {
+        source_referer(&reqwest::Url::parse(url).unwrap(), BASE)
+    }
+
+    #[test]
+    fn test_source_referer() {
+        let expected = Some("https://x3.sokuja.uk/".to_string());
+        assert_eq!(
+            referer("https://www.mp4upload.com/embed-abc.html"),
+            expected
+        );
+        assert_eq!(referer("https://X3.sokuja.uk/video/1.mp4"), expected);
+        assert_eq!(referer("https://www.blogger.com/video.g?token=abc"), None);
+        assert_eq!(referer("https://cdn.example.com/1.mp4"), None);
+    }
+
+    #[test]
+    fn test_source_check_error() {
+        let request = |count: usize| SourceCheckRequest {
+            urls: vec!["https://example.com/1.mp4".to_string(); count],
+        };
+        assert!(source_check_error(&request(0)).is_some());
+        assert!(source_check_error(&request(1)).is_none());
+        assert!(source_check_error(&request(MAX_SOURCE_CHECK_URLS)).is_none());
+        assert!(source_check_error(&request(MAX_SOURCE_CHECK_URLS + 1)).is_some());
+    }
+}
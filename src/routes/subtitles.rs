@@ -0,0 +1,197 @@
+//! Subtitle proxy routes for the Anime Scraper API
+//!
+//! Web players load `<track>` files with CORS, which subtitle hosts rarely
+//! allow, and browsers only play WebVTT. The proxy refetches stored
+//! subtitle tracks, converts SRT files to WebVTT, and serves them to any
+//! origin. Only URLs saved from episode pages are fetched, so it can't be
+//! used as an open proxy:
+//! - GET /api/subtitles/proxy - Fetch a subtitle track as WebVTT
+
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use tracing::{error, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use super::images::read_body_capped;
+use super::sources::source_referer;
+use crate::db::is_known_subtitle_url;
+use crate::models::{ApiError, ErrorCode};
+use crate::routes::AppState;
+
+/// Path of the proxy endpoint
+pub const PROXY_PATH: &str = "/api/subtitles/proxy";
+
+/// Largest subtitle file the proxy will relay (2 MB)
+const MAX_SUBTITLE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Timeout for fetching a subtitle file from upstream
+const SUBTITLE_TIMEOUT_SECS: u64 = 10;
+
+/// How long clients may cache a proxied subtitle file
+const SUBTITLE_MAX_AGE_SECS: u64 = 86400;
+
+/// Query parameters for the subtitle proxy
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct SubtitleProxyQuery {
+    /// Subtitle track URL from an episode response
+    pub url: String,
+}
+
+/// Convert a subtitle file to WebVTT
+///
+/// WebVTT files are returned as they are. Anything else is read as SRT:
+/// cue numbers are kept as cue identifiers and the comma before the
+/// milliseconds of each timestamp becomes a dot. A leading byte order mark
+/// is dropped and line endings are normalized.
+pub fn to_webvtt(text: &str) -> String {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    if text.starts_with("WEBVTT") {
+        return text;
+    }
+
+    let mut vtt = String::from("WEBVTT\n\n");
+    for line in text.trim_start().lines() {
+        if line.contains("-->") {
+            vtt.push_str(&line.replace(',', "."));
+        } else {
+            vtt.push_str(line);
+        }
+        vtt.push('\n');
+    }
+    vtt
+}
+
+/// 502 response for a subtitle file that couldn't be fetched
+fn upstream_error(message: &str) -> HttpResponse {
+    HttpResponse::BadGateway().json(ApiError::new(ErrorCode::UpstreamUnavailable, message))
+}
+
+/// GET /api/subtitles/proxy - Fetch a subtitle track as WebVTT
+///
+/// Doesn't require authentication. Responses carry
+/// `Access-Control-Allow-Origin: *` so players on any origin can load them,
+/// and are publicly cacheable for a day.
+///
+/// Query parameters:
+/// - url: Subtitle track URL from an episode response
+///
+/// # Responses
+/// - 200: The subtitle file as WebVTT
+/// - 404: The URL isn't a known subtitle track
+/// - 500: Internal server error
+/// - 502: Upstream fetch failed or the file is too large
+#[utoipa::path(
+    get,
+    path = "/api/subtitles/proxy",
+    tag = "subtitles",
+    params(SubtitleProxyQuery),
+    responses(
+        (status = 200, description = "Subtitle file", content_type = "text/vtt"),
+        (status = 404, description = "Unknown subtitle track", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 502, description = "Upstream error", body = ApiError)
+    )
+)]
+pub async fn proxy_subtitle_handler(
+    data: web::Data<AppState>,
+    query: web::Query<SubtitleProxyQuery>,
+) -> impl Responder {
+    let url = &query.url;
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => parsed,
+        _ => {
+            return HttpResponse::NotFound().json(ApiError::new(
+                ErrorCode::NotFound,
+                "Subtitle track not found",
+            ))
+        }
+    };
+    match is_known_subtitle_url(data.db.pool(), url).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::NotFound().json(ApiError::new(
+                ErrorCode::NotFound,
+                "Subtitle track not found",
+            ))
+        }
+        Err(e) => {
+            error!("Failed to look up subtitle track {}: {}", url, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to fetch subtitles",
+            ));
+        }
+    }
+
+    let mut request = data
+        .media_client
+        .get(parsed.clone())
+        .timeout(Duration::from_secs(SUBTITLE_TIMEOUT_SECS));
+    if let Some(referer) = source_referer(&parsed, &data.config.load().base_url) {
+        request = request.header(reqwest::header::REFERER, referer);
+    }
+    let response = match request.send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            warn!("Subtitle proxy got {} for {}", response.status(), url);
+            return upstream_error("Failed to fetch subtitles");
+        }
+        Err(e) => {
+            warn!("Subtitle proxy failed to fetch {}: {}", url, e);
+            return upstream_error("Failed to fetch subtitles");
+        }
+    };
+    let body = match read_body_capped(response, MAX_SUBTITLE_BYTES).await {
+        Ok(Some(body)) => body,
+        Ok(None) => return upstream_error("Subtitle file is too large"),
+        Err(e) => {
+            warn!("Subtitle proxy failed to read {}: {}", url, e);
+            return upstream_error("Failed to fetch subtitles");
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/vtt; charset=utf-8")
+        .insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"))
+        .insert_header((
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", SUBTITLE_MAX_AGE_SECS),
+        ))
+        .body(to_webvtt(&String::from_utf8_lossy(&body)))
+}
+
+/// Configure subtitle proxy routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow it.
+pub fn configure_subtitle_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/subtitles").route("/proxy", web::get().to(proxy_subtitle_handler)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_webvtt_converts_srt() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\nHalo, Frieren.\r\n\r\n2\r\n00:01:00,250 --> 00:01:03,000\r\nSatu, dua\r\n";
+        assert_eq!(
+            to_webvtt(srt),
+            "WEBVTT\n\n1\n00:00:01.000 --> 00:00:02.500\nHalo, Frieren.\n\n2\n00:01:00.250 --> 00:01:03.000\nSatu, dua\n"
+        );
+    }
+
+    #[test]
+    fn test_to_webvtt_keeps_vtt() {
+        let vtt = "WEBVTT\r\n\r\n00:01.000 --> 00:02.000\r\nHalo, dunia\r\n";
+        assert_eq!(
+            to_webvtt(vtt),
+            "WEBVTT\n\n00:01.000 --> 00:02.000\nHalo, dunia\n"
+        );
+    }
+}
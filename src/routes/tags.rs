@@ -0,0 +1,389 @@
+//! Tag routes for the Anime Scraper API
+//!
+//! Tags describe anime beyond the scraped genres ("time travel", "tournament
+//! arc"). Users tag anime, creating uncurated tags by applying new names;
+//! admins curate them through the admin routes. Tags filter `/api/catalog`,
+//! `/api/anime/list`, and `/api/search` with `tag=<slug>`:
+//! - GET /api/tags - Tags ordered by popularity
+//! - GET /api/anime/:slug/tags - Tags on an anime
+//! - POST /api/anime/:slug/tags - Tag an anime
+//! - DELETE /api/anime/:slug/tags/:tag - Take back a tag
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Deserialize;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::auth::Auth;
+use crate::db::{
+    get_anime_tags, get_tagged_slugs, get_tags, get_user_muted_until, tag_anime, untag_anime,
+    Database, RepositoryError, RepositoryResult, TagOutcome,
+};
+use crate::middleware::limits::Slug;
+use crate::models::{AnimeTag, ApiError, ApiResponse, ErrorCode, Tag, TagAnimeRequest};
+use crate::routes::AppState;
+
+/// Longest accepted tag name, in characters
+pub const MAX_TAG_NAME_LEN: usize = 50;
+
+/// Uncurated tags a user may create per day
+pub const MAX_NEW_TAGS_PER_DAY: i64 = 20;
+
+/// Default number of tags listed
+const DEFAULT_TAGS_LIMIT: i64 = 100;
+
+/// Maximum number of tags listed
+const MAX_TAGS_LIMIT: i64 = 500;
+
+/// Derive a tag's slug from its name
+///
+/// The name is lowercased and runs of characters other than ASCII letters
+/// and digits are turned into `-`, so "Time Travel!" and "time-travel" are
+/// the same tag.
+pub fn tag_slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Trim and check a tag name
+///
+/// # Returns
+/// The trimmed name and its slug, or a message describing the problem
+pub fn validate_tag_name(name: &str) -> Result<(String, String), String> {
+    let name = name.trim();
+    if name.chars().count() > MAX_TAG_NAME_LEN {
+        return Err(format!(
+            "Tag name must be at most {} characters",
+            MAX_TAG_NAME_LEN
+        ));
+    }
+    let slug = tag_slug(name);
+    if slug.is_empty() {
+        return Err("Tag name must contain letters or digits".to_string());
+    }
+    Ok((name.to_string(), slug))
+}
+
+/// Normalize a `tag` filter parameter into a tag slug; empty means "any"
+pub fn tag_filter(value: Option<&str>) -> Option<String> {
+    value.map(tag_slug).filter(|slug| !slug.is_empty())
+}
+
+/// Keep only the items whose anime carries a tag
+///
+/// Filters results scraped from the source site, which knows nothing of
+/// tags. Nothing is filtered without a tag.
+pub(crate) async fn retain_tagged<T>(
+    db: &Database,
+    tag: Option<&str>,
+    items: &mut Vec<T>,
+    slug: fn(&T) -> &str,
+) -> RepositoryResult<()> {
+    let Some(tag) = tag else {
+        return Ok(());
+    };
+    let slugs: Vec<String> = items.iter().map(|item| slug(item).to_string()).collect();
+    let tagged = db.read(|pool| get_tagged_slugs(pool, tag, &slugs)).await?;
+    items.retain(|item| tagged.contains(slug(item)));
+    Ok(())
+}
+
+/// Query parameters for the tag list
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct TagsQuery {
+    /// Only curated (true) or uncurated (false) tags
+    pub curated: Option<bool>,
+    /// Maximum number of tags (default: 100, max: 500)
+    pub limit: Option<i64>,
+}
+
+/// GET /api/tags - Tags ordered by popularity
+///
+/// Tags on the most anime come first, then the most applied ones.
+///
+/// # Responses
+/// - 200: Returns the tags with their counts
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/tags",
+    tag = "tags",
+    params(TagsQuery),
+    responses(
+        (status = 200, description = "Tags retrieved", body = ApiResponse<Vec<Tag>>),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_tags_handler(
+    data: web::Data<AppState>,
+    query: web::Query<TagsQuery>,
+) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TAGS_LIMIT)
+        .clamp(1, MAX_TAGS_LIMIT);
+
+    match data
+        .db
+        .read(|pool| get_tags(pool, query.curated, limit))
+        .await
+    {
+        Ok(tags) => HttpResponse::Ok().json(ApiResponse::new(tags)),
+        Err(e) => {
+            error!("Failed to get tags: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get tags",
+            ))
+        }
+    }
+}
+
+/// Respond with the anime's tags as seen by a user
+async fn anime_tags_response(data: &AppState, user_id: Option<i32>, slug: &str) -> HttpResponse {
+    match get_anime_tags(data.db.pool(), slug, user_id).await {
+        Ok(tags) => HttpResponse::Ok().json(ApiResponse::new(tags)),
+        Err(e) => {
+            error!("Failed to get tags of {}: {}", slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get tags",
+            ))
+        }
+    }
+}
+
+/// GET /api/anime/:slug/tags - Tags on an anime
+///
+/// Tags applied by the most users come first. `taggedByMe` is set on
+/// authenticated requests.
+///
+/// # Responses
+/// - 200: Returns the anime's tags
+/// - 400: Invalid slug
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/anime/{slug}/tags",
+    tag = "tags",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    responses(
+        (status = 200, description = "Anime tags retrieved", body = ApiResponse<Vec<AnimeTag>>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_anime_tags_handler(
+    data: web::Data<AppState>,
+    auth: Option<Auth>,
+    slug: Slug,
+) -> impl Responder {
+    anime_tags_response(&data, auth.map(|a| a.user_id), &slug).await
+}
+
+/// POST /api/anime/:slug/tags - Tag an anime
+///
+/// Requires authentication via JWT token in Authorization header. Only
+/// scraped anime can be tagged. A name matching no tag creates an uncurated
+/// one, at most 20 a day per user; applying a tag twice is a no-op. Users
+/// muted by moderators can't tag until the mute ends.
+///
+/// # Request Body
+/// - name: Tag name (at most 50 characters)
+///
+/// # Responses
+/// - 200: Returns the anime's tags
+/// - 400: Invalid slug or tag name
+/// - 401: Not authenticated
+/// - 403: User is muted
+/// - 404: Anime not found
+/// - 429: User created too many tags today
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/anime/{slug}/tags",
+    tag = "tags",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier")
+    ),
+    request_body = TagAnimeRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Anime tagged", body = ApiResponse<Vec<AnimeTag>>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 403, description = "User is muted", body = ApiError),
+        (status = 404, description = "Anime not found", body = ApiError),
+        (status = 429, description = "Too many new tags today", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn tag_anime_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    slug: Slug,
+    body: web::Json<TagAnimeRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let (name, tag) = match validate_tag_name(&body.name) {
+        Ok(validated) => validated,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match get_user_muted_until(pool, auth.user_id).await {
+        Ok(Some(until)) => {
+            return HttpResponse::Forbidden().json(
+                ApiError::new(ErrorCode::Forbidden, "You are muted and can't tag anime")
+                    .with_details(serde_json::json!({ "mutedUntil": until.to_rfc3339() })),
+            );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to check mute of user {}: {}", auth.user_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to tag anime",
+            ));
+        }
+    }
+
+    match tag_anime(pool, auth.user_id, &slug, &tag, &name, MAX_NEW_TAGS_PER_DAY).await {
+        Ok(TagOutcome::NewTagLimitReached) => HttpResponse::TooManyRequests().json(ApiError::new(
+            ErrorCode::TooManyRequests,
+            format!(
+                "You can create at most {} new tags a day",
+                MAX_NEW_TAGS_PER_DAY
+            ),
+        )),
+        Ok(outcome) => {
+            if outcome == TagOutcome::Tagged {
+                info!("User {} tagged {} with {}", auth.user_id, &*slug, tag);
+            }
+            anime_tags_response(&data, Some(auth.user_id), &slug).await
+        }
+        Err(RepositoryError::NotFound(_)) => HttpResponse::NotFound()
+            .json(ApiError::new(ErrorCode::AnimeNotFound, "Anime not found")),
+        Err(e) => {
+            error!("Failed to tag {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to tag anime",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/anime/:slug/tags/:tag - Take back a tag
+///
+/// Requires authentication via JWT token in Authorization header. Only the
+/// user's own tagging is removed; taking back a tag the user didn't apply is
+/// a no-op.
+///
+/// # Responses
+/// - 200: Returns the anime's tags
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/anime/{slug}/tags/{tag}",
+    tag = "tags",
+    params(
+        ("slug" = String, Path, description = "Anime slug identifier"),
+        ("tag" = String, Path, description = "Tag slug")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Tag removed", body = ApiResponse<Vec<AnimeTag>>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn untag_anime_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<(String, String)>,
+    slug: Slug,
+) -> impl Responder {
+    let (_, tag) = path.into_inner();
+
+    match untag_anime(data.db.pool(), auth.user_id, &slug, &tag).await {
+        Ok(untagged) => {
+            if untagged {
+                info!("User {} untagged {} from {}", auth.user_id, tag, &*slug);
+            }
+            anime_tags_response(&data, Some(auth.user_id), &slug).await
+        }
+        Err(e) => {
+            error!("Failed to untag {}: {}", &*slug, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to untag anime",
+            ))
+        }
+    }
+}
+
+/// Configure tag routes
+///
+/// Must be configured before `configure_routes` so the `/api` scope doesn't
+/// shadow them.
+pub fn configure_tag_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/api/tags").route(web::get().to(get_tags_handler)))
+        .service(
+            web::scope("/api/anime/{slug}/tags")
+                .route("", web::get().to(get_anime_tags_handler))
+                .route("", web::post().to(tag_anime_handler))
+                .route("/{tag}", web::delete().to(untag_anime_handler)),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_slug() {
+        assert_eq!(tag_slug("Time Travel!"), "time-travel");
+        assert_eq!(tag_slug("  tournament -- arc "), "tournament-arc");
+        assert_eq!(tag_slug("Isekai"), "isekai");
+        assert_eq!(tag_slug("!!!"), "");
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        assert_eq!(
+            tag_filter(Some("Time Travel")),
+            Some("time-travel".to_string())
+        );
+        assert_eq!(tag_filter(Some(" ")), None);
+        assert_eq!(tag_filter(None), None);
+    }
+
+    #[test]
+    fn test_validate_tag_name() {
+        assert_eq!(
+            validate_tag_name("  Time Travel "),
+            Ok(("Time Travel".to_string(), "time-travel".to_string()))
+        );
+        assert!(validate_tag_name("").is_err());
+        assert!(validate_tag_name("???").is_err());
+        assert!(validate_tag_name(&"a".repeat(MAX_TAG_NAME_LEN + 1)).is_err());
+    }
+}
@@ -10,19 +10,58 @@
 //! - POST /api/history - Record watched episode
 //! - GET /api/history - Get watch history
 //! - DELETE /api/history/:slug - Remove from history
+//! - GET /api/user/continue-watching - Next episode of each anime being watched
+//! - GET /api/user/watched/:slug - Get watched episodes of an anime
+//! - POST /api/user/watched/:slug - Mark episodes watched or unwatched
+//! - GET /api/user/preferences - Get notification and playback preferences
+//! - PATCH /api/user/preferences - Update preferences
+//! - GET /api/user/sessions - List active device sessions
+//! - DELETE /api/user/sessions/:id - Revoke a device session
+//! - POST /api/user/saved-searches - Save a search to be notified about
+//! - GET /api/user/saved-searches - List saved searches
+//! - DELETE /api/user/saved-searches/:id - Delete a saved search
+//! - POST /api/user/avatar - Upload a profile picture
+//! - POST /api/user/deactivate - Deactivate the account until reactivated by email
+//! - GET /api/user/data-export - Request an archive of the user's data
+//! - GET /api/user/data-export/:id/download - Download an archive via signed URL
+//! - DELETE /api/user/data - Erase the account and all its data
 
+use std::collections::HashMap;
+
+use actix_multipart::form::bytes::Bytes;
+use actix_multipart::form::MultipartForm;
+use actix_web::http::header;
 use actix_web::{web, HttpResponse, Responder};
+use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
-use tracing::{error, info};
-use utoipa::ToSchema;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::auth::Auth;
+use crate::auth::signing::SignatureError;
+use crate::auth::{create_logout_cookie, Auth, Viewer};
 use crate::db::{
-    add_favorite, add_subscription, add_to_history, get_favorites, get_history, get_subscriptions,
-    remove_favorite, remove_from_history, remove_subscription, RepositoryError,
+    add_favorite, add_subscription, add_to_history, bump_token_version, count_saved_searches,
+    create_data_export, create_saved_search, deactivate_user, delete_saved_search, erase_user_data,
+    fail_data_export, find_user_by_id, get_active_sessions, get_continue_watching, get_data_export,
+    get_favorites, get_history, get_latest_data_export, get_saved_searches, get_subscriptions,
+    get_user_preferences, get_watch_progress, mark_episodes_watched, normalize_search_query,
+    remove_favorite, remove_from_history, remove_subscription, revoke_session,
+    revoke_user_sessions, update_user_avatar, update_user_preferences, EpisodeSelection,
+    RepositoryError,
+};
+use crate::email::Language;
+use crate::jobs::data_export::enqueue_export_user_data;
+use crate::middleware::limits::{is_valid_slug, Slug};
+use crate::models::{
+    ApiError, ApiResponse, ContinueWatching, DataErasure, DataExport, ErrorCode, SavedSearch,
+    Session, UpdatePreferencesRequest, User, UserFavorite, UserHistory, UserPreferences,
+    UserSubscription, WatchProgress, DATA_EXPORT_PENDING, DATA_EXPORT_READY, DIGEST_FREQUENCIES,
+};
+use crate::routes::images::{
+    is_stored_avatar, process_avatar, sniff_image_type, with_avatar_url, AVATAR_CONTENT_TYPES,
 };
-use crate::models::{ApiError, ApiResponse, UserFavorite, UserHistory, UserSubscription};
 use crate::routes::AppState;
+use crate::storage::keys;
 
 // ============================================================================
 // Request Bodies
@@ -68,9 +107,56 @@ pub struct AddHistoryRequest {
     pub thumbnail: String,
 }
 
+/// Request body for saving a search
+///
+/// At least one of query, type, and status is required.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSavedSearchRequest {
+    /// Display name (defaults to the filters, e.g. "isekai TV")
+    pub name: Option<String>,
+    /// Text matched against titles and genres
+    pub query: Option<String>,
+    /// Anime type filter (TV, OVA, Movie, etc.)
+    #[serde(rename = "type")]
+    pub anime_type: Option<String>,
+    /// Status filter (Ongoing, Completed, etc.)
+    pub status: Option<String>,
+}
+
+/// Request body for marking episodes watched
+///
+/// Exactly one of all, upTo, and episodes is required.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkWatchedRequest {
+    /// Mark every released episode (e.g., a whole season)
+    pub all: Option<bool>,
+    /// Mark episodes numbered up to and including this one (e.g., "12")
+    pub up_to: Option<String>,
+    /// Mark episodes with these slugs
+    pub episodes: Option<Vec<String>>,
+    /// Mark watched (default) or unwatched
+    pub watched: Option<bool>,
+}
+
+/// Query parameters for continue watching
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct ContinueWatchingQuery {
+    /// Maximum number of anime to return (default: 20, max: 100)
+    pub limit: Option<i64>,
+}
+
+/// Maximum number of saved searches per user
+pub const MAX_SAVED_SEARCHES: i64 = 20;
+
+/// Maximum number of episode slugs in one mark-watched request
+pub const MAX_MARKED_EPISODES: usize = 2000;
+
 /// POST /api/favorites - Add an anime to user's favorites
 ///
-/// Requires authentication via JWT token in Authorization header.
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
 ///
 /// # Request Body
 /// - animeSlug: Unique identifier for the anime (required)
@@ -89,7 +175,8 @@ pub struct AddHistoryRequest {
     tag = "user",
     request_body = AddFavoriteRequest,
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("device_token" = [])
     ),
     responses(
         (status = 200, description = "Favorite added successfully", body = ApiResponse<UserFavorite>),
@@ -101,23 +188,35 @@ pub struct AddHistoryRequest {
 )]
 pub async fn add_favorite_handler(
     data: web::Data<AppState>,
-    auth: Auth,
+    viewer: Viewer,
     body: web::Json<AddFavoriteRequest>,
 ) -> impl Responder {
     let pool = data.db.pool();
 
     // Validate required fields
     if body.anime_slug.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Anime slug is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Anime slug is required",
+        ));
+    }
+    if !is_valid_slug(&body.anime_slug) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid anime slug",
+        ));
     }
 
     if body.anime_title.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Anime title is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Anime title is required",
+        ));
     }
 
     match add_favorite(
         pool,
-        auth.user_id,
+        viewer.user_id,
         &body.anime_slug,
         &body.anime_title,
         &body.thumbnail,
@@ -125,20 +224,29 @@ pub async fn add_favorite_handler(
     .await
     {
         Ok(favorite) => {
-            info!("User {} added favorite: {}", auth.user_id, body.anime_slug);
+            info!(
+                "User {} added favorite: {}",
+                viewer.user_id, body.anime_slug
+            );
             HttpResponse::Ok().json(ApiResponse::new(favorite))
         }
-        Err(RepositoryError::Conflict(msg)) => HttpResponse::Conflict().json(ApiError::new(msg)),
+        Err(RepositoryError::Conflict(msg)) => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg))
+        }
         Err(e) => {
             error!("Failed to add favorite: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to add favorite"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to add favorite",
+            ))
         }
     }
 }
 
 /// GET /api/favorites - Get user's favorite anime list
 ///
-/// Requires authentication via JWT token in Authorization header.
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
 ///
 /// # Responses
 /// - 200: Returns list of favorites
@@ -149,7 +257,8 @@ pub async fn add_favorite_handler(
     path = "/api/favorites",
     tag = "user",
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("device_token" = [])
     ),
     responses(
         (status = 200, description = "Favorites retrieved successfully", body = ApiResponse<Vec<UserFavorite>>),
@@ -157,27 +266,32 @@ pub async fn add_favorite_handler(
         (status = 500, description = "Internal server error", body = ApiError)
     )
 )]
-pub async fn get_favorites_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+pub async fn get_favorites_handler(data: web::Data<AppState>, viewer: Viewer) -> impl Responder {
     let pool = data.db.pool();
 
-    match get_favorites(pool, auth.user_id).await {
+    match get_favorites(pool, viewer.user_id).await {
         Ok(favorites) => HttpResponse::Ok().json(ApiResponse::new(favorites)),
         Err(e) => {
             error!("Failed to get favorites: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to get favorites"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get favorites",
+            ))
         }
     }
 }
 
 /// DELETE /api/favorites/{slug} - Remove an anime from user's favorites
 ///
-/// Requires authentication via JWT token in Authorization header.
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
 ///
 /// # Path Parameters
 /// - slug: Anime slug to remove from favorites
 ///
 /// # Responses
 /// - 200: Favorite removed successfully
+/// - 400: Invalid slug
 /// - 401: Not authenticated
 /// - 404: Favorite not found
 /// - 500: Internal server error
@@ -189,10 +303,12 @@ pub async fn get_favorites_handler(data: web::Data<AppState>, auth: Auth) -> imp
         ("slug" = String, Path, description = "Anime slug to remove from favorites")
     ),
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("device_token" = [])
     ),
     responses(
         (status = 200, description = "Favorite removed successfully", body = ApiResponse<String>),
+        (status = 400, description = "Invalid slug", body = ApiError),
         (status = 401, description = "Not authenticated", body = ApiError),
         (status = 404, description = "Favorite not found", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
@@ -200,23 +316,28 @@ pub async fn get_favorites_handler(data: web::Data<AppState>, auth: Auth) -> imp
 )]
 pub async fn remove_favorite_handler(
     data: web::Data<AppState>,
-    auth: Auth,
-    path: web::Path<String>,
+    viewer: Viewer,
+    path: Slug,
 ) -> impl Responder {
     let pool = data.db.pool();
     let anime_slug = path.into_inner();
 
-    match remove_favorite(pool, auth.user_id, &anime_slug).await {
+    match remove_favorite(pool, viewer.user_id, &anime_slug).await {
         Ok(true) => {
-            info!("User {} removed favorite: {}", auth.user_id, anime_slug);
+            info!("User {} removed favorite: {}", viewer.user_id, anime_slug);
             HttpResponse::Ok().json(ApiResponse::new(
                 "Favorite removed successfully".to_string(),
             ))
         }
-        Ok(false) => HttpResponse::NotFound().json(ApiError::new("Favorite not found")),
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Favorite not found"))
+        }
         Err(e) => {
             error!("Failed to remove favorite: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to remove favorite"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to remove favorite",
+            ))
         }
     }
 }
@@ -261,11 +382,23 @@ pub async fn add_subscription_handler(
 
     // Validate required fields
     if body.anime_slug.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Anime slug is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Anime slug is required",
+        ));
+    }
+    if !is_valid_slug(&body.anime_slug) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid anime slug",
+        ));
     }
 
     if body.anime_title.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Anime title is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Anime title is required",
+        ));
     }
 
     match add_subscription(
@@ -281,10 +414,15 @@ pub async fn add_subscription_handler(
             info!("User {} subscribed to: {}", auth.user_id, body.anime_slug);
             HttpResponse::Ok().json(ApiResponse::new(subscription))
         }
-        Err(RepositoryError::Conflict(msg)) => HttpResponse::Conflict().json(ApiError::new(msg)),
+        Err(RepositoryError::Conflict(msg)) => {
+            HttpResponse::Conflict().json(ApiError::new(ErrorCode::Conflict, msg))
+        }
         Err(e) => {
             error!("Failed to add subscription: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to add subscription"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to add subscription",
+            ))
         }
     }
 }
@@ -317,7 +455,10 @@ pub async fn get_subscriptions_handler(data: web::Data<AppState>, auth: Auth) ->
         Ok(subscriptions) => HttpResponse::Ok().json(ApiResponse::new(subscriptions)),
         Err(e) => {
             error!("Failed to get subscriptions: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to get subscriptions"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get subscriptions",
+            ))
         }
     }
 }
@@ -331,6 +472,7 @@ pub async fn get_subscriptions_handler(data: web::Data<AppState>, auth: Auth) ->
 ///
 /// # Responses
 /// - 200: Unsubscribed successfully
+/// - 400: Invalid slug
 /// - 401: Not authenticated
 /// - 404: Subscription not found
 /// - 500: Internal server error
@@ -346,6 +488,7 @@ pub async fn get_subscriptions_handler(data: web::Data<AppState>, auth: Auth) ->
     ),
     responses(
         (status = 200, description = "Unsubscribed successfully", body = ApiResponse<String>),
+        (status = 400, description = "Invalid slug", body = ApiError),
         (status = 401, description = "Not authenticated", body = ApiError),
         (status = 404, description = "Subscription not found", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
@@ -354,7 +497,7 @@ pub async fn get_subscriptions_handler(data: web::Data<AppState>, auth: Auth) ->
 pub async fn remove_subscription_handler(
     data: web::Data<AppState>,
     auth: Auth,
-    path: web::Path<String>,
+    path: Slug,
 ) -> impl Responder {
     let pool = data.db.pool();
     let anime_slug = path.into_inner();
@@ -364,17 +507,22 @@ pub async fn remove_subscription_handler(
             info!("User {} unsubscribed from: {}", auth.user_id, anime_slug);
             HttpResponse::Ok().json(ApiResponse::new("Unsubscribed successfully".to_string()))
         }
-        Ok(false) => HttpResponse::NotFound().json(ApiError::new("Subscription not found")),
+        Ok(false) => HttpResponse::NotFound()
+            .json(ApiError::new(ErrorCode::NotFound, "Subscription not found")),
         Err(e) => {
             error!("Failed to remove subscription: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to remove subscription"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to remove subscription",
+            ))
         }
     }
 }
 
 /// POST /api/history - Record a watched episode
 ///
-/// Requires authentication via JWT token in Authorization header.
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
 /// If the episode already exists in history, updates the watched_at timestamp.
 ///
 /// # Request Body
@@ -395,7 +543,8 @@ pub async fn remove_subscription_handler(
     tag = "user",
     request_body = AddHistoryRequest,
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("device_token" = [])
     ),
     responses(
         (status = 200, description = "History entry added successfully", body = ApiResponse<UserHistory>),
@@ -406,23 +555,41 @@ pub async fn remove_subscription_handler(
 )]
 pub async fn add_history_handler(
     data: web::Data<AppState>,
-    auth: Auth,
+    viewer: Viewer,
     body: web::Json<AddHistoryRequest>,
 ) -> impl Responder {
     let pool = data.db.pool();
 
     // Validate required fields
     if body.episode_slug.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Episode slug is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Episode slug is required",
+        ));
+    }
+    if !is_valid_slug(&body.episode_slug) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid episode slug",
+        ));
     }
 
     if body.anime_slug.is_empty() {
-        return HttpResponse::BadRequest().json(ApiError::new("Anime slug is required"));
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Anime slug is required",
+        ));
+    }
+    if !is_valid_slug(&body.anime_slug) {
+        return HttpResponse::BadRequest().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Invalid anime slug",
+        ));
     }
 
     match add_to_history(
         pool,
-        auth.user_id,
+        viewer.user_id,
         &body.episode_slug,
         &body.anime_slug,
         &body.episode_title,
@@ -434,21 +601,24 @@ pub async fn add_history_handler(
         Ok(history) => {
             info!(
                 "User {} recorded history: {}",
-                auth.user_id, body.episode_slug
+                viewer.user_id, body.episode_slug
             );
             HttpResponse::Ok().json(ApiResponse::new(history))
         }
         Err(e) => {
             error!("Failed to add history: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to record watch history"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to record watch history",
+            ))
         }
     }
 }
 
 /// GET /api/history - Get user's watch history
 ///
-/// Requires authentication via JWT token in Authorization header.
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
 /// Returns history sorted by most recently watched first.
 ///
 /// # Responses
@@ -460,7 +630,8 @@ pub async fn add_history_handler(
     path = "/api/history",
     tag = "user",
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("device_token" = [])
     ),
     responses(
         (status = 200, description = "History retrieved successfully", body = ApiResponse<Vec<UserHistory>>),
@@ -468,27 +639,32 @@ pub async fn add_history_handler(
         (status = 500, description = "Internal server error", body = ApiError)
     )
 )]
-pub async fn get_history_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+pub async fn get_history_handler(data: web::Data<AppState>, viewer: Viewer) -> impl Responder {
     let pool = data.db.pool();
 
-    match get_history(pool, auth.user_id).await {
+    match get_history(pool, viewer.user_id).await {
         Ok(history) => HttpResponse::Ok().json(ApiResponse::new(history)),
         Err(e) => {
             error!("Failed to get history: {}", e);
-            HttpResponse::InternalServerError().json(ApiError::new("Failed to get watch history"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get watch history",
+            ))
         }
     }
 }
 
 /// DELETE /api/history/{slug} - Remove an episode from user's watch history
 ///
-/// Requires authentication via JWT token in Authorization header.
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
 ///
 /// # Path Parameters
 /// - slug: Episode slug to remove from history
 ///
 /// # Responses
 /// - 200: History entry removed successfully
+/// - 400: Invalid slug
 /// - 401: Not authenticated
 /// - 404: History entry not found
 /// - 500: Internal server error
@@ -500,10 +676,12 @@ pub async fn get_history_handler(data: web::Data<AppState>, auth: Auth) -> impl
         ("slug" = String, Path, description = "Episode slug to remove from history")
     ),
     security(
-        ("bearer_auth" = [])
+        ("bearer_auth" = []),
+        ("device_token" = [])
     ),
     responses(
         (status = 200, description = "History entry removed successfully", body = ApiResponse<String>),
+        (status = 400, description = "Invalid slug", body = ApiError),
         (status = 401, description = "Not authenticated", body = ApiError),
         (status = 404, description = "History entry not found", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
@@ -511,49 +689,1395 @@ pub async fn get_history_handler(data: web::Data<AppState>, auth: Auth) -> impl
 )]
 pub async fn remove_history_handler(
     data: web::Data<AppState>,
-    auth: Auth,
-    path: web::Path<String>,
+    viewer: Viewer,
+    path: Slug,
 ) -> impl Responder {
     let pool = data.db.pool();
     let episode_slug = path.into_inner();
 
-    match remove_from_history(pool, auth.user_id, &episode_slug).await {
+    match remove_from_history(pool, viewer.user_id, &episode_slug).await {
         Ok(true) => {
-            info!("User {} removed history: {}", auth.user_id, episode_slug);
+            info!("User {} removed history: {}", viewer.user_id, episode_slug);
             HttpResponse::Ok().json(ApiResponse::new(
                 "History entry removed successfully".to_string(),
             ))
         }
-        Ok(false) => HttpResponse::NotFound().json(ApiError::new("History entry not found")),
+        Ok(false) => HttpResponse::NotFound().json(ApiError::new(
+            ErrorCode::NotFound,
+            "History entry not found",
+        )),
         Err(e) => {
             error!("Failed to remove history: {}", e);
-            HttpResponse::InternalServerError()
-                .json(ApiError::new("Failed to remove history entry"))
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to remove history entry",
+            ))
         }
     }
 }
 
-/// Configure user routes (favorites, subscriptions, history)
-pub fn configure_user_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(
-        web::scope("/api")
-            // Favorites
-            .route("/favorites", web::post().to(add_favorite_handler))
-            .route("/favorites", web::get().to(get_favorites_handler))
-            .route(
-                "/favorites/{slug}",
-                web::delete().to(remove_favorite_handler),
-            )
-            // Subscriptions
-            .route("/subscriptions", web::post().to(add_subscription_handler))
-            .route("/subscriptions", web::get().to(get_subscriptions_handler))
-            .route(
-                "/subscriptions/{slug}",
-                web::delete().to(remove_subscription_handler),
-            )
-            // History
-            .route("/history", web::post().to(add_history_handler))
-            .route("/history", web::get().to(get_history_handler))
-            .route("/history/{slug}", web::delete().to(remove_history_handler)),
+/// GET /api/user/continue-watching - Next episode of each anime being watched
+///
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header. For every
+/// anime in the user's history, returns the first unwatched episode after the
+/// most recently watched one. Anime the user is caught up on are omitted.
+/// Entries whose next episode came out after the last watch are flagged with
+/// `newEpisode` and sorted by that release.
+///
+/// Query parameters:
+/// - limit: Maximum number of anime (default: 20, max: 100)
+///
+/// # Responses
+/// - 200: Returns entries, most recent activity first
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/continue-watching",
+    tag = "user",
+    params(ContinueWatchingQuery),
+    security(
+        ("bearer_auth" = []),
+        ("device_token" = [])
+    ),
+    responses(
+        (status = 200, description = "Continue watching retrieved successfully", body = ApiResponse<Vec<ContinueWatching>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn continue_watching_handler(
+    data: web::Data<AppState>,
+    viewer: Viewer,
+    query: web::Query<ContinueWatchingQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    match get_continue_watching(data.db.pool(), viewer.user_id, limit).await {
+        Ok(entries) => HttpResponse::Ok().json(ApiResponse::new(entries)),
+        Err(e) => {
+            error!("Failed to get continue watching: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get continue watching",
+            ))
+        }
+    }
+}
+
+/// Validate a mark-watched request
+///
+/// # Returns
+/// The episodes to mark and whether to mark them watched, or a message
+/// describing the invalid field
+fn validate_mark_watched(body: MarkWatchedRequest) -> Result<(EpisodeSelection, bool), String> {
+    let watched = body.watched.unwrap_or(true);
+    let selection = match (body.all, body.up_to, body.episodes) {
+        (Some(true), None, None) => EpisodeSelection::All,
+        (None, Some(up_to), None) => match up_to.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() && number >= 0.0 => EpisodeSelection::UpTo(number),
+            _ => return Err("upTo must be an episode number".to_string()),
+        },
+        (None, None, Some(episodes)) => {
+            if episodes.is_empty() {
+                return Err("episodes must not be empty".to_string());
+            }
+            if episodes.len() > MAX_MARKED_EPISODES {
+                return Err(format!(
+                    "At most {} episodes can be marked at once",
+                    MAX_MARKED_EPISODES
+                ));
+            }
+            EpisodeSelection::Episodes(episodes)
+        }
+        _ => return Err("Exactly one of all, upTo, or episodes is required".to_string()),
+    };
+    Ok((selection, watched))
+}
+
+/// GET /api/user/watched/{slug} - Get the user's watched episodes of an anime
+///
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header.
+///
+/// # Path Parameters
+/// - slug: Anime slug
+///
+/// # Responses
+/// - 200: Returns watch progress
+/// - 400: Invalid slug
+/// - 401: Not authenticated
+/// - 404: Anime not found
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/watched/{slug}",
+    tag = "user",
+    params(
+        ("slug" = String, Path, description = "Anime slug")
+    ),
+    security(
+        ("bearer_auth" = []),
+        ("device_token" = [])
+    ),
+    responses(
+        (status = 200, description = "Watch progress retrieved successfully", body = ApiResponse<WatchProgress>),
+        (status = 400, description = "Invalid slug", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Anime not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_watched_handler(
+    data: web::Data<AppState>,
+    viewer: Viewer,
+    path: Slug,
+) -> impl Responder {
+    let anime_slug = path.into_inner();
+
+    match get_watch_progress(data.db.pool(), viewer.user_id, &anime_slug).await {
+        Ok(Some(progress)) => HttpResponse::Ok().json(ApiResponse::new(progress)),
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Anime not found"))
+        }
+        Err(e) => {
+            error!("Failed to get watch progress: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get watch progress",
+            ))
+        }
+    }
+}
+
+/// POST /api/user/watched/{slug} - Mark episodes of an anime watched or unwatched
+///
+/// Requires authentication via JWT token in Authorization header, or a
+/// device token in the X-Device-Token header. Episodes
+/// that haven't been crawled yet can't be marked.
+///
+/// # Path Parameters
+/// - slug: Anime slug
+///
+/// # Request Body
+/// - all: Mark every released episode (optional)
+/// - upTo: Mark episodes up to and including this number (optional)
+/// - episodes: Episode slugs to mark (optional)
+/// - watched: Mark watched (default: true) or unwatched (optional)
+///
+/// # Responses
+/// - 200: Returns the updated watch progress
+/// - 400: Invalid request body
+/// - 401: Not authenticated
+/// - 404: Anime not found
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/user/watched/{slug}",
+    tag = "user",
+    params(
+        ("slug" = String, Path, description = "Anime slug")
+    ),
+    request_body = MarkWatchedRequest,
+    security(
+        ("bearer_auth" = []),
+        ("device_token" = [])
+    ),
+    responses(
+        (status = 200, description = "Episodes marked", body = ApiResponse<WatchProgress>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Anime not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn mark_watched_handler(
+    data: web::Data<AppState>,
+    viewer: Viewer,
+    path: Slug,
+    body: web::Json<MarkWatchedRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+    let anime_slug = path.into_inner();
+
+    let (selection, watched) = match validate_mark_watched(body.into_inner()) {
+        Ok(valid) => valid,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    let result = match get_watch_progress(pool, viewer.user_id, &anime_slug).await {
+        Ok(Some(_)) => {
+            mark_episodes_watched(pool, viewer.user_id, &anime_slug, &selection, watched).await
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(ApiError::new(ErrorCode::NotFound, "Anime not found"))
+        }
+        Err(e) => Err(e),
+    };
+
+    let marked = match result {
+        Ok(marked) => marked,
+        Err(e) => {
+            error!("Failed to mark episodes watched: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to mark episodes",
+            ));
+        }
+    };
+    info!(
+        "User {} marked {} episodes of {} (watched: {})",
+        viewer.user_id, marked, anime_slug, watched
     );
+
+    match get_watch_progress(pool, viewer.user_id, &anime_slug).await {
+        Ok(Some(progress)) => HttpResponse::Ok().json(ApiResponse::new(progress)),
+        Ok(None) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Anime not found"))
+        }
+        Err(e) => {
+            error!("Failed to get watch progress: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get watch progress",
+            ))
+        }
+    }
+}
+
+/// Validate a preferences update
+///
+/// # Returns
+/// The update with language codes normalized, or a message describing the invalid field
+fn validate_preferences_update(
+    mut update: UpdatePreferencesRequest,
+) -> Result<UpdatePreferencesRequest, String> {
+    if let Some(frequency) = &update.digest_frequency {
+        if !DIGEST_FREQUENCIES.contains(&frequency.as_str()) {
+            return Err(format!(
+                "Invalid digest frequency, expected one of: {}",
+                DIGEST_FREQUENCIES.join(", ")
+            ));
+        }
+    }
+
+    if let Some(quality) = &update.preferred_quality {
+        let valid = quality.is_empty()
+            || quality
+                .strip_suffix(['p', 'P'])
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+        if !valid {
+            return Err("Invalid video quality, expected a value like \"720p\"".to_string());
+        }
+        update.preferred_quality = Some(quality.to_ascii_lowercase());
+    }
+
+    if let Some(language) = &update.language {
+        let parsed = Language::from_code(language);
+        if !parsed.code().eq_ignore_ascii_case(language) {
+            return Err("Invalid language, expected \"en\" or \"id\"".to_string());
+        }
+        update.language = Some(parsed.code().to_string());
+    }
+
+    Ok(update)
+}
+
+/// GET /api/user/preferences - Get user's notification and playback preferences
+///
+/// Requires authentication via JWT token in Authorization header. Returns the
+/// defaults if the user has never saved preferences.
+///
+/// # Responses
+/// - 200: Returns preferences
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/preferences",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Preferences retrieved successfully", body = ApiResponse<UserPreferences>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_preferences_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    match get_user_preferences(data.db.pool(), auth.user_id).await {
+        Ok(preferences) => HttpResponse::Ok().json(ApiResponse::new(preferences)),
+        Err(e) => {
+            error!("Failed to get preferences: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get preferences",
+            ))
+        }
+    }
+}
+
+/// PATCH /api/user/preferences - Update user's preferences
+///
+/// Requires authentication via JWT token in Authorization header. Only the
+/// fields present in the body are changed.
+///
+/// # Request Body
+/// - emailNotifications: Send new-episode emails (optional)
+/// - digestFrequency: instant, daily, or weekly (optional)
+/// - webhooksEnabled: Deliver notifications to webhooks (optional)
+/// - preferredQuality: Video quality like "720p", empty string to clear (optional)
+/// - language: "en" or "id" (optional)
+/// - communityOptOut: Leave watching and favorites out of community leaderboards (optional)
+///
+/// # Responses
+/// - 200: Returns the updated preferences
+/// - 400: Invalid field value
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    patch,
+    path = "/api/user/preferences",
+    tag = "user",
+    request_body = UpdatePreferencesRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Preferences updated successfully", body = ApiResponse<UserPreferences>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn update_preferences_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    body: web::Json<UpdatePreferencesRequest>,
+) -> impl Responder {
+    let update = match validate_preferences_update(body.into_inner()) {
+        Ok(update) => update,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match update_user_preferences(data.db.pool(), auth.user_id, &update).await {
+        Ok(preferences) => {
+            info!("User {} updated preferences", auth.user_id);
+            HttpResponse::Ok().json(ApiResponse::new(preferences))
+        }
+        Err(e) => {
+            error!("Failed to update preferences: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to update preferences",
+            ))
+        }
+    }
+}
+
+/// GET /api/user/sessions - List the user's active device sessions
+///
+/// Requires authentication via JWT token in Authorization header. The session
+/// the request was made with is flagged as `current`.
+///
+/// # Responses
+/// - 200: Returns active sessions, most recently used first
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/sessions",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Sessions retrieved successfully", body = ApiResponse<Vec<Session>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn list_sessions_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    match get_active_sessions(data.db.pool(), auth.user_id).await {
+        Ok(sessions) => {
+            let sessions: Vec<Session> = sessions
+                .into_iter()
+                .map(|session| Session {
+                    current: Some(session.id) == auth.session_id,
+                    ..session
+                })
+                .collect();
+            HttpResponse::Ok().json(ApiResponse::new(sessions))
+        }
+        Err(e) => {
+            error!("Failed to get sessions: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get sessions",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/user/sessions/:id - Revoke a device session
+///
+/// Requires authentication via JWT token in Authorization header. Tokens
+/// issued for the session are rejected from then on.
+///
+/// # Responses
+/// - 200: Session revoked
+/// - 401: Not authenticated
+/// - 404: Session not found or already revoked
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/user/sessions/{id}",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "Session ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Session revoked", body = ApiResponse<String>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Session not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn revoke_session_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let session_id = path.into_inner();
+
+    match revoke_session(data.db.pool(), auth.user_id, session_id).await {
+        Ok(true) => {
+            info!("User {} revoked session {}", auth.user_id, session_id);
+            HttpResponse::Ok().json(ApiResponse::new("Session revoked".to_string()))
+        }
+        Ok(false) => {
+            HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Session not found"))
+        }
+        Err(e) => {
+            error!("Failed to revoke session {}: {}", session_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to revoke session",
+            ))
+        }
+    }
+}
+
+/// A validated saved search request
+#[derive(Debug, Clone, PartialEq)]
+struct NewSavedSearch {
+    name: String,
+    query: Option<String>,
+    anime_type: Option<String>,
+    status: Option<String>,
+}
+
+/// Validate a saved search request
+///
+/// Trims the filters, drops empty ones, and normalizes the query the way
+/// search queries are normalized.
+///
+/// # Returns
+/// The validated search, or a message describing the invalid field
+fn validate_saved_search(body: CreateSavedSearchRequest) -> Result<NewSavedSearch, String> {
+    let non_empty = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let query = body
+        .query
+        .map(|q| normalize_search_query(&q))
+        .filter(|q| !q.is_empty());
+    let anime_type = non_empty(body.anime_type);
+    let status = non_empty(body.status);
+
+    if query.is_none() && anime_type.is_none() && status.is_none() {
+        return Err("At least one of query, type, or status is required".to_string());
+    }
+
+    let name = non_empty(body.name).unwrap_or_else(|| {
+        [&query, &anime_type, &status]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    });
+    if name.chars().count() > 200 {
+        return Err("Name must be at most 200 characters".to_string());
+    }
+
+    Ok(NewSavedSearch {
+        name,
+        query,
+        anime_type,
+        status,
+    })
+}
+
+/// POST /api/user/saved-searches - Save a search to be notified about
+///
+/// Requires authentication via JWT token in Authorization header. Anime
+/// crawled after the search is saved that match it are emailed to the user
+/// (if email notifications are enabled).
+///
+/// # Request Body
+/// - name: Display name (optional)
+/// - query: Text matched against titles and genres (optional)
+/// - type: Anime type filter (optional)
+/// - status: Status filter (optional)
+///
+/// # Responses
+/// - 200: Search saved
+/// - 400: No filters given, or too many saved searches
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/user/saved-searches",
+    tag = "user",
+    request_body = CreateSavedSearchRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Search saved", body = ApiResponse<SavedSearch>),
+        (status = 400, description = "Invalid request", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn create_saved_search_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    body: web::Json<CreateSavedSearchRequest>,
+) -> impl Responder {
+    let pool = data.db.pool();
+
+    let search = match validate_saved_search(body.into_inner()) {
+        Ok(search) => search,
+        Err(msg) => {
+            return HttpResponse::BadRequest().json(ApiError::new(ErrorCode::ValidationFailed, msg))
+        }
+    };
+
+    match count_saved_searches(pool, auth.user_id).await {
+        Ok(count) if count >= MAX_SAVED_SEARCHES => {
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                format!("At most {} saved searches are allowed", MAX_SAVED_SEARCHES),
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to count saved searches: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to save search",
+            ));
+        }
+    }
+
+    match create_saved_search(
+        pool,
+        auth.user_id,
+        &search.name,
+        search.query.as_deref(),
+        search.anime_type.as_deref(),
+        search.status.as_deref(),
+    )
+    .await
+    {
+        Ok(saved) => {
+            info!("User {} saved search {}", auth.user_id, saved.id);
+            HttpResponse::Ok().json(ApiResponse::new(saved))
+        }
+        Err(e) => {
+            error!("Failed to save search: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to save search",
+            ))
+        }
+    }
+}
+
+/// GET /api/user/saved-searches - List user's saved searches
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Responses
+/// - 200: Returns saved searches, newest first
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/saved-searches",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Saved searches retrieved successfully", body = ApiResponse<Vec<SavedSearch>>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn get_saved_searches_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    match get_saved_searches(data.db.pool(), auth.user_id).await {
+        Ok(searches) => HttpResponse::Ok().json(ApiResponse::new(searches)),
+        Err(e) => {
+            error!("Failed to get saved searches: {}", e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get saved searches",
+            ))
+        }
+    }
+}
+
+/// DELETE /api/user/saved-searches/:id - Delete a saved search
+///
+/// Requires authentication via JWT token in Authorization header.
+///
+/// # Responses
+/// - 200: Saved search deleted
+/// - 401: Not authenticated
+/// - 404: Saved search not found
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/user/saved-searches/{id}",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "Saved search ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Saved search deleted", body = ApiResponse<String>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "Saved search not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn delete_saved_search_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    path: web::Path<i32>,
+) -> impl Responder {
+    let search_id = path.into_inner();
+
+    match delete_saved_search(data.db.pool(), auth.user_id, search_id).await {
+        Ok(true) => {
+            info!("User {} deleted saved search {}", auth.user_id, search_id);
+            HttpResponse::Ok().json(ApiResponse::new("Saved search deleted".to_string()))
+        }
+        Ok(false) => HttpResponse::NotFound()
+            .json(ApiError::new(ErrorCode::NotFound, "Saved search not found")),
+        Err(e) => {
+            error!("Failed to delete saved search {}: {}", search_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to delete saved search",
+            ))
+        }
+    }
+}
+
+/// POST /api/user/deactivate - Deactivate the account
+///
+/// Requires authentication via JWT token in Authorization header. Unlike
+/// DELETE /api/user/data, nothing is deleted: every session is signed out,
+/// the account can't sign in or use existing tokens, and it stops receiving
+/// notifications until it is reactivated through POST /api/auth/reactivate.
+/// The auth cookie is cleared.
+///
+/// # Responses
+/// - 200: Account deactivated
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/user/deactivate",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Account deactivated", body = ApiResponse<String>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn deactivate_account_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    let pool = data.db.pool();
+
+    if let Err(e) = deactivate_user(pool, auth.user_id).await {
+        error!("Failed to deactivate user {}: {}", auth.user_id, e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to deactivate account",
+        ));
+    }
+
+    // Tokens are rejected while deactivated; revoking keeps them dead after reactivation
+    if let Err(e) = revoke_user_sessions(pool, auth.user_id).await {
+        warn!("Failed to revoke sessions of user {}: {}", auth.user_id, e);
+    }
+    if let Err(e) = bump_token_version(pool, auth.user_id).await {
+        warn!("Failed to revoke tokens of user {}: {}", auth.user_id, e);
+    }
+
+    info!("User {} deactivated their account", auth.user_id);
+    HttpResponse::Ok()
+        .cookie(create_logout_cookie(&data.config.load().cookies))
+        .json(ApiResponse::new("Account deactivated".to_string()))
+}
+
+/// Multipart form for uploading an avatar
+#[derive(Debug, MultipartForm, ToSchema)]
+pub struct AvatarUploadForm {
+    /// JPEG, PNG, GIF, or WebP image
+    #[schema(value_type = String, format = Binary)]
+    pub avatar: Bytes,
+}
+
+// ============================================================================
+// Personal Data
+// ============================================================================
+
+/// Path of a data export's download endpoint, covered by its signature
+pub fn data_export_download_path(export_id: i32) -> String {
+    format!("/api/user/data-export/{}/download", export_id)
+}
+
+/// Whether a ready export's archive is still kept in storage
+///
+/// Archives are purged with the other exports after `retention_days`.
+pub fn is_export_available(export: &DataExport, retention_days: u64, now: DateTime<Utc>) -> bool {
+    let completed_at = export
+        .completed_at
+        .as_deref()
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok());
+    export.status == DATA_EXPORT_READY
+        && export.object_key.is_some()
+        && completed_at
+            .is_some_and(|at| now - at.with_timezone(&Utc) < Duration::days(retention_days as i64))
+}
+
+/// GET /api/user/data-export - Request an archive of the user's data
+///
+/// Requires authentication via JWT token in Authorization header. Returns the
+/// latest export: once its archive is built, with a signed download URL that
+/// works without a bearer token. If there is no export yet, or the last one
+/// failed or its archive was purged, a new one is queued. The archive is a
+/// JSON document holding the profile and every row stored about the user.
+///
+/// # Responses
+/// - 200: Export ready, returns it with its download URL
+/// - 202: Export queued or still being built; poll again later
+/// - 401: Not authenticated
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/data-export",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Export ready", body = ApiResponse<DataExport>),
+        (status = 202, description = "Export being built", body = ApiResponse<DataExport>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn data_export_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    let pool = data.db.pool();
+    let config = data.config.load_full();
+
+    let latest = match get_latest_data_export(pool, auth.user_id).await {
+        Ok(latest) => latest,
+        Err(e) => {
+            error!("Failed to get data export: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get data export",
+            ));
+        }
+    };
+
+    let now = Utc::now();
+    match latest {
+        Some(export) if export.status == DATA_EXPORT_PENDING => {
+            return HttpResponse::Accepted().json(ApiResponse::new(export));
+        }
+        Some(export) if is_export_available(&export, config.storage.export_retention_days, now) => {
+            let expires_at = now + Duration::seconds(config.signed_url_ttl_secs);
+            let url = config.url_signer().sign_at(
+                &data_export_download_path(export.id),
+                &[],
+                expires_at.timestamp(),
+            );
+            return HttpResponse::Ok().json(ApiResponse::new(DataExport {
+                download_url: Some(url),
+                expires_at: Some(expires_at.to_rfc3339()),
+                ..export
+            }));
+        }
+        _ => {}
+    }
+
+    let export = match create_data_export(pool, auth.user_id).await {
+        Ok(export) => export,
+        Err(e) => {
+            error!("Failed to create data export: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to create data export",
+            ));
+        }
+    };
+    if let Err(e) = enqueue_export_user_data(pool, export.id).await {
+        error!("Failed to queue data export {}: {}", export.id, e);
+        if let Err(e) = fail_data_export(pool, export.id, &e.to_string()).await {
+            warn!("Failed to mark export {} failed: {}", export.id, e);
+        }
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to queue data export",
+        ));
+    }
+
+    info!("User {} requested data export {}", auth.user_id, export.id);
+    HttpResponse::Accepted().json(ApiResponse::new(export))
+}
+
+/// GET /api/user/data-export/:id/download - Download a data export archive
+///
+/// Doesn't require a bearer token; the signature from GET
+/// /api/user/data-export authorizes the request.
+///
+/// # Responses
+/// - 200: The archive, as a JSON attachment
+/// - 403: Missing, invalid, or expired signature
+/// - 404: Export not ready or its archive was purged
+/// - 500: Internal server error
+#[utoipa::path(
+    get,
+    path = "/api/user/data-export/{id}/download",
+    tag = "user",
+    params(
+        ("id" = i32, Path, description = "Data export ID"),
+        ("expires" = i64, Query, description = "Expiry (unix seconds)"),
+        ("kid" = String, Query, description = "Signing key ID"),
+        ("sig" = String, Query, description = "Signature")
+    ),
+    responses(
+        (status = 200, description = "Export archive", content_type = "application/json"),
+        (status = 403, description = "Invalid or expired signature", body = ApiError),
+        (status = 404, description = "Export not available", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn download_data_export_handler(
+    data: web::Data<AppState>,
+    path: web::Path<i32>,
+    query: web::Query<HashMap<String, String>>,
+) -> impl Responder {
+    let export_id = path.into_inner();
+
+    if let Err(e) = data
+        .config
+        .load()
+        .url_signer()
+        .verify(&data_export_download_path(export_id), &query)
+    {
+        let message = match e {
+            SignatureError::Expired => "Signed URL has expired",
+            _ => "Invalid signature",
+        };
+        return HttpResponse::Forbidden().json(ApiError::new(ErrorCode::Forbidden, message));
+    }
+
+    let not_found = || {
+        HttpResponse::NotFound().json(ApiError::new(ErrorCode::NotFound, "Export not available"))
+    };
+
+    let key = match get_data_export(data.db.pool(), export_id).await {
+        Ok(Some(DataExport {
+            status,
+            object_key: Some(key),
+            ..
+        })) if status == DATA_EXPORT_READY => key,
+        Ok(_) => return not_found(),
+        Err(e) => {
+            error!("Failed to get data export {}: {}", export_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to get data export",
+            ));
+        }
+    };
+
+    match data.storage.get(&key).await {
+        Ok(Some(object)) => HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"user-data-{}.json\"", export_id),
+            ))
+            .insert_header((header::CACHE_CONTROL, "private, no-store"))
+            .body(object.bytes),
+        Ok(None) => not_found(),
+        Err(e) => {
+            error!("Failed to read data export {}: {}", export_id, e);
+            HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to read data export",
+            ))
+        }
+    }
+}
+
+/// POST /api/user/avatar - Upload a profile picture
+///
+/// Requires authentication via JWT token in Authorization header. Takes a
+/// multipart/form-data body with a JPEG, PNG, GIF, or WebP image in the
+/// `avatar` field, up to REQUEST_MAX_UPLOAD_BYTES. The image is cropped to a
+/// square and resized to a 256x256 PNG, stored in object storage, and served
+/// through the image proxy; a previously uploaded avatar is deleted.
+///
+/// # Responses
+/// - 200: Avatar updated, returns the user with a signed avatar URL
+/// - 400: Missing avatar field or image could not be decoded
+/// - 401: Not authenticated
+/// - 413: Image is too large
+/// - 415: Not a multipart body or not a supported image type
+/// - 500: Internal server error
+#[utoipa::path(
+    post,
+    path = "/api/user/avatar",
+    tag = "user",
+    request_body(content = AvatarUploadForm, content_type = "multipart/form-data"),
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Avatar updated", body = ApiResponse<User>),
+        (status = 400, description = "Invalid image", body = ApiError),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 413, description = "Image too large", body = ApiError),
+        (status = 415, description = "Unsupported image type", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn upload_avatar_handler(
+    data: web::Data<AppState>,
+    auth: Auth,
+    form: MultipartForm<AvatarUploadForm>,
+) -> impl Responder {
+    let upload = form.into_inner().avatar.data;
+    if !sniff_image_type(&upload).is_some_and(|t| AVATAR_CONTENT_TYPES.contains(&t)) {
+        return HttpResponse::UnsupportedMediaType().json(ApiError::new(
+            ErrorCode::ValidationFailed,
+            "Avatar must be a JPEG, PNG, GIF, or WebP image",
+        ));
+    }
+
+    let png = match web::block(move || process_avatar(&upload)).await {
+        Ok(Ok(png)) => png,
+        Ok(Err(e)) => {
+            warn!("Rejected avatar of user {}: {}", auth.user_id, e);
+            return HttpResponse::BadRequest().json(ApiError::new(
+                ErrorCode::ValidationFailed,
+                "Avatar image could not be decoded",
+            ));
+        }
+        Err(e) => {
+            error!("Failed to process avatar: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to process avatar",
+            ));
+        }
+    };
+
+    let pool = data.db.pool();
+    let previous = match find_user_by_id(pool, auth.user_id).await {
+        Ok(Some(user)) => user.avatar,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(ApiError::new(ErrorCode::NotFound, "User not found"))
+        }
+        Err(e) => {
+            error!("Failed to find user: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to update avatar",
+            ));
+        }
+    };
+
+    let key = keys::avatar(auth.user_id, &png);
+    if let Err(e) = data.storage.put(&key, png, "image/png").await {
+        error!("Failed to store avatar {}: {}", key, e);
+        return HttpResponse::InternalServerError().json(ApiError::new(
+            ErrorCode::InternalError,
+            "Failed to store avatar",
+        ));
+    }
+
+    let user = match update_user_avatar(pool, auth.user_id, &key).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(ApiError::new(ErrorCode::NotFound, "User not found"))
+        }
+        Err(e) => {
+            error!("Failed to update avatar: {}", e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to update avatar",
+            ));
+        }
+    };
+
+    // Avatars have no retention rule, so the replaced upload is deleted here
+    if let Some(previous) = previous.filter(|p| is_stored_avatar(p) && *p != key) {
+        if let Err(e) = data.storage.delete(&previous).await {
+            warn!("Failed to delete old avatar {}: {}", previous, e);
+        }
+    }
+
+    info!("Updated avatar of user {}", auth.user_id);
+    HttpResponse::Ok().json(ApiResponse::new(with_avatar_url(&data.config.load(), user)))
+}
+
+/// Delete every object under `prefix`, logging failures
+async fn delete_stored_objects(data: &AppState, prefix: &str) {
+    match data.storage.list(prefix).await {
+        Ok(objects) => {
+            for object in objects {
+                if let Err(e) = data.storage.delete(&object.key).await {
+                    warn!("Failed to delete {}: {}", object.key, e);
+                }
+            }
+        }
+        Err(e) => warn!("Failed to list objects under {}: {}", prefix, e),
+    }
+}
+
+/// DELETE /api/user/data - Erase the account and everything stored about it
+///
+/// Requires authentication via JWT token in Authorization header. Deletes
+/// the user, their favorites, subscriptions, history, preferences, saved
+/// searches, sessions, roles, moderation records, export archives, and
+/// uploaded avatars, and
+/// anonymizes emails sent to them. Cannot be undone; the erasure is logged
+/// without personal data for administrators. The auth cookie is cleared.
+///
+/// # Responses
+/// - 200: Account erased, returns the rows affected per table
+/// - 401: Not authenticated
+/// - 404: User not found
+/// - 500: Internal server error
+#[utoipa::path(
+    delete,
+    path = "/api/user/data",
+    tag = "user",
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Account erased", body = ApiResponse<DataErasure>),
+        (status = 401, description = "Not authenticated", body = ApiError),
+        (status = 404, description = "User not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    )
+)]
+pub async fn erase_data_handler(data: web::Data<AppState>, auth: Auth) -> impl Responder {
+    let erasure = match erase_user_data(data.db.pool(), auth.user_id).await {
+        Ok(Some(erasure)) => erasure,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(ApiError::new(ErrorCode::NotFound, "User not found"))
+        }
+        Err(e) => {
+            error!("Failed to erase data of user {}: {}", auth.user_id, e);
+            return HttpResponse::InternalServerError().json(ApiError::new(
+                ErrorCode::InternalError,
+                "Failed to erase data",
+            ));
+        }
+    };
+
+    // The user is gone, so a failure here only leaves unreferenced objects behind
+    delete_stored_objects(&data, &keys::user_data_exports(auth.user_id)).await;
+    delete_stored_objects(&data, &keys::user_avatars(auth.user_id)).await;
+
+    info!(
+        "Erased data of user {} (erasure {})",
+        auth.user_id, erasure.id
+    );
+    HttpResponse::Ok()
+        .cookie(create_logout_cookie(&data.config.load().cookies))
+        .json(ApiResponse::new(erasure))
+}
+
+/// Configure user routes (favorites, subscriptions, history, preferences, sessions,
+/// saved searches, avatar, deactivation, personal data)
+///
+/// Each resource gets its own scope so these routes are not shadowed by the
+/// catch-all `/api` scope; configure them before `configure_routes`.
+pub fn configure_user_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        // Favorites
+        .service(
+            web::scope("/api/favorites")
+                .route("", web::post().to(add_favorite_handler))
+                .route("", web::get().to(get_favorites_handler))
+                .route("/{slug}", web::delete().to(remove_favorite_handler)),
+        )
+        // Subscriptions
+        .service(
+            web::scope("/api/subscriptions")
+                .route("", web::post().to(add_subscription_handler))
+                .route("", web::get().to(get_subscriptions_handler))
+                .route("/{slug}", web::delete().to(remove_subscription_handler)),
+        )
+        // History
+        .service(
+            web::scope("/api/history")
+                .route("", web::post().to(add_history_handler))
+                .route("", web::get().to(get_history_handler))
+                .route("/{slug}", web::delete().to(remove_history_handler)),
+        )
+        // Account
+        .service(
+            web::scope("/api/user")
+                .route(
+                    "/continue-watching",
+                    web::get().to(continue_watching_handler),
+                )
+                .route("/watched/{slug}", web::get().to(get_watched_handler))
+                .route("/watched/{slug}", web::post().to(mark_watched_handler))
+                .route("/preferences", web::get().to(get_preferences_handler))
+                .route("/preferences", web::patch().to(update_preferences_handler))
+                .route("/sessions", web::get().to(list_sessions_handler))
+                .route("/sessions/{id}", web::delete().to(revoke_session_handler))
+                .route(
+                    "/saved-searches",
+                    web::post().to(create_saved_search_handler),
+                )
+                .route("/saved-searches", web::get().to(get_saved_searches_handler))
+                .route(
+                    "/saved-searches/{id}",
+                    web::delete().to(delete_saved_search_handler),
+                )
+                .route("/avatar", web::post().to(upload_avatar_handler))
+                .route("/deactivate", web::post().to(deactivate_account_handler))
+                .route("/data-export", web::get().to(data_export_handler))
+                .route(
+                    "/data-export/{id}/download",
+                    web::get().to(download_data_export_handler),
+                )
+                .route("/data", web::delete().to(erase_data_handler)),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_preferences_update_valid() {
+        let update = UpdatePreferencesRequest {
+            digest_frequency: Some("weekly".to_string()),
+            preferred_quality: Some("1080P".to_string()),
+            language: Some("ID".to_string()),
+            ..Default::default()
+        };
+
+        let validated = validate_preferences_update(update).unwrap();
+        assert_eq!(validated.preferred_quality.as_deref(), Some("1080p"));
+        assert_eq!(validated.language.as_deref(), Some("id"));
+
+        let clear = UpdatePreferencesRequest {
+            preferred_quality: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(validate_preferences_update(clear).is_ok());
+    }
+
+    #[test]
+    fn test_validate_preferences_update_invalid() {
+        let invalid = [
+            UpdatePreferencesRequest {
+                digest_frequency: Some("hourly".to_string()),
+                ..Default::default()
+            },
+            UpdatePreferencesRequest {
+                preferred_quality: Some("hd".to_string()),
+                ..Default::default()
+            },
+            UpdatePreferencesRequest {
+                preferred_quality: Some("p".to_string()),
+                ..Default::default()
+            },
+            UpdatePreferencesRequest {
+                language: Some("fr".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        for update in invalid {
+            assert!(validate_preferences_update(update).is_err());
+        }
+    }
+
+    #[test]
+    fn test_validate_mark_watched() {
+        let (selection, watched) = validate_mark_watched(MarkWatchedRequest {
+            all: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(selection, EpisodeSelection::All);
+        assert!(watched);
+
+        let (selection, watched) = validate_mark_watched(MarkWatchedRequest {
+            up_to: Some(" 12 ".to_string()),
+            watched: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(selection, EpisodeSelection::UpTo(12.0));
+        assert!(!watched);
+
+        let (selection, _) = validate_mark_watched(MarkWatchedRequest {
+            episodes: Some(vec!["naruto-episode-1".to_string()]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            selection,
+            EpisodeSelection::Episodes(vec!["naruto-episode-1".to_string()])
+        );
+
+        let invalid = [
+            MarkWatchedRequest::default(),
+            MarkWatchedRequest {
+                all: Some(false),
+                ..Default::default()
+            },
+            MarkWatchedRequest {
+                all: Some(true),
+                up_to: Some("3".to_string()),
+                ..Default::default()
+            },
+            MarkWatchedRequest {
+                up_to: Some("twelve".to_string()),
+                ..Default::default()
+            },
+            MarkWatchedRequest {
+                episodes: Some(Vec::new()),
+                ..Default::default()
+            },
+        ];
+        for body in invalid {
+            assert!(validate_mark_watched(body).is_err());
+        }
+    }
+
+    #[test]
+    fn test_validate_saved_search() {
+        let search = validate_saved_search(CreateSavedSearchRequest {
+            query: Some("  Isekai ".to_string()),
+            anime_type: Some("TV".to_string()),
+            status: Some(" ".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            search,
+            NewSavedSearch {
+                name: "isekai TV".to_string(),
+                query: Some("isekai".to_string()),
+                anime_type: Some("TV".to_string()),
+                status: None,
+            }
+        );
+
+        let named = validate_saved_search(CreateSavedSearchRequest {
+            name: Some("New movies".to_string()),
+            anime_type: Some("Movie".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(named.name, "New movies");
+
+        assert!(validate_saved_search(CreateSavedSearchRequest::default()).is_err());
+        assert!(validate_saved_search(CreateSavedSearchRequest {
+            name: Some("x".repeat(201)),
+            query: Some("isekai".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_is_export_available() {
+        let now = Utc::now();
+        let export = |status: &str, completed_days_ago: Option<i64>| DataExport {
+            id: 1,
+            user_id: 1,
+            status: status.to_string(),
+            object_key: Some("exports/user-data/1/1.json".to_string()),
+            created_at: now.to_rfc3339(),
+            completed_at: completed_days_ago.map(|days| (now - Duration::days(days)).to_rfc3339()),
+            download_url: None,
+            expires_at: None,
+        };
+
+        assert!(is_export_available(
+            &export(DATA_EXPORT_READY, Some(1)),
+            7,
+            now
+        ));
+        assert!(!is_export_available(
+            &export(DATA_EXPORT_READY, Some(8)),
+            7,
+            now
+        ));
+        assert!(!is_export_available(
+            &export(DATA_EXPORT_PENDING, None),
+            7,
+            now
+        ));
+        assert!(!is_export_available(
+            &export(crate::models::DATA_EXPORT_FAILED, Some(0)),
+            7,
+            now
+        ));
+    }
 }
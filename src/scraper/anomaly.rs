@@ -0,0 +1,288 @@
+//! Upstream response anomalies
+//!
+//! When the source site changes (a bot challenge in front of it, a domain
+//! move done with a meta refresh, an error page served with status 200),
+//! the parsers quietly return nothing. Fetched pages are checked for the
+//! usual signs of that, and each anomaly is logged with structured fields,
+//! counted, and kept in a short in-memory list for
+//! GET /api/admin/anomalies.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use ::scraper::{Html, Selector};
+
+use crate::models::{
+    UpstreamAnomaly, UpstreamAnomalyCounts, UpstreamAnomalyKind, UpstreamAnomalyReport,
+};
+
+/// Anomalies kept for the admin endpoint
+pub const MAX_RECENT_ANOMALIES: usize = 100;
+
+/// Bodies shorter than this (trimmed, in bytes) are too small to be a page
+pub const TINY_BODY_BYTES: usize = 512;
+
+/// Pages with less visible text than this (in characters) and a script
+/// count as JS-only
+const JS_ONLY_MAX_TEXT_CHARS: usize = 200;
+
+/// Bodies larger than this aren't parsed for meta refreshes or scripts;
+/// redirect stubs and bot challenges are far smaller
+const STRUCTURE_CHECK_MAX_BYTES: usize = 256 * 1024;
+
+/// Elements whose text isn't shown on the page
+const HIDDEN_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+/// Check a fetched page for signs that it isn't the page asked for
+///
+/// # Arguments
+/// * `content_type` - Content-Type header of the response, if any
+/// * `body` - Response body
+///
+/// # Returns
+/// Each anomaly found with a short description of what was seen
+pub fn detect(content_type: Option<&str>, body: &str) -> Vec<(UpstreamAnomalyKind, String)> {
+    let mut anomalies = Vec::new();
+
+    let media_type = content_type
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+    match media_type.as_deref() {
+        Some("text/html" | "application/xhtml+xml") => {}
+        Some(other) => anomalies.push((
+            UpstreamAnomalyKind::UnexpectedContentType,
+            other.to_string(),
+        )),
+        None => anomalies.push((
+            UpstreamAnomalyKind::UnexpectedContentType,
+            "no content type".to_string(),
+        )),
+    }
+
+    let trimmed = body.trim().len();
+    if trimmed < TINY_BODY_BYTES {
+        anomalies.push((UpstreamAnomalyKind::TinyBody, format!("{} bytes", trimmed)));
+    }
+
+    if body.len() > STRUCTURE_CHECK_MAX_BYTES {
+        return anomalies;
+    }
+    let document = Html::parse_document(body);
+
+    if let Some(target) = meta_refresh(&document) {
+        anomalies.push((UpstreamAnomalyKind::MetaRefresh, target));
+    }
+
+    let scripts =
+        Selector::parse("script").map_or(0, |selector| document.select(&selector).count());
+    if scripts > 0 {
+        let text = visible_text_len(&document);
+        if text < JS_ONLY_MAX_TEXT_CHARS {
+            anomalies.push((
+                UpstreamAnomalyKind::JsOnly,
+                format!("{} scripts, {} characters of text", scripts, text),
+            ));
+        }
+    }
+
+    anomalies
+}
+
+/// Content of the page's `<meta http-equiv="refresh">`, if it has one
+fn meta_refresh(document: &Html) -> Option<String> {
+    let selector = Selector::parse("meta[http-equiv]").ok()?;
+    document
+        .select(&selector)
+        .find(|meta| {
+            meta.value()
+                .attr("http-equiv")
+                .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"))
+        })
+        .map(|meta| {
+            meta.value()
+                .attr("content")
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+}
+
+/// Characters of text shown on the page, ignoring whitespace
+fn visible_text_len(document: &Html) -> usize {
+    document
+        .root_element()
+        .descendants()
+        .filter_map(|node| {
+            let text = node.value().as_text()?;
+            let hidden = node.ancestors().any(|ancestor| {
+                ancestor
+                    .value()
+                    .as_element()
+                    .is_some_and(|element| HIDDEN_TEXT_ELEMENTS.contains(&element.name()))
+            });
+            (!hidden).then(|| text.chars().filter(|c| !c.is_whitespace()).count())
+        })
+        .sum()
+}
+
+#[derive(Debug, Default)]
+struct AnomalyLogState {
+    counts: UpstreamAnomalyCounts,
+    recent: VecDeque<UpstreamAnomaly>,
+}
+
+/// Anomalous responses seen since startup
+///
+/// Cloning shares the log, so the scraper records into the same one the
+/// admin endpoint reads.
+#[derive(Debug, Clone, Default)]
+pub struct AnomalyLog {
+    state: Arc<Mutex<AnomalyLogState>>,
+}
+
+impl AnomalyLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check a fetched page and record any anomalies found
+    ///
+    /// # Arguments
+    /// * `url` - URL the page was served from
+    /// * `status` - HTTP status of the response
+    /// * `content_type` - Content-Type header of the response, if any
+    /// * `body` - Response body
+    ///
+    /// # Returns
+    /// The number of anomalies found
+    pub fn inspect(&self, url: &str, status: u16, content_type: Option<&str>, body: &str) -> usize {
+        let found = detect(content_type, body);
+        let count = found.len();
+        for (kind, detail) in found {
+            tracing::warn!(
+                anomaly = kind.as_str(),
+                url,
+                status,
+                content_type,
+                body_bytes = body.len(),
+                detail = %detail,
+                "Anomalous upstream response"
+            );
+            self.record(UpstreamAnomaly {
+                kind,
+                url: url.to_string(),
+                status,
+                content_type: content_type.map(str::to_string),
+                body_bytes: body.len(),
+                detail,
+                detected_at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+        count
+    }
+
+    /// Count an anomaly and keep it, dropping the oldest past
+    /// [`MAX_RECENT_ANOMALIES`]
+    pub fn record(&self, anomaly: UpstreamAnomaly) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.counts.add(anomaly.kind);
+        if state.recent.len() == MAX_RECENT_ANOMALIES {
+            state.recent.pop_front();
+        }
+        state.recent.push_back(anomaly);
+    }
+
+    /// Counts since startup and the latest anomalies, newest first
+    pub fn report(&self) -> UpstreamAnomalyReport {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        UpstreamAnomalyReport {
+            counts: state.counts.clone(),
+            recent: state.recent.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(content_type: Option<&str>, body: &str) -> Vec<UpstreamAnomalyKind> {
+        detect(content_type, body)
+            .into_iter()
+            .map(|(kind, _)| kind)
+            .collect()
+    }
+
+    fn page(body: &str) -> String {
+        format!(
+            "<html><head><title>Anime</title></head><body>{}<p>{}</p></body></html>",
+            body,
+            "Sinopsis anime yang cukup panjang. ".repeat(20)
+        )
+    }
+
+    #[test]
+    fn test_detect() {
+        let html = Some("text/html; charset=UTF-8");
+        assert!(kinds(html, &page("")).is_empty());
+        assert!(kinds(html, &page("<script>track()</script>")).is_empty());
+
+        assert_eq!(
+            kinds(Some("application/json"), &page("")),
+            vec![UpstreamAnomalyKind::UnexpectedContentType]
+        );
+        assert_eq!(
+            kinds(None, &page("")),
+            vec![UpstreamAnomalyKind::UnexpectedContentType]
+        );
+        assert_eq!(
+            kinds(html, "<html><body>Error</body></html>"),
+            vec![UpstreamAnomalyKind::TinyBody]
+        );
+
+        let refresh = page(r#"<meta http-equiv="Refresh" content="0; url=https://x4.sokuja.uk/">"#);
+        assert_eq!(
+            detect(html, &refresh),
+            vec![(
+                UpstreamAnomalyKind::MetaRefresh,
+                "0; url=https://x4.sokuja.uk/".to_string()
+            )]
+        );
+
+        let challenge = format!(
+            "<html><body><noscript>Enable JavaScript and cookies to continue</noscript><script>{}</script></body></html>",
+            "var a = 1; ".repeat(100)
+        );
+        assert_eq!(kinds(html, &challenge), vec![UpstreamAnomalyKind::JsOnly]);
+    }
+
+    #[test]
+    fn test_anomaly_log() {
+        let log = AnomalyLog::new();
+        assert_eq!(
+            log.inspect("https://example.com/", 200, Some("text/html"), &page("")),
+            0
+        );
+        assert_eq!(log.inspect("https://example.com/a", 200, None, ""), 2);
+
+        for i in 0..MAX_RECENT_ANOMALIES {
+            log.inspect(
+                &format!("https://example.com/{}", i),
+                200,
+                Some("text/plain"),
+                &page(""),
+            );
+        }
+
+        let report = log.report();
+        assert_eq!(report.counts.tiny_body, 1);
+        assert_eq!(
+            report.counts.unexpected_content_type,
+            MAX_RECENT_ANOMALIES as u64 + 1
+        );
+        assert_eq!(report.recent.len(), MAX_RECENT_ANOMALIES);
+        assert_eq!(report.recent[0].url, "https://example.com/99");
+        assert_eq!(report.recent[0].content_type.as_deref(), Some("text/plain"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Fixture-backed scrape client for tests
+//!
+//! [`MockScraper`] answers from pages registered up front, keyed by URL, and
+//! records every URL it was asked for. Unknown URLs fail with HTTP 404, like
+//! a missing page on the live site. Registered redirects are followed and
+//! reported like the live scraper's.
+//!
+//! ```
+//! use anime_scraper::constants::endpoints;
+//! use anime_scraper::scraper::{MockScraper, ScrapeClient};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let base = "https://example.com";
+//! let scraper = MockScraper::new()
+//!     .with_page(endpoints::home(base), "<html></html>")
+//!     .with_error(endpoints::anime(base, "gone"), 500);
+//!
+//! let page = scraper.fetch_page(&endpoints::home(base)).await.unwrap();
+//! assert_eq!(page.html, "<html></html>");
+//! assert!(scraper.fetch_page(&endpoints::anime(base, "gone")).await.is_err());
+//! assert_eq!(scraper.requests().len(), 2);
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{Redirect, RedirectPolicy, ScrapeClient, ScrapeFuture, ScraperError, ScraperResult};
+
+/// Canned response for a URL
+#[derive(Debug, Clone)]
+enum MockResponse {
+    Page(String),
+    Status(u16),
+    Redirect(String, u16),
+}
+
+/// Scrape client serving registered pages instead of the network
+#[derive(Debug, Default)]
+pub struct MockScraper {
+    responses: HashMap<String, MockResponse>,
+    requests: Mutex<Vec<String>>,
+    redirect_policy: RedirectPolicy,
+}
+
+impl MockScraper {
+    /// Create a mock with no pages
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `html` for `url`
+    pub fn with_page(mut self, url: impl Into<String>, html: impl Into<String>) -> Self {
+        self.responses
+            .insert(url.into(), MockResponse::Page(html.into()));
+        self
+    }
+
+    /// Serve the contents of a fixture file for `url`
+    pub fn with_fixture(self, url: impl Into<String>, path: impl AsRef<Path>) -> io::Result<Self> {
+        let html = std::fs::read_to_string(path)?;
+        Ok(self.with_page(url, html))
+    }
+
+    /// Fail requests for `url` with an HTTP status (429 fails as rate limited)
+    pub fn with_error(mut self, url: impl Into<String>, status: u16) -> Self {
+        self.responses
+            .insert(url.into(), MockResponse::Status(status));
+        self
+    }
+
+    /// Redirect requests for `url` to `to` with a redirect status
+    pub fn with_redirect(
+        mut self,
+        url: impl Into<String>,
+        to: impl Into<String>,
+        status: u16,
+    ) -> Self {
+        self.responses
+            .insert(url.into(), MockResponse::Redirect(to.into(), status));
+        self
+    }
+
+    /// Follow redirects under `policy` instead of the default one
+    pub fn with_redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = policy;
+        self
+    }
+
+    /// URLs requested so far, in order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Record a request and look up its response, following redirects
+    fn respond(&self, url: &str) -> Result<ScraperResult, ScraperError> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(url.to_string());
+
+        let mut redirects = Vec::new();
+        let mut current = url;
+        loop {
+            match self.responses.get(current) {
+                Some(MockResponse::Page(html)) => {
+                    return Ok(ScraperResult {
+                        html: html.clone(),
+                        status: 200,
+                        redirects,
+                        final_url: current.to_string(),
+                        wire_bytes: html.len() as u64,
+                    })
+                }
+                Some(MockResponse::Redirect(to, status)) => {
+                    self.redirect_policy.check(url, redirects.len(), to)?;
+                    redirects.push(Redirect {
+                        from: current.to_string(),
+                        to: to.clone(),
+                        status: *status,
+                    });
+                    current = to;
+                }
+                Some(MockResponse::Status(429)) => return Err(ScraperError::RateLimited),
+                Some(MockResponse::Status(status)) => return Err(ScraperError::HttpError(*status)),
+                None => return Err(ScraperError::HttpError(404)),
+            }
+        }
+    }
+}
+
+impl ScrapeClient for MockScraper {
+    fn fetch_page<'a>(&'a self, url: &'a str) -> ScrapeFuture<'a> {
+        Box::pin(async move { self.respond(url) })
+    }
+
+    fn fetch_page_no_delay<'a>(&'a self, url: &'a str) -> ScrapeFuture<'a> {
+        Box::pin(async move { self.respond(url) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::constants::endpoints;
+    use crate::parser::parse_anime_detail;
+
+    const BASE: &str = "https://example.com";
+
+    #[tokio::test]
+    async fn test_serves_fixture_pages() {
+        let url = endpoints::anime(BASE, "frieren");
+        let scraper: Arc<dyn ScrapeClient> = Arc::new(
+            MockScraper::new()
+                .with_fixture(&url, "fixtures/parser/anime_detail/frieren.html")
+                .unwrap(),
+        );
+
+        let result = scraper
+            .fetch_page_within(&url, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(result.status, 200);
+        assert!(!parse_anime_detail(&result.html).title.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_unknown_urls() {
+        let scraper = MockScraper::new()
+            .with_error(endpoints::home(BASE), 503)
+            .with_error(endpoints::search(BASE, "x"), 429);
+
+        let err = scraper
+            .fetch_page(&endpoints::home(BASE))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScraperError::HttpError(503)));
+        let err = scraper
+            .fetch_page(&endpoints::search(BASE, "x"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScraperError::RateLimited));
+        let err = scraper
+            .fetch_page_no_delay(&endpoints::anime(BASE, "missing"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScraperError::HttpError(404)));
+
+        assert_eq!(
+            scraper.requests(),
+            vec![
+                endpoints::home(BASE),
+                endpoints::search(BASE, "x"),
+                endpoints::anime(BASE, "missing"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_followed_and_reported() {
+        let old = endpoints::anime(BASE, "old");
+        let new = endpoints::anime(BASE, "new");
+        let scraper = MockScraper::new()
+            .with_redirect(&old, &new, 301)
+            .with_page(&new, "<html></html>")
+            .with_redirect(
+                endpoints::anime(BASE, "loop"),
+                endpoints::anime(BASE, "loop"),
+                302,
+            );
+
+        let result = scraper.fetch_page(&old).await.unwrap();
+        assert_eq!(result.status, 200);
+        assert_eq!(
+            result.redirects,
+            vec![Redirect {
+                from: old.clone(),
+                to: new.clone(),
+                status: 301,
+            }]
+        );
+        assert_eq!(result.moved_to(), Some(new.as_str()));
+        assert_eq!(result.final_url, new);
+        assert_eq!(result.redirect_count(), 1);
+
+        let err = scraper
+            .fetch_page(&endpoints::anime(BASE, "loop"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScraperError::TooManyRedirects(_)));
+
+        let scraper = MockScraper::new()
+            .with_redirect(&old, "https://elsewhere.example.com/", 302)
+            .with_redirect_policy(RedirectPolicy {
+                max_redirects: 5,
+                same_host_only: true,
+            });
+        let err = scraper.fetch_page(&old).await.unwrap_err();
+        assert!(matches!(err, ScraperError::CrossHostRedirect(_)));
+    }
+}
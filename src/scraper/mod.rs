@@ -2,14 +2,47 @@
 //!
 //! This module provides HTTP client functionality with browser-like headers
 //! and anti-detection features to fetch HTML content from sokuja.uk.
+//!
+//! A scraper can be given an archive storage, in which case the raw HTML of
+//! every fetched page is kept for debugging parser regressions.
+//!
+//! Response bodies are read chunk by chunk and capped at
+//! `ScraperConfig::max_body_bytes`, so a runaway page (the "all" anime list
+//! runs to several MB) fails fast instead of being buffered whole. Pages are
+//! decompressed here rather than by the HTTP client, so
+//! [`ScraperResult::wire_bytes`] counts what actually crossed the network.
+//!
+//! Redirects are followed by the scraper itself under a [`RedirectPolicy`]
+//! (at most [`MAX_REDIRECTS`] hops by default, optionally only within the
+//! requested host), and every hop is reported in
+//! [`ScraperResult::redirects`] along with the URL the fetch landed on, so
+//! callers can notice pages that moved.
+//!
+//! Fetched pages are checked for signs of site changes (see [`anomaly`]);
+//! anomalies are logged and kept in the scraper's [`AnomalyLog`].
+//!
+//! Handlers and the crawler fetch through the [`ScrapeClient`] trait, so they
+//! can be run against a [`MockScraper`] serving fixture pages instead of the
+//! network.
+
+pub mod anomaly;
+pub mod mock;
 
 use rand::Rng;
 use reqwest::{Client, StatusCode};
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use thiserror::Error;
 use tokio::time::sleep;
 
+use crate::storage::{keys, Storage};
+
+pub use anomaly::AnomalyLog;
+pub use mock::MockScraper;
+
 /// Errors that can occur during scraping operations
 #[derive(Error, Debug)]
 pub enum ScraperError {
@@ -28,6 +61,87 @@ pub enum ScraperError {
     /// Rate limited by server
     #[error("Rate limited, retry after delay")]
     RateLimited,
+
+    /// Response body larger than the configured limit
+    #[error("Response body exceeds {0} bytes")]
+    BodyTooLarge(usize),
+
+    /// No complete response within the caller's latency budget (milliseconds)
+    #[error("No response within {0} ms")]
+    Timeout(u64),
+
+    /// More redirects than the redirect policy allows
+    #[error("Exceeded {0} redirects")]
+    TooManyRedirects(usize),
+
+    /// Redirect away from the requested host, refused by the redirect policy
+    #[error("Refused redirect to another host: {0}")]
+    CrossHostRedirect(String),
+}
+
+/// Default for the most redirects followed for one fetch
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Which redirects a fetch follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectPolicy {
+    /// Most redirects followed for one fetch; 0 follows none
+    pub max_redirects: usize,
+    /// Refuse redirects to a host other than the requested URL's
+    pub same_host_only: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: MAX_REDIRECTS,
+            same_host_only: false,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// Check whether a fetch of `origin` may follow another redirect
+    ///
+    /// # Arguments
+    /// * `origin` - URL the fetch was made for
+    /// * `followed` - Redirects already followed
+    /// * `target` - Absolute URL the redirect points to
+    pub fn check(&self, origin: &str, followed: usize, target: &str) -> Result<(), ScraperError> {
+        if followed >= self.max_redirects {
+            return Err(ScraperError::TooManyRedirects(self.max_redirects));
+        }
+        if self.same_host_only && url_host(origin) != url_host(target) {
+            return Err(ScraperError::CrossHostRedirect(target.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Lowercased host of a URL, or `None` if it has none
+fn url_host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(str::to_ascii_lowercase)
+}
+
+/// One redirect followed while fetching a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// URL that answered with the redirect
+    pub from: String,
+    /// Absolute URL it pointed to
+    pub to: String,
+    /// Redirect status code (301, 302, 303, 307, or 308)
+    pub status: u16,
+}
+
+impl Redirect {
+    /// Whether the page moved for good (301 or 308)
+    pub fn is_permanent(&self) -> bool {
+        matches!(self.status, 301 | 308)
+    }
 }
 
 /// Result of a successful page fetch
@@ -37,6 +151,58 @@ pub struct ScraperResult {
     pub html: String,
     /// The HTTP status code
     pub status: u16,
+    /// Redirects followed to reach the page, in order; empty if the
+    /// requested URL answered directly
+    pub redirects: Vec<Redirect>,
+    /// URL the page was served from, after any redirects
+    pub final_url: String,
+    /// Size of the page body as downloaded, before decompression
+    pub wire_bytes: u64,
+}
+
+impl ScraperResult {
+    /// Number of redirects followed to reach the page
+    pub fn redirect_count(&self) -> usize {
+        self.redirects.len()
+    }
+
+    /// Where the requested page permanently moved to
+    ///
+    /// # Returns
+    /// The final URL if every redirect followed was permanent, or `None` if
+    /// there were no redirects or one of them was temporary
+    pub fn moved_to(&self) -> Option<&str> {
+        let last = self.redirects.last()?;
+        self.redirects
+            .iter()
+            .all(Redirect::is_permanent)
+            .then_some(last.to.as_str())
+    }
+}
+
+/// Future returned by [`ScrapeClient`] fetches
+pub type ScrapeFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<ScraperResult, ScraperError>> + Send + 'a>>;
+
+/// Source of scraped pages
+///
+/// Implemented by [`Scraper`] for the live site and by [`MockScraper`] for
+/// tests. Shared through `AppState` as `Arc<dyn ScrapeClient>`.
+pub trait ScrapeClient: Send + Sync {
+    /// Fetch a page as part of a sequence (e.g., a crawl), pacing requests
+    fn fetch_page<'a>(&'a self, url: &'a str) -> ScrapeFuture<'a>;
+
+    /// Fetch a single page on demand, giving up once `budget` has passed
+    fn fetch_page_within<'a>(&'a self, url: &'a str, budget: Duration) -> ScrapeFuture<'a> {
+        Box::pin(async move {
+            tokio::time::timeout(budget, self.fetch_page_no_delay(url))
+                .await
+                .unwrap_or(Err(ScraperError::Timeout(budget.as_millis() as u64)))
+        })
+    }
+
+    /// Fetch a single page once, without pacing or retries
+    fn fetch_page_no_delay<'a>(&'a self, url: &'a str) -> ScrapeFuture<'a>;
 }
 
 /// Configuration for anti-detection features
@@ -52,8 +218,15 @@ pub struct ScraperConfig {
     pub max_retries: u32,
     /// Base delay for exponential backoff in milliseconds
     pub backoff_base_ms: u64,
+    /// Largest response body accepted, in bytes (after decompression)
+    pub max_body_bytes: usize,
+    /// Which redirects are followed
+    pub redirects: RedirectPolicy,
 }
 
+/// Default response body limit: well above the largest listing page
+pub const DEFAULT_MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
 impl Default for ScraperConfig {
     fn default() -> Self {
         Self {
@@ -62,12 +235,14 @@ impl Default for ScraperConfig {
             rotate_user_agent: true,
             max_retries: 3,
             backoff_base_ms: 1000,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            redirects: RedirectPolicy::default(),
         }
     }
 }
 
 /// List of realistic user agents for rotation
-const USER_AGENTS: &[&str] = &[
+pub(crate) const USER_AGENTS: &[&str] = &[
     // Chrome on Windows
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36",
@@ -88,6 +263,8 @@ pub struct Scraper {
     client: Client,
     config: ScraperConfig,
     request_count: AtomicUsize,
+    archive: Option<Storage>,
+    anomalies: AnomalyLog,
 }
 
 impl Default for Scraper {
@@ -107,6 +284,10 @@ impl Scraper {
         let client = Client::builder()
             .timeout(Duration::from_secs(30))
             .connect_timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .no_gzip()
+            .no_brotli()
+            .no_deflate()
             .build()
             .expect("Failed to build HTTP client");
 
@@ -114,6 +295,34 @@ impl Scraper {
             client,
             config,
             request_count: AtomicUsize::new(0),
+            archive: None,
+            anomalies: AnomalyLog::default(),
+        }
+    }
+
+    /// Archive the raw HTML of fetched pages to `archive`, if set
+    pub fn with_archive(mut self, archive: Option<Storage>) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    /// Record anomalous responses in `anomalies` instead of a private log
+    pub fn with_anomaly_log(mut self, anomalies: AnomalyLog) -> Self {
+        self.anomalies = anomalies;
+        self
+    }
+
+    /// Store a fetched page in the archive; failures are only logged
+    async fn archive_page(&self, url: &str, html: &str) {
+        let Some(archive) = &self.archive else {
+            return;
+        };
+        let key = keys::page(url, chrono::Utc::now());
+        if let Err(e) = archive
+            .put(&key, html.as_bytes().to_vec(), "text/html; charset=utf-8")
+            .await
+        {
+            tracing::warn!("Failed to archive {}: {}", url, e);
         }
     }
 
@@ -175,6 +384,7 @@ impl Scraper {
     }
 
     /// Fetch a page from the given URL with anti-detection features
+    #[tracing::instrument(name = "scrape", skip(self))]
     pub async fn fetch_page(&self, url: &str) -> Result<ScraperResult, ScraperError> {
         // Apply delay before request (except for first request)
         let count = self.request_count.fetch_add(1, Ordering::SeqCst);
@@ -182,6 +392,11 @@ impl Scraper {
             self.apply_delay().await;
         }
 
+        self.fetch_with_retries(url).await
+    }
+
+    /// Fetch a page, retrying with backoff on rate limiting and server errors
+    async fn fetch_with_retries(&self, url: &str) -> Result<ScraperResult, ScraperError> {
         let mut last_error = None;
 
         for attempt in 0..self.config.max_retries {
@@ -210,9 +425,24 @@ impl Scraper {
         )))
     }
 
-    /// Internal fetch implementation
-    async fn do_fetch(&self, url: &str) -> Result<ScraperResult, ScraperError> {
-        let user_agent = self.get_user_agent();
+    /// Fetch a single page with retries, giving up once `budget` has passed
+    ///
+    /// Unlike [`fetch_page`](Self::fetch_page), requests are not paced, so
+    /// concurrent API requests sharing a scraper don't wait on each other.
+    /// The budget covers retries and reading the body.
+    #[tracing::instrument(name = "scrape", skip(self))]
+    pub async fn fetch_page_within(
+        &self,
+        url: &str,
+        budget: Duration,
+    ) -> Result<ScraperResult, ScraperError> {
+        tokio::time::timeout(budget, self.fetch_with_retries(url))
+            .await
+            .unwrap_or(Err(ScraperError::Timeout(budget.as_millis() as u64)))
+    }
+
+    /// Build a browser-like request for `url`
+    fn build_request(&self, url: &str, user_agent: &'static str) -> reqwest::RequestBuilder {
         let (sec_ch_ua, sec_ch_ua_mobile, sec_ch_ua_platform) = self.get_sec_ch_ua(user_agent);
 
         let mut request = self
@@ -237,19 +467,49 @@ impl Scraper {
                 .header("Sec-Ch-Ua-Mobile", sec_ch_ua_mobile)
                 .header("Sec-Ch-Ua-Platform", sec_ch_ua_platform);
         }
+        request
+    }
 
-        let response = request.send().await.map_err(|e| {
-            if e.is_timeout() {
-                ScraperError::NetworkError("Connection timeout".to_string())
-            } else if e.is_connect() {
-                ScraperError::NetworkError("Failed to connect to server".to_string())
-            } else {
-                ScraperError::NetworkError(e.to_string())
-            }
-        })?;
+    /// Internal fetch implementation; each attempt gets its own span
+    ///
+    /// Redirects are followed here rather than by the HTTP client so each
+    /// hop can be reported.
+    #[tracing::instrument(name = "http_fetch", skip(self), fields(status = tracing::field::Empty))]
+    async fn do_fetch(&self, url: &str) -> Result<ScraperResult, ScraperError> {
+        let user_agent = self.get_user_agent();
+        let mut redirects: Vec<Redirect> = Vec::new();
+        let mut current = url.to_string();
+
+        let response = loop {
+            let response = self
+                .build_request(&current, user_agent)
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        ScraperError::NetworkError("Connection timeout".to_string())
+                    } else if e.is_connect() {
+                        ScraperError::NetworkError("Failed to connect to server".to_string())
+                    } else {
+                        ScraperError::NetworkError(e.to_string())
+                    }
+                })?;
+
+            let Some(target) = redirect_target(&response) else {
+                break response;
+            };
+            self.config.redirects.check(url, redirects.len(), &target)?;
+            tracing::debug!("{} redirected to {}", current, target);
+            redirects.push(Redirect {
+                from: std::mem::replace(&mut current, target.clone()),
+                to: target,
+                status: response.status().as_u16(),
+            });
+        };
 
         let status = response.status();
         let status_code = status.as_u16();
+        tracing::Span::current().record("status", status_code);
 
         // Handle rate limiting
         if status_code == 429 {
@@ -260,17 +520,70 @@ impl Scraper {
             return Err(ScraperError::HttpError(status_code));
         }
 
-        let html = response
-            .text()
-            .await
-            .map_err(|e| ScraperError::ResponseError(e.to_string()))?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let (html, wire_bytes) = self.read_body(response).await?;
+
+        self.anomalies
+            .inspect(&current, status_code, content_type.as_deref(), &html);
+        self.archive_page(url, &html).await;
 
         Ok(ScraperResult {
             html,
             status: status_code,
+            redirects,
+            final_url: current,
+            wire_bytes,
         })
     }
 
+    /// Read and decompress a response body, failing once it exceeds
+    /// `max_body_bytes`
+    ///
+    /// The declared Content-Length is checked first so oversized pages are
+    /// rejected before any of the body is read; the limit applies to the
+    /// decompressed page too. Valid UTF-8 is turned into a `String` without
+    /// copying; anything else is decoded lossily.
+    ///
+    /// # Returns
+    /// The page, and the bytes downloaded for it
+    async fn read_body(
+        &self,
+        mut response: reqwest::Response,
+    ) -> Result<(String, u64), ScraperError> {
+        let limit = self.config.max_body_bytes;
+        let encoding = response
+            .headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let declared = response.content_length().unwrap_or(0);
+        if declared > limit as u64 {
+            return Err(ScraperError::BodyTooLarge(limit));
+        }
+
+        let mut body = Vec::with_capacity(declared as usize);
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ScraperError::ResponseError(e.to_string()))?
+        {
+            if body.len() + chunk.len() > limit {
+                return Err(ScraperError::BodyTooLarge(limit));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let wire_bytes = body.len() as u64;
+        let body = decode_body(encoding.as_deref(), body, limit)?;
+        let html = String::from_utf8(body)
+            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+        Ok((html, wire_bytes))
+    }
+
     /// Fetch a page without delay (for single requests)
     pub async fn fetch_page_no_delay(&self, url: &str) -> Result<ScraperResult, ScraperError> {
         self.do_fetch(url).await
@@ -287,6 +600,75 @@ impl Scraper {
     }
 }
 
+/// Decompress a body sent with the given Content-Encoding
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The decompressed body
+/// * `Err(ScraperError::BodyTooLarge)` - It decompresses to more than `limit`
+///   bytes
+/// * `Err(ScraperError::ResponseError)` - The encoding is unknown or the body
+///   is corrupt
+fn decode_body(
+    encoding: Option<&str>,
+    body: Vec<u8>,
+    limit: usize,
+) -> Result<Vec<u8>, ScraperError> {
+    let encoding = encoding.map(|e| e.trim().to_ascii_lowercase());
+    let decoder: Box<dyn Read + '_> = match encoding.as_deref() {
+        None | Some("") | Some("identity") => return Ok(body),
+        Some("gzip") | Some("x-gzip") => Box::new(flate2::read::MultiGzDecoder::new(&body[..])),
+        Some("deflate") => Box::new(flate2::read::ZlibDecoder::new(&body[..])),
+        Some("br") => Box::new(brotli::Decompressor::new(&body[..], 4096)),
+        Some(other) => {
+            return Err(ScraperError::ResponseError(format!(
+                "Unsupported content encoding: {}",
+                other
+            )))
+        }
+    };
+
+    let mut decoded = Vec::new();
+    decoder
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|e| ScraperError::ResponseError(e.to_string()))?;
+    if decoded.len() > limit {
+        return Err(ScraperError::BodyTooLarge(limit));
+    }
+    Ok(decoded)
+}
+
+/// Absolute URL a redirect response points to
+///
+/// # Returns
+/// The Location resolved against the response URL, or `None` if the
+/// response isn't a redirect or has no usable Location
+fn redirect_target(response: &reqwest::Response) -> Option<String> {
+    if !matches!(response.status().as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)?
+        .to_str()
+        .ok()?;
+    response.url().join(location).ok().map(String::from)
+}
+
+impl ScrapeClient for Scraper {
+    fn fetch_page<'a>(&'a self, url: &'a str) -> ScrapeFuture<'a> {
+        Box::pin(Scraper::fetch_page(self, url))
+    }
+
+    fn fetch_page_within<'a>(&'a self, url: &'a str, budget: Duration) -> ScrapeFuture<'a> {
+        Box::pin(Scraper::fetch_page_within(self, url, budget))
+    }
+
+    fn fetch_page_no_delay<'a>(&'a self, url: &'a str) -> ScrapeFuture<'a> {
+        Box::pin(Scraper::fetch_page_no_delay(self, url))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,6 +679,66 @@ mod tests {
         assert_eq!(scraper.request_count(), 0);
     }
 
+    #[test]
+    fn test_moved_to() {
+        let hop = |from: &str, to: &str, status| Redirect {
+            from: from.to_string(),
+            to: to.to_string(),
+            status,
+        };
+        let result = |redirects| ScraperResult {
+            html: String::new(),
+            status: 200,
+            redirects,
+            final_url: String::new(),
+            wire_bytes: 0,
+        };
+
+        assert_eq!(result(Vec::new()).moved_to(), None);
+        assert_eq!(
+            result(vec![hop("/a", "/b", 301), hop("/b", "/c", 308)]).moved_to(),
+            Some("/c")
+        );
+        assert_eq!(
+            result(vec![hop("/a", "/b", 301), hop("/b", "/c", 302)]).moved_to(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_redirect_policy() {
+        let origin = "https://x3.sokuja.uk/anime/old/";
+        let policy = RedirectPolicy::default();
+        assert!(policy
+            .check(origin, 0, "https://other.example.com/")
+            .is_ok());
+        assert!(matches!(
+            policy.check(origin, MAX_REDIRECTS, "https://x3.sokuja.uk/anime/new/"),
+            Err(ScraperError::TooManyRedirects(MAX_REDIRECTS))
+        ));
+
+        let policy = RedirectPolicy {
+            max_redirects: 0,
+            same_host_only: true,
+        };
+        assert!(matches!(
+            policy.check(origin, 0, "https://x3.sokuja.uk/anime/new/"),
+            Err(ScraperError::TooManyRedirects(0))
+        ));
+
+        let policy = RedirectPolicy {
+            max_redirects: 5,
+            same_host_only: true,
+        };
+        assert!(policy
+            .check(origin, 1, "http://X3.sokuja.uk/anime/new/")
+            .is_ok());
+        assert!(matches!(
+            policy.check(origin, 1, "http://169.254.169.254/latest/meta-data/"),
+            Err(ScraperError::CrossHostRedirect(_))
+        ));
+    }
+
     #[test]
     fn test_scraper_with_config() {
         let config = ScraperConfig {
@@ -305,6 +747,8 @@ mod tests {
             rotate_user_agent: false,
             max_retries: 5,
             backoff_base_ms: 2000,
+            max_body_bytes: 1024,
+            redirects: RedirectPolicy::default(),
         };
         let scraper = Scraper::with_config(config);
         assert_eq!(scraper.config.min_delay_ms, 500);
@@ -351,5 +795,128 @@ mod tests {
         assert_eq!(config.max_delay_ms, 3000);
         assert!(config.rotate_user_agent);
         assert_eq!(config.max_retries, 3);
+        assert_eq!(config.max_body_bytes, DEFAULT_MAX_BODY_BYTES);
+    }
+
+    /// Serve one raw HTTP response on a local port
+    async fn serve_response(response: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = socket.read(&mut request).await;
+            let _ = socket.write_all(&response).await;
+        });
+
+        format!("http://{}/", addr)
+    }
+
+    /// Serve one HTTP response with a body of `len` bytes on a local port
+    async fn serve_once(len: usize, content_length: bool) -> String {
+        let body = "a".repeat(len);
+        let response = if content_length {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                len, body
+            )
+        } else {
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+                len, body
+            )
+        };
+        serve_response(response.into_bytes()).await
+    }
+
+    /// Serve one gzip-compressed HTML response of `len` bytes on a local port
+    ///
+    /// # Returns
+    /// The URL, and the size of the compressed body
+    async fn serve_gzipped(len: usize) -> (String, u64) {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all("a".repeat(len).as_bytes()).unwrap();
+        let body = encoder.finish().unwrap();
+
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        let wire_bytes = body.len() as u64;
+        response.extend(body);
+        (serve_response(response).await, wire_bytes)
+    }
+
+    fn limited_scraper(max_body_bytes: usize) -> Scraper {
+        Scraper::with_config(ScraperConfig {
+            max_body_bytes,
+            ..ScraperConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit() {
+        let url = serve_once(1000, false).await;
+        let result = limited_scraper(1000)
+            .fetch_page_no_delay(&url)
+            .await
+            .unwrap();
+        assert_eq!(result.html.len(), 1000);
+        assert_eq!(result.wire_bytes, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_body() {
+        // Counted as downloaded, limited as decompressed
+        let (url, wire_bytes) = serve_gzipped(1000).await;
+        let result = limited_scraper(1000)
+            .fetch_page_no_delay(&url)
+            .await
+            .unwrap();
+        assert_eq!(result.html, "a".repeat(1000));
+        assert_eq!(result.wire_bytes, wire_bytes);
+        assert!(wire_bytes < 100);
+
+        let (url, _) = serve_gzipped(1001).await;
+        let err = limited_scraper(1000)
+            .fetch_page_no_delay(&url)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScraperError::BodyTooLarge(1000)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_within_times_out() {
+        // Accepts the connection but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let _socket = listener.accept().await;
+            sleep(Duration::from_secs(5)).await;
+        });
+
+        let err = Scraper::new()
+            .fetch_page_within(&url, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScraperError::Timeout(100)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_body_over_limit() {
+        // Rejected from the declared length, then while streaming without one
+        for content_length in [true, false] {
+            let url = serve_once(1001, content_length).await;
+            let err = limited_scraper(1000)
+                .fetch_page_no_delay(&url)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ScraperError::BodyTooLarge(1000)), "{:?}", err);
+        }
     }
 }
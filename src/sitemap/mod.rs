@@ -0,0 +1,130 @@
+//! Search engine sitemaps
+//!
+//! Frontends that proxy this API serve `/sitemap.xml` from their own
+//! domain. It is a sitemap index of partials under `/sitemaps/`, each
+//! listing up to [`SITEMAP_PAGE_SIZE`] stored anime detail pages. Links
+//! point at SITEMAP_BASE_URL, the frontend's origin.
+
+use crate::models::{SitemapEntry, SitemapPage};
+use crate::xml::XmlWriter;
+
+/// Anime per sitemap partial, the sitemap protocol's limit
+pub const SITEMAP_PAGE_SIZE: i64 = 50_000;
+
+/// Namespace of sitemap documents
+const SITEMAP_XMLNS: &str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+
+/// File name of a sitemap partial
+pub fn partial_name(page: i64) -> String {
+    format!("anime-{}.xml", page)
+}
+
+/// Page number of a sitemap partial from its file name
+pub fn parse_partial_name(name: &str) -> Option<i64> {
+    let page: i64 = name
+        .strip_prefix("anime-")?
+        .strip_suffix(".xml")?
+        .parse()
+        .ok()?;
+    (page >= 1).then_some(page)
+}
+
+/// Frontend URL of an anime detail page
+pub fn anime_url(base_url: &str, slug: &str) -> String {
+    format!("{}/anime/{}", base_url, urlencoding::encode(slug))
+}
+
+/// Build the sitemap index listing the partials
+pub fn sitemap_index(base_url: &str, pages: &[SitemapPage]) -> String {
+    let mut xml = XmlWriter::new();
+    xml.start("sitemapindex", &[("xmlns", SITEMAP_XMLNS)]);
+    for page in pages {
+        xml.start("sitemap", &[]);
+        xml.element(
+            "loc",
+            &[],
+            &format!("{}/sitemaps/{}", base_url, partial_name(page.page)),
+        );
+        if let Some(last_modified) = &page.last_modified {
+            xml.element("lastmod", &[], last_modified);
+        }
+        xml.end();
+    }
+    xml.finish()
+}
+
+/// Build a sitemap partial listing anime detail pages
+pub fn urlset(base_url: &str, entries: &[SitemapEntry]) -> String {
+    let mut xml = XmlWriter::new();
+    xml.start("urlset", &[("xmlns", SITEMAP_XMLNS)]);
+    for entry in entries {
+        xml.start("url", &[]);
+        xml.element("loc", &[], &anime_url(base_url, &entry.slug));
+        if let Some(last_modified) = &entry.last_modified {
+            xml.element("lastmod", &[], last_modified);
+        }
+        xml.end();
+    }
+    xml.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://anime.example.com";
+
+    #[test]
+    fn test_partial_name() {
+        assert_eq!(partial_name(3), "anime-3.xml");
+        assert_eq!(parse_partial_name("anime-3.xml"), Some(3));
+        assert_eq!(parse_partial_name("anime-0.xml"), None);
+        assert_eq!(parse_partial_name("anime-x.xml"), None);
+        assert_eq!(parse_partial_name("anime-3.txt"), None);
+        assert_eq!(parse_partial_name("episodes-3.xml"), None);
+    }
+
+    #[test]
+    fn test_sitemap_index() {
+        let xml = sitemap_index(
+            BASE,
+            &[
+                SitemapPage {
+                    page: 1,
+                    last_modified: Some("2024-12-27T12:00:00+00:00".to_string()),
+                },
+                SitemapPage {
+                    page: 2,
+                    last_modified: None,
+                },
+            ],
+        );
+        assert!(
+            xml.contains("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">")
+        );
+        assert!(xml.contains(
+            "<loc>https://anime.example.com/sitemaps/anime-1.xml</loc>\n    <lastmod>2024-12-27T12:00:00+00:00</lastmod>"
+        ));
+        assert!(
+            xml.contains("<loc>https://anime.example.com/sitemaps/anime-2.xml</loc>\n  </sitemap>")
+        );
+    }
+
+    #[test]
+    fn test_urlset() {
+        let xml = urlset(
+            BASE,
+            &[SitemapEntry {
+                slug: "sousou-no-frieren".to_string(),
+                last_modified: Some("2024-12-27T12:00:00+00:00".to_string()),
+            }],
+        );
+        assert!(xml.contains("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"));
+        assert!(xml.contains(
+            "<url>\n    <loc>https://anime.example.com/anime/sousou-no-frieren</loc>\n    <lastmod>2024-12-27T12:00:00+00:00</lastmod>\n  </url>"
+        ));
+        assert!(urlset(BASE, &[]).ends_with(
+            "<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n</urlset>\n"
+        ));
+    }
+}
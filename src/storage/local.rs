@@ -0,0 +1,181 @@
+//! Local directory storage backend
+//!
+//! Each key maps to a file below the root directory. Writes go to a temporary
+//! file that is renamed into place, so readers never see partial objects.
+//! Content types are not recorded.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use super::{ObjectInfo, StorageResult, StoredObject};
+
+/// Suffix of in-progress writes, skipped when listing
+const TMP_SUFFIX: &str = ".tmp";
+
+/// Storage in a directory on local disk
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Create a backend rooted at `root`; the directory is created on first write
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Root directory of the backend
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    pub(super) async fn put(&self, key: &str, bytes: Vec<u8>) -> StorageResult<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let tmp = path.with_file_name(format!(
+            "{}.{}{}",
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("object"),
+            uuid::Uuid::new_v4().simple(),
+            TMP_SUFFIX
+        ));
+        tokio::fs::write(&tmp, bytes).await?;
+        if let Err(e) = tokio::fs::rename(&tmp, &path).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    pub(super) async fn get(&self, key: &str) -> StorageResult<Option<StoredObject>> {
+        match tokio::fs::read(self.path(key)).await {
+            Ok(bytes) => Ok(Some(StoredObject {
+                bytes,
+                content_type: None,
+            })),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(super) async fn exists(&self, key: &str) -> StorageResult<bool> {
+        Ok(tokio::fs::try_exists(self.path(key)).await?)
+    }
+
+    pub(super) async fn delete(&self, key: &str) -> StorageResult<()> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(super) async fn list(&self, prefix: &str) -> StorageResult<Vec<ObjectInfo>> {
+        // Only walk the directory the prefix points into
+        let start = match prefix.rsplit_once('/') {
+            Some((dir, _)) => self.root.join(dir),
+            None => self.root.clone(),
+        };
+
+        let mut objects = Vec::new();
+        let mut pending = vec![start];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                let path = entry.path();
+                if metadata.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                let Some(key) = path
+                    .strip_prefix(&self.root)
+                    .ok()
+                    .and_then(|p| p.to_str())
+                    .map(|p| p.replace(std::path::MAIN_SEPARATOR, "/"))
+                else {
+                    continue;
+                };
+                if !key.starts_with(prefix) || key.ends_with(TMP_SUFFIX) {
+                    continue;
+                }
+
+                objects.push(ObjectInfo {
+                    key,
+                    size: metadata.len(),
+                    last_modified: metadata
+                        .modified()
+                        .map(DateTime::<Utc>::from)
+                        .unwrap_or_else(|_| Utc::now()),
+                });
+            }
+        }
+
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::storage::Storage;
+
+    #[tokio::test]
+    async fn test_local_roundtrip_and_cleanup() {
+        let root = std::env::temp_dir().join(format!("storage-test-{}", uuid::Uuid::new_v4()));
+        let storage = Storage::Local(LocalStorage::new(&root));
+
+        storage
+            .put("images/abc", b"image".to_vec(), "image/png")
+            .await
+            .unwrap();
+        storage
+            .put("pages/2024-12-27/a.html", b"<html>".to_vec(), "text/html")
+            .await
+            .unwrap();
+
+        let object = storage.get("images/abc").await.unwrap().unwrap();
+        assert_eq!(object.bytes, b"image");
+        assert!(storage.get("images/missing").await.unwrap().is_none());
+        assert!(storage.exists("images/abc").await.unwrap());
+        assert!(!storage.exists("images/missing").await.unwrap());
+
+        let pages = storage.list("pages/").await.unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].key, "pages/2024-12-27/a.html");
+        assert_eq!(pages[0].size, 6);
+
+        // Nothing is older than an hour; everything is older than zero
+        assert_eq!(
+            storage
+                .cleanup("images/", Duration::from_secs(3600))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(storage.cleanup("", Duration::ZERO).await.unwrap(), 2);
+        assert!(storage.list("").await.unwrap().is_empty());
+
+        storage.delete("images/abc").await.unwrap();
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,335 @@
+//! Object storage for cached images, archived pages, and exports
+//!
+//! Objects are addressed by slash-separated keys (see [`keys`]) and stored in
+//! one of two backends, selected by STORAGE_BACKEND:
+//! - [`LocalStorage`] - A directory on disk (the default)
+//! - [`S3Storage`] - Any S3-compatible service (AWS S3, MinIO, R2, ...)
+//!
+//! Apart from uploaded avatars, nothing in storage is authoritative; every
+//! object can be refetched or regenerated, so old objects are deleted by a
+//! background cleanup task according to per-prefix retention (see
+//! [`spawn_cleanup`]). Avatars have no retention rule and are deleted when
+//! replaced or when their user is erased.
+
+pub mod local;
+pub mod s3;
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+use crate::config::{StorageBackend, StorageConfig};
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// Storage errors
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Invalid object key: {0}")]
+    InvalidKey(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage request failed: {0}")]
+    Request(String),
+
+    #[error("Unexpected storage response: {0}")]
+    InvalidResponse(String),
+}
+
+/// Result type for storage operations
+pub type StorageResult<T> = Result<T, StorageError>;
+
+/// An object read back from storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    /// Object contents
+    pub bytes: Vec<u8>,
+    /// Content type, if the backend records one
+    pub content_type: Option<String>,
+}
+
+/// Metadata of a stored object, as returned by listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    /// Object key
+    pub key: String,
+    /// Size in bytes
+    pub size: u64,
+    /// When the object was last written
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Object keys and their prefixes
+pub mod keys {
+    use chrono::{DateTime, Utc};
+    use sha2::{Digest, Sha256};
+
+    /// Prefix of cached proxy images
+    pub const IMAGES: &str = "images/";
+    /// Prefix of archived raw HTML pages
+    pub const PAGES: &str = "pages/";
+    /// Prefix of export files
+    pub const EXPORTS: &str = "exports/";
+    /// Prefix of uploaded user avatars
+    pub const AVATARS: &str = "avatars/";
+
+    fn url_hash(url: &str) -> String {
+        hex::encode(Sha256::digest(url.as_bytes()))
+    }
+
+    /// Key of the cached copy of an image URL
+    pub fn image(url: &str) -> String {
+        format!("{}{}", IMAGES, url_hash(url))
+    }
+
+    /// Key of a page archived at `fetched_at`, grouped by day
+    pub fn page(url: &str, fetched_at: DateTime<Utc>) -> String {
+        format!(
+            "{}{}/{}-{}.html",
+            PAGES,
+            fetched_at.format("%Y-%m-%d"),
+            fetched_at.format("%H%M%S"),
+            &url_hash(url)[..16]
+        )
+    }
+
+    /// Key of an export file
+    pub fn export(name: &str) -> String {
+        format!("{}{}", EXPORTS, name)
+    }
+
+    /// Prefix of a user's personal data exports
+    pub fn user_data_exports(user_id: i32) -> String {
+        export(&format!("user-data/{}/", user_id))
+    }
+
+    /// Key of a personal data export archive
+    pub fn user_data_export(user_id: i32, export_id: i32) -> String {
+        format!("{}{}.json", user_data_exports(user_id), export_id)
+    }
+
+    /// Prefix of a user's uploaded avatars
+    pub fn user_avatars(user_id: i32) -> String {
+        format!("{}{}/", AVATARS, user_id)
+    }
+
+    /// Key of an uploaded avatar, named after its contents
+    pub fn avatar(user_id: i32, png: &[u8]) -> String {
+        format!(
+            "{}{}.png",
+            user_avatars(user_id),
+            &hex::encode(Sha256::digest(png))[..16]
+        )
+    }
+}
+
+/// Check that a key is relative, has no empty or dot segments, and only uses
+/// URL- and filesystem-safe characters
+pub fn validate_key(key: &str) -> StorageResult<()> {
+    let valid = !key.is_empty()
+        && key.len() <= 1024
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
+
+/// Configured object storage backend
+#[derive(Debug, Clone)]
+pub enum Storage {
+    /// Directory on local disk
+    Local(LocalStorage),
+    /// S3-compatible bucket
+    S3(S3Storage),
+}
+
+impl Storage {
+    /// Create the backend selected in the configuration
+    pub fn from_config(config: &StorageConfig) -> Self {
+        match &config.backend {
+            StorageBackend::Local { dir } => Storage::Local(LocalStorage::new(dir)),
+            StorageBackend::S3(s3_config) => Storage::S3(S3Storage::new(s3_config.clone())),
+        }
+    }
+
+    /// Short backend name for logging
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            Storage::Local(_) => "local",
+            Storage::S3(_) => "s3",
+        }
+    }
+
+    /// Store an object, replacing any existing object with the same key
+    pub async fn put(&self, key: &str, bytes: Vec<u8>, content_type: &str) -> StorageResult<()> {
+        validate_key(key)?;
+        match self {
+            Storage::Local(local) => local.put(key, bytes).await,
+            Storage::S3(s3) => s3.put(key, bytes, content_type).await,
+        }
+    }
+
+    /// Read an object, or `None` if it doesn't exist
+    pub async fn get(&self, key: &str) -> StorageResult<Option<StoredObject>> {
+        validate_key(key)?;
+        match self {
+            Storage::Local(local) => local.get(key).await,
+            Storage::S3(s3) => s3.get(key).await,
+        }
+    }
+
+    /// Whether an object exists, without reading it
+    pub async fn exists(&self, key: &str) -> StorageResult<bool> {
+        validate_key(key)?;
+        match self {
+            Storage::Local(local) => local.exists(key).await,
+            Storage::S3(s3) => s3.exists(key).await,
+        }
+    }
+
+    /// Delete an object; deleting a missing object is not an error
+    pub async fn delete(&self, key: &str) -> StorageResult<()> {
+        validate_key(key)?;
+        match self {
+            Storage::Local(local) => local.delete(key).await,
+            Storage::S3(s3) => s3.delete(key).await,
+        }
+    }
+
+    /// List all objects whose key starts with `prefix`
+    pub async fn list(&self, prefix: &str) -> StorageResult<Vec<ObjectInfo>> {
+        match self {
+            Storage::Local(local) => local.list(prefix).await,
+            Storage::S3(s3) => s3.list(prefix).await,
+        }
+    }
+
+    /// Delete objects under `prefix` last written more than `max_age` ago
+    ///
+    /// # Returns
+    /// Number of deleted objects
+    pub async fn cleanup(&self, prefix: &str, max_age: Duration) -> StorageResult<usize> {
+        let max_age = chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now().checked_sub_signed(max_age);
+        let Some(cutoff) = cutoff else {
+            return Ok(0);
+        };
+
+        let mut deleted = 0;
+        for object in self.list(prefix).await? {
+            if object.last_modified < cutoff {
+                self.delete(&object.key).await?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Retention of objects under a key prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionRule {
+    /// Key prefix the rule applies to
+    pub prefix: &'static str,
+    /// Objects older than this are deleted
+    pub max_age: Duration,
+}
+
+/// Retention rules for the configured lifetimes
+pub fn retention_rules(config: &StorageConfig) -> Vec<RetentionRule> {
+    let days = |d: u64| Duration::from_secs(d * 24 * 60 * 60);
+    vec![
+        RetentionRule {
+            prefix: keys::IMAGES,
+            max_age: days(config.image_retention_days),
+        },
+        RetentionRule {
+            prefix: keys::PAGES,
+            max_age: days(config.page_retention_days),
+        },
+        RetentionRule {
+            prefix: keys::EXPORTS,
+            max_age: days(config.export_retention_days),
+        },
+    ]
+}
+
+/// Spawn a task applying the retention rules every `interval`
+///
+/// A failing rule is logged and retried on the next run.
+pub fn spawn_cleanup(
+    storage: Storage,
+    rules: Vec<RetentionRule>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for rule in &rules {
+                match storage.cleanup(rule.prefix, rule.max_age).await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Deleted {} old objects under {}", deleted, rule.prefix),
+                    Err(e) => error!("Storage cleanup of {} failed: {}", rule.prefix, e),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("images/abc123").is_ok());
+        assert!(validate_key("pages/2024-12-27/101500-abcd.html").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("/etc/passwd").is_err());
+        assert!(validate_key("images/../secret").is_err());
+        assert!(validate_key("images//a").is_err());
+        assert!(validate_key("images/a b").is_err());
+    }
+
+    #[test]
+    fn test_keys_are_valid_and_stable() {
+        let image = keys::image("https://x3.sokuja.uk/a.jpg");
+        assert!(image.starts_with(keys::IMAGES));
+        assert_eq!(image, keys::image("https://x3.sokuja.uk/a.jpg"));
+        assert!(validate_key(&image).is_ok());
+
+        let fetched_at = DateTime::parse_from_rfc3339("2024-12-27T10:15:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let page = keys::page("https://x3.sokuja.uk/", fetched_at);
+        assert!(page.starts_with("pages/2024-12-27/101500-"));
+        assert!(validate_key(&page).is_ok());
+
+        assert_eq!(keys::export("favorites.csv"), "exports/favorites.csv");
+        assert_eq!(keys::user_data_export(7, 42), "exports/user-data/7/42.json");
+
+        let avatar = keys::avatar(7, b"png bytes");
+        assert!(avatar.starts_with(&keys::user_avatars(7)));
+        assert!(avatar.starts_with(keys::AVATARS));
+        assert_eq!(avatar, keys::avatar(7, b"png bytes"));
+        assert_ne!(avatar, keys::avatar(7, b"other bytes"));
+        assert!(validate_key(&avatar).is_ok());
+    }
+}
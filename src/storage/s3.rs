@@ -0,0 +1,410 @@
+//! S3-compatible storage backend
+//!
+//! Talks to the S3 REST API directly with AWS Signature Version 4, which
+//! works with AWS S3 as well as MinIO, Cloudflare R2, and other compatible
+//! services. MinIO and most self-hosted services need path-style addressing
+//! (`endpoint/bucket/key`); AWS also accepts virtual-hosted style
+//! (`bucket.endpoint/key`).
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode};
+use sha2::{Digest, Sha256};
+
+use super::{ObjectInfo, StorageError, StorageResult, StoredObject};
+use crate::config::S3Config;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Timeout for a single S3 request
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Objects per ListObjectsV2 page (the S3 maximum)
+const LIST_PAGE_SIZE: &str = "1000";
+
+/// Storage in an S3-compatible bucket
+#[derive(Debug, Clone)]
+pub struct S3Storage {
+    client: Client,
+    config: S3Config,
+}
+
+/// One page of a ListObjectsV2 response
+#[derive(Debug, Default, PartialEq)]
+struct ListPage {
+    objects: Vec<ObjectInfo>,
+    next_token: Option<String>,
+}
+
+impl S3Storage {
+    /// Create a backend for the configured bucket
+    pub fn new(config: S3Config) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .expect("Failed to build S3 client");
+        Self { client, config }
+    }
+
+    /// Host and path of an object (or of the bucket, for an empty key)
+    fn host_and_path(&self, key: &str) -> (String, String) {
+        let endpoint = self.config.endpoint.trim_end_matches('/');
+        let host = endpoint
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .unwrap_or(endpoint)
+            .to_string();
+        let key = uri_encode(key, false);
+
+        if self.config.path_style {
+            (host, format!("/{}/{}", self.config.bucket, key))
+        } else {
+            (
+                format!("{}.{}", self.config.bucket, host),
+                format!("/{}", key),
+            )
+        }
+    }
+
+    /// Send a signed request
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> StorageResult<reqwest::Response> {
+        let (host, path) = self.host_and_path(key);
+        let scheme = if self.config.endpoint.starts_with("http://") {
+            "http"
+        } else {
+            "https"
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let canonical_query = canonical_query(query);
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method.as_str(),
+            path,
+            canonical_query,
+            host,
+            payload_hash,
+            amz_date,
+            payload_hash
+        );
+        let authorization = authorization_header(
+            &self.config.access_key_id,
+            &self.config.secret_access_key,
+            &self.config.region,
+            now,
+            &canonical_request,
+        );
+
+        let mut url = format!("{}://{}{}", scheme, host, path);
+        if !canonical_query.is_empty() {
+            url = format!("{}?{}", url, canonical_query);
+        }
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(reqwest::header::AUTHORIZATION, authorization);
+        if let Some(content_type) = content_type {
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+
+        request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| StorageError::Request(e.to_string()))
+    }
+
+    /// Turn a non-success response into an error
+    async fn check(response: reqwest::Response) -> StorageResult<reqwest::Response> {
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        Err(StorageError::Request(format!(
+            "HTTP {}: {}",
+            status,
+            xml_value(&body, "Code").unwrap_or("unknown error")
+        )))
+    }
+
+    pub(super) async fn put(
+        &self,
+        key: &str,
+        bytes: Vec<u8>,
+        content_type: &str,
+    ) -> StorageResult<()> {
+        let response = self
+            .send(Method::PUT, key, &[], bytes, Some(content_type))
+            .await?;
+        Self::check(response).await?;
+        Ok(())
+    }
+
+    pub(super) async fn get(&self, key: &str) -> StorageResult<Option<StoredObject>> {
+        let response = self.send(Method::GET, key, &[], Vec::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = Self::check(response).await?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| StorageError::Request(e.to_string()))?;
+
+        Ok(Some(StoredObject {
+            bytes: bytes.to_vec(),
+            content_type,
+        }))
+    }
+
+    pub(super) async fn exists(&self, key: &str) -> StorageResult<bool> {
+        let response = self.send(Method::HEAD, key, &[], Vec::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        Self::check(response).await?;
+        Ok(true)
+    }
+
+    pub(super) async fn delete(&self, key: &str) -> StorageResult<()> {
+        let response = self
+            .send(Method::DELETE, key, &[], Vec::new(), None)
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        Self::check(response).await?;
+        Ok(())
+    }
+
+    pub(super) async fn list(&self, prefix: &str) -> StorageResult<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+
+        loop {
+            let mut query = vec![
+                ("list-type", "2"),
+                ("max-keys", LIST_PAGE_SIZE),
+                ("prefix", prefix),
+            ];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+
+            let response = self.send(Method::GET, "", &query, Vec::new(), None).await?;
+            let body = Self::check(response)
+                .await?
+                .text()
+                .await
+                .map_err(|e| StorageError::Request(e.to_string()))?;
+
+            let page = parse_list_response(&body)?;
+            objects.extend(page.objects);
+            match page.next_token {
+                Some(next) => token = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(objects)
+    }
+}
+
+/// Percent-encode per SigV4 rules; `/` is kept unless `encode_slash`
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Sorted, encoded query string as used in the canonical request
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Derive the SigV4 signing key for a day, region, and service
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret).as_bytes(), date);
+    let key = hmac(&key, region);
+    let key = hmac(&key, service);
+    hmac(&key, "aws4_request")
+}
+
+/// Authorization header value for a canonical request
+fn authorization_header(
+    access_key_id: &str,
+    secret: &str,
+    region: &str,
+    now: DateTime<Utc>,
+    canonical_request: &str,
+) -> String {
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        now.format("%Y%m%dT%H%M%SZ"),
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac(
+        &signing_key(secret, &date, region, "s3"),
+        &string_to_sign,
+    ));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+        access_key_id, scope, signature
+    )
+}
+
+/// Text of the first `<tag>` element in `xml`
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(&xml[start..end])
+}
+
+/// Undo the XML escaping S3 applies to keys and tokens
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parse a ListObjectsV2 response body
+fn parse_list_response(xml: &str) -> StorageResult<ListPage> {
+    let mut objects = Vec::new();
+
+    for chunk in xml.split("<Contents>").skip(1) {
+        let invalid = || StorageError::InvalidResponse("malformed <Contents> entry".to_string());
+        let key = xml_value(chunk, "Key").ok_or_else(invalid)?;
+        let size = xml_value(chunk, "Size")
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)?;
+        let last_modified = xml_value(chunk, "LastModified")
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .ok_or_else(invalid)?
+            .with_timezone(&Utc);
+
+        objects.push(ObjectInfo {
+            key: xml_unescape(key),
+            size,
+            last_modified,
+        });
+    }
+
+    let truncated = xml_value(xml, "IsTruncated") == Some("true");
+    let next_token = xml_value(xml, "NextContinuationToken")
+        .filter(|_| truncated)
+        .map(xml_unescape);
+
+    Ok(ListPage {
+        objects,
+        next_token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_canonical_query_sorts_and_encodes() {
+        assert_eq!(
+            canonical_query(&[("prefix", "pages/2024 12"), ("list-type", "2")]),
+            "list-type=2&prefix=pages%2F2024%2012"
+        );
+        assert_eq!(uri_encode("images/a~b c", false), "images/a~b%20c");
+    }
+
+    #[test]
+    fn test_parse_list_response() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+  <Name>anime</Name>
+  <IsTruncated>true</IsTruncated>
+  <Contents><Key>images/a</Key><LastModified>2024-12-27T10:15:00.000Z</LastModified><Size>1024</Size></Contents>
+  <Contents><Key>images/b&amp;c</Key><LastModified>2024-12-28T00:00:00.000Z</LastModified><Size>7</Size></Contents>
+  <NextContinuationToken>token+1</NextContinuationToken>
+</ListBucketResult>"#;
+
+        let page = parse_list_response(xml).unwrap();
+        assert_eq!(page.objects.len(), 2);
+        assert_eq!(page.objects[0].key, "images/a");
+        assert_eq!(page.objects[0].size, 1024);
+        assert_eq!(
+            page.objects[0].last_modified.to_rfc3339(),
+            "2024-12-27T10:15:00+00:00"
+        );
+        assert_eq!(page.objects[1].key, "images/b&c");
+        assert_eq!(page.next_token.as_deref(), Some("token+1"));
+
+        let last = parse_list_response("<IsTruncated>false</IsTruncated>").unwrap();
+        assert_eq!(last, ListPage::default());
+    }
+}
@@ -0,0 +1,201 @@
+//! Multi-tenancy
+//!
+//! One deployment can serve several frontends, each with its own isolated
+//! user base. Scraped anime data is shared; users, and everything they own,
+//! belong to exactly one tenant.
+//!
+//! The tenant of a request is resolved by the `resolve_tenant` middleware:
+//! 1. The tenant header (`X-Tenant` by default) naming a tenant slug
+//! 2. The request hostname matched against each tenant's hostnames
+//! 3. The default tenant
+//!
+//! Tenants rarely change, so they are kept in memory and reloaded when an
+//! admin creates one.
+
+use std::future::{ready, Ready};
+use std::sync::{Arc, RwLock};
+
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use sqlx::PgPool;
+
+use crate::db::{get_tenants, RepositoryError, DEFAULT_TENANT_ID};
+use crate::models::Tenant;
+
+/// The tenant a request was resolved to
+///
+/// Inserted into the request extensions by the `resolve_tenant` middleware.
+/// As an extractor it falls back to the default tenant if the middleware is
+/// not installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentTenant {
+    /// Tenant ID
+    pub id: i32,
+    /// Tenant slug
+    pub slug: String,
+}
+
+impl Default for CurrentTenant {
+    fn default() -> Self {
+        Self {
+            id: DEFAULT_TENANT_ID,
+            slug: "default".to_string(),
+        }
+    }
+}
+
+impl From<&Tenant> for CurrentTenant {
+    fn from(tenant: &Tenant) -> Self {
+        Self {
+            id: tenant.id,
+            slug: tenant.slug.clone(),
+        }
+    }
+}
+
+impl FromRequest for CurrentTenant {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<CurrentTenant>()
+            .cloned()
+            .unwrap_or_default()))
+    }
+}
+
+/// In-memory tenant lookup
+#[derive(Debug, Clone, Default)]
+pub struct TenantRegistry {
+    tenants: Arc<RwLock<Vec<Tenant>>>,
+}
+
+impl TenantRegistry {
+    /// Create a registry from a list of tenants
+    pub fn new(tenants: Vec<Tenant>) -> Self {
+        Self {
+            tenants: Arc::new(RwLock::new(tenants)),
+        }
+    }
+
+    /// Load all tenants from the database
+    pub async fn load(pool: &PgPool) -> Result<Self, RepositoryError> {
+        Ok(Self::new(get_tenants(pool).await?))
+    }
+
+    /// Reload tenants from the database
+    pub async fn reload(&self, pool: &PgPool) -> Result<(), RepositoryError> {
+        let tenants = get_tenants(pool).await?;
+        *self.tenants.write().unwrap_or_else(|e| e.into_inner()) = tenants;
+        Ok(())
+    }
+
+    /// All known tenants
+    pub fn all(&self) -> Vec<Tenant> {
+        self.tenants
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Resolve a tenant from the tenant header and the request host
+    ///
+    /// An unknown slug in the header falls through to host matching. The
+    /// port is ignored when matching hosts.
+    pub fn resolve(&self, header: Option<&str>, host: Option<&str>) -> CurrentTenant {
+        let tenants = self.tenants.read().unwrap_or_else(|e| e.into_inner());
+
+        let by_header = header
+            .map(str::trim)
+            .filter(|slug| !slug.is_empty())
+            .and_then(|slug| tenants.iter().find(|t| t.slug.eq_ignore_ascii_case(slug)));
+
+        let by_host = || {
+            let host = host?;
+            let host = match host.rsplit_once(':') {
+                Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+                _ => host,
+            };
+            tenants.iter().find(|t| {
+                t.hostnames
+                    .iter()
+                    .any(|hostname| hostname.eq_ignore_ascii_case(host))
+            })
+        };
+
+        by_header
+            .or_else(by_host)
+            .map(CurrentTenant::from)
+            .unwrap_or_default()
+    }
+}
+
+/// Whether a slug is valid for a new tenant
+pub fn is_valid_tenant_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= 100
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant(id: i32, slug: &str, hostnames: &[&str]) -> Tenant {
+        Tenant {
+            id,
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            hostnames: hostnames.iter().map(|h| h.to_string()).collect(),
+            created_at: String::new(),
+        }
+    }
+
+    fn registry() -> TenantRegistry {
+        TenantRegistry::new(vec![
+            tenant(1, "default", &[]),
+            tenant(2, "acme", &["anime.acme.test"]),
+            tenant(3, "otaku", &["otaku.test", "www.otaku.test"]),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_prefers_header() {
+        let resolved = registry().resolve(Some("acme"), Some("otaku.test"));
+        assert_eq!(resolved.id, 2);
+        assert_eq!(registry().resolve(Some("OTAKU"), None).id, 3);
+    }
+
+    #[test]
+    fn test_resolve_by_host_ignoring_port() {
+        assert_eq!(registry().resolve(None, Some("anime.acme.test")).id, 2);
+        assert_eq!(registry().resolve(None, Some("WWW.otaku.test:8080")).id, 3);
+        assert_eq!(
+            registry().resolve(Some("unknown"), Some("otaku.test")).id,
+            3
+        );
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_default() {
+        assert_eq!(
+            registry().resolve(None, Some("example.com")),
+            CurrentTenant::default()
+        );
+        assert_eq!(registry().resolve(None, None).id, DEFAULT_TENANT_ID);
+    }
+
+    #[test]
+    fn test_is_valid_tenant_slug() {
+        assert!(is_valid_tenant_slug("acme-2"));
+        assert!(!is_valid_tenant_slug(""));
+        assert!(!is_valid_tenant_slug("Acme"));
+        assert!(!is_valid_tenant_slug("-acme"));
+        assert!(!is_valid_tenant_slug("a b"));
+    }
+}
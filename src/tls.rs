@@ -0,0 +1,178 @@
+//! HTTPS for the bundled server
+//!
+//! Loads the certificate chain and private key named in [`TlsConfig`] into a
+//! rustls server config, and answers plain HTTP with redirects to HTTPS when
+//! TLS_REDIRECT_HTTP_PORT is set.
+
+use std::sync::Arc;
+
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse};
+use rustls::ServerConfig;
+use rustls_pki_types::pem::{self, PemObject};
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use thiserror::Error;
+
+use crate::config::TlsConfig;
+
+/// Errors loading the certificate or key
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Failed to read certificates from {0}: {1}")]
+    Certificates(String, pem::Error),
+
+    #[error("No certificates in {0}")]
+    NoCertificates(String),
+
+    #[error("Failed to read private key from {0}: {1}")]
+    Key(String, pem::Error),
+
+    #[error("Invalid certificate or key: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// Build the rustls server config from the certificate and key files
+pub fn server_config(config: &TlsConfig) -> Result<ServerConfig, TlsError> {
+    let certs = CertificateDer::pem_file_iter(&config.cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| TlsError::Certificates(config.cert_file.clone(), e))?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(config.cert_file.clone()));
+    }
+    let key = PrivateKeyDer::from_pem_file(&config.key_file)
+        .map_err(|e| TlsError::Key(config.key_file.clone(), e))?;
+
+    let server_config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+    Ok(server_config)
+}
+
+/// Port HTTPS is served on, for [`redirect_to_https`]
+#[derive(Debug, Clone, Copy)]
+pub struct HttpsPort(pub u16);
+
+/// Redirect a plain HTTP request to the same URL over HTTPS
+///
+/// 308 keeps the method and body, so API clients posting over HTTP are
+/// redirected too. The host comes from the `Host` header, else the server's
+/// configured hostname; forwarding headers such as `X-Forwarded-Host` are
+/// ignored, as the HTTP port isn't behind a trusted proxy.
+pub async fn redirect_to_https(req: HttpRequest, port: web::Data<HttpsPort>) -> HttpResponse {
+    let path_and_query = req.uri().path_and_query().map_or("/", |p| p.as_str());
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or_else(|| req.app_config().host());
+    let location = https_location(host, port.0, path_and_query);
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+/// HTTPS URL of a request to `host` (with or without its HTTP port)
+fn https_location(host: &str, port: u16, path_and_query: &str) -> String {
+    // Drop the HTTP port, but not the colons of a bracketed IPv6 address
+    let hostname = match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    };
+    if port == 443 {
+        format!("https://{}{}", hostname, path_and_query)
+    } else {
+        format!("https://{}:{}{}", hostname, port, path_and_query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::App;
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn tls_config(cert: &str, key: &str) -> TlsConfig {
+        TlsConfig {
+            cert_file: fixture(cert),
+            key_file: fixture(key),
+            redirect_port: None,
+        }
+    }
+
+    #[test]
+    fn test_server_config_loads_fixture() {
+        assert!(server_config(&tls_config("cert.pem", "key.pem")).is_ok());
+    }
+
+    #[test]
+    fn test_server_config_errors() {
+        assert!(matches!(
+            server_config(&tls_config("missing.pem", "key.pem")),
+            Err(TlsError::Certificates(..))
+        ));
+        assert!(matches!(
+            server_config(&tls_config("key.pem", "key.pem")),
+            Err(TlsError::NoCertificates(_))
+        ));
+        assert!(matches!(
+            server_config(&tls_config("cert.pem", "cert.pem")),
+            Err(TlsError::Key(..))
+        ));
+    }
+
+    #[test]
+    fn test_https_location() {
+        assert_eq!(
+            https_location("example.com", 443, "/api/search?q=a"),
+            "https://example.com/api/search?q=a"
+        );
+        assert_eq!(
+            https_location("example.com:80", 8443, "/"),
+            "https://example.com:8443/"
+        );
+        assert_eq!(https_location("[::1]:80", 443, "/"), "https://[::1]/");
+        assert_eq!(https_location("[::1]", 8443, "/"), "https://[::1]:8443/");
+    }
+
+    #[actix_rt::test]
+    async fn test_redirect_to_https() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(HttpsPort(443)))
+                .default_service(web::to(redirect_to_https)),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/api/auth/login?next=%2F")
+            .insert_header((header::HOST, "anime.example.com:80"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(
+            res.status(),
+            actix_web::http::StatusCode::PERMANENT_REDIRECT
+        );
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://anime.example.com/api/auth/login?next=%2F"
+        );
+
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header((header::HOST, "anime.example.com"))
+            .insert_header(("x-forwarded-host", "evil.example.net"))
+            .insert_header((header::FORWARDED, "host=evil.example.net"))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(
+            res.headers().get(header::LOCATION).unwrap(),
+            "https://anime.example.com/"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Synopsis translation
+//!
+//! The source site writes synopses in Indonesian. When TRANSLATION_URL
+//! points at a LibreTranslate-compatible service, anime detail responses can
+//! carry the synopsis in another language, picked with `?lang=` or the
+//! Accept-Language header. Translations are made on demand and stored in
+//! synopsis_translations, keyed by a hash of the source text.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::TranslationConfig;
+use crate::middleware::trace::with_traceparent;
+
+/// Language synopses are scraped in
+pub const SOURCE_LANGUAGE: &str = "id";
+
+/// Languages synopses can be served in
+pub const LANGUAGES: [&str; 2] = [SOURCE_LANGUAGE, "en"];
+
+/// Timeout for translation requests
+const TRANSLATION_TIMEOUT_SECS: u64 = 10;
+
+/// Look up a supported language by code, ignoring case and region
+///
+/// "en-US" and "EN" both resolve to "en".
+pub fn parse_language(code: &str) -> Option<&'static str> {
+    let primary = code.trim().split(['-', '_']).next()?;
+    LANGUAGES
+        .into_iter()
+        .find(|language| language.eq_ignore_ascii_case(primary))
+}
+
+/// Pick the preferred supported language from an Accept-Language header
+///
+/// Entries are tried in order of their quality values; entries with `q=0`
+/// and wildcards are skipped.
+///
+/// # Returns
+/// The language, or `None` if the header names no supported language
+pub fn negotiate_language(accept_language: &str) -> Option<&'static str> {
+    let mut ranges: Vec<(&str, f32)> = accept_language
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let range = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+            Some((range, quality))
+        })
+        .filter(|&(range, quality)| !range.is_empty() && range != "*" && quality > 0.0)
+        .collect();
+    // Stable sort keeps header order among equal qualities
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranges
+        .into_iter()
+        .find_map(|(range, _)| parse_language(range))
+}
+
+/// Request body of a LibreTranslate /translate call
+#[derive(Debug, Serialize)]
+struct TranslateRequest<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+/// Response of a LibreTranslate /translate call
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TranslateResponse {
+    translated_text: String,
+}
+
+/// Translate text with the configured service
+///
+/// # Arguments
+/// * `config` - Service URL and API key
+/// * `text` - Text to translate
+/// * `source` - Language code of `text`
+/// * `target` - Language code to translate into
+///
+/// # Returns
+/// * `Ok(String)` - The translated text
+/// * `Err(reqwest::Error)` - The service could not be reached or refused the request
+pub async fn translate(
+    config: &TranslationConfig,
+    text: &str,
+    source: &str,
+    target: &str,
+) -> Result<String, reqwest::Error> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(TRANSLATION_TIMEOUT_SECS))
+        .build()?;
+    let response: TranslateResponse =
+        with_traceparent(client.post(format!("{}/translate", config.url)))
+            .json(&TranslateRequest {
+                q: text,
+                source,
+                target,
+                format: "text",
+                api_key: config.api_key.as_deref(),
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+    Ok(response.translated_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_language() {
+        assert_eq!(parse_language("en"), Some("en"));
+        assert_eq!(parse_language("EN-us"), Some("en"));
+        assert_eq!(parse_language("id_ID"), Some("id"));
+        assert_eq!(parse_language("fr"), None);
+        assert_eq!(parse_language(""), None);
+    }
+
+    #[test]
+    fn test_negotiate_language() {
+        assert_eq!(negotiate_language("en-US,en;q=0.9,id;q=0.8"), Some("en"));
+        assert_eq!(negotiate_language("fr, id;q=0.5, en;q=0.7"), Some("en"));
+        assert_eq!(negotiate_language("id, en"), Some("id"));
+        assert_eq!(negotiate_language("en;q=0, id;q=0.1"), Some("id"));
+        assert_eq!(negotiate_language("fr-FR, *"), None);
+        assert_eq!(negotiate_language("en;q=abc"), None);
+        assert_eq!(negotiate_language(""), None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Video server rules
+//!
+//! Some mirrors on episode pages are consistently dead or ad-ridden. Sources
+//! from blocked servers are dropped and the rest ordered by server priority,
+//! both when scraped sources are saved and when episodes are served.
+//!
+//! Defaults come from VIDEO_SERVER_BLACKLIST and VIDEO_SERVER_PRIORITY.
+//! Admins override them per server at runtime; overrides are stored in
+//! video_server_rules. Rules are consulted on every episode request, so they
+//! are kept in memory and reloaded when an admin changes one, or with the
+//! defaults on a config reload (see [`crate::reload`]).
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use sqlx::PgPool;
+
+use crate::config::Config;
+use crate::db::{get_video_server_rules, RepositoryError};
+use crate::models::{EpisodeDetail, VideoServerRule, VideoServerRuleOrigin, VideoSource};
+
+/// Key a server name is matched by: trimmed and lowercased
+pub fn normalize_server(server: &str) -> String {
+    server.trim().to_lowercase()
+}
+
+/// Rules from the config lists
+///
+/// Servers in the priority list get descending priorities, the first one
+/// highest. A server can be in both lists.
+pub fn config_rules(blacklist: &[String], priority: &[String]) -> Vec<VideoServerRule> {
+    let mut rules = Vec::new();
+    for (rank, server) in priority.iter().enumerate() {
+        config_rule(&mut rules, server).priority = (priority.len() - rank) as i32;
+    }
+    for server in blacklist {
+        config_rule(&mut rules, server).blocked = true;
+    }
+    rules
+}
+
+/// The config rule for a server, added if it has none yet
+fn config_rule<'a>(rules: &'a mut Vec<VideoServerRule>, server: &str) -> &'a mut VideoServerRule {
+    let server = normalize_server(server);
+    let index = match rules.iter().position(|rule| rule.server == server) {
+        Some(index) => index,
+        None => {
+            rules.push(VideoServerRule {
+                server,
+                blocked: false,
+                priority: 0,
+                origin: VideoServerRuleOrigin::Config,
+                updated_at: None,
+            });
+            rules.len() - 1
+        }
+    };
+    &mut rules[index]
+}
+
+/// In-memory video server rules: the config rules, overridden by admin rules
+#[derive(Debug, Clone, Default)]
+pub struct VideoServerRules {
+    defaults: Arc<RwLock<Vec<VideoServerRule>>>,
+    rules: Arc<RwLock<HashMap<String, VideoServerRule>>>,
+}
+
+impl VideoServerRules {
+    /// Create the rules from config rules and admin rules
+    pub fn new(defaults: Vec<VideoServerRule>, admin_rules: Vec<VideoServerRule>) -> Self {
+        let rules = Self {
+            defaults: Arc::new(RwLock::new(defaults)),
+            rules: Arc::default(),
+        };
+        rules.replace_admin_rules(admin_rules);
+        rules
+    }
+
+    /// Load the config rules and the admin rules from the database
+    pub async fn load(pool: &PgPool, config: &Config) -> Result<Self, RepositoryError> {
+        Ok(Self::new(
+            config_rules(
+                &config.video_server_blacklist,
+                &config.video_server_priority,
+            ),
+            get_video_server_rules(pool).await?,
+        ))
+    }
+
+    /// Reload admin rules from the database
+    pub async fn reload(&self, pool: &PgPool) -> Result<(), RepositoryError> {
+        self.replace_admin_rules(get_video_server_rules(pool).await?);
+        Ok(())
+    }
+
+    /// Replace both the config rules and the admin rules
+    pub fn replace(&self, defaults: Vec<VideoServerRule>, admin_rules: Vec<VideoServerRule>) {
+        *self.defaults.write().unwrap_or_else(|e| e.into_inner()) = defaults;
+        self.replace_admin_rules(admin_rules);
+    }
+
+    fn replace_admin_rules(&self, admin_rules: Vec<VideoServerRule>) {
+        let merged = self
+            .defaults
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .chain(admin_rules)
+            .map(|rule| (rule.server.clone(), rule))
+            .collect();
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = merged;
+    }
+
+    /// All rules in effect, highest priority first, then by server name
+    pub fn all(&self) -> Vec<VideoServerRule> {
+        let mut rules: Vec<VideoServerRule> = self
+            .rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        rules.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then_with(|| a.server.cmp(&b.server))
+        });
+        rules
+    }
+
+    /// The rule in effect for a server, ignoring case
+    pub fn get(&self, server: &str) -> Option<VideoServerRule> {
+        self.rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&normalize_server(server))
+            .cloned()
+    }
+
+    /// Drop sources from blocked servers and order the rest by priority
+    ///
+    /// The sort is stable, so sources of equal priority keep the scraped
+    /// order.
+    pub fn apply_sources(&self, sources: &mut Vec<VideoSource>) {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        let rule = |source: &VideoSource| rules.get(&normalize_server(&source.server));
+
+        sources.retain(|source| !rule(source).is_some_and(|rule| rule.blocked));
+        sources.sort_by_key(|source| Reverse(rule(source).map_or(0, |rule| rule.priority)));
+    }
+
+    /// Apply the rules to an episode's sources
+    ///
+    /// A default video from a dropped source is replaced by the first
+    /// remaining source, or cleared when none remain.
+    pub fn apply(&self, mut detail: EpisodeDetail) -> EpisodeDetail {
+        let is_default = |source: &VideoSource| source.url == detail.default_video;
+        let was_source = detail.sources.iter().any(is_default);
+        self.apply_sources(&mut detail.sources);
+
+        let is_default = |source: &VideoSource| source.url == detail.default_video;
+        if was_source && !detail.sources.iter().any(is_default) {
+            detail.default_video = detail
+                .sources
+                .first()
+                .map(|source| source.url.clone())
+                .unwrap_or_default();
+        }
+        detail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(server: &str) -> VideoSource {
+        VideoSource {
+            server: server.to_string(),
+            quality: "720p".to_string(),
+            url: format!("https://example.com/{}.mp4", server),
+            embed: None,
+        }
+    }
+
+    fn admin_rule(server: &str, blocked: bool, priority: i32) -> VideoServerRule {
+        VideoServerRule {
+            server: server.to_string(),
+            blocked,
+            priority,
+            origin: VideoServerRuleOrigin::Admin,
+            updated_at: None,
+        }
+    }
+
+    fn list(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_config_rules() {
+        let rules = config_rules(&list(&["Ads", "sokuja"]), &list(&["SOKUJA", "Blogger"]));
+        let summary: Vec<(&str, bool, i32)> = rules
+            .iter()
+            .map(|rule| (rule.server.as_str(), rule.blocked, rule.priority))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("sokuja", true, 2), ("blogger", false, 1), ("ads", true, 0)]
+        );
+        assert!(rules
+            .iter()
+            .all(|rule| rule.origin == VideoServerRuleOrigin::Config));
+    }
+
+    #[test]
+    fn test_admin_rules_override_config() {
+        let rules = VideoServerRules::new(
+            config_rules(&list(&["ads"]), &list(&["blogger"])),
+            vec![admin_rule("ads", false, 0), admin_rule("mirror", true, 0)],
+        );
+
+        assert_eq!(rules.get("ADS").map(|rule| rule.blocked), Some(false));
+        assert_eq!(
+            rules.get("mirror").map(|rule| rule.origin),
+            Some(VideoServerRuleOrigin::Admin)
+        );
+        assert_eq!(rules.get("unknown"), None);
+        let servers: Vec<String> = rules.all().into_iter().map(|rule| rule.server).collect();
+        assert_eq!(servers, vec!["blogger", "ads", "mirror"]);
+    }
+
+    #[test]
+    fn test_replace_defaults() {
+        let rules = VideoServerRules::new(config_rules(&list(&["ads"]), &[]), Vec::new());
+        let clone = rules.clone();
+        rules.replace(
+            config_rules(&list(&["mirror"]), &[]),
+            vec![admin_rule("blogger", false, 3)],
+        );
+
+        // Clones in other workers see the new rules too
+        assert_eq!(clone.get("ads"), None);
+        assert!(clone.get("mirror").unwrap().blocked);
+        assert_eq!(clone.get("blogger").unwrap().priority, 3);
+    }
+
+    #[test]
+    fn test_apply_sources() {
+        let rules = VideoServerRules::new(
+            config_rules(&list(&["ads"]), &list(&["blogger"])),
+            Vec::new(),
+        );
+        let mut sources = vec![
+            source("SOKUJA"),
+            source("Ads"),
+            source("Other"),
+            source("Blogger"),
+        ];
+        rules.apply_sources(&mut sources);
+
+        let servers: Vec<&str> = sources.iter().map(|s| s.server.as_str()).collect();
+        assert_eq!(servers, vec!["Blogger", "SOKUJA", "Other"]);
+    }
+
+    #[test]
+    fn test_apply_replaces_dropped_default_video() {
+        let rules = VideoServerRules::new(config_rules(&list(&["ads"]), &[]), Vec::new());
+        let detail = EpisodeDetail {
+            title: "Episode 1".to_string(),
+            default_video: source("ads").url,
+            sources: vec![source("ads"), source("sokuja")],
+            subtitles: Vec::new(),
+            comment_count: None,
+        };
+
+        let applied = rules.apply(detail.clone());
+        assert_eq!(applied.default_video, source("sokuja").url);
+        assert_eq!(applied.sources, vec![source("sokuja")]);
+
+        // A default video that isn't one of the sources is left alone
+        let embedded = EpisodeDetail {
+            default_video: "https://example.com/embed.mp4".to_string(),
+            ..detail
+        };
+        assert_eq!(
+            rules.apply(embedded).default_video,
+            "https://example.com/embed.mp4"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! XML serialization
+//!
+//! A minimal writer for the small documents the API emits (NFO files).
+//! Element and attribute names are written as given and must be valid XML
+//! names; text and attribute values are escaped.
+
+/// Escape text for use in XML content or a quoted attribute value
+///
+/// Characters XML 1.0 doesn't allow at all (most control characters) are
+/// dropped.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{fffe}' || c == '\u{ffff}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes an indented XML document
+///
+/// Elements left open are closed by [`XmlWriter::finish`].
+#[derive(Debug)]
+pub struct XmlWriter {
+    out: String,
+    open: Vec<&'static str>,
+}
+
+impl Default for XmlWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XmlWriter {
+    /// Start a UTF-8 document with its XML declaration
+    pub fn new() -> Self {
+        Self {
+            out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n"),
+            open: Vec::new(),
+        }
+    }
+
+    fn write_tag(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.out.push_str(&"  ".repeat(self.open.len()));
+        self.out.push('<');
+        self.out.push_str(name);
+        for (key, value) in attrs {
+            self.out
+                .push_str(&format!(" {}=\"{}\"", key, escape(value)));
+        }
+        self.out.push('>');
+    }
+
+    /// Open an element; later elements are nested in it until [`XmlWriter::end`]
+    pub fn start(&mut self, name: &'static str, attrs: &[(&str, &str)]) {
+        self.write_tag(name, attrs);
+        self.out.push('\n');
+        self.open.push(name);
+    }
+
+    /// Close the innermost open element
+    pub fn end(&mut self) {
+        if let Some(name) = self.open.pop() {
+            self.out.push_str(&"  ".repeat(self.open.len()));
+            self.out.push_str(&format!("</{}>\n", name));
+        }
+    }
+
+    /// Write an element holding only text
+    pub fn element(&mut self, name: &str, attrs: &[(&str, &str)], text: &str) {
+        self.write_tag(name, attrs);
+        self.out.push_str(&escape(text));
+        self.out.push_str(&format!("</{}>\n", name));
+    }
+
+    /// Close all open elements and return the document
+    pub fn finish(mut self) -> String {
+        while !self.open.is_empty() {
+            self.end();
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"Tom & Jerry's <"show">"#),
+            "Tom &amp; Jerry&apos;s &lt;&quot;show&quot;&gt;"
+        );
+        assert_eq!(escape("a\u{0}b\u{1b}c\nd"), "abc\nd");
+        assert_eq!(escape("葬送のフリーレン"), "葬送のフリーレン");
+    }
+
+    #[test]
+    fn test_xml_writer() {
+        let mut xml = XmlWriter::new();
+        xml.start("tvshow", &[]);
+        xml.element("title", &[], "A & B");
+        xml.start("ratings", &[]);
+        xml.element("rating", &[("name", "x\"y")], "9");
+        let doc = xml.finish();
+
+        assert_eq!(
+            doc,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <tvshow>\n  \
+             <title>A &amp; B</title>\n  \
+             <ratings>\n    \
+             <rating name=\"x&quot;y\">9</rating>\n  \
+             </ratings>\n\
+             </tvshow>\n"
+        );
+    }
+}